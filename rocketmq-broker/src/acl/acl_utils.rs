@@ -0,0 +1,149 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeMap;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use sha1::Digest;
+use sha1::Sha1;
+
+pub(crate) const ACCESS_KEY: &str = "AccessKey";
+pub(crate) const SIGNATURE: &str = "Signature";
+
+const HMAC_BLOCK_SIZE: usize = 64;
+
+/// Base64 of the HMAC-SHA1 of `data` keyed by `secret_key`, the signature of the Java client.
+pub(crate) fn calculate_signature(data: &[u8], secret_key: &str) -> String {
+    let mut key = secret_key.as_bytes().to_vec();
+    if key.len() > HMAC_BLOCK_SIZE {
+        key = Sha1::digest(&key).to_vec();
+    }
+    key.resize(HMAC_BLOCK_SIZE, 0);
+    let inner_pad = key.iter().map(|byte| byte ^ 0x36).collect::<Vec<_>>();
+    let outer_pad = key.iter().map(|byte| byte ^ 0x5c).collect::<Vec<_>>();
+    let inner = Sha1::new()
+        .chain_update(&inner_pad)
+        .chain_update(data)
+        .finalize();
+    let outer = Sha1::new()
+        .chain_update(&outer_pad)
+        .chain_update(inner)
+        .finalize();
+    STANDARD.encode(outer)
+}
+
+/// The signed content of a request: the values of its extension fields but the signature,
+/// ordered by field name, followed by the body.
+pub(crate) fn combine_request_content(request: &RemotingCommand) -> Vec<u8> {
+    let fields = request
+        .get_ext_fields()
+        .map(|fields| {
+            fields
+                .iter()
+                .filter(|(key, _)| key.as_str() != SIGNATURE)
+                .collect::<BTreeMap<_, _>>()
+        })
+        .unwrap_or_default();
+    let mut content = Vec::new();
+    for value in fields.values() {
+        content.extend_from_slice(value.as_bytes());
+    }
+    if let Some(body) = request.get_body() {
+        content.extend_from_slice(body);
+    }
+    content
+}
+
+/// Whether `remote_addr` matches the white list entry `pattern`: `*`, an address, a comma
+/// separated list of addresses, or an IPv4 address whose segments are `*`, a range `1-20` or
+/// a set `{1,3,5}`.
+pub(crate) fn match_remote_address(pattern: &str, remote_addr: &str) -> bool {
+    let pattern = pattern.trim();
+    if pattern.is_empty() {
+        return false;
+    }
+    if pattern == "*" || pattern == "*.*.*.*" {
+        return true;
+    }
+    if pattern.contains(',') && !pattern.contains('{') {
+        return pattern
+            .split(',')
+            .any(|pattern| match_remote_address(pattern, remote_addr));
+    }
+    let segments = pattern.split('.').collect::<Vec<_>>();
+    let addr_segments = remote_addr.split('.').collect::<Vec<_>>();
+    if segments.len() != 4 || addr_segments.len() != 4 {
+        return pattern == remote_addr;
+    }
+    segments
+        .iter()
+        .zip(addr_segments)
+        .all(|(segment, addr_segment)| match_segment(segment, addr_segment))
+}
+
+fn match_segment(segment: &str, addr_segment: &str) -> bool {
+    if segment == "*" {
+        return true;
+    }
+    let Ok(value) = addr_segment.parse::<u8>() else {
+        return false;
+    };
+    if let Some(set) = segment
+        .strip_prefix('{')
+        .and_then(|segment| segment.strip_suffix('}'))
+    {
+        return set
+            .split(',')
+            .any(|item| item.trim().parse::<u8>() == Ok(value));
+    }
+    if let Some((start, end)) = segment.split_once('-') {
+        return match (start.parse::<u8>(), end.parse::<u8>()) {
+            (Ok(start), Ok(end)) => (start..=end).contains(&value),
+            _ => false,
+        };
+    }
+    segment.parse::<u8>() == Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_matches_java_hmac_sha1() {
+        // javax.crypto.Mac "HmacSHA1" keyed "key" over the pangram, base64 encoded
+        assert_eq!(
+            calculate_signature(b"The quick brown fox jumps over the lazy dog", "key"),
+            "3nybhbi3iqa8ino29wqQcBydtNk="
+        );
+    }
+
+    #[test]
+    fn remote_address_patterns() {
+        assert!(match_remote_address("*", "10.0.0.1"));
+        assert!(match_remote_address("10.0.0.1", "10.0.0.1"));
+        assert!(match_remote_address("10.0.0.2,10.0.0.1", "10.0.0.1"));
+        assert!(match_remote_address("10.0.*.*", "10.0.3.1"));
+        assert!(match_remote_address("10.0.0.1-10", "10.0.0.7"));
+        assert!(!match_remote_address("10.0.0.1-10", "10.0.0.17"));
+        assert!(match_remote_address("10.0.0.{1,7}", "10.0.0.7"));
+        assert!(!match_remote_address("10.0.0.{1,7}", "10.0.0.2"));
+        assert!(!match_remote_address("", "10.0.0.1"));
+        assert!(match_remote_address("::1", "::1"));
+    }
+}
@@ -0,0 +1,88 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+
+use rocketmq_remoting::code::request_code::RequestCode;
+
+pub(crate) const DENY: u8 = 1;
+pub(crate) const ANY: u8 = 1 << 1;
+pub(crate) const PUB: u8 = 1 << 2;
+pub(crate) const SUB: u8 = 1 << 3;
+
+/// Parses `PUB`, `SUB`, `PUB|SUB` or `DENY`, anything else denies.
+pub(crate) fn parse_perm(perm: &str) -> u8 {
+    match perm.trim() {
+        "PUB" => PUB,
+        "SUB" => SUB,
+        "PUB|SUB" | "SUB|PUB" => PUB | SUB,
+        "ANY" => ANY,
+        _ => DENY,
+    }
+}
+
+pub(crate) fn check_permission(needed_perm: u8, owned_perm: u8) -> bool {
+    if owned_perm & DENY > 0 {
+        return false;
+    }
+    if needed_perm & ANY > 0 {
+        return owned_perm & (PUB | SUB) > 0;
+    }
+    needed_perm & owned_perm > 0
+}
+
+/// Parses `resource=PERM` entries, entries without a `=` are skipped.
+pub(crate) fn parse_resource_perms(entries: &[String]) -> HashMap<String, u8> {
+    entries
+        .iter()
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(resource, perm)| (resource.trim().to_string(), parse_perm(perm)))
+        .collect()
+}
+
+/// Requests only accounts flagged `admin` may send.
+pub(crate) fn need_admin_perm(request_code: RequestCode) -> bool {
+    matches!(
+        request_code,
+        RequestCode::UpdateAndCreateTopic
+            | RequestCode::UpdateAndCreateTopicList
+            | RequestCode::UpdateAndCreateStaticTopic
+            | RequestCode::DeleteTopicInBroker
+            | RequestCode::UpdateBrokerConfig
+            | RequestCode::UpdateAndCreateSubscriptionGroup
+            | RequestCode::DeleteSubscriptionGroup
+            | RequestCode::UpdateAndCreateAclConfig
+            | RequestCode::DeleteAclConfig
+            | RequestCode::UpdateGlobalWhiteAddrsConfig
+            | RequestCode::GetBrokerClusterAclInfo
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deny_wins_and_any_needs_pub_or_sub() {
+        assert!(check_permission(PUB, PUB | SUB));
+        assert!(!check_permission(SUB, PUB));
+        assert!(!check_permission(PUB, DENY));
+        assert!(check_permission(ANY, SUB));
+        assert!(!check_permission(ANY, DENY));
+        assert_eq!(parse_perm("SUB|PUB"), PUB | SUB);
+        assert_eq!(parse_perm("READ"), DENY);
+    }
+}
@@ -0,0 +1,61 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+
+/// Content of `plain_acl.yml`. Fields and accounts are written back in this order, so the
+/// file stays stable across updates.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PlainAclConfig {
+    #[serde(default)]
+    pub global_white_remote_addresses: Vec<String>,
+    #[serde(default)]
+    pub accounts: Vec<PlainAccessConfig>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PlainAccessConfig {
+    #[serde(deserialize_with = "scalar_string")]
+    pub access_key: String,
+    #[serde(deserialize_with = "scalar_string")]
+    pub secret_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub white_remote_address: Option<String>,
+    #[serde(default)]
+    pub admin: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_topic_perm: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_group_perm: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub topic_perms: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub group_perms: Vec<String>,
+}
+
+/// Keys are often plain numbers in hand written files, `secretKey: 12345678`.
+fn scalar_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match serde_yaml::Value::deserialize(deserializer)? {
+        serde_yaml::Value::String(value) => Ok(value),
+        serde_yaml::Value::Number(value) => Ok(value.to_string()),
+        serde_yaml::Value::Bool(value) => Ok(value.to_string()),
+        _ => Err(serde::de::Error::custom("expected a string")),
+    }
+}
@@ -0,0 +1,620 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use parking_lot::RwLock;
+use rocketmq_common::common::mix_all;
+use rocketmq_common::FileUtils;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::DataVersion;
+use rocketmq_remoting::runtime::RPCHook;
+use sha1::Digest;
+use sha1::Sha1;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+use crate::acl::acl_utils;
+use crate::acl::permission;
+use crate::acl::plain_access_config::PlainAccessConfig;
+use crate::acl::plain_access_config::PlainAclConfig;
+use crate::RemotingError;
+
+const MIN_KEY_LENGTH: usize = 6;
+const MAX_CACHED_SIGNATURES: usize = 4096;
+
+/// Verified signatures of an access key, with the digest of the content each one signs.
+type VerifiedSignatures = HashMap<String, Vec<u8>>;
+
+/// Checks requests against the accounts of `plain_acl.yml`. Admin requests update the accounts
+/// and rewrite the file, and edits made to the file by hand are picked up by
+/// [`reload_if_changed`](Self::reload_if_changed).
+#[derive(Clone)]
+pub(crate) struct PlainAccessValidator {
+    file_path: Arc<PathBuf>,
+    permission: Arc<RwLock<PlainPermission>>,
+    verified_signatures: Arc<Mutex<HashMap<String, VerifiedSignatures>>>,
+}
+
+#[derive(Default)]
+struct PlainPermission {
+    config: PlainAclConfig,
+    accounts: HashMap<String, PlainAccessResource>,
+    data_version: DataVersion,
+    /// What the file held when it was last read or written.
+    file_content: Option<String>,
+}
+
+struct PlainAccessResource {
+    secret_key: String,
+    white_remote_address: Option<String>,
+    admin: bool,
+    default_topic_perm: u8,
+    default_group_perm: u8,
+    topic_perms: HashMap<String, u8>,
+    group_perms: HashMap<String, u8>,
+}
+
+impl From<&PlainAccessConfig> for PlainAccessResource {
+    fn from(config: &PlainAccessConfig) -> Self {
+        let default_perm = |perm: &Option<String>| {
+            perm.as_deref()
+                .map_or(permission::DENY, permission::parse_perm)
+        };
+        Self {
+            secret_key: config.secret_key.clone(),
+            white_remote_address: config.white_remote_address.clone(),
+            admin: config.admin,
+            default_topic_perm: default_perm(&config.default_topic_perm),
+            default_group_perm: default_perm(&config.default_group_perm),
+            topic_perms: permission::parse_resource_perms(&config.topic_perms),
+            group_perms: permission::parse_resource_perms(&config.group_perms),
+        }
+    }
+}
+
+/// What a request needs from the ACL: who signed it and the topics and groups it touches.
+#[derive(Debug, Default)]
+pub(crate) struct AccessResource {
+    pub access_key: Option<String>,
+    pub signature: Option<String>,
+    pub content: Vec<u8>,
+    pub remote_addr: String,
+    pub request_code: i32,
+    pub topic_perms: Vec<(String, u8)>,
+    pub group_perms: Vec<(String, u8)>,
+}
+
+impl AccessResource {
+    pub fn parse(request: &RemotingCommand, remote_addr: SocketAddr) -> Self {
+        let field = |name: &str| {
+            request
+                .get_ext_fields()
+                .and_then(|fields| fields.get(name))
+                .filter(|value| !value.is_empty())
+                .map(|value| value.to_string())
+        };
+        let mut resource = AccessResource {
+            access_key: field(acl_utils::ACCESS_KEY),
+            signature: field(acl_utils::SIGNATURE),
+            content: acl_utils::combine_request_content(request),
+            remote_addr: remote_addr.ip().to_string(),
+            request_code: request.code(),
+            ..Default::default()
+        };
+        match RequestCode::from(request.code()) {
+            RequestCode::SendMessage | RequestCode::SendReplyMessage => {
+                resource.add_send_topic(field("topic"));
+            }
+            RequestCode::SendMessageV2
+            | RequestCode::SendBatchMessage
+            | RequestCode::SendReplyMessageV2 => {
+                resource.add_send_topic(field("b"));
+            }
+            RequestCode::ConsumerSendMsgBack => {
+                resource.add_group(field("group"));
+            }
+            RequestCode::PullMessage => {
+                resource.add_topic(field("topic"), permission::SUB);
+                resource.add_group(field("consumerGroup"));
+            }
+            RequestCode::QueryMessage => {
+                resource.add_topic(field("topic"), permission::SUB);
+            }
+            RequestCode::UpdateConsumerOffset | RequestCode::QueryConsumerOffset => {
+                resource.add_topic(field("topic"), permission::SUB);
+                resource.add_group(field("consumerGroup"));
+            }
+            RequestCode::UnregisterClient | RequestCode::GetConsumerListByGroup => {
+                resource.add_group(field("consumerGroup"));
+            }
+            _ => {}
+        }
+        resource
+    }
+
+    /// Sending to a retry topic is sending a message back for its group.
+    fn add_send_topic(&mut self, topic: Option<String>) {
+        match topic {
+            Some(topic) if topic.starts_with(mix_all::RETRY_GROUP_TOPIC_PREFIX) => {
+                let group = topic[mix_all::RETRY_GROUP_TOPIC_PREFIX.len()..].to_string();
+                self.add_group(Some(group));
+            }
+            topic => self.add_topic(topic, permission::PUB),
+        }
+    }
+
+    fn add_topic(&mut self, topic: Option<String>, perm: u8) {
+        if let Some(topic) = topic {
+            self.topic_perms.push((topic, perm));
+        }
+    }
+
+    fn add_group(&mut self, group: Option<String>) {
+        if let Some(group) = group {
+            self.group_perms.push((group, permission::SUB));
+        }
+    }
+}
+
+impl PlainAccessValidator {
+    pub fn new(file_path: impl Into<PathBuf>) -> Self {
+        Self {
+            file_path: Arc::new(file_path.into()),
+            permission: Arc::new(RwLock::new(PlainPermission::default())),
+            verified_signatures: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn file_path(&self) -> &PathBuf {
+        &self.file_path
+    }
+
+    /// Loads the ACL file, a missing file leaves every request but the white listed ones
+    /// rejected.
+    pub fn load(&self) -> bool {
+        match std::fs::read_to_string(self.file_path.as_path()) {
+            Ok(content) => self.apply_file_content(content),
+            Err(error) => {
+                warn!(
+                    "read acl file {} failed: {}, no account is configured",
+                    self.file_path.display(),
+                    error
+                );
+                true
+            }
+        }
+    }
+
+    /// Reloads the ACL file when its content is not the one last read or written.
+    pub fn reload_if_changed(&self) -> bool {
+        let Ok(content) = std::fs::read_to_string(self.file_path.as_path()) else {
+            return false;
+        };
+        if self.permission.read().file_content.as_deref() == Some(content.as_str()) {
+            return false;
+        }
+        let reloaded = self.apply_file_content(content);
+        if reloaded {
+            info!("acl file {} changed, reloaded", self.file_path.display());
+        }
+        reloaded
+    }
+
+    fn apply_file_content(&self, content: String) -> bool {
+        let config = if content.trim().is_empty() {
+            PlainAclConfig::default()
+        } else {
+            match serde_yaml::from_str::<PlainAclConfig>(&content) {
+                Ok(config) => config,
+                Err(error) => {
+                    error!(
+                        "parse acl file {} failed: {}",
+                        self.file_path.display(),
+                        error
+                    );
+                    // Keep the accounts loaded last until the file is fixed.
+                    self.permission.write().file_content = Some(content);
+                    return false;
+                }
+            }
+        };
+        let mut permission = self.permission.write();
+        permission.set_config(config);
+        permission.file_content = Some(content);
+        self.verified_signatures.lock().clear();
+        true
+    }
+
+    pub fn validate(&self, resource: &AccessResource) -> Result<(), String> {
+        let permission = self.permission.read();
+        if permission
+            .config
+            .global_white_remote_addresses
+            .iter()
+            .any(|pattern| acl_utils::match_remote_address(pattern, &resource.remote_addr))
+        {
+            return Ok(());
+        }
+        let Some(access_key) = resource.access_key.as_deref() else {
+            return Err("No accessKey is configured".to_string());
+        };
+        let Some(account) = permission.accounts.get(access_key) else {
+            return Err(format!("No acl config for {}", access_key));
+        };
+        if account
+            .white_remote_address
+            .as_deref()
+            .is_some_and(|pattern| acl_utils::match_remote_address(pattern, &resource.remote_addr))
+        {
+            return Ok(());
+        }
+        self.check_signature(access_key, account, resource)?;
+
+        if permission::need_admin_perm(RequestCode::from(resource.request_code)) && !account.admin {
+            return Err(format!(
+                "Need admin permission for request code={}, but accessKey={} is not",
+                resource.request_code, access_key
+            ));
+        }
+        for (topic, needed_perm) in &resource.topic_perms {
+            let owned_perm = account
+                .topic_perms
+                .get(topic)
+                .copied()
+                .unwrap_or(account.default_topic_perm);
+            if !permission::check_permission(*needed_perm, owned_perm) {
+                return Err(format!("No permission for topic={}", topic));
+            }
+        }
+        for (group, needed_perm) in &resource.group_perms {
+            let owned_perm = account
+                .group_perms
+                .get(group)
+                .copied()
+                .unwrap_or(account.default_group_perm);
+            if !permission::check_permission(*needed_perm, owned_perm) {
+                return Err(format!("No permission for group={}", group));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_signature(
+        &self,
+        access_key: &str,
+        account: &PlainAccessResource,
+        resource: &AccessResource,
+    ) -> Result<(), String> {
+        let Some(signature) = resource.signature.as_deref() else {
+            return Err(format!("No signature of accessKey={}", access_key));
+        };
+        let digest = Sha1::digest(&resource.content).to_vec();
+        let mut verified_signatures = self.verified_signatures.lock();
+        let verified = verified_signatures
+            .entry(access_key.to_string())
+            .or_default();
+        if verified.get(signature) == Some(&digest) {
+            return Ok(());
+        }
+        if acl_utils::calculate_signature(&resource.content, &account.secret_key) != signature {
+            return Err(format!(
+                "Check signature failed for accessKey={}",
+                access_key
+            ));
+        }
+        if verified.len() >= MAX_CACHED_SIGNATURES {
+            verified.clear();
+        }
+        verified.insert(signature.to_string(), digest);
+        Ok(())
+    }
+
+    /// Creates the account of `config` or updates the fields it sets, a missing secret key keeps
+    /// the current one.
+    pub fn update_access_config(&self, config: PlainAccessConfig) -> Result<(), String> {
+        if config.access_key.len() < MIN_KEY_LENGTH {
+            return Err(format!(
+                "The accessKey={} cannot be less than {} characters",
+                config.access_key, MIN_KEY_LENGTH
+            ));
+        }
+        let mut permission = self.permission.write();
+        let mut acl_config = permission.config.clone();
+        match acl_config
+            .accounts
+            .iter_mut()
+            .find(|account| account.access_key == config.access_key)
+        {
+            Some(account) => {
+                if !config.secret_key.is_empty() {
+                    account.secret_key = config.secret_key;
+                }
+                if config.white_remote_address.is_some() {
+                    account.white_remote_address = config.white_remote_address;
+                }
+                account.admin = config.admin;
+                if config.default_topic_perm.is_some() {
+                    account.default_topic_perm = config.default_topic_perm;
+                }
+                if config.default_group_perm.is_some() {
+                    account.default_group_perm = config.default_group_perm;
+                }
+                if !config.topic_perms.is_empty() {
+                    account.topic_perms = config.topic_perms;
+                }
+                if !config.group_perms.is_empty() {
+                    account.group_perms = config.group_perms;
+                }
+            }
+            None => acl_config.accounts.push(config.clone()),
+        }
+        if let Some(account) = acl_config
+            .accounts
+            .iter()
+            .find(|account| account.access_key == config.access_key)
+        {
+            if account.secret_key.len() < MIN_KEY_LENGTH {
+                return Err(format!(
+                    "The secretKey of accessKey={} cannot be less than {} characters",
+                    config.access_key, MIN_KEY_LENGTH
+                ));
+            }
+        }
+        self.verified_signatures.lock().remove(&config.access_key);
+        self.persist(&mut permission, acl_config)
+    }
+
+    pub fn delete_access_config(&self, access_key: &str) -> Result<(), String> {
+        let mut permission = self.permission.write();
+        let mut acl_config = permission.config.clone();
+        let accounts = acl_config.accounts.len();
+        acl_config
+            .accounts
+            .retain(|account| account.access_key != access_key);
+        if acl_config.accounts.len() == accounts {
+            return Err(format!("No acl config for {}", access_key));
+        }
+        self.verified_signatures.lock().remove(access_key);
+        self.persist(&mut permission, acl_config)
+    }
+
+    pub fn update_global_white_addrs(&self, addresses: Vec<String>) -> Result<(), String> {
+        let mut permission = self.permission.write();
+        let mut acl_config = permission.config.clone();
+        acl_config.global_white_remote_addresses = addresses;
+        self.persist(&mut permission, acl_config)
+    }
+
+    pub fn data_version(&self) -> DataVersion {
+        self.permission.read().data_version.clone()
+    }
+
+    fn persist(
+        &self,
+        permission: &mut PlainPermission,
+        acl_config: PlainAclConfig,
+    ) -> Result<(), String> {
+        let content = serde_yaml::to_string(&acl_config).map_err(|error| error.to_string())?;
+        if let Some(parent) = self.file_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+        }
+        FileUtils::string_to_file(&content, &self.file_path.to_string_lossy())
+            .map_err(|error| error.to_string())?;
+        permission.set_config(acl_config);
+        permission.file_content = Some(content);
+        Ok(())
+    }
+}
+
+impl PlainPermission {
+    fn set_config(&mut self, config: PlainAclConfig) {
+        self.accounts = config
+            .accounts
+            .iter()
+            .map(|account| (account.access_key.clone(), account.into()))
+            .collect();
+        self.config = config;
+        self.data_version.next_version();
+    }
+}
+
+impl RPCHook for PlainAccessValidator {
+    fn do_before_request(
+        &self,
+        remote_addr: SocketAddr,
+        request: &mut RemotingCommand,
+    ) -> rocketmq_remoting::Result<()> {
+        self.validate(&AccessResource::parse(request, remote_addr))
+            .map_err(|message| {
+                RemotingError::AbortProcessException(ResponseCode::NoPermission.into(), message)
+            })
+    }
+
+    fn do_after_response(
+        &self,
+        _remote_addr: SocketAddr,
+        _response: &mut RemotingCommand,
+    ) -> rocketmq_remoting::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn signed_request(
+        code: RequestCode,
+        fields: &[(&str, &str)],
+        access_key: &str,
+        secret_key: &str,
+    ) -> RemotingCommand {
+        let mut request = RemotingCommand::create_remoting_command(code);
+        for (key, value) in fields {
+            request.add_ext_field(*key, *value);
+        }
+        request.add_ext_field(acl_utils::ACCESS_KEY, access_key);
+        let signature = acl_utils::calculate_signature(
+            &acl_utils::combine_request_content(&request),
+            secret_key,
+        );
+        request.add_ext_field(acl_utils::SIGNATURE, signature);
+        request
+    }
+
+    pub(crate) fn check(validator: &PlainAccessValidator, request: &RemotingCommand) -> bool {
+        validator
+            .validate(&AccessResource::parse(
+                request,
+                "10.1.1.1:5000".parse().unwrap(),
+            ))
+            .is_ok()
+    }
+
+    fn send(topic: &str, access_key: &str, secret_key: &str) -> RemotingCommand {
+        signed_request(
+            RequestCode::SendMessage,
+            &[("topic", topic)],
+            access_key,
+            secret_key,
+        )
+    }
+
+    const ACL: &str = r#"
+globalWhiteRemoteAddresses:
+- 192.168.0.*
+accounts:
+- accessKey: RocketMQ
+  secretKey: 12345678
+  defaultTopicPerm: DENY
+  topicPerms:
+  - topicA=PUB
+- accessKey: rocketmq2
+  secretKey: "87654321"
+  admin: true
+"#;
+
+    fn load(dir: &tempfile::TempDir) -> PlainAccessValidator {
+        let path = dir.path().join("conf").join("plain_acl.yml");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, ACL).unwrap();
+        let validator = PlainAccessValidator::new(path);
+        assert!(validator.load());
+        validator
+    }
+
+    #[test]
+    fn enforces_signature_and_topic_permissions() {
+        let dir = tempfile::tempdir().unwrap();
+        let validator = load(&dir);
+
+        assert!(check(&validator, &send("topicA", "RocketMQ", "12345678")));
+        assert!(!check(&validator, &send("topicB", "RocketMQ", "12345678")));
+        assert!(!check(
+            &validator,
+            &send("topicA", "RocketMQ", "wrong-secret")
+        ));
+        assert!(!check(&validator, &send("topicA", "unknown", "12345678")));
+
+        // Admin requests need an admin account.
+        let create_topic = |access_key, secret_key| {
+            signed_request(
+                RequestCode::UpdateAndCreateTopic,
+                &[("topic", "topicC")],
+                access_key,
+                secret_key,
+            )
+        };
+        assert!(!check(&validator, &create_topic("RocketMQ", "12345678")));
+        assert!(check(&validator, &create_topic("rocketmq2", "87654321")));
+
+        // Global white addresses need no signature.
+        let unsigned = RemotingCommand::create_remoting_command(RequestCode::SendMessage);
+        let resource = AccessResource::parse(&unsigned, "192.168.0.7:5000".parse().unwrap());
+        assert!(validator.validate(&resource).is_ok());
+        assert!(!check(&validator, &unsigned));
+    }
+
+    #[test]
+    fn new_secret_key_invalidates_verified_signatures() {
+        let dir = tempfile::tempdir().unwrap();
+        let validator = load(&dir);
+        let request = send("topicA", "RocketMQ", "12345678");
+        assert!(check(&validator, &request));
+        assert!(check(&validator, &request));
+
+        validator
+            .update_access_config(PlainAccessConfig {
+                access_key: "RocketMQ".to_string(),
+                secret_key: "abcdefgh".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(!check(&validator, &request));
+        assert!(check(&validator, &send("topicA", "RocketMQ", "abcdefgh")));
+    }
+
+    #[test]
+    fn reloads_file_edited_by_hand() {
+        let dir = tempfile::tempdir().unwrap();
+        let validator = load(&dir);
+        let version = validator.data_version();
+        assert!(!validator.reload_if_changed());
+
+        std::fs::write(
+            validator.file_path(),
+            ACL.replace("topicA=PUB", "topicB=PUB"),
+        )
+        .unwrap();
+        assert!(validator.reload_if_changed());
+        assert!(!check(&validator, &send("topicA", "RocketMQ", "12345678")));
+        assert!(check(&validator, &send("topicB", "RocketMQ", "12345678")));
+        assert!(validator.data_version() > version);
+
+        // A broken file keeps the accounts loaded last.
+        std::fs::write(validator.file_path(), "accounts: [").unwrap();
+        assert!(!validator.reload_if_changed());
+        assert!(check(&validator, &send("topicB", "RocketMQ", "12345678")));
+    }
+
+    #[test]
+    fn rejects_short_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let validator = load(&dir);
+        assert!(validator
+            .update_access_config(PlainAccessConfig {
+                access_key: "short".to_string(),
+                secret_key: "12345678".to_string(),
+                ..Default::default()
+            })
+            .is_err());
+        assert!(validator
+            .update_access_config(PlainAccessConfig {
+                access_key: "newAccount".to_string(),
+                ..Default::default()
+            })
+            .is_err());
+        assert_eq!(std::fs::read_to_string(validator.file_path()).unwrap(), ACL);
+    }
+}
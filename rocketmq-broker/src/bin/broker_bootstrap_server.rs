@@ -16,22 +16,42 @@
  */
 
 use std::path::PathBuf;
+use std::time::Duration;
 
 use clap::Parser;
 use rocketmq_broker::command::Args;
 use rocketmq_broker::Builder;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::mq_version::RocketMqVersion;
+use rocketmq_common::log::init_logger_with_layers;
+use rocketmq_common::log::LoggingConfig;
+use rocketmq_common::log::BROKER_LOG_FILE;
 use rocketmq_common::EnvUtils::EnvUtils;
 use rocketmq_common::ParseConfigFile;
+use rocketmq_remoting::request_tracing;
 use rocketmq_rust::rocketmq;
 use rocketmq_store::config::message_store_config::MessageStoreConfig;
 use tracing::info;
+use tracing_subscriber::Layer;
 
 #[rocketmq::main]
 async fn main() -> anyhow::Result<()> {
-    // init logger
-    rocketmq_common::log::init_logger();
     let (broker_config, message_store_config) = parse_config_file();
+    // init logger
+    let log_dir = PathBuf::from(EnvUtils::get_rocketmq_home()).join("logs");
+    let mut layers = Vec::new();
+    if !broker_config.trace_otlp_exporter_endpoint.is_empty() {
+        layers.push(
+            request_tracing::otlp_layer(
+                "rocketmq-broker",
+                broker_config.trace_otlp_exporter_endpoint.as_str(),
+                Duration::from_millis(broker_config.trace_otlp_exporter_time_out_in_mills),
+            )?
+            .boxed(),
+        );
+    }
+    init_logger_with_layers(LoggingConfig::new(log_dir, BROKER_LOG_FILE), layers)?;
+    print_banner(&broker_config, &message_store_config);
     // boot strap broker
     Builder::new()
         .set_broker_config(broker_config)
@@ -68,6 +88,31 @@ fn parse_config_file() -> (BrokerConfig, MessageStoreConfig) {
                 .unwrap(),
         )
     };
-    info!("Rocketmq(Rust) home: {}", home);
     config
 }
+
+fn print_banner(broker_config: &BrokerConfig, message_store_config: &MessageStoreConfig) {
+    info!("Rocketmq(Rust) home: {}", EnvUtils::get_rocketmq_home());
+    info!(
+        "Rocketmq Broker(Rust) version: {}, commit: {}",
+        RocketMqVersion::CURRENT_VERSION.name(),
+        option_env!("ROCKETMQ_GIT_COMMIT").unwrap_or("unknown")
+    );
+    info!(
+        "Broker cluster: {}, name: {}, id: {}, listen port: {}, namesrv: {}",
+        broker_config.broker_identity.broker_cluster_name,
+        broker_config.broker_identity.broker_name,
+        broker_config.broker_identity.broker_id,
+        broker_config.listen_port,
+        broker_config
+            .namesrv_addr
+            .as_ref()
+            .map_or("none", |addr| addr.as_str())
+    );
+    info!(
+        "Store root: {}, broker role: {:?}, flush disk type: {:?}",
+        message_store_config.store_path_root_dir,
+        message_store_config.broker_role,
+        message_store_config.flush_disk_type
+    );
+}
@@ -0,0 +1,61 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::path::PathBuf;
+
+use clap::Parser;
+use rocketmq_broker::command::Args;
+use rocketmq_broker::BrokerContainer;
+use rocketmq_broker::BrokerContainerConfig;
+use rocketmq_common::common::mq_version::RocketMqVersion;
+use rocketmq_common::log::init_logger_with_layers;
+use rocketmq_common::log::LoggingConfig;
+use rocketmq_common::log::BROKER_LOG_FILE;
+use rocketmq_common::EnvUtils::EnvUtils;
+use rocketmq_common::ParseConfigFile;
+use rocketmq_rust::rocketmq;
+use tracing::info;
+
+#[rocketmq::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let config_file = args.config_file.unwrap_or_else(|| {
+        PathBuf::from(EnvUtils::get_rocketmq_home())
+            .join("conf")
+            .join("broker-container.toml")
+    });
+    let container_config =
+        ParseConfigFile::parse_config_file_over_default::<BrokerContainerConfig>(&config_file)?;
+    let log_dir = PathBuf::from(EnvUtils::get_rocketmq_home()).join("logs");
+    init_logger_with_layers(LoggingConfig::new(log_dir, BROKER_LOG_FILE), Vec::new())?;
+    info!(
+        "Rocketmq Broker Container(Rust) version: {}, listen port: {}, brokers: {:?}",
+        RocketMqVersion::CURRENT_VERSION.name(),
+        container_config.listen_port,
+        container_config.broker_config_paths
+    );
+
+    let container = BrokerContainer::new(container_config);
+    let started = container.start().await;
+    if started {
+        tokio::signal::ctrl_c().await?;
+    }
+    tokio::task::spawn_blocking(move || container.shutdown()).await?;
+    if !started {
+        anyhow::bail!("broker container failed to start");
+    }
+    Ok(())
+}
@@ -16,3 +16,6 @@
  */
 
 pub mod broker_hook;
+pub mod broker_member_group_cache;
+pub(crate) mod broker_pre_online_service;
+pub(crate) mod broker_task_manager;
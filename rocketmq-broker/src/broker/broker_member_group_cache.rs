@@ -0,0 +1,217 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use cheetah_string::CheetahString;
+use parking_lot::RwLock;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::mix_all::MASTER_ID;
+use rocketmq_remoting::protocol::body::broker_body::broker_member_group::BrokerMemberGroup;
+use rocketmq_store::log_file::MessageStore;
+use tracing::info;
+
+/// Role transition triggered by a change of the minimum broker id in the replica group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BrokerRoleChange {
+    ChangeToMaster,
+    ChangeToSlave,
+}
+
+impl BrokerRoleChange {
+    /// Applies the transition to the local store: an acting master accepts writes again, a
+    /// broker stepping down stops accepting them.
+    pub(crate) fn apply<MS: MessageStore>(self, message_store: &MS) {
+        match self {
+            BrokerRoleChange::ChangeToMaster => {
+                message_store.get_running_flags().get_and_make_writeable();
+            }
+            BrokerRoleChange::ChangeToSlave => {
+                message_store
+                    .get_running_flags()
+                    .get_and_make_not_writeable();
+            }
+        }
+    }
+}
+
+/// The replica peers of this broker as last reported by the name server, together with the
+/// minimum broker id currently online in the group.
+pub(crate) struct BrokerMemberGroupCache {
+    broker_id: u64,
+    group: RwLock<BrokerMemberGroup>,
+    min_broker_id: AtomicU64,
+    min_broker_addr: RwLock<Option<CheetahString>>,
+    acting_master: AtomicBool,
+}
+
+impl BrokerMemberGroupCache {
+    pub fn new(broker_config: &BrokerConfig) -> Self {
+        let broker_id = broker_config.broker_identity.broker_id;
+        let broker_addr: CheetahString = broker_config.get_broker_addr().into();
+        let mut group = BrokerMemberGroup::new(
+            broker_config.broker_identity.broker_cluster_name.clone(),
+            broker_config.broker_identity.broker_name.clone(),
+        );
+        group.broker_addrs.insert(broker_id, broker_addr.clone());
+        Self {
+            broker_id,
+            group: RwLock::new(group),
+            min_broker_id: AtomicU64::new(broker_id),
+            min_broker_addr: RwLock::new(Some(broker_addr)),
+            acting_master: AtomicBool::new(broker_id == MASTER_ID),
+        }
+    }
+
+    pub fn group(&self) -> BrokerMemberGroup {
+        self.group.read().clone()
+    }
+
+    pub fn broker_addrs(&self) -> HashMap<u64, CheetahString> {
+        self.group.read().broker_addrs.clone()
+    }
+
+    pub fn contains_broker(&self, broker_id: u64) -> bool {
+        self.group.read().broker_addrs.contains_key(&broker_id)
+    }
+
+    pub fn min_broker_id(&self) -> u64 {
+        self.min_broker_id.load(Ordering::Acquire)
+    }
+
+    pub fn min_broker_addr(&self) -> Option<CheetahString> {
+        self.min_broker_addr.read().clone()
+    }
+
+    /// Whether this broker currently serves as the master of its group, either because it is
+    /// the master or because it holds the minimum broker id while the master is offline.
+    pub fn is_acting_master(&self) -> bool {
+        self.acting_master.load(Ordering::Acquire)
+    }
+
+    /// Replaces the cached group with the one fetched from the name server and derives the
+    /// minimum broker id from it. An empty group is ignored, the name server may not have seen
+    /// the registration yet.
+    pub fn update_group(&self, group: BrokerMemberGroup) -> Option<BrokerRoleChange> {
+        let min = group
+            .broker_addrs
+            .iter()
+            .min_by_key(|(broker_id, _)| **broker_id)
+            .map(|(broker_id, addr)| (*broker_id, addr.clone()));
+        *self.group.write() = group;
+        let (min_broker_id, min_broker_addr) = min?;
+        self.update_min_broker(min_broker_id, Some(min_broker_addr))
+    }
+
+    /// Records a new minimum broker id and returns the role transition this broker has to go
+    /// through, if any.
+    pub fn update_min_broker(
+        &self,
+        min_broker_id: u64,
+        min_broker_addr: Option<CheetahString>,
+    ) -> Option<BrokerRoleChange> {
+        *self.min_broker_addr.write() = min_broker_addr;
+        let previous = self.min_broker_id.swap(min_broker_id, Ordering::AcqRel);
+        if previous != min_broker_id {
+            info!(
+                "Min broker id of group changed, old: {}, new: {}",
+                previous, min_broker_id
+            );
+        }
+        let acting_master = self.broker_id == MASTER_ID || self.broker_id == min_broker_id;
+        if self.acting_master.swap(acting_master, Ordering::AcqRel) == acting_master {
+            return None;
+        }
+        if acting_master {
+            Some(BrokerRoleChange::ChangeToMaster)
+        } else {
+            Some(BrokerRoleChange::ChangeToSlave)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::broker::broker_config::BrokerIdentity;
+
+    use super::*;
+
+    fn cache(broker_id: u64) -> BrokerMemberGroupCache {
+        BrokerMemberGroupCache::new(&BrokerConfig {
+            broker_identity: BrokerIdentity {
+                broker_id,
+                ..BrokerIdentity::default()
+            },
+            ..BrokerConfig::default()
+        })
+    }
+
+    #[test]
+    fn slave_acts_as_master_while_it_holds_the_min_broker_id() {
+        let cache = cache(1);
+        assert!(!cache.is_acting_master());
+        assert_eq!(
+            cache.update_min_broker(0, Some("127.0.0.1:10911".into())),
+            None
+        );
+
+        assert_eq!(
+            cache.update_min_broker(1, Some("127.0.0.1:10921".into())),
+            Some(BrokerRoleChange::ChangeToMaster)
+        );
+        assert!(cache.is_acting_master());
+        assert_eq!(cache.min_broker_id(), 1);
+
+        assert_eq!(
+            cache.update_min_broker(0, Some("127.0.0.1:10911".into())),
+            Some(BrokerRoleChange::ChangeToSlave)
+        );
+        assert!(!cache.is_acting_master());
+        assert_eq!(cache.min_broker_addr(), Some("127.0.0.1:10911".into()));
+    }
+
+    #[test]
+    fn slave_started_without_master_becomes_acting_master() {
+        let cache = cache(1);
+        assert_eq!(
+            cache.update_min_broker(1, None),
+            Some(BrokerRoleChange::ChangeToMaster)
+        );
+        assert_eq!(cache.update_min_broker(1, None), None);
+    }
+
+    #[test]
+    fn master_keeps_its_role_when_the_min_broker_id_changes() {
+        let cache = cache(MASTER_ID);
+        assert!(cache.is_acting_master());
+        assert_eq!(cache.update_min_broker(1, None), None);
+        assert!(cache.is_acting_master());
+    }
+
+    #[test]
+    fn update_group_derives_the_min_broker_id() {
+        let cache = cache(2);
+        let mut group = cache.group();
+        group.broker_addrs.insert(1, "127.0.0.1:10921".into());
+        assert_eq!(cache.update_group(group), None);
+        assert_eq!(cache.min_broker_id(), 1);
+        assert!(cache.contains_broker(1));
+        assert!(cache.contains_broker(2));
+    }
+}
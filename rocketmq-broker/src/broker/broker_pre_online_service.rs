@@ -0,0 +1,229 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::mix_all::MASTER_ID;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
+use rocketmq_store::log_file::MessageStore;
+use tracing::info;
+use tracing::warn;
+
+use crate::out_api::broker_outer_api::BrokerOuterAPI;
+
+/// Runtime info key holding the commit log max offset of a broker.
+pub const COMMIT_LOG_MAX_OFFSET: &str = "commitLogMaxOffset";
+
+/// How far behind a starting broker is.
+pub(crate) trait PreOnlineProbe {
+    /// Commit log bytes not yet dispatched to the consume queues.
+    fn dispatch_behind_bytes(&self) -> i64;
+
+    /// Commit log bytes the broker lags behind its master, `None` on a master or while the master
+    /// cannot be reached.
+    async fn slave_fall_behind_bytes(&self) -> Option<i64>;
+}
+
+/// Keeps a starting broker isolated, i.e. unregistered and refusing heartbeats and pulls, until
+/// its store has caught up, so that clients never see the offsets of a stale store.
+pub(crate) struct BrokerPreOnlineService {
+    is_isolated: Arc<AtomicBool>,
+    max_dispatch_behind_bytes: i64,
+    ha_slave_fallbehind_max: i64,
+}
+
+impl BrokerPreOnlineService {
+    pub fn new(
+        is_isolated: Arc<AtomicBool>,
+        broker_config: &BrokerConfig,
+        message_store_config: &MessageStoreConfig,
+    ) -> Self {
+        Self {
+            is_isolated,
+            max_dispatch_behind_bytes: broker_config.broker_pre_online_max_dispatch_behind_bytes,
+            ha_slave_fallbehind_max: message_store_config.ha_slave_fallbehind_max,
+        }
+    }
+
+    pub async fn caught_up(&self, probe: &impl PreOnlineProbe) -> bool {
+        let dispatch_behind_bytes = probe.dispatch_behind_bytes();
+        if dispatch_behind_bytes > self.max_dispatch_behind_bytes {
+            info!(
+                "broker pre-online: dispatch behind {} bytes, wait until below {}",
+                dispatch_behind_bytes, self.max_dispatch_behind_bytes
+            );
+            return false;
+        }
+        match probe.slave_fall_behind_bytes().await {
+            Some(fall_behind) if fall_behind > self.ha_slave_fallbehind_max => {
+                info!(
+                    "broker pre-online: slave falls behind master {} bytes, wait until below {}",
+                    fall_behind, self.ha_slave_fallbehind_max
+                );
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Checks `probe` every `period` until the broker caught up, then lifts the isolation.
+    pub async fn wait_online(&self, probe: &impl PreOnlineProbe, period: Duration) {
+        while !self.caught_up(probe).await {
+            tokio::time::sleep(period).await;
+        }
+        self.is_isolated.store(false, Ordering::Release);
+        info!("broker pre-online finished, broker is online");
+    }
+}
+
+/// Probes the local message store, and the master's commit log through the name server.
+pub(crate) struct StorePreOnlineProbe<MS> {
+    pub(crate) message_store: ArcMut<MS>,
+    pub(crate) broker_out_api: Arc<BrokerOuterAPI>,
+    pub(crate) broker_config: Arc<BrokerConfig>,
+    pub(crate) is_slave: bool,
+}
+
+impl<MS: MessageStore> StorePreOnlineProbe<MS> {
+    async fn master_max_offset(&self) -> Option<i64> {
+        let identity = &self.broker_config.broker_identity;
+        let group = match self
+            .broker_out_api
+            .sync_broker_member_group(&identity.broker_cluster_name, &identity.broker_name)
+            .await
+        {
+            Ok(group) => group?,
+            Err(e) => {
+                warn!("broker pre-online: fetch broker member group failed, {}", e);
+                return None;
+            }
+        };
+        let master_addr = group.broker_addrs.get(&MASTER_ID)?;
+        match self
+            .broker_out_api
+            .get_broker_runtime_info(master_addr, 3000)
+            .await
+        {
+            Ok(runtime_info) => runtime_info
+                .table
+                .get(&CheetahString::from_static_str(COMMIT_LOG_MAX_OFFSET))
+                .and_then(|offset| offset.parse().ok()),
+            Err(e) => {
+                warn!(
+                    "broker pre-online: fetch runtime info of master {} failed, {}",
+                    master_addr, e
+                );
+                None
+            }
+        }
+    }
+}
+
+impl<MS: MessageStore> PreOnlineProbe for StorePreOnlineProbe<MS> {
+    fn dispatch_behind_bytes(&self) -> i64 {
+        self.message_store.dispatch_behind_bytes()
+    }
+
+    async fn slave_fall_behind_bytes(&self) -> Option<i64> {
+        if !self.is_slave {
+            return None;
+        }
+        let master_max_offset = self.master_max_offset().await?;
+        Some(master_max_offset - self.message_store.get_max_phy_offset())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicI64;
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    /// Dispatches `step` bytes of the backlog on every check.
+    struct CatchingUpProbe {
+        dispatch_behind: AtomicI64,
+        step: i64,
+        slave_fall_behind: Option<i64>,
+        checks: AtomicUsize,
+    }
+
+    impl PreOnlineProbe for CatchingUpProbe {
+        fn dispatch_behind_bytes(&self) -> i64 {
+            self.checks.fetch_add(1, Ordering::Relaxed);
+            self.dispatch_behind.fetch_sub(self.step, Ordering::Relaxed)
+        }
+
+        async fn slave_fall_behind_bytes(&self) -> Option<i64> {
+            self.slave_fall_behind
+        }
+    }
+
+    fn service(is_isolated: Arc<AtomicBool>) -> BrokerPreOnlineService {
+        let broker_config = BrokerConfig {
+            broker_pre_online_max_dispatch_behind_bytes: 100,
+            ..BrokerConfig::default()
+        };
+        let message_store_config = MessageStoreConfig {
+            ha_slave_fallbehind_max: 1000,
+            ..MessageStoreConfig::default()
+        };
+        BrokerPreOnlineService::new(is_isolated, &broker_config, &message_store_config)
+    }
+
+    #[tokio::test]
+    async fn stays_isolated_until_dispatch_caught_up() {
+        let is_isolated = Arc::new(AtomicBool::new(true));
+        let service = service(is_isolated.clone());
+        let probe = CatchingUpProbe {
+            dispatch_behind: AtomicI64::new(400),
+            step: 100,
+            slave_fall_behind: None,
+            checks: AtomicUsize::new(0),
+        };
+
+        assert!(!service.caught_up(&probe).await);
+        assert!(is_isolated.load(Ordering::Acquire));
+
+        service.wait_online(&probe, Duration::from_millis(1)).await;
+        assert!(!is_isolated.load(Ordering::Acquire));
+        assert_eq!(probe.checks.load(Ordering::Relaxed), 4);
+    }
+
+    #[tokio::test]
+    async fn slave_waits_for_master_commit_log() {
+        let service = service(Arc::new(AtomicBool::new(true)));
+        let lagging = CatchingUpProbe {
+            dispatch_behind: AtomicI64::new(0),
+            step: 0,
+            slave_fall_behind: Some(1001),
+            checks: AtomicUsize::new(0),
+        };
+        assert!(!service.caught_up(&lagging).await);
+
+        let close = CatchingUpProbe {
+            slave_fall_behind: Some(1000),
+            ..lagging
+        };
+        assert!(service.caught_up(&close).await);
+    }
+}
@@ -0,0 +1,254 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::FutureExt;
+use parking_lot::Condvar;
+use parking_lot::Mutex;
+use parking_lot::RwLock;
+use rand::Rng;
+use rocketmq_common::TimeUtils::get_current_millis;
+use tokio::sync::Notify;
+use tracing::error;
+use tracing::info;
+use tracing::Instrument;
+
+/// Upper bound of the random delay added to the initial delay of every task, so that tasks
+/// sharing an interval do not all tick at once.
+const MAX_START_JITTER: Duration = Duration::from_secs(1);
+
+/// Runs the broker housekeeping tasks at fixed rates.
+///
+/// A panic raised by a tick is logged and the task keeps its schedule. [`shutdown`] stops
+/// scheduling new ticks and waits for the running ones to complete.
+///
+/// [`shutdown`]: BrokerTaskManager::shutdown
+pub(crate) struct BrokerTaskManager {
+    handle: tokio::runtime::Handle,
+    state: Arc<State>,
+}
+
+#[derive(Default)]
+struct State {
+    stopped: AtomicBool,
+    stop: Notify,
+    in_flight: Mutex<usize>,
+    idle: Condvar,
+    last_runs: RwLock<BTreeMap<&'static str, u64>>,
+}
+
+impl State {
+    /// Reserves a tick, `false` once the manager is shut down.
+    fn begin_tick(&self) -> bool {
+        let mut in_flight = self.in_flight.lock();
+        if self.stopped.load(Ordering::Acquire) {
+            return false;
+        }
+        *in_flight += 1;
+        true
+    }
+
+    fn end_tick(&self, name: &'static str) {
+        self.last_runs.write().insert(name, get_current_millis());
+        let mut in_flight = self.in_flight.lock();
+        *in_flight -= 1;
+        if *in_flight == 0 {
+            self.idle.notify_all();
+        }
+    }
+}
+
+impl BrokerTaskManager {
+    pub fn new(handle: tokio::runtime::Handle) -> Self {
+        Self {
+            handle,
+            state: Arc::new(State::default()),
+        }
+    }
+
+    /// Runs `task` every `period` after `initial_delay` plus a small random jitter. Ticks never
+    /// overlap: a tick that overruns its period delays the next one.
+    pub fn schedule_at_fixed_rate<F, Fut>(
+        &self,
+        name: &'static str,
+        initial_delay: Duration,
+        period: Duration,
+        mut task: F,
+    ) where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let state = self.state.clone();
+        let jitter = rand::thread_rng().gen_range(Duration::ZERO..=MAX_START_JITTER.min(period));
+        self.handle.spawn(
+            async move {
+                info!("{} Start scheduled task", name);
+                let mut next_execution_time = tokio::time::Instant::now() + initial_delay + jitter;
+                while !state.stopped.load(Ordering::Acquire) {
+                    tokio::select! {
+                        _ = tokio::time::sleep_until(next_execution_time) => {}
+                        _ = state.stop.notified() => {}
+                    }
+                    if !state.begin_tick() {
+                        break;
+                    }
+                    next_execution_time = tokio::time::Instant::now() + period;
+                    if AssertUnwindSafe(task()).catch_unwind().await.is_err() {
+                        error!(
+                            "Scheduled task {} panicked, it will run again in {:?}",
+                            name, period
+                        );
+                    }
+                    state.end_tick(name);
+                }
+                info!("{} scheduled task stopped", name);
+            }
+            .in_current_span(),
+        );
+    }
+
+    pub fn last_runs(&self) -> TaskLastRuns {
+        TaskLastRuns(self.state.clone())
+    }
+
+    /// Stops every task and waits up to `timeout` for the running ticks, returning `false` when
+    /// some tick is still running after it.
+    pub fn shutdown(&self, timeout: Duration) -> bool {
+        let mut in_flight = self.state.in_flight.lock();
+        self.state.stopped.store(true, Ordering::Release);
+        self.state.stop.notify_waiters();
+        let deadline = std::time::Instant::now() + timeout;
+        while *in_flight > 0 {
+            if self
+                .state
+                .idle
+                .wait_until(&mut in_flight, deadline)
+                .timed_out()
+            {
+                return *in_flight == 0;
+            }
+        }
+        true
+    }
+}
+
+/// Read-only view of the last run timestamps of a [`BrokerTaskManager`].
+#[derive(Clone)]
+pub struct TaskLastRuns(Arc<State>);
+
+impl TaskLastRuns {
+    /// Completion timestamps of the last tick of every task that ran at least once.
+    pub fn get(&self) -> BTreeMap<&'static str, u64> {
+        self.0.last_runs.read().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn panicking_tick_does_not_stop_the_task() {
+        let manager = BrokerTaskManager::new(tokio::runtime::Handle::current());
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let counter = ticks.clone();
+        manager.schedule_at_fixed_rate(
+            "panicking",
+            Duration::ZERO,
+            Duration::from_millis(5),
+            move || {
+                let tick = counter.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if tick == 0 {
+                        panic!("first tick fails");
+                    }
+                }
+            },
+        );
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+        while ticks.load(Ordering::SeqCst) < 2 && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert!(ticks.load(Ordering::SeqCst) > 1);
+        assert!(manager.last_runs().get().contains_key("panicking"));
+        assert!(manager.shutdown(Duration::from_secs(1)));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn shutdown_waits_for_running_tick() {
+        let manager = Arc::new(BrokerTaskManager::new(tokio::runtime::Handle::current()));
+        let started = Arc::new(AtomicUsize::new(0));
+        let finished = Arc::new(AtomicUsize::new(0));
+        let (started_clone, finished_clone) = (started.clone(), finished.clone());
+        manager.schedule_at_fixed_rate(
+            "slow",
+            Duration::ZERO,
+            Duration::from_millis(1),
+            move || {
+                let started = started_clone.clone();
+                let finished = finished_clone.clone();
+                async move {
+                    started.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    finished.fetch_add(1, Ordering::SeqCst);
+                }
+            },
+        );
+        while started.load(Ordering::SeqCst) == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        let stopper = manager.clone();
+        let stopped = tokio::task::spawn_blocking(move || stopper.shutdown(Duration::from_secs(5)))
+            .await
+            .unwrap();
+        assert!(stopped);
+        let finished_at_shutdown = finished.load(Ordering::SeqCst);
+        assert_eq!(finished_at_shutdown, started.load(Ordering::SeqCst));
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(started.load(Ordering::SeqCst), finished_at_shutdown);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn shutdown_before_first_tick_never_runs_task() {
+        let manager = BrokerTaskManager::new(tokio::runtime::Handle::current());
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let counter = ticks.clone();
+        manager.schedule_at_fixed_rate(
+            "idle",
+            Duration::from_secs(60),
+            Duration::from_secs(60),
+            move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async {}
+            },
+        );
+        assert!(manager.shutdown(Duration::from_millis(10)));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(ticks.load(Ordering::SeqCst), 0);
+        assert!(manager.last_runs().get().is_empty());
+    }
+}
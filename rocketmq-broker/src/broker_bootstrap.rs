@@ -18,9 +18,14 @@
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
 use rocketmq_common::common::server::config::ServerConfig;
 use rocketmq_store::config::message_store_config::MessageStoreConfig;
+use rocketmq_store::plugin::dyn_message_store::BoxedMessageStore;
+use rocketmq_store::plugin::message_store_plugin_context::MessageStorePluginContext;
 use tracing::error;
+use tracing::info_span;
+use tracing::Instrument;
 
 use crate::broker_runtime::BrokerRuntime;
+use crate::plugin::message_store_factory::MessageStoreFactory;
 
 pub struct BrokerBootstrap {
     broker_runtime: BrokerRuntime,
@@ -28,11 +33,36 @@ pub struct BrokerBootstrap {
 
 impl BrokerBootstrap {
     pub async fn boot(mut self) {
+        // every record logged while booting, and by the services started from it, carries the
+        // identity of this broker
+        let span = info_span!(
+            "broker",
+            identity = %self
+                .broker_runtime
+                .broker_config()
+                .broker_identity
+                .get_logger_identifier()
+        );
+        async move {
+            if !self.initialize().await {
+                error!("initialize fail");
+                return;
+            }
+            let (_start_result, _ctrl_c) = tokio::join!(self.start(), tokio::signal::ctrl_c());
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// Initializes and starts the broker without waiting for a shutdown signal, for a broker
+    /// embedded in another process. Returns `false` if initialization failed.
+    pub async fn start_embedded(&mut self) -> bool {
         if !self.initialize().await {
             error!("initialize fail");
-            return;
+            return false;
         }
-        let (_start_result, _ctrl_c) = tokio::join!(self.start(), tokio::signal::ctrl_c());
+        self.start().await;
+        true
     }
 
     async fn initialize(&mut self) -> bool {
@@ -48,6 +78,7 @@ pub struct Builder {
     broker_config: BrokerConfig,
     message_store_config: MessageStoreConfig,
     server_config: ServerConfig,
+    message_store_factory: MessageStoreFactory,
 }
 
 impl Builder {
@@ -56,6 +87,7 @@ impl Builder {
             broker_config: Default::default(),
             message_store_config: MessageStoreConfig::default(),
             server_config: Default::default(),
+            message_store_factory: MessageStoreFactory::default(),
         }
     }
 
@@ -74,14 +106,26 @@ impl Builder {
         self
     }
 
+    /// Registers a message store plugin, enabled by naming it in `messageStorePlugIn`.
+    pub fn register_message_store_plugin<F>(mut self, name: &str, builder: F) -> Self
+    where
+        F: Fn(&MessageStorePluginContext, BoxedMessageStore) -> BoxedMessageStore
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.message_store_factory.register(name, builder);
+        self
+    }
+
     pub fn build(self) -> BrokerBootstrap {
-        BrokerBootstrap {
-            broker_runtime: BrokerRuntime::new(
-                self.broker_config,
-                self.message_store_config,
-                self.server_config,
-            ),
-        }
+        let mut broker_runtime = BrokerRuntime::new(
+            self.broker_config,
+            self.message_store_config,
+            self.server_config,
+        );
+        broker_runtime.set_message_store_factory(self.message_store_factory);
+        BrokerBootstrap { broker_runtime }
     }
 }
 
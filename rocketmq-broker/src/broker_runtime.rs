@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
@@ -28,9 +29,9 @@ use rocketmq_common::common::config_manager::ConfigManager;
 use rocketmq_common::common::constant::PermName;
 use rocketmq_common::common::server::config::ServerConfig;
 use rocketmq_common::common::statistics::state_getter::StateGetter;
+use rocketmq_common::log::WATER_MARK_LOGGER_NAME;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_common::UtilAll::compute_next_morning_time_millis;
-use rocketmq_remoting::protocol::body::broker_body::broker_member_group::BrokerMemberGroup;
 use rocketmq_remoting::protocol::body::topic_info_wrapper::topic_config_wrapper::TopicConfigAndMappingSerializeWrapper;
 use rocketmq_remoting::protocol::body::topic_info_wrapper::topic_config_wrapper::TopicConfigSerializeWrapper;
 use rocketmq_remoting::protocol::namespace_util::NamespaceUtil;
@@ -38,33 +39,54 @@ use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_detail::Topic
 use rocketmq_remoting::protocol::DataVersion;
 use rocketmq_remoting::remoting_server::server::RocketMQServer;
 use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
+use rocketmq_remoting::runtime::RPCHook;
 use rocketmq_runtime::RocketMQRuntime;
 use rocketmq_rust::ArcMut;
 use rocketmq_store::base::store_enum::StoreType;
+use rocketmq_store::config::broker_role::BrokerRole;
 use rocketmq_store::config::message_store_config::MessageStoreConfig;
 use rocketmq_store::log_file::MessageStore;
 use rocketmq_store::message_store::default_message_store::DefaultMessageStore;
+use rocketmq_store::plugin::dyn_message_store::BoxedMessageStore;
+use rocketmq_store::plugin::message_store_plugin_context::MessageStorePluginContext;
 use rocketmq_store::stats::broker_stats::BrokerStats;
 use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
 use rocketmq_store::timer::timer_message_store::TimerMessageStore;
+use tokio::runtime::Handle;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::error;
 use tracing::info;
 use tracing::warn;
 
+use crate::acl::plain_access_validator::PlainAccessValidator;
 use crate::broker::broker_hook::BrokerShutdownHook;
+use crate::broker::broker_member_group_cache::BrokerMemberGroupCache;
+use crate::broker::broker_pre_online_service::BrokerPreOnlineService;
+use crate::broker::broker_pre_online_service::StorePreOnlineProbe;
+use crate::broker::broker_task_manager::BrokerTaskManager;
 use crate::client::default_consumer_ids_change_listener::DefaultConsumerIdsChangeListener;
 use crate::client::manager::consumer_manager::ConsumerManager;
 use crate::client::manager::producer_manager::ProducerManager;
 use crate::client::net::broker_to_client::Broker2Client;
 use crate::client::rebalance::rebalance_lock_manager::RebalanceLockManager;
+use crate::coldctr::cold_data_cg_ctr_service::ColdDataCgCtrService;
+use crate::coldctr::cold_data_pull_request_hold_service::ColdDataPullRequestHoldService;
+use crate::filter::commit_log_dispatcher_calc_bit_map::CommitLogDispatcherCalcBitMap;
 use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
 use crate::hook::batch_check_before_put_message::BatchCheckBeforePutMessageHook;
 use crate::hook::check_before_put_message::CheckBeforePutMessageHook;
+use crate::hook::schedule_message_hook::ScheduleMessageHook;
+use crate::long_polling::long_polling_service::pop_long_polling_service::PopLongPollingService;
 use crate::long_polling::long_polling_service::pull_request_hold_service::PullRequestHoldService;
 use crate::long_polling::notify_message_arriving_listener::NotifyMessageArrivingListener;
+use crate::metrics::broker_metrics_manager::BrokerMetricsManager;
+use crate::metrics::broker_metrics_manager::BrokerMetricsSource;
 use crate::offset::manager::broadcast_offset_manager::BroadcastOffsetManager;
 use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
 use crate::offset::manager::consumer_order_info_manager::ConsumerOrderInfoManager;
 use crate::out_api::broker_outer_api::BrokerOuterAPI;
+use crate::plugin::message_store_factory::MessageStoreFactory;
 use crate::processor::admin_broker_processor::AdminBrokerProcessor;
 use crate::processor::client_manage_processor::ClientManageProcessor;
 use crate::processor::consumer_manage_processor::ConsumerManageProcessor;
@@ -72,6 +94,7 @@ use crate::processor::default_pull_message_result_handler::DefaultPullMessageRes
 use crate::processor::end_transaction_processor::EndTransactionProcessor;
 use crate::processor::pull_message_processor::PullMessageProcessor;
 use crate::processor::pull_message_result_handler::PullMessageResultHandler;
+use crate::processor::query_assignment_processor::QueryAssignmentProcessor;
 use crate::processor::query_message_processor::QueryMessageProcessor;
 use crate::processor::reply_message_processor::ReplyMessageProcessor;
 use crate::processor::send_message_processor::SendMessageProcessor;
@@ -80,6 +103,7 @@ use crate::schedule::schedule_message_service::ScheduleMessageService;
 use crate::subscription::manager::subscription_group_manager::SubscriptionGroupManager;
 use crate::topic::manager::topic_config_manager::TopicConfigManager;
 use crate::topic::manager::topic_queue_mapping_manager::TopicQueueMappingManager;
+use crate::topic::manager::topic_route_info_manager::TopicRouteInfoManager;
 use crate::topic::topic_queue_mapping_clean_service::TopicQueueMappingCleanService;
 use crate::transaction::queue::default_transactional_message_check_listener::DefaultTransactionalMessageCheckListener;
 use crate::transaction::queue::default_transactional_message_service::DefaultTransactionalMessageService;
@@ -87,6 +111,12 @@ use crate::transaction::queue::transactional_message_bridge::TransactionalMessag
 use crate::transaction::transaction_metrics_flush_service::TransactionMetricsFlushService;
 use crate::transaction::transactional_message_check_service::TransactionalMessageCheckService;
 
+/// The request processor of a broker storing messages locally.
+pub(crate) type DefaultBrokerRequestProcessor = BrokerRequestProcessor<
+    BoxedMessageStore,
+    DefaultTransactionalMessageService<DefaultMessageStore>,
+>;
+
 pub(crate) struct BrokerRuntime {
     broker_config: Arc<BrokerConfig>,
     message_store_config: Arc<MessageStoreConfig>,
@@ -95,20 +125,30 @@ pub(crate) struct BrokerRuntime {
     topic_queue_mapping_manager: Arc<TopicQueueMappingManager>,
     consumer_offset_manager: ConsumerOffsetManager,
     #[cfg(feature = "local_file_store")]
-    subscription_group_manager: Arc<SubscriptionGroupManager<DefaultMessageStore>>,
+    subscription_group_manager: Arc<SubscriptionGroupManager<BoxedMessageStore>>,
     consumer_filter_manager: Arc<ConsumerFilterManager>,
     consumer_order_info_manager: Arc<ConsumerOrderInfoManager>,
     #[cfg(feature = "local_file_store")]
     message_store: Option<ArcMut<DefaultMessageStore>>,
+    /// `message_store` wrapped with the configured plugins, what client requests are served from.
+    plugin_message_store: Option<ArcMut<BoxedMessageStore>>,
+    message_store_factory: Arc<MessageStoreFactory>,
     #[cfg(feature = "local_file_store")]
     broker_stats: Option<Arc<BrokerStats<DefaultMessageStore>>>,
     //message_store: Option<Arc<Mutex<LocalFileMessageStore>>>,
     schedule_message_service: ScheduleMessageService,
-    timer_message_store: Option<TimerMessageStore>,
+    plain_access_validator: PlainAccessValidator,
+    timer_message_store: Option<Arc<TimerMessageStore>>,
 
     broker_out_api: Arc<BrokerOuterAPI>,
+    topic_route_info_manager: Arc<TopicRouteInfoManager>,
 
     broker_runtime: Option<RocketMQRuntime>,
+    /// Where the services of the broker run: `broker_runtime`, or the runtime of the container
+    /// hosting the broker.
+    runtime_handle: Handle,
+    /// The processor of the remoting server of the container, for a broker hosted in one.
+    container_request_processor: Option<DefaultBrokerRequestProcessor>,
     producer_manager: Arc<ProducerManager>,
     consumer_manager: Arc<ConsumerManager>,
     broadcast_offset_manager: Arc<BroadcastOffsetManager>,
@@ -121,9 +161,14 @@ pub(crate) struct BrokerRuntime {
     should_start_time: Arc<AtomicU64>,
     is_isolated: Arc<AtomicBool>,
     #[cfg(feature = "local_file_store")]
-    pull_request_hold_service: Option<ArcMut<PullRequestHoldService<DefaultMessageStore>>>,
+    pull_request_hold_service: Option<ArcMut<PullRequestHoldService<BoxedMessageStore>>>,
+    cold_data_cg_ctr_service: Arc<ColdDataCgCtrService>,
+    #[cfg(feature = "local_file_store")]
+    cold_data_pull_request_hold_service:
+        Option<ArcMut<ColdDataPullRequestHoldService<BoxedMessageStore>>>,
+    pop_long_polling_service: Arc<PopLongPollingService>,
     rebalance_lock_manager: Arc<RebalanceLockManager>,
-    broker_member_group: Arc<BrokerMemberGroup>,
+    broker_member_group: Arc<BrokerMemberGroupCache>,
     #[cfg(feature = "local_file_store")]
     transactional_message_service:
         Option<ArcMut<DefaultTransactionalMessageService<DefaultMessageStore>>>,
@@ -132,6 +177,8 @@ pub(crate) struct BrokerRuntime {
         Option<Arc<DefaultTransactionalMessageCheckListener<DefaultMessageStore>>>,
     transactional_message_check_service: Option<Arc<TransactionalMessageCheckService>>,
     transaction_metrics_flush_service: Option<Arc<TransactionMetricsFlushService>>,
+    broker_metrics_manager: Option<Arc<BrokerMetricsManager>>,
+    task_manager: Arc<BrokerTaskManager>,
 }
 
 impl Clone for BrokerRuntime {
@@ -144,14 +191,20 @@ impl Clone for BrokerRuntime {
             topic_queue_mapping_manager: self.topic_queue_mapping_manager.clone(),
             consumer_offset_manager: self.consumer_offset_manager.clone(),
             subscription_group_manager: self.subscription_group_manager.clone(),
-            consumer_filter_manager: Arc::new(Default::default()),
+            consumer_filter_manager: self.consumer_filter_manager.clone(),
             consumer_order_info_manager: Arc::new(Default::default()),
             message_store: self.message_store.clone(),
+            plugin_message_store: self.plugin_message_store.clone(),
+            message_store_factory: self.message_store_factory.clone(),
             broker_stats: self.broker_stats.clone(),
             schedule_message_service: self.schedule_message_service.clone(),
+            plain_access_validator: self.plain_access_validator.clone(),
             timer_message_store: self.timer_message_store.clone(),
             broker_out_api: self.broker_out_api.clone(),
+            topic_route_info_manager: self.topic_route_info_manager.clone(),
             broker_runtime: None,
+            runtime_handle: self.runtime_handle.clone(),
+            container_request_processor: self.container_request_processor.clone(),
             producer_manager: self.producer_manager.clone(),
             consumer_manager: self.consumer_manager.clone(),
             broadcast_offset_manager: self.broadcast_offset_manager.clone(),
@@ -164,12 +217,17 @@ impl Clone for BrokerRuntime {
             should_start_time: self.should_start_time.clone(),
             is_isolated: self.is_isolated.clone(),
             pull_request_hold_service: self.pull_request_hold_service.clone(),
+            cold_data_cg_ctr_service: self.cold_data_cg_ctr_service.clone(),
+            cold_data_pull_request_hold_service: self.cold_data_pull_request_hold_service.clone(),
+            pop_long_polling_service: self.pop_long_polling_service.clone(),
             rebalance_lock_manager: self.rebalance_lock_manager.clone(),
             broker_member_group: self.broker_member_group.clone(),
             transactional_message_service: self.transactional_message_service.clone(),
             transactional_message_check_listener: self.transactional_message_check_listener.clone(),
             transactional_message_check_service: None,
             transaction_metrics_flush_service: None,
+            broker_metrics_manager: self.broker_metrics_manager.clone(),
+            task_manager: self.task_manager.clone(),
         }
     }
 }
@@ -180,8 +238,55 @@ impl BrokerRuntime {
         message_store_config: MessageStoreConfig,
         server_config: ServerConfig,
     ) -> Self {
+        Self::with_runtime_handle(broker_config, message_store_config, server_config, None)
+    }
+
+    /// A broker hosted in a container: its services run on `runtime_handle` and requests reach
+    /// it through [`container_request_processor`](Self::container_request_processor) instead
+    /// of servers of its own.
+    pub(crate) fn new_in_container(
+        mut broker_config: BrokerConfig,
+        mut message_store_config: MessageStoreConfig,
+        runtime_handle: Handle,
+    ) -> Self {
+        broker_config.is_in_broker_container = true;
+        broker_config.broker_identity.is_in_broker_container = true;
+        message_store_config.isolate_store_path_by_identity = true;
+        Self::with_runtime_handle(
+            broker_config,
+            message_store_config,
+            ServerConfig::default(),
+            Some(runtime_handle),
+        )
+    }
+
+    fn with_runtime_handle(
+        broker_config: BrokerConfig,
+        message_store_config: MessageStoreConfig,
+        server_config: ServerConfig,
+        runtime_handle: Option<Handle>,
+    ) -> Self {
+        let mut broker_config = broker_config;
+        let mut message_store_config = message_store_config;
+        if message_store_config.isolate_store_path(&broker_config.broker_identity) {
+            // broker metadata lives under the same root as the store it describes
+            broker_config.store_path_root_dir = message_store_config.store_path_root_dir.clone();
+            info!(
+                "{} store root isolated to {}",
+                broker_config.broker_identity.get_logger_identifier(),
+                message_store_config.store_path_root_dir
+            );
+        }
         let broker_config = Arc::new(broker_config);
-        let runtime = RocketMQRuntime::new_multi(10, "broker-thread");
+        let (runtime, runtime_handle) = match runtime_handle {
+            Some(runtime_handle) => (None, runtime_handle),
+            None => {
+                let runtime = RocketMQRuntime::new_multi(10, "broker-thread");
+                let runtime_handle = runtime.get_handle().clone();
+                (Some(runtime), runtime_handle)
+            }
+        };
+        let task_manager = Arc::new(BrokerTaskManager::new(runtime_handle.clone()));
         let broker_outer_api =
             Arc::new(BrokerOuterAPI::new(Arc::new(TokioClientConfig::default())));
         let server_config = Arc::new(server_config);
@@ -199,8 +304,11 @@ impl BrokerRuntime {
             TopicConfigManager::new(broker_config.clone(), broker_runtime_inner);
         let mut stats_manager = BrokerStatsManager::new(broker_config.clone());
         let producer_manager = Arc::new(ProducerManager::new());
+        let consumer_filter_manager = Arc::new(ConsumerFilterManager::new(broker_config.clone()));
         let consumer_manager = Arc::new(ConsumerManager::new_with_broker_stats(
-            Box::new(DefaultConsumerIdsChangeListener {}),
+            Box::new(DefaultConsumerIdsChangeListener::new(
+                consumer_filter_manager.clone(),
+            )),
             broker_config.clone(),
         ));
         stats_manager.set_producer_state_getter(Arc::new(ProducerStateGetter {
@@ -213,14 +321,11 @@ impl BrokerRuntime {
         }));
         let broker_stats_manager = Arc::new(stats_manager);
         consumer_manager.set_broker_stats_manager(Some(Arc::downgrade(&broker_stats_manager)));
-        let mut broker_member_group = BrokerMemberGroup::new(
-            broker_config.broker_identity.broker_cluster_name.clone(),
-            broker_config.broker_identity.broker_name.clone(),
-        );
-        broker_member_group.broker_addrs.insert(
-            broker_config.broker_identity.broker_id,
-            broker_config.get_broker_addr().into(),
-        );
+        let broker_member_group = Arc::new(BrokerMemberGroupCache::new(&broker_config));
+        let schedule_message_service =
+            ScheduleMessageService::new(broker_config.clone(), message_store_config.clone());
+        let plain_access_validator =
+            PlainAccessValidator::new(broker_config.acl_file_path.as_str());
         Self {
             broker_config: broker_config.clone(),
             message_store_config,
@@ -232,14 +337,22 @@ impl BrokerRuntime {
                 broker_config.clone(),
                 None,
             )),
-            consumer_filter_manager: Arc::new(Default::default()),
+            consumer_filter_manager,
             consumer_order_info_manager: Arc::new(Default::default()),
             message_store: None,
+            plugin_message_store: None,
+            message_store_factory: Arc::new(MessageStoreFactory::default()),
             broker_stats: None,
-            schedule_message_service: Default::default(),
+            schedule_message_service,
+            plain_access_validator,
             timer_message_store: None,
+            topic_route_info_manager: Arc::new(TopicRouteInfoManager::new(
+                broker_outer_api.clone(),
+            )),
             broker_out_api: broker_outer_api,
-            broker_runtime: Some(runtime),
+            broker_runtime: runtime,
+            runtime_handle,
+            container_request_processor: None,
             producer_manager,
             consumer_manager,
             broadcast_offset_manager: Arc::new(Default::default()),
@@ -252,12 +365,17 @@ impl BrokerRuntime {
             should_start_time: Arc::new(AtomicU64::new(0)),
             is_isolated: Arc::new(AtomicBool::new(false)),
             pull_request_hold_service: None,
+            cold_data_cg_ctr_service: Arc::new(ColdDataCgCtrService::new(broker_config.clone())),
+            cold_data_pull_request_hold_service: None,
+            pop_long_polling_service: Arc::new(PopLongPollingService::new()),
             rebalance_lock_manager: Arc::new(Default::default()),
-            broker_member_group: Arc::new(broker_member_group),
+            broker_member_group,
             transactional_message_service: None,
             transactional_message_check_listener: None,
             transactional_message_check_service: None,
             transaction_metrics_flush_service: None,
+            broker_metrics_manager: None,
+            task_manager,
         }
     }
 
@@ -269,19 +387,49 @@ impl BrokerRuntime {
         &self.message_store_config
     }
 
+    pub(crate) fn set_message_store_factory(&mut self, message_store_factory: MessageStoreFactory) {
+        self.message_store_factory = Arc::new(message_store_factory);
+    }
+
+    /// Stops the services of the broker and persists its metadata. Only the first call has an
+    /// effect.
     pub fn shutdown(&mut self) {
+        if self.drop.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        self.shutdown.store(true, Ordering::Release);
+        if !self.task_manager.shutdown(Duration::from_secs(5)) {
+            warn!("Broker scheduled tasks still running after shutdown timeout");
+        }
         self.broker_out_api.shutdown();
-        if let Some(message_store) = &mut self.message_store {
+        if let Some(broker_metrics_manager) = self.broker_metrics_manager.take() {
+            broker_metrics_manager.shutdown();
+        }
+        if let Some(timer_message_store) = self.timer_message_store.as_ref() {
+            timer_message_store.shutdown();
+        }
+        if let Some(message_store) = &mut self.plugin_message_store {
             message_store.shutdown()
         }
 
         self.topic_config_manager.persist();
         info!("[Broker shutdown]TopicConfigManager persist success");
         let _ = self.topic_config_manager.stop();
+        self.subscription_group_manager.persist();
+        self.consumer_filter_manager.persist();
+        self.consumer_order_info_manager.persist();
+        self.consumer_offset_manager.persist();
+        self.schedule_message_service.persist();
+        info!("[Broker shutdown]consumer metadata persist success");
 
         if let Some(pull_request_hold_service) = self.pull_request_hold_service.as_mut() {
             pull_request_hold_service.shutdown();
         }
+        if let Some(cold_data_pull_request_hold_service) =
+            self.cold_data_pull_request_hold_service.as_mut()
+        {
+            cold_data_pull_request_hold_service.shutdown();
+        }
 
         if let Some(runtime) = self.broker_runtime.take() {
             runtime.shutdown();
@@ -299,13 +447,7 @@ impl BrokerRuntime {
 
 impl Drop for BrokerRuntime {
     fn drop(&mut self) {
-        let result =
-            self.drop
-                .clone()
-                .compare_exchange(false, true, Ordering::SeqCst, Ordering::Relaxed);
-        if result.is_ok() {
-            self.shutdown();
-        }
+        self.shutdown();
     }
 }
 
@@ -348,15 +490,41 @@ impl BrokerRuntime {
             ));
             let message_store_clone = message_store.clone();
             message_store.set_message_store_arc(Some(message_store_clone));
+            message_store.register_first_dispatcher(Box::new(CommitLogDispatcherCalcBitMap::new(
+                self.broker_config.clone(),
+                self.consumer_filter_manager.clone(),
+            )));
             if self.message_store_config.is_timer_wheel_enable() {
-                let time_message_store = TimerMessageStore::new(Some(message_store.clone()));
-                message_store.set_timer_message_store(Arc::new(time_message_store));
+                let timer_message_store =
+                    Arc::new(TimerMessageStore::new(Some(message_store.clone())));
+                message_store.set_timer_message_store(timer_message_store.clone());
+                self.timer_message_store = Some(timer_message_store);
             }
             self.consumer_offset_manager
                 .set_message_store(Some(message_store.clone()));
             self.topic_config_manager
                 .set_message_store(Some(message_store.clone()));
             self.broker_stats = Some(Arc::new(BrokerStats::new(message_store.clone())));
+            let context = MessageStorePluginContext::new(
+                self.message_store_config.clone(),
+                self.broker_config.clone(),
+                Some(self.broker_stats_manager.clone()),
+            );
+            match self
+                .message_store_factory
+                .build(&context, Box::new(message_store.clone()))
+            {
+                Ok(plugin_message_store) => {
+                    let plugin_message_store = ArcMut::new(plugin_message_store);
+                    self.schedule_message_service
+                        .set_message_store(plugin_message_store.clone());
+                    self.plugin_message_store = Some(plugin_message_store);
+                }
+                Err(e) => {
+                    error!("Build message store with plugins failed: {}", e);
+                    return false;
+                }
+            }
             self.message_store = Some(message_store);
         } else if self.message_store_config.store_type == StoreType::RocksDB {
             info!("Use RocksDB as message store");
@@ -376,11 +544,22 @@ impl BrokerRuntime {
         }
         if self.message_store.is_some() {
             self.register_message_store_hook();
-            self.message_store.as_mut().unwrap().load().await;
+            self.plugin_message_store.as_mut().unwrap().load().await;
+            let broker_metrics_manager = BrokerMetricsManager::new(
+                self.broker_config.clone(),
+                BrokerMetricsSource {
+                    message_store: self.message_store.clone().unwrap(),
+                    consumer_offset_manager: self.consumer_offset_manager.clone(),
+                    producer_manager: self.producer_manager.clone(),
+                    consumer_manager: self.consumer_manager.clone(),
+                },
+            )
+            .await;
+            self.broker_metrics_manager = Some(Arc::new(broker_metrics_manager));
         }
 
-        if self.broker_config.timer_wheel_config.timer_wheel_enable {
-            result &= self.timer_message_store.as_mut().unwrap().load();
+        if let Some(timer_message_store) = self.timer_message_store.as_ref() {
+            result &= timer_message_store.load();
         }
         result &= self.schedule_message_service.load();
 
@@ -405,6 +584,11 @@ impl BrokerRuntime {
             message_store.set_put_message_hook(Box::new(BatchCheckBeforePutMessageHook::new(
                 self.topic_config_manager.topic_config_table(),
             )));
+            message_store.set_put_message_hook(Box::new(ScheduleMessageHook::new(
+                message_store.clone(),
+                self.message_store_config.clone(),
+                self.schedule_message_service.clone(),
+            )));
         }
     }
 
@@ -417,28 +601,25 @@ impl BrokerRuntime {
         self.topic_queue_mapping_clean_service = Some(Arc::new(TopicQueueMappingCleanService));
     }
 
-    fn init_processor(
-        &mut self,
-    ) -> BrokerRequestProcessor<
-        DefaultMessageStore,
-        DefaultTransactionalMessageService<DefaultMessageStore>,
-    > {
+    fn init_processor(&mut self) -> DefaultBrokerRequestProcessor {
+        let message_store = self.plugin_message_store.clone().unwrap();
         let send_message_processor = SendMessageProcessor::new(
             self.topic_queue_mapping_manager.clone(),
             self.subscription_group_manager.clone(),
             self.topic_config_manager.clone(),
             self.broker_config.clone(),
-            self.message_store.clone().unwrap(),
+            message_store.clone(),
             self.transactional_message_service.as_ref().unwrap().clone(),
             self.rebalance_lock_manager.clone(),
             self.broker_stats_manager.clone(),
+            self.broker_metrics_manager.clone().unwrap(),
         );
         let reply_message_processor = ReplyMessageProcessor::new(
             self.topic_queue_mapping_manager.clone(),
             self.subscription_group_manager.clone(),
             self.topic_config_manager.clone(),
             self.broker_config.clone(),
-            self.message_store.clone().unwrap(),
+            message_store.clone(),
             self.rebalance_lock_manager.clone(),
             self.broker_stats_manager.clone(),
             Some(self.producer_manager.clone()),
@@ -454,8 +635,9 @@ impl BrokerRuntime {
                 self.broker_stats_manager.clone(),
                 self.broker_config.clone(),
                 Arc::new(Default::default()),
+                self.broker_metrics_manager.clone().unwrap(),
+                self.broker_member_group.clone(),
             )) as Box<dyn PullMessageResultHandler>);
-        let message_store = self.message_store.clone().unwrap();
         let pull_message_processor = ArcMut::new(PullMessageProcessor::new(
             pull_message_result_handler.clone(),
             self.broker_config.clone(),
@@ -467,6 +649,7 @@ impl BrokerRuntime {
             Arc::new(self.consumer_offset_manager.clone()),
             Arc::new(BroadcastOffsetManager::default()),
             message_store.clone(),
+            self.cold_data_cg_ctr_service.clone(),
             self.broker_out_api.clone(),
         ));
 
@@ -477,7 +660,7 @@ impl BrokerRuntime {
             self.subscription_group_manager.clone(),
             Arc::new(self.consumer_offset_manager.clone()),
             Arc::new(self.topic_config_manager.clone()),
-            self.message_store.clone().unwrap(),
+            message_store.clone(),
         );
         self.pull_request_hold_service = Some(ArcMut::new(PullRequestHoldService::new(
             message_store.clone(),
@@ -485,18 +668,41 @@ impl BrokerRuntime {
             self.broker_config.clone(),
         )));
 
+        self.consumer_manager
+            .append_consumer_ids_change_listener(Box::new(
+                self.pull_request_hold_service.clone().unwrap(),
+            ));
+        if let Some(broker_metrics_manager) = self.broker_metrics_manager.as_ref() {
+            let pull_request_hold_service = self.pull_request_hold_service.clone().unwrap();
+            broker_metrics_manager
+                .set_pull_hold_requests(move || pull_request_hold_service.held_request_count());
+        }
+
         let pull_message_result_handler = pull_message_result_handler.as_mut().as_mut();
         pull_message_result_handler
             .as_any_mut()
             .downcast_mut::<DefaultPullMessageResultHandler>()
             .expect("downcast DefaultPullMessageResultHandler failed")
             .set_pull_request_hold_service(self.pull_request_hold_service.clone());
+        self.cold_data_pull_request_hold_service =
+            Some(ArcMut::new(ColdDataPullRequestHoldService::new(
+                pull_message_processor.clone(),
+                self.broker_config.clone(),
+            )));
+        pull_message_processor
+            .mut_from_ref()
+            .set_cold_data_pull_request_hold_service(
+                self.cold_data_pull_request_hold_service.clone(),
+            );
 
         self.message_store
             .as_mut()
             .unwrap()
             .set_message_arriving_listener(Some(Arc::new(Box::new(
-                NotifyMessageArrivingListener::new(self.pull_request_hold_service.clone().unwrap()),
+                NotifyMessageArrivingListener::new(
+                    self.pull_request_hold_service.clone().unwrap(),
+                    self.pop_long_polling_service.clone(),
+                ),
             ))));
         let query_message_processor =
             QueryMessageProcessor::new(self.message_store_config.clone(), message_store.clone());
@@ -507,15 +713,22 @@ impl BrokerRuntime {
             self.message_store_config.clone(),
             self.topic_config_manager.clone(),
             self.consumer_offset_manager.clone(),
+            self.subscription_group_manager.clone(),
             self.topic_queue_mapping_manager.clone(),
             self.message_store.as_ref().unwrap().clone(),
             self.schedule_message_service.clone(),
             self.broker_stats.clone(),
             self.consumer_manager.clone(),
+            self.producer_manager.clone(),
+            self.consumer_filter_manager.clone(),
             self.broker_out_api.clone(),
             self.broker_stats_manager.clone(),
             self.rebalance_lock_manager.clone(),
             self.broker_member_group.clone(),
+            self.is_isolated.clone(),
+            self.task_manager.last_runs(),
+            self.cold_data_cg_ctr_service.clone(),
+            self.plain_access_validator.clone(),
         );
 
         BrokerRequestProcessor {
@@ -537,143 +750,251 @@ impl BrokerRuntime {
                 self.subscription_group_manager.clone(),
             )),
             consumer_manage_processor: ArcMut::new(consumer_manage_processor),
-            query_assignment_processor: Default::default(),
+            query_assignment_processor: ArcMut::new(QueryAssignmentProcessor::new(
+                self.broker_config.clone(),
+                self.message_store_config.clone(),
+                self.consumer_manager.clone(),
+                self.topic_route_info_manager.clone(),
+            )),
             query_message_processor: ArcMut::new(query_message_processor),
             end_transaction_processor: ArcMut::new(EndTransactionProcessor::new(
                 self.message_store_config.clone(),
                 self.broker_config.clone(),
                 self.transactional_message_service.as_ref().unwrap().clone(),
-                self.message_store.as_ref().unwrap().clone(),
+                message_store.clone(),
             )),
+            broker_metrics_manager: self.broker_metrics_manager.clone().unwrap(),
+            is_isolated: self.is_isolated.clone(),
         }
     }
 
     async fn initialize_scheduled_tasks(&mut self) {
+        let task_manager = self.task_manager.clone();
+
         let initial_delay = compute_next_morning_time_millis() - get_current_millis();
-        let period = Duration::from_days(1).as_millis() as u64;
         let broker_stats = self.broker_stats.clone();
-        self.broker_runtime
-            .as_ref()
-            .unwrap()
-            .get_handle()
-            .spawn(async move {
-                info!("BrokerStats Start scheduled task");
-                tokio::time::sleep(Duration::from_millis(initial_delay)).await;
-                loop {
-                    let current_execution_time = tokio::time::Instant::now();
-                    broker_stats.as_ref().unwrap().record();
-                    let next_execution_time =
-                        current_execution_time + Duration::from_millis(period);
-                    let delay =
-                        next_execution_time.saturating_duration_since(tokio::time::Instant::now());
-                    tokio::time::sleep(delay).await;
+        task_manager.schedule_at_fixed_rate(
+            "BrokerStats",
+            Duration::from_millis(initial_delay),
+            Duration::from_days(1),
+            move || {
+                if let Some(broker_stats) = broker_stats.as_ref() {
+                    broker_stats.record();
                 }
-            });
+                async {}
+            },
+        );
 
         let consumer_offset_manager = self.consumer_offset_manager.clone();
-        let flush_consumer_offset_interval = self.broker_config.flush_consumer_offset_interval;
-        self.broker_runtime
-            .as_ref()
-            .unwrap()
-            .get_handle()
-            .spawn(async move {
-                info!("Consumer offset manager Start scheduled task");
-                tokio::time::sleep(Duration::from_millis(1000 * 10)).await;
-                loop {
-                    let current_execution_time = tokio::time::Instant::now();
-                    consumer_offset_manager.persist();
-                    let next_execution_time = current_execution_time
-                        + Duration::from_millis(flush_consumer_offset_interval);
-                    let delay =
-                        next_execution_time.saturating_duration_since(tokio::time::Instant::now());
-                    tokio::time::sleep(delay).await;
+        task_manager.schedule_at_fixed_rate(
+            "ConsumerOffsetManager",
+            Duration::from_secs(10),
+            Duration::from_millis(self.broker_config.flush_consumer_offset_interval),
+            move || {
+                consumer_offset_manager.persist();
+                async {}
+            },
+        );
+
+        let topic_route_info_manager = self.topic_route_info_manager.clone();
+        task_manager.schedule_at_fixed_rate(
+            "TopicRouteInfoManager",
+            Duration::from_millis(1000),
+            Duration::from_millis(self.broker_config.load_balance_poll_name_server_interval),
+            move || {
+                let topic_route_info_manager = topic_route_info_manager.clone();
+                async move {
+                    topic_route_info_manager
+                        .update_topic_route_info_from_name_server()
+                        .await;
                 }
-            });
+            },
+        );
 
         let consumer_filter_manager = self.consumer_filter_manager.clone();
         let consumer_order_info_manager = self.consumer_order_info_manager.clone();
-        self.broker_runtime
-            .as_ref()
-            .unwrap()
-            .get_handle()
-            .spawn(async move {
-                info!("consumer filter manager Start scheduled task");
-                info!("consumer order info manager Start scheduled task");
-                tokio::time::sleep(Duration::from_millis(1000 * 10)).await;
-                loop {
-                    let current_execution_time = tokio::time::Instant::now();
-                    consumer_filter_manager.persist();
-                    consumer_order_info_manager.persist();
-                    let next_execution_time =
-                        current_execution_time + Duration::from_millis(1000 * 10);
-                    let delay =
-                        next_execution_time.saturating_duration_since(tokio::time::Instant::now());
-                    tokio::time::sleep(delay).await;
+        task_manager.schedule_at_fixed_rate(
+            "ConsumerFilterManager",
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            move || {
+                consumer_filter_manager.persist();
+                consumer_order_info_manager.persist();
+                async {}
+            },
+        );
+
+        if self.schedule_message_service.get_max_delay_level() > 0
+            && self.message_store_config.broker_role != BrokerRole::Slave
+        {
+            let schedule_message_service = self.schedule_message_service.clone();
+            task_manager.schedule_at_fixed_rate(
+                "ScheduleMessageService",
+                Duration::from_secs(1),
+                Duration::from_millis(100),
+                move || {
+                    let schedule_message_service = schedule_message_service.clone();
+                    async move {
+                        schedule_message_service
+                            .deliver(get_current_millis() as i64)
+                            .await;
+                    }
+                },
+            );
+
+            let schedule_message_service = self.schedule_message_service.clone();
+            task_manager.schedule_at_fixed_rate(
+                "ScheduleMessageServicePersist",
+                Duration::from_secs(10),
+                Duration::from_millis(self.message_store_config.flush_delay_offset_interval as u64),
+                move || {
+                    schedule_message_service.persist();
+                    schedule_message_service.sample_deliver_tps(get_current_millis());
+                    schedule_message_service.check_backlog();
+                    async {}
+                },
+            );
+        }
+
+        let broker_config = self.broker_config.clone();
+        let consumer_offset_manager = self.consumer_offset_manager.clone();
+        let subscription_group_manager = self.subscription_group_manager.clone();
+        let message_store = self.message_store.clone();
+        task_manager.schedule_at_fixed_rate(
+            "ProtectBroker",
+            Duration::from_secs(3 * 60),
+            Duration::from_secs(3 * 60),
+            move || {
+                if let Some(message_store) = message_store.as_ref() {
+                    Self::protect_broker(
+                        &broker_config,
+                        &consumer_offset_manager,
+                        &subscription_group_manager,
+                        message_store.as_ref(),
+                    );
                 }
-            });
+                async {}
+            },
+        );
 
-        let mut runtime = self.clone();
-        self.broker_runtime
-            .as_ref()
-            .unwrap()
-            .get_handle()
-            .spawn(async move {
-                info!("Protect broker Start scheduled task");
-                tokio::time::sleep(Duration::from_mins(3)).await;
-                loop {
-                    let current_execution_time = tokio::time::Instant::now();
-                    runtime.protect_broker();
-                    let next_execution_time = current_execution_time + Duration::from_mins(3);
-                    let delay =
-                        next_execution_time.saturating_duration_since(tokio::time::Instant::now());
-                    tokio::time::sleep(delay).await;
+        let broker_metrics_manager = self.broker_metrics_manager.clone();
+        task_manager.schedule_at_fixed_rate(
+            "PrintWaterMark",
+            Duration::from_secs(10),
+            Duration::from_secs(1),
+            move || {
+                if let Some(broker_metrics_manager) = broker_metrics_manager.as_ref() {
+                    Self::print_water_mark(broker_metrics_manager);
                 }
-            });
+                async {}
+            },
+        );
+
+        let broker_stats_manager = self.broker_stats_manager.clone();
+        task_manager.schedule_at_fixed_rate(
+            "PrintPutMessageDistribution",
+            Duration::from_secs(60),
+            Duration::from_secs(60),
+            move || {
+                broker_stats_manager.print_put_message_distribution();
+                async {}
+            },
+        );
 
         let message_store = self.message_store.clone();
-        self.broker_runtime
-            .as_ref()
-            .unwrap()
-            .get_handle()
-            .spawn(async move {
-                info!("Message store dispatch_behind_bytes Start scheduled task");
-                tokio::time::sleep(Duration::from_secs(10)).await;
-                loop {
-                    let current_execution_time = tokio::time::Instant::now();
-                    message_store.as_ref().unwrap().dispatch_behind_bytes();
-                    let next_execution_time = current_execution_time + Duration::from_secs(60);
-                    let delay =
-                        next_execution_time.saturating_duration_since(tokio::time::Instant::now());
-                    tokio::time::sleep(delay).await;
+        task_manager.schedule_at_fixed_rate(
+            "DispatchBehindBytes",
+            Duration::from_secs(10),
+            Duration::from_secs(60),
+            move || {
+                if let Some(message_store) = message_store.as_ref() {
+                    info!(
+                        "Dispatch task fall behind commit log {}bytes",
+                        message_store.dispatch_behind_bytes()
+                    );
+                    info!(
+                        "Flush task fall behind commit log {}bytes",
+                        message_store.remain_how_many_data_to_flush()
+                    );
                 }
-            });
+                async {}
+            },
+        );
 
         if self.broker_config.enable_controller_mode {
             self.update_master_haserver_addr_periodically = true;
         }
 
-        if let Some(ref namesrv_address) = self.broker_config.namesrv_addr.clone() {
-            self.update_namesrv_addr().await;
+        if let Some(namesrv_addr_file) = self.broker_config.namesrv_addr_file.clone() {
+            if let Some(namesrv_addr) = read_namesrv_addr_file(namesrv_addr_file.as_str()) {
+                self.broker_out_api
+                    .switch_name_server_address_list(namesrv_addr)
+                    .await;
+            }
+            info!(
+                "Watch name remoting_server address file: {}",
+                namesrv_addr_file
+            );
+            let topic_config_manager = self.topic_config_manager.clone();
+            task_manager.schedule_at_fixed_rate(
+                "NamesrvAddrFileWatch",
+                Duration::from_secs(1),
+                Duration::from_secs(1),
+                move || {
+                    let namesrv_addr_file = namesrv_addr_file.clone();
+                    let topic_config_manager = topic_config_manager.clone();
+                    async move {
+                        Self::reload_namesrv_addr_file(&namesrv_addr_file, &topic_config_manager)
+                            .await;
+                    }
+                },
+            );
+        } else if let Some(ref namesrv_address) = self.broker_config.namesrv_addr.clone() {
+            Self::update_namesrv_addr(&self.broker_config, &self.broker_out_api).await;
             info!(
                 "Set user specified name remoting_server address: {}",
                 namesrv_address
             );
-            let mut broker_runtime = self.clone();
-            self.broker_runtime
-                .as_ref()
-                .unwrap()
-                .get_handle()
-                .spawn(async move {
-                    tokio::time::sleep(Duration::from_secs(10)).await;
-                    loop {
-                        let current_execution_time = tokio::time::Instant::now();
-                        broker_runtime.update_namesrv_addr().await;
-                        let next_execution_time = current_execution_time + Duration::from_secs(60);
-                        let delay = next_execution_time
-                            .saturating_duration_since(tokio::time::Instant::now());
-                        tokio::time::sleep(delay).await;
+            let broker_config = self.broker_config.clone();
+            let broker_out_api = self.broker_out_api.clone();
+            task_manager.schedule_at_fixed_rate(
+                "UpdateNamesrvAddr",
+                Duration::from_secs(10),
+                Duration::from_secs(60),
+                move || {
+                    let broker_config = broker_config.clone();
+                    let broker_out_api = broker_out_api.clone();
+                    async move {
+                        Self::update_namesrv_addr(&broker_config, &broker_out_api).await;
                     }
-                });
+                },
+            );
+        } else if self.broker_config.fetch_namesrv_addr_by_address_server {
+            let broker_out_api = self.broker_out_api.clone();
+            task_manager.schedule_at_fixed_rate(
+                "FetchNamesrvAddr",
+                Duration::from_secs(10),
+                Duration::from_secs(120),
+                move || {
+                    let broker_out_api = broker_out_api.clone();
+                    async move {
+                        broker_out_api.fetch_name_server_addr().await;
+                    }
+                },
+            );
+        }
+
+        if !self.broker_config.enable_controller_mode
+            && self.message_store_config.broker_role == BrokerRole::Slave
+        {
+            task_manager.schedule_at_fixed_rate(
+                "SlaveSynchronize",
+                Duration::from_secs(3),
+                Duration::from_secs(10),
+                || async {
+                    Self::slave_synchronize().await;
+                },
+            );
         }
     }
 
@@ -703,52 +1024,217 @@ impl BrokerRuntime {
         self.transaction_metrics_flush_service = Some(Arc::new(TransactionMetricsFlushService));
     }
 
-    fn initial_acl(&mut self) {}
+    fn initial_acl(&mut self) {
+        if !self.broker_config.acl_enable {
+            info!("The broker does not enable acl");
+            return;
+        }
+        self.plain_access_validator.load();
+
+        // Hand edits of the ACL file apply without a restart.
+        let plain_access_validator = self.plain_access_validator.clone();
+        self.task_manager.schedule_at_fixed_rate(
+            "AclFileWatch",
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+            move || {
+                plain_access_validator.reload_if_changed();
+                async {}
+            },
+        );
+    }
 
     fn initial_rpc_hooks(&mut self) {}
 
+    /// Hooks every request received by the remoting servers goes through.
+    fn rpc_hooks(&self) -> Vec<Box<dyn RPCHook>> {
+        let mut rpc_hooks: Vec<Box<dyn RPCHook>> = Vec::new();
+        if self.broker_config.acl_enable {
+            rpc_hooks.push(Box::new(self.plain_access_validator.clone()));
+        }
+        rpc_hooks
+    }
+
     fn initial_request_pipeline(&mut self) {}
 
-    fn protect_broker(&mut self) {}
+    fn start_remoting_servers(
+        &self,
+        request_processor: DefaultBrokerRequestProcessor,
+        fast_request_processor: DefaultBrokerRequestProcessor,
+    ) {
+        let (conn_disconnect_notify, _) = broadcast::channel::<SocketAddr>(100);
+        self.start_channel_close_listener(conn_disconnect_notify.subscribe());
+        let server = RocketMQServer::new(self.server_config.clone())
+            .with_conn_disconnect_notify(conn_disconnect_notify.clone());
+        //start nomarl broker remoting_server
+        let rpc_hooks = self.rpc_hooks();
+        tokio::spawn(async move {
+            server
+                .run_with_rpc_hooks(request_processor, rpc_hooks)
+                .await
+        });
+        //start fast broker remoting_server
+        let mut fast_server_config = (*self.server_config).clone();
+        fast_server_config.listen_port = self.server_config.listen_port - 2;
+        let fast_server = RocketMQServer::new(Arc::new(fast_server_config))
+            .with_conn_disconnect_notify(conn_disconnect_notify);
+        let rpc_hooks = self.rpc_hooks();
+        tokio::spawn(async move {
+            fast_server
+                .run_with_rpc_hooks(fast_request_processor, rpc_hooks)
+                .await
+        });
+    }
+
+    /// Cleans up after the consumers whose connection closed, the same way as when they
+    /// unregister explicitly.
+    fn start_channel_close_listener(&self, mut receiver: broadcast::Receiver<SocketAddr>) {
+        let consumer_manager = self.consumer_manager.clone();
+        let subscription_group_manager = self.subscription_group_manager.clone();
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(remote_addr) => {
+                        consumer_manager.do_channel_close_event(remote_addr, |group| {
+                            subscription_group_manager
+                                .find_subscription_group_config(group)
+                                .map_or(true, |config| config.notify_consumer_ids_changed_enable())
+                        });
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("missed {} channel close events", skipped);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    /// The processor of the requests addressed to this broker, once a broker hosted in a
+    /// container started.
+    pub(crate) fn container_request_processor(&self) -> Option<DefaultBrokerRequestProcessor> {
+        self.container_request_processor.clone()
+    }
+
+    /// Disables consumption of the groups falling further behind than
+    /// `consumer_fallbehind_threshold`, reading that much cold data would hurt every client.
+    fn protect_broker<MS: MessageStore, SMS: MessageStore>(
+        broker_config: &BrokerConfig,
+        consumer_offset_manager: &ConsumerOffsetManager,
+        subscription_group_manager: &SubscriptionGroupManager<SMS>,
+        message_store: &MS,
+    ) {
+        if !broker_config.disable_consume_if_consumer_read_slowly {
+            return;
+        }
+        for (group, fall_behind_bytes) in
+            consumer_offset_manager.fall_behind_bytes_by_group(message_store)
+        {
+            if fall_behind_bytes > broker_config.consumer_fallbehind_threshold as i64
+                && subscription_group_manager.disable_consume(&group)
+            {
+                warn!(
+                    "[PROTECT_BROKER] the consumer[{}] consume slowly, {} bytes, disable it",
+                    group, fall_behind_bytes
+                );
+            }
+        }
+    }
+
+    fn print_water_mark(broker_metrics_manager: &BrokerMetricsManager) {
+        for sample in broker_metrics_manager.processor_watermarks() {
+            info!(
+                target: WATER_MARK_LOGGER_NAME,
+                "[WATERMARK] {} in flight: {}, head wait: {}ms",
+                sample.processor,
+                sample.in_flight,
+                sample.head_wait_millis
+            );
+        }
+        info!(
+            target: WATER_MARK_LOGGER_NAME,
+            "[WATERMARK] pull hold requests: {}",
+            broker_metrics_manager.pull_hold_requests()
+        );
+    }
+
+    /// Pulls topic configs, consumer offsets and subscription groups from the master.
+    async fn slave_synchronize() {
+        // Metadata replication from the master is not supported yet, slaves rely on the
+        // metadata they were started with.
+    }
 
     fn start_basic_service(&mut self) {
         let request_processor = self.init_processor();
         let fast_request_processor = request_processor.clone();
-        self.message_store
+        self.plugin_message_store
             .as_mut()
             .unwrap()
             .start()
             .expect("Message store start error");
+        if let Some(timer_message_store) = self.timer_message_store.as_ref() {
+            timer_message_store.start();
+        }
 
-        let server = RocketMQServer::new(self.server_config.clone());
-        //start nomarl broker remoting_server
-        tokio::spawn(async move { server.run(request_processor).await });
-        //start fast broker remoting_server
-        let mut fast_server_config = (*self.server_config).clone();
-        fast_server_config.listen_port = self.server_config.listen_port - 2;
-        let fast_server = RocketMQServer::new(Arc::new(fast_server_config));
-        tokio::spawn(async move { fast_server.run(fast_request_processor).await });
+        if self.broker_config.is_in_broker_container {
+            // the container serves the requests of all its brokers
+            self.container_request_processor = Some(request_processor);
+        } else {
+            self.start_remoting_servers(request_processor, fast_request_processor);
+        }
 
         if let Some(pull_request_hold_service) = self.pull_request_hold_service.as_mut() {
             let this = pull_request_hold_service.clone();
             pull_request_hold_service.start(this);
         }
+        if let Some(cold_data_pull_request_hold_service) =
+            self.cold_data_pull_request_hold_service.as_mut()
+        {
+            let this = cold_data_pull_request_hold_service.clone();
+            cold_data_pull_request_hold_service.start(this);
+        }
     }
 
-    async fn update_namesrv_addr(&mut self) {
-        if self.broker_config.fetch_name_srv_addr_by_dns_lookup {
-            if let Some(namesrv_addr) = &self.broker_config.namesrv_addr {
-                self.broker_out_api
-                    .update_name_server_address_list_by_dns_lookup(namesrv_addr.clone())
-                    .await;
-            }
-        } else if let Some(namesrv_addr) = &self.broker_config.namesrv_addr {
-            self.broker_out_api
-                .update_name_server_address_list(namesrv_addr.clone())
+    async fn update_namesrv_addr(broker_config: &BrokerConfig, broker_out_api: &BrokerOuterAPI) {
+        // an address switched to at runtime wins over the configured one
+        let Some(namesrv_addr) = broker_out_api
+            .name_server_address()
+            .or_else(|| broker_config.namesrv_addr.clone())
+        else {
+            return;
+        };
+        if broker_config.fetch_name_srv_addr_by_dns_lookup {
+            broker_out_api
+                .update_name_server_address_list_by_dns_lookup(namesrv_addr)
+                .await;
+        } else {
+            broker_out_api
+                .update_name_server_address_list(namesrv_addr)
                 .await;
         }
     }
 
+    /// Switches to the name servers listed in `namesrv_addr_file` when they changed.
+    async fn reload_namesrv_addr_file(
+        namesrv_addr_file: &str,
+        topic_config_manager: &TopicConfigManager,
+    ) {
+        let Some(namesrv_addr) = read_namesrv_addr_file(namesrv_addr_file) else {
+            return;
+        };
+        let broker_runtime_inner = topic_config_manager.broker_runtime_inner();
+        if broker_runtime_inner.broker_out_api.name_server_address() == Some(namesrv_addr.clone()) {
+            return;
+        }
+        info!(
+            "name remoting_server address file {} changed",
+            namesrv_addr_file
+        );
+        broker_runtime_inner
+            .switch_name_server_address(namesrv_addr, topic_config_manager)
+            .await;
+    }
+
     pub async fn start(&mut self) {
         self.should_start_time.store(
             (get_current_millis() as i64 + self.message_store_config.disappear_time_after_start)
@@ -760,6 +1246,9 @@ impl BrokerRuntime {
         {
             self.is_isolated.store(true, Ordering::Release);
         }
+        if self.broker_config.enable_broker_pre_online && !self.broker_config.skip_pre_online {
+            self.is_isolated.store(true, Ordering::Release);
+        }
 
         self.broker_out_api.start().await;
         self.start_basic_service();
@@ -775,44 +1264,50 @@ impl BrokerRuntime {
         let should_start_time = self.should_start_time.clone();
         let is_isolated = self.is_isolated.clone();
         let broker_config = self.broker_config.clone();
-        self.broker_runtime
-            .as_ref()
-            .unwrap()
-            .get_handle()
-            .spawn(async move {
-                let period = Duration::from_millis(
-                    10000.max(60000.min(broker_config.register_name_server_period)),
-                );
-                let initial_delay = Duration::from_secs(10);
-                tokio::time::sleep(initial_delay).await;
-                loop {
-                    let start_time = should_start_time.load(Ordering::Relaxed);
-                    if get_current_millis() < start_time {
-                        info!("Register to namesrv after {}", start_time);
-                        continue;
-                    }
-                    if is_isolated.load(Ordering::Relaxed) {
-                        info!("Skip register for broker is isolated");
-                        continue;
-                    }
-                    // record current execution time
-                    let current_execution_time = tokio::time::Instant::now();
-                    // execute task
-                    cloned_broker_runtime
-                        .register_broker_all(true, false, broker_config.force_register)
-                        .await;
-                    // Calculate the time of the next execution
-                    let next_execution_time = current_execution_time + period;
-
-                    // Wait until the next execution
-                    let delay =
-                        next_execution_time.saturating_duration_since(tokio::time::Instant::now());
-                    tokio::time::sleep(delay).await;
+        let shutdown = self.shutdown.clone();
+        self.runtime_handle.spawn(async move {
+            let period = Duration::from_millis(
+                10000.max(60000.min(broker_config.register_name_server_period)),
+            );
+            let initial_delay = Duration::from_secs(10);
+            tokio::time::sleep(initial_delay).await;
+            loop {
+                if shutdown.load(Ordering::Acquire) {
+                    break;
                 }
-            });
+                let start_time = should_start_time.load(Ordering::Relaxed);
+                if get_current_millis() < start_time {
+                    info!("Register to namesrv after {}", start_time);
+                    tokio::time::sleep(period).await;
+                    continue;
+                }
+                if is_isolated.load(Ordering::Relaxed) {
+                    info!("Skip register for broker is isolated");
+                    tokio::time::sleep(period).await;
+                    continue;
+                }
+                // record current execution time
+                let current_execution_time = tokio::time::Instant::now();
+                // execute task
+                cloned_broker_runtime
+                    .register_broker_all(true, false, broker_config.force_register)
+                    .await;
+                // Calculate the time of the next execution
+                let next_execution_time = current_execution_time + period;
+
+                // Wait until the next execution
+                let delay =
+                    next_execution_time.saturating_duration_since(tokio::time::Instant::now());
+                tokio::time::sleep(delay).await;
+            }
+        });
 
         if self.broker_config.enable_slave_acting_master {
             self.schedule_send_heartbeat();
+            self.schedule_sync_broker_member_group();
+        } else if self.broker_config.slave_read_enable {
+            // lagging consumers are only sent to slaves known to be online
+            self.schedule_sync_broker_member_group();
         }
 
         if self.broker_config.enable_controller_mode {
@@ -821,31 +1316,33 @@ impl BrokerRuntime {
 
         if self.broker_config.skip_pre_online {
             self.start_service_without_condition();
+        } else if self.is_isolated.load(Ordering::Acquire) {
+            self.start_pre_online_service();
         }
 
         let broker_out_api = self.broker_out_api.clone();
-        self.broker_runtime
-            .as_ref()
-            .unwrap()
-            .get_handle()
-            .spawn(async move {
-                let period = Duration::from_secs(5);
-                let initial_delay = Duration::from_secs(10);
-                tokio::time::sleep(initial_delay).await;
-                loop {
-                    // record current execution time
-                    let current_execution_time = tokio::time::Instant::now();
-                    // execute task
-                    broker_out_api.refresh_metadata();
-                    // Calculate the time of the next execution
-                    let next_execution_time = current_execution_time + period;
-
-                    // Wait until the next execution
-                    let delay =
-                        next_execution_time.saturating_duration_since(tokio::time::Instant::now());
-                    tokio::time::sleep(delay).await;
+        let shutdown = self.shutdown.clone();
+        self.runtime_handle.spawn(async move {
+            let period = Duration::from_secs(5);
+            let initial_delay = Duration::from_secs(10);
+            tokio::time::sleep(initial_delay).await;
+            loop {
+                if shutdown.load(Ordering::Acquire) {
+                    break;
                 }
-            });
+                // record current execution time
+                let current_execution_time = tokio::time::Instant::now();
+                // execute task
+                broker_out_api.refresh_metadata();
+                // Calculate the time of the next execution
+                let next_execution_time = current_execution_time + period;
+
+                // Wait until the next execution
+                let delay =
+                    next_execution_time.saturating_duration_since(tokio::time::Instant::now());
+                tokio::time::sleep(delay).await;
+            }
+        });
         info!(
             "Rocketmq Broker({} ----Rust) start success",
             self.broker_config.broker_identity.broker_name
@@ -854,7 +1351,81 @@ impl BrokerRuntime {
 
     pub(crate) fn schedule_send_heartbeat(&mut self) {}
 
-    pub(crate) fn start_service_without_condition(&mut self) {}
+    /// Periodically refreshes the replica peers of this broker from the name server. The store
+    /// only follows role changes when slaves may act as master.
+    fn schedule_sync_broker_member_group(&mut self) {
+        let broker_out_api = self.broker_out_api.clone();
+        let broker_member_group = self.broker_member_group.clone();
+        let broker_config = self.broker_config.clone();
+        let message_store = self.message_store.clone();
+        let shutdown = self.shutdown.clone();
+        self.runtime_handle.spawn(async move {
+            let period = Duration::from_millis(broker_config.sync_broker_member_group_period);
+            let initial_delay = Duration::from_secs(1);
+            tokio::time::sleep(initial_delay).await;
+            loop {
+                if shutdown.load(Ordering::Acquire) {
+                    break;
+                }
+                let current_execution_time = tokio::time::Instant::now();
+                match broker_out_api
+                    .sync_broker_member_group(
+                        &broker_config.broker_identity.broker_cluster_name,
+                        &broker_config.broker_identity.broker_name,
+                    )
+                    .await
+                {
+                    Ok(Some(group)) => {
+                        let change = broker_member_group
+                            .update_group(group)
+                            .filter(|_| broker_config.enable_slave_acting_master);
+                        if let Some(change) = change {
+                            if let Some(message_store) = message_store.as_ref() {
+                                change.apply(message_store.as_ref());
+                            }
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        warn!("syncBrokerMemberGroup from namesrv failed, {}", e);
+                    }
+                }
+                let next_execution_time = current_execution_time + period;
+                let delay =
+                    next_execution_time.saturating_duration_since(tokio::time::Instant::now());
+                tokio::time::sleep(delay).await;
+            }
+        });
+    }
+
+    pub(crate) fn start_service_without_condition(&mut self) {
+        self.is_isolated.store(false, Ordering::Release);
+    }
+
+    /// Keeps the broker isolated until its store caught up, then registers it to the name server.
+    fn start_pre_online_service(&mut self) {
+        let pre_online_service = BrokerPreOnlineService::new(
+            self.is_isolated.clone(),
+            &self.broker_config,
+            &self.message_store_config,
+        );
+        let probe = StorePreOnlineProbe {
+            message_store: self.message_store.as_ref().unwrap().clone(),
+            broker_out_api: self.broker_out_api.clone(),
+            broker_config: self.broker_config.clone(),
+            is_slave: self.message_store_config.broker_role == BrokerRole::Slave,
+        };
+        let mut cloned_broker_runtime = self.clone();
+        self.runtime_handle.spawn(async move {
+            pre_online_service
+                .wait_online(&probe, Duration::from_secs(1))
+                .await;
+            let force_register = cloned_broker_runtime.broker_config.force_register;
+            cloned_broker_runtime
+                .register_broker_all(true, false, force_register)
+                .await;
+        });
+    }
 
     /// Register broker to name remoting_server
     pub(crate) async fn register_broker_all(
@@ -992,6 +1563,19 @@ impl BrokerRuntime {
     }
 }
 
+/// Reads the name server addresses from `path`, one per line or separated by `;`.
+fn read_namesrv_addr_file(path: &str) -> Option<CheetahString> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let addrs = content
+        .split(|c: char| c == ';' || c.is_whitespace())
+        .filter(|addr| !addr.is_empty())
+        .collect::<Vec<_>>();
+    if addrs.is_empty() {
+        return None;
+    }
+    Some(addrs.join(";").into())
+}
+
 #[derive(Clone)]
 pub(crate) struct BrokerRuntimeInner {
     pub(crate) broker_out_api: Arc<BrokerOuterAPI>,
@@ -1002,6 +1586,56 @@ pub(crate) struct BrokerRuntimeInner {
 }
 
 impl BrokerRuntimeInner {
+    /// Points the broker at the name servers in `namesrv_addr`: the ones dropped from the list
+    /// forget the broker and every server still listed gets all the topics registered.
+    pub async fn switch_name_server_address(
+        &self,
+        namesrv_addr: CheetahString,
+        topic_config_manager: &TopicConfigManager,
+    ) {
+        info!("switch name remoting_server address to {}", namesrv_addr);
+        let removed = self
+            .broker_out_api
+            .switch_name_server_address_list(namesrv_addr)
+            .await;
+        let broker_addr = CheetahString::from_string(format!(
+            "{}:{}",
+            self.broker_config.broker_ip1, self.server_config.listen_port
+        ));
+        for namesrv_addr in removed.iter() {
+            if let Err(e) = self
+                .broker_out_api
+                .unregister_broker(
+                    namesrv_addr,
+                    self.broker_config
+                        .broker_identity
+                        .broker_cluster_name
+                        .clone(),
+                    broker_addr.clone(),
+                    self.broker_config.broker_identity.broker_name.clone(),
+                    self.broker_config.broker_identity.broker_id,
+                )
+                .await
+            {
+                warn!(
+                    "Unregister broker from removed name remoting_server {} failed: {}",
+                    namesrv_addr, e
+                );
+            }
+        }
+        let topic_config_list = topic_config_manager
+            .topic_config_table()
+            .lock()
+            .values()
+            .cloned()
+            .collect();
+        self.register_increment_broker_data(
+            topic_config_list,
+            topic_config_manager.data_version().as_ref().clone(),
+        )
+        .await;
+    }
+
     pub async fn register_single_topic_all(&self, topic_config: TopicConfig) {
         let mut topic_config = topic_config;
         if !PermName::is_writeable(self.broker_config.broker_permission)
@@ -1169,3 +1803,501 @@ impl StateGetter for ConsumerStateGetter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::path::Path;
+
+    use bytes::Bytes;
+    use futures::StreamExt;
+    use rocketmq_common::common::broker::broker_config::BrokerIdentity;
+    use rocketmq_common::common::consumer::consume_from_where::ConsumeFromWhere;
+    use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+    use rocketmq_common::common::message::MessageTrait;
+    use rocketmq_common::common::topic::TopicValidator;
+    use rocketmq_remoting::code::request_code::RequestCode;
+    use rocketmq_remoting::code::response_code::ResponseCode;
+    use rocketmq_remoting::codec::remoting_command_codec::RemotingCommandCodec;
+    use rocketmq_remoting::connection::Connection;
+    use rocketmq_remoting::net::channel::Channel;
+    use rocketmq_remoting::protocol::filter::filter_api::FilterAPI;
+    use rocketmq_remoting::protocol::header::message_operation_header::send_message_request_header::SendMessageRequestHeader;
+    use rocketmq_remoting::protocol::header::pull_message_response_header::PullMessageResponseHeader;
+    use rocketmq_remoting::protocol::heartbeat::consume_type::ConsumeType;
+    use rocketmq_remoting::protocol::heartbeat::message_model::MessageModel;
+    use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+    use rocketmq_remoting::protocol::subscription::subscription_group_config::SubscriptionGroupConfig;
+    use rocketmq_remoting::protocol::LanguageCode;
+    use rocketmq_remoting::remoting_server::server::run;
+    use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
+    use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContextWrapper;
+    use rocketmq_remoting::runtime::processor::RequestProcessor;
+    use rocketmq_store::base::message_status_enum::PutMessageStatus;
+    use rocketmq_store::config::flush_disk_type::FlushDiskType;
+    use rocketmq_store::test_util::start_store;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+    use tokio_util::codec::FramedRead;
+
+    use super::*;
+    use crate::client::client_channel_info::ClientChannelInfo;
+    use crate::filter::expression_message_filter::ExpressionMessageFilter;
+    use crate::long_polling::pull_request::PullRequest;
+    use crate::test_util;
+
+    fn broker(root: &Path, broker_name: &str, listen_port: u32) -> BrokerRuntime {
+        broker_with_config(root, broker_config(root, broker_name, listen_port))
+    }
+
+    fn broker_config(root: &Path, broker_name: &str, listen_port: u32) -> BrokerConfig {
+        let mut broker_config = BrokerConfig {
+            store_path_root_dir: root.to_string_lossy().into_owned().into(),
+            listen_port,
+            ..BrokerConfig::default()
+        };
+        broker_config.broker_identity = BrokerIdentity {
+            broker_name: broker_name.into(),
+            broker_cluster_name: "IsolationCluster".into(),
+            broker_id: 0,
+            is_broker_container: false,
+            is_in_broker_container: false,
+        };
+        broker_config
+    }
+
+    fn broker_with_config(root: &Path, broker_config: BrokerConfig) -> BrokerRuntime {
+        let message_store_config = MessageStoreConfig {
+            store_path_root_dir: root.to_string_lossy().into_owned().into(),
+            mapped_file_size_commit_log: 1024 * 1024,
+            flush_disk_type: FlushDiskType::AsyncFlush,
+            isolate_store_path_by_identity: true,
+            ..MessageStoreConfig::default()
+        };
+        BrokerRuntime::new(broker_config, message_store_config, ServerConfig::default())
+    }
+
+    #[test]
+    fn two_brokers_in_one_process_keep_separate_store_roots() {
+        let root_a = tempfile::tempdir().unwrap();
+        let root_b = tempfile::tempdir().unwrap();
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        // brokers own runtimes of their own, so they are built and dropped outside `block_on`
+        let guard = runtime.enter();
+        let mut broker_a = broker(root_a.path(), "broker-a", 30911);
+        let mut broker_b = broker(root_b.path(), "broker-b", 30921);
+
+        let store_root_a = root_a.path().join("IsolationCluster_broker-a_0");
+        let store_root_b = root_b.path().join("IsolationCluster_broker-b_0");
+        assert_eq!(
+            broker_a.message_store_config().store_path_root_dir.as_str(),
+            store_root_a.to_string_lossy()
+        );
+        assert_eq!(
+            broker_b.broker_config().store_path_root_dir.as_str(),
+            store_root_b.to_string_lossy()
+        );
+
+        assert!(runtime.block_on(broker_a.initialize()));
+        assert!(runtime.block_on(broker_b.initialize()));
+        drop(broker_a);
+        drop(broker_b);
+
+        for store_root in [&store_root_a, &store_root_b] {
+            assert!(store_root.join("commitlog").is_dir());
+            assert!(store_root.join("consumequeue").is_dir());
+            assert!(store_root.join("config").join("topics.json").is_file());
+        }
+        assert!(!root_a.path().join("IsolationCluster_broker-b_0").exists());
+        assert!(!root_b.path().join("IsolationCluster_broker-a_0").exists());
+        drop(guard);
+        drop(runtime);
+    }
+
+    /// Name server answering every request and reporting the request codes it got.
+    #[derive(Clone)]
+    struct RecordingNamesrv(mpsc::UnboundedSender<i32>);
+
+    impl RequestProcessor for RecordingNamesrv {
+        async fn process_request(
+            &mut self,
+            _channel: Channel,
+            _ctx: ConnectionHandlerContext,
+            request: RemotingCommand,
+        ) -> rocketmq_remoting::Result<Option<RemotingCommand>> {
+            let _ = self.0.send(request.code());
+            Ok(Some(RemotingCommand::create_response_command()))
+        }
+    }
+
+    async fn start_namesrv() -> (CheetahString, mpsc::UnboundedReceiver<i32>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string().into();
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(run(
+            listener,
+            std::future::pending::<()>(),
+            RecordingNamesrv(tx),
+            None,
+            vec![],
+        ));
+        (addr, rx)
+    }
+
+    #[test]
+    fn namesrv_addr_file_moves_the_registration_to_the_new_servers() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        // the outer api owns a runtime of its own, so it is built and dropped outside `block_on`
+        let guard = runtime.enter();
+        let broker_config = Arc::new(BrokerConfig {
+            store_path_root_dir: dir.path().to_string_lossy().into_owned().into(),
+            ..BrokerConfig::default()
+        });
+        let topic_config_manager = test_util::topic_config_manager(broker_config);
+        let namesrv_addr_file = dir.path().join("namesrv_addr");
+        let namesrv_addr_file = namesrv_addr_file.to_str().unwrap();
+
+        runtime.block_on(async {
+            let (old_addr, mut old_namesrv) = start_namesrv().await;
+            let (new_addr, mut new_namesrv) = start_namesrv().await;
+            std::fs::write(namesrv_addr_file, format!("{}\n", old_addr)).unwrap();
+            BrokerRuntime::reload_namesrv_addr_file(namesrv_addr_file, &topic_config_manager).await;
+            assert_eq!(
+                old_namesrv.recv().await,
+                Some(RequestCode::RegisterBroker.to_i32())
+            );
+
+            std::fs::write(namesrv_addr_file, format!("{}\n", new_addr)).unwrap();
+            BrokerRuntime::reload_namesrv_addr_file(namesrv_addr_file, &topic_config_manager).await;
+            assert_eq!(
+                old_namesrv.recv().await,
+                Some(RequestCode::UnregisterBroker.to_i32())
+            );
+            assert_eq!(
+                new_namesrv.recv().await,
+                Some(RequestCode::RegisterBroker.to_i32())
+            );
+
+            // an unchanged file registers nothing
+            BrokerRuntime::reload_namesrv_addr_file(namesrv_addr_file, &topic_config_manager).await;
+            assert!(new_namesrv.try_recv().is_err());
+            assert!(old_namesrv.try_recv().is_err());
+        });
+        drop(topic_config_manager);
+        drop(guard);
+        drop(runtime);
+    }
+
+    #[test]
+    fn namesrv_addr_file_lists_one_address_per_line_or_separated_by_semicolons() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("namesrv_addr");
+        let path_str = path.to_str().unwrap();
+        assert_eq!(read_namesrv_addr_file(path_str), None);
+
+        std::fs::write(&path, "a:9876\n b:9876;c:9876 \n\n").unwrap();
+        assert_eq!(
+            read_namesrv_addr_file(path_str).as_deref(),
+            Some("a:9876;b:9876;c:9876")
+        );
+
+        std::fs::write(&path, "\n").unwrap();
+        assert_eq!(read_namesrv_addr_file(path_str), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn protect_broker_disables_slow_groups_until_re_enabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let store_root: CheetahString = dir.path().to_string_lossy().into_owned().into();
+        let mut store = start_store(&dir, MessageStoreConfig::default()).await;
+        let topic = CheetahString::from_static_str("ProtectBrokerTopic");
+        for _ in 0..4 {
+            let mut msg = MessageExtBrokerInner::default();
+            msg.set_topic(topic.clone());
+            msg.set_body(Bytes::from_static(&[0; 256]));
+            let result = store.put_message(msg).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        }
+        for _ in 0..500 {
+            if store.get_max_offset_in_queue(&topic, 0) == 4 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(store.get_max_offset_in_queue(&topic, 0), 4);
+
+        let mut broker_config = BrokerConfig {
+            store_path_root_dir: store_root,
+            disable_consume_if_consumer_read_slowly: true,
+            // one message behind is fine, the whole queue behind is not
+            consumer_fallbehind_threshold: store.get_max_phy_offset() as u64 / 2,
+            ..BrokerConfig::default()
+        };
+        let consumer_offset_manager =
+            ConsumerOffsetManager::new(Arc::new(broker_config.clone()), None);
+        let subscription_group_manager = SubscriptionGroupManager::<DefaultMessageStore>::new(
+            Arc::new(broker_config.clone()),
+            None,
+        );
+        let client_host = "127.0.0.1:10911".parse().unwrap();
+        let slow_group = CheetahString::from_static_str("SlowGroup");
+        let fast_group = CheetahString::from_static_str("FastGroup");
+        for (group, offset) in [(&slow_group, 0), (&fast_group, 3)] {
+            let mut config = SubscriptionGroupConfig::default();
+            config.set_group_name(group.clone());
+            subscription_group_manager.update_subscription_group_config(config);
+            consumer_offset_manager.commit_offset(client_host, group, &topic, 0, offset);
+        }
+        let consume_enable = |group: &CheetahString| {
+            subscription_group_manager
+                .find_subscription_group_config_inner(group)
+                .unwrap()
+                .consume_enable()
+        };
+        let protect = |broker_config: &BrokerConfig| {
+            BrokerRuntime::protect_broker(
+                broker_config,
+                &consumer_offset_manager,
+                &subscription_group_manager,
+                store.as_ref(),
+            )
+        };
+
+        broker_config.disable_consume_if_consumer_read_slowly = false;
+        protect(&broker_config);
+        assert!(consume_enable(&slow_group));
+
+        broker_config.disable_consume_if_consumer_read_slowly = true;
+        protect(&broker_config);
+        assert!(!consume_enable(&slow_group));
+        assert!(consume_enable(&fast_group));
+
+        // an operator enables the group again once it caught up
+        let mut config = subscription_group_manager
+            .find_subscription_group_config_inner(&slow_group)
+            .unwrap();
+        config.set_consume_enable(true);
+        subscription_group_manager.update_subscription_group_config(config);
+        consumer_offset_manager.commit_offset(client_host, &slow_group, &topic, 0, 4);
+        protect(&broker_config);
+        assert!(consume_enable(&slow_group));
+        store.shutdown();
+    }
+
+    /// A client channel and the broker side reader of what is written to it.
+    async fn client_channel() -> (
+        Channel,
+        FramedRead<tokio::net::TcpStream, RemotingCommandCodec>,
+    ) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stream = tokio::net::TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (peer, _) = listener.accept().await.unwrap();
+        let channel = Channel::new(
+            stream.local_addr().unwrap(),
+            stream.peer_addr().unwrap(),
+            Connection::new(stream),
+            ArcMut::new(HashMap::new()),
+        );
+        (channel, FramedRead::new(peer, RemotingCommandCodec::new()))
+    }
+
+    #[test]
+    fn cluster_topic_takes_messages_only_when_enabled() {
+        for (cluster_topic_enable, listen_port, expected) in [
+            (true, 30951, ResponseCode::Success),
+            (false, 30961, ResponseCode::NoPermission),
+        ] {
+            let root = tempfile::tempdir().unwrap();
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            // brokers own runtimes of their own, so they are built and dropped outside `block_on`
+            let guard = runtime.enter();
+            let broker_config = BrokerConfig {
+                cluster_topic_enable,
+                ..broker_config(root.path(), "broker-a", listen_port)
+            };
+            let mut broker = broker_with_config(root.path(), broker_config);
+            assert!(runtime.block_on(broker.initialize()));
+            let mut processor = broker.init_processor();
+
+            let response = runtime.block_on(async {
+                let (channel, mut peer) = client_channel().await;
+                let ctx = ArcMut::new(ConnectionHandlerContextWrapper::new(channel.clone()));
+                let request_header = SendMessageRequestHeader {
+                    producer_group: "ClusterTopicProducer".into(),
+                    topic: "IsolationCluster".into(),
+                    default_topic: TopicValidator::AUTO_CREATE_TOPIC_KEY_TOPIC.into(),
+                    default_topic_queue_nums: 4,
+                    queue_id: Some(0),
+                    born_timestamp: get_current_millis() as i64,
+                    ..SendMessageRequestHeader::default()
+                };
+                let mut request = RemotingCommand::create_request_command(
+                    RequestCode::SendMessage,
+                    request_header,
+                )
+                .set_body(Bytes::from_static(b"smoke test"));
+                request.make_custom_header_to_net();
+                let response = processor
+                    .process_request(channel, ArcMut::downgrade(&ctx), request)
+                    .await
+                    .unwrap();
+                match response {
+                    Some(response) => response,
+                    // stored messages are acknowledged on the connection by the processor itself
+                    None => tokio::time::timeout(Duration::from_secs(5), peer.next())
+                        .await
+                        .expect("the send is answered")
+                        .unwrap()
+                        .unwrap(),
+                }
+            });
+            assert_eq!(
+                response.code(),
+                expected as i32,
+                "cluster_topic_enable={cluster_topic_enable}: {:?}",
+                response.remark()
+            );
+            drop(processor);
+            drop(broker);
+            drop(guard);
+            drop(runtime);
+        }
+    }
+
+    #[test]
+    fn consumers_going_away_do_not_leave_their_long_polls_held() {
+        let root = tempfile::tempdir().unwrap();
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        // brokers own runtimes of their own, so they are built and dropped outside `block_on`
+        let guard = runtime.enter();
+        let mut broker = broker(root.path(), "broker-a", 30941);
+        assert!(runtime.block_on(broker.initialize()));
+        broker.init_processor();
+        let hold_service = broker.pull_request_hold_service.clone().unwrap();
+        let consumer_manager = broker.consumer_manager.clone();
+        let group = CheetahString::from_static_str("LongPollGroup");
+        let topic = CheetahString::from_static_str("LongPollTopic");
+
+        runtime.block_on(async {
+            let subscription_data =
+                FilterAPI::build_subscription_data(&topic, &"*".into()).unwrap();
+            let mut consumers = Vec::new();
+            for (queue_id, client_id) in ["closing-client", "unregistering-client"]
+                .into_iter()
+                .enumerate()
+            {
+                let (channel, peer) = client_channel().await;
+                let ctx = ArcMut::new(ConnectionHandlerContextWrapper::new(channel.clone()));
+                let client_channel_info = ClientChannelInfo::new(
+                    channel.clone(),
+                    client_id.into(),
+                    LanguageCode::RUST,
+                    0,
+                );
+                consumer_manager.register_consumer(
+                    &group,
+                    client_channel_info.clone(),
+                    ConsumeType::ConsumePassively,
+                    MessageModel::Clustering,
+                    ConsumeFromWhere::ConsumeFromLastOffset,
+                    HashSet::from([subscription_data.clone()]),
+                    false,
+                );
+                hold_service.suspend_pull_request(
+                    topic.as_str(),
+                    queue_id as i32,
+                    PullRequest::new(
+                        RemotingCommand::create_remoting_command(RequestCode::PullMessage)
+                            .set_opaque(100 + queue_id as i32),
+                        channel,
+                        ArcMut::downgrade(&ctx),
+                        15_000,
+                        0,
+                        7,
+                        subscription_data.clone(),
+                        Arc::new(Box::new(ExpressionMessageFilter::new(
+                            Some(subscription_data.clone()),
+                            None,
+                            broker.consumer_filter_manager.clone(),
+                        ))),
+                    ),
+                );
+                consumers.push((client_channel_info, ctx, peer));
+            }
+            assert_eq!(hold_service.held_request_count(), 2);
+
+            // the first consumer's connection closes without an UNREGISTER_CLIENT
+            let closing_addr = consumers[0].0.channel().remote_address();
+            assert_eq!(
+                consumer_manager.do_channel_close_event(closing_addr, |_| true),
+                1
+            );
+            assert_eq!(hold_service.held_request_count(), 1);
+            let remaining = consumer_manager.get_consumer_group_info(&group).unwrap();
+            assert_eq!(remaining.get_all_client_ids(), vec!["unregistering-client"]);
+
+            // the second one unregisters explicitly and gets the same cleanup
+            consumer_manager.unregister_consumer(&group, &consumers[1].0, true);
+            assert_eq!(hold_service.held_request_count(), 0);
+            assert!(consumer_manager.get_consumer_group_info(&group).is_none());
+
+            // the first consumer left while the second was still in the group, so the second
+            // one is also told to rebalance
+            for (queue_id, (_, _, peer)) in consumers.iter_mut().enumerate() {
+                let mut commands = Vec::new();
+                for _ in 0..=queue_id {
+                    commands.push(
+                        tokio::time::timeout(Duration::from_secs(5), peer.next())
+                            .await
+                            .expect("the held pull request is answered")
+                            .unwrap()
+                            .unwrap(),
+                    );
+                }
+                let (responses, requests): (Vec<_>, Vec<_>) = commands
+                    .into_iter()
+                    .partition(RemotingCommand::is_response_type);
+                assert_eq!(responses.len(), 1);
+                let response = &responses[0];
+                assert_eq!(response.opaque(), 100 + queue_id as i32);
+                assert_eq!(
+                    ResponseCode::from(response.code()),
+                    ResponseCode::PullNotFound
+                );
+                let response_header = response
+                    .decode_command_custom_header::<PullMessageResponseHeader>()
+                    .unwrap();
+                assert_eq!(response_header.next_begin_offset, Some(7));
+                let notified = requests
+                    .iter()
+                    .map(|request| RequestCode::from(request.code()))
+                    .collect::<Vec<_>>();
+                let expected = if queue_id == 0 {
+                    vec![]
+                } else {
+                    vec![RequestCode::NotifyConsumerIdsChanged]
+                };
+                assert_eq!(notified, expected);
+            }
+        });
+        drop(hold_service);
+        drop(broker);
+        drop(guard);
+        drop(runtime);
+    }
+}
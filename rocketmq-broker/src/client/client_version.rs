@@ -0,0 +1,156 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use rocketmq_common::common::mq_version::get_version_desc;
+use rocketmq_common::utils::serde_json_utils::SerdeJsonUtils;
+use rocketmq_remoting::protocol::heartbeat::heartbeat_data::HeartbeatData;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::LanguageCode;
+
+use crate::error::BrokerError;
+
+/// Whether the SDK of `language` numbers its releases with the MQVersion ordinals. Other SDKs
+/// use their own numbering, their reported version says nothing about protocol support.
+fn uses_mq_version(language: LanguageCode) -> bool {
+    matches!(language, LanguageCode::JAVA | LanguageCode::RUST)
+}
+
+/// Rejects a request from a client older than `min_version`, `action` names what the client
+/// tried to do in the error.
+pub(crate) fn check_client_version(
+    request: &RemotingCommand,
+    min_version: i32,
+    action: &str,
+) -> Option<RemotingCommand> {
+    if min_version <= 0 || !uses_mq_version(request.language()) || request.version() >= min_version
+    {
+        return None;
+    }
+    Some(
+        BrokerError::VersionNotSupported(format!(
+            "the {} client version {} is not allowed to {}, please upgrade the client to {} or \
+             later",
+            request.language(),
+            get_version_desc(request.version()),
+            action,
+            get_version_desc(min_version)
+        ))
+        .into(),
+    )
+}
+
+/// Decodes the heartbeat body, the error is the remark to answer with. SDKs of other languages are
+/// known to leave out the client id, the remote address stands in for it so their channels can
+/// still be tracked.
+pub(crate) fn decode_heartbeat(
+    request: &RemotingCommand,
+    remote_address: &str,
+) -> Result<HeartbeatData, &'static str> {
+    let mut heartbeat_data = request
+        .body()
+        .as_ref()
+        .and_then(|body| SerdeJsonUtils::decode::<HeartbeatData>(body.as_ref()).ok())
+        .ok_or("the heartbeat body is missing or malformed")?;
+    if heartbeat_data.client_id.is_empty() {
+        if request.language() == LanguageCode::JAVA {
+            return Err("the heartbeat carries no clientID");
+        }
+        heartbeat_data.client_id = CheetahString::from_slice(remote_address);
+    }
+    Ok(heartbeat_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::mq_version::RocketMqVersion;
+    use rocketmq_remoting::code::request_code::RequestCode;
+    use rocketmq_remoting::code::response_code::ResponseCode;
+
+    use super::*;
+
+    fn heartbeat(language: LanguageCode, version: RocketMqVersion, body: &str) -> RemotingCommand {
+        RemotingCommand::new_request(RequestCode::HeartBeat, body.to_string())
+            .set_language(language)
+            .set_version(version.into())
+    }
+
+    #[test]
+    fn old_java_and_rust_clients_are_asked_to_upgrade() {
+        let min_version = i32::from(RocketMqVersion::V500);
+        for language in [LanguageCode::JAVA, LanguageCode::RUST] {
+            let request = heartbeat(language, RocketMqVersion::V494, "{}");
+            let response = check_client_version(&request, min_version, "send heartbeat").unwrap();
+            assert_eq!(
+                ResponseCode::from(response.code()),
+                ResponseCode::VersionNotSupported
+            );
+            let remark = response.remark().unwrap();
+            assert!(remark.contains("V4_9_4"), "{remark}");
+            assert!(
+                remark.contains("please upgrade the client to V5_0_0"),
+                "{remark}"
+            );
+        }
+    }
+
+    #[test]
+    fn recent_clients_and_other_languages_are_accepted() {
+        let min_version = i32::from(RocketMqVersion::V500);
+        let request = heartbeat(LanguageCode::JAVA, RocketMqVersion::V500, "{}");
+        assert!(check_client_version(&request, min_version, "send heartbeat").is_none());
+        let request = heartbeat(LanguageCode::JAVA, RocketMqVersion::V531, "{}");
+        assert!(check_client_version(&request, min_version, "send heartbeat").is_none());
+        // Go and C++ SDKs report their own release numbers
+        for language in [LanguageCode::GO, LanguageCode::CPP, LanguageCode::HTTP] {
+            let request = heartbeat(language, RocketMqVersion::V300Snapshot, "{}");
+            assert!(check_client_version(&request, min_version, "send heartbeat").is_none());
+        }
+        // no minimum configured
+        let request = heartbeat(LanguageCode::JAVA, RocketMqVersion::V300Snapshot, "{}");
+        assert!(check_client_version(&request, 0, "send heartbeat").is_none());
+    }
+
+    #[test]
+    fn heartbeat_without_client_id_is_lenient_for_other_languages() {
+        let request = heartbeat(LanguageCode::GO, RocketMqVersion::V500, "{}");
+        let heartbeat_data = decode_heartbeat(&request, "127.0.0.1:6000").unwrap();
+        assert_eq!(heartbeat_data.client_id, "127.0.0.1:6000");
+
+        let request = heartbeat(LanguageCode::JAVA, RocketMqVersion::V500, "{}");
+        assert_eq!(
+            decode_heartbeat(&request, "127.0.0.1:6000"),
+            Err("the heartbeat carries no clientID")
+        );
+
+        let request = heartbeat(
+            LanguageCode::JAVA,
+            RocketMqVersion::V500,
+            r#"{"clientID":"client-a"}"#,
+        );
+        let heartbeat_data = decode_heartbeat(&request, "127.0.0.1:6000").unwrap();
+        assert_eq!(heartbeat_data.client_id, "client-a");
+    }
+
+    #[test]
+    fn malformed_heartbeat_is_rejected_instead_of_panicking() {
+        let request = heartbeat(LanguageCode::JAVA, RocketMqVersion::V500, "not json");
+        assert_eq!(
+            decode_heartbeat(&request, "127.0.0.1:6000"),
+            Err("the heartbeat body is missing or malformed")
+        );
+    }
+}
@@ -15,15 +15,87 @@
  * limitations under the License.
  */
 use std::any::Any;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::heartbeat::subscription_data::SubscriptionData;
+use tracing::warn;
 
 use crate::client::consumer_group_event::ConsumerGroupEvent;
 use crate::client::consumer_ids_change_listener::ConsumerIdsChangeListener;
+use crate::client::net::broker_to_client::Broker2Client;
+use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
 
 #[derive(Default)]
-pub struct DefaultConsumerIdsChangeListener {}
+pub struct DefaultConsumerIdsChangeListener {
+    consumer_filter_manager: Arc<ConsumerFilterManager>,
+    broker_to_client: Broker2Client,
+}
+
+impl DefaultConsumerIdsChangeListener {
+    pub(crate) fn new(consumer_filter_manager: Arc<ConsumerFilterManager>) -> Self {
+        DefaultConsumerIdsChangeListener {
+            consumer_filter_manager,
+            broker_to_client: Broker2Client,
+        }
+    }
+
+    fn notify_consumer_ids_changed(&self, group: &str, channels: &[Channel]) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            warn!(
+                "no runtime to notify the consumers of group {} of the change",
+                group
+            );
+            return;
+        };
+        let group = CheetahString::from_slice(group);
+        for channel in channels {
+            let broker_to_client = self.broker_to_client.clone();
+            let mut channel = channel.clone();
+            let group = group.clone();
+            runtime.spawn(async move {
+                if let Err(e) = broker_to_client
+                    .notify_consumer_ids_changed(&mut channel, &group)
+                    .await
+                {
+                    warn!(
+                        "notify consumer {} of group {} changed failed: {}",
+                        channel.remote_address(),
+                        group,
+                        e
+                    );
+                }
+            });
+        }
+    }
+}
 
 impl ConsumerIdsChangeListener for DefaultConsumerIdsChangeListener {
-    fn handle(&self, _event: ConsumerGroupEvent, _group: &str, _args: &[&dyn Any]) {}
+    fn handle(&self, event: ConsumerGroupEvent, group: &str, args: &[&dyn Any]) {
+        match event {
+            ConsumerGroupEvent::Unregister => self.consumer_filter_manager.unregister(group),
+            ConsumerGroupEvent::Change => {
+                if let Some(channels) = args
+                    .first()
+                    .and_then(|arg| arg.downcast_ref::<Vec<Channel>>())
+                {
+                    self.notify_consumer_ids_changed(group, channels);
+                }
+            }
+            ConsumerGroupEvent::Register => {
+                if let Some(sub_list) = args
+                    .first()
+                    .and_then(|arg| arg.downcast_ref::<HashSet<SubscriptionData>>())
+                {
+                    self.consumer_filter_manager
+                        .register(&CheetahString::from_slice(group), sub_list);
+                }
+            }
+            _ => {}
+        }
+    }
 
     fn shutdown(&self) {
         todo!()
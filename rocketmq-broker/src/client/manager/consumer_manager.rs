@@ -17,6 +17,7 @@
 use std::any::Any;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::sync::Weak;
 
@@ -24,10 +25,12 @@ use cheetah_string::CheetahString;
 use parking_lot::RwLock;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
 use rocketmq_common::common::consumer::consume_from_where::ConsumeFromWhere;
+use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::heartbeat::consume_type::ConsumeType;
 use rocketmq_remoting::protocol::heartbeat::message_model::MessageModel;
 use rocketmq_remoting::protocol::heartbeat::subscription_data::SubscriptionData;
 use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
+use tracing::info;
 
 use crate::client::client_channel_info::ClientChannelInfo;
 use crate::client::consumer_group_event::ConsumerGroupEvent;
@@ -38,7 +41,7 @@ pub struct ConsumerManager {
     consumer_table: Arc<RwLock<HashMap<CheetahString, ConsumerGroupInfo>>>,
     consumer_compensation_table: Arc<RwLock<HashMap<CheetahString, ConsumerGroupInfo>>>,
     consumer_ids_change_listener_list:
-        Vec<Box<dyn ConsumerIdsChangeListener + Send + Sync + 'static>>,
+        RwLock<Vec<Box<dyn ConsumerIdsChangeListener + Send + Sync + 'static>>>,
     broker_stats_manager: Arc<RwLock<Option<Weak<BrokerStatsManager>>>>,
     channel_expired_timeout: u64,
     subscription_expired_timeout: u64,
@@ -49,7 +52,7 @@ impl ConsumerManager {
         consumer_ids_change_listener: Box<dyn ConsumerIdsChangeListener + Send + Sync + 'static>,
         expired_timeout: u64,
     ) -> Self {
-        let consumer_ids_change_listener_list = vec![consumer_ids_change_listener];
+        let consumer_ids_change_listener_list = RwLock::new(vec![consumer_ids_change_listener]);
         ConsumerManager {
             consumer_table: Arc::new(RwLock::new(HashMap::new())),
             consumer_compensation_table: Arc::new(RwLock::new(HashMap::new())),
@@ -64,7 +67,7 @@ impl ConsumerManager {
         consumer_ids_change_listener: Box<dyn ConsumerIdsChangeListener + Send + Sync + 'static>,
         broker_config: Arc<BrokerConfig>,
     ) -> Self {
-        let consumer_ids_change_listener_list = vec![consumer_ids_change_listener];
+        let consumer_ids_change_listener_list = RwLock::new(vec![consumer_ids_change_listener]);
         ConsumerManager {
             consumer_table: Arc::new(RwLock::new(HashMap::new())),
             consumer_compensation_table: Arc::new(RwLock::new(HashMap::new())),
@@ -77,12 +80,26 @@ impl ConsumerManager {
 }
 
 impl ConsumerManager {
+    /// Adds a listener for the services created after the manager, it sees every later event.
+    pub fn append_consumer_ids_change_listener(
+        &self,
+        listener: Box<dyn ConsumerIdsChangeListener + Send + Sync + 'static>,
+    ) {
+        self.consumer_ids_change_listener_list
+            .write()
+            .push(listener);
+    }
+
     pub fn set_broker_stats_manager(&self, broker_stats_manager: Option<Weak<BrokerStatsManager>>) {
         *self.broker_stats_manager.write() = broker_stats_manager;
     }
 }
 
 impl ConsumerManager {
+    pub fn get_consumer_table(&self) -> Arc<RwLock<HashMap<CheetahString, ConsumerGroupInfo>>> {
+        self.consumer_table.clone()
+    }
+
     pub fn find_subscription_data(
         &self,
         group: &CheetahString,
@@ -244,13 +261,92 @@ impl ConsumerManager {
         r1 || r2
     }
 
+    pub fn unregister_consumer(
+        &self,
+        group: &CheetahString,
+        client_channel_info: &ClientChannelInfo,
+        is_notify_consumer_ids_changed_enable: bool,
+    ) {
+        let mut write_guard = self.consumer_table.write();
+        let Some(consumer_group_info) = write_guard.get(group) else {
+            return;
+        };
+        if consumer_group_info.unregister_channel(client_channel_info) {
+            let subscribe_topics = consumer_group_info.get_subscribe_topics();
+            self.call_consumer_ids_change_listener(
+                ConsumerGroupEvent::ClientUnregister,
+                group,
+                &[
+                    client_channel_info as &dyn Any,
+                    &subscribe_topics as &dyn Any,
+                ],
+            );
+        }
+        let all_channel = consumer_group_info.get_all_channels();
+        if consumer_group_info
+            .get_channel_info_table()
+            .read()
+            .is_empty()
+            && write_guard.remove(group).is_some()
+        {
+            self.call_consumer_ids_change_listener(ConsumerGroupEvent::Unregister, group, &[]);
+        }
+        if is_notify_consumer_ids_changed_enable {
+            self.call_consumer_ids_change_listener(
+                ConsumerGroupEvent::Change,
+                group,
+                &[&all_channel as &dyn Any],
+            );
+        }
+    }
+
+    /// Unregisters the consumers of the connection to `remote_addr`, which closed without an
+    /// explicit `UNREGISTER_CLIENT`. The cleanup is the same as for an explicit unregister, so
+    /// the held pull requests of the connection are answered and the remaining consumers of the
+    /// group are told to rebalance. Pop checkpoints are left alone, their messages become visible
+    /// again once their invisible time is over.
+    pub fn do_channel_close_event(
+        &self,
+        remote_addr: SocketAddr,
+        is_notify_consumer_ids_changed_enable: impl Fn(&CheetahString) -> bool,
+    ) -> usize {
+        let closed = self
+            .consumer_table
+            .read()
+            .iter()
+            .flat_map(|(group, consumer_group_info)| {
+                consumer_group_info
+                    .get_channel_info_table()
+                    .read()
+                    .values()
+                    .filter(|info| info.channel().remote_address() == remote_addr)
+                    .map(|info| (group.clone(), info.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        for (group, client_channel_info) in &closed {
+            info!(
+                "consumer {} of group {} closed its channel {}",
+                client_channel_info.client_id(),
+                group,
+                remote_addr
+            );
+            self.unregister_consumer(
+                group,
+                client_channel_info,
+                is_notify_consumer_ids_changed_enable(group),
+            );
+        }
+        closed.len()
+    }
+
     pub fn call_consumer_ids_change_listener(
         &self,
         event: ConsumerGroupEvent,
         group: &str,
         args: &[&dyn Any],
     ) {
-        for listener in self.consumer_ids_change_listener_list.iter() {
+        for listener in self.consumer_ids_change_listener_list.read().iter() {
             listener.handle(event, group, args);
         }
     }
@@ -264,4 +360,16 @@ impl ConsumerManager {
         }
         groups
     }
+
+    /// Channels of the consumers whose group subscribes to `topic`.
+    pub fn find_channels_by_topic(&self, topic: &CheetahString) -> Vec<Channel> {
+        self.consumer_table
+            .read()
+            .values()
+            .filter(|consumer_group_info| {
+                consumer_group_info.find_subscription_data(topic).is_some()
+            })
+            .flat_map(|consumer_group_info| consumer_group_info.get_all_channels())
+            .collect()
+    }
 }
@@ -16,6 +16,7 @@
  */
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::atomic::AtomicI32;
 use std::sync::Arc;
 
@@ -27,12 +28,16 @@ use tracing::info;
 
 use crate::client::client_channel_info::ClientChannelInfo;
 
+/// Topics remembered per producer connection, the rest of a longer heartbeat list is ignored.
+const MAX_TOPICS_PER_CHANNEL: usize = 1024;
+
 #[derive(Default)]
 pub struct ProducerManager {
     group_channel_table: parking_lot::Mutex<
         HashMap<CheetahString /* group name */, HashMap<Channel, ClientChannelInfo>>,
     >,
     client_channel_table: parking_lot::Mutex<HashMap<CheetahString, Channel /* client ip:port */>>,
+    channel_topic_table: parking_lot::Mutex<HashMap<Channel, HashSet<CheetahString>>>,
     positive_atomic_counter: Arc<AtomicI32>,
 }
 
@@ -41,12 +46,19 @@ impl ProducerManager {
         Self {
             group_channel_table: parking_lot::Mutex::new(HashMap::new()),
             client_channel_table: parking_lot::Mutex::new(HashMap::new()),
+            channel_topic_table: parking_lot::Mutex::new(HashMap::new()),
             positive_atomic_counter: Arc::new(Default::default()),
         }
     }
 }
 
 impl ProducerManager {
+    pub fn get_group_channel_table(
+        &self,
+    ) -> HashMap<CheetahString, HashMap<Channel, ClientChannelInfo>> {
+        self.group_channel_table.lock().clone()
+    }
+
     pub fn group_online(&self, group: String) -> bool {
         let binding = self.group_channel_table.lock();
         let channels = binding.get(group.as_str());
@@ -68,6 +80,9 @@ impl ProducerManager {
             if !ct.is_empty() {
                 if let Some(ctx) = ctx.upgrade() {
                     let old = ct.remove(ctx.channel());
+                    if old.is_some() {
+                        self.channel_topic_table.lock().remove(ctx.channel());
+                    }
                     //let old = ct.remove(client_channel_info.channel());
                     if old.is_some() {
                         info!(
@@ -117,6 +132,33 @@ impl ProducerManager {
         );
     }
 
+    /// Remembers the topics the producers behind `channel` publish to, replacing what its
+    /// previous heartbeat reported.
+    #[allow(clippy::mutable_key_type)]
+    pub fn update_channel_topics(&self, channel: &Channel, topics: HashSet<CheetahString>) {
+        let mut channel_topic_table = self.channel_topic_table.lock();
+        if topics.is_empty() {
+            channel_topic_table.remove(channel);
+            return;
+        }
+        let topics = if topics.len() > MAX_TOPICS_PER_CHANNEL {
+            topics.into_iter().take(MAX_TOPICS_PER_CHANNEL).collect()
+        } else {
+            topics
+        };
+        channel_topic_table.insert(channel.clone(), topics);
+    }
+
+    /// Channels of the producers whose last heartbeat reported publishing to `topic`.
+    pub fn find_channels_by_topic(&self, topic: &CheetahString) -> Vec<Channel> {
+        self.channel_topic_table
+            .lock()
+            .iter()
+            .filter(|(_, topics)| topics.contains(topic))
+            .map(|(channel, _)| channel.clone())
+            .collect()
+    }
+
     pub fn find_channel(&self, client_id: &str) -> Option<Channel> {
         self.client_channel_table.lock().get(client_id).cloned()
     }
@@ -140,3 +182,63 @@ impl ProducerManager {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::client_channel;
+
+    fn topics(names: &[&str]) -> HashSet<CheetahString> {
+        names
+            .iter()
+            .map(|name| CheetahString::from(*name))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn channels_are_found_by_the_topics_their_last_heartbeat_reported() {
+        let producer_manager = ProducerManager::new();
+        let first = client_channel().await;
+        let second = client_channel().await;
+        producer_manager.update_channel_topics(&first, topics(&["a", "b"]));
+        producer_manager.update_channel_topics(&second, topics(&["b"]));
+
+        assert_eq!(
+            producer_manager.find_channels_by_topic(&"a".into()),
+            vec![first.clone()]
+        );
+        assert_eq!(
+            producer_manager.find_channels_by_topic(&"b".into()).len(),
+            2
+        );
+
+        producer_manager.update_channel_topics(&first, topics(&["c"]));
+        producer_manager.update_channel_topics(&second, HashSet::new());
+        assert!(producer_manager
+            .find_channels_by_topic(&"a".into())
+            .is_empty());
+        assert!(producer_manager
+            .find_channels_by_topic(&"b".into())
+            .is_empty());
+        assert_eq!(
+            producer_manager.find_channels_by_topic(&"c".into()),
+            vec![first]
+        );
+    }
+
+    #[tokio::test]
+    async fn topics_remembered_per_channel_are_capped() {
+        let producer_manager = ProducerManager::new();
+        let channel = client_channel().await;
+        let reported = (0..MAX_TOPICS_PER_CHANNEL + 10)
+            .map(|index| CheetahString::from(format!("topic-{index}")))
+            .collect::<HashSet<_>>();
+        producer_manager.update_channel_topics(&channel, reported.clone());
+
+        let remembered = reported
+            .iter()
+            .filter(|topic| !producer_manager.find_channels_by_topic(topic).is_empty())
+            .count();
+        assert_eq!(remembered, MAX_TOPICS_PER_CHANNEL);
+    }
+}
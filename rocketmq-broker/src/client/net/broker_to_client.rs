@@ -14,24 +14,38 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::collections::HashMap;
+
 use cheetah_string::CheetahString;
 use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_common::MessageDecoder;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::body::reset_offset_body::ResetOffsetBody;
 use rocketmq_remoting::protocol::header::check_transaction_state_request_header::CheckTransactionStateRequestHeader;
+use rocketmq_remoting::protocol::header::notify_consumer_ids_changed_request_header::NotifyConsumerIdsChangedRequestHeader;
+use rocketmq_remoting::protocol::header::notify_topic_route_changed_request_header::NotifyTopicRouteChangedRequestHeader;
+use rocketmq_remoting::protocol::header::reset_offset_request_header::ResetOffsetRequestHeader;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::RemotingSerializable;
+use tracing::error;
+use tracing::info;
 
 use crate::error::BrokerError::BrokerClientError;
 use crate::error::BrokerError::BrokerCommonError;
 use crate::Result;
 
+/// Requests the broker sends to clients over the connections they opened.
 #[derive(Default, Clone)]
 pub struct Broker2Client;
 
 impl Broker2Client {
+    /// Sends `request` to the client behind `channel` and waits for its answer. A client that
+    /// disconnects before answering fails the call with
+    /// `BrokerClientError(Error::ChannelClosed)`.
     pub async fn call_client(
-        &mut self,
+        &self,
         channel: &mut Channel,
         request: RemotingCommand,
         timeout_millis: u64,
@@ -66,4 +80,260 @@ impl Broker2Client {
             Err(e) => Err(BrokerClientError(e)),
         }
     }
+
+    /// Tells a consumer the members of its group changed, so it rebalances right away instead
+    /// of on its next periodic rebalance.
+    pub async fn notify_consumer_ids_changed(
+        &self,
+        channel: &mut Channel,
+        consumer_group: &CheetahString,
+    ) -> Result<()> {
+        if consumer_group.is_empty() {
+            error!("notifyConsumerIdsChanged consumerGroup is null");
+            return Ok(());
+        }
+        let request_header = NotifyConsumerIdsChangedRequestHeader {
+            consumer_group: consumer_group.clone(),
+            rpc_request_header: None,
+        };
+        let request = RemotingCommand::create_request_command(
+            RequestCode::NotifyConsumerIdsChanged,
+            request_header,
+        );
+        match channel.send_one_way(request, 10).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(BrokerClientError(e)),
+        }
+    }
+
+    /// Tells a client the route of `topic` changed, so it refreshes it from the name server
+    /// right away instead of on its next poll.
+    pub async fn notify_topic_route_changed(
+        &self,
+        channel: &mut Channel,
+        topic: &CheetahString,
+    ) -> Result<()> {
+        let request = RemotingCommand::create_request_command(
+            RequestCode::NotifyTopicRouteChanged,
+            NotifyTopicRouteChangedRequestHeader {
+                topic: topic.clone(),
+            },
+        );
+        match channel.send_one_way(request, 10).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(BrokerClientError(e)),
+        }
+    }
+
+    /// Pushes the offsets the group of `request_header` is reset to to each of `channels`.
+    /// Returns how many clients the push was handed to.
+    pub async fn reset_offset(
+        &self,
+        request_header: ResetOffsetRequestHeader,
+        offset_table: HashMap<MessageQueue, i64>,
+        channels: Vec<Channel>,
+    ) -> usize {
+        let topic = request_header.topic.clone();
+        let group = request_header.group.clone();
+        let timestamp = request_header.timestamp;
+        let body = ResetOffsetBody { offset_table };
+        let request = RemotingCommand::create_request_command(
+            RequestCode::ResetConsumerClientOffset,
+            request_header,
+        )
+        .set_body(body.encode());
+        let mut notified = 0;
+        for mut channel in channels {
+            match channel.send_one_way(request.clone(), 5000).await {
+                Ok(_) => {
+                    notified += 1;
+                    info!(
+                        "[reset-offset] reset offset success. topic={}, group={}, clientId={}, \
+                         timestamp={}",
+                        topic,
+                        group,
+                        channel.remote_address(),
+                        timestamp
+                    );
+                }
+                Err(e) => error!(
+                    "[reset-offset] reset offset exception. topic={}, group={}, clientId={}: {}",
+                    topic,
+                    group,
+                    channel.remote_address(),
+                    e
+                ),
+            }
+        }
+        notified
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::SinkExt;
+    use futures::StreamExt;
+    use rocketmq_remoting::codec::remoting_command_codec::RemotingCommandCodec;
+    use rocketmq_remoting::protocol::RemotingDeserializable;
+    use rocketmq_remoting::remoting_server::server::run;
+    use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
+    use rocketmq_remoting::runtime::processor::RequestProcessor;
+    use tokio::net::TcpListener;
+    use tokio::net::TcpStream;
+    use tokio::sync::mpsc;
+    use tokio_util::codec::Framed;
+
+    use super::*;
+    use crate::error::BrokerError;
+
+    /// Hands the broker side of every client connection to the test.
+    #[derive(Clone)]
+    struct ChannelCapturingProcessor(mpsc::UnboundedSender<Channel>);
+
+    impl RequestProcessor for ChannelCapturingProcessor {
+        async fn process_request(
+            &mut self,
+            channel: Channel,
+            _ctx: ConnectionHandlerContext,
+            _request: RemotingCommand,
+        ) -> rocketmq_remoting::Result<Option<RemotingCommand>> {
+            let _ = self.0.send(channel);
+            Ok(Some(RemotingCommand::create_response_command()))
+        }
+    }
+
+    /// Connects a fake client and returns it with the channel the broker talks to it over.
+    async fn connect_client() -> (Framed<TcpStream, RemotingCommandCodec>, Channel) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        tokio::spawn(run(
+            listener,
+            std::future::pending::<()>(),
+            ChannelCapturingProcessor(tx),
+            None,
+            vec![],
+        ));
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut client = Framed::new(stream, RemotingCommandCodec::new());
+        client
+            .send(RemotingCommand::create_remoting_command(RequestCode::HeartBeat).set_opaque(1))
+            .await
+            .unwrap();
+        client.next().await.unwrap().unwrap();
+        (client, rx.recv().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn call_client_returns_the_client_answer() {
+        let (mut client, mut channel) = connect_client().await;
+        tokio::spawn(async move {
+            let request = client.next().await.unwrap().unwrap();
+            client
+                .send(
+                    RemotingCommand::create_response_command()
+                        .set_opaque(request.opaque())
+                        .set_remark("consumer running info"),
+                )
+                .await
+                .unwrap();
+            client.next().await;
+        });
+
+        let response = Broker2Client
+            .call_client(
+                &mut channel,
+                RemotingCommand::create_remoting_command(RequestCode::GetConsumerRunningInfo),
+                5_000,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            response.remark().map(|remark| remark.as_str()),
+            Some("consumer running info")
+        );
+    }
+
+    #[tokio::test]
+    async fn call_client_fails_when_the_client_disconnects() {
+        let (mut client, mut channel) = connect_client().await;
+        tokio::spawn(async move {
+            client.next().await.unwrap().unwrap();
+            drop(client);
+        });
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            Broker2Client.call_client(
+                &mut channel,
+                RemotingCommand::create_remoting_command(RequestCode::GetConsumerRunningInfo),
+                60_000,
+            ),
+        )
+        .await
+        .expect("call outlived the connection");
+        assert!(matches!(
+            result,
+            Err(BrokerError::BrokerClientError(
+                rocketmq_remoting::error::Error::ChannelClosed(_)
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn consumer_is_told_its_group_changed() {
+        let (mut client, mut channel) = connect_client().await;
+
+        Broker2Client
+            .notify_consumer_ids_changed(&mut channel, &CheetahString::from_static_str("group"))
+            .await
+            .unwrap();
+
+        let request = client.next().await.unwrap().unwrap();
+        assert_eq!(request.code(), RequestCode::NotifyConsumerIdsChanged as i32);
+        assert!(request.is_oneway_rpc());
+        let header = request
+            .decode_command_custom_header::<NotifyConsumerIdsChangedRequestHeader>()
+            .unwrap();
+        assert_eq!(header.consumer_group, "group");
+    }
+
+    #[tokio::test]
+    async fn reset_offset_pushes_the_offsets_to_every_client() {
+        let (mut first, first_channel) = connect_client().await;
+        let (mut second, second_channel) = connect_client().await;
+        let queue = MessageQueue::from_parts("topic", "broker-a", 0);
+        let request_header = ResetOffsetRequestHeader {
+            topic: CheetahString::from_static_str("topic"),
+            group: CheetahString::from_static_str("group"),
+            timestamp: 1000,
+            ..Default::default()
+        };
+
+        let notified = Broker2Client
+            .reset_offset(
+                request_header,
+                HashMap::from([(queue.clone(), 42)]),
+                vec![first_channel, second_channel],
+            )
+            .await;
+        assert_eq!(notified, 2);
+
+        for client in [&mut first, &mut second] {
+            let request = client.next().await.unwrap().unwrap();
+            assert_eq!(
+                request.code(),
+                RequestCode::ResetConsumerClientOffset as i32
+            );
+            let header = request
+                .decode_command_custom_header::<ResetOffsetRequestHeader>()
+                .unwrap();
+            assert_eq!(header.group, "group");
+            assert_eq!(header.timestamp, 1000);
+            let body = ResetOffsetBody::decode(request.get_body().unwrap()).unwrap();
+            assert_eq!(body.offset_table.get(&queue), Some(&42));
+        }
+    }
 }
@@ -22,6 +22,8 @@ use std::sync::Arc;
 use lazy_static::lazy_static;
 use parking_lot::RwLock;
 use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::common::system_clock::Clock;
+use rocketmq_common::common::system_clock::SystemClock;
 use rocketmq_common::TimeUtils::get_current_millis;
 use tracing::info;
 use tracing::warn;
@@ -37,13 +39,30 @@ lazy_static! {
 
 type MessageQueueLockTable = HashMap<String, HashMap<MessageQueue, LockEntry>>;
 
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct RebalanceLockManager {
     mq_lock_table: Arc<RwLock<MessageQueueLockTable>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for RebalanceLockManager {
+    fn default() -> Self {
+        Self {
+            mq_lock_table: Default::default(),
+            clock: Arc::new(SystemClock),
+        }
+    }
 }
 
 impl RebalanceLockManager {
+    /// Uses `clock` to stamp and expire the locks.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub fn is_lock_all_expired(&self, group: &str) -> bool {
+        let now = self.clock.now_millis() as i64;
         let lock_table = self.mq_lock_table.read();
         let lock_entry = lock_table.get(group);
         if lock_entry.is_none() {
@@ -51,7 +70,7 @@ impl RebalanceLockManager {
         }
         let lock_entry = lock_entry.unwrap();
         for (_, entry) in lock_entry.iter() {
-            if !entry.is_expired() {
+            if !entry.is_expired(now) {
                 return false;
             }
         }
@@ -74,6 +93,7 @@ impl RebalanceLockManager {
             }
         }
         if !not_locked_mqs.is_empty() {
+            let now = self.clock.now_millis() as i64;
             let mut write_guard = self.mq_lock_table.write();
             let group_value = write_guard
                 .entry(group.to_string())
@@ -88,24 +108,22 @@ impl RebalanceLockManager {
                     );
                     LockEntry {
                         client_id: client_id.to_string(),
-                        last_update_timestamp: AtomicI64::new(get_current_millis() as i64),
+                        last_update_timestamp: AtomicI64::new(now),
                     }
                 });
-                if lock_entry.is_locked(client_id) {
-                    lock_entry.last_update_timestamp.store(
-                        get_current_millis() as i64,
-                        std::sync::atomic::Ordering::Relaxed,
-                    );
+                if lock_entry.is_locked(client_id, now) {
+                    lock_entry
+                        .last_update_timestamp
+                        .store(now, std::sync::atomic::Ordering::Relaxed);
                     lock_mqs.insert(mq);
                     continue;
                 }
                 let old_client_id = lock_entry.client_id.as_str().to_string();
-                if lock_entry.is_expired() {
+                if lock_entry.is_expired(now) {
                     lock_entry.client_id = client_id.to_string();
-                    lock_entry.last_update_timestamp.store(
-                        get_current_millis() as i64,
-                        std::sync::atomic::Ordering::Relaxed,
-                    );
+                    lock_entry
+                        .last_update_timestamp
+                        .store(now, std::sync::atomic::Ordering::Relaxed);
                     warn!(
                         "RebalanceLockManager#tryLockBatch: try to lock a expired message queue, \
                          group={} mq={:?}, old client id={}, new client id={}",
@@ -175,12 +193,12 @@ impl RebalanceLockManager {
             return false;
         }
         let lock_entry = lock_entry.unwrap();
-        let locked = lock_entry.is_locked(client_id);
+        let now = self.clock.now_millis() as i64;
+        let locked = lock_entry.is_locked(client_id, now);
         if locked {
-            lock_entry.last_update_timestamp.store(
-                get_current_millis() as i64,
-                std::sync::atomic::Ordering::Relaxed,
-            );
+            lock_entry
+                .last_update_timestamp
+                .store(now, std::sync::atomic::Ordering::Relaxed);
         }
         locked
     }
@@ -200,8 +218,7 @@ impl LockEntry {
     }
 
     #[inline]
-    pub fn is_expired(&self) -> bool {
-        let now = get_current_millis() as i64;
+    pub fn is_expired(&self, now: i64) -> bool {
         let last_update_timestamp = self
             .last_update_timestamp
             .load(std::sync::atomic::Ordering::Relaxed);
@@ -209,16 +226,18 @@ impl LockEntry {
     }
 
     #[inline]
-    pub fn is_locked(&self, client_id: &str) -> bool {
-        self.client_id == client_id && !self.is_expired()
+    pub fn is_locked(&self, client_id: &str, now: i64) -> bool {
+        self.client_id == client_id && !self.is_expired(now)
     }
 }
 
 #[cfg(test)]
 mod rebalance_lock_manager_tests {
     use std::sync::Arc;
+    use std::time::Duration;
 
     use rocketmq_common::common::message::message_queue::MessageQueue;
+    use rocketmq_common::common::system_clock::MockClock;
 
     use super::*;
 
@@ -298,4 +317,26 @@ mod rebalance_lock_manager_tests {
         let mq = MessageQueue::default();
         assert!(!manager.is_locked("test_group", &mq, "client_1"));
     }
+
+    #[test]
+    fn expired_lock_is_taken_over_by_another_client() {
+        let clock = Arc::new(MockClock::new(1_000_000));
+        let manager = RebalanceLockManager::default().with_clock(clock.clone());
+        let mq = MessageQueue::default();
+        let set = HashSet::from([mq.clone()]);
+        manager.try_lock_batch("test_group", &set, "client_1");
+
+        clock.advance(Duration::from_millis(*REBALANCE_LOCK_MAX_LIVE_TIME as u64));
+        assert!(!manager.is_lock_all_expired("test_group"));
+        assert!(manager
+            .try_lock_batch("test_group", &set, "client_2")
+            .is_empty());
+
+        clock.advance(Duration::from_millis(
+            *REBALANCE_LOCK_MAX_LIVE_TIME as u64 + 1,
+        ));
+        assert!(manager.is_lock_all_expired("test_group"));
+        assert_eq!(manager.try_lock_batch("test_group", &set, "client_2"), set);
+        assert!(!manager.is_locked("test_group", &mq, "client_1"));
+    }
 }
@@ -14,12 +14,238 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::collections::HashMap;
+use std::sync::Arc;
 
-#[derive(Default)]
-pub struct ColdDataCgCtrService;
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use parking_lot::RwLock;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::mix_all;
+use rocketmq_common::common::system_clock::Clock;
+use rocketmq_common::common::system_clock::SystemClock;
+use serde_json::json;
+
+/// Cold data read by a consumer group, metered by a token bucket refilled at the group's
+/// threshold in bytes per second and holding at most one second worth of tokens.
+struct ColdReadBucket {
+    tokens: i64,
+    last_refill_millis: u64,
+    cold_read_bytes: u64,
+    throttled_times: u64,
+}
+
+/// Decides which consumer groups must stop reading cold commit log data for a while, so that
+/// catching-up consumers don't evict the pages producers and tailing consumers depend on.
+pub struct ColdDataCgCtrService {
+    broker_config: Arc<BrokerConfig>,
+    // the cold control list: groups whose threshold overrides coldMaxPullThresholdPerGroup
+    cg_cold_read_threshold: RwLock<HashMap<CheetahString, u64>>,
+    cg_cold_read_runtime: Mutex<HashMap<CheetahString, ColdReadBucket>>,
+    clock: Arc<dyn Clock>,
+}
 
 impl ColdDataCgCtrService {
-    pub fn is_cg_need_cold_data_flow_ctr(&self, _consumer_group: &str) -> bool {
-        false
+    pub fn new(broker_config: Arc<BrokerConfig>) -> Self {
+        Self {
+            broker_config,
+            cg_cold_read_threshold: RwLock::new(HashMap::new()),
+            cg_cold_read_runtime: Mutex::new(HashMap::new()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Uses `clock` to refill the buckets.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Whether `consumer_group` used up its cold read budget and has to wait before reading
+    /// cold data again.
+    pub fn is_cg_need_cold_data_flow_ctr(&self, consumer_group: &str) -> bool {
+        if !self.broker_config.cold_ctr_strategy_enable
+            || mix_all::is_sys_consumer_group_for_no_cold_read_limit(consumer_group)
+        {
+            return false;
+        }
+        let threshold = self.threshold(consumer_group);
+        let mut runtime = self.cg_cold_read_runtime.lock();
+        let Some(bucket) = runtime.get_mut(consumer_group) else {
+            return false;
+        };
+        self.refill(bucket, threshold);
+        if bucket.tokens > 0 {
+            return false;
+        }
+        bucket.throttled_times += 1;
+        true
+    }
+
+    /// Charges `cold_data_bytes` read from the cold area to the budget of `consumer_group`.
+    pub fn cold_acc(&self, consumer_group: &str, cold_data_bytes: u64) {
+        if cold_data_bytes == 0 {
+            return;
+        }
+        let threshold = self.threshold(consumer_group);
+        let now = self.clock.now_millis();
+        let mut runtime = self.cg_cold_read_runtime.lock();
+        let bucket = runtime
+            .entry(CheetahString::from_slice(consumer_group))
+            .or_insert_with(|| ColdReadBucket {
+                tokens: threshold as i64,
+                last_refill_millis: now,
+                cold_read_bytes: 0,
+                throttled_times: 0,
+            });
+        self.refill(bucket, threshold);
+        bucket.tokens -= cold_data_bytes as i64;
+        bucket.cold_read_bytes += cold_data_bytes;
+    }
+
+    fn threshold(&self, consumer_group: &str) -> u64 {
+        self.cg_cold_read_threshold
+            .read()
+            .get(consumer_group)
+            .copied()
+            .unwrap_or(self.broker_config.cold_max_pull_threshold_per_group)
+    }
+
+    fn refill(&self, bucket: &mut ColdReadBucket, threshold: u64) {
+        let now = self.clock.now_millis();
+        let elapsed = now.saturating_sub(bucket.last_refill_millis);
+        let refill = (elapsed as u128 * threshold as u128 / 1000).min(i64::MAX as u128) as i64;
+        bucket.tokens = bucket.tokens.saturating_add(refill).min(threshold as i64);
+        bucket.last_refill_millis = now;
+    }
+
+    /// Adds `consumer_group` to the cold control list, or updates its threshold.
+    pub fn add_or_update_group_config(&self, consumer_group: CheetahString, threshold: u64) {
+        self.cg_cold_read_threshold
+            .write()
+            .insert(consumer_group, threshold);
+    }
+
+    /// Removes `consumer_group` from the cold control list, it falls back to the default
+    /// threshold.
+    pub fn remove_group_config(&self, consumer_group: &str) -> bool {
+        self.cg_cold_read_threshold
+            .write()
+            .remove(consumer_group)
+            .is_some()
+    }
+
+    pub fn get_cold_data_flow_ctr_info(&self) -> String {
+        let runtime_table = self
+            .cg_cold_read_runtime
+            .lock()
+            .iter()
+            .map(|(group, bucket)| {
+                (
+                    group.to_string(),
+                    json!({
+                        "coldReadBytes": bucket.cold_read_bytes,
+                        "throttledTimes": bucket.throttled_times,
+                    }),
+                )
+            })
+            .collect::<serde_json::Map<_, _>>();
+        json!({
+            "coldCtrStrategyEnable": self.broker_config.cold_ctr_strategy_enable,
+            "coldMaxPullThresholdPerGroup": self.broker_config.cold_max_pull_threshold_per_group,
+            "cgColdReadThreshold": &*self.cg_cold_read_threshold.read(),
+            "runtimeTable": runtime_table,
+        })
+        .to_string()
+    }
+
+    /// Adds the cold read counters of every group that read cold data to `runtime_info`.
+    pub fn build_running_stats(&self, runtime_info: &mut HashMap<String, String>) {
+        for (group, bucket) in self.cg_cold_read_runtime.lock().iter() {
+            runtime_info.insert(
+                format!("coldReadBytes_{group}"),
+                bucket.cold_read_bytes.to_string(),
+            );
+            runtime_info.insert(
+                format!("coldReadThrottledTimes_{group}"),
+                bucket.throttled_times.to_string(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rocketmq_common::common::system_clock::MockClock;
+
+    use super::*;
+
+    fn service(clock: Arc<MockClock>) -> ColdDataCgCtrService {
+        ColdDataCgCtrService::new(Arc::new(BrokerConfig {
+            cold_ctr_strategy_enable: true,
+            cold_max_pull_threshold_per_group: 1000,
+            ..BrokerConfig::default()
+        }))
+        .with_clock(clock)
+    }
+
+    #[test]
+    fn group_is_throttled_until_its_bucket_refills() {
+        let clock = Arc::new(MockClock::new(10_000));
+        let service = service(clock.clone());
+        assert!(!service.is_cg_need_cold_data_flow_ctr("GroupA"));
+
+        service.cold_acc("GroupA", 600);
+        assert!(!service.is_cg_need_cold_data_flow_ctr("GroupA"));
+        service.cold_acc("GroupA", 600);
+        assert!(service.is_cg_need_cold_data_flow_ctr("GroupA"));
+        assert!(!service.is_cg_need_cold_data_flow_ctr("GroupB"));
+
+        // 200 bytes of debt are paid back after 200ms
+        clock.advance(Duration::from_millis(100));
+        assert!(service.is_cg_need_cold_data_flow_ctr("GroupA"));
+        clock.advance(Duration::from_millis(150));
+        assert!(!service.is_cg_need_cold_data_flow_ctr("GroupA"));
+
+        let mut runtime_info = HashMap::new();
+        service.build_running_stats(&mut runtime_info);
+        assert_eq!(runtime_info["coldReadBytes_GroupA"], "1200");
+        assert_eq!(runtime_info["coldReadThrottledTimes_GroupA"], "2");
+        assert!(!runtime_info.contains_key("coldReadBytes_GroupB"));
+    }
+
+    #[test]
+    fn control_list_overrides_the_default_threshold() {
+        let clock = Arc::new(MockClock::new(10_000));
+        let service = service(clock);
+        service.add_or_update_group_config("GroupA".into(), 100_000);
+        service.cold_acc("GroupA", 5000);
+        assert!(!service.is_cg_need_cold_data_flow_ctr("GroupA"));
+
+        assert!(service.remove_group_config("GroupA"));
+        assert!(!service.remove_group_config("GroupA"));
+        service.cold_acc("GroupA", 100_000);
+        assert!(service.is_cg_need_cold_data_flow_ctr("GroupA"));
+        let info: serde_json::Value =
+            serde_json::from_str(&service.get_cold_data_flow_ctr_info()).unwrap();
+        assert_eq!(info["runtimeTable"]["GroupA"]["coldReadBytes"], 105_000);
+    }
+
+    #[test]
+    fn disabled_strategy_and_system_groups_are_never_throttled() {
+        let clock = Arc::new(MockClock::new(10_000));
+        let service = ColdDataCgCtrService::new(Arc::new(BrokerConfig {
+            cold_max_pull_threshold_per_group: 1000,
+            ..BrokerConfig::default()
+        }))
+        .with_clock(clock.clone());
+        service.cold_acc("GroupA", 5000);
+        assert!(!service.is_cg_need_cold_data_flow_ctr("GroupA"));
+
+        let service = self::service(clock);
+        service.cold_acc(mix_all::TOOLS_CONSUMER_GROUP, 5000);
+        assert!(!service.is_cg_need_cold_data_flow_ctr(mix_all::TOOLS_CONSUMER_GROUP));
     }
 }
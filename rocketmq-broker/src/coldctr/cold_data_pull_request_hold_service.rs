@@ -14,6 +14,96 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::sync::Arc;
+use std::time::Duration;
+
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::system_clock::Clock;
+use rocketmq_common::common::system_clock::SystemClock;
+use rocketmq_rust::ArcMut;
+use rocketmq_rust::ServiceTask;
+use rocketmq_store::log_file::MessageStore;
+use tracing::info;
+
+use crate::long_polling::pull_request::PullRequest;
+use crate::processor::pull_message_processor::PullMessageProcessor;
+
+/// Marks a pull re-executed after a cold data hold, it is not held again.
 pub const NO_SUSPEND_KEY: &str = "_noSuspend_";
-#[derive(Default)]
-pub struct ColdDataPullRequestHoldService {}
+
+const CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Holds the pulls of groups throttled for reading cold data for `cold_pull_suspend_millis`,
+/// then runs them again without reading cold data.
+pub struct ColdDataPullRequestHoldService<MS> {
+    pull_request_cold_hold_queue: parking_lot::Mutex<Vec<PullRequest>>,
+    pull_message_processor: ArcMut<PullMessageProcessor<MS>>,
+    broker_config: Arc<BrokerConfig>,
+    service: Arc<ServiceTask>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<MS> ColdDataPullRequestHoldService<MS>
+where
+    MS: MessageStore + Send + Sync,
+{
+    pub fn new(
+        pull_message_processor: ArcMut<PullMessageProcessor<MS>>,
+        broker_config: Arc<BrokerConfig>,
+    ) -> Self {
+        Self {
+            pull_request_cold_hold_queue: parking_lot::Mutex::new(Vec::new()),
+            pull_message_processor,
+            broker_config,
+            service: Arc::new(ServiceTask::new("ColdDataPullRequestHoldService")),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn start(&mut self, this: ArcMut<Self>) {
+        self.service.start(move |context| async move {
+            info!("{} service started", context.service_name());
+            while !context.is_stopped() {
+                context.wait_for_running(CHECK_INTERVAL).await;
+                if context.is_stopped() {
+                    break;
+                }
+                this.check_cold_data_pull_request();
+            }
+            info!("{} service end", context.service_name());
+        });
+    }
+
+    pub fn shutdown(&mut self) {
+        self.service.shutdown(false);
+    }
+
+    pub fn suspend_cold_data_read_request(&self, pull_request: PullRequest) {
+        self.pull_request_cold_hold_queue.lock().push(pull_request);
+    }
+
+    fn check_cold_data_pull_request(&self) {
+        let now = self.clock.now_millis();
+        let hold_millis = self.broker_config.cold_pull_suspend_millis;
+        let expired = {
+            let mut queue = self.pull_request_cold_hold_queue.lock();
+            let (expired, held): (Vec<_>, Vec<_>) = queue
+                .drain(..)
+                .partition(|request| now >= request.suspend_timestamp() + hold_millis);
+            *queue = held;
+            expired
+        };
+        for mut request in expired {
+            request
+                .request_command_mut()
+                .add_ext_field(NO_SUSPEND_KEY, "1");
+            let pull_message_this = self.pull_message_processor.clone();
+            self.pull_message_processor.execute_request_when_wakeup(
+                pull_message_this,
+                request.client_channel().clone(),
+                request.connection_handler_context().clone(),
+                request.request_command().clone(),
+            );
+        }
+    }
+}
@@ -0,0 +1,212 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::ParseConfigFile;
+use rocketmq_remoting::remoting_server::server::RocketMQServer;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
+use tokio::runtime::Handle;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+use crate::broker_runtime::BrokerRuntime;
+use crate::broker_runtime::DefaultBrokerRequestProcessor;
+use crate::container::broker_container_config::BrokerContainerConfig;
+use crate::container::broker_container_processor::BrokerContainerProcessor;
+use crate::error::BrokerError;
+
+/// Hosts several brokers in one process. The brokers share the remoting server of the
+/// container and the tokio runtime it is started on, while each keeps its store and metadata
+/// under a root of its own. Requests are handed to the broker named by their `brokerName`
+/// field.
+#[derive(Clone)]
+pub struct BrokerContainer {
+    container_config: Arc<BrokerContainerConfig>,
+    /// Hosted brokers by broker name.
+    brokers: Arc<Mutex<HashMap<CheetahString, BrokerRuntime>>>,
+}
+
+impl BrokerContainer {
+    pub fn new(container_config: BrokerContainerConfig) -> Self {
+        Self {
+            container_config: Arc::new(container_config),
+            brokers: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Starts the brokers of `broker_config_paths`, then the remoting server. Returns `false`
+    /// if one of the brokers failed to start, the others keep running.
+    pub async fn start(&self) -> bool {
+        let mut started = true;
+        for config_path in &self.container_config.broker_config_paths {
+            if let Err(e) = self.add_broker_from_file(config_path).await {
+                error!(
+                    "Start broker of {} in container failed: {}",
+                    config_path.display(),
+                    e
+                );
+                started = false;
+            }
+        }
+        let server = RocketMQServer::new(Arc::new(self.container_config.server_config()));
+        let processor = BrokerContainerProcessor::new(self.clone());
+        tokio::spawn(async move { server.run(processor).await });
+        info!(
+            "Broker container started on port {} with brokers {:?}",
+            self.container_config.listen_port,
+            self.broker_names()
+        );
+        started
+    }
+
+    /// Stops every hosted broker.
+    pub fn shutdown(&self) {
+        let brokers = self.brokers.lock().drain().collect::<Vec<_>>();
+        for (broker_name, mut broker) in brokers {
+            broker.shutdown();
+            info!("Broker {} in container shut down", broker_name);
+        }
+    }
+
+    pub fn broker_names(&self) -> Vec<CheetahString> {
+        let mut broker_names = self.brokers.lock().keys().cloned().collect::<Vec<_>>();
+        broker_names.sort();
+        broker_names
+    }
+
+    /// Starts the broker configured by the TOML file `config_path`, which holds both the
+    /// broker and the message store items.
+    pub(crate) async fn add_broker_from_file(&self, config_path: &Path) -> crate::Result<()> {
+        let parse_error = |e: anyhow::Error| {
+            BrokerError::SystemError(format!(
+                "parse broker config {} failed: {}",
+                config_path.display(),
+                e
+            ))
+        };
+        let broker_config =
+            ParseConfigFile::parse_config_file_over_default::<BrokerConfig>(config_path)
+                .map_err(parse_error)?;
+        let message_store_config =
+            ParseConfigFile::parse_config_file_over_default::<MessageStoreConfig>(config_path)
+                .map_err(parse_error)?;
+        self.add_broker(broker_config, message_store_config).await
+    }
+
+    pub(crate) async fn add_broker(
+        &self,
+        broker_config: BrokerConfig,
+        message_store_config: MessageStoreConfig,
+    ) -> crate::Result<()> {
+        let broker_name = broker_config.broker_identity.broker_name.clone();
+        if self.brokers.lock().contains_key(&broker_name) {
+            return Err(BrokerError::SystemError(format!(
+                "broker {} already exists in the container",
+                broker_name
+            )));
+        }
+        let mut broker =
+            BrokerRuntime::new_in_container(broker_config, message_store_config, Handle::current());
+        if !broker.initialize().await {
+            shutdown_off_runtime(broker).await;
+            return Err(BrokerError::SystemError(format!(
+                "initialize broker {} failed",
+                broker_name
+            )));
+        }
+        broker.start().await;
+
+        let duplicate = {
+            let mut brokers = self.brokers.lock();
+            if brokers.contains_key(&broker_name) {
+                Some(broker)
+            } else {
+                brokers.insert(broker_name.clone(), broker);
+                None
+            }
+        };
+        if let Some(broker) = duplicate {
+            shutdown_off_runtime(broker).await;
+            return Err(BrokerError::SystemError(format!(
+                "broker {} already exists in the container",
+                broker_name
+            )));
+        }
+        info!("Broker {} added to the container", broker_name);
+        Ok(())
+    }
+
+    /// Stops the broker `broker_name` if its cluster and id match, the other brokers keep
+    /// serving.
+    pub(crate) async fn remove_broker(
+        &self,
+        broker_cluster_name: &str,
+        broker_name: &str,
+        broker_id: u64,
+    ) -> crate::Result<()> {
+        let broker = {
+            let mut brokers = self.brokers.lock();
+            match brokers.get(broker_name) {
+                Some(broker)
+                    if broker.broker_config().broker_identity.broker_cluster_name
+                        == broker_cluster_name
+                        && broker.broker_config().broker_identity.broker_id == broker_id =>
+                {
+                    brokers.remove(broker_name)
+                }
+                _ => None,
+            }
+        };
+        let Some(broker) = broker else {
+            return Err(BrokerError::SystemError(format!(
+                "broker {}:{}:{} not found in the container",
+                broker_cluster_name, broker_name, broker_id
+            )));
+        };
+        shutdown_off_runtime(broker).await;
+        info!("Broker {} removed from the container", broker_name);
+        Ok(())
+    }
+
+    /// The processor of the broker a request is addressed to, a container hosting a single
+    /// broker also takes requests naming no broker.
+    pub(crate) fn request_processor(
+        &self,
+        broker_name: Option<&str>,
+    ) -> Option<DefaultBrokerRequestProcessor> {
+        let brokers = self.brokers.lock();
+        let broker = match broker_name {
+            Some(broker_name) => brokers.get(broker_name),
+            None if brokers.len() == 1 => brokers.values().next(),
+            None => None,
+        };
+        broker.and_then(BrokerRuntime::container_request_processor)
+    }
+}
+
+/// Shutting a broker down waits for its tasks, which must not block the runtime they run on.
+async fn shutdown_off_runtime(mut broker: BrokerRuntime) {
+    if let Err(e) = tokio::task::spawn_blocking(move || broker.shutdown()).await {
+        warn!("Shut down broker in container failed: {}", e);
+    }
+}
@@ -0,0 +1,52 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::path::PathBuf;
+
+use rocketmq_common::common::server::config::ServerConfig;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Configuration of a [`BrokerContainer`](crate::BrokerContainer), the brokers it hosts are
+/// configured by files of their own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrokerContainerConfig {
+    /// Port of the remoting server shared by the hosted brokers.
+    pub listen_port: u32,
+    pub bind_address: String,
+    /// Broker config files, every file holds the broker and message store items of one broker.
+    pub broker_config_paths: Vec<PathBuf>,
+}
+
+impl Default for BrokerContainerConfig {
+    fn default() -> Self {
+        Self {
+            listen_port: 10811,
+            bind_address: "0.0.0.0".to_string(),
+            broker_config_paths: Vec::new(),
+        }
+    }
+}
+
+impl BrokerContainerConfig {
+    pub fn server_config(&self) -> ServerConfig {
+        ServerConfig {
+            listen_port: self.listen_port,
+            bind_address: self.bind_address.clone(),
+        }
+    }
+}
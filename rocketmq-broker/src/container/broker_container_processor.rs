@@ -0,0 +1,141 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::header::broker::remove_broker_request_header::RemoveBrokerRequestHeader;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::rpc::rpc_request_header::RpcRequestHeader;
+use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
+use rocketmq_remoting::runtime::processor::RequestProcessor;
+use rocketmq_remoting::Result;
+use tracing::warn;
+
+use crate::container::broker_container::BrokerContainer;
+
+/// Serves the remoting server of a container: adds and removes brokers, and hands every other
+/// request to the broker it names.
+#[derive(Clone)]
+pub(crate) struct BrokerContainerProcessor {
+    container: BrokerContainer,
+}
+
+impl BrokerContainerProcessor {
+    pub fn new(container: BrokerContainer) -> Self {
+        Self { container }
+    }
+
+    /// ADD_BROKER carries the path of the broker config file in its body.
+    async fn add_broker(&self, request: &RemotingCommand) -> RemotingCommand {
+        let config_path = request
+            .get_body()
+            .map(|body| String::from_utf8_lossy(body).trim().to_string())
+            .filter(|path| !path.is_empty());
+        let Some(config_path) = config_path else {
+            return RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::SystemError,
+                "the broker config path is required",
+            );
+        };
+        match self
+            .container
+            .add_broker_from_file(&PathBuf::from(config_path))
+            .await
+        {
+            Ok(()) => RemotingCommand::create_response_command(),
+            Err(e) => {
+                warn!("Add broker to container failed: {}", e);
+                RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::SystemError,
+                    e.to_string(),
+                )
+            }
+        }
+    }
+
+    async fn remove_broker(&self, request: &RemotingCommand) -> Option<RemotingCommand> {
+        let request_header = request.decode_command_custom_header::<RemoveBrokerRequestHeader>()?;
+        let response = match self
+            .container
+            .remove_broker(
+                &request_header.broker_cluster_name,
+                &request_header.broker_name,
+                request_header.broker_id,
+            )
+            .await
+        {
+            Ok(()) => RemotingCommand::create_response_command(),
+            Err(e) => {
+                warn!("Remove broker from container failed: {}", e);
+                RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::SystemError,
+                    e.to_string(),
+                )
+            }
+        };
+        Some(response)
+    }
+}
+
+impl RequestProcessor for BrokerContainerProcessor {
+    async fn process_request(
+        &mut self,
+        channel: Channel,
+        ctx: ConnectionHandlerContext,
+        request: RemotingCommand,
+    ) -> Result<Option<RemotingCommand>> {
+        match RequestCode::from(request.code()) {
+            RequestCode::AddBroker => Ok(Some(self.add_broker(&request).await)),
+            RequestCode::RemoveBroker => Ok(self.remove_broker(&request).await),
+            _ => {
+                let broker_name = request
+                    .get_ext_fields()
+                    .and_then(|fields| fields.get(RpcRequestHeader::BROKER_NAME))
+                    .map(|broker_name| broker_name.to_string());
+                let Some(processor) = self.container.request_processor(broker_name.as_deref())
+                else {
+                    return Ok(Some(
+                        RemotingCommand::create_response_command_with_code_remark(
+                            ResponseCode::SystemError,
+                            format!(
+                                "broker {} not found in the container",
+                                broker_name.as_deref().unwrap_or_default()
+                            ),
+                        ),
+                    ));
+                };
+                dispatch(processor, channel, ctx, request).await
+            }
+        }
+    }
+}
+
+/// Boxed inside a generic function, so the future is `Send` by the bounds of
+/// [`RequestProcessor`]: rustc cannot prove it for the boxed message store of the concrete
+/// processor once the future is held by another one.
+fn dispatch<P: RequestProcessor + 'static>(
+    mut processor: P,
+    channel: Channel,
+    ctx: ConnectionHandlerContext,
+    request: RemotingCommand,
+) -> Pin<Box<dyn Future<Output = Result<Option<RemotingCommand>>> + Send>> {
+    Box::pin(async move { processor.process_request(channel, ctx, request).await })
+}
@@ -14,8 +14,15 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_store::base::message_status_enum::GetMessageStatus;
+use rocketmq_store::base::message_status_enum::PutMessageStatus;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
 use thiserror::Error;
 
+/// Errors raised while serving a request. The request-level variants carry the remark sent
+/// back to the client; [`BrokerError::response_code`] gives the code that goes with it.
 #[allow(clippy::enum_variant_names)]
 #[derive(Debug, Error)]
 pub enum BrokerError {
@@ -27,4 +34,377 @@ pub enum BrokerError {
 
     #[error("Client exception occurred: CODE:{0}, broker address:{2}, Message:{1}")]
     MQBrokerError(i32, String, String),
+
+    #[error("Message store plugin error: {0}")]
+    MessageStorePluginError(String),
+
+    #[error("{0}")]
+    TopicNotExist(String),
+
+    #[error("{0}")]
+    NoPermission(String),
+
+    #[error("{0}")]
+    SubscriptionGroupNotExist(String),
+
+    #[error("{0}")]
+    SubscriptionNotExist(String),
+
+    #[error("{0}")]
+    SubscriptionParseFailed(String),
+
+    #[error("{0}")]
+    FilterDataNotExist(String),
+
+    #[error("{0}")]
+    VersionNotSupported(String),
+
+    #[error("{0}")]
+    MessageIllegal(String),
+
+    #[error("{0}")]
+    ServiceNotAvailable(String),
+
+    #[error("{0}")]
+    SystemError(String),
+
+    #[error("{0}")]
+    SystemBusy(String),
+
+    #[error("{0}")]
+    NotLeaderForQueue(String),
+
+    #[error("{0}")]
+    PullNotFound(String),
+
+    #[error("{0}")]
+    PullRetryImmediately(String),
+
+    #[error("{0}")]
+    PullOffsetMoved(String),
+
+    #[error("{0}")]
+    TransactionShouldCommit(String),
+
+    #[error("{0}")]
+    TransactionShouldRollback(String),
+
+    #[error("{0}")]
+    TransactionStateUnknown(String),
+}
+
+impl BrokerError {
+    /// The response code a client sees for this error.
+    pub fn response_code(&self) -> ResponseCode {
+        match self {
+            BrokerError::MQBrokerError(code, _, _) => ResponseCode::from(*code),
+            BrokerError::TopicNotExist(_) => ResponseCode::TopicNotExist,
+            BrokerError::NoPermission(_) => ResponseCode::NoPermission,
+            BrokerError::SubscriptionGroupNotExist(_) => ResponseCode::SubscriptionGroupNotExist,
+            BrokerError::SubscriptionNotExist(_) => ResponseCode::SubscriptionNotExist,
+            BrokerError::SubscriptionParseFailed(_) => ResponseCode::SubscriptionParseFailed,
+            BrokerError::FilterDataNotExist(_) => ResponseCode::FilterDataNotExist,
+            BrokerError::VersionNotSupported(_) => ResponseCode::VersionNotSupported,
+            BrokerError::MessageIllegal(_) => ResponseCode::MessageIllegal,
+            BrokerError::ServiceNotAvailable(_) => ResponseCode::ServiceNotAvailable,
+            BrokerError::SystemBusy(_) => ResponseCode::SystemBusy,
+            BrokerError::NotLeaderForQueue(_) => ResponseCode::NotLeaderForQueue,
+            BrokerError::PullNotFound(_) => ResponseCode::PullNotFound,
+            BrokerError::PullRetryImmediately(_) => ResponseCode::PullRetryImmediately,
+            BrokerError::PullOffsetMoved(_) => ResponseCode::PullOffsetMoved,
+            BrokerError::TransactionShouldCommit(_) => ResponseCode::TransactionShouldCommit,
+            BrokerError::TransactionShouldRollback(_) => ResponseCode::TransactionShouldRollback,
+            BrokerError::TransactionStateUnknown(_) => ResponseCode::TransactionStateUnknow,
+            BrokerError::BrokerClientError(_)
+            | BrokerError::BrokerCommonError(_)
+            | BrokerError::MessageStorePluginError(_)
+            | BrokerError::SystemError(_) => ResponseCode::SystemError,
+        }
+    }
+
+    /// Writes the code and remark of this error into an already built `response`, keeping
+    /// its custom header.
+    pub fn apply_to(&self, response: &mut RemotingCommand) {
+        response
+            .set_code_mut(self.response_code())
+            .set_remark_mut(self.to_string());
+    }
+
+    /// Maps the outcome of a store write to the error reported to the producer, or `None`
+    /// when the message was stored, possibly without the requested flush or replication.
+    pub fn from_put_message_status(
+        status: PutMessageStatus,
+        message_store_config: &MessageStoreConfig,
+        disk_full: bool,
+    ) -> Option<BrokerError> {
+        let error = match status {
+            PutMessageStatus::PutOk
+            | PutMessageStatus::FlushDiskTimeout
+            | PutMessageStatus::FlushSlaveTimeout
+            | PutMessageStatus::SlaveNotAvailable => return None,
+            PutMessageStatus::ServiceNotAvailable if disk_full => {
+                BrokerError::SystemError(format!(
+                    "the broker's disk is full, used space exceeds diskMaxUsedSpaceRatio {}%, \
+                     messages can not be put until space is reclaimed",
+                    message_store_config.disk_max_used_space_ratio
+                ))
+            }
+            PutMessageStatus::ServiceNotAvailable => BrokerError::ServiceNotAvailable(
+                "service not available now. It may be caused by one of the following reasons: \
+                 messages are put to the slave, message store has been shut down, etc."
+                    .to_string(),
+            ),
+            PutMessageStatus::CreateMappedFileFailed => BrokerError::SystemError(
+                "create mapped file failed, remoting_server is busy or broken.".to_string(),
+            ),
+            PutMessageStatus::MessageIllegal | PutMessageStatus::PropertiesSizeExceeded => {
+                BrokerError::MessageIllegal(format!(
+                    "the message is illegal, maybe msg body or properties length not matched. msg \
+                     body length limit {}B, msg properties length limit 32KB.",
+                    message_store_config.max_message_size
+                ))
+            }
+            PutMessageStatus::OsPageCacheBusy => BrokerError::SystemError(
+                "[PC_SYNCHRONIZED]broker busy, start flow control for a while".to_string(),
+            ),
+            PutMessageStatus::UnknownError => BrokerError::SystemError("UNKNOWN_ERROR".to_string()),
+            PutMessageStatus::InSyncReplicasNotEnough => {
+                BrokerError::SystemError("in-sync replicas not enough".to_string())
+            }
+            PutMessageStatus::PutToRemoteBrokerFail => {
+                BrokerError::SystemError("put to remote broker failed".to_string())
+            }
+            PutMessageStatus::LmqConsumeQueueNumExceeded => BrokerError::SystemError(
+                "[LMQ_CONSUME_QUEUE_NUM_EXCEEDED]broker config enableLmq and enableMultiDispatch, \
+                 lmq consumeQueue num exceed maxLmqConsumeQueueNum config num, default limit 2w."
+                    .to_string(),
+            ),
+            PutMessageStatus::WheelTimerFlowControl => BrokerError::SystemError(format!(
+                "timer message is under flow control, max num limit is {} or the current value is \
+                 greater than {} and less than {}, trigger random flow control",
+                message_store_config.timer_congest_num_each_slot * 2,
+                message_store_config.timer_congest_num_each_slot,
+                message_store_config.timer_congest_num_each_slot * 2,
+            )),
+            PutMessageStatus::WheelTimerMsgIllegal => BrokerError::MessageIllegal(format!(
+                "timer message illegal, the delay time should not be bigger than the max delay \
+                 {}ms; or if set del msg, the delay time should be bigger than the current time",
+                message_store_config.timer_max_delay_sec * 1000
+            )),
+            PutMessageStatus::WheelTimerNotEnable => BrokerError::SystemError(format!(
+                "accurate timer message is not enabled, timerWheelEnable is {}",
+                message_store_config.timer_wheel_enable
+            )),
+        };
+        Some(error)
+    }
+
+    /// Maps the outcome of a store read to the pull result code the consumer acts on, or
+    /// `None` when messages were found. `request_offset` is the offset the consumer asked for.
+    pub fn from_get_message_status(
+        status: GetMessageStatus,
+        request_offset: i64,
+    ) -> Option<BrokerError> {
+        let remark = status.to_string();
+        let error = match status {
+            GetMessageStatus::Found => return None,
+            GetMessageStatus::MessageWasRemoving | GetMessageStatus::NoMatchedMessage => {
+                BrokerError::PullRetryImmediately(remark)
+            }
+            GetMessageStatus::NoMatchedLogicQueue | GetMessageStatus::NoMessageInQueue
+                if request_offset != 0 =>
+            {
+                BrokerError::PullOffsetMoved(remark)
+            }
+            GetMessageStatus::NoMatchedLogicQueue
+            | GetMessageStatus::NoMessageInQueue
+            | GetMessageStatus::OffsetFoundNull
+            | GetMessageStatus::OffsetOverflowOne => BrokerError::PullNotFound(remark),
+            GetMessageStatus::OffsetOverflowBadly
+            | GetMessageStatus::OffsetReset
+            | GetMessageStatus::OffsetTooSmall => BrokerError::PullOffsetMoved(remark),
+        };
+        Some(error)
+    }
+}
+
+impl From<BrokerError> for RemotingCommand {
+    fn from(error: BrokerError) -> Self {
+        RemotingCommand::create_response_command_with_code_remark(
+            error.response_code(),
+            error.to_string(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code_and_remark(error: BrokerError) -> (i32, String) {
+        let response = RemotingCommand::from(error);
+        (
+            response.code(),
+            response.remark().map(|remark| remark.to_string()).unwrap(),
+        )
+    }
+
+    #[test]
+    fn request_errors_become_responses_with_their_code_and_remark() {
+        let cases = [
+            (
+                BrokerError::TopicNotExist("topic[T] not exist".to_string()),
+                ResponseCode::TopicNotExist,
+            ),
+            (
+                BrokerError::NoPermission("the topic[T] pulling message is forbidden".to_string()),
+                ResponseCode::NoPermission,
+            ),
+            (
+                BrokerError::SubscriptionGroupNotExist("subscription group not exist".to_string()),
+                ResponseCode::SubscriptionGroupNotExist,
+            ),
+            (
+                BrokerError::VersionNotSupported("please upgrade the client".to_string()),
+                ResponseCode::VersionNotSupported,
+            ),
+            (
+                BrokerError::PullOffsetMoved("OffsetTooSmall".to_string()),
+                ResponseCode::PullOffsetMoved,
+            ),
+            (
+                BrokerError::TransactionShouldCommit("commit it".to_string()),
+                ResponseCode::TransactionShouldCommit,
+            ),
+            (
+                BrokerError::TransactionStateUnknown("unknown".to_string()),
+                ResponseCode::TransactionStateUnknow,
+            ),
+            (
+                BrokerError::MessageStorePluginError("no plugin".to_string()),
+                ResponseCode::SystemError,
+            ),
+        ];
+        for (error, code) in cases {
+            let remark = error.to_string();
+            assert_eq!(code_and_remark(error), (code as i32, remark));
+        }
+        assert_eq!(
+            code_and_remark(BrokerError::TopicNotExist("topic[T] not exist".to_string())).1,
+            "topic[T] not exist"
+        );
+    }
+
+    #[test]
+    fn apply_to_keeps_the_opaque() {
+        let mut response = RemotingCommand::create_response_command().set_opaque(42);
+        BrokerError::NoPermission("forbidden".to_string()).apply_to(&mut response);
+        assert_eq!(response.opaque(), 42);
+        assert_eq!(response.code(), ResponseCode::NoPermission as i32);
+        assert_eq!(response.remark().unwrap().as_str(), "forbidden");
+    }
+
+    #[test]
+    fn put_message_status_conversion() {
+        let config = MessageStoreConfig::default();
+        for status in [
+            PutMessageStatus::PutOk,
+            PutMessageStatus::FlushDiskTimeout,
+            PutMessageStatus::FlushSlaveTimeout,
+            PutMessageStatus::SlaveNotAvailable,
+        ] {
+            assert!(BrokerError::from_put_message_status(status, &config, false).is_none());
+        }
+
+        let illegal =
+            BrokerError::from_put_message_status(PutMessageStatus::MessageIllegal, &config, false)
+                .unwrap();
+        assert_eq!(
+            code_and_remark(illegal),
+            (
+                ResponseCode::MessageIllegal as i32,
+                format!(
+                    "the message is illegal, maybe msg body or properties length not matched. msg \
+                     body length limit {}B, msg properties length limit 32KB.",
+                    config.max_message_size
+                )
+            )
+        );
+
+        let not_available = BrokerError::from_put_message_status(
+            PutMessageStatus::ServiceNotAvailable,
+            &config,
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            not_available.response_code(),
+            ResponseCode::ServiceNotAvailable
+        );
+        let disk_full = BrokerError::from_put_message_status(
+            PutMessageStatus::ServiceNotAvailable,
+            &config,
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            code_and_remark(disk_full),
+            (
+                ResponseCode::SystemError as i32,
+                format!(
+                    "the broker's disk is full, used space exceeds diskMaxUsedSpaceRatio {}%, \
+                     messages can not be put until space is reclaimed",
+                    config.disk_max_used_space_ratio
+                )
+            )
+        );
+
+        let page_cache_busy =
+            BrokerError::from_put_message_status(PutMessageStatus::OsPageCacheBusy, &config, false)
+                .unwrap();
+        assert_eq!(
+            code_and_remark(page_cache_busy),
+            (
+                ResponseCode::SystemError as i32,
+                "[PC_SYNCHRONIZED]broker busy, start flow control for a while".to_string()
+            )
+        );
+        let timer_illegal = BrokerError::from_put_message_status(
+            PutMessageStatus::WheelTimerMsgIllegal,
+            &config,
+            false,
+        )
+        .unwrap();
+        assert_eq!(timer_illegal.response_code(), ResponseCode::MessageIllegal);
+    }
+
+    #[test]
+    fn get_message_status_conversion() {
+        let code = |status, offset| {
+            BrokerError::from_get_message_status(status, offset).map(|error| error.response_code())
+        };
+        assert_eq!(code(GetMessageStatus::Found, 5), None);
+        assert_eq!(
+            code(GetMessageStatus::NoMatchedMessage, 5),
+            Some(ResponseCode::PullRetryImmediately)
+        );
+        assert_eq!(
+            code(GetMessageStatus::NoMessageInQueue, 0),
+            Some(ResponseCode::PullNotFound)
+        );
+        assert_eq!(
+            code(GetMessageStatus::NoMessageInQueue, 5),
+            Some(ResponseCode::PullOffsetMoved)
+        );
+        assert_eq!(
+            code(GetMessageStatus::OffsetOverflowOne, 5),
+            Some(ResponseCode::PullNotFound)
+        );
+        assert_eq!(
+            code(GetMessageStatus::OffsetTooSmall, 5),
+            Some(ResponseCode::PullOffsetMoved)
+        );
+        let error = BrokerError::from_get_message_status(GetMessageStatus::OffsetReset, 5).unwrap();
+        assert_eq!(error.to_string(), "OffsetReset");
+    }
 }
@@ -0,0 +1,265 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_filter::utils::bits_array::BitsArray;
+use rocketmq_store::base::commit_log_dispatcher::CommitLogDispatcher;
+use rocketmq_store::base::dispatch_request::DispatchRequest;
+use tracing::error;
+use tracing::warn;
+
+use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
+use crate::filter::message_evaluation_context::MessageEvaluationContext;
+
+/// Evaluates the SQL92 filters of the consumers of a topic on dispatch and hands the bloom
+/// filter bit map of the matching ones to the consume queue, registered ahead of it.
+pub(crate) struct CommitLogDispatcherCalcBitMap {
+    broker_config: Arc<BrokerConfig>,
+    consumer_filter_manager: Arc<ConsumerFilterManager>,
+}
+
+impl CommitLogDispatcherCalcBitMap {
+    pub fn new(
+        broker_config: Arc<BrokerConfig>,
+        consumer_filter_manager: Arc<ConsumerFilterManager>,
+    ) -> Self {
+        CommitLogDispatcherCalcBitMap {
+            broker_config,
+            consumer_filter_manager,
+        }
+    }
+
+    /// The bit map of the filters matching a message, `None` when no filter consumes `topic`.
+    fn calc_bit_map(
+        &self,
+        topic: &str,
+        properties: Option<&HashMap<CheetahString, CheetahString>>,
+    ) -> Option<Vec<u8>> {
+        let filter_datas = self.consumer_filter_manager.get_by_topic(topic);
+        if filter_datas.is_empty() {
+            return None;
+        }
+        let bloom_filter = self.consumer_filter_manager.get_bloom_filter()?;
+        let mut filter_bit_map = BitsArray::create(bloom_filter.m() as usize);
+        let context = MessageEvaluationContext::new(properties);
+        for filter_data in &filter_datas {
+            let Some(compiled_expression) = filter_data.compiled_expression() else {
+                error!(
+                    "[BUG] Consumer in filter manager has no compiled expression! {}",
+                    filter_data
+                );
+                continue;
+            };
+            let Some(bloom_filter_data) = filter_data.bloom_filter_data() else {
+                error!(
+                    "[BUG] Consumer in filter manager has no bloom data! {}",
+                    filter_data
+                );
+                continue;
+            };
+            let matched = match compiled_expression.evaluate(&context) {
+                Ok(ret) => ret.downcast_ref::<bool>() == Some(&true),
+                Err(e) => {
+                    error!("Calc filter bit map error! {}, {}", filter_data, e);
+                    false
+                }
+            };
+            if matched {
+                if let Err(e) = bloom_filter.hash_to(bloom_filter_data, &mut filter_bit_map) {
+                    error!("Calc filter bit map error! {}, {}", filter_data, e);
+                }
+            }
+        }
+        Some(filter_bit_map.into_bytes())
+    }
+}
+
+impl CommitLogDispatcher for CommitLogDispatcherCalcBitMap {
+    fn dispatch(&self, dispatch_request: &mut DispatchRequest) {
+        if !self.broker_config.enable_calc_filter_bit_map {
+            return;
+        }
+        let start = Instant::now();
+        let bit_map = self.calc_bit_map(
+            dispatch_request.topic.as_str(),
+            dispatch_request.properties_map.as_ref(),
+        );
+        if bit_map.is_none() {
+            return;
+        }
+        dispatch_request.bit_map = bit_map;
+        let elapsed = start.elapsed();
+        if elapsed.as_millis() >= 1 {
+            warn!(
+                "Spend {} ms to calc bit map, topic={}",
+                elapsed.as_millis(),
+                dispatch_request.topic
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+    use rocketmq_common::common::filter::expression_type::ExpressionType;
+    use rocketmq_common::TimeUtils::get_current_millis;
+    use rocketmq_remoting::protocol::heartbeat::subscription_data::SubscriptionData;
+    use rocketmq_store::consume_queue::consume_queue_ext::CqExtUnit;
+    use rocketmq_store::filter::MessageFilter;
+
+    use super::*;
+    use crate::filter::consumer_filter_data::ConsumerFilterData;
+    use crate::filter::expression_message_filter::ExpressionMessageFilter;
+
+    const TOPIC: &str = "FilterBitMapTopic";
+    const EXPRESSIONS: [&str; 5] = [
+        "a > 5",
+        "a BETWEEN 2 AND 4 AND b = 'x'",
+        "b IN ('y', 'z') OR c IS NULL",
+        "NOT (a < 8)",
+        "c = 'true' AND b <> 'z'",
+    ];
+
+    fn dispatcher(enable_calc_filter_bit_map: bool) -> CommitLogDispatcherCalcBitMap {
+        let broker_config = Arc::new(BrokerConfig {
+            enable_calc_filter_bit_map,
+            ..Default::default()
+        });
+        let consumer_filter_manager = Arc::new(ConsumerFilterManager::new(broker_config.clone()));
+        for (i, expression) in EXPRESSIONS.iter().enumerate() {
+            assert!(consumer_filter_manager.register_filter(
+                &TOPIC.into(),
+                &format!("filter_group_{}", i).into(),
+                &(*expression).into(),
+                &ExpressionType::SQL92.into(),
+                1,
+            ));
+        }
+        CommitLogDispatcherCalcBitMap::new(broker_config, consumer_filter_manager)
+    }
+
+    fn random_properties(rng: &mut impl Rng) -> HashMap<CheetahString, CheetahString> {
+        let mut properties = HashMap::new();
+        if rng.gen_bool(0.9) {
+            properties.insert("a".into(), rng.gen_range(0..10).to_string().into());
+        }
+        if rng.gen_bool(0.9) {
+            properties.insert("b".into(), ["x", "y", "z"][rng.gen_range(0..3)].into());
+        }
+        if rng.gen_bool(0.7) {
+            properties.insert("c".into(), ["true", "false"][rng.gen_range(0..2)].into());
+        }
+        properties
+    }
+
+    fn message_filter(
+        filter_data: &ConsumerFilterData,
+        consumer_filter_manager: &Arc<ConsumerFilterManager>,
+    ) -> ExpressionMessageFilter {
+        let subscription_data = SubscriptionData {
+            topic: TOPIC.into(),
+            sub_string: filter_data.expression().cloned().unwrap(),
+            expression_type: ExpressionType::SQL92.into(),
+            ..Default::default()
+        };
+        ExpressionMessageFilter::new(
+            Some(subscription_data),
+            Some(filter_data.clone()),
+            consumer_filter_manager.clone(),
+        )
+    }
+
+    #[test]
+    fn bit_map_agrees_with_direct_evaluation() {
+        let dispatcher = dispatcher(true);
+        let consumer_filter_manager = &dispatcher.consumer_filter_manager;
+        let bloom_filter = consumer_filter_manager.get_bloom_filter().unwrap();
+        let filter_datas = consumer_filter_manager.get_by_topic(TOPIC);
+        assert_eq!(filter_datas.len(), EXPRESSIONS.len());
+        let message_filters = filter_datas
+            .iter()
+            .map(|filter_data| message_filter(filter_data, consumer_filter_manager))
+            .collect::<Vec<_>>();
+
+        let mut rng = rand::thread_rng();
+        let mut skipped = 0;
+        for _ in 0..500 {
+            let properties = random_properties(&mut rng);
+            let mut request = DispatchRequest {
+                topic: TOPIC.into(),
+                store_timestamp: get_current_millis() as i64 + 1,
+                properties_map: Some(properties.clone()),
+                ..Default::default()
+            };
+            dispatcher.dispatch(&mut request);
+            let bits = BitsArray::from_bytes(request.bit_map.as_ref().unwrap());
+            assert_eq!(bits.bit_length(), bloom_filter.m() as usize);
+            let cq_ext_unit = CqExtUnit::new(0, request.store_timestamp, request.bit_map.clone());
+
+            for (filter_data, message_filter) in filter_datas.iter().zip(&message_filters) {
+                let matched = filter_data
+                    .compiled_expression()
+                    .unwrap()
+                    .evaluate(&MessageEvaluationContext::new(Some(&properties)))
+                    .unwrap()
+                    .downcast_ref::<bool>()
+                    == Some(&true);
+                let hit = bloom_filter
+                    .is_hit(filter_data.bloom_filter_data().unwrap(), &bits)
+                    .unwrap();
+                // the bit map may only let through more than the expression matches
+                assert!(
+                    hit || !matched,
+                    "{} matches {:?} but its bits are clear",
+                    filter_data,
+                    properties
+                );
+                assert_eq!(
+                    message_filter.is_matched_by_consume_queue(Some(0), Some(&cq_ext_unit)),
+                    hit
+                );
+                assert_eq!(
+                    message_filter.is_matched_by_commit_log(None, Some(&properties)),
+                    matched
+                );
+                if !hit {
+                    skipped += 1;
+                }
+            }
+        }
+        assert!(skipped > 0, "the bit map never spared an evaluation");
+    }
+
+    #[test]
+    fn bit_map_is_only_calculated_when_enabled_and_subscribed() {
+        let mut request = DispatchRequest {
+            topic: TOPIC.into(),
+            ..Default::default()
+        };
+        dispatcher(false).dispatch(&mut request);
+        assert!(request.bit_map.is_none());
+
+        request.topic = "TopicWithoutFilter".into();
+        dispatcher(true).dispatch(&mut request);
+        assert!(request.bit_map.is_none());
+    }
+}
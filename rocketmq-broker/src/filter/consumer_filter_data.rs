@@ -14,9 +14,12 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::fmt::Display;
+use std::fmt::Formatter;
 use std::sync::Arc;
 
 use cheetah_string::CheetahString;
+use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_filter::expression::Expression;
 use rocketmq_filter::utils::bloom_filter_data::BloomFilterData;
 use serde::Deserialize;
@@ -70,6 +73,29 @@ impl ConsumerFilterData {
         self.client_version
     }
 
+    pub fn compiled_expression(&self) -> Option<&Arc<Box<dyn Expression + Send + Sync + 'static>>> {
+        self.compiled_expression.as_ref()
+    }
+
+    /// Dead once the consumer unregistered after this filter was born.
+    pub fn is_dead(&self) -> bool {
+        self.dead_time >= self.born_time
+    }
+
+    /// Milliseconds since the filter died, -1 while it is alive.
+    pub fn how_long_after_death(&self) -> i64 {
+        if self.is_dead() {
+            get_current_millis() as i64 - self.dead_time as i64
+        } else {
+            -1
+        }
+    }
+
+    /// Only messages stored after the filter was born carry its bits in their filter bit map.
+    pub fn is_msg_in_live(&self, msg_store_time: u64) -> bool {
+        msg_store_time > self.born_time
+    }
+
     pub fn set_consumer_group(&mut self, consumer_group: CheetahString) {
         self.consumer_group = consumer_group;
     }
@@ -101,4 +127,30 @@ impl ConsumerFilterData {
     pub fn set_client_version(&mut self, client_version: u64) {
         self.client_version = client_version;
     }
+
+    pub fn set_compiled_expression(
+        &mut self,
+        compiled_expression: Option<Arc<Box<dyn Expression + Send + Sync + 'static>>>,
+    ) {
+        self.compiled_expression = compiled_expression;
+    }
+}
+
+impl Display for ConsumerFilterData {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ConsumerFilterData [consumerGroup={}, topic={}, expression={:?}, \
+             expressionType={:?}, bornTime={}, deadTime={}, bloomFilterData={:?}, \
+             clientVersion={}]",
+            self.consumer_group,
+            self.topic,
+            self.expression,
+            self.expression_type,
+            self.born_time,
+            self.dead_time,
+            self.bloom_filter_data,
+            self.client_version
+        )
+    }
 }
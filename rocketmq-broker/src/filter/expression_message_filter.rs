@@ -17,14 +17,19 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use bytes::Bytes;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::filter::expression_type::ExpressionType;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_filter::utils::bits_array::BitsArray;
 use rocketmq_remoting::protocol::heartbeat::subscription_data::SubscriptionData;
 use rocketmq_store::consume_queue::consume_queue_ext::CqExtUnit;
 use rocketmq_store::filter::MessageFilter;
+use tracing::error;
 
 use crate::filter::consumer_filter_data::ConsumerFilterData;
 use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
+use crate::filter::message_evaluation_context::MessageEvaluationContext;
 
 pub struct ExpressionMessageFilter {
     subscription_data: Option<SubscriptionData>,
@@ -56,7 +61,6 @@ impl ExpressionMessageFilter {
     }
 }
 
-#[allow(unused_variables)]
 impl MessageFilter for ExpressionMessageFilter {
     fn is_matched_by_consume_queue(
         &self,
@@ -81,7 +85,38 @@ impl MessageFilter for ExpressionMessageFilter {
                 .code_set
                 .contains(&(tags_code.unwrap() as i32))
         } else {
-            unimplemented!("SQL92 expression type is not supported yet.")
+            let Some(consumer_filter_data) = self.consumer_filter_data.as_ref() else {
+                return true;
+            };
+            let Some(bloom_filter_data) = consumer_filter_data.bloom_filter_data() else {
+                return true;
+            };
+            if consumer_filter_data.expression().is_none()
+                || consumer_filter_data.compiled_expression().is_none()
+            {
+                return true;
+            }
+            // messages stored before the filter was born carry no bits of it
+            let Some(cq_ext_unit) = cq_ext_unit else {
+                return true;
+            };
+            if !consumer_filter_data.is_msg_in_live(cq_ext_unit.msg_store_time() as u64) {
+                return true;
+            }
+            let Some(filter_bit_map) = cq_ext_unit.filter_bit_map().as_ref() else {
+                return true;
+            };
+            let Some(bloom_filter) = self.consumer_filter_manager.get_bloom_filter() else {
+                return true;
+            };
+            if !self.bloom_data_valid
+                || filter_bit_map.len() * 8 != bloom_filter_data.bit_num() as usize
+            {
+                return true;
+            }
+            bloom_filter
+                .is_hit(bloom_filter_data, &BitsArray::from_bytes(filter_bit_map))
+                .unwrap_or(true)
         }
     }
 
@@ -107,6 +142,34 @@ impl MessageFilter for ExpressionMessageFilter {
         if real_filter_data.expression().is_none() || real_filter_data.expression_type().is_none() {
             return true;
         }
-        unimplemented!("SQL92 expression type is not supported yet.")
+        let Some(compiled_expression) = real_filter_data.compiled_expression() else {
+            return true;
+        };
+        let decoded;
+        let properties = match (properties, msg_buffer) {
+            (Some(properties), _) => Some(properties),
+            (None, Some(msg_buffer)) => {
+                decoded = message_decoder::decode(
+                    &mut Bytes::copy_from_slice(msg_buffer),
+                    false,
+                    false,
+                    false,
+                    false,
+                    false,
+                );
+                decoded.as_ref().map(|msg| msg.properties())
+            }
+            (None, None) => None,
+        };
+        match compiled_expression.evaluate(&MessageEvaluationContext::new(properties)) {
+            Ok(ret) => ret.downcast_ref::<bool>() == Some(&true),
+            Err(e) => {
+                error!(
+                    "Message Filter error, {}, {:?}: {}",
+                    real_filter_data, properties, e
+                );
+                false
+            }
+        }
     }
 }
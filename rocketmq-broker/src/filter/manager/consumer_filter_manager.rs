@@ -14,7 +14,7 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -22,22 +22,37 @@ use cheetah_string::CheetahString;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
 use rocketmq_common::common::config_manager::ConfigManager;
 use rocketmq_common::common::filter::expression_type::ExpressionType;
+use rocketmq_common::utils::serde_json_utils::SerdeJsonUtils;
 use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_filter::filter_factory::FilterFactory;
 use rocketmq_filter::utils::bloom_filter::BloomFilter;
+use rocketmq_remoting::protocol::heartbeat::subscription_data::SubscriptionData;
+use rocketmq_remoting::protocol::RemotingSerializable;
+use tracing::error;
+use tracing::info;
 
 use crate::broker_path_config_helper::get_consumer_filter_path;
 use crate::filter::consumer_filter_data::ConsumerFilterData;
 use crate::filter::manager::consumer_filter_wrapper::ConsumerFilterWrapper;
+use crate::filter::manager::consumer_filter_wrapper::FilterDataMapByTopic;
 
-const MS_24_HOUR: u64 = Duration::from_hours(24).as_millis() as u64;
+/// Consumers loaded from disk are taken as dead this long before the broker started.
+const LOAD_DEAD_TIME_BACKOFF: u64 = Duration::from_secs(30).as_millis() as u64;
 
-#[derive(Default)]
+/// Keeps the SQL92 filters of the consumer groups, each with the bloom filter bits its matches
+/// set in the filter bit map calculated on dispatch.
 pub(crate) struct ConsumerFilterManager {
     broker_config: Arc<BrokerConfig>,
     consumer_filter_wrapper: Arc<parking_lot::RwLock<ConsumerFilterWrapper>>,
     bloom_filter: Option<BloomFilter>,
 }
 
+impl Default for ConsumerFilterManager {
+    fn default() -> Self {
+        Self::new(Arc::new(BrokerConfig::default()))
+    }
+}
+
 impl ConsumerFilterManager {
     pub fn new(mut broker_config: Arc<BrokerConfig>) -> Self {
         let consumer_filter_wrapper =
@@ -57,34 +72,60 @@ impl ConsumerFilterManager {
     }
 }
 
-//Fully implemented will be removed
-#[allow(unused_variables)]
 impl ConfigManager for ConsumerFilterManager {
-    fn decode0(&mut self, key: &[u8], body: &[u8]) {
-        todo!()
-    }
-
-    fn stop(&mut self) -> bool {
-        todo!()
-    }
-
     fn config_file_path(&self) -> String {
         get_consumer_filter_path(self.broker_config.store_path_root_dir.as_str())
     }
 
-    fn encode(&mut self) -> String {
-        todo!()
-    }
-
     fn encode_pretty(&self, pretty_format: bool) -> String {
-        "".to_string()
+        self.clean();
+        let wrapper = self.consumer_filter_wrapper.read();
+        if pretty_format {
+            wrapper.to_json_pretty()
+        } else {
+            wrapper.to_json()
+        }
     }
 
-    fn decode(&self, json_string: &str) {}
+    fn decode(&self, json_string: &str) {
+        if json_string.is_empty() {
+            return;
+        }
+        let mut wrapper = match SerdeJsonUtils::from_json_str::<ConsumerFilterWrapper>(json_string)
+        {
+            Ok(wrapper) => wrapper,
+            Err(e) => {
+                error!("decode consumer filter data failed: {:?}", e);
+                return;
+            }
+        };
+        let now = get_current_millis();
+        for data_map in wrapper.filter_data_by_topic_mut().values_mut() {
+            for filter_data in data_map.filter_data_map_mut().values_mut() {
+                compile(filter_data);
+                // the bits of another bloom filter mean nothing in the bit maps to come
+                if !self.is_valid_bloom_filter_data(filter_data) {
+                    info!(
+                        "Bloom filter is changed! So ignore all filter data persisted! {}",
+                        filter_data
+                    );
+                    return;
+                }
+                info!("load exist consumer filter data: {}", filter_data);
+                if filter_data.dead_time() == 0 {
+                    // no consumer is registered right after a restart
+                    let dead_time = now.saturating_sub(LOAD_DEAD_TIME_BACKOFF);
+                    filter_data.set_dead_time(dead_time.max(filter_data.born_time()));
+                }
+            }
+        }
+        *self.consumer_filter_wrapper.write() = wrapper;
+    }
 }
 
-#[allow(unused_variables)]
 impl ConsumerFilterManager {
+    /// Builds and compiles the filter data of a SQL92 subscription, `None` for tag
+    /// subscriptions and expressions that do not compile.
     pub fn build(
         topic: CheetahString,
         consumer_group: CheetahString,
@@ -104,22 +145,93 @@ impl ConsumerFilterManager {
         consumer_filter_data.set_expression(expression);
         consumer_filter_data.set_expression_type(type_);
         consumer_filter_data.set_client_version(client_version);
+        if compile(&mut consumer_filter_data) {
+            Some(consumer_filter_data)
+        } else {
+            None
+        }
+    }
 
-        /*        let filter_factory = FilterFactory;
-                match filter_factory.get(&type_).compile(&expression) {
-                    Ok(compiled_expression) => {
-                        consumer_filter_data.set_compiled_expression(compiled_expression);
-                    }
-                    Err(e) => {
-                        eprintln!(
-                            "parse error: expr={}, topic={}, group={}, error={}",
-                            expression, topic, consumer_group, e
-                        );
-                        return None;
-                    }
+    /// Registers the filters of every subscription of `consumer_group`, the filters of topics
+    /// it no longer subscribes die.
+    pub fn register(
+        &self,
+        consumer_group: &CheetahString,
+        sub_list: &HashSet<SubscriptionData>,
+    ) -> bool {
+        for subscription_data in sub_list {
+            self.register_filter(
+                &subscription_data.topic,
+                consumer_group,
+                &subscription_data.sub_string,
+                &subscription_data.expression_type,
+                subscription_data.sub_version as u64,
+            );
+        }
+
+        let now = get_current_millis();
+        let mut wrapper = self.consumer_filter_wrapper.write();
+        for (topic, data_map) in wrapper.filter_data_by_topic_mut().iter_mut() {
+            if sub_list
+                .iter()
+                .any(|subscription_data| subscription_data.topic.as_str() == topic)
+            {
+                continue;
+            }
+            if let Some(filter_data) = data_map
+                .filter_data_map_mut()
+                .get_mut(consumer_group.as_str())
+            {
+                if !filter_data.is_dead() {
+                    filter_data.set_dead_time(now);
+                    info!(
+                        "Consumer filter changed: {}, make illegal topic dead:{}",
+                        consumer_group, filter_data
+                    );
                 }
-        */
-        Some(consumer_filter_data)
+            }
+        }
+        true
+    }
+
+    pub fn register_filter(
+        &self,
+        topic: &CheetahString,
+        consumer_group: &CheetahString,
+        expression: &CheetahString,
+        type_: &CheetahString,
+        client_version: u64,
+    ) -> bool {
+        if ExpressionType::is_tag_type(Some(type_.as_str())) || expression.is_empty() {
+            return false;
+        }
+        let Some(bloom_filter) = self.bloom_filter.as_ref() else {
+            return false;
+        };
+        let bloom_filter_data = bloom_filter.generate(&format!("{}#{}", consumer_group, topic));
+        self.consumer_filter_wrapper
+            .write()
+            .filter_data_by_topic_mut()
+            .entry(topic.to_string())
+            .or_insert_with(|| FilterDataMapByTopic::new(topic.as_str()))
+            .register(
+                consumer_group,
+                expression,
+                type_,
+                bloom_filter_data,
+                client_version,
+            )
+    }
+
+    pub fn unregister(&self, consumer_group: &str) {
+        for data_map in self
+            .consumer_filter_wrapper
+            .write()
+            .filter_data_by_topic_mut()
+            .values_mut()
+        {
+            data_map.unregister(consumer_group);
+        }
     }
 
     pub fn get_consumer_filter_data(
@@ -127,10 +239,192 @@ impl ConsumerFilterManager {
         topic: &CheetahString,
         consumer_group: &CheetahString,
     ) -> Option<ConsumerFilterData> {
-        None
+        self.consumer_filter_wrapper
+            .read()
+            .filter_data_by_topic()
+            .get(topic.as_str())?
+            .filter_data_map()
+            .get(consumer_group.as_str())
+            .cloned()
+    }
+
+    /// The filters of every consumer group subscribing `topic`.
+    pub fn get_by_topic(&self, topic: &str) -> Vec<ConsumerFilterData> {
+        self.consumer_filter_wrapper
+            .read()
+            .filter_data_by_topic()
+            .get(topic)
+            .map(|data_map| data_map.filter_data_map().values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Removes the filters dead for longer than `filter_data_clean_time_span` and the topics
+    /// left without any.
+    pub fn clean(&self) {
+        let clean_time_span = self.broker_config.filter_data_clean_time_span as i64;
+        self.consumer_filter_wrapper
+            .write()
+            .filter_data_by_topic_mut()
+            .retain(|topic, data_map| {
+                data_map.filter_data_map_mut().retain(|_, filter_data| {
+                    let expired = filter_data.how_long_after_death() >= clean_time_span;
+                    if expired {
+                        info!("Remove filter consumer {}, died too long!", filter_data);
+                    }
+                    !expired
+                });
+                if data_map.filter_data_map().is_empty() {
+                    info!("Topic has no consumer, remove it! {}", topic);
+                    return false;
+                }
+                true
+            });
     }
 
     pub fn get_bloom_filter(&self) -> Option<&BloomFilter> {
         self.bloom_filter.as_ref()
     }
+
+    fn is_valid_bloom_filter_data(&self, filter_data: &ConsumerFilterData) -> bool {
+        self.bloom_filter
+            .as_ref()
+            .is_some_and(|bloom_filter| bloom_filter.is_valid(filter_data.bloom_filter_data()))
+    }
+}
+
+fn compile(filter_data: &mut ConsumerFilterData) -> bool {
+    let compiled = match (filter_data.expression_type(), filter_data.expression()) {
+        (Some(type_), Some(expression)) => FilterFactory::compile(type_, expression),
+        _ => {
+            error!("consumer filter data without expression: {}", filter_data);
+            return false;
+        }
+    };
+    match compiled {
+        Ok(compiled) => {
+            filter_data.set_compiled_expression(Some(Arc::new(compiled)));
+            true
+        }
+        Err(e) => {
+            error!("parse error: {}, error={}", filter_data, e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use rocketmq_filter::utils::bits_array::BitsArray;
+    use rocketmq_store::consume_queue::consume_queue_ext::CqExtUnit;
+    use rocketmq_store::filter::MessageFilter;
+
+    use super::*;
+    use crate::filter::expression_message_filter::ExpressionMessageFilter;
+
+    const TOPIC: &str = "FilterManagerTopic";
+    const GROUP: &str = "filter_manager_group";
+
+    fn register(manager: &ConsumerFilterManager, expression: &str, client_version: u64) -> bool {
+        manager.register_filter(
+            &TOPIC.into(),
+            &GROUP.into(),
+            &expression.into(),
+            &ExpressionType::SQL92.into(),
+            client_version,
+        )
+    }
+
+    fn filter_data(manager: &ConsumerFilterManager) -> Option<ConsumerFilterData> {
+        manager.get_consumer_filter_data(&TOPIC.into(), &GROUP.into())
+    }
+
+    #[test]
+    fn changed_expression_is_born_again_and_skips_older_bit_maps() {
+        let manager = Arc::new(ConsumerFilterManager::default());
+        assert!(register(&manager, "a > 1", 1));
+        let first = filter_data(&manager).unwrap();
+        assert!(!register(&manager, "a > 2", 1));
+        assert!(!register(&manager, "a >", 2));
+        assert!(filter_data(&manager).is_none());
+
+        assert!(register(&manager, "a > 1", 3));
+        thread::sleep(Duration::from_millis(5));
+        assert!(register(&manager, "a > 2", 4));
+        let second = filter_data(&manager).unwrap();
+        assert_eq!(second.expression().unwrap().as_str(), "a > 2");
+        assert!(second.born_time() > first.born_time());
+        // positions follow the group and topic, never the expression
+        assert_eq!(second.bloom_filter_data(), first.bloom_filter_data());
+
+        // a bit map calculated for the old expression has none of its bits set
+        let bloom_filter = manager.get_bloom_filter().unwrap();
+        let stale_bits = BitsArray::create(bloom_filter.m() as usize).into_bytes();
+        let subscription_data = SubscriptionData {
+            topic: TOPIC.into(),
+            sub_string: "a > 2".into(),
+            expression_type: ExpressionType::SQL92.into(),
+            ..Default::default()
+        };
+        let message_filter =
+            ExpressionMessageFilter::new(Some(subscription_data), Some(second.clone()), manager);
+        let before_rebirth = CqExtUnit::new(0, second.born_time() as i64, Some(stale_bits.clone()));
+        let after_rebirth = CqExtUnit::new(0, second.born_time() as i64 + 1, Some(stale_bits));
+        assert!(message_filter.is_matched_by_consume_queue(Some(0), Some(&before_rebirth)));
+        assert!(!message_filter.is_matched_by_consume_queue(Some(0), Some(&after_rebirth)));
+    }
+
+    #[test]
+    fn dead_filters_are_cleaned_after_the_time_span() {
+        let manager = ConsumerFilterManager::new(Arc::new(BrokerConfig {
+            filter_data_clean_time_span: 0,
+            ..Default::default()
+        }));
+        assert!(register(&manager, "a > 1", 1));
+        manager.unregister(GROUP);
+        assert!(filter_data(&manager).unwrap().is_dead());
+        // the same client version registering again revives it
+        assert!(register(&manager, "a > 1", 1));
+        assert!(!filter_data(&manager).unwrap().is_dead());
+
+        // subscribing other topics only kills the filter of this one
+        let other = SubscriptionData {
+            topic: "OtherTopic".into(),
+            sub_string: "b = 'x'".into(),
+            expression_type: ExpressionType::SQL92.into(),
+            ..Default::default()
+        };
+        assert!(manager.register(&GROUP.into(), &HashSet::from([other])));
+        assert!(filter_data(&manager).unwrap().is_dead());
+
+        manager.clean();
+        assert!(filter_data(&manager).is_none());
+        assert!(manager.get_by_topic(TOPIC).is_empty());
+        assert_eq!(manager.get_by_topic("OtherTopic").len(), 1);
+    }
+
+    #[test]
+    fn filter_data_survives_persist_and_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker_config = Arc::new(BrokerConfig {
+            store_path_root_dir: dir.path().to_string_lossy().to_string().into(),
+            ..Default::default()
+        });
+        let manager = ConsumerFilterManager::new(broker_config.clone());
+        assert!(register(&manager, "a BETWEEN 1 AND 3", 1));
+        manager.persist();
+
+        let reloaded = ConsumerFilterManager::new(broker_config);
+        assert!(reloaded.load());
+        let loaded = filter_data(&reloaded).unwrap();
+        let registered = filter_data(&manager).unwrap();
+        assert_eq!(loaded.expression(), registered.expression());
+        assert_eq!(loaded.bloom_filter_data(), registered.bloom_filter_data());
+        assert!(loaded.compiled_expression().is_some());
+        // nobody is registered right after a restart
+        assert!(loaded.is_dead());
+        assert!(register(&reloaded, "a BETWEEN 1 AND 3", 1));
+        assert!(!filter_data(&reloaded).unwrap().is_dead());
+    }
 }
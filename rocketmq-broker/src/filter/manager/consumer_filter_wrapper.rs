@@ -16,10 +16,16 @@
  */
 use std::collections::HashMap;
 
+use cheetah_string::CheetahString;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_filter::utils::bloom_filter_data::BloomFilterData;
 use serde::Deserialize;
 use serde::Serialize;
+use tracing::info;
+use tracing::warn;
 
 use crate::filter::consumer_filter_data::ConsumerFilterData;
+use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
 
 #[derive(Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
@@ -27,9 +33,119 @@ pub struct ConsumerFilterWrapper {
     filter_data_by_topic: HashMap<String /* Topic */, FilterDataMapByTopic>,
 }
 
+impl ConsumerFilterWrapper {
+    pub fn filter_data_by_topic(&self) -> &HashMap<String, FilterDataMapByTopic> {
+        &self.filter_data_by_topic
+    }
+
+    pub fn filter_data_by_topic_mut(&mut self) -> &mut HashMap<String, FilterDataMapByTopic> {
+        &mut self.filter_data_by_topic
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct FilterDataMapByTopic {
     filter_data_map: HashMap<String /* consumer group */, ConsumerFilterData>,
     topic: String,
 }
+
+impl FilterDataMapByTopic {
+    pub fn new(topic: impl Into<String>) -> Self {
+        FilterDataMapByTopic {
+            filter_data_map: HashMap::new(),
+            topic: topic.into(),
+        }
+    }
+
+    pub fn filter_data_map(&self) -> &HashMap<String, ConsumerFilterData> {
+        &self.filter_data_map
+    }
+
+    pub fn filter_data_map_mut(&mut self) -> &mut HashMap<String, ConsumerFilterData> {
+        &mut self.filter_data_map
+    }
+
+    pub fn unregister(&mut self, consumer_group: &str) {
+        let Some(data) = self.filter_data_map.get_mut(consumer_group) else {
+            return;
+        };
+        if data.is_dead() {
+            return;
+        }
+        let now = get_current_millis();
+        info!("Unregister consumer filter: {}, deadTime: {}", data, now);
+        data.set_dead_time(now);
+    }
+
+    /// Registers the filter of `consumer_group`, a newer client version with a changed
+    /// expression replaces the filter so it is born again and skips older bit maps.
+    pub fn register(
+        &mut self,
+        consumer_group: &CheetahString,
+        expression: &CheetahString,
+        type_: &CheetahString,
+        bloom_filter_data: BloomFilterData,
+        client_version: u64,
+    ) -> bool {
+        if let Some(old) = self.filter_data_map.get_mut(consumer_group.as_str()) {
+            let change = old.expression() != Some(expression)
+                || old.expression_type() != Some(type_)
+                || old.bloom_filter_data() != Some(&bloom_filter_data);
+            if client_version <= old.client_version() {
+                if change {
+                    warn!(
+                        "Ignore consumer({} : {}) filter, because of version {} <= {}, but maybe \
+                         info changed!old={:?}:{:?}, ignored={}:{}",
+                        consumer_group,
+                        self.topic,
+                        client_version,
+                        old.client_version(),
+                        old.expression_type(),
+                        old.expression(),
+                        type_,
+                        expression
+                    );
+                }
+                if client_version == old.client_version() && old.is_dead() {
+                    re_alive(old);
+                    return true;
+                }
+                return false;
+            }
+            if !change {
+                old.set_client_version(client_version);
+                if old.is_dead() {
+                    re_alive(old);
+                }
+                return true;
+            }
+        }
+
+        let Some(mut consumer_filter_data) = ConsumerFilterManager::build(
+            CheetahString::from_string(self.topic.clone()),
+            consumer_group.clone(),
+            Some(expression.clone()),
+            Some(type_.clone()),
+            client_version,
+        ) else {
+            // the new expression does not compile, drop the old one and let the client report
+            self.filter_data_map.remove(consumer_group.as_str());
+            return false;
+        };
+        consumer_filter_data.set_bloom_filter_data(Some(bloom_filter_data));
+        info!("New consumer filter registered: {}", consumer_filter_data);
+        self.filter_data_map
+            .insert(consumer_group.to_string(), consumer_filter_data);
+        true
+    }
+}
+
+fn re_alive(filter_data: &mut ConsumerFilterData) {
+    let old_dead_time = filter_data.dead_time();
+    filter_data.set_dead_time(0);
+    info!(
+        "Re alive consumer filter: {}, oldDeadTime: {}",
+        filter_data, old_dead_time
+    );
+}
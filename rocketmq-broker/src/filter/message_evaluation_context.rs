@@ -0,0 +1,49 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::any::Any;
+use std::collections::HashMap;
+
+use cheetah_string::CheetahString;
+use rocketmq_filter::expression::evaluation_context::EvaluationContext;
+
+/// Evaluates filter expressions against the properties of a message.
+pub struct MessageEvaluationContext<'a> {
+    properties: Option<&'a HashMap<CheetahString, CheetahString>>,
+}
+
+impl<'a> MessageEvaluationContext<'a> {
+    pub fn new(properties: Option<&'a HashMap<CheetahString, CheetahString>>) -> Self {
+        MessageEvaluationContext { properties }
+    }
+}
+
+impl EvaluationContext for MessageEvaluationContext<'_> {
+    fn get(&self, name: &str) -> Option<&dyn Any> {
+        self.properties?.get(name).map(|value| value as &dyn Any)
+    }
+
+    fn key_values(&self) -> HashMap<String, Box<dyn Any>> {
+        self.properties
+            .map(|properties| {
+                properties
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), Box::new(value.clone()) as Box<dyn Any>))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,65 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::Arc;
+
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::base::message_result::PutMessageResult;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
+use rocketmq_store::hook::put_message_hook::PutMessageHook;
+use rocketmq_store::log_file::MessageStore;
+
+use crate::schedule::schedule_message_service::ScheduleMessageService;
+use crate::util::hook_utils::HookUtils;
+
+pub struct ScheduleMessageHook<MS> {
+    message_store: ArcMut<MS>,
+    message_store_config: Arc<MessageStoreConfig>,
+    schedule_message_service: ScheduleMessageService,
+}
+
+impl<MS: MessageStore> ScheduleMessageHook<MS> {
+    pub fn new(
+        message_store: ArcMut<MS>,
+        message_store_config: Arc<MessageStoreConfig>,
+        schedule_message_service: ScheduleMessageService,
+    ) -> Self {
+        Self {
+            message_store,
+            message_store_config,
+            schedule_message_service,
+        }
+    }
+}
+
+impl<MS: MessageStore> PutMessageHook for ScheduleMessageHook<MS> {
+    fn hook_name(&self) -> String {
+        "scheduleMessageHook".to_string()
+    }
+
+    fn execute_before_put_message(
+        &self,
+        msg: &mut MessageExtBrokerInner,
+    ) -> Option<PutMessageResult> {
+        HookUtils::handle_schedule_message(
+            self.message_store.get_timer_message_store().as_ref(),
+            &self.schedule_message_service,
+            &self.message_store_config,
+            msg,
+        )
+    }
+}
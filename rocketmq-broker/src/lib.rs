@@ -21,29 +21,38 @@
 
 pub use broker_bootstrap::BrokerBootstrap;
 pub use broker_bootstrap::Builder;
+pub use container::broker_container::BrokerContainer;
+pub use container::broker_container_config::BrokerContainerConfig;
 
 use crate::error::BrokerError;
 
 pub mod command;
 
+pub(crate) mod acl;
 pub(crate) mod broker;
 pub(crate) mod broker_bootstrap;
 pub(crate) mod broker_path_config_helper;
 pub(crate) mod broker_runtime;
 pub(crate) mod client;
 pub(crate) mod coldctr;
+pub(crate) mod container;
 pub(crate) mod controller;
 pub(crate) mod error;
 pub(crate) mod filter;
 pub(crate) mod hook;
 pub(crate) mod load_balance;
 pub(crate) mod long_polling;
+pub(crate) mod metrics;
 pub(crate) mod mqtrace;
 pub(crate) mod offset;
 pub(crate) mod out_api;
+pub(crate) mod pagecache;
+pub(crate) mod plugin;
 pub(crate) mod processor;
 pub(crate) mod schedule;
 pub(crate) mod subscription;
+#[cfg(test)]
+pub(crate) mod test_util;
 pub(crate) mod topic;
 mod transaction;
 pub(crate) mod util;
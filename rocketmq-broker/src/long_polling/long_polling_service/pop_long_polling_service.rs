@@ -0,0 +1,127 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use parking_lot::RwLock;
+use tokio::sync::Notify;
+
+/// Queue id under which a pop request waits for arrivals on any queue of a topic.
+pub const ALL_QUEUES: i32 = -1;
+
+type PollingTable = HashMap<CheetahString /* topic */, HashMap<i32, Arc<Notify>>>;
+
+/// Wakes pop requests parked while their queues were empty.
+///
+/// A pop request obtains a [`Notify`] through [`PopLongPollingService::polling`], creates its
+/// `notified()` future, re-checks the queue and then awaits. Message arrivals reported by the
+/// store wake every request polling the queue as well as the ones polling the whole topic.
+#[derive(Default)]
+pub struct PopLongPollingService {
+    polling_table: RwLock<PollingTable>,
+}
+
+impl PopLongPollingService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn polling(&self, topic: &CheetahString, queue_id: i32) -> Arc<Notify> {
+        if let Some(notify) = self
+            .polling_table
+            .read()
+            .get(topic)
+            .and_then(|queues| queues.get(&queue_id))
+        {
+            return notify.clone();
+        }
+        self.polling_table
+            .write()
+            .entry(topic.clone())
+            .or_default()
+            .entry(queue_id)
+            .or_default()
+            .clone()
+    }
+
+    /// Wakes the pop requests waiting on `topic`/`queue_id`, returns whether anyone was polling.
+    pub fn notify_message_arriving(&self, topic: &CheetahString, queue_id: i32) -> bool {
+        let table = self.polling_table.read();
+        let Some(queues) = table.get(topic) else {
+            return false;
+        };
+        let wake = |id: i32| {
+            queues
+                .get(&id)
+                .map(|notify| notify.notify_waiters())
+                .is_some()
+        };
+        let notified = wake(queue_id);
+        (queue_id != ALL_QUEUES && wake(ALL_QUEUES)) || notified
+    }
+
+    pub fn polling_num(&self) -> usize {
+        self.polling_table.read().values().map(HashMap::len).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn arrivals_wake_queue_and_topic_pollers() {
+        let service = PopLongPollingService::new();
+        let topic = CheetahString::from_static_str("pop_topic");
+
+        let queue = service.polling(&topic, 1);
+        let all = service.polling(&topic, ALL_QUEUES);
+        let other = service.polling(&topic, 2);
+        assert_eq!(service.polling_num(), 3);
+
+        let queue_waiter = queue.notified();
+        let all_waiter = all.notified();
+        let other_waiter = other.notified();
+        tokio::pin!(other_waiter);
+
+        assert!(service.notify_message_arriving(&topic, 1));
+        tokio::time::timeout(Duration::from_secs(1), queue_waiter)
+            .await
+            .expect("queue poller woken");
+        tokio::time::timeout(Duration::from_secs(1), all_waiter)
+            .await
+            .expect("topic poller woken");
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), &mut other_waiter)
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn arrivals_without_pollers_report_nothing() {
+        let service = PopLongPollingService::new();
+        let topic = CheetahString::from_static_str("pop_topic");
+        assert!(!service.notify_message_arriving(&topic, 0));
+        service.polling(&topic, 3);
+        assert!(!service.notify_message_arriving(&topic, 0));
+        assert!(service.notify_message_arriving(&topic, 3));
+    }
+}
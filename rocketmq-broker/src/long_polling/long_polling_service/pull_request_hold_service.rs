@@ -15,32 +15,44 @@
  * limitations under the License.
  */
 
+use std::any::Any;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use cheetah_string::CheetahString;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
-use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_common::common::system_clock::Clock;
+use rocketmq_common::common::system_clock::SystemClock;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::header::pull_message_response_header::PullMessageResponseHeader;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_rust::ArcMut;
+use rocketmq_rust::ServiceTask;
 use rocketmq_store::consume_queue::consume_queue_ext::CqExtUnit;
 use rocketmq_store::log_file::MessageStore;
-use tokio::sync::Notify;
 use tokio::time::Instant;
 use tracing::info;
 use tracing::warn;
 
+use crate::client::client_channel_info::ClientChannelInfo;
+use crate::client::consumer_group_event::ConsumerGroupEvent;
+use crate::client::consumer_ids_change_listener::ConsumerIdsChangeListener;
 use crate::long_polling::many_pull_request::ManyPullRequest;
 use crate::long_polling::pull_request::PullRequest;
 use crate::processor::pull_message_processor::PullMessageProcessor;
 
-const TOPIC_QUEUE_ID_SEPARATOR: &str = "@";
+type PullRequestTable = HashMap<CheetahString /* topic */, HashMap<i32, ManyPullRequest>>;
 
 pub struct PullRequestHoldService<MS> {
-    pull_request_table: Arc<parking_lot::RwLock<HashMap<String, ManyPullRequest>>>,
+    // keyed by topic first so arrivals are looked up without building a key
+    pull_request_table: Arc<parking_lot::RwLock<PullRequestTable>>,
     pull_message_processor: ArcMut<PullMessageProcessor<MS>>,
     message_store: ArcMut<MS>,
     broker_config: Arc<BrokerConfig>,
-    shutdown: Arc<Notify>,
+    service: Arc<ServiceTask>,
+    clock: Arc<dyn Clock>,
 }
 
 impl<MS> PullRequestHoldService<MS>
@@ -57,9 +69,16 @@ where
             pull_message_processor,
             message_store,
             broker_config,
-            shutdown: Arc::new(Default::default()),
+            service: Arc::new(ServiceTask::new("PullRequestHoldService")),
+            clock: Arc::new(SystemClock),
         }
     }
+
+    /// Uses `clock` to time out the held requests.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
 }
 
 #[allow(unused_variables)]
@@ -68,21 +87,17 @@ where
     MS: MessageStore + Send + Sync,
 {
     pub fn start(&mut self, this: ArcMut<Self>) {
-        tokio::spawn(async move {
-            loop {
-                let handle_future = if this.broker_config.long_polling_enable {
-                    tokio::time::sleep(tokio::time::Duration::from_secs(5))
+        self.service.start(move |context| async move {
+            info!("{} service started", context.service_name());
+            while !context.is_stopped() {
+                let interval = if this.broker_config.long_polling_enable {
+                    Duration::from_secs(5)
                 } else {
-                    tokio::time::sleep(tokio::time::Duration::from_millis(
-                        this.broker_config.short_polling_time_mills,
-                    ))
+                    Duration::from_millis(this.broker_config.short_polling_time_mills)
                 };
-                tokio::select! {
-                    _ = handle_future => {}
-                    _ = this.shutdown.notified() => {
-                        info!("PullRequestHoldService: shutdown..........");
-                        break;
-                    }
+                context.wait_for_running(interval).await;
+                if context.is_stopped() {
+                    break;
                 }
                 let instant = Instant::now();
                 this.check_hold_request();
@@ -94,35 +109,33 @@ where
                     );
                 }
             }
+            info!("{} service end", context.service_name());
         });
     }
 
     pub fn shutdown(&mut self) {
-        self.shutdown.notify_waiters();
+        self.service.shutdown(false);
     }
+
     pub fn suspend_pull_request(&self, topic: &str, queue_id: i32, mut pull_request: PullRequest) {
-        let key = build_key(topic, queue_id);
         let mut table = self.pull_request_table.write();
-        let mpr = table.entry(key).or_insert_with(ManyPullRequest::new);
+        let mpr = table
+            .entry(CheetahString::from_slice(topic))
+            .or_default()
+            .entry(queue_id)
+            .or_insert_with(ManyPullRequest::new);
         pull_request.request_command_mut().set_suspended_ref(true);
         mpr.add_pull_request(pull_request);
     }
 
     fn check_hold_request(&self) {
         let binding = self.pull_request_table.read();
-        let keys = binding.keys().cloned().collect::<Vec<String>>();
+        let keys = binding
+            .iter()
+            .flat_map(|(topic, queues)| queues.keys().map(|queue_id| (topic.clone(), *queue_id)))
+            .collect::<Vec<_>>();
         drop(binding);
-        for key in keys {
-            let key_parts: Vec<&str> = key.split(TOPIC_QUEUE_ID_SEPARATOR).collect();
-            if key_parts.len() != 2 {
-                continue;
-            }
-            let topic = CheetahString::from(key_parts[0]);
-            let queue_id = key_parts[1].parse::<i32>().unwrap();
-            /*info!(
-                "check hold request, topic: {}, queue_id: {}",
-                topic, queue_id
-            );*/
+        for (topic, queue_id) in keys {
             let max_offset = self.message_store.get_max_offset_in_queue(&topic, queue_id);
             self.notify_message_arriving(&topic, queue_id, max_offset);
         }
@@ -139,12 +152,11 @@ where
         max_offset: i64,
         tags_code: Option<i64>,
         msg_store_time: i64,
-        filter_bit_map: Option<Vec<u8>>,
+        filter_bit_map: Option<&[u8]>,
         properties: Option<&HashMap<CheetahString, CheetahString>>,
     ) {
-        let key = build_key(topic, queue_id);
-        let mut table = self.pull_request_table.write();
-        if let Some(mpr) = table.get_mut(&key) {
+        let table = self.pull_request_table.read();
+        if let Some(mpr) = table.get(topic).and_then(|queues| queues.get(&queue_id)) {
             if let Some(request_list) = mpr.clone_list_and_clear() {
                 if request_list.is_empty() {
                     return;
@@ -165,7 +177,7 @@ where
                                 Some(&CqExtUnit::new(
                                     tags_code.unwrap_or(0),
                                     msg_store_time,
-                                    filter_bit_map.clone(),
+                                    filter_bit_map.map(<[u8]>::to_vec),
                                 )),
                             );
                         let mut match_by_commit_log = match_by_consume_queue;
@@ -187,9 +199,7 @@ where
                         }
                     }
 
-                    if get_current_millis()
-                        >= (request.suspend_timestamp() + request.timeout_millis())
-                    {
+                    if request.is_timed_out(self.clock.now_millis()) {
                         let pull_message_this = self.pull_message_processor.clone();
                         self.pull_message_processor.execute_request_when_wakeup(
                             pull_message_this,
@@ -205,15 +215,75 @@ where
 
                 if !replay_list.is_empty() {
                     let mut table = self.pull_request_table.write();
-                    let mpr = table.entry(key).or_insert_with(ManyPullRequest::new);
+                    let mpr = table
+                        .entry(topic.clone())
+                        .or_default()
+                        .entry(queue_id)
+                        .or_insert_with(ManyPullRequest::new);
                     mpr.add_pull_requests(replay_list);
                 }
             }
         }
     }
 
+    /// Answers the requests held for `channel` with `PULL_NOT_FOUND` right away, the consumer
+    /// behind it is gone and must not be left waiting until the requests time out.
+    pub fn complete_pull_requests_of_channel(&self, channel: &Channel) -> usize {
+        let mut completed = Vec::new();
+        for (topic, queues) in self.pull_request_table.read().iter() {
+            for (queue_id, mpr) in queues {
+                completed.extend(
+                    mpr.remove_by_channel(channel)
+                        .into_iter()
+                        .map(|request| (topic.clone(), *queue_id, request)),
+                );
+            }
+        }
+        for (topic, queue_id, request) in &completed {
+            let response_header = PullMessageResponseHeader {
+                next_begin_offset: Some(request.pull_from_this_offset()),
+                min_offset: Some(self.message_store.get_min_offset_in_queue(topic, *queue_id)),
+                max_offset: Some(self.message_store.get_max_offset_in_queue(topic, *queue_id)),
+                ..Default::default()
+            };
+            let response = RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::PullNotFound,
+                "the consumer unregistered",
+            )
+            .set_command_custom_header(response_header);
+            self.pull_message_processor.write_response_when_wakeup(
+                request.connection_handler_context().clone(),
+                request.request_command(),
+                response,
+            );
+        }
+        if !completed.is_empty() {
+            info!(
+                "completed {} pull requests held for {}",
+                completed.len(),
+                channel.remote_address()
+            );
+        }
+        completed.len()
+    }
+
+    /// Number of the pull requests currently held.
+    pub fn held_request_count(&self) -> usize {
+        self.pull_request_table
+            .read()
+            .values()
+            .flat_map(|queues| queues.values())
+            .map(ManyPullRequest::len)
+            .sum()
+    }
+
     pub async fn notify_master_online(&self) {
-        for (_, mpr) in self.pull_request_table.read().iter() {
+        for mpr in self
+            .pull_request_table
+            .read()
+            .values()
+            .flat_map(|queues| queues.values())
+        {
             if let Some(request_list) = mpr.clone_list_and_clear() {
                 for request in request_list {
                     info!(
@@ -234,6 +304,21 @@ where
     }
 }
 
-fn build_key(topic: &str, queue_id: i32) -> String {
-    format!("{}{}{}", topic, TOPIC_QUEUE_ID_SEPARATOR, queue_id)
+impl<MS> ConsumerIdsChangeListener for ArcMut<PullRequestHoldService<MS>>
+where
+    MS: MessageStore + Send + Sync,
+{
+    fn handle(&self, event: ConsumerGroupEvent, _group: &str, args: &[&dyn Any]) {
+        if !matches!(event, ConsumerGroupEvent::ClientUnregister) {
+            return;
+        }
+        if let Some(client_channel_info) = args
+            .first()
+            .and_then(|arg| arg.downcast_ref::<ClientChannelInfo>())
+        {
+            self.complete_pull_requests_of_channel(client_channel_info.channel());
+        }
+    }
+
+    fn shutdown(&self) {}
 }
@@ -17,6 +17,7 @@
 use std::sync::Arc;
 
 use parking_lot::Mutex;
+use rocketmq_remoting::net::channel::Channel;
 
 use crate::long_polling::pull_request::PullRequest;
 
@@ -52,11 +53,25 @@ impl ManyPullRequest {
         }
     }
 
+    /// Takes out the requests held for `channel`, leaving the others in place.
+    pub fn remove_by_channel(&self, channel: &Channel) -> Vec<PullRequest> {
+        let mut list = self.pull_request_list.lock();
+        let (removed, kept) = list
+            .drain(..)
+            .partition(|request| request.client_channel() == channel);
+        *list = kept;
+        removed
+    }
+
     /*    pub fn get_pull_request_list(&self) -> Vec<PullRequest> {
         let list = self.pull_request_list.lock();
         list.clone()
     }*/
 
+    pub fn len(&self) -> usize {
+        self.pull_request_list.lock().len()
+    }
+
     pub fn is_empty(&self) -> bool {
         let list = self.pull_request_list.lock();
         list.is_empty()
@@ -15,30 +15,38 @@
  * limitations under the License.
  */
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use cheetah_string::CheetahString;
 use rocketmq_rust::ArcMut;
 use rocketmq_store::base::message_arriving_listener::MessageArrivingListener;
 use rocketmq_store::log_file::MessageStore;
 
+use crate::long_polling::long_polling_service::pop_long_polling_service::PopLongPollingService;
 use crate::long_polling::long_polling_service::pull_request_hold_service::PullRequestHoldService;
 
+/// Invoked by the reput service once the consume queue entry of a message is visible, wakes
+/// the pull and pop requests held on its queue.
 pub struct NotifyMessageArrivingListener<MS> {
     pull_request_hold_service: ArcMut<PullRequestHoldService<MS>>,
+    pop_long_polling_service: Arc<PopLongPollingService>,
 }
 
 impl<MS> NotifyMessageArrivingListener<MS>
 where
     MS: MessageStore + Send + Sync,
 {
-    pub fn new(pull_request_hold_service: ArcMut<PullRequestHoldService<MS>>) -> Self {
+    pub fn new(
+        pull_request_hold_service: ArcMut<PullRequestHoldService<MS>>,
+        pop_long_polling_service: Arc<PopLongPollingService>,
+    ) -> Self {
         Self {
             pull_request_hold_service,
+            pop_long_polling_service,
         }
     }
 }
 
-#[allow(unused_variables)]
 impl<MS> MessageArrivingListener for NotifyMessageArrivingListener<MS>
 where
     MS: MessageStore + Send + Sync,
@@ -50,7 +58,7 @@ where
         logic_offset: i64,
         tags_code: Option<i64>,
         msg_store_time: i64,
-        filter_bit_map: Option<Vec<u8>>,
+        filter_bit_map: Option<&[u8]>,
         properties: Option<&HashMap<CheetahString, CheetahString>>,
     ) {
         self.pull_request_hold_service.notify_message_arriving_ext(
@@ -62,5 +70,7 @@ where
             filter_bit_map,
             properties,
         );
+        self.pop_long_polling_service
+            .notify_message_arriving(topic, queue_id);
     }
 }
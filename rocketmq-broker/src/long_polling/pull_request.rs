@@ -89,6 +89,11 @@ impl PullRequest {
         self.suspend_timestamp
     }
 
+    /// Whether the request has been held for its whole timeout at `now_millis`.
+    pub fn is_timed_out(&self, now_millis: u64) -> bool {
+        now_millis >= self.suspend_timestamp.saturating_add(self.timeout_millis)
+    }
+
     pub fn connection_handler_context(&self) -> &ConnectionHandlerContext {
         &self.ctx
     }
@@ -0,0 +1,65 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+pub const OPEN_TELEMETRY_METER_NAME: &str = "broker-meter";
+
+pub const GAUGE_PROCESSOR_WATERMARK: &str = "rocketmq_processor_watermark";
+pub const GAUGE_PROCESSOR_HEAD_WAIT_TIME: &str = "rocketmq_processor_head_wait_time";
+pub const GAUGE_PULL_HOLD_REQUESTS: &str = "rocketmq_pull_hold_requests";
+pub const COUNTER_SLOW_REQUESTS_TOTAL: &str = "rocketmq_slow_requests_total";
+pub const GAUGE_BROKER_PERMISSION: &str = "rocketmq_broker_permission";
+
+pub const COUNTER_MESSAGES_IN_TOTAL: &str = "rocketmq_messages_in_total";
+pub const COUNTER_MESSAGES_OUT_TOTAL: &str = "rocketmq_messages_out_total";
+pub const COUNTER_THROUGHPUT_IN_TOTAL: &str = "rocketmq_throughput_in_total";
+pub const COUNTER_THROUGHPUT_OUT_TOTAL: &str = "rocketmq_throughput_out_total";
+pub const HISTOGRAM_MESSAGE_SIZE: &str = "rocketmq_message_size";
+pub const COUNTER_OVERSIZED_EXT_FIELDS_TOTAL: &str = "rocketmq_oversized_ext_fields_total";
+
+pub const GAUGE_PRODUCER_CONNECTIONS: &str = "rocketmq_producer_connections";
+pub const GAUGE_CONSUMER_CONNECTIONS: &str = "rocketmq_consumer_connections";
+
+pub const GAUGE_CONSUMER_LAG_LATENCY: &str = "rocketmq_consumer_lag_latency";
+pub const GAUGE_CONSUMER_QUEUEING_LATENCY: &str = "rocketmq_consumer_queueing_latency";
+
+pub const LABEL_CLUSTER_NAME: &str = "cluster";
+pub const LABEL_NODE_TYPE: &str = "node_type";
+pub const NODE_TYPE_BROKER: &str = "broker";
+pub const LABEL_NODE_ID: &str = "node_id";
+pub const LABEL_PROCESSOR: &str = "processor";
+pub const LABEL_REQUEST_CODE: &str = "request_code";
+
+pub const LABEL_TOPIC: &str = "topic";
+pub const LABEL_IS_RETRY: &str = "is_retry";
+pub const LABEL_IS_SYSTEM: &str = "is_system";
+pub const LABEL_CONSUMER_GROUP: &str = "consumer_group";
+pub const LABEL_MESSAGE_TYPE: &str = "message_type";
+pub const LABEL_LANGUAGE: &str = "language";
+pub const LABEL_VERSION: &str = "version";
+pub const LABEL_CONSUME_MODE: &str = "consume_mode";
+
+/// Topic label used once `metrics_max_topics_tracked` distinct topics have been seen.
+pub const OVERFLOW_TOPIC_LABEL: &str = "__other__";
+
+/// Upper bounds (bytes) of the message size buckets: 1KB, 4KB, 512KB, 1MB, 2MB and 4MB.
+pub const MESSAGE_SIZE_BUCKETS: [f64; 6] = [
+    1024.0,
+    4.0 * 1024.0,
+    512.0 * 1024.0,
+    1024.0 * 1024.0,
+    2.0 * 1024.0 * 1024.0,
+    4.0 * 1024.0 * 1024.0,
+];
@@ -0,0 +1,1208 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use cheetah_string::CheetahString;
+use opentelemetry::metrics::noop::NoopMeterProvider;
+use opentelemetry::metrics::Counter;
+use opentelemetry::metrics::Histogram;
+use opentelemetry::metrics::Meter;
+use opentelemetry::metrics::MeterProvider as _;
+use opentelemetry::metrics::ObservableCounter;
+use opentelemetry::metrics::ObservableGauge;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::MetricsExporterBuilder;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::metrics::new_view;
+use opentelemetry_sdk::metrics::reader::DefaultAggregationSelector;
+use opentelemetry_sdk::metrics::reader::DefaultTemporalitySelector;
+use opentelemetry_sdk::metrics::Aggregation;
+use opentelemetry_sdk::metrics::Instrument;
+use opentelemetry_sdk::metrics::MeterProvider;
+use opentelemetry_sdk::metrics::MeterProviderBuilder;
+use opentelemetry_sdk::metrics::PeriodicReader;
+use opentelemetry_sdk::metrics::Stream;
+use opentelemetry_sdk::runtime;
+use opentelemetry_sdk::Resource;
+use parking_lot::Mutex;
+use parking_lot::RwLock;
+use prometheus::Encoder;
+use prometheus::Registry;
+use prometheus::TextEncoder;
+use rocketmq_common::common::attribute::topic_message_type::TopicMessageType;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::metrics::metrics_exporter_type::MetricsExporterType;
+use rocketmq_common::common::mix_all;
+use rocketmq_common::common::mq_version::get_version_desc;
+use rocketmq_common::common::topic::TopicValidator;
+use rocketmq_common::log::SLOW_REQUEST_LOGGER_NAME;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_remoting::codec::remoting_command_codec::oversized_ext_fields_rejected;
+use rocketmq_remoting::protocol::heartbeat::consume_type::ConsumeType;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::log_file::MessageStore;
+use rocketmq_store::message_store::default_message_store::DefaultMessageStore;
+use rocketmq_store::metrics::default_store_metrics_manager::DefaultStoreMetricsManager;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+use crate::client::manager::consumer_manager::ConsumerManager;
+use crate::client::manager::producer_manager::ProducerManager;
+use crate::metrics::broker_metrics_constant::*;
+use crate::metrics::logging_metrics_exporter::LoggingMetricsExporter;
+use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
+use crate::offset::manager::consumer_offset_manager::TOPIC_GROUP_SEPARATOR;
+
+/// Label values longer than this are truncated.
+const MAX_LABEL_VALUE_LENGTH: usize = 256;
+
+/// Processors whose watermark is reported even before they handle their first request.
+const PROCESSORS: [&str; 9] = [
+    "send",
+    "pull",
+    "reply",
+    "query",
+    "client_manager",
+    "consumer_manager",
+    "end_transaction",
+    "query_assignment",
+    "admin",
+];
+
+/// Counts the pull requests held by the long polling service.
+type HeldRequestCount = Box<dyn Fn() -> usize + Send + Sync>;
+
+/// The broker state sampled by the observable gauges.
+pub(crate) struct BrokerMetricsSource {
+    pub(crate) message_store: ArcMut<DefaultMessageStore>,
+    pub(crate) consumer_offset_manager: ConsumerOffsetManager,
+    pub(crate) producer_manager: Arc<ProducerManager>,
+    pub(crate) consumer_manager: Arc<ConsumerManager>,
+}
+
+/// Registers the standard broker metrics on the meter provider selected by
+/// `BrokerConfig::metrics_exporter_type` and hooks the message store instruments up to it.
+///
+/// When metrics are disabled the instruments are created on a no-op meter, so callers record
+/// unconditionally.
+pub(crate) struct BrokerMetricsManager {
+    base_attributes: Vec<KeyValue>,
+    topic_labels: Arc<TopicLabelLimiter>,
+    processor_watermarks: Arc<RwLock<HashMap<&'static str, Arc<ProcessorWatermark>>>>,
+    pull_hold_requests: Arc<RwLock<Option<HeldRequestCount>>>,
+    slow_request_threshold: Duration,
+    meter_provider: Mutex<Option<MeterProvider>>,
+    prometheus_server: Mutex<Option<JoinHandle<()>>>,
+    prometheus_addr: Option<SocketAddr>,
+
+    messages_in_total: Counter<u64>,
+    messages_out_total: Counter<u64>,
+    throughput_in_total: Counter<u64>,
+    throughput_out_total: Counter<u64>,
+    message_size: Histogram<u64>,
+    slow_requests_total: Counter<u64>,
+    _processor_watermark: ObservableGauge<i64>,
+    _processor_head_wait_time: ObservableGauge<i64>,
+    _pull_hold_requests: ObservableGauge<u64>,
+    _broker_permission: ObservableGauge<u64>,
+    _producer_connections: ObservableGauge<u64>,
+    _consumer_connections: ObservableGauge<u64>,
+    _consumer_lag_latency: ObservableGauge<u64>,
+    _consumer_queueing_latency: ObservableGauge<u64>,
+    _oversized_ext_fields_total: ObservableCounter<u64>,
+}
+
+impl BrokerMetricsManager {
+    pub(crate) async fn new(broker_config: Arc<BrokerConfig>, source: BrokerMetricsSource) -> Self {
+        let base_attributes = vec![
+            KeyValue::new(
+                LABEL_CLUSTER_NAME,
+                sanitize_label_value(&broker_config.broker_identity.broker_cluster_name),
+            ),
+            KeyValue::new(LABEL_NODE_TYPE, NODE_TYPE_BROKER),
+            KeyValue::new(
+                LABEL_NODE_ID,
+                sanitize_label_value(&broker_config.broker_name),
+            ),
+        ];
+        let topic_labels = Arc::new(TopicLabelLimiter::new(
+            broker_config.metrics_max_topics_tracked,
+        ));
+        let processor_watermarks = Arc::new(RwLock::new(
+            PROCESSORS
+                .iter()
+                .map(|processor| (*processor, Arc::default()))
+                .collect::<HashMap<_, _>>(),
+        ));
+        let pull_hold_requests = Arc::new(RwLock::new(None));
+
+        let mut exporter = MeterExporter::default();
+        let meter_provider = exporter.build_meter_provider(&broker_config).await;
+        let meter = match meter_provider.as_ref() {
+            Some(meter_provider) => {
+                let mut message_store = source.message_store.clone();
+                message_store.init_metrics(meter_provider);
+                meter_provider.meter(OPEN_TELEMETRY_METER_NAME)
+            }
+            None => NoopMeterProvider::new().meter(OPEN_TELEMETRY_METER_NAME),
+        };
+
+        let source = Arc::new(source);
+        let gauges = Gauges {
+            meter: &meter,
+            broker_config: &broker_config,
+            base_attributes: &base_attributes,
+            topic_labels: &topic_labels,
+            source: &source,
+        };
+        Self {
+            messages_in_total: meter
+                .u64_counter(COUNTER_MESSAGES_IN_TOTAL)
+                .with_description("Total number of incoming messages")
+                .init(),
+            messages_out_total: meter
+                .u64_counter(COUNTER_MESSAGES_OUT_TOTAL)
+                .with_description("Total number of outgoing messages")
+                .init(),
+            throughput_in_total: meter
+                .u64_counter(COUNTER_THROUGHPUT_IN_TOTAL)
+                .with_description("Total traffic of incoming messages in bytes")
+                .init(),
+            throughput_out_total: meter
+                .u64_counter(COUNTER_THROUGHPUT_OUT_TOTAL)
+                .with_description("Total traffic of outgoing messages in bytes")
+                .init(),
+            message_size: meter
+                .u64_histogram(HISTOGRAM_MESSAGE_SIZE)
+                .with_description("Incoming messages size in bytes")
+                .init(),
+            slow_requests_total: meter
+                .u64_counter(COUNTER_SLOW_REQUESTS_TOTAL)
+                .with_description("Total number of requests written to the slow request log")
+                .init(),
+            _processor_watermark: gauges.processor_watermark(&processor_watermarks),
+            _processor_head_wait_time: gauges.processor_head_wait_time(&processor_watermarks),
+            _pull_hold_requests: gauges.pull_hold_requests(&pull_hold_requests),
+            _broker_permission: gauges.broker_permission(),
+            _producer_connections: gauges.producer_connections(),
+            _consumer_connections: gauges.consumer_connections(),
+            _consumer_lag_latency: gauges.consumer_lag_latency(),
+            _consumer_queueing_latency: gauges.consumer_queueing_latency(),
+            _oversized_ext_fields_total: gauges.oversized_ext_fields_total(),
+            base_attributes,
+            topic_labels,
+            processor_watermarks,
+            pull_hold_requests,
+            slow_request_threshold: Duration::from_millis(
+                broker_config.slow_request_threshold_millis,
+            ),
+            meter_provider: Mutex::new(meter_provider),
+            prometheus_addr: exporter.prometheus_addr,
+            prometheus_server: Mutex::new(exporter.prometheus_server),
+        }
+    }
+
+    /// Views to register on the meter provider so histograms use the broker's buckets.
+    pub(crate) fn get_metrics_view() -> Vec<Box<dyn opentelemetry_sdk::metrics::View>> {
+        let message_size_view = new_view(
+            Instrument::new().name(HISTOGRAM_MESSAGE_SIZE),
+            Stream::new().aggregation(Aggregation::ExplicitBucketHistogram {
+                boundaries: MESSAGE_SIZE_BUCKETS.to_vec(),
+                record_min_max: false,
+            }),
+        );
+        let mut views = DefaultStoreMetricsManager::get_metrics_view();
+        match message_size_view {
+            Ok(view) => views.push(view),
+            Err(err) => warn!("create message size view failed: {}", err),
+        }
+        views
+    }
+
+    /// The address the Prometheus endpoint listens on, when the `PROM` exporter is enabled.
+    pub(crate) fn prometheus_addr(&self) -> Option<SocketAddr> {
+        self.prometheus_addr
+    }
+
+    pub(crate) fn inc_messages_in(
+        &self,
+        topic: &str,
+        message_type: &TopicMessageType,
+        msg_num: i32,
+        wrote_bytes: i32,
+    ) {
+        if msg_num <= 0 {
+            return;
+        }
+        let attributes = self.attributes([
+            KeyValue::new(LABEL_TOPIC, self.topic_labels.label(topic)),
+            KeyValue::new(LABEL_MESSAGE_TYPE, message_type.get_metrics_value()),
+            KeyValue::new(LABEL_IS_SYSTEM, TopicValidator::is_system_topic(topic)),
+        ]);
+        self.messages_in_total.add(msg_num as u64, &attributes);
+        self.throughput_in_total
+            .add(wrote_bytes.max(0) as u64, &attributes);
+        self.message_size
+            .record((wrote_bytes.max(0) / msg_num) as u64, &attributes);
+    }
+
+    pub(crate) fn inc_messages_out(
+        &self,
+        topic: &str,
+        consumer_group: &str,
+        msg_count: i32,
+        total_size: i32,
+    ) {
+        if msg_count <= 0 {
+            return;
+        }
+        let attributes = self.attributes([
+            KeyValue::new(LABEL_TOPIC, self.topic_labels.label(topic)),
+            KeyValue::new(LABEL_CONSUMER_GROUP, sanitize_label_value(consumer_group)),
+            KeyValue::new(
+                LABEL_IS_RETRY,
+                topic.starts_with(mix_all::RETRY_GROUP_TOPIC_PREFIX),
+            ),
+            KeyValue::new(
+                LABEL_IS_SYSTEM,
+                TopicValidator::is_system_topic(topic)
+                    || mix_all::is_sys_consumer_group(consumer_group),
+            ),
+        ]);
+        self.messages_out_total.add(msg_count as u64, &attributes);
+        self.throughput_out_total
+            .add(total_size.max(0) as u64, &attributes);
+    }
+
+    /// Counts a request as in flight on `processor` until the returned guard is dropped.
+    pub(crate) fn processor_in_flight(&self, processor: &'static str) -> ProcessorInFlight {
+        let watermark = self.processor_watermarks.read().get(processor).cloned();
+        let watermark = watermark.unwrap_or_else(|| {
+            self.processor_watermarks
+                .write()
+                .entry(processor)
+                .or_default()
+                .clone()
+        });
+        let (id, started) = watermark.enter();
+        ProcessorInFlight {
+            processor,
+            watermark,
+            id,
+            started,
+        }
+    }
+
+    /// Requests currently in flight on every processor that served at least one request, and
+    /// how long the oldest of them has been waiting.
+    pub(crate) fn processor_watermarks(&self) -> Vec<ProcessorWatermarkSample> {
+        let mut watermarks = self
+            .processor_watermarks
+            .read()
+            .iter()
+            .map(|(processor, watermark)| watermark.sample(processor))
+            .collect::<Vec<_>>();
+        watermarks.sort_unstable_by_key(|sample| sample.processor);
+        watermarks
+    }
+
+    /// Reports the pull requests held by the long polling service, created after the metrics.
+    pub(crate) fn set_pull_hold_requests(
+        &self,
+        held_request_count: impl Fn() -> usize + Send + Sync + 'static,
+    ) {
+        *self.pull_hold_requests.write() = Some(Box::new(held_request_count));
+    }
+
+    /// The pull requests held by the long polling service, 0 before it is created.
+    pub(crate) fn pull_hold_requests(&self) -> usize {
+        self.pull_hold_requests
+            .read()
+            .as_ref()
+            .map_or(0, |held_request_count| held_request_count())
+    }
+
+    /// Writes `request` to the slow request log and counts it when it has been in flight for
+    /// longer than `slow_request_threshold_millis`. `true` if it was slow.
+    pub(crate) fn record_if_slow(
+        &self,
+        in_flight: &ProcessorInFlight,
+        request: &RequestSummary,
+    ) -> bool {
+        let elapsed = in_flight.started.elapsed();
+        if self.slow_request_threshold.is_zero() || elapsed <= self.slow_request_threshold {
+            return false;
+        }
+        warn!(
+            target: SLOW_REQUEST_LOGGER_NAME,
+            "[SLOW_REQUEST] processor: {}, code: {}, topic: {}, group: {}, peer: {}, elapsed: {}ms",
+            in_flight.processor,
+            request.code,
+            request.topic.as_deref().unwrap_or("-"),
+            request.group.as_deref().unwrap_or("-"),
+            request.peer,
+            elapsed.as_millis()
+        );
+        self.slow_requests_total.add(
+            1,
+            &self.attributes([
+                KeyValue::new(LABEL_PROCESSOR, in_flight.processor),
+                KeyValue::new(LABEL_REQUEST_CODE, request.code as i64),
+            ]),
+        );
+        true
+    }
+
+    pub(crate) fn shutdown(&self) {
+        if let Some(meter_provider) = self.meter_provider.lock().take() {
+            if let Err(err) = meter_provider.shutdown() {
+                warn!("shutdown meter provider failed: {}", err);
+            }
+        }
+        if let Some(server) = self.prometheus_server.lock().take() {
+            server.abort();
+        }
+    }
+
+    fn attributes<const N: usize>(&self, labels: [KeyValue; N]) -> Vec<KeyValue> {
+        let mut attributes = Vec::with_capacity(self.base_attributes.len() + N);
+        attributes.extend_from_slice(&self.base_attributes);
+        attributes.extend(labels);
+        attributes
+    }
+}
+
+/// The requests in flight on a processor by arrival, the first one being the oldest.
+#[derive(Default)]
+struct ProcessorWatermark {
+    in_flight: Mutex<(u64, BTreeMap<u64, Instant>)>,
+}
+
+impl ProcessorWatermark {
+    fn enter(&self) -> (u64, Instant) {
+        let mut in_flight = self.in_flight.lock();
+        let id = in_flight.0;
+        let started = Instant::now();
+        in_flight.0 += 1;
+        in_flight.1.insert(id, started);
+        (id, started)
+    }
+
+    fn leave(&self, id: u64) {
+        self.in_flight.lock().1.remove(&id);
+    }
+
+    fn sample(&self, processor: &'static str) -> ProcessorWatermarkSample {
+        let in_flight = self.in_flight.lock();
+        ProcessorWatermarkSample {
+            processor,
+            in_flight: in_flight.1.len() as i64,
+            head_wait_millis: in_flight
+                .1
+                .values()
+                .next()
+                .map_or(0, |started| started.elapsed().as_millis() as i64),
+        }
+    }
+}
+
+/// A processor watermark as logged and exported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ProcessorWatermarkSample {
+    pub(crate) processor: &'static str,
+    pub(crate) in_flight: i64,
+    /// How long the oldest request in flight has been waiting, 0 when there is none.
+    pub(crate) head_wait_millis: i64,
+}
+
+/// Takes the request off the processor watermark when it completes.
+pub(crate) struct ProcessorInFlight {
+    processor: &'static str,
+    watermark: Arc<ProcessorWatermark>,
+    id: u64,
+    started: Instant,
+}
+
+impl Drop for ProcessorInFlight {
+    fn drop(&mut self) {
+        self.watermark.leave(self.id);
+    }
+}
+
+/// What the slow request log records of a request besides the time it took.
+pub(crate) struct RequestSummary {
+    pub(crate) code: i32,
+    pub(crate) topic: Option<CheetahString>,
+    pub(crate) group: Option<CheetahString>,
+    pub(crate) peer: SocketAddr,
+}
+
+/// Caps the number of distinct topic label values so a broker hosting many topics cannot blow
+/// up the series count; topics seen after the limit is reached share [`OVERFLOW_TOPIC_LABEL`].
+pub(crate) struct TopicLabelLimiter {
+    max_topics: usize,
+    tracked: RwLock<HashSet<String>>,
+}
+
+impl TopicLabelLimiter {
+    pub(crate) fn new(max_topics: usize) -> Self {
+        Self {
+            max_topics,
+            tracked: RwLock::new(HashSet::new()),
+        }
+    }
+
+    pub(crate) fn label(&self, topic: &str) -> String {
+        let topic = sanitize_label_value(topic);
+        if self.tracked.read().contains(&topic) {
+            return topic;
+        }
+        let mut tracked = self.tracked.write();
+        if tracked.contains(&topic) {
+            return topic;
+        }
+        if tracked.len() >= self.max_topics {
+            return OVERFLOW_TOPIC_LABEL.to_string();
+        }
+        if tracked.len() + 1 == self.max_topics {
+            warn!(
+                "metrics track {} topics now, metrics of further topics are labeled {}",
+                self.max_topics, OVERFLOW_TOPIC_LABEL
+            );
+        }
+        tracked.insert(topic.clone());
+        topic
+    }
+}
+
+/// Keeps label values printable and bounded: control characters, quotes and backslashes are
+/// replaced by `_` and the value is cut at [`MAX_LABEL_VALUE_LENGTH`] characters.
+pub(crate) fn sanitize_label_value(value: &str) -> String {
+    value
+        .trim()
+        .chars()
+        .take(MAX_LABEL_VALUE_LENGTH)
+        .map(|c| {
+            if c.is_control() || c == '"' || c == '\\' {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Builds the observable gauges, each sampling the broker state when the reader collects.
+struct Gauges<'a> {
+    meter: &'a Meter,
+    broker_config: &'a Arc<BrokerConfig>,
+    base_attributes: &'a [KeyValue],
+    topic_labels: &'a Arc<TopicLabelLimiter>,
+    source: &'a Arc<BrokerMetricsSource>,
+}
+
+impl Gauges<'_> {
+    fn with_base(&self, labels: impl IntoIterator<Item = KeyValue>) -> Vec<KeyValue> {
+        with_base(self.base_attributes, labels)
+    }
+
+    fn processor_watermark(
+        &self,
+        processor_watermarks: &Arc<RwLock<HashMap<&'static str, Arc<ProcessorWatermark>>>>,
+    ) -> ObservableGauge<i64> {
+        let base_attributes = self.base_attributes.to_vec();
+        let processor_watermarks = processor_watermarks.clone();
+        self.meter
+            .i64_observable_gauge(GAUGE_PROCESSOR_WATERMARK)
+            .with_description("Request processor watermark")
+            .with_callback(move |observer| {
+                for (processor, watermark) in processor_watermarks.read().iter() {
+                    observer.observe(
+                        watermark.sample(processor).in_flight,
+                        &with_base(
+                            &base_attributes,
+                            [KeyValue::new(LABEL_PROCESSOR, *processor)],
+                        ),
+                    );
+                }
+            })
+            .init()
+    }
+
+    fn processor_head_wait_time(
+        &self,
+        processor_watermarks: &Arc<RwLock<HashMap<&'static str, Arc<ProcessorWatermark>>>>,
+    ) -> ObservableGauge<i64> {
+        let base_attributes = self.base_attributes.to_vec();
+        let processor_watermarks = processor_watermarks.clone();
+        self.meter
+            .i64_observable_gauge(GAUGE_PROCESSOR_HEAD_WAIT_TIME)
+            .with_description("Milliseconds the oldest request in flight on a processor waits")
+            .with_callback(move |observer| {
+                for (processor, watermark) in processor_watermarks.read().iter() {
+                    observer.observe(
+                        watermark.sample(processor).head_wait_millis,
+                        &with_base(
+                            &base_attributes,
+                            [KeyValue::new(LABEL_PROCESSOR, *processor)],
+                        ),
+                    );
+                }
+            })
+            .init()
+    }
+
+    fn pull_hold_requests(
+        &self,
+        pull_hold_requests: &Arc<RwLock<Option<HeldRequestCount>>>,
+    ) -> ObservableGauge<u64> {
+        let attributes = self.with_base([]);
+        let pull_hold_requests = pull_hold_requests.clone();
+        self.meter
+            .u64_observable_gauge(GAUGE_PULL_HOLD_REQUESTS)
+            .with_description("Pull requests held by the long polling service")
+            .with_callback(move |observer| {
+                if let Some(held_request_count) = pull_hold_requests.read().as_ref() {
+                    observer.observe(held_request_count() as u64, &attributes);
+                }
+            })
+            .init()
+    }
+
+    fn broker_permission(&self) -> ObservableGauge<u64> {
+        let attributes = self.with_base([]);
+        let broker_config = self.broker_config.clone();
+        self.meter
+            .u64_observable_gauge(GAUGE_BROKER_PERMISSION)
+            .with_description("Broker permission")
+            .with_callback(move |observer| {
+                observer.observe(broker_config.broker_permission as u64, &attributes);
+            })
+            .init()
+    }
+
+    fn oversized_ext_fields_total(&self) -> ObservableCounter<u64> {
+        let attributes = self.with_base([]);
+        self.meter
+            .u64_observable_counter(COUNTER_OVERSIZED_EXT_FIELDS_TOTAL)
+            .with_description("Total number of requests rejected for oversized extFields")
+            .with_callback(move |observer| {
+                observer.observe(oversized_ext_fields_rejected(), &attributes);
+            })
+            .init()
+    }
+
+    fn producer_connections(&self) -> ObservableGauge<u64> {
+        let base_attributes = self.base_attributes.to_vec();
+        let source = self.source.clone();
+        self.meter
+            .u64_observable_gauge(GAUGE_PRODUCER_CONNECTIONS)
+            .with_description("Producer connections")
+            .with_callback(move |observer| {
+                let mut connections = HashMap::new();
+                for channels in source.producer_manager.get_group_channel_table().values() {
+                    for info in channels.values() {
+                        *connections
+                            .entry((info.language().to_string(), info.version()))
+                            .or_insert(0u64) += 1;
+                    }
+                }
+                for ((language, version), count) in connections {
+                    observer.observe(
+                        count,
+                        &with_base(
+                            &base_attributes,
+                            [
+                                KeyValue::new(LABEL_LANGUAGE, language.to_lowercase()),
+                                KeyValue::new(LABEL_VERSION, version_desc(version)),
+                            ],
+                        ),
+                    );
+                }
+            })
+            .init()
+    }
+
+    fn consumer_connections(&self) -> ObservableGauge<u64> {
+        let base_attributes = self.base_attributes.to_vec();
+        let source = self.source.clone();
+        self.meter
+            .u64_observable_gauge(GAUGE_CONSUMER_CONNECTIONS)
+            .with_description("Consumer connections")
+            .with_callback(move |observer| {
+                let consumer_table = source.consumer_manager.get_consumer_table();
+                let mut connections = HashMap::new();
+                for (group, group_info) in consumer_table.read().iter() {
+                    let consume_mode = consume_mode(group_info.get_consume_type());
+                    for info in group_info.get_channel_info_table().read().values() {
+                        *connections
+                            .entry((
+                                group.clone(),
+                                info.language().to_string(),
+                                info.version(),
+                                consume_mode,
+                            ))
+                            .or_insert(0u64) += 1;
+                    }
+                }
+                for ((group, language, version, consume_mode), count) in connections {
+                    observer.observe(
+                        count,
+                        &with_base(
+                            &base_attributes,
+                            [
+                                KeyValue::new(LABEL_CONSUMER_GROUP, sanitize_label_value(&group)),
+                                KeyValue::new(LABEL_LANGUAGE, language.to_lowercase()),
+                                KeyValue::new(LABEL_VERSION, version_desc(version)),
+                                KeyValue::new(LABEL_CONSUME_MODE, consume_mode),
+                            ],
+                        ),
+                    );
+                }
+            })
+            .init()
+    }
+
+    fn consumer_lag_latency(&self) -> ObservableGauge<u64> {
+        self.consumer_latency(
+            GAUGE_CONSUMER_LAG_LATENCY,
+            "Consumer lag time in ms",
+            |_, _, _, _, committed_offset| committed_offset,
+        )
+    }
+
+    fn consumer_queueing_latency(&self) -> ObservableGauge<u64> {
+        self.consumer_latency(
+            GAUGE_CONSUMER_QUEUEING_LATENCY,
+            "Consumer queueing time in ms",
+            |source, group, topic, queue_id, _| {
+                source
+                    .consumer_offset_manager
+                    .query_pull_offset(group, topic, queue_id)
+            },
+        )
+    }
+
+    /// Reports, per consumer group and topic, how long ago the oldest message at the offset
+    /// chosen by `offset_of` was stored; the committed offset is passed in as the last argument.
+    fn consumer_latency(
+        &self,
+        name: &'static str,
+        description: &'static str,
+        offset_of: fn(&BrokerMetricsSource, &CheetahString, &CheetahString, i32, i64) -> i64,
+    ) -> ObservableGauge<u64> {
+        let base_attributes = self.base_attributes.to_vec();
+        let topic_labels = self.topic_labels.clone();
+        let source = self.source.clone();
+        self.meter
+            .u64_observable_gauge(name)
+            .with_description(description)
+            .with_callback(move |observer| {
+                let now = get_current_millis() as i64;
+                for (key, queue_offsets) in source.consumer_offset_manager.offset_table() {
+                    let Some((topic, group)) = key.split_once(TOPIC_GROUP_SEPARATOR) else {
+                        continue;
+                    };
+                    let topic_label = topic_labels.label(topic);
+                    if topic_label == OVERFLOW_TOPIC_LABEL {
+                        continue;
+                    }
+                    let topic = CheetahString::from_slice(topic);
+                    let group = CheetahString::from_slice(group);
+                    let mut earliest = None;
+                    for (queue_id, committed_offset) in queue_offsets {
+                        let offset = offset_of(&source, &group, &topic, queue_id, committed_offset);
+                        let max_offset = source
+                            .message_store
+                            .get_max_offset_in_queue(&topic, queue_id);
+                        if offset < 0 || offset >= max_offset {
+                            continue;
+                        }
+                        let store_timestamp = source
+                            .message_store
+                            .get_message_store_timestamp(&topic, queue_id, offset);
+                        if store_timestamp > 0 {
+                            earliest = Some(earliest.map_or(store_timestamp, |earliest: i64| {
+                                earliest.min(store_timestamp)
+                            }));
+                        }
+                    }
+                    let latency = earliest.map_or(0, |earliest| (now - earliest).max(0));
+                    observer.observe(
+                        latency as u64,
+                        &with_base(
+                            &base_attributes,
+                            [
+                                KeyValue::new(LABEL_TOPIC, topic_label),
+                                KeyValue::new(
+                                    LABEL_CONSUMER_GROUP,
+                                    sanitize_label_value(group.as_str()),
+                                ),
+                                KeyValue::new(
+                                    LABEL_IS_RETRY,
+                                    topic.starts_with(mix_all::RETRY_GROUP_TOPIC_PREFIX),
+                                ),
+                                KeyValue::new(
+                                    LABEL_IS_SYSTEM,
+                                    TopicValidator::is_system_topic(topic.as_str())
+                                        || mix_all::is_sys_consumer_group(group.as_str()),
+                                ),
+                            ],
+                        ),
+                    );
+                }
+            })
+            .init()
+    }
+}
+
+fn with_base(
+    base_attributes: &[KeyValue],
+    labels: impl IntoIterator<Item = KeyValue>,
+) -> Vec<KeyValue> {
+    let mut attributes = base_attributes.to_vec();
+    attributes.extend(labels);
+    attributes
+}
+
+fn consume_mode(consume_type: ConsumeType) -> &'static str {
+    match consume_type {
+        ConsumeType::ConsumeActively => "pull",
+        ConsumeType::ConsumePassively => "push",
+        ConsumeType::ConsumePop => "pop",
+    }
+}
+
+fn version_desc(version: i32) -> String {
+    get_version_desc(version).to_lowercase()
+}
+
+/// The reader side of the meter provider, plus the Prometheus endpoint when one is served.
+#[derive(Default)]
+struct MeterExporter {
+    prometheus_server: Option<JoinHandle<()>>,
+    prometheus_addr: Option<SocketAddr>,
+}
+
+impl MeterExporter {
+    async fn build_meter_provider(
+        &mut self,
+        broker_config: &BrokerConfig,
+    ) -> Option<MeterProvider> {
+        let exporter_type = broker_config.metrics_exporter_type;
+        if !exporter_type.is_enable() {
+            return None;
+        }
+        let mut builder = MeterProvider::builder().with_resource(Resource::new([
+            KeyValue::new(
+                LABEL_CLUSTER_NAME,
+                broker_config
+                    .broker_identity
+                    .broker_cluster_name
+                    .to_string(),
+            ),
+            KeyValue::new(LABEL_NODE_TYPE, NODE_TYPE_BROKER),
+            KeyValue::new(LABEL_NODE_ID, broker_config.broker_name.to_string()),
+        ]));
+        for view in BrokerMetricsManager::get_metrics_view() {
+            builder = builder.with_view(view);
+        }
+        let builder = match exporter_type {
+            MetricsExporterType::OtlpGrpc => Self::with_otlp_reader(broker_config, builder),
+            MetricsExporterType::Prom => self.with_prometheus_reader(broker_config, builder).await,
+            MetricsExporterType::Log => Some(
+                builder.with_reader(
+                    PeriodicReader::builder(LoggingMetricsExporter::default(), runtime::Tokio)
+                        .with_interval(Duration::from_millis(
+                            broker_config.metrics_logging_exporter_interval_in_mills,
+                        ))
+                        .build(),
+                ),
+            ),
+            MetricsExporterType::Disable => None,
+        }?;
+        info!("metrics exporter {} initialized", exporter_type.get_name());
+        Some(builder.build())
+    }
+
+    fn with_otlp_reader(
+        broker_config: &BrokerConfig,
+        builder: MeterProviderBuilder,
+    ) -> Option<MeterProviderBuilder> {
+        let target = broker_config.metrics_grpc_exporter_target.as_str();
+        if target.is_empty() {
+            warn!("metricsGrpcExporterTarget is empty, metrics are disabled");
+            return None;
+        }
+        let exporter = MetricsExporterBuilder::from(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(target)
+                .with_timeout(Duration::from_millis(
+                    broker_config.metrics_grpc_exporter_time_out_in_mills,
+                )),
+        )
+        .build_metrics_exporter(
+            Box::new(DefaultTemporalitySelector::new()),
+            Box::new(DefaultAggregationSelector::new()),
+        );
+        let exporter = match exporter {
+            Ok(exporter) => exporter,
+            Err(err) => {
+                error!("create otlp metrics exporter to {} failed: {}", target, err);
+                return None;
+            }
+        };
+        let reader = PeriodicReader::builder(exporter, runtime::Tokio)
+            .with_interval(Duration::from_millis(
+                broker_config.metrics_grpc_exporter_interval_in_mills,
+            ))
+            .build();
+        Some(builder.with_reader(reader))
+    }
+
+    async fn with_prometheus_reader(
+        &mut self,
+        broker_config: &BrokerConfig,
+        builder: MeterProviderBuilder,
+    ) -> Option<MeterProviderBuilder> {
+        let registry = Registry::new();
+        let exporter = opentelemetry_prometheus::exporter()
+            .with_registry(registry.clone())
+            .without_units()
+            .without_counter_suffixes()
+            .build();
+        let exporter = match exporter {
+            Ok(exporter) => exporter,
+            Err(err) => {
+                error!("create prometheus metrics exporter failed: {}", err);
+                return None;
+            }
+        };
+        let host = match broker_config.metrics_prom_exporter_host.as_str() {
+            "" => broker_config.broker_ip1.as_str(),
+            host => host,
+        };
+        let addr = format!("{}:{}", host, broker_config.metrics_prom_exporter_port);
+        let listener = match TcpListener::bind(&addr).await {
+            Ok(listener) => listener,
+            Err(err) => {
+                error!("bind prometheus metrics endpoint {} failed: {}", addr, err);
+                return None;
+            }
+        };
+        self.prometheus_addr = listener.local_addr().ok();
+        info!("prometheus metrics endpoint listening on {}", addr);
+        self.prometheus_server = Some(tokio::spawn(serve_prometheus(listener, registry)));
+        Some(builder.with_reader(exporter))
+    }
+}
+
+/// Answers every connection with the text exposition of `registry`, whatever the request path.
+async fn serve_prometheus(listener: TcpListener, registry: Registry) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, remote)) => {
+                let registry = registry.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = write_metrics(stream, &registry).await {
+                        warn!("serve prometheus metrics to {} failed: {}", remote, err);
+                    }
+                });
+            }
+            Err(err) => {
+                error!("accept prometheus metrics connection failed: {}", err);
+                return;
+            }
+        }
+    }
+}
+
+async fn write_metrics(mut stream: TcpStream, registry: &Registry) -> std::io::Result<()> {
+    let mut request = [0u8; 1024];
+    let _ = stream.read(&mut request).await?;
+    let encoder = TextEncoder::new();
+    let mut body = Vec::new();
+    if let Err(err) = encoder.encode(&registry.gather(), &mut body) {
+        warn!("encode prometheus metrics failed: {}", err);
+    }
+    let header = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        encoder.format_type(),
+        body.len()
+    );
+    stream.write_all(header.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_remoting::code::request_code::RequestCode;
+    use rocketmq_store::config::message_store_config::MessageStoreConfig;
+
+    use super::*;
+    use crate::client::default_consumer_ids_change_listener::DefaultConsumerIdsChangeListener;
+
+    async fn metrics_manager(
+        dir: &tempfile::TempDir,
+        broker_config: BrokerConfig,
+    ) -> (BrokerMetricsManager, ArcMut<DefaultMessageStore>) {
+        let broker_config = Arc::new(broker_config);
+        let mut message_store = ArcMut::new(DefaultMessageStore::new(
+            Arc::new(MessageStoreConfig {
+                store_path_root_dir: CheetahString::from_string(
+                    dir.path().to_string_lossy().to_string(),
+                ),
+                ..MessageStoreConfig::default()
+            }),
+            broker_config.clone(),
+            Arc::new(Mutex::new(HashMap::new())),
+            None,
+            false,
+        ));
+        let message_store_clone = message_store.clone();
+        message_store.set_message_store_arc(Some(message_store_clone));
+        let manager = BrokerMetricsManager::new(
+            broker_config.clone(),
+            BrokerMetricsSource {
+                message_store: message_store.clone(),
+                consumer_offset_manager: ConsumerOffsetManager::new(
+                    broker_config.clone(),
+                    Some(message_store.clone()),
+                ),
+                producer_manager: Arc::new(ProducerManager::new()),
+                consumer_manager: Arc::new(ConsumerManager::new(
+                    Box::new(DefaultConsumerIdsChangeListener::default()),
+                    broker_config.channel_expired_timeout,
+                )),
+            },
+        )
+        .await;
+        (manager, message_store)
+    }
+
+    async fn scrape(addr: SocketAddr) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        response
+    }
+
+    #[tokio::test]
+    async fn prometheus_endpoint_serves_registry() {
+        let registry = Registry::new();
+        let counter = prometheus::IntCounter::new("test_requests_total", "test").unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        counter.inc();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(serve_prometheus(listener, registry));
+
+        let response = scrape(addr).await;
+        server.abort();
+
+        assert!(response.contains("test_requests_total 1"), "{response}");
+    }
+
+    #[tokio::test]
+    async fn prometheus_endpoint_exposes_broker_metrics() {
+        let dir = tempfile::tempdir().unwrap();
+        let (manager, _) = metrics_manager(
+            &dir,
+            BrokerConfig {
+                metrics_exporter_type: MetricsExporterType::Prom,
+                metrics_prom_exporter_host: CheetahString::from_static_str("127.0.0.1"),
+                metrics_prom_exporter_port: 0,
+                ..BrokerConfig::default()
+            },
+        )
+        .await;
+        manager.inc_messages_in("TopicA", &TopicMessageType::Normal, 2, 200);
+        manager.inc_messages_out("TopicA", "GroupA", 1, 100);
+        let in_flight = manager.processor_in_flight("send");
+
+        let response = scrape(manager.prometheus_addr().unwrap()).await;
+        drop(in_flight);
+        manager.shutdown();
+
+        for series in [
+            COUNTER_MESSAGES_IN_TOTAL,
+            COUNTER_THROUGHPUT_IN_TOTAL,
+            COUNTER_MESSAGES_OUT_TOTAL,
+            COUNTER_THROUGHPUT_OUT_TOTAL,
+            "rocketmq_message_size_bucket",
+            GAUGE_BROKER_PERMISSION,
+            GAUGE_PROCESSOR_WATERMARK,
+        ] {
+            assert!(response.contains(series), "missing {series}: {response}");
+        }
+        assert!(response.contains("topic=\"TopicA\""), "{response}");
+        assert!(response.contains("consumer_group=\"GroupA\""), "{response}");
+        assert!(response.contains("message_type=\"normal\""), "{response}");
+        assert!(response.contains("node_type=\"broker\""), "{response}");
+        assert!(
+            response
+                .lines()
+                .any(|line| line.starts_with(GAUGE_PROCESSOR_WATERMARK)
+                    && line.contains("processor=\"send\"")
+                    && line.ends_with(" 1")),
+            "{response}"
+        );
+    }
+
+    /// Collects the formatted log events.
+    #[derive(Clone, Default)]
+    struct CapturedLog(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLog {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn slow_request_log_captures_a_delayed_processor() {
+        let dir = tempfile::tempdir().unwrap();
+        let (manager, _) = metrics_manager(
+            &dir,
+            BrokerConfig {
+                metrics_exporter_type: MetricsExporterType::Prom,
+                metrics_prom_exporter_host: CheetahString::from_static_str("127.0.0.1"),
+                metrics_prom_exporter_port: 0,
+                slow_request_threshold_millis: 50,
+                ..BrokerConfig::default()
+            },
+        )
+        .await;
+        manager.set_pull_hold_requests(|| 3);
+        let log = CapturedLog::default();
+        let writer = log.clone();
+        let _subscriber = tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_ansi(false)
+                .with_writer(move || writer.clone())
+                .finish(),
+        );
+        let request = RequestSummary {
+            code: RequestCode::SendMessageV2.to_i32(),
+            topic: Some(CheetahString::from_static_str("TopicA")),
+            group: Some(CheetahString::from_static_str("GroupA")),
+            peer: "127.0.0.1:10911".parse().unwrap(),
+        };
+
+        let fast = manager.processor_in_flight("send");
+        assert!(!manager.record_if_slow(&fast, &request));
+        drop(fast);
+
+        let delayed = manager.processor_in_flight("send");
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        let send = manager
+            .processor_watermarks()
+            .into_iter()
+            .find(|sample| sample.processor == "send")
+            .unwrap();
+        assert_eq!(send.in_flight, 1);
+        assert!(send.head_wait_millis >= 80, "{send:?}");
+        assert!(manager.record_if_slow(&delayed, &request));
+        drop(delayed);
+        assert_eq!(manager.pull_hold_requests(), 3);
+
+        let logged = String::from_utf8(log.0.lock().clone()).unwrap();
+        assert_eq!(logged.matches("[SLOW_REQUEST]").count(), 1, "{logged}");
+        for field in [
+            SLOW_REQUEST_LOGGER_NAME,
+            "processor: send",
+            &format!("code: {}", request.code),
+            "topic: TopicA",
+            "group: GroupA",
+            "peer: 127.0.0.1:10911",
+        ] {
+            assert!(logged.contains(field), "missing {field}: {logged}");
+        }
+
+        let response = scrape(manager.prometheus_addr().unwrap()).await;
+        manager.shutdown();
+        assert!(
+            response
+                .lines()
+                .any(|line| line.starts_with(COUNTER_SLOW_REQUESTS_TOTAL)
+                    && line.contains("processor=\"send\"")
+                    && line.ends_with(" 1")),
+            "{response}"
+        );
+        assert!(
+            response
+                .lines()
+                .any(|line| line.starts_with(GAUGE_PULL_HOLD_REQUESTS) && line.ends_with(" 3")),
+            "{response}"
+        );
+        assert!(
+            response.contains(GAUGE_PROCESSOR_HEAD_WAIT_TIME),
+            "{response}"
+        );
+    }
+
+    #[tokio::test]
+    async fn disabled_exporter_records_into_noop_instruments() {
+        let dir = tempfile::tempdir().unwrap();
+        let (manager, message_store) = metrics_manager(&dir, BrokerConfig::default()).await;
+        manager.inc_messages_in("TopicA", &TopicMessageType::Normal, 1, 10);
+        assert!(manager.prometheus_addr().is_none());
+        assert!(message_store.store_metrics_manager().is_none());
+    }
+
+    #[test]
+    fn topics_beyond_limit_share_overflow_label() {
+        let limiter = TopicLabelLimiter::new(2);
+        assert_eq!(limiter.label("TopicA"), "TopicA");
+        assert_eq!(limiter.label("TopicB"), "TopicB");
+        assert_eq!(limiter.label("TopicC"), OVERFLOW_TOPIC_LABEL);
+        assert_eq!(limiter.label("TopicA"), "TopicA");
+    }
+
+    #[test]
+    fn sanitize_label_value_strips_unsafe_characters() {
+        assert_eq!(sanitize_label_value(" Topic\n\"A\"\\ "), "Topic__A__");
+        assert_eq!(
+            sanitize_label_value(&"x".repeat(MAX_LABEL_VALUE_LENGTH + 10)).len(),
+            MAX_LABEL_VALUE_LENGTH
+        );
+    }
+}
@@ -0,0 +1,70 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use async_trait::async_trait;
+use opentelemetry::metrics::Result;
+use opentelemetry_sdk::metrics::data::ResourceMetrics;
+use opentelemetry_sdk::metrics::data::Temporality;
+use opentelemetry_sdk::metrics::exporter::PushMetricsExporter;
+use opentelemetry_sdk::metrics::reader::AggregationSelector;
+use opentelemetry_sdk::metrics::reader::DefaultAggregationSelector;
+use opentelemetry_sdk::metrics::reader::DefaultTemporalitySelector;
+use opentelemetry_sdk::metrics::reader::TemporalitySelector;
+use opentelemetry_sdk::metrics::Aggregation;
+use opentelemetry_sdk::metrics::InstrumentKind;
+use tracing::info;
+
+/// Writes every collected metric to the broker log, the `LOG` exporter type.
+#[derive(Default)]
+pub(crate) struct LoggingMetricsExporter {
+    temporality_selector: DefaultTemporalitySelector,
+    aggregation_selector: DefaultAggregationSelector,
+}
+
+impl TemporalitySelector for LoggingMetricsExporter {
+    fn temporality(&self, kind: InstrumentKind) -> Temporality {
+        self.temporality_selector.temporality(kind)
+    }
+}
+
+impl AggregationSelector for LoggingMetricsExporter {
+    fn aggregation(&self, kind: InstrumentKind) -> Aggregation {
+        self.aggregation_selector.aggregation(kind)
+    }
+}
+
+#[async_trait]
+impl PushMetricsExporter for LoggingMetricsExporter {
+    async fn export(&self, metrics: &mut ResourceMetrics) -> Result<()> {
+        for scope_metrics in &metrics.scope_metrics {
+            for metric in &scope_metrics.metrics {
+                info!(
+                    "metrics {} {}: {:?}",
+                    scope_metrics.scope.name, metric.name, metric.data
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn force_flush(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -40,6 +40,7 @@ use serde::Deserialize;
 use serde::Deserializer;
 use serde::Serialize;
 use serde::Serializer;
+use tracing::error;
 use tracing::warn;
 
 use crate::broker_path_config_helper::get_consumer_offset_path;
@@ -93,6 +94,32 @@ impl ConsumerOffsetManager {
         }
     }
 
+    /// Drops every committed, reset and pull offset of `group`, whatever the topic.
+    pub fn remove_offset(&self, group: &str) {
+        let belongs_to_group = |topic_at_group: &CheetahString| {
+            let arrays: Vec<&str> = topic_at_group.split(TOPIC_GROUP_SEPARATOR).collect();
+            arrays.len() == 2 && arrays[1] == group
+        };
+        self.consumer_offset_wrapper
+            .offset_table
+            .write()
+            .retain(|topic_at_group, _| {
+                let remove = belongs_to_group(topic_at_group);
+                if remove {
+                    warn!("clean group offset {}", topic_at_group);
+                }
+                !remove
+            });
+        self.consumer_offset_wrapper
+            .reset_offset_table
+            .write()
+            .retain(|topic_at_group, _| !belongs_to_group(topic_at_group));
+        self.consumer_offset_wrapper
+            .pull_offset_table
+            .write()
+            .retain(|topic_at_group, _| !belongs_to_group(topic_at_group));
+    }
+
     pub fn which_group_by_topic(&self, topic: &str) -> HashSet<CheetahString> {
         let read_guard = self.consumer_offset_wrapper.offset_table.read();
         let mut groups = HashSet::new();
@@ -192,6 +219,100 @@ impl ConsumerOffsetManager {
         -1
     }
 
+    /// The committed offset of `group` on each queue of `topic`.
+    pub fn query_offset_table(&self, group: &str, topic: &str) -> HashMap<i32, i64> {
+        let key = format!("{}{}{}", topic, TOPIC_GROUP_SEPARATOR, group);
+        self.consumer_offset_wrapper
+            .offset_table
+            .read()
+            .get(key.as_str())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Copies the committed offsets of `src_group` on `topic` to `dest_group`, replacing those
+    /// `dest_group` had. Nothing changes when `src_group` has no offset on `topic`.
+    pub fn clone_offset(&self, src_group: &str, dest_group: &str, topic: &str) {
+        let src_key = format!("{}{}{}", topic, TOPIC_GROUP_SEPARATOR, src_group);
+        let mut offset_table = self.consumer_offset_wrapper.offset_table.write();
+        if let Some(offsets) = offset_table.get(src_key.as_str()).cloned() {
+            offset_table.insert(
+                CheetahString::from_string(format!(
+                    "{}{}{}",
+                    topic, TOPIC_GROUP_SEPARATOR, dest_group
+                )),
+                offsets,
+            );
+        }
+    }
+
+    /// The smallest offset any group committed on each queue of `topic`, leaving out the comma
+    /// separated `filter_groups` and the offsets the store no longer holds messages for.
+    pub fn query_min_offset_in_all_group<MS: MessageStore>(
+        &self,
+        topic: &CheetahString,
+        filter_groups: Option<&str>,
+        message_store: &MS,
+    ) -> HashMap<i32, i64> {
+        let filter_groups = filter_groups
+            .map(|filter_groups| {
+                filter_groups
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|group| !group.is_empty())
+                    .collect::<HashSet<_>>()
+            })
+            .unwrap_or_default();
+        let mut queue_min_offset = HashMap::new();
+        for (topic_at_group, offsets) in self.consumer_offset_wrapper.offset_table.read().iter() {
+            let arr: Vec<&str> = topic_at_group.split(TOPIC_GROUP_SEPARATOR).collect();
+            if arr.len() != 2 || arr[0] != topic.as_str() || filter_groups.contains(arr[1]) {
+                continue;
+            }
+            for (queue_id, offset) in offsets {
+                if *offset < message_store.get_min_offset_in_queue(topic, *queue_id) {
+                    continue;
+                }
+                queue_min_offset
+                    .entry(*queue_id)
+                    .and_modify(|min_offset: &mut i64| *min_offset = (*min_offset).min(*offset))
+                    .or_insert(*offset);
+            }
+        }
+        queue_min_offset
+    }
+
+    /// How many commit log bytes each group falls behind the newest message, the most any of
+    /// its queues does.
+    pub fn fall_behind_bytes_by_group<MS: MessageStore>(
+        &self,
+        message_store: &MS,
+    ) -> HashMap<CheetahString /* group */, i64> {
+        let max_phy_offset = message_store.get_max_phy_offset();
+        let mut fall_behind_by_group = HashMap::new();
+        for (topic_at_group, offsets) in self.consumer_offset_wrapper.offset_table.read().iter() {
+            let arr: Vec<&str> = topic_at_group.split(TOPIC_GROUP_SEPARATOR).collect();
+            if arr.len() != 2 {
+                continue;
+            }
+            let topic = CheetahString::from_slice(arr[0]);
+            for (queue_id, offset) in offsets {
+                if *offset >= message_store.get_max_offset_in_queue(&topic, *queue_id) {
+                    continue;
+                }
+                let offset =
+                    (*offset).max(message_store.get_min_offset_in_queue(&topic, *queue_id));
+                let commit_log_offset =
+                    message_store.get_commit_log_offset_in_queue(&topic, *queue_id, offset);
+                let fall_behind = fall_behind_by_group
+                    .entry(CheetahString::from_slice(arr[1]))
+                    .or_insert(0);
+                *fall_behind = (*fall_behind).max(max_phy_offset - commit_log_offset);
+            }
+        }
+        fall_behind_by_group
+    }
+
     pub fn which_topic_by_consumer(&self, group: &CheetahString) -> HashSet<CheetahString> {
         let read_guard = self.consumer_offset_wrapper.offset_table.read();
         let mut topics = HashSet::new();
@@ -223,7 +344,13 @@ impl ConfigManager for ConsumerOffsetManager {
         if json_string.is_empty() {
             return;
         }
-        let wrapper = SerdeJsonUtils::from_json_str::<ConsumerOffsetWrapper>(json_string).unwrap();
+        let wrapper = match SerdeJsonUtils::from_json_str::<ConsumerOffsetWrapper>(json_string) {
+            Ok(wrapper) => wrapper,
+            Err(e) => {
+                error!("decode consumer offset failed: {:?}", e);
+                return;
+            }
+        };
         if !wrapper.offset_table.read().is_empty() {
             self.consumer_offset_wrapper
                 .offset_table
@@ -255,6 +382,32 @@ impl ConsumerOffsetManager {
             .insert(queue_id, offset);
     }
 
+    /// Returns the pulled offset of the queue, falling back to the committed offset when the
+    /// queue has not been pulled since the broker started.
+    pub fn query_pull_offset(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+    ) -> i64 {
+        let key = format!("{}{}{}", topic, TOPIC_GROUP_SEPARATOR, group);
+        if let Some(offset) = self
+            .consumer_offset_wrapper
+            .pull_offset_table
+            .read()
+            .get(key.as_str())
+            .and_then(|value| value.get(&queue_id))
+        {
+            return *offset;
+        }
+        self.query_offset(group, topic, queue_id)
+    }
+
+    /// Snapshot of the committed offsets keyed by `topic@group`.
+    pub fn offset_table(&self) -> HashMap<CheetahString, HashMap<i32, i64>> {
+        self.consumer_offset_wrapper.offset_table.read().clone()
+    }
+
     pub fn query_then_erase_reset_offset(
         &self,
         topic: &CheetahString,
@@ -17,23 +17,33 @@
 use std::collections::HashSet;
 use std::sync::Arc;
 use std::sync::Weak;
+use std::time::Duration;
 
 use cheetah_string::CheetahString;
 use dns_lookup::lookup_host;
 use rocketmq_common::common::broker::broker_config::BrokerIdentity;
 use rocketmq_common::common::config::TopicConfig;
 use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::common::mix_all;
+use rocketmq_common::common::namesrv::default_top_addressing::DefaultTopAddressing;
+use rocketmq_common::common::namesrv::top_addressing::TopAddressing;
 use rocketmq_common::utils::crc32_utils;
+use rocketmq_common::utils::name_server_address_utils::NameServerAddressUtils;
 use rocketmq_common::utils::serde_json_utils::SerdeJsonUtils;
 use rocketmq_remoting::clients::rocketmq_default_impl::RocketmqDefaultClient;
 use rocketmq_remoting::clients::RemotingClient;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::body::broker_body::broker_member_group::BrokerMemberGroup;
+use rocketmq_remoting::protocol::body::broker_body::broker_member_group::GetBrokerMemberGroupResponseBody;
 use rocketmq_remoting::protocol::body::broker_body::register_broker_body::RegisterBrokerBody;
 use rocketmq_remoting::protocol::body::kv_table::KVTable;
 use rocketmq_remoting::protocol::body::response::lock_batch_response_body::LockBatchResponseBody;
 use rocketmq_remoting::protocol::body::topic_info_wrapper::topic_config_wrapper::TopicConfigAndMappingSerializeWrapper;
+use rocketmq_remoting::protocol::header::client_request_header::GetRouteInfoRequestHeader;
 use rocketmq_remoting::protocol::header::lock_batch_mq_request_header::LockBatchMqRequestHeader;
+use rocketmq_remoting::protocol::header::namesrv::broker_request::GetBrokerMemberGroupRequestHeader;
+use rocketmq_remoting::protocol::header::namesrv::broker_request::UnRegisterBrokerRequestHeader;
 use rocketmq_remoting::protocol::header::namesrv::register_broker_header::RegisterBrokerRequestHeader;
 use rocketmq_remoting::protocol::header::namesrv::register_broker_header::RegisterBrokerResponseHeader;
 use rocketmq_remoting::protocol::header::namesrv::topic_operation_header::RegisterTopicRequestHeader;
@@ -54,14 +64,20 @@ use rocketmq_rust::ArcMut;
 use tracing::debug;
 use tracing::error;
 use tracing::info;
+use tracing::warn;
 
 use crate::error::BrokerError;
 use crate::error::BrokerError::BrokerClientError;
 use crate::Result;
 
+/// How often a registration the name server could not take right now is tried again.
+const REGISTER_BROKER_RETRY_TIMES: usize = 2;
+const REGISTER_BROKER_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
 pub struct BrokerOuterAPI {
     remoting_client: ArcMut<RocketmqDefaultClient<DefaultRemotingRequestProcessor>>,
-    name_server_address: Option<String>,
+    name_server_address: parking_lot::Mutex<Option<String>>,
+    top_addressing: Arc<dyn TopAddressing>,
     rpc_client: RpcClientImpl,
     client_metadata: ClientMetadata,
 }
@@ -75,7 +91,11 @@ impl BrokerOuterAPI {
         let client_metadata = ClientMetadata::new();
         Self {
             remoting_client: client.clone(),
-            name_server_address: None,
+            name_server_address: parking_lot::Mutex::new(None),
+            top_addressing: Arc::new(DefaultTopAddressing::new(
+                mix_all::get_ws_addr().into(),
+                None,
+            )),
             rpc_client: RpcClientImpl::new(client_metadata.clone(), client),
             client_metadata,
         }
@@ -95,7 +115,11 @@ impl BrokerOuterAPI {
         }
         Self {
             remoting_client: client.clone(),
-            name_server_address: None,
+            name_server_address: parking_lot::Mutex::new(None),
+            top_addressing: Arc::new(DefaultTopAddressing::new(
+                mix_all::get_ws_addr().into(),
+                None,
+            )),
             rpc_client: RpcClientImpl::new(client_metadata.clone(), client),
             client_metadata,
         }
@@ -129,15 +153,68 @@ impl BrokerOuterAPI {
     }
 
     pub async fn update_name_server_address_list(&self, addrs: CheetahString) {
-        let addr_vec = addrs
-            .split(";")
-            .map(CheetahString::from_slice)
-            .collect::<Vec<CheetahString>>();
         self.remoting_client
-            .update_name_server_address_list(addr_vec)
+            .update_name_server_address_list(NameServerAddressUtils::split_addresses(&addrs))
             .await
     }
 
+    /// Fetches the name server addresses from the address server and applies them when they
+    /// changed, returning the addresses in use.
+    pub async fn fetch_name_server_addr(&self) -> Option<String> {
+        let top_addressing = self.top_addressing.clone();
+        let addrs = tokio::task::spawn_blocking(move || top_addressing.fetch_ns_addr())
+            .await
+            .ok()
+            .flatten()
+            .filter(|addrs| !addrs.is_empty());
+        if let Some(addrs) = addrs {
+            let changed = {
+                let mut name_server_address = self.name_server_address.lock();
+                if name_server_address.as_deref() != Some(addrs.as_str()) {
+                    info!(
+                        "name server address changed, old={:?}, new={}",
+                        name_server_address, addrs
+                    );
+                    *name_server_address = Some(addrs.clone());
+                    true
+                } else {
+                    false
+                }
+            };
+            if changed {
+                self.update_name_server_address_list(addrs.into()).await;
+            }
+        }
+        self.name_server_address.lock().clone()
+    }
+
+    /// Replaces the name servers the broker talks to with `addrs` right away, returning the
+    /// ones no longer listed.
+    pub async fn switch_name_server_address_list(
+        &self,
+        addrs: CheetahString,
+    ) -> Vec<CheetahString> {
+        let new_addrs = NameServerAddressUtils::split_addresses(&addrs);
+        let (_, removed) = NameServerAddressUtils::diff_addresses(
+            self.remoting_client.get_name_server_address_list(),
+            &new_addrs,
+        );
+        *self.name_server_address.lock() = Some(addrs.to_string());
+        self.remoting_client
+            .update_name_server_address_list(new_addrs)
+            .await;
+        self.remoting_client.scan_available_name_srv().await;
+        removed
+    }
+
+    /// The name server addresses last switched to or fetched from the address server.
+    pub fn name_server_address(&self) -> Option<CheetahString> {
+        self.name_server_address
+            .lock()
+            .as_ref()
+            .map(|addrs| addrs.as_str().into())
+    }
+
     pub async fn update_name_server_address_list_by_dns_lookup(&self, domain: CheetahString) {
         let address_list = dns_lookup_address_by_domain(domain.as_str());
         self.remoting_client
@@ -174,6 +251,8 @@ impl BrokerOuterAPI {
                 enable_acting_master: Some(enable_acting_master),
                 compressed: false,
                 heartbeat_timeout_millis,
+                epoch: None,
+                max_phy_offset: None,
                 body_crc32: 0,
             };
 
@@ -193,18 +272,45 @@ impl BrokerOuterAPI {
                 let addr = namesrv_addr.clone();
                 let outer_api = this.clone();
                 let join_handle = tokio::spawn(async move {
-                    if let Some(outer_api) = outer_api.upgrade() {
-                        outer_api
+                    let outer_api = outer_api.upgrade()?;
+                    let mut retries = 0;
+                    loop {
+                        match outer_api
                             .register_broker(
                                 &addr,
                                 oneway,
                                 timeout_mills,
-                                cloned_header,
-                                cloned_body,
+                                cloned_header.clone(),
+                                cloned_body.clone(),
                             )
                             .await
-                    } else {
-                        None
+                        {
+                            Ok(result) => return result,
+                            Err(err)
+                                if retries < REGISTER_BROKER_RETRY_TIMES
+                                    && is_retryable_registration_error(&err) =>
+                            {
+                                retries += 1;
+                                warn!(
+                                    "Register broker to name remoting_server failed, retry {}/{} \
+                                     in {:?}, namesrv_addr={}, error={}",
+                                    retries,
+                                    REGISTER_BROKER_RETRY_TIMES,
+                                    REGISTER_BROKER_RETRY_INTERVAL,
+                                    addr,
+                                    err
+                                );
+                                tokio::time::sleep(REGISTER_BROKER_RETRY_INTERVAL).await;
+                            }
+                            Err(err) => {
+                                error!(
+                                    "Register broker to name remoting_server error, \
+                                     namesrv_addr={}, error={}",
+                                    addr, err
+                                );
+                                return None;
+                            }
+                        }
                     }
                 });
                 /*let handle =
@@ -215,11 +321,7 @@ impl BrokerOuterAPI {
                 let result = tokio::join!(handle);
                 match result.0 {
                     Ok(value) => {
-                        if let Some(v) = value {
-                            register_broker_result_list.push(v);
-                        } else {
-                            error!("Register broker to name remoting_server error");
-                        }
+                        register_broker_result_list.extend(value);
                     }
                     Err(e) => {
                         error!("Register broker to name remoting_server error, error={}", e);
@@ -238,7 +340,7 @@ impl BrokerOuterAPI {
         timeout_mills: u64,
         request_header: RegisterBrokerRequestHeader,
         body: Vec<u8>,
-    ) -> Option<RegisterBrokerResult> {
+    ) -> Result<Option<RegisterBrokerResult>> {
         debug!(
             "Register broker to name remoting_server, namesrv_addr={},request_code={:?}, \
              request_header={:?}, body={:?}",
@@ -254,46 +356,91 @@ impl BrokerOuterAPI {
             self.remoting_client
                 .invoke_oneway(namesrv_addr, request, timeout_mills)
                 .await;
-            return None;
+            return Ok(None);
         }
-        match self
+        let response = self
             .remoting_client
             .invoke_async(Some(namesrv_addr), request, timeout_mills)
-            .await
-        {
-            Ok(response) => match From::from(response.code()) {
-                ResponseCode::Success => {
+            .await?;
+        match From::from(response.code()) {
+            ResponseCode::Success => {
+                info!(
+                    "Register broker to name remoting_server success, namesrv_addr={} response \
+                     body={:?}",
+                    namesrv_addr,
+                    response.body()
+                );
+                let register_broker_result =
+                    response.decode_command_custom_header::<RegisterBrokerResponseHeader>();
+                let mut result = RegisterBrokerResult::default();
+                if let Some(header) = register_broker_result {
+                    result.ha_server_addr = header
+                        .ha_server_addr
+                        .clone()
+                        .unwrap_or(CheetahString::empty());
+                    result.master_addr =
+                        header.master_addr.clone().unwrap_or(CheetahString::empty());
+                }
+                if let Some(body) = response.body() {
+                    result.kv_table = SerdeJsonUtils::decode::<KVTable>(body.as_ref()).unwrap();
+                }
+                Ok(Some(result))
+            }
+            _ => Err(BrokerError::MQBrokerError(
+                response.code(),
+                response
+                    .remark()
+                    .cloned()
+                    .unwrap_or(CheetahString::empty())
+                    .to_string(),
+                namesrv_addr.to_string(),
+            )),
+        }
+    }
+
+    /// Removes the broker from the name server at `namesrv_addr`.
+    pub async fn unregister_broker(
+        &self,
+        namesrv_addr: &CheetahString,
+        cluster_name: CheetahString,
+        broker_addr: CheetahString,
+        broker_name: CheetahString,
+        broker_id: u64,
+    ) -> Result<()> {
+        let request = RemotingCommand::create_request_command(
+            RequestCode::UnregisterBroker,
+            UnRegisterBrokerRequestHeader {
+                broker_name,
+                broker_addr,
+                cluster_name,
+                broker_id,
+            },
+        );
+        let result = self
+            .remoting_client
+            .invoke_async(Some(namesrv_addr), request, 3000)
+            .await;
+        match result {
+            Ok(response) => {
+                if ResponseCode::from(response.code()) == ResponseCode::Success {
                     info!(
-                        "Register broker to name remoting_server success, namesrv_addr={} \
-                         response body={:?}",
-                        namesrv_addr,
-                        response.body()
+                        "Unregister broker from name remoting_server success, namesrv_addr={}",
+                        namesrv_addr
                     );
-                    let register_broker_result =
-                        response.decode_command_custom_header::<RegisterBrokerResponseHeader>();
-                    let mut result = RegisterBrokerResult::default();
-                    if let Some(header) = register_broker_result {
-                        result.ha_server_addr = header
-                            .ha_server_addr
-                            .clone()
-                            .unwrap_or(CheetahString::empty());
-                        result.master_addr =
-                            header.master_addr.clone().unwrap_or(CheetahString::empty());
-                    }
-                    if let Some(body) = response.body() {
-                        result.kv_table = SerdeJsonUtils::decode::<KVTable>(body.as_ref()).unwrap();
-                    }
-                    Some(result)
+                    Ok(())
+                } else {
+                    Err(BrokerError::MQBrokerError(
+                        response.code(),
+                        response
+                            .remark()
+                            .cloned()
+                            .unwrap_or(CheetahString::empty())
+                            .to_string(),
+                        namesrv_addr.to_string(),
+                    ))
                 }
-                _ => None,
-            },
-            Err(err) => {
-                error!(
-                    "Register broker to name remoting_server error, namesrv_addr={}, error={}",
-                    namesrv_addr, err
-                );
-                None
             }
+            Err(e) => Err(BrokerClientError(e)),
         }
     }
 
@@ -332,6 +479,113 @@ impl BrokerOuterAPI {
         &self.rpc_client
     }
 
+    /// Fetches the route of `topic` from the name server, `None` if the topic does not exist.
+    pub async fn get_topic_route_info_from_name_server(
+        &self,
+        topic: &CheetahString,
+        timeout_millis: u64,
+    ) -> Result<Option<TopicRouteData>> {
+        let request = RemotingCommand::create_request_command(
+            RequestCode::GetRouteinfoByTopic,
+            GetRouteInfoRequestHeader {
+                topic: topic.clone(),
+                accept_standard_json_only: None,
+                topic_request_header: None,
+            },
+        );
+        let result = self
+            .remoting_client
+            .invoke_async(None, request, timeout_millis)
+            .await;
+        match result {
+            Ok(response) => match ResponseCode::from(response.code()) {
+                ResponseCode::Success => Ok(response
+                    .get_body()
+                    .and_then(|body| TopicRouteData::decode(body).ok())),
+                ResponseCode::TopicNotExist => Ok(None),
+                _ => Err(BrokerError::MQBrokerError(
+                    response.code(),
+                    response
+                        .remark()
+                        .cloned()
+                        .unwrap_or(CheetahString::empty())
+                        .to_string(),
+                    "".to_string(),
+                )),
+            },
+            Err(e) => Err(BrokerClientError(e)),
+        }
+    }
+
+    /// Fetches the replica group of `broker_name` from the name server.
+    pub async fn sync_broker_member_group(
+        &self,
+        cluster_name: &CheetahString,
+        broker_name: &CheetahString,
+    ) -> Result<Option<BrokerMemberGroup>> {
+        let request = RemotingCommand::create_request_command(
+            RequestCode::GetBrokerMemberGroup,
+            GetBrokerMemberGroupRequestHeader::new(cluster_name.clone(), broker_name.clone()),
+        );
+        let result = self.remoting_client.invoke_async(None, request, 3000).await;
+        match result {
+            Ok(response) => {
+                if ResponseCode::from(response.code()) == ResponseCode::Success {
+                    Ok(response.get_body().and_then(|body| {
+                        GetBrokerMemberGroupResponseBody::decode(body)
+                            .ok()
+                            .and_then(|body| body.broker_member_group)
+                    }))
+                } else {
+                    Err(BrokerError::MQBrokerError(
+                        response.code(),
+                        response
+                            .remark()
+                            .cloned()
+                            .unwrap_or(CheetahString::empty())
+                            .to_string(),
+                        "".to_string(),
+                    ))
+                }
+            }
+            Err(e) => Err(BrokerClientError(e)),
+        }
+    }
+
+    /// Fetches the runtime info of the broker at `addr`.
+    pub async fn get_broker_runtime_info(
+        &self,
+        addr: &CheetahString,
+        timeout_millis: u64,
+    ) -> Result<KVTable> {
+        let request = RemotingCommand::create_remoting_command(RequestCode::GetBrokerRuntimeInfo);
+        let result = self
+            .remoting_client
+            .invoke_async(Some(addr), request, timeout_millis)
+            .await;
+        match result {
+            Ok(response) => {
+                if ResponseCode::from(response.code()) == ResponseCode::Success {
+                    Ok(response
+                        .get_body()
+                        .and_then(|body| serde_json::from_slice::<KVTable>(body).ok())
+                        .unwrap_or_default())
+                } else {
+                    Err(BrokerError::MQBrokerError(
+                        response.code(),
+                        response
+                            .remark()
+                            .cloned()
+                            .unwrap_or(CheetahString::empty())
+                            .to_string(),
+                        addr.to_string(),
+                    ))
+                }
+            }
+            Err(e) => Err(BrokerClientError(e)),
+        }
+    }
+
     pub async fn lock_batch_mq_async(
         &self,
         addr: &CheetahString,
@@ -438,10 +692,36 @@ fn dns_lookup_address_by_domain(domain: &str) -> Vec<CheetahString> {
     address_list
 }
 
+/// Transport failures and a busy name server are worth another try, anything else the name
+/// server refused keeps failing until the broker state changes.
+fn is_retryable_registration_error(error: &BrokerError) -> bool {
+    match error {
+        BrokerClientError(_) => true,
+        BrokerError::MQBrokerError(..) => error.response_code() == ResponseCode::SystemBusy,
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn only_busy_name_server_registrations_are_retried() {
+        let refused = |code: ResponseCode, remark: &str| {
+            BrokerError::MQBrokerError(code as i32, remark.to_string(), "127.0.0.1:9876".into())
+        };
+        assert!(is_retryable_registration_error(&refused(
+            ResponseCode::SystemBusy,
+            "the route table was not writable within 3000ms"
+        )));
+        assert!(!is_retryable_registration_error(&refused(
+            ResponseCode::SystemError,
+            "broker 10.0.0.2:10911 registers with epoch 1, but epoch 2 is already recorded for \
+             broker id 0"
+        )));
+    }
+
     #[test]
     fn dns_lookup_address_by_domain_returns_correct_addresses() {
         let domain = "localhost:8080";
@@ -0,0 +1,59 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use rocketmq_remoting::protocol::remoting_command::BodyParts;
+use rocketmq_store::base::get_message_result::GetMessageResult;
+use rocketmq_store::log_file::mapped_file::default_mapped_file_impl::MappedBuffer;
+use tracing::warn;
+
+/// Exposes the mapped buffers of a [`GetMessageResult`] as the body of a pull response, so the
+/// messages are written to the socket straight from the mapped files.
+///
+/// The mapped files stay referenced until the transfer is dropped. Messages of a file forcibly
+/// unmapped before the transfer was created are left out.
+pub(crate) struct ManyMessageTransfer {
+    _get_message_result: GetMessageResult,
+    buffers: Vec<MappedBuffer>,
+}
+
+impl ManyMessageTransfer {
+    pub fn new(get_message_result: GetMessageResult) -> Self {
+        let buffers = get_message_result
+            .message_mapped_list()
+            .iter()
+            .filter_map(|buffer| match buffer.get_buffer() {
+                Ok(mapped) => Some(mapped),
+                Err(error) => {
+                    warn!(
+                        "skip message at offset {} of the pull response: {}",
+                        buffer.start_offset, error
+                    );
+                    None
+                }
+            })
+            .collect();
+        Self {
+            _get_message_result: get_message_result,
+            buffers,
+        }
+    }
+}
+
+impl BodyParts for ManyMessageTransfer {
+    fn parts(&self) -> Vec<&[u8]> {
+        self.buffers.iter().map(|buffer| &buffer[..]).collect()
+    }
+}
@@ -0,0 +1,238 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+
+use cheetah_string::CheetahString;
+use rocketmq_store::plugin::dyn_message_store::BoxedMessageStore;
+use rocketmq_store::plugin::message_store_plugin_context::MessageStorePluginContext;
+
+use crate::plugin::put_count_plugin_message_store::PutCountPluginMessageStore;
+use crate::BrokerError::MessageStorePluginError;
+
+/// Wraps the store handed in with one plugin.
+pub(crate) type MessageStorePluginBuilder =
+    Box<dyn Fn(&MessageStorePluginContext, BoxedMessageStore) -> BoxedMessageStore + Send + Sync>;
+
+/// Builds the message store the broker serves requests from, out of the plugins named by
+/// `messageStorePlugIn`.
+pub(crate) struct MessageStoreFactory {
+    plugins: HashMap<CheetahString, MessageStorePluginBuilder>,
+}
+
+impl MessageStoreFactory {
+    pub fn register<F>(&mut self, name: impl Into<CheetahString>, builder: F)
+    where
+        F: Fn(&MessageStorePluginContext, BoxedMessageStore) -> BoxedMessageStore
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.plugins.insert(name.into(), Box::new(builder));
+    }
+
+    /// Wraps `message_store` with the configured plugins. The first plugin listed ends up
+    /// outermost, so it sees every call before the plugins listed after it.
+    pub fn build(
+        &self,
+        context: &MessageStorePluginContext,
+        message_store: BoxedMessageStore,
+    ) -> crate::Result<BoxedMessageStore> {
+        let plugin_names = context
+            .broker_config()
+            .message_store_plugin
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<_>>();
+        let mut builders = Vec::with_capacity(plugin_names.len());
+        for name in plugin_names {
+            let Some(builder) = self.plugins.get(name) else {
+                return Err(MessageStorePluginError(format!(
+                    "message store plugin {name} is not registered"
+                )));
+            };
+            builders.push(builder);
+        }
+        Ok(builders
+            .into_iter()
+            .rev()
+            .fold(message_store, |next, builder| builder(context, next)))
+    }
+}
+
+impl Default for MessageStoreFactory {
+    fn default() -> Self {
+        let mut factory = Self {
+            plugins: HashMap::new(),
+        };
+        factory.register(PutCountPluginMessageStore::PLUGIN_NAME, |_, next| {
+            Box::new(PutCountPluginMessageStore::new(next))
+        });
+        factory
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+    use parking_lot::Mutex;
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+    use rocketmq_common::common::message::MessageTrait;
+    use rocketmq_rust::ArcMut;
+    use rocketmq_store::base::message_result::PutMessageResult;
+    use rocketmq_store::base::message_status_enum::PutMessageStatus;
+    use rocketmq_store::config::flush_disk_type::FlushDiskType;
+    use rocketmq_store::config::message_store_config::MessageStoreConfig;
+    use rocketmq_store::log_file::MessageStore;
+    use rocketmq_store::message_store::default_message_store::DefaultMessageStore;
+    use rocketmq_store::plugin::abstract_plugin_message_store::AbstractPluginMessageStore;
+
+    use super::*;
+
+    struct RecordingPluginMessageStore {
+        name: &'static str,
+        calls: Arc<Mutex<Vec<&'static str>>>,
+        next: BoxedMessageStore,
+    }
+
+    impl AbstractPluginMessageStore for RecordingPluginMessageStore {
+        fn next(&self) -> &BoxedMessageStore {
+            &self.next
+        }
+
+        fn next_mut(&mut self) -> &mut BoxedMessageStore {
+            &mut self.next
+        }
+
+        async fn put_message(&mut self, msg: MessageExtBrokerInner) -> PutMessageResult {
+            self.calls.lock().push(self.name);
+            self.next.put_message(msg).await
+        }
+    }
+
+    fn recording_factory(calls: &Arc<Mutex<Vec<&'static str>>>) -> MessageStoreFactory {
+        let mut factory = MessageStoreFactory::default();
+        for name in ["A", "B"] {
+            let calls = calls.clone();
+            factory.register(name, move |_, next| {
+                Box::new(RecordingPluginMessageStore {
+                    name,
+                    calls: calls.clone(),
+                    next,
+                })
+            });
+        }
+        factory
+    }
+
+    fn context(dir: &tempfile::TempDir, message_store_plugin: &str) -> MessageStorePluginContext {
+        MessageStorePluginContext::new(
+            Arc::new(MessageStoreConfig {
+                store_path_root_dir: dir.path().to_string_lossy().into_owned().into(),
+                mapped_file_size_commit_log: 1024 * 1024,
+                flush_disk_type: FlushDiskType::AsyncFlush,
+                ..MessageStoreConfig::default()
+            }),
+            Arc::new(BrokerConfig {
+                message_store_plugin: message_store_plugin.into(),
+                ..BrokerConfig::default()
+            }),
+            None,
+        )
+    }
+
+    async fn start_store(context: &MessageStorePluginContext) -> ArcMut<DefaultMessageStore> {
+        let mut store = ArcMut::new(DefaultMessageStore::new(
+            context.message_store_config().clone(),
+            context.broker_config().clone(),
+            Arc::new(Mutex::new(Default::default())),
+            None,
+            false,
+        ));
+        let store_clone = store.clone();
+        store.set_message_store_arc(Some(store_clone));
+        assert!(store.load().await);
+        store.start().unwrap();
+        store
+    }
+
+    fn message(topic: &str) -> MessageExtBrokerInner {
+        let mut msg = MessageExtBrokerInner::default();
+        msg.set_topic(topic.into());
+        msg.set_body(Bytes::from_static(b"plugin"));
+        msg
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn first_configured_plugin_is_outermost() {
+        let dir = tempfile::tempdir().unwrap();
+        let context = context(&dir, "B, A");
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let store = start_store(&context).await;
+        let mut plugin_message_store = recording_factory(&calls)
+            .build(&context, Box::new(store.clone()))
+            .unwrap();
+
+        let result = plugin_message_store
+            .put_message(message("PluginTopic"))
+            .await;
+        assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        assert_eq!(*calls.lock(), vec!["B", "A"]);
+        // calls the plugins do not override still reach the wrapped store
+        assert_eq!(
+            plugin_message_store.get_max_phy_offset(),
+            store.get_max_phy_offset()
+        );
+        plugin_message_store.shutdown();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn put_count_plugin_counts_successful_puts_per_topic() {
+        let dir = tempfile::tempdir().unwrap();
+        let context = context(&dir, PutCountPluginMessageStore::PLUGIN_NAME);
+        let store = start_store(&context).await;
+        let mut plugin_message_store = MessageStoreFactory::default()
+            .build(&context, Box::new(store))
+            .unwrap();
+
+        for topic in ["CountedTopic", "CountedTopic", "OtherTopic"] {
+            plugin_message_store.put_message(message(topic)).await;
+        }
+        let runtime_info = plugin_message_store.get_runtime_info();
+        assert_eq!(runtime_info["putCount_CountedTopic"], "2");
+        assert_eq!(runtime_info["putCount_OtherTopic"], "1");
+        plugin_message_store.shutdown();
+    }
+
+    #[test]
+    fn unknown_plugin_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let context = context(&dir, "NoSuchPlugin");
+        let store = ArcMut::new(DefaultMessageStore::new(
+            context.message_store_config().clone(),
+            context.broker_config().clone(),
+            Arc::new(Mutex::new(Default::default())),
+            None,
+            false,
+        ));
+        let result = MessageStoreFactory::default().build(&context, Box::new(store));
+        assert!(matches!(result, Err(MessageStorePluginError(_))));
+    }
+}
@@ -0,0 +1,93 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_common::common::message::message_batch::MessageExtBatch;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_store::base::message_result::PutMessageResult;
+use rocketmq_store::log_file::MessageStore;
+use rocketmq_store::plugin::abstract_plugin_message_store::AbstractPluginMessageStore;
+use rocketmq_store::plugin::dyn_message_store::BoxedMessageStore;
+
+/// Example plugin: counts the messages successfully put into each topic, and reports the
+/// counts as `putCount_{topic}` in the store runtime info.
+pub(crate) struct PutCountPluginMessageStore {
+    next: BoxedMessageStore,
+    put_count_table: Mutex<HashMap<CheetahString, u64>>,
+}
+
+impl PutCountPluginMessageStore {
+    pub const PLUGIN_NAME: &'static str = "PutCountPluginMessageStore";
+
+    pub fn new(next: BoxedMessageStore) -> Self {
+        Self {
+            next,
+            put_count_table: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get_put_count(&self, topic: &CheetahString) -> u64 {
+        self.put_count_table
+            .lock()
+            .get(topic)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn inc_put_count(&self, topic: CheetahString, result: &PutMessageResult, nums: u64) {
+        if result.is_ok() {
+            *self.put_count_table.lock().entry(topic).or_default() += nums;
+        }
+    }
+}
+
+impl AbstractPluginMessageStore for PutCountPluginMessageStore {
+    fn next(&self) -> &BoxedMessageStore {
+        &self.next
+    }
+
+    fn next_mut(&mut self) -> &mut BoxedMessageStore {
+        &mut self.next
+    }
+
+    async fn put_message(&mut self, msg: MessageExtBrokerInner) -> PutMessageResult {
+        let topic = msg.get_topic().clone();
+        let result = self.next.put_message(msg).await;
+        self.inc_put_count(topic, &result, 1);
+        result
+    }
+
+    async fn put_messages(&mut self, msg_batch: MessageExtBatch) -> PutMessageResult {
+        let topic = msg_batch.message_ext_broker_inner.get_topic().clone();
+        let result = self.next.put_messages(msg_batch).await;
+        let nums = result
+            .append_message_result()
+            .map_or(0, |append| append.msg_num.max(0) as u64);
+        self.inc_put_count(topic, &result, nums);
+        result
+    }
+
+    fn get_runtime_info(&self) -> HashMap<String, String> {
+        let mut runtime_info = self.next.get_runtime_info();
+        for (topic, count) in self.put_count_table.lock().iter() {
+            runtime_info.insert(format!("putCount_{topic}"), count.to_string());
+        }
+        runtime_info
+    }
+}
@@ -14,7 +14,12 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
 use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
@@ -25,6 +30,8 @@ use rocketmq_store::log_file::MessageStore;
 use tracing::info;
 
 use self::client_manage_processor::ClientManageProcessor;
+use crate::metrics::broker_metrics_manager::BrokerMetricsManager;
+use crate::metrics::broker_metrics_manager::RequestSummary;
 use crate::processor::ack_message_processor::AckMessageProcessor;
 use crate::processor::admin_broker_processor::AdminBrokerProcessor;
 use crate::processor::change_invisible_time_processor::ChangeInvisibleTimeProcessor;
@@ -76,6 +83,8 @@ pub struct BrokerRequestProcessor<MS, TS> {
     pub(crate) query_assignment_processor: ArcMut<QueryAssignmentProcessor>,
     pub(crate) end_transaction_processor: ArcMut<EndTransactionProcessor<TS, MS>>,
     pub(crate) admin_broker_processor: ArcMut<AdminBrokerProcessor>,
+    pub(crate) broker_metrics_manager: Arc<BrokerMetricsManager>,
+    pub(crate) is_isolated: Arc<AtomicBool>,
 }
 impl<MS, TS> Clone for BrokerRequestProcessor<MS, TS> {
     fn clone(&self) -> Self {
@@ -95,6 +104,8 @@ impl<MS, TS> Clone for BrokerRequestProcessor<MS, TS> {
             query_assignment_processor: self.query_assignment_processor.clone(),
             query_message_processor: self.query_message_processor.clone(),
             end_transaction_processor: self.end_transaction_processor.clone(),
+            broker_metrics_manager: self.broker_metrics_manager.clone(),
+            is_isolated: self.is_isolated.clone(),
         }
     }
 }
@@ -112,6 +123,21 @@ where
     ) -> Result<Option<RemotingCommand>> {
         let request_code = RequestCode::from(request.code());
         info!("process_request: {:?}", request_code);
+        if rejected_while_isolated(request_code) && self.is_isolated.load(Ordering::Acquire) {
+            return Ok(Some(
+                RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::SystemBusy,
+                    format!(
+                        "broker is isolated while catching up, request {} rejected",
+                        request_code.to_i32()
+                    ),
+                ),
+            ));
+        }
+        let in_flight = self
+            .broker_metrics_manager
+            .processor_in_flight(processor_name(request_code));
+        let request_summary = request_summary(request_code, &request, &channel);
         let result = match request_code {
             RequestCode::SendMessage
             | RequestCode::SendMessageV2
@@ -160,12 +186,83 @@ where
                     .await
             }
 
+            RequestCode::QueryAssignment | RequestCode::SetMessageRequestMode => {
+                self.query_assignment_processor
+                    .process_request(channel, ctx, request_code, request)
+                    .await
+            }
+
             _ => {
                 self.admin_broker_processor
                     .process_request(channel, ctx, request_code, request)
                     .await
             }
         };
+        self.broker_metrics_manager
+            .record_if_slow(&in_flight, &request_summary);
         Ok(result)
     }
 }
+
+/// Requests an isolated broker refuses, so clients keep using brokers that are already online.
+fn rejected_while_isolated(request_code: RequestCode) -> bool {
+    matches!(
+        request_code,
+        RequestCode::HeartBeat | RequestCode::PullMessage | RequestCode::LitePullMessage
+    )
+}
+
+/// The topic and group of a request for the slow request log, read from the header fields.
+/// Send V2 headers carry them under the short names `b` and `a`.
+fn request_summary(
+    request_code: RequestCode,
+    request: &RemotingCommand,
+    channel: &Channel,
+) -> RequestSummary {
+    let compact_header = matches!(
+        request_code,
+        RequestCode::SendMessageV2
+            | RequestCode::SendBatchMessage
+            | RequestCode::SendReplyMessageV2
+    );
+    let field = |names: &[&str]| {
+        let ext_fields = request.ext_fields()?;
+        names.iter().find_map(|name| ext_fields.get(*name)).cloned()
+    };
+    let (topic, group) = if compact_header {
+        (field(&["b", "topic"]), field(&["a", "producerGroup"]))
+    } else {
+        (
+            field(&["topic"]),
+            field(&["consumerGroup", "producerGroup", "group"]),
+        )
+    };
+    RequestSummary {
+        code: request.code(),
+        topic,
+        group,
+        peer: channel.remote_address(),
+    }
+}
+
+/// The processor label of `request_code` in the `rocketmq_processor_watermark` gauge.
+fn processor_name(request_code: RequestCode) -> &'static str {
+    match request_code {
+        RequestCode::SendMessage
+        | RequestCode::SendMessageV2
+        | RequestCode::SendBatchMessage
+        | RequestCode::ConsumerSendMsgBack => "send",
+        RequestCode::SendReplyMessage | RequestCode::SendReplyMessageV2 => "reply",
+        RequestCode::HeartBeat | RequestCode::UnregisterClient | RequestCode::CheckClientConfig => {
+            "client_manager"
+        }
+        RequestCode::PullMessage | RequestCode::LitePullMessage => "pull",
+        RequestCode::GetConsumerListByGroup
+        | RequestCode::UpdateConsumerOffset
+        | RequestCode::QueryConsumerOffset => "consumer_manager",
+        RequestCode::QueryMessage | RequestCode::ViewMessageById => "query",
+        RequestCode::EndTransaction => "end_transaction",
+        RequestCode::QueryAssignment | RequestCode::SetMessageRequestMode => "query_assignment",
+        _ => "admin",
+    }
+}
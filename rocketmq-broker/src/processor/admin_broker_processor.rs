@@ -14,6 +14,7 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
@@ -21,34 +22,47 @@ use rocketmq_common::common::server::config::ServerConfig;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
-use rocketmq_remoting::protocol::body::broker_body::broker_member_group::BrokerMemberGroup;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_rust::ArcMut;
 use rocketmq_store::config::message_store_config::MessageStoreConfig;
 use rocketmq_store::message_store::default_message_store::DefaultMessageStore;
+use rocketmq_store::plugin::dyn_message_store::BoxedMessageStore;
 use rocketmq_store::stats::broker_stats::BrokerStats;
 use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
 use tracing::warn;
 
+use crate::acl::plain_access_validator::PlainAccessValidator;
+use crate::broker::broker_member_group_cache::BrokerMemberGroupCache;
+use crate::broker::broker_task_manager::TaskLastRuns;
 use crate::client::manager::consumer_manager::ConsumerManager;
+use crate::client::manager::producer_manager::ProducerManager;
 use crate::client::rebalance::rebalance_lock_manager::RebalanceLockManager;
+use crate::coldctr::cold_data_cg_ctr_service::ColdDataCgCtrService;
+use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
 use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
 use crate::out_api::broker_outer_api::BrokerOuterAPI;
+use crate::processor::admin_broker_processor::acl_request_handler::AclRequestHandler;
 use crate::processor::admin_broker_processor::batch_mq_handler::BatchMqHandler;
 use crate::processor::admin_broker_processor::broker_config_request_handler::BrokerConfigRequestHandler;
+use crate::processor::admin_broker_processor::broker_member_group_handler::BrokerMemberGroupHandler;
 use crate::processor::admin_broker_processor::consumer_request_handler::ConsumerRequestHandler;
 use crate::processor::admin_broker_processor::offset_request_handler::OffsetRequestHandler;
+use crate::processor::admin_broker_processor::subscription_group_handler::SubscriptionGroupHandler;
 use crate::processor::admin_broker_processor::topic_request_handler::TopicRequestHandler;
 use crate::processor::pop_inflight_message_counter::PopInflightMessageCounter;
 use crate::schedule::schedule_message_service::ScheduleMessageService;
+use crate::subscription::manager::subscription_group_manager::SubscriptionGroupManager;
 use crate::topic::manager::topic_config_manager::TopicConfigManager;
 use crate::topic::manager::topic_queue_mapping_manager::TopicQueueMappingManager;
 
+mod acl_request_handler;
 mod batch_mq_handler;
 mod broker_config_request_handler;
+mod broker_member_group_handler;
 mod consumer_request_handler;
 mod offset_request_handler;
+mod subscription_group_handler;
 mod topic_request_handler;
 
 pub struct AdminBrokerProcessor {
@@ -57,24 +71,35 @@ pub struct AdminBrokerProcessor {
     consumer_request_handler: ConsumerRequestHandler,
     offset_request_handler: OffsetRequestHandler,
     batch_mq_handler: BatchMqHandler,
+    broker_member_group_handler: BrokerMemberGroupHandler,
+    subscription_group_handler: SubscriptionGroupHandler,
+    acl_request_handler: AclRequestHandler,
 }
 
 impl AdminBrokerProcessor {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         broker_config: Arc<BrokerConfig>,
         server_config: Arc<ServerConfig>,
         message_store_config: Arc<MessageStoreConfig>,
         topic_config_manager: TopicConfigManager,
         consumer_offset_manager: ConsumerOffsetManager,
+        subscription_group_manager: Arc<SubscriptionGroupManager<BoxedMessageStore>>,
         topic_queue_mapping_manager: Arc<TopicQueueMappingManager>,
         default_message_store: ArcMut<DefaultMessageStore>,
         schedule_message_service: ScheduleMessageService,
         broker_stats: Option<Arc<BrokerStats<DefaultMessageStore>>>,
         consume_manager: Arc<ConsumerManager>,
+        producer_manager: Arc<ProducerManager>,
+        consumer_filter_manager: Arc<ConsumerFilterManager>,
         broker_out_api: Arc<BrokerOuterAPI>,
         broker_stats_manager: Arc<BrokerStatsManager>,
         rebalance_lock_manager: Arc<RebalanceLockManager>,
-        broker_member_group: Arc<BrokerMemberGroup>,
+        broker_member_group: Arc<BrokerMemberGroupCache>,
+        is_isolated: Arc<AtomicBool>,
+        task_last_runs: TaskLastRuns,
+        cold_data_cg_ctr_service: Arc<ColdDataCgCtrService>,
+        plain_access_validator: PlainAccessValidator,
     ) -> Self {
         let inner = Inner {
             broker_config,
@@ -82,28 +107,41 @@ impl AdminBrokerProcessor {
             message_store_config,
             topic_config_manager,
             consumer_offset_manager,
+            subscription_group_manager,
             topic_queue_mapping_manager,
             default_message_store,
             pop_inflight_message_counter: Arc::new(PopInflightMessageCounter),
             schedule_message_service,
             broker_stats,
             consume_manager,
+            producer_manager,
+            consumer_filter_manager,
             broker_out_api,
             broker_stats_manager,
             rebalance_lock_manager,
             broker_member_group,
+            is_isolated,
+            task_last_runs,
+            cold_data_cg_ctr_service,
+            plain_access_validator,
         };
         let topic_request_handler = TopicRequestHandler::new(inner.clone());
         let broker_config_request_handler = BrokerConfigRequestHandler::new(inner.clone());
         let consumer_request_handler = ConsumerRequestHandler::new(inner.clone());
         let offset_request_handler = OffsetRequestHandler::new(inner.clone());
         let batch_mq_handler = BatchMqHandler::new(inner.clone());
+        let broker_member_group_handler = BrokerMemberGroupHandler::new(inner.clone());
+        let subscription_group_handler = SubscriptionGroupHandler::new(inner.clone());
+        let acl_request_handler = AclRequestHandler::new(inner.clone());
         AdminBrokerProcessor {
             topic_request_handler,
             broker_config_request_handler,
             consumer_request_handler,
             offset_request_handler,
             batch_mq_handler,
+            broker_member_group_handler,
+            subscription_group_handler,
+            acl_request_handler,
         }
     }
 }
@@ -122,6 +160,11 @@ impl AdminBrokerProcessor {
                     .update_and_create_topic(channel, ctx, request_code, request)
                     .await
             }
+            RequestCode::UpdateAndCreateStaticTopic => {
+                self.topic_request_handler
+                    .update_and_create_static_topic(channel, ctx, request_code, request)
+                    .await
+            }
             RequestCode::UpdateAndCreateTopicList => {
                 self.topic_request_handler
                     .update_and_create_topic_list(channel, ctx, request_code, request)
@@ -167,11 +210,41 @@ impl AdminBrokerProcessor {
                     .get_consume_stats(channel, ctx, request_code, request)
                     .await
             }
+            RequestCode::GetBrokerConsumeStats => {
+                self.consumer_request_handler
+                    .get_broker_consume_stats(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::QueryConsumeQueue => {
+                self.consumer_request_handler
+                    .query_consume_queue(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::UpdateAndCreateSubscriptionGroup => {
+                self.subscription_group_handler
+                    .update_and_create_subscription_group(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::DeleteSubscriptionGroup => {
+                self.subscription_group_handler
+                    .delete_subscription_group(channel, ctx, request_code, request)
+                    .await
+            }
             RequestCode::GetAllConsumerOffset => {
                 self.consumer_request_handler
                     .get_all_consumer_offset(channel, ctx, request_code, request)
                     .await
             }
+            RequestCode::CloneGroupOffset => {
+                self.consumer_request_handler
+                    .clone_group_offset(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::QueryCorrectionOffset => {
+                self.consumer_request_handler
+                    .query_correction_offset(channel, ctx, request_code, request)
+                    .await
+            }
             RequestCode::GetTopicConfig => {
                 self.topic_request_handler
                     .get_topic_config(channel, ctx, request_code, request)
@@ -182,6 +255,11 @@ impl AdminBrokerProcessor {
                     .get_broker_runtime_info(channel, ctx, request_code, request)
                     .await
             }
+            RequestCode::QueryDelayProgress => {
+                self.broker_config_request_handler
+                    .query_delay_progress(channel, ctx, request_code, request)
+                    .await
+            }
             RequestCode::QueryTopicConsumeByWho => {
                 self.topic_request_handler
                     .query_topic_consume_by_who(channel, ctx, request_code, request)
@@ -202,6 +280,16 @@ impl AdminBrokerProcessor {
                     .get_min_offset(channel, ctx, request_code, request)
                     .await
             }
+            RequestCode::SearchOffsetByTimestamp => {
+                self.offset_request_handler
+                    .search_offset_by_timestamp(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::GetEarliestMsgStoreTime => {
+                self.offset_request_handler
+                    .get_earliest_msg_storetime(channel, ctx, request_code, request)
+                    .await
+            }
 
             RequestCode::LockBatchMq => {
                 self.batch_mq_handler
@@ -214,6 +302,51 @@ impl AdminBrokerProcessor {
                     .unlock_batch_mq(channel, ctx, request_code, request)
                     .await
             }
+            RequestCode::GetBrokerClusterInfo => {
+                self.broker_member_group_handler
+                    .get_broker_cluster_info(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::NotifyMinBrokerIdChange => {
+                self.broker_member_group_handler
+                    .notify_min_broker_id_change(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::UpdateColdDataFlowCtrConfig => {
+                self.broker_config_request_handler
+                    .update_cold_data_flow_ctr_config(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::RemoveColdDataFlowCtrConfig => {
+                self.broker_config_request_handler
+                    .remove_cold_data_flow_ctr_config(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::GetColdDataFlowCtrInfo => {
+                self.broker_config_request_handler
+                    .get_cold_data_flow_ctr_info(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::UpdateAndCreateAclConfig => {
+                self.acl_request_handler
+                    .update_and_create_acl_config(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::DeleteAclConfig => {
+                self.acl_request_handler
+                    .delete_acl_config(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::GetBrokerClusterAclInfo => {
+                self.acl_request_handler
+                    .get_broker_cluster_acl_info(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::UpdateGlobalWhiteAddrsConfig => {
+                self.acl_request_handler
+                    .update_global_white_addrs_config(channel, ctx, request_code, request)
+                    .await
+            }
             _ => Some(get_unknown_cmd_response(request_code)),
         }
     }
@@ -238,14 +371,21 @@ struct Inner {
     message_store_config: Arc<MessageStoreConfig>,
     topic_config_manager: TopicConfigManager,
     consumer_offset_manager: ConsumerOffsetManager,
+    subscription_group_manager: Arc<SubscriptionGroupManager<BoxedMessageStore>>,
     topic_queue_mapping_manager: Arc<TopicQueueMappingManager>,
     default_message_store: ArcMut<DefaultMessageStore>,
     pop_inflight_message_counter: Arc<PopInflightMessageCounter>,
     schedule_message_service: ScheduleMessageService,
     broker_stats: Option<Arc<BrokerStats<DefaultMessageStore>>>,
     consume_manager: Arc<ConsumerManager>,
+    producer_manager: Arc<ProducerManager>,
+    consumer_filter_manager: Arc<ConsumerFilterManager>,
     broker_out_api: Arc<BrokerOuterAPI>,
     broker_stats_manager: Arc<BrokerStatsManager>,
     rebalance_lock_manager: Arc<RebalanceLockManager>,
-    broker_member_group: Arc<BrokerMemberGroup>,
+    broker_member_group: Arc<BrokerMemberGroupCache>,
+    is_isolated: Arc<AtomicBool>,
+    task_last_runs: TaskLastRuns,
+    cold_data_cg_ctr_service: Arc<ColdDataCgCtrService>,
+    plain_access_validator: PlainAccessValidator,
 }
@@ -0,0 +1,399 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::header::create_access_config_request_header::CreateAccessConfigRequestHeader;
+use rocketmq_remoting::protocol::header::delete_access_config_request_header::DeleteAccessConfigRequestHeader;
+use rocketmq_remoting::protocol::header::get_broker_acl_config_response_header::GetBrokerAclConfigResponseHeader;
+use rocketmq_remoting::protocol::header::update_global_white_addrs_config_request_header::UpdateGlobalWhiteAddrsConfigRequestHeader;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::RemotingSerializable;
+use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
+use tracing::info;
+use tracing::warn;
+
+use crate::acl::plain_access_config::PlainAccessConfig;
+use crate::acl::plain_access_validator::PlainAccessValidator;
+use crate::processor::admin_broker_processor::Inner;
+
+#[derive(Clone)]
+pub(super) struct AclRequestHandler {
+    inner: Inner,
+}
+
+impl AclRequestHandler {
+    pub fn new(inner: Inner) -> Self {
+        Self { inner }
+    }
+}
+
+impl AclRequestHandler {
+    pub async fn update_and_create_acl_config(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        if let Some(response) = acl_disabled_response(&self.inner.broker_config) {
+            return Some(response);
+        }
+        let request_header =
+            request.decode_command_custom_header::<CreateAccessConfigRequestHeader>()?;
+        Some(update_and_create_acl_config_response(
+            &self.inner.plain_access_validator,
+            request_header,
+        ))
+    }
+
+    pub async fn delete_acl_config(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        if let Some(response) = acl_disabled_response(&self.inner.broker_config) {
+            return Some(response);
+        }
+        let request_header =
+            request.decode_command_custom_header::<DeleteAccessConfigRequestHeader>()?;
+        Some(delete_acl_config_response(
+            &self.inner.plain_access_validator,
+            request_header,
+        ))
+    }
+
+    pub async fn update_global_white_addrs_config(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        if let Some(response) = acl_disabled_response(&self.inner.broker_config) {
+            return Some(response);
+        }
+        let request_header =
+            request.decode_command_custom_header::<UpdateGlobalWhiteAddrsConfigRequestHeader>()?;
+        Some(update_global_white_addrs_response(
+            &self.inner.plain_access_validator,
+            request_header,
+        ))
+    }
+
+    pub async fn get_broker_cluster_acl_info(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        _request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        if let Some(response) = acl_disabled_response(&self.inner.broker_config) {
+            return Some(response);
+        }
+        Some(broker_acl_info_response(
+            &self.inner.plain_access_validator,
+            &self.inner.broker_config,
+        ))
+    }
+}
+
+fn acl_disabled_response(broker_config: &BrokerConfig) -> Option<RemotingCommand> {
+    (!broker_config.acl_enable).then(|| {
+        RemotingCommand::create_response_command_with_code_remark(
+            ResponseCode::SystemError,
+            "The broker does not enable acl",
+        )
+    })
+}
+
+fn acl_result_response(result: Result<(), String>) -> RemotingCommand {
+    match result {
+        Ok(()) => RemotingCommand::create_response_command(),
+        Err(message) => {
+            warn!("Update acl config failed: {}", message);
+            RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::SystemError,
+                message,
+            )
+        }
+    }
+}
+
+fn split_list(value: Option<impl AsRef<str>>) -> Vec<String> {
+    value
+        .map(|value| {
+            value
+                .as_ref()
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn update_and_create_acl_config_response(
+    validator: &PlainAccessValidator,
+    request_header: CreateAccessConfigRequestHeader,
+) -> RemotingCommand {
+    info!(
+        "updateAndCreateAccessConfig, accessKey: {}",
+        request_header.access_key
+    );
+    let config = PlainAccessConfig {
+        access_key: request_header.access_key.to_string(),
+        secret_key: request_header
+            .secret_key
+            .map(|key| key.to_string())
+            .unwrap_or_default(),
+        white_remote_address: request_header
+            .white_remote_address
+            .map(|address| address.to_string()),
+        admin: request_header.admin,
+        default_topic_perm: request_header
+            .default_topic_perm
+            .map(|perm| perm.to_string()),
+        default_group_perm: request_header
+            .default_group_perm
+            .map(|perm| perm.to_string()),
+        topic_perms: split_list(request_header.topic_perms),
+        group_perms: split_list(request_header.group_perms),
+    };
+    acl_result_response(validator.update_access_config(config))
+}
+
+fn delete_acl_config_response(
+    validator: &PlainAccessValidator,
+    request_header: DeleteAccessConfigRequestHeader,
+) -> RemotingCommand {
+    info!(
+        "deleteAccessConfig, accessKey: {}",
+        request_header.access_key
+    );
+    acl_result_response(validator.delete_access_config(&request_header.access_key))
+}
+
+fn update_global_white_addrs_response(
+    validator: &PlainAccessValidator,
+    request_header: UpdateGlobalWhiteAddrsConfigRequestHeader,
+) -> RemotingCommand {
+    info!(
+        "updateGlobalWhiteAddrsConfig, addresses: {}",
+        request_header.global_white_addrs
+    );
+    acl_result_response(
+        validator.update_global_white_addrs(split_list(Some(request_header.global_white_addrs))),
+    )
+}
+
+fn broker_acl_info_response(
+    validator: &PlainAccessValidator,
+    broker_config: &BrokerConfig,
+) -> RemotingCommand {
+    RemotingCommand::create_response_command().set_command_custom_header(
+        GetBrokerAclConfigResponseHeader {
+            version: validator.data_version().to_json().into(),
+            broker_name: broker_config.broker_name(),
+            broker_addr: broker_config.get_broker_addr().into(),
+            cluster_name: broker_config.broker_identity.broker_cluster_name.clone(),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use cheetah_string::CheetahString;
+
+    use super::*;
+    use crate::acl::plain_access_validator::tests::check;
+    use crate::acl::plain_access_validator::tests::signed_request;
+
+    fn create(header: CreateAccessConfigRequestHeader) -> CreateAccessConfigRequestHeader {
+        let mut request =
+            RemotingCommand::create_request_command(RequestCode::UpdateAndCreateAclConfig, header);
+        request.make_custom_header_to_net();
+        request
+            .decode_command_custom_header::<CreateAccessConfigRequestHeader>()
+            .unwrap()
+    }
+
+    fn delete(access_key: &str) -> DeleteAccessConfigRequestHeader {
+        let mut request = RemotingCommand::create_request_command(
+            RequestCode::DeleteAclConfig,
+            DeleteAccessConfigRequestHeader {
+                access_key: access_key.into(),
+            },
+        );
+        request.make_custom_header_to_net();
+        request
+            .decode_command_custom_header::<DeleteAccessConfigRequestHeader>()
+            .unwrap()
+    }
+
+    fn white_addrs(addresses: &str) -> UpdateGlobalWhiteAddrsConfigRequestHeader {
+        let mut request = RemotingCommand::create_request_command(
+            RequestCode::UpdateGlobalWhiteAddrsConfig,
+            UpdateGlobalWhiteAddrsConfigRequestHeader {
+                global_white_addrs: addresses.into(),
+                acl_file_full_path: None,
+            },
+        );
+        request.make_custom_header_to_net();
+        request
+            .decode_command_custom_header::<UpdateGlobalWhiteAddrsConfigRequestHeader>()
+            .unwrap()
+    }
+
+    fn pull(access_key: &str, secret_key: &str) -> RemotingCommand {
+        signed_request(
+            RequestCode::PullMessage,
+            &[("topic", "topicA"), ("consumerGroup", "groupA")],
+            access_key,
+            secret_key,
+        )
+    }
+
+    fn assert_success(response: RemotingCommand) {
+        assert_eq!(
+            ResponseCode::from(response.code()),
+            ResponseCode::Success,
+            "{:?}",
+            response.remark()
+        );
+    }
+
+    #[test]
+    fn admin_requests_update_enforcement_and_acl_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("conf").join("plain_acl.yml");
+        let validator = PlainAccessValidator::new(&path);
+        assert!(validator.load());
+        assert!(!check(&validator, &pull("RocketMQ", "12345678")));
+
+        assert_success(update_and_create_acl_config_response(
+            &validator,
+            create(CreateAccessConfigRequestHeader {
+                access_key: "RocketMQ".into(),
+                secret_key: Some("12345678".into()),
+                default_group_perm: Some("SUB".into()),
+                topic_perms: Some("topicA=SUB,topicB=PUB|SUB".into()),
+                ..Default::default()
+            }),
+        ));
+        assert!(check(&validator, &pull("RocketMQ", "12345678")));
+        assert_success(update_and_create_acl_config_response(
+            &validator,
+            create(CreateAccessConfigRequestHeader {
+                access_key: "rocketmq2".into(),
+                secret_key: Some("87654321".into()),
+                admin: true,
+                ..Default::default()
+            }),
+        ));
+        assert_success(update_global_white_addrs_response(
+            &validator,
+            white_addrs("10.10.103.*, 192.168.0.*"),
+        ));
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "globalWhiteRemoteAddresses:\n- 10.10.103.*\n- 192.168.0.*\naccounts:\n- accessKey: \
+             RocketMQ\n  secretKey: '12345678'\n  admin: false\n  defaultGroupPerm: SUB\n  \
+             topicPerms:\n  - topicA=SUB\n  - topicB=PUB|SUB\n- accessKey: rocketmq2\n  \
+             secretKey: '87654321'\n  admin: true\n"
+        );
+
+        // Taking the topic permission away applies to the next request.
+        assert_success(update_and_create_acl_config_response(
+            &validator,
+            create(CreateAccessConfigRequestHeader {
+                access_key: "RocketMQ".into(),
+                topic_perms: Some("topicA=DENY".into()),
+                ..Default::default()
+            }),
+        ));
+        assert!(!check(&validator, &pull("RocketMQ", "12345678")));
+
+        assert_success(delete_acl_config_response(&validator, delete("RocketMQ")));
+        assert!(!check(&validator, &pull("RocketMQ", "12345678")));
+        let response = delete_acl_config_response(&validator, delete("RocketMQ"));
+        assert_eq!(
+            ResponseCode::from(response.code()),
+            ResponseCode::SystemError
+        );
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("accessKey: RocketMQ"));
+        assert!(content.contains("accessKey: rocketmq2"));
+
+        // A restarted broker reads back what the admin requests wrote.
+        let restarted = PlainAccessValidator::new(&path);
+        assert!(restarted.load());
+        let create_topic = signed_request(
+            RequestCode::UpdateAndCreateTopic,
+            &[("topic", "topicC")],
+            "rocketmq2",
+            "87654321",
+        );
+        assert!(check(&restarted, &create_topic));
+    }
+
+    #[test]
+    fn acl_info_reports_data_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let validator = PlainAccessValidator::new(dir.path().join("plain_acl.yml"));
+        let broker_config = BrokerConfig::default();
+        let before = broker_acl_info_response(&validator, &broker_config)
+            .decode_command_custom_header::<GetBrokerAclConfigResponseHeader>();
+        assert_success(update_global_white_addrs_response(
+            &validator,
+            white_addrs("*"),
+        ));
+        let mut response = broker_acl_info_response(&validator, &broker_config);
+        response.make_custom_header_to_net();
+        let header = response
+            .decode_command_custom_header::<GetBrokerAclConfigResponseHeader>()
+            .unwrap();
+        assert!(before.is_none());
+        assert_eq!(
+            header.version,
+            CheetahString::from(validator.data_version().to_json())
+        );
+        assert_eq!(header.broker_name, broker_config.broker_name());
+        assert_eq!(
+            header.cluster_name,
+            broker_config.broker_identity.broker_cluster_name
+        );
+    }
+
+    #[test]
+    fn rejects_requests_when_acl_is_disabled() {
+        let response = acl_disabled_response(&BrokerConfig::default()).unwrap();
+        assert_eq!(
+            ResponseCode::from(response.code()),
+            ResponseCode::SystemError
+        );
+        assert!(acl_disabled_response(&BrokerConfig {
+            acl_enable: true,
+            ..BrokerConfig::default()
+        })
+        .is_none());
+    }
+}
@@ -73,7 +73,7 @@ impl BatchMqHandler {
                     *mq_lock_map.entry(mq.clone()).or_insert(0) += 1;
                 }
                 let mut addr_map = HashMap::with_capacity(8);
-                addr_map.extend(self.inner.broker_member_group.broker_addrs.clone());
+                addr_map.extend(self.inner.broker_member_group.broker_addrs());
                 addr_map.remove(&self.inner.broker_config.broker_identity.broker_id);
 
                 let count_down_latch = CountDownLatch::new(addr_map.len() as u32);
@@ -135,7 +135,7 @@ impl BatchMqHandler {
         } else {
             request_body.only_this_broker = true;
             let request_body = Bytes::from(request_body.encode());
-            for broker_addr in self.inner.broker_member_group.broker_addrs.values() {
+            for broker_addr in self.inner.broker_member_group.broker_addrs().values() {
                 match self
                     .inner
                     .broker_out_api
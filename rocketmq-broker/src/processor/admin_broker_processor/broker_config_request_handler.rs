@@ -16,20 +16,31 @@
  */
 
 use std::collections::HashMap;
+use std::sync::atomic::Ordering;
 
 use cheetah_string::CheetahString;
 use rocketmq_common::common::mix_all;
 use rocketmq_common::common::mq_version::RocketMqVersion;
 use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::body::kv_table::KVTable;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_store::log_file::MessageStore;
 use sysinfo::Disks;
+use tracing::error;
+use tracing::info;
 
+use crate::broker::broker_pre_online_service::COMMIT_LOG_MAX_OFFSET;
 use crate::processor::admin_broker_processor::Inner;
 
+const NAMESRV_ADDR: &str = "namesrvAddr";
+
+/// Broker settings `UPDATE_BROKER_CONFIG` can change while the broker runs.
+const RUNTIME_UPDATABLE_CONFIGS: &[&str] = &[NAMESRV_ADDR];
+
 #[derive(Clone)]
 pub(super) struct BrokerConfigRequestHandler {
     inner: Inner,
@@ -46,9 +57,29 @@ impl BrokerConfigRequestHandler {
         _channel: Channel,
         _ctx: ConnectionHandlerContext,
         _request_code: RequestCode,
-        _request: RemotingCommand,
+        request: RemotingCommand,
     ) -> Option<RemotingCommand> {
-        todo!()
+        let properties = match parse_broker_config_update(&request) {
+            Ok(properties) => properties,
+            Err(remark) => {
+                return Some(RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::SystemError,
+                    remark,
+                ))
+            }
+        };
+        info!("updateBrokerConfig, new config: [{:?}]", properties);
+        if let Some(namesrv_addr) = properties
+            .get(NAMESRV_ADDR)
+            .filter(|namesrv_addr| !namesrv_addr.is_empty())
+        {
+            let topic_config_manager = &self.inner.topic_config_manager;
+            topic_config_manager
+                .broker_runtime_inner()
+                .switch_name_server_address(namesrv_addr.clone(), topic_config_manager)
+                .await;
+        }
+        Some(RemotingCommand::create_response_command())
     }
 
     pub async fn get_broker_config(
@@ -99,18 +130,125 @@ impl BrokerConfigRequestHandler {
         Some(response)
     }
 
+    pub async fn query_delay_progress(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        _request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let progress = self.inner.schedule_message_service.delay_progress();
+        Some(RemotingCommand::create_response_command().set_body(progress.encode()))
+    }
+
+    pub async fn update_cold_data_flow_ctr_config(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let Some(body) = request.get_body() else {
+            return Some(RemotingCommand::create_response_command());
+        };
+        let body = String::from_utf8_lossy(body);
+        info!("updateColdDataFlowCtrGroupConfig, new config: [{}]", body);
+        let thresholds = mix_all::string_to_properties(&body).and_then(|properties| {
+            properties
+                .into_iter()
+                .map(|(group, threshold)| Some((group, threshold.parse::<u64>().ok()?)))
+                .collect::<Option<Vec<_>>>()
+        });
+        let Some(thresholds) = thresholds else {
+            error!(
+                "updateColdDataFlowCtrGroupConfig, parse config failed: [{}]",
+                body
+            );
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::SystemError,
+                "string2Properties error",
+            ));
+        };
+        for (group, threshold) in thresholds {
+            self.inner
+                .cold_data_cg_ctr_service
+                .add_or_update_group_config(group, threshold);
+        }
+        Some(RemotingCommand::create_response_command())
+    }
+
+    pub async fn remove_cold_data_flow_ctr_config(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let Some(body) = request.get_body() else {
+            return Some(RemotingCommand::create_response_command());
+        };
+        let consumer_group = String::from_utf8_lossy(body);
+        let consumer_group = consumer_group.trim();
+        if !consumer_group.is_empty() {
+            info!(
+                "removeColdDataFlowCtrGroupConfig, consumerGroup: {}",
+                consumer_group
+            );
+            self.inner
+                .cold_data_cg_ctr_service
+                .remove_group_config(consumer_group);
+        }
+        Some(RemotingCommand::create_response_command())
+    }
+
+    pub async fn get_cold_data_flow_ctr_info(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        _request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        Some(
+            RemotingCommand::create_response_command().set_body(
+                self.inner
+                    .cold_data_cg_ctr_service
+                    .get_cold_data_flow_ctr_info(),
+            ),
+        )
+    }
+
     fn prepare_runtime_info(&self) -> HashMap<CheetahString, CheetahString> {
         let mut runtime_info = self.inner.default_message_store.get_runtime_info();
         self.inner
             .schedule_message_service
             .build_running_stats(&mut runtime_info);
+        self.inner
+            .cold_data_cg_ctr_service
+            .build_running_stats(&mut runtime_info);
+        let put_message_distribution = self
+            .inner
+            .broker_stats_manager
+            .put_message_distribution()
+            .last_interval();
+        runtime_info.insert(
+            "putMessageDistributeTime".to_string(),
+            put_message_distribution.distribute_time_string(),
+        );
+        runtime_info.insert(
+            "putMessageMaxLatency".to_string(),
+            put_message_distribution.max_latency_millis.to_string(),
+        );
+        runtime_info.insert(
+            "putMessageClockSkewTimes".to_string(),
+            put_message_distribution.clock_skew.to_string(),
+        );
         runtime_info.insert(
             "brokerActive".to_string(),
             self.is_special_service_running().to_string(),
         );
         let version = RocketMqVersion::CURRENT_VERSION;
-        runtime_info.insert("brokerVersionDesc".to_string(), version.to_string());
-        runtime_info.insert("brokerVersion".to_string(), version.to_string());
+        runtime_info.insert("brokerVersionDesc".to_string(), version.name());
+        runtime_info.insert("brokerVersion".to_string(), i32::from(version).to_string());
         let msg_put_total_yesterday_morning = match &self.inner.broker_stats {
             Some(broker_stats) => broker_stats
                 .get_msg_put_total_yesterday_morning()
@@ -169,6 +307,27 @@ impl BrokerConfigRequestHandler {
                 .dispatch_behind_bytes()
                 .to_string(),
         );
+        runtime_info.insert(
+            COMMIT_LOG_MAX_OFFSET.to_string(),
+            self.inner
+                .default_message_store
+                .get_max_phy_offset()
+                .to_string(),
+        );
+        runtime_info.insert(
+            "commitLogMinOffset".to_string(),
+            self.inner
+                .default_message_store
+                .get_min_phy_offset()
+                .to_string(),
+        );
+        runtime_info.insert(
+            "isIsolated".to_string(),
+            self.inner.is_isolated.load(Ordering::Acquire).to_string(),
+        );
+        for (task, last_run) in self.inner.task_last_runs.get() {
+            runtime_info.insert(format!("taskLastRunTimestamp_{task}"), last_run.to_string());
+        }
         runtime_info.insert(
             "pageCacheLockTimeMills".to_string(),
             self.inner
@@ -262,8 +421,8 @@ impl BrokerConfigRequestHandler {
                 false,
             ),
         );
-        let store_path_root_dir = &self.inner.message_store_config.store_path_root_dir;
-        let commit_log_dir = std::path::Path::new(store_path_root_dir.as_str());
+        let store_path_commit_log = self.inner.message_store_config.get_store_path_commit_log();
+        let commit_log_dir = std::path::Path::new(store_path_commit_log.as_str());
         if commit_log_dir.exists() {
             let disks = Disks::new_with_refreshed_list();
             let path_str = commit_log_dir.to_str().unwrap();
@@ -292,3 +451,50 @@ impl BrokerConfigRequestHandler {
         true
     }
 }
+
+/// Parses the `key=value` lines of an `UPDATE_BROKER_CONFIG` request, rejecting the whole
+/// update when one of the keys cannot change at runtime.
+fn parse_broker_config_update(
+    request: &RemotingCommand,
+) -> Result<HashMap<CheetahString, CheetahString>, String> {
+    let Some(body) = request.get_body() else {
+        return Ok(HashMap::new());
+    };
+    let Some(properties) = mix_all::string_to_properties(&String::from_utf8_lossy(body)) else {
+        return Err("string2Properties error".to_string());
+    };
+    if let Some(key) = properties
+        .keys()
+        .find(|key| !RUNTIME_UPDATABLE_CONFIGS.contains(&key.as_str()))
+    {
+        return Err(format!("{} can not be updated at runtime", key));
+    }
+    Ok(properties)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broker_config_update_only_accepts_runtime_settings() {
+        let request = RemotingCommand::create_remoting_command(RequestCode::UpdateBrokerConfig)
+            .set_body("namesrvAddr=127.0.0.1:9876;127.0.0.1:9877\n");
+        let properties = parse_broker_config_update(&request).unwrap();
+        assert_eq!(
+            properties.get(NAMESRV_ADDR).map(|addr| addr.as_str()),
+            Some("127.0.0.1:9876;127.0.0.1:9877")
+        );
+
+        let request = RemotingCommand::create_remoting_command(RequestCode::UpdateBrokerConfig)
+            .set_body("namesrvAddr=127.0.0.1:9876\nbrokerPermission=4\n");
+        assert_eq!(
+            parse_broker_config_update(&request).unwrap_err(),
+            "brokerPermission can not be updated at runtime"
+        );
+
+        let request = RemotingCommand::create_remoting_command(RequestCode::UpdateBrokerConfig)
+            .set_body("namesrvAddr");
+        assert!(parse_broker_config_update(&request).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::body::broker_body::cluster_info::ClusterInfo;
+use rocketmq_remoting::protocol::header::namesrv::brokerid_change_request_header::NotifyMinBrokerIdChangeRequestHeader;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::route::route_data_view::BrokerData;
+use rocketmq_remoting::protocol::RemotingSerializable;
+use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
+use rocketmq_store::log_file::MessageStore;
+use tracing::warn;
+
+use crate::broker::broker_member_group_cache::BrokerMemberGroupCache;
+use crate::processor::admin_broker_processor::Inner;
+
+#[derive(Clone)]
+pub(super) struct BrokerMemberGroupHandler {
+    inner: Inner,
+}
+
+impl BrokerMemberGroupHandler {
+    pub fn new(inner: Inner) -> Self {
+        Self { inner }
+    }
+}
+
+impl BrokerMemberGroupHandler {
+    pub async fn notify_min_broker_id_change(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let request_header =
+            request.decode_command_custom_header::<NotifyMinBrokerIdChangeRequestHeader>()?;
+        Some(min_broker_id_change_response(
+            &self.inner.broker_member_group,
+            self.inner.default_message_store.as_ref(),
+            request_header,
+        ))
+    }
+
+    pub async fn get_broker_cluster_info(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        _request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        Some(cluster_info_response(
+            &self.inner.broker_member_group,
+            self.inner.broker_config.enable_slave_acting_master,
+        ))
+    }
+}
+
+/// Answers GET_BROKER_CLUSTER_INFO with the replica group this broker learned from the name
+/// server, admin tools fall back to it when no name server is reachable.
+fn cluster_info_response(
+    broker_member_group: &BrokerMemberGroupCache,
+    enable_acting_master: bool,
+) -> RemotingCommand {
+    let group = broker_member_group.group();
+    let mut broker_data = BrokerData::new(
+        group.cluster.clone(),
+        group.broker_name.clone(),
+        group.broker_addrs,
+        None,
+    );
+    broker_data.set_enable_acting_master(enable_acting_master);
+    let cluster_info = ClusterInfo::new(
+        Some(HashMap::from([(group.broker_name.clone(), broker_data)])),
+        Some(HashMap::from([(
+            group.cluster,
+            HashSet::from([group.broker_name]),
+        )])),
+    );
+    RemotingCommand::create_response_command().set_body(cluster_info.encode())
+}
+
+/// Records the min broker id pushed by the name server and switches this broker between
+/// master and slave behaviour right away instead of waiting for the next group sync.
+fn min_broker_id_change_response<MS: MessageStore>(
+    broker_member_group: &BrokerMemberGroupCache,
+    message_store: &MS,
+    request_header: NotifyMinBrokerIdChangeRequestHeader,
+) -> RemotingCommand {
+    let Some(min_broker_id) = request_header.min_broker_id else {
+        warn!("Ignore min broker id change without minBrokerId");
+        return RemotingCommand::create_response_command_with_code_remark(
+            ResponseCode::SystemError,
+            "minBrokerId is required",
+        );
+    };
+    if let Some(change) =
+        broker_member_group.update_min_broker(min_broker_id, request_header.min_broker_addr)
+    {
+        change.apply(message_store);
+    }
+    RemotingCommand::create_response_command()
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::broker::broker_config::BrokerIdentity;
+    use rocketmq_remoting::protocol::RemotingDeserializable;
+    use rocketmq_store::config::message_store_config::MessageStoreConfig;
+    use rocketmq_store::test_util::load_store;
+
+    use super::*;
+
+    fn notify(min_broker_id: u64, min_broker_addr: &str) -> NotifyMinBrokerIdChangeRequestHeader {
+        let mut request = RemotingCommand::create_request_command(
+            RequestCode::NotifyMinBrokerIdChange,
+            NotifyMinBrokerIdChangeRequestHeader::new(
+                Some(min_broker_id),
+                Some("broker-a".into()),
+                Some(min_broker_addr.into()),
+                None,
+                None,
+            ),
+        );
+        request.make_custom_header_to_net();
+        request
+            .decode_command_custom_header::<NotifyMinBrokerIdChangeRequestHeader>()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn min_broker_id_change_flips_slave_to_acting_master_and_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = load_store(&dir, MessageStoreConfig::default()).await;
+        let cache = BrokerMemberGroupCache::new(&BrokerConfig {
+            broker_identity: BrokerIdentity {
+                broker_id: 1,
+                ..BrokerIdentity::default()
+            },
+            ..BrokerConfig::default()
+        });
+
+        // The master went offline, this slave now holds the min broker id.
+        let response =
+            min_broker_id_change_response(&cache, store.as_ref(), notify(1, "127.0.0.1:10921"));
+        assert_eq!(ResponseCode::from(response.code()), ResponseCode::Success);
+        assert!(cache.is_acting_master());
+        assert_eq!(cache.min_broker_id(), 1);
+        assert!(store.get_running_flags().is_writeable());
+
+        // The master is back, step down again.
+        let response =
+            min_broker_id_change_response(&cache, store.as_ref(), notify(0, "127.0.0.1:10911"));
+        assert_eq!(ResponseCode::from(response.code()), ResponseCode::Success);
+        assert!(!cache.is_acting_master());
+        assert_eq!(cache.min_broker_id(), 0);
+        assert_eq!(cache.min_broker_addr(), Some("127.0.0.1:10911".into()));
+        assert!(!store.get_running_flags().is_writeable());
+    }
+
+    #[test]
+    fn cluster_info_reports_the_known_replica_group() {
+        let cache = BrokerMemberGroupCache::new(&BrokerConfig::default());
+        let mut group = cache.group();
+        group.broker_addrs.insert(1, "127.0.0.1:10921".into());
+        cache.update_group(group.clone());
+
+        let response = cluster_info_response(&cache, true);
+        assert_eq!(ResponseCode::from(response.code()), ResponseCode::Success);
+        let cluster_info = ClusterInfo::decode(response.get_body().unwrap()).unwrap();
+        let broker_data = &cluster_info.broker_addr_table.unwrap()[&group.broker_name];
+        assert_eq!(broker_data.broker_addrs(), &group.broker_addrs);
+        assert!(broker_data.enable_acting_master());
+        assert_eq!(
+            cluster_info.cluster_addr_table.unwrap()[&group.cluster],
+            HashSet::from([group.broker_name])
+        );
+    }
+
+    #[tokio::test]
+    async fn min_broker_id_change_without_id_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = load_store(&dir, MessageStoreConfig::default()).await;
+        let cache = BrokerMemberGroupCache::new(&BrokerConfig::default());
+        let response = min_broker_id_change_response(
+            &cache,
+            store.as_ref(),
+            NotifyMinBrokerIdChangeRequestHeader::default(),
+        );
+        assert_eq!(
+            ResponseCode::from(response.code()),
+            ResponseCode::SystemError
+        );
+        assert!(cache.is_acting_master());
+    }
+}
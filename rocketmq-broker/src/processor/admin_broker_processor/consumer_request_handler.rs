@@ -15,8 +15,12 @@
  * limitations under the License.
  */
 
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::time::Duration;
+use std::time::Instant;
 
+use cheetah_string::CheetahString;
 use rocketmq_common::common::config_manager::ConfigManager;
 use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_remoting::code::request_code::RequestCode;
@@ -25,15 +29,32 @@ use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::admin::consume_stats::ConsumeStats;
 use rocketmq_remoting::protocol::admin::offset_wrapper::OffsetWrapper;
 use rocketmq_remoting::protocol::body::connection::Connection;
+use rocketmq_remoting::protocol::body::consume_queue_data::ConsumeQueueData;
+use rocketmq_remoting::protocol::body::consume_stats_list::ConsumeStatsList;
 use rocketmq_remoting::protocol::body::consumer_connection::ConsumerConnection;
+use rocketmq_remoting::protocol::body::query_consume_queue_response_body::QueryConsumeQueueResponseBody;
+use rocketmq_remoting::protocol::body::query_correction_offset_body::QueryCorrectionOffsetBody;
+use rocketmq_remoting::protocol::header::clone_group_offset_request_header::CloneGroupOffsetRequestHeader;
+use rocketmq_remoting::protocol::header::get_consume_stats_in_broker_header::GetConsumeStatsInBrokerHeader;
 use rocketmq_remoting::protocol::header::get_consume_stats_request_header::GetConsumeStatsRequestHeader;
 use rocketmq_remoting::protocol::header::get_consumer_connection_list_request_header::GetConsumerConnectionListRequestHeader;
+use rocketmq_remoting::protocol::header::query_consume_queue_request_header::QueryConsumeQueueRequestHeader;
+use rocketmq_remoting::protocol::header::query_correction_offset_header::QueryCorrectionOffsetHeader;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
+use rocketmq_store::filter::MessageFilter;
 use rocketmq_store::log_file::MessageStore;
+use rocketmq_store::message_store::default_message_store::DefaultMessageStore;
+use rocketmq_store::queue::single_consume_queue::ConsumeQueue;
+use rocketmq_store::queue::ConsumeQueueTrait;
+use serde_json::json;
 use tracing::warn;
 
+use crate::client::consumer_group_info::ConsumerGroupInfo;
+use crate::client::manager::consumer_manager::ConsumerManager;
+use crate::filter::expression_message_filter::ExpressionMessageFilter;
+use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
 use crate::processor::admin_broker_processor::Inner;
 
 #[derive(Clone)]
@@ -65,25 +86,7 @@ impl ConsumerRequestHandler {
             .get_consumer_group_info(request_header.get_consumer_group());
         match consumer_group_info {
             Some(consumer_group_info) => {
-                let mut body_data = ConsumerConnection::new();
-                body_data.set_consume_from_where(consumer_group_info.get_consume_from_where());
-                body_data.set_consume_type(consumer_group_info.get_consume_type());
-                body_data.set_message_model(consumer_group_info.get_message_model());
-                let subscription_table =
-                    consumer_group_info.get_subscription_table().read().clone();
-                body_data
-                    .get_subscription_table()
-                    .extend(subscription_table);
-
-                for (channel, info) in consumer_group_info.get_channel_info_table().read().iter() {
-                    let mut connection = Connection::new();
-                    connection.set_client_id(info.client_id().clone());
-                    connection.set_language(info.language());
-                    connection.set_version(info.version());
-                    connection.set_client_addr(channel.remote_address().to_string().into());
-                    body_data.get_connection_set().insert(connection);
-                }
-                let body = body_data.encode();
+                let body = consumer_connection(&consumer_group_info).encode();
                 response.set_body_mut_ref(body);
                 Some(response)
             }
@@ -106,9 +109,8 @@ impl ConsumerRequestHandler {
         request: RemotingCommand,
     ) -> Option<RemotingCommand> {
         let mut response = RemotingCommand::create_response_command();
-        let request_header = request
-            .decode_command_custom_header::<GetConsumeStatsRequestHeader>()
-            .unwrap();
+        let request_header =
+            request.decode_command_custom_header::<GetConsumeStatsRequestHeader>()?;
         let mut consume_stats = ConsumeStats::new();
         let mut topics = HashSet::new();
         if request_header.get_topic().is_empty() {
@@ -120,104 +122,53 @@ impl ConsumerRequestHandler {
             topics.insert(request_header.get_topic().clone());
         }
         for topic in topics.iter() {
-            let topic_config = self.inner.topic_config_manager.select_topic_config(topic);
-            if topic_config.is_none() {
-                warn!(
-                    "AdminBrokerProcessor#getConsumeStats: topic config does not exist, topic={}",
-                    topic
-                );
-                continue;
-            }
-
-            let mapping_detail = self
-                .inner
-                .topic_queue_mapping_manager
-                .get_topic_queue_mapping(topic);
-
-            let find_subscription_data = self
-                .inner
-                .consume_manager
-                .find_subscription_data(request_header.get_consumer_group(), topic);
-
-            if find_subscription_data.is_none()
-                && self
-                    .inner
-                    .consume_manager
-                    .find_subscription_data_count(request_header.get_consumer_group())
-                    > 0
+            if let Some(topic_consume_stats) =
+                self.topic_consume_stats(request_header.get_consumer_group(), topic, false)
             {
-                warn!(
-                    "AdminBrokerProcessor#getConsumeStats: topic does not exist in consumer \
-                     group's subscription, topic={}, consumer group={}",
-                    topic,
-                    request_header.get_consumer_group()
-                );
-                continue;
-            }
-
-            for i in 0..topic_config.unwrap().get_read_queue_nums() {
-                let mut mq = MessageQueue::new();
-                mq.set_topic(topic.to_string().into());
-                mq.set_broker_name(self.inner.broker_config.broker_name.clone());
-                mq.set_queue_id(i as i32);
-
-                let mut offset_wrapper = OffsetWrapper::new();
-
-                let mut broker_offset = self
-                    .inner
-                    .default_message_store
-                    .get_max_offset_in_queue(topic, i as i32);
-                if broker_offset < 0 {
-                    broker_offset = 0;
-                }
-
-                let mut consumer_offset = self.inner.consumer_offset_manager.query_offset(
-                    request_header.get_consumer_group(),
-                    topic,
-                    i as i32,
-                );
-
-                if mapping_detail.is_none() && consumer_offset < 0 {
-                    consumer_offset = 0;
-                }
-
-                let pull_offset = self.inner.consumer_offset_manager.query_offset(
-                    request_header.get_consumer_group(),
-                    topic,
-                    i as i32,
-                );
-
-                offset_wrapper.set_broker_offset(broker_offset);
-                offset_wrapper.set_consumer_offset(consumer_offset);
-                offset_wrapper.set_pull_offset(std::cmp::max(consumer_offset, pull_offset));
-
-                let time_offset = consumer_offset - 1;
-                if time_offset >= 0 {
-                    let last_timestamp = self
-                        .inner
-                        .default_message_store
-                        .get_message_store_timestamp(topic, i as i32, time_offset);
-                    if last_timestamp > 0 {
-                        offset_wrapper.set_last_timestamp(last_timestamp);
-                    }
-                }
-
-                consume_stats.get_offset_table().insert(mq, offset_wrapper);
+                consume_stats
+                    .get_offset_table_mut()
+                    .extend(topic_consume_stats.get_offset_table());
+                let new_consume_tps =
+                    consume_stats.get_consume_tps() + topic_consume_stats.get_consume_tps();
+                consume_stats.set_consume_tps(new_consume_tps);
             }
-
-            let consume_tps = self
-                .inner
-                .broker_stats_manager
-                .tps_group_get_nums(request_header.get_consumer_group(), topic);
-
-            let new_consume_tps = consume_stats.get_consume_tps() + consume_tps;
-            consume_stats.set_consume_tps(new_consume_tps);
         }
         let body = consume_stats.encode();
         response.set_body_mut_ref(body);
         Some(response)
     }
 
+    pub async fn get_broker_consume_stats(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let request_header =
+            request.decode_command_custom_header::<GetConsumeStatsInBrokerHeader>()?;
+        let deadline = request_header
+            .timeout_millis
+            .map(|timeout_millis| Instant::now() + Duration::from_millis(timeout_millis));
+        let mut groups = self
+            .inner
+            .subscription_group_manager
+            .subscription_group_names();
+        groups.sort();
+        let mut consume_stats_list = collect_consume_stats(groups, deadline, |group| {
+            self.group_consume_stats(group, request_header.is_order)
+        });
+        consume_stats_list.broker_addr = self.inner.broker_config.get_broker_addr().into();
+        if consume_stats_list.truncated {
+            warn!(
+                "getBrokerConsumeStats ran out of its {}ms budget after {} groups",
+                request_header.timeout_millis.unwrap_or_default(),
+                consume_stats_list.consume_stats_list.len()
+            );
+        }
+        Some(RemotingCommand::create_response_command().set_body(consume_stats_list.encode()))
+    }
+
     pub async fn get_all_consumer_offset(
         &mut self,
         _channel: Channel,
@@ -238,4 +189,868 @@ impl ConsumerRequestHandler {
             )
         }
     }
+
+    pub async fn clone_group_offset(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let request_header =
+            request.decode_command_custom_header::<CloneGroupOffsetRequestHeader>()?;
+        clone_group_offset(
+            &self.inner.consumer_offset_manager,
+            &self.inner.consume_manager,
+            &request_header,
+            |topic| {
+                self.inner
+                    .topic_config_manager
+                    .select_topic_config(topic)
+                    .is_some()
+            },
+        );
+        Some(RemotingCommand::create_response_command())
+    }
+
+    pub async fn query_correction_offset(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let request_header =
+            request.decode_command_custom_header::<QueryCorrectionOffsetHeader>()?;
+        let body = query_correction_offset(
+            &self.inner.consumer_offset_manager,
+            self.inner.default_message_store.as_ref(),
+            &request_header,
+        );
+        Some(RemotingCommand::create_response_command().set_body(body.encode()))
+    }
+
+    pub async fn query_consume_queue(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let request_header = request
+            .decode_command_custom_header::<QueryConsumeQueueRequestHeader>()
+            .unwrap();
+        let response = RemotingCommand::create_response_command();
+        let topic = &request_header.topic;
+        let queue_id = request_header.queue_id;
+        let message_store = self.inner.default_message_store.as_ref();
+        let Some(consume_queue) = message_store.find_consume_queue(topic, queue_id) else {
+            return Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark(format!("{}@{} is not exist!", queue_id, topic)),
+            );
+        };
+        let mut body = QueryConsumeQueueResponseBody {
+            max_queue_index: consume_queue.get_max_offset_in_queue(),
+            min_queue_index: consume_queue.get_min_offset_in_queue(),
+            ..QueryConsumeQueueResponseBody::default()
+        };
+
+        let mut message_filter = None;
+        if let Some(group) = request_header.consumer_group.as_ref() {
+            body.subscription_data = self
+                .inner
+                .consume_manager
+                .find_subscription_data(group, topic);
+            match body.subscription_data.as_ref() {
+                None => body.filter_data = Some(format!("{}@{} is not online!", group, topic)),
+                Some(subscription_data) => {
+                    let filter_data = self
+                        .inner
+                        .consumer_filter_manager
+                        .get_consumer_filter_data(topic, group);
+                    body.filter_data = serde_json::to_string(&filter_data).ok();
+                    let filter = ExpressionMessageFilter::new(
+                        Some(subscription_data.clone()),
+                        filter_data,
+                        self.inner.consumer_filter_manager.clone(),
+                    );
+                    body.estimated_message_count = Some(message_store.estimate_message_count(
+                        topic,
+                        queue_id,
+                        request_header.index,
+                        body.max_queue_index,
+                        &filter,
+                    ));
+                    message_filter = Some(filter);
+                }
+            }
+        }
+
+        let response = match consume_queue_data(
+            &**consume_queue,
+            request_header.index,
+            request_header.count,
+            message_filter
+                .as_ref()
+                .map(|filter| filter as &dyn MessageFilter),
+        ) {
+            Some(queue_data) => {
+                body.queue_data = queue_data;
+                response
+            }
+            None => response.set_remark(format!(
+                "Can't find consume queue unit from index: {}",
+                request_header.index
+            )),
+        };
+        Some(response.set_body(body.encode()))
+    }
+
+    /// The stats of every topic `group` has committed offsets on, only ordered topics when
+    /// `is_order` is set.
+    fn group_consume_stats(&self, group: &CheetahString, is_order: bool) -> Vec<ConsumeStats> {
+        let mut topics = self
+            .inner
+            .consumer_offset_manager
+            .which_topic_by_consumer(group)
+            .into_iter()
+            .collect::<Vec<_>>();
+        topics.sort();
+        topics
+            .iter()
+            .filter_map(|topic| self.topic_consume_stats(group, topic, is_order))
+            .collect()
+    }
+
+    /// The progress of `group` on every readable queue of `topic` and its consume TPS there.
+    /// `None` when the topic is unknown, not ordered while `is_order` is set, or outside the
+    /// subscription of the group.
+    fn topic_consume_stats(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        is_order: bool,
+    ) -> Option<ConsumeStats> {
+        let Some(topic_config) = self.inner.topic_config_manager.select_topic_config(topic) else {
+            warn!("topic config does not exist, topic={}", topic);
+            return None;
+        };
+        if is_order && !topic_config.order {
+            return None;
+        }
+        if self
+            .inner
+            .consume_manager
+            .find_subscription_data(group, topic)
+            .is_none()
+            && self
+                .inner
+                .consume_manager
+                .find_subscription_data_count(group)
+                > 0
+        {
+            warn!(
+                "topic does not exist in consumer group's subscription, topic={}, consumer \
+                 group={}",
+                topic, group
+            );
+            return None;
+        }
+
+        let static_topic = self
+            .inner
+            .topic_queue_mapping_manager
+            .get_topic_queue_mapping(topic)
+            .is_some();
+        let mut consume_stats = ConsumeStats::new();
+        for queue_id in 0..topic_config.get_read_queue_nums() as i32 {
+            let mq = MessageQueue::from_parts(
+                topic.clone(),
+                self.inner.broker_config.broker_name.clone(),
+                queue_id,
+            );
+            let offset_wrapper = queue_offset_wrapper(
+                self.inner.default_message_store.as_ref(),
+                &self.inner.consumer_offset_manager,
+                group,
+                topic,
+                queue_id,
+                static_topic,
+            );
+            consume_stats
+                .get_offset_table_mut()
+                .insert(mq, offset_wrapper);
+        }
+        consume_stats.set_consume_tps(
+            self.inner
+                .broker_stats_manager
+                .tps_group_get_nums(group, topic),
+        );
+        Some(consume_stats)
+    }
+}
+
+/// Copies the offsets of the source group to the destination group on the requested topic, or
+/// on every topic the source group has offsets of. Topics `topic_exists` does not know are
+/// skipped, as are, while the source group is online, topics it no longer subscribes to.
+fn clone_group_offset(
+    consumer_offset_manager: &ConsumerOffsetManager,
+    consumer_manager: &ConsumerManager,
+    request_header: &CloneGroupOffsetRequestHeader,
+    topic_exists: impl Fn(&CheetahString) -> bool,
+) {
+    let src_group = &request_header.src_group;
+    let topics = match request_header
+        .topic
+        .as_ref()
+        .filter(|topic| !topic.trim().is_empty())
+    {
+        Some(topic) => HashSet::from([topic.clone()]),
+        None => consumer_offset_manager.which_topic_by_consumer(src_group),
+    };
+    for topic in topics {
+        if !topic_exists(&topic) {
+            warn!("[cloneGroupOffset], topic config not exist, {}", topic);
+            continue;
+        }
+        if !request_header.offline
+            && consumer_manager.find_subscription_data_count(src_group) > 0
+            && consumer_manager
+                .find_subscription_data(src_group, &topic)
+                .is_none()
+        {
+            warn!(
+                "[cloneGroupOffset], topic does not exist in consumer group's subscription, \
+                 topic={}, consumer group={}",
+                topic, src_group
+            );
+            continue;
+        }
+        consumer_offset_manager.clone_offset(src_group, &request_header.dest_group, &topic);
+    }
+}
+
+/// Per queue, the smallest offset the groups consuming the topic committed, or `i64::MAX` where
+/// the compared group is already behind all of them.
+fn query_correction_offset<MS: MessageStore>(
+    consumer_offset_manager: &ConsumerOffsetManager,
+    message_store: &MS,
+    request_header: &QueryCorrectionOffsetHeader,
+) -> QueryCorrectionOffsetBody {
+    let mut correction_offsets = consumer_offset_manager.query_min_offset_in_all_group(
+        &request_header.topic,
+        request_header.filter_groups.as_deref(),
+        message_store,
+    );
+    let compare_offsets = consumer_offset_manager
+        .query_offset_table(&request_header.compare_group, &request_header.topic);
+    for (queue_id, compare_offset) in compare_offsets {
+        if let Some(min_offset) = correction_offsets.get_mut(&queue_id) {
+            if *min_offset > compare_offset {
+                *min_offset = i64::MAX;
+            }
+        }
+    }
+    QueryCorrectionOffsetBody { correction_offsets }
+}
+
+/// Collects the stats `group_consume_stats` reports for each of `groups`, with their total lag
+/// and consume TPS. Groups are no longer visited once `deadline` has passed, the result is then
+/// marked truncated.
+fn collect_consume_stats(
+    groups: impl IntoIterator<Item = CheetahString>,
+    deadline: Option<Instant>,
+    mut group_consume_stats: impl FnMut(&CheetahString) -> Vec<ConsumeStats>,
+) -> ConsumeStatsList {
+    let mut consume_stats_list = ConsumeStatsList::default();
+    for group in groups {
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            consume_stats_list.truncated = true;
+            break;
+        }
+        let group_stats = group_consume_stats(&group);
+        for consume_stats in &group_stats {
+            consume_stats_list.total_diff += consume_stats.compute_total_diff();
+            consume_stats_list.total_inflight_diff += consume_stats.compute_inflight_total_diff();
+            consume_stats_list.total_consume_tps += consume_stats.get_consume_tps();
+        }
+        consume_stats_list
+            .consume_stats_list
+            .push(HashMap::from([(group, group_stats)]));
+    }
+    consume_stats_list
+}
+
+/// The connections of a consumer group, each carrying the language and version the client
+/// reported with its last heartbeat.
+fn consumer_connection(consumer_group_info: &ConsumerGroupInfo) -> ConsumerConnection {
+    let mut body_data = ConsumerConnection::new();
+    body_data.set_consume_from_where(consumer_group_info.get_consume_from_where());
+    body_data.set_consume_type(consumer_group_info.get_consume_type());
+    body_data.set_message_model(consumer_group_info.get_message_model());
+    body_data.set_subscription_table(consumer_group_info.get_subscription_table().read().clone());
+    let connection_set = consumer_group_info
+        .get_channel_info_table()
+        .read()
+        .iter()
+        .map(|(channel, info)| {
+            let mut connection = Connection::new();
+            connection.set_client_id(info.client_id().clone());
+            connection.set_language(info.language());
+            connection.set_version(info.version());
+            connection.set_client_addr(channel.remote_address().to_string().into());
+            connection
+        })
+        .collect();
+    body_data.set_connection_set(connection_set);
+    body_data
+}
+
+/// Decodes up to `count` entries of `consume_queue` from `index`, each with the verdict of
+/// `message_filter` when there is one. `None` when nothing is stored at `index`.
+fn consume_queue_data(
+    consume_queue: &dyn ConsumeQueueTrait,
+    index: i64,
+    count: i32,
+    message_filter: Option<&dyn MessageFilter>,
+) -> Option<Vec<ConsumeQueueData>> {
+    let cq_units = consume_queue.iterate_from(index)?;
+    let queue_data = cq_units
+        .take_while(|cq_unit| cq_unit.queue_offset - index < count as i64)
+        .map(|cq_unit| {
+            let mut data = ConsumeQueueData {
+                physic_offset: cq_unit.pos,
+                physic_size: cq_unit.size,
+                tags_code: cq_unit.tags_code,
+                ..ConsumeQueueData::default()
+            };
+            match cq_unit.cq_ext_unit.as_ref() {
+                Some(cq_ext_unit) => {
+                    data.extend_data_json = Some(
+                        json!({
+                            "size": cq_ext_unit.size(),
+                            "tagsCode": cq_ext_unit.tags_code(),
+                            "msgStoreTime": cq_ext_unit.msg_store_time(),
+                            "bitMapSize": cq_ext_unit.bit_map_size(),
+                        })
+                        .to_string(),
+                    );
+                    data.bit_map = cq_ext_unit.filter_bit_map().as_ref().map(|bit_map| {
+                        bit_map
+                            .iter()
+                            .map(|byte| format!("{byte:08b}"))
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    });
+                }
+                None if ConsumeQueue::is_ext_addr(cq_unit.tags_code) => {
+                    data.msg = Some(format!("Cq extend not exist!addr: {}", cq_unit.tags_code));
+                }
+                None => {}
+            }
+            data.eval = message_filter.is_some_and(|filter| {
+                filter.is_matched_by_consume_queue(
+                    Some(cq_unit.tags_code),
+                    cq_unit.cq_ext_unit.as_ref(),
+                )
+            });
+            data
+        })
+        .collect();
+    Some(queue_data)
+}
+
+/// Builds the consume progress of `group` on one queue. A consumer offset past the broker offset,
+/// e.g. after the queue was truncated or the offset was reset forward, is reported at the broker
+/// offset so the lag never goes negative.
+fn queue_offset_wrapper(
+    message_store: &DefaultMessageStore,
+    consumer_offset_manager: &ConsumerOffsetManager,
+    group: &CheetahString,
+    topic: &CheetahString,
+    queue_id: i32,
+    static_topic: bool,
+) -> OffsetWrapper {
+    let broker_offset = message_store
+        .get_max_offset_in_queue(topic, queue_id)
+        .max(0);
+    let mut consumer_offset = consumer_offset_manager.query_offset(group, topic, queue_id);
+    if !static_topic {
+        consumer_offset = consumer_offset.clamp(0, broker_offset);
+    }
+    let pull_offset = consumer_offset_manager.query_pull_offset(group, topic, queue_id);
+
+    let mut offset_wrapper = OffsetWrapper::new();
+    offset_wrapper.set_broker_offset(broker_offset);
+    offset_wrapper.set_consumer_offset(consumer_offset);
+    offset_wrapper.set_pull_offset(consumer_offset.max(pull_offset));
+
+    let time_offset = consumer_offset - 1;
+    if time_offset >= 0 {
+        let last_timestamp =
+            message_store.get_message_store_timestamp(topic, queue_id, time_offset);
+        if last_timestamp > 0 {
+            offset_wrapper.set_last_timestamp(last_timestamp);
+        }
+    }
+    offset_wrapper
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::consumer::consume_from_where::ConsumeFromWhere;
+    use rocketmq_common::common::message::message_decoder::message_properties_to_string;
+    use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+    use rocketmq_common::common::message::MessageConst;
+    use rocketmq_common::common::message::MessageTrait;
+    use rocketmq_common::common::mq_version::RocketMqVersion;
+    use rocketmq_common::MessageAccessor::MessageAccessor;
+    use rocketmq_remoting::protocol::filter::filter_api::FilterAPI;
+    use rocketmq_remoting::protocol::heartbeat::consume_type::ConsumeType;
+    use rocketmq_remoting::protocol::heartbeat::message_model::MessageModel;
+    use rocketmq_remoting::protocol::LanguageCode;
+    use rocketmq_remoting::protocol::RemotingDeserializable;
+    use rocketmq_rust::ArcMut;
+    use rocketmq_store::base::message_status_enum::PutMessageStatus;
+    use rocketmq_store::config::message_store_config::MessageStoreConfig;
+    use rocketmq_store::test_util::start_store;
+    use rocketmq_store::test_util::wait_dispatched;
+
+    use super::*;
+    use crate::client::client_channel_info::ClientChannelInfo;
+    use crate::client::client_version::decode_heartbeat;
+    use crate::client::default_consumer_ids_change_listener::DefaultConsumerIdsChangeListener;
+    use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
+    use crate::test_util::client_channel;
+
+    async fn put_messages(store: &mut ArcMut<DefaultMessageStore>, topic: &str, count: usize) {
+        for _ in 0..count {
+            let mut msg = MessageExtBrokerInner::default();
+            msg.message_ext_inner.message.topic = CheetahString::from_slice(topic);
+            msg.message_ext_inner.message.body = Some(bytes::Bytes::from_static(b"stats"));
+            let result = store.put_message(msg).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        }
+        wait_dispatched(store).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn consume_stats_of_group_consuming_two_topics() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = start_store(&dir, MessageStoreConfig::default()).await;
+        put_messages(&mut store, "TopicA", 3).await;
+        put_messages(&mut store, "TopicB", 2).await;
+
+        let consumer_offset_manager =
+            ConsumerOffsetManager::new(Arc::new(BrokerConfig::default()), None);
+        let client: SocketAddr = "127.0.0.1:9876".parse().unwrap();
+        let group = CheetahString::from_static_str("GroupA");
+        let topic_a = CheetahString::from_static_str("TopicA");
+        let topic_b = CheetahString::from_static_str("TopicB");
+        consumer_offset_manager.commit_offset(client, &group, &topic_a, 0, 2);
+        // reset beyond what the broker holds
+        consumer_offset_manager.commit_offset(client, &group, &topic_b, 0, 5);
+
+        let topics = consumer_offset_manager.which_topic_by_consumer(&group);
+        assert_eq!(topics, HashSet::from([topic_a.clone(), topic_b.clone()]));
+
+        let wrapper_a = queue_offset_wrapper(
+            store.as_ref(),
+            &consumer_offset_manager,
+            &group,
+            &topic_a,
+            0,
+            false,
+        );
+        assert_eq!(wrapper_a.get_broker_offset(), 3);
+        assert_eq!(wrapper_a.get_consumer_offset(), 2);
+        assert_eq!(wrapper_a.get_pull_offset(), 2);
+        assert!(wrapper_a.get_last_timestamp() > 0);
+
+        let wrapper_b = queue_offset_wrapper(
+            store.as_ref(),
+            &consumer_offset_manager,
+            &group,
+            &topic_b,
+            0,
+            false,
+        );
+        assert_eq!(wrapper_b.get_broker_offset(), 2);
+        assert_eq!(wrapper_b.get_consumer_offset(), 2);
+        assert!(wrapper_b.get_last_timestamp() >= wrapper_a.get_last_timestamp());
+
+        // a queue the group never committed to
+        let wrapper_empty = queue_offset_wrapper(
+            store.as_ref(),
+            &consumer_offset_manager,
+            &group,
+            &topic_a,
+            1,
+            false,
+        );
+        assert_eq!(wrapper_empty.get_broker_offset(), 0);
+        assert_eq!(wrapper_empty.get_consumer_offset(), 0);
+        assert_eq!(wrapper_empty.get_last_timestamp(), 0);
+        store.shutdown();
+    }
+
+    fn lagging_stats(topic: &str, broker_offset: i64, consumer_offset: i64) -> ConsumeStats {
+        let mut offset_wrapper = OffsetWrapper::new();
+        offset_wrapper.set_broker_offset(broker_offset);
+        offset_wrapper.set_consumer_offset(consumer_offset);
+        offset_wrapper.set_pull_offset(broker_offset);
+        let mut consume_stats = ConsumeStats::new();
+        consume_stats.get_offset_table_mut().insert(
+            MessageQueue::from_parts(topic, "broker-a", 0),
+            offset_wrapper,
+        );
+        consume_stats.set_consume_tps(1.5);
+        consume_stats
+    }
+
+    fn three_groups() -> Vec<CheetahString> {
+        vec!["GroupA".into(), "GroupB".into(), "GroupC".into()]
+    }
+
+    #[test]
+    fn consume_stats_list_totals_every_group() {
+        let consume_stats_list = collect_consume_stats(three_groups(), None, |group| {
+            if group == "GroupB" {
+                vec![
+                    lagging_stats("TopicA", 10, 4),
+                    lagging_stats("TopicB", 5, 5),
+                ]
+            } else {
+                vec![lagging_stats("TopicA", 10, 9)]
+            }
+        });
+        assert!(!consume_stats_list.truncated);
+        assert_eq!(consume_stats_list.consume_stats_list.len(), 3);
+        assert_eq!(consume_stats_list.consume_stats_list[1]["GroupB"].len(), 2);
+        assert_eq!(consume_stats_list.total_diff, 6 + 1 + 1);
+        assert_eq!(consume_stats_list.total_inflight_diff, 8);
+        assert_eq!(consume_stats_list.total_consume_tps, 1.5 * 4.0);
+
+        let json = String::from_utf8(consume_stats_list.encode()).unwrap();
+        assert!(json.contains(r#""totalDiff":8"#), "{json}");
+        assert!(json.contains(r#""truncated":false"#), "{json}");
+    }
+
+    #[test]
+    fn consume_stats_list_stops_when_the_timeout_runs_out() {
+        let deadline = Instant::now() + Duration::from_millis(20);
+        let mut visited = Vec::new();
+        let consume_stats_list = collect_consume_stats(three_groups(), Some(deadline), |group| {
+            visited.push(group.clone());
+            if group == "GroupB" {
+                std::thread::sleep(Duration::from_millis(40));
+            }
+            vec![lagging_stats("TopicA", 3, 1)]
+        });
+        assert!(consume_stats_list.truncated);
+        assert_eq!(visited, &three_groups()[..2]);
+        assert_eq!(consume_stats_list.consume_stats_list.len(), 2);
+        assert_eq!(consume_stats_list.total_diff, 4);
+
+        let consume_stats_list =
+            collect_consume_stats(three_groups(), Some(Instant::now()), |_| {
+                panic!("no group is visited once the deadline passed")
+            });
+        assert!(consume_stats_list.truncated);
+        assert!(consume_stats_list.consume_stats_list.is_empty());
+    }
+
+    #[tokio::test]
+    async fn consumer_connections_report_language_and_version_of_heartbeats() {
+        let consumer_group_info = ConsumerGroupInfo::new(
+            "GroupA",
+            ConsumeType::ConsumePassively,
+            MessageModel::Clustering,
+            ConsumeFromWhere::ConsumeFromLastOffset,
+        );
+        for (language, version, body) in [
+            (
+                LanguageCode::JAVA,
+                RocketMqVersion::V531,
+                r#"{"clientID":"java-client"}"#,
+            ),
+            (LanguageCode::GO, RocketMqVersion::V494, "{}"),
+        ] {
+            let channel = client_channel().await;
+            let request = RemotingCommand::new_request(RequestCode::HeartBeat, body)
+                .set_language(language)
+                .set_version(version.into());
+            let heartbeat_data =
+                decode_heartbeat(&request, channel.remote_address().to_string().as_str()).unwrap();
+            consumer_group_info.update_channel(
+                ClientChannelInfo::new(
+                    channel,
+                    heartbeat_data.client_id,
+                    request.language(),
+                    request.version(),
+                ),
+                ConsumeType::ConsumePassively,
+                MessageModel::Clustering,
+                ConsumeFromWhere::ConsumeFromLastOffset,
+            );
+        }
+
+        let consumer_connection = consumer_connection(&consumer_group_info);
+        let mut connections = consumer_connection
+            .get_connection_set()
+            .into_iter()
+            .map(|connection| (connection.get_language(), connection.get_version()))
+            .collect::<Vec<_>>();
+        connections.sort_by_key(|(_, version)| *version);
+        assert_eq!(
+            connections,
+            vec![
+                (LanguageCode::GO, i32::from(RocketMqVersion::V494)),
+                (LanguageCode::JAVA, i32::from(RocketMqVersion::V531)),
+            ]
+        );
+        let json = String::from_utf8(consumer_connection.encode()).unwrap();
+        assert!(json.contains(r#""clientId":"java-client""#), "{json}");
+        assert!(json.contains(r#""connectionSet""#), "{json}");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn consume_queue_data_evaluates_the_subscription() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = start_store(&dir, MessageStoreConfig::default()).await;
+        let topic = CheetahString::from_static_str("TaggedTopic");
+        for i in 0..6 {
+            let mut msg = MessageExtBrokerInner::default();
+            msg.message_ext_inner.message.topic = topic.clone();
+            msg.message_ext_inner.message.body = Some(bytes::Bytes::from_static(b"tagged"));
+            let tags = if i % 2 == 0 { "TagA" } else { "TagB" };
+            MessageAccessor::put_property(
+                &mut msg,
+                CheetahString::from_static_str(MessageConst::PROPERTY_TAGS),
+                CheetahString::from_static_str(tags),
+            );
+            msg.properties_string = message_properties_to_string(msg.get_properties());
+            let result = store.put_message(msg).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        }
+        wait_dispatched(&store).await;
+
+        let subscription_data =
+            FilterAPI::build_subscription_data(&topic, &CheetahString::from_static_str("TagA"))
+                .unwrap();
+        let filter = ExpressionMessageFilter::new(
+            Some(subscription_data),
+            None,
+            Arc::new(ConsumerFilterManager::default()),
+        );
+        let consume_queue = store.find_consume_queue(&topic, 0).unwrap();
+
+        let queue_data = consume_queue_data(&**consume_queue, 2, 3, Some(&filter)).unwrap();
+        let evals: Vec<bool> = queue_data.iter().map(|data| data.eval).collect();
+        assert_eq!(evals, vec![true, false, true]);
+        assert!(queue_data
+            .windows(2)
+            .all(|pair| pair[0].physic_offset < pair[1].physic_offset));
+        assert!(queue_data.iter().all(|data| data.msg.is_none()));
+
+        // without a consumer group nothing is evaluated
+        let queue_data = consume_queue_data(&**consume_queue, 4, 32, None).unwrap();
+        assert_eq!(queue_data.len(), 2);
+        assert!(queue_data.iter().all(|data| !data.eval));
+
+        assert!(consume_queue_data(&**consume_queue, 6, 1, None).is_none());
+        store.shutdown();
+    }
+
+    fn seed_offsets(
+        consumer_offset_manager: &ConsumerOffsetManager,
+        topic: &str,
+        group: &str,
+        offsets: &[(i32, i64)],
+    ) {
+        let client: SocketAddr = "127.0.0.1:9876".parse().unwrap();
+        for (queue_id, offset) in offsets {
+            consumer_offset_manager.commit_offset(
+                client,
+                &group.into(),
+                &topic.into(),
+                *queue_id,
+                *offset,
+            );
+        }
+    }
+
+    fn clone_request(
+        src_group: &str,
+        dest_group: &str,
+        topic: Option<&str>,
+        offline: bool,
+    ) -> CloneGroupOffsetRequestHeader {
+        CloneGroupOffsetRequestHeader {
+            src_group: src_group.into(),
+            dest_group: dest_group.into(),
+            topic: topic.map(CheetahString::from_slice),
+            offline,
+        }
+    }
+
+    #[tokio::test]
+    async fn clone_group_offset_copies_the_subscribed_topics() {
+        let consumer_offset_manager =
+            ConsumerOffsetManager::new(Arc::new(BrokerConfig::default()), None);
+        seed_offsets(
+            &consumer_offset_manager,
+            "TopicA",
+            "GroupA",
+            &[(0, 5), (1, 7)],
+        );
+        seed_offsets(&consumer_offset_manager, "TopicAB", "GroupA", &[(0, 3)]);
+        seed_offsets(&consumer_offset_manager, "TopicB", "GroupA", &[(0, 2)]);
+        seed_offsets(&consumer_offset_manager, "TopicA", "GroupA2", &[(0, 100)]);
+        seed_offsets(
+            &consumer_offset_manager,
+            "TopicA",
+            "GroupC",
+            &[(0, 1), (2, 1)],
+        );
+        let consumer_manager = ConsumerManager::new(
+            Box::new(DefaultConsumerIdsChangeListener::default()),
+            BrokerConfig::default().channel_expired_timeout,
+        );
+        let known_topic = |topic: &CheetahString| topic != "TopicB";
+
+        // every known topic of the offline source, replacing what the destination had
+        clone_group_offset(
+            &consumer_offset_manager,
+            &consumer_manager,
+            &clone_request("GroupA", "GroupC", None, false),
+            known_topic,
+        );
+        assert_eq!(
+            consumer_offset_manager.query_offset_table("GroupC", "TopicA"),
+            HashMap::from([(0, 5), (1, 7)])
+        );
+        assert_eq!(
+            consumer_offset_manager.query_offset_table("GroupC", "TopicAB"),
+            HashMap::from([(0, 3)])
+        );
+        assert!(consumer_offset_manager
+            .query_offset_table("GroupC", "TopicB")
+            .is_empty());
+        assert_eq!(
+            consumer_offset_manager.query_offset_table("GroupA2", "TopicA"),
+            HashMap::from([(0, 100)])
+        );
+
+        // an online source only gives the offsets of the topics it still subscribes to
+        consumer_manager.register_consumer(
+            &"GroupA".into(),
+            ClientChannelInfo::new(
+                client_channel().await,
+                "client-a".into(),
+                LanguageCode::RUST,
+                0,
+            ),
+            ConsumeType::ConsumePassively,
+            MessageModel::Clustering,
+            ConsumeFromWhere::ConsumeFromLastOffset,
+            HashSet::from([
+                FilterAPI::build_subscription_data(&"TopicAB".into(), &"*".into()).unwrap(),
+            ]),
+            false,
+        );
+        clone_group_offset(
+            &consumer_offset_manager,
+            &consumer_manager,
+            &clone_request("GroupA", "GroupD", Some("TopicA"), false),
+            known_topic,
+        );
+        assert!(consumer_offset_manager
+            .query_offset_table("GroupD", "TopicA")
+            .is_empty());
+        clone_group_offset(
+            &consumer_offset_manager,
+            &consumer_manager,
+            &clone_request("GroupA", "GroupD", Some("TopicA"), true),
+            known_topic,
+        );
+        assert_eq!(
+            consumer_offset_manager.query_offset_table("GroupD", "TopicA"),
+            HashMap::from([(0, 5), (1, 7)])
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn correction_offset_is_the_smallest_offset_of_the_other_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = start_store(&dir, MessageStoreConfig::default()).await;
+        let consumer_offset_manager =
+            ConsumerOffsetManager::new(Arc::new(BrokerConfig::default()), None);
+        seed_offsets(
+            &consumer_offset_manager,
+            "TopicA",
+            "GroupA",
+            &[(0, 5), (1, 7)],
+        );
+        seed_offsets(
+            &consumer_offset_manager,
+            "TopicA",
+            "GroupB",
+            &[(0, 3), (1, 9)],
+        );
+        seed_offsets(
+            &consumer_offset_manager,
+            "TopicA",
+            "GroupD",
+            &[(0, 1), (1, 20)],
+        );
+        seed_offsets(
+            &consumer_offset_manager,
+            "TopicAB",
+            "GroupE",
+            &[(0, 0), (1, 0)],
+        );
+        let request =
+            |filter_groups: Option<&str>, compare_group: &str| QueryCorrectionOffsetHeader {
+                filter_groups: filter_groups.map(CheetahString::from_slice),
+                compare_group: compare_group.into(),
+                topic: "TopicA".into(),
+            };
+
+        // GroupD is behind the others on queue 0, nothing to correct there
+        let body = query_correction_offset(
+            &consumer_offset_manager,
+            store.as_ref(),
+            &request(Some("GroupD"), "GroupD"),
+        );
+        assert_eq!(
+            body.correction_offsets,
+            HashMap::from([(0, i64::MAX), (1, 7)])
+        );
+
+        let body = query_correction_offset(
+            &consumer_offset_manager,
+            store.as_ref(),
+            &request(Some(" GroupA ,GroupD"), "GroupB"),
+        );
+        assert_eq!(body.correction_offsets, HashMap::from([(0, 3), (1, 9)]));
+
+        let body = query_correction_offset(
+            &consumer_offset_manager,
+            store.as_ref(),
+            &request(None, "GroupA"),
+        );
+        assert_eq!(body.correction_offsets, HashMap::from([(0, 1), (1, 7)]));
+        let decoded = QueryCorrectionOffsetBody::decode(&body.encode()).unwrap();
+        assert_eq!(decoded.correction_offsets, body.correction_offsets);
+        store.shutdown();
+    }
 }
@@ -17,11 +17,15 @@
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::header::get_earliest_msg_storetime_request_header::GetEarliestMsgStoretimeRequestHeader;
+use rocketmq_remoting::protocol::header::get_earliest_msg_storetime_response_header::GetEarliestMsgStoretimeResponseHeader;
 use rocketmq_remoting::protocol::header::get_max_offset_request_header::GetMaxOffsetRequestHeader;
 use rocketmq_remoting::protocol::header::get_max_offset_response_header::GetMaxOffsetResponseHeader;
 use rocketmq_remoting::protocol::header::get_min_offset_request_header::GetMinOffsetRequestHeader;
 use rocketmq_remoting::protocol::header::get_min_offset_response_header::GetMinOffsetResponseHeader;
 use rocketmq_remoting::protocol::header::message_operation_header::TopicRequestHeaderTrait;
+use rocketmq_remoting::protocol::header::search_offset_request_header::SearchOffsetRequestHeader;
+use rocketmq_remoting::protocol::header::search_offset_response_header::SearchOffsetResponseHeader;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_context::TopicQueueMappingContext;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_utils::TopicQueueMappingUtils;
@@ -29,6 +33,7 @@ use rocketmq_remoting::rpc::rpc_client::RpcClient;
 use rocketmq_remoting::rpc::rpc_request::RpcRequest;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_store::log_file::MessageStore;
+use rocketmq_store::message_store::default_message_store::DefaultMessageStore;
 
 use crate::processor::admin_broker_processor::Inner;
 
@@ -56,23 +61,15 @@ impl OffsetRequestHandler {
             .inner
             .topic_queue_mapping_manager
             .build_topic_queue_mapping_context(&request_header, false);
-        let topic = request_header.topic.clone();
-        let queue_id = request_header.queue_id;
+        let local_response =
+            max_offset_response(self.inner.default_message_store.as_ref(), &request_header);
         let rewrite_result = self
             .rewrite_request_for_static_topic(request_header, mapping_context)
             .await;
         if rewrite_result.is_some() {
             return rewrite_result;
         }
-
-        let offset = self
-            .inner
-            .default_message_store
-            .get_max_offset_in_queue(topic.as_ref(), queue_id);
-        let response_header = GetMaxOffsetResponseHeader { offset };
-        Some(RemotingCommand::create_response_command_with_header(
-            response_header,
-        ))
+        Some(local_response)
     }
 
     pub async fn get_min_offset(
@@ -88,31 +85,222 @@ impl OffsetRequestHandler {
             .inner
             .topic_queue_mapping_manager
             .build_topic_queue_mapping_context(&request_header, false);
-        let topic = request_header.topic.clone();
-        let queue_id = request_header.queue_id;
+        let local_response =
+            min_offset_response(self.inner.default_message_store.as_ref(), &request_header);
         let rewrite_result = self
             .handle_get_min_offset_for_static_topic(request_header, mapping_context)
             .await;
         if rewrite_result.is_some() {
             return rewrite_result;
         }
+        Some(local_response)
+    }
+
+    pub async fn get_earliest_msg_storetime(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let request_header =
+            request.decode_command_custom_header::<GetEarliestMsgStoretimeRequestHeader>()?;
 
-        let offset = self
+        let mapping_context = self
             .inner
-            .default_message_store
-            .get_min_offset_in_queue(topic.as_ref(), queue_id);
-        let response_header = GetMinOffsetResponseHeader { offset };
+            .topic_queue_mapping_manager
+            .build_topic_queue_mapping_context(&request_header, false);
+        let local_response = earliest_msg_storetime_response(
+            self.inner.default_message_store.as_ref(),
+            &request_header,
+        );
+        let rewrite_result = self
+            .handle_get_earliest_msg_storetime_for_static_topic(request_header, mapping_context)
+            .await;
+        if rewrite_result.is_some() {
+            return rewrite_result;
+        }
+        Some(local_response)
+    }
+
+    pub async fn search_offset_by_timestamp(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let request_header = request.decode_command_custom_header::<SearchOffsetRequestHeader>()?;
+
+        let mapping_context = self
+            .inner
+            .topic_queue_mapping_manager
+            .build_topic_queue_mapping_context(&request_header, false);
+        let local_response =
+            search_offset_response(self.inner.default_message_store.as_ref(), &request_header);
+        let rewrite_result = self
+            .handle_search_offset_for_static_topic(request_header, mapping_context)
+            .await;
+        if rewrite_result.is_some() {
+            return rewrite_result;
+        }
+        Some(local_response)
+    }
+
+    async fn handle_search_offset_for_static_topic(
+        &mut self,
+        mut request_header: SearchOffsetRequestHeader,
+        mapping_context: TopicQueueMappingContext,
+    ) -> Option<RemotingCommand> {
+        let mapping_detail = mapping_context.mapping_detail.as_ref()?;
+        if !mapping_context.is_leader() {
+            return Some(
+                RemotingCommand::create_response_command_with_code(ResponseCode::NotLeaderForQueue)
+                    .set_remark(format!(
+                        "{}-{:?} does not exit in request process of current broker {:?}",
+                        mapping_context.topic,
+                        mapping_context.global_id,
+                        mapping_detail.topic_queue_mapping_info.bname
+                    )),
+            );
+        }
+
+        // walk the items from the oldest one, the first that holds the timestamp wins
+        let mut offset = -1;
+        for item in &mapping_context.mapping_item_list {
+            if !item.check_if_logic_offset_decided() {
+                continue;
+            }
+            if item.bname == mapping_detail.topic_queue_mapping_info.bname {
+                let physical_offset = self
+                    .inner
+                    .default_message_store
+                    .get_offset_in_queue_by_time(
+                        &mapping_context.topic,
+                        item.queue_id,
+                        request_header.timestamp,
+                    );
+                if physical_offset > 0 {
+                    offset = item.compute_static_queue_offset_strictly(physical_offset);
+                    break;
+                }
+            } else {
+                request_header.set_broker_name(item.bname.clone()?);
+                request_header.set_lo(Some(false));
+                request_header.queue_id = item.queue_id;
+                let rpc_request = RpcRequest::new(
+                    RequestCode::SearchOffsetByTimestamp.to_i32(),
+                    request_header.clone(),
+                    None,
+                );
+                let rpc_response = self
+                    .inner
+                    .broker_out_api
+                    .rpc_client()
+                    .invoke(rpc_request, self.inner.broker_config.forward_timeout)
+                    .await;
+                let physical_offset = match rpc_response {
+                    Err(e) => {
+                        return Some(
+                            RemotingCommand::create_response_command_with_code(
+                                ResponseCode::SystemError,
+                            )
+                            .set_remark(format!("{}", e)),
+                        );
+                    }
+                    Ok(rpc_response) => {
+                        match rpc_response.get_header::<SearchOffsetResponseHeader>() {
+                            None => {
+                                return Some(
+                                    RemotingCommand::create_response_command_with_code(
+                                        ResponseCode::SystemError,
+                                    )
+                                    .set_remark("Rpc response header is None"),
+                                );
+                            }
+                            Some(response_header) => response_header.offset,
+                        }
+                    }
+                };
+                if physical_offset < 0
+                    || (item.check_if_end_offset_decided() && physical_offset >= item.end_offset)
+                {
+                    continue;
+                }
+                offset = item.compute_static_queue_offset_strictly(physical_offset);
+            }
+        }
         Some(RemotingCommand::create_response_command_with_header(
-            response_header,
+            SearchOffsetResponseHeader { offset },
         ))
     }
-    /*
-    async fn handle_get_min_offset(
+
+    async fn handle_get_earliest_msg_storetime_for_static_topic(
         &mut self,
-        mut request_header: GetMinOffsetRequestHeader,
+        mut request_header: GetEarliestMsgStoretimeRequestHeader,
+        mapping_context: TopicQueueMappingContext,
     ) -> Option<RemotingCommand> {
+        let mapping_detail = mapping_context.mapping_detail.as_ref()?;
+        if !mapping_context.is_leader() {
+            return Some(
+                RemotingCommand::create_response_command_with_code(ResponseCode::NotLeaderForQueue)
+                    .set_remark(format!(
+                        "{}-{:?} does not exit in request process of current broker {:?}",
+                        mapping_context.topic,
+                        mapping_context.global_id,
+                        mapping_detail.topic_queue_mapping_info.bname
+                    )),
+            );
+        }
 
-    }*/
+        // the earliest message lives in the item holding logic offset 0
+        let min_item = TopicQueueMappingUtils::find_logic_queue_mapping_item(
+            &mapping_context.mapping_item_list,
+            0,
+            true,
+        )?;
+        request_header.set_broker_name(min_item.bname.clone()?);
+        request_header.set_lo(Some(false));
+        request_header.queue_id = min_item.queue_id;
+        if min_item.bname == mapping_detail.topic_queue_mapping_info.bname {
+            return Some(earliest_msg_storetime_response(
+                self.inner.default_message_store.as_ref(),
+                &request_header,
+            ));
+        }
+        let rpc_request = RpcRequest::new(
+            RequestCode::GetEarliestMsgStoreTime.to_i32(),
+            request_header,
+            None,
+        );
+        let rpc_response = self
+            .inner
+            .broker_out_api
+            .rpc_client()
+            .invoke(rpc_request, self.inner.broker_config.forward_timeout)
+            .await;
+        match rpc_response {
+            Err(e) => Some(
+                RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                    .set_remark(format!("{}", e)),
+            ),
+            Ok(rpc_response) => match rpc_response
+                .get_header::<GetEarliestMsgStoretimeResponseHeader>()
+            {
+                None => Some(
+                    RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                        .set_remark("Rpc response header is None"),
+                ),
+                Some(response_header) => {
+                    Some(RemotingCommand::create_response_command_with_header(
+                        GetEarliestMsgStoretimeResponseHeader {
+                            timestamp: response_header.timestamp,
+                        },
+                    ))
+                }
+            },
+        }
+    }
 
     async fn handle_get_min_offset_for_static_topic(
         &mut self,
@@ -255,3 +443,208 @@ impl OffsetRequestHandler {
         ))
     }
 }
+
+/// Answers `GET_MAX_OFFSET` from the local store. Unless `committed` is false the offset only
+/// covers messages already dispatched to the consume queue.
+fn max_offset_response(
+    message_store: &DefaultMessageStore,
+    request_header: &GetMaxOffsetRequestHeader,
+) -> RemotingCommand {
+    let offset = message_store.get_max_offset_in_queue_committed(
+        &request_header.topic,
+        request_header.queue_id,
+        request_header.committed,
+    );
+    RemotingCommand::create_response_command_with_header(GetMaxOffsetResponseHeader { offset })
+}
+
+fn min_offset_response(
+    message_store: &DefaultMessageStore,
+    request_header: &GetMinOffsetRequestHeader,
+) -> RemotingCommand {
+    let offset =
+        message_store.get_min_offset_in_queue(&request_header.topic, request_header.queue_id);
+    RemotingCommand::create_response_command_with_header(GetMinOffsetResponseHeader { offset })
+}
+
+/// Answers `SEARCH_OFFSET_BY_TIMESTAMP` from the local store with the first offset whose store
+/// time is not earlier than the requested timestamp, clamped to the queue's offsets.
+fn search_offset_response(
+    message_store: &DefaultMessageStore,
+    request_header: &SearchOffsetRequestHeader,
+) -> RemotingCommand {
+    let offset = message_store.get_offset_in_queue_by_time(
+        &request_header.topic,
+        request_header.queue_id,
+        request_header.timestamp,
+    );
+    RemotingCommand::create_response_command_with_header(SearchOffsetResponseHeader { offset })
+}
+
+fn earliest_msg_storetime_response(
+    message_store: &DefaultMessageStore,
+    request_header: &GetEarliestMsgStoretimeRequestHeader,
+) -> RemotingCommand {
+    let timestamp = message_store
+        .get_earliest_message_time_in_queue(&request_header.topic, request_header.queue_id);
+    RemotingCommand::create_response_command_with_header(GetEarliestMsgStoretimeResponseHeader {
+        timestamp,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use cheetah_string::CheetahString;
+    use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+    use rocketmq_remoting::protocol::command_custom_header::CommandCustomHeader;
+    use rocketmq_remoting::protocol::command_custom_header::FromMap;
+    use rocketmq_rust::ArcMut;
+    use rocketmq_store::base::message_status_enum::PutMessageStatus;
+    use rocketmq_store::config::message_store_config::MessageStoreConfig;
+    use rocketmq_store::test_util::load_store;
+    use rocketmq_store::test_util::wait_dispatched;
+
+    use super::*;
+
+    async fn put_message(store: &mut ArcMut<DefaultMessageStore>, topic: &str) {
+        let mut msg = MessageExtBrokerInner::default();
+        msg.message_ext_inner.message.topic = CheetahString::from_slice(topic);
+        msg.message_ext_inner.message.body = Some(bytes::Bytes::from_static(b"offset"));
+        let result = store.put_message(msg).await;
+        assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+    }
+
+    /// Sends the header over the wire format and decodes it the way the handler does.
+    fn decode<T>(code: RequestCode, header: T) -> T
+    where
+        T: CommandCustomHeader + FromMap<Target = T> + Sync + Send + 'static,
+    {
+        let mut request = RemotingCommand::create_request_command(code, header);
+        request.make_custom_header_to_net();
+        request.decode_command_custom_header::<T>().unwrap()
+    }
+
+    fn max_offset(store: &DefaultMessageStore, topic: &str, committed: bool) -> i64 {
+        let request_header = decode(
+            RequestCode::GetMaxOffset,
+            GetMaxOffsetRequestHeader {
+                topic: CheetahString::from_slice(topic),
+                queue_id: 0,
+                committed,
+                ..Default::default()
+            },
+        );
+        max_offset_response(store, &request_header)
+            .read_custom_header_ref::<GetMaxOffsetResponseHeader>()
+            .unwrap()
+            .offset
+    }
+
+    fn min_offset(store: &DefaultMessageStore, topic: &str) -> i64 {
+        let request_header = decode(
+            RequestCode::GetMinOffset,
+            GetMinOffsetRequestHeader {
+                topic: CheetahString::from_slice(topic),
+                queue_id: 0,
+                ..Default::default()
+            },
+        );
+        min_offset_response(store, &request_header)
+            .read_custom_header_ref::<GetMinOffsetResponseHeader>()
+            .unwrap()
+            .offset
+    }
+
+    fn earliest_msg_storetime(store: &DefaultMessageStore, topic: &str) -> i64 {
+        let request_header = decode(
+            RequestCode::GetEarliestMsgStoreTime,
+            GetEarliestMsgStoretimeRequestHeader {
+                topic: CheetahString::from_slice(topic),
+                queue_id: 0,
+                ..Default::default()
+            },
+        );
+        earliest_msg_storetime_response(store, &request_header)
+            .read_custom_header_ref::<GetEarliestMsgStoretimeResponseHeader>()
+            .unwrap()
+            .timestamp
+    }
+
+    fn search_offset(store: &DefaultMessageStore, topic: &str, timestamp: i64) -> i64 {
+        let request_header = decode(
+            RequestCode::SearchOffsetByTimestamp,
+            SearchOffsetRequestHeader {
+                topic: CheetahString::from_slice(topic),
+                queue_id: 0,
+                timestamp,
+                ..Default::default()
+            },
+        );
+        search_offset_response(store, &request_header)
+            .read_custom_header_ref::<SearchOffsetResponseHeader>()
+            .unwrap()
+            .offset
+    }
+
+    #[tokio::test]
+    async fn max_offset_is_committed_only_when_requested() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = load_store(&dir, MessageStoreConfig::default()).await;
+        put_message(&mut store, "TopicTest").await;
+        put_message(&mut store, "TopicTest").await;
+
+        // written but the reput service is not running: nothing is dispatched yet
+        assert_eq!(max_offset(&store, "TopicTest", false), 2);
+        assert_eq!(max_offset(&store, "TopicTest", true), 0);
+        assert_eq!(earliest_msg_storetime(&store, "TopicTest"), -1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn offset_requests_answer_from_the_message_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = load_store(&dir, MessageStoreConfig::default()).await;
+        store.start().unwrap();
+        let begin = rocketmq_common::TimeUtils::get_current_millis() as i64;
+        put_message(&mut store, "TopicTest").await;
+        put_message(&mut store, "TopicTest").await;
+        wait_dispatched(&store).await;
+
+        assert_eq!(max_offset(&store, "TopicTest", true), 2);
+        assert_eq!(max_offset(&store, "TopicTest", false), 2);
+        assert_eq!(min_offset(&store, "TopicTest"), 0);
+        let earliest = earliest_msg_storetime(&store, "TopicTest");
+        assert!(earliest >= begin, "{earliest} < {begin}");
+        store.shutdown();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn search_offset_by_timestamp_finds_the_first_later_message() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = load_store(&dir, MessageStoreConfig::default()).await;
+        store.start().unwrap();
+        put_message(&mut store, "TopicTest").await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let between = rocketmq_common::TimeUtils::get_current_millis() as i64;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        put_message(&mut store, "TopicTest").await;
+        wait_dispatched(&store).await;
+
+        assert_eq!(search_offset(&store, "TopicTest", 0), 0);
+        assert_eq!(search_offset(&store, "TopicTest", between), 1);
+        assert_eq!(search_offset(&store, "TopicTest", i64::MAX), 2);
+        assert_eq!(search_offset(&store, "EmptyTopic", between), 0);
+        store.shutdown();
+    }
+
+    #[tokio::test]
+    async fn offset_requests_for_unknown_queue_return_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = load_store(&dir, MessageStoreConfig::default()).await;
+        assert_eq!(max_offset(&store, "NoSuchTopic", true), 0);
+        assert_eq!(max_offset(&store, "NoSuchTopic", false), 0);
+        assert_eq!(min_offset(&store, "NoSuchTopic"), 0);
+        assert_eq!(earliest_msg_storetime(&store, "NoSuchTopic"), -1);
+    }
+}
@@ -0,0 +1,286 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::mix_all;
+use rocketmq_common::common::topic::TopicValidator;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::header::delete_subscription_group_request_header::DeleteSubscriptionGroupRequestHeader;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::subscription::subscription_group_config::SubscriptionGroupConfig;
+use rocketmq_remoting::protocol::RemotingDeserializable;
+use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
+use rocketmq_store::log_file::MessageStore;
+use tracing::info;
+
+use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
+use crate::processor::admin_broker_processor::Inner;
+use crate::subscription::manager::subscription_group_manager::SubscriptionGroupManager;
+use crate::topic::manager::topic_config_manager::TopicConfigManager;
+
+#[derive(Clone)]
+pub(super) struct SubscriptionGroupHandler {
+    inner: Inner,
+}
+
+impl SubscriptionGroupHandler {
+    pub fn new(inner: Inner) -> Self {
+        Self { inner }
+    }
+}
+
+impl SubscriptionGroupHandler {
+    pub async fn update_and_create_subscription_group(
+        &mut self,
+        channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        info!(
+            "AdminBrokerProcessor#updateAndCreateSubscriptionGroup called by {}",
+            channel.remote_address()
+        );
+        Some(update_subscription_group_response(
+            self.inner.subscription_group_manager.as_ref(),
+            &request,
+        ))
+    }
+
+    pub async fn delete_subscription_group(
+        &mut self,
+        channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let request_header =
+            request.decode_command_custom_header::<DeleteSubscriptionGroupRequestHeader>()?;
+        info!(
+            "AdminBrokerProcessor#deleteSubscriptionGroup, caller={}",
+            channel.remote_address()
+        );
+        Some(delete_subscription_group_response(
+            self.inner.subscription_group_manager.as_ref(),
+            &self.inner.consumer_offset_manager,
+            &self.inner.topic_config_manager,
+            &request_header,
+        ))
+    }
+}
+
+/// Answers `UPDATE_AND_CREATE_SUBSCRIPTIONGROUP` by storing the config carried as JSON in the
+/// request body.
+fn update_subscription_group_response<MS: MessageStore>(
+    subscription_group_manager: &SubscriptionGroupManager<MS>,
+    request: &RemotingCommand,
+) -> RemotingCommand {
+    let response = RemotingCommand::create_response_command();
+    let config = match request
+        .body()
+        .as_ref()
+        .map(|body| SubscriptionGroupConfig::decode(body.as_ref()))
+    {
+        Some(Ok(config)) => config,
+        Some(Err(err)) => {
+            return response
+                .set_code(ResponseCode::SystemError)
+                .set_remark(format!("decode subscription group config failed: {err}"));
+        }
+        None => {
+            return response
+                .set_code(ResponseCode::SystemError)
+                .set_remark("the subscription group config is missing.");
+        }
+    };
+    if config.group_name().is_empty()
+        || TopicValidator::is_topic_or_group_illegal(config.group_name())
+    {
+        return response
+            .set_code(ResponseCode::SystemError)
+            .set_remark(format!(
+                "the specified group [{}] is blank or contains illegal characters.",
+                config.group_name()
+            ));
+    }
+    subscription_group_manager.update_subscription_group_config(config);
+    response.set_code(ResponseCode::Success)
+}
+
+/// Answers `DELETE_SUBSCRIPTIONGROUP`. With `cleanOffset` the group's consumer offsets and its
+/// retry and DLQ topics go away as well.
+fn delete_subscription_group_response<MS: MessageStore>(
+    subscription_group_manager: &SubscriptionGroupManager<MS>,
+    consumer_offset_manager: &ConsumerOffsetManager,
+    topic_config_manager: &TopicConfigManager,
+    request_header: &DeleteSubscriptionGroupRequestHeader,
+) -> RemotingCommand {
+    let group = &request_header.group_name;
+    subscription_group_manager.delete_subscription_group_config(group);
+    if request_header.clean_offset {
+        consumer_offset_manager.remove_offset(group);
+        for topic in [
+            mix_all::get_retry_topic(group),
+            mix_all::get_dlq_topic(group),
+        ] {
+            let topic = CheetahString::from_string(topic);
+            if topic_config_manager.select_topic_config(&topic).is_some() {
+                topic_config_manager.delete_topic_config(&topic);
+            }
+        }
+    }
+    RemotingCommand::create_response_command().set_code(ResponseCode::Success)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::config::TopicConfig;
+    use rocketmq_remoting::protocol::subscription::group_retry_policy_type::GroupRetryPolicyType;
+    use rocketmq_store::message_store::default_message_store::DefaultMessageStore;
+
+    use super::*;
+    use crate::test_util::topic_config_manager;
+
+    fn broker_config(dir: &tempfile::TempDir) -> Arc<BrokerConfig> {
+        Arc::new(BrokerConfig {
+            store_path_root_dir: dir.path().to_string_lossy().into_owned().into(),
+            ..BrokerConfig::default()
+        })
+    }
+
+    #[test]
+    fn update_subscription_group_keeps_retry_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager =
+            SubscriptionGroupManager::<DefaultMessageStore>::new(broker_config(&dir), None);
+        let body = r#"{
+            "groupName": "AdminGroup",
+            "consumeEnable": true,
+            "consumeBroadcastEnable": false,
+            "retryQueueNums": 1,
+            "retryMaxTimes": 3,
+            "brokerId": 0,
+            "whichBrokerWhenConsumeSlowly": 1,
+            "groupRetryPolicy": {
+                "type": "EXPONENTIAL",
+                "exponentialRetryPolicy": {"initial": 2000, "max": 20000, "multiplier": 2}
+            }
+        }"#;
+        let request =
+            RemotingCommand::create_remoting_command(RequestCode::UpdateAndCreateSubscriptionGroup)
+                .set_body(body);
+
+        let response = update_subscription_group_response(&manager, &request);
+        assert_eq!(ResponseCode::from(response.code()), ResponseCode::Success);
+        let config = manager
+            .find_subscription_group_config_inner(&CheetahString::from_static_str("AdminGroup"))
+            .unwrap();
+        assert!(!config.consume_broadcast_enable());
+        assert_eq!(config.retry_max_times(), 3);
+        assert_eq!(
+            config.group_retry_policy().type_(),
+            GroupRetryPolicyType::Exponential
+        );
+        assert_eq!(
+            config
+                .group_retry_policy()
+                .exponential_retry_policy()
+                .as_ref()
+                .unwrap()
+                .initial(),
+            2000
+        );
+
+        let request =
+            RemotingCommand::create_remoting_command(RequestCode::UpdateAndCreateSubscriptionGroup)
+                .set_body(r#"{"groupName": ""}"#);
+        let response = update_subscription_group_response(&manager, &request);
+        assert_eq!(
+            ResponseCode::from(response.code()),
+            ResponseCode::SystemError
+        );
+    }
+
+    #[test]
+    fn delete_subscription_group_cleans_offsets_and_retry_topics_on_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker_config = broker_config(&dir);
+        let subscription_group_manager =
+            SubscriptionGroupManager::<DefaultMessageStore>::new(broker_config.clone(), None);
+        let consumer_offset_manager = ConsumerOffsetManager::new(broker_config.clone(), None);
+        let topic_config_manager = topic_config_manager(broker_config);
+        let client_host = "127.0.0.1:10911".parse().unwrap();
+        let topic = CheetahString::from_static_str("AdminTopic");
+        let groups = [
+            CheetahString::from_static_str("KeptGroup"),
+            CheetahString::from_static_str("CleanedGroup"),
+        ];
+        for group in groups.iter() {
+            subscription_group_manager
+                .update_subscription_group_config(SubscriptionGroupConfig::new(group.clone()));
+            consumer_offset_manager.commit_offset(client_host, group, &topic, 0, 7);
+            for topic in [
+                mix_all::get_retry_topic(group),
+                mix_all::get_dlq_topic(group),
+            ] {
+                topic_config_manager.put_topic_config(TopicConfig::new(topic));
+            }
+        }
+        let delete = |group: &CheetahString, clean_offset: bool| {
+            let response = delete_subscription_group_response(
+                &subscription_group_manager,
+                &consumer_offset_manager,
+                &topic_config_manager,
+                &DeleteSubscriptionGroupRequestHeader {
+                    group_name: group.clone(),
+                    clean_offset,
+                    rpc: None,
+                },
+            );
+            assert_eq!(ResponseCode::from(response.code()), ResponseCode::Success);
+        };
+        let retry_topic_exists = |group: &CheetahString| {
+            topic_config_manager
+                .select_topic_config(&mix_all::get_retry_topic(group).into())
+                .is_some()
+        };
+
+        delete(&groups[0], false);
+        assert!(!subscription_group_manager.contains_subscription_group(&groups[0]));
+        assert_eq!(
+            consumer_offset_manager.query_offset(&groups[0], &topic, 0),
+            7
+        );
+        assert!(retry_topic_exists(&groups[0]));
+
+        delete(&groups[1], true);
+        assert!(!subscription_group_manager.contains_subscription_group(&groups[1]));
+        assert_eq!(
+            consumer_offset_manager.query_offset(&groups[1], &topic, 0),
+            -1
+        );
+        assert!(!retry_topic_exists(&groups[1]));
+        assert!(topic_config_manager
+            .select_topic_config(&mix_all::get_dlq_topic(&groups[1]).into())
+            .is_none());
+    }
+}
@@ -16,6 +16,7 @@
  */
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 
 use cheetah_string::CheetahString;
 use rocketmq_common::common::attribute::attribute_parser::AttributeParser;
@@ -49,7 +50,9 @@ use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_store::log_file::MessageStore;
 use tracing::info;
+use tracing::warn;
 
+use crate::client::net::broker_to_client::Broker2Client;
 use crate::processor::admin_broker_processor::Inner;
 
 #[derive(Clone)]
@@ -93,16 +96,15 @@ impl TopicRequestHandler {
             .inner
             .broker_config
             .validate_system_topic_when_update_topic
-            && TopicValidator::is_system_topic(topic.as_str())
         {
-            return Some(
-                response
-                    .set_code(ResponseCode::SystemError)
-                    .set_remark(format!(
-                        "The topic[{}] is conflict with system topic.",
-                        topic.as_str()
-                    )),
-            );
+            let result = TopicValidator::check_system_topic(topic.as_str());
+            if !result.valid() {
+                return Some(
+                    response
+                        .set_code(ResponseCode::SystemError)
+                        .set_remark(result.remark().clone()),
+                );
+            }
         }
 
         let attributes = match AttributeParser::parse_to_map(
@@ -161,9 +163,17 @@ impl TopicRequestHandler {
             );
             return Some(response.set_code(ResponseCode::Success));
         }
-        self.inner
+        if let Err(err) = self
+            .inner
             .topic_config_manager
-            .update_topic_config(&mut topic_config);
+            .update_topic_config(&mut topic_config)
+        {
+            return Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark(err.to_string()),
+            );
+        }
 
         if self.inner.broker_config.enable_single_topic_register {
             self.inner
@@ -185,6 +195,163 @@ impl TopicRequestHandler {
                 )
                 .await;
         }
+        self.notify_topic_route_changed(&topic).await;
+
+        Some(response.set_code(ResponseCode::Success))
+    }
+
+    /// Tells the producers and consumers using `topic` that its route changed. Called once the
+    /// name servers were told, so the clients refresh to the new route.
+    #[allow(clippy::mutable_key_type)]
+    async fn notify_topic_route_changed(&self, topic: &CheetahString) {
+        if !self.inner.broker_config.enable_topic_route_notify {
+            return;
+        }
+        let channels = self
+            .inner
+            .producer_manager
+            .find_channels_by_topic(topic)
+            .into_iter()
+            .chain(self.inner.consume_manager.find_channels_by_topic(topic))
+            .collect::<HashSet<_>>();
+        for mut channel in channels {
+            if let Err(err) = Broker2Client
+                .notify_topic_route_changed(&mut channel, topic)
+                .await
+            {
+                warn!(
+                    "notify client[{}] of the route change of topic={} failed: {}",
+                    channel.remote_address(),
+                    topic,
+                    err
+                );
+            }
+        }
+    }
+
+    pub async fn update_and_create_static_topic(
+        &mut self,
+        channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let response = RemotingCommand::create_response_command();
+        let request_header = request
+            .decode_command_custom_header::<CreateTopicRequestHeader>()
+            .unwrap();
+        info!(
+            "Broker receive request to update or create static topic={}, caller address={}",
+            request_header.topic,
+            channel.remote_address()
+        );
+        let mapping_detail = match request
+            .body()
+            .as_ref()
+            .map(|body| TopicQueueMappingDetail::decode(body.as_ref()))
+        {
+            Some(Ok(value)) => value,
+            Some(Err(err)) => {
+                return Some(
+                    response
+                        .set_code(ResponseCode::SystemError)
+                        .set_remark(format!("decode topic queue mapping failed: {err}")),
+                );
+            }
+            None => {
+                return Some(
+                    response
+                        .set_code(ResponseCode::SystemError)
+                        .set_remark("topic queue mapping body is missing"),
+                );
+            }
+        };
+        let topic = request_header.topic.clone();
+        let result = TopicValidator::validate_topic(topic.as_str());
+        if !result.valid() {
+            return Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark(result.remark().clone()),
+            );
+        }
+        if TopicValidator::is_system_topic(topic.as_str()) {
+            return Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark(format!("The topic[{topic}] is conflict with system topic.")),
+            );
+        }
+        if mapping_detail.topic_queue_mapping_info.topic.as_ref() != Some(&topic) {
+            return Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark(format!(
+                        "The topic queue mapping does not belong to topic[{topic}]"
+                    )),
+            );
+        }
+
+        let attributes = match AttributeParser::parse_to_map(
+            request_header
+                .attributes
+                .clone()
+                .unwrap_or(CheetahString::empty())
+                .as_str(),
+        ) {
+            Ok(value) => value
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+            Err(err) => {
+                return Some(response.set_code(ResponseCode::SystemError).set_remark(err));
+            }
+        };
+        let mut topic_config = TopicConfig {
+            topic_name: Some(topic.clone()),
+            read_queue_nums: request_header.read_queue_nums as u32,
+            write_queue_nums: request_header.write_queue_nums as u32,
+            perm: request_header.perm as u32,
+            topic_filter_type: TopicFilterType::from(request_header.topic_filter_type.as_str()),
+            topic_sys_flag: request_header.topic_sys_flag.unwrap_or_default() as u32,
+            order: request_header.order,
+            attributes,
+        };
+
+        if let Err(err) = self
+            .inner
+            .topic_queue_mapping_manager
+            .update_topic_queue_mapping(mapping_detail, request_header.force.unwrap_or(false), true)
+        {
+            return Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark(err.to_string()),
+            );
+        }
+        if let Err(err) = self
+            .inner
+            .topic_config_manager
+            .update_topic_config(&mut topic_config)
+        {
+            return Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark(err.to_string()),
+            );
+        }
+        self.inner
+            .topic_config_manager
+            .broker_runtime_inner()
+            .register_increment_broker_data(
+                vec![topic_config],
+                self.inner
+                    .topic_config_manager
+                    .data_version()
+                    .as_ref()
+                    .clone(),
+            )
+            .await;
 
         Some(response.set_code(ResponseCode::Success))
     }
@@ -223,16 +390,15 @@ impl TopicRequestHandler {
                 .inner
                 .broker_config
                 .validate_system_topic_when_update_topic
-                && TopicValidator::is_system_topic(topic)
             {
-                return Some(
-                    response
-                        .set_code(ResponseCode::SystemError)
-                        .set_remark(format!(
-                            "The topic[{}] is conflict with system topic.",
-                            topic
-                        )),
-                );
+                let result = TopicValidator::check_system_topic(topic);
+                if !result.valid() {
+                    return Some(
+                        response
+                            .set_code(ResponseCode::SystemError)
+                            .set_remark(result.remark().clone()),
+                    );
+                }
             }
             if topic_config.get_topic_message_type() == TopicMessageType::Mixed
                 && !self.inner.broker_config.enable_mixed_message_type
@@ -262,9 +428,22 @@ impl TopicRequestHandler {
             }
         }
 
-        self.inner
+        if let Err(err) = self
+            .inner
             .topic_config_manager
-            .update_topic_config_list(request_body.topic_config_list.as_mut_slice());
+            .update_topic_config_list(request_body.topic_config_list.as_mut_slice())
+        {
+            return Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark(err.to_string()),
+            );
+        }
+        let topics = request_body
+            .topic_config_list
+            .iter()
+            .filter_map(|topic_config| topic_config.topic_name.clone())
+            .collect::<Vec<_>>();
         if self.inner.broker_config.enable_single_topic_register {
             for topic_config in request_body.topic_config_list.iter() {
                 self.inner
@@ -287,6 +466,9 @@ impl TopicRequestHandler {
                 )
                 .await;
         }
+        for topic in topics.iter() {
+            self.notify_topic_route_changed(topic).await;
+        }
         Some(response.set_code(ResponseCode::Success))
     }
 
@@ -315,19 +497,12 @@ impl TopicRequestHandler {
                     .set_remark("he specified topic is blank."),
             );
         }
-        if self
-            .inner
-            .broker_config
-            .validate_system_topic_when_update_topic
-            && TopicValidator::is_system_topic(topic)
-        {
+        let result = TopicValidator::check_system_topic_deletion(topic);
+        if !result.valid() {
             return Some(
                 response
                     .set_code(ResponseCode::SystemError)
-                    .set_remark(format!(
-                        "The topic[{}] is conflict with system topic.",
-                        topic
-                    )),
+                    .set_remark(result.remark().clone()),
             );
         }
         let groups = self
@@ -416,9 +591,8 @@ impl TopicRequestHandler {
         _request: RemotingCommand,
     ) -> Option<RemotingCommand> {
         let mut response = RemotingCommand::create_response_command();
-        let topics = TopicValidator::get_system_topic_set();
         let topic_list = TopicList {
-            topic_list: topics.into_iter().map(|s| s.into()).collect(),
+            topic_list: self.inner.topic_config_manager.system_topic_list(),
             broker_addr: None,
         };
         response.set_body_mut_ref(topic_list.encode());
@@ -469,13 +643,14 @@ impl TopicRequestHandler {
                     .get_max_offset_in_queue(topic, i as i32),
                 0,
             );
-            let mut timestamp = 0;
-            if max > 0 {
-                timestamp = self
-                    .inner
+            // an empty queue has no last message, report -1 like the store does
+            let timestamp = if max > 0 {
+                self.inner
                     .default_message_store
-                    .get_message_store_timestamp(topic, i as i32, max - 1);
-            }
+                    .get_message_store_timestamp(topic, i as i32, max - 1)
+            } else {
+                -1
+            };
             topic_offset.set_min_offset(min);
             topic_offset.set_max_offset(max);
             topic_offset.set_last_update_timestamp(timestamp);
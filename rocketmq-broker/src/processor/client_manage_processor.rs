@@ -20,13 +20,10 @@ use std::sync::Arc;
 
 use cheetah_string::CheetahString;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
-use rocketmq_common::common::constant::PermName;
-use rocketmq_common::common::mix_all;
 use rocketmq_common::common::mix_all::IS_SUB_CHANGE;
 use rocketmq_common::common::mix_all::IS_SUPPORT_HEART_BEAT_V2;
-use rocketmq_common::common::sys_flag::topic_sys_flag;
-use rocketmq_common::utils::serde_json_utils::SerdeJsonUtils;
 use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::header::unregister_client_request_header::UnregisterClientRequestHeader;
 use rocketmq_remoting::protocol::heartbeat::consume_type::ConsumeType;
@@ -37,6 +34,8 @@ use rocketmq_store::log_file::MessageStore;
 use tracing::info;
 
 use crate::client::client_channel_info::ClientChannelInfo;
+use crate::client::client_version::check_client_version;
+use crate::client::client_version::decode_heartbeat;
 use crate::client::manager::consumer_manager::ConsumerManager;
 use crate::client::manager::producer_manager::ProducerManager;
 use crate::subscription::manager::subscription_group_manager::SubscriptionGroupManager;
@@ -122,11 +121,19 @@ where
                 .unregister_producer(group, &client_channel_info, &ctx);
         }
 
-        if let Some(ref _group) = request_header.consumer_group {
-            unimplemented!()
+        if let Some(ref group) = request_header.consumer_group {
+            let is_notify_consumer_ids_changed_enable = self
+                .subscription_group_manager
+                .find_subscription_group_config(group)
+                .map_or(true, |config| config.notify_consumer_ids_changed_enable());
+            self.consumer_manager.unregister_consumer(
+                group,
+                &client_channel_info,
+                is_notify_consumer_ids_changed_enable,
+            );
         }
 
-        Some(RemotingCommand::create_response_command())
+        Some(RemotingCommand::create_response_command().set_code(ResponseCode::Success))
     }
 
     fn heart_beat(
@@ -135,10 +142,23 @@ where
         ctx: ConnectionHandlerContext,
         request: RemotingCommand,
     ) -> Option<RemotingCommand> {
-        let heartbeat_data = SerdeJsonUtils::decode::<HeartbeatData>(
-            request.body().as_ref().map(|v| v.as_ref()).unwrap(),
-        )
-        .unwrap();
+        if let Some(response) = check_client_version(
+            &request,
+            self.broker_config.min_client_version_for_heartbeat,
+            "send heartbeat",
+        ) {
+            return Some(response);
+        }
+        let heartbeat_data =
+            match decode_heartbeat(&request, channel.remote_address().to_string().as_str()) {
+                Ok(heartbeat_data) => heartbeat_data,
+                Err(remark) => {
+                    return Some(RemotingCommand::create_response_command_with_code_remark(
+                        ResponseCode::SystemError,
+                        remark,
+                    ))
+                }
+            };
         let client_channel_info = ClientChannelInfo::new(
             channel.clone(),
             heartbeat_data.client_id.clone(),
@@ -179,22 +199,12 @@ where
             let subscription_group_config = subscription_group_config.unwrap();
             let is_notify_consumer_ids_changed_enable =
                 subscription_group_config.notify_consumer_ids_changed_enable();
-            let topic_sys_flag = if consumer_data.unit_mode {
-                topic_sys_flag::build_sys_flag(false, true)
-            } else {
-                0
-            };
-            let new_topic = CheetahString::from_string(mix_all::get_retry_topic(
+            self.topic_config_manager.create_retry_topic(
                 consumer_data.group_name.as_str(),
-            ));
-            self.topic_config_manager
-                .create_topic_in_send_message_back_method(
-                    &new_topic,
-                    subscription_group_config.retry_queue_nums(),
-                    PermName::PERM_WRITE | PermName::PERM_READ,
-                    has_order_topic_sub,
-                    topic_sys_flag,
-                );
+                subscription_group_config.retry_queue_nums(),
+                has_order_topic_sub,
+                consumer_data.unit_mode,
+            );
             let changed = self.consumer_manager.register_consumer(
                 consumer_data.group_name.as_ref(),
                 client_channel_info.clone(),
@@ -218,12 +228,28 @@ where
             self.producer_manager
                 .register_producer(&producer_data.group_name, &client_channel_info);
         }
+        self.update_producer_topics(&heartbeat_data, &client_channel_info);
         let mut response_command = RemotingCommand::create_response_command();
         response_command.add_ext_field(IS_SUPPORT_HEART_BEAT_V2.to_string(), true.to_string());
         response_command.add_ext_field(IS_SUB_CHANGE.to_string(), true.to_string());
         Some(response_command)
     }
 
+    /// Records the topics of every producer of the heartbeat against its connection.
+    fn update_producer_topics(
+        &self,
+        heartbeat_data: &HeartbeatData,
+        client_channel_info: &ClientChannelInfo,
+    ) {
+        let topics = heartbeat_data
+            .producer_data_set
+            .iter()
+            .flat_map(|producer_data| producer_data.topics.iter().cloned())
+            .collect();
+        self.producer_manager
+            .update_channel_topics(client_channel_info.channel(), topics);
+    }
+
     fn heart_beat_v2(
         &self,
         _channel: &Channel,
@@ -239,6 +265,7 @@ where
             self.producer_manager
                 .register_producer(&producer_data.group_name, &client_channel_info);
         }
+        self.update_producer_topics(&heartbeat_data, &client_channel_info);
         let mut response_command = RemotingCommand::create_response_command();
         response_command.add_ext_field(IS_SUPPORT_HEART_BEAT_V2.to_string(), true.to_string());
         response_command.add_ext_field(IS_SUB_CHANGE.to_string(), is_sub_change.to_string());
@@ -268,12 +268,10 @@ where
             let min_offset = self
                 .message_store
                 .get_min_offset_in_queue(request_header.topic.as_ref(), request_header.queue_id);
-            if let Some(value) = request_header.set_zero_if_not_found {
-                if !value {
-                    response = response
-                        .set_code(ResponseCode::QueryNotFound)
-                        .set_remark("Not found, do not set to zero, maybe this group boot first");
-                }
+            if request_header.set_zero_if_not_found == Some(false) {
+                response = response
+                    .set_code(ResponseCode::QueryNotFound)
+                    .set_remark("Not found, do not set to zero, maybe this group boot first");
             } else if min_offset <= 0
                 && self.message_store.check_in_mem_by_consume_offset(
                     request_header.topic.as_ref(),
@@ -22,7 +22,6 @@ use bytes::Bytes;
 use bytes::BytesMut;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
-use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_common::common::mix_all::MASTER_ID;
 use rocketmq_common::common::sys_flag::pull_sys_flag::PullSysFlag;
 use rocketmq_common::TimeUtils::get_current_millis;
@@ -37,7 +36,6 @@ use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::protocol::request_source::RequestSource;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_context::TopicQueueMappingContext;
 use rocketmq_remoting::protocol::subscription::subscription_group_config::SubscriptionGroupConfig;
-use rocketmq_remoting::protocol::topic::OffsetMovedEvent;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_rust::ArcMut;
 use rocketmq_store::base::get_message_result::GetMessageResult;
@@ -45,20 +43,24 @@ use rocketmq_store::base::message_status_enum::GetMessageStatus;
 use rocketmq_store::config::broker_role::BrokerRole;
 use rocketmq_store::config::message_store_config::MessageStoreConfig;
 use rocketmq_store::filter::MessageFilter;
-use rocketmq_store::message_store::default_message_store::DefaultMessageStore;
+use rocketmq_store::plugin::dyn_message_store::BoxedMessageStore;
 use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
 use rocketmq_store::stats::stats_type::StatsType;
 use tracing::debug;
 use tracing::info;
 use tracing::warn;
 
+use crate::broker::broker_member_group_cache::BrokerMemberGroupCache;
 use crate::client::manager::consumer_manager::ConsumerManager;
+use crate::error::BrokerError;
 use crate::long_polling::long_polling_service::pull_request_hold_service::PullRequestHoldService;
 use crate::long_polling::pull_request::PullRequest;
+use crate::metrics::broker_metrics_manager::BrokerMetricsManager;
 use crate::mqtrace::consume_message_context::ConsumeMessageContext;
 use crate::mqtrace::consume_message_hook::ConsumeMessageHook;
 use crate::offset::manager::broadcast_offset_manager::BroadcastOffsetManager;
 use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
+use crate::pagecache::many_message_transfer::ManyMessageTransfer;
 use crate::processor::pull_message_processor::is_broadcast;
 use crate::processor::pull_message_processor::rewrite_response_for_static_topic;
 use crate::processor::pull_message_result_handler::PullMessageResultHandler;
@@ -73,7 +75,9 @@ pub struct DefaultPullMessageResultHandler {
     broker_stats_manager: Arc<BrokerStatsManager>,
     broker_config: Arc<BrokerConfig>,
     consume_message_hook_list: Arc<Vec<Box<dyn ConsumeMessageHook>>>,
-    pull_request_hold_service: Option<ArcMut<PullRequestHoldService<DefaultMessageStore>>>,
+    pull_request_hold_service: Option<ArcMut<PullRequestHoldService<BoxedMessageStore>>>,
+    broker_metrics_manager: Arc<BrokerMetricsManager>,
+    broker_member_group: Arc<BrokerMemberGroupCache>,
 }
 
 impl DefaultPullMessageResultHandler {
@@ -86,6 +90,8 @@ impl DefaultPullMessageResultHandler {
         broker_stats_manager: Arc<BrokerStatsManager>,
         broker_config: Arc<BrokerConfig>,
         consume_message_hook_list: Arc<Vec<Box<dyn ConsumeMessageHook>>>,
+        broker_metrics_manager: Arc<BrokerMetricsManager>,
+        broker_member_group: Arc<BrokerMemberGroupCache>,
     ) -> Self {
         Self {
             topic_config_manager,
@@ -97,12 +103,14 @@ impl DefaultPullMessageResultHandler {
             broker_config,
             consume_message_hook_list,
             pull_request_hold_service: None,
+            broker_metrics_manager,
+            broker_member_group,
         }
     }
 
     pub fn set_pull_request_hold_service(
         &mut self,
-        pull_request_hold_service: Option<ArcMut<PullRequestHoldService<DefaultMessageStore>>>,
+        pull_request_hold_service: Option<ArcMut<PullRequestHoldService<BoxedMessageStore>>>,
     ) {
         self.pull_request_hold_service = pull_request_hold_service;
     }
@@ -130,6 +138,7 @@ impl PullMessageResultHandler for DefaultPullMessageResultHandler {
             .select_topic_config(request_header.topic.as_ref());
         Self::compose_response_header(
             &self.broker_config,
+            &self.broker_member_group,
             &request_header,
             &get_message_result,
             topic_config.as_ref().unwrap().topic_sys_flag as i32,
@@ -191,6 +200,12 @@ impl PullMessageResultHandler for DefaultPullMessageResultHandler {
                     request_header.topic.as_str(),
                     get_message_result.message_count(),
                 );
+                self.broker_metrics_manager.inc_messages_out(
+                    request_header.topic.as_str(),
+                    request_header.consumer_group.as_str(),
+                    get_message_result.message_count(),
+                    get_message_result.buffer_total_size(),
+                );
 
                 ctx.upgrade()?;
 
@@ -206,7 +221,8 @@ impl PullMessageResultHandler for DefaultPullMessageResultHandler {
                     }
                     Some(response)
                 } else {
-                    None
+                    response.set_body_parts_mut_ref(ManyMessageTransfer::new(get_message_result));
+                    Some(response)
                 }
             }
             ResponseCode::PullNotFound => {
@@ -249,27 +265,18 @@ impl PullMessageResultHandler for DefaultPullMessageResultHandler {
                 if self.message_store_config.broker_role != BrokerRole::Slave
                     || self.message_store_config.offset_check_in_slave
                 {
+                    // The offset moved event itself is published by the pull processor,
+                    // which owns the message store.
                     let response_header = response
                         .read_custom_header_mut::<PullMessageResponseHeader>()
                         .unwrap();
-                    let mut mq = MessageQueue::new();
-                    mq.set_topic(request_header.topic.clone());
-                    mq.set_broker_name(self.broker_config.broker_name.clone());
-                    mq.set_queue_id(request_header.queue_id.unwrap());
-
-                    let offset_moved_event = OffsetMovedEvent {
-                        consumer_group: request_header.consumer_group.to_string(),
-                        message_queue: mq,
-                        offset_request: request_header.queue_offset,
-                        offset_new: get_message_result.next_begin_offset(),
-                    };
                     warn!(
                         "PULL_OFFSET_MOVED:correction offset. topic={}, groupId={}, \
                          requestOffset={}, newOffset={}, suggestBrokerId={}",
                         request_header.topic,
                         request_header.consumer_group,
-                        offset_moved_event.offset_request,
-                        offset_moved_event.offset_new,
+                        request_header.queue_offset,
+                        get_message_result.next_begin_offset(),
                         response_header.suggest_which_broker_id.unwrap()
                     );
                 } else {
@@ -314,9 +321,9 @@ impl DefaultPullMessageResultHandler {
         let mut bytes_mut =
             BytesMut::with_capacity(get_message_result.buffer_total_size() as usize);
         for msg in get_message_result.message_mapped_list() {
-            let data = &msg.mapped_file.as_ref().unwrap().get_mapped_file()
-                [msg.start_offset as usize..(msg.start_offset + msg.size as u64) as usize];
-            bytes_mut.extend_from_slice(data);
+            if let Ok(data) = msg.get_buffer() {
+                bytes_mut.extend_from_slice(&data);
+            }
         }
         Some(bytes_mut.freeze())
     }
@@ -412,6 +419,7 @@ impl DefaultPullMessageResultHandler {
 impl DefaultPullMessageResultHandler {
     fn compose_response_header(
         broker_config: &Arc<BrokerConfig>,
+        broker_member_group: &BrokerMemberGroupCache,
         request_header: &PullMessageRequestHeader,
         get_message_result: &GetMessageResult,
         topic_sys_flag: i32,
@@ -427,34 +435,26 @@ impl DefaultPullMessageResultHandler {
         response_header.topic_sys_flag = Some(topic_sys_flag);
         response_header.group_sys_flag = Some(subscription_group_config.group_sys_flag());
 
-        match get_message_result.status().unwrap() {
-            GetMessageStatus::Found => {
-                response.set_code_ref(RemotingSysResponseCode::Success);
-            }
-            GetMessageStatus::MessageWasRemoving | GetMessageStatus::NoMatchedMessage => {
-                response.set_code_ref(ResponseCode::PullRetryImmediately);
-            }
-            GetMessageStatus::NoMatchedLogicQueue | GetMessageStatus::NoMessageInQueue => {
-                if request_header.queue_offset != 0 {
-                    response.set_code_ref(ResponseCode::PullOffsetMoved);
-                    info!(
-                        "The broker stores no queue data, fix the request offset {} to {}, Topic: \
-                         {} QueueId: {} Consumer Group: {}",
-                        request_header.queue_offset,
-                        get_message_result.next_begin_offset(),
-                        request_header.topic,
-                        request_header.queue_id.unwrap_or(0),
-                        request_header.consumer_group
-                    );
-                } else {
-                    response.set_code_ref(ResponseCode::PullNotFound);
-                }
-            }
-            GetMessageStatus::OffsetFoundNull | GetMessageStatus::OffsetOverflowOne => {
-                response.set_code_ref(ResponseCode::PullNotFound);
+        let status = get_message_result.status().unwrap();
+        match BrokerError::from_get_message_status(status, request_header.queue_offset) {
+            Some(error) => response.set_code_ref(error.response_code()),
+            None => response.set_code_ref(RemotingSysResponseCode::Success),
+        }
+        match status {
+            GetMessageStatus::NoMatchedLogicQueue | GetMessageStatus::NoMessageInQueue
+                if request_header.queue_offset != 0 =>
+            {
+                info!(
+                    "The broker stores no queue data, fix the request offset {} to {}, Topic: {} \
+                     QueueId: {} Consumer Group: {}",
+                    request_header.queue_offset,
+                    get_message_result.next_begin_offset(),
+                    request_header.topic,
+                    request_header.queue_id.unwrap_or(0),
+                    request_header.consumer_group
+                );
             }
             GetMessageStatus::OffsetOverflowBadly => {
-                response.set_code_ref(ResponseCode::PullOffsetMoved);
                 info!(
                     "The request offset: {} over flow badly, fix to {}, broker max offset: {}, \
                      consumer: {}",
@@ -465,14 +465,12 @@ impl DefaultPullMessageResultHandler {
                 );
             }
             GetMessageStatus::OffsetReset => {
-                response.set_code_ref(ResponseCode::PullOffsetMoved);
                 info!(
                     "The queue under pulling was previously reset to start from {}",
                     get_message_result.next_begin_offset()
                 );
             }
             GetMessageStatus::OffsetTooSmall => {
-                response.set_code_ref(ResponseCode::PullOffsetMoved);
                 info!(
                     "The request offset too small. group={}, topic={}, requestOffset={}, \
                      brokerMinOffset={}, clientIp={}",
@@ -483,12 +481,15 @@ impl DefaultPullMessageResultHandler {
                     client_address
                 );
             }
+            _ => {}
         }
 
         if broker_config.slave_read_enable && !broker_config.is_in_broker_container {
             if get_message_result.suggest_pulling_from_slave() {
-                response_header.suggest_which_broker_id =
-                    Some(subscription_group_config.which_broker_when_consume_slowly());
+                response_header.suggest_which_broker_id = Some(broker_id_when_consume_slowly(
+                    broker_member_group,
+                    subscription_group_config,
+                ));
             } else {
                 response_header.suggest_which_broker_id =
                     Some(subscription_group_config.broker_id());
@@ -497,8 +498,11 @@ impl DefaultPullMessageResultHandler {
             response_header.suggest_which_broker_id = Some(MASTER_ID);
         }
 
+        // While the master is offline the broker holding the min broker id serves the group, so
+        // there is nobody to redirect to.
         if broker_config.broker_identity.broker_id != MASTER_ID
             && !get_message_result.suggest_pulling_from_slave()
+            && broker_member_group.min_broker_id() == MASTER_ID
         {
             debug!(
                 "slave redirect pullRequest to master, topic: {}, queueId: {}, consumer group: \
@@ -600,3 +604,227 @@ impl DefaultPullMessageResultHandler {
         }
     }
 }
+
+/// A lagging consumer is sent to the configured slave while it is still part of the replica
+/// group, otherwise to the broker currently holding the min broker id.
+fn broker_id_when_consume_slowly(
+    broker_member_group: &BrokerMemberGroupCache,
+    subscription_group_config: &SubscriptionGroupConfig,
+) -> u64 {
+    let which_broker = subscription_group_config.which_broker_when_consume_slowly();
+    if broker_member_group.contains_broker(which_broker) {
+        which_broker
+    } else {
+        broker_member_group.min_broker_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rocketmq_common::common::broker::broker_config::BrokerIdentity;
+    use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+    use rocketmq_common::common::message::MessageTrait;
+    use rocketmq_store::base::message_status_enum::PutMessageStatus;
+    use rocketmq_store::log_file::MessageStore;
+    use rocketmq_store::message_store::default_message_store::DefaultMessageStore;
+    use rocketmq_store::test_util::start_store;
+
+    use super::*;
+
+    fn compose(
+        broker_id: u64,
+        broker_member_group: &BrokerMemberGroupCache,
+        suggest_pulling_from_slave: bool,
+    ) -> RemotingCommand {
+        let broker_config = Arc::new(BrokerConfig {
+            broker_identity: BrokerIdentity {
+                broker_id,
+                ..BrokerIdentity::default()
+            },
+            slave_read_enable: true,
+            ..BrokerConfig::default()
+        });
+        let mut get_message_result = GetMessageResult::new();
+        get_message_result.set_status(Some(GetMessageStatus::Found));
+        get_message_result.set_suggest_pulling_from_slave(suggest_pulling_from_slave);
+        let mut subscription_group_config = SubscriptionGroupConfig::default();
+        subscription_group_config.set_which_broker_when_consume_slowly(2);
+        let mut response = RemotingCommand::create_response_command();
+        DefaultPullMessageResultHandler::compose_response_header(
+            &broker_config,
+            broker_member_group,
+            &PullMessageRequestHeader::default(),
+            &get_message_result,
+            0,
+            &subscription_group_config,
+            &mut response,
+            "127.0.0.1:10000",
+        );
+        response
+    }
+
+    fn suggested_broker_id(response: &RemotingCommand) -> Option<u64> {
+        response
+            .read_custom_header_ref::<PullMessageResponseHeader>()
+            .unwrap()
+            .suggest_which_broker_id
+    }
+
+    #[test]
+    fn lagging_consumer_is_sent_to_a_broker_of_the_group() {
+        let broker_member_group = BrokerMemberGroupCache::new(&BrokerConfig::default());
+        let mut group = broker_member_group.group();
+        group.broker_addrs.insert(1, "127.0.0.1:10921".into());
+        broker_member_group.update_group(group.clone());
+
+        // Broker 2 is not part of the group, fall back to the min broker id.
+        let response = compose(MASTER_ID, &broker_member_group, true);
+        assert_eq!(suggested_broker_id(&response), Some(MASTER_ID));
+
+        group.broker_addrs.insert(2, "127.0.0.1:10931".into());
+        broker_member_group.update_group(group);
+        let response = compose(MASTER_ID, &broker_member_group, true);
+        assert_eq!(suggested_broker_id(&response), Some(2));
+    }
+
+    #[test]
+    fn slave_redirects_to_master_only_while_master_is_online() {
+        let broker_member_group = BrokerMemberGroupCache::new(&BrokerConfig {
+            broker_identity: BrokerIdentity {
+                broker_id: 1,
+                ..BrokerIdentity::default()
+            },
+            ..BrokerConfig::default()
+        });
+        broker_member_group.update_min_broker(MASTER_ID, None);
+        let response = compose(1, &broker_member_group, false);
+        assert_eq!(suggested_broker_id(&response), Some(MASTER_ID));
+
+        broker_member_group.update_min_broker(1, None);
+        let response = compose(1, &broker_member_group, false);
+        assert_eq!(
+            suggested_broker_id(&response),
+            Some(SubscriptionGroupConfig::default().broker_id())
+        );
+    }
+
+    /// A store holding `count` messages of `topic` of which nothing is expected in page cache.
+    async fn cold_store(
+        dir: &tempfile::TempDir,
+        topic: &CheetahString,
+        count: usize,
+    ) -> ArcMut<DefaultMessageStore> {
+        let mut store = start_store(
+            dir,
+            MessageStoreConfig {
+                access_message_in_memory_max_ratio: 0,
+                ..MessageStoreConfig::default()
+            },
+        )
+        .await;
+        for _ in 0..count {
+            let mut msg = MessageExtBrokerInner::default();
+            msg.set_topic(topic.clone());
+            msg.set_body(Bytes::from_static(b"accumulated"));
+            let result = store.put_message(msg).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        }
+        for _ in 0..500 {
+            if store.get_max_offset_in_queue(topic, 0) == count as i64 {
+                return store;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("commit log not dispatched");
+    }
+
+    async fn pull(
+        store: &ArcMut<DefaultMessageStore>,
+        (broker_config, broker_member_group): &(Arc<BrokerConfig>, BrokerMemberGroupCache),
+        subscription_group_config: &SubscriptionGroupConfig,
+        offset: i64,
+    ) -> RemotingCommand {
+        let get_message_result = store
+            .get_message(
+                &CheetahString::from_static_str("SlowGroup"),
+                &CheetahString::from_static_str("AccumulatedTopic"),
+                0,
+                offset,
+                1,
+                1024 * 1024,
+                None,
+            )
+            .await
+            .unwrap();
+        let mut response = RemotingCommand::create_response_command();
+        DefaultPullMessageResultHandler::compose_response_header(
+            broker_config,
+            broker_member_group,
+            &PullMessageRequestHeader::default(),
+            &get_message_result,
+            0,
+            subscription_group_config,
+            &mut response,
+            "127.0.0.1:10000",
+        );
+        response
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn suggested_broker_flips_back_to_master_once_the_consumer_caught_up() {
+        let topic = CheetahString::from_static_str("AccumulatedTopic");
+        let master_dir = tempfile::tempdir().unwrap();
+        let slave_dir = tempfile::tempdir().unwrap();
+        // the slave holds a replica of everything written to the master
+        let mut master_store = cold_store(&master_dir, &topic, 3).await;
+        let mut slave_store = cold_store(&slave_dir, &topic, 3).await;
+
+        let broker = |broker_id: u64| {
+            let broker_config = Arc::new(BrokerConfig {
+                broker_identity: BrokerIdentity {
+                    broker_id,
+                    ..BrokerIdentity::default()
+                },
+                slave_read_enable: true,
+                ..BrokerConfig::default()
+            });
+            let broker_member_group = BrokerMemberGroupCache::new(&broker_config);
+            let mut group = broker_member_group.group();
+            group
+                .broker_addrs
+                .insert(MASTER_ID, "127.0.0.1:10911".into());
+            group.broker_addrs.insert(1, "127.0.0.1:10921".into());
+            broker_member_group.update_group(group);
+            (broker_config, broker_member_group)
+        };
+        let master = broker(MASTER_ID);
+        let slave = broker(1);
+        let mut subscription_group_config = SubscriptionGroupConfig::default();
+        subscription_group_config.set_which_broker_when_consume_slowly(1);
+
+        // far behind, the master sends the consumer to the slave and the slave keeps it
+        let response = pull(&master_store, &master, &subscription_group_config, 0).await;
+        assert_eq!(suggested_broker_id(&response), Some(1));
+        let response = pull(&slave_store, &slave, &subscription_group_config, 1).await;
+        assert_eq!(suggested_broker_id(&response), Some(1));
+        assert_eq!(ResponseCode::from(response.code()), ResponseCode::Success);
+
+        // caught up, the slave hands the consumer back to the master which keeps it
+        let response = pull(&slave_store, &slave, &subscription_group_config, 3).await;
+        assert_eq!(suggested_broker_id(&response), Some(MASTER_ID));
+        assert_eq!(
+            ResponseCode::from(response.code()),
+            ResponseCode::PullRetryImmediately
+        );
+        let response = pull(&master_store, &master, &subscription_group_config, 3).await;
+        assert_eq!(
+            suggested_broker_id(&response),
+            Some(subscription_group_config.broker_id())
+        );
+
+        master_store.shutdown();
+        slave_store.shutdown();
+    }
+}
@@ -35,12 +35,12 @@ use rocketmq_remoting::protocol::header::end_transaction_request_header::EndTran
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_rust::ArcMut;
-use rocketmq_store::base::message_status_enum::PutMessageStatus;
 use rocketmq_store::config::broker_role::BrokerRole;
 use rocketmq_store::config::message_store_config::MessageStoreConfig;
 use rocketmq_store::log_file::MessageStore;
 use tracing::warn;
 
+use crate::error::BrokerError;
 use crate::transaction::operation_result::OperationResult;
 use crate::transaction::queue::transactional_message_util::TransactionalMessageUtil;
 use crate::transaction::transactional_message_service::TransactionalMessageService;
@@ -265,73 +265,12 @@ where
     async fn send_final_message(&mut self, msg_inner: MessageExtBrokerInner) -> RemotingCommand {
         let put_message_result = self.message_store.put_message(msg_inner).await;
         let mut response = RemotingCommand::create_response_command();
-        match put_message_result.put_message_status() {
-            PutMessageStatus::PutOk
-            | PutMessageStatus::FlushDiskTimeout
-            | PutMessageStatus::FlushSlaveTimeout
-            | PutMessageStatus::SlaveNotAvailable => {}
-            PutMessageStatus::ServiceNotAvailable => {
-                response.set_code_mut(ResponseCode::ServiceNotAvailable);
-                response.set_remark_mut("Service not available now. ");
-            }
-            PutMessageStatus::CreateMappedFileFailed => {
-                response.set_code_mut(ResponseCode::SystemError);
-                response.set_remark_mut("Create mapped file failed.");
-            }
-            PutMessageStatus::MessageIllegal | PutMessageStatus::PropertiesSizeExceeded => {
-                response.set_code_mut(ResponseCode::MessageIllegal);
-                response.set_remark_mut(format!(
-                    "The message is illegal, maybe msg body or properties length not matched. msg \
-                     body length limit {}B, msg properties length limit 32KB.",
-                    self.message_store_config.max_message_size
-                ));
-            }
-            PutMessageStatus::OsPageCacheBusy => {
-                response.set_code_mut(ResponseCode::SystemError);
-                response.set_remark_mut("OS page cache busy, please try another machine");
-            }
-            PutMessageStatus::UnknownError => {
-                response.set_code_mut(ResponseCode::SystemError);
-                response.set_remark_mut("Unknown error");
-            }
-            PutMessageStatus::InSyncReplicasNotEnough => {
-                response.set_code_mut(ResponseCode::SystemError);
-                response.set_remark_mut("In sync replicas not enough");
-            }
-            PutMessageStatus::PutToRemoteBrokerFail => {
-                response.set_code_mut(ResponseCode::SystemError);
-                response.set_remark_mut("Put to remote broker failed");
-            }
-            PutMessageStatus::LmqConsumeQueueNumExceeded => {
-                response.set_code_mut(ResponseCode::SystemError);
-                response.set_remark_mut("UNKNOWN_ERROR DEFAULT");
-            }
-            PutMessageStatus::WheelTimerFlowControl => {
-                response.set_code_mut(ResponseCode::SystemError);
-                response.set_remark_mut(format!(
-                    "timer message is under flow control, max num limit is {} or the current \
-                     value is greater than {} and less than {}, trigger random flow control",
-                    self.message_store_config.timer_congest_num_each_slot * 2,
-                    self.message_store_config.timer_congest_num_each_slot,
-                    self.message_store_config.timer_congest_num_each_slot * 2,
-                ));
-            }
-            PutMessageStatus::WheelTimerMsgIllegal => {
-                response.set_code_mut(ResponseCode::MessageIllegal);
-                response.set_remark_mut(format!(
-                    "timer message illegal, the delay time should not be bigger than the max \
-                     delay {}ms; or if set del msg, the delay time should be bigger than the \
-                     current time",
-                    self.message_store_config.timer_max_delay_sec * 1000
-                ));
-            }
-            PutMessageStatus::WheelTimerNotEnable => {
-                response.set_code_mut(ResponseCode::SystemError);
-                response.set_remark_mut(format!(
-                    "accurate timer message is not enabled, timerWheelEnable is {}",
-                    self.message_store_config.timer_wheel_enable
-                ));
-            }
+        if let Some(error) = BrokerError::from_put_message_status(
+            put_message_result.put_message_status(),
+            &self.message_store_config,
+            self.message_store.get_running_flags().is_disk_full(),
+        ) {
+            error.apply_to(&mut response);
         }
         response
     }
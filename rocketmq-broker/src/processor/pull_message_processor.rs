@@ -14,15 +14,23 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Instant;
 
+use bytes::Bytes;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::config::TopicConfig;
 use rocketmq_common::common::constant::PermName;
 use rocketmq_common::common::filter::expression_type::ExpressionType;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::common::sys_flag::pull_sys_flag::PullSysFlag;
+use rocketmq_common::common::topic::TopicValidator;
 use rocketmq_common::common::FAQUrl;
+use rocketmq_common::MessageDecoder;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::RemotingSysResponseCode;
@@ -34,11 +42,15 @@ use rocketmq_remoting::protocol::header::pull_message_request_header::PullMessag
 use rocketmq_remoting::protocol::header::pull_message_response_header::PullMessageResponseHeader;
 use rocketmq_remoting::protocol::heartbeat::consume_type::ConsumeType;
 use rocketmq_remoting::protocol::heartbeat::message_model::MessageModel;
+use rocketmq_remoting::protocol::heartbeat::subscription_data::SubscriptionData;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::protocol::request_source::RequestSource;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_context::TopicQueueMappingContext;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_detail::TopicQueueMappingDetail;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_utils::TopicQueueMappingUtils;
+use rocketmq_remoting::protocol::topic::OffsetMovedEvent;
+use rocketmq_remoting::protocol::RemotingSerializable;
+use rocketmq_remoting::request_tracing;
 use rocketmq_remoting::rpc::rpc_client::RpcClient;
 use rocketmq_remoting::rpc::rpc_client_utils::RpcClientUtils;
 use rocketmq_remoting::rpc::rpc_request::RpcRequest;
@@ -47,20 +59,25 @@ use rocketmq_runtime::RocketMQRuntime;
 use rocketmq_rust::ArcMut;
 use rocketmq_store::base::get_message_result::GetMessageResult;
 use rocketmq_store::base::message_status_enum::GetMessageStatus;
+use rocketmq_store::base::message_status_enum::PutMessageStatus;
 use rocketmq_store::filter::MessageFilter;
 use rocketmq_store::log_file::MessageStore;
 use rocketmq_store::log_file::MAX_PULL_MSG_SIZE;
 use tokio::sync::Mutex;
 use tracing::error;
+use tracing::info;
 use tracing::warn;
 
 use crate::client::consumer_group_info::ConsumerGroupInfo;
 use crate::client::manager::consumer_manager::ConsumerManager;
 use crate::coldctr::cold_data_cg_ctr_service::ColdDataCgCtrService;
+use crate::coldctr::cold_data_pull_request_hold_service::ColdDataPullRequestHoldService;
 use crate::coldctr::cold_data_pull_request_hold_service::NO_SUSPEND_KEY;
+use crate::error::BrokerError;
 use crate::filter::expression_for_retry_message_filter::ExpressionForRetryMessageFilter;
 use crate::filter::expression_message_filter::ExpressionMessageFilter;
 use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
+use crate::long_polling::pull_request::PullRequest;
 use crate::offset::manager::broadcast_offset_manager::BroadcastOffsetManager;
 use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
 use crate::out_api::broker_outer_api::BrokerOuterAPI;
@@ -81,7 +98,9 @@ pub struct PullMessageProcessor<MS> {
     broadcast_offset_manager: Arc<BroadcastOffsetManager>,
     message_store: ArcMut<MS>,
     cold_data_cg_ctr_service: Arc<ColdDataCgCtrService>,
+    cold_data_pull_request_hold_service: Option<ArcMut<ColdDataPullRequestHoldService<MS>>>,
     broker_outer_api: Arc<BrokerOuterAPI>,
+    store_host: SocketAddr,
     // write message to consume client runtime
     write_message_runtime: Arc<RocketMQRuntime>,
     // write message to consume client lock
@@ -100,9 +119,13 @@ impl<MS> PullMessageProcessor<MS> {
         consumer_offset_manager: Arc<ConsumerOffsetManager>,
         broadcast_offset_manager: Arc<BroadcastOffsetManager>,
         message_store: ArcMut<MS>,
+        cold_data_cg_ctr_service: Arc<ColdDataCgCtrService>,
         broker_outer_api: Arc<BrokerOuterAPI>,
     ) -> Self {
         let cpus = num_cpus::get();
+        let store_host = broker_config
+            .get_store_host()
+            .expect("parse store host failed");
         Self {
             pull_message_result_handler,
             broker_config,
@@ -114,8 +137,10 @@ impl<MS> PullMessageProcessor<MS> {
             consumer_offset_manager,
             broadcast_offset_manager,
             message_store,
-            cold_data_cg_ctr_service: Arc::new(Default::default()),
+            cold_data_cg_ctr_service,
+            cold_data_pull_request_hold_service: None,
             broker_outer_api,
+            store_host,
             write_message_runtime: Arc::new(RocketMQRuntime::new_multi(
                 cpus,
                 "write_consumer_message_runtime",
@@ -124,6 +149,13 @@ impl<MS> PullMessageProcessor<MS> {
         }
     }
 
+    pub fn set_cold_data_pull_request_hold_service(
+        &mut self,
+        cold_data_pull_request_hold_service: Option<ArcMut<ColdDataPullRequestHoldService<MS>>>,
+    ) {
+        self.cold_data_pull_request_hold_service = cold_data_pull_request_hold_service;
+    }
+
     pub async fn rewrite_request_for_static_topic(
         &self,
         request_header: &mut PullMessageRequestHeader,
@@ -221,6 +253,25 @@ impl<MS> PullMessageProcessor<MS> {
     }
 }
 
+/// Rejects a pull carrying a newer subscription version than the one the broker got from the
+/// consumer's last heartbeat, the consumer retries once a heartbeat has caught the broker up.
+fn check_subscription_latest(
+    subscription_data: &SubscriptionData,
+    request_header: &PullMessageRequestHeader,
+) -> Option<RemotingCommand> {
+    if subscription_data.sub_version >= request_header.sub_version {
+        return None;
+    }
+    warn!(
+        "The broker's subscription is not latest, group: {} {}",
+        request_header.consumer_group, subscription_data.sub_string
+    );
+    Some(RemotingCommand::create_response_command_with_code_remark(
+        ResponseCode::SubscriptionNotLatest,
+        "the consumer's subscription not latest",
+    ))
+}
+
 pub fn rewrite_response_for_static_topic(
     request_header: &PullMessageRequestHeader,
     response_header: &mut PullMessageResponseHeader,
@@ -376,61 +427,56 @@ where
         let mut request_header = request
             .decode_command_custom_header_fast::<PullMessageRequestHeader>()
             .unwrap();
+        request_tracing::record_request_fields(
+            Some(request_header.topic.as_str()),
+            Some(request_header.consumer_group.as_str()),
+            request_header.queue_id,
+        );
         //info!("receive pull message request: {:?}", request_header);
         let mut response_header = PullMessageResponseHeader::default();
 
         if !PermName::is_readable(self.broker_config.broker_permission) {
             response_header.forbidden_type = Some(ForbiddenType::BROKER_FORBIDDEN);
-            return Some(
-                response
-                    .set_code(ResponseCode::NoPermission)
-                    .set_command_custom_header(response_header)
-                    .set_remark(format!(
-                        "the broker[{}] pulling message is forbidden",
-                        self.broker_config.broker_ip1
-                    )),
-            );
+            BrokerError::NoPermission(format!(
+                "the broker[{}] pulling message is forbidden",
+                self.broker_config.broker_ip1
+            ))
+            .apply_to(&mut response);
+            return Some(response.set_command_custom_header(response_header));
         }
         if RequestCode::LitePullMessage == request_code
             && !self.broker_config.lite_pull_message_enable
         {
             response_header.forbidden_type = Some(ForbiddenType::BROKER_FORBIDDEN);
-            return Some(
-                response
-                    .set_code(ResponseCode::NoPermission)
-                    .set_command_custom_header(response_header)
-                    .set_remark(format!(
-                        "the broker[{}] pulling message is forbidden",
-                        self.broker_config.broker_ip1
-                    )),
-            );
+            BrokerError::NoPermission(format!(
+                "the broker[{}] pulling message is forbidden",
+                self.broker_config.broker_ip1
+            ))
+            .apply_to(&mut response);
+            return Some(response.set_command_custom_header(response_header));
         }
         let subscription_group_config = self
             .subscription_group_manager
             .find_subscription_group_config(request_header.consumer_group.as_ref());
 
         if subscription_group_config.is_none() {
-            return Some(
-                response
-                    .set_code(ResponseCode::SubscriptionGroupNotExist)
-                    .set_remark(format!(
-                        "subscription group [{}] does not exist, {}",
-                        request_header.consumer_group,
-                        FAQUrl::suggest_todo(FAQUrl::SUBSCRIPTION_GROUP_NOT_EXIST)
-                    )),
-            );
+            BrokerError::SubscriptionGroupNotExist(format!(
+                "subscription group [{}] does not exist, {}",
+                request_header.consumer_group,
+                FAQUrl::suggest_todo(FAQUrl::SUBSCRIPTION_GROUP_NOT_EXIST)
+            ))
+            .apply_to(&mut response);
+            return Some(response);
         }
 
         if !subscription_group_config.as_ref().unwrap().consume_enable() {
             response_header.forbidden_type = Some(ForbiddenType::GROUP_FORBIDDEN);
-            return Some(
-                response
-                    .set_code(ResponseCode::NoPermission)
-                    .set_remark(format!(
-                        "subscription group no permission, {}",
-                        request_header.consumer_group,
-                    )),
-            );
+            BrokerError::NoPermission(format!(
+                "subscription group no permission, {}",
+                request_header.consumer_group,
+            ))
+            .apply_to(&mut response);
+            return Some(response.set_command_custom_header(response_header));
         }
         let topic_config = self
             .topic_config_manager
@@ -441,26 +487,22 @@ where
                 request_header.topic,
                 channel.remote_address()
             );
-            return Some(
-                response
-                    .set_code(ResponseCode::TopicNotExist)
-                    .set_remark(format!(
-                        "topic[{}] not exist, apply first please! {}",
-                        request_header.topic,
-                        FAQUrl::suggest_todo(FAQUrl::APPLY_TOPIC_URL)
-                    )),
-            );
+            BrokerError::TopicNotExist(format!(
+                "topic[{}] not exist, apply first please! {}",
+                request_header.topic,
+                FAQUrl::suggest_todo(FAQUrl::APPLY_TOPIC_URL)
+            ))
+            .apply_to(&mut response);
+            return Some(response);
         }
         if !PermName::is_readable(topic_config.as_ref().unwrap().perm) {
             response_header.forbidden_type = Some(ForbiddenType::TOPIC_FORBIDDEN);
-            return Some(
-                response
-                    .set_code(ResponseCode::NoPermission)
-                    .set_remark(format!(
-                        "the topic[{}] pulling message is forbidden",
-                        request_header.topic,
-                    )),
-            );
+            BrokerError::NoPermission(format!(
+                "the topic[{}] pulling message is forbidden",
+                request_header.topic,
+            ))
+            .apply_to(&mut response);
+            return Some(response.set_command_custom_header(response_header));
         }
         let mut topic_queue_mapping_context = self
             .topic_queue_mapping_manager
@@ -620,17 +662,10 @@ where
                 );
             }
 
-            if subscription_data.as_ref().unwrap().sub_version < request_header.sub_version {
-                warn!(
-                    "The broker's subscription is not latest, group: {} {}",
-                    request_header.consumer_group,
-                    subscription_data.as_ref().unwrap().sub_string
-                );
-                return Some(
-                    response
-                        .set_code(ResponseCode::SubscriptionNotExist)
-                        .set_remark("the consumer's subscription not latest"),
-                );
+            if let Some(response) =
+                check_subscription_latest(subscription_data.as_ref().unwrap(), &request_header)
+            {
+                return Some(response);
             }
 
             let consumer_filter_data = if !ExpressionType::is_tag_type(Some(
@@ -698,13 +733,25 @@ where
             ))
         };
 
-        //ColdDataFlow not implement
-
-        cfg_if::cfg_if! {
-            if #[cfg(feature = "local_file_store")] {
-                if self.cold_data_cg_ctr_service.is_cg_need_cold_data_flow_ctr(request_header.consumer_group.as_str()) {
-                    unimplemented!("ColdDataFlow not implement")
-                }
+        let (cold_data_read, cold_data_flow_ctr) = check_cold_data_flow_ctr(
+            &self.broker_config,
+            self.message_store.as_ref(),
+            &self.cold_data_cg_ctr_service,
+            &request_header,
+        );
+        if cold_data_flow_ctr && broker_allow_flow_ctr_suspend {
+            if let Some(hold_service) = self.cold_data_pull_request_hold_service.as_ref() {
+                hold_service.suspend_cold_data_read_request(PullRequest::new(
+                    request,
+                    channel,
+                    ctx,
+                    self.broker_config.cold_pull_suspend_millis,
+                    get_current_millis(),
+                    request_header.queue_offset,
+                    subscription_data,
+                    Arc::new(message_filter),
+                ));
+                return None;
             }
         }
 
@@ -738,6 +785,16 @@ where
                 get_message_result.set_status(Some(GetMessageStatus::OffsetReset));
                 get_message_result.set_next_begin_offset(broadcast_init_offset);
                 Some(get_message_result)
+            } else if cold_data_flow_ctr {
+                // answered PULL_NOT_FOUND without touching the cold data
+                let mut get_message_result = GetMessageResult::new();
+                get_message_result.set_status(Some(GetMessageStatus::OffsetFoundNull));
+                get_message_result.set_next_begin_offset(request_header.queue_offset);
+                get_message_result
+                    .set_min_offset(self.message_store.get_min_offset_in_queue(topic, queue_id));
+                get_message_result
+                    .set_max_offset(self.message_store.get_max_offset_in_queue(topic, queue_id));
+                Some(get_message_result)
             } else {
                 let result = self
                     .message_store
@@ -758,11 +815,24 @@ where
                             .set_remark("store getMessage return None"),
                     );
                 }
+                if cold_data_read {
+                    if let Some(result) = result.as_ref() {
+                        self.cold_data_cg_ctr_service
+                            .cold_acc(group.as_str(), result.buffer_total_size().max(0) as u64);
+                    }
+                }
                 result
             }
         };
         if let Some(get_message_result) = get_message_result {
-            return self.pull_message_result_handler.handle(
+            let message_queue = MessageQueue::from_parts(
+                topic.clone(),
+                self.broker_config.broker_name.clone(),
+                queue_id,
+            );
+            let consumer_group = group.to_string();
+            let offset_request = request_header.queue_offset;
+            let response = self.pull_message_result_handler.handle(
                 get_message_result,
                 request,
                 request_header,
@@ -776,6 +846,30 @@ where
                 topic_queue_mapping_context,
                 begin_time_mills,
             );
+            // The result handler only keeps PULL_OFFSET_MOVED when the correction is
+            // authoritative, i.e. on the master or on a slave with offsetCheckInSlave.
+            if let Some(response) = response.as_ref() {
+                if ResponseCode::from(response.code()) == ResponseCode::PullOffsetMoved {
+                    let offset_new = response
+                        .read_custom_header_ref::<PullMessageResponseHeader>()
+                        .and_then(|header| header.next_begin_offset)
+                        .unwrap_or_default();
+                    let event = OffsetMovedEvent {
+                        consumer_group,
+                        message_queue,
+                        offset_request,
+                        offset_new,
+                    };
+                    generate_offset_moved_event(
+                        self.message_store.as_mut(),
+                        self.topic_config_manager.as_ref(),
+                        self.store_host,
+                        &event,
+                    )
+                    .await;
+                }
+            }
+            return response;
         }
         None
     }
@@ -859,7 +953,86 @@ where
             }
         });
     }
+
+    /// Answers a held request with `response` instead of processing it again.
+    pub fn write_response_when_wakeup(
+        &self,
+        ctx: ConnectionHandlerContext,
+        request: &RemotingCommand,
+        response: RemotingCommand,
+    ) {
+        let lock = Arc::clone(&self.write_message_lock);
+        let command = response.set_opaque(request.opaque()).mark_response_type();
+        self.write_message_runtime.get_handle().spawn(async move {
+            if let Some(mut ctx) = ctx.upgrade() {
+                let guard = lock.lock().await;
+                ctx.write(command).await;
+                drop(guard);
+            }
+        });
+    }
+}
+/// Writes `event` to the `OFFSET_MOVED_EVENT` system topic so that operators can audit where
+/// a consumer's pull offset was corrected, and thus where messages may have been skipped.
+pub(crate) async fn generate_offset_moved_event<MS: MessageStore>(
+    message_store: &mut MS,
+    topic_config_manager: &TopicConfigManager,
+    store_host: SocketAddr,
+    event: &OffsetMovedEvent,
+) {
+    let topic = CheetahString::from_static_str(TopicValidator::RMQ_SYS_OFFSET_MOVED_EVENT);
+    if !topic_config_manager.contains_topic(&topic) {
+        info!("create system topic {} on demand", topic);
+        topic_config_manager.put_topic_config(TopicConfig::with_queues(topic.as_str(), 1, 1));
+    }
+    let consumer_group = CheetahString::from_string(event.consumer_group.clone());
+    let mut msg_inner = MessageExtBrokerInner::default();
+    msg_inner.set_topic(topic);
+    msg_inner.set_tags(consumer_group.clone());
+    msg_inner.set_keys(consumer_group);
+    msg_inner.set_body(Bytes::from(event.encode()));
+    msg_inner.tags_code =
+        MessageExtBrokerInner::tags_string_to_tags_code(event.consumer_group.as_str());
+    msg_inner.properties_string =
+        MessageDecoder::message_properties_to_string(msg_inner.get_properties());
+    msg_inner.message_ext_inner.queue_id = 0;
+    msg_inner.message_ext_inner.sys_flag = 0;
+    msg_inner.message_ext_inner.born_timestamp = get_current_millis() as i64;
+    msg_inner.message_ext_inner.born_host = store_host;
+    msg_inner.message_ext_inner.store_host = store_host;
+    msg_inner.message_ext_inner.reconsume_times = 0;
+    let result = message_store.put_message(msg_inner).await;
+    if result.put_message_status() != PutMessageStatus::PutOk {
+        warn!(
+            "generateOffsetMovedEvent failed, {}, status={:?}",
+            event,
+            result.put_message_status()
+        );
+    }
 }
+
+/// Whether the pull reads the cold area of the commit log, and whether its group has to stay
+/// off the cold data for now. Reads far behind the commit log max offset likely miss the page
+/// cache, a group over its cold read budget waits instead of evicting the pages hot readers
+/// depend on.
+pub(crate) fn check_cold_data_flow_ctr<MS: MessageStore>(
+    broker_config: &BrokerConfig,
+    message_store: &MS,
+    cold_data_cg_ctr_service: &ColdDataCgCtrService,
+    request_header: &PullMessageRequestHeader,
+) -> (bool, bool) {
+    let cold_data_read = broker_config.cold_ctr_strategy_enable
+        && message_store.check_in_cold_area_by_consume_offset(
+            &request_header.topic,
+            request_header.queue_id.unwrap_or_default(),
+            request_header.queue_offset,
+        );
+    let cold_data_flow_ctr = cold_data_read
+        && cold_data_cg_ctr_service
+            .is_cg_need_cold_data_flow_ctr(request_header.consumer_group.as_str());
+    (cold_data_read, cold_data_flow_ctr)
+}
+
 pub(crate) fn is_broadcast(
     proxy_pull_broadcast: bool,
     consumer_group_info: Option<&ConsumerGroupInfo>,
@@ -873,12 +1046,75 @@ pub(crate) fn is_broadcast(
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+    use std::collections::HashSet;
+
     use rocketmq_common::common::consumer::consume_from_where::ConsumeFromWhere;
+    use rocketmq_common::common::message::message_decoder;
+    use rocketmq_remoting::protocol::command_custom_header::CommandCustomHeader;
+    use rocketmq_remoting::protocol::command_custom_header::FromMap;
+    use rocketmq_remoting::protocol::filter::filter_api::FilterAPI;
     use rocketmq_remoting::protocol::heartbeat::consume_type::ConsumeType;
     use rocketmq_remoting::protocol::heartbeat::message_model::MessageModel;
+    use rocketmq_remoting::protocol::static_topic::logic_queue_mapping_item::LogicQueueMappingItem;
+    use rocketmq_remoting::protocol::static_topic::topic_queue_info::TopicQueueMappingInfo;
+    use rocketmq_store::config::message_store_config::MessageStoreConfig;
+    use rocketmq_store::test_util::load_store;
+    use rocketmq_store::test_util::wait_dispatched;
 
     use super::*;
     use crate::client::consumer_group_info::ConsumerGroupInfo;
+    use crate::test_util::topic_config_manager;
+
+    #[test]
+    fn pull_with_a_newer_subscription_is_rejected_until_the_heartbeat_catches_up() {
+        let consumer_group_info = ConsumerGroupInfo::new(
+            "sub_version_group".to_string(),
+            ConsumeType::ConsumePassively,
+            MessageModel::Clustering,
+            ConsumeFromWhere::ConsumeFromLastOffset,
+        );
+        let topic = CheetahString::from_static_str("SubVersionTopic");
+        let mut registered = FilterAPI::build_subscription_data(&topic, &"tagA".into()).unwrap();
+        registered.sub_version = 1_000;
+        consumer_group_info.update_subscription(&HashSet::from([registered.clone()]));
+
+        // The consumer resubscribed but its heartbeat has not reached the broker yet.
+        let mut resubscribed =
+            FilterAPI::build_subscription_data(&topic, &"tagA||tagB".into()).unwrap();
+        resubscribed.sub_version = 2_000;
+        let sent = PullMessageRequestHeader {
+            consumer_group: "sub_version_group".into(),
+            topic: topic.clone(),
+            subscription: Some(resubscribed.sub_string.clone()),
+            sub_version: resubscribed.sub_version,
+            expression_type: Some(resubscribed.expression_type.clone()),
+            ..Default::default()
+        };
+        let request_header =
+            <PullMessageRequestHeader as FromMap>::from(&sent.to_map().unwrap()).unwrap();
+        assert_eq!(request_header.sub_version, 2_000);
+        assert_eq!(
+            request_header.expression_type.as_deref(),
+            Some(ExpressionType::TAG)
+        );
+
+        let broker_subscription = consumer_group_info.find_subscription_data(&topic).unwrap();
+        let response = check_subscription_latest(&broker_subscription, &request_header).unwrap();
+        assert_eq!(
+            ResponseCode::from(response.code()),
+            ResponseCode::SubscriptionNotLatest
+        );
+
+        consumer_group_info.update_subscription(&HashSet::from([resubscribed]));
+        let broker_subscription = consumer_group_info.find_subscription_data(&topic).unwrap();
+        assert!(check_subscription_latest(&broker_subscription, &request_header).is_none());
+        assert!(broker_subscription.tags_set.contains("tagB"));
+
+        let mut stale_request = request_header;
+        stale_request.sub_version = registered.sub_version;
+        assert!(check_subscription_latest(&broker_subscription, &stale_request).is_none());
+    }
 
     #[test]
     fn returns_true_for_proxy_pull_broadcast() {
@@ -927,4 +1163,195 @@ mod tests {
             "Should return false when no consumer group info is provided"
         );
     }
+
+    #[test]
+    fn pull_response_offsets_are_rewritten_to_static_offsets() {
+        let items = vec![
+            LogicQueueMappingItem {
+                gen: 0,
+                bname: Some(CheetahString::from_static_str("broker-a")),
+                end_offset: 100,
+                ..LogicQueueMappingItem::default()
+            },
+            LogicQueueMappingItem {
+                gen: 1,
+                bname: Some(CheetahString::from_static_str("broker-b")),
+                logic_offset: 101,
+                ..LogicQueueMappingItem::default()
+            },
+        ];
+        let mapping_detail = TopicQueueMappingDetail {
+            topic_queue_mapping_info: TopicQueueMappingInfo {
+                topic: Some(CheetahString::from_static_str("static_topic")),
+                bname: Some(CheetahString::from_static_str("broker-b")),
+                total_queues: 1,
+                ..TopicQueueMappingInfo::default()
+            },
+            hosted_queues: Some(HashMap::from([(0, items.clone())])),
+        };
+        let mut mapping_context = TopicQueueMappingContext {
+            topic: CheetahString::from_static_str("static_topic"),
+            global_id: Some(0),
+            mapping_detail: Some(mapping_detail),
+            leader_item: Some(items[1].clone()),
+            current_item: Some(items[1].clone()),
+            mapping_item_list: items,
+        };
+        // the consumer asked for static offset 120, i.e. physical offset 19 on broker-b
+        let request_header = PullMessageRequestHeader {
+            queue_offset: 19,
+            ..PullMessageRequestHeader::default()
+        };
+        let mut response_header = PullMessageResponseHeader {
+            next_begin_offset: Some(29),
+            min_offset: Some(0),
+            max_offset: Some(30),
+            ..PullMessageResponseHeader::default()
+        };
+
+        let rewritten = rewrite_response_for_static_topic(
+            &request_header,
+            &mut response_header,
+            &mut mapping_context,
+            ResponseCode::Success,
+        );
+        assert!(rewritten.is_none());
+        assert_eq!(response_header.next_begin_offset, Some(130));
+        assert_eq!(response_header.min_offset, Some(101));
+        assert_eq!(response_header.max_offset, Some(131));
+        assert_eq!(response_header.offset_delta, Some(101));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cold_data_flow_control_only_throttles_cold_offsets() {
+        let dir = tempfile::tempdir().unwrap();
+        // nothing is expected in page cache, every stored message is cold
+        let mut store = load_store(
+            &dir,
+            MessageStoreConfig {
+                access_message_in_memory_max_ratio: 0,
+                ..MessageStoreConfig::default()
+            },
+        )
+        .await;
+        store.start().unwrap();
+        let topic = CheetahString::from_static_str("ColdDataTopic");
+        for _ in 0..2 {
+            let mut msg = MessageExtBrokerInner::default();
+            msg.set_topic(topic.clone());
+            msg.set_body(Bytes::from_static(b"cold data"));
+            let result = store.put_message(msg).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        }
+        wait_dispatched(&store).await;
+
+        let broker_config = BrokerConfig {
+            cold_ctr_strategy_enable: true,
+            cold_max_pull_threshold_per_group: 100,
+            ..BrokerConfig::default()
+        };
+        let service = ColdDataCgCtrService::new(Arc::new(broker_config.clone()));
+        service.cold_acc("CatchingUpGroup", 1000);
+        let pull = |group: &'static str, queue_offset: i64| {
+            let request_header = PullMessageRequestHeader {
+                consumer_group: CheetahString::from_static_str(group),
+                topic: topic.clone(),
+                queue_id: Some(0),
+                queue_offset,
+                ..PullMessageRequestHeader::default()
+            };
+            check_cold_data_flow_ctr(&broker_config, store.as_ref(), &service, &request_header)
+        };
+        assert_eq!(pull("CatchingUpGroup", 0), (true, true));
+        // tailing the queue reads nothing cold
+        assert_eq!(pull("CatchingUpGroup", 2), (false, false));
+        assert_eq!(pull("OtherGroup", 0), (true, false));
+
+        let disabled = BrokerConfig::default();
+        let request_header = PullMessageRequestHeader {
+            consumer_group: CheetahString::from_static_str("CatchingUpGroup"),
+            topic: topic.clone(),
+            queue_id: Some(0),
+            ..PullMessageRequestHeader::default()
+        };
+        assert_eq!(
+            check_cold_data_flow_ctr(&disabled, store.as_ref(), &service, &request_header),
+            (false, false)
+        );
+        store.shutdown();
+    }
+
+    #[test]
+    fn offset_overflow_badly_publishes_offset_moved_event() {
+        // The broker outer API owns a runtime, keep it out of the async context it can't be
+        // dropped from.
+        let broker_config = Arc::new(BrokerConfig::default());
+        let topic_config_manager = topic_config_manager(broker_config.clone());
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let mut store = load_store(&dir, MessageStoreConfig::default()).await;
+            store.start().unwrap();
+            let topic = CheetahString::from_static_str("OffsetMovedTopic");
+            let group = CheetahString::from_static_str("OffsetMovedGroup");
+            let mut msg = MessageExtBrokerInner::default();
+            msg.set_topic(topic.clone());
+            msg.set_body(Bytes::from_static(b"offset moved"));
+            let result = store.put_message(msg).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+            wait_dispatched(&store).await;
+
+            let get_message_result = store
+                .get_message(&group, &topic, 0, 100, 32, MAX_PULL_MSG_SIZE, None)
+                .await
+                .unwrap();
+            assert_eq!(
+                get_message_result.status(),
+                Some(GetMessageStatus::OffsetOverflowBadly)
+            );
+
+            let event_topic =
+                CheetahString::from_static_str(TopicValidator::RMQ_SYS_OFFSET_MOVED_EVENT);
+            topic_config_manager.remove_topic_config(event_topic.as_str());
+            let event = OffsetMovedEvent {
+                consumer_group: group.to_string(),
+                message_queue: MessageQueue::from_parts(
+                    topic.clone(),
+                    broker_config.broker_name.clone(),
+                    0,
+                ),
+                offset_request: 100,
+                offset_new: get_message_result.next_begin_offset(),
+            };
+            generate_offset_moved_event(
+                store.as_mut(),
+                &topic_config_manager,
+                "127.0.0.1:10911".parse().unwrap(),
+                &event,
+            )
+            .await;
+            assert!(topic_config_manager.contains_topic(&event_topic));
+            wait_dispatched(&store).await;
+
+            let event_result = store
+                .get_message(&group, &event_topic, 0, 0, 32, MAX_PULL_MSG_SIZE, None)
+                .await
+                .unwrap();
+            assert_eq!(event_result.status(), Some(GetMessageStatus::Found));
+            let mut bytes = event_result.message_mapped_list()[0].get_bytes().unwrap();
+            let msg_ext =
+                message_decoder::decode(&mut bytes, true, false, false, false, false).unwrap();
+            assert_eq!(msg_ext.get_tags(), Some(group.clone()));
+            let body: serde_json::Value =
+                serde_json::from_slice(msg_ext.get_body().unwrap()).unwrap();
+            assert_eq!(body["consumerGroup"], "OffsetMovedGroup");
+            assert_eq!(body["messageQueue"]["topic"], "OffsetMovedTopic");
+            assert_eq!(body["offsetRequest"], 100);
+            assert_eq!(body["offsetNew"], 1);
+            store.shutdown();
+        });
+    }
 }
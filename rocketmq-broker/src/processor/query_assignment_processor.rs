@@ -14,19 +14,569 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
 
+use cheetah_string::CheetahString;
+use rocketmq_client_rust::consumer::allocate_message_queue_strategy::AllocateMessageQueueStrategy;
+use rocketmq_client_rust::consumer::rebalance_strategy::allocate_message_queue_averagely::AllocateMessageQueueAveragely;
+use rocketmq_client_rust::consumer::rebalance_strategy::allocate_message_queue_averagely_by_circle::AllocateMessageQueueAveragelyByCircle;
+use rocketmq_client_rust::consumer::rebalance_strategy::allocate_message_queue_consistent_hash::AllocateMessageQueueConsistentHash;
+use rocketmq_client_rust::consumer::rebalance_strategy::allocate_message_queue_sticky::AllocateMessageQueueSticky;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::config_manager::ConfigManager;
+use rocketmq_common::common::message::message_enum::MessageRequestMode;
+use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::common::message::message_queue_assignment::MessageQueueAssignment;
+use rocketmq_common::common::mix_all;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::body::query_assignment_request_body::QueryAssignmentRequestBody;
+use rocketmq_remoting::protocol::body::query_assignment_response_body::QueryAssignmentResponseBody;
+use rocketmq_remoting::protocol::body::set_message_request_mode_request_body::SetMessageRequestModeRequestBody;
+use rocketmq_remoting::protocol::heartbeat::message_model::MessageModel;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::RemotingDeserializable;
+use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
+use tracing::warn;
 
-#[derive(Default)]
-pub struct QueryAssignmentProcessor {}
+use crate::client::manager::consumer_manager::ConsumerManager;
+use crate::load_balance::message_request_mode_manager::MessageRequestModeManager;
+use crate::topic::manager::topic_route_info_manager::TopicRouteInfoManager;
+
+/// Computes the queue assignment of consumers that rebalance on the broker, and records the
+/// request mode (PULL or POP) each topic@group is served with.
+pub struct QueryAssignmentProcessor {
+    broker_config: Arc<BrokerConfig>,
+    consumer_manager: Arc<ConsumerManager>,
+    topic_route_info_manager: Arc<TopicRouteInfoManager>,
+    message_request_mode_manager: MessageRequestModeManager,
+    name_to_load_strategy: HashMap<&'static str, Arc<dyn AllocateMessageQueueStrategy>>,
+}
 
 impl QueryAssignmentProcessor {
-    fn process_request(
-        &self,
+    pub(crate) fn new(
+        broker_config: Arc<BrokerConfig>,
+        message_store_config: Arc<MessageStoreConfig>,
+        consumer_manager: Arc<ConsumerManager>,
+        topic_route_info_manager: Arc<TopicRouteInfoManager>,
+    ) -> Self {
+        let strategies: [Arc<dyn AllocateMessageQueueStrategy>; 3] = [
+            Arc::new(AllocateMessageQueueAveragely),
+            Arc::new(AllocateMessageQueueAveragelyByCircle),
+            Arc::new(AllocateMessageQueueConsistentHash::default()),
+        ];
+        // the broker allocates every consumer of a group, so it can remember who owned what
+        let sticky = strategies
+            .clone()
+            .map(|strategy| Arc::new(AllocateMessageQueueSticky::new(strategy)) as Arc<_>);
+        let message_request_mode_manager = MessageRequestModeManager::new(message_store_config);
+        message_request_mode_manager.load();
+        Self {
+            broker_config,
+            consumer_manager,
+            topic_route_info_manager,
+            message_request_mode_manager,
+            name_to_load_strategy: strategies
+                .into_iter()
+                .chain(sticky)
+                .map(|strategy| (strategy.get_name(), strategy))
+                .collect(),
+        }
+    }
+
+    pub async fn process_request(
+        &mut self,
+        _channel: Channel,
         _ctx: ConnectionHandlerContext,
-        _request: RemotingCommand,
+        request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        match request_code {
+            RequestCode::QueryAssignment => Some(self.query_assignment(request).await),
+            RequestCode::SetMessageRequestMode => Some(self.set_message_request_mode(request)),
+            _ => None,
+        }
+    }
+
+    async fn query_assignment(&self, request: RemotingCommand) -> RemotingCommand {
+        let Some(request_body) = request
+            .get_body()
+            .and_then(|body| QueryAssignmentRequestBody::decode(body).ok())
+        else {
+            return RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::SystemError,
+                "decode QueryAssignmentRequestBody failed",
+            );
+        };
+        let topic = &request_body.topic;
+        let consumer_group = &request_body.consumer_group;
+        let mode_body = self
+            .message_request_mode_manager
+            .get_message_request_mode(topic, consumer_group)
+            .unwrap_or_else(|| {
+                // retry topics are always pulled
+                let mode = if topic.starts_with(mix_all::RETRY_GROUP_TOPIC_PREFIX) {
+                    MessageRequestMode::Pull
+                } else {
+                    self.broker_config.default_message_request_mode
+                };
+                SetMessageRequestModeRequestBody {
+                    topic: topic.clone(),
+                    consumer_group: consumer_group.clone(),
+                    mode,
+                    pop_share_queue_num: if mode == MessageRequestMode::Pop {
+                        self.broker_config.default_pop_share_queue_num
+                    } else {
+                        0
+                    },
+                }
+            });
+
+        let message_queue_assignments = self
+            .do_load_balance(&request_body, &mode_body)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|message_queue| MessageQueueAssignment {
+                message_queue: Some(message_queue),
+                mode: mode_body.mode,
+                attachments: None,
+            })
+            .collect();
+        RemotingCommand::create_response_command().set_body(
+            QueryAssignmentResponseBody {
+                message_queue_assignments,
+            }
+            .encode(),
+        )
+    }
+
+    async fn do_load_balance(
+        &self,
+        request_body: &QueryAssignmentRequestBody,
+        mode_body: &SetMessageRequestModeRequestBody,
+    ) -> Option<HashSet<MessageQueue>> {
+        let topic = &request_body.topic;
+        let consumer_group = &request_body.consumer_group;
+        let mq_set = self
+            .topic_route_info_manager
+            .get_topic_subscribe_info(topic)
+            .await;
+        match request_body.message_model {
+            MessageModel::Broadcasting => {
+                if mq_set.is_none() {
+                    warn!(
+                        "QueryLoad: no assignment for group[{}], the topic[{}] does not exist.",
+                        consumer_group, topic
+                    );
+                }
+                mq_set
+            }
+            MessageModel::Clustering => {
+                let Some(mq_set) = mq_set else {
+                    if !topic.starts_with(mix_all::RETRY_GROUP_TOPIC_PREFIX) {
+                        warn!(
+                            "QueryLoad: no assignment for group[{}], the topic[{}] does not exist.",
+                            consumer_group, topic
+                        );
+                    }
+                    return None;
+                };
+                if !self.broker_config.server_load_balancer_enable {
+                    return Some(mq_set);
+                }
+                let Some(consumer_group_info) = self
+                    .consumer_manager
+                    .get_consumer_group_info(consumer_group)
+                else {
+                    warn!(
+                        "QueryLoad: no assignment for group[{}] topic[{}], get consumer id list \
+                         failed",
+                        consumer_group, topic
+                    );
+                    return None;
+                };
+                let mut mq_all = mq_set.into_iter().collect::<Vec<_>>();
+                mq_all.sort();
+                let mut cid_all = consumer_group_info.get_all_client_ids();
+                cid_all.sort();
+
+                let Some(strategy) = self
+                    .name_to_load_strategy
+                    .get(request_body.strategy_name.as_str())
+                else {
+                    warn!(
+                        "QueryLoad: unsupported strategy [{}]",
+                        request_body.strategy_name
+                    );
+                    return None;
+                };
+                let allocate_result = if mode_body.mode == MessageRequestMode::Pop {
+                    allocate_for_pop(
+                        strategy.as_ref(),
+                        consumer_group,
+                        &request_body.client_id,
+                        &mq_all,
+                        &cid_all,
+                        mode_body.pop_share_queue_num,
+                    )
+                } else {
+                    strategy.allocate(consumer_group, &request_body.client_id, &mq_all, &cid_all)
+                };
+                match allocate_result {
+                    Ok(allocated) => Some(allocated.into_iter().collect()),
+                    Err(e) => {
+                        warn!(
+                            "QueryLoad: allocate message queue of group[{}] topic[{}] by strategy \
+                             [{}] failed: {}",
+                            consumer_group, topic, request_body.strategy_name, e
+                        );
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    fn set_message_request_mode(&self, request: RemotingCommand) -> RemotingCommand {
+        let Some(request_body) = request
+            .get_body()
+            .and_then(|body| SetMessageRequestModeRequestBody::decode(body).ok())
+        else {
+            return RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::SystemError,
+                "decode SetMessageRequestModeRequestBody failed",
+            );
+        };
+        if request_body
+            .topic
+            .starts_with(mix_all::RETRY_GROUP_TOPIC_PREFIX)
+        {
+            return RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::NoPermission,
+                "retry topic is not allowed to set mode",
+            );
+        }
+        self.message_request_mode_manager.set_message_request_mode(
+            request_body.topic.clone(),
+            request_body.consumer_group.clone(),
+            request_body,
+        );
+        self.message_request_mode_manager.persist();
+        RemotingCommand::create_response_command()
+    }
+}
+
+/// POP consumers may share queues: with `pop_share_queue_num` N every consumer also pops the
+/// queues of the N consumers following it, and a share covering the whole group hands every
+/// queue to every consumer.
+fn allocate_for_pop(
+    strategy: &dyn AllocateMessageQueueStrategy,
+    consumer_group: &CheetahString,
+    client_id: &CheetahString,
+    mq_all: &[MessageQueue],
+    cid_all: &[CheetahString],
+    pop_share_queue_num: i32,
+) -> rocketmq_client_rust::Result<Vec<MessageQueue>> {
+    if pop_share_queue_num <= 0 || pop_share_queue_num as usize >= cid_all.len().saturating_sub(1) {
+        // a queue id of -1 lets the consumer pop from every queue of the broker
+        return Ok(mq_all
+            .iter()
+            .map(|mq| MessageQueue::from_parts(mq.get_topic(), mq.get_broker_name(), -1))
+            .collect());
+    }
+    if mq_all.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(index) = cid_all.iter().position(|cid| cid == client_id) else {
+        return strategy.allocate(consumer_group, client_id, mq_all, cid_all);
+    };
+    if cid_all.len() > mq_all.len() {
+        // more consumers than queues: make sure every consumer gets one
+        return Ok(vec![mq_all[index % mq_all.len()].clone()]);
+    }
+    let mut allocated = strategy.allocate(consumer_group, client_id, mq_all, cid_all)?;
+    for offset in 1..=pop_share_queue_num as usize {
+        let follower = &cid_all[(index + offset) % cid_all.len()];
+        allocated.extend(strategy.allocate(consumer_group, follower, mq_all, cid_all)?);
+    }
+    Ok(allocated)
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::constant::PermName;
+    use rocketmq_common::common::consumer::consume_from_where::ConsumeFromWhere;
+    use rocketmq_remoting::protocol::heartbeat::consume_type::ConsumeType;
+    use rocketmq_remoting::protocol::route::route_data_view::QueueData;
+    use rocketmq_remoting::protocol::route::topic_route_data::TopicRouteData;
+    use rocketmq_remoting::protocol::LanguageCode;
+    use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
+
+    use super::*;
+    use crate::client::client_channel_info::ClientChannelInfo;
+    use crate::client::default_consumer_ids_change_listener::DefaultConsumerIdsChangeListener;
+    use crate::out_api::broker_outer_api::BrokerOuterAPI;
+    use crate::test_util::client_channel;
+
+    const TOPIC: &str = "AssignmentTopic";
+    const GROUP: &str = "AssignmentGroup";
+
+    fn processor(
+        store_path_root_dir: &str,
+        consumer_manager: Arc<ConsumerManager>,
+    ) -> QueryAssignmentProcessor {
+        let topic_route_info_manager = Arc::new(TopicRouteInfoManager::new(Arc::new(
+            BrokerOuterAPI::new(Arc::new(TokioClientConfig::default())),
+        )));
+        let route = TopicRouteData {
+            queue_datas: ["broker-a", "broker-b"]
+                .into_iter()
+                .map(|broker_name| {
+                    QueueData::new(
+                        broker_name.into(),
+                        4,
+                        4,
+                        PermName::PERM_READ | PermName::PERM_WRITE,
+                        0,
+                    )
+                })
+                .collect(),
+            ..TopicRouteData::default()
+        };
+        topic_route_info_manager.update_topic_subscribe_info(&TOPIC.into(), Some(&route));
+        QueryAssignmentProcessor::new(
+            Arc::new(BrokerConfig::default()),
+            Arc::new(MessageStoreConfig {
+                store_path_root_dir: store_path_root_dir.into(),
+                ..MessageStoreConfig::default()
+            }),
+            consumer_manager,
+            topic_route_info_manager,
+        )
+    }
+
+    async fn register_consumer(consumer_manager: &ConsumerManager, client_id: &str) {
+        consumer_manager.register_consumer(
+            &GROUP.into(),
+            ClientChannelInfo::new(
+                client_channel().await,
+                client_id.into(),
+                LanguageCode::RUST,
+                0,
+            ),
+            ConsumeType::ConsumePassively,
+            MessageModel::Clustering,
+            ConsumeFromWhere::ConsumeFromLastOffset,
+            HashSet::new(),
+            false,
+        );
+    }
+
+    fn query_request(client_id: &str) -> RemotingCommand {
+        query_request_with_strategy(client_id, "AVG")
+    }
+
+    fn query_request_with_strategy(client_id: &str, strategy_name: &str) -> RemotingCommand {
+        RemotingCommand::create_remoting_command(RequestCode::QueryAssignment).set_body(
+            QueryAssignmentRequestBody {
+                topic: TOPIC.into(),
+                consumer_group: GROUP.into(),
+                client_id: client_id.into(),
+                strategy_name: strategy_name.into(),
+                message_model: MessageModel::Clustering,
+            }
+            .encode(),
+        )
+    }
+
+    fn assignments(response: &RemotingCommand) -> HashSet<MessageQueueAssignment> {
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        QueryAssignmentResponseBody::decode(response.get_body().unwrap())
+            .unwrap()
+            .message_queue_assignments
+    }
+
+    fn set_mode_request(
+        topic: &str,
+        mode: MessageRequestMode,
+        pop_share_queue_num: i32,
     ) -> RemotingCommand {
-        todo!()
+        RemotingCommand::create_remoting_command(RequestCode::SetMessageRequestMode).set_body(
+            SetMessageRequestModeRequestBody {
+                topic: topic.into(),
+                consumer_group: GROUP.into(),
+                mode,
+                pop_share_queue_num,
+            }
+            .encode(),
+        )
+    }
+
+    #[test]
+    fn concurrent_queries_split_the_queues_between_clients() {
+        let dir = tempfile::tempdir().unwrap();
+        let consumer_manager = Arc::new(ConsumerManager::new(
+            Box::new(DefaultConsumerIdsChangeListener::default()),
+            BrokerConfig::default().channel_expired_timeout,
+        ));
+        let processor = processor(dir.path().to_str().unwrap(), consumer_manager.clone());
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            register_consumer(&consumer_manager, "client-a").await;
+            register_consumer(&consumer_manager, "client-b").await;
+
+            let (response_a, response_b) = tokio::join!(
+                processor.query_assignment(query_request("client-a")),
+                processor.query_assignment(query_request("client-b"))
+            );
+            let (assigned_a, assigned_b) = (assignments(&response_a), assignments(&response_b));
+            assert_eq!(assigned_a.len(), 4);
+            assert_eq!(assigned_b.len(), 4);
+            assert!(assigned_a
+                .iter()
+                .chain(assigned_b.iter())
+                .all(|assignment| assignment.mode == MessageRequestMode::Pull));
+            let queues_a = assigned_a
+                .into_iter()
+                .map(|assignment| assignment.message_queue.unwrap())
+                .collect::<HashSet<_>>();
+            let queues_b = assigned_b
+                .into_iter()
+                .map(|assignment| assignment.message_queue.unwrap())
+                .collect::<HashSet<_>>();
+            assert!(queues_a.is_disjoint(&queues_b));
+            assert_eq!(queues_a.union(&queues_b).count(), 8);
+
+            // the same view yields the same answer on a second query
+            let again = assignments(&processor.query_assignment(query_request("client-a")).await);
+            assert_eq!(
+                again
+                    .into_iter()
+                    .map(|assignment| assignment.message_queue.unwrap())
+                    .collect::<HashSet<_>>(),
+                queues_a
+            );
+        });
+    }
+
+    #[test]
+    fn sticky_strategy_keeps_queues_of_surviving_clients() {
+        let dir = tempfile::tempdir().unwrap();
+        let consumer_manager = Arc::new(ConsumerManager::new(
+            Box::new(DefaultConsumerIdsChangeListener::default()),
+            BrokerConfig::default().channel_expired_timeout,
+        ));
+        let processor = processor(dir.path().to_str().unwrap(), consumer_manager.clone());
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let query = |client_id: &'static str| {
+                let processor = &processor;
+                async move {
+                    assignments(
+                        &processor
+                            .query_assignment(query_request_with_strategy(
+                                client_id,
+                                "STICKY_CONSISTENT_HASH",
+                            ))
+                            .await,
+                    )
+                    .into_iter()
+                    .map(|assignment| assignment.message_queue.unwrap())
+                    .collect::<HashSet<_>>()
+                }
+            };
+            register_consumer(&consumer_manager, "client-a").await;
+            register_consumer(&consumer_manager, "client-b").await;
+            let before_a = query("client-a").await;
+            let before_b = query("client-b").await;
+            assert_eq!(before_a.len() + before_b.len(), 8);
+
+            register_consumer(&consumer_manager, "client-c").await;
+            let after_a = query("client-a").await;
+            let after_b = query("client-b").await;
+            let after_c = query("client-c").await;
+            assert!(after_a.is_subset(&before_a));
+            assert!(after_b.is_subset(&before_b));
+            assert_eq!(after_c.len(), 2);
+            assert_eq!(after_a.len() + after_b.len(), 6);
+        });
+    }
+
+    #[test]
+    fn pop_mode_is_persisted_and_shares_every_queue() {
+        let dir = tempfile::tempdir().unwrap();
+        let store_path_root_dir = dir.path().to_str().unwrap();
+        let consumer_manager = Arc::new(ConsumerManager::new(
+            Box::new(DefaultConsumerIdsChangeListener::default()),
+            BrokerConfig::default().channel_expired_timeout,
+        ));
+        let processor = processor(store_path_root_dir, consumer_manager.clone());
+        let response =
+            processor.set_message_request_mode(set_mode_request(TOPIC, MessageRequestMode::Pop, 0));
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        let retry_topic = format!("{}{}", mix_all::RETRY_GROUP_TOPIC_PREFIX, GROUP);
+        let response = processor.set_message_request_mode(set_mode_request(
+            &retry_topic,
+            MessageRequestMode::Pop,
+            0,
+        ));
+        assert_eq!(response.code(), ResponseCode::NoPermission as i32);
+
+        // a fresh processor picks the mode up from disk
+        let reloaded = self::processor(store_path_root_dir, consumer_manager.clone());
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            register_consumer(&consumer_manager, "client-a").await;
+            register_consumer(&consumer_manager, "client-b").await;
+
+            let assigned = assignments(&reloaded.query_assignment(query_request("client-a")).await);
+            assert_eq!(assigned.len(), 2);
+            for assignment in assigned {
+                assert_eq!(assignment.mode, MessageRequestMode::Pop);
+                assert_eq!(assignment.message_queue.unwrap().get_queue_id(), -1);
+            }
+        });
+    }
+
+    #[test]
+    fn pop_share_hands_out_own_and_following_allocations() {
+        let group = CheetahString::from_static_str(GROUP);
+        let mq_all = (0..8)
+            .map(|queue_id| MessageQueue::from_parts(TOPIC, "broker-a", queue_id))
+            .collect::<Vec<_>>();
+        let cid_all = ["c0", "c1", "c2", "c3"]
+            .map(CheetahString::from_static_str)
+            .to_vec();
+        let strategy = AllocateMessageQueueAveragely;
+
+        let allocated =
+            allocate_for_pop(&strategy, &group, &cid_all[3], &mq_all, &cid_all, 1).unwrap();
+        let queue_ids = allocated
+            .iter()
+            .map(MessageQueue::get_queue_id)
+            .collect::<Vec<_>>();
+        assert_eq!(queue_ids, vec![6, 7, 0, 1]);
+
+        let crowded = (0..6)
+            .map(|i| CheetahString::from(format!("c{i}")))
+            .collect::<Vec<_>>();
+        let allocated =
+            allocate_for_pop(&strategy, &group, &crowded[5], &mq_all[..3], &crowded, 1).unwrap();
+        assert_eq!(allocated, vec![mq_all[2].clone()]);
     }
 }
@@ -74,9 +74,9 @@ where
         producer_manager: Option<Arc<ProducerManager>>,
         transactional_message_service: ArcMut<TS>,
     ) -> Self {
-        let store_host = format!("{}:{}", broker_config.broker_ip1, broker_config.listen_port)
-            .parse::<SocketAddr>()
-            .unwrap();
+        let store_host = broker_config
+            .get_store_host()
+            .expect("parse store host failed");
         Self {
             inner: Inner {
                 broker_config,
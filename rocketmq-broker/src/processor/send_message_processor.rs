@@ -23,14 +23,19 @@ use std::time::Instant;
 use cheetah_string::CheetahString;
 use rand::Rng;
 use rocketmq_common::common::attribute::cleanup_policy::CleanupPolicy;
+use rocketmq_common::common::attribute::topic_message_type::TopicMessageType;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::config::TopicConfig;
 use rocketmq_common::common::constant::PermName;
 use rocketmq_common::common::key_builder::KeyBuilder;
+use rocketmq_common::common::message::message_accessor::MessageAccessor;
 use rocketmq_common::common::message::message_batch::MessageExtBatch;
 use rocketmq_common::common::message::message_client_id_setter::MessageClientIDSetter;
 use rocketmq_common::common::message::message_enum::MessageType;
 use rocketmq_common::common::message::message_ext::MessageExt;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::message_validator::MessageCheckError;
+use rocketmq_common::common::message::message_validator::MessageValidator;
 use rocketmq_common::common::message::MessageConst;
 use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::common::mix_all;
@@ -54,6 +59,7 @@ use rocketmq_remoting::code::response_code::RemotingSysResponseCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::code::response_code::ResponseCode::SystemError;
 use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::header::consumer_send_msg_back_request_header::ConsumerSendMsgBackRequestHeader;
 use rocketmq_remoting::protocol::header::message_operation_header::send_message_request_header::parse_request_header;
 use rocketmq_remoting::protocol::header::message_operation_header::send_message_request_header::SendMessageRequestHeader;
 use rocketmq_remoting::protocol::header::message_operation_header::send_message_response_header::SendMessageResponseHeader;
@@ -61,28 +67,38 @@ use rocketmq_remoting::protocol::header::message_operation_header::TopicRequestH
 use rocketmq_remoting::protocol::namespace_util::NamespaceUtil;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_context::TopicQueueMappingContext;
+use rocketmq_remoting::protocol::subscription::group_retry_policy::GroupRetryPolicy;
+use rocketmq_remoting::protocol::subscription::group_retry_policy_type::GroupRetryPolicyType;
+use rocketmq_remoting::request_tracing;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_rust::ArcMut;
 use rocketmq_store::base::message_result::PutMessageResult;
+use rocketmq_store::base::message_status_enum::PutMessageStatus;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
 use rocketmq_store::log_file::MessageStore;
 use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
 use rocketmq_store::stats::stats_type::StatsType;
 use tracing::info;
 use tracing::warn;
 
+use crate::client::client_version::check_client_version;
 use crate::client::manager::producer_manager::ProducerManager;
 use crate::client::net::broker_to_client::Broker2Client;
 use crate::client::rebalance::rebalance_lock_manager::RebalanceLockManager;
+use crate::error::BrokerError;
+use crate::metrics::broker_metrics_manager::BrokerMetricsManager;
 use crate::mqtrace::send_message_context::SendMessageContext;
 use crate::mqtrace::send_message_hook::SendMessageHook;
 use crate::subscription::manager::subscription_group_manager::SubscriptionGroupManager;
 use crate::topic::manager::topic_config_manager::TopicConfigManager;
+use crate::topic::manager::topic_config_manager::DLQ_NUMS_PER_GROUP;
 use crate::topic::manager::topic_queue_mapping_manager::TopicQueueMappingManager;
 use crate::transaction::transactional_message_service::TransactionalMessageService;
 
 pub struct SendMessageProcessor<MS, TS> {
     inner: ArcMut<Inner<MS, TS>>,
     store_host: SocketAddr,
+    broker_metrics_manager: Arc<BrokerMetricsManager>,
 }
 
 // RequestProcessor implementation
@@ -111,12 +127,22 @@ where
         request_code: RequestCode,
         request: RemotingCommand,
     ) -> Option<RemotingCommand> {
+        if let Some(response) = check_client_version(
+            &request,
+            self.inner.broker_config.min_client_version_for_send,
+            "send messages",
+        ) {
+            return Some(response);
+        }
         match request_code {
-            RequestCode::ConsumerSendMsgBack => {
-                self.inner.consumer_send_msg_back(&channel, &ctx, &request)
-            }
+            RequestCode::ConsumerSendMsgBack => self.consumer_send_msg_back(&request).await,
             _ => {
                 let mut request_header = parse_request_header(&request, request_code)?;
+                request_tracing::record_request_fields(
+                    Some(request_header.topic.as_str()),
+                    Some(request_header.producer_group.as_str()),
+                    request_header.queue_id,
+                );
                 let mapping_context = self
                     .inner
                     .topic_queue_mapping_manager
@@ -184,10 +210,11 @@ where
         transactional_message_service: ArcMut<TS>,
         rebalance_lock_manager: Arc<RebalanceLockManager>,
         broker_stats_manager: Arc<BrokerStatsManager>,
+        broker_metrics_manager: Arc<BrokerMetricsManager>,
     ) -> Self {
-        let store_host = format!("{}:{}", broker_config.broker_ip1, broker_config.listen_port)
-            .parse::<SocketAddr>()
-            .unwrap();
+        let store_host = broker_config
+            .get_store_host()
+            .expect("parse store host failed");
         Self {
             inner: ArcMut::new(Inner {
                 broker_config,
@@ -203,7 +230,151 @@ where
                 broker_to_client: Default::default(),
             }),
             store_host,
+            broker_metrics_manager,
+        }
+    }
+
+    /// Handles a message a consumer failed to consume: the original message is looked up in the
+    /// commit log and re-put to the group's retry topic with the next delay level, or to its DLQ
+    /// once it has been reconsumed `maxReconsumeTimes` times.
+    async fn consumer_send_msg_back(
+        &mut self,
+        request: &RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let response = RemotingCommand::create_response_command();
+        let request_header =
+            request.decode_command_custom_header::<ConsumerSendMsgBackRequestHeader>()?;
+        request_tracing::record_request_fields(
+            request_header.origin_topic.as_deref(),
+            Some(request_header.group.as_str()),
+            None,
+        );
+        let subscription_group_config = self
+            .inner
+            .subscription_group_manager
+            .find_subscription_group_config(&request_header.group);
+        let Some(subscription_group_config) = subscription_group_config else {
+            return Some(
+                BrokerError::SubscriptionGroupNotExist(format!(
+                    "subscription group not exist, {} {}",
+                    request_header.group,
+                    FAQUrl::suggest_todo(FAQUrl::SUBSCRIPTION_GROUP_NOT_EXIST)
+                ))
+                .into(),
+            );
+        };
+        if !PermName::is_writeable(self.inner.broker_config.broker_permission()) {
+            return Some(
+                BrokerError::NoPermission(format!(
+                    "the broker[{}] sending message is forbidden",
+                    self.inner.broker_config.broker_ip1
+                ))
+                .into(),
+            );
+        }
+        if subscription_group_config.retry_queue_nums() <= 0 {
+            return Some(response);
+        }
+
+        let mut new_topic =
+            CheetahString::from_string(mix_all::get_retry_topic(request_header.group.as_str()));
+        let mut queue_id_int = self
+            .inner
+            .random_queue_id(subscription_group_config.retry_queue_nums() as u32)
+            as i32;
+        let topic_config = self.inner.topic_config_manager.create_retry_topic(
+            request_header.group.as_str(),
+            subscription_group_config.retry_queue_nums(),
+            false,
+            request_header.unit_mode,
+        );
+        let Some(topic_config) = topic_config else {
+            return Some(
+                BrokerError::SystemError(format!("topic[{}] not exist", new_topic)).into(),
+            );
+        };
+        if !PermName::is_writeable(topic_config.perm) {
+            return Some(
+                BrokerError::NoPermission(format!(
+                    "the topic[{}] sending message is forbidden",
+                    new_topic
+                ))
+                .into(),
+            );
+        }
+
+        let Some(mut msg_ext) = self
+            .inner
+            .message_store
+            .look_message_by_offset(request_header.offset)
+        else {
+            return Some(
+                BrokerError::SystemError(format!(
+                    "look message by offset failed, {}",
+                    request_header.offset
+                ))
+                .into(),
+            );
+        };
+        let mut max_reconsume_times = subscription_group_config.retry_max_times();
+        if request.version() >= From::from(RocketMqVersion::V349) {
+            if let Some(times) = request_header.max_reconsume_times {
+                max_reconsume_times = times;
+            }
+        }
+        let to_dlq =
+            msg_ext.reconsume_times >= max_reconsume_times || request_header.delay_level < 0;
+        if to_dlq {
+            new_topic =
+                CheetahString::from_string(mix_all::get_dlq_topic(request_header.group.as_str()));
+            queue_id_int = self.inner.random_queue_id(DLQ_NUMS_PER_GROUP) as i32;
+            let dlq_topic_config = self
+                .inner
+                .topic_config_manager
+                .create_dlq_topic(request_header.group.as_str());
+            if dlq_topic_config.is_none() {
+                return Some(
+                    response
+                        .set_code(ResponseCode::SystemError)
+                        .set_remark(format!("topic[{}] not exist", new_topic)),
+                );
+            }
+        }
+        // the broker only picks the backoff when the client left the delay level to it
+        let deliver_delay_ms = if to_dlq || request_header.delay_level != 0 {
+            None
+        } else {
+            exponential_retry_delay_ms(
+                subscription_group_config.group_retry_policy(),
+                self.inner.message_store.get_message_store_config(),
+                msg_ext.reconsume_times,
+            )
+        };
+        let msg_inner = build_send_back_message(
+            &mut msg_ext,
+            new_topic,
+            queue_id_int,
+            request_header.delay_level,
+            deliver_delay_ms,
+        );
+
+        let put_message_result = self.inner.message_store.put_message(msg_inner).await;
+        if put_message_result.put_message_status() != PutMessageStatus::PutOk {
+            return Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark(put_message_result.put_message_status().to_string()),
+            );
         }
+        let back_topic = msg_ext
+            .get_property(&CheetahString::from_static_str(
+                MessageConst::PROPERTY_RETRY_TOPIC,
+            ))
+            .unwrap_or_else(|| msg_ext.get_topic().clone());
+        self.inner
+            .broker_stats_manager
+            .inc_send_back_nums(request_header.group.as_str(), back_topic.as_str());
+        Some(response)
     }
 
     async fn send_batch_message<F>(
@@ -378,7 +549,7 @@ where
                 queue_id.unwrap(),
                 start,
                 &mut mapping_context,
-                MessageType::NormalMsg,
+                TopicMessageType::Normal,
             )
             .await
             //Java version has a send_message_callback here, but it is not used
@@ -403,7 +574,7 @@ where
                 queue_id.unwrap(),
                 start,
                 &mut mapping_context,
-                MessageType::NormalMsg,
+                TopicMessageType::Normal,
             )
             .await
             //Java version has a send_message_callback here, but it is not used
@@ -483,6 +654,21 @@ where
             .get(MessageConst::PROPERTY_TRANSACTION_PREPARED)
             .cloned();
         message_ext.message_ext_inner.message.properties = ori_props;
+        let message_type = TopicMessageType::parse_from_message_property(
+            &message_ext.message_ext_inner.message.properties,
+        );
+        // Retry and DLQ topics take back whatever type the consumed message had
+        if self.inner.broker_config.enable_topic_message_type_check
+            && !request_header.topic().starts_with(RETRY_GROUP_TOPIC_PREFIX)
+        {
+            if let Err(remark) = check_topic_message_type(&topic_config, &message_type) {
+                return Some(
+                    response
+                        .set_code(ResponseCode::MessageIllegal)
+                        .set_remark(remark),
+                );
+            }
+        }
         let cleanup_policy = CleanupPolicyUtils::get_delete_policy(Some(&topic_config));
 
         if cleanup_policy == CleanupPolicy::COMPACTION {
@@ -573,7 +759,7 @@ where
                 queue_id.unwrap(),
                 start,
                 &mut mapping_context,
-                MessageType::NormalMsg,
+                message_type,
             )
             .await
             //Java version has a send_message_callback here, but it is not used
@@ -599,7 +785,7 @@ where
                 queue_id.unwrap(),
                 start,
                 &mut mapping_context,
-                MessageType::NormalMsg,
+                message_type,
             )
             .await
             //Java version has a send_message_callback here, but it is not used
@@ -619,62 +805,28 @@ where
         queue_id_int: i32,
         begin_time_millis: Instant,
         mapping_context: &mut TopicQueueMappingContext,
-        _message_type: MessageType,
+        message_type: TopicMessageType,
     ) -> Option<RemotingCommand> {
-        let mut send_ok = false;
-        match put_message_result.put_message_status() {
-                    rocketmq_store::base::message_status_enum::PutMessageStatus::PutOk => {
-                        send_ok = true;
-                        response.set_code_ref(RemotingSysResponseCode::Success);
-                    },
-                    rocketmq_store::base::message_status_enum::PutMessageStatus::FlushDiskTimeout => {
-                        send_ok = true;
-                        response.set_code_ref(ResponseCode::FlushDiskTimeout);
-                    },
-                    rocketmq_store::base::message_status_enum::PutMessageStatus::FlushSlaveTimeout => {
-                        send_ok = true;
-                        response.set_code_ref(ResponseCode::FlushSlaveTimeout);
-                    },
-                    rocketmq_store::base::message_status_enum::PutMessageStatus::SlaveNotAvailable =>{
-                        send_ok = true;
-                        response.set_code_ref(ResponseCode::SlaveNotAvailable);
-                    },
-                    rocketmq_store::base::message_status_enum::PutMessageStatus::ServiceNotAvailable => {
-                        response.set_code_mut(ResponseCode::ServiceNotAvailable).set_remark_mut("service not available now. It may be caused by one of the following reasons: \
-                        the broker's disk is full %s, messages are put to the slave, message store has been shut down, etc.");
-                    },
-                    rocketmq_store::base::message_status_enum::PutMessageStatus::CreateMappedFileFailed => {
-                       response.set_code_mut(RemotingSysResponseCode::SystemError).set_remark_mut("create mapped file failed, remoting_server is busy or broken.");
-                    },
-                    rocketmq_store::base::message_status_enum::PutMessageStatus::MessageIllegal |
-                    rocketmq_store::base::message_status_enum::PutMessageStatus::PropertiesSizeExceeded => {
-                       response.set_code_mut(ResponseCode::MessageIllegal).set_remark_mut("the message is illegal, maybe msg body or properties length not matched. msg body length limit B, msg properties length limit 32KB.");
-                    },
-                    rocketmq_store::base::message_status_enum::PutMessageStatus::OsPageCacheBusy =>{
-                        response.set_code_mut(RemotingSysResponseCode::SystemError).set_remark_mut("[PC_SYNCHRONIZED]broker busy, start flow control for a while");
-                    },
-                    rocketmq_store::base::message_status_enum::PutMessageStatus::UnknownError => {
-                       response.set_code_mut(RemotingSysResponseCode::SystemError).set_remark_mut("UNKNOWN_ERROR");
-                    },
-                    rocketmq_store::base::message_status_enum::PutMessageStatus::InSyncReplicasNotEnough => {
-                        response.set_code_mut(RemotingSysResponseCode::SystemError).set_remark_mut("in-sync replicas not enough");
-                    },
-                    rocketmq_store::base::message_status_enum::PutMessageStatus::LmqConsumeQueueNumExceeded => {
-                        response.set_code_mut(RemotingSysResponseCode::SystemError).set_remark_mut("[LMQ_CONSUME_QUEUE_NUM_EXCEEDED]broker config enableLmq and enableMultiDispatch, lmq consumeQueue num exceed maxLmqConsumeQueueNum config num, default limit 2w.");
-                    },
-                    rocketmq_store::base::message_status_enum::PutMessageStatus::WheelTimerFlowControl => {
-                        response.set_code_mut(RemotingSysResponseCode::SystemError).set_remark_mut("timer message is under flow control, max num limit is %d or the current value is greater than %d and less than %d, trigger random flow control");
-                    },
-                    rocketmq_store::base::message_status_enum::PutMessageStatus::WheelTimerMsgIllegal => {
-                        response.set_code_mut(ResponseCode::MessageIllegal).set_remark_mut("timer message illegal, the delay time should not be bigger than the max delay %dms; or if set del msg, the delay time should be bigger than the current time");
-                    },
-                    rocketmq_store::base::message_status_enum::PutMessageStatus::WheelTimerNotEnable => {
-                        response.set_code_mut(RemotingSysResponseCode::SystemError).set_remark_mut("accurate timer message is not enabled, timerWheelEnable is %s");
-                    },
-                    _ => {
-                        response.set_code_mut(RemotingSysResponseCode::SystemError).set_remark_mut("UNKNOWN_ERROR DEFAULT");
-                    }
-                }
+        let status = put_message_result.put_message_status();
+        let send_ok = match BrokerError::from_put_message_status(
+            status,
+            self.inner.message_store.get_message_store_config(),
+            self.inner.message_store.get_running_flags().is_disk_full(),
+        ) {
+            Some(error) => {
+                error.apply_to(&mut response);
+                false
+            }
+            None => {
+                response.set_code_ref(match status {
+                    PutMessageStatus::FlushDiskTimeout => ResponseCode::FlushDiskTimeout,
+                    PutMessageStatus::FlushSlaveTimeout => ResponseCode::FlushSlaveTimeout,
+                    PutMessageStatus::SlaveNotAvailable => ResponseCode::SlaveNotAvailable,
+                    _ => ResponseCode::Success,
+                });
+                true
+            }
+        };
 
         let binding = HashMap::new();
         let ext_fields = request.ext_fields().unwrap_or(&binding);
@@ -727,11 +879,24 @@ where
                 topic,
                 put_message_result.append_message_result().unwrap().msg_num,
             );
+            let append_message_result = put_message_result.append_message_result().unwrap();
+            self.inner.broker_stats_manager.record_put_message_latency(
+                send_message_context.born_time_stamp,
+                append_message_result.store_timestamp,
+                append_message_result.msg_num,
+            );
             self.inner.broker_stats_manager.inc_topic_put_latency(
                 topic,
                 queue_id_int,
                 begin_time_millis.elapsed().as_millis() as i32,
             );
+            let append_message_result = put_message_result.append_message_result().unwrap();
+            self.broker_metrics_manager.inc_messages_in(
+                topic,
+                &message_type,
+                append_message_result.msg_num,
+                append_message_result.wrote_bytes,
+            );
 
             response_header.set_msg_id(
                 put_message_result
@@ -889,7 +1054,7 @@ where
             }
             let reconsume_times = request_header.reconsume_times.unwrap_or(0);
             let mut send_retry_message_to_dead_letter_queue_directly = false;
-            if self
+            if !self
                 .inner
                 .rebalance_lock_manager
                 .is_lock_all_expired(group_name.as_str())
@@ -915,13 +1080,7 @@ where
                 let new_topic_config = self
                     .inner
                     .topic_config_manager
-                    .create_topic_in_send_message_back_method(
-                        new_topic,
-                        DLQ_NUMS_PER_GROUP as i32,
-                        PermName::PERM_WRITE | PermName::PERM_READ,
-                        false,
-                        0,
-                    );
+                    .create_dlq_topic(group_name.as_str());
                 // can optimize
                 msg.message.topic = CheetahString::from_string(new_topic.to_string());
                 msg.queue_id = queue_id_int;
@@ -945,7 +1104,109 @@ where
     }
 }
 
-const DLQ_NUMS_PER_GROUP: u32 = 1;
+/// Delay before redelivering a message consumed `reconsume_times` times, when the group backs
+/// off exponentially. `None` keeps the delay level ladder, which is also the fallback while the
+/// timer wheel is disabled. The delay is capped so the timer store accepts it.
+fn exponential_retry_delay_ms(
+    group_retry_policy: &GroupRetryPolicy,
+    message_store_config: &MessageStoreConfig,
+    reconsume_times: i32,
+) -> Option<i64> {
+    if group_retry_policy.type_() != GroupRetryPolicyType::Exponential
+        || !message_store_config.timer_wheel_enable
+    {
+        return None;
+    }
+    let delay_ms = group_retry_policy
+        .get_retry_policy()
+        .next_delay_duration(reconsume_times);
+    Some(delay_ms.min((message_store_config.timer_max_delay_sec * 1000) as i64))
+}
+
+/// Builds the message re-put for a consumer send-back from the original `msg_ext`. Messages bound
+/// for a DLQ topic are stored without delay. With `deliver_delay_ms` the message goes through the
+/// timer store instead of a delay level; otherwise a zero `delay_level` picks the next retry level
+/// from the reconsume times.
+fn build_send_back_message(
+    msg_ext: &mut MessageExt,
+    topic: CheetahString,
+    queue_id: i32,
+    delay_level: i32,
+    deliver_delay_ms: Option<i64>,
+) -> MessageExtBrokerInner {
+    let retry_topic = CheetahString::from_static_str(MessageConst::PROPERTY_RETRY_TOPIC);
+    if msg_ext.get_property(&retry_topic).is_none() {
+        let origin_topic = msg_ext.get_topic().clone();
+        MessageAccessor::put_property(msg_ext, retry_topic, origin_topic);
+    }
+    msg_ext.set_wait_store_msg_ok(false);
+    let topic_is_dlq = topic.starts_with(mix_all::DLQ_GROUP_TOPIC_PREFIX);
+    if topic_is_dlq {
+        msg_ext.set_delay_time_level(0);
+    } else if deliver_delay_ms.is_some() {
+        MessageAccessor::clear_property(msg_ext, MessageConst::PROPERTY_DELAY_TIME_LEVEL);
+        // left over when the consumed message was a timer message itself
+        for key in [
+            MessageConst::PROPERTY_TIMER_OUT_MS,
+            MessageConst::PROPERTY_TIMER_DELAY_SEC,
+            MessageConst::PROPERTY_TIMER_DELAY_MS,
+        ] {
+            MessageAccessor::clear_property(msg_ext, key);
+        }
+    } else if delay_level == 0 {
+        msg_ext.set_delay_time_level(3 + msg_ext.reconsume_times);
+    } else {
+        msg_ext.set_delay_time_level(delay_level);
+    }
+
+    let mut msg_inner = MessageExtBrokerInner::default();
+    msg_inner.set_topic(topic);
+    if let Some(body) = msg_ext.get_body() {
+        msg_inner.set_body(body.clone());
+    }
+    msg_inner.set_flag(msg_ext.get_flag());
+    msg_inner.tags_code = MessageExtBrokerInner::tags_string_to_tags_code(
+        msg_ext.get_tags().unwrap_or_default().as_str(),
+    );
+    msg_inner.message_ext_inner.queue_id = queue_id;
+    msg_inner.message_ext_inner.sys_flag = msg_ext.sys_flag;
+    msg_inner.message_ext_inner.born_timestamp = msg_ext.born_timestamp;
+    msg_inner.message_ext_inner.born_host = msg_ext.born_host;
+    msg_inner.message_ext_inner.store_host = msg_ext.store_host;
+    msg_inner.message_ext_inner.reconsume_times = msg_ext.reconsume_times + 1;
+    let origin_msg_id = MessageAccessor::get_origin_message_id(msg_ext)
+        .filter(|origin_msg_id| !origin_msg_id.trim().is_empty())
+        .unwrap_or_else(|| msg_ext.msg_id.clone());
+    msg_inner.message_ext_inner.message.properties = msg_ext.get_properties().clone();
+    MessageAccessor::set_origin_message_id(&mut msg_inner, origin_msg_id);
+    if let Some(delay_ms) = deliver_delay_ms.filter(|_| !topic_is_dlq) {
+        MessageAccessor::put_property(
+            &mut msg_inner,
+            CheetahString::from_static_str(MessageConst::PROPERTY_TIMER_DELIVER_MS),
+            CheetahString::from_string(
+                (TimeUtils::get_current_millis() as i64 + delay_ms).to_string(),
+            ),
+        );
+    }
+    msg_inner.properties_string =
+        message_properties_to_string(msg_inner.message_ext_inner.message.get_properties());
+    msg_inner
+}
+
+/// Fills in the response for a write the store refused. A full disk is reported as a
+/// `SYSTEM_ERROR` naming the usage limit, so producers see why instead of retrying blindly.
+fn service_not_available_response<MS: MessageStore>(
+    message_store: &MS,
+    response: &mut RemotingCommand,
+) {
+    if let Some(error) = BrokerError::from_put_message_status(
+        PutMessageStatus::ServiceNotAvailable,
+        message_store.get_message_store_config(),
+        message_store.get_running_flags().is_disk_full(),
+    ) {
+        error.apply_to(response);
+    }
+}
 
 pub(crate) struct Inner<MS, TS> {
     pub(crate) topic_config_manager: TopicConfigManager,
@@ -994,15 +1255,6 @@ impl<MS, TS> Inner<MS, TS> {
         }
     }
 
-    pub(crate) fn consumer_send_msg_back(
-        &self,
-        _channel: &Channel,
-        _ctx: &ConnectionHandlerContext,
-        _request: &RemotingCommand,
-    ) -> Option<RemotingCommand> {
-        todo!()
-    }
-
     pub(crate) fn build_msg_context(
         &self,
         channel: &Channel,
@@ -1075,39 +1327,52 @@ impl<MS, TS> Inner<MS, TS> {
         &mut self,
         channel: &Channel,
         _ctx: &ConnectionHandlerContext,
-        _request: &RemotingCommand,
+        request: &RemotingCommand,
         request_header: &SendMessageRequestHeader,
         response: &mut RemotingCommand,
-    ) {
+    ) where
+        MS: MessageStore,
+    {
+        if let Err(error) = self.check_send_message(channel, request, request_header) {
+            error.apply_to(response);
+        }
+    }
+
+    fn check_send_message(
+        &mut self,
+        channel: &Channel,
+        request: &RemotingCommand,
+        request_header: &SendMessageRequestHeader,
+    ) -> Result<(), BrokerError>
+    where
+        MS: MessageStore,
+    {
         //check broker permission
         if !PermName::is_writeable(self.broker_config.broker_permission())
             && self
                 .topic_config_manager
                 .is_order_topic(request_header.topic.as_str())
         {
-            response.with_code(ResponseCode::NoPermission);
-            response.with_remark(format!(
+            return Err(BrokerError::NoPermission(format!(
                 "the broker[{}] sending message is forbidden",
-                self.broker_config.broker_ip1.clone()
-            ));
-            return;
+                self.broker_config.broker_ip1
+            )));
         }
 
-        //check Topic
-        let result = TopicValidator::validate_topic(request_header.topic.as_str());
-        if !result.valid() {
-            response.with_code(SystemError);
-            response.with_remark(result.remark().clone());
-            return;
-        }
-
-        if TopicValidator::is_not_allowed_send_topic(request_header.topic.as_str()) {
-            response.with_code(ResponseCode::NoPermission);
-            response.with_remark(format!(
-                "Sending message to topic[{}] is forbidden.",
-                request_header.topic.as_str()
-            ));
-            return;
+        //check Topic, body and properties
+        let max_message_size = self
+            .message_store
+            .get_message_store_config()
+            .max_message_size;
+        if let Err(err) = check_message(
+            request_header,
+            request.body().as_deref(),
+            max_message_size.max(0) as usize,
+        ) {
+            return Err(match check_error_code(&err) {
+                ResponseCode::NoPermission => BrokerError::NoPermission(err.to_string()),
+                _ => BrokerError::MessageIllegal(err.to_string()),
+            });
         }
         let mut topic_config = self
             .topic_config_manager
@@ -1136,42 +1401,41 @@ impl<MS, TS> Inner<MS, TS> {
                     topic_sys_flag,
                 );
 
-            if topic_config.is_none() && request_header.topic.starts_with(RETRY_GROUP_TOPIC_PREFIX)
-            {
-                topic_config = self
-                    .topic_config_manager
-                    .create_topic_in_send_message_back_method(
-                        request_header.topic.as_ref(),
+            if topic_config.is_none() {
+                if let Some(group) = request_header.topic.strip_prefix(RETRY_GROUP_TOPIC_PREFIX) {
+                    topic_config = self.topic_config_manager.create_retry_topic(
+                        group,
                         1,
-                        PermName::PERM_WRITE | PermName::PERM_READ,
                         false,
-                        topic_sys_flag,
+                        request_header.unit_mode.unwrap_or(false),
                     );
+                }
             }
 
             if topic_config.is_none() {
-                response.with_code(ResponseCode::TopicNotExist);
-                response.with_remark(format!(
+                return Err(BrokerError::TopicNotExist(format!(
                     "topic[{}] not exist, apply first please!",
                     request_header.topic.as_str()
-                ));
+                )));
             }
         }
 
-        let queue_id_int = request_header.queue_id.unwrap();
         let topic_config_inner = topic_config.as_ref().unwrap();
+        check_topic_writeable(topic_config_inner).map_err(BrokerError::NoPermission)?;
+
+        let queue_id_int = request_header.queue_id.unwrap();
         let id_valid = topic_config_inner
             .write_queue_nums
             .max(topic_config_inner.read_queue_nums);
         if queue_id_int >= id_valid as i32 {
-            response.with_code(ResponseCode::SystemError);
-            response.with_remark(format!(
+            return Err(BrokerError::SystemError(format!(
                 "request queueId[{}] is illegal, {:?} Producer: {}",
                 queue_id_int,
                 topic_config_inner,
                 channel.remote_address()
-            ));
+            )));
         }
+        Ok(())
     }
 
     pub(crate) fn random_queue_id(&self, write_queue_nums: u32) -> u32 {
@@ -1179,6 +1443,61 @@ impl<MS, TS> Inner<MS, TS> {
     }
 }
 
+/// Checks the topic, body and properties of a single or batch send request, with the same
+/// limits the producer applies before sending.
+pub(crate) fn check_message(
+    request_header: &SendMessageRequestHeader,
+    body: Option<&[u8]>,
+    max_message_size: usize,
+) -> Result<(), MessageCheckError> {
+    MessageValidator::check(
+        request_header.topic.as_str(),
+        body,
+        request_header
+            .properties
+            .as_ref()
+            .map_or(0, |properties| properties.len()),
+        max_message_size,
+    )
+}
+
+/// Topics seeded without write permission, e.g. the cluster name topic with
+/// `clusterTopicEnable=false`, only exist for routing and must not receive messages.
+fn check_topic_writeable(topic_config: &TopicConfig) -> Result<(), String> {
+    if PermName::is_writeable(topic_config.perm) {
+        return Ok(());
+    }
+    Err(format!(
+        "the topic[{}] sending message is forbidden",
+        topic_config.topic_name.as_deref().unwrap_or_default()
+    ))
+}
+
+/// A topic declaring its `message.type` only takes messages of that type, unless it is `MIXED`.
+fn check_topic_message_type(
+    topic_config: &TopicConfig,
+    message_type: &TopicMessageType,
+) -> Result<(), String> {
+    let topic_message_type = topic_config.get_topic_message_type();
+    if topic_message_type == TopicMessageType::Mixed || &topic_message_type == message_type {
+        return Ok(());
+    }
+    Err(format!(
+        "the topic[{}] only accepts {} messages, but the message is {}",
+        topic_config.topic_name.as_deref().unwrap_or_default(),
+        topic_message_type,
+        message_type
+    ))
+}
+
+fn check_error_code(err: &MessageCheckError) -> ResponseCode {
+    match err {
+        MessageCheckError::IllegalTopic(_) => SystemError,
+        MessageCheckError::NotAllowedSendTopic(_) => ResponseCode::NoPermission,
+        _ => ResponseCode::MessageIllegal,
+    }
+}
+
 fn rewrite_response_for_static_topic(
     response_header: &mut SendMessageResponseHeader,
     mapping_context: &TopicQueueMappingContext,
@@ -1206,3 +1525,358 @@ fn rewrite_response_for_static_topic(
     response_header.set_queue_offset(static_logic_offset);
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rocketmq_remoting::protocol::subscription::exponential_retry_policy::ExponentialRetryPolicy;
+    use rocketmq_store::message_store::default_message_store::DefaultMessageStore;
+
+    use super::*;
+    use crate::test_util::topic_config_manager;
+
+    #[test]
+    fn disk_full_is_reported_as_system_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let message_store = DefaultMessageStore::new(
+            Arc::new(MessageStoreConfig {
+                store_path_root_dir: CheetahString::from_string(
+                    dir.path().to_string_lossy().to_string(),
+                ),
+                disk_max_used_space_ratio: 80,
+                ..MessageStoreConfig::default()
+            }),
+            Arc::new(BrokerConfig::default()),
+            Arc::new(parking_lot::Mutex::new(HashMap::new())),
+            None,
+            false,
+        );
+
+        message_store.get_running_flags().get_and_make_disk_full();
+        let mut response = RemotingCommand::create_response_command();
+        service_not_available_response(&message_store, &mut response);
+        assert_eq!(response.code(), RemotingSysResponseCode::SystemError as i32);
+        let remark = response.remark().unwrap();
+        assert!(remark.contains("disk is full"), "{remark}");
+        assert!(remark.contains("diskMaxUsedSpaceRatio 80%"), "{remark}");
+
+        message_store.get_running_flags().get_and_make_disk_ok();
+        let mut response = RemotingCommand::create_response_command();
+        service_not_available_response(&message_store, &mut response);
+        assert_eq!(response.code(), ResponseCode::ServiceNotAvailable as i32);
+    }
+
+    #[test]
+    fn cluster_topic_is_writeable_only_when_enabled() {
+        let mut broker_config = BrokerConfig::default();
+        let cluster_name = broker_config.broker_identity.broker_cluster_name.clone();
+        let manager = topic_config_manager(Arc::new(broker_config.clone()));
+        let topic_config = manager.select_topic_config(&cluster_name).unwrap();
+        assert_eq!(topic_config.read_queue_nums, 1);
+        assert_eq!(topic_config.write_queue_nums, 1);
+        assert!(PermName::is_inherited(topic_config.perm));
+        assert!(check_topic_writeable(&topic_config).is_ok());
+        assert!(TopicValidator::is_system_topic(cluster_name.as_str()));
+
+        broker_config.cluster_topic_enable = false;
+        let manager = topic_config_manager(Arc::new(broker_config));
+        let topic_config = manager.select_topic_config(&cluster_name).unwrap();
+        let remark = check_topic_writeable(&topic_config).unwrap_err();
+        assert!(remark.contains("sending message is forbidden"), "{remark}");
+        // still routable, so producers get NO_PERMISSION instead of TOPIC_NOT_EXIST
+        assert!(PermName::is_inherited(topic_config.perm));
+    }
+
+    #[test]
+    fn topic_message_type_must_match_the_message() {
+        let message_type = |properties: &[(&str, &str)]| {
+            TopicMessageType::parse_from_message_property(
+                &properties
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect::<HashMap<_, _>>(),
+            )
+        };
+        let half_message = message_type(&[(MessageConst::PROPERTY_TRANSACTION_PREPARED, "true")]);
+        let delay_message = message_type(&[(MessageConst::PROPERTY_DELAY_TIME_LEVEL, "3")]);
+        let topic_config = |topic_message_type: Option<&str>| {
+            let mut topic_config = TopicConfig::new("TopicA");
+            if let Some(topic_message_type) = topic_message_type {
+                topic_config
+                    .attributes
+                    .insert("message.type".into(), topic_message_type.into());
+            }
+            topic_config
+        };
+
+        let remark = check_topic_message_type(&topic_config(None), &half_message).unwrap_err();
+        assert_eq!(
+            remark,
+            "the topic[TopicA] only accepts NORMAL messages, but the message is TRANSACTION"
+        );
+        assert!(
+            check_topic_message_type(&topic_config(Some("TRANSACTION")), &half_message).is_ok()
+        );
+        let remark =
+            check_topic_message_type(&topic_config(Some("FIFO")), &delay_message).unwrap_err();
+        assert!(remark.contains("only accepts FIFO messages"), "{remark}");
+        assert!(check_topic_message_type(&topic_config(Some("MIXED")), &delay_message).is_ok());
+        assert!(check_topic_message_type(&topic_config(None), &message_type(&[])).is_ok());
+    }
+
+    fn send_header(topic: &str, properties: Option<String>) -> SendMessageRequestHeader {
+        SendMessageRequestHeader {
+            topic: CheetahString::from_slice(topic),
+            properties: properties.map(CheetahString::from_string),
+            ..SendMessageRequestHeader::default()
+        }
+    }
+
+    #[test]
+    fn check_message_enforces_body_size_limit() {
+        let header = send_header("TopicA", None);
+        let body = vec![1u8; 1025];
+        assert!(check_message(&header, Some(&body[..1024]), 1024).is_ok());
+
+        let err = check_message(&header, Some(&body), 1024).unwrap_err();
+        assert_eq!(check_error_code(&err), ResponseCode::MessageIllegal);
+        assert!(err.to_string().contains("1025"), "{err}");
+        let err = check_message(&header, Some(&[]), 1024).unwrap_err();
+        assert_eq!(check_error_code(&err), ResponseCode::MessageIllegal);
+    }
+
+    #[test]
+    fn check_message_enforces_properties_length_limit() {
+        // "K" + separator + value + separator
+        let at_max = format!("K\u{1}{}\u{2}", "v".repeat(32767 - 3));
+        assert_eq!(at_max.len(), 32767);
+        assert!(check_message(
+            &send_header("TopicA", Some(at_max.clone())),
+            Some(b"body"),
+            1024
+        )
+        .is_ok());
+
+        let over = format!("{at_max}x");
+        let err =
+            check_message(&send_header("TopicA", Some(over)), Some(b"body"), 1024).unwrap_err();
+        assert_eq!(check_error_code(&err), ResponseCode::MessageIllegal);
+    }
+
+    #[test]
+    fn check_message_maps_topic_errors() {
+        let err =
+            check_message(&send_header("illegal topic", None), Some(b"body"), 1024).unwrap_err();
+        assert_eq!(check_error_code(&err), ResponseCode::SystemError);
+        let err =
+            check_message(&send_header(&"t".repeat(128), None), Some(b"body"), 1024).unwrap_err();
+        assert_eq!(check_error_code(&err), ResponseCode::SystemError);
+        assert!(check_message(&send_header(&"t".repeat(127), None), Some(b"body"), 1024).is_ok());
+        let err = check_message(
+            &send_header(TopicValidator::RMQ_SYS_SCHEDULE_TOPIC, None),
+            Some(b"body"),
+            1024,
+        )
+        .unwrap_err();
+        assert_eq!(check_error_code(&err), ResponseCode::NoPermission);
+        // retry topics stay sendable, their reconsume times are checked against the group
+        assert!(check_message(&send_header("%RETRY%GroupA", None), Some(b"body"), 1024).is_ok());
+    }
+
+    fn consumed_message(reconsume_times: i32) -> MessageExt {
+        let mut msg_ext = MessageExt::default();
+        msg_ext.set_topic(CheetahString::from_static_str("TopicA"));
+        msg_ext.set_body(bytes::Bytes::from_static(b"body"));
+        msg_ext.msg_id = CheetahString::from_static_str("MSG-ID");
+        msg_ext.reconsume_times = reconsume_times;
+        msg_ext
+    }
+
+    #[test]
+    fn send_back_message_goes_to_retry_topic_with_next_delay_level() {
+        let mut msg_ext = consumed_message(2);
+        let msg_inner = build_send_back_message(
+            &mut msg_ext,
+            CheetahString::from_string(mix_all::get_retry_topic("GroupA")),
+            0,
+            0,
+            None,
+        );
+        assert_eq!(msg_inner.get_topic().as_str(), "%RETRY%GroupA");
+        assert_eq!(
+            msg_inner.message_ext_inner.message.get_delay_time_level(),
+            5
+        );
+        assert_eq!(msg_inner.reconsume_times(), 3);
+        assert_eq!(
+            msg_inner
+                .message_ext_inner
+                .get_property(&CheetahString::from_static_str(
+                    MessageConst::PROPERTY_RETRY_TOPIC
+                ))
+                .unwrap()
+                .as_str(),
+            "TopicA"
+        );
+        assert_eq!(
+            MessageAccessor::get_origin_message_id(&msg_inner.message_ext_inner)
+                .unwrap()
+                .as_str(),
+            "MSG-ID"
+        );
+        assert_eq!(
+            msg_inner.message_ext_inner.get_body().unwrap().as_ref(),
+            b"body"
+        );
+
+        let mut msg_ext = consumed_message(0);
+        let msg_inner = build_send_back_message(
+            &mut msg_ext,
+            CheetahString::from_string(mix_all::get_retry_topic("GroupA")),
+            0,
+            7,
+            None,
+        );
+        assert_eq!(
+            msg_inner.message_ext_inner.message.get_delay_time_level(),
+            7
+        );
+    }
+
+    #[test]
+    fn send_back_message_goes_to_dlq_without_delay() {
+        let mut msg_ext = consumed_message(16);
+        MessageAccessor::set_origin_message_id(
+            &mut msg_ext,
+            CheetahString::from_static_str("FIRST-MSG-ID"),
+        );
+        msg_ext.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_RETRY_TOPIC),
+            CheetahString::from_static_str("TopicA"),
+        );
+        msg_ext.set_topic(CheetahString::from_static_str("%RETRY%GroupA"));
+        let msg_inner = build_send_back_message(
+            &mut msg_ext,
+            CheetahString::from_string(mix_all::get_dlq_topic("GroupA")),
+            0,
+            3,
+            Some(1000),
+        );
+        assert_eq!(msg_inner.get_topic().as_str(), "%DLQ%GroupA");
+        assert_eq!(
+            msg_inner.message_ext_inner.message.get_delay_time_level(),
+            0
+        );
+        assert!(msg_inner
+            .property(MessageConst::PROPERTY_TIMER_DELIVER_MS)
+            .is_none());
+        assert_eq!(msg_inner.reconsume_times(), 17);
+        assert_eq!(
+            msg_inner
+                .message_ext_inner
+                .get_property(&CheetahString::from_static_str(
+                    MessageConst::PROPERTY_RETRY_TOPIC
+                ))
+                .unwrap()
+                .as_str(),
+            "TopicA"
+        );
+        assert_eq!(
+            MessageAccessor::get_origin_message_id(&msg_inner.message_ext_inner)
+                .unwrap()
+                .as_str(),
+            "FIRST-MSG-ID"
+        );
+    }
+
+    fn exponential_policy(initial: u64, max: u64, multiplier: u64) -> GroupRetryPolicy {
+        let mut policy = GroupRetryPolicy::default();
+        policy.set_type_(GroupRetryPolicyType::Exponential);
+        policy.set_exponential_retry_policy(Some(ExponentialRetryPolicy::new(
+            initial, max, multiplier,
+        )));
+        policy
+    }
+
+    #[test]
+    fn exponential_retry_delay_grows_until_capped() {
+        let policy = exponential_policy(1000, 20_000, 2);
+        let message_store_config = MessageStoreConfig {
+            timer_wheel_enable: true,
+            ..MessageStoreConfig::default()
+        };
+        let delays = (0..=5)
+            .map(|times| exponential_retry_delay_ms(&policy, &message_store_config, times).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(delays, vec![1000, 2000, 4000, 8000, 16_000, 20_000]);
+
+        // the timer store rejects anything beyond its own maximum
+        let message_store_config = MessageStoreConfig {
+            timer_wheel_enable: true,
+            timer_max_delay_sec: 10,
+            ..MessageStoreConfig::default()
+        };
+        assert_eq!(
+            exponential_retry_delay_ms(&policy, &message_store_config, 5),
+            Some(10_000)
+        );
+    }
+
+    #[test]
+    fn exponential_retry_falls_back_to_delay_levels() {
+        let message_store_config = MessageStoreConfig {
+            timer_wheel_enable: false,
+            ..MessageStoreConfig::default()
+        };
+        let policy = exponential_policy(1000, 20_000, 2);
+        assert_eq!(
+            exponential_retry_delay_ms(&policy, &message_store_config, 1),
+            None
+        );
+        let message_store_config = MessageStoreConfig {
+            timer_wheel_enable: true,
+            ..MessageStoreConfig::default()
+        };
+        assert_eq!(
+            exponential_retry_delay_ms(&GroupRetryPolicy::default(), &message_store_config, 1),
+            None
+        );
+    }
+
+    #[test]
+    fn send_back_message_with_deliver_delay_uses_timer() {
+        let mut msg_ext = consumed_message(2);
+        msg_ext.set_delay_time_level(4);
+        msg_ext.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_TIMER_OUT_MS),
+            CheetahString::from_static_str("1"),
+        );
+        let before = TimeUtils::get_current_millis() as i64;
+        let msg_inner = build_send_back_message(
+            &mut msg_ext,
+            CheetahString::from_string(mix_all::get_retry_topic("GroupA")),
+            0,
+            0,
+            Some(4000),
+        );
+        assert_eq!(msg_inner.get_topic().as_str(), "%RETRY%GroupA");
+        assert_eq!(
+            msg_inner.message_ext_inner.message.get_delay_time_level(),
+            0
+        );
+        assert!(msg_inner
+            .property(MessageConst::PROPERTY_TIMER_OUT_MS)
+            .is_none());
+        let deliver_ms = msg_inner
+            .property(MessageConst::PROPERTY_TIMER_DELIVER_MS)
+            .unwrap()
+            .parse::<i64>()
+            .unwrap();
+        assert!(deliver_ms >= before + 4000);
+        assert!(deliver_ms <= TimeUtils::get_current_millis() as i64 + 4000);
+        assert!(msg_inner
+            .properties_string
+            .contains(MessageConst::PROPERTY_TIMER_DELIVER_MS));
+    }
+}
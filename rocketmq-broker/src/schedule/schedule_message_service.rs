@@ -15,52 +15,581 @@
  * limitations under the License.
  */
 
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
 use rocketmq_common::common::config_manager::ConfigManager;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::common::mix_all;
+use rocketmq_common::common::running::running_stats::RunningStats;
+use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
+use rocketmq_common::common::topic::TopicValidator;
+use rocketmq_common::common::TopicFilterType;
+use rocketmq_common::MessageAccessor::MessageAccessor;
+use rocketmq_remoting::protocol::body::delay_progress::DelayLevelProgress;
+use rocketmq_remoting::protocol::body::delay_progress::DelayProgressBody;
+use rocketmq_remoting::protocol::DataVersion;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
+use rocketmq_store::log_file::MAX_PULL_MSG_SIZE;
+use rocketmq_store::plugin::dyn_message_store::BoxedMessageStore;
+use rocketmq_store::plugin::dyn_message_store::DynMessageStore;
 use rocketmq_store::store_path_config_helper::get_delay_offset_store_path;
+use tracing::error;
+use tracing::warn;
 
+use crate::schedule::delay_offset_serialize_wrapper::DelayOffsetSerializeWrapper;
+
+const DELIVER_BATCH_SIZE: i32 = 32;
+
+/// Delivers the messages parked in `SCHEDULE_TOPIC_XXXX` to their real topic once the delay of
+/// their level has elapsed. Every delay level has its own schedule queue, and the next offset to
+/// deliver of each queue is persisted in the delay offset file.
 #[derive(Default, Clone)]
 pub struct ScheduleMessageService {
     pub(crate) broker_config: Arc<BrokerConfig>,
+    message_store_config: Arc<MessageStoreConfig>,
+    delay_level_table: Arc<BTreeMap<i32 /* level */, DelayLevel>>,
+    offset_table: Arc<Mutex<HashMap<i32 /* level */, i64 /* offset */>>>,
+    data_version: Arc<Mutex<DataVersion>>,
+    message_store: Option<ArcMut<BoxedMessageStore>>,
+}
+
+struct DelayLevel {
+    delay_millis: i64,
+    deliver_stats: Mutex<DeliverStats>,
+}
+
+#[derive(Default)]
+struct DeliverStats {
+    delivered: u64,
+    sampled: u64,
+    sampled_at: u64,
+    tps: f64,
 }
 
 impl ScheduleMessageService {
+    pub fn new(
+        broker_config: Arc<BrokerConfig>,
+        message_store_config: Arc<MessageStoreConfig>,
+    ) -> Self {
+        let delay_level_table =
+            parse_delay_level(message_store_config.message_delay_level.as_str())
+                .unwrap_or_else(|| {
+                    error!(
+                        "parse messageDelayLevel {} failed, delay messages are disabled",
+                        message_store_config.message_delay_level
+                    );
+                    BTreeMap::new()
+                })
+                .into_iter()
+                .map(|(level, delay_millis)| {
+                    (
+                        level,
+                        DelayLevel {
+                            delay_millis,
+                            deliver_stats: Mutex::new(DeliverStats::default()),
+                        },
+                    )
+                })
+                .collect();
+        Self {
+            broker_config,
+            message_store_config,
+            delay_level_table: Arc::new(delay_level_table),
+            offset_table: Arc::new(Mutex::new(HashMap::new())),
+            data_version: Arc::new(Mutex::new(DataVersion::new())),
+            message_store: None,
+        }
+    }
+
+    pub fn set_message_store(&mut self, message_store: ArcMut<BoxedMessageStore>) {
+        self.message_store = Some(message_store);
+    }
+
     pub fn delay_level2queue_id(delay_level: i32) -> i32 {
         delay_level - 1
     }
 
-    pub fn build_running_stats(&self, _stats: &mut HashMap<String, String>) {
-        //TODO
+    pub fn queue_id2delay_level(queue_id: i32) -> i32 {
+        queue_id + 1
     }
 
     pub fn get_max_delay_level(&self) -> i32 {
-        0
+        self.delay_level_table
+            .keys()
+            .next_back()
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Next offset of the schedule queue of `delay_level` to deliver.
+    pub fn get_offset(&self, delay_level: i32) -> i64 {
+        self.offset_table
+            .lock()
+            .get(&delay_level)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn update_offset(&self, delay_level: i32, offset: i64) {
+        self.offset_table.lock().insert(delay_level, offset);
+        self.data_version.lock().next_version();
+    }
+
+    /// Messages of `delay_level` stored in the schedule queue but not delivered yet.
+    pub fn get_pending(&self, delay_level: i32) -> i64 {
+        self.max_offset(delay_level).map_or(0, |max_offset| {
+            (max_offset - self.get_offset(delay_level)).max(0)
+        })
+    }
+
+    fn max_offset(&self, delay_level: i32) -> Option<i64> {
+        self.message_store.as_ref().map(|message_store| {
+            message_store.get_max_offset_in_queue(
+                &CheetahString::from_static_str(TopicValidator::RMQ_SYS_SCHEDULE_TOPIC),
+                Self::delay_level2queue_id(delay_level),
+            )
+        })
+    }
+
+    /// Delivers the messages of every level whose delay has elapsed at `now`, returns how many
+    /// were put back to their real topic.
+    pub async fn deliver(&self, now: i64) -> usize {
+        let Some(message_store) = self.message_store.clone() else {
+            return 0;
+        };
+        let mut delivered = 0;
+        for (level, delay_level) in self.delay_level_table.iter() {
+            let count = self
+                .deliver_level(&message_store, *level, delay_level.delay_millis, now)
+                .await;
+            delay_level.deliver_stats.lock().delivered += count as u64;
+            delivered += count;
+        }
+        delivered
+    }
+
+    async fn deliver_level(
+        &self,
+        message_store: &ArcMut<BoxedMessageStore>,
+        delay_level: i32,
+        delay_millis: i64,
+        now: i64,
+    ) -> usize {
+        let group = CheetahString::from_static_str(mix_all::SCHEDULE_CONSUMER_GROUP);
+        let topic = CheetahString::from_static_str(TopicValidator::RMQ_SYS_SCHEDULE_TOPIC);
+        let queue_id = Self::delay_level2queue_id(delay_level);
+        let mut offset = self
+            .get_offset(delay_level)
+            .max(message_store.get_min_offset_in_queue(&topic, queue_id));
+        let mut delivered = 0;
+        while offset < message_store.get_max_offset_in_queue(&topic, queue_id) {
+            let Some(result) = message_store
+                .get_message(
+                    &group,
+                    &topic,
+                    queue_id,
+                    offset,
+                    DELIVER_BATCH_SIZE,
+                    MAX_PULL_MSG_SIZE,
+                    None,
+                )
+                .await
+            else {
+                break;
+            };
+            if result.message_mapped_list().is_empty() {
+                if result.next_begin_offset() > offset {
+                    offset = result.next_begin_offset();
+                    self.update_offset(delay_level, offset);
+                    continue;
+                }
+                break;
+            }
+            for buffer in result.message_mapped_list() {
+                let Some(mut bytes) = buffer.get_bytes() else {
+                    continue;
+                };
+                let Some(msg_ext) =
+                    message_decoder::decode(&mut bytes, true, false, false, false, false)
+                else {
+                    continue;
+                };
+                if msg_ext.store_timestamp + delay_millis > now {
+                    self.update_offset(delay_level, msg_ext.queue_offset);
+                    return delivered;
+                }
+                let result = message_store
+                    .mut_from_ref()
+                    .put_message(message_time_up(&msg_ext))
+                    .await;
+                if !result.is_ok() {
+                    warn!(
+                        "deliver delay message failed, level: {}, offset: {}, status: {:?}",
+                        delay_level,
+                        msg_ext.queue_offset,
+                        result.put_message_status()
+                    );
+                    self.update_offset(delay_level, msg_ext.queue_offset);
+                    return delivered;
+                }
+                delivered += 1;
+            }
+            offset = result.next_begin_offset();
+            self.update_offset(delay_level, offset);
+        }
+        delivered
+    }
+
+    /// Recomputes the delivery TPS of every level from the messages delivered since the last
+    /// sample.
+    pub fn sample_deliver_tps(&self, now: u64) {
+        for delay_level in self.delay_level_table.values() {
+            let mut stats = delay_level.deliver_stats.lock();
+            if stats.sampled_at > 0 && now > stats.sampled_at {
+                stats.tps = (stats.delivered - stats.sampled) as f64 * 1000.0
+                    / (now - stats.sampled_at) as f64;
+            }
+            stats.sampled = stats.delivered;
+            stats.sampled_at = now;
+        }
+    }
+
+    /// Logs a warning for every level with more pending messages than
+    /// `schedule_backlog_warn_threshold`.
+    pub fn check_backlog(&self) {
+        let threshold = self.message_store_config.schedule_backlog_warn_threshold;
+        if threshold <= 0 {
+            return;
+        }
+        for level in self.delay_level_table.keys() {
+            let pending = self.get_pending(*level);
+            if pending > threshold {
+                warn!(
+                    "delay level {} has {} pending messages, more than the threshold {}",
+                    level, pending, threshold
+                );
+            }
+        }
+    }
+
+    pub fn delay_progress(&self) -> DelayProgressBody {
+        let progress_list = self
+            .delay_level_table
+            .iter()
+            .map(|(level, delay_level)| {
+                let offset = self.get_offset(*level);
+                let max_offset = self.max_offset(*level).unwrap_or_default();
+                DelayLevelProgress {
+                    delay_level: *level,
+                    delay_time_millis: delay_level.delay_millis,
+                    offset,
+                    max_offset,
+                    pending: (max_offset - offset).max(0),
+                    deliver_tps: delay_level.deliver_stats.lock().tps,
+                }
+            })
+            .collect();
+        DelayProgressBody { progress_list }
+    }
+
+    pub fn build_running_stats(&self, stats: &mut HashMap<String, String>) {
+        for progress in self.delay_progress().progress_list {
+            stats.insert(
+                format!(
+                    "{}_{}",
+                    RunningStats::ScheduleMessageOffset.name(),
+                    progress.delay_level
+                ),
+                format!("{},{}", progress.offset, progress.max_offset),
+            );
+            stats.insert(
+                format!("schedulePending_{}", progress.delay_level),
+                progress.pending.to_string(),
+            );
+            stats.insert(
+                format!("scheduleDeliverTps_{}", progress.delay_level),
+                format!("{:.2}", progress.deliver_tps),
+            );
+        }
     }
 }
 
-impl ConfigManager for ScheduleMessageService {
-    fn decode0(&mut self, _key: &[u8], _body: &[u8]) {
-        todo!()
+/// Parses `messageDelayLevel`, a space separated list of delays such as `1s 5m 2h 1d`, into the
+/// delay in milliseconds of every level, levels starting from 1.
+fn parse_delay_level(message_delay_level: &str) -> Option<BTreeMap<i32, i64>> {
+    let mut delay_level_table = BTreeMap::new();
+    for (index, value) in message_delay_level.split_whitespace().enumerate() {
+        let unit = match value.chars().last()? {
+            's' => 1000,
+            'm' => 1000 * 60,
+            'h' => 1000 * 60 * 60,
+            'd' => 1000 * 60 * 60 * 24,
+            _ => return None,
+        };
+        let num = value[..value.len() - 1].parse::<i64>().ok()?;
+        delay_level_table.insert(index as i32 + 1, num * unit);
     }
+    Some(delay_level_table)
+}
 
-    fn stop(&mut self) -> bool {
-        todo!()
+/// Restores the real topic and queue of a schedule message whose delay has elapsed.
+fn message_time_up(msg_ext: &MessageExt) -> MessageExtBrokerInner {
+    let mut msg_inner = MessageExtBrokerInner::default();
+    if let Some(body) = msg_ext.get_body() {
+        msg_inner.set_body(body.clone());
     }
+    msg_inner.set_flag(msg_ext.get_flag());
+    MessageAccessor::set_properties(&mut msg_inner, msg_ext.get_properties().clone());
+    let topic_filter_type =
+        if msg_ext.sys_flag & MessageSysFlag::MULTI_TAGS_FLAG == MessageSysFlag::MULTI_TAGS_FLAG {
+            TopicFilterType::MultiTag
+        } else {
+            TopicFilterType::SingleTag
+        };
+    msg_inner.tags_code = match msg_ext.get_tags() {
+        Some(tags) => {
+            MessageExtBrokerInner::tags_string2tags_code(&topic_filter_type, tags.as_str())
+        }
+        None => 0,
+    };
+    msg_inner.message_ext_inner.sys_flag = msg_ext.sys_flag;
+    msg_inner.message_ext_inner.born_timestamp = msg_ext.born_timestamp;
+    msg_inner.message_ext_inner.born_host = msg_ext.born_host;
+    msg_inner.message_ext_inner.store_host = msg_ext.store_host;
+    msg_inner.message_ext_inner.reconsume_times = msg_ext.reconsume_times;
+    msg_inner.set_wait_store_msg_ok(false);
+    MessageAccessor::clear_property(&mut msg_inner, MessageConst::PROPERTY_DELAY_TIME_LEVEL);
+
+    msg_inner.set_topic(
+        msg_ext
+            .get_property(&CheetahString::from_static_str(
+                MessageConst::PROPERTY_REAL_TOPIC,
+            ))
+            .unwrap_or_default(),
+    );
+    msg_inner.message_ext_inner.queue_id = msg_ext
+        .get_property(&CheetahString::from_static_str(
+            MessageConst::PROPERTY_REAL_QUEUE_ID,
+        ))
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_default();
+    MessageAccessor::clear_property(&mut msg_inner, MessageConst::PROPERTY_REAL_TOPIC);
+    MessageAccessor::clear_property(&mut msg_inner, MessageConst::PROPERTY_REAL_QUEUE_ID);
+    msg_inner.properties_string =
+        message_decoder::message_properties_to_string(msg_inner.get_properties());
+    msg_inner
+}
 
+impl ConfigManager for ScheduleMessageService {
     fn config_file_path(&self) -> String {
         get_delay_offset_store_path(self.broker_config.store_path_root_dir.as_str())
     }
 
-    fn encode(&mut self) -> String {
-        todo!()
+    fn encode_pretty(&self, pretty_format: bool) -> String {
+        let wrapper = DelayOffsetSerializeWrapper::new(
+            self.offset_table.lock().clone(),
+            self.data_version.lock().clone(),
+        );
+        if pretty_format {
+            serde_json::to_string_pretty(&wrapper)
+        } else {
+            serde_json::to_string(&wrapper)
+        }
+        .unwrap_or_default()
     }
 
-    fn encode_pretty(&self, _pretty_format: bool) -> String {
-        todo!()
+    fn decode(&self, json_string: &str) {
+        if json_string.is_empty() {
+            return;
+        }
+        let Ok(wrapper) = serde_json::from_str::<DelayOffsetSerializeWrapper>(json_string) else {
+            error!("decode delay offset table failed: {}", json_string);
+            return;
+        };
+        self.offset_table
+            .lock()
+            .extend(wrapper.offset_table().iter().map(|(k, v)| (*k, *v)));
+        self.data_version
+            .lock()
+            .assign_new_one(wrapper.data_version());
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use rocketmq_common::TimeUtils::get_current_millis;
+    use rocketmq_store::message_store::default_message_store::DefaultMessageStore;
+    use rocketmq_store::test_util::start_store;
+    use rocketmq_store::test_util::wait_dispatched;
 
-    fn decode(&self, _json_string: &str) {}
+    use super::*;
+    use crate::util::hook_utils::HookUtils;
+
+    const DELAY_TOPIC: &str = "DelayTopic";
+
+    fn service(
+        dir: &tempfile::TempDir,
+        store: &ArcMut<DefaultMessageStore>,
+    ) -> ScheduleMessageService {
+        let mut service = ScheduleMessageService::new(
+            Arc::new(BrokerConfig {
+                store_path_root_dir: dir.path().to_string_lossy().into_owned().into(),
+                ..BrokerConfig::default()
+            }),
+            Arc::new(MessageStoreConfig {
+                message_delay_level: "1s 5s".to_string(),
+                schedule_backlog_warn_threshold: 2,
+                ..MessageStoreConfig::default()
+            }),
+        );
+        service.set_message_store(ArcMut::new(Box::new(store.clone())));
+        service
+    }
+
+    async fn put_delay_messages(
+        store: &ArcMut<DefaultMessageStore>,
+        service: &ScheduleMessageService,
+        delay_level: i32,
+        count: usize,
+    ) {
+        for _ in 0..count {
+            let mut msg = MessageExtBrokerInner::default();
+            msg.set_topic(DELAY_TOPIC.into());
+            msg.set_body(Bytes::from_static(b"delay"));
+            msg.message_ext_inner.born_host = "127.0.0.1:10911".parse().unwrap();
+            msg.message_ext_inner.store_host = "127.0.0.1:10911".parse().unwrap();
+            msg.message_ext_inner
+                .message
+                .set_delay_time_level(delay_level);
+            HookUtils::transform_delay_level_message(service, &mut msg);
+            assert!(store.mut_from_ref().put_message(msg).await.is_ok());
+        }
+        wait_dispatched(store).await;
+    }
+
+    fn delivered(store: &ArcMut<DefaultMessageStore>) -> i64 {
+        DynMessageStore::get_max_offset_in_queue(store, &DELAY_TOPIC.into(), 0)
+    }
+
+    #[test]
+    fn parse_delay_level_accepts_every_unit() {
+        let table = parse_delay_level("1s 2m 3h 1d").unwrap();
+        assert_eq!(
+            table.into_iter().collect::<Vec<_>>(),
+            vec![(1, 1000), (2, 120_000), (3, 10_800_000), (4, 86_400_000)]
+        );
+        assert!(parse_delay_level("1s 5x").is_none());
+        assert!(parse_delay_level("s").is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn backlog_is_reported_before_and_after_delivery() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = start_store(&dir, MessageStoreConfig::default()).await;
+        let service = service(&dir, &store);
+        assert_eq!(service.get_max_delay_level(), 2);
+        put_delay_messages(&store, &service, 1, 2).await;
+        put_delay_messages(&store, &service, 2, 3).await;
+
+        assert_eq!(service.get_pending(1), 2);
+        assert_eq!(service.get_pending(2), 3);
+        let mut stats = HashMap::new();
+        service.build_running_stats(&mut stats);
+        assert_eq!(stats["scheduleMessageOffset_1"], "0,2");
+        assert_eq!(stats["scheduleMessageOffset_2"], "0,3");
+        assert_eq!(stats["schedulePending_2"], "3");
+        service.check_backlog();
+
+        let now = get_current_millis() as i64;
+        assert_eq!(service.deliver(now).await, 0);
+        assert_eq!(service.get_pending(1), 2);
+
+        assert_eq!(service.deliver(now + 2_000).await, 2);
+        wait_dispatched(&store).await;
+        assert_eq!(service.get_pending(1), 0);
+        assert_eq!(service.get_pending(2), 3);
+        assert_eq!(delivered(&store), 2);
+
+        assert_eq!(service.deliver(now + 10_000).await, 3);
+        wait_dispatched(&store).await;
+        assert_eq!(delivered(&store), 5);
+        let progress = service.delay_progress().progress_list;
+        assert_eq!(progress.len(), 2);
+        assert!(progress
+            .iter()
+            .all(|level| level.pending == 0 && level.offset == level.max_offset));
+        assert_eq!(progress[1].delay_time_millis, 5000);
+        let mut stats = HashMap::new();
+        service.build_running_stats(&mut stats);
+        assert_eq!(stats["scheduleMessageOffset_2"], "3,3");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn delivered_messages_lose_the_delay_properties() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = start_store(&dir, MessageStoreConfig::default()).await;
+        let service = service(&dir, &store);
+        put_delay_messages(&store, &service, 1, 1).await;
+        assert_eq!(
+            service.deliver(get_current_millis() as i64 + 2_000).await,
+            1
+        );
+        wait_dispatched(&store).await;
+
+        let result = DynMessageStore::get_message(
+            &store,
+            &"group".into(),
+            &DELAY_TOPIC.into(),
+            0,
+            0,
+            1,
+            MAX_PULL_MSG_SIZE,
+            None,
+        )
+        .await
+        .unwrap();
+        let mut bytes = result.message_mapped_list()[0].get_bytes().unwrap();
+        let msg = message_decoder::decode(&mut bytes, true, false, false, false, false).unwrap();
+        assert_eq!(msg.get_topic().as_str(), DELAY_TOPIC);
+        assert!(msg
+            .get_property(&MessageConst::PROPERTY_DELAY_TIME_LEVEL.into())
+            .is_none());
+        assert!(msg
+            .get_property(&MessageConst::PROPERTY_REAL_TOPIC.into())
+            .is_none());
+    }
+
+    #[test]
+    fn offset_table_survives_persist_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker_config = Arc::new(BrokerConfig {
+            store_path_root_dir: dir.path().to_string_lossy().into_owned().into(),
+            ..BrokerConfig::default()
+        });
+        let service = ScheduleMessageService::new(
+            broker_config.clone(),
+            Arc::new(MessageStoreConfig::default()),
+        );
+        service.update_offset(1, 7);
+        service.update_offset(3, 11);
+        service.persist();
+
+        let loaded =
+            ScheduleMessageService::new(broker_config, Arc::new(MessageStoreConfig::default()));
+        assert!(loaded.load());
+        assert_eq!(loaded.get_offset(1), 7);
+        assert_eq!(loaded.get_offset(3), 11);
+        assert_eq!(loaded.get_max_delay_level(), 18);
+    }
 }
@@ -30,6 +30,7 @@ use rocketmq_store::log_file::MessageStore;
 use serde::Deserialize;
 use serde::Serialize;
 use tracing::info;
+use tracing::warn;
 
 use crate::broker_path_config_helper::get_subscription_group_path;
 
@@ -112,6 +113,15 @@ where
             .contains_key(group)
     }
 
+    pub fn subscription_group_names(&self) -> Vec<CheetahString> {
+        self.subscription_group_wrapper
+            .lock()
+            .subscription_group_table
+            .keys()
+            .cloned()
+            .collect()
+    }
+
     pub fn find_subscription_group_config(
         &self,
         group: &CheetahString,
@@ -138,15 +148,7 @@ where
                     subscription_group_config_new
                 );
             }
-            let state_machine_version = if let Some(ref store) = self.message_store {
-                store.get_state_machine_version()
-            } else {
-                0
-            };
-            self.subscription_group_wrapper
-                .lock()
-                .data_version
-                .next_version_with(state_machine_version);
+            self.update_data_version();
             self.persist();
             subscription_group_config = Some(subscription_group_config_new);
         }
@@ -164,6 +166,84 @@ where
             .cloned()
     }
 
+    /// Creates or replaces the config of its group.
+    pub fn update_subscription_group_config(&self, config: SubscriptionGroupConfig) {
+        let old = self
+            .subscription_group_wrapper
+            .lock()
+            .subscription_group_table
+            .insert(
+                CheetahString::from_slice(config.group_name()),
+                config.clone(),
+            );
+        match old {
+            Some(old) => info!(
+                "update subscription group config, old: {:?} new: {:?}",
+                old, config
+            ),
+            None => info!("create new subscription group, {:?}", config),
+        }
+        self.update_data_version();
+        self.persist();
+    }
+
+    /// Removes the config and forbidden flags of `group`.
+    pub fn delete_subscription_group_config(&self, group: &CheetahString) {
+        let old = {
+            let mut wrapper = self.subscription_group_wrapper.lock();
+            wrapper.forbidden_table.remove(group);
+            wrapper.subscription_group_table.remove(group)
+        };
+        match old {
+            Some(old) => {
+                info!(
+                    "delete subscription group OK, subscription group: {:?}",
+                    old
+                );
+                self.update_data_version();
+                self.persist();
+            }
+            None => warn!(
+                "delete subscription group failed, subscription group: {} not exist",
+                group
+            ),
+        }
+    }
+
+    /// Rejects pulls of `group` until its config is updated with consumption enabled again.
+    /// Returns whether the group consumed until now.
+    pub fn disable_consume(&self, group: &CheetahString) -> bool {
+        let disabled = match self
+            .subscription_group_wrapper
+            .lock()
+            .subscription_group_table
+            .get_mut(group)
+        {
+            Some(config) if config.consume_enable() => {
+                config.set_consume_enable(false);
+                true
+            }
+            _ => false,
+        };
+        if disabled {
+            self.update_data_version();
+            self.persist();
+        }
+        disabled
+    }
+
+    fn update_data_version(&self) {
+        let state_machine_version = if let Some(ref store) = self.message_store {
+            store.get_state_machine_version()
+        } else {
+            0
+        };
+        self.subscription_group_wrapper
+            .lock()
+            .data_version
+            .next_version_with(state_machine_version);
+    }
+
     pub fn get_forbidden(&self, group: &str, topic: &str, forbidden_index: i32) -> bool {
         let topic_forbidden = self.get_forbidden_internal(group, topic);
         let bit_forbidden = 1 << forbidden_index;
@@ -0,0 +1,63 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Fixtures shared by the unit tests of this crate.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::server::config::ServerConfig;
+use rocketmq_remoting::connection::Connection;
+use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+
+use crate::broker_runtime::BrokerRuntimeInner;
+use crate::out_api::broker_outer_api::BrokerOuterAPI;
+use crate::topic::manager::topic_config_manager::TopicConfigManager;
+use crate::topic::manager::topic_queue_mapping_manager::TopicQueueMappingManager;
+
+/// A topic config manager backed by a runtime built from `broker_config` and default configs
+/// otherwise. Topic configs persist under the store root of `broker_config`.
+pub(crate) fn topic_config_manager(broker_config: Arc<BrokerConfig>) -> TopicConfigManager {
+    let broker_runtime_inner = Arc::new(BrokerRuntimeInner {
+        broker_out_api: Arc::new(BrokerOuterAPI::new(Arc::new(TokioClientConfig::default()))),
+        broker_config: broker_config.clone(),
+        message_store_config: Arc::new(MessageStoreConfig::default()),
+        server_config: Arc::new(ServerConfig::default()),
+        topic_queue_mapping_manager: Arc::new(TopicQueueMappingManager::new(broker_config.clone())),
+    });
+    TopicConfigManager::new(broker_config, broker_runtime_inner)
+}
+
+/// A channel over a loopback connection, standing for a connected client. Nothing reads from
+/// the other end.
+pub(crate) async fn client_channel() -> Channel {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let remote_address = listener.local_addr().unwrap();
+    let stream = TcpStream::connect(remote_address).await.unwrap();
+    let local_address = stream.local_addr().unwrap();
+    Channel::new(
+        local_address,
+        remote_address,
+        Connection::new(stream),
+        ArcMut::new(HashMap::new()),
+    )
+}
@@ -17,3 +17,4 @@
 
 pub(crate) mod topic_config_manager;
 pub(crate) mod topic_queue_mapping_manager;
+pub(crate) mod topic_route_info_manager;
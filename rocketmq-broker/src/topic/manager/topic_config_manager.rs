@@ -28,6 +28,7 @@ use rocketmq_common::common::config_manager::ConfigManager;
 use rocketmq_common::common::constant::PermName;
 use rocketmq_common::common::mix_all;
 use rocketmq_common::common::topic::TopicValidator;
+use rocketmq_common::common::TopicSysFlag;
 use rocketmq_common::utils::serde_json_utils::SerdeJsonUtils;
 use rocketmq_common::TopicAttributes::ALL;
 use rocketmq_remoting::protocol::body::topic_info_wrapper::topic_config_wrapper::TopicConfigAndMappingSerializeWrapper;
@@ -38,11 +39,18 @@ use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_rust::ArcMut;
 use rocketmq_store::log_file::MessageStore;
 use rocketmq_store::message_store::default_message_store::DefaultMessageStore;
+use rocketmq_store::timer::timer_message_store::TIMER_TOPIC;
+use tracing::error;
 use tracing::info;
 use tracing::warn;
 
 use crate::broker_path_config_helper::get_topic_config_path;
 use crate::broker_runtime::BrokerRuntimeInner;
+use crate::error::BrokerError;
+use crate::Result;
+
+/// Queues of the `%DLQ%` topic of a consumer group.
+pub(crate) const DLQ_NUMS_PER_GROUP: u32 = 1;
 
 pub(crate) struct TopicConfigManager {
     topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
@@ -123,7 +131,8 @@ impl TopicConfigManager {
                 .broker_identity
                 .broker_cluster_name
                 .to_string();
-            let mut config = TopicConfig::new(topic);
+            TopicValidator::add_system_topic(topic.clone());
+            let mut config = TopicConfig::with_queues(topic, 1, 1);
             let mut perm = PermName::PERM_INHERIT;
             if self.broker_config.cluster_topic_enable {
                 perm |= PermName::PERM_READ | PermName::PERM_WRITE;
@@ -134,7 +143,8 @@ impl TopicConfigManager {
 
         {
             let topic = self.broker_config.broker_identity.broker_name.to_string();
-            let mut config = TopicConfig::new(topic);
+            TopicValidator::add_system_topic(topic.clone());
+            let mut config = TopicConfig::with_queues(topic, 1, 1);
             let mut perm = PermName::PERM_INHERIT;
             if self.broker_config.broker_topic_enable {
                 perm |= PermName::PERM_READ | PermName::PERM_WRITE;
@@ -152,6 +162,7 @@ impl TopicConfigManager {
         }
 
         {
+            TopicValidator::add_system_topic(TopicValidator::RMQ_SYS_SCHEDULE_TOPIC);
             self.put_topic_config(TopicConfig::with_queues(
                 TopicValidator::RMQ_SYS_SCHEDULE_TOPIC,
                 Self::SCHEDULE_TOPIC_QUEUE_NUM,
@@ -162,6 +173,7 @@ impl TopicConfigManager {
         {
             if self.broker_config.trace_topic_enable {
                 let topic = self.broker_config.msg_trace_topic_name.clone();
+                TopicValidator::add_system_topic(topic.clone());
                 self.put_topic_config(TopicConfig::with_queues(topic, 1, 1));
             }
         }
@@ -172,6 +184,7 @@ impl TopicConfigManager {
                 self.broker_config.broker_identity.broker_name,
                 mix_all::REPLY_TOPIC_POSTFIX
             );
+            TopicValidator::add_system_topic(topic.clone());
             self.put_topic_config(TopicConfig::with_queues(topic, 1, 1));
         }
 
@@ -181,7 +194,7 @@ impl TopicConfigManager {
                 "rmq_sys_REVIVE_LOG_{}",
                 self.broker_config.broker_identity.broker_cluster_name
             );
-
+            TopicValidator::add_system_topic(topic.clone());
             self.put_topic_config(TopicConfig::with_queues(
                 topic,
                 self.broker_config.revive_queue_num,
@@ -199,25 +212,54 @@ impl TopicConfigManager {
         }
 
         {
-            self.put_topic_config(TopicConfig::with_queues(
-                TopicValidator::RMQ_SYS_TRANS_HALF_TOPIC,
-                1,
-                1,
-            ));
+            if !self.broker_config.reject_transaction_message {
+                for topic in [
+                    TopicValidator::RMQ_SYS_TRANS_HALF_TOPIC,
+                    TopicValidator::RMQ_SYS_TRANS_OP_HALF_TOPIC,
+                ] {
+                    TopicValidator::add_system_topic(topic);
+                    self.put_topic_config(TopicConfig::with_queues(topic, 1, 1));
+                }
+            }
         }
 
         {
-            self.put_topic_config(TopicConfig::with_queues(
-                TopicValidator::RMQ_SYS_TRANS_OP_HALF_TOPIC,
-                1,
-                1,
-            ));
+            if self.broker_config.timer_wheel_config.timer_wheel_enable {
+                TopicValidator::add_system_topic(TIMER_TOPIC);
+                self.put_topic_config(TopicConfig::with_queues(TIMER_TOPIC, 1, 1));
+            }
         }
     }
 
-    #[inline]
+    /// The system topics this broker hosts: the registered ones found in the topic table,
+    /// which grows as features create their topics, plus every retry and DLQ topic.
+    pub fn system_topic_list(&self) -> Vec<CheetahString> {
+        self.topic_config_table
+            .lock()
+            .iter()
+            .filter(|(topic, topic_config)| {
+                TopicValidator::is_system_topic(topic)
+                    || TopicSysFlag::has_retry_flag(topic_config.topic_sys_flag)
+                    || TopicSysFlag::has_dlq_flag(topic_config.topic_sys_flag)
+            })
+            .map(|(topic, _)| topic.clone())
+            .collect()
+    }
+
     pub fn select_topic_config(&self, topic: &CheetahString) -> Option<TopicConfig> {
-        self.topic_config_table.lock().get(topic).cloned()
+        if let Some(topic_config) = self.topic_config_table.lock().get(topic) {
+            return Some(topic_config.clone());
+        }
+        // LMQs are created on demand by multi-dispatch and never registered
+        if mix_all::is_lmq(Some(topic.as_str())) {
+            return Some(TopicConfig::with_perm(
+                topic.clone(),
+                1,
+                1,
+                PermName::PERM_READ | PermName::PERM_WRITE,
+            ));
+        }
+        None
     }
 
     pub fn build_serialize_wrapper(
@@ -263,6 +305,9 @@ impl TopicConfigManager {
         )
     }
 
+    /// Creates `topic` from `default_topic` on its first send, with the queue number the
+    /// producer asks for capped by the default topic and the broker's default queue number. An
+    /// existing topic is returned as is, whatever the producer asks for.
     pub fn create_topic_in_send_message_method(
         &mut self,
         topic: &str,
@@ -290,6 +335,11 @@ impl TopicConfigManager {
                     let mut topic_config = TopicConfig::new(topic);
                     let queue_nums = client_default_topic_queue_nums
                         .min(default_topic_config.write_queue_nums as i32)
+                        .min(
+                            self.broker_config
+                                .topic_queue_config
+                                .default_topic_queue_nums as i32,
+                        )
                         .max(0);
                     topic_config.write_queue_nums = queue_nums as u32;
                     topic_config.read_queue_nums = queue_nums as u32;
@@ -301,12 +351,9 @@ impl TopicConfigManager {
                         default_topic, topic_config, remote_address
                     );
                     self.put_topic_config(topic_config.clone());
-                    self.data_version.mut_from_ref().next_version_with(
-                        self.message_store
-                            .as_ref()
-                            .unwrap()
-                            .get_state_machine_version(),
-                    );
+                    self.data_version
+                        .mut_from_ref()
+                        .next_version_with(self.state_machine_version());
                     self.persist();
                     (Some(topic_config), true)
                 } else {
@@ -339,9 +386,15 @@ impl TopicConfigManager {
         topic_sys_flag: u32,
     ) -> Option<TopicConfig> {
         if let Some(ref mut config) = self.get_topic_config(topic) {
-            if is_order != config.order {
+            let missing_sys_flag = topic_sys_flag & !config.topic_sys_flag;
+            if is_order != config.order || missing_sys_flag != 0 {
                 config.order = is_order;
-                self.update_topic_config(config);
+                config.topic_sys_flag |= missing_sys_flag;
+                // no attribute delta, the stored attributes are kept
+                config.attributes.clear();
+                if let Err(err) = self.update_topic_config(config) {
+                    warn!("update topic config failed, topic: {}, {}", topic, err);
+                }
             }
             return Some(config.clone());
         }
@@ -362,12 +415,9 @@ impl TopicConfigManager {
             config.order = is_order;
 
             self.put_topic_config(config.clone());
-            self.data_version.mut_from_ref().next_version_with(
-                self.message_store
-                    .as_ref()
-                    .unwrap()
-                    .get_state_machine_version(),
-            );
+            self.data_version
+                .mut_from_ref()
+                .next_version_with(self.state_machine_version());
             self.persist();
             (Some(config), true)
         } else {
@@ -380,6 +430,50 @@ impl TopicConfigManager {
         topic_config
     }
 
+    /// Creates the retry topic of `group` the first time it sends a message back or heartbeats,
+    /// with the retry queues of its subscription group and the permission of the broker.
+    pub fn create_retry_topic(
+        &mut self,
+        group: &str,
+        retry_queue_nums: i32,
+        is_order: bool,
+        unit_mode: bool,
+    ) -> Option<TopicConfig> {
+        let mut topic_sys_flag = TopicSysFlag::set_retry_flag(0);
+        if unit_mode {
+            topic_sys_flag = TopicSysFlag::set_unit_sub_flag(topic_sys_flag);
+        }
+        self.create_topic_in_send_message_back_method(
+            &CheetahString::from_string(mix_all::get_retry_topic(group)),
+            retry_queue_nums,
+            self.broker_config.broker_permission & (PermName::PERM_READ | PermName::PERM_WRITE),
+            is_order,
+            topic_sys_flag,
+        )
+    }
+
+    /// Creates the DLQ topic of `group` when a message runs out of retries. It is write-only
+    /// unless `auto_create_dlq_readable` is set, so dead messages are not consumed by accident.
+    pub fn create_dlq_topic(&mut self, group: &str) -> Option<TopicConfig> {
+        let mut perm = PermName::PERM_WRITE;
+        if self.broker_config.auto_create_dlq_readable {
+            perm |= PermName::PERM_READ;
+        }
+        self.create_topic_in_send_message_back_method(
+            &CheetahString::from_string(mix_all::get_dlq_topic(group)),
+            DLQ_NUMS_PER_GROUP as i32,
+            perm,
+            false,
+            TopicSysFlag::set_dlq_flag(0),
+        )
+    }
+
+    fn state_machine_version(&self) -> i64 {
+        self.message_store
+            .as_ref()
+            .map_or(0, |message_store| message_store.get_state_machine_version())
+    }
+
     fn register_broker_data(&mut self, topic_config: &TopicConfig) {
         let broker_config = self.broker_config.clone();
         let broker_runtime_inner = self.broker_runtime_inner.clone();
@@ -395,10 +489,14 @@ impl TopicConfigManager {
         });
     }
 
-    pub fn update_topic_config_list(&mut self, topic_config_list: &mut [TopicConfig]) {
+    pub fn update_topic_config_list(
+        &mut self,
+        topic_config_list: &mut [TopicConfig],
+    ) -> Result<()> {
         for topic_config in topic_config_list {
-            self.update_topic_config(topic_config);
+            self.update_topic_config(topic_config)?;
         }
+        Ok(())
     }
 
     #[inline]
@@ -410,21 +508,18 @@ impl TopicConfigManager {
         let old = self.remove_topic_config(topic);
         if let Some(old) = old {
             info!("delete topic config OK, topic: {:?}", old);
-            let state_machine_version = if let Some(message_store) = self.message_store.as_ref() {
-                message_store.get_state_machine_version()
-            } else {
-                0
-            };
             self.data_version
                 .mut_from_ref()
-                .next_version_with(state_machine_version);
+                .next_version_with(self.state_machine_version());
             self.persist();
         } else {
             warn!("delete topic config failed, topic: {} not exists", topic);
         }
     }
 
-    pub fn update_topic_config(&mut self, topic_config: &mut TopicConfig) {
+    /// Creates or updates `topic_config`. Its attributes are a `+key`/`-key` delta against the
+    /// stored ones, which is refused as a whole if any attribute in it is invalid.
+    pub fn update_topic_config(&mut self, topic_config: &mut TopicConfig) -> Result<()> {
         let new_attributes = Self::request(topic_config);
         let current_attributes = self.current(topic_config.topic_name.as_ref().unwrap().as_str());
         let create = self
@@ -433,15 +528,9 @@ impl TopicConfigManager {
             .get(topic_config.topic_name.as_ref().unwrap().as_str())
             .is_none();
 
-        let final_attributes = alter_current_attributes(
-            create,
-            ALL.clone()
-                .into_iter()
-                .map(|(k, v)| (k.into(), v))
-                .collect(),
-            new_attributes,
-            current_attributes,
-        );
+        let final_attributes =
+            alter_current_attributes(create, &ALL, current_attributes, new_attributes)
+                .map_err(BrokerError::SystemError)?;
         topic_config.attributes = final_attributes;
         match self.put_topic_config(topic_config.clone()) {
             None => {
@@ -455,16 +544,14 @@ impl TopicConfigManager {
             }
         }
 
-        self.data_version.mut_from_ref().next_version_with(
-            self.message_store
-                .as_ref()
-                .unwrap()
-                .get_state_machine_version(),
-        );
+        self.data_version
+            .mut_from_ref()
+            .next_version_with(self.state_machine_version());
         self.persist_with_topic(
             topic_config.topic_name.as_ref().unwrap().as_str(),
             topic_config.clone(),
         );
+        Ok(())
     }
 
     fn request(topic_config: &TopicConfig) -> HashMap<CheetahString, CheetahString> {
@@ -578,8 +665,14 @@ impl ConfigManager for TopicConfigManager {
         if json_string.is_empty() {
             return;
         }
-        let wrapper = SerdeJsonUtils::from_json_str::<TopicConfigSerializeWrapper>(json_string)
-            .expect("Decode TopicConfigSerializeWrapper from json failed");
+        let wrapper =
+            match SerdeJsonUtils::from_json_str::<TopicConfigSerializeWrapper>(json_string) {
+                Ok(wrapper) => wrapper,
+                Err(e) => {
+                    error!("decode topic config failed: {:?}", e);
+                    return;
+                }
+            };
         if let Some(value) = wrapper.data_version() {
             self.data_version.mut_from_ref().assign_new_one(value);
         }
@@ -592,3 +685,257 @@ impl ConfigManager for TopicConfigManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::attribute::attribute_parser::AttributeParser;
+    use rocketmq_common::common::attribute::topic_message_type::TopicMessageType;
+    use rocketmq_common::common::broker::broker_config::TimerWheelConfig;
+
+    use super::*;
+    use crate::test_util;
+
+    fn topic_config_manager(
+        dir: &tempfile::TempDir,
+        broker_config: BrokerConfig,
+    ) -> TopicConfigManager {
+        test_util::topic_config_manager(Arc::new(BrokerConfig {
+            store_path_root_dir: dir.path().to_string_lossy().into_owned().into(),
+            ..broker_config
+        }))
+    }
+
+    #[test]
+    fn retry_topic_inherits_broker_permission() {
+        // topic creation registers the topic from a spawned task
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = topic_config_manager(&dir, BrokerConfig::default());
+        let retry_topic = manager
+            .create_retry_topic("GroupA", 3, false, false)
+            .unwrap();
+        assert_eq!(retry_topic.topic_name.as_deref(), Some("%RETRY%GroupA"));
+        assert_eq!(retry_topic.read_queue_nums, 3);
+        assert_eq!(retry_topic.write_queue_nums, 3);
+        assert_eq!(retry_topic.perm, PermName::PERM_READ | PermName::PERM_WRITE);
+        assert!(TopicSysFlag::has_retry_flag(retry_topic.topic_sys_flag));
+        assert!(!TopicSysFlag::has_unit_sub_flag(retry_topic.topic_sys_flag));
+
+        let mut manager = topic_config_manager(
+            &dir,
+            BrokerConfig {
+                broker_permission: PermName::PERM_READ,
+                ..BrokerConfig::default()
+            },
+        );
+        let retry_topic = manager.create_retry_topic("GroupB", 1, true, true).unwrap();
+        assert_eq!(retry_topic.perm, PermName::PERM_READ);
+        assert!(retry_topic.order);
+        assert!(TopicSysFlag::has_retry_flag(retry_topic.topic_sys_flag));
+        assert!(TopicSysFlag::has_unit_sub_flag(retry_topic.topic_sys_flag));
+    }
+
+    #[test]
+    fn dlq_topic_is_write_only_unless_configured_readable() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = topic_config_manager(&dir, BrokerConfig::default());
+        let dlq_topic = manager.create_dlq_topic("GroupA").unwrap();
+        assert_eq!(dlq_topic.topic_name.as_deref(), Some("%DLQ%GroupA"));
+        assert_eq!(dlq_topic.read_queue_nums, DLQ_NUMS_PER_GROUP);
+        assert_eq!(dlq_topic.perm, PermName::PERM_WRITE);
+        assert!(TopicSysFlag::has_dlq_flag(dlq_topic.topic_sys_flag));
+        assert!(!TopicSysFlag::has_retry_flag(dlq_topic.topic_sys_flag));
+
+        let mut manager = topic_config_manager(
+            &dir,
+            BrokerConfig {
+                auto_create_dlq_readable: true,
+                ..BrokerConfig::default()
+            },
+        );
+        let dlq_topic = manager.create_dlq_topic("GroupB").unwrap();
+        assert_eq!(dlq_topic.perm, PermName::PERM_READ | PermName::PERM_WRITE);
+    }
+
+    #[test]
+    fn auto_created_topic_negotiates_its_queue_number() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let remote_address = "127.0.0.1:10911".parse().unwrap();
+        let broker_config = |default_topic_queue_nums| {
+            let mut broker_config = BrokerConfig {
+                auto_create_topic_enable: true,
+                ..BrokerConfig::default()
+            };
+            broker_config.topic_queue_config.default_topic_queue_nums = default_topic_queue_nums;
+            broker_config
+        };
+        let dir_a = tempfile::tempdir().unwrap();
+        let mut broker_a = topic_config_manager(&dir_a, broker_config(8));
+        let dir_b = tempfile::tempdir().unwrap();
+        let mut broker_b = topic_config_manager(&dir_b, broker_config(2));
+
+        for (broker, queue_nums) in [(&mut broker_a, 4), (&mut broker_b, 2)] {
+            let topic_config = broker
+                .create_topic_in_send_message_method(
+                    "AutoCreatedTopic",
+                    TopicValidator::AUTO_CREATE_TOPIC_KEY_TOPIC,
+                    remote_address,
+                    4,
+                    0,
+                )
+                .unwrap();
+            assert_eq!(topic_config.write_queue_nums, queue_nums);
+            assert_eq!(topic_config.read_queue_nums, queue_nums);
+            assert!(!PermName::is_inherited(topic_config.perm));
+        }
+
+        // another producer with a smaller default must not shrink the topic
+        let topic_config = broker_a
+            .create_topic_in_send_message_method(
+                "AutoCreatedTopic",
+                TopicValidator::AUTO_CREATE_TOPIC_KEY_TOPIC,
+                remote_address,
+                1,
+                0,
+            )
+            .unwrap();
+        assert_eq!(topic_config.write_queue_nums, 4);
+        assert_eq!(
+            broker_a
+                .select_topic_config(&"AutoCreatedTopic".into())
+                .unwrap()
+                .write_queue_nums,
+            4
+        );
+    }
+
+    #[test]
+    fn existing_retry_topic_gets_the_retry_flag() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = topic_config_manager(&dir, BrokerConfig::default());
+        manager.put_topic_config(TopicConfig::with_queues("%RETRY%GroupA", 2, 2));
+        let retry_topic = manager
+            .create_retry_topic("GroupA", 1, false, false)
+            .unwrap();
+        assert_eq!(retry_topic.read_queue_nums, 2);
+        assert!(TopicSysFlag::has_retry_flag(retry_topic.topic_sys_flag));
+        assert!(TopicSysFlag::has_retry_flag(
+            manager
+                .select_topic_config(&"%RETRY%GroupA".into())
+                .unwrap()
+                .topic_sys_flag
+        ));
+    }
+    #[test]
+    fn message_type_attribute_round_trips_through_update() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = topic_config_manager(&dir, BrokerConfig::default());
+        let update = |manager: &mut TopicConfigManager, attributes: &str| {
+            let mut topic_config = TopicConfig::with_queues("TopicA", 4, 4);
+            topic_config.attributes = AttributeParser::parse_to_map(attributes)
+                .unwrap()
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect();
+            manager.update_topic_config(&mut topic_config).unwrap();
+            manager.select_topic_config(&"TopicA".into()).unwrap()
+        };
+
+        let topic_config = update(&mut manager, "+message.type=FIFO");
+        assert_eq!(
+            topic_config.attributes,
+            HashMap::from([("message.type".into(), "FIFO".into())])
+        );
+        assert_eq!(
+            topic_config.get_topic_message_type(),
+            TopicMessageType::Fifo
+        );
+
+        let topic_config = update(&mut manager, "+message.type=TRANSACTION");
+        assert_eq!(
+            topic_config.get_topic_message_type(),
+            TopicMessageType::Transaction
+        );
+        assert!(manager
+            .encode_pretty(false)
+            .contains("\"message.type\":\"TRANSACTION\""));
+    }
+    #[test]
+    fn invalid_attribute_delta_leaves_the_topic_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = topic_config_manager(&dir, BrokerConfig::default());
+        let mut topic_config = TopicConfig::with_queues("TopicA", 4, 4);
+        topic_config.attributes = HashMap::from([("+queue.type".into(), "BatchCQ".into())]);
+        manager.update_topic_config(&mut topic_config).unwrap();
+
+        for (attributes, error) in [
+            (
+                ("+queue.type", "SimpleCQ"),
+                "attempt to update an unchangeable attribute. key: queue.type",
+            ),
+            (
+                ("+message.type", "ORDERLY"),
+                "wrong value of attribute message.type: value is not in set",
+            ),
+            (("+unknown.key", "value"), "unsupported key: unknown.key"),
+        ] {
+            let mut topic_config = TopicConfig::with_queues("TopicA", 8, 8);
+            topic_config.attributes = HashMap::from([(attributes.0.into(), attributes.1.into())]);
+            let err = manager.update_topic_config(&mut topic_config).unwrap_err();
+            assert!(err.to_string().starts_with(error), "{err}");
+        }
+        let topic_config = manager.select_topic_config(&"TopicA".into()).unwrap();
+        assert_eq!(topic_config.write_queue_nums, 4);
+        assert_eq!(
+            topic_config.attributes,
+            HashMap::from([("queue.type".into(), "BatchCQ".into())])
+        );
+    }
+
+    #[test]
+    fn system_topic_list_grows_with_enabled_features() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = topic_config_manager(
+            &dir,
+            BrokerConfig {
+                reject_transaction_message: true,
+                ..BrokerConfig::default()
+            },
+        )
+        .system_topic_list();
+        assert!(base.contains(&TopicValidator::RMQ_SYS_SCHEDULE_TOPIC.into()));
+        assert!(base.contains(&"rmq_sys_REVIVE_LOG_DefaultCluster".into()));
+        assert!(!base.contains(&TopicValidator::RMQ_SYS_TRANS_HALF_TOPIC.into()));
+        assert!(!base.contains(&TopicValidator::RMQ_SYS_TRACE_TOPIC.into()));
+
+        let manager = topic_config_manager(
+            &dir,
+            BrokerConfig {
+                trace_topic_enable: true,
+                timer_wheel_config: TimerWheelConfig {
+                    timer_wheel_enable: true,
+                },
+                ..BrokerConfig::default()
+            },
+        );
+        let topics = manager.system_topic_list();
+        for topic in [
+            TopicValidator::RMQ_SYS_TRANS_HALF_TOPIC,
+            TopicValidator::RMQ_SYS_TRANS_OP_HALF_TOPIC,
+            TopicValidator::RMQ_SYS_TRACE_TOPIC,
+            TIMER_TOPIC,
+        ] {
+            assert!(topics.contains(&topic.into()), "{topic}");
+            assert!(TopicValidator::is_system_topic(topic), "{topic}");
+        }
+        assert!(base.iter().all(|topic| topics.contains(topic)));
+        assert_eq!(topics.len(), base.len() + 4);
+        assert!(!topics.contains(&"TopicA".into()));
+    }
+}
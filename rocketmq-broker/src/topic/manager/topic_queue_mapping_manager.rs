@@ -22,11 +22,13 @@ use cheetah_string::CheetahString;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
 use rocketmq_common::common::config_manager::ConfigManager;
 use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::error::Error as RemotingError;
 use rocketmq_remoting::protocol::body::topic_info_wrapper::topic_queue_wrapper::TopicQueueMappingSerializeWrapper;
 use rocketmq_remoting::protocol::header::message_operation_header::TopicRequestHeaderTrait;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_context::TopicQueueMappingContext;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_detail::TopicQueueMappingDetail;
+use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_utils::TopicQueueMappingUtils;
 use rocketmq_remoting::protocol::DataVersion;
 use rocketmq_remoting::protocol::RemotingSerializable;
 use tracing::info;
@@ -190,6 +192,55 @@ impl TopicQueueMappingManager {
         }
     }
 
+    /// Installs the mapping of a static topic hosted by this broker.
+    ///
+    /// Unless `force` is set, a mapping with a smaller epoch, another scope or an older leader
+    /// gen is rejected. Logic queues missing from `new_detail` keep their current items.
+    pub fn update_topic_queue_mapping(
+        &self,
+        mut new_detail: TopicQueueMappingDetail,
+        force: bool,
+        flush: bool,
+    ) -> rocketmq_remoting::Result<()> {
+        let info = &new_detail.topic_queue_mapping_info;
+        let Some(topic) = info.topic.clone() else {
+            return Err(illegal(
+                "The topic of the mapping detail is missing".to_string(),
+            ));
+        };
+        if info.bname.as_ref() != Some(&self.broker_config.broker_name) {
+            return Err(illegal(format!(
+                "The mapping detail of {} belongs to broker {:?}, not {}",
+                topic, info.bname, self.broker_config.broker_name
+            )));
+        }
+        for items in new_detail.hosted_queues.iter().flat_map(HashMap::values) {
+            TopicQueueMappingUtils::check_logic_queue_mapping_item_offset(items)?;
+        }
+
+        let mut table = self.topic_queue_mapping_table.lock();
+        if let Some(old_detail) = table.get(&topic) {
+            if !force {
+                check_mapping_update(old_detail, &new_detail)?;
+            }
+            // keep the items of the queues the new mapping does not mention
+            let hosted_queues = new_detail.hosted_queues.get_or_insert_with(HashMap::new);
+            for (global_id, items) in old_detail.hosted_queues.iter().flatten() {
+                hosted_queues
+                    .entry(*global_id)
+                    .or_insert_with(|| items.clone());
+            }
+        }
+        table.insert(topic, new_detail);
+        drop(table);
+
+        self.data_version.lock().next_version();
+        if flush {
+            self.persist();
+        }
+        Ok(())
+    }
+
     pub fn get_topic_queue_mapping(&self, topic: &str) -> Option<TopicQueueMappingDetail> {
         self.topic_queue_mapping_table.lock().get(topic).cloned()
     }
@@ -215,6 +266,48 @@ impl TopicQueueMappingManager {
     }
 }
 
+fn check_mapping_update(
+    old_detail: &TopicQueueMappingDetail,
+    new_detail: &TopicQueueMappingDetail,
+) -> rocketmq_remoting::Result<()> {
+    let old_info = &old_detail.topic_queue_mapping_info;
+    let new_info = &new_detail.topic_queue_mapping_info;
+    if new_info.epoch < old_info.epoch {
+        return Err(illegal(format!(
+            "Can't accept data with small epoch {} < {}",
+            new_info.epoch, old_info.epoch
+        )));
+    }
+    if new_info.scope != old_info.scope {
+        return Err(illegal(format!(
+            "Can't accept data with unmatched scope {:?} vs {:?}",
+            new_info.scope, old_info.scope
+        )));
+    }
+    let Some(new_queues) = new_detail.hosted_queues.as_ref() else {
+        return Ok(());
+    };
+    for (global_id, old_items) in old_detail.hosted_queues.iter().flatten() {
+        let (Some(old_leader), Some(new_leader)) = (
+            old_items.last(),
+            new_queues.get(global_id).and_then(|items| items.last()),
+        ) else {
+            continue;
+        };
+        if new_leader.gen < old_leader.gen {
+            return Err(illegal(format!(
+                "The new leader gen {} of queue {} is smaller than the old one {}",
+                new_leader.gen, global_id, old_leader.gen
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn illegal(reason: String) -> RemotingError {
+    RemotingError::RemotingCommandException(reason)
+}
+
 //Fully implemented will be removed
 impl ConfigManager for TopicQueueMappingManager {
     fn config_file_path(&self) -> String {
@@ -256,6 +349,10 @@ mod tests {
     use std::sync::Arc;
 
     use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_remoting::protocol::header::namesrv::topic_operation_header::TopicRequestHeader;
+    use rocketmq_remoting::protocol::header::pull_message_request_header::PullMessageRequestHeader;
+    use rocketmq_remoting::protocol::static_topic::logic_queue_mapping_item::LogicQueueMappingItem;
+    use rocketmq_remoting::protocol::static_topic::topic_queue_info::TopicQueueMappingInfo;
 
     use super::*;
 
@@ -306,4 +403,179 @@ mod tests {
 
         assert!(manager.get_topic_queue_mapping("existing_topic").is_none());
     }
+
+    const STATIC_TOPIC: &str = "static_topic";
+
+    fn broker_manager(broker_name: &str, dir: &tempfile::TempDir) -> TopicQueueMappingManager {
+        TopicQueueMappingManager::new(Arc::new(BrokerConfig {
+            broker_name: CheetahString::from_slice(broker_name),
+            store_path_root_dir: CheetahString::from_string(
+                dir.path().to_string_lossy().to_string(),
+            ),
+            ..BrokerConfig::default()
+        }))
+    }
+
+    fn mapping_item(
+        gen: i32,
+        bname: &str,
+        logic_offset: i64,
+        end_offset: i64,
+    ) -> LogicQueueMappingItem {
+        LogicQueueMappingItem {
+            gen,
+            queue_id: 0,
+            bname: Some(CheetahString::from_slice(bname)),
+            logic_offset,
+            end_offset,
+            ..LogicQueueMappingItem::default()
+        }
+    }
+
+    /// Logic queue 0 was first hosted by broker-a, then moved to broker-b at logic offset 101.
+    fn moved_queue_detail(bname: &str, epoch: i64) -> TopicQueueMappingDetail {
+        TopicQueueMappingDetail {
+            topic_queue_mapping_info: TopicQueueMappingInfo {
+                topic: Some(CheetahString::from_static_str(STATIC_TOPIC)),
+                total_queues: 1,
+                bname: Some(CheetahString::from_slice(bname)),
+                epoch,
+                ..TopicQueueMappingInfo::default()
+            },
+            hosted_queues: Some(HashMap::from([(
+                0,
+                vec![
+                    mapping_item(0, "broker-a", 0, 100),
+                    mapping_item(1, "broker-b", 101, -1),
+                ],
+            )])),
+        }
+    }
+
+    fn pull_header(queue_offset: i64) -> PullMessageRequestHeader {
+        PullMessageRequestHeader {
+            topic: CheetahString::from_static_str(STATIC_TOPIC),
+            queue_id: Some(0),
+            queue_offset,
+            topic_request: Some(TopicRequestHeader::default()),
+            ..PullMessageRequestHeader::default()
+        }
+    }
+
+    #[test]
+    fn static_topic_offsets_are_remapped_across_brokers() {
+        let dir_a = tempfile::tempdir().unwrap();
+        let dir_b = tempfile::tempdir().unwrap();
+        let broker_a = broker_manager("broker-a", &dir_a);
+        let broker_b = broker_manager("broker-b", &dir_b);
+        broker_a
+            .update_topic_queue_mapping(moved_queue_detail("broker-a", 1), false, true)
+            .unwrap();
+        broker_b
+            .update_topic_queue_mapping(moved_queue_detail("broker-b", 1), false, true)
+            .unwrap();
+
+        // broker-b leads the logic queue and serves offsets past 101 from its physical queue
+        let context = broker_b.build_topic_queue_mapping_context(&pull_header(130), false);
+        assert!(context.is_leader());
+        let item = TopicQueueMappingUtils::find_logic_queue_mapping_item(
+            &context.mapping_item_list,
+            130,
+            true,
+        )
+        .unwrap();
+        assert_eq!(item.bname.as_deref(), Some("broker-b"));
+        assert_eq!(item.compute_physical_queue_offset(130), 29);
+        assert_eq!(item.compute_static_queue_offset_strictly(29), 130);
+
+        // older offsets still live on broker-a
+        let item = TopicQueueMappingUtils::find_logic_queue_mapping_item(
+            &context.mapping_item_list,
+            42,
+            true,
+        )
+        .unwrap();
+        assert_eq!(item.bname.as_deref(), Some("broker-a"));
+        assert_eq!(item.compute_physical_queue_offset(42), 42);
+        assert_eq!(
+            TopicQueueMappingDetail::compute_max_offset_from_mapping(
+                context.mapping_detail.as_ref().unwrap(),
+                Some(0)
+            ),
+            101
+        );
+
+        // broker-a no longer leads, sends are redirected
+        let mut send_header = pull_header(0);
+        let context = broker_a.build_topic_queue_mapping_context(&send_header, true);
+        assert!(!context.is_leader());
+        let response =
+            TopicQueueMappingManager::rewrite_request_for_static_topic(&mut send_header, &context)
+                .unwrap();
+        assert_eq!(
+            ResponseCode::from(response.code()),
+            ResponseCode::NotLeaderForQueue
+        );
+
+        // the mapping survives a restart
+        let reloaded = broker_manager("broker-b", &dir_b);
+        assert!(reloaded.load());
+        assert_eq!(
+            reloaded.get_topic_queue_mapping(STATIC_TOPIC),
+            Some(moved_queue_detail("broker-b", 1))
+        );
+        assert_eq!(reloaded.data_version.lock().get_counter(), 1);
+    }
+
+    #[test]
+    fn update_rejects_stale_mapping_unless_forced() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = broker_manager("broker-b", &dir);
+        manager
+            .update_topic_queue_mapping(moved_queue_detail("broker-b", 2), false, false)
+            .unwrap();
+
+        assert!(manager
+            .update_topic_queue_mapping(moved_queue_detail("broker-b", 1), false, false)
+            .is_err());
+        assert!(manager
+            .update_topic_queue_mapping(moved_queue_detail("broker-a", 3), true, false)
+            .is_err());
+
+        let mut demoted = moved_queue_detail("broker-b", 2);
+        demoted.hosted_queues = Some(HashMap::from([(
+            0,
+            vec![mapping_item(0, "broker-b", 0, -1)],
+        )]));
+        assert!(manager
+            .update_topic_queue_mapping(demoted.clone(), false, false)
+            .is_err());
+        manager
+            .update_topic_queue_mapping(demoted.clone(), true, false)
+            .unwrap();
+        assert_eq!(manager.get_topic_queue_mapping(STATIC_TOPIC), Some(demoted));
+    }
+
+    #[test]
+    fn update_keeps_queues_missing_from_new_mapping() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = broker_manager("broker-b", &dir);
+        manager
+            .update_topic_queue_mapping(moved_queue_detail("broker-b", 1), false, false)
+            .unwrap();
+
+        let mut other_queue = moved_queue_detail("broker-b", 2);
+        other_queue.hosted_queues = Some(HashMap::from([(
+            1,
+            vec![mapping_item(0, "broker-b", 0, -1)],
+        )]));
+        manager
+            .update_topic_queue_mapping(other_queue, false, false)
+            .unwrap();
+        let detail = manager.get_topic_queue_mapping(STATIC_TOPIC).unwrap();
+        let hosted_queues = detail.hosted_queues.unwrap();
+        assert_eq!(hosted_queues.len(), 2);
+        assert_eq!(hosted_queues[&0].len(), 2);
+        assert_eq!(detail.topic_queue_mapping_info.epoch, 2);
+    }
 }
@@ -0,0 +1,119 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::constant::PermName;
+use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_remoting::protocol::route::topic_route_data::TopicRouteData;
+use tracing::info;
+use tracing::warn;
+
+use crate::out_api::broker_outer_api::BrokerOuterAPI;
+
+const GET_TOPIC_ROUTE_TIMEOUT: u64 = 3000;
+
+/// Caches the readable queues of the topics whose assignment the broker computes, as the name
+/// server reports them across every broker of the cluster.
+pub(crate) struct TopicRouteInfoManager {
+    broker_outer_api: Arc<BrokerOuterAPI>,
+    topic_subscribe_info_table:
+        parking_lot::RwLock<HashMap<CheetahString /* topic */, HashSet<MessageQueue>>>,
+}
+
+impl TopicRouteInfoManager {
+    pub(crate) fn new(broker_outer_api: Arc<BrokerOuterAPI>) -> Self {
+        Self {
+            broker_outer_api,
+            topic_subscribe_info_table: parking_lot::RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The readable queues of `topic`, fetched from the name server on the first request.
+    pub(crate) async fn get_topic_subscribe_info(
+        &self,
+        topic: &CheetahString,
+    ) -> Option<HashSet<MessageQueue>> {
+        let cached = self.topic_subscribe_info_table.read().get(topic).cloned();
+        if cached.is_some() {
+            return cached;
+        }
+        self.update_topic_route_info_from_name_server_topic(topic)
+            .await;
+        self.topic_subscribe_info_table.read().get(topic).cloned()
+    }
+
+    /// Refreshes every cached topic, dropping the ones the name server no longer knows.
+    pub(crate) async fn update_topic_route_info_from_name_server(&self) {
+        let topics = self
+            .topic_subscribe_info_table
+            .read()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        for topic in topics {
+            self.update_topic_route_info_from_name_server_topic(&topic)
+                .await;
+        }
+    }
+
+    async fn update_topic_route_info_from_name_server_topic(&self, topic: &CheetahString) {
+        match self
+            .broker_outer_api
+            .get_topic_route_info_from_name_server(topic, GET_TOPIC_ROUTE_TIMEOUT)
+            .await
+        {
+            Ok(route) => self.update_topic_subscribe_info(topic, route.as_ref()),
+            Err(e) => warn!(
+                "get topic route of {} from name server failed: {}",
+                topic, e
+            ),
+        }
+    }
+
+    pub(crate) fn update_topic_subscribe_info(
+        &self,
+        topic: &CheetahString,
+        route: Option<&TopicRouteData>,
+    ) {
+        let mut table = self.topic_subscribe_info_table.write();
+        let Some(route) = route else {
+            if table.remove(topic).is_some() {
+                info!("topic {} removed from the subscribe info table", topic);
+            }
+            return;
+        };
+        let mut mq_set = HashSet::new();
+        for queue_data in &route.queue_datas {
+            if PermName::is_readable(queue_data.perm) {
+                for queue_id in 0..queue_data.read_queue_nums {
+                    mq_set.insert(MessageQueue::from_parts(
+                        topic.clone(),
+                        queue_data.broker_name.clone(),
+                        queue_id as i32,
+                    ));
+                }
+            }
+        }
+        if table.get(topic) != Some(&mq_set) {
+            info!("topic subscribe info of {} changed: {:?}", topic, mq_set);
+            table.insert(topic.clone(), mq_set);
+        }
+    }
+}
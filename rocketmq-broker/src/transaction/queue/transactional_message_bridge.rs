@@ -19,7 +19,6 @@ use std::collections::HashSet;
 use std::net::SocketAddr;
 use std::sync::Arc;
 
-use bytes::Bytes;
 use cheetah_string::CheetahString;
 use rocketmq_client_rust::consumer::pull_result::PullResult;
 use rocketmq_client_rust::consumer::pull_status::PullStatus;
@@ -76,8 +75,8 @@ where
         broker_config: Arc<BrokerConfig>,
         topic_config_manager: TopicConfigManager,
     ) -> Self {
-        let store_host = format!("{}:{}", broker_config.broker_ip1, broker_config.listen_port)
-            .parse::<SocketAddr>()
+        let store_host = broker_config
+            .get_store_host()
             .expect("parse store host failed");
         Self {
             op_queue_map: Arc::new(Mutex::new(HashMap::new())),
@@ -230,9 +229,9 @@ where
     fn decode_msg_list(get_message_result: &GetMessageResult) -> Vec<MessageClientExt> {
         let mut found_list = Vec::new();
         for bb in get_message_result.message_mapped_list() {
-            let data = &bb.mapped_file.as_ref().unwrap().get_mapped_file()
-                [bb.start_offset as usize..(bb.start_offset + bb.size as u64) as usize];
-            let mut bytes = Bytes::copy_from_slice(data);
+            let Some(mut bytes) = bb.get_bytes() else {
+                continue;
+            };
             let msg_ext = message_decoder::decode_client(&mut bytes, true, false, false, false);
             if let Some(msg_ext) = msg_ext {
                 found_list.push(msg_ext);
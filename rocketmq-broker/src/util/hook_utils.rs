@@ -188,8 +188,10 @@ impl HookUtils {
                     return Some(transform_res);
                 }
             }
-            // Delay Delivery
-            if msg.message_ext_inner.message.get_delay_time_level() > 0 {
+            // Delay Delivery, only once the schedule service knows its delay levels
+            if msg.message_ext_inner.message.get_delay_time_level() > 0
+                && schedule_message_service.get_max_delay_level() > 0
+            {
                 Self::transform_delay_level_message(schedule_message_service, msg);
             }
         }
@@ -15,9 +15,14 @@
  * limitations under the License.
  */
 use clap::Parser;
+use rocketmq_cli::clone_group_offset::clone_group_offset;
 use rocketmq_cli::command_line::Commands;
 use rocketmq_cli::command_line::RootCli;
 use rocketmq_cli::content_show::print_content;
+use rocketmq_cli::print_message_by_queue::print_msg_by_queue;
+use rocketmq_cli::print_message_by_queue::PrintMsgByQueue;
+use rocketmq_cli::query_message::query_msg_by_id;
+use rocketmq_cli::query_message::query_msg_by_unique_key;
 
 fn main() {
     let cli = RootCli::parse();
@@ -25,5 +30,47 @@ fn main() {
         Commands::ReadMessageLog { config, from, to } => {
             print_content(from, to, config);
         }
+        Commands::QueryMsgById {
+            msg_id,
+            topic,
+            namesrv_addr,
+        } => query_msg_by_id(namesrv_addr, topic, msg_id),
+        Commands::QueryMsgByUniqueKey {
+            unique_key,
+            topic,
+            namesrv_addr,
+        } => query_msg_by_unique_key(namesrv_addr, topic, unique_key),
+        Commands::PrintMsgByQueue {
+            topic,
+            broker_name,
+            queue_id,
+            sub_expression,
+            key,
+            begin_timestamp,
+            end_timestamp,
+            print_body,
+            charset,
+            count_only,
+            namesrv_addr,
+        } => print_msg_by_queue(PrintMsgByQueue {
+            namesrv_addr,
+            topic,
+            broker_name,
+            queue_id,
+            sub_expression,
+            key,
+            begin_timestamp,
+            end_timestamp,
+            print_body,
+            charset,
+            count_only,
+        }),
+        Commands::CloneGroupOffset {
+            src_group,
+            dest_group,
+            topic,
+            offline,
+            namesrv_addr,
+        } => clone_group_offset(namesrv_addr, src_group, dest_group, topic, offline),
     }
 }
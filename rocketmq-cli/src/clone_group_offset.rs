@@ -0,0 +1,60 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use rocketmq_client_rust::admin::default_mq_admin_ext::DefaultMQAdminExt;
+use rocketmq_client_rust::base::client_config::ClientConfig;
+
+const CLONE_TIMEOUT_MILLIS: u64 = 5_000;
+
+/// Lets `dest_group` start consuming `topic` where `src_group` is, on every broker.
+pub fn clone_group_offset(
+    namesrv_addr: Option<String>,
+    src_group: String,
+    dest_group: String,
+    topic: String,
+    offline: bool,
+) {
+    let runtime = tokio::runtime::Runtime::new().expect("create runtime failed");
+    runtime.block_on(async move {
+        let mut client_config = ClientConfig::default();
+        if let Some(namesrv_addr) = namesrv_addr {
+            client_config.namesrv_addr = Some(CheetahString::from_string(namesrv_addr));
+        }
+        let mut admin = DefaultMQAdminExt::new(client_config);
+        if let Err(err) = admin.start().await {
+            println!("start admin failed: {}", err);
+            return;
+        }
+        let result = admin
+            .clone_group_offset(
+                &src_group,
+                &dest_group,
+                Some(&topic),
+                offline,
+                CLONE_TIMEOUT_MILLIS,
+            )
+            .await;
+        admin.shutdown().await;
+        match result {
+            Ok(()) => println!(
+                "clone group offset success. srcGroup[{}], destGroup=[{}], topic[{}]",
+                src_group, dest_group, topic
+            ),
+            Err(err) => println!("clone group offset failed: {}", err),
+        }
+    });
+}
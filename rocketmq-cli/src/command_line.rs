@@ -64,4 +64,180 @@ pub enum Commands {
         )]
         to: Option<u32>,
     },
+
+    #[command(
+        arg_required_else_help = true,
+        author = "mxsm",
+        version = "0.2.0",
+        about = "query a message by its offset message id"
+    )]
+    QueryMsgById {
+        #[arg(short = 'i', long, value_name = "MSG_ID", help = "offset message id")]
+        msg_id: String,
+
+        #[arg(short = 't', long, value_name = "TOPIC", help = "topic of the message")]
+        topic: Option<String>,
+
+        #[arg(
+            short = 'n',
+            long,
+            value_name = "NAMESRV_ADDR",
+            help = "name server address list, eg: '192.168.0.1:9876;192.168.0.2:9876'"
+        )]
+        namesrv_addr: Option<String>,
+    },
+
+    #[command(
+        arg_required_else_help = true,
+        author = "mxsm",
+        version = "0.2.0",
+        about = "query a message by the unique key assigned by its producer"
+    )]
+    QueryMsgByUniqueKey {
+        #[arg(
+            short = 'i',
+            long,
+            value_name = "UNIQUE_KEY",
+            help = "unique key of the message"
+        )]
+        unique_key: String,
+
+        #[arg(short = 't', long, value_name = "TOPIC", help = "topic of the message")]
+        topic: String,
+
+        #[arg(
+            short = 'n',
+            long,
+            value_name = "NAMESRV_ADDR",
+            help = "name server address list, eg: '192.168.0.1:9876;192.168.0.2:9876'"
+        )]
+        namesrv_addr: Option<String>,
+    },
+
+    #[command(
+        arg_required_else_help = true,
+        author = "mxsm",
+        version = "0.2.0",
+        about = "print the messages a queue stored between two points in time"
+    )]
+    PrintMsgByQueue {
+        #[arg(short = 't', long, value_name = "TOPIC", help = "topic of the queue")]
+        topic: String,
+
+        #[arg(
+            short = 'a',
+            long,
+            value_name = "BROKER_NAME",
+            help = "broker of the queue"
+        )]
+        broker_name: String,
+
+        #[arg(short = 'i', long, value_name = "QUEUE_ID", help = "id of the queue")]
+        queue_id: i32,
+
+        #[arg(
+            short = 's',
+            long,
+            value_name = "SUB_EXPRESSION",
+            default_value = "*",
+            help = "tags of the messages to print, eg: 'TagA || TagB'"
+        )]
+        sub_expression: String,
+
+        #[arg(
+            short = 'k',
+            long,
+            value_name = "KEY",
+            help = "only print the messages carrying this key"
+        )]
+        key: Option<String>,
+
+        #[arg(
+            short = 'b',
+            long,
+            value_name = "BEGIN_TIMESTAMP",
+            help = "begin of the time range in milliseconds or as yyyy-MM-dd#HH:mm:ss:SSS, \
+                    defaults to the first message"
+        )]
+        begin_timestamp: Option<String>,
+
+        #[arg(
+            short = 'e',
+            long,
+            value_name = "END_TIMESTAMP",
+            help = "end of the time range in milliseconds or as yyyy-MM-dd#HH:mm:ss:SSS, defaults \
+                    to now"
+        )]
+        end_timestamp: Option<String>,
+
+        #[arg(short = 'd', long, help = "print the message body")]
+        print_body: bool,
+
+        #[arg(
+            short = 'c',
+            long,
+            value_name = "CHARSET",
+            default_value = "UTF-8",
+            help = "charset of the message body"
+        )]
+        charset: String,
+
+        #[arg(
+            short = 'f',
+            long,
+            help = "only count the messages of each tag instead of printing them"
+        )]
+        count_only: bool,
+
+        #[arg(
+            short = 'n',
+            long,
+            value_name = "NAMESRV_ADDR",
+            help = "name server address list, eg: '192.168.0.1:9876;192.168.0.2:9876'"
+        )]
+        namesrv_addr: Option<String>,
+    },
+
+    #[command(
+        arg_required_else_help = true,
+        author = "mxsm",
+        version = "0.2.0",
+        about = "clone the consume offsets of a group to another group"
+    )]
+    CloneGroupOffset {
+        #[arg(
+            short = 's',
+            long,
+            value_name = "SRC_GROUP",
+            help = "group to copy the offsets of"
+        )]
+        src_group: String,
+
+        #[arg(
+            short = 'd',
+            long,
+            value_name = "DEST_GROUP",
+            help = "group to copy the offsets to"
+        )]
+        dest_group: String,
+
+        #[arg(short = 't', long, value_name = "TOPIC", help = "topic of the offsets")]
+        topic: String,
+
+        #[arg(
+            short = 'o',
+            long,
+            help = "the source group is offline, clone even if it no longer subscribes to the \
+                    topic"
+        )]
+        offline: bool,
+
+        #[arg(
+            short = 'n',
+            long,
+            value_name = "NAMESRV_ADDR",
+            help = "name server address list, eg: '192.168.0.1:9876;192.168.0.2:9876'"
+        )]
+        namesrv_addr: Option<String>,
+    },
 }
@@ -15,5 +15,8 @@
  * limitations under the License.
  */
 
+pub mod clone_group_offset;
 pub mod command_line;
 pub mod content_show;
+pub mod print_message_by_queue;
+pub mod query_message;
@@ -0,0 +1,442 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+use cheetah_string::CheetahString;
+use chrono::Local;
+use chrono::TimeZone;
+use encoding_rs::Encoding;
+use rocketmq_client_rust::admin::default_mq_admin_ext::DefaultMQAdminExt;
+use rocketmq_client_rust::base::client_config::ClientConfig;
+use rocketmq_client_rust::consumer::pull_status::PullStatus;
+use rocketmq_common::common::message::message_client_ext::MessageClientExt;
+use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::utils::util_all;
+use rocketmq_common::TimeUtils::get_current_millis;
+
+const PULL_BATCH_SIZE: i32 = 32;
+const TIMESTAMP_PATTERN: &str = "%Y-%m-%d#%H:%M:%S:%3f";
+
+/// Dumps the messages a queue stored between two points in time.
+pub struct PrintMsgByQueue {
+    pub namesrv_addr: Option<String>,
+    pub topic: String,
+    pub broker_name: String,
+    pub queue_id: i32,
+    pub sub_expression: String,
+    /// Only messages carrying this key are reported.
+    pub key: Option<String>,
+    pub begin_timestamp: Option<String>,
+    pub end_timestamp: Option<String>,
+    pub print_body: bool,
+    pub charset: String,
+    /// Reports how many messages there are of each tag instead of the messages.
+    pub count_only: bool,
+}
+
+/// How many messages were seen of each tag, listed from the most common tag.
+#[derive(Debug, Default, PartialEq)]
+pub struct TagTally(HashMap<String, u64>);
+
+impl TagTally {
+    pub fn add(&mut self, msg: &MessageClientExt) {
+        let tag = msg.get_tags().unwrap_or_default().to_string();
+        *self.0.entry(tag).or_default() += 1;
+    }
+
+    pub fn count(&self, tag: &str) -> u64 {
+        self.0.get(tag).copied().unwrap_or_default()
+    }
+}
+
+impl Display for TagTally {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut counts = self.0.iter().collect::<Vec<_>>();
+        counts.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        for (tag, count) in counts {
+            writeln!(f, "Tag: {:<30} Count: {}", tag, count)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn print_msg_by_queue(command: PrintMsgByQueue) {
+    let Some(charset) = Encoding::for_label(command.charset.as_bytes()) else {
+        println!("unknown charset: {}", command.charset);
+        return;
+    };
+    let begin = match parse_timestamp(command.begin_timestamp.as_deref(), 0) {
+        Ok(begin) => begin,
+        Err(err) => return println!("{}", err),
+    };
+    let end = match parse_timestamp(
+        command.end_timestamp.as_deref(),
+        get_current_millis() as i64,
+    ) {
+        Ok(end) => end,
+        Err(err) => return println!("{}", err),
+    };
+    let mq = MessageQueue::from_parts(
+        command.topic.as_str(),
+        command.broker_name.as_str(),
+        command.queue_id,
+    );
+    let key = command.key.clone();
+    let filter = move |msg: &MessageClientExt| match &key {
+        Some(key) => msg
+            .get_keys()
+            .is_some_and(|keys| keys.split(' ').any(|k| k == key)),
+        None => true,
+    };
+
+    let runtime = tokio::runtime::Runtime::new().expect("create runtime failed");
+    runtime.block_on(async move {
+        let mut client_config = ClientConfig::default();
+        if let Some(namesrv_addr) = command.namesrv_addr {
+            client_config.namesrv_addr = Some(CheetahString::from_string(namesrv_addr));
+        }
+        let mut admin = DefaultMQAdminExt::new(client_config);
+        if let Err(err) = admin.start().await {
+            println!("start admin failed: {}", err);
+            return;
+        }
+        let mut tally = TagTally::default();
+        let result = scan_queue(
+            &mut admin,
+            &mq,
+            &command.sub_expression,
+            begin,
+            end,
+            filter,
+            |msg| {
+                if command.count_only {
+                    tally.add(msg);
+                } else {
+                    println!("{}", format_row(msg, command.print_body, charset));
+                }
+            },
+        )
+        .await;
+        admin.shutdown().await;
+        match result {
+            Ok(()) if command.count_only => print!("{}", tally),
+            Ok(()) => {}
+            Err(err) => println!("print message by queue failed: {}", err),
+        }
+    });
+}
+
+/// Pulls the messages of `mq` stored from `begin` until `end`, both in milliseconds, and hands
+/// those whose tag matches `sub_expression` and that `filter` accepts to `sink`.
+pub async fn scan_queue<F, S>(
+    admin: &mut DefaultMQAdminExt,
+    mq: &MessageQueue,
+    sub_expression: &str,
+    begin: i64,
+    end: i64,
+    filter: F,
+    mut sink: S,
+) -> rocketmq_client_rust::Result<()>
+where
+    F: Fn(&MessageClientExt) -> bool,
+    S: FnMut(&MessageClientExt),
+{
+    let min_offset = admin.search_offset(mq, begin.max(0) as u64).await?;
+    let max_offset = admin.search_offset(mq, end.max(0) as u64).await?;
+    let mut offset = min_offset;
+    while offset < max_offset {
+        let pull_result = admin
+            .pull_message(mq, sub_expression, offset, PULL_BATCH_SIZE)
+            .await?;
+        let next_offset = pull_result.next_begin_offset as i64;
+        match pull_result.pull_status {
+            PullStatus::Found => {
+                for msg in pull_result.msg_found_list.iter() {
+                    if msg.message_ext_inner.queue_offset >= max_offset {
+                        return Ok(());
+                    }
+                    if filter(msg) {
+                        sink(msg);
+                    }
+                }
+            }
+            // a batch without a matching tag still moves the offset forward
+            PullStatus::NoMatchedMsg if next_offset > offset => {}
+            _ => break,
+        }
+        offset = next_offset;
+    }
+    Ok(())
+}
+
+/// One line per message: its id, store time, tags and keys, then the body decoded with
+/// `charset` if asked for.
+pub fn format_row(msg: &MessageClientExt, print_body: bool, charset: &'static Encoding) -> String {
+    let mut row = format!(
+        "MSGID: {} STORE_TIME: {} TAGS: [{}] KEYS: [{}]",
+        msg.get_msg_id(),
+        util_all::time_millis_to_human_string2(msg.message_ext_inner.store_timestamp),
+        msg.get_tags().unwrap_or_default(),
+        msg.get_keys().unwrap_or_default(),
+    );
+    if print_body {
+        let body = msg
+            .get_body()
+            .map(|body| charset.decode(body).0.to_string())
+            .unwrap_or_default();
+        row.push_str(" BODY: ");
+        row.push_str(&body);
+    }
+    row
+}
+
+/// Milliseconds given as a number or as local time in the `yyyy-MM-dd#HH:mm:ss:SSS` format
+/// of the Java tools, `default` when absent.
+pub fn parse_timestamp(value: Option<&str>, default: i64) -> Result<i64, String> {
+    let Some(value) = value else {
+        return Ok(default);
+    };
+    if let Ok(millis) = value.parse::<i64>() {
+        return Ok(millis);
+    }
+    util_all::parse_date(value, TIMESTAMP_PATTERN)
+        .and_then(|date| Local.from_local_datetime(&date).earliest())
+        .map(|date| date.timestamp_millis())
+        .ok_or_else(|| format!("illegal timestamp: {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bytes::BytesMut;
+    use rocketmq_common::common::message::message_decoder;
+    use rocketmq_common::common::message::message_ext::MessageExt;
+    use rocketmq_common::common::message::MessageConst;
+    use rocketmq_common::common::mix_all;
+    use rocketmq_remoting::code::request_code::RequestCode;
+    use rocketmq_remoting::code::response_code::ResponseCode;
+    use rocketmq_remoting::protocol::header::pull_message_request_header::PullMessageRequestHeader;
+    use rocketmq_remoting::protocol::header::pull_message_response_header::PullMessageResponseHeader;
+    use rocketmq_remoting::protocol::header::search_offset_request_header::SearchOffsetRequestHeader;
+    use rocketmq_remoting::protocol::header::search_offset_response_header::SearchOffsetResponseHeader;
+    use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+    use rocketmq_remoting::test_util::FakeBroker;
+
+    use super::*;
+
+    const TOPIC: &str = "ScanTopic";
+    const TAGS: [&str; 3] = ["TagA", "TagB", "TagC"];
+
+    /// Messages of a single queue of `TOPIC` stored every second by the broker at `addr`, with
+    /// tags cycling through `TAGS`.
+    fn stored_messages(addr: &CheetahString, count: i64) -> Vec<MessageExt> {
+        (0..count)
+            .map(|i| {
+                let mut msg = MessageExt::default();
+                msg.set_topic(TOPIC.into());
+                msg.set_body(bytes::Bytes::from(format!("body-{i}")));
+                msg.put_property(
+                    CheetahString::from_static_str(MessageConst::PROPERTY_TAGS),
+                    CheetahString::from_static_str(TAGS[i as usize % TAGS.len()]),
+                );
+                msg.put_property(
+                    CheetahString::from_static_str(MessageConst::PROPERTY_KEYS),
+                    CheetahString::from_string(format!("order-{} seq-{i}", i % 2)),
+                );
+                msg.queue_offset = i;
+                msg.commit_log_offset = i * 100;
+                msg.store_timestamp = i * 1000;
+                msg.store_host = addr.parse().unwrap();
+                msg.born_host = addr.parse().unwrap();
+                msg
+            })
+            .collect()
+    }
+
+    fn search_offset(stored: &[MessageExt], request: &RemotingCommand) -> RemotingCommand {
+        let request_header = request
+            .decode_command_custom_header::<SearchOffsetRequestHeader>()
+            .unwrap();
+        let offset = stored
+            .iter()
+            .find(|msg| msg.store_timestamp >= request_header.timestamp)
+            .map_or(stored.len() as i64, |msg| msg.queue_offset);
+        RemotingCommand::create_response_command_with_header(SearchOffsetResponseHeader { offset })
+    }
+
+    fn pull(stored: &[MessageExt], request: &RemotingCommand) -> RemotingCommand {
+        let request_header = request
+            .decode_command_custom_header::<PullMessageRequestHeader>()
+            .unwrap();
+        assert_eq!(request_header.consumer_group, mix_all::TOOLS_CONSUMER_GROUP);
+        assert_eq!(request_header.commit_offset, 0);
+        let from = request_header.queue_offset as usize;
+        let batch = stored
+            .iter()
+            .skip(from)
+            .take(request_header.max_msg_nums as usize);
+        let mut body = BytesMut::new();
+        for msg in batch {
+            body.extend_from_slice(&message_decoder::encode(msg, false).unwrap());
+        }
+        let next_begin_offset =
+            (from + request_header.max_msg_nums as usize).min(stored.len()) as i64;
+        let response_header = PullMessageResponseHeader {
+            next_begin_offset: Some(next_begin_offset),
+            min_offset: Some(0),
+            max_offset: Some(stored.len() as i64),
+            ..Default::default()
+        };
+        if body.is_empty() {
+            return RemotingCommand::create_response_command_with_header(response_header)
+                .set_code(ResponseCode::PullNotFound);
+        }
+        RemotingCommand::create_response_command_with_header(response_header)
+            .set_body(body.freeze())
+    }
+
+    async fn tally(
+        admin: &mut DefaultMQAdminExt,
+        sub_expression: &str,
+        key: Option<&str>,
+        begin: i64,
+        end: i64,
+    ) -> TagTally {
+        let mq = MessageQueue::from_parts(TOPIC, "broker-a", 0);
+        let mut tally = TagTally::default();
+        scan_queue(
+            admin,
+            &mq,
+            sub_expression,
+            begin,
+            end,
+            |msg| key.is_none_or(|key| msg.get_keys().unwrap().split(' ').any(|k| k == key)),
+            |msg| tally.add(msg),
+        )
+        .await
+        .unwrap();
+        tally
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn count_only_tallies_the_tags_of_the_time_range() {
+        let broker = FakeBroker::bind().await;
+        let stored = Arc::new(stored_messages(&broker.addr(), 100));
+        let stored_for_pull = stored.clone();
+        let addr = broker
+            .on(RequestCode::SearchOffsetByTimestamp, move |_, request| {
+                search_offset(&stored, request)
+            })
+            .on(RequestCode::PullMessage, move |_, request| {
+                pull(&stored_for_pull, request)
+            })
+            .spawn();
+        let mut admin = DefaultMQAdminExt::new(ClientConfig {
+            client_ip: Some("127.0.0.1".into()),
+            instance_name: "print-msg-by-queue".into(),
+            namesrv_addr: Some(addr),
+            vip_channel_enabled: false,
+            ..Default::default()
+        });
+        admin.start().await.unwrap();
+
+        // offsets 10 to 79, more than two pull batches
+        let all = tally(&mut admin, "*", None, 10_000, 80_000).await;
+        assert_eq!(
+            (all.count("TagA"), all.count("TagB"), all.count("TagC")),
+            (23, 24, 23)
+        );
+        assert_eq!(
+            all.to_string(),
+            format!(
+                "Tag: {:<30} Count: 24\nTag: {:<30} Count: 23\nTag: {:<30} Count: 23\n",
+                "TagB", "TagA", "TagC"
+            )
+        );
+
+        let tagged = tally(&mut admin, "TagA || TagC", None, 10_000, 80_000).await;
+        assert_eq!(
+            (
+                tagged.count("TagA"),
+                tagged.count("TagB"),
+                tagged.count("TagC")
+            ),
+            (23, 0, 23)
+        );
+
+        // offsets 2 to 6 carrying the key order-0: 2, 4 and 6
+        let keyed = tally(&mut admin, "*", Some("order-0"), 2_000, 7_000).await;
+        assert_eq!(
+            (
+                keyed.count("TagA"),
+                keyed.count("TagB"),
+                keyed.count("TagC")
+            ),
+            (1, 1, 1)
+        );
+
+        let empty = tally(&mut admin, "*", None, 200_000, 300_000).await;
+        assert_eq!(empty, TagTally::default());
+        admin.shutdown().await;
+    }
+
+    #[test]
+    fn timestamps_are_millis_or_java_tools_dates() {
+        assert_eq!(parse_timestamp(None, 42), Ok(42));
+        assert_eq!(
+            parse_timestamp(Some("1700000000000"), 0),
+            Ok(1_700_000_000_000)
+        );
+        let date = Local
+            .with_ymd_and_hms(2024, 3, 2, 23, 39, 45)
+            .unwrap()
+            .timestamp_millis()
+            + 296;
+        assert_eq!(
+            parse_timestamp(Some("2024-03-02#23:39:45:296"), 0),
+            Ok(date)
+        );
+        assert!(parse_timestamp(Some("yesterday"), 0).is_err());
+    }
+
+    #[test]
+    fn row_prints_the_body_in_the_given_charset() {
+        let mut msg = MessageClientExt::default();
+        msg.message_ext_inner
+            .set_body(bytes::Bytes::from_static(b"caf\xe9"));
+        msg.message_ext_inner.store_timestamp = 0;
+        msg.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_TAGS),
+            CheetahString::from_static_str("TagA"),
+        );
+        msg.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX),
+            CheetahString::from_static_str("0A0C14686EC018B4AAC20A3A2F1001F4"),
+        );
+        let latin1 = Encoding::for_label(b"ISO-8859-1").unwrap();
+        assert_eq!(
+            format_row(&msg, true, latin1),
+            "MSGID: 0A0C14686EC018B4AAC20A3A2F1001F4 STORE_TIME: 1970-01-01 00:00:00,000 TAGS: \
+             [TagA] KEYS: [] BODY: café"
+        );
+        assert!(!format_row(&msg, false, latin1).contains("BODY"));
+    }
+}
@@ -0,0 +1,123 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fmt::Write;
+use std::future::Future;
+
+use cheetah_string::CheetahString;
+use rocketmq_client_rust::admin::default_mq_admin_ext::DefaultMQAdminExt;
+use rocketmq_client_rust::base::client_config::ClientConfig;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::common::message::STRING_HASH_SET;
+use rocketmq_common::utils::util_all;
+
+/// Prints the message stored under an offset message id.
+pub fn query_msg_by_id(namesrv_addr: Option<String>, topic: Option<String>, msg_id: String) {
+    with_admin(namesrv_addr, |mut admin| async move {
+        let topic = topic.unwrap_or_default();
+        let result = admin.view_message(&topic, &msg_id).await;
+        admin.shutdown().await;
+        result
+    });
+}
+
+/// Prints the message a producer sent under a unique key, the latest stored copy if it was
+/// stored more than once.
+pub fn query_msg_by_unique_key(namesrv_addr: Option<String>, topic: String, unique_key: String) {
+    with_admin(namesrv_addr, |mut admin| async move {
+        let result = admin.query_message_by_unique_key(&topic, &unique_key).await;
+        admin.shutdown().await;
+        result
+    });
+}
+
+fn with_admin<F, Fut>(namesrv_addr: Option<String>, query: F)
+where
+    F: FnOnce(DefaultMQAdminExt) -> Fut,
+    Fut: Future<Output = rocketmq_client_rust::Result<MessageExt>>,
+{
+    let runtime = tokio::runtime::Runtime::new().expect("create runtime failed");
+    runtime.block_on(async move {
+        let mut client_config = ClientConfig::default();
+        if let Some(namesrv_addr) = namesrv_addr {
+            client_config.namesrv_addr = Some(CheetahString::from_string(namesrv_addr));
+        }
+        let mut admin = DefaultMQAdminExt::new(client_config);
+        if let Err(err) = admin.start().await {
+            println!("start admin failed: {}", err);
+            return;
+        }
+        match query(admin).await {
+            Ok(msg) => print!("{}", format_message(&msg)),
+            Err(err) => println!("query message failed: {}", err),
+        }
+    });
+}
+
+/// Renders every field of a message, its system properties apart from the user ones.
+pub fn format_message(msg: &MessageExt) -> String {
+    let mut system_properties = Vec::new();
+    let mut user_properties = Vec::new();
+    for (key, value) in msg.get_properties() {
+        if STRING_HASH_SET.contains(key.as_str()) {
+            system_properties.push(format!("{}={}", key, value));
+        } else {
+            user_properties.push(format!("{}={}", key, value));
+        }
+    }
+    system_properties.sort();
+    user_properties.sort();
+
+    let mut out = String::new();
+    let mut line = |name: &str, value: String| {
+        let _ = writeln!(out, "{:<20} {}", format!("{}:", name), value);
+    };
+    line("OffsetID", msg.msg_id.to_string());
+    line("Topic", msg.get_topic().to_string());
+    line("Tags", format!("[{}]", msg.get_tags().unwrap_or_default()));
+    line("Keys", format!("[{}]", msg.get_keys().unwrap_or_default()));
+    line("Queue ID", msg.queue_id.to_string());
+    line("Queue Offset", msg.queue_offset.to_string());
+    line("CommitLog Offset", msg.commit_log_offset.to_string());
+    line("Reconsume Times", msg.reconsume_times.to_string());
+    line(
+        "Born Timestamp",
+        util_all::time_millis_to_human_string2(msg.born_timestamp),
+    );
+    line(
+        "Store Timestamp",
+        util_all::time_millis_to_human_string2(msg.store_timestamp),
+    );
+    line("Born Host", msg.born_host.to_string());
+    line("Store Host", msg.store_host.to_string());
+    line("System Flag", msg.sys_flag.to_string());
+    line(
+        "System Properties",
+        format!("{{{}}}", system_properties.join(", ")),
+    );
+    line(
+        "User Properties",
+        format!("{{{}}}", user_properties.join(", ")),
+    );
+    line(
+        "Message Body",
+        msg.get_body()
+            .map(|body| String::from_utf8_lossy(body).to_string())
+            .unwrap_or_default(),
+    );
+    out
+}
@@ -35,7 +35,7 @@ pub async fn main() -> rocketmq_client_rust::Result<()> {
     let mut producer = builder
         .producer_group(PRODUCER_GROUP.to_string())
         .name_server_addr(DEFAULT_NAMESRVADDR.to_string())
-        .build();
+        .build()?;
     producer.start().await?;
 
     let mut messages = Vec::new();
@@ -45,7 +45,7 @@ pub async fn main() -> Result<()> {
         .consumer_group(CONSUMER_GROUP.to_string())
         .name_server_addr(DEFAULT_NAMESRVADDR.to_string())
         .message_model(MessageModel::Broadcasting)
-        .build();
+        .build()?;
     consumer.subscribe(TOPIC, SUB_EXPRESSION)?;
     consumer.set_consume_from_where(ConsumeFromWhere::ConsumeFromFirstOffset);
     consumer.register_message_listener_concurrently(MyMessageListener);
@@ -60,7 +60,7 @@ impl MessageListenerConcurrently for MyMessageListener {
     fn consume_message(
         &self,
         msgs: &[&MessageExt],
-        _context: &ConsumeConcurrentlyContext,
+        _context: &mut ConsumeConcurrentlyContext,
     ) -> Result<ConsumeConcurrentlyStatus> {
         for msg in msgs {
             info!("Receive message: {:?}", msg);
@@ -48,7 +48,7 @@ pub async fn main() -> Result<()> {
         .consumer_group(CONSUMER_GROUP.to_string())
         .name_server_addr(DEFAULT_NAMESRVADDR.to_string())
         .message_model(MessageModel::Clustering)
-        .build();
+        .build()?;
     consumer.subscribe(TOPIC, TAG)?;
     consumer.set_consume_from_where(ConsumeFromWhere::ConsumeFromFirstOffset);
     consumer.register_message_listener_orderly(MyMessageListener::new());
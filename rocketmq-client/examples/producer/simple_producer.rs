@@ -38,7 +38,7 @@ pub async fn main() -> Result<()> {
     let mut producer = builder
         .producer_group(PRODUCER_GROUP.to_string())
         .name_server_addr(DEFAULT_NAMESRVADDR.to_string())
-        .build();
+        .build()?;
 
     producer.start().await?;
 
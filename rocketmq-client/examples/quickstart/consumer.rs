@@ -41,9 +41,9 @@ pub async fn main() -> Result<()> {
     let mut consumer = builder
         .consumer_group(CONSUMER_GROUP.to_string())
         .name_server_addr(DEFAULT_NAMESRVADDR.to_string())
-        .build();
+        .message_listener_concurrently(MyMessageListener)
+        .build()?;
     consumer.subscribe(TOPIC, "*")?;
-    consumer.register_message_listener_concurrently(MyMessageListener);
     consumer.start().await?;
     let _ = tokio::signal::ctrl_c().await;
     Ok(())
@@ -55,7 +55,7 @@ impl MessageListenerConcurrently for MyMessageListener {
     fn consume_message(
         &self,
         msgs: &[&MessageExt],
-        _context: &ConsumeConcurrentlyContext,
+        _context: &mut ConsumeConcurrentlyContext,
     ) -> Result<ConsumeConcurrentlyStatus> {
         for msg in msgs {
             info!("Receive message: {:?}", msg);
@@ -50,7 +50,7 @@ pub async fn main() -> Result<()> {
         .name_server_addr(DEFAULT_NAMESRVADDR.to_string())
         .topics(vec![TOPIC])
         .transaction_listener(TransactionListenerImpl::default())
-        .build();
+        .build()?;
 
     producer.start().await?;
 
@@ -14,4 +14,5 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+pub mod default_mq_admin_ext;
 pub(crate) mod mq_admin_ext_inner;
@@ -0,0 +1,479 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::filter::expression_type::ExpressionType;
+use rocketmq_common::common::message::message_enum::MessageRequestMode;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::common::mix_all;
+use rocketmq_common::common::sys_flag::pull_sys_flag::PullSysFlag;
+use rocketmq_remoting::protocol::body::set_message_request_mode_request_body::SetMessageRequestModeRequestBody;
+use rocketmq_remoting::protocol::body::topic::topic_list::TopicList;
+use rocketmq_remoting::protocol::filter::filter_api::FilterAPI;
+use rocketmq_remoting::protocol::header::clone_group_offset_request_header::CloneGroupOffsetRequestHeader;
+use rocketmq_rust::ArcMut;
+
+use crate::admin::mq_admin_ext_inner::MQAdminExtInner;
+use crate::base::client_config::ClientConfig;
+use crate::base::query_result::QueryResult;
+use crate::base::validators::Validators;
+use crate::consumer::consumer_impl::pull_api_wrapper::PullAPIWrapper;
+use crate::consumer::consumer_impl::pull_request_ext::PullResultExt;
+use crate::consumer::pull_callback::PullCallback;
+use crate::consumer::pull_result::PullResult;
+use crate::error::MQClientError::MQClientErr;
+use crate::factory::mq_client_instance::MQClientInstance;
+use crate::implementation::communication_mode::CommunicationMode;
+use crate::implementation::mq_client_manager::MQClientManager;
+use crate::Result;
+
+const ADMIN_EXT_GROUP: &str = "admin_ext_group";
+const PULL_TIMEOUT_MILLIS: u64 = 10_000;
+
+struct AdminExtInner;
+
+impl MQAdminExtInner for AdminExtInner {}
+
+/// Pulls of the admin ext are synchronous, the result is returned instead of called back.
+struct SyncPull;
+
+impl PullCallback for SyncPull {
+    async fn on_success(&mut self, _pull_result: PullResultExt) {}
+
+    fn on_exception(&mut self, _e: Box<dyn std::error::Error + Send>) {}
+}
+
+/// Administration client used by the tools to inspect a cluster, such as looking up the
+/// messages stored by the brokers.
+pub struct DefaultMQAdminExt {
+    client_config: ClientConfig,
+    admin_ext_group: CheetahString,
+    client_instance: Option<ArcMut<MQClientInstance>>,
+}
+
+impl DefaultMQAdminExt {
+    pub fn new(client_config: ClientConfig) -> Self {
+        Self {
+            client_config,
+            admin_ext_group: CheetahString::from_static_str(ADMIN_EXT_GROUP),
+            client_instance: None,
+        }
+    }
+
+    pub async fn start(&mut self) -> Result<()> {
+        if self.client_instance.is_some() {
+            return Ok(());
+        }
+        Validators::check_client_config(&self.client_config)?;
+        self.client_config.change_instance_name_to_pid();
+        let mut client_instance = MQClientManager::get_instance()
+            .get_or_create_mq_client_instance(self.client_config.clone(), None)
+            .await;
+        let registered = client_instance
+            .register_admin_ext(self.admin_ext_group.as_str(), Box::new(AdminExtInner))
+            .await;
+        if !registered {
+            return Err(MQClientErr(
+                -1,
+                format!(
+                    "The adminExt group[{}] has created already, specified another name please.",
+                    self.admin_ext_group
+                ),
+            ));
+        }
+        let this = client_instance.clone();
+        client_instance.start(this).await?;
+        self.client_instance = Some(client_instance);
+        Ok(())
+    }
+
+    pub async fn shutdown(&mut self) {
+        if let Some(mut client_instance) = self.client_instance.take() {
+            client_instance
+                .unregister_admin_ext(self.admin_ext_group.as_str())
+                .await;
+            client_instance.shutdown().await;
+        }
+    }
+
+    /// Fetches a message by its offset message id, see [`Self::query_message_by_unique_key`]
+    /// for the id assigned by the producer.
+    pub async fn view_message(&mut self, topic: &str, msg_id: &str) -> Result<MessageExt> {
+        self.client_instance()?
+            .mq_admin_impl
+            .view_message(topic, msg_id)
+            .await
+    }
+
+    pub async fn query_message(
+        &mut self,
+        topic: &str,
+        key: &str,
+        max_num: i32,
+        begin: i64,
+        end: i64,
+    ) -> Result<QueryResult> {
+        self.client_instance()?
+            .mq_admin_impl
+            .query_message(topic, key, max_num, begin, end)
+            .await
+    }
+
+    pub async fn query_message_by_unique_key(
+        &mut self,
+        topic: &str,
+        unique_key: &str,
+    ) -> Result<MessageExt> {
+        self.client_instance()?
+            .mq_admin_impl
+            .query_message_by_unique_key(topic, unique_key)
+            .await
+    }
+
+    /// The topics of the cluster, only those starting with `topic_prefix` if given.
+    pub async fn fetch_all_topic_list(
+        &mut self,
+        topic_prefix: Option<&str>,
+        timeout_millis: u64,
+    ) -> Result<TopicList> {
+        self.client_instance()?
+            .get_mq_client_api_impl()
+            .get_topic_list_from_name_server(topic_prefix, timeout_millis)
+            .await
+    }
+
+    /// The offset of the first message of `mq` stored at or after `timestamp`, in milliseconds.
+    pub async fn search_offset(&mut self, mq: &MessageQueue, timestamp: u64) -> Result<i64> {
+        self.client_instance()?
+            .mq_admin_impl
+            .search_offset(mq, timestamp)
+            .await
+    }
+
+    pub async fn max_offset(&mut self, mq: &MessageQueue) -> Result<i64> {
+        self.client_instance()?.mq_admin_impl.max_offset(mq).await
+    }
+
+    /// Pulls up to `max_nums` messages of `mq` from `offset` as the tools consumer group,
+    /// keeping those whose tag matches `sub_expression`. The group is never registered, so
+    /// nothing is rebalanced and no consume offset is committed.
+    pub async fn pull_message(
+        &mut self,
+        mq: &MessageQueue,
+        sub_expression: &str,
+        offset: i64,
+        max_nums: i32,
+    ) -> Result<PullResult> {
+        let sub_expression = CheetahString::from_slice(sub_expression);
+        let subscription_data =
+            FilterAPI::build_subscription_data(mq.get_topic_cs(), &sub_expression)
+                .map_err(|err| MQClientErr(-1, err))?;
+        let mut pull_api_wrapper = PullAPIWrapper::new(
+            self.client_instance()?.clone(),
+            CheetahString::from_static_str(mix_all::TOOLS_CONSUMER_GROUP),
+            false,
+        );
+        let sys_flag = PullSysFlag::build_sys_flag(false, false, true, false);
+        let mut pull_result = pull_api_wrapper
+            .pull_kernel_impl(
+                mq,
+                subscription_data.sub_string.clone(),
+                CheetahString::from_static_str(ExpressionType::TAG),
+                0,
+                offset,
+                max_nums,
+                i32::MAX,
+                sys_flag as i32,
+                0,
+                0,
+                PULL_TIMEOUT_MILLIS,
+                CommunicationMode::Sync,
+                SyncPull,
+            )
+            .await?
+            .ok_or_else(|| MQClientErr(-1, "sync pull returned no result".to_string()))?;
+        pull_api_wrapper.process_pull_result(mq, &mut pull_result, &subscription_data);
+        Ok(pull_result.pull_result)
+    }
+
+    /// Switches how the broker at `broker_addr` serves `topic` to `consumer_group`; in POP
+    /// mode each consumer also pops the queues of the `pop_share_queue_num` consumers after it.
+    pub async fn set_message_request_mode(
+        &mut self,
+        broker_addr: &str,
+        topic: &str,
+        consumer_group: &str,
+        mode: MessageRequestMode,
+        pop_share_queue_num: i32,
+        timeout_millis: u64,
+    ) -> Result<()> {
+        let request_body = SetMessageRequestModeRequestBody {
+            topic: CheetahString::from_slice(topic),
+            consumer_group: CheetahString::from_slice(consumer_group),
+            mode,
+            pop_share_queue_num,
+        };
+        self.client_instance()?
+            .get_mq_client_api_impl()
+            .set_message_request_mode(broker_addr, request_body, timeout_millis)
+            .await
+    }
+
+    /// Puts `consumer_group` on the cold control list of the broker at `broker_addr`, letting it
+    /// read `threshold` bytes per second of cold data.
+    pub async fn update_cold_data_flow_ctr_group_config(
+        &mut self,
+        broker_addr: &str,
+        consumer_group: &str,
+        threshold: u64,
+        timeout_millis: u64,
+    ) -> Result<()> {
+        let properties = HashMap::from([(
+            CheetahString::from_slice(consumer_group),
+            CheetahString::from_string(threshold.to_string()),
+        )]);
+        self.client_instance()?
+            .get_mq_client_api_impl()
+            .update_cold_data_flow_ctr_group_config(broker_addr, &properties, timeout_millis)
+            .await
+    }
+
+    pub async fn remove_cold_data_flow_ctr_group_config(
+        &mut self,
+        broker_addr: &str,
+        consumer_group: &str,
+        timeout_millis: u64,
+    ) -> Result<()> {
+        self.client_instance()?
+            .get_mq_client_api_impl()
+            .remove_cold_data_flow_ctr_group_config(broker_addr, consumer_group, timeout_millis)
+            .await
+    }
+
+    pub async fn get_cold_data_flow_ctr_info(
+        &mut self,
+        broker_addr: &str,
+        timeout_millis: u64,
+    ) -> Result<String> {
+        self.client_instance()?
+            .get_mq_client_api_impl()
+            .get_cold_data_flow_ctr_info(broker_addr, timeout_millis)
+            .await
+    }
+
+    /// Copies the committed offsets of `src_group` to `dest_group` on every broker serving the
+    /// retry topic of `src_group`, on `topic` or on every topic `src_group` consumed. Unless
+    /// `offline`, only the topics `src_group` still subscribes to are cloned.
+    pub async fn clone_group_offset(
+        &mut self,
+        src_group: &str,
+        dest_group: &str,
+        topic: Option<&str>,
+        offline: bool,
+        timeout_millis: u64,
+    ) -> Result<()> {
+        let mut mq_client_api_impl = self.client_instance()?.get_mq_client_api_impl();
+        let retry_topic = mix_all::get_retry_topic(src_group);
+        let topic_route_data = mq_client_api_impl
+            .get_topic_route_info_from_name_server(&retry_topic, timeout_millis)
+            .await?
+            .ok_or_else(|| {
+                MQClientErr(-1, format!("No topic route info for topic {}", retry_topic))
+            })?;
+        for broker_data in &topic_route_data.broker_datas {
+            let Some(addr) = broker_data.select_broker_addr() else {
+                continue;
+            };
+            let request_header = CloneGroupOffsetRequestHeader {
+                src_group: CheetahString::from_slice(src_group),
+                dest_group: CheetahString::from_slice(dest_group),
+                topic: topic.map(CheetahString::from_slice),
+                offline,
+            };
+            mq_client_api_impl
+                .clone_group_offset(&addr, request_header, timeout_millis)
+                .await?;
+        }
+        Ok(())
+    }
+
+    fn client_instance(&mut self) -> Result<&mut ArcMut<MQClientInstance>> {
+        self.client_instance.as_mut().ok_or_else(|| {
+            MQClientErr(
+                -1,
+                "The admin ext is not started, call start() first".to_string(),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bytes::BytesMut;
+    use rocketmq_common::common::message::message_decoder;
+    use rocketmq_common::common::message::MessageConst;
+    use rocketmq_common::common::message::MessageTrait;
+    use rocketmq_common::common::mix_all;
+    use rocketmq_common::utils::message_utils;
+    use rocketmq_remoting::code::request_code::RequestCode;
+    use rocketmq_remoting::code::response_code::ResponseCode;
+    use rocketmq_remoting::protocol::header::query_message_request_header::QueryMessageRequestHeader;
+    use rocketmq_remoting::protocol::header::query_message_response_header::QueryMessageResponseHeader;
+    use rocketmq_remoting::protocol::header::view_message_request_header::ViewMessageRequestHeader;
+    use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+    use rocketmq_remoting::test_util::FakeBroker;
+
+    use super::*;
+
+    const TOPIC: &str = "QueryTopic";
+    const UNIQUE_KEY: &str = "7F0000010001B4AAC6DC1B0EA0470000";
+
+    /// Answers like an index lookup: every message of `stored` whose unique key shares the
+    /// queried key's hash slot, which the fake approximates with the key length.
+    fn query(stored: &[MessageExt], request: &RemotingCommand) -> RemotingCommand {
+        let request_header = request
+            .decode_command_custom_header::<QueryMessageRequestHeader>()
+            .unwrap();
+        assert_eq!(
+            request
+                .ext_fields()
+                .unwrap()
+                .get(mix_all::UNIQUE_MSG_QUERY_FLAG)
+                .unwrap(),
+            "true"
+        );
+        let mut body = BytesMut::new();
+        for msg in stored {
+            if msg.get_topic() == &request_header.topic
+                && msg.get_property(&unique_key_property()).unwrap().len()
+                    == request_header.key.len()
+            {
+                body.extend_from_slice(&message_decoder::encode(msg, false).unwrap());
+            }
+        }
+        if body.is_empty() {
+            return RemotingCommand::create_response_command_with_code(ResponseCode::QueryNotFound);
+        }
+        RemotingCommand::create_response_command_with_header(QueryMessageResponseHeader::default())
+            .set_body(body.freeze())
+    }
+
+    fn view(stored: &[MessageExt], request: &RemotingCommand) -> RemotingCommand {
+        let request_header = request
+            .decode_command_custom_header::<ViewMessageRequestHeader>()
+            .unwrap();
+        match stored
+            .iter()
+            .find(|msg| msg.commit_log_offset == request_header.offset)
+        {
+            Some(msg) => RemotingCommand::create_response_command()
+                .set_body(message_decoder::encode(msg, false).unwrap()),
+            None => RemotingCommand::create_response_command_with_code(ResponseCode::SystemError),
+        }
+    }
+
+    fn unique_key_property() -> CheetahString {
+        CheetahString::from_static_str(MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX)
+    }
+
+    fn stored_message(
+        addr: &CheetahString,
+        unique_key: &str,
+        commit_log_offset: i64,
+        store_timestamp: i64,
+    ) -> MessageExt {
+        let mut msg = MessageExt::default();
+        msg.set_topic(TOPIC.into());
+        msg.set_body(bytes::Bytes::from(format!("body-{commit_log_offset}")));
+        msg.put_property(unique_key_property(), CheetahString::from_slice(unique_key));
+        msg.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_KEYS),
+            CheetahString::from_static_str("order-1"),
+        );
+        msg.put_property(
+            CheetahString::from_static_str("user-property"),
+            CheetahString::from_static_str("user-value"),
+        );
+        msg.commit_log_offset = commit_log_offset;
+        msg.store_timestamp = store_timestamp;
+        msg.store_host = addr.parse().unwrap();
+        msg.born_host = addr.parse().unwrap();
+        msg
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn stored_message_is_found_by_offset_msg_id_and_unique_key() {
+        let broker = FakeBroker::bind().await;
+        let addr = broker.addr();
+        // the same unique key stored twice, then a different message colliding in the index
+        let stored = Arc::new(vec![
+            stored_message(&addr, UNIQUE_KEY, 100, 1_000),
+            stored_message(&addr, UNIQUE_KEY, 300, 3_000),
+            stored_message(&addr, &UNIQUE_KEY.replace('7', "8"), 500, 5_000),
+        ]);
+        let stored_for_view = stored.clone();
+        broker
+            .on(RequestCode::QueryMessage, move |_, request| {
+                query(&stored, request)
+            })
+            .on(RequestCode::ViewMessageById, move |_, request| {
+                view(&stored_for_view, request)
+            })
+            .spawn();
+
+        let mut admin = DefaultMQAdminExt::new(ClientConfig {
+            client_ip: Some("127.0.0.1".into()),
+            instance_name: "query-message".into(),
+            namesrv_addr: Some(addr.clone()),
+            vip_channel_enabled: false,
+            ..Default::default()
+        });
+        admin.start().await.unwrap();
+
+        let msg_id = message_utils::build_message_id(addr.parse().unwrap(), 300);
+        let by_id = admin.view_message(TOPIC, &msg_id).await.unwrap();
+        assert_eq!(by_id.commit_log_offset, 300);
+        assert_eq!(by_id.get_body().unwrap().as_ref(), b"body-300");
+        assert_eq!(
+            by_id
+                .get_property(&CheetahString::from_static_str("user-property"))
+                .unwrap(),
+            "user-value"
+        );
+
+        let by_key = admin
+            .query_message_by_unique_key(TOPIC, UNIQUE_KEY)
+            .await
+            .unwrap();
+        assert_eq!(by_key.commit_log_offset, 300);
+        assert_eq!(
+            by_key.get_property(&unique_key_property()).unwrap(),
+            UNIQUE_KEY
+        );
+
+        let missing = admin
+            .query_message_by_unique_key(TOPIC, &UNIQUE_KEY.replace('7', "9"))
+            .await;
+        assert!(missing.is_err());
+        assert!(admin.view_message(TOPIC, "not-an-id").await.is_err());
+        admin.shutdown().await;
+    }
+}
@@ -26,7 +26,6 @@ use rocketmq_common::utils::name_server_address_utils::NameServerAddressUtils;
 use rocketmq_common::utils::name_server_address_utils::NAMESRV_ENDPOINT_PATTERN;
 use rocketmq_common::utils::network_util::NetworkUtil;
 use rocketmq_common::utils::string_utils::StringUtils;
-use rocketmq_common::TimeUtils::get_current_nano;
 use rocketmq_remoting::protocol::namespace_util::NamespaceUtil;
 use rocketmq_remoting::protocol::request_type::RequestType;
 use rocketmq_remoting::protocol::LanguageCode;
@@ -128,10 +127,10 @@ impl ClientConfig {
             enable_stream_request_type: false,
             send_latency_enable: env::var(SEND_LATENCY_ENABLE)
                 .unwrap_or_else(|_| "false".to_string())
-                == "false",
+                == "true",
             start_detector_enable: env::var(START_DETECTOR_ENABLE)
                 .unwrap_or_else(|_| "false".to_string())
-                == "false",
+                == "true",
             enable_heartbeat_channel_event_listener: true,
             enable_trace: false,
             trace_topic: None,
@@ -180,9 +179,11 @@ impl ClientConfig {
         self.namespace.clone()
     }
 
+    /// Replaces the default instance name with the process id, so that clients of one process
+    /// share an instance while clients of different processes on the same host do not collide.
     pub fn change_instance_name_to_pid(&mut self) {
         if self.instance_name == "DEFAULT" {
-            self.instance_name = format!("{}#{}", std::process::id(), get_current_nano()).into();
+            self.instance_name = std::process::id().to_string().into();
         }
     }
 
@@ -220,3 +221,30 @@ impl ClientConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_instance_name_becomes_pid() {
+        let mut client_config = ClientConfig {
+            client_ip: Some("10.0.0.1".into()),
+            instance_name: "DEFAULT".into(),
+            unit_name: Some("unit".into()),
+            ..Default::default()
+        };
+        client_config.change_instance_name_to_pid();
+        assert_eq!(
+            client_config.build_mq_client_id(),
+            format!("10.0.0.1@{}@unit", std::process::id())
+        );
+
+        let mut named = ClientConfig {
+            instance_name: "orders".into(),
+            ..Default::default()
+        };
+        named.change_instance_name_to_pid();
+        assert_eq!(named.instance_name, "orders");
+    }
+}
@@ -19,43 +19,145 @@ use std::collections::HashMap;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::config::TopicConfig;
 use rocketmq_common::common::constant::PermName;
+use rocketmq_common::common::message::message_validator::MessageValidator;
 use rocketmq_common::common::message::MessageConst;
 use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::common::mix_all;
 use rocketmq_common::common::topic::TopicValidator;
+use rocketmq_common::common::validators;
+use rocketmq_common::common::validators::ValidationError;
 use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::heartbeat::message_model::MessageModel;
 
+use crate::base::client_config::ClientConfig;
+use crate::consumer::default_mq_push_consumer::ConsumerConfig;
 use crate::error::MQClientError::MQClientErr;
 use crate::producer::default_mq_producer::ProducerConfig;
 use crate::Result;
 
+fn check_range(name: &'static str, value: i64, min: i64, max: i64) -> Result<()> {
+    Ok(validators::Validators::check_range(name, value, min, max)?)
+}
+
 pub struct Validators;
 
 impl Validators {
-    pub const CHARACTER_MAX_LENGTH: usize = 255;
+    pub const CHARACTER_MAX_LENGTH: usize = validators::Validators::CHARACTER_MAX_LENGTH;
     pub const TOPIC_MAX_LENGTH: usize = 127;
+    pub const MIN_POP_INVISIBLE_TIME: u64 = 5000;
+    pub const MAX_POP_INVISIBLE_TIME: u64 = 300000;
 
     pub fn check_group(group: &str) -> Result<()> {
-        if group.trim().is_empty() {
-            return Err(MQClientErr(-1, "the specified group is blank".to_string()));
+        Ok(validators::Validators::check_group(group)?)
+    }
+
+    /// Checks the name server list and the trace topic of `client_config`.
+    pub fn check_client_config(client_config: &ClientConfig) -> Result<()> {
+        validators::Validators::check_name_server_addr(
+            client_config.namesrv_addr.as_deref(),
+            mix_all::is_ws_addr_configured(),
+        )?;
+        if client_config.enable_trace {
+            if let Some(ref trace_topic) = client_config.trace_topic {
+                Self::check_topic(trace_topic)?;
+            }
         }
+        Ok(())
+    }
 
-        if group.len() > Self::CHARACTER_MAX_LENGTH {
-            return Err(MQClientErr(
-                -1,
-                "the specified group is longer than group max length 255.".to_string(),
-            ));
+    pub fn check_producer_config(producer_config: &ProducerConfig) -> Result<()> {
+        validators::Validators::check_client_group(
+            producer_config.producer_group(),
+            mix_all::DEFAULT_PRODUCER_GROUP,
+        )?;
+        Ok(())
+    }
+
+    /// Checks the options of a push consumer that can be told apart from its configuration
+    /// alone, the listener being optional until `start()`.
+    pub fn check_consumer_config(consumer_config: &ConsumerConfig) -> Result<()> {
+        validators::Validators::check_client_group(
+            consumer_config.consumer_group.as_str(),
+            mix_all::DEFAULT_CONSUMER_GROUP,
+        )?;
+        let is_orderly = consumer_config
+            .message_listener
+            .as_ref()
+            .is_some_and(|listener| listener.message_listener_orderly.is_some());
+        if is_orderly && consumer_config.message_model == MessageModel::Broadcasting {
+            return Err(ValidationError::OrderlyBroadcasting.into());
         }
 
-        if TopicValidator::is_topic_or_group_illegal(group) {
-            return Err(MQClientErr(
-                -1,
-                format!(
-                    "the specified group[{}] contains illegal characters, allowing only \
-                     ^[%|a-zA-Z0-9_-]+$",
-                    group
-                ),
-            ));
+        let consume_thread_min = consumer_config.consume_thread_min;
+        let consume_thread_max = consumer_config.consume_thread_max;
+        check_range("consumeThreadMin", consume_thread_min as i64, 1, 1000)?;
+        check_range("consumeThreadMax", consume_thread_max as i64, 1, 1000)?;
+        if consume_thread_min > consume_thread_max {
+            return Err(ValidationError::ConsumeThreadMinOverMax {
+                min: consume_thread_min,
+                max: consume_thread_max,
+            }
+            .into());
+        }
+        check_range(
+            "consumeConcurrentlyMaxSpan",
+            consumer_config.consume_concurrently_max_span as i64,
+            1,
+            65535,
+        )?;
+        check_range(
+            "pullThresholdForQueue",
+            consumer_config.pull_threshold_for_queue as i64,
+            1,
+            65535,
+        )?;
+        if consumer_config.pull_threshold_for_topic != -1 {
+            check_range(
+                "pullThresholdForTopic",
+                consumer_config.pull_threshold_for_topic as i64,
+                1,
+                6553500,
+            )?;
         }
+        check_range(
+            "pullThresholdSizeForQueue",
+            consumer_config.pull_threshold_size_for_queue as i64,
+            1,
+            1024,
+        )?;
+        if consumer_config.pull_threshold_size_for_topic != -1 {
+            check_range(
+                "pullThresholdSizeForTopic",
+                consumer_config.pull_threshold_size_for_topic as i64,
+                1,
+                102400,
+            )?;
+        }
+        check_range(
+            "pullInterval",
+            consumer_config.pull_interval as i64,
+            0,
+            65535,
+        )?;
+        check_range(
+            "consumeMessageBatchMaxSize",
+            consumer_config.consume_message_batch_max_size as i64,
+            1,
+            1024,
+        )?;
+        check_range(
+            "pullBatchSize",
+            consumer_config.pull_batch_size as i64,
+            1,
+            1024,
+        )?;
+        check_range(
+            "popInvisibleTime",
+            consumer_config.pop_invisible_time as i64,
+            Self::MIN_POP_INVISIBLE_TIME as i64,
+            Self::MAX_POP_INVISIBLE_TIME as i64,
+        )?;
+        check_range("popBatchNums", consumer_config.pop_batch_nums as i64, 1, 32)?;
         Ok(())
     }
 
@@ -73,30 +175,16 @@ impl Validators {
         Self::check_topic(msg.get_topic())?;
         Self::is_not_allowed_send_topic(msg.get_topic())?;
 
-        if msg.get_body().is_none() {
-            return Err(MQClientErr(
-                ResponseCode::MessageIllegal as i32,
-                "the message body is null".to_string(),
-            ));
-        }
-
-        let length = msg.get_body().unwrap().len();
-        if length == 0 {
-            return Err(MQClientErr(
-                ResponseCode::MessageIllegal as i32,
-                "the message body length is zero".to_string(),
-            ));
-        }
-
-        if length > producer_config.max_message_size() as usize {
-            return Err(MQClientErr(
-                ResponseCode::MessageIllegal as i32,
-                format!(
-                    "the message body size over max value, MAX: {}",
-                    producer_config.max_message_size()
-                ),
-            ));
-        }
+        MessageValidator::check_body(
+            msg.get_body().map(|body| body.as_ref()),
+            producer_config.max_message_size() as usize,
+        )
+        .and_then(|_| {
+            MessageValidator::check_properties_length(MessageValidator::properties_length(
+                msg.get_properties(),
+            ))
+        })
+        .map_err(|err| MQClientErr(ResponseCode::MessageIllegal as i32, err.to_string()))?;
 
         let lmq_path = msg.get_user_property(&CheetahString::from_static_str(
             MessageConst::PROPERTY_INNER_MULTI_DISPATCH,
@@ -197,8 +285,62 @@ mod tests {
     use std::collections::HashMap;
 
     use rocketmq_common::common::config::TopicConfig;
+    use rocketmq_common::common::message::message_ext::MessageExt;
+    use rocketmq_common::common::message::message_single::Message;
 
     use super::*;
+    use crate::consumer::default_mq_push_consumer::DefaultMQPushConsumer;
+    use crate::consumer::default_mq_push_consumer_builder::DefaultMQPushConsumerBuilder;
+    use crate::consumer::listener::consume_orderly_context::ConsumeOrderlyContext;
+    use crate::consumer::listener::consume_orderly_status::ConsumeOrderlyStatus;
+    use crate::consumer::listener::message_listener_orderly::MessageListenerOrderly;
+    use crate::producer::default_mq_producer::DefaultMQProducer;
+    use crate::producer::transaction_mq_producer::TransactionMQProducer;
+
+    const NAMESRV_ADDR: &str = "127.0.0.1:9876";
+
+    struct NoopOrderlyListener;
+
+    impl MessageListenerOrderly for NoopOrderlyListener {
+        fn consume_message(
+            &self,
+            _msgs: &[&MessageExt],
+            _context: &mut ConsumeOrderlyContext,
+        ) -> Result<ConsumeOrderlyStatus> {
+            Ok(ConsumeOrderlyStatus::Success)
+        }
+    }
+
+    fn consumer_builder() -> DefaultMQPushConsumerBuilder {
+        DefaultMQPushConsumer::builder()
+            .name_server_addr(NAMESRV_ADDR)
+            .consumer_group("consumer_group")
+    }
+
+    fn consumer_build_error(builder: DefaultMQPushConsumerBuilder) -> String {
+        match builder.build() {
+            Ok(_) => panic!("the consumer should be refused"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    fn producer_build_error(group: &str, client_config: ClientConfig) -> String {
+        match DefaultMQProducer::builder()
+            .client_config(client_config)
+            .producer_group(group)
+            .build()
+        {
+            Ok(_) => panic!("the producer should be refused"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    fn client_config_with_namesrv() -> ClientConfig {
+        ClientConfig {
+            namesrv_addr: Some(NAMESRV_ADDR.into()),
+            ..Default::default()
+        }
+    }
 
     #[test]
     fn check_group_blank_group() {
@@ -285,4 +427,231 @@ mod tests {
         let result = Validators::check_broker_config(&broker_config);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn check_message_body_size_limit() {
+        let producer_config = ProducerConfig::default();
+        let max = producer_config.max_message_size() as usize;
+        let body = vec![0u8; max + 1];
+
+        let at_max = Message::new("TopicA", &body[..max]);
+        assert!(Validators::check_message(Some(&at_max), &producer_config).is_ok());
+        let over = Message::new("TopicA", &body);
+        assert!(Validators::check_message(Some(&over), &producer_config).is_err());
+        let empty = Message::new("TopicA", &[]);
+        assert!(Validators::check_message(Some(&empty), &producer_config).is_err());
+    }
+
+    #[test]
+    fn check_message_properties_length_limit() {
+        let producer_config = ProducerConfig::default();
+        let mut msg = Message::new("TopicA", b"body");
+        // name + value + two separators, on top of the properties already set
+        let room = MessageValidator::MAX_PROPERTIES_LENGTH
+            - MessageValidator::properties_length(msg.get_properties())
+            - 3;
+        msg.put_property(
+            CheetahString::from_static_str("K"),
+            CheetahString::from_string("v".repeat(room)),
+        );
+        assert!(Validators::check_message(Some(&msg), &producer_config).is_ok());
+        msg.put_property(
+            CheetahString::from_static_str("K"),
+            CheetahString::from_string("v".repeat(room + 1)),
+        );
+        assert!(Validators::check_message(Some(&msg), &producer_config).is_err());
+    }
+
+    #[test]
+    fn producer_builder_refuses_invalid_group() {
+        let client_config = client_config_with_namesrv();
+        assert_eq!(
+            producer_build_error("", client_config.clone()),
+            "the specified group is blank"
+        );
+        assert_eq!(
+            producer_build_error(&"g".repeat(256), client_config.clone()),
+            "the specified group is longer than group max length 255."
+        );
+        assert_eq!(
+            producer_build_error("bad@group", client_config.clone()),
+            "the specified group[bad@group] contains illegal characters, allowing only \
+             ^[%|a-zA-Z0-9_-]+$"
+        );
+        assert_eq!(
+            producer_build_error(mix_all::DEFAULT_PRODUCER_GROUP, client_config),
+            "the specified group[DEFAULT_PRODUCER] is reserved, please specify another one."
+        );
+    }
+
+    #[test]
+    fn producer_builder_requires_name_server_addr() {
+        let client_config = ClientConfig {
+            namesrv_addr: None,
+            ..Default::default()
+        };
+        assert_eq!(
+            producer_build_error("producer_group", client_config),
+            ValidationError::MissingNameServerAddr.to_string()
+        );
+        let blank_entries = ClientConfig {
+            namesrv_addr: Some(" ; ".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            producer_build_error("producer_group", blank_entries),
+            ValidationError::MissingNameServerAddr.to_string()
+        );
+    }
+
+    #[test]
+    fn producer_builder_checks_customized_trace_topic() {
+        let error = match DefaultMQProducer::builder()
+            .client_config(client_config_with_namesrv())
+            .producer_group("producer_group")
+            .enable_msg_trace(true)
+            .customized_trace_topic("bad topic")
+            .build()
+        {
+            Ok(_) => panic!("the producer should be refused"),
+            Err(err) => err.to_string(),
+        };
+        assert!(error.contains("The specified topic[bad topic] contains illegal characters"));
+    }
+
+    #[test]
+    fn producer_builders_accept_valid_config() {
+        let producer = DefaultMQProducer::builder()
+            .client_config(client_config_with_namesrv())
+            .producer_group("producer_group")
+            .send_msg_timeout(5000)
+            .retry_times(4)
+            .enable_msg_trace(true)
+            .build()
+            .unwrap();
+        assert_eq!(producer.retry_times_when_send_failed(), 4);
+        assert!(producer.client_config().enable_trace);
+
+        let result = TransactionMQProducer::builder()
+            .client_config(client_config_with_namesrv())
+            .producer_group("")
+            .build();
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "the specified group is blank"
+        );
+    }
+
+    #[test]
+    fn consumer_builder_refuses_invalid_group() {
+        let builder = consumer_builder().consumer_group("bad group");
+        assert_eq!(
+            consumer_build_error(builder),
+            "the specified group[bad group] contains illegal characters, allowing only \
+             ^[%|a-zA-Z0-9_-]+$"
+        );
+        let builder = consumer_builder().consumer_group(mix_all::DEFAULT_CONSUMER_GROUP);
+        assert_eq!(
+            consumer_build_error(builder),
+            "the specified group[DEFAULT_CONSUMER] is reserved, please specify another one."
+        );
+    }
+
+    #[test]
+    fn consumer_builder_requires_name_server_addr() {
+        let builder = DefaultMQPushConsumer::builder()
+            .client_config(ClientConfig {
+                namesrv_addr: None,
+                ..Default::default()
+            })
+            .consumer_group("consumer_group");
+        assert_eq!(
+            consumer_build_error(builder),
+            ValidationError::MissingNameServerAddr.to_string()
+        );
+    }
+
+    #[test]
+    fn consumer_builder_refuses_orderly_broadcasting() {
+        let builder = consumer_builder()
+            .message_model(MessageModel::Broadcasting)
+            .message_listener_orderly(NoopOrderlyListener);
+        assert_eq!(
+            consumer_build_error(builder),
+            "orderly consumption is not supported in BROADCASTING message model"
+        );
+    }
+
+    #[test]
+    fn consumer_builder_refuses_out_of_range_options() {
+        let cases = [
+            (
+                consumer_builder().consume_thread_min(0),
+                "consumeThreadMin Out of range [1, 1000]",
+            ),
+            (
+                consumer_builder().consume_thread_max(1001),
+                "consumeThreadMax Out of range [1, 1000]",
+            ),
+            (
+                consumer_builder()
+                    .consume_thread_min(30)
+                    .consume_thread_max(20),
+                "consumeThreadMin (30) is larger than consumeThreadMax (20)",
+            ),
+            (
+                consumer_builder().consume_concurrently_max_span(0),
+                "consumeConcurrentlyMaxSpan Out of range [1, 65535]",
+            ),
+            (
+                consumer_builder().pull_threshold_for_queue(65536),
+                "pullThresholdForQueue Out of range [1, 65535]",
+            ),
+            (
+                consumer_builder().pull_threshold_for_topic(0),
+                "pullThresholdForTopic Out of range [1, 6553500]",
+            ),
+            (
+                consumer_builder().pull_threshold_size_for_queue(1025),
+                "pullThresholdSizeForQueue Out of range [1, 1024]",
+            ),
+            (
+                consumer_builder().pull_threshold_size_for_topic(102401),
+                "pullThresholdSizeForTopic Out of range [1, 102400]",
+            ),
+            (
+                consumer_builder().pull_interval(65536),
+                "pullInterval Out of range [0, 65535]",
+            ),
+            (
+                consumer_builder().consume_message_batch_max_size(1025),
+                "consumeMessageBatchMaxSize Out of range [1, 1024]",
+            ),
+            (
+                consumer_builder().pull_batch_size(0),
+                "pullBatchSize Out of range [1, 1024]",
+            ),
+            (
+                consumer_builder().pop_invisible_time(4999),
+                "popInvisibleTime Out of range [5000, 300000]",
+            ),
+            (
+                consumer_builder().pop_batch_nums(33),
+                "popBatchNums Out of range [1, 32]",
+            ),
+        ];
+        for (builder, message) in cases {
+            assert_eq!(consumer_build_error(builder), message);
+        }
+    }
+
+    #[test]
+    fn consumer_builder_accepts_valid_config() {
+        let result = consumer_builder()
+            .message_listener_orderly(NoopOrderlyListener)
+            .enable_msg_trace(true)
+            .customized_trace_topic("consumer_trace")
+            .build();
+        assert!(result.is_ok());
+    }
 }
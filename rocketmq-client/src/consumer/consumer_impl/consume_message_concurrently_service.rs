@@ -32,10 +32,12 @@ use rocketmq_remoting::protocol::heartbeat::message_model::MessageModel;
 use rocketmq_runtime::RocketMQRuntime;
 use rocketmq_rust::ArcMut;
 use rocketmq_rust::WeakArcMut;
+use tokio_util::task::TaskTracker;
 use tracing::info;
 use tracing::warn;
 
 use crate::base::client_config::ClientConfig;
+use crate::consumer::consumer_impl::consume_message_service::await_consume_requests;
 use crate::consumer::consumer_impl::consume_message_service::ConsumeMessageServiceTrait;
 use crate::consumer::consumer_impl::default_mq_push_consumer_impl::DefaultMQPushConsumerImpl;
 use crate::consumer::consumer_impl::pop_process_queue::PopProcessQueue;
@@ -54,6 +56,7 @@ pub struct ConsumeMessageConcurrentlyService {
     pub(crate) consumer_group: CheetahString,
     pub(crate) message_listener: ArcBoxMessageListenerConcurrently,
     pub(crate) consume_runtime: RocketMQRuntime,
+    pub(crate) consume_requests: TaskTracker,
 }
 
 impl ConsumeMessageConcurrentlyService {
@@ -76,28 +79,39 @@ impl ConsumeMessageConcurrentlyService {
                 consume_thread as usize,
                 consumer_group_tag.as_str(),
             ),
+            consume_requests: TaskTracker::new(),
         }
     }
 }
 
 impl ConsumeMessageConcurrentlyService {
-    async fn clean_expire_msg(&mut self) {
-        if let Some(default_mqpush_consumer_impl) = self
+    /// Sends back the messages stuck in the listener longer than `consume_timeout` and commits
+    /// the offsets their removal frees up.
+    pub(crate) async fn clean_expire_msg(&mut self) {
+        if let Some(mut default_mqpush_consumer_impl) = self
             .default_mqpush_consumer_impl
             .as_ref()
             .unwrap()
             .upgrade()
         {
-            let process_queue_table = default_mqpush_consumer_impl
+            let process_queues = default_mqpush_consumer_impl
                 .rebalance_impl
                 .rebalance_impl_inner
                 .process_queue_table
                 .read()
-                .await;
-            for (_, process_queue) in process_queue_table.iter() {
-                process_queue
+                .await
+                .iter()
+                .map(|(mq, process_queue)| (mq.clone(), process_queue.clone()))
+                .collect::<Vec<_>>();
+            for (mq, process_queue) in process_queues {
+                let offset = process_queue
                     .clean_expired_msg(self.default_mqpush_consumer_impl.clone())
                     .await;
+                if offset >= 0 && !process_queue.is_dropped() {
+                    if let Some(offset_store) = default_mqpush_consumer_impl.offset_store.as_mut() {
+                        offset_store.update_offset(&mq, offset, true).await;
+                    }
+                }
             }
         }
     }
@@ -126,8 +140,11 @@ impl ConsumeMessageConcurrentlyService {
 
         match self.consumer_config.message_model {
             MessageModel::Broadcasting => {
-                for i in ((ack_index + 1) as usize)..consume_request.msgs.len() {
-                    warn!("BROADCASTING, the message consume failed, drop it");
+                for msg in &consume_request.msgs[((ack_index + 1) as usize)..] {
+                    warn!(
+                        "BROADCASTING, the message consume failed, drop it, {}",
+                        msg.message_ext_inner.msg_id
+                    );
                 }
             }
             MessageModel::Clustering => {
@@ -136,14 +153,21 @@ impl ConsumeMessageConcurrentlyService {
                 let mut msg_back_success = Vec::with_capacity(len);
                 let failed_msgs = consume_request.msgs.split_off((ack_index + 1) as usize);
                 for mut msg in failed_msgs {
+                    // Maybe the message is expired and cleaned, just ignore it.
                     if !consume_request
                         .process_queue
                         .contains_message(&msg.message_ext_inner)
                         .await
                     {
-                        /*info!("Message is not found in its process queue; skip send-back-procedure, topic={}, "
-                            + "brokerName={}, queueId={}, queueOffset={}", msg.get_topic(), msg.get_broker_name(),
-                        msg.getQueueId(), msg.getQueueOffset());*/
+                        info!(
+                            "Message is not found in its process queue; skip send-back-procedure, \
+                             topic={}, brokerName={}, queueId={}, queueOffset={}",
+                            msg.get_topic(),
+                            msg.message_ext_inner.broker_name,
+                            msg.message_ext_inner.queue_id,
+                            msg.message_ext_inner.queue_offset
+                        );
+                        msg_back_success.push(msg);
                         continue;
                     }
 
@@ -158,8 +182,10 @@ impl ConsumeMessageConcurrentlyService {
                         msg_back_success.push(msg);
                     }
                 }
+                // Messages sent back to the broker are consumed from this queue's point of view,
+                // only the ones that failed to be sent back stay in flight.
+                consume_request.msgs.append(&mut msg_back_success);
                 if !msg_back_failed.is_empty() {
-                    consume_request.msgs.append(&mut msg_back_success);
                     self.submit_consume_request_later(
                         msg_back_failed,
                         this,
@@ -251,7 +277,12 @@ impl ConsumeMessageServiceTrait for ConsumeMessageConcurrentlyService {
     }
 
     async fn shutdown(&mut self, await_terminate_millis: u64) {
-        // todo!()
+        await_consume_requests(
+            &self.consume_requests,
+            self.consumer_group.as_str(),
+            await_terminate_millis,
+        )
+        .await;
     }
 
     fn update_core_pool_size(&self, core_pool_size: usize) {
@@ -298,9 +329,10 @@ impl ConsumeMessageServiceTrait for ConsumeMessageConcurrentlyService {
                 default_mqpush_consumer_impl: self.default_mqpush_consumer_impl.clone(),
             };
 
-            self.consume_runtime
-                .get_handle()
-                .spawn(async move { consume_request.run(this).await });
+            self.consume_requests.spawn_on(
+                async move { consume_request.run(this).await },
+                self.consume_runtime.get_handle(),
+            );
         } else {
             msgs.chunks(consume_batch_size as usize)
                 .map(|t| t.to_vec())
@@ -315,11 +347,14 @@ impl ConsumeMessageServiceTrait for ConsumeMessageConcurrentlyService {
                         default_mqpush_consumer_impl: self.default_mqpush_consumer_impl.clone(),
                     };
                     let consume_message_concurrently_service = this.clone();
-                    self.consume_runtime.get_handle().spawn(async move {
-                        consume_request
-                            .run(consume_message_concurrently_service)
-                            .await
-                    });
+                    self.consume_requests.spawn_on(
+                        async move {
+                            consume_request
+                                .run(consume_message_concurrently_service)
+                                .await
+                        },
+                        self.consume_runtime.get_handle(),
+                    );
                 });
         }
     }
@@ -356,11 +391,7 @@ impl ConsumeRequest {
             );
             return;
         }
-        let context = ConsumeConcurrentlyContext {
-            message_queue: self.message_queue.clone(),
-            delay_level_when_next_consume: 0,
-            ack_index: i32::MAX,
-        };
+        let mut context = ConsumeConcurrentlyContext::new(self.message_queue.clone());
 
         let default_mqpush_consumer_impl = self
             .default_mqpush_consumer_impl
@@ -416,7 +447,7 @@ impl ConsumeRequest {
                 .iter()
                 .map(|msg| &msg.message_ext_inner)
                 .collect::<Vec<&MessageExt>>();
-            match self.message_listener.consume_message(&vec, &context) {
+            match self.message_listener.consume_message(&vec, &mut context) {
                 Ok(value) => {
                     status = Some(value);
                 }
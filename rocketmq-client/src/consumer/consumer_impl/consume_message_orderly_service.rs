@@ -35,9 +35,11 @@ use rocketmq_runtime::RocketMQRuntime;
 use rocketmq_rust::ArcMut;
 use rocketmq_rust::RocketMQTokioMutex;
 use rocketmq_rust::WeakArcMut;
+use tokio_util::task::TaskTracker;
 use tracing::warn;
 
 use crate::base::client_config::ClientConfig;
+use crate::consumer::consumer_impl::consume_message_service::await_consume_requests;
 use crate::consumer::consumer_impl::consume_message_service::ConsumeMessageServiceTrait;
 use crate::consumer::consumer_impl::default_mq_push_consumer_impl::DefaultMQPushConsumerImpl;
 use crate::consumer::consumer_impl::pop_process_queue::PopProcessQueue;
@@ -70,6 +72,7 @@ pub struct ConsumeMessageOrderlyService {
     pub(crate) stopped: AtomicBool,
     pub(crate) global_lock: Arc<RocketMQTokioMutex<()>>,
     pub(crate) message_queue_lock: MessageQueueLock,
+    pub(crate) consume_requests: TaskTracker,
 }
 
 impl ConsumeMessageOrderlyService {
@@ -95,6 +98,7 @@ impl ConsumeMessageOrderlyService {
             stopped: AtomicBool::new(false),
             global_lock: Arc::new(Default::default()),
             message_queue_lock: Default::default(),
+            consume_requests: TaskTracker::new(),
         }
     }
 
@@ -293,7 +297,13 @@ impl ConsumeMessageOrderlyService {
                         &mut msg.message_ext_inner,
                         CheetahString::from_string(reconsume_times.to_string()),
                     );
-                    if !self.send_message_back(&msg.message_ext_inner).await {
+                    if self.consumer_config.message_model == MessageModel::Broadcasting {
+                        // broadcasting consumers have no retry topic to fall back on
+                        warn!(
+                            "BROADCASTING, the message consume failed {} times, drop it, {}",
+                            reconsume_times, msg.message_ext_inner.msg_id
+                        );
+                    } else if !self.send_message_back(&msg.message_ext_inner).await {
                         suspend = true;
                         msg.message_ext_inner.reconsume_times = reconsume_times + 1;
                     }
@@ -414,6 +424,14 @@ impl ConsumeMessageServiceTrait for ConsumeMessageOrderlyService {
     }
 
     async fn shutdown(&mut self, await_terminate_millis: u64) {
+        self.stopped
+            .store(true, std::sync::atomic::Ordering::Release);
+        await_consume_requests(
+            &self.consume_requests,
+            self.consumer_group.as_str(),
+            await_terminate_millis,
+        )
+        .await;
         if MessageModel::Clustering == self.consumer_config.message_model {
             self.unlock_all_mq().await;
         }
@@ -460,9 +478,12 @@ impl ConsumeMessageServiceTrait for ConsumeMessageOrderlyService {
             default_mqpush_consumer_impl: self.default_mqpush_consumer_impl.clone(),
             consumer_group: self.consumer_group.clone(),
         };
-        self.consume_runtime.get_handle().spawn(async move {
-            consume_request.run(this).await;
-        });
+        self.consume_requests.spawn_on(
+            async move {
+                consume_request.run(this).await;
+            },
+            self.consume_runtime.get_handle(),
+        );
     }
 
     async fn submit_pop_consume_request(
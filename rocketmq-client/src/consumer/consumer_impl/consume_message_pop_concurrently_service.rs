@@ -23,8 +23,10 @@ use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_remoting::protocol::body::consume_message_directly_result::ConsumeMessageDirectlyResult;
 use rocketmq_rust::ArcMut;
 use rocketmq_rust::WeakArcMut;
+use tokio_util::task::TaskTracker;
 
 use crate::base::client_config::ClientConfig;
+use crate::consumer::consumer_impl::consume_message_service::await_consume_requests;
 use crate::consumer::consumer_impl::consume_message_service::ConsumeMessageServiceTrait;
 use crate::consumer::consumer_impl::default_mq_push_consumer_impl::DefaultMQPushConsumerImpl;
 use crate::consumer::consumer_impl::pop_process_queue::PopProcessQueue;
@@ -38,6 +40,7 @@ pub struct ConsumeMessagePopConcurrentlyService {
     pub(crate) consumer_config: ArcMut<ConsumerConfig>,
     pub(crate) consumer_group: CheetahString,
     pub(crate) message_listener: ArcBoxMessageListenerConcurrently,
+    pub(crate) consume_requests: TaskTracker,
 }
 
 impl ConsumeMessagePopConcurrentlyService {
@@ -53,6 +56,7 @@ impl ConsumeMessagePopConcurrentlyService {
             consumer_config,
             consumer_group,
             message_listener,
+            consume_requests: TaskTracker::new(),
         }
     }
 }
@@ -63,7 +67,12 @@ impl ConsumeMessageServiceTrait for ConsumeMessagePopConcurrentlyService {
     }
 
     async fn shutdown(&mut self, await_terminate_millis: u64) {
-        todo!()
+        await_consume_requests(
+            &self.consume_requests,
+            self.consumer_group.as_str(),
+            await_terminate_millis,
+        )
+        .await;
     }
 
     fn update_core_pool_size(&self, core_pool_size: usize) {
@@ -16,24 +16,44 @@
  */
 use std::sync::Arc;
 
+use cheetah_string::CheetahString;
 use rocketmq_common::common::message::message_client_ext::MessageClientExt;
 use rocketmq_common::common::message::message_ext::MessageExt;
 use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_remoting::protocol::body::consume_message_directly_result::ConsumeMessageDirectlyResult;
 use rocketmq_rust::ArcMut;
 use rocketmq_rust::WeakArcMut;
+use tokio_util::task::TaskTracker;
 
+use crate::consumer::consumer_impl::consume_message_service::await_consume_requests;
 use crate::consumer::consumer_impl::consume_message_service::ConsumeMessageServiceTrait;
 use crate::consumer::consumer_impl::pop_process_queue::PopProcessQueue;
 use crate::consumer::consumer_impl::process_queue::ProcessQueue;
 
-pub struct ConsumeMessagePopOrderlyService;
+pub struct ConsumeMessagePopOrderlyService {
+    pub(crate) consumer_group: CheetahString,
+    pub(crate) consume_requests: TaskTracker,
+}
+
+impl ConsumeMessagePopOrderlyService {
+    pub fn new(consumer_group: CheetahString) -> Self {
+        Self {
+            consumer_group,
+            consume_requests: TaskTracker::new(),
+        }
+    }
+}
 
 impl ConsumeMessageServiceTrait for ConsumeMessagePopOrderlyService {
     fn start(&mut self, this: WeakArcMut<Self>) {}
 
     async fn shutdown(&mut self, await_terminate_millis: u64) {
-        todo!()
+        await_consume_requests(
+            &self.consume_requests,
+            self.consumer_group.as_str(),
+            await_terminate_millis,
+        )
+        .await;
     }
 
     fn update_core_pool_size(&self, core_pool_size: usize) {
@@ -80,3 +100,27 @@ impl ConsumeMessageServiceTrait for ConsumeMessagePopOrderlyService {
         todo!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn shutdown_drains_running_consume_requests() {
+        let mut service = ConsumeMessagePopOrderlyService::new("group".into());
+        let consumed = Arc::new(AtomicBool::new(false));
+        let flag = consumed.clone();
+        service.consume_requests.spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            flag.store(true, Ordering::Release);
+        });
+
+        service.shutdown(5_000).await;
+        assert!(consumed.load(Ordering::Acquire));
+        assert!(service.consume_requests.is_closed());
+    }
+}
@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 use std::sync::Arc;
+use std::time::Duration;
 
 use rocketmq_common::common::message::message_client_ext::MessageClientExt;
 use rocketmq_common::common::message::message_ext::MessageExt;
@@ -22,10 +23,35 @@ use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_remoting::protocol::body::consume_message_directly_result::ConsumeMessageDirectlyResult;
 use rocketmq_rust::ArcMut;
 use rocketmq_rust::WeakArcMut;
+use tokio_util::task::TaskTracker;
+use tracing::warn;
 
 use crate::consumer::consumer_impl::pop_process_queue::PopProcessQueue;
 use crate::consumer::consumer_impl::process_queue::ProcessQueue;
 
+/// Waits up to `await_terminate_millis` for the consume requests already submitted to `tracker`.
+pub(crate) async fn await_consume_requests(
+    tracker: &TaskTracker,
+    consumer_group: &str,
+    await_terminate_millis: u64,
+) {
+    tracker.close();
+    if tokio::time::timeout(
+        Duration::from_millis(await_terminate_millis),
+        tracker.wait(),
+    )
+    .await
+    .is_err()
+    {
+        warn!(
+            "the consumer [{}] still has {} consume requests running after {}ms",
+            consumer_group,
+            tracker.len(),
+            await_terminate_millis
+        );
+    }
+}
+
 pub struct ConsumeMessageServiceGeneral<T, K> {
     consume_message_concurrently_service: Option<ArcMut<T>>,
     consume_message_orderly_service: Option<ArcMut<K>>,
@@ -68,7 +94,18 @@ where
     }
 
     pub async fn shutdown(&mut self, await_terminate_millis: u64) {
-        todo!()
+        if let Some(consume_message_concurrently_service) =
+            &mut self.consume_message_concurrently_service
+        {
+            consume_message_concurrently_service
+                .shutdown(await_terminate_millis)
+                .await;
+        }
+        if let Some(consume_message_orderly_service) = &mut self.consume_message_orderly_service {
+            consume_message_orderly_service
+                .shutdown(await_terminate_millis)
+                .await;
+        }
     }
 
     pub fn update_core_pool_size(&self, core_pool_size: usize) {
@@ -187,7 +224,20 @@ where
     }
 
     pub async fn shutdown(&mut self, await_terminate_millis: u64) {
-        todo!()
+        if let Some(consume_message_pop_concurrently_service) =
+            &mut self.consume_message_pop_concurrently_service
+        {
+            consume_message_pop_concurrently_service
+                .shutdown(await_terminate_millis)
+                .await;
+        }
+        if let Some(consume_message_pop_orderly_service) =
+            &mut self.consume_message_pop_orderly_service
+        {
+            consume_message_pop_orderly_service
+                .shutdown(await_terminate_millis)
+                .await;
+        }
     }
 
     fn update_core_pool_size(&self, core_pool_size: usize) {
@@ -269,3 +319,37 @@ pub trait ConsumeMessageServiceTrait {
         message_queue: &MessageQueue,
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn await_consume_requests_waits_for_running_requests() {
+        let tracker = TaskTracker::new();
+        let consumed = Arc::new(AtomicBool::new(false));
+        let flag = consumed.clone();
+        tracker.spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            flag.store(true, Ordering::Release);
+        });
+
+        await_consume_requests(&tracker, "group", 5_000).await;
+        assert!(consumed.load(Ordering::Acquire));
+        assert!(tracker.is_empty());
+    }
+
+    #[tokio::test]
+    async fn await_consume_requests_gives_up_after_timeout() {
+        let tracker = TaskTracker::new();
+        tracker.spawn(std::future::pending::<()>());
+
+        let start = tokio::time::Instant::now();
+        await_consume_requests(&tracker, "group", 50).await;
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(tracker.len(), 1);
+    }
+}
@@ -33,7 +33,6 @@ use rocketmq_common::common::message::message_single::Message;
 use rocketmq_common::common::message::MessageConst;
 use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::common::mix_all;
-use rocketmq_common::common::mix_all::DEFAULT_CONSUMER_GROUP;
 use rocketmq_common::common::sys_flag::pull_sys_flag::PullSysFlag;
 use rocketmq_common::common::FAQUrl;
 use rocketmq_common::MessageAccessor::MessageAccessor;
@@ -68,6 +67,7 @@ use crate::consumer::consumer_impl::re_balance::rebalance_push_impl::RebalancePu
 use crate::consumer::consumer_impl::re_balance::Rebalance;
 use crate::consumer::default_mq_push_consumer::ConsumerConfig;
 use crate::consumer::listener::message_listener::MessageListener;
+use crate::consumer::message_selector::MessageSelector;
 use crate::consumer::mq_consumer_inner::MQConsumerInner;
 use crate::consumer::mq_consumer_inner::MQConsumerInnerImpl;
 use crate::consumer::pull_callback::DefaultPullCallback;
@@ -90,8 +90,6 @@ pub(crate) const PULL_TIME_DELAY_MILLS_WHEN_BROKER_FLOW_CONTROL: u64 = 20;
 const PULL_TIME_DELAY_MILLS_WHEN_SUSPEND: u64 = 1000;
 const BROKER_SUSPEND_MAX_TIME_MILLIS: u64 = 1000 * 15;
 const CONSUMER_TIMEOUT_MILLIS_WHEN_SUSPEND: u64 = 1000 * 30;
-const MAX_POP_INVISIBLE_TIME: u64 = 300000;
-const MIN_POP_INVISIBLE_TIME: u64 = 5000;
 const ASYNC_TIMEOUT: u64 = 3000;
 const DO_NOT_UPDATE_TOPIC_SUBSCRIBE_INFO_WHEN_SUBSCRIPTION_CHANGED: bool = false;
 const _1MB: u64 = 1024 * 1024;
@@ -239,6 +237,7 @@ impl DefaultMQPushConsumerImpl {
                 if let Some(pull_api_wrapper) = self.pull_api_wrapper.as_mut() {
                     pull_api_wrapper
                         .register_filter_message_hook(self.filter_message_hook_list.clone());
+                    pull_api_wrapper.set_ext_fields(self.consumer_config.ext_fields.clone());
                 }
                 match self.consumer_config.message_model {
                     MessageModel::Broadcasting => {
@@ -312,7 +311,9 @@ impl DefaultMQPushConsumerImpl {
                             )));
 
                         let consume_message_pop_orderly_service =
-                            ArcMut::new(ConsumeMessagePopOrderlyService);
+                            ArcMut::new(ConsumeMessagePopOrderlyService::new(
+                                self.consumer_config.consumer_group.clone(),
+                            ));
                         self.consume_message_pop_service =
                             Some(ArcMut::new(ConsumeMessagePopServiceGeneral::new(
                                 None,
@@ -332,7 +333,8 @@ impl DefaultMQPushConsumerImpl {
                 {
                     consume_message_orderly_service.start();
                 }
-                self.client_instance
+                let register_ok = self
+                    .client_instance
                     .as_mut()
                     .unwrap()
                     .register_consumer(
@@ -342,6 +344,23 @@ impl DefaultMQPushConsumerImpl {
                         },
                     )
                     .await;
+                if !register_ok {
+                    *self.service_state = ServiceState::CreateJust;
+                    if let Some(consume_message_service) = self.consume_message_service.as_mut() {
+                        consume_message_service
+                            .shutdown(self.consumer_config.await_termination_millis_when_shutdown)
+                            .await;
+                    }
+                    return Err(MQClientError::MQClientErr(
+                        -1,
+                        format!(
+                            "The consumer group[{}] has been created before, specify another name \
+                             please.{}",
+                            self.consumer_config.consumer_group,
+                            FAQUrl::suggest_todo(FAQUrl::GROUP_NAME_DUPLICATE_URL)
+                        ),
+                    ));
+                }
                 let cloned = self.client_instance.as_mut().cloned().unwrap();
                 self.client_instance.as_mut().unwrap().start(cloned).await?;
                 info!(
@@ -398,10 +417,14 @@ impl DefaultMQPushConsumerImpl {
                 );
             }
             ServiceState::Running => {
-                if let Some(consume_message_concurrently_service) =
-                    self.consume_message_service.as_mut()
+                if let Some(consume_message_service) = self.consume_message_service.as_mut() {
+                    consume_message_service
+                        .shutdown(await_terminate_millis)
+                        .await;
+                }
+                if let Some(consume_message_pop_service) = self.consume_message_pop_service.as_mut()
                 {
-                    consume_message_concurrently_service
+                    consume_message_pop_service
                         .shutdown(await_terminate_millis)
                         .await;
                 }
@@ -415,7 +438,7 @@ impl DefaultMQPushConsumerImpl {
                     "the consumer [{}] shutdown OK",
                     self.consumer_config.consumer_group.as_str()
                 );
-                self.rebalance_impl.destroy();
+                self.rebalance_impl.destroy().await;
                 *self.service_state = ServiceState::ShutdownAlready;
             }
             ServiceState::ShutdownAlready => {
@@ -450,27 +473,7 @@ impl DefaultMQPushConsumerImpl {
     }
 
     fn check_config(&mut self) -> Result<()> {
-        Validators::check_group(self.consumer_config.consumer_group.as_str())?;
-        if self.consumer_config.consumer_group.is_empty() {
-            return Err(MQClientError::MQClientErr(
-                -1,
-                format!(
-                    "consumer_group is empty, {}",
-                    FAQUrl::suggest_todo(FAQUrl::CLIENT_PARAMETER_CHECK_URL)
-                ),
-            ));
-        }
-
-        if self.consumer_config.consumer_group == DEFAULT_CONSUMER_GROUP {
-            return Err(MQClientError::MQClientErr(
-                -1,
-                format!(
-                    "consumer_group can not equal {} please specify another one.{}",
-                    DEFAULT_CONSUMER_GROUP,
-                    FAQUrl::suggest_todo(FAQUrl::CLIENT_PARAMETER_CHECK_URL)
-                ),
-            ));
-        }
+        Validators::check_consumer_config(&self.consumer_config)?;
 
         if self
             .consumer_config
@@ -521,154 +524,6 @@ impl DefaultMQPushConsumerImpl {
             ));
         }
 
-        let consume_thread_min = self.consumer_config.consume_thread_min;
-        let consume_thread_max = self.consumer_config.consume_thread_max;
-        if !(1..=1000).contains(&consume_thread_min) {
-            return Err(MQClientError::MQClientErr(
-                -1,
-                format!(
-                    "consumeThreadMin Out of range [1, 1000]{}",
-                    FAQUrl::suggest_todo(FAQUrl::CLIENT_PARAMETER_CHECK_URL)
-                ),
-            ));
-        }
-        if !(1..=1000).contains(&consume_thread_max) {
-            return Err(MQClientError::MQClientErr(
-                -1,
-                format!(
-                    "consumeThreadMax Out of range [1, 1000]{}",
-                    FAQUrl::suggest_todo(FAQUrl::CLIENT_PARAMETER_CHECK_URL)
-                ),
-            ));
-        }
-        if consume_thread_min > consume_thread_max {
-            return Err(MQClientError::MQClientErr(
-                -1,
-                format!(
-                    "consumeThreadMin ({}) is larger than consumeThreadMax ({})",
-                    consume_thread_min, consume_thread_max
-                ),
-            ));
-        }
-
-        if self.consumer_config.consume_concurrently_max_span < 1
-            || self.consumer_config.consume_concurrently_max_span > 65535
-        {
-            return Err(MQClientError::MQClientErr(
-                -1,
-                format!(
-                    "consumeConcurrentlyMaxSpan Out of range [1, 65535]{}",
-                    FAQUrl::suggest_todo(FAQUrl::CLIENT_PARAMETER_CHECK_URL)
-                ),
-            ));
-        }
-
-        if self.consumer_config.pull_threshold_for_queue < 1
-            || self.consumer_config.pull_threshold_for_queue > 65535
-        {
-            return Err(MQClientError::MQClientErr(
-                -1,
-                format!(
-                    "pullThresholdForQueue Out of range [1, 65535]{}",
-                    FAQUrl::suggest_todo(FAQUrl::CLIENT_PARAMETER_CHECK_URL)
-                ),
-            ));
-        }
-
-        if self.consumer_config.pull_threshold_for_topic != -1
-            && (self.consumer_config.pull_threshold_for_topic < 1
-                || self.consumer_config.pull_threshold_for_topic > 6553500)
-        {
-            return Err(MQClientError::MQClientErr(
-                -1,
-                format!(
-                    "pullThresholdForTopic Out of range [1, 65535]{}",
-                    FAQUrl::suggest_todo(FAQUrl::CLIENT_PARAMETER_CHECK_URL)
-                ),
-            ));
-        }
-
-        if self.consumer_config.pull_threshold_size_for_queue < 1
-            || self.consumer_config.pull_threshold_size_for_queue > 1024
-        {
-            return Err(MQClientError::MQClientErr(
-                -1,
-                format!(
-                    "pullThresholdSizeForQueue Out of range [1, 1024]{}",
-                    FAQUrl::suggest_todo(FAQUrl::CLIENT_PARAMETER_CHECK_URL)
-                ),
-            ));
-        }
-
-        if self.consumer_config.pull_threshold_size_for_topic != -1
-            && (self.consumer_config.pull_threshold_size_for_topic < 1
-                || self.consumer_config.pull_threshold_size_for_topic > 102400)
-        {
-            return Err(MQClientError::MQClientErr(
-                -1,
-                format!(
-                    "pullThresholdSizeForTopic Out of range [1, 102400]{}",
-                    FAQUrl::suggest_todo(FAQUrl::CLIENT_PARAMETER_CHECK_URL)
-                ),
-            ));
-        }
-
-        if self.consumer_config.pull_interval > 65535 {
-            return Err(MQClientError::MQClientErr(
-                -1,
-                format!(
-                    "pullInterval Out of range [0, 65535]{}",
-                    FAQUrl::suggest_todo(FAQUrl::CLIENT_PARAMETER_CHECK_URL)
-                ),
-            ));
-        }
-
-        if self.consumer_config.consume_message_batch_max_size < 1
-            || self.consumer_config.consume_message_batch_max_size > 1024
-        {
-            return Err(MQClientError::MQClientErr(
-                -1,
-                format!(
-                    "consumeMessageBatchMaxSize Out of range [1, 1024]{}",
-                    FAQUrl::suggest_todo(FAQUrl::CLIENT_PARAMETER_CHECK_URL)
-                ),
-            ));
-        }
-
-        if self.consumer_config.pull_batch_size < 1 || self.consumer_config.pull_batch_size > 1024 {
-            return Err(MQClientError::MQClientErr(
-                -1,
-                format!(
-                    "pullBatchSize Out of range [1, 1024]{}",
-                    FAQUrl::suggest_todo(FAQUrl::CLIENT_PARAMETER_CHECK_URL)
-                ),
-            ));
-        }
-
-        if self.consumer_config.pop_invisible_time < MIN_POP_INVISIBLE_TIME
-            || self.consumer_config.pop_invisible_time > MAX_POP_INVISIBLE_TIME
-        {
-            return Err(MQClientError::MQClientErr(
-                -1,
-                format!(
-                    "popInvisibleTime Out of range [{}, {}]{}",
-                    MIN_POP_INVISIBLE_TIME,
-                    MAX_POP_INVISIBLE_TIME,
-                    FAQUrl::suggest_todo(FAQUrl::CLIENT_PARAMETER_CHECK_URL)
-                ),
-            ));
-        }
-
-        if self.consumer_config.pop_batch_nums < 1 || self.consumer_config.pop_batch_nums > 32 {
-            return Err(MQClientError::MQClientErr(
-                -1,
-                format!(
-                    "popBatchNums Out of range [1, 32]{}",
-                    FAQUrl::suggest_todo(FAQUrl::CLIENT_PARAMETER_CHECK_URL)
-                ),
-            ));
-        }
-
         Ok(())
     }
 
@@ -729,14 +584,46 @@ impl DefaultMQPushConsumerImpl {
         topic: CheetahString,
         sub_expression: CheetahString,
     ) -> Result<()> {
-        let subscription_data = FilterAPI::build_subscription_data(&topic, &sub_expression);
-        if let Err(e) = subscription_data {
-            return Err(MQClientError::MQClientErr(
-                -1,
-                format!("buildSubscriptionData exception, {}", e),
-            ));
-        }
-        let subscription_data = subscription_data.unwrap();
+        let subscription_data = FilterAPI::build_subscription_data(&topic, &sub_expression)
+            .map_err(|e| {
+                MQClientError::MQClientErr(-1, format!("buildSubscriptionData exception, {}", e))
+            })?;
+        self.put_subscription_and_send_heartbeat(topic, subscription_data)
+            .await;
+        Ok(())
+    }
+
+    pub async fn subscribe_with_selector(
+        &mut self,
+        topic: CheetahString,
+        selector: Option<MessageSelector>,
+    ) -> Result<()> {
+        let Some(selector) = selector else {
+            return self
+                .subscribe(
+                    topic,
+                    CheetahString::from_static_str(SubscriptionData::SUB_ALL),
+                )
+                .await;
+        };
+        let subscription_data = FilterAPI::build(
+            &topic,
+            &CheetahString::from(selector.get_expression()),
+            Some(CheetahString::from(selector.get_expression_type())),
+        )
+        .map_err(|e| {
+            MQClientError::MQClientErr(-1, format!("buildSubscriptionData exception, {}", e))
+        })?;
+        self.put_subscription_and_send_heartbeat(topic, subscription_data)
+            .await;
+        Ok(())
+    }
+
+    async fn put_subscription_and_send_heartbeat(
+        &mut self,
+        topic: CheetahString,
+        subscription_data: SubscriptionData,
+    ) {
         self.rebalance_impl
             .put_subscription_data(topic, subscription_data)
             .await;
@@ -745,7 +632,6 @@ impl DefaultMQPushConsumerImpl {
                 .send_heartbeat_to_all_broker_with_lock()
                 .await;
         }
-        Ok(())
     }
 
     pub async fn execute_pull_request_immediately(&mut self, pull_request: PullRequest) {
@@ -1137,54 +1023,31 @@ impl DefaultMQPushConsumerImpl {
         broker_name: Option<CheetahString>,
         mq: Option<&MessageQueue>,
     ) -> Result<()> {
-        let need_retry = true;
-        if broker_name.is_some()
-            && broker_name
-                .as_ref()
-                .unwrap()
-                .starts_with(mix_all::LOGICAL_QUEUE_MOCK_BROKER_PREFIX)
-            && mq.is_some()
-            && mq
-                .unwrap()
-                .get_broker_name()
-                .starts_with(mix_all::LOGICAL_QUEUE_MOCK_BROKER_PREFIX)
-        {
-            let _ = self.send_message_back_as_normal_message(msg).await;
+        let mut need_retry = true;
+        let result = if broker_name
+            .as_ref()
+            .is_some_and(|name| name.starts_with(mix_all::LOGICAL_QUEUE_MOCK_BROKER_PREFIX))
+            || mq.is_some_and(|mq| {
+                mq.get_broker_name()
+                    .starts_with(mix_all::LOGICAL_QUEUE_MOCK_BROKER_PREFIX)
+            }) {
+            need_retry = false;
+            self.send_message_back_as_normal_message(msg).await
         } else {
-            let broker_addr = if let Some(ref broker_name_) = broker_name {
-                self.client_instance
-                    .as_mut()
-                    .unwrap()
-                    .find_broker_address_in_publish(broker_name_.as_ref())
-                    .await
-                    .unwrap()
-            } else {
-                CheetahString::from_string(msg.store_host.to_string())
-            };
-            let max_consume_retry_times = self.get_max_reconsume_times();
-            let result = self
-                .client_instance
-                .as_mut()
-                .unwrap()
-                .mq_client_api_impl
-                .as_mut()
-                .unwrap()
-                .consumer_send_message_back(
-                    broker_addr.as_str(),
-                    broker_name.as_ref().unwrap().as_str(),
-                    msg,
-                    self.consumer_config.consumer_group.as_str(),
-                    delay_level,
-                    5000,
-                    max_consume_retry_times,
-                )
-                .await;
-            if let Err(e) = result {
-                error!("send message back error: {}", e);
-
-                self.send_message_back_as_normal_message(msg).await?;
+            self.consumer_send_message_back(msg, delay_level, broker_name.as_ref())
+                .await
+        };
+        let result = match result {
+            Err(e) if need_retry => {
+                error!(
+                    "Failed to send message back, consumerGroup={}, brokerName={:?}, mq={:?}, \
+                     message={:?}, error={}",
+                    self.consumer_config.consumer_group, broker_name, mq, msg, e
+                );
+                self.send_message_back_as_normal_message(msg).await
             }
-        }
+            result => result,
+        };
         msg.set_topic(CheetahString::from_string(
             NamespaceUtil::without_namespace_with_namespace(
                 msg.get_topic().as_str(),
@@ -1194,7 +1057,44 @@ impl DefaultMQPushConsumerImpl {
                     .as_str(),
             ),
         ));
-        Ok(())
+        result
+    }
+
+    async fn consumer_send_message_back(
+        &mut self,
+        msg: &MessageExt,
+        delay_level: i32,
+        broker_name: Option<&CheetahString>,
+    ) -> Result<()> {
+        let broker_addr = match broker_name {
+            Some(broker_name) => self
+                .client_instance
+                .as_mut()
+                .unwrap()
+                .find_broker_address_in_publish(broker_name)
+                .await
+                .ok_or_else(|| {
+                    MQClientError::MQClientErr(-1, format!("The broker[{}] not exist", broker_name))
+                })?,
+            None => CheetahString::from_string(msg.store_host.to_string()),
+        };
+        let max_consume_retry_times = self.get_max_reconsume_times();
+        self.client_instance
+            .as_mut()
+            .unwrap()
+            .mq_client_api_impl
+            .as_mut()
+            .unwrap()
+            .consumer_send_message_back(
+                broker_addr.as_str(),
+                broker_name.map(|name| name.as_str()),
+                msg,
+                self.consumer_config.consumer_group.as_str(),
+                delay_level,
+                5000,
+                max_consume_retry_times,
+            )
+            .await
     }
 
     async fn send_message_back_as_normal_message(&mut self, msg: &MessageExt) -> Result<()> {
@@ -1202,7 +1102,7 @@ impl DefaultMQPushConsumerImpl {
         let body = msg.get_body().cloned();
         let mut new_msg = Message::new_body(topic.as_str(), body);
         let origin_msg_id =
-            MessageAccessor::get_origin_message_id(&new_msg).unwrap_or(msg.msg_id.clone());
+            MessageAccessor::get_origin_message_id(msg).unwrap_or(msg.msg_id.clone());
         MessageAccessor::set_origin_message_id(&mut new_msg, origin_msg_id);
         new_msg.set_flag(msg.get_flag());
         MessageAccessor::set_properties(&mut new_msg, msg.get_properties().clone());
@@ -34,6 +34,8 @@ use rocketmq_rust::ArcMut;
 use rocketmq_rust::RocketMQTokioRwLock;
 use rocketmq_rust::WeakArcMut;
 use tokio::sync::RwLock;
+use tracing::error;
+use tracing::info;
 
 use crate::consumer::consumer_impl::default_mq_push_consumer_impl::DefaultMQPushConsumerImpl;
 use crate::consumer::consumer_impl::PULL_MAX_IDLE_TIME;
@@ -130,66 +132,71 @@ impl ProcessQueue {
         self.try_unlock_times.fetch_add(1, Ordering::AcqRel);
     }
 
+    /// Sends the messages consumed for longer than `consume_timeout` minutes back to the broker
+    /// at delay level 3 and drops them locally, at most 16 per call, so that a stuck listener
+    /// does not hold the offset of the queue forever.
+    ///
+    /// Returns the offset the queue can be committed up to after the last removal, `-1` when
+    /// nothing was removed.
     pub(crate) async fn clean_expired_msg(
         &self,
         push_consumer: Option<WeakArcMut<DefaultMQPushConsumerImpl>>,
-    ) {
-        if push_consumer.is_none() {
-            return;
-        }
-        let push_consumer = push_consumer.unwrap().upgrade();
-        if push_consumer.is_none() {
-            return;
-        }
-        let mut push_consumer = push_consumer.unwrap();
+    ) -> i64 {
+        let Some(mut push_consumer) = push_consumer.and_then(|consumer| consumer.upgrade()) else {
+            return -1;
+        };
         if push_consumer.is_consume_orderly() {
-            return;
+            return -1;
         }
+        let consume_timeout_millis = push_consumer.consumer_config.consume_timeout * 1000 * 60;
+        let mut offset = -1;
         let loop_ = 16.min(self.msg_tree_map.read().await.len());
         for _ in 0..loop_ {
             let msg = {
                 let msg_tree_map = self.msg_tree_map.read().await;
-                if !msg_tree_map.is_empty() {
-                    let value = msg_tree_map.first_key_value().unwrap().1;
+                msg_tree_map.first_key_value().and_then(|(_, value)| {
                     let consume_start_time_stamp =
-                        MessageAccessor::get_consume_start_time_stamp(value.as_ref());
-                    if let Some(consume_start_time_stamp) = consume_start_time_stamp {
-                        if get_current_millis() - consume_start_time_stamp.parse::<u64>().unwrap()
-                            > push_consumer.consumer_config.consume_timeout * 1000 * 60
-                        {
-                            Some(value.clone())
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
+                        MessageAccessor::get_consume_start_time_stamp(value.as_ref())?
+                            .parse::<u64>()
+                            .ok()?;
+                    (get_current_millis().saturating_sub(consume_start_time_stamp)
+                        > consume_timeout_millis)
+                        .then(|| value.clone())
+                })
             };
-            if msg.is_none() {
+            let Some(mut msg) = msg else {
                 break;
-            }
+            };
 
-            let mut msg = msg.unwrap();
             let msg_inner = &mut msg.as_mut().message_ext_inner;
             msg_inner.set_topic(
                 push_consumer
                     .client_config
                     .with_namespace(msg_inner.topic()),
             );
-            let _ = push_consumer
+            if let Err(err) = push_consumer
                 .send_message_back_with_broker_name(msg_inner, 3, None, None)
-                .await;
-            let msg_tree_map = self.msg_tree_map.write().await;
-            if !msg_tree_map.is_empty()
-                && msg.message_ext_inner.queue_offset == *msg_tree_map.first_key_value().unwrap().0
+                .await
+            {
+                error!("send expired msg exception: {}", err);
+                break;
+            }
+            info!(
+                "send expired msg success, topic={}, queueOffset={}, msgId={}",
+                msg_inner.topic(),
+                msg_inner.queue_offset,
+                msg_inner.msg_id()
+            );
+            let msg_tree_map = self.msg_tree_map.read().await;
+            if msg_tree_map
+                .first_key_value()
+                .is_some_and(|(first, _)| *first == msg.message_ext_inner.queue_offset)
             {
                 drop(msg_tree_map);
-                self.remove_message(&[msg]).await;
+                offset = self.remove_message(&[msg]).await;
             }
         }
+        offset
     }
 
     pub(crate) async fn put_message(&self, messages: Vec<ArcMut<MessageClientExt>>) -> bool {
@@ -265,13 +272,12 @@ impl ProcessQueue {
                     Ordering::AcqRel,
                 );
             }
-            self.msg_count.fetch_sub(removed_cnt, Ordering::AcqRel);
-            if self.msg_count.load(Ordering::Acquire) == 0 {
-                self.msg_size.store(0, Ordering::Release);
-            }
-            if !msg_tree_map.is_empty() {
-                result = *msg_tree_map.first_key_value().unwrap().0;
-            }
+        }
+        if self.msg_count.fetch_sub(removed_cnt, Ordering::AcqRel) == removed_cnt {
+            self.msg_size.store(0, Ordering::Release);
+        }
+        if !msg_tree_map.is_empty() {
+            result = *msg_tree_map.first_key_value().unwrap().0;
         }
         result
     }
@@ -56,6 +56,7 @@ pub struct PullAPIWrapper {
     connect_broker_by_user: bool,
     default_broker_id: u64,
     filter_message_hook_list: Vec<Arc<Box<dyn FilterMessageHook + Send + Sync>>>,
+    ext_fields: HashMap<CheetahString, CheetahString>,
 }
 
 impl PullAPIWrapper {
@@ -72,6 +73,7 @@ impl PullAPIWrapper {
             connect_broker_by_user: false,
             default_broker_id: mix_all::MASTER_ID,
             filter_message_hook_list: Vec::new(),
+            ext_fields: HashMap::new(),
         }
     }
 
@@ -82,6 +84,11 @@ impl PullAPIWrapper {
         self.filter_message_hook_list = filter_message_hook_list;
     }
 
+    /// Passthrough extFields attached to every pull request.
+    pub fn set_ext_fields(&mut self, ext_fields: HashMap<CheetahString, CheetahString>) {
+        self.ext_fields = ext_fields;
+    }
+
     #[inline]
     pub fn update_pull_from_which_node(&mut self, mq: &MessageQueue, broker_id: u64) {
         let atomic_u64 = self
@@ -160,8 +167,10 @@ impl PullAPIWrapper {
                 };
             if self.has_hook() {
                 let context = FilterMessageContext {
-                    unit_mode: self.unit_mode,
+                    consumer_group: Some(self.consumer_group.to_string()),
                     msg_list: &msg_list_filter_again,
+                    mq: Some(message_queue),
+                    unit_mode: self.unit_mode,
                     ..Default::default()
                 };
                 self.execute_hook(&context);
@@ -360,6 +369,7 @@ impl PullAPIWrapper {
                 timeout_millis,
                 communication_mode,
                 pull_callback,
+                &self.ext_fields,
             )
             .await
         } else {
@@ -414,3 +424,122 @@ pub fn random_num() -> i32 {
     }
     value
 }
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use bytes::BytesMut;
+    use rocketmq_common::common::hasher::string_hasher::JavaStringHasher;
+    use rocketmq_common::common::message::message_ext::MessageExt;
+    use rocketmq_remoting::protocol::filter::filter_api::FilterAPI;
+
+    use super::*;
+    use crate::base::client_config::ClientConfig;
+    use crate::consumer::pull_result::PullResult;
+    use crate::implementation::mq_client_manager::MQClientManager;
+
+    const TOPIC: &str = "PullApiWrapperTopic";
+
+    async fn pull_api_wrapper() -> PullAPIWrapper {
+        let client_config = ClientConfig {
+            client_ip: Some("127.0.0.1".into()),
+            instance_name: "pull-api-wrapper-test".into(),
+            ..Default::default()
+        };
+        let instance = MQClientManager::get_instance()
+            .get_or_create_mq_client_instance(client_config, None)
+            .await;
+        PullAPIWrapper::new(instance, "pull_api_wrapper_group".into(), false)
+    }
+
+    fn encoded_message(tags: &str, queue_offset: i64, sys_flag: i32) -> Bytes {
+        let mut message_ext = MessageExt::default();
+        message_ext.set_topic(TOPIC.into());
+        message_ext.set_tags(tags.into());
+        message_ext.set_body(Bytes::from(format!("body-{tags}")));
+        message_ext.queue_offset = queue_offset;
+        message_ext.sys_flag = sys_flag;
+        message_decoder::encode(&message_ext, true).unwrap()
+    }
+
+    fn pull_result_ext(message_binary: Bytes, suggest_which_broker_id: u64) -> PullResultExt {
+        PullResultExt {
+            pull_result: PullResult::new(PullStatus::Found, 12, 3, 20, Vec::new()),
+            suggest_which_broker_id,
+            message_binary: Some(message_binary),
+            offset_delta: None,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn process_pull_result_refilters_hash_colliding_tags() {
+        assert_eq!(
+            JavaStringHasher::new().hash_str("Aa"),
+            JavaStringHasher::new().hash_str("BB")
+        );
+        let mut wrapper = pull_api_wrapper().await;
+        let mq = MessageQueue::from_parts(TOPIC, "broker-a", 1);
+        let subscription_data =
+            FilterAPI::build_subscription_data(&TOPIC.into(), &"Aa".into()).unwrap();
+
+        let mut message_binary = BytesMut::new();
+        message_binary.extend_from_slice(&encoded_message("Aa", 10, 0));
+        message_binary.extend_from_slice(&encoded_message("BB", 11, 0));
+        let mut result = pull_result_ext(message_binary.freeze(), 1);
+
+        wrapper.process_pull_result(&mq, &mut result, &subscription_data);
+
+        let found = &result.pull_result.msg_found_list;
+        assert_eq!(found.len(), 1);
+        let msg = &found[0];
+        assert_eq!(msg.get_tags().unwrap().as_str(), "Aa");
+        assert_eq!(msg.message_ext_inner.queue_offset, 10);
+        assert_eq!(msg.message_ext_inner.broker_name.as_str(), "broker-a");
+        assert_eq!(msg.message_ext_inner.queue_id, 1);
+        assert_eq!(
+            msg.get_property(&CheetahString::from_static_str(
+                MessageConst::PROPERTY_MIN_OFFSET
+            ))
+            .unwrap()
+            .as_str(),
+            "3"
+        );
+        assert_eq!(
+            msg.get_property(&CheetahString::from_static_str(
+                MessageConst::PROPERTY_MAX_OFFSET
+            ))
+            .unwrap()
+            .as_str(),
+            "20"
+        );
+        assert!(result.message_binary.is_none());
+        assert_eq!(wrapper.recalculate_pull_from_which_node(&mq), 1);
+
+        let mut result = pull_result_ext(Bytes::new(), mix_all::MASTER_ID);
+        result.pull_result.pull_status = PullStatus::NoNewMsg;
+        wrapper.process_pull_result(&mq, &mut result, &subscription_data);
+        assert_eq!(
+            wrapper.recalculate_pull_from_which_node(&mq),
+            mix_all::MASTER_ID
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn process_pull_result_decompresses_body() {
+        let mut wrapper = pull_api_wrapper().await;
+        let mq = MessageQueue::from_parts(TOPIC, "broker-a", 0);
+        let subscription_data =
+            FilterAPI::build_subscription_data(&TOPIC.into(), &"*".into()).unwrap();
+        let sys_flag = MessageSysFlag::COMPRESSED_FLAG | MessageSysFlag::COMPRESSION_ZLIB_TYPE;
+        let mut result = pull_result_ext(encoded_message("TagA", 7, sys_flag), mix_all::MASTER_ID);
+
+        wrapper.process_pull_result(&mq, &mut result, &subscription_data);
+
+        let found = &result.pull_result.msg_found_list;
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            found[0].get_body().unwrap().as_ref(),
+            b"body-TagA".as_slice()
+        );
+    }
+}
@@ -83,5 +83,5 @@ pub trait RebalanceLocal {
 
     fn client_rebalance(&mut self, topic: &str) -> bool;
 
-    fn destroy(&mut self);
+    async fn destroy(&mut self);
 }
@@ -21,6 +21,7 @@ use std::ops::Deref;
 use std::sync::Arc;
 
 use cheetah_string::CheetahString;
+use rocketmq_common::common::message::message_enum::MessageRequestMode;
 use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_common::common::mix_all;
 use rocketmq_common::TimeUtils::get_current_millis;
@@ -41,6 +42,7 @@ use crate::consumer::consumer_impl::pop_process_queue::PopProcessQueue;
 use crate::consumer::consumer_impl::process_queue::ProcessQueue;
 use crate::consumer::consumer_impl::pull_request::PullRequest;
 use crate::consumer::consumer_impl::re_balance::Rebalance;
+use crate::error::MQClientError;
 use crate::factory::mq_client_instance::MQClientInstance;
 
 const TIMEOUT_CHECK_TIMES: u32 = 3;
@@ -103,7 +105,6 @@ where
             let topics = sub_table.keys().cloned().collect::<Vec<CheetahString>>();
             drop(sub_table);
             for topic in &topics {
-                //try_query_assignment unimplemented
                 if !self.client_rebalance(topic) && self.try_query_assignment(topic).await {
                     if !self.get_rebalance_result_from_broker(topic, is_order).await {
                         balanced = false;
@@ -125,8 +126,58 @@ where
         }
     }
 
-    async fn try_query_assignment(&self, topic: &str) -> bool {
-        unimplemented!("try_query_assignment")
+    /// Whether the broker computes the assignment of `topic`. Brokers that reject the query
+    /// fall the topic back to client rebalance; transport failures are retried with a growing
+    /// timeout before doing the same.
+    async fn try_query_assignment(&mut self, topic: &CheetahString) -> bool {
+        if self.topic_client_rebalance.read().await.contains_key(topic) {
+            return false;
+        }
+        if self.topic_broker_rebalance.read().await.contains_key(topic) {
+            return true;
+        }
+        let strategy_name = self
+            .allocate_message_queue_strategy
+            .as_ref()
+            .map_or("", |strategy| strategy.get_name());
+        let consumer_group = self.consumer_group.clone().unwrap_or_default();
+        let message_model = self.message_model.unwrap_or_default();
+        for retry_times in 1..=TIMEOUT_CHECK_TIMES {
+            let timeout = QUERY_ASSIGNMENT_TIMEOUT / TIMEOUT_CHECK_TIMES * retry_times;
+            match self
+                .client_instance
+                .as_mut()
+                .unwrap()
+                .query_assignment(
+                    topic,
+                    &consumer_group,
+                    strategy_name,
+                    message_model,
+                    timeout as u64,
+                )
+                .await
+            {
+                Ok(_) => {
+                    self.topic_broker_rebalance
+                        .write()
+                        .await
+                        .insert(topic.clone(), topic.clone());
+                    return true;
+                }
+                Err(MQClientError::RemotingError(e)) => {
+                    warn!("tryQueryAssignment of {} failed, retry: {}", topic, e);
+                }
+                Err(e) => {
+                    error!("tryQueryAssignment error. {}", e);
+                    break;
+                }
+            }
+        }
+        self.topic_client_rebalance
+            .write()
+            .await
+            .insert(topic.clone(), topic.clone());
+        false
     }
 
     async fn truncate_message_queue_not_my_topic(&self) {
@@ -167,8 +218,77 @@ where
         topic_broker_rebalance.retain(|topic, _| sub_table.contains_key(topic));
     }
 
-    async fn get_rebalance_result_from_broker(&self, topic: &str, is_order: bool) -> bool {
-        unimplemented!("get_rebalance_result_from_broker")
+    async fn get_rebalance_result_from_broker(
+        &mut self,
+        topic: &CheetahString,
+        is_order: bool,
+    ) -> bool {
+        let strategy_name = self
+            .allocate_message_queue_strategy
+            .as_ref()
+            .map_or("", |strategy| strategy.get_name());
+        let consumer_group = self.consumer_group.clone().unwrap_or_default();
+        let assignments = match self
+            .client_instance
+            .as_mut()
+            .unwrap()
+            .query_assignment(
+                topic,
+                &consumer_group,
+                strategy_name,
+                self.message_model.unwrap_or_default(),
+                QUERY_ASSIGNMENT_TIMEOUT as u64,
+            )
+            .await
+        {
+            Ok(Some(assignments)) => assignments,
+            // no result is not an empty assignment, keep the queues consumed so far
+            Ok(None) => return false,
+            Err(e) => {
+                error!(
+                    "allocate message queue exception. strategy name: {}, ex: {}",
+                    strategy_name, e
+                );
+                return false;
+            }
+        };
+        let mut mq_set = HashSet::with_capacity(assignments.len());
+        for assignment in assignments {
+            let Some(mq) = assignment.message_queue else {
+                continue;
+            };
+            if assignment.mode == MessageRequestMode::Pop {
+                warn!(
+                    "broker assigned {} to {} in POP mode, which this consumer does not support, \
+                     skip it",
+                    mq, consumer_group
+                );
+                continue;
+            }
+            mq_set.insert(mq);
+        }
+        let changed = self
+            .update_process_queue_table_in_rebalance(topic, &mq_set, is_order)
+            .await;
+        if changed {
+            info!(
+                "broker rebalanced result changed. allocateMessageQueueStrategyName={}, group={}, \
+                 topic={}, clientId={}, assignmentSet={:?}",
+                strategy_name,
+                consumer_group,
+                topic,
+                self.client_instance.as_ref().unwrap().client_id,
+                mq_set
+            );
+            if let Some(mut sub_rebalance_impl) =
+                self.sub_rebalance_impl.as_ref().unwrap().upgrade()
+            {
+                sub_rebalance_impl
+                    .message_queue_changed(topic, &HashSet::new(), &mq_set)
+                    .await;
+            }
+        }
+        true
     }
 
     async fn update_process_queue_table_in_rebalance(
@@ -631,6 +751,22 @@ where
         }*/
     }
 
+    /// Drops every assigned queue, so that no pull or consume request runs for them any more.
+    pub async fn destroy(&mut self) {
+        for process_queue in self
+            .process_queue_table
+            .write()
+            .await
+            .drain()
+            .map(|(_, pq)| pq)
+        {
+            process_queue.set_dropped(true);
+        }
+        for (_, mut pop_process_queue) in self.pop_process_queue_table.write().await.drain() {
+            pop_process_queue.set_dropped(true);
+        }
+    }
+
     pub async fn unlock_all(&mut self, oneway: bool) {
         let broker_mqs = self.build_process_queue_table_by_broker_name().await;
         for (broker_name, mqs) in broker_mqs {
@@ -692,3 +828,26 @@ where
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consumer::consumer_impl::re_balance::rebalance_push_impl::RebalancePushImpl;
+
+    #[tokio::test]
+    async fn destroy_drops_and_clears_process_queues() {
+        let mut rebalance_impl = RebalanceImpl::<RebalancePushImpl>::new(None, None, None, None);
+        let process_queue = Arc::new(ProcessQueue::new());
+        let mq = MessageQueue::from_parts("TopicTest", "broker-a", 0);
+        rebalance_impl
+            .process_queue_table
+            .write()
+            .await
+            .insert(mq, process_queue.clone());
+
+        rebalance_impl.destroy().await;
+
+        assert!(process_queue.is_dropped());
+        assert!(rebalance_impl.process_queue_table.read().await.is_empty());
+    }
+}
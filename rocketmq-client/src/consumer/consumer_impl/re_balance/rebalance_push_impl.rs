@@ -182,23 +182,25 @@ impl Rebalance for RebalancePushImpl {
         let process_queue_table = self.rebalance_impl_inner.process_queue_table.read().await;
         let current_queue_count = process_queue_table.len();
         if current_queue_count != 0 {
+            // the topic thresholds are shared by the queues currently assigned to this consumer
             let pull_threshold_for_topic = self.consumer_config.pull_threshold_for_topic;
             if pull_threshold_for_topic != -1 {
-                let new_val = 1.max(pull_threshold_for_topic / current_queue_count as i32);
+                let new_val = 1.max(pull_threshold_for_topic / current_queue_count as i32) as u32;
                 info!(
                     "The pullThresholdForQueue is changed from {} to {}",
-                    pull_threshold_for_topic, new_val
+                    self.consumer_config.pull_threshold_for_queue, new_val
                 );
-                self.consumer_config.pull_threshold_for_topic = new_val;
+                self.consumer_config.pull_threshold_for_queue = new_val;
             }
             let pull_threshold_size_for_topic = self.consumer_config.pull_threshold_size_for_topic;
             if pull_threshold_size_for_topic != -1 {
-                let new_val = 1.max(pull_threshold_size_for_topic / current_queue_count as i32);
+                let new_val =
+                    1.max(pull_threshold_size_for_topic / current_queue_count as i32) as u32;
                 info!(
                     "The pullThresholdSizeForQueue is changed from {} to {}",
-                    pull_threshold_size_for_topic, new_val
+                    self.consumer_config.pull_threshold_size_for_queue, new_val
                 );
-                self.consumer_config.pull_threshold_size_for_topic = new_val;
+                self.consumer_config.pull_threshold_size_for_queue = new_val;
             }
         }
 
@@ -346,13 +348,23 @@ impl Rebalance for RebalancePushImpl {
                             .max_offset(mq)
                             .await?
                     } else {
+                        let consume_timestamp = self
+                            .consumer_config
+                            .consume_timestamp
+                            .clone()
+                            .unwrap_or_default();
                         let timestamp = util_all::parse_date(
-                            self.consumer_config.consume_timestamp.as_ref().unwrap(),
+                            consume_timestamp.as_str(),
                             util_all::YYYYMMDDHHMMSS,
                         )
-                        .unwrap()
+                        .ok_or_else(|| {
+                            MQClientError::MQClientErr(
+                                -1,
+                                format!("Illegal consume timestamp {}", consume_timestamp),
+                            )
+                        })?
                         .and_utc()
-                        .timestamp();
+                        .timestamp_millis();
                         self.rebalance_impl_inner
                             .client_instance
                             .as_mut()
@@ -517,7 +529,212 @@ impl Rebalance for RebalancePushImpl {
             }
     }
 
-    fn destroy(&mut self) {
-        unimplemented!()
+    async fn destroy(&mut self) {
+        self.rebalance_impl_inner.destroy().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_remoting::code::request_code::RequestCode;
+    use rocketmq_remoting::protocol::header::get_max_offset_request_header::GetMaxOffsetRequestHeader;
+    use rocketmq_remoting::protocol::header::get_max_offset_response_header::GetMaxOffsetResponseHeader;
+    use rocketmq_remoting::protocol::header::query_consumer_offset_request_header::QueryConsumerOffsetRequestHeader;
+    use rocketmq_remoting::protocol::header::query_consumer_offset_response_header::QueryConsumerOffsetResponseHeader;
+    use rocketmq_remoting::protocol::header::search_offset_request_header::SearchOffsetRequestHeader;
+    use rocketmq_remoting::protocol::header::search_offset_response_header::SearchOffsetResponseHeader;
+    use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+    use rocketmq_remoting::test_util::FakeBroker;
+    use rocketmq_remoting::test_util::FAKE_BROKER_NAME;
+
+    use super::*;
+    use crate::consumer::default_mq_push_consumer::DefaultMQPushConsumer;
+    use crate::consumer::store::offset_store::OffsetStore;
+    use crate::consumer::store::remote_broker_offset_store::RemoteBrokerOffsetStore;
+    use crate::implementation::mq_client_manager::MQClientManager;
+
+    const SEEDED_TOPIC: &str = "SeededTopic";
+    const EMPTY_TOPIC: &str = "EmptyTopic";
+    const COMMITTED_GROUP: &str = "committed_group";
+    const BROKEN_GROUP: &str = "broken_group";
+    /// 2024-01-01 00:00:00 UTC
+    const FIRST_STORE_TIMESTAMP: i64 = 1_704_067_200_000;
+
+    fn store_timestamps(topic: &str) -> Vec<i64> {
+        if topic == SEEDED_TOPIC {
+            (0..3).map(|i| FIRST_STORE_TIMESTAMP + i * 60_000).collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn query_consumer_offset(request: &RemotingCommand) -> RemotingCommand {
+        let request_header = request
+            .decode_command_custom_header::<QueryConsumerOffsetRequestHeader>()
+            .unwrap();
+        match request_header.consumer_group.as_str() {
+            COMMITTED_GROUP => RemotingCommand::create_response_command_with_header(
+                QueryConsumerOffsetResponseHeader { offset: Some(1) },
+            ),
+            BROKEN_GROUP => {
+                RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+            }
+            _ => RemotingCommand::create_response_command_with_code(ResponseCode::QueryNotFound),
+        }
+    }
+
+    fn max_offset(request: &RemotingCommand) -> RemotingCommand {
+        let request_header = request
+            .decode_command_custom_header::<GetMaxOffsetRequestHeader>()
+            .unwrap();
+        RemotingCommand::create_response_command_with_header(GetMaxOffsetResponseHeader {
+            offset: store_timestamps(&request_header.topic).len() as i64,
+        })
+    }
+
+    fn search_offset(request: &RemotingCommand) -> RemotingCommand {
+        let request_header = request
+            .decode_command_custom_header::<SearchOffsetRequestHeader>()
+            .unwrap();
+        let store_timestamps = store_timestamps(&request_header.topic);
+        let offset = store_timestamps
+            .iter()
+            .position(|store_timestamp| *store_timestamp >= request_header.timestamp)
+            .unwrap_or(store_timestamps.len());
+        RemotingCommand::create_response_command_with_header(SearchOffsetResponseHeader {
+            offset: offset as i64,
+        })
+    }
+
+    /// Starts a fake broker and a client instance whose name server and broker are both that
+    /// fake. Queue 0 of `SEEDED_TOPIC` holds three messages stored a minute apart, `EMPTY_TOPIC`
+    /// holds none. Only `COMMITTED_GROUP` has a committed offset and queries from `BROKEN_GROUP`
+    /// fail.
+    async fn client_instance(instance_name: &str) -> ArcMut<MQClientInstance> {
+        let addr = FakeBroker::bind()
+            .await
+            .on(RequestCode::QueryConsumerOffset, |_, request| {
+                query_consumer_offset(request)
+            })
+            .on(RequestCode::GetMaxOffset, |_, request| max_offset(request))
+            .on(RequestCode::SearchOffsetByTimestamp, |_, request| {
+                search_offset(request)
+            })
+            .spawn();
+        let client_config = ClientConfig {
+            client_ip: Some("127.0.0.1".into()),
+            instance_name: instance_name.into(),
+            vip_channel_enabled: false,
+            ..Default::default()
+        };
+        let instance = MQClientManager::get_instance()
+            .get_or_create_mq_client_instance(client_config, None)
+            .await;
+        instance
+            .mq_client_api_impl
+            .as_ref()
+            .unwrap()
+            .update_name_server_address_list(addr.as_str())
+            .await;
+        instance
+    }
+
+    async fn compute_pull_from_where(
+        instance: &ArcMut<MQClientInstance>,
+        consumer_group: &str,
+        consume_from_where: ConsumeFromWhere,
+        topic: &str,
+    ) -> i64 {
+        let mut consumer = DefaultMQPushConsumer::builder()
+            .name_server_addr("127.0.0.1:9876")
+            .consumer_group(consumer_group)
+            .consume_from_where(consume_from_where)
+            .consume_timestamp("20240101000130")
+            .build()
+            .unwrap();
+        let consumer_impl = consumer.default_mqpush_consumer_impl.as_mut().unwrap();
+        consumer_impl.offset_store = Some(ArcMut::new(OffsetStore::new_with_remote(
+            RemoteBrokerOffsetStore::new(instance.clone(), consumer_group.into()),
+        )));
+        consumer_impl
+            .rebalance_impl
+            .set_mq_client_factory(instance.clone());
+        let mq = MessageQueue::from_parts(topic, FAKE_BROKER_NAME, 0);
+        consumer_impl
+            .rebalance_impl
+            .compute_pull_from_where(&mq)
+            .await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn consume_from_last_offset() {
+        let instance = client_instance("consume-from-last-offset").await;
+        let last = ConsumeFromWhere::ConsumeFromLastOffset;
+        assert_eq!(
+            compute_pull_from_where(&instance, "first_boot", last, SEEDED_TOPIC).await,
+            3
+        );
+        assert_eq!(
+            compute_pull_from_where(&instance, "first_boot", last, EMPTY_TOPIC).await,
+            0
+        );
+        let retry_topic = mix_all::get_retry_topic("first_boot");
+        assert_eq!(
+            compute_pull_from_where(&instance, "first_boot", last, &retry_topic).await,
+            0
+        );
+        assert_eq!(
+            compute_pull_from_where(&instance, COMMITTED_GROUP, last, SEEDED_TOPIC).await,
+            1
+        );
+        assert_eq!(
+            compute_pull_from_where(&instance, BROKEN_GROUP, last, SEEDED_TOPIC).await,
+            -1
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn consume_from_first_offset() {
+        let instance = client_instance("consume-from-first-offset").await;
+        let first = ConsumeFromWhere::ConsumeFromFirstOffset;
+        assert_eq!(
+            compute_pull_from_where(&instance, "first_boot", first, SEEDED_TOPIC).await,
+            0
+        );
+        assert_eq!(
+            compute_pull_from_where(&instance, "first_boot", first, EMPTY_TOPIC).await,
+            0
+        );
+        assert_eq!(
+            compute_pull_from_where(&instance, COMMITTED_GROUP, first, SEEDED_TOPIC).await,
+            1
+        );
+        assert_eq!(
+            compute_pull_from_where(&instance, BROKEN_GROUP, first, SEEDED_TOPIC).await,
+            -1
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn consume_from_timestamp() {
+        let instance = client_instance("consume-from-timestamp").await;
+        let timestamp = ConsumeFromWhere::ConsumeFromTimestamp;
+        // 00:01:30 lies between the second and the third message
+        assert_eq!(
+            compute_pull_from_where(&instance, "first_boot", timestamp, SEEDED_TOPIC).await,
+            2
+        );
+        assert_eq!(
+            compute_pull_from_where(&instance, "first_boot", timestamp, EMPTY_TOPIC).await,
+            0
+        );
+        assert_eq!(
+            compute_pull_from_where(&instance, COMMITTED_GROUP, timestamp, SEEDED_TOPIC).await,
+            1
+        );
+        assert_eq!(
+            compute_pull_from_where(&instance, BROKEN_GROUP, timestamp, SEEDED_TOPIC).await,
+            -1
+        );
     }
 }
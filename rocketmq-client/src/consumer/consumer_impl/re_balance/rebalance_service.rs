@@ -65,7 +65,8 @@ impl RebalanceService {
         let (mut shutdown, tx_shutdown) = Shutdown::new(1);
         self.tx_shutdown = Some(tx_shutdown);
         tokio::spawn(async move {
-            let mut last_rebalance_timestamp = Instant::now();
+            // the first wakeup, sent when a consumer starts, is served right away
+            let mut last_rebalance_timestamp: Option<Instant> = None;
             let min_interval = *MIN_INTERVAL;
             let mut real_wait_interval = *WAIT_INTERVAL;
             info!(">>>>>>>>>RebalanceService started<<<<<<<<<");
@@ -78,7 +79,8 @@ impl RebalanceService {
                 if shutdown.is_shutdown() {
                     return;
                 }
-                let interval = Instant::now() - last_rebalance_timestamp;
+                let interval = last_rebalance_timestamp
+                    .map_or(min_interval, |timestamp| Instant::now() - timestamp);
                 if interval < min_interval {
                     real_wait_interval = min_interval - interval;
                 } else {
@@ -88,14 +90,15 @@ impl RebalanceService {
                     } else {
                         min_interval
                     };
-                    last_rebalance_timestamp = Instant::now();
+                    last_rebalance_timestamp = Some(Instant::now());
                 }
             }
         });
     }
 
+    /// Asks for a rebalance, remembered until the service loop next waits if it is busy.
     pub fn wakeup(&self) {
-        self.notify.notify_waiters();
+        self.notify.notify_one();
     }
 
     pub fn shutdown(&self) {
@@ -23,6 +23,7 @@ use cheetah_string::CheetahString;
 use rocketmq_common::common::consumer::consume_from_where::ConsumeFromWhere;
 use rocketmq_common::common::message::message_ext::MessageExt;
 use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::common::topic::TopicValidator;
 use rocketmq_common::utils::util_all;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::protocol::heartbeat::message_model::MessageModel;
@@ -91,6 +92,8 @@ pub struct ConsumerConfig {
     pub(crate) trace_dispatcher: Option<Arc<Box<dyn TraceDispatcher + Send + Sync>>>,
     pub(crate) client_rebalance: bool,
     pub(crate) rpc_hook: Option<Arc<Box<dyn RPCHook>>>,
+    /// `ext.`-prefixed extFields attached to every pull request, the broker echoes them back.
+    pub(crate) ext_fields: HashMap<CheetahString, CheetahString>,
 }
 
 impl ConsumerConfig {
@@ -222,6 +225,10 @@ impl ConsumerConfig {
         &self.rpc_hook
     }
 
+    pub fn ext_fields(&self) -> &HashMap<CheetahString, CheetahString> {
+        &self.ext_fields
+    }
+
     pub fn set_consumer_group(&mut self, consumer_group: CheetahString) {
         self.consumer_group = consumer_group;
     }
@@ -377,6 +384,10 @@ impl ConsumerConfig {
     pub fn set_rpc_hook(&mut self, rpc_hook: Option<Arc<Box<dyn RPCHook>>>) {
         self.rpc_hook = rpc_hook;
     }
+
+    pub fn set_ext_fields(&mut self, ext_fields: HashMap<CheetahString, CheetahString>) {
+        self.ext_fields = ext_fields;
+    }
 }
 
 impl Default for ConsumerConfig {
@@ -421,6 +432,7 @@ impl Default for ConsumerConfig {
             trace_dispatcher: None,
             client_rebalance: true,
             rpc_hook: None,
+            ext_fields: HashMap::new(),
         }
     }
 }
@@ -523,7 +535,10 @@ impl MQPushConsumer for DefaultMQPushConsumer {
             let mut dispatcher = AsyncTraceDispatcher::new(
                 self.consumer_config.consumer_group.as_str(),
                 Type::Consume,
-                self.client_config.trace_topic.clone().unwrap().as_str(),
+                self.client_config
+                    .trace_topic
+                    .as_ref()
+                    .map_or(TopicValidator::RMQ_SYS_TRACE_TOPIC, |topic| topic.as_str()),
                 self.consumer_config.rpc_hook.clone(),
             );
             dispatcher
@@ -546,7 +561,15 @@ impl MQPushConsumer for DefaultMQPushConsumer {
     }
 
     async fn shutdown(&mut self) {
-        todo!()
+        let await_termination_millis = self.consumer_config.await_termination_millis_when_shutdown;
+        if let Some(default_mqpush_consumer_impl) = self.default_mqpush_consumer_impl.as_mut() {
+            default_mqpush_consumer_impl
+                .shutdown(await_termination_millis)
+                .await;
+        }
+        if let Some(ref trace_dispatcher) = self.consumer_config.trace_dispatcher {
+            trace_dispatcher.shutdown();
+        }
     }
 
     fn register_message_listener_concurrently_fn<MLCFN>(&mut self, message_listener: MLCFN)
@@ -628,7 +651,11 @@ impl MQPushConsumer for DefaultMQPushConsumer {
         topic: &str,
         selector: Option<MessageSelector>,
     ) -> crate::Result<()> {
-        todo!()
+        self.default_mqpush_consumer_impl
+            .as_mut()
+            .unwrap()
+            .subscribe_with_selector(topic.into(), selector)
+            .await
     }
 
     async fn unsubscribe(&mut self, topic: &str) {
@@ -684,3 +711,633 @@ impl DefaultMQPushConsumer {
         self.consumer_config.consume_from_where = consume_from_where;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    use bytes::BytesMut;
+    use rocketmq_common::common::message::message_decoder;
+    use rocketmq_common::common::message::MessageTrait;
+    use rocketmq_common::common::mix_all;
+    use rocketmq_remoting::code::request_code::RequestCode;
+    use rocketmq_remoting::code::response_code::ResponseCode;
+    use rocketmq_remoting::protocol::body::get_consumer_listby_group_response_body::GetConsumerListByGroupResponseBody;
+    use rocketmq_remoting::protocol::header::consumer_send_msg_back_request_header::ConsumerSendMsgBackRequestHeader;
+    use rocketmq_remoting::protocol::header::get_max_offset_response_header::GetMaxOffsetResponseHeader;
+    use rocketmq_remoting::protocol::header::pull_message_request_header::PullMessageRequestHeader;
+    use rocketmq_remoting::protocol::header::pull_message_response_header::PullMessageResponseHeader;
+    use rocketmq_remoting::protocol::heartbeat::heartbeat_data::HeartbeatData;
+    use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+    use rocketmq_remoting::protocol::RemotingDeserializable;
+    use rocketmq_remoting::protocol::RemotingSerializable;
+    use rocketmq_remoting::test_util::FakeBroker;
+    use rocketmq_remoting::test_util::FAKE_BROKER_NAME;
+
+    use super::*;
+    use crate::consumer::store::read_offset_type::ReadOffsetType;
+
+    const TOPIC: &str = "BroadcastTopic";
+    const QUEUE_NUMS: i32 = 2;
+    const MESSAGES_PER_QUEUE: i64 = 3;
+
+    fn commit_log_offset(queue_id: i32, queue_offset: i64) -> i64 {
+        queue_id as i64 * MESSAGES_PER_QUEUE + queue_offset
+    }
+
+    /// What a fake broker serving `TOPIC`, whose queues each hold `MESSAGES_PER_QUEUE`
+    /// messages, was sent by its consumers.
+    #[derive(Clone, Default)]
+    struct BrokerState {
+        addr: CheetahString,
+        heartbeat_models: Arc<parking_lot::Mutex<Vec<MessageModel>>>,
+        client_ids: Arc<parking_lot::Mutex<HashSet<CheetahString>>>,
+        consumer_list_queried: Arc<AtomicBool>,
+        /// Commit log offsets whose first send-back is refused.
+        refused_send_backs: Arc<parking_lot::Mutex<HashSet<i64>>>,
+        /// Accepted send-back requests.
+        sent_back: Arc<parking_lot::Mutex<Vec<ConsumerSendMsgBackRequestHeader>>>,
+        /// Serves a full batch on every pull instead of `MESSAGES_PER_QUEUE` messages in total.
+        endless: bool,
+    }
+
+    impl BrokerState {
+        /// Starts the fake broker, returning its address.
+        async fn spawn(&mut self) -> CheetahString {
+            let broker = FakeBroker::bind().await.queue_nums(QUEUE_NUMS as u32);
+            self.addr = broker.addr();
+            let (heartbeat, consumer_list, pull, send_back) =
+                (self.clone(), self.clone(), self.clone(), self.clone());
+            broker
+                .on(RequestCode::HeartBeat, move |_, request| {
+                    heartbeat.heartbeat(request)
+                })
+                .on(RequestCode::GetConsumerListByGroup, move |_, _| {
+                    consumer_list.consumer_list()
+                })
+                .on(RequestCode::GetMaxOffset, |_, _| {
+                    RemotingCommand::create_response_command_with_header(
+                        GetMaxOffsetResponseHeader {
+                            offset: MESSAGES_PER_QUEUE,
+                        },
+                    )
+                })
+                .on(RequestCode::QueryConsumerOffset, |_, _| {
+                    RemotingCommand::create_response_command_with_code(ResponseCode::QueryNotFound)
+                })
+                .on_async(RequestCode::PullMessage, move |_, request| {
+                    let pull = pull.clone();
+                    async move { pull.pull(&request).await }
+                })
+                .on(RequestCode::ConsumerSendMsgBack, move |_, request| {
+                    send_back.send_back(request)
+                })
+                // the fallback of a refused send-back, fail it too so the client retries locally
+                .on(RequestCode::SendMessage, |_, _| {
+                    RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                })
+                .on(RequestCode::SendMessageV2, |_, _| {
+                    RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                })
+                .spawn()
+        }
+
+        fn heartbeat(&self, request: &RemotingCommand) -> RemotingCommand {
+            let heartbeat_data = HeartbeatData::decode(request.body().as_ref().unwrap()).unwrap();
+            self.client_ids
+                .lock()
+                .insert(heartbeat_data.client_id.clone());
+            self.heartbeat_models.lock().extend(
+                heartbeat_data
+                    .consumer_data_set
+                    .iter()
+                    .map(|consumer_data| consumer_data.message_model),
+            );
+            RemotingCommand::create_response_command()
+        }
+
+        fn consumer_list(&self) -> RemotingCommand {
+            self.consumer_list_queried.store(true, Ordering::Release);
+            let body = GetConsumerListByGroupResponseBody {
+                consumer_id_list: self.client_ids.lock().iter().cloned().collect(),
+            };
+            RemotingCommand::create_response_command().set_body(body.encode())
+        }
+
+        async fn pull(&self, request: &RemotingCommand) -> RemotingCommand {
+            let request_header = request
+                .decode_command_custom_header::<PullMessageRequestHeader>()
+                .unwrap();
+            let max_offset = if self.endless {
+                request_header.queue_offset + request_header.max_msg_nums as i64
+            } else {
+                MESSAGES_PER_QUEUE
+            };
+            // only the subscribed topic holds messages, retry topics stay empty
+            let queue_offset = if request_header.topic == TOPIC {
+                request_header.queue_offset
+            } else {
+                max_offset
+            };
+            let mut response_header = PullMessageResponseHeader {
+                suggest_which_broker_id: Some(mix_all::MASTER_ID),
+                next_begin_offset: Some(max_offset),
+                min_offset: Some(0),
+                max_offset: Some(max_offset),
+                ..Default::default()
+            };
+            if queue_offset >= max_offset {
+                // stands in for the broker holding the long polling request
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                response_header.next_begin_offset = Some(queue_offset);
+                return RemotingCommand::create_response_command_with_code(
+                    ResponseCode::PullNotFound,
+                )
+                .set_command_custom_header(response_header);
+            }
+            let mut body = BytesMut::new();
+            for offset in queue_offset..max_offset {
+                let mut message_ext = MessageExt::default();
+                message_ext.set_topic(TOPIC.into());
+                message_ext.set_body(bytes::Bytes::from(format!("message-{offset}")));
+                message_ext.queue_id = request_header.queue_id.unwrap_or_default();
+                message_ext.queue_offset = offset;
+                message_ext.commit_log_offset = commit_log_offset(message_ext.queue_id, offset);
+                message_ext.store_host = self.addr.parse().unwrap();
+                body.extend_from_slice(&message_decoder::encode(&message_ext, false).unwrap());
+            }
+            RemotingCommand::create_response_command()
+                .set_command_custom_header(response_header)
+                .set_body(body.freeze())
+        }
+
+        fn send_back(&self, request: &RemotingCommand) -> RemotingCommand {
+            let request_header = request
+                .decode_command_custom_header::<ConsumerSendMsgBackRequestHeader>()
+                .unwrap();
+            if self
+                .refused_send_backs
+                .lock()
+                .remove(&request_header.offset)
+            {
+                return RemotingCommand::create_response_command_with_code(
+                    ResponseCode::SystemError,
+                );
+            }
+            self.sent_back.lock().push(request_header);
+            RemotingCommand::create_response_command()
+        }
+    }
+
+    #[derive(Default)]
+    struct CollectingListener {
+        received: Arc<parking_lot::Mutex<HashSet<(i32, i64)>>>,
+    }
+
+    impl MessageListenerConcurrently for CollectingListener {
+        fn consume_message(
+            &self,
+            msgs: &[&MessageExt],
+            _context: &mut ConsumeConcurrentlyContext,
+        ) -> crate::Result<ConsumeConcurrentlyStatus> {
+            let mut received = self.received.lock();
+            for msg in msgs {
+                received.insert((msg.queue_id, msg.queue_offset));
+            }
+            Ok(ConsumeConcurrentlyStatus::ConsumeSuccess)
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn broadcasting_consumers_each_receive_every_message() {
+        let offset_dir =
+            std::env::temp_dir().join(format!("rocketmq-broadcast-test-{}", std::process::id()));
+        std::env::set_var("rocketmq.client.localOffsetStoreDir", &offset_dir);
+
+        let mut broker = BrokerState::default();
+        let addr = broker.spawn().await;
+
+        let consumer_group = format!("broadcast_group_{}", get_current_millis());
+        let mut consumers = Vec::new();
+        let mut received = Vec::new();
+        for instance_name in ["broadcast-a", "broadcast-b"] {
+            let client_config = ClientConfig {
+                client_ip: Some("127.0.0.1".into()),
+                instance_name: instance_name.into(),
+                namesrv_addr: Some(addr.clone()),
+                vip_channel_enabled: false,
+                ..Default::default()
+            };
+            let mut consumer = DefaultMQPushConsumer::builder()
+                .client_config(client_config)
+                .consumer_group(consumer_group.as_str())
+                .message_model(MessageModel::Broadcasting)
+                .consume_from_where(ConsumeFromWhere::ConsumeFromFirstOffset)
+                .build()
+                .unwrap();
+            consumer
+                .default_mqpush_consumer_impl
+                .as_mut()
+                .unwrap()
+                .subscribe(TOPIC.into(), "*".into())
+                .await
+                .unwrap();
+            let listener = CollectingListener::default();
+            received.push(listener.received.clone());
+            consumer.register_message_listener_concurrently(listener);
+            consumer.start().await.unwrap();
+            consumers.push(consumer);
+        }
+
+        let expected = (0..QUEUE_NUMS)
+            .flat_map(|queue_id| (0..MESSAGES_PER_QUEUE).map(move |offset| (queue_id, offset)))
+            .collect::<HashSet<_>>();
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(20);
+        while received.iter().any(|received| *received.lock() != expected) {
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "not every consumer received every message"
+            );
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        for consumer in &mut consumers {
+            consumer.shutdown().await;
+        }
+        assert!(!broker.consumer_list_queried.load(Ordering::Acquire));
+        let heartbeat_models = broker.heartbeat_models.lock();
+        assert!(!heartbeat_models.is_empty());
+        assert!(heartbeat_models
+            .iter()
+            .all(|message_model| *message_model == MessageModel::Broadcasting));
+        let _ = std::fs::remove_dir_all(offset_dir);
+    }
+
+    /// Acknowledges a batch up to its first message that was not redelivered yet, asking for
+    /// the rest to be consumed again at delay level 5.
+    #[derive(Default)]
+    struct PartiallyFailingListener {
+        consumed: Arc<parking_lot::Mutex<Vec<(i32, i64, i32)>>>,
+    }
+
+    impl MessageListenerConcurrently for PartiallyFailingListener {
+        fn consume_message(
+            &self,
+            msgs: &[&MessageExt],
+            context: &mut ConsumeConcurrentlyContext,
+        ) -> crate::Result<ConsumeConcurrentlyStatus> {
+            let mut consumed = self.consumed.lock();
+            for (index, msg) in msgs.iter().enumerate() {
+                if msg.queue_offset > 0 && msg.reconsume_times == 0 {
+                    context.set_ack_index(index as i32 - 1);
+                    context.set_delay_level_when_next_consume(5);
+                    break;
+                }
+                consumed.push((msg.queue_id, msg.queue_offset, msg.reconsume_times));
+            }
+            Ok(ConsumeConcurrentlyStatus::ConsumeSuccess)
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn clustering_consumer_sends_failed_messages_back() {
+        let mut broker = BrokerState {
+            refused_send_backs: Arc::new(parking_lot::Mutex::new(HashSet::from([
+                commit_log_offset(0, 2),
+            ]))),
+            ..Default::default()
+        };
+        let addr = broker.spawn().await;
+
+        let consumer_group = format!("send_back_group_{}", get_current_millis());
+        let client_config = ClientConfig {
+            client_ip: Some("127.0.0.1".into()),
+            instance_name: "send-back".into(),
+            namesrv_addr: Some(addr.clone()),
+            vip_channel_enabled: false,
+            ..Default::default()
+        };
+        let mut consumer = DefaultMQPushConsumer::builder()
+            .client_config(client_config)
+            .consumer_group(consumer_group.as_str())
+            .consume_from_where(ConsumeFromWhere::ConsumeFromFirstOffset)
+            .consume_message_batch_max_size(MESSAGES_PER_QUEUE as u32)
+            .build()
+            .unwrap();
+        consumer
+            .default_mqpush_consumer_impl
+            .as_mut()
+            .unwrap()
+            .subscribe(TOPIC.into(), "*".into())
+            .await
+            .unwrap();
+        let listener = PartiallyFailingListener::default();
+        let consumed = listener.consumed.clone();
+        consumer.register_message_listener_concurrently(listener);
+        consumer.start().await.unwrap();
+
+        // the refused send-back is consumed again locally once its 5s back-off is over
+        let offset_store = consumer
+            .default_mqpush_consumer_impl
+            .as_ref()
+            .unwrap()
+            .offset_store
+            .clone()
+            .unwrap();
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(20);
+        for queue_id in 0..QUEUE_NUMS {
+            let mq = MessageQueue::from_parts(TOPIC, FAKE_BROKER_NAME, queue_id);
+            while offset_store
+                .read_offset(&mq, ReadOffsetType::ReadFromMemory)
+                .await
+                != MESSAGES_PER_QUEUE
+            {
+                assert!(
+                    tokio::time::Instant::now() < deadline,
+                    "offset of queue {queue_id} was not advanced past the sent back messages"
+                );
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        }
+        consumer.shutdown().await;
+
+        let mut consumed = consumed.lock().clone();
+        consumed.sort();
+        assert_eq!(consumed, vec![(0, 0, 0), (0, 2, 1), (1, 0, 0)]);
+        let sent_back = broker.sent_back.lock();
+        let mut offsets = sent_back
+            .iter()
+            .map(|request_header| request_header.offset)
+            .collect::<Vec<_>>();
+        offsets.sort();
+        assert_eq!(
+            offsets,
+            vec![
+                commit_log_offset(0, 1),
+                commit_log_offset(1, 1),
+                commit_log_offset(1, 2)
+            ]
+        );
+        for request_header in sent_back.iter() {
+            assert_eq!(request_header.group.as_str(), consumer_group);
+            assert_eq!(request_header.delay_level, 5);
+            assert_eq!(request_header.max_reconsume_times, Some(16));
+            assert_eq!(request_header.origin_topic.as_deref(), Some(TOPIC));
+        }
+    }
+
+    /// Holds every message until `released` is set.
+    struct BlockedListener {
+        released: Arc<AtomicBool>,
+    }
+
+    impl MessageListenerConcurrently for BlockedListener {
+        fn consume_message(
+            &self,
+            _msgs: &[&MessageExt],
+            _context: &mut ConsumeConcurrentlyContext,
+        ) -> crate::Result<ConsumeConcurrentlyStatus> {
+            while !self.released.load(Ordering::Acquire) {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            Ok(ConsumeConcurrentlyStatus::ConsumeSuccess)
+        }
+    }
+
+    const FLOW_CONTROL_PULL_BATCH_SIZE: u32 = 8;
+
+    /// Consumes an endless topic with a blocked listener and returns, once the cached messages
+    /// stopped growing, the message count and offset span cached for each queue along with the
+    /// per-queue count threshold then in force.
+    async fn plateaued_cache(
+        instance_name: &str,
+        configure: impl FnOnce(DefaultMQPushConsumerBuilder) -> DefaultMQPushConsumerBuilder,
+    ) -> (Vec<(u64, u64)>, u32) {
+        let mut broker = BrokerState {
+            endless: true,
+            ..Default::default()
+        };
+        let addr = broker.spawn().await;
+
+        let client_config = ClientConfig {
+            client_ip: Some("127.0.0.1".into()),
+            instance_name: instance_name.into(),
+            namesrv_addr: Some(addr),
+            vip_channel_enabled: false,
+            ..Default::default()
+        };
+        let builder = DefaultMQPushConsumer::builder()
+            .client_config(client_config)
+            .consumer_group(format!("{}_{}", instance_name, get_current_millis()))
+            .consume_from_where(ConsumeFromWhere::ConsumeFromFirstOffset)
+            .pull_batch_size(FLOW_CONTROL_PULL_BATCH_SIZE);
+        let mut consumer = configure(builder).build().unwrap();
+        consumer
+            .default_mqpush_consumer_impl
+            .as_mut()
+            .unwrap()
+            .subscribe(TOPIC.into(), "*".into())
+            .await
+            .unwrap();
+        let released = Arc::new(AtomicBool::new(false));
+        consumer.register_message_listener_concurrently(BlockedListener {
+            released: released.clone(),
+        });
+        consumer.start().await.unwrap();
+
+        let process_queue_table = consumer
+            .default_mqpush_consumer_impl
+            .as_ref()
+            .unwrap()
+            .rebalance_impl
+            .rebalance_impl_inner
+            .process_queue_table
+            .clone();
+        let mut previous = Vec::new();
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(20);
+        let cached = loop {
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "the cached messages kept growing"
+            );
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            let mut cached = Vec::new();
+            for (mq, process_queue) in process_queue_table.read().await.iter() {
+                // the retry topic queues stay empty
+                if mq.get_topic() == TOPIC {
+                    cached.push((
+                        process_queue.msg_count(),
+                        process_queue.get_max_span().await,
+                    ));
+                }
+            }
+            cached.sort();
+            if cached.len() == QUEUE_NUMS as usize
+                && cached.iter().all(|(count, _)| *count > 0)
+                && cached == previous
+            {
+                break cached;
+            }
+            previous = cached;
+        };
+        let pull_threshold_for_queue = consumer.consumer_config.pull_threshold_for_queue;
+        released.store(true, Ordering::Release);
+        consumer.shutdown().await;
+        (cached, pull_threshold_for_queue)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn slow_listener_stops_the_pulls_at_the_queue_thresholds() {
+        let (cached, _) = plateaued_cache("flow-control-count", |builder| {
+            builder.pull_threshold_for_queue(20)
+        })
+        .await;
+        for (count, _) in cached {
+            assert!(
+                (20..=20 + FLOW_CONTROL_PULL_BATCH_SIZE as u64).contains(&count),
+                "{count} messages cached"
+            );
+        }
+
+        let (cached, _) = plateaued_cache("flow-control-span", |builder| {
+            builder.consume_concurrently_max_span(30)
+        })
+        .await;
+        for (_, span) in cached {
+            assert!(
+                (30..=30 + FLOW_CONTROL_PULL_BATCH_SIZE as u64).contains(&span),
+                "span of {span} cached"
+            );
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn topic_threshold_is_shared_by_the_assigned_queues() {
+        let (cached, pull_threshold_for_queue) = plateaued_cache("flow-control-topic", |builder| {
+            builder.pull_threshold_for_topic(48)
+        })
+        .await;
+        // the queues of the retry topic take their share as well
+        assert_eq!(pull_threshold_for_queue, 48 / (2 * QUEUE_NUMS) as u32);
+        for (count, _) in cached {
+            assert!(
+                (12..=12 + FLOW_CONTROL_PULL_BATCH_SIZE as u64).contains(&count),
+                "{count} messages cached"
+            );
+        }
+    }
+
+    /// Never returns from `STUCK` until `released` is set, consumes everything else.
+    struct StuckListener {
+        released: Arc<AtomicBool>,
+        received: Arc<parking_lot::Mutex<HashSet<(i32, i64)>>>,
+    }
+
+    const STUCK: (i32, i64) = (0, 1);
+
+    impl MessageListenerConcurrently for StuckListener {
+        fn consume_message(
+            &self,
+            msgs: &[&MessageExt],
+            _context: &mut ConsumeConcurrentlyContext,
+        ) -> crate::Result<ConsumeConcurrentlyStatus> {
+            for msg in msgs {
+                if (msg.queue_id, msg.queue_offset) == STUCK {
+                    while !self.released.load(Ordering::Acquire) {
+                        std::thread::sleep(Duration::from_millis(10));
+                    }
+                } else {
+                    self.received
+                        .lock()
+                        .insert((msg.queue_id, msg.queue_offset));
+                }
+            }
+            Ok(ConsumeConcurrentlyStatus::ConsumeSuccess)
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn expired_message_is_sent_back_and_the_offset_moves_past_it() {
+        let mut broker = BrokerState::default();
+        let addr = broker.spawn().await;
+
+        let client_config = ClientConfig {
+            client_ip: Some("127.0.0.1".into()),
+            instance_name: "expired-message".into(),
+            namesrv_addr: Some(addr.clone()),
+            vip_channel_enabled: false,
+            ..Default::default()
+        };
+        let mut consumer = DefaultMQPushConsumer::builder()
+            .client_config(client_config)
+            .consumer_group(format!("expired_group_{}", get_current_millis()))
+            .consume_from_where(ConsumeFromWhere::ConsumeFromFirstOffset)
+            .build()
+            .unwrap();
+        consumer
+            .default_mqpush_consumer_impl
+            .as_mut()
+            .unwrap()
+            .subscribe(TOPIC.into(), "*".into())
+            .await
+            .unwrap();
+        let released = Arc::new(AtomicBool::new(false));
+        let received = Arc::new(parking_lot::Mutex::new(HashSet::new()));
+        consumer.register_message_listener_concurrently(StuckListener {
+            released: released.clone(),
+            received: received.clone(),
+        });
+        consumer.start().await.unwrap();
+
+        let consumer_impl = consumer.default_mqpush_consumer_impl.clone().unwrap();
+        let offset_store = consumer_impl.offset_store.clone().unwrap();
+        let stuck_queue = MessageQueue::from_parts(TOPIC, FAKE_BROKER_NAME, STUCK.0);
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(20);
+        while received.lock().len() < (QUEUE_NUMS as i64 * MESSAGES_PER_QUEUE - 1) as usize {
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "the other messages were not consumed"
+            );
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(
+            offset_store
+                .read_offset(&stuck_queue, ReadOffsetType::ReadFromMemory)
+                .await,
+            STUCK.1
+        );
+
+        // everything in flight has expired with a zero timeout
+        consumer.consumer_config.consume_timeout = 0;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        consumer_impl
+            .consume_message_service
+            .as_ref()
+            .unwrap()
+            .get_consume_message_concurrently_service_weak()
+            .upgrade()
+            .unwrap()
+            .clean_expire_msg()
+            .await;
+
+        assert_eq!(
+            offset_store
+                .read_offset(&stuck_queue, ReadOffsetType::ReadFromMemory)
+                .await,
+            MESSAGES_PER_QUEUE
+        );
+        let sent_back = broker
+            .sent_back
+            .lock()
+            .iter()
+            .map(|request_header| (request_header.offset, request_header.delay_level))
+            .collect::<Vec<_>>();
+        assert_eq!(sent_back, vec![(commit_log_offset(STUCK.0, STUCK.1), 3)]);
+
+        released.store(true, Ordering::Release);
+        consumer.shutdown().await;
+    }
+}
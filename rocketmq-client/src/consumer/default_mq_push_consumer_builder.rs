@@ -20,16 +20,24 @@ use std::sync::Arc;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::consumer::consume_from_where::ConsumeFromWhere;
 use rocketmq_remoting::protocol::heartbeat::message_model::MessageModel;
+use rocketmq_remoting::protocol::remoting_command::passthrough_ext_field_key;
 use rocketmq_remoting::runtime::RPCHook;
 use rocketmq_rust::ArcMut;
 
 use crate::base::client_config::ClientConfig;
+use crate::base::validators::Validators;
 use crate::consumer::allocate_message_queue_strategy::AllocateMessageQueueStrategy;
 use crate::consumer::default_mq_push_consumer::ConsumerConfig;
 use crate::consumer::default_mq_push_consumer::DefaultMQPushConsumer;
+use crate::consumer::listener::message_listener::MessageListener;
+use crate::consumer::listener::message_listener_concurrently::MessageListenerConcurrently;
+use crate::consumer::listener::message_listener_orderly::MessageListenerOrderly;
 use crate::consumer::message_queue_listener::MessageQueueListener;
 use crate::consumer::mq_push_consumer::MQPushConsumer;
+use crate::consumer::rebalance_strategy::allocate_message_queue_averagely::AllocateMessageQueueAveragely;
+use crate::consumer::rebalance_strategy::allocate_message_queue_sticky::AllocateMessageQueueSticky;
 use crate::trace::trace_dispatcher::TraceDispatcher;
+use crate::Result;
 
 pub struct DefaultMQPushConsumerBuilder {
     client_config: Option<ClientConfig>,
@@ -66,7 +74,12 @@ pub struct DefaultMQPushConsumerBuilder {
     await_termination_millis_when_shutdown: Option<u64>,
     trace_dispatcher: Option<Arc<Box<dyn TraceDispatcher + Send + Sync>>>,
     client_rebalance: Option<bool>,
+    sticky_rebalance: Option<bool>,
     rpc_hook: Option<Arc<Box<dyn RPCHook>>>,
+    ext_fields: HashMap<CheetahString, CheetahString>,
+    message_listener: Option<MessageListener>,
+    enable_msg_trace: Option<bool>,
+    customized_trace_topic: Option<CheetahString>,
 }
 
 impl Default for DefaultMQPushConsumerBuilder {
@@ -104,7 +117,12 @@ impl Default for DefaultMQPushConsumerBuilder {
             await_termination_millis_when_shutdown: None,
             trace_dispatcher: None,
             client_rebalance: None,
+            sticky_rebalance: None,
             rpc_hook: None,
+            ext_fields: HashMap::new(),
+            message_listener: None,
+            enable_msg_trace: None,
+            customized_trace_topic: None,
         }
     }
 }
@@ -146,6 +164,8 @@ impl DefaultMQPushConsumerBuilder {
         self
     }
 
+    /// Strategy spreading the queues over the group, e.g.
+    /// [`AllocateMessageQueueConsistentHash`](crate::consumer::rebalance_strategy::allocate_message_queue_consistent_hash::AllocateMessageQueueConsistentHash).
     pub fn allocate_message_queue_strategy(
         mut self,
         allocate_message_queue_strategy: Arc<dyn AllocateMessageQueueStrategy>,
@@ -307,13 +327,73 @@ impl DefaultMQPushConsumerBuilder {
         self
     }
 
+    /// Keeps queues with their consumer when the group changes, see
+    /// [`AllocateMessageQueueSticky`]. The assignment is kept by the broker, so client-side
+    /// rebalance is turned off unless set explicitly.
+    pub fn sticky_rebalance(mut self, sticky_rebalance: bool) -> Self {
+        self.sticky_rebalance = Some(sticky_rebalance);
+        self
+    }
+
     pub fn rpc_hook(mut self, rpc_hook: Option<Arc<Box<dyn RPCHook>>>) -> Self {
         self.rpc_hook = rpc_hook;
         self
     }
 
-    // Build method to create a ConsumerConfig instance
-    pub fn build(mut self) -> DefaultMQPushConsumer {
+    /// Attaches `key`, prefixed with `ext.`, to every pull request. The broker echoes it back in
+    /// the response.
+    pub fn with_ext_field(
+        mut self,
+        key: impl Into<CheetahString>,
+        value: impl Into<CheetahString>,
+    ) -> Self {
+        self.ext_fields
+            .insert(passthrough_ext_field_key(key), value.into());
+        self
+    }
+
+    /// Consumes the subscribed messages concurrently with `message_listener`.
+    pub fn message_listener_concurrently(
+        mut self,
+        message_listener: impl MessageListenerConcurrently + 'static,
+    ) -> Self {
+        self.message_listener = Some(MessageListener {
+            message_listener_concurrently: Some((Some(Arc::new(Box::new(message_listener))), None)),
+            message_listener_orderly: None,
+        });
+        self
+    }
+
+    /// Consumes the subscribed messages queue by queue, in order, with `message_listener`.
+    pub fn message_listener_orderly(
+        mut self,
+        message_listener: impl MessageListenerOrderly + 'static,
+    ) -> Self {
+        self.message_listener = Some(MessageListener {
+            message_listener_concurrently: None,
+            message_listener_orderly: Some((Some(Arc::new(Box::new(message_listener))), None)),
+        });
+        self
+    }
+
+    /// Records the messages consumed by this consumer to the trace topic.
+    pub fn enable_msg_trace(mut self, enable_msg_trace: bool) -> Self {
+        self.enable_msg_trace = Some(enable_msg_trace);
+        self
+    }
+
+    /// Trace topic used instead of `RMQ_SYS_TRACE_TOPIC` when message trace is enabled.
+    pub fn customized_trace_topic(
+        mut self,
+        customized_trace_topic: impl Into<CheetahString>,
+    ) -> Self {
+        self.customized_trace_topic = Some(customized_trace_topic.into());
+        self
+    }
+
+    /// Builds the consumer, refusing an invalid group, name server list or option combination
+    /// instead of failing later in `start()`.
+    pub fn build(mut self) -> Result<DefaultMQPushConsumer> {
         let mut consumer_config = ConsumerConfig::default();
         if let Some(consumer_group) = self.consumer_group {
             consumer_config.consumer_group = consumer_group;
@@ -325,17 +405,28 @@ impl DefaultMQPushConsumerBuilder {
             consumer_config.consume_from_where = consume_from_where;
         }
 
-        consumer_config.consume_timestamp = self.consume_timestamp.take();
+        if let Some(consume_timestamp) = self.consume_timestamp.take() {
+            consumer_config.consume_timestamp = Some(consume_timestamp);
+        }
 
         if self.allocate_message_queue_strategy.is_some() {
             consumer_config.allocate_message_queue_strategy =
                 self.allocate_message_queue_strategy.take();
         }
+        if self.sticky_rebalance == Some(true) {
+            let strategy = consumer_config
+                .allocate_message_queue_strategy
+                .take()
+                .unwrap_or_else(|| Arc::new(AllocateMessageQueueAveragely));
+            consumer_config.allocate_message_queue_strategy =
+                Some(Arc::new(AllocateMessageQueueSticky::new(strategy)));
+            consumer_config.client_rebalance = false;
+        }
         if let Some(subscription) = self.subscription {
             consumer_config.subscription = subscription;
         }
 
-        //consumer_config.message_listener = self.message_listener.take();
+        consumer_config.message_listener = self.message_listener.take().map(ArcMut::new);
 
         consumer_config.message_queue_listener = self.message_queue_listener.take();
 
@@ -410,18 +501,24 @@ impl DefaultMQPushConsumerBuilder {
             consumer_config.client_rebalance = client_rebalance;
         }
         consumer_config.rpc_hook = self.rpc_hook.clone();
+        consumer_config.ext_fields = self.ext_fields.clone();
+
+        let mut client_config = self.client_config.take().unwrap_or_default();
+        if let Some(enable_msg_trace) = self.enable_msg_trace {
+            client_config.enable_trace = enable_msg_trace;
+        }
+        if let Some(customized_trace_topic) = self.customized_trace_topic.take() {
+            client_config.trace_topic = Some(customized_trace_topic);
+        }
+        Validators::check_client_config(&client_config)?;
+        Validators::check_consumer_config(&consumer_config)?;
 
-        let mut consumer = DefaultMQPushConsumer::new(
-            self.client_config.take().unwrap_or_default(),
-            consumer_config,
-        );
+        let mut consumer = DefaultMQPushConsumer::new(client_config, consumer_config);
         if self.topic_sub_expression.0.is_some() && self.topic_sub_expression.1.is_some() {
             let topic = self.topic_sub_expression.0.take().unwrap();
             let sub_expression = self.topic_sub_expression.1.take().unwrap();
-            consumer
-                .subscribe(&topic, &sub_expression)
-                .expect("subscribe failed");
+            consumer.subscribe(&topic, &sub_expression)?;
         }
-        consumer
+        Ok(consumer)
     }
 }
@@ -26,7 +26,7 @@ pub trait MessageListenerConcurrently: Sync + Send {
     fn consume_message(
         &self,
         msgs: &[&MessageExt],
-        context: &ConsumeConcurrentlyContext,
+        context: &mut ConsumeConcurrentlyContext,
     ) -> Result<ConsumeConcurrentlyStatus>;
 }
 
@@ -15,6 +15,10 @@
  * limitations under the License.
  */
 
+use rocketmq_common::common::filter::expression_type::ExpressionType;
+
+/// Filter expression a push consumer subscribes with, either a `||`-separated tag list or a
+/// SQL92 expression over message properties.
 pub struct MessageSelector {
     type_: String,
     expression: String,
@@ -29,11 +33,11 @@ impl MessageSelector {
     }
 
     pub fn by_sql(sql: &str) -> Self {
-        Self::new("SQL92", sql)
+        Self::new(ExpressionType::SQL92, sql)
     }
 
     pub fn by_tag(tag: &str) -> Self {
-        Self::new("TAG", tag)
+        Self::new(ExpressionType::TAG, tag)
     }
 
     pub fn get_expression_type(&self) -> &str {
@@ -44,3 +48,47 @@ impl MessageSelector {
         &self.expression
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cheetah_string::CheetahString;
+    use rocketmq_remoting::protocol::filter::filter_api::FilterAPI;
+    use rocketmq_remoting::protocol::heartbeat::subscription_data::SubscriptionData;
+
+    use super::*;
+
+    fn subscription_data(selector: &MessageSelector) -> Result<SubscriptionData, String> {
+        FilterAPI::build(
+            &CheetahString::from_static_str("SelectorTopic"),
+            &CheetahString::from(selector.get_expression()),
+            Some(CheetahString::from(selector.get_expression_type())),
+        )
+    }
+
+    #[test]
+    fn by_tag_builds_a_tag_subscription() {
+        let selector = MessageSelector::by_tag("tagA || tagB");
+        assert_eq!(selector.get_expression_type(), ExpressionType::TAG);
+
+        let subscription_data = subscription_data(&selector).unwrap();
+        assert_eq!(subscription_data.expression_type, ExpressionType::TAG);
+        assert!(subscription_data.tags_set.contains("tagA"));
+        assert!(subscription_data.tags_set.contains("tagB"));
+    }
+
+    #[test]
+    fn by_sql_keeps_the_expression_untouched() {
+        let selector = MessageSelector::by_sql("a > 5 || b = 'x'");
+        assert_eq!(selector.get_expression_type(), ExpressionType::SQL92);
+
+        let subscription_data = subscription_data(&selector).unwrap();
+        assert_eq!(subscription_data.expression_type, ExpressionType::SQL92);
+        assert_eq!(subscription_data.sub_string, "a > 5 || b = 'x'");
+        assert!(subscription_data.tags_set.is_empty());
+    }
+
+    #[test]
+    fn by_tag_with_an_empty_tag_is_rejected() {
+        assert!(subscription_data(&MessageSelector::by_tag("tagA|| ||tagB")).is_err());
+    }
+}
@@ -173,29 +173,16 @@ impl PullCallback for DefaultPullCallback {
         let message_queue_inner = self.message_queue_inner.take().unwrap();
         let pull_request = self.pull_request.take().unwrap();
         let topic = message_queue_inner.get_topic().to_string();
+        let broker_response_code = err
+            .downcast_ref::<MQClientError>()
+            .filter(|er| matches!(er, MQClientError::MQBrokerError(..)))
+            .map(|er| ResponseCode::from(er.response_code()));
         if !topic.starts_with(mix_all::RETRY_GROUP_TOPIC_PREFIX) {
-            if let Some(er) = err.downcast_ref::<MQClientError>() {
-                match er {
-                    MQClientError::MQBrokerError(code, msg, addr) => {
-                        if ResponseCode::from(*code) == ResponseCode::SubscriptionNotLatest {
-                            warn!(
-                                "the subscription is not latest, group={}",
-                                push_consumer_impl.consumer_config.consumer_group,
-                            );
-                        } else {
-                            warn!(
-                                "execute the pull request exception, group={}",
-                                push_consumer_impl.consumer_config.consumer_group
-                            );
-                        }
-                    }
-                    _ => {
-                        warn!(
-                            "execute the pull request exception, group={}",
-                            push_consumer_impl.consumer_config.consumer_group
-                        );
-                    }
-                }
+            if broker_response_code == Some(ResponseCode::SubscriptionNotLatest) {
+                warn!(
+                    "the subscription is not latest, group={}",
+                    push_consumer_impl.consumer_config.consumer_group,
+                );
             } else {
                 warn!(
                     "execute the pull request exception, group={}",
@@ -203,17 +190,8 @@ impl PullCallback for DefaultPullCallback {
                 );
             }
         }
-        let time_delay = if let Some(er) = err.downcast_ref::<MQClientError>() {
-            match er {
-                MQClientError::MQBrokerError(code, _, _) => {
-                    if ResponseCode::from(*code) == ResponseCode::FlowControl {
-                        PULL_TIME_DELAY_MILLS_WHEN_BROKER_FLOW_CONTROL
-                    } else {
-                        push_consumer_impl.pull_time_delay_mills_when_exception
-                    }
-                }
-                _ => push_consumer_impl.pull_time_delay_mills_when_exception,
-            }
+        let time_delay = if broker_response_code == Some(ResponseCode::FlowControl) {
+            PULL_TIME_DELAY_MILLS_WHEN_BROKER_FLOW_CONTROL
         } else {
             push_consumer_impl.pull_time_delay_mills_when_exception
         };
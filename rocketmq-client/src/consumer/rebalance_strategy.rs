@@ -15,7 +15,9 @@
  * limitations under the License.
  */
 pub mod allocate_message_queue_averagely;
-mod allocate_message_queue_averagely_by_circle;
+pub mod allocate_message_queue_averagely_by_circle;
+pub mod allocate_message_queue_consistent_hash;
+pub mod allocate_message_queue_sticky;
 
 use std::collections::HashSet;
 
@@ -0,0 +1,239 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use md5::Digest;
+use md5::Md5;
+use rocketmq_common::common::message::message_queue::MessageQueue;
+
+use crate::consumer::allocate_message_queue_strategy::AllocateMessageQueueStrategy;
+use crate::consumer::rebalance_strategy::check;
+use crate::error::MQClientError::IllegalArgumentError;
+
+/// Hash placing consumers and queues on the consistent hash ring.
+pub trait HashFunction: Send + Sync {
+    fn hash(&self, key: &str) -> u64;
+}
+
+/// First four bytes of the MD5 digest, the hash of the Java client, so mixed groups agree.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Md5Hash;
+
+impl HashFunction for Md5Hash {
+    fn hash(&self, key: &str) -> u64 {
+        let digest = Md5::digest(key.as_bytes());
+        digest[..4]
+            .iter()
+            .fold(0u64, |h, byte| (h << 8) | *byte as u64)
+    }
+}
+
+/// 64-bit FNV-1a, cheaper than MD5 when every consumer of the group is a Rust client.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Fnv1aHash;
+
+impl HashFunction for Fnv1aHash {
+    fn hash(&self, key: &str) -> u64 {
+        key.as_bytes()
+            .iter()
+            .fold(0xcbf2_9ce4_8422_2325u64, |h, byte| {
+                (h ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+}
+
+/// Places every consumer on a hash ring `virtual_node_cnt` times and gives each queue to the
+/// first consumer found clockwise from the queue hash. A joining consumer only takes queues
+/// from its neighbours on the ring, the others keep theirs.
+pub struct AllocateMessageQueueConsistentHash {
+    virtual_node_cnt: usize,
+    hash_function: Arc<dyn HashFunction>,
+}
+
+impl AllocateMessageQueueConsistentHash {
+    pub fn new(virtual_node_cnt: usize) -> Self {
+        Self::with_hash_function(virtual_node_cnt, Arc::new(Md5Hash))
+    }
+
+    pub fn with_hash_function(
+        virtual_node_cnt: usize,
+        hash_function: Arc<dyn HashFunction>,
+    ) -> Self {
+        Self {
+            virtual_node_cnt,
+            hash_function,
+        }
+    }
+
+    pub fn virtual_node_cnt(&self) -> usize {
+        self.virtual_node_cnt
+    }
+
+    fn build_ring<'a>(&self, cid_all: &'a [CheetahString]) -> BTreeMap<u64, &'a CheetahString> {
+        let mut ring = BTreeMap::new();
+        for cid in cid_all {
+            for index in 0..self.virtual_node_cnt {
+                ring.insert(self.hash_function.hash(&format!("{}-{}", cid, index)), cid);
+            }
+        }
+        ring
+    }
+
+    fn route<'a>(
+        &self,
+        ring: &BTreeMap<u64, &'a CheetahString>,
+        mq: &MessageQueue,
+    ) -> Option<&'a CheetahString> {
+        let key = format!(
+            "MessageQueue [topic={}, brokerName={}, queueId={}]",
+            mq.get_topic(),
+            mq.get_broker_name(),
+            mq.get_queue_id()
+        );
+        let hash = self.hash_function.hash(&key);
+        ring.range(hash..)
+            .next()
+            .or_else(|| ring.iter().next())
+            .map(|(_, cid)| *cid)
+    }
+}
+
+impl Default for AllocateMessageQueueConsistentHash {
+    fn default() -> Self {
+        Self::new(10)
+    }
+}
+
+impl AllocateMessageQueueStrategy for AllocateMessageQueueConsistentHash {
+    fn allocate(
+        &self,
+        consumer_group: &CheetahString,
+        current_cid: &CheetahString,
+        mq_all: &[MessageQueue],
+        cid_all: &[CheetahString],
+    ) -> crate::Result<Vec<MessageQueue>> {
+        if self.virtual_node_cnt == 0 {
+            return Err(IllegalArgumentError(
+                "virtualNodeCnt must be greater than 0".to_string(),
+            ));
+        }
+        let mut result = Vec::new();
+        if !check(consumer_group, current_cid, mq_all, cid_all)? {
+            return Ok(result);
+        }
+        let ring = self.build_ring(cid_all);
+        for mq in mq_all {
+            if self.route(&ring, mq) == Some(current_cid) {
+                result.push(mq.clone());
+            }
+        }
+        Ok(result)
+    }
+
+    #[inline]
+    fn get_name(&self) -> &'static str {
+        "CONSISTENT_HASH"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn allocate_all(
+        strategy: &AllocateMessageQueueConsistentHash,
+        mq_all: &[MessageQueue],
+        cid_all: &[CheetahString],
+    ) -> HashMap<MessageQueue, CheetahString> {
+        let mut owners = HashMap::new();
+        for cid in cid_all {
+            for mq in strategy
+                .allocate(&CheetahString::from("group"), cid, mq_all, cid_all)
+                .unwrap()
+            {
+                assert!(owners.insert(mq, cid.clone()).is_none());
+            }
+        }
+        owners
+    }
+
+    fn queues(size: i32) -> Vec<MessageQueue> {
+        (0..size)
+            .map(|i| MessageQueue::from_parts("topic", "broker", i))
+            .collect()
+    }
+
+    fn consumers(size: usize) -> Vec<CheetahString> {
+        (0..size)
+            .map(|i| format!("10.0.0.{}@1", i).into())
+            .collect()
+    }
+
+    #[test]
+    fn md5_hash_matches_java_client() {
+        // MD5("a") = 0cc175b9..., the Java client reads the first four bytes big endian.
+        assert_eq!(Md5Hash.hash("a"), 0x0cc1_75b9);
+    }
+
+    #[test]
+    fn fnv1a_hash_matches_reference_vectors() {
+        assert_eq!(Fnv1aHash.hash(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(Fnv1aHash.hash("a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn every_queue_has_exactly_one_owner() {
+        let mq_all = queues(16);
+        let cid_all = consumers(3);
+        for strategy in [
+            AllocateMessageQueueConsistentHash::new(10),
+            AllocateMessageQueueConsistentHash::with_hash_function(100, Arc::new(Fnv1aHash)),
+        ] {
+            assert_eq!(allocate_all(&strategy, &mq_all, &cid_all).len(), 16);
+        }
+    }
+
+    #[test]
+    fn joining_consumer_only_takes_queues() {
+        let strategy = AllocateMessageQueueConsistentHash::default();
+        let mq_all = queues(32);
+        let before = allocate_all(&strategy, &mq_all, &consumers(4));
+        let cid_all = consumers(5);
+        let after = allocate_all(&strategy, &mq_all, &cid_all);
+        for (mq, owner) in &after {
+            assert!(owner == &before[mq] || owner == &cid_all[4]);
+        }
+    }
+
+    #[test]
+    fn zero_virtual_nodes_is_rejected() {
+        let strategy = AllocateMessageQueueConsistentHash::new(0);
+        let cid_all = consumers(1);
+        assert!(strategy
+            .allocate(
+                &CheetahString::from("group"),
+                &cid_all[0],
+                &queues(1),
+                &cid_all
+            )
+            .is_err());
+    }
+}
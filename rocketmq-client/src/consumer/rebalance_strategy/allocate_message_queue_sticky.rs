@@ -0,0 +1,290 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_common::common::message::message_queue::MessageQueue;
+
+use crate::consumer::allocate_message_queue_strategy::AllocateMessageQueueStrategy;
+use crate::consumer::rebalance_strategy::check;
+
+type Assignment = HashMap<MessageQueue, CheetahString>;
+
+/// Makes `strategy` sticky: when the consumer id set changes, a surviving consumer keeps the
+/// queues it owned as long as the group stays balanced, and only the surplus moves. Queues
+/// without an owner go to the consumer `strategy` picks for them when it has room.
+///
+/// The last assignment of every group and topic is kept in this instance, so all consumers of a
+/// group must be allocated by the same instance: the broker does it when client-side rebalance
+/// is off.
+pub struct AllocateMessageQueueSticky {
+    strategy: Arc<dyn AllocateMessageQueueStrategy>,
+    assignments: Mutex<HashMap<(CheetahString, CheetahString), Assignment>>,
+}
+
+impl AllocateMessageQueueSticky {
+    pub fn new(strategy: Arc<dyn AllocateMessageQueueStrategy>) -> Self {
+        Self {
+            strategy,
+            assignments: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn preferred_owners(
+        &self,
+        consumer_group: &CheetahString,
+        mq_all: &[MessageQueue],
+        cid_all: &[CheetahString],
+    ) -> crate::Result<Assignment> {
+        let mut owners = HashMap::with_capacity(mq_all.len());
+        for cid in cid_all {
+            for mq in self
+                .strategy
+                .allocate(consumer_group, cid, mq_all, cid_all)?
+            {
+                owners.entry(mq).or_insert_with(|| cid.clone());
+            }
+        }
+        Ok(owners)
+    }
+}
+
+impl AllocateMessageQueueStrategy for AllocateMessageQueueSticky {
+    fn allocate(
+        &self,
+        consumer_group: &CheetahString,
+        current_cid: &CheetahString,
+        mq_all: &[MessageQueue],
+        cid_all: &[CheetahString],
+    ) -> crate::Result<Vec<MessageQueue>> {
+        if !check(consumer_group, current_cid, mq_all, cid_all)? {
+            return Ok(Vec::new());
+        }
+        let mut mq_all = mq_all.to_vec();
+        mq_all.sort();
+        let mut cid_all = cid_all.to_vec();
+        cid_all.sort();
+        cid_all.dedup();
+
+        let preferred = self.preferred_owners(consumer_group, &mq_all, &cid_all)?;
+        let key = (consumer_group.clone(), mq_all[0].get_topic_cs().clone());
+        let mut assignments = self.assignments.lock();
+        let previous = assignments.get(&key).cloned().unwrap_or_default();
+        let assignment = sticky_assign(&previous, &preferred, &mq_all, &cid_all);
+        let result = mq_all
+            .iter()
+            .filter(|mq| assignment.get(*mq) == Some(current_cid))
+            .cloned()
+            .collect();
+        assignments.insert(key, assignment);
+        Ok(result)
+    }
+
+    fn get_name(&self) -> &'static str {
+        match self.strategy.get_name() {
+            "AVG" => "STICKY_AVG",
+            "AVG_BY_CIRCLE" => "STICKY_AVG_BY_CIRCLE",
+            "CONSISTENT_HASH" => "STICKY_CONSISTENT_HASH",
+            _ => "STICKY",
+        }
+    }
+}
+
+/// Balances `mq_all` over `cid_all` moving as few queues away from their `previous` owner as
+/// possible. The consumers keeping the most queues get the `len % n` spare slots, so a balanced
+/// `previous` with unchanged inputs comes back unchanged.
+fn sticky_assign(
+    previous: &Assignment,
+    preferred: &Assignment,
+    mq_all: &[MessageQueue],
+    cid_all: &[CheetahString],
+) -> Assignment {
+    let mut kept: HashMap<&CheetahString, Vec<&MessageQueue>> =
+        cid_all.iter().map(|cid| (cid, Vec::new())).collect();
+    for mq in mq_all {
+        if let Some(queues) = previous.get(mq).and_then(|owner| kept.get_mut(owner)) {
+            queues.push(mq);
+        }
+    }
+
+    let mut by_kept = cid_all.iter().collect::<Vec<_>>();
+    by_kept.sort_by(|a, b| kept[b].len().cmp(&kept[a].len()).then_with(|| a.cmp(b)));
+    let average = mq_all.len() / cid_all.len();
+    let spare = mq_all.len() % cid_all.len();
+    let mut room: HashMap<&CheetahString, usize> = HashMap::with_capacity(cid_all.len());
+    let mut assignment = HashMap::with_capacity(mq_all.len());
+    for (index, cid) in by_kept.into_iter().enumerate() {
+        let quota = average + usize::from(index < spare);
+        let queues = &kept[cid];
+        for mq in queues.iter().take(quota) {
+            assignment.insert((*mq).clone(), cid.clone());
+        }
+        room.insert(cid, quota.saturating_sub(queues.len()));
+    }
+
+    let mut unplaced = Vec::new();
+    for mq in mq_all {
+        if assignment.contains_key(mq) {
+            continue;
+        }
+        match preferred
+            .get(mq)
+            .and_then(|cid| room.get_mut(cid).map(|room| (cid, room)))
+        {
+            Some((cid, room)) if *room > 0 => {
+                *room -= 1;
+                assignment.insert(mq.clone(), cid.clone());
+            }
+            _ => unplaced.push(mq),
+        }
+    }
+    for mq in unplaced {
+        if let Some(cid) = cid_all.iter().find(|cid| room[cid] > 0) {
+            *room.get_mut(cid).unwrap() -= 1;
+            assignment.insert(mq.clone(), cid.clone());
+        }
+    }
+    assignment
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::consumer::rebalance_strategy::allocate_message_queue_averagely::AllocateMessageQueueAveragely;
+    use crate::consumer::rebalance_strategy::allocate_message_queue_consistent_hash::AllocateMessageQueueConsistentHash;
+    use crate::consumer::rebalance_strategy::allocate_message_queue_consistent_hash::Fnv1aHash;
+
+    fn allocate_all(
+        strategy: &AllocateMessageQueueSticky,
+        mq_all: &[MessageQueue],
+        cid_all: &[CheetahString],
+    ) -> Assignment {
+        let mut owners = HashMap::new();
+        for cid in cid_all {
+            for mq in strategy
+                .allocate(&CheetahString::from("group"), cid, mq_all, cid_all)
+                .unwrap()
+            {
+                assert!(owners.insert(mq, cid.clone()).is_none());
+            }
+        }
+        assert_eq!(owners.len(), mq_all.len());
+        let average = mq_all.len() / cid_all.len();
+        for cid in cid_all {
+            let load = owners.values().filter(|owner| *owner == cid).count();
+            assert!(load == average || load == average + 1);
+        }
+        owners
+    }
+
+    fn queues(topic: &str, size: usize) -> Vec<MessageQueue> {
+        (0..size)
+            .map(|i| MessageQueue::from_parts(topic, "broker-a", i as i32))
+            .collect()
+    }
+
+    fn consumers(size: usize) -> Vec<CheetahString> {
+        (0..size)
+            .map(|i| format!("10.0.0.{}@1", i).into())
+            .collect()
+    }
+
+    #[test]
+    fn adding_a_consumer_moves_at_most_its_share() {
+        for virtual_node_cnt in [10, 100] {
+            for n in 1..=8 {
+                for q in 1..=32 {
+                    let strategy = AllocateMessageQueueSticky::new(Arc::new(
+                        AllocateMessageQueueConsistentHash::with_hash_function(
+                            virtual_node_cnt,
+                            Arc::new(Fnv1aHash),
+                        ),
+                    ));
+                    let mq_all = queues("topic", q);
+                    let before = allocate_all(&strategy, &mq_all, &consumers(n));
+                    let cid_all = consumers(n + 1);
+                    let after = allocate_all(&strategy, &mq_all, &cid_all);
+                    let moved = mq_all
+                        .iter()
+                        .filter(|mq| before[*mq] != after[*mq])
+                        .collect::<Vec<_>>();
+                    assert!(moved.len() <= q.div_ceil(n + 1), "n={} q={}", n, q);
+                    assert!(moved.iter().all(|mq| after[*mq] == cid_all[n]));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn removing_a_consumer_only_moves_its_queues() {
+        let strategy = AllocateMessageQueueSticky::new(Arc::new(
+            AllocateMessageQueueConsistentHash::default(),
+        ));
+        let mq_all = queues("topic", 20);
+        let cid_all = consumers(4);
+        let before = allocate_all(&strategy, &mq_all, &cid_all);
+        let after = allocate_all(&strategy, &mq_all, &cid_all[1..]);
+        for mq in &mq_all {
+            if before[mq] != cid_all[0] {
+                assert_eq!(before[mq], after[mq]);
+            }
+        }
+    }
+
+    #[test]
+    fn repeated_allocation_is_stable() {
+        let strategy = AllocateMessageQueueSticky::new(Arc::new(AllocateMessageQueueAveragely));
+        let mq_all = queues("topic", 10);
+        let cid_all = consumers(3);
+        let first = allocate_all(&strategy, &mq_all, &cid_all);
+        let changed = allocate_all(&strategy, &mq_all, &consumers(4));
+        assert_ne!(first, changed);
+        assert_eq!(changed, allocate_all(&strategy, &mq_all, &consumers(4)));
+    }
+
+    #[test]
+    fn topics_are_tracked_separately() {
+        let strategy = AllocateMessageQueueSticky::new(Arc::new(
+            AllocateMessageQueueConsistentHash::default(),
+        ));
+        let cid_all = consumers(2);
+        allocate_all(&strategy, &queues("topic-a", 4), &cid_all);
+        allocate_all(&strategy, &queues("topic-b", 6), &cid_all);
+        let tracked = strategy
+            .assignments
+            .lock()
+            .keys()
+            .map(|(_, topic)| topic.to_string())
+            .collect::<HashSet<_>>();
+        assert_eq!(
+            tracked,
+            HashSet::from(["topic-a".to_string(), "topic-b".to_string()])
+        );
+    }
+
+    #[test]
+    fn name_follows_the_wrapped_strategy() {
+        let strategy = AllocateMessageQueueSticky::new(Arc::new(
+            AllocateMessageQueueConsistentHash::default(),
+        ));
+        assert_eq!(strategy.get_name(), "STICKY_CONSISTENT_HASH");
+    }
+}
@@ -155,25 +155,27 @@ impl OffsetStoreTrait for LocalFileOffsetStore {
             ReadOffsetType::ReadFromMemory | ReadOffsetType::MemoryFirstThenStore => {
                 let offset_table = self.offset_table.lock().await;
                 if let Some(offset) = offset_table.get(mq) {
-                    offset.get_offset()
-                } else {
-                    -1
+                    return offset.get_offset();
+                } else if type_ == ReadOffsetType::ReadFromMemory {
+                    return -1;
                 }
             }
-            ReadOffsetType::ReadFromStore => match self.read_local_offset() {
-                Ok(offset_serialize_wrapper) => {
-                    if let Some(offset_serialize_wrapper) = offset_serialize_wrapper {
-                        if let Some(offset) = offset_serialize_wrapper.offset_table.get(mq) {
-                            offset.load(Ordering::Relaxed)
-                        } else {
-                            -1
-                        }
-                    } else {
-                        -1
-                    }
-                }
-                Err(_) => -1,
-            },
+            ReadOffsetType::ReadFromStore => {}
+        }
+        let offset = match self.read_local_offset() {
+            Ok(Some(offset_serialize_wrapper)) => offset_serialize_wrapper
+                .offset_table
+                .get(mq)
+                .map(|offset| offset.load(Ordering::Relaxed)),
+            Ok(None) => None,
+            Err(_) => None,
+        };
+        match offset {
+            Some(offset) => {
+                self.update_offset(mq, offset, false).await;
+                offset
+            }
+            None => -1,
         }
     }
 
@@ -239,8 +241,8 @@ impl OffsetStoreTrait for LocalFileOffsetStore {
         offset_table.remove(mq);
         info!(
             "remove unnecessary messageQueue offset. group={}, mq={}, offsetTableSize={}",
-            mq,
             self.group_name,
+            mq,
             offset_table.len()
         );
     }
@@ -194,7 +194,7 @@ impl OffsetStoreTrait for RemoteBrokerOffsetStore {
 
         for (mq, offset) in used_mq {
             match self
-                .update_consume_offset_to_broker(&mq, offset, true)
+                .update_consume_offset_to_broker(&mq, offset, false)
                 .await
             {
                 Ok(_) => {
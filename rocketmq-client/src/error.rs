@@ -14,9 +14,12 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use rocketmq_common::common::validators::ValidationError;
 use rocketmq_remoting::error::Error as RemotingError;
 use thiserror::Error;
 
+use crate::producer::send_result::SendResult;
+
 #[derive(Debug, Error)]
 pub enum MQClientError {
     #[error("Client exception occurred: CODE:{0}, Message:{1}")]
@@ -39,4 +42,87 @@ pub enum MQClientError {
 
     #[error("{0}")]
     IllegalArgumentError(String),
+
+    #[error("{0}")]
+    InvalidConfig(#[from] ValidationError),
+
+    #[error("Batch send aborted after {} chunk(s) were sent: {source}", sent.len())]
+    BatchSendAborted {
+        sent: Vec<SendResult>,
+        source: Box<MQClientError>,
+    },
+
+    #[error("Async send failed after {attempts} attempt(s): {source}")]
+    AsyncSendFailed {
+        attempts: u32,
+        source: Box<MQClientError>,
+    },
+}
+
+impl MQClientError {
+    /// The response code the failing request was answered with, `-1` if the error did not
+    /// come from a response.
+    pub fn response_code(&self) -> i32 {
+        match self {
+            MQClientError::MQClientErr(code, _)
+            | MQClientError::MQBrokerError(code, _, _)
+            | MQClientError::RequestTimeoutError(code, _)
+            | MQClientError::OffsetNotFoundError(code, _, _)
+            | MQClientError::RemotingError(RemotingError::RpcException(code, _)) => *code,
+            MQClientError::BatchSendAborted { source, .. }
+            | MQClientError::AsyncSendFailed { source, .. } => source.response_code(),
+            _ => -1,
+        }
+    }
+
+    /// Address of the broker that answered with the error, if it came from a broker.
+    pub fn broker_addr(&self) -> Option<&str> {
+        match self {
+            MQClientError::MQBrokerError(_, _, broker_addr)
+            | MQClientError::OffsetNotFoundError(_, broker_addr, _) => Some(broker_addr.as_str()),
+            MQClientError::BatchSendAborted { source, .. }
+            | MQClientError::AsyncSendFailed { source, .. } => source.broker_addr(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_remoting::code::response_code::ResponseCode;
+
+    use super::*;
+
+    #[test]
+    fn broker_errors_expose_code_and_address() {
+        let error = MQClientError::MQBrokerError(
+            ResponseCode::TopicNotExist as i32,
+            "topic[T] not exist, apply first please!".to_string(),
+            "127.0.0.1:10911".to_string(),
+        );
+        assert_eq!(error.response_code(), ResponseCode::TopicNotExist as i32);
+        assert_eq!(error.broker_addr(), Some("127.0.0.1:10911"));
+
+        let error = MQClientError::OffsetNotFoundError(
+            ResponseCode::QueryNotFound as i32,
+            "127.0.0.1:10911".to_string(),
+            "no offset".to_string(),
+        );
+        assert_eq!(error.response_code(), ResponseCode::QueryNotFound as i32);
+        assert_eq!(error.broker_addr(), Some("127.0.0.1:10911"));
+    }
+
+    #[test]
+    fn other_errors_have_no_broker() {
+        let error = MQClientError::RemotingError(RemotingError::RpcException(
+            ResponseCode::SystemBusy as i32,
+            "busy".to_string(),
+        ));
+        assert_eq!(error.response_code(), ResponseCode::SystemBusy as i32);
+        assert_eq!(error.broker_addr(), None);
+
+        let error = MQClientError::IllegalArgumentError("bad".to_string());
+        assert_eq!(error.response_code(), -1);
+        assert_eq!(error.broker_addr(), None);
+    }
 }
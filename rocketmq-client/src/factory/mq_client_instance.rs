@@ -28,11 +28,14 @@ use rocketmq_common::common::base::service_state::ServiceState;
 use rocketmq_common::common::constant::PermName;
 use rocketmq_common::common::filter::expression_type::ExpressionType;
 use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::common::message::message_queue_assignment::MessageQueueAssignment;
 use rocketmq_common::common::mix_all;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::base::connection_net_event::ConnectionNetEvent;
+use rocketmq_remoting::protocol::body::query_assignment_request_body::QueryAssignmentRequestBody;
 use rocketmq_remoting::protocol::heartbeat::consumer_data::ConsumerData;
 use rocketmq_remoting::protocol::heartbeat::heartbeat_data::HeartbeatData;
+use rocketmq_remoting::protocol::heartbeat::message_model::MessageModel;
 use rocketmq_remoting::protocol::heartbeat::producer_data::ProducerData;
 use rocketmq_remoting::protocol::route::topic_route_data::TopicRouteData;
 use rocketmq_remoting::rpc::client_metadata::ClientMetadata;
@@ -43,6 +46,7 @@ use rocketmq_rust::ArcMut;
 use rocketmq_rust::RocketMQTokioMutex;
 use tokio::runtime::Handle;
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 use tracing::error;
 use tracing::info;
 use tracing::warn;
@@ -58,6 +62,7 @@ use crate::implementation::client_remoting_processor::ClientRemotingProcessor;
 use crate::implementation::find_broker_result::FindBrokerResult;
 use crate::implementation::mq_admin_impl::MQAdminImpl;
 use crate::implementation::mq_client_api_impl::MQClientAPIImpl;
+use crate::implementation::mq_client_manager::MQClientManager;
 use crate::producer::default_mq_producer::DefaultMQProducer;
 use crate::producer::default_mq_producer::ProducerConfig;
 use crate::producer::producer_impl::mq_producer_inner::MQProducerInnerImpl;
@@ -114,6 +119,7 @@ pub struct MQClientInstance {
         >,
     >,
     send_heartbeat_times_total: Arc<AtomicI64>,
+    scheduled_tasks: Vec<JoinHandle<()>>,
 }
 
 impl MQClientInstance {
@@ -209,7 +215,7 @@ impl MQClientInstance {
                 DefaultMQProducer::builder()
                     .producer_group(mix_all::CLIENT_INNER_PRODUCER_GROUP)
                     .client_config(client_config.clone())
-                    .build(),
+                    .build_unchecked(),
             ),
             instance_runtime: Arc::new(RocketMQRuntime::new_multi(
                 num_cpus::get(),
@@ -218,6 +224,7 @@ impl MQClientInstance {
             broker_addr_table,
             broker_version_table: Arc::new(Default::default()),
             send_heartbeat_times_total: Arc::new(AtomicI64::new(0)),
+            scheduled_tasks: Vec::new(),
         });
         let instance_clone = instance.clone();
         instance.mq_admin_impl.set_client(instance_clone);
@@ -294,7 +301,15 @@ impl MQClientInstance {
             ServiceState::CreateJust => {
                 self.service_state = ServiceState::StartFailed;
                 // If not specified,looking address from name remoting_server
-                if self.client_config.namesrv_addr.is_none() {
+                if let Some(namesrv_addr) = self.client_config.namesrv_addr.clone() {
+                    // also applied from a thread spawned on creation, which the first request
+                    // after start may outrun
+                    self.mq_client_api_impl
+                        .as_mut()
+                        .expect("mq_client_api_impl is None")
+                        .update_name_server_address_list(namesrv_addr.as_str())
+                        .await;
+                } else {
                     self.mq_client_api_impl
                         .as_mut()
                         .expect("mq_client_api_impl is None")
@@ -339,7 +354,28 @@ impl MQClientInstance {
         Ok(())
     }
 
-    pub async fn shutdown(&mut self) {}
+    /// Shuts the instance down once no consumer, admin or user producer uses it any more.
+    pub async fn shutdown(&mut self) {
+        if !self.consumer_table.read().await.is_empty()
+            || !self.admin_ext_table.read().await.is_empty()
+            || self.producer_table.read().await.len() > 1
+        {
+            return;
+        }
+        if self.service_state != ServiceState::Running {
+            return;
+        }
+        self.service_state = ServiceState::ShutdownAlready;
+        self.pull_message_service.shutdown();
+        self.rebalance_service.shutdown();
+        for task in self.scheduled_tasks.drain(..) {
+            task.abort();
+        }
+        MQClientManager::get_instance()
+            .remove_client_factory(self.client_id.as_str())
+            .await;
+        info!("the client factory [{}] shutdown OK", self.client_id);
+    }
 
     pub async fn register_producer(&mut self, group: &str, producer: MQProducerInnerImpl) -> bool {
         if group.is_empty() {
@@ -354,11 +390,32 @@ impl MQClientInstance {
         true
     }
 
+    pub async fn register_admin_ext(
+        &mut self,
+        group: &str,
+        admin: Box<dyn MQAdminExtInner>,
+    ) -> bool {
+        if group.is_empty() {
+            return false;
+        }
+        let mut admin_ext_table = self.admin_ext_table.write().await;
+        if admin_ext_table.contains_key(group) {
+            warn!("the admin group[{}] exist already.", group);
+            return false;
+        }
+        admin_ext_table.insert(group.into(), admin);
+        true
+    }
+
+    pub async fn unregister_admin_ext(&mut self, group: &str) {
+        self.admin_ext_table.write().await.remove(group);
+    }
+
     fn start_scheduled_task(&mut self, this: ArcMut<Self>) {
         if self.client_config.namesrv_addr.is_none() {
             // Fetch name server address
             let mut mq_client_api_impl = self.mq_client_api_impl.as_ref().unwrap().clone();
-            self.instance_runtime.get_handle().spawn(async move {
+            let handle = self.instance_runtime.get_handle().spawn(async move {
                 info!("ScheduledTask fetchNameServerAddr started");
                 tokio::time::sleep(Duration::from_secs(10)).await;
                 loop {
@@ -370,12 +427,13 @@ impl MQClientInstance {
                     tokio::time::sleep(delay).await;
                 }
             });
+            self.scheduled_tasks.push(handle);
         }
 
         // Update topic route info from name server
         let mut client_instance = this.clone();
         let poll_name_server_interval = self.client_config.poll_name_server_interval;
-        self.instance_runtime.get_handle().spawn(async move {
+        let handle = self.instance_runtime.get_handle().spawn(async move {
             info!("ScheduledTask update_topic_route_info_from_name_server started");
             tokio::time::sleep(Duration::from_millis(10)).await;
             loop {
@@ -390,11 +448,12 @@ impl MQClientInstance {
                 tokio::time::sleep(delay).await;
             }
         });
+        self.scheduled_tasks.push(handle);
 
         // Clean offline broker and send heartbeat to all broker
         let mut client_instance = this.clone();
         let heartbeat_broker_interval = self.client_config.heartbeat_broker_interval;
-        self.instance_runtime.get_handle().spawn(async move {
+        let handle = self.instance_runtime.get_handle().spawn(async move {
             info!("ScheduledTask clean_offline_broker started");
             tokio::time::sleep(Duration::from_secs(1)).await;
             loop {
@@ -410,12 +469,13 @@ impl MQClientInstance {
                 tokio::time::sleep(delay).await;
             }
         });
+        self.scheduled_tasks.push(handle);
 
         // Persist all consumer offset
         let mut client_instance = this;
         let persist_consumer_offset_interval =
             self.client_config.persist_consumer_offset_interval as u64;
-        self.instance_runtime.get_handle().spawn(async move {
+        let handle = self.instance_runtime.get_handle().spawn(async move {
             info!("ScheduledTask persistAllConsumerOffset started");
             tokio::time::sleep(Duration::from_secs(10)).await;
             loop {
@@ -428,6 +488,7 @@ impl MQClientInstance {
                 tokio::time::sleep(delay).await;
             }
         });
+        self.scheduled_tasks.push(handle);
     }
 
     pub async fn update_topic_route_info_from_name_server(&mut self) {
@@ -501,6 +562,42 @@ impl MQClientInstance {
         None
     }
 
+    /// Asks a broker serving `topic` for the queues the group assigns to this client.
+    pub async fn query_assignment(
+        &mut self,
+        topic: &CheetahString,
+        consumer_group: &CheetahString,
+        strategy_name: &str,
+        message_model: MessageModel,
+        timeout_millis: u64,
+    ) -> Result<Option<HashSet<MessageQueueAssignment>>> {
+        let mut broker_addr = self.find_broker_addr_by_topic(topic).await;
+        if broker_addr.is_none() {
+            self.update_topic_route_info_from_name_server_topic(topic)
+                .await;
+            broker_addr = self.find_broker_addr_by_topic(topic).await;
+        }
+        let Some(broker_addr) = broker_addr else {
+            return Ok(None);
+        };
+        let request_body = QueryAssignmentRequestBody {
+            topic: topic.clone(),
+            consumer_group: consumer_group.clone(),
+            client_id: self.client_id.clone(),
+            strategy_name: CheetahString::from_slice(strategy_name),
+            message_model,
+        };
+        self.mq_client_api_impl
+            .as_mut()
+            .unwrap()
+            .query_assignment(broker_addr.as_str(), request_body, timeout_millis)
+            .await
+    }
+
+    pub async fn get_an_exist_topic_route_data(&self, topic: &str) -> Option<TopicRouteData> {
+        self.topic_route_table.read().await.get(topic).cloned()
+    }
+
     pub async fn find_broker_addr_by_topic(&self, topic: &str) -> Option<CheetahString> {
         let topic_route_table = self.topic_route_table.read().await;
         if let Some(topic_route_data) = topic_route_table.get(topic) {
@@ -522,7 +619,8 @@ impl MQClientInstance {
         producer_config: Option<&Arc<ProducerConfig>>,
     ) -> bool {
         let lock = self.lock_namesrv.lock().await;
-        let topic_route_data = if is_default && producer_config.is_some() {
+        let default_producer_config = producer_config.filter(|_| is_default);
+        let topic_route_data = if let Some(producer_config) = default_producer_config {
             let mut result = self
                 .mq_client_api_impl
                 .as_mut()
@@ -533,14 +631,10 @@ impl MQClientInstance {
                 .await
                 .unwrap_or(None);
             if let Some(topic_route_data) = result.as_mut() {
-                for data in topic_route_data.queue_datas.iter_mut() {
-                    let queue_nums = producer_config
-                        .unwrap()
-                        .default_topic_queue_nums()
-                        .max(data.read_queue_nums);
-                    data.read_queue_nums = queue_nums;
-                    data.write_queue_nums = queue_nums;
-                }
+                limit_default_topic_queue_nums(
+                    topic_route_data,
+                    producer_config.default_topic_queue_nums(),
+                );
             }
             result
         } else {
@@ -588,33 +682,7 @@ impl MQClientInstance {
                     }
                 }
 
-                // Update Pub info
-                {
-                    let mut publish_info =
-                        topic_route_data2topic_publish_info(topic, &mut topic_route_data);
-                    publish_info.have_topic_router_info = true;
-                    let mut producer_table = self.producer_table.write().await;
-                    for (_, value) in producer_table.iter_mut() {
-                        value.update_topic_publish_info(
-                            topic.to_string(),
-                            Some(publish_info.clone()),
-                        );
-                    }
-                }
-
-                // Update sub info
-                {
-                    let consumer_table = self.consumer_table.read().await;
-                    if !consumer_table.is_empty() {
-                        let subscribe_info =
-                            topic_route_data2topic_subscribe_info(topic, &topic_route_data);
-                        for (_, value) in consumer_table.iter() {
-                            value
-                                .update_topic_subscribe_info(topic.clone(), &subscribe_info)
-                                .await;
-                        }
-                    }
-                }
+                self.fan_out_topic_route(topic, &mut topic_route_data).await;
                 let clone_topic_route_data = TopicRouteData::from_existing(&topic_route_data);
                 topic_route_table.insert(topic.clone(), clone_topic_route_data);
                 return true;
@@ -631,6 +699,36 @@ impl MQClientInstance {
         false
     }
 
+    /// Pushes the route of `topic` to the local cache of every registered producer and consumer.
+    async fn fan_out_topic_route(
+        &self,
+        topic: &CheetahString,
+        topic_route_data: &mut TopicRouteData,
+    ) {
+        // Update Pub info
+        {
+            let mut publish_info = topic_route_data2topic_publish_info(topic, topic_route_data);
+            publish_info.have_topic_router_info = true;
+            let mut producer_table = self.producer_table.write().await;
+            for value in producer_table.values_mut() {
+                value.update_topic_publish_info(topic.to_string(), Some(publish_info.clone()));
+            }
+        }
+
+        // Update sub info
+        {
+            let consumer_table = self.consumer_table.read().await;
+            if !consumer_table.is_empty() {
+                let subscribe_info = topic_route_data2topic_subscribe_info(topic, topic_route_data);
+                for value in consumer_table.values() {
+                    value
+                        .update_topic_subscribe_info(topic.clone(), &subscribe_info)
+                        .await;
+                }
+            }
+        }
+    }
+
     async fn is_need_update_topic_route_info(&self, topic: &CheetahString) -> bool {
         let mut result = false;
         let producer_table = self.producer_table.read().await;
@@ -918,9 +1016,15 @@ impl MQClientInstance {
         }
         drop(consumer_table);
         let producer_table = self.producer_table.read().await;
-        for (group_name, _) in producer_table.iter() {
+        for (group_name, producer) in producer_table.iter() {
+            let mut topics = producer
+                .get_publish_topic_list()
+                .into_iter()
+                .collect::<Vec<_>>();
+            topics.sort();
             let producer_data = ProducerData {
                 group_name: group_name.clone(),
+                topics,
             };
             heartbeat_data.producer_data_set.insert(producer_data);
         }
@@ -1097,7 +1201,9 @@ impl MQClientInstance {
     }
 
     pub async fn unregister_consumer(&mut self, group: impl Into<CheetahString>) {
-        self.unregister_client(None, Some(group.into())).await;
+        let group = group.into();
+        self.consumer_table.write().await.remove(&group);
+        self.unregister_client(None, Some(group)).await;
     }
     pub async fn unregister_producer(&mut self, group: impl Into<CheetahString>) {
         self.unregister_client(Some(group.into()), None).await;
@@ -1150,6 +1256,17 @@ impl MQClientInstance {
     }
 }
 
+/// Sends to a topic that does not exist yet go through the route of the default topic, on every
+/// broker that can create it. Each broker creates the topic with no more queues than the producer
+/// asks for, so only that many queues of each broker are used.
+fn limit_default_topic_queue_nums(route: &mut TopicRouteData, default_topic_queue_nums: u32) {
+    for data in route.queue_datas.iter_mut() {
+        let queue_nums = default_topic_queue_nums.min(data.read_queue_nums);
+        data.read_queue_nums = queue_nums;
+        data.write_queue_nums = queue_nums;
+    }
+}
+
 pub fn topic_route_data2topic_publish_info(
     topic: &str,
     route: &mut TopicRouteData,
@@ -1258,3 +1375,148 @@ pub fn topic_route_data2topic_subscribe_info(
     }
     mq_list
 }
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::constant::PermName;
+    use rocketmq_remoting::protocol::route::route_data_view::BrokerData;
+    use rocketmq_remoting::protocol::route::route_data_view::QueueData;
+
+    use super::*;
+    use crate::consumer::default_mq_push_consumer::DefaultMQPushConsumer;
+
+    const TOPIC: &str = "SharedInstanceTopic";
+
+    fn client_config() -> ClientConfig {
+        ClientConfig {
+            client_ip: Some("127.0.0.1".into()),
+            instance_name: "shared-instance-test".into(),
+            ..Default::default()
+        }
+    }
+
+    fn consumer_inner(consumer: &DefaultMQPushConsumer) -> MQConsumerInnerImpl {
+        MQConsumerInnerImpl {
+            default_mqpush_consumer_impl: Some(ArcMut::downgrade(
+                consumer.default_mqpush_consumer_impl.as_ref().unwrap(),
+            )),
+        }
+    }
+
+    #[test]
+    fn default_topic_route_spreads_a_new_topic_over_all_brokers() {
+        let broker_data = |broker_name: &str| {
+            BrokerData::new(
+                "DefaultCluster".into(),
+                broker_name.into(),
+                HashMap::from([(mix_all::MASTER_ID, "127.0.0.1:10911".into())]),
+                None,
+            )
+        };
+        let queue_data = |broker_name: &str, queue_nums| {
+            QueueData::new(
+                broker_name.into(),
+                queue_nums,
+                queue_nums,
+                PermName::PERM_INHERIT | PermName::PERM_READ | PermName::PERM_WRITE,
+                0,
+            )
+        };
+        let mut route = TopicRouteData {
+            broker_datas: vec![broker_data("broker-a"), broker_data("broker-b")],
+            queue_datas: vec![queue_data("broker-a", 8), queue_data("broker-b", 2)],
+            ..Default::default()
+        };
+        limit_default_topic_queue_nums(&mut route, 4);
+
+        let info = topic_route_data2topic_publish_info("NewTopic", &mut route);
+        let queues_of = |broker_name: &str| {
+            info.message_queue_list
+                .iter()
+                .filter(|mq| mq.get_broker_name() == broker_name)
+                .map(|mq| mq.get_queue_id())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(queues_of("broker-a"), vec![0, 1, 2, 3]);
+        assert_eq!(queues_of("broker-b"), vec![0, 1]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn consumers_of_one_process_share_an_instance() {
+        let manager = MQClientManager::get_instance();
+        let mut instance = manager
+            .get_or_create_mq_client_instance(client_config(), None)
+            .await;
+        let other = manager
+            .get_or_create_mq_client_instance(client_config(), None)
+            .await;
+        assert!(Arc::ptr_eq(instance.get_inner(), other.get_inner()));
+
+        let mut consumers = Vec::new();
+        for group in ["group_a", "group_b"] {
+            let mut consumer = DefaultMQPushConsumer::builder()
+                .name_server_addr("127.0.0.1:9876")
+                .consumer_group(group)
+                .build()
+                .unwrap();
+            consumer
+                .default_mqpush_consumer_impl
+                .as_mut()
+                .unwrap()
+                .subscribe(TOPIC.into(), "*".into())
+                .await
+                .unwrap();
+            assert!(
+                instance
+                    .register_consumer(&group.into(), consumer_inner(&consumer))
+                    .await
+            );
+            consumers.push(consumer);
+        }
+        assert!(
+            !instance
+                .register_consumer(&"group_a".into(), consumer_inner(&consumers[0]))
+                .await
+        );
+
+        let heartbeat_data = instance.prepare_heartbeat_data(false).await;
+        let groups = heartbeat_data
+            .consumer_data_set
+            .iter()
+            .map(|consumer_data| consumer_data.group_name.to_string())
+            .collect::<HashSet<_>>();
+        assert_eq!(
+            groups,
+            HashSet::from(["group_a".to_string(), "group_b".to_string()])
+        );
+
+        let mut topic_route_data = TopicRouteData {
+            queue_datas: vec![QueueData::new(
+                "broker-a".into(),
+                4,
+                4,
+                PermName::PERM_READ | PermName::PERM_WRITE,
+                0,
+            )],
+            ..Default::default()
+        };
+        instance
+            .fan_out_topic_route(&TOPIC.into(), &mut topic_route_data)
+            .await;
+        for consumer in &consumers {
+            let topic_subscribe_info_table = consumer
+                .default_mqpush_consumer_impl
+                .as_ref()
+                .unwrap()
+                .rebalance_impl
+                .rebalance_impl_inner
+                .topic_subscribe_info_table
+                .read()
+                .await;
+            assert_eq!(
+                topic_subscribe_info_table.get(TOPIC).map(HashSet::len),
+                Some(4)
+            );
+        }
+    }
+}
@@ -31,6 +31,7 @@ use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::header::check_transaction_state_request_header::CheckTransactionStateRequestHeader;
 use rocketmq_remoting::protocol::header::notify_consumer_ids_changed_request_header::NotifyConsumerIdsChangedRequestHeader;
+use rocketmq_remoting::protocol::header::notify_topic_route_changed_request_header::NotifyTopicRouteChangedRequestHeader;
 use rocketmq_remoting::protocol::header::reply_message_request_header::ReplyMessageRequestHeader;
 use rocketmq_remoting::protocol::namespace_util::NamespaceUtil;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
@@ -85,6 +86,9 @@ impl RequestProcessor for ClientRemotingProcessor {
             RequestCode::NotifyConsumerIdsChanged => {
                 self.notify_consumer_ids_changed(channel, ctx, request)
             }
+            RequestCode::NotifyTopicRouteChanged => {
+                self.notify_topic_route_changed(channel, request)
+            }
 
             _ => {
                 info!("Unknown request code: {:?}", request_code);
@@ -213,6 +217,32 @@ impl ClientRemotingProcessor {
         Ok(None)
     }
 
+    fn notify_topic_route_changed(
+        &mut self,
+        channel: Channel,
+        request: RemotingCommand,
+    ) -> Result<Option<RemotingCommand>> {
+        let Some(request_header) =
+            request.decode_command_custom_header::<NotifyTopicRouteChangedRequestHeader>()
+        else {
+            return Ok(None);
+        };
+        info!(
+            "receive broker's notification[{}], the route of topic: {} changed, update it \
+             immediately",
+            channel.remote_address(),
+            request_header.topic
+        );
+        if let Some(mut client_instance) = self.client_instance.upgrade() {
+            tokio::spawn(async move {
+                client_instance
+                    .update_topic_route_info_from_name_server_topic(&request_header.topic)
+                    .await;
+            });
+        }
+        Ok(None)
+    }
+
     async fn check_transaction_state(
         &mut self,
         channel: Channel,
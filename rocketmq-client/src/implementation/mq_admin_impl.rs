@@ -14,11 +14,20 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use cheetah_string::CheetahString;
+use rocketmq_common::common::message::message_ext::MessageExt;
 use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::utils::message_utils;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::header::query_message_request_header::QueryMessageRequestHeader;
 use rocketmq_remoting::protocol::namespace_util::NamespaceUtil;
 use rocketmq_rust::ArcMut;
+use tracing::warn;
 
 use crate::base::client_config::ClientConfig;
+use crate::base::query_result::QueryResult;
 use crate::error::MQClientError::MQClientErr;
 use crate::factory::mq_client_instance;
 use crate::factory::mq_client_instance::MQClientInstance;
@@ -97,6 +106,159 @@ impl MQAdminImpl {
     }
 
     pub async fn max_offset(&mut self, mq: &MessageQueue) -> Result<i64> {
+        let broker_addr = self.find_broker_address(mq).await?;
+        self.client
+            .as_mut()
+            .expect("client is None")
+            .mq_client_api_impl
+            .as_mut()
+            .expect("mq_client_api_impl is None")
+            .get_max_offset(&broker_addr, mq, self.timeout_millis)
+            .await
+    }
+
+    /// Looks up the offset of the first message of `mq` stored at or after `timestamp`, given in
+    /// milliseconds.
+    pub async fn search_offset(&mut self, mq: &MessageQueue, timestamp: u64) -> Result<i64> {
+        let broker_addr = self.find_broker_address(mq).await?;
+        self.client
+            .as_mut()
+            .expect("client is None")
+            .mq_client_api_impl
+            .as_mut()
+            .expect("mq_client_api_impl is None")
+            .search_offset(&broker_addr, mq, timestamp as i64, self.timeout_millis)
+            .await
+    }
+
+    /// Fetches a message by the offset message id the broker returned when storing it, which
+    /// encodes the broker address and the commit log offset.
+    pub async fn view_message(&mut self, topic: &str, msg_id: &str) -> Result<MessageExt> {
+        let Some((address, offset)) = message_utils::parse_message_id(msg_id) else {
+            return Err(MQClientErr(
+                -1,
+                format!("the message id[{}] is not an offset message id", msg_id),
+            ));
+        };
+        self.client
+            .as_mut()
+            .expect("client is None")
+            .mq_client_api_impl
+            .as_mut()
+            .expect("mq_client_api_impl is None")
+            .view_message(
+                address.to_string().as_str(),
+                &CheetahString::from_slice(topic),
+                offset,
+                self.timeout_millis,
+            )
+            .await
+    }
+
+    /// Queries every broker serving `topic` for the messages indexed under `key`.
+    pub async fn query_message(
+        &mut self,
+        topic: &str,
+        key: &str,
+        max_num: i32,
+        begin: i64,
+        end: i64,
+    ) -> Result<QueryResult> {
+        self.query_message_inner(topic, key, max_num, begin, end, false)
+            .await
+    }
+
+    /// Looks a message up by the unique key the client assigned to it when sending. Index hash
+    /// collisions are filtered out and, should the message have been stored more than once, the
+    /// most recently stored copy is returned.
+    pub async fn query_message_by_unique_key(
+        &mut self,
+        topic: &str,
+        unique_key: &str,
+    ) -> Result<MessageExt> {
+        let query_result = self
+            .query_message_inner(topic, unique_key, 32, 0, i64::MAX, true)
+            .await?;
+        select_unique_key_message(query_result.message_list().clone(), unique_key).ok_or_else(
+            || {
+                MQClientErr(
+                    ResponseCode::NoMessage as i32,
+                    format!(
+                        "query message by key finished, but no message[{}].",
+                        unique_key
+                    ),
+                )
+            },
+        )
+    }
+
+    async fn query_message_inner(
+        &mut self,
+        topic: &str,
+        key: &str,
+        max_num: i32,
+        begin: i64,
+        end: i64,
+        is_unique_key: bool,
+    ) -> Result<QueryResult> {
+        let client = self.client.as_mut().expect("client is None");
+        let topic = CheetahString::from_slice(topic);
+        let mut topic_route_data = client.get_an_exist_topic_route_data(&topic).await;
+        if topic_route_data.is_none() {
+            client
+                .update_topic_route_info_from_name_server_topic(&topic)
+                .await;
+            topic_route_data = client.get_an_exist_topic_route_data(&topic).await;
+        }
+        let Some(topic_route_data) = topic_route_data else {
+            return Err(MQClientErr(
+                -1,
+                format!("The topic[{}] not matched route info", topic),
+            ));
+        };
+
+        let mut index_last_update_timestamp = 0;
+        let mut message_list = Vec::new();
+        let mut last_error = None;
+        for broker_data in topic_route_data.broker_datas.iter() {
+            let Some(addr) = broker_data.select_broker_addr() else {
+                continue;
+            };
+            let request_header = QueryMessageRequestHeader {
+                topic: topic.clone(),
+                key: CheetahString::from_slice(key),
+                max_num,
+                begin_timestamp: begin,
+                end_timestamp: end,
+                topic_request_header: None,
+            };
+            match client
+                .mq_client_api_impl
+                .as_mut()
+                .expect("mq_client_api_impl is None")
+                .query_message(&addr, request_header, self.timeout_millis, is_unique_key)
+                .await
+            {
+                Ok(query_result) => {
+                    index_last_update_timestamp =
+                        index_last_update_timestamp.max(query_result.index_last_update_timestamp());
+                    message_list.extend(query_result.message_list().iter().cloned());
+                }
+                Err(err) => {
+                    warn!("queryMessage from broker[{}] exception, {}", addr, err);
+                    last_error = Some(err);
+                }
+            }
+        }
+        if message_list.is_empty() {
+            if let Some(err) = last_error {
+                return Err(err);
+            }
+        }
+        Ok(QueryResult::new(index_last_update_timestamp, message_list))
+    }
+
+    async fn find_broker_address(&mut self, mq: &MessageQueue) -> Result<CheetahString> {
         let client = self.client.as_mut().expect("client is None");
         let broker_name = client.get_broker_name_from_message_queue(mq).await;
         let mut broker_addr = client
@@ -111,19 +273,56 @@ impl MQAdminImpl {
                 .find_broker_address_in_publish(broker_name.as_ref())
                 .await;
         }
-        if let Some(ref broker_addr) = broker_addr {
-            let offset = client
-                .mq_client_api_impl
-                .as_mut()
-                .expect("mq_client_api_impl is None")
-                .get_max_offset(broker_addr, mq, self.timeout_millis)
-                .await?;
-            return Ok(offset);
-        }
+        broker_addr.ok_or_else(|| {
+            MQClientErr(
+                -1,
+                format!("The broker[{}] not exist", mq.get_broker_name()),
+            )
+        })
+    }
+}
 
-        unimplemented!("max_offset")
+/// Picks the message whose unique key is exactly `unique_key` among the candidates of an index
+/// lookup, preferring the latest stored one.
+fn select_unique_key_message(
+    message_list: Vec<MessageExt>,
+    unique_key: &str,
+) -> Option<MessageExt> {
+    let unique_key_property =
+        CheetahString::from_static_str(MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX);
+    message_list
+        .into_iter()
+        .filter(|msg| {
+            msg.get_property(&unique_key_property)
+                .is_some_and(|key| key == unique_key)
+        })
+        .max_by_key(|msg| msg.store_timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(unique_key: &str, store_timestamp: i64) -> MessageExt {
+        let mut msg = MessageExt::default();
+        msg.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX),
+            CheetahString::from_slice(unique_key),
+        );
+        msg.store_timestamp = store_timestamp;
+        msg
     }
-    pub async fn search_offset(&mut self, mq: &MessageQueue, timestamp: u64) -> Result<i64> {
-        unimplemented!("max_offset")
+
+    #[test]
+    fn select_unique_key_message_filters_collisions_and_prefers_latest() {
+        let candidates = vec![
+            message("KEY", 1000),
+            message("OTHER", 5000),
+            message("KEY", 3000),
+            message("KEY", 2000),
+        ];
+        let selected = select_unique_key_message(candidates.clone(), "KEY").unwrap();
+        assert_eq!(selected.store_timestamp, 3000);
+        assert!(select_unique_key_message(candidates, "MISSING").is_none());
     }
 }
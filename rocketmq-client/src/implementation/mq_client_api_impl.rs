@@ -14,8 +14,10 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Instant;
 
@@ -23,8 +25,10 @@ use cheetah_string::CheetahString;
 use lazy_static::lazy_static;
 use rocketmq_common::common::message::message_batch::MessageBatch;
 use rocketmq_common::common::message::message_client_id_setter::MessageClientIDSetter;
+use rocketmq_common::common::message::message_decoder;
 use rocketmq_common::common::message::message_ext::MessageExt;
 use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::common::message::message_queue_assignment::MessageQueueAssignment;
 use rocketmq_common::common::message::MessageConst;
 use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::common::mix_all;
@@ -33,17 +37,25 @@ use rocketmq_common::common::namesrv::name_server_update_callback::NameServerUpd
 use rocketmq_common::common::namesrv::top_addressing::TopAddressing;
 use rocketmq_common::common::sys_flag::pull_sys_flag::PullSysFlag;
 use rocketmq_common::common::topic::TopicValidator;
+use rocketmq_common::utils::name_server_address_utils::NameServerAddressUtils;
 use rocketmq_remoting::base::connection_net_event::ConnectionNetEvent;
 use rocketmq_remoting::clients::rocketmq_default_impl::RocketmqDefaultClient;
 use rocketmq_remoting::clients::RemotingClient;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::error::Error as RemotingError;
 use rocketmq_remoting::protocol::body::check_client_request_body::CheckClientRequestBody;
 use rocketmq_remoting::protocol::body::get_consumer_listby_group_response_body::GetConsumerListByGroupResponseBody;
+use rocketmq_remoting::protocol::body::query_assignment_request_body::QueryAssignmentRequestBody;
+use rocketmq_remoting::protocol::body::query_assignment_response_body::QueryAssignmentResponseBody;
+use rocketmq_remoting::protocol::body::query_correction_offset_body::QueryCorrectionOffsetBody;
 use rocketmq_remoting::protocol::body::request::lock_batch_request_body::LockBatchRequestBody;
 use rocketmq_remoting::protocol::body::response::lock_batch_response_body::LockBatchResponseBody;
+use rocketmq_remoting::protocol::body::set_message_request_mode_request_body::SetMessageRequestModeRequestBody;
+use rocketmq_remoting::protocol::body::topic::topic_list::TopicList;
 use rocketmq_remoting::protocol::body::unlock_batch_request_body::UnlockBatchRequestBody;
 use rocketmq_remoting::protocol::header::client_request_header::GetRouteInfoRequestHeader;
+use rocketmq_remoting::protocol::header::clone_group_offset_request_header::CloneGroupOffsetRequestHeader;
 use rocketmq_remoting::protocol::header::consumer_send_msg_back_request_header::ConsumerSendMsgBackRequestHeader;
 use rocketmq_remoting::protocol::header::end_transaction_request_header::EndTransactionRequestHeader;
 use rocketmq_remoting::protocol::header::get_consumer_listby_group_request_header::GetConsumerListByGroupRequestHeader;
@@ -54,13 +66,20 @@ use rocketmq_remoting::protocol::header::lock_batch_mq_request_header::LockBatch
 use rocketmq_remoting::protocol::header::message_operation_header::send_message_request_header::SendMessageRequestHeader;
 use rocketmq_remoting::protocol::header::message_operation_header::send_message_request_header_v2::SendMessageRequestHeaderV2;
 use rocketmq_remoting::protocol::header::message_operation_header::send_message_response_header::SendMessageResponseHeader;
+use rocketmq_remoting::protocol::header::namesrv::topic_operation_header::GetAllTopicListRequestHeader;
 use rocketmq_remoting::protocol::header::pull_message_request_header::PullMessageRequestHeader;
 use rocketmq_remoting::protocol::header::pull_message_response_header::PullMessageResponseHeader;
 use rocketmq_remoting::protocol::header::query_consumer_offset_request_header::QueryConsumerOffsetRequestHeader;
 use rocketmq_remoting::protocol::header::query_consumer_offset_response_header::QueryConsumerOffsetResponseHeader;
+use rocketmq_remoting::protocol::header::query_correction_offset_header::QueryCorrectionOffsetHeader;
+use rocketmq_remoting::protocol::header::query_message_request_header::QueryMessageRequestHeader;
+use rocketmq_remoting::protocol::header::query_message_response_header::QueryMessageResponseHeader;
+use rocketmq_remoting::protocol::header::search_offset_request_header::SearchOffsetRequestHeader;
+use rocketmq_remoting::protocol::header::search_offset_response_header::SearchOffsetResponseHeader;
 use rocketmq_remoting::protocol::header::unlock_batch_mq_request_header::UnlockBatchMqRequestHeader;
 use rocketmq_remoting::protocol::header::unregister_client_request_header::UnregisterClientRequestHeader;
 use rocketmq_remoting::protocol::header::update_consumer_offset_header::UpdateConsumerOffsetRequestHeader;
+use rocketmq_remoting::protocol::header::view_message_request_header::ViewMessageRequestHeader;
 use rocketmq_remoting::protocol::heartbeat::heartbeat_data::HeartbeatData;
 use rocketmq_remoting::protocol::heartbeat::subscription_data::SubscriptionData;
 use rocketmq_remoting::protocol::namespace_util::NamespaceUtil;
@@ -75,9 +94,11 @@ use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
 use rocketmq_remoting::runtime::RPCHook;
 use rocketmq_rust::ArcMut;
 use tracing::error;
+use tracing::info;
 use tracing::warn;
 
 use crate::base::client_config::ClientConfig;
+use crate::base::query_result::QueryResult;
 use crate::consumer::consumer_impl::pull_request_ext::PullResultExt;
 use crate::consumer::pull_callback::PullCallback;
 use crate::consumer::pull_result::PullResult;
@@ -104,7 +125,7 @@ lazy_static! {
 
 pub struct MQClientAPIImpl {
     remoting_client: ArcMut<RocketmqDefaultClient<ClientRemotingProcessor>>,
-    top_addressing: Box<dyn TopAddressing>,
+    top_addressing: Arc<dyn TopAddressing>,
     // client_remoting_processor: ClientRemotingProcessor,
     name_srv_addr: Option<String>,
     client_config: ClientConfig,
@@ -132,7 +153,7 @@ impl MQClientAPIImpl {
 
         MQClientAPIImpl {
             remoting_client: ArcMut::new(default_client),
-            top_addressing: Box::new(DefaultTopAddressing::new(
+            top_addressing: Arc::new(DefaultTopAddressing::new(
                 mix_all::get_ws_addr().into(),
                 client_config.unit_name.clone(),
             )),
@@ -148,33 +169,28 @@ impl MQClientAPIImpl {
     }
 
     pub async fn fetch_name_server_addr(&mut self) -> Option<String> {
-        let addrs = self.top_addressing.fetch_ns_addr();
-        if addrs.is_some() && !addrs.as_ref().unwrap().is_empty() {
-            let mut notify = false;
-            if let Some(addr) = self.name_srv_addr.as_mut() {
-                let addrs = addrs.unwrap();
-                if addr != addrs.as_str() {
-                    *addr = addrs.clone();
-                    notify = true;
-                }
-            }
-            if notify {
-                let name_srv = self.name_srv_addr.as_ref().unwrap().as_str();
-                self.update_name_server_address_list(name_srv).await;
-                return Some(name_srv.to_string());
+        let top_addressing = self.top_addressing.clone();
+        let addrs = tokio::task::spawn_blocking(move || top_addressing.fetch_ns_addr())
+            .await
+            .ok()
+            .flatten()
+            .filter(|addrs| !addrs.is_empty());
+        if let Some(addrs) = addrs {
+            if self.name_srv_addr.as_deref() != Some(addrs.as_str()) {
+                info!(
+                    "name server address changed, old={:?}, new={}",
+                    self.name_srv_addr, addrs
+                );
+                self.update_name_server_address_list(addrs.as_str()).await;
+                self.name_srv_addr = Some(addrs);
             }
         }
-
         self.name_srv_addr.clone()
     }
 
     pub async fn update_name_server_address_list(&self, addrs: &str) {
-        let addr_vec = addrs
-            .split(";")
-            .map(CheetahString::from_slice)
-            .collect::<Vec<CheetahString>>();
         self.remoting_client
-            .update_name_server_address_list(addr_vec)
+            .update_name_server_address_list(NameServerAddressUtils::split_addresses(addrs))
             .await;
     }
 
@@ -260,10 +276,50 @@ impl MQClientAPIImpl {
         }
     }
 
+    /// The topics known to the name server, only those starting with `topic_prefix` if given,
+    /// which lets tools page through a cluster with many topics.
+    pub async fn get_topic_list_from_name_server(
+        &self,
+        topic_prefix: Option<&str>,
+        timeout_millis: u64,
+    ) -> Result<TopicList> {
+        let request_header = GetAllTopicListRequestHeader {
+            topic_prefix: topic_prefix.map(CheetahString::from_slice),
+        };
+        let request = RemotingCommand::create_request_command(
+            RequestCode::GetAllTopicListFromNameserver,
+            request_header,
+        );
+        let response = self
+            .remoting_client
+            .invoke_async(None, request, timeout_millis)
+            .await?;
+        if ResponseCode::from(response.code()) == ResponseCode::Success {
+            if let Some(body) = response.body() {
+                return TopicList::decode(body.as_ref())
+                    .map_err(|err| MQClientError::MQClientErr(-1, err.to_string()));
+            }
+        }
+        Err(MQClientError::MQClientErr(
+            response.code(),
+            response.remark().cloned().unwrap_or_default().to_string(),
+        ))
+    }
+
     pub fn get_name_server_address_list(&self) -> &[CheetahString] {
         self.remoting_client.get_name_server_address_list()
     }
 
+    /// The name server route and topic list requests currently go to.
+    pub fn get_namesrv_addr_choosed(&self) -> Option<CheetahString> {
+        self.remoting_client.namesrv_addr_choosed()
+    }
+
+    /// Connect and request failures per name server, for troubleshooting.
+    pub fn get_namesrv_failure_counts(&self) -> HashMap<CheetahString, u64> {
+        self.remoting_client.namesrv_failure_counts()
+    }
+
     pub async fn send_message<T>(
         &mut self,
         addr: &CheetahString,
@@ -320,6 +376,9 @@ impl MQClientAPIImpl {
                 RemotingCommand::create_request_command(RequestCode::SendMessage, request_header)
             }
         };
+        for (key, value) in producer.ext_fields() {
+            request.add_ext_field(key.clone(), value.clone());
+        }
 
         // if compressed_body is not None, set request body to compressed_body
         if msg.get_compressed_body_mut().is_some() {
@@ -450,66 +509,65 @@ impl MQClientAPIImpl {
             .remoting_client
             .invoke_async(Some(addr), request.clone(), timeout_millis)
             .await;
-        match result {
+        let (err, need_retry) = match result {
             Ok(response) => {
-                let cost_time = (Instant::now() - begin_start_time).as_millis() as u64;
-                if send_callback.is_none() {
-                    let send_result = self.process_send_response(broker_name, msg, &response, addr);
-                    if let Ok(result) = send_result {
-                        if context.is_some() {
-                            let inner = context.as_mut().unwrap();
-                            inner.send_result = Some(result.clone());
-                            producer.execute_send_message_hook_after(context);
-                        }
-                    }
-                    let duration = (Instant::now() - begin_start_time).as_millis() as u64;
-                    producer
-                        .update_fault_item(broker_name.clone(), duration, false, true)
-                        .await;
-                    return;
-                }
                 let send_result = self.process_send_response(broker_name, msg, &response, addr);
                 match send_result {
-                    Ok(result) => {
+                    Ok(mut result) => {
+                        result.set_attempts(times.load(Ordering::Acquire) + 1);
                         if context.is_some() {
                             let inner = context.as_mut().unwrap();
                             inner.send_result = Some(result.clone());
                             producer.execute_send_message_hook_after(context);
                         }
                         let duration = (Instant::now() - begin_start_time).as_millis() as u64;
-                        send_callback.as_ref().unwrap()(Some(&result), None);
+                        if let Some(send_callback) = send_callback {
+                            producer
+                                .send_callback_executor()
+                                .execute(move || send_callback(Some(&result), None));
+                        }
                         producer
                             .update_fault_item(broker_name.clone(), duration, false, true)
                             .await;
+                        return;
                     }
                     Err(err) => {
-                        let duration = (Instant::now() - begin_start_time).as_millis() as u64;
-                        producer
-                            .update_fault_item(broker_name.clone(), duration, true, true)
-                            .await;
-                        Box::pin(self.on_exception_impl(
-                            broker_name,
-                            msg,
-                            duration,
-                            request,
-                            send_callback,
-                            topic_publish_info,
-                            instance,
-                            retry_times_when_send_failed,
-                            times,
-                            err,
-                            context,
-                            false,
-                            producer,
-                        ))
-                        .await;
+                        let need_retry = err.response_code() == ResponseCode::SystemBusy as i32;
+                        (err, need_retry)
                     }
                 }
             }
             Err(err) => {
                 error!("send message async error: {:?}", err);
+                // only requests that never reached the broker are safe to send again
+                let need_retry = matches!(
+                    err,
+                    RemotingError::ConnectionInvalid(_)
+                        | RemotingError::ChannelSendRequestFailed(_)
+                );
+                (MQClientError::RemotingError(err), need_retry)
             }
-        }
+        };
+        let duration = (Instant::now() - begin_start_time).as_millis() as u64;
+        producer
+            .update_fault_item(broker_name.clone(), duration, true, true)
+            .await;
+        Box::pin(self.on_exception_impl(
+            broker_name,
+            msg,
+            timeout_millis,
+            request,
+            send_callback,
+            topic_publish_info,
+            instance,
+            retry_times_when_send_failed,
+            times,
+            err,
+            context,
+            need_retry,
+            producer,
+        ))
+        .await;
     }
 
     fn process_send_response<T>(
@@ -647,10 +705,22 @@ impl MQClientAPIImpl {
                 producer,
             ))
             .await;
-        } else if context.is_some() {
-            let inner = context.as_mut().unwrap();
-            inner.exception = Some(Arc::new(Box::new(e)));
-            producer.execute_send_message_hook_after(context);
+        } else {
+            let err: Arc<Box<dyn std::error::Error + Send + Sync>> =
+                Arc::new(Box::new(MQClientError::AsyncSendFailed {
+                    attempts: tmp + 1,
+                    source: Box::new(e),
+                }));
+            if context.is_some() {
+                let inner = context.as_mut().unwrap();
+                inner.exception = Some(err.clone());
+                producer.execute_send_message_hook_after(context);
+            }
+            if let Some(send_callback) = send_callback {
+                producer
+                    .send_callback_executor()
+                    .execute(move || send_callback(None, Some(err.as_ref().as_ref())));
+            }
         }
     }
 
@@ -867,15 +937,19 @@ impl MQClientAPIImpl {
         timeout_millis: u64,
         communication_mode: CommunicationMode,
         pull_callback: PCB,
+        ext_fields: &HashMap<CheetahString, CheetahString>,
     ) -> Result<Option<PullResultExt>>
     where
         PCB: PullCallback + 'static,
     {
-        let request = if PullSysFlag::has_lite_pull_flag(request_header.sys_flag as u32) {
+        let mut request = if PullSysFlag::has_lite_pull_flag(request_header.sys_flag as u32) {
             RemotingCommand::create_request_command(RequestCode::LitePullMessage, request_header)
         } else {
             RemotingCommand::create_request_command(RequestCode::PullMessage, request_header)
         };
+        for (key, value) in ext_fields {
+            request.add_ext_field(key.clone(), value.clone());
+        }
         match communication_mode {
             CommunicationMode::Sync => {
                 let result_ext = this
@@ -981,7 +1055,7 @@ impl MQClientAPIImpl {
     pub async fn consumer_send_message_back(
         &mut self,
         addr: &str,
-        broker_name: &str,
+        broker_name: Option<&str>,
         msg: &MessageExt,
         consumer_group: &str,
         delay_level: i32,
@@ -999,7 +1073,7 @@ impl MQClientAPIImpl {
             rpc_request_header: Some(RpcRequestHeader {
                 namespace: None,
                 namespaced: None,
-                broker_name: Some(CheetahString::from_slice(broker_name)),
+                broker_name: broker_name.map(CheetahString::from_slice),
                 oneway: None,
             }),
         };
@@ -1143,6 +1217,227 @@ impl MQClientAPIImpl {
         }
     }
 
+    /// Asks the broker at `addr` which queues of `topic` this client consumes when the
+    /// broker rebalances the group, `None` when the broker has no assignment to give.
+    pub async fn query_assignment(
+        &mut self,
+        addr: &str,
+        request_body: QueryAssignmentRequestBody,
+        timeout_millis: u64,
+    ) -> Result<Option<HashSet<MessageQueueAssignment>>> {
+        let request = RemotingCommand::create_remoting_command(RequestCode::QueryAssignment)
+            .set_body(request_body.encode());
+        let response = self
+            .remoting_client
+            .invoke_async(
+                Some(&mix_all::broker_vip_channel(
+                    self.client_config.vip_channel_enabled,
+                    addr,
+                )),
+                request,
+                timeout_millis,
+            )
+            .await?;
+        if ResponseCode::from(response.code()) == ResponseCode::Success {
+            match response.body() {
+                Some(body) => QueryAssignmentResponseBody::decode(body.as_ref())
+                    .map(|body| Some(body.message_queue_assignments))
+                    .map_err(|e| MQBrokerError(response.code(), e.to_string(), addr.to_string())),
+                None => Ok(None),
+            }
+        } else {
+            Err(MQBrokerError(
+                response.code(),
+                response.remark().map_or("".to_string(), |s| s.to_string()),
+                addr.to_string(),
+            ))
+        }
+    }
+
+    /// Switches how the broker at `addr` serves `request_body.topic` to its consumer group.
+    pub async fn set_message_request_mode(
+        &mut self,
+        addr: &str,
+        request_body: SetMessageRequestModeRequestBody,
+        timeout_millis: u64,
+    ) -> Result<()> {
+        let request = RemotingCommand::create_remoting_command(RequestCode::SetMessageRequestMode)
+            .set_body(request_body.encode());
+        let response = self
+            .remoting_client
+            .invoke_async(
+                Some(&mix_all::broker_vip_channel(
+                    self.client_config.vip_channel_enabled,
+                    addr,
+                )),
+                request,
+                timeout_millis,
+            )
+            .await?;
+        if ResponseCode::from(response.code()) == ResponseCode::Success {
+            Ok(())
+        } else {
+            Err(MQBrokerError(
+                response.code(),
+                response.remark().map_or("".to_string(), |s| s.to_string()),
+                addr.to_string(),
+            ))
+        }
+    }
+
+    /// Adds the groups of `properties` to the cold control list of the broker at `addr`, each
+    /// with the cold bytes per second it may read.
+    pub async fn update_cold_data_flow_ctr_group_config(
+        &mut self,
+        addr: &str,
+        properties: &HashMap<CheetahString, CheetahString>,
+        timeout_millis: u64,
+    ) -> Result<()> {
+        let request =
+            RemotingCommand::create_remoting_command(RequestCode::UpdateColdDataFlowCtrConfig)
+                .set_body(mix_all::properties_to_string(properties));
+        self.invoke_cold_data_flow_ctr(addr, request, timeout_millis)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn remove_cold_data_flow_ctr_group_config(
+        &mut self,
+        addr: &str,
+        consumer_group: &str,
+        timeout_millis: u64,
+    ) -> Result<()> {
+        let request =
+            RemotingCommand::create_remoting_command(RequestCode::RemoveColdDataFlowCtrConfig)
+                .set_body(consumer_group.to_string());
+        self.invoke_cold_data_flow_ctr(addr, request, timeout_millis)
+            .await
+            .map(|_| ())
+    }
+
+    /// The cold control list and the per group cold read counters as JSON.
+    pub async fn get_cold_data_flow_ctr_info(
+        &mut self,
+        addr: &str,
+        timeout_millis: u64,
+    ) -> Result<String> {
+        let request = RemotingCommand::create_remoting_command(RequestCode::GetColdDataFlowCtrInfo);
+        let response = self
+            .invoke_cold_data_flow_ctr(addr, request, timeout_millis)
+            .await?;
+        Ok(response
+            .get_body()
+            .map(|body| String::from_utf8_lossy(body).into_owned())
+            .unwrap_or_default())
+    }
+
+    async fn invoke_cold_data_flow_ctr(
+        &mut self,
+        addr: &str,
+        request: RemotingCommand,
+        timeout_millis: u64,
+    ) -> Result<RemotingCommand> {
+        let response = self
+            .remoting_client
+            .invoke_async(
+                Some(&mix_all::broker_vip_channel(
+                    self.client_config.vip_channel_enabled,
+                    addr,
+                )),
+                request,
+                timeout_millis,
+            )
+            .await?;
+        if ResponseCode::from(response.code()) == ResponseCode::Success {
+            Ok(response)
+        } else {
+            Err(MQBrokerError(
+                response.code(),
+                response.remark().map_or("".to_string(), |s| s.to_string()),
+                addr.to_string(),
+            ))
+        }
+    }
+
+    /// Copies the committed offsets of `src_group` to `dest_group` on the broker at `addr`, see
+    /// [`CloneGroupOffsetRequestHeader`].
+    pub async fn clone_group_offset(
+        &mut self,
+        addr: &str,
+        request_header: CloneGroupOffsetRequestHeader,
+        timeout_millis: u64,
+    ) -> Result<()> {
+        let request =
+            RemotingCommand::create_request_command(RequestCode::CloneGroupOffset, request_header);
+        let response = self
+            .remoting_client
+            .invoke_async(
+                Some(&mix_all::broker_vip_channel(
+                    self.client_config.vip_channel_enabled,
+                    addr,
+                )),
+                request,
+                timeout_millis,
+            )
+            .await?;
+        if ResponseCode::from(response.code()) == ResponseCode::Success {
+            Ok(())
+        } else {
+            Err(MQBrokerError(
+                response.code(),
+                response.remark().map_or("".to_string(), |s| s.to_string()),
+                addr.to_string(),
+            ))
+        }
+    }
+
+    /// The offset per queue `compare_group` can be corrected to on the broker at `addr`, see
+    /// [`QueryCorrectionOffsetBody`].
+    pub async fn query_correction_offset(
+        &mut self,
+        addr: &str,
+        topic: &str,
+        compare_group: &str,
+        filter_groups: Option<&str>,
+        timeout_millis: u64,
+    ) -> Result<HashMap<i32, i64>> {
+        let request_header = QueryCorrectionOffsetHeader {
+            filter_groups: filter_groups.map(CheetahString::from_slice),
+            compare_group: CheetahString::from_slice(compare_group),
+            topic: CheetahString::from_slice(topic),
+        };
+        let request = RemotingCommand::create_request_command(
+            RequestCode::QueryCorrectionOffset,
+            request_header,
+        );
+        let response = self
+            .remoting_client
+            .invoke_async(
+                Some(&mix_all::broker_vip_channel(
+                    self.client_config.vip_channel_enabled,
+                    addr,
+                )),
+                request,
+                timeout_millis,
+            )
+            .await?;
+        if ResponseCode::from(response.code()) == ResponseCode::Success {
+            let body = response
+                .get_body()
+                .map(|body| QueryCorrectionOffsetBody::decode(body))
+                .transpose()
+                .map_err(|err| MQClientError::MQClientErr(-1, err.to_string()))?
+                .unwrap_or_default();
+            Ok(body.correction_offsets)
+        } else {
+            Err(MQBrokerError(
+                response.code(),
+                response.remark().map_or("".to_string(), |s| s.to_string()),
+                addr.to_string(),
+            ))
+        }
+    }
+
     pub async fn end_transaction_oneway(
         &mut self,
         addr: &CheetahString,
@@ -1205,4 +1500,150 @@ impl MQClientAPIImpl {
             addr.to_string(),
         ))
     }
+
+    pub async fn search_offset(
+        &mut self,
+        addr: &str,
+        message_queue: &MessageQueue,
+        timestamp: i64,
+        timeout_millis: u64,
+    ) -> Result<i64> {
+        let request_header = SearchOffsetRequestHeader {
+            topic: CheetahString::from_slice(message_queue.get_topic()),
+            queue_id: message_queue.get_queue_id(),
+            timestamp,
+            topic_request_header: Some(TopicRequestHeader {
+                rpc_request_header: Some(RpcRequestHeader {
+                    broker_name: Some(CheetahString::from_slice(message_queue.get_broker_name())),
+                    ..Default::default()
+                }),
+                lo: None,
+            }),
+        };
+
+        let request = RemotingCommand::create_request_command(
+            RequestCode::SearchOffsetByTimestamp,
+            request_header,
+        );
+
+        let response = self
+            .remoting_client
+            .invoke_async(
+                Some(&mix_all::broker_vip_channel(
+                    self.client_config.vip_channel_enabled,
+                    addr,
+                )),
+                request,
+                timeout_millis,
+            )
+            .await?;
+        if ResponseCode::from(response.code()) == ResponseCode::Success {
+            let response_header = response
+                .decode_command_custom_header::<SearchOffsetResponseHeader>()
+                .expect("decode error");
+            return Ok(response_header.offset);
+        }
+        Err(MQBrokerError(
+            response.code(),
+            response.remark().map_or("".to_string(), |s| s.to_string()),
+            addr.to_string(),
+        ))
+    }
+
+    /// Fetches the message stored at `phy_offset` in the commit log of the broker at `addr`.
+    pub async fn view_message(
+        &mut self,
+        addr: &str,
+        topic: &CheetahString,
+        phy_offset: i64,
+        timeout_millis: u64,
+    ) -> Result<MessageExt> {
+        let request_header = ViewMessageRequestHeader {
+            topic: topic.clone(),
+            offset: phy_offset,
+        };
+        let request =
+            RemotingCommand::create_request_command(RequestCode::ViewMessageById, request_header);
+        let response = self
+            .remoting_client
+            .invoke_async(
+                Some(&mix_all::broker_vip_channel(
+                    self.client_config.vip_channel_enabled,
+                    addr,
+                )),
+                request,
+                timeout_millis,
+            )
+            .await?;
+        if ResponseCode::from(response.code()) == ResponseCode::Success {
+            if let Some(mut body) = response.body().clone() {
+                if let Some(mut message_ext) =
+                    message_decoder::decode(&mut body, true, true, false, false, false)
+                {
+                    if let Some(namespace) = self.client_config.get_namespace() {
+                        let topic = NamespaceUtil::without_namespace_with_namespace(
+                            message_ext.get_topic(),
+                            namespace.as_str(),
+                        );
+                        message_ext.set_topic(topic.into());
+                    }
+                    return Ok(message_ext);
+                }
+            }
+        }
+        Err(MQBrokerError(
+            response.code(),
+            response.remark().map_or("".to_string(), |s| s.to_string()),
+            addr.to_string(),
+        ))
+    }
+
+    /// Queries the index of the broker at `addr` for the messages of a topic stored under a key.
+    /// A unique key query looks the key up as a message id instead of a user defined key.
+    pub async fn query_message(
+        &mut self,
+        addr: &str,
+        request_header: QueryMessageRequestHeader,
+        timeout_millis: u64,
+        is_unique_key: bool,
+    ) -> Result<QueryResult> {
+        let mut request =
+            RemotingCommand::create_request_command(RequestCode::QueryMessage, request_header);
+        request.add_ext_field(
+            CheetahString::from_static_str(mix_all::UNIQUE_MSG_QUERY_FLAG),
+            CheetahString::from_string(is_unique_key.to_string()),
+        );
+        let response = self
+            .remoting_client
+            .invoke_async(
+                Some(&mix_all::broker_vip_channel(
+                    self.client_config.vip_channel_enabled,
+                    addr,
+                )),
+                request,
+                timeout_millis,
+            )
+            .await?;
+        match ResponseCode::from(response.code()) {
+            ResponseCode::Success => {
+                let response_header = response
+                    .decode_command_custom_header::<QueryMessageResponseHeader>()
+                    .expect("decode error");
+                let message_list = match response.body().clone() {
+                    Some(mut body) => message_decoder::decodes_batch(&mut body, true, true),
+                    None => Vec::new(),
+                };
+                Ok(QueryResult::new(
+                    response_header.index_last_update_timestamp as u64,
+                    message_list,
+                ))
+            }
+            ResponseCode::QueryNotFound => Ok(QueryResult::new(0, Vec::new())),
+            _ => Err(MQBrokerError(
+                response.code(),
+                response.remark().map_or("".to_string(), |s| s.to_string()),
+                addr.to_string(),
+            )),
+        }
+    }
 }
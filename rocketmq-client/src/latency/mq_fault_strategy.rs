@@ -81,7 +81,7 @@ impl MQFaultStrategy {
     }
 
     pub fn is_start_detector_enable(&self) -> bool {
-        unimplemented!("not implemented")
+        self.start_detector_enable.load(Ordering::Acquire)
     }
 
     pub fn select_one_message_queue(
@@ -22,7 +22,7 @@ extern crate core;
 
 use crate::error::MQClientError;
 
-mod admin;
+pub mod admin;
 pub mod base;
 mod common;
 pub mod consumer;
@@ -14,19 +14,23 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::sync::Arc;
 
 use cheetah_string::CheetahString;
 use rocketmq_common::common::compression::compression_type::CompressionType;
 use rocketmq_common::common::compression::compressor::Compressor;
+use rocketmq_remoting::protocol::remoting_command::passthrough_ext_field_key;
 use rocketmq_remoting::runtime::RPCHook;
 
 use crate::base::client_config::ClientConfig;
+use crate::base::validators::Validators;
 use crate::producer::default_mq_producer::DefaultMQProducer;
 use crate::producer::produce_accumulator::ProduceAccumulator;
 use crate::producer::producer_impl::default_mq_producer_impl::DefaultMQProducerImpl;
 use crate::trace::trace_dispatcher::TraceDispatcher;
+use crate::Result;
 
 #[derive(Default)]
 pub struct DefaultMQProducerBuilder {
@@ -53,6 +57,9 @@ pub struct DefaultMQProducerBuilder {
     compress_level: Option<i32>,
     compress_type: Option<CompressionType>,
     compressor: Option<Arc<Box<dyn Compressor + Send + Sync>>>,
+    ext_fields: HashMap<CheetahString, CheetahString>,
+    enable_msg_trace: Option<bool>,
+    customized_trace_topic: Option<CheetahString>,
 }
 
 impl DefaultMQProducerBuilder {
@@ -81,6 +88,9 @@ impl DefaultMQProducerBuilder {
             compress_level: None,
             compress_type: None,
             compressor: None,
+            ext_fields: HashMap::new(),
+            enable_msg_trace: None,
+            customized_trace_topic: None,
         }
     }
 
@@ -147,6 +157,11 @@ impl DefaultMQProducerBuilder {
         self
     }
 
+    /// Shorthand for [`Self::retry_times_when_send_failed`], the retries of a synchronous send.
+    pub fn retry_times(self, retry_times: u32) -> Self {
+        self.retry_times_when_send_failed(retry_times)
+    }
+
     pub fn retry_times_when_send_async_failed(
         mut self,
         retry_times_when_send_async_failed: u32,
@@ -230,9 +245,51 @@ impl DefaultMQProducerBuilder {
         self
     }
 
-    pub fn build(self) -> DefaultMQProducer {
+    /// Attaches `key`, prefixed with `ext.`, to every send request. The broker echoes it back in
+    /// the response.
+    pub fn with_ext_field(
+        mut self,
+        key: impl Into<CheetahString>,
+        value: impl Into<CheetahString>,
+    ) -> Self {
+        self.ext_fields
+            .insert(passthrough_ext_field_key(key), value.into());
+        self
+    }
+
+    /// Records the messages sent by this producer to the trace topic.
+    pub fn enable_msg_trace(mut self, enable_msg_trace: bool) -> Self {
+        self.enable_msg_trace = Some(enable_msg_trace);
+        self
+    }
+
+    /// Trace topic used instead of `RMQ_SYS_TRACE_TOPIC` when message trace is enabled.
+    pub fn customized_trace_topic(
+        mut self,
+        customized_trace_topic: impl Into<CheetahString>,
+    ) -> Self {
+        self.customized_trace_topic = Some(customized_trace_topic.into());
+        self
+    }
+
+    /// Builds the producer, refusing an invalid producer group or name server list.
+    pub fn build(self) -> Result<DefaultMQProducer> {
+        let producer = self.build_unchecked();
+        Validators::check_client_config(producer.client_config())?;
+        Validators::check_producer_config(producer.producer_config())?;
+        Ok(producer)
+    }
+
+    /// Builds the producer without validating it, leaving the checks to `start()`.
+    pub(crate) fn build_unchecked(self) -> DefaultMQProducer {
         let mut mq_producer = DefaultMQProducer::default();
-        if let Some(client_config) = self.client_config {
+        if let Some(mut client_config) = self.client_config {
+            if let Some(enable_msg_trace) = self.enable_msg_trace {
+                client_config.enable_trace = enable_msg_trace;
+            }
+            if let Some(customized_trace_topic) = self.customized_trace_topic {
+                client_config.trace_topic = Some(customized_trace_topic);
+            }
             mq_producer.set_client_config(client_config);
         }
 
@@ -300,6 +357,7 @@ impl DefaultMQProducerBuilder {
         if let Some(compressor) = self.compressor {
             mq_producer.set_compressor(Some(compressor));
         }
+        mq_producer.set_ext_fields(self.ext_fields);
 
         if let Some(default_mqproducer_impl) = self.default_mqproducer_impl {
             mq_producer.set_default_mqproducer_impl(default_mqproducer_impl);
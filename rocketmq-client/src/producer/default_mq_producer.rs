@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::sync::Arc;
 
@@ -22,6 +23,7 @@ use cheetah_string::CheetahString;
 use rocketmq_common::common::compression::compression_type::CompressionType;
 use rocketmq_common::common::compression::compressor::Compressor;
 use rocketmq_common::common::compression::compressor_factory::CompressorFactory;
+use rocketmq_common::common::message::message_batch::ListSplitter;
 use rocketmq_common::common::message::message_batch::MessageBatch;
 use rocketmq_common::common::message::message_client_id_setter::MessageClientIDSetter;
 use rocketmq_common::common::message::message_queue::MessageQueue;
@@ -39,6 +41,7 @@ use tracing::error;
 
 use crate::base::client_config::ClientConfig;
 use crate::base::validators::Validators;
+use crate::error::MQClientError;
 use crate::error::MQClientError::MQClientErr;
 use crate::producer::default_mq_produce_builder::DefaultMQProducerBuilder;
 use crate::producer::mq_producer::MQProducer;
@@ -46,6 +49,7 @@ use crate::producer::produce_accumulator::ProduceAccumulator;
 use crate::producer::producer_impl::default_mq_producer_impl::DefaultMQProducerImpl;
 use crate::producer::send_callback::SendMessageCallback;
 use crate::producer::send_result::SendResult;
+use crate::producer::send_status::SendStatus;
 use crate::producer::transaction_send_result::TransactionSendResult;
 use crate::trace::async_trace_dispatcher::AsyncTraceDispatcher;
 use crate::trace::hook::end_transaction_trace_hook_impl::EndTransactionTraceHookImpl;
@@ -103,6 +107,8 @@ pub struct ProducerConfig {
     compress_level: i32,
     compress_type: CompressionType,
     compressor: Option<Arc<Box<dyn Compressor + Send + Sync>>>,
+    /// `ext.`-prefixed extFields attached to every send request, the broker echoes them back.
+    ext_fields: HashMap<CheetahString, CheetahString>,
 }
 
 impl ProducerConfig {
@@ -190,6 +196,10 @@ impl ProducerConfig {
     pub fn compressor(&self) -> &Option<Arc<Box<dyn Compressor + Send + Sync>>> {
         &self.compressor
     }
+
+    pub fn ext_fields(&self) -> &HashMap<CheetahString, CheetahString> {
+        &self.ext_fields
+    }
 }
 
 impl Default for ProducerConfig {
@@ -238,6 +248,7 @@ impl Default for ProducerConfig {
             compressor: Some(Arc::new(CompressorFactory::get_compressor(
                 compression_type,
             ))),
+            ext_fields: HashMap::new(),
         }
     }
 }
@@ -255,7 +266,7 @@ impl DefaultMQProducer {
         DefaultMQProducerBuilder::new()
     }
     pub fn new() -> Self {
-        Self::builder().build()
+        Self::builder().build_unchecked()
     }
 
     pub fn client_config(&self) -> &ClientConfig {
@@ -464,6 +475,10 @@ impl DefaultMQProducer {
         self.producer_config.compressor = compressor;
     }
 
+    pub fn set_ext_fields(&mut self, ext_fields: HashMap<CheetahString, CheetahString>) {
+        self.producer_config.ext_fields = ext_fields;
+    }
+
     pub fn producer_config(&self) -> &ProducerConfig {
         &self.producer_config
     }
@@ -476,26 +491,111 @@ impl DefaultMQProducer {
     }
 
     fn batch(&mut self, messages: Vec<Message>) -> Result<MessageBatch> {
-        match MessageBatch::generate_from_vec(messages) {
-            Ok(mut msg_batch) => {
-                for message in msg_batch.messages.as_mut().unwrap() {
-                    Validators::check_message(Some(message), &self.producer_config)?;
-                    MessageClientIDSetter::set_uniq_id(message);
-                    message.set_topic(self.with_namespace(message.get_topic()));
+        let messages = self.prepare_batch(messages)?;
+        self.wrap_batch(messages)
+    }
+
+    /// Validates the messages of a batch and stamps each with its unique id and namespaced
+    /// topic, so that their encoded size is final before the batch is split.
+    fn prepare_batch(&mut self, messages: Vec<Message>) -> Result<Vec<Message>> {
+        let mut messages = Self::generate_batch(messages)?.messages.unwrap_or_default();
+        for message in &mut messages {
+            Validators::check_message(Some(message), &self.producer_config)?;
+            MessageClientIDSetter::set_uniq_id(message);
+            message.set_topic(self.with_namespace(message.get_topic()));
+        }
+        Ok(messages)
+    }
+
+    fn wrap_batch(&mut self, messages: Vec<Message>) -> Result<MessageBatch> {
+        let mut msg_batch = Self::generate_batch(messages)?;
+        MessageClientIDSetter::set_uniq_id(&mut msg_batch.final_message);
+        msg_batch.set_body(msg_batch.encode());
+        msg_batch.set_topic(self.with_namespace(msg_batch.get_topic()));
+        Ok(msg_batch)
+    }
+
+    fn generate_batch(messages: Vec<Message>) -> Result<MessageBatch> {
+        MessageBatch::generate_from_vec(messages).map_err(|err| {
+            error!("Failed to initiate the MessageBatch: {:?}", err);
+            MQClientErr(-1, "Failed to initiate the MessageBatch".to_string())
+        })
+    }
+
+    /// Sends a batch as consecutive chunks that each encode to at most `max_message_size`
+    /// bytes, one request at a time, and returns the result of every chunk. The first chunk
+    /// that fails aborts the send; once earlier chunks went through, the error is a
+    /// `BatchSendAborted` carrying their results.
+    pub async fn send_batch_chunks(
+        &mut self,
+        msgs: Vec<Message>,
+        mq: Option<MessageQueue>,
+        timeout: Option<u64>,
+    ) -> Result<Vec<SendResult>> {
+        let messages = self.prepare_batch(msgs)?;
+        let chunks = ListSplitter::new(messages, self.producer_config.max_message_size as usize)
+            .collect::<Vec<_>>();
+        let mut sent = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            let result = match self.send_batch_chunk(chunk, mq.clone(), timeout).await {
+                Ok(result) => result,
+                Err(err) if sent.is_empty() => return Err(err),
+                Err(err) => {
+                    return Err(MQClientError::BatchSendAborted {
+                        sent,
+                        source: Box::new(err),
+                    })
                 }
-                MessageClientIDSetter::set_uniq_id(&mut msg_batch.final_message);
-                msg_batch.set_body(msg_batch.encode());
-                msg_batch.set_topic(self.with_namespace(msg_batch.get_topic()));
-                Ok(msg_batch)
+            };
+            sent.push(result);
+        }
+        Ok(sent)
+    }
+
+    async fn send_batch_chunk(
+        &mut self,
+        chunk: Vec<Message>,
+        mq: Option<MessageQueue>,
+        timeout: Option<u64>,
+    ) -> Result<SendResult> {
+        let mut batch = self.wrap_batch(chunk)?;
+        let producer_impl = self.default_mqproducer_impl.as_mut().unwrap();
+        let result = match (mq, timeout) {
+            (None, None) => producer_impl.send(&mut batch).await?,
+            (None, Some(timeout)) => producer_impl.send_with_timeout(&mut batch, timeout).await?,
+            (Some(mq), None) => {
+                producer_impl
+                    .sync_send_with_message_queue(batch, mq)
+                    .await?
             }
-            Err(err) => {
-                error!("Failed to initiate the MessageBatch: {:?}", err);
-                Err(MQClientErr(
-                    -1,
-                    "Failed to initiate the MessageBatch".to_string(),
-                ))
+            (Some(mq), Some(timeout)) => {
+                producer_impl
+                    .sync_send_with_message_queue_timeout(batch, mq, timeout)
+                    .await?
             }
+        };
+        Ok(result.expect("SendResult should not be None"))
+    }
+
+    /// Folds the results of a chunked batch into one: the first status that is not `SendOk`
+    /// wins, the message ids are joined, and the queue and offset are those of the first chunk.
+    fn merge_send_results(results: Vec<SendResult>) -> SendResult {
+        let mut results = results.into_iter();
+        let mut merged = results.next().unwrap_or_default();
+        for result in results {
+            if merged.send_status == SendStatus::SendOk {
+                merged.send_status = result.send_status;
+            }
+            merged.msg_id = match (merged.msg_id.take(), result.msg_id) {
+                (Some(ids), Some(id)) => Some(format!("{},{}", ids, id).into()),
+                (ids, id) => ids.or(id),
+            };
+            merged.offset_msg_id = match (merged.offset_msg_id.take(), result.offset_msg_id) {
+                (Some(ids), Some(id)) => Some(format!("{},{}", ids, id)),
+                (ids, id) => ids.or(id),
+            };
         }
+        merged
     }
 
     #[inline]
@@ -642,7 +742,10 @@ impl MQProducer for DefaultMQProducer {
             let mut dispatcher = AsyncTraceDispatcher::new(
                 self.producer_config.producer_group.as_str(),
                 Type::Produce,
-                self.client_config.trace_topic.clone().unwrap().as_str(),
+                self.client_config
+                    .trace_topic
+                    .as_ref()
+                    .map_or(TopicValidator::RMQ_SYS_TRACE_TOPIC, |topic| topic.as_str()),
                 self.producer_config.rpc_hook.clone(),
             );
             dispatcher.set_host_producer(self.default_mqproducer_impl.as_ref().unwrap().clone());
@@ -1016,14 +1119,8 @@ impl MQProducer for DefaultMQProducer {
     }
 
     async fn send_batch(&mut self, msgs: Vec<Message>) -> Result<SendResult> {
-        let mut batch = self.batch(msgs)?;
-        let result = self
-            .default_mqproducer_impl
-            .as_mut()
-            .unwrap()
-            .send(&mut batch)
-            .await?;
-        Ok(result.expect("SendResult should not be None"))
+        let results = self.send_batch_chunks(msgs, None, None).await?;
+        Ok(Self::merge_send_results(results))
     }
 
     async fn send_batch_with_timeout(
@@ -1031,14 +1128,8 @@ impl MQProducer for DefaultMQProducer {
         msgs: Vec<Message>,
         timeout: u64,
     ) -> Result<SendResult> {
-        let mut batch = self.batch(msgs)?;
-        let result = self
-            .default_mqproducer_impl
-            .as_mut()
-            .unwrap()
-            .send_with_timeout(&mut batch, timeout)
-            .await?;
-        Ok(result.expect("SendResult should not be None"))
+        let results = self.send_batch_chunks(msgs, None, Some(timeout)).await?;
+        Ok(Self::merge_send_results(results))
     }
 
     async fn send_batch_to_queue(
@@ -1046,14 +1137,8 @@ impl MQProducer for DefaultMQProducer {
         msgs: Vec<Message>,
         mq: MessageQueue,
     ) -> Result<SendResult> {
-        let batch = self.batch(msgs)?;
-        let result = self
-            .default_mqproducer_impl
-            .as_mut()
-            .unwrap()
-            .sync_send_with_message_queue(batch, mq)
-            .await?;
-        Ok(result.expect("SendResult should not be None"))
+        let results = self.send_batch_chunks(msgs, Some(mq), None).await?;
+        Ok(Self::merge_send_results(results))
     }
 
     async fn send_batch_to_queue_with_timeout(
@@ -1062,14 +1147,10 @@ impl MQProducer for DefaultMQProducer {
         mq: MessageQueue,
         timeout: u64,
     ) -> Result<SendResult> {
-        let batch = self.batch(msgs)?;
-        let result = self
-            .default_mqproducer_impl
-            .as_mut()
-            .unwrap()
-            .sync_send_with_message_queue_timeout(batch, mq, timeout)
+        let results = self
+            .send_batch_chunks(msgs, Some(mq), Some(timeout))
             .await?;
-        Ok(result.expect("SendResult should not be None"))
+        Ok(Self::merge_send_results(results))
     }
 
     async fn send_batch_with_callback<F>(&mut self, msgs: Vec<Message>, f: F) -> Result<()>
@@ -1260,3 +1341,459 @@ impl MQProducer for DefaultMQProducer {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use bytes::Bytes;
+    use futures::future::BoxFuture;
+    use opentelemetry::trace::SpanKind;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry::Key;
+    use opentelemetry::Value;
+    use opentelemetry_sdk::export::trace::ExportResult;
+    use opentelemetry_sdk::export::trace::SpanData;
+    use opentelemetry_sdk::export::trace::SpanExporter;
+    use opentelemetry_sdk::trace::TracerProvider;
+    use rocketmq_common::common::message::message_decoder;
+    use rocketmq_remoting::code::request_code::RequestCode;
+    use rocketmq_remoting::net::channel::Channel;
+    use rocketmq_remoting::protocol::header::message_operation_header::send_message_response_header::SendMessageResponseHeader;
+    use rocketmq_remoting::protocol::header::notify_topic_route_changed_request_header::NotifyTopicRouteChangedRequestHeader;
+    use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+    use rocketmq_remoting::test_util::FakeBroker;
+    use rocketmq_remoting::test_util::topic_route;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::producer::producer_impl::send_callback_executor::SEND_CALLBACK_THREAD_PREFIX;
+
+    const TOPIC: &str = "TracedTopic";
+
+    /// What a fake broker of `TOPIC` accepting every send once `busy_sends` sends were turned
+    /// down as busy, and recording the body of every batch until `fail_batches_from` batches
+    /// were received, was sent. The route it serves has `write_queue_nums` queues, and it
+    /// remembers the queue and the connection of every accepted send.
+    #[derive(Clone)]
+    struct BrokerState {
+        batches: Arc<parking_lot::Mutex<Vec<Bytes>>>,
+        fail_batches_from: Option<usize>,
+        busy_sends: Arc<AtomicUsize>,
+        write_queue_nums: Arc<AtomicUsize>,
+        sent_queue_ids: Arc<parking_lot::Mutex<Vec<i32>>>,
+        senders: Arc<parking_lot::Mutex<Vec<Channel>>>,
+    }
+
+    impl BrokerState {
+        fn new() -> Self {
+            BrokerState {
+                batches: Arc::default(),
+                fail_batches_from: None,
+                busy_sends: Arc::default(),
+                write_queue_nums: Arc::new(AtomicUsize::new(1)),
+                sent_queue_ids: Arc::default(),
+                senders: Arc::default(),
+            }
+        }
+
+        /// Starts the fake broker, returning its address.
+        async fn spawn(&self) -> CheetahString {
+            let broker = FakeBroker::bind().await;
+            let addr = broker.addr();
+            let (send, send_v2, send_batch) = (self.clone(), self.clone(), self.clone());
+            let write_queue_nums = self.write_queue_nums.clone();
+            broker
+                .on(RequestCode::GetRouteinfoByTopic, move |_, _| {
+                    topic_route(&addr, write_queue_nums.load(Ordering::Acquire) as u32)
+                })
+                .on(RequestCode::SendMessage, move |channel, request| {
+                    send.send(channel, request)
+                })
+                .on(RequestCode::SendMessageV2, move |channel, request| {
+                    send_v2.send(channel, request)
+                })
+                .on(RequestCode::SendBatchMessage, move |_, request| {
+                    send_batch.send_batch(request)
+                })
+                .spawn()
+        }
+
+        fn send(&self, channel: Channel, request: &RemotingCommand) -> RemotingCommand {
+            if self
+                .busy_sends
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |busy| {
+                    busy.checked_sub(1)
+                })
+                .is_ok()
+            {
+                return RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::SystemBusy,
+                    "broker busy",
+                );
+            }
+            // the V2 header abbreviates `queueId` to `e`
+            let queue_id = request.ext_fields().and_then(|fields| {
+                fields
+                    .get("queueId")
+                    .or_else(|| fields.get("e"))
+                    .and_then(|queue_id| queue_id.parse().ok())
+            });
+            self.sent_queue_ids.lock().push(queue_id.unwrap_or(-1));
+            self.senders.lock().push(channel);
+            let mut response_header = SendMessageResponseHeader::default();
+            response_header.set_msg_id("0A00000100002A9F0000000000000000");
+            RemotingCommand::create_response_command().set_command_custom_header(response_header)
+        }
+
+        fn send_batch(&self, request: &RemotingCommand) -> RemotingCommand {
+            let mut batches = self.batches.lock();
+            if self
+                .fail_batches_from
+                .is_some_and(|from| batches.len() >= from)
+            {
+                return RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::SystemError,
+                    "batch rejected",
+                );
+            }
+            batches.push(request.body().clone().unwrap());
+            let mut response_header = SendMessageResponseHeader::default();
+            response_header.set_msg_id("0A00000100002A9F0000000000000000");
+            RemotingCommand::create_response_command().set_command_custom_header(response_header)
+        }
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct InMemorySpanExporter {
+        spans: Arc<parking_lot::Mutex<Vec<SpanData>>>,
+    }
+
+    impl SpanExporter for InMemorySpanExporter {
+        fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+            self.spans.lock().extend(batch);
+            Box::pin(std::future::ready(Ok(())))
+        }
+    }
+
+    fn request_code(span: &SpanData) -> Option<i64> {
+        match span.attributes.get(&Key::new("request_code")) {
+            Some(Value::I64(code)) => Some(*code),
+            _ => None,
+        }
+    }
+
+    fn is_send(span: &SpanData) -> bool {
+        request_code(span).is_some_and(|code| {
+            code == RequestCode::SendMessage as i64 || code == RequestCode::SendMessageV2 as i64
+        })
+    }
+
+    #[tokio::test]
+    async fn send_is_traced_from_producer_to_broker_handling() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = TracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let _subscriber = tracing::subscriber::set_default(
+            tracing_subscriber::registry()
+                .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test"))),
+        );
+
+        let addr = BrokerState::new().spawn().await;
+        let client_config = ClientConfig {
+            client_ip: Some("127.0.0.1".into()),
+            instance_name: "traced-producer".into(),
+            namesrv_addr: Some(addr),
+            vip_channel_enabled: false,
+            ..Default::default()
+        };
+        let mut producer = DefaultMQProducer::builder()
+            .client_config(client_config)
+            .producer_group("traced_producer_group")
+            .build()
+            .unwrap();
+        producer.start().await.unwrap();
+        producer.send(Message::new(TOPIC, b"traced")).await.unwrap();
+
+        // spans are exported once they end, the broker side first
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+        let (send, invoke, request) = loop {
+            let spans = exporter.spans.lock().clone();
+            let find = |name: &str, send_only: bool| {
+                spans
+                    .iter()
+                    .find(|span| span.name == name && (!send_only || is_send(span)))
+                    .cloned()
+            };
+            if let (Some(send), Some(invoke), Some(request)) = (
+                find("producer.send", false),
+                find("remoting.invoke", true),
+                find("remoting.request", true),
+            ) {
+                break (send, invoke, request);
+            }
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "the send was not traced from the producer to the broker"
+            );
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        };
+        producer.shutdown().await;
+
+        let trace_id = send.span_context.trace_id();
+        assert_eq!(send.span_kind, SpanKind::Producer);
+        assert_eq!(invoke.span_kind, SpanKind::Client);
+        assert_eq!(invoke.span_context.trace_id(), trace_id);
+        assert_eq!(invoke.parent_span_id, send.span_context.span_id());
+        assert_eq!(request.span_kind, SpanKind::Server);
+        assert_eq!(request.span_context.trace_id(), trace_id);
+        assert_eq!(request.parent_span_id, invoke.span_context.span_id());
+        assert_eq!(
+            request.attributes.get(&Key::new("response_code")),
+            Some(&Value::I64(ResponseCode::Success as i64))
+        );
+        assert!(matches!(
+            request.attributes.get(&Key::new("response_size")),
+            Some(Value::I64(size)) if *size > 0
+        ));
+        assert!(request.attributes.get(&Key::new("peer")).is_some());
+    }
+
+    async fn start_producer(broker: BrokerState, instance_name: &str) -> DefaultMQProducer {
+        let addr = broker.spawn().await;
+        let client_config = ClientConfig {
+            client_ip: Some("127.0.0.1".into()),
+            instance_name: instance_name.into(),
+            namesrv_addr: Some(addr),
+            vip_channel_enabled: false,
+            ..Default::default()
+        };
+        let mut producer = DefaultMQProducer::builder()
+            .client_config(client_config)
+            .producer_group("test_producer_group")
+            .retry_times_when_send_failed(0)
+            .build()
+            .unwrap();
+        producer.start().await.unwrap();
+        producer
+    }
+
+    /// About 10MB of messages whose bodies range from a byte to just over a megabyte.
+    fn big_batch() -> Vec<Message> {
+        let sizes = [1, 700, 16 * 1024, 333 * 1024, 1024 * 1024 + 7, 64];
+        let mut messages = Vec::new();
+        let mut total = 0;
+        while total < 10 * 1024 * 1024 {
+            let size = sizes[messages.len() % sizes.len()];
+            messages.push(Message::new(TOPIC, &vec![messages.len() as u8; size]));
+            total += size;
+        }
+        messages
+    }
+
+    #[tokio::test]
+    async fn big_batch_is_split_into_chunks_under_the_message_size_limit() {
+        let broker = BrokerState::new();
+        let batches = broker.batches.clone();
+        let mut producer = start_producer(broker, "big-batch-producer").await;
+        let messages = big_batch();
+        let max_message_size = producer.max_message_size() as usize;
+
+        let result = producer.send_batch(messages.clone()).await.unwrap();
+        producer.shutdown().await;
+
+        assert_eq!(result.send_status, SendStatus::SendOk);
+        let chunks = batches
+            .lock()
+            .iter()
+            .map(|body| message_decoder::decode_messages(&mut body.clone()))
+            .collect::<Vec<_>>();
+        assert!(chunks.len() >= 3, "sent {} chunk(s)", chunks.len());
+        assert_eq!(
+            result.msg_id.unwrap().split(',').count(),
+            messages.len(),
+            "one id per message"
+        );
+        for (index, chunk) in chunks.iter().enumerate() {
+            let size = batches.lock()[index].len();
+            assert!(size <= max_message_size, "chunk {index} is {size} bytes");
+            if let Some(next) = chunks.get(index + 1) {
+                assert!(size + message_decoder::encoded_message_size(&next[0]) > max_message_size);
+            }
+        }
+        let delivered = chunks.into_iter().flatten().collect::<Vec<_>>();
+        assert_eq!(delivered.len(), messages.len());
+        for (delivered, message) in delivered.iter().zip(&messages) {
+            assert_eq!(delivered.get_body(), message.get_body());
+            assert!(delivered
+                .get_property(&MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX.into())
+                .is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_chunk_aborts_the_batch_with_the_chunks_already_sent() {
+        let broker = BrokerState {
+            fail_batches_from: Some(1),
+            ..BrokerState::new()
+        };
+        let batches = broker.batches.clone();
+        let mut producer = start_producer(broker, "aborted-batch-producer").await;
+
+        let err = producer.send_batch(big_batch()).await.unwrap_err();
+        producer.shutdown().await;
+
+        assert!(err
+            .to_string()
+            .starts_with("Batch send aborted after 1 chunk(s) were sent"));
+        match err {
+            MQClientError::BatchSendAborted { sent, source } => {
+                assert_eq!(sent.len(), 1);
+                assert_eq!(sent[0].send_status, SendStatus::SendOk);
+                assert!(!matches!(*source, MQClientError::BatchSendAborted { .. }));
+            }
+            err => panic!("unexpected error: {err}"),
+        }
+        assert_eq!(batches.lock().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn busy_async_send_is_retried_and_calls_back_off_the_sender_threads() {
+        let broker = BrokerState::new();
+        broker.busy_sends.store(1, Ordering::Release);
+        let mut producer = start_producer(broker, "retried-async-producer").await;
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let release_rx = parking_lot::Mutex::new(release_rx);
+        let (done_tx, mut done_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        producer
+            .send_with_callback(Message::new(TOPIC, b"retried"), move |result, err| {
+                let thread = std::thread::current()
+                    .name()
+                    .unwrap_or_default()
+                    .to_string();
+                done_tx
+                    .send((result.cloned(), err.map(|err| err.to_string()), thread))
+                    .unwrap();
+                // a slow callback must not hold up the sends that follow
+                let _ = release_rx.lock().recv_timeout(Duration::from_secs(10));
+            })
+            .await
+            .unwrap();
+        let (result, err, thread) = tokio::time::timeout(Duration::from_secs(10), done_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let next = tokio::time::timeout(
+            Duration::from_secs(5),
+            producer.send(Message::new(TOPIC, b"next")),
+        )
+        .await;
+        release_tx.send(()).unwrap();
+        producer.shutdown().await;
+
+        assert_eq!(err, None);
+        let result = result.unwrap();
+        assert_eq!(result.send_status, SendStatus::SendOk);
+        assert_eq!(result.attempts(), 2);
+        assert!(thread.starts_with(SEND_CALLBACK_THREAD_PREFIX), "{thread}");
+        assert!(next.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn async_send_reports_the_attempts_once_the_retries_are_exhausted() {
+        let broker = BrokerState::new();
+        broker.busy_sends.store(usize::MAX, Ordering::Release);
+        let mut producer = start_producer(broker, "exhausted-async-producer").await;
+        let (done_tx, mut done_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        producer
+            .send_with_callback(Message::new(TOPIC, b"busy"), move |result, err| {
+                done_tx
+                    .send((result.is_some(), err.map(|err| err.to_string())))
+                    .unwrap();
+            })
+            .await
+            .unwrap();
+        let (succeeded, failure) = tokio::time::timeout(Duration::from_secs(10), done_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        producer.shutdown().await;
+
+        assert!(!succeeded);
+        let failure = failure.unwrap();
+        let attempts = producer.retry_times_when_send_async_failed() + 1;
+        assert!(
+            failure.starts_with(&format!("Async send failed after {attempts} attempt(s)")),
+            "{failure}"
+        );
+        assert!(failure.contains("broker busy"), "{failure}");
+    }
+
+    /// Sends `count` messages and returns the queues the broker received them on.
+    async fn send_round(
+        producer: &mut DefaultMQProducer,
+        sent_queue_ids: &parking_lot::Mutex<Vec<i32>>,
+        count: usize,
+    ) -> Vec<i32> {
+        sent_queue_ids.lock().clear();
+        for _ in 0..count {
+            producer.send(Message::new(TOPIC, b"route")).await.unwrap();
+        }
+        std::mem::take(&mut *sent_queue_ids.lock())
+    }
+
+    #[tokio::test]
+    async fn route_change_notification_refreshes_the_route_within_a_second() {
+        let broker = BrokerState::new();
+        let write_queue_nums = broker.write_queue_nums.clone();
+        let sent_queue_ids = broker.sent_queue_ids.clone();
+        let senders = broker.senders.clone();
+        let mut producer = start_producer(broker, "route-notified-producer").await;
+        // let the poll the client instance runs right after starting go by
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        assert_eq!(send_round(&mut producer, &sent_queue_ids, 1).await, vec![0]);
+        write_queue_nums.store(4, Ordering::Release);
+        // left alone, the producer keeps the route it polled until the next poll
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(
+            send_round(&mut producer, &sent_queue_ids, 4).await,
+            vec![0; 4]
+        );
+
+        let mut channel = senders.lock()[0].clone();
+        let notified_at = Instant::now();
+        channel
+            .send_one_way(
+                RemotingCommand::create_request_command(
+                    RequestCode::NotifyTopicRouteChanged,
+                    NotifyTopicRouteChangedRequestHeader {
+                        topic: TOPIC.into(),
+                    },
+                ),
+                1000,
+            )
+            .await
+            .unwrap();
+        let mut queue_ids = Vec::new();
+        while notified_at.elapsed() < Duration::from_secs(1) {
+            queue_ids = send_round(&mut producer, &sent_queue_ids, 4).await;
+            queue_ids.sort_unstable();
+            if queue_ids == [0, 1, 2, 3] {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let refreshed_in = notified_at.elapsed();
+        producer.shutdown().await;
+
+        assert_eq!(queue_ids, vec![0, 1, 2, 3], "refreshed in {refreshed_in:?}");
+        assert!(refreshed_in < Duration::from_secs(1), "{refreshed_in:?}");
+    }
+}
@@ -17,4 +17,5 @@
 pub(crate) mod default_mq_producer_impl;
 pub(crate) mod mq_producer_inner;
 pub mod queue_filter;
+pub(crate) mod send_callback_executor;
 pub mod topic_publish_info;
@@ -18,6 +18,7 @@ use std::any::Any;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::future::Future;
+use std::net::IpAddr;
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
@@ -36,7 +37,6 @@ use rocketmq_common::common::message::MessageConst;
 use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::common::mix_all;
 use rocketmq_common::common::mix_all::CLIENT_INNER_PRODUCER_GROUP;
-use rocketmq_common::common::mix_all::DEFAULT_PRODUCER_GROUP;
 use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
 use rocketmq_common::common::FAQUrl;
 use rocketmq_common::utils::correlation_id_util::CorrelationIdUtil;
@@ -56,6 +56,7 @@ use tokio::runtime::Handle;
 use tokio::sync::RwLock;
 use tokio::sync::Semaphore;
 use tracing::warn;
+use tracing::Instrument;
 
 use crate::base::client_config::ClientConfig;
 use crate::base::validators::Validators;
@@ -81,6 +82,7 @@ use crate::producer::local_transaction_state::LocalTransactionState;
 use crate::producer::message_queue_selector::MessageQueueSelectorFn;
 use crate::producer::producer_impl::mq_producer_inner::MQProducerInner;
 use crate::producer::producer_impl::mq_producer_inner::MQProducerInnerImpl;
+use crate::producer::producer_impl::send_callback_executor::SendCallbackExecutor;
 use crate::producer::producer_impl::topic_publish_info::TopicPublishInfo;
 use crate::producer::request_callback::RequestCallbackFn;
 use crate::producer::request_future_holder::REQUEST_FUTURE_HOLDER;
@@ -92,6 +94,10 @@ use crate::producer::transaction_listener::TransactionListener;
 use crate::producer::transaction_send_result::TransactionSendResult;
 use crate::Result;
 
+/// Callbacks queued for the send callback threads before the executor overflows onto the
+/// blocking pool.
+const SEND_CALLBACK_QUEUE_CAPACITY: usize = 10_000;
+
 pub struct DefaultMQProducerImpl {
     client_config: ClientConfig,
     producer_config: Arc<ProducerConfig>,
@@ -107,6 +113,7 @@ pub struct DefaultMQProducerImpl {
     semaphore_async_send_size: Arc<Semaphore>,
     async_sender_runtime: Option<Arc<RocketMQRuntime>>,
     default_async_sender_runtime: Option<Arc<RocketMQRuntime>>,
+    send_callback_executor: Arc<SendCallbackExecutor>,
     default_mqproducer_impl_inner: Option<ArcMut<DefaultMQProducerImpl>>,
     transaction_listener: Option<Arc<Box<dyn TransactionListener>>>,
     check_runtime: Option<Arc<RocketMQRuntime>>,
@@ -146,6 +153,10 @@ impl DefaultMQProducerImpl {
                 num_cpus::get(),
                 "async-sender",
             ))),
+            send_callback_executor: Arc::new(SendCallbackExecutor::new(
+                num_cpus::get(),
+                SEND_CALLBACK_QUEUE_CAPACITY,
+            )),
             default_mqproducer_impl_inner: None,
             transaction_listener: None,
             check_runtime: None,
@@ -665,6 +676,11 @@ impl DefaultMQProducerImpl {
         Ok(())
     }
 
+    #[inline]
+    pub(crate) fn send_callback_executor(&self) -> &SendCallbackExecutor {
+        &self.send_callback_executor
+    }
+
     #[inline]
     pub fn get_async_sender_executor(&self) -> &Arc<RocketMQRuntime> {
         if let Some(ref async_sender_runtime) = self.async_sender_runtime {
@@ -681,6 +697,28 @@ impl DefaultMQProducerImpl {
         send_callback: Option<SendMessageCallback>,
         timeout: u64,
     ) -> Result<Option<SendResult>>
+    where
+        T: MessageTrait + Clone + Send + Sync,
+    {
+        // parent of the spans of every attempt, see `rocketmq_remoting::request_tracing`
+        let span = tracing::trace_span!(
+            "producer.send",
+            otel.kind = "producer",
+            topic = msg.get_topic().as_str(),
+            communication_mode = ?communication_mode,
+        );
+        self.send_default_impl_inner(msg, communication_mode, send_callback, timeout)
+            .instrument(span)
+            .await
+    }
+
+    async fn send_default_impl_inner<T>(
+        &mut self,
+        msg: &mut T,
+        communication_mode: CommunicationMode,
+        send_callback: Option<SendMessageCallback>,
+        timeout: u64,
+    ) -> Result<Option<SendResult>>
     where
         T: MessageTrait + Clone + Send + Sync,
     {
@@ -1013,6 +1051,16 @@ impl DefaultMQProducerImpl {
                 sys_flag |= MessageSysFlag::TRANSACTION_PREPARED_TYPE;
             }
         }
+        // The broker records the born host in the address family the producer connects from.
+        let local_ip_is_v6 = self
+            .client_config
+            .client_ip
+            .as_ref()
+            .and_then(|ip| ip.parse::<IpAddr>().ok())
+            .is_some_and(|ip| ip.is_ipv6());
+        if local_ip_is_v6 {
+            sys_flag |= MessageSysFlag::BORNHOST_V6_FLAG;
+        }
 
         if self.has_check_forbidden_hook() {
             let check_forbidden_context = CheckForbiddenContext {
@@ -1215,6 +1263,11 @@ impl DefaultMQProducerImpl {
         }
     }
 
+    /// The passthrough extFields attached to every send request.
+    pub fn ext_fields(&self) -> &HashMap<CheetahString, CheetahString> {
+        self.producer_config.ext_fields()
+    }
+
     pub fn has_send_message_hook(&self) -> bool {
         !self.send_message_hook_list.is_empty()
     }
@@ -2302,18 +2355,7 @@ impl DefaultMQProducerImpl {
 
     #[inline]
     fn check_config(&self) -> Result<()> {
-        Validators::check_group(self.producer_config.producer_group())?;
-        if self.producer_config.producer_group() == DEFAULT_PRODUCER_GROUP {
-            return Err(MQClientError::MQClientErr(
-                -1,
-                format!(
-                    "The specified group name[{}] is equal to default group, please specify \
-                     another one.",
-                    DEFAULT_PRODUCER_GROUP
-                ),
-            ));
-        }
-        Ok(())
+        Validators::check_producer_config(&self.producer_config)
     }
 
     async fn init_topic_route(&mut self) {
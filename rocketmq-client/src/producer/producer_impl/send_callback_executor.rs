@@ -0,0 +1,81 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::panic::AssertUnwindSafe;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::SyncSender;
+use std::sync::mpsc::TrySendError;
+use std::sync::Arc;
+use std::thread;
+
+use parking_lot::Mutex;
+use tracing::error;
+use tracing::warn;
+
+pub(crate) const SEND_CALLBACK_THREAD_PREFIX: &str = "SendCallbackExecutor_";
+
+type Task = Box<dyn FnOnce() + Send>;
+
+/// Runs send callbacks on a fixed set of dedicated threads fed by a bounded queue, so that a slow
+/// callback holds up neither the remoting reactor nor the async sender runtime. Once the queue is
+/// full, callbacks overflow onto tokio's blocking pool.
+pub(crate) struct SendCallbackExecutor {
+    tx: SyncSender<Task>,
+}
+
+impl SendCallbackExecutor {
+    pub(crate) fn new(threads: usize, capacity: usize) -> Self {
+        let (tx, rx) = std::sync::mpsc::sync_channel::<Task>(capacity);
+        let rx = Arc::new(Mutex::new(rx));
+        for index in 0..threads.max(1) {
+            let rx = rx.clone();
+            thread::Builder::new()
+                .name(format!("{}{}", SEND_CALLBACK_THREAD_PREFIX, index))
+                .spawn(move || Self::run(&rx))
+                .expect("failed to spawn a send callback thread");
+        }
+        SendCallbackExecutor { tx }
+    }
+
+    fn run(rx: &Mutex<Receiver<Task>>) {
+        loop {
+            // bound separately so the lock is released before the callback runs; the threads
+            // exit once the executor, and with it the sender, is dropped
+            let task = rx.lock().recv();
+            let Ok(task) = task else {
+                break;
+            };
+            if std::panic::catch_unwind(AssertUnwindSafe(task)).is_err() {
+                error!("send callback panicked");
+            }
+        }
+    }
+
+    /// Queues `callback`, or hands it to the blocking pool of the current runtime when the queue
+    /// is full. Must be called from within a tokio runtime.
+    pub(crate) fn execute<F>(&self, callback: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        match self.tx.try_send(Box::new(callback)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(task)) | Err(TrySendError::Disconnected(task)) => {
+                warn!("send callback queue is full, running the callback on the blocking pool");
+                drop(tokio::task::spawn_blocking(task));
+            }
+        }
+    }
+}
@@ -32,6 +32,8 @@ pub struct SendResult {
     pub region_id: Option<String>,
     pub trace_on: bool,
     pub raw_resp_body: Option<Vec<u8>>,
+    /// How many times the message was sent until it was accepted.
+    pub attempts: u32,
 }
 
 impl Default for SendResult {
@@ -46,6 +48,7 @@ impl Default for SendResult {
             region_id: None,
             trace_on: true,
             raw_resp_body: None,
+            attempts: 1,
         }
     }
 }
@@ -68,6 +71,7 @@ impl SendResult {
             region_id: None,
             trace_on: true,
             raw_resp_body: None,
+            attempts: 1,
         }
     }
 
@@ -90,6 +94,7 @@ impl SendResult {
             region_id,
             trace_on: true,
             raw_resp_body: None,
+            attempts: 1,
         }
     }
 
@@ -136,6 +141,14 @@ impl SendResult {
     pub fn get_raw_resp_body(&self) -> Option<&[u8]> {
         self.raw_resp_body.as_deref()
     }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn set_attempts(&mut self, attempts: u32) {
+        self.attempts = attempts;
+    }
 }
 
 impl std::fmt::Display for SendResult {
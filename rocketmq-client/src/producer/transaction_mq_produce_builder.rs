@@ -14,16 +14,19 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::sync::Arc;
 
 use cheetah_string::CheetahString;
 use rocketmq_common::common::compression::compression_type::CompressionType;
 use rocketmq_common::common::compression::compressor::Compressor;
+use rocketmq_remoting::protocol::remoting_command::passthrough_ext_field_key;
 use rocketmq_remoting::runtime::RPCHook;
 use rocketmq_runtime::RocketMQRuntime;
 
 use crate::base::client_config::ClientConfig;
+use crate::base::validators::Validators;
 use crate::producer::default_mq_producer::DefaultMQProducer;
 use crate::producer::produce_accumulator::ProduceAccumulator;
 use crate::producer::producer_impl::default_mq_producer_impl::DefaultMQProducerImpl;
@@ -31,6 +34,7 @@ use crate::producer::transaction_listener::TransactionListener;
 use crate::producer::transaction_mq_producer::TransactionMQProducer;
 use crate::producer::transaction_mq_producer::TransactionProducerConfig;
 use crate::trace::trace_dispatcher::TraceDispatcher;
+use crate::Result;
 
 #[derive(Default)]
 pub struct TransactionMQProducerBuilder {
@@ -57,6 +61,9 @@ pub struct TransactionMQProducerBuilder {
     compress_level: Option<i32>,
     compress_type: Option<CompressionType>,
     compressor: Option<Arc<Box<dyn Compressor + Send + Sync>>>,
+    ext_fields: HashMap<CheetahString, CheetahString>,
+    enable_msg_trace: Option<bool>,
+    customized_trace_topic: Option<CheetahString>,
     transaction_listener: Option<Arc<Box<dyn TransactionListener>>>,
     check_runtime: Option<Arc<RocketMQRuntime>>,
 }
@@ -87,6 +94,9 @@ impl TransactionMQProducerBuilder {
             compress_level: None,
             compress_type: None,
             compressor: None,
+            ext_fields: HashMap::new(),
+            enable_msg_trace: None,
+            customized_trace_topic: None,
             transaction_listener: None,
             check_runtime: None,
         }
@@ -155,6 +165,11 @@ impl TransactionMQProducerBuilder {
         self
     }
 
+    /// Shorthand for [`Self::retry_times_when_send_failed`], the retries of a synchronous send.
+    pub fn retry_times(self, retry_times: u32) -> Self {
+        self.retry_times_when_send_failed(retry_times)
+    }
+
     pub fn retry_times_when_send_async_failed(
         mut self,
         retry_times_when_send_async_failed: u32,
@@ -238,14 +253,48 @@ impl TransactionMQProducerBuilder {
         self
     }
 
+    /// Attaches `key`, prefixed with `ext.`, to every send request. The broker echoes it back in
+    /// the response.
+    pub fn with_ext_field(
+        mut self,
+        key: impl Into<CheetahString>,
+        value: impl Into<CheetahString>,
+    ) -> Self {
+        self.ext_fields
+            .insert(passthrough_ext_field_key(key), value.into());
+        self
+    }
+
     pub fn transaction_listener(mut self, transaction_listener: impl TransactionListener) -> Self {
         self.transaction_listener = Some(Arc::new(Box::new(transaction_listener)));
         self
     }
 
-    pub fn build(self) -> TransactionMQProducer {
+    /// Records the messages sent by this producer to the trace topic.
+    pub fn enable_msg_trace(mut self, enable_msg_trace: bool) -> Self {
+        self.enable_msg_trace = Some(enable_msg_trace);
+        self
+    }
+
+    /// Trace topic used instead of `RMQ_SYS_TRACE_TOPIC` when message trace is enabled.
+    pub fn customized_trace_topic(
+        mut self,
+        customized_trace_topic: impl Into<CheetahString>,
+    ) -> Self {
+        self.customized_trace_topic = Some(customized_trace_topic.into());
+        self
+    }
+
+    /// Builds the producer, refusing an invalid producer group or name server list.
+    pub fn build(self) -> Result<TransactionMQProducer> {
         let mut mq_producer = DefaultMQProducer::default();
-        if let Some(client_config) = self.client_config {
+        if let Some(mut client_config) = self.client_config {
+            if let Some(enable_msg_trace) = self.enable_msg_trace {
+                client_config.enable_trace = enable_msg_trace;
+            }
+            if let Some(customized_trace_topic) = self.customized_trace_topic {
+                client_config.trace_topic = Some(customized_trace_topic);
+            }
             mq_producer.set_client_config(client_config);
         }
 
@@ -313,6 +362,9 @@ impl TransactionMQProducerBuilder {
         if let Some(compressor) = self.compressor {
             mq_producer.set_compressor(Some(compressor));
         }
+        mq_producer.set_ext_fields(self.ext_fields);
+        Validators::check_client_config(mq_producer.client_config())?;
+        Validators::check_producer_config(mq_producer.producer_config())?;
 
         if let Some(default_mqproducer_impl) = self.default_mqproducer_impl {
             mq_producer.set_default_mqproducer_impl(default_mqproducer_impl);
@@ -331,7 +383,10 @@ impl TransactionMQProducerBuilder {
             check_request_hold_max: 0,
             check_runtime: self.check_runtime,
         };
-        TransactionMQProducer::new(transaction_producer_config, mq_producer)
+        Ok(TransactionMQProducer::new(
+            transaction_producer_config,
+            mq_producer,
+        ))
     }
 
     pub fn check_runtime(&mut self, check_runtime: RocketMQRuntime) {
@@ -44,6 +44,7 @@ pub mod hasher;
 pub mod key_builder;
 pub mod macros;
 pub mod message;
+pub mod metrics;
 pub mod mix_all;
 pub mod mq_version;
 pub mod namesrv;
@@ -58,6 +59,7 @@ pub mod sys_flag;
 pub mod system_clock;
 pub mod thread;
 pub mod topic;
+pub mod validators;
 
 #[derive(Clone, Default, Eq, PartialEq, Copy)]
 pub enum TopicFilterType {
@@ -14,7 +14,11 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::sync::Arc;
+
+pub mod attribute_boolean;
 pub mod attribute_enum;
+pub mod attribute_long_range;
 pub mod attribute_parser;
 pub mod attribute_util;
 pub mod cleanup_policy;
@@ -46,7 +50,24 @@ pub trait AttributeTrait {
     ///
     /// # Arguments
     /// * `value` - A string slice representing the value to be verified.
-    fn verify(&self, value: &str);
+    ///
+    /// # Returns
+    /// An `Err` describing why the value is not accepted.
+    fn verify(&self, value: &str) -> Result<(), String>;
+}
+
+impl<A: AttributeTrait + ?Sized> AttributeTrait for Arc<A> {
+    fn name(&self) -> String {
+        (**self).name()
+    }
+
+    fn changeable(&self) -> bool {
+        (**self).changeable()
+    }
+
+    fn verify(&self, value: &str) -> Result<(), String> {
+        (**self).verify(value)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -54,3 +75,12 @@ pub struct Attribute {
     pub(crate) name: String,
     pub(crate) changeable: bool,
 }
+
+impl Attribute {
+    pub fn new(name: impl Into<String>, changeable: bool) -> Self {
+        Self {
+            name: name.into(),
+            changeable,
+        }
+    }
+}
@@ -0,0 +1,75 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::common::attribute::Attribute;
+use crate::common::attribute::AttributeTrait;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BooleanAttribute {
+    pub(crate) attribute: Attribute,
+    pub(crate) default_value: bool,
+}
+
+impl AttributeTrait for BooleanAttribute {
+    fn name(&self) -> String {
+        self.attribute.name.clone()
+    }
+
+    fn changeable(&self) -> bool {
+        self.attribute.changeable
+    }
+
+    fn verify(&self, value: &str) -> Result<(), String> {
+        if value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false") {
+            return Ok(());
+        }
+        Err(String::from("boolean attribute format is wrong."))
+    }
+}
+
+impl BooleanAttribute {
+    pub fn new(name: impl Into<String>, changeable: bool, default_value: bool) -> Self {
+        Self {
+            attribute: Attribute::new(name, changeable),
+            default_value,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        self.attribute.name.as_str()
+    }
+
+    pub fn get_default_value(&self) -> bool {
+        self.default_value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_accepts_true_and_false_in_any_case() {
+        let attribute = BooleanAttribute::new("bool.key", true, false);
+        assert!(attribute.verify("true").is_ok());
+        assert!(attribute.verify("FALSE").is_ok());
+        assert_eq!(
+            attribute.verify("yes").unwrap_err(),
+            "boolean attribute format is wrong."
+        );
+        assert!(attribute.verify("").is_err());
+    }
+}
@@ -35,12 +35,30 @@ impl AttributeTrait for EnumAttribute {
         self.attribute.changeable
     }
 
-    fn verify(&self, _value: &str) {
-        todo!()
+    fn verify(&self, value: &str) -> Result<(), String> {
+        if self.universe.contains(value) {
+            return Ok(());
+        }
+        let mut universe = self.universe.iter().collect::<Vec<_>>();
+        universe.sort();
+        Err(format!("value is not in set: {:?}", universe))
     }
 }
 
 impl EnumAttribute {
+    pub fn new(
+        name: impl Into<String>,
+        changeable: bool,
+        universe: HashSet<String>,
+        default_value: impl Into<String>,
+    ) -> Self {
+        Self {
+            attribute: Attribute::new(name, changeable),
+            universe,
+            default_value: default_value.into(),
+        }
+    }
+
     pub fn get_name(&self) -> &str {
         self.attribute.name.as_str()
     }
@@ -52,8 +70,25 @@ impl EnumAttribute {
     pub fn get_universe(&self) -> &HashSet<String> {
         &self.universe
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashset;
 
-    pub fn verify(&self, value: &str) -> bool {
-        !self.universe.contains(value)
+    #[test]
+    fn verify_accepts_only_values_of_the_universe() {
+        let attribute = EnumAttribute::new(
+            "cleanup.policy",
+            false,
+            hashset! {String::from("DELETE"), String::from("COMPACTION")},
+            "DELETE",
+        );
+        assert!(attribute.verify("COMPACTION").is_ok());
+        assert_eq!(
+            attribute.verify("delete").unwrap_err(),
+            r#"value is not in set: ["COMPACTION", "DELETE"]"#
+        );
     }
 }
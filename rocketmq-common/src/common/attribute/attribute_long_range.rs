@@ -0,0 +1,95 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::common::attribute::Attribute;
+use crate::common::attribute::AttributeTrait;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LongRangeAttribute {
+    pub(crate) attribute: Attribute,
+    pub(crate) min: i64,
+    pub(crate) max: i64,
+    pub(crate) default_value: i64,
+}
+
+impl AttributeTrait for LongRangeAttribute {
+    fn name(&self) -> String {
+        self.attribute.name.clone()
+    }
+
+    fn changeable(&self) -> bool {
+        self.attribute.changeable
+    }
+
+    fn verify(&self, value: &str) -> Result<(), String> {
+        let value = value
+            .parse::<i64>()
+            .map_err(|_| format!("value is not a number: {}", value))?;
+        if value < self.min || value > self.max {
+            return Err(format!("value is not in range({}, {})", self.min, self.max));
+        }
+        Ok(())
+    }
+}
+
+impl LongRangeAttribute {
+    pub fn new(
+        name: impl Into<String>,
+        changeable: bool,
+        min: i64,
+        max: i64,
+        default_value: i64,
+    ) -> Self {
+        Self {
+            attribute: Attribute::new(name, changeable),
+            min,
+            max,
+            default_value,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        self.attribute.name.as_str()
+    }
+
+    pub fn get_default_value(&self) -> i64 {
+        self.default_value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_accepts_numbers_within_the_range() {
+        let attribute = LongRangeAttribute::new("long.range.key", true, 10, 20, 15);
+        assert!(attribute.verify("10").is_ok());
+        assert!(attribute.verify("20").is_ok());
+        assert_eq!(
+            attribute.verify("21").unwrap_err(),
+            "value is not in range(10, 20)"
+        );
+        assert_eq!(
+            attribute.verify("9").unwrap_err(),
+            "value is not in range(10, 20)"
+        );
+        assert_eq!(
+            attribute.verify("ten").unwrap_err(),
+            "value is not a number: ten"
+        );
+    }
+}
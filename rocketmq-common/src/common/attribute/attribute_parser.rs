@@ -36,21 +36,20 @@ impl AttributeParser {
             .split(ATTR_ARRAY_SEPARATOR_COMMA)
             .collect();
         for kv in kvs {
-            let mut key = String::new();
-            let mut value = String::new();
-            if kv.contains(ATTR_KEY_VALUE_EQUAL_SIGN) {
-                let splits: Vec<&str> = kv.split(ATTR_KEY_VALUE_EQUAL_SIGN).collect();
-                key.push_str(splits[0]);
-                value.push_str(splits[1]);
-                if !key.contains(ATTR_ADD_PLUS_SIGN) {
-                    return Err(format!("add/alter attribute format is wrong: {}", key));
+            let (key, value) = match kv.split_once(ATTR_KEY_VALUE_EQUAL_SIGN) {
+                Some((key, value)) => {
+                    if !key.starts_with(ATTR_ADD_PLUS_SIGN) {
+                        return Err(format!("add/alter attribute format is wrong: {}", key));
+                    }
+                    (key.to_string(), value.to_string())
                 }
-            } else {
-                key.push_str(kv);
-                if !key.contains(ATTR_DELETE_MINUS_SIGN) {
-                    return Err(format!("delete attribute format is wrong: {}", key));
+                None => {
+                    if !kv.starts_with(ATTR_DELETE_MINUS_SIGN) {
+                        return Err(format!("delete attribute format is wrong: {}", kv));
+                    }
+                    (kv.to_string(), String::new())
                 }
-            }
+            };
             if attributes.insert(key.clone(), value).is_some() {
                 return Err(format!("key duplication: {}", key));
             }
@@ -117,6 +116,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_to_map_topic_attributes() {
+        let result = AttributeParser::parse_to_map("+message.type=FIFO,-cleanup.policy");
+        assert_eq!(
+            result.unwrap(),
+            HashMap::from([
+                ("+message.type".to_string(), "FIFO".to_string()),
+                ("-cleanup.policy".to_string(), "".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_to_map_sign_must_prefix_the_key() {
+        assert_eq!(
+            AttributeParser::parse_to_map("message.type+=FIFO").unwrap_err(),
+            "add/alter attribute format is wrong: message.type+".to_string()
+        );
+        assert_eq!(
+            AttributeParser::parse_to_map("cleanup-policy").unwrap_err(),
+            "delete attribute format is wrong: cleanup-policy".to_string()
+        );
+    }
+
     #[test]
     fn test_parse_to_map_key_duplication_error() {
         let input = "+key1=value1,+key1=value2";
@@ -16,20 +16,21 @@
  */
 use std::collections::HashMap;
 use std::collections::HashSet;
-use std::string::ToString;
-use std::sync::Arc;
 
 use cheetah_string::CheetahString;
 use tracing::info;
 
 use crate::common::attribute::AttributeTrait;
 
+/// Applies the `+key`/`-key` deltas of `new_attributes` to `current_attributes`. A topic being
+/// created only takes additions, and only changeable attributes can be added, altered or
+/// deleted afterwards. Every value is verified by its attribute in `all`.
 pub fn alter_current_attributes<A: AttributeTrait>(
     create: bool,
-    all: HashMap<CheetahString, A>,
+    all: &HashMap<String, A>,
     current_attributes: HashMap<CheetahString, CheetahString>,
     new_attributes: HashMap<CheetahString, CheetahString>,
-) -> HashMap<CheetahString, CheetahString> {
+) -> Result<HashMap<CheetahString, CheetahString>, String> {
     let mut init = HashMap::new();
     let mut add = HashMap::new();
     let mut update = HashMap::new();
@@ -38,85 +39,297 @@ pub fn alter_current_attributes<A: AttributeTrait>(
 
     for (key, value) in new_attributes {
         let real_key = real_key(key.as_str());
-        validate(&real_key);
-        duplication_check(&mut keys, &real_key);
+        validate(&real_key)?;
+        duplication_check(&mut keys, &real_key)?;
 
         if create {
             if key.starts_with('+') {
-                init.insert(real_key.clone().into(), value);
+                init.insert(real_key, value);
             } else {
-                panic!(
+                return Err(format!(
                     "only add attribute is supported while creating topic. key: {}",
                     real_key
-                );
+                ));
             }
         } else if key.starts_with('+') {
             if !current_attributes.contains_key(real_key.as_str()) {
-                add.insert(real_key.clone().into(), value);
+                add.insert(real_key, value);
             } else {
-                update.insert(real_key.clone().into(), value);
+                update.insert(real_key, value);
             }
         } else if key.starts_with('-') {
             if !current_attributes.contains_key(real_key.as_str()) {
-                panic!("attempt to delete a nonexistent key: {}", real_key);
+                return Err(format!("attempt to delete a nonexistent key: {}", real_key));
             }
-            delete.insert(real_key.clone().into(), value);
+            delete.insert(real_key, value);
         } else {
-            panic!("wrong format key: {}", real_key);
+            return Err(format!("wrong format key: {}", key));
         }
     }
 
-    validate_alter(&all, &init, true, false);
-    validate_alter(&all, &add, false, false);
-    validate_alter(&all, &update, false, false);
-    validate_alter(&all, &delete, false, true);
+    validate_alter(all, &init, true, false)?;
+    validate_alter(all, &add, false, false)?;
+    validate_alter(all, &update, false, false)?;
+    validate_alter(all, &delete, false, true)?;
 
     info!("add: {:?}, update: {:?}, delete: {:?}", add, update, delete);
 
-    let mut final_attributes = current_attributes.clone();
+    let mut final_attributes = current_attributes;
     final_attributes.extend(init);
     final_attributes.extend(add);
+    final_attributes.extend(update);
     for key in delete.keys() {
         final_attributes.remove(key);
     }
 
-    final_attributes
+    Ok(final_attributes)
 }
 
-fn duplication_check(keys: &mut HashSet<CheetahString>, key: &String) {
-    if !keys.insert(key.into()) {
-        panic!("alter duplication key. key: {}", key);
+fn duplication_check(keys: &mut HashSet<CheetahString>, key: &CheetahString) -> Result<(), String> {
+    if !keys.insert(key.clone()) {
+        return Err(format!("alter duplication key. key: {}", key));
     }
+    Ok(())
 }
 
-fn validate(kv_attribute: &str) {
+fn validate(kv_attribute: &str) -> Result<(), String> {
     if kv_attribute.is_empty() || kv_attribute.contains('+') || kv_attribute.contains('-') {
-        panic!("kv string format wrong.");
+        return Err(format!("kv string format wrong. key: {}", kv_attribute));
     }
+    Ok(())
 }
 
 fn validate_alter<A: AttributeTrait>(
-    all: &HashMap<CheetahString, A>,
+    all: &HashMap<String, A>,
     alter: &HashMap<CheetahString, CheetahString>,
     init: bool,
     delete: bool,
-) {
+) -> Result<(), String> {
     for (key, value) in alter {
-        let attribute = all.get(key);
-        if attribute.is_none() {
-            panic!("unsupported key: {}", key);
-        }
-        let attribute = attribute.unwrap();
+        let Some(attribute) = all.get(key.as_str()) else {
+            return Err(format!("unsupported key: {}", key));
+        };
         if !init && !attribute.changeable() {
-            panic!("attempt to update an unchangeable attribute. key: {}", key);
+            return Err(format!(
+                "attempt to update an unchangeable attribute. key: {}",
+                key
+            ));
         }
 
         if !delete {
-            attribute.verify(value);
+            attribute
+                .verify(value)
+                .map_err(|err| format!("wrong value of attribute {}: {}", key, err))?;
         }
     }
+    Ok(())
+}
+
+fn real_key(key: &str) -> CheetahString {
+    CheetahString::from_string(key.chars().skip(1).collect())
 }
 
-fn real_key(key: &str) -> String {
-    key.chars().skip(1).collect()
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::common::attribute::attribute_boolean::BooleanAttribute;
+    use crate::common::attribute::attribute_enum::EnumAttribute;
+    use crate::common::attribute::attribute_long_range::LongRangeAttribute;
+    use crate::hashset;
+
+    fn all() -> HashMap<String, Arc<dyn AttributeTrait + Send + Sync>> {
+        let attributes: [Arc<dyn AttributeTrait + Send + Sync>; 4] = [
+            Arc::new(EnumAttribute::new(
+                "enum.key",
+                true,
+                hashset! {String::from("enum-1"), String::from("enum-2")},
+                "enum-1",
+            )),
+            Arc::new(BooleanAttribute::new("bool.key", false, false)),
+            Arc::new(LongRangeAttribute::new("long.range.key", true, 10, 20, 15)),
+            Arc::new(BooleanAttribute::new("unchangeable.key", false, false)),
+        ];
+        attributes
+            .into_iter()
+            .map(|attribute| (attribute.name(), attribute))
+            .collect()
+    }
+
+    fn attributes(kvs: &[(&str, &str)]) -> HashMap<CheetahString, CheetahString> {
+        kvs.iter()
+            .map(|(key, value)| ((*key).into(), (*value).into()))
+            .collect()
+    }
+
+    fn current() -> HashMap<CheetahString, CheetahString> {
+        attributes(&[
+            ("enum.key", "enum-1"),
+            ("long.range.key", "15"),
+            ("unchangeable.key", "true"),
+        ])
+    }
+
+    #[test]
+    fn create_takes_only_additions() {
+        let created = alter_current_attributes(
+            true,
+            &all(),
+            HashMap::new(),
+            attributes(&[("+bool.key", "true"), ("+unchangeable.key", "false")]),
+        )
+        .unwrap();
+        assert_eq!(
+            created,
+            attributes(&[("bool.key", "true"), ("unchangeable.key", "false")])
+        );
+
+        let err = alter_current_attributes(
+            true,
+            &all(),
+            HashMap::new(),
+            attributes(&[("-enum.key", "")]),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            "only add attribute is supported while creating topic. key: enum.key"
+        );
+    }
+
+    #[test]
+    fn add_new_attribute() {
+        let altered = alter_current_attributes(
+            false,
+            &all(),
+            current(),
+            attributes(&[("+enum.key", "enum-2"), ("+long.range.key", "20")]),
+        )
+        .unwrap();
+        assert_eq!(altered.get("enum.key").unwrap(), "enum-2");
+        assert_eq!(altered.get("long.range.key").unwrap(), "20");
+
+        let altered = alter_current_attributes(
+            false,
+            &all(),
+            HashMap::new(),
+            attributes(&[("+long.range.key", "10")]),
+        )
+        .unwrap();
+        assert_eq!(altered, attributes(&[("long.range.key", "10")]));
+    }
+
+    #[test]
+    fn modify_changeable_attribute() {
+        let altered = alter_current_attributes(
+            false,
+            &all(),
+            current(),
+            attributes(&[("+long.range.key", "11")]),
+        )
+        .unwrap();
+        assert_eq!(
+            altered,
+            attributes(&[
+                ("enum.key", "enum-1"),
+                ("long.range.key", "11"),
+                ("unchangeable.key", "true"),
+            ])
+        );
+
+        let err = alter_current_attributes(
+            false,
+            &all(),
+            current(),
+            attributes(&[("+long.range.key", "21")]),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            "wrong value of attribute long.range.key: value is not in range(10, 20)"
+        );
+        let err = alter_current_attributes(
+            false,
+            &all(),
+            current(),
+            attributes(&[("+enum.key", "enum-3")]),
+        )
+        .unwrap_err();
+        assert!(
+            err.starts_with("wrong value of attribute enum.key"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn modify_unchangeable_attribute_is_rejected() {
+        for delta in [
+            ("+unchangeable.key", "false"),
+            ("-unchangeable.key", ""),
+            ("+bool.key", "true"),
+        ] {
+            let key = &delta.0[1..];
+            let err = alter_current_attributes(false, &all(), current(), attributes(&[delta]))
+                .unwrap_err();
+            assert_eq!(
+                err,
+                format!("attempt to update an unchangeable attribute. key: {key}")
+            );
+        }
+    }
+
+    #[test]
+    fn delete_attribute() {
+        let altered =
+            alter_current_attributes(false, &all(), current(), attributes(&[("-enum.key", "")]))
+                .unwrap();
+        assert_eq!(
+            altered,
+            attributes(&[("long.range.key", "15"), ("unchangeable.key", "true")])
+        );
+
+        let err = alter_current_attributes(
+            false,
+            &all(),
+            attributes(&[("long.range.key", "15")]),
+            attributes(&[("-enum.key", "")]),
+        )
+        .unwrap_err();
+        assert_eq!(err, "attempt to delete a nonexistent key: enum.key");
+    }
+
+    #[test]
+    fn unknown_attribute_is_rejected() {
+        for create in [true, false] {
+            let err = alter_current_attributes(
+                create,
+                &all(),
+                current(),
+                attributes(&[("+unknown.key", "value")]),
+            )
+            .unwrap_err();
+            assert_eq!(err, "unsupported key: unknown.key");
+        }
+    }
+
+    #[test]
+    fn malformed_keys_are_rejected() {
+        let err = alter_current_attributes(
+            false,
+            &all(),
+            current(),
+            attributes(&[("enum.key", "enum-2")]),
+        )
+        .unwrap_err();
+        assert_eq!(err, "wrong format key: enum.key");
+        let err = alter_current_attributes(
+            false,
+            &all(),
+            current(),
+            attributes(&[("+-enum.key", "enum-2")]),
+        )
+        .unwrap_err();
+        assert_eq!(err, "kv string format wrong. key: -enum.key");
+    }
 }
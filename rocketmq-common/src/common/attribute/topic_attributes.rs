@@ -1,10 +1,12 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use lazy_static::lazy_static;
 
 use crate::common::attribute::attribute_enum::EnumAttribute;
 use crate::common::attribute::topic_message_type::TopicMessageType;
 use crate::common::attribute::Attribute;
+use crate::common::attribute::AttributeTrait;
 use crate::hashset;
 
 lazy_static! {
@@ -32,20 +34,16 @@ lazy_static! {
         universe: hashset! {String::from("BatchCQ"), String::from("SimpleCQ")},
         default_value: String::from("SimpleCQ"),
     };
-    pub static ref ALL: HashMap<String, EnumAttribute> = {
-        let mut map = HashMap::<String, EnumAttribute>::new();
-        map.insert(
-            QUEUE_TYPE_ATTRIBUTE.get_name().to_string(),
-            QUEUE_TYPE_ATTRIBUTE.clone(),
-        );
-        map.insert(
-            CLEANUP_POLICY_ATTRIBUTE.get_name().to_string(),
-            CLEANUP_POLICY_ATTRIBUTE.clone(),
-        );
-        map.insert(
-            TOPIC_MESSAGE_TYPE_ATTRIBUTE.get_name().to_string(),
-            TOPIC_MESSAGE_TYPE_ATTRIBUTE.clone(),
-        );
-        map
+    /// Every attribute a topic config accepts, by name.
+    pub static ref ALL: HashMap<String, Arc<dyn AttributeTrait + Send + Sync>> = {
+        let attributes: [Arc<dyn AttributeTrait + Send + Sync>; 3] = [
+            Arc::new(QUEUE_TYPE_ATTRIBUTE.clone()),
+            Arc::new(CLEANUP_POLICY_ATTRIBUTE.clone()),
+            Arc::new(TOPIC_MESSAGE_TYPE_ATTRIBUTE.clone()),
+        ];
+        attributes
+            .into_iter()
+            .map(|attribute| (attribute.name(), attribute))
+            .collect()
     };
 }
@@ -14,9 +14,11 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::borrow::Borrow;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt::Display;
+use std::hash::Hash;
 use std::str::FromStr;
 
 use crate::common::message::MessageConst;
@@ -59,10 +61,13 @@ impl TopicMessageType {
         .collect()
     }
 
-    pub fn parse_from_message_property(message_property: &HashMap<String, String>) -> Self {
+    pub fn parse_from_message_property<K, V>(message_property: &HashMap<K, V>) -> Self
+    where
+        K: Borrow<str> + Eq + Hash,
+        V: AsRef<str>,
+    {
         let is_trans = message_property.get(MessageConst::PROPERTY_TRANSACTION_PREPARED);
-        let is_trans_value = "true";
-        if Some(&is_trans_value.to_string()) == is_trans {
+        if is_trans.is_some_and(|is_trans| is_trans.as_ref() == "true") {
             return Self::Transaction;
         } else if message_property.contains_key(MessageConst::PROPERTY_DELAY_TIME_LEVEL)
             || message_property.contains_key(MessageConst::PROPERTY_TIMER_DELIVER_MS)
@@ -156,7 +161,7 @@ mod tests {
 
     #[test]
     fn test_parse_from_message_property_normal() {
-        let message_property = HashMap::new();
+        let message_property = HashMap::<String, String>::new();
         assert_eq!(
             TopicMessageType::parse_from_message_property(&message_property),
             TopicMessageType::Normal
@@ -17,6 +17,9 @@
 
 use std::any::Any;
 use std::collections::HashMap;
+use std::env;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 
 use cheetah_string::CheetahString;
 use lazy_static::lazy_static;
@@ -24,10 +27,15 @@ use serde::Deserialize;
 use serde::Serialize;
 
 use crate::common::constant::PermName;
+use crate::common::message::message_enum::MessageRequestMode;
+use crate::common::metrics::metrics_exporter_type::MetricsExporterType;
 use crate::common::mix_all;
-use crate::common::mix_all::NAMESRV_ADDR_PROPERTY;
+use crate::common::mix_all::ROCKETMQ_HOME_ENV;
+use crate::common::mix_all::ROCKETMQ_HOME_PROPERTY;
 use crate::common::server::config::ServerConfig;
 use crate::common::topic::TopicValidator;
+use crate::utils::name_server_address_utils::NameServerAddressUtils;
+use crate::utils::network_util::NetworkUtil;
 
 const DEFAULT_CLUSTER_NAME: &str = "DefaultCluster";
 
@@ -41,7 +49,8 @@ lazy_static! {
         }
     };
     pub static ref NAMESRV_ADDR: Option<String> =
-        std::env::var(NAMESRV_ADDR_PROPERTY).map_or(Some("127.0.0.1:9876".to_string()), Some);
+        NameServerAddressUtils::get_name_server_addresses()
+            .or_else(|| Some("127.0.0.1:9876".to_string()));
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -100,6 +109,23 @@ impl BrokerIdentity {
             is_in_broker_container,
         }
     }
+
+    /// `{clusterName}_{brokerName}_{brokerId}`, or `BrokerContainer` for the container itself.
+    pub fn get_canonical_name(&self) -> String {
+        if self.is_broker_container {
+            "BrokerContainer".to_string()
+        } else {
+            format!(
+                "{}_{}_{}",
+                self.broker_cluster_name, self.broker_name, self.broker_id
+            )
+        }
+    }
+
+    /// Identifier used to tag log records emitted on behalf of this broker.
+    pub fn get_logger_identifier(&self) -> String {
+        format!("#{}#", self.get_canonical_name())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -135,6 +161,9 @@ pub struct BrokerConfig {
     pub start_accept_send_request_time_stamp: i64,
     pub auto_create_topic_enable: bool,
     pub enable_single_topic_register: bool,
+    /// Push the route change of a created or updated topic to the clients that use it, so they
+    /// refresh it right away instead of on their next name server poll.
+    pub enable_topic_route_notify: bool,
     pub broker_topic_enable: bool,
     pub cluster_topic_enable: bool,
     pub revive_queue_num: u32,
@@ -144,11 +173,22 @@ pub struct BrokerConfig {
     pub flush_consumer_offset_interval: u64,
     pub force_register: bool,
     pub register_name_server_period: u64,
+    pub sync_broker_member_group_period: u64,
     pub skip_pre_online: bool,
+    /// Keep a starting broker isolated until its store has caught up.
+    pub enable_broker_pre_online: bool,
+    pub broker_pre_online_max_dispatch_behind_bytes: i64,
     pub namesrv_addr: Option<CheetahString>,
+    /// File listing the name server addresses, watched for changes. Takes precedence over
+    /// `namesrv_addr`.
+    pub namesrv_addr_file: Option<CheetahString>,
     pub fetch_name_srv_addr_by_dns_lookup: bool,
+    /// Periodically fetch the name server addresses from the address server.
+    pub fetch_namesrv_addr_by_address_server: bool,
     pub lite_pull_message_enable: bool,
     pub auto_create_subscription_group: bool,
+    /// Create `%DLQ%` topics readable as well, by default they are write-only.
+    pub auto_create_dlq_readable: bool,
     pub channel_expired_timeout: u64,
     pub subscription_expired_timeout: u64,
     pub enable_property_filter: bool,
@@ -157,6 +197,10 @@ pub struct BrokerConfig {
     pub slave_read_enable: bool,
     pub commercial_base_count: i32,
     pub reject_pull_consumer_enable: bool,
+    /// Heartbeats from clients below this MQVersion ordinal are rejected, 0 accepts all.
+    pub min_client_version_for_heartbeat: i32,
+    /// Sends from clients below this MQVersion ordinal are rejected, 0 accepts all.
+    pub min_client_version_for_send: i32,
     pub consumer_offset_update_version_step: i64,
     pub enable_broadcast_offset_store: bool,
     pub transfer_msg_by_heap: bool,
@@ -165,14 +209,59 @@ pub struct BrokerConfig {
     pub max_error_rate_of_bloom_filter: i32,
     pub expect_consumer_num_use_filter: i32,
     pub bit_map_length_consume_queue_ext: i32,
+    /// Pre-calculates the SQL92 filter bit map of each dispatched message, it is only stored when
+    /// the consume queue extension is enabled.
+    pub enable_calc_filter_bit_map: bool,
+    /// How long the filter data of an unregistered consumer is kept, in milliseconds.
+    pub filter_data_clean_time_span: u64,
     pub validate_system_topic_when_update_topic: bool,
     pub enable_mixed_message_type: bool,
+    /// Rejects sends whose message type differs from the `message.type` attribute of the topic.
+    pub enable_topic_message_type_check: bool,
     pub auto_delete_unused_stats: bool,
     pub forward_timeout: u64,
     pub store_reply_message_enable: bool,
     pub lock_in_strict_mode: bool,
     pub transaction_timeout: u64,
     pub transaction_op_msg_max_size: i32,
+    pub metrics_exporter_type: MetricsExporterType,
+    pub metrics_grpc_exporter_target: CheetahString,
+    pub metrics_grpc_exporter_time_out_in_mills: u64,
+    pub metrics_grpc_exporter_interval_in_mills: u64,
+    pub metrics_prom_exporter_host: CheetahString,
+    pub metrics_prom_exporter_port: u16,
+    pub metrics_logging_exporter_interval_in_mills: u64,
+    /// Distinct topics labeled in broker metrics before further topics are folded together.
+    pub metrics_max_topics_tracked: usize,
+    /// OTLP/gRPC endpoint the request spans are exported to, not exported when empty.
+    pub trace_otlp_exporter_endpoint: CheetahString,
+    pub trace_otlp_exporter_time_out_in_mills: u64,
+    /// Compute the queue assignment of QUERY_ASSIGNMENT, else every queue is returned.
+    pub server_load_balancer_enable: bool,
+    /// Request mode of groups without an entry in messageRequestMode.json.
+    pub default_message_request_mode: MessageRequestMode,
+    /// Consumers following a POP consumer whose queues it shares, -1 shares every queue.
+    pub default_pop_share_queue_num: i32,
+    pub load_balance_poll_name_server_interval: u64,
+    /// Throttles consumer groups reading commit log data that is unlikely to be in page cache.
+    pub cold_ctr_strategy_enable: bool,
+    /// Cold bytes per second a group may read, unless configured otherwise for the group.
+    pub cold_max_pull_threshold_per_group: u64,
+    /// How long a throttled cold pull is held before it is answered.
+    pub cold_pull_suspend_millis: u64,
+    /// Disables consumption of groups falling further behind than
+    /// `consumer_fallbehind_threshold`, until an operator enables the group again.
+    pub disable_consume_if_consumer_read_slowly: bool,
+    /// Commit log bytes a consumer group may fall behind before it counts as reading slowly.
+    pub consumer_fallbehind_threshold: u64,
+    /// Comma separated message store plugins, the first one being the outermost wrapper.
+    pub message_store_plugin: CheetahString,
+    /// Checks the signature and permissions of every request against the plain ACL file.
+    pub acl_enable: bool,
+    /// The plain ACL file, `conf/plain_acl.yml` under the RocketMQ home by default.
+    pub acl_file_path: CheetahString,
+    /// Requests taking longer to process are written to the slow request log, 0 disables it.
+    pub slow_request_threshold_millis: u64,
 }
 
 impl Default for BrokerConfig {
@@ -217,6 +306,7 @@ impl Default for BrokerConfig {
             start_accept_send_request_time_stamp: 0,
             auto_create_topic_enable: true,
             enable_single_topic_register: true,
+            enable_topic_route_notify: false,
             broker_topic_enable: true,
             cluster_topic_enable: true,
             revive_queue_num: 8,
@@ -226,11 +316,17 @@ impl Default for BrokerConfig {
             flush_consumer_offset_interval: 1000 * 5,
             force_register: true,
             register_name_server_period: 1000 * 30,
+            sync_broker_member_group_period: 1000,
             skip_pre_online: false,
+            enable_broker_pre_online: false,
+            broker_pre_online_max_dispatch_behind_bytes: 1024 * 1024,
             namesrv_addr: NAMESRV_ADDR.clone().map(|addr| addr.into()),
+            namesrv_addr_file: None,
             fetch_name_srv_addr_by_dns_lookup: false,
+            fetch_namesrv_addr_by_address_server: false,
             lite_pull_message_enable: true,
             auto_create_subscription_group: true,
+            auto_create_dlq_readable: false,
             channel_expired_timeout: 1000 * 120,
             subscription_expired_timeout: 1000 * 60 * 10,
             enable_property_filter: false,
@@ -239,6 +335,8 @@ impl Default for BrokerConfig {
             slave_read_enable: false,
             commercial_base_count: 1,
             reject_pull_consumer_enable: false,
+            min_client_version_for_heartbeat: 0,
+            min_client_version_for_send: 0,
             consumer_offset_update_version_step: 500,
             enable_broadcast_offset_store: true,
             transfer_msg_by_heap: true,
@@ -247,14 +345,40 @@ impl Default for BrokerConfig {
             max_error_rate_of_bloom_filter: 20,
             expect_consumer_num_use_filter: 32,
             bit_map_length_consume_queue_ext: 64,
+            enable_calc_filter_bit_map: false,
+            filter_data_clean_time_span: 24 * 3600 * 1000,
             forward_timeout: 3 * 1000,
             validate_system_topic_when_update_topic: true,
             enable_mixed_message_type: false,
+            enable_topic_message_type_check: false,
             auto_delete_unused_stats: false,
             store_reply_message_enable: true,
             lock_in_strict_mode: false,
             transaction_timeout: 6_000,
             transaction_op_msg_max_size: 4096,
+            metrics_exporter_type: MetricsExporterType::Disable,
+            metrics_grpc_exporter_target: CheetahString::empty(),
+            metrics_grpc_exporter_time_out_in_mills: 3 * 1000,
+            metrics_grpc_exporter_interval_in_mills: 60 * 1000,
+            metrics_prom_exporter_host: CheetahString::empty(),
+            metrics_prom_exporter_port: 5557,
+            metrics_logging_exporter_interval_in_mills: 10 * 1000,
+            metrics_max_topics_tracked: 1000,
+            trace_otlp_exporter_endpoint: CheetahString::empty(),
+            trace_otlp_exporter_time_out_in_mills: 3 * 1000,
+            server_load_balancer_enable: true,
+            default_message_request_mode: MessageRequestMode::Pull,
+            default_pop_share_queue_num: -1,
+            load_balance_poll_name_server_interval: 30 * 1000,
+            cold_ctr_strategy_enable: false,
+            cold_max_pull_threshold_per_group: 3 * 1024 * 1024,
+            cold_pull_suspend_millis: 1000,
+            disable_consume_if_consumer_read_slowly: false,
+            consumer_fallbehind_threshold: 1024 * 1024 * 1024 * 16,
+            message_store_plugin: CheetahString::empty(),
+            acl_enable: false,
+            acl_file_path: default_acl_file_path().into(),
+            slow_request_threshold_millis: 1000,
         }
     }
 }
@@ -296,6 +420,11 @@ impl BrokerConfig {
         format!("{}:{}", self.broker_ip1, self.listen_port)
     }
 
+    /// The address stamped on stored messages; an IPv6 `broker_ip1` yields an IPv6 store host.
+    pub fn get_store_host(&self) -> Option<SocketAddr> {
+        NetworkUtil::string_to_socket_address(self.broker_ip1.as_str(), self.listen_port as u16)
+    }
+
     pub fn get_start_accept_send_request_time_stamp(&self) -> i64 {
         self.start_accept_send_request_time_stamp
     }
@@ -410,6 +539,10 @@ impl BrokerConfig {
             "enableSingleTopicRegister".into(),
             self.enable_single_topic_register.to_string().into(),
         );
+        properties.insert(
+            "enableTopicRouteNotify".into(),
+            self.enable_topic_route_notify.to_string().into(),
+        );
         properties.insert(
             "brokerTopicEnable".into(),
             self.broker_topic_enable.to_string().into(),
@@ -446,18 +579,40 @@ impl BrokerConfig {
             "registerNameServerPeriod".into(),
             self.register_name_server_period.to_string().into(),
         );
+        properties.insert(
+            "syncBrokerMemberGroupPeriod".into(),
+            self.sync_broker_member_group_period.to_string().into(),
+        );
         properties.insert(
             "skipPreOnline".into(),
             self.skip_pre_online.to_string().into(),
         );
+        properties.insert(
+            "enableBrokerPreOnline".into(),
+            self.enable_broker_pre_online.to_string().into(),
+        );
+        properties.insert(
+            "brokerPreOnlineMaxDispatchBehindBytes".into(),
+            self.broker_pre_online_max_dispatch_behind_bytes
+                .to_string()
+                .into(),
+        );
         properties.insert(
             "namesrvAddr".into(),
             self.namesrv_addr.clone().unwrap_or_default(),
         );
+        properties.insert(
+            "namesrvAddrFile".into(),
+            self.namesrv_addr_file.clone().unwrap_or_default(),
+        );
         properties.insert(
             "fetchNameSrvAddrByDnsLookup".into(),
             self.fetch_name_srv_addr_by_dns_lookup.to_string().into(),
         );
+        properties.insert(
+            "fetchNamesrvAddrByAddressServer".into(),
+            self.fetch_namesrv_addr_by_address_server.to_string().into(),
+        );
         properties.insert(
             "litePullMessageEnable".into(),
             self.lite_pull_message_enable.to_string().into(),
@@ -466,6 +621,10 @@ impl BrokerConfig {
             "autoCreateSubscriptionGroup".into(),
             self.auto_create_subscription_group.to_string().into(),
         );
+        properties.insert(
+            "autoCreateDlqReadable".into(),
+            self.auto_create_dlq_readable.to_string().into(),
+        );
         properties.insert(
             "channelExpiredTimeout".into(),
             self.channel_expired_timeout.to_string().into(),
@@ -498,6 +657,14 @@ impl BrokerConfig {
             "rejectPullConsumerEnable".into(),
             self.reject_pull_consumer_enable.to_string().into(),
         );
+        properties.insert(
+            "minClientVersionForHeartbeat".into(),
+            self.min_client_version_for_heartbeat.to_string().into(),
+        );
+        properties.insert(
+            "minClientVersionForSend".into(),
+            self.min_client_version_for_send.to_string().into(),
+        );
         properties.insert(
             "consumerOffsetUpdateVersionStep".into(),
             self.consumer_offset_update_version_step.to_string().into(),
@@ -530,6 +697,14 @@ impl BrokerConfig {
             "bitMapLengthConsumeQueueExt".into(),
             self.bit_map_length_consume_queue_ext.to_string().into(),
         );
+        properties.insert(
+            "enableCalcFilterBitMap".into(),
+            self.enable_calc_filter_bit_map.to_string().into(),
+        );
+        properties.insert(
+            "filterDataCleanTimeSpan".into(),
+            self.filter_data_clean_time_span.to_string().into(),
+        );
         properties.insert(
             "validateSystemTopicWhenUpdateTopic".into(),
             self.validate_system_topic_when_update_topic
@@ -540,6 +715,10 @@ impl BrokerConfig {
             "enableMixedMessageType".into(),
             self.enable_mixed_message_type.to_string().into(),
         );
+        properties.insert(
+            "enableTopicMessageTypeCheck".into(),
+            self.enable_topic_message_type_check.to_string().into(),
+        );
         properties.insert(
             "autoDeleteUnusedStats".into(),
             self.auto_delete_unused_stats.to_string().into(),
@@ -548,10 +727,118 @@ impl BrokerConfig {
             "forwardTimeout".into(),
             self.forward_timeout.to_string().into(),
         );
+        properties.insert(
+            "metricsExporterType".into(),
+            self.metrics_exporter_type.get_name().into(),
+        );
+        properties.insert(
+            "metricsGrpcExporterTarget".into(),
+            self.metrics_grpc_exporter_target.clone(),
+        );
+        properties.insert(
+            "metricsGrpcExporterTimeOutInMills".into(),
+            self.metrics_grpc_exporter_time_out_in_mills
+                .to_string()
+                .into(),
+        );
+        properties.insert(
+            "metricsGrpcExporterIntervalInMills".into(),
+            self.metrics_grpc_exporter_interval_in_mills
+                .to_string()
+                .into(),
+        );
+        properties.insert(
+            "metricsPromExporterHost".into(),
+            self.metrics_prom_exporter_host.clone(),
+        );
+        properties.insert(
+            "metricsPromExporterPort".into(),
+            self.metrics_prom_exporter_port.to_string().into(),
+        );
+        properties.insert(
+            "metricsLoggingExporterIntervalInMills".into(),
+            self.metrics_logging_exporter_interval_in_mills
+                .to_string()
+                .into(),
+        );
+        properties.insert(
+            "metricsMaxTopicsTracked".into(),
+            self.metrics_max_topics_tracked.to_string().into(),
+        );
+        properties.insert(
+            "traceOtlpExporterEndpoint".into(),
+            self.trace_otlp_exporter_endpoint.clone(),
+        );
+        properties.insert(
+            "traceOtlpExporterTimeOutInMills".into(),
+            self.trace_otlp_exporter_time_out_in_mills
+                .to_string()
+                .into(),
+        );
+        properties.insert(
+            "serverLoadBalancerEnable".into(),
+            self.server_load_balancer_enable.to_string().into(),
+        );
+        properties.insert(
+            "defaultMessageRequestMode".into(),
+            self.default_message_request_mode.get_name().into(),
+        );
+        properties.insert(
+            "defaultPopShareQueueNum".into(),
+            self.default_pop_share_queue_num.to_string().into(),
+        );
+        properties.insert(
+            "loadBalancePollNameServerInterval".into(),
+            self.load_balance_poll_name_server_interval
+                .to_string()
+                .into(),
+        );
+        properties.insert(
+            "coldCtrStrategyEnable".into(),
+            self.cold_ctr_strategy_enable.to_string().into(),
+        );
+        properties.insert(
+            "coldMaxPullThresholdPerGroup".into(),
+            self.cold_max_pull_threshold_per_group.to_string().into(),
+        );
+        properties.insert(
+            "coldPullSuspendMillis".into(),
+            self.cold_pull_suspend_millis.to_string().into(),
+        );
+        properties.insert(
+            "disableConsumeIfConsumerReadSlowly".into(),
+            self.disable_consume_if_consumer_read_slowly
+                .to_string()
+                .into(),
+        );
+        properties.insert(
+            "consumerFallbehindThreshold".into(),
+            self.consumer_fallbehind_threshold.to_string().into(),
+        );
+        properties.insert(
+            "messageStorePlugIn".into(),
+            self.message_store_plugin.clone(),
+        );
+        properties.insert("aclEnable".into(), self.acl_enable.to_string().into());
+        properties.insert("aclFilePath".into(), self.acl_file_path.clone());
+        properties.insert(
+            "slowRequestThresholdMillis".into(),
+            self.slow_request_threshold_millis.to_string().into(),
+        );
         properties
     }
 }
 
+fn default_acl_file_path() -> String {
+    let rocketmq_home = env::var(ROCKETMQ_HOME_PROPERTY)
+        .unwrap_or_else(|_| env::var(ROCKETMQ_HOME_ENV).unwrap_or_default());
+    PathBuf::from(rocketmq_home)
+        .join("conf")
+        .join("plain_acl.yml")
+        .to_string_lossy()
+        .into_owned()
+}
+
 pub fn default_broker_name() -> String {
     LOCAL_HOST_NAME
         .clone()
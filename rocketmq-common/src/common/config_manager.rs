@@ -17,6 +17,7 @@
 
 use std::collections::HashMap;
 
+use serde::de::IgnoredAny;
 use tracing::error;
 use tracing::info;
 use tracing::warn;
@@ -27,54 +28,71 @@ use crate::FileUtils;
 pub trait ConfigManager {
     /// Loads the configuration from a file.
     ///
-    /// This method attempts to load the configuration from a file whose path is returned by
-    /// `config_file_path`. If the file content is empty, it attempts to load from a backup
-    /// file. If the file content is not empty, it decodes the content and logs a success
-    /// message.
+    /// This method loads the configuration from the file whose path is returned by
+    /// `config_file_path`. When that file is missing, empty or not valid JSON, e.g. truncated
+    /// by a crash, the backup written by the previous `persist` is loaded instead. A temp file
+    /// left behind by an interrupted `persist` is never read.
     ///
     /// # Returns
-    /// * `true` if the configuration is successfully loaded and decoded.
-    /// * `false` if the configuration loading fails.
+    /// * `true` if a copy of the configuration is loaded, or there is none to load.
+    /// * `false` if the configuration file is corrupt and no backup can replace it.
     fn load(&self) -> bool {
         let file_name = self.config_file_path();
-        let result = FileUtils::file_to_string(file_name.as_str());
-        match result {
-            Ok(ref content) => {
-                if content.is_empty() {
-                    warn!("load bak config file");
-                    self.load_bak()
-                } else {
-                    self.decode(content);
-                    info!("load Config file: {} -----OK", file_name);
-                    true
+        match read_config_file(file_name.as_str()) {
+            Ok(Some(content)) => {
+                self.decode(content.as_str());
+                info!("load Config file: {} -----OK", file_name);
+                true
+            }
+            Ok(None) => {
+                warn!("Config file {} is empty, load bak config file", file_name);
+                self.load_bak()
+            }
+            Err(e) => {
+                warn!(
+                    "Config file {} is corrupt, load bak config file: {}",
+                    file_name, e
+                );
+                let bak_file_name = format!("{}.bak", file_name);
+                match read_config_file(bak_file_name.as_str()) {
+                    Ok(Some(content)) => {
+                        self.decode(content.as_str());
+                        info!("load Config file: {} -----OK", bak_file_name);
+                        true
+                    }
+                    _ => {
+                        error!(
+                            "load Config file: {} -----Failed, no usable {}",
+                            file_name, bak_file_name
+                        );
+                        false
+                    }
                 }
             }
-            Err(_) => self.load_bak(),
         }
     }
 
     /// Loads the configuration from a backup file.
     ///
-    /// This method attempts to load the configuration from a backup file whose path is returned by
-    /// `config_file_path` with ".bak" appended. If the file content is not empty, it decodes
-    /// the content and logs a success message.
+    /// This method loads the configuration from the file whose path is returned by
+    /// `config_file_path` with ".bak" appended.
     ///
     /// # Returns
-    /// * `true` if the configuration is successfully loaded and decoded.
-    /// * `false` if the configuration loading fails.
+    /// * `true` if the backup is loaded, or there is no backup.
+    /// * `false` if the backup can't be read or is not valid JSON.
     fn load_bak(&self) -> bool {
-        let file_name = self.config_file_path();
-        if let Ok(ref content) =
-            FileUtils::file_to_string(format!("{}{}", file_name, ".bak").as_str())
-        {
-            if !content.is_empty() {
-                self.decode(content);
-                info!("load Config file: {}.bak -----OK", file_name);
+        let file_name = format!("{}.bak", self.config_file_path());
+        match read_config_file(file_name.as_str()) {
+            Ok(Some(content)) => {
+                self.decode(content.as_str());
+                info!("load Config file: {} -----OK", file_name);
+                true
+            }
+            Ok(None) => true,
+            Err(e) => {
+                error!("load Config file: {} -----Failed: {}", file_name, e);
+                false
             }
-            true
-        } else {
-            error!("load Config file: {}.bak -----Failed", file_name);
-            false
         }
     }
 
@@ -97,8 +115,9 @@ pub trait ConfigManager {
     /// Persists the configuration.
     ///
     /// This method persists the configuration to a file whose path is returned by
-    /// `config_file_path`. If the encoded configuration is not empty, it writes the
-    /// configuration to the file.
+    /// `config_file_path`. If the encoded configuration is not empty, it replaces the file
+    /// atomically and keeps its previous content as a ".bak" file, see
+    /// [`FileUtils::string_to_file`].
     fn persist(&self) {
         let json = self.encode_pretty(true);
         if !json.is_empty() {
@@ -170,3 +189,123 @@ pub trait ConfigManager {
     /// * `json_string` - A `&str` representing the configuration in JSON format.
     fn decode(&self, json_string: &str);
 }
+
+/// Reads a JSON config file, `None` when it is missing or empty.
+fn read_config_file(file_name: &str) -> Result<Option<String>, String> {
+    let content = FileUtils::file_to_string(file_name).map_err(|e| e.to_string())?;
+    if content.trim().is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_str::<IgnoredAny>(content.as_str()).map_err(|e| e.to_string())?;
+    Ok(Some(content))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use parking_lot::Mutex;
+
+    use super::*;
+
+    struct OffsetConfig {
+        path: String,
+        offsets: Mutex<HashMap<String, i64>>,
+    }
+
+    impl OffsetConfig {
+        fn new(dir: &Path) -> Self {
+            Self {
+                path: dir.join("offsets.json").to_string_lossy().into_owned(),
+                offsets: Mutex::new(HashMap::new()),
+            }
+        }
+
+        fn set(&self, key: &str, offset: i64) {
+            self.offsets.lock().insert(key.to_string(), offset);
+        }
+
+        fn get(&self, key: &str) -> Option<i64> {
+            self.offsets.lock().get(key).copied()
+        }
+    }
+
+    impl ConfigManager for OffsetConfig {
+        fn config_file_path(&self) -> String {
+            self.path.clone()
+        }
+
+        fn encode_pretty(&self, pretty_format: bool) -> String {
+            let offsets = self.offsets.lock();
+            if pretty_format {
+                serde_json::to_string_pretty(&*offsets).unwrap()
+            } else {
+                serde_json::to_string(&*offsets).unwrap()
+            }
+        }
+
+        fn decode(&self, json_string: &str) {
+            let offsets: HashMap<String, i64> = serde_json::from_str(json_string).unwrap();
+            *self.offsets.lock() = offsets;
+        }
+    }
+
+    #[test]
+    fn corrupt_config_file_is_recovered_from_bak() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = OffsetConfig::new(dir.path());
+        config.set("group@topic", 10);
+        config.persist();
+        config.set("group@topic", 20);
+        config.persist();
+
+        // truncated in the middle of the JSON document
+        let content = std::fs::read_to_string(&config.path).unwrap();
+        std::fs::write(&config.path, &content[..content.len() / 2]).unwrap();
+
+        let reloaded = OffsetConfig::new(dir.path());
+        assert!(reloaded.load());
+        assert_eq!(reloaded.get("group@topic"), Some(10));
+    }
+
+    #[test]
+    fn temp_file_of_an_interrupted_persist_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = OffsetConfig::new(dir.path());
+        config.set("group@topic", 10);
+        config.persist();
+
+        // the broker died while writing the next version
+        let tmp_file = format!("{}.tmp", config.path);
+        std::fs::write(&tmp_file, "{\"group@topic\": 2").unwrap();
+
+        let reloaded = OffsetConfig::new(dir.path());
+        assert!(reloaded.load());
+        assert_eq!(reloaded.get("group@topic"), Some(10));
+
+        reloaded.set("group@topic", 30);
+        reloaded.persist();
+        assert!(!Path::new(&tmp_file).exists());
+        let reloaded = OffsetConfig::new(dir.path());
+        assert!(reloaded.load());
+        assert_eq!(reloaded.get("group@topic"), Some(30));
+    }
+
+    #[test]
+    fn corrupt_config_file_without_bak_fails_to_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = OffsetConfig::new(dir.path());
+        std::fs::write(&config.path, "{\"group@topic\": ").unwrap();
+
+        assert!(!config.load());
+    }
+
+    #[test]
+    fn missing_config_file_loads_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = OffsetConfig::new(dir.path());
+
+        assert!(config.load());
+        assert_eq!(config.get("group@topic"), None);
+    }
+}
@@ -39,6 +39,7 @@ pub mod message_id;
 pub mod message_queue;
 pub mod message_queue_assignment;
 pub mod message_single;
+pub mod message_validator;
 
 /// This module defines the `MessageTrait` trait, which provides a flexible interface for working
 ///
@@ -277,4 +277,149 @@ impl MessageAccessor {
             MessageConst::PROPERTY_CONSUME_START_TIMESTAMP,
         ))
     }
+
+    /// Sets the retry topic of a message.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - A mutable reference to a message implementing the `MessageTrait`.
+    /// * `retry_topic` - The retry topic value.
+    #[inline]
+    pub fn set_retry_topic<T: MessageTrait>(msg: &mut T, retry_topic: CheetahString) {
+        msg.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_RETRY_TOPIC),
+            retry_topic,
+        );
+    }
+
+    /// Gets the retry topic of a message.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - A reference to a message implementing the `MessageTrait`.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<CheetahString>` - The retry topic value if it exists.
+    #[inline]
+    pub fn get_retry_topic<T: MessageTrait>(msg: &T) -> Option<CheetahString> {
+        msg.get_property(&CheetahString::from_static_str(
+            MessageConst::PROPERTY_RETRY_TOPIC,
+        ))
+    }
+
+    /// Sets the unique client message id of a message.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - A mutable reference to a message implementing the `MessageTrait`.
+    /// * `uniq_key` - The unique client message id value.
+    #[inline]
+    pub fn set_uniq_key<T: MessageTrait>(msg: &mut T, uniq_key: CheetahString) {
+        msg.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX),
+            uniq_key,
+        );
+    }
+
+    /// Gets the unique client message id of a message.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - A reference to a message implementing the `MessageTrait`.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<CheetahString>` - The unique client message id value if it exists.
+    #[inline]
+    pub fn get_uniq_key<T: MessageTrait>(msg: &T) -> Option<CheetahString> {
+        msg.get_property(&CheetahString::from_static_str(
+            MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX,
+        ))
+    }
+
+    /// Sets the real topic of a message.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - A mutable reference to a message implementing the `MessageTrait`.
+    /// * `real_topic` - The real topic value.
+    #[inline]
+    pub fn set_real_topic<T: MessageTrait>(msg: &mut T, real_topic: CheetahString) {
+        msg.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_REAL_TOPIC),
+            real_topic,
+        );
+    }
+
+    /// Gets the real topic of a message.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - A reference to a message implementing the `MessageTrait`.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<CheetahString>` - The real topic value if it exists.
+    #[inline]
+    pub fn get_real_topic<T: MessageTrait>(msg: &T) -> Option<CheetahString> {
+        msg.get_property(&CheetahString::from_static_str(
+            MessageConst::PROPERTY_REAL_TOPIC,
+        ))
+    }
+
+    /// Sets the real queue id of a message.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - A mutable reference to a message implementing the `MessageTrait`.
+    /// * `real_queue_id` - The real queue id value.
+    #[inline]
+    pub fn set_real_queue_id<T: MessageTrait>(msg: &mut T, real_queue_id: CheetahString) {
+        msg.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_REAL_QUEUE_ID),
+            real_queue_id,
+        );
+    }
+
+    /// Gets the real queue id of a message.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - A reference to a message implementing the `MessageTrait`.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<CheetahString>` - The real queue id value if it exists.
+    #[inline]
+    pub fn get_real_queue_id<T: MessageTrait>(msg: &T) -> Option<CheetahString> {
+        msg.get_property(&CheetahString::from_static_str(
+            MessageConst::PROPERTY_REAL_QUEUE_ID,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn well_known_property_accessors() {
+        let mut msg = Message::new("TopicTest", b"body");
+        MessageAccessor::set_retry_topic(&mut msg, CheetahString::from_static_str("TopicTest"));
+        MessageAccessor::set_uniq_key(&mut msg, CheetahString::from_static_str("UNIQ"));
+        MessageAccessor::set_real_topic(&mut msg, CheetahString::from_static_str("RealTopic"));
+        MessageAccessor::set_real_queue_id(&mut msg, CheetahString::from_static_str("3"));
+        assert_eq!(MessageAccessor::get_retry_topic(&msg).unwrap(), "TopicTest");
+        assert_eq!(MessageAccessor::get_uniq_key(&msg).unwrap(), "UNIQ");
+        assert_eq!(
+            msg.get_property(&CheetahString::from_static_str("UNIQ_KEY"))
+                .unwrap(),
+            "UNIQ"
+        );
+        assert_eq!(MessageAccessor::get_real_topic(&msg).unwrap(), "RealTopic");
+        assert_eq!(MessageAccessor::get_real_queue_id(&msg).unwrap(), "3");
+        MessageAccessor::clear_property(&mut msg, MessageConst::PROPERTY_RETRY_TOPIC);
+        assert!(MessageAccessor::get_retry_topic(&msg).is_none());
+    }
 }
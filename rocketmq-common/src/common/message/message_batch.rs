@@ -20,7 +20,9 @@ use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::iter::Peekable;
 use std::path::Iter;
+use std::vec::IntoIter;
 
 use bytes::Bytes;
 use cheetah_string::CheetahString;
@@ -114,6 +116,45 @@ impl MessageBatch {
     }
 }
 
+/// Splits a list of messages into consecutive chunks whose batch encoding stays within
+/// `size_limit` bytes, keeping the original order. A message that exceeds the limit on its own
+/// makes up a chunk by itself.
+pub struct ListSplitter {
+    messages: Peekable<IntoIter<Message>>,
+    size_limit: usize,
+}
+
+impl ListSplitter {
+    pub fn new(messages: Vec<Message>, size_limit: usize) -> Self {
+        ListSplitter {
+            messages: messages.into_iter().peekable(),
+            size_limit,
+        }
+    }
+}
+
+impl Iterator for ListSplitter {
+    type Item = Vec<Message>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk = Vec::new();
+        let mut chunk_size = 0;
+        while let Some(message) = self.messages.peek() {
+            let size = message_decoder::encoded_message_size(message);
+            if !chunk.is_empty() && chunk_size + size > self.size_limit {
+                break;
+            }
+            chunk_size += size;
+            chunk.extend(self.messages.next());
+        }
+        if chunk.is_empty() {
+            None
+        } else {
+            Some(chunk)
+        }
+    }
+}
+
 impl fmt::Display for MessageBatch {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let messages_str = match &self.messages {
@@ -224,3 +265,71 @@ impl MessageExtBatch {
         self.message_ext_broker_inner.get_tags()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE_LIMIT: usize = 4 * 1024 * 1024;
+
+    fn varied_messages(total_body_size: usize) -> Vec<Message> {
+        let sizes = [1, 700, 16 * 1024, 333 * 1024, 1024 * 1024 + 7, 64];
+        let mut messages = Vec::new();
+        let mut total = 0;
+        while total < total_body_size {
+            let size = sizes[messages.len() % sizes.len()];
+            let mut message = Message::new("BatchTopic", &vec![messages.len() as u8; size]);
+            message.set_keys(CheetahString::from_string(format!(
+                "key-{}",
+                messages.len()
+            )));
+            messages.push(message);
+            total += size;
+        }
+        messages
+    }
+
+    #[test]
+    fn split_keeps_chunks_within_the_limit_and_as_full_as_possible() {
+        let messages = varied_messages(10 * 1024 * 1024);
+        let chunks = ListSplitter::new(messages.clone(), SIZE_LIMIT).collect::<Vec<_>>();
+
+        assert!(chunks.len() >= 3);
+        for (index, chunk) in chunks.iter().enumerate() {
+            let encoded = message_decoder::encode_messages(chunk).len();
+            assert!(
+                encoded <= SIZE_LIMIT,
+                "chunk {index} encodes to {encoded} bytes"
+            );
+            let encoded_size: usize = chunk
+                .iter()
+                .map(message_decoder::encoded_message_size)
+                .sum();
+            assert_eq!(encoded_size, encoded);
+            if let Some(next) = chunks.get(index + 1) {
+                assert!(encoded + message_decoder::encoded_message_size(&next[0]) > SIZE_LIMIT);
+            }
+        }
+        let rejoined = chunks.into_iter().flatten().collect::<Vec<_>>();
+        assert_eq!(rejoined.len(), messages.len());
+        for (split, original) in rejoined.iter().zip(&messages) {
+            assert_eq!(split.get_keys(), original.get_keys());
+            assert_eq!(split.get_body(), original.get_body());
+        }
+    }
+
+    #[test]
+    fn oversized_message_is_split_into_a_chunk_of_its_own() {
+        let messages = vec![
+            Message::new("BatchTopic", &[1; 10]),
+            Message::new("BatchTopic", &[2; 200]),
+            Message::new("BatchTopic", &[3; 10]),
+        ];
+        let chunks = ListSplitter::new(messages, 100)
+            .map(|chunk| chunk.len())
+            .collect::<Vec<_>>();
+
+        assert_eq!(chunks, vec![1, 1, 1]);
+        assert!(ListSplitter::new(Vec::new(), 100).next().is_none());
+    }
+}
@@ -14,17 +14,20 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::net::IpAddr;
 use std::sync::atomic::AtomicI32;
 use std::sync::atomic::Ordering;
 
 use bytes::BufMut;
 use bytes::BytesMut;
 use cheetah_string::CheetahString;
+use chrono::DateTime;
 use chrono::Datelike;
+use chrono::Local;
 use chrono::Months;
+use chrono::NaiveTime;
+use chrono::Offset;
 use chrono::TimeZone;
-use chrono::Timelike;
-use chrono::Utc;
 use lazy_static::lazy_static;
 use parking_lot::Mutex;
 
@@ -35,6 +38,7 @@ use crate::common::message::MessageTrait;
 use crate::utils::util_all;
 use crate::TimeUtils::get_current_millis;
 use crate::UtilAll::bytes_to_string;
+use crate::UtilAll::string_to_bytes;
 use crate::UtilAll::write_int;
 use crate::UtilAll::write_short;
 
@@ -78,18 +82,15 @@ impl MessageClientIDSetter {
         ))
     }
 
+    /// Counts the time part of new ids from the start of the month of `millis`, in the local
+    /// time zone like the Java client, until the next month starts.
     fn set_start_time(millis: i64) {
-        let dt = Utc.timestamp_millis_opt(millis).unwrap();
-        let cal = Utc
-            .with_ymd_and_hms(dt.year(), dt.month(), 1, 0, 0, 0)
-            .unwrap();
-
-        *START_TIME.lock() = cal.timestamp_millis();
-
-        *NEXT_START_TIME.lock() = cal
-            .checked_add_months(Months::new(1))
-            .unwrap()
-            .timestamp_millis();
+        let now = Local
+            .timestamp_millis_opt(millis)
+            .single()
+            .unwrap_or_else(Local::now);
+        *START_TIME.lock() = month_start(&now, 0);
+        *NEXT_START_TIME.lock() = month_start(&now, 1);
     }
 
     pub fn create_uniq_id() -> String {
@@ -134,6 +135,85 @@ impl MessageClientIDSetter {
             );
         }
     }
+
+    /// The ip of the client that made `msg_id`, 4 bytes for ipv4 and 16 for ipv6.
+    pub fn get_ip_from_id(msg_id: &str) -> Option<Vec<u8>> {
+        let bytes = id_to_bytes(msg_id)?;
+        Some(bytes[..ip_length(&bytes)].to_vec())
+    }
+
+    pub fn get_ip_str_from_id(msg_id: &str) -> Option<String> {
+        let ip = Self::get_ip_from_id(msg_id)?;
+        let ip = match <[u8; 16]>::try_from(ip.as_slice()) {
+            Ok(v6) => IpAddr::from(v6),
+            Err(_) => IpAddr::from(<[u8; 4]>::try_from(ip.as_slice()).ok()?),
+        };
+        Some(ip.to_string())
+    }
+
+    /// The process id of the client that made `msg_id`, truncated to 16 bits.
+    pub fn get_pid_from_id(msg_id: &str) -> Option<i32> {
+        let bytes = id_to_bytes(msg_id)?;
+        let pos = ip_length(&bytes);
+        Some(u16::from_be_bytes([bytes[pos], bytes[pos + 1]]) as i32)
+    }
+
+    /// When `msg_id` was made, in millis. Ids only carry the time since the start of their
+    /// month, so the month is taken to be the current one, or the previous one if that time
+    /// is still to come this month.
+    pub fn get_nearly_time_from_id(msg_id: &str) -> Option<i64> {
+        nearly_time_from_id_at(msg_id, &Local::now())
+    }
+}
+
+/// The first instant of the month of `at` moved by `months`, in millis and in `at`'s zone.
+fn month_start<Tz: TimeZone>(at: &DateTime<Tz>, months: i32) -> i64 {
+    let first = at
+        .date_naive()
+        .with_day(1)
+        .unwrap_or_else(|| at.date_naive());
+    let first = if months >= 0 {
+        first.checked_add_months(Months::new(months as u32))
+    } else {
+        first.checked_sub_months(Months::new(months.unsigned_abs()))
+    }
+    .unwrap_or(first)
+    .and_time(NaiveTime::MIN);
+    match at.timezone().from_local_datetime(&first).earliest() {
+        Some(start) => start.timestamp_millis(),
+        // midnight skipped by a daylight saving change
+        None => {
+            first.and_utc().timestamp_millis() - at.offset().fix().local_minus_utc() as i64 * 1000
+        }
+    }
+}
+
+fn nearly_time_from_id_at<Tz: TimeZone>(msg_id: &str, now: &DateTime<Tz>) -> Option<i64> {
+    let bytes = id_to_bytes(msg_id)?;
+    let pos = ip_length(&bytes) + 2 + 4;
+    let span = u32::from_be_bytes(bytes[pos..pos + 4].try_into().ok()?) as i64;
+    let mut start = month_start(now, 0);
+    // unlike the Java client a key made in the current millisecond still belongs to this month
+    if start + span > now.timestamp_millis() {
+        start = month_start(now, -1);
+    }
+    Some(start + span)
+}
+
+/// The bytes of a unique key, which is 16 bytes long with an ipv4 address and 28 with ipv6.
+fn id_to_bytes(msg_id: &str) -> Option<Vec<u8>> {
+    if msg_id.len() % 2 != 0 || !msg_id.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    string_to_bytes(msg_id).filter(|bytes| bytes.len() == 16 || bytes.len() == 28)
+}
+
+fn ip_length(id_bytes: &[u8]) -> usize {
+    if id_bytes.len() == 28 {
+        16
+    } else {
+        4
+    }
 }
 
 #[cfg(test)]
@@ -189,4 +269,98 @@ mod tests {
         let locked_next_start_time = *NEXT_START_TIME.lock();
         assert!(locked_next_start_time > millis);
     }
+
+    // keys in the Java client layout: ip, pid, class loader hash, millis since the start of
+    // the month and counter
+    const IPV4_KEY: &str = "0A0C14686EC018B4AAC20A3A2F1001F4";
+    const HIGH_PID_KEY: &str = "C0A8016EFFF018B4AAC2000000000000";
+    const IPV6_KEY: &str = "FE8000000000000000000000000000010B1818B4AAC20A3A2F100000";
+
+    #[test]
+    fn java_keys_decode_to_ip_and_pid() {
+        assert_eq!(
+            MessageClientIDSetter::get_ip_from_id(IPV4_KEY),
+            Some(vec![10, 12, 20, 104])
+        );
+        assert_eq!(
+            MessageClientIDSetter::get_ip_str_from_id(IPV4_KEY).as_deref(),
+            Some("10.12.20.104")
+        );
+        assert_eq!(
+            MessageClientIDSetter::get_pid_from_id(IPV4_KEY),
+            Some(28352)
+        );
+        assert_eq!(
+            MessageClientIDSetter::get_pid_from_id(HIGH_PID_KEY),
+            Some(65520)
+        );
+
+        assert_eq!(
+            MessageClientIDSetter::get_ip_str_from_id(IPV6_KEY).as_deref(),
+            Some("fe80::1")
+        );
+        assert_eq!(MessageClientIDSetter::get_pid_from_id(IPV6_KEY), Some(2840));
+
+        assert_eq!(MessageClientIDSetter::get_ip_from_id("0A0C1468"), None);
+        assert_eq!(MessageClientIDSetter::get_pid_from_id(&IPV4_KEY[1..]), None);
+        assert_eq!(
+            MessageClientIDSetter::get_nearly_time_from_id("ZZ0C14686EC018B4AAC20A3A2F1001F4"),
+            None
+        );
+    }
+
+    #[test]
+    fn java_key_time_is_taken_from_this_month_or_the_previous_one() {
+        let at = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+
+        // 1 day 23:39:45.296 into the month
+        let time = nearly_time_from_id_at(IPV4_KEY, &at("2024-03-10T00:00:00Z"));
+        assert_eq!(
+            time,
+            Some(at("2024-03-02T23:39:45.296Z").timestamp_millis())
+        );
+
+        let time = nearly_time_from_id_at(IPV6_KEY, &at("2024-03-02T10:00:00Z"));
+        assert_eq!(
+            time,
+            Some(at("2024-02-02T23:39:45.296Z").timestamp_millis())
+        );
+    }
+
+    #[test]
+    fn generated_key_decodes_to_this_client() {
+        let before = get_current_millis() as i64;
+        let id = MessageClientIDSetter::create_uniq_id();
+        let after = get_current_millis() as i64;
+
+        let ip = util_all::get_ip().unwrap_or_else(|_| create_fake_ip());
+        assert_eq!(id.len(), *LEN * 2);
+        assert_eq!(MessageClientIDSetter::get_ip_from_id(&id), Some(ip));
+        assert_eq!(
+            MessageClientIDSetter::get_pid_from_id(&id),
+            Some((std::process::id() & 0xFFFF) as i32)
+        );
+        let time = MessageClientIDSetter::get_nearly_time_from_id(&id).unwrap();
+        assert!(before <= time && time <= after);
+    }
+
+    #[test]
+    fn stale_month_is_refreshed_before_the_next_key() {
+        let now = get_current_millis() as i64;
+        MessageClientIDSetter::set_start_time(now - 40 * 24 * 3600 * 1000);
+
+        let id = MessageClientIDSetter::create_uniq_id();
+        let time = MessageClientIDSetter::get_nearly_time_from_id(&id).unwrap();
+        assert!((time - now).abs() < 60_000);
+        assert!(*NEXT_START_TIME.lock() > now);
+    }
+
+    #[test]
+    fn month_start_rolls_over_the_year() {
+        let at = "2023-12-31T23:59:59Z".parse::<DateTime<Utc>>().unwrap();
+        let start = |s: &str| s.parse::<DateTime<Utc>>().unwrap().timestamp_millis();
+        assert_eq!(month_start(&at, 0), start("2023-12-01T00:00:00Z"));
+        assert_eq!(month_start(&at, 1), start("2024-01-01T00:00:00Z"));
+        assert_eq!(month_start(&at, -1), start("2023-11-01T00:00:00Z"));
+    }
 }
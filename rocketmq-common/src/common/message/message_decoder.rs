@@ -15,7 +15,6 @@
  * limitations under the License.
  */
 use std::collections::HashMap;
-use std::net::IpAddr;
 use std::net::Ipv4Addr;
 use std::net::Ipv6Addr;
 use std::net::SocketAddr;
@@ -35,10 +34,11 @@ use crate::common::message::message_client_ext::MessageClientExt;
 use crate::common::message::message_ext::MessageExt;
 use crate::common::message::message_id::MessageId;
 use crate::common::message::message_single::Message;
+use crate::common::message::MessageConst;
 use crate::common::message::MessageTrait;
 use crate::common::message::MessageVersion;
 use crate::common::sys_flag::message_sys_flag::MessageSysFlag;
-use crate::utils::util_all;
+use crate::utils::message_utils;
 use crate::CRC32Utils::crc32;
 use crate::MessageAccessor::MessageAccessor;
 use crate::MessageUtils::build_message_id;
@@ -137,6 +137,21 @@ pub fn message_properties_to_string(
     CheetahString::from_string(sb)
 }
 
+/// Writes the `PROPERTY_CRC32` property holding `crc32` at the start of `input`, which must have
+/// room for `PROPERTY_CRC32 + NAME_VALUE_SEPARATOR + 10 digits + PROPERTY_SEPARATOR`. The digits
+/// are written least significant first, as the Java broker does.
+pub fn create_crc32(input: &mut [u8], crc32: u32) {
+    let name = MessageConst::PROPERTY_CRC32.as_bytes();
+    input[..name.len()].copy_from_slice(name);
+    input[name.len()] = NAME_VALUE_SEPARATOR as u8;
+    let mut value = crc32;
+    for digit in &mut input[name.len() + 1..name.len() + 11] {
+        *digit = b'0' + (value % 10) as u8;
+        value /= 10;
+    }
+    input[name.len() + 11] = PROPERTY_SEPARATOR as u8;
+}
+
 pub fn decode_client(
     byte_buffer: &mut Bytes,
     read_body: bool,
@@ -223,29 +238,22 @@ pub fn decode(
     msg_ext.set_born_timestamp(born_time_stamp);
 
     // 10 BORNHOST
-    let (born_host_address, born_host_ip_length) =
-        if sys_flag & MessageSysFlag::BORNHOST_V6_FLAG != 0 {
-            let mut born_host = [0; 16];
-            byte_buffer.copy_to_slice(&mut born_host);
-            let port = byte_buffer.get_i32();
-            (
-                SocketAddr::V6(SocketAddrV6::new(
-                    Ipv6Addr::from(born_host),
-                    port as u16,
-                    0,
-                    0,
-                )),
-                16,
-            )
-        } else {
-            let mut born_host = [0; 4];
-            byte_buffer.copy_to_slice(&mut born_host);
-            let port = byte_buffer.get_i32();
-            (
-                SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::from(born_host), port as u16)),
-                4,
-            )
-        };
+    let born_host_address = if sys_flag & MessageSysFlag::BORNHOST_V6_FLAG != 0 {
+        let mut born_host = [0; 16];
+        byte_buffer.copy_to_slice(&mut born_host);
+        let port = byte_buffer.get_i32();
+        SocketAddr::V6(SocketAddrV6::new(
+            Ipv6Addr::from(born_host),
+            port as u16,
+            0,
+            0,
+        ))
+    } else {
+        let mut born_host = [0; 4];
+        byte_buffer.copy_to_slice(&mut born_host);
+        let port = byte_buffer.get_i32();
+        SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::from(born_host), port as u16))
+    };
     msg_ext.set_born_host(born_host_address);
 
     // 11 STORETIMESTAMP
@@ -253,29 +261,22 @@ pub fn decode(
     msg_ext.set_store_timestamp(store_timestamp);
 
     // 12 STOREHOST
-    let (store_host_address, store_host_ip_length) =
-        if sys_flag & MessageSysFlag::STOREHOSTADDRESS_V6_FLAG != 0 {
-            let mut store_host = [0; 16];
-            byte_buffer.copy_to_slice(&mut store_host);
-            let port = byte_buffer.get_i32();
-            (
-                SocketAddr::V6(SocketAddrV6::new(
-                    Ipv6Addr::from(store_host),
-                    port as u16,
-                    0,
-                    0,
-                )),
-                16,
-            )
-        } else {
-            let mut store_host = [0; 4];
-            byte_buffer.copy_to_slice(&mut store_host);
-            let port = byte_buffer.get_i32();
-            (
-                SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::from(store_host), port as u16)),
-                4,
-            )
-        };
+    let store_host_address = if sys_flag & MessageSysFlag::STOREHOSTADDRESS_V6_FLAG != 0 {
+        let mut store_host = [0; 16];
+        byte_buffer.copy_to_slice(&mut store_host);
+        let port = byte_buffer.get_i32();
+        SocketAddr::V6(SocketAddrV6::new(
+            Ipv6Addr::from(store_host),
+            port as u16,
+            0,
+            0,
+        ))
+    } else {
+        let mut store_host = [0; 4];
+        byte_buffer.copy_to_slice(&mut store_host);
+        let port = byte_buffer.get_i32();
+        SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::from(store_host), port as u16))
+    };
     msg_ext.set_store_host(store_host_address);
 
     // 13 RECONSUMETIMES
@@ -303,25 +304,12 @@ pub fn decode(
             if de_compress_body
                 && (sys_flag & MessageSysFlag::COMPRESSED_FLAG) == MessageSysFlag::COMPRESSED_FLAG
             {
-                let compression_type = CompressionType::find_by_value(
-                    (flag & MessageSysFlag::COMPRESSION_TYPE_COMPARATOR) >> 8,
-                );
+                let compression_type = MessageSysFlag::get_compression_type(sys_flag);
                 body_bytes = compression_type.decompression(&body_bytes)
             }
             msg_ext.message.body = Some(body_bytes);
         } else {
-            let _ = byte_buffer.split_to(
-                BORN_TIMESTAMP_POSITION
-                    + born_host_ip_length
-                    + 4
-                    + 8
-                    + store_host_ip_length
-                    + 4
-                    + 4
-                    + 8
-                    + 4
-                    + body_len as usize,
-            );
+            byte_buffer.advance(body_len as usize);
         }
     }
 
@@ -396,6 +384,18 @@ pub fn encode_messages(messages: &[Message]) -> Bytes {
     bytes.freeze()
 }
 
+/// Size of `message` once encoded by [`encode_message`], the unit a batch is measured in.
+pub fn encoded_message_size(message: &Message) -> usize {
+    let body_len = message.body.as_ref().map_or(0, |body| body.len());
+    let properties_length = message_properties_to_string(&message.properties).len();
+    4 // 1 TOTALSIZE
+        + 4 // 2 MAGICCOD
+        + 4 // 3 BODYCRC
+        + 4 // 4 FLAG
+        + 4 + body_len // 4 BODY
+        + 2 + properties_length
+}
+
 pub fn encode_message(message: &Message) -> Bytes {
     let body = message.body.as_ref().unwrap();
     let body_len = body.len();
@@ -537,24 +537,28 @@ pub fn decode_message(buffer: &mut Bytes) -> Message {
     }
 }
 
+/// Decodes an offset message id of either length; panics if `msg_id` is not one.
 pub fn decode_message_id(msg_id: &str) -> MessageId {
-    let bytes = util_all::string_to_bytes(msg_id).unwrap();
-    let mut buffer = Bytes::from(bytes);
-    let len = if msg_id.len() == 32 {
-        let mut ip = [0u8; 4];
-        buffer.copy_to_slice(&mut ip);
-        let port = buffer.get_i32();
-        SocketAddr::new(IpAddr::V4(Ipv4Addr::from(ip)), port as u16)
+    let (address, offset) = message_utils::parse_message_id(msg_id)
+        .unwrap_or_else(|| panic!("invalid offset message id: {}", msg_id));
+    MessageId { address, offset }
+}
+
+/// The message's sys flag with the host address flags matching the families of its born and
+/// store hosts, so the encoded addresses always agree with the flags describing them.
+fn sys_flag_with_host_families(message_ext: &MessageExt) -> i32 {
+    let mut sys_flag = message_ext.sys_flag;
+    if message_ext.born_host.is_ipv6() {
+        sys_flag |= MessageSysFlag::BORNHOST_V6_FLAG;
     } else {
-        let mut ip = [0u8; 16];
-        buffer.copy_to_slice(&mut ip);
-        let port = buffer.get_i32();
-        SocketAddr::new(IpAddr::V6(Ipv6Addr::from(ip)), port as u16)
-    };
-    MessageId {
-        address: len,
-        offset: buffer.get_i64(),
+        sys_flag &= !MessageSysFlag::BORNHOST_V6_FLAG;
+    }
+    if message_ext.store_host.is_ipv6() {
+        sys_flag |= MessageSysFlag::STOREHOSTADDRESS_V6_FLAG;
+    } else {
+        sys_flag &= !MessageSysFlag::STOREHOSTADDRESS_V6_FLAG;
     }
+    sys_flag
 }
 
 pub fn encode(message_ext: &MessageExt, need_compress: bool) -> Result<Bytes> {
@@ -564,7 +568,7 @@ pub fn encode(message_ext: &MessageExt, need_compress: bool) -> Result<Bytes> {
     let properties = message_properties_to_string(message_ext.get_properties());
     let properties_bytes = properties.as_bytes();
     let properties_length = properties_bytes.len();
-    let sys_flag = message_ext.sys_flag;
+    let sys_flag = sys_flag_with_host_families(message_ext);
     let born_host_length = if (sys_flag & MessageSysFlag::BORNHOST_V6_FLAG) == 0 {
         8
     } else {
@@ -586,11 +590,7 @@ pub fn encode(message_ext: &MessageExt, need_compress: bool) -> Result<Bytes> {
         None
     };
     let body_len = new_body.as_ref().map_or(body.len(), |b| b.len());
-    let store_size = message_ext.store_size;
-    let mut byte_buffer = if store_size > 0 {
-        BytesMut::with_capacity(store_size as usize)
-    } else {
-        let store_size = 4 // 1 TOTALSIZE
+    let store_size = 4 // 1 TOTALSIZE
             + 4 // 2 MAGICCODE
             + 4 // 3 BODYCRC
             + 4 // 4 QUEUEID
@@ -607,11 +607,10 @@ pub fn encode(message_ext: &MessageExt, need_compress: bool) -> Result<Bytes> {
             + 4 + body_len // 14 BODY
             + 1 + topic_len // 15 TOPIC
             + 2 + properties_length; // 16 propertiesLength
-        BytesMut::with_capacity(store_size)
-    };
+    let mut byte_buffer = BytesMut::with_capacity(store_size);
 
     // 1 TOTALSIZE
-    byte_buffer.put_i32(store_size);
+    byte_buffer.put_i32(store_size as i32);
 
     // 2 MAGICCODE
     byte_buffer.put_i32(MESSAGE_MAGIC_CODE);
@@ -632,7 +631,7 @@ pub fn encode(message_ext: &MessageExt, need_compress: bool) -> Result<Bytes> {
     byte_buffer.put_i64(message_ext.commit_log_offset);
 
     // 8 SYSFLAG
-    byte_buffer.put_i32(message_ext.sys_flag);
+    byte_buffer.put_i32(sys_flag);
 
     // 9 BORNTIMESTAMP
     byte_buffer.put_i64(message_ext.born_timestamp);
@@ -675,7 +674,7 @@ pub fn encode(message_ext: &MessageExt, need_compress: bool) -> Result<Bytes> {
     }
 
     // 16 TOPIC
-    byte_buffer.put_i16(topic_len as i16);
+    byte_buffer.put_u8(topic_len as u8);
     byte_buffer.put_slice(topics);
 
     // 17 properties
@@ -692,7 +691,7 @@ pub fn encode_uniquely(message_ext: &MessageExt, need_compress: bool) -> Result<
     let properties = message_properties_to_string(message_ext.get_properties());
     let properties_bytes = properties.as_bytes();
     let properties_length = properties_bytes.len();
-    let sys_flag = message_ext.sys_flag;
+    let sys_flag = sys_flag_with_host_families(message_ext);
     let born_host_length = if (sys_flag & MessageSysFlag::BORNHOST_V6_FLAG) == 0 {
         8
     } else {
@@ -709,11 +708,7 @@ pub fn encode_uniquely(message_ext: &MessageExt, need_compress: bool) -> Result<
         None
     };
     let body_len = new_body.as_ref().map_or(body.len(), |b| b.len());
-    let store_size = message_ext.store_size;
-    let mut byte_buffer = if store_size > 0 {
-        BytesMut::with_capacity((store_size - 8) as usize)
-    } else {
-        let store_size = 4 // 1 TOTALSIZE
+    let store_size = 4 // 1 TOTALSIZE
             + 4 // 2 MAGICCODE
             + 4 // 3 BODYCRC
             + 4 // 4 QUEUEID
@@ -728,11 +723,10 @@ pub fn encode_uniquely(message_ext: &MessageExt, need_compress: bool) -> Result<
             + 4 + body_len // 13 BODY
             + 1 + topic_len // 14 TOPIC
             + 2 + properties_length; // 15 propertiesLength
-        BytesMut::with_capacity(store_size)
-    };
+    let mut byte_buffer = BytesMut::with_capacity(store_size);
 
     // 1 TOTALSIZE
-    byte_buffer.put_i32(store_size);
+    byte_buffer.put_i32(store_size as i32);
 
     // 2 MAGICCODE
     byte_buffer.put_i32(MESSAGE_MAGIC_CODE);
@@ -753,7 +747,7 @@ pub fn encode_uniquely(message_ext: &MessageExt, need_compress: bool) -> Result<
     byte_buffer.put_i64(message_ext.commit_log_offset);
 
     // 8 SYSFLAG
-    byte_buffer.put_i32(message_ext.sys_flag);
+    byte_buffer.put_i32(sys_flag);
 
     // 9 BORNTIMESTAMP
     byte_buffer.put_i64(message_ext.born_timestamp);
@@ -782,7 +776,7 @@ pub fn encode_uniquely(message_ext: &MessageExt, need_compress: bool) -> Result<
     }
 
     // 14 TOPIC
-    byte_buffer.put_i16(topic_len as i16);
+    byte_buffer.put_u8(topic_len as u8);
     byte_buffer.put_slice(topics);
 
     // 15 properties
@@ -830,6 +824,16 @@ mod tests {
         assert_eq!(count_inner_msg_num(Some(bytes.freeze())), 1);
     }
 
+    #[test]
+    fn create_crc32_writes_the_digits_least_significant_first() {
+        let mut input = [0u8; 20];
+        create_crc32(&mut input, 1234567);
+        assert_eq!(&input[..8], MessageConst::PROPERTY_CRC32.as_bytes());
+        assert_eq!(input[8], NAME_VALUE_SEPARATOR as u8);
+        assert_eq!(&input[9..19], b"7654321000");
+        assert_eq!(input[19], PROPERTY_SEPARATOR as u8);
+    }
+
     #[test]
     fn decode_message_id_ipv4() {
         let msg_id = "7F0000010007D8260BF075769D36C348";
@@ -838,6 +842,66 @@ mod tests {
         assert_eq!(message_id.offset, 860316681131967304);
     }
 
+    #[test]
+    fn decode_message_id_ipv6() {
+        let address: SocketAddr = "[2001:db8::1]:10911".parse().unwrap();
+        let msg_id = build_message_id(address, 4096);
+        assert_eq!(msg_id.len(), 56);
+        let message_id = decode_message_id(&msg_id);
+        assert_eq!(message_id.address, address);
+        assert_eq!(message_id.offset, 4096);
+    }
+
+    #[test]
+    fn encode_and_decode_mixed_host_families() {
+        let hosts: [(SocketAddr, SocketAddr); 4] = [
+            (
+                "10.0.0.1:5000".parse().unwrap(),
+                "10.0.0.2:10911".parse().unwrap(),
+            ),
+            (
+                "[2001:db8::1]:5000".parse().unwrap(),
+                "10.0.0.2:10911".parse().unwrap(),
+            ),
+            (
+                "10.0.0.1:5000".parse().unwrap(),
+                "[2001:db8::2]:10911".parse().unwrap(),
+            ),
+            (
+                "[2001:db8::1]:5000".parse().unwrap(),
+                "[2001:db8::2]:10911".parse().unwrap(),
+            ),
+        ];
+        for (born_host, store_host) in hosts {
+            let mut message_ext = MessageExt::default();
+            message_ext.set_topic(CheetahString::from_static_str("TopicTest"));
+            message_ext.set_body(Bytes::from_static(b"hello"));
+            message_ext.set_born_host(born_host);
+            message_ext.set_store_host(store_host);
+            message_ext.set_commit_log_offset(1024);
+
+            let encoded = encode(&message_ext, false).unwrap();
+            assert_eq!(
+                (&encoded[..4]).get_i32() as usize,
+                encoded.len(),
+                "store size must cover the whole encoded message"
+            );
+            let decoded = decode(&mut encoded.clone(), true, false, false, false, false).unwrap();
+            assert_eq!(decoded.born_host, born_host);
+            assert_eq!(decoded.store_host, store_host);
+            assert_eq!(decoded.get_body().unwrap().as_ref(), b"hello");
+            assert_eq!(decoded.get_topic().as_str(), "TopicTest");
+            let expected_len = if store_host.is_ipv6() { 56 } else { 32 };
+            assert_eq!(decoded.msg_id.len(), expected_len);
+            assert_eq!(decode_message_id(&decoded.msg_id).address, store_host);
+
+            let without_body =
+                decode(&mut encoded.clone(), false, false, false, false, false).unwrap();
+            assert!(without_body.get_body().is_none());
+            assert_eq!(without_body.get_topic().as_str(), "TopicTest");
+        }
+    }
+
     #[test]
     fn encode_with_compression() {
         let mut message_ext = MessageExt::default();
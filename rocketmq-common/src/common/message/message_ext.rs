@@ -21,10 +21,16 @@ use std::fmt::Display;
 use std::fmt::Formatter;
 use std::net::SocketAddr;
 
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use bytes::Buf;
 use bytes::BufMut;
 use bytes::Bytes;
 use cheetah_string::CheetahString;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
 
 use crate::common::message::message_single::Message;
 use crate::common::message::MessageTrait;
@@ -95,6 +101,14 @@ impl MessageExt {
         self.sys_flag |= MessageSysFlag::STOREHOSTADDRESS_V6_FLAG;
     }
 
+    pub fn clear_born_host_v6_flag(&mut self) {
+        self.sys_flag &= !MessageSysFlag::BORNHOST_V6_FLAG;
+    }
+
+    pub fn clear_store_host_v6_flag(&mut self) {
+        self.sys_flag &= !MessageSysFlag::STOREHOSTADDRESS_V6_FLAG;
+    }
+
     pub fn body(&self) -> Option<bytes::Bytes> {
         self.message.body()
     }
@@ -275,6 +289,121 @@ impl fmt::Display for MessageExt {
         )
     }
 }
+/// JSON shape of a `MessageExt` as produced by the Java admin `viewMessage` command: the message
+/// fields are flattened, names are camelCase and the body is base64 encoded.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MessageExtJson {
+    topic: CheetahString,
+    flag: i32,
+    #[serde(default)]
+    properties: HashMap<CheetahString, CheetahString>,
+    #[serde(default, with = "base64_body")]
+    body: Option<Bytes>,
+    #[serde(default)]
+    transaction_id: Option<CheetahString>,
+    #[serde(default)]
+    broker_name: CheetahString,
+    queue_id: i32,
+    store_size: i32,
+    queue_offset: i64,
+    sys_flag: i32,
+    born_timestamp: i64,
+    born_host: SocketAddr,
+    store_timestamp: i64,
+    store_host: SocketAddr,
+    msg_id: CheetahString,
+    commit_log_offset: i64,
+    #[serde(rename = "bodyCRC")]
+    body_crc: u32,
+    reconsume_times: i32,
+    prepared_transaction_offset: i64,
+}
+
+mod base64_body {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        body: &Option<Bytes>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match body {
+            Some(body) => serializer.serialize_str(STANDARD.encode(body).as_str()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Bytes>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(body) => STANDARD
+                .decode(body)
+                .map(|body| Some(Bytes::from(body)))
+                .map_err(serde::de::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Serialize for MessageExt {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let message = &self.message;
+        MessageExtJson {
+            topic: message.topic.clone(),
+            flag: message.flag,
+            properties: message.properties.clone(),
+            body: message.body.clone(),
+            transaction_id: message.transaction_id.clone(),
+            broker_name: self.broker_name.clone(),
+            queue_id: self.queue_id,
+            store_size: self.store_size,
+            queue_offset: self.queue_offset,
+            sys_flag: self.sys_flag,
+            born_timestamp: self.born_timestamp,
+            born_host: self.born_host,
+            store_timestamp: self.store_timestamp,
+            store_host: self.store_host,
+            msg_id: self.msg_id.clone(),
+            commit_log_offset: self.commit_log_offset,
+            body_crc: self.body_crc,
+            reconsume_times: self.reconsume_times,
+            prepared_transaction_offset: self.prepared_transaction_offset,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for MessageExt {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = MessageExtJson::deserialize(deserializer)?;
+        Ok(MessageExt {
+            message: Message {
+                topic: json.topic,
+                flag: json.flag,
+                properties: json.properties,
+                body: json.body,
+                compressed_body: None,
+                transaction_id: json.transaction_id,
+            },
+            broker_name: json.broker_name,
+            queue_id: json.queue_id,
+            store_size: json.store_size,
+            queue_offset: json.queue_offset,
+            sys_flag: json.sys_flag,
+            born_timestamp: json.born_timestamp,
+            born_host: json.born_host,
+            store_timestamp: json.store_timestamp,
+            store_host: json.store_host,
+            msg_id: json.msg_id,
+            commit_log_offset: json.commit_log_offset,
+            body_crc: json.body_crc,
+            reconsume_times: json.reconsume_times,
+            prepared_transaction_offset: json.prepared_transaction_offset,
+        })
+    }
+}
+
 impl MessageTrait for MessageExt {
     fn put_property(&mut self, key: CheetahString, value: CheetahString) {
         self.message.put_property(key, value);
@@ -349,3 +478,78 @@ impl MessageTrait for MessageExt {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn view_message() -> MessageExt {
+        let mut message = Message::with_tags("TopicTest", "TagA", b"Hello RocketMQ");
+        message.set_transaction_id(CheetahString::from_static_str("tx-1"));
+        MessageExt {
+            message,
+            broker_name: CheetahString::from_static_str("broker-a"),
+            queue_id: 3,
+            store_size: 210,
+            queue_offset: 42,
+            sys_flag: MessageSysFlag::BORNHOST_V6_FLAG,
+            born_timestamp: 1700000000000,
+            born_host: "[::1]:53920".parse().unwrap(),
+            store_timestamp: 1700000000010,
+            store_host: "192.168.0.1:10911".parse().unwrap(),
+            msg_id: CheetahString::from_static_str("C0A8000100002A9F000000000000A2B8"),
+            commit_log_offset: 41656,
+            body_crc: 1932557065,
+            reconsume_times: 1,
+            prepared_transaction_offset: 0,
+        }
+    }
+
+    #[test]
+    fn serialize_matches_view_message_shape() {
+        let value = serde_json::to_value(view_message()).unwrap();
+        assert_eq!(value["topic"], json!("TopicTest"));
+        assert_eq!(value["properties"]["TAGS"], json!("TagA"));
+        assert_eq!(value["body"], json!("SGVsbG8gUm9ja2V0TVE="));
+        assert_eq!(value["transactionId"], json!("tx-1"));
+        assert_eq!(value["brokerName"], json!("broker-a"));
+        assert_eq!(value["queueId"], json!(3));
+        assert_eq!(value["queueOffset"], json!(42));
+        assert_eq!(value["sysFlag"], json!(16));
+        assert_eq!(value["bornHost"], json!("[::1]:53920"));
+        assert_eq!(value["storeHost"], json!("192.168.0.1:10911"));
+        assert_eq!(value["commitLogOffset"], json!(41656));
+        assert_eq!(value["bodyCRC"], json!(1932557065u32));
+        assert_eq!(value["reconsumeTimes"], json!(1));
+        assert_eq!(value["preparedTransactionOffset"], json!(0));
+        assert!(value.get("compressedBody").is_none());
+    }
+
+    #[test]
+    fn deserialize_round_trip() {
+        let expected = view_message();
+        let json = serde_json::to_string(&expected).unwrap();
+        let actual: MessageExt = serde_json::from_str(json.as_str()).unwrap();
+        assert_eq!(actual.topic(), expected.topic());
+        assert_eq!(actual.body(), expected.body());
+        assert_eq!(actual.get_tags(), expected.get_tags());
+        assert_eq!(actual.born_host(), expected.born_host());
+        assert_eq!(actual.store_host(), expected.store_host());
+        assert_eq!(actual.msg_id(), expected.msg_id());
+        assert_eq!(actual.body_crc(), expected.body_crc());
+        assert_eq!(actual.get_transaction_id(), expected.get_transaction_id());
+    }
+
+    #[test]
+    fn deserialize_without_body() {
+        let json = r#"{"topic":"t","flag":0,"queueId":0,"storeSize":0,"queueOffset":0,
+            "sysFlag":0,"bornTimestamp":0,"bornHost":"127.0.0.1:1","storeTimestamp":0,
+            "storeHost":"127.0.0.1:2","msgId":"id","commitLogOffset":0,"bodyCRC":0,
+            "reconsumeTimes":0,"preparedTransactionOffset":0}"#;
+        let message: MessageExt = serde_json::from_str(json).unwrap();
+        assert!(message.body().is_none());
+        assert!(message.properties().is_empty());
+    }
+}
@@ -86,6 +86,14 @@ impl MessageExtBrokerInner {
         self.message_ext_inner.with_store_host_v6_flag()
     }
 
+    pub fn clear_born_host_v6_flag(&mut self) {
+        self.message_ext_inner.clear_born_host_v6_flag()
+    }
+
+    pub fn clear_store_host_v6_flag(&mut self) {
+        self.message_ext_inner.clear_store_host_v6_flag()
+    }
+
     pub fn body(&self) -> Option<bytes::Bytes> {
         self.message_ext_inner.body()
     }
@@ -264,3 +272,22 @@ impl MessageTrait for MessageExtBrokerInner {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_string_to_tags_code_uses_java_hash() {
+        assert_eq!(MessageExtBrokerInner::tags_string_to_tags_code(""), 0);
+        assert_eq!(
+            MessageExtBrokerInner::tags_string_to_tags_code("TagA"),
+            2598919
+        );
+        // longer tags overflow i32 the same way String.hashCode() does in Java
+        assert_eq!(
+            MessageExtBrokerInner::tags_string_to_tags_code("TagA||TagB||TagC"),
+            -136118632
+        );
+    }
+}
@@ -0,0 +1,225 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+
+use cheetah_string::CheetahString;
+use thiserror::Error;
+
+use crate::common::topic::TopicValidator;
+
+/// Reason a message is refused before it is sent or stored.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MessageCheckError {
+    #[error("{0}")]
+    IllegalTopic(CheetahString),
+
+    #[error("Sending message to topic[{0}] is forbidden.")]
+    NotAllowedSendTopic(CheetahString),
+
+    #[error("the message body is null")]
+    MissingBody,
+
+    #[error("the message body length is zero")]
+    EmptyBody,
+
+    #[error("the message body size {size} over max value, MAX: {max}")]
+    BodyTooLarge { size: usize, max: usize },
+
+    #[error("the message properties length {length} over max value, MAX: {max}")]
+    PropertiesTooLong { length: usize, max: usize },
+}
+
+/// Checks shared by the producer, which fails fast before sending, and the broker send path.
+pub struct MessageValidator;
+
+impl MessageValidator {
+    /// The properties length is encoded as a short in the commit log.
+    pub const MAX_PROPERTIES_LENGTH: usize = i16::MAX as usize;
+
+    pub fn check_topic(topic: &str) -> Result<(), MessageCheckError> {
+        let result = TopicValidator::validate_topic(topic);
+        if !result.valid() {
+            return Err(MessageCheckError::IllegalTopic(result.remark().clone()));
+        }
+        if TopicValidator::is_not_allowed_send_topic(topic) {
+            return Err(MessageCheckError::NotAllowedSendTopic(
+                CheetahString::from_slice(topic),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn check_body(
+        body: Option<&[u8]>,
+        max_message_size: usize,
+    ) -> Result<(), MessageCheckError> {
+        let size = body.ok_or(MessageCheckError::MissingBody)?.len();
+        if size == 0 {
+            return Err(MessageCheckError::EmptyBody);
+        }
+        if size > max_message_size {
+            return Err(MessageCheckError::BodyTooLarge {
+                size,
+                max: max_message_size,
+            });
+        }
+        Ok(())
+    }
+
+    /// `length` is the length of the encoded properties string.
+    pub fn check_properties_length(length: usize) -> Result<(), MessageCheckError> {
+        if length > Self::MAX_PROPERTIES_LENGTH {
+            return Err(MessageCheckError::PropertiesTooLong {
+                length,
+                max: Self::MAX_PROPERTIES_LENGTH,
+            });
+        }
+        Ok(())
+    }
+
+    /// Length of `properties` once encoded, without building the string.
+    pub fn properties_length(properties: &HashMap<CheetahString, CheetahString>) -> usize {
+        properties
+            .iter()
+            .map(|(name, value)| name.len() + value.len() + 2)
+            .sum()
+    }
+
+    pub fn check(
+        topic: &str,
+        body: Option<&[u8]>,
+        properties_length: usize,
+        max_message_size: usize,
+    ) -> Result<(), MessageCheckError> {
+        Self::check_topic(topic)?;
+        Self::check_body(body, max_message_size)?;
+        Self::check_properties_length(properties_length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::message::message_decoder::message_properties_to_string;
+
+    const MAX_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
+
+    #[test]
+    fn body_size_limits() {
+        let body = vec![0u8; MAX_MESSAGE_SIZE + 1];
+        assert_eq!(
+            MessageValidator::check_body(Some(&body[..MAX_MESSAGE_SIZE]), MAX_MESSAGE_SIZE),
+            Ok(())
+        );
+        assert_eq!(
+            MessageValidator::check_body(Some(&body), MAX_MESSAGE_SIZE),
+            Err(MessageCheckError::BodyTooLarge {
+                size: MAX_MESSAGE_SIZE + 1,
+                max: MAX_MESSAGE_SIZE
+            })
+        );
+        assert_eq!(
+            MessageValidator::check_body(Some(&[]), MAX_MESSAGE_SIZE),
+            Err(MessageCheckError::EmptyBody)
+        );
+        assert_eq!(
+            MessageValidator::check_body(None, MAX_MESSAGE_SIZE),
+            Err(MessageCheckError::MissingBody)
+        );
+        assert_eq!(
+            MessageValidator::check_body(Some(b"a"), MAX_MESSAGE_SIZE),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn body_too_large_remark_reports_sizes() {
+        let err = MessageValidator::check_body(Some(b"abc"), 2).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the message body size 3 over max value, MAX: 2"
+        );
+    }
+
+    #[test]
+    fn properties_length_limits() {
+        assert_eq!(
+            MessageValidator::check_properties_length(MessageValidator::MAX_PROPERTIES_LENGTH),
+            Ok(())
+        );
+        assert_eq!(
+            MessageValidator::check_properties_length(32768),
+            Err(MessageCheckError::PropertiesTooLong {
+                length: 32768,
+                max: 32767
+            })
+        );
+    }
+
+    #[test]
+    fn properties_length_matches_encoded_string() {
+        let properties = HashMap::from([
+            (
+                CheetahString::from_static_str("KEYS"),
+                CheetahString::from_static_str("order-1"),
+            ),
+            (
+                CheetahString::from_static_str("TAGS"),
+                CheetahString::from_static_str("tagA"),
+            ),
+        ]);
+        assert_eq!(
+            MessageValidator::properties_length(&properties),
+            message_properties_to_string(&properties).len()
+        );
+    }
+
+    #[test]
+    fn topic_limits() {
+        let at_max = "t".repeat(127);
+        assert_eq!(MessageValidator::check_topic(&at_max), Ok(()));
+        assert!(matches!(
+            MessageValidator::check_topic(&"t".repeat(128)),
+            Err(MessageCheckError::IllegalTopic(_))
+        ));
+        assert!(matches!(
+            MessageValidator::check_topic("bad topic"),
+            Err(MessageCheckError::IllegalTopic(_))
+        ));
+        assert!(matches!(
+            MessageValidator::check_topic(""),
+            Err(MessageCheckError::IllegalTopic(_))
+        ));
+        assert_eq!(
+            MessageValidator::check_topic(TopicValidator::RMQ_SYS_SCHEDULE_TOPIC),
+            Err(MessageCheckError::NotAllowedSendTopic(
+                CheetahString::from_static_str(TopicValidator::RMQ_SYS_SCHEDULE_TOPIC)
+            ))
+        );
+    }
+
+    #[test]
+    fn check_runs_every_limit() {
+        assert_eq!(
+            MessageValidator::check("TopicA", Some(b"body"), 10, 16),
+            Ok(())
+        );
+        assert!(MessageValidator::check("TopicA", Some(b"body"), 40000, 16).is_err());
+        assert!(MessageValidator::check("TopicA", Some(b"body"), 10, 3).is_err());
+        assert!(MessageValidator::check("", Some(b"body"), 10, 16).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use serde::Deserialize;
+use serde::Serialize;
+
+/// How the broker exports its metrics, mirroring `MetricsExporterType` of the Java broker.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MetricsExporterType {
+    #[default]
+    Disable,
+    OtlpGrpc,
+    Prom,
+    Log,
+}
+
+impl MetricsExporterType {
+    pub fn value(&self) -> i32 {
+        match self {
+            MetricsExporterType::Disable => 0,
+            MetricsExporterType::OtlpGrpc => 1,
+            MetricsExporterType::Prom => 2,
+            MetricsExporterType::Log => 3,
+        }
+    }
+
+    pub fn value_of(value: i32) -> Self {
+        match value {
+            1 => MetricsExporterType::OtlpGrpc,
+            2 => MetricsExporterType::Prom,
+            3 => MetricsExporterType::Log,
+            _ => MetricsExporterType::Disable,
+        }
+    }
+
+    pub fn is_enable(&self) -> bool {
+        self.value() > 0
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            MetricsExporterType::Disable => "DISABLE",
+            MetricsExporterType::OtlpGrpc => "OTLP_GRPC",
+            MetricsExporterType::Prom => "PROM",
+            MetricsExporterType::Log => "LOG",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn value_of_falls_back_to_disable() {
+        assert_eq!(MetricsExporterType::value_of(2), MetricsExporterType::Prom);
+        assert_eq!(
+            MetricsExporterType::value_of(9),
+            MetricsExporterType::Disable
+        );
+        assert!(!MetricsExporterType::Disable.is_enable());
+        assert!(MetricsExporterType::OtlpGrpc.is_enable());
+    }
+
+    #[test]
+    fn deserializes_java_names() {
+        let exporter: MetricsExporterType = serde_json::from_str("\"OTLP_GRPC\"").unwrap();
+        assert_eq!(exporter, MetricsExporterType::OtlpGrpc);
+        assert_eq!(
+            serde_json::to_string(&MetricsExporterType::Prom).unwrap(),
+            "\"PROM\""
+        );
+    }
+}
@@ -114,6 +114,12 @@ pub fn is_lmq(lmq_meta_data: Option<&str>) -> bool {
     }
 }
 
+/// Whether an address server domain is configured, so name server addresses can be looked up
+/// when none is specified.
+pub fn is_ws_addr_configured() -> bool {
+    env::var("rocketmq.namesrv.domain").is_ok_and(|domain| !domain.trim().is_empty())
+}
+
 pub fn get_ws_addr() -> String {
     let ws_domain_name = env::var("rocketmq.namesrv.domain")
         .unwrap_or_else(|_| DEFAULT_NAMESRV_ADDR_LOOKUP.to_string());
@@ -183,6 +189,16 @@ pub fn string_to_properties(input: &str) -> Option<HashMap<CheetahString, Cheeta
     Some(properties)
 }
 
+/// Renders `properties` as `key=value` lines sorted by key, readable by [`string_to_properties`].
+pub fn properties_to_string(properties: &HashMap<CheetahString, CheetahString>) -> String {
+    let mut entries: Vec<_> = properties.iter().collect();
+    entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    entries
+        .into_iter()
+        .map(|(key, value)| format!("{}={}\n", key, value))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -294,4 +310,16 @@ mod tests {
         let result = string_to_properties(input);
         assert!(result.is_none(), "Parsing should fail for invalid input");
     }
+
+    #[test]
+    fn properties_to_string_round_trips() {
+        let properties = HashMap::from([
+            (CheetahString::from("b"), CheetahString::from("2")),
+            (CheetahString::from("a"), CheetahString::from("x=y")),
+        ]);
+        let text = properties_to_string(&properties);
+        assert_eq!(text, "a=x=y\nb=2\n");
+        assert_eq!(string_to_properties(&text), Some(properties));
+        assert_eq!(properties_to_string(&HashMap::new()), "");
+    }
 }
@@ -1260,7 +1260,39 @@ impl From<RocketMqVersion> for i32 {
     }
 }
 impl RocketMqVersion {
-    pub const CURRENT_VERSION: RocketMqVersion = RocketMqVersion::HigherVerSion;
+    pub const CURRENT_VERSION: RocketMqVersion = RocketMqVersion::V531;
+
+    /// The name of the version as spelled by the Java `MQVersion.Version` enum, e.g. `V5_3_1` or
+    /// `V4_0_0_SNAPSHOT`.
+    pub fn name(&self) -> String {
+        if *self == RocketMqVersion::HigherVerSion {
+            return "HIGHER_VERSION".to_string();
+        }
+        let debug = format!("{:?}", self);
+        let digits_end = debug[1..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(debug.len(), |index| index + 1);
+        let (digits, qualifier) = debug[1..].split_at(digits_end - 1);
+        let mut name = format!("V{}_{}_{}", &digits[..1], &digits[1..2], &digits[2..]);
+        let mut rest = qualifier;
+        while !rest.is_empty() {
+            let end = rest[1..]
+                .find(|c: char| c.is_ascii_uppercase())
+                .map_or(rest.len(), |index| index + 1);
+            name.push('_');
+            name.push_str(&rest[..end].to_ascii_uppercase());
+            rest = &rest[end..];
+        }
+        name
+    }
+}
+
+/// Describes a version ordinal the way Java's `MQVersion.getVersionDesc` does, ordinals beyond the
+/// known table map to `HIGHER_VERSION`.
+pub fn get_version_desc(value: i32) -> String {
+    RocketMqVersion::try_from(value)
+        .unwrap_or(RocketMqVersion::HigherVerSion)
+        .name()
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -1888,6 +1920,36 @@ impl fmt::Display for RocketMqVersion {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn test_version_from_str() {}
+
+    #[test]
+    fn ordinals_match_java_mq_version() {
+        for (ordinal, version) in [
+            (0, RocketMqVersion::V300Snapshot),
+            (1, RocketMqVersion::V300Alpha1),
+            (37, RocketMqVersion::V3011),
+            (232, RocketMqVersion::V410Snapshot),
+            (401, RocketMqVersion::V494),
+            (413, RocketMqVersion::V500),
+            (475, RocketMqVersion::V531),
+        ] {
+            assert_eq!(RocketMqVersion::try_from(ordinal), Ok(version));
+            assert_eq!(i32::from(version), ordinal);
+        }
+        assert_eq!(i32::from(RocketMqVersion::CURRENT_VERSION), 475);
+    }
+
+    #[test]
+    fn version_desc_uses_java_names() {
+        assert_eq!(get_version_desc(0), "V3_0_0_SNAPSHOT");
+        assert_eq!(get_version_desc(6), "V3_0_0_BETA5");
+        assert_eq!(get_version_desc(7), "V3_0_0_BETA6_SNAPSHOT");
+        assert_eq!(get_version_desc(37), "V3_0_11");
+        assert_eq!(get_version_desc(413), "V5_0_0");
+        assert_eq!(get_version_desc(475), "V5_3_1");
+        assert_eq!(get_version_desc(10_000), "HIGHER_VERSION");
+    }
 }
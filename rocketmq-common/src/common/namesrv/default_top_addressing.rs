@@ -119,3 +119,70 @@ impl TopAddressing for DefaultTopAddressing {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufRead;
+    use std::io::BufReader;
+    use std::io::Write;
+    use std::net::TcpListener;
+
+    use super::*;
+
+    /// Serves one HTTP response per body, in order, on a local port.
+    fn serve(bodies: Vec<(u16, &'static str)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for (code, body) in bodies {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                    line.clear();
+                }
+                write!(
+                    stream,
+                    "HTTP/1.1 {code} OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+        format!("http://{addr}/rocketmq/nsaddr")
+    }
+
+    #[test]
+    fn fetch_ns_addr_follows_address_server_changes() {
+        let ws_addr = serve(vec![
+            (200, "10.0.0.1:9876;10.0.0.2:9876\n"),
+            (200, "10.0.0.3:9876\n"),
+        ]);
+        let top_addressing = DefaultTopAddressing::new(ws_addr.into(), None);
+        assert_eq!(
+            top_addressing.fetch_ns_addr().as_deref(),
+            Some("10.0.0.1:9876;10.0.0.2:9876")
+        );
+        assert_eq!(
+            top_addressing.fetch_ns_addr().as_deref(),
+            Some("10.0.0.3:9876")
+        );
+    }
+
+    #[test]
+    fn fetch_ns_addr_ignores_failed_and_empty_responses() {
+        let ws_addr = serve(vec![(500, "boom"), (200, "")]);
+        let top_addressing = DefaultTopAddressing::new(ws_addr.into(), None);
+        assert_eq!(top_addressing.fetch_ns_addr(), None);
+        assert_eq!(top_addressing.fetch_ns_addr(), None);
+    }
+
+    #[test]
+    fn fetch_ns_addr_without_address_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let ws_addr = format!("http://{}/rocketmq/nsaddr", listener.local_addr().unwrap());
+        drop(listener);
+        let top_addressing = DefaultTopAddressing::new(ws_addr.into(), None);
+        assert_eq!(top_addressing.fetch_ns_addr_inner(false, 500), None);
+    }
+}
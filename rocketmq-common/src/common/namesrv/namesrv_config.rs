@@ -91,6 +91,23 @@ pub struct NamesrvConfig {
 
     #[serde(alias = "configBlackList")]
     pub config_black_list: String,
+
+    /// Drops broker groups without a live address from topic routes, so producers stop picking
+    /// their queues before the group is expired.
+    #[serde(alias = "filterUnavailableBrokerInRoute")]
+    pub filter_unavailable_broker_in_route: bool,
+
+    /// Serves topic routes from a cache of their encoded responses, dropped as soon as a
+    /// registration or an admin request changes the route.
+    #[serde(alias = "enableRouteResponseCache")]
+    pub enable_route_response_cache: bool,
+
+    /// OTLP/gRPC endpoint the request spans are exported to, not exported when empty.
+    #[serde(alias = "traceOtlpExporterEndpoint")]
+    pub trace_otlp_exporter_endpoint: String,
+
+    #[serde(alias = "traceOtlpExporterTimeOutInMills")]
+    pub trace_otlp_exporter_time_out_in_mills: u64,
 }
 
 impl Default for NamesrvConfig {
@@ -138,6 +155,10 @@ impl Default for NamesrvConfig {
             wait_seconds_for_service: 45,
             delete_topic_with_broker_registration: false,
             config_black_list: "configBlackList;configStorePath;kvConfigPath".to_string(),
+            filter_unavailable_broker_in_route: false,
+            enable_route_response_cache: false,
+            trace_otlp_exporter_endpoint: String::new(),
+            trace_otlp_exporter_time_out_in_mills: 3 * 1000,
         }
     }
 }
@@ -250,6 +271,24 @@ impl NamesrvConfig {
                         .parse()
                         .map_err(|_| format!("Invalid string value for key '{}'", key))?
                 }
+                "filterUnavailableBrokerInRoute" => {
+                    self.filter_unavailable_broker_in_route = value
+                        .parse()
+                        .map_err(|_| format!("Invalid boolean value for key '{}'", key))?
+                }
+                "enableRouteResponseCache" => {
+                    self.enable_route_response_cache = value
+                        .parse()
+                        .map_err(|_| format!("Invalid boolean value for key '{}'", key))?
+                }
+                "traceOtlpExporterEndpoint" => {
+                    self.trace_otlp_exporter_endpoint = value.to_string()
+                }
+                "traceOtlpExporterTimeOutInMills" => {
+                    self.trace_otlp_exporter_time_out_in_mills = value
+                        .parse()
+                        .map_err(|_| format!("Invalid integer value for key '{}'", key))?
+                }
                 _ => {
                     return Err(format!("Unknown configuration key: '{}'", key));
                 }
@@ -313,6 +352,8 @@ mod tests {
         assert_eq!(config.need_wait_for_service, false);
         assert_eq!(config.wait_seconds_for_service, 45);
         assert_eq!(config.delete_topic_with_broker_registration, false);
+        assert!(!config.filter_unavailable_broker_in_route);
+        assert!(!config.enable_route_response_cache);
         assert_eq!(
             config.config_black_list,
             "configBlackList;configStorePath;kvConfigPath".to_string()
@@ -21,3 +21,15 @@ pub enum RunningStats {
     ConsumeQueueDiskRatio,
     ScheduleMessageOffset,
 }
+
+impl RunningStats {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RunningStats::CommitLogMaxOffset => "commitLogMaxOffset",
+            RunningStats::CommitLogMinOffset => "commitLogMinOffset",
+            RunningStats::CommitLogDiskRatio => "commitLogDiskRatio",
+            RunningStats::ConsumeQueueDiskRatio => "consumeQueueDiskRatio",
+            RunningStats::ScheduleMessageOffset => "scheduleMessageOffset",
+        }
+    }
+}
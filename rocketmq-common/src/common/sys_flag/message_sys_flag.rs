@@ -55,6 +55,31 @@ impl MessageSysFlag {
         CompressionType::find_by_value(compression_type_value)
     }
 
+    #[inline]
+    pub const fn is_compressed(flag: i32) -> bool {
+        Self::check(flag, Self::COMPRESSED_FLAG)
+    }
+
+    #[inline]
+    pub const fn has_multi_tags(flag: i32) -> bool {
+        Self::check(flag, Self::MULTI_TAGS_FLAG)
+    }
+
+    #[inline]
+    pub const fn is_born_host_v6(flag: i32) -> bool {
+        Self::check(flag, Self::BORNHOST_V6_FLAG)
+    }
+
+    #[inline]
+    pub const fn is_store_host_v6(flag: i32) -> bool {
+        Self::check(flag, Self::STOREHOSTADDRESS_V6_FLAG)
+    }
+
+    #[inline]
+    pub const fn is_inner_batch(flag: i32) -> bool {
+        Self::check(flag, Self::INNER_BATCH_FLAG)
+    }
+
     #[inline]
     pub const fn check(flag: i32, expected_flag: i32) -> bool {
         (flag & expected_flag) != 0
@@ -114,4 +139,20 @@ mod tests {
             MessageSysFlag::COMPRESSED_FLAG
         ));
     }
+
+    #[test]
+    fn flag_helpers_check_single_bits() {
+        let flag = MessageSysFlag::COMPRESSED_FLAG
+            | MessageSysFlag::BORNHOST_V6_FLAG
+            | MessageSysFlag::TRANSACTION_PREPARED_TYPE;
+        assert!(MessageSysFlag::is_compressed(flag));
+        assert!(MessageSysFlag::is_born_host_v6(flag));
+        assert!(!MessageSysFlag::is_store_host_v6(flag));
+        assert!(!MessageSysFlag::has_multi_tags(flag));
+        assert!(!MessageSysFlag::is_inner_batch(flag));
+        assert_eq!(
+            MessageSysFlag::get_transaction_value(flag),
+            MessageSysFlag::TRANSACTION_PREPARED_TYPE
+        );
+    }
 }
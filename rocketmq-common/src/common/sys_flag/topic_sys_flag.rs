@@ -17,6 +17,8 @@
 
 const FLAG_UNIT: u32 = 0x1 << 0;
 const FLAG_UNIT_SUB: u32 = 0x1 << 1;
+const FLAG_RETRY: u32 = 0x1 << 2;
+const FLAG_DLQ: u32 = 0x1 << 3;
 
 pub fn build_sys_flag(unit: bool, has_unit_sub: bool) -> u32 {
     let mut sys_flag = 0;
@@ -53,6 +55,24 @@ pub fn has_unit_sub_flag(sys_flag: u32) -> bool {
     (sys_flag & FLAG_UNIT_SUB) == FLAG_UNIT_SUB
 }
 
+/// Marks the `%RETRY%` topic of a consumer group.
+pub fn set_retry_flag(sys_flag: u32) -> u32 {
+    sys_flag | FLAG_RETRY
+}
+
+pub fn has_retry_flag(sys_flag: u32) -> bool {
+    (sys_flag & FLAG_RETRY) == FLAG_RETRY
+}
+
+/// Marks the `%DLQ%` topic of a consumer group.
+pub fn set_dlq_flag(sys_flag: u32) -> u32 {
+    sys_flag | FLAG_DLQ
+}
+
+pub fn has_dlq_flag(sys_flag: u32) -> bool {
+    (sys_flag & FLAG_DLQ) == FLAG_DLQ
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,4 +119,16 @@ mod tests {
         assert!(has_unit_sub_flag(FLAG_UNIT_SUB));
         assert!(!has_unit_sub_flag(FLAG_UNIT));
     }
+
+    #[test]
+    fn retry_and_dlq_flags_are_independent_of_unit_flags() {
+        let sys_flag = set_retry_flag(build_sys_flag(false, true));
+        assert!(has_retry_flag(sys_flag));
+        assert!(has_unit_sub_flag(sys_flag));
+        assert!(!has_dlq_flag(sys_flag));
+        let sys_flag = set_dlq_flag(0);
+        assert!(has_dlq_flag(sys_flag));
+        assert!(!has_retry_flag(sys_flag));
+        assert!(!has_unit_flag(sys_flag));
+    }
 }
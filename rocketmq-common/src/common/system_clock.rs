@@ -15,15 +15,104 @@
  * limitations under the License.
  */
 
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
 use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
 
+use once_cell::sync::Lazy;
+
+/// Wall clock time and a monotonic instant taken together, used when the system clock is set
+/// before the Unix epoch.
+static CLOCK_BASE: Lazy<(Duration, Instant)> =
+    Lazy::new(|| (wall_time_since_epoch().unwrap_or_default(), Instant::now()));
+
+fn wall_time_since_epoch() -> Option<Duration> {
+    SystemTime::now().duration_since(UNIX_EPOCH).ok()
+}
+
+/// Time elapsed since the Unix epoch. Never panics: if the system clock reads before the epoch
+/// the time is derived from the monotonic clock instead.
+pub fn since_epoch() -> Duration {
+    let base = &*CLOCK_BASE;
+    wall_time_since_epoch().unwrap_or_else(|| base.0 + base.1.elapsed())
+}
+
+/// A source of wall clock time, so that expiry logic can be driven by a [`MockClock`] in tests.
+pub trait Clock: Send + Sync {
+    /// Milliseconds since the Unix epoch.
+    fn now_millis(&self) -> u64;
+}
+
+#[derive(Debug, Default, Clone, Copy)]
 pub struct SystemClock;
 
 impl SystemClock {
     pub fn now() -> u128 {
-        let now = SystemTime::now();
-        let duration = now.duration_since(UNIX_EPOCH).expect("Time went backwards");
-        duration.as_millis()
+        since_epoch().as_millis()
+    }
+}
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now_millis(&self) -> u64 {
+        since_epoch().as_millis() as u64
+    }
+}
+
+/// A clock that only moves when told to.
+#[derive(Debug, Default)]
+pub struct MockClock {
+    now_millis: AtomicU64,
+}
+
+impl MockClock {
+    pub fn new(now_millis: u64) -> Self {
+        Self {
+            now_millis: AtomicU64::new(now_millis),
+        }
+    }
+
+    pub fn set_millis(&self, now_millis: u64) {
+        self.now_millis.store(now_millis, Ordering::Release);
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now_millis
+            .fetch_add(duration.as_millis() as u64, Ordering::AcqRel);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_millis(&self) -> u64 {
+        self.now_millis.load(Ordering::Acquire)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn system_clock_follows_wall_time() {
+        let before = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let now = SystemClock.now_millis();
+        assert!(now >= before);
+        assert!(SystemClock::now() as u64 >= now);
+    }
+
+    #[test]
+    fn mock_clock_moves_only_when_told() {
+        let clock = MockClock::new(1000);
+        assert_eq!(clock.now_millis(), 1000);
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(clock.now_millis(), 3000);
+        clock.set_millis(10);
+        assert_eq!(clock.now_millis(), 10);
     }
 }
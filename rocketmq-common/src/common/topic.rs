@@ -16,10 +16,10 @@
  */
 
 use std::collections::HashSet;
-use std::sync::Mutex;
 
 use cheetah_string::CheetahString;
 use lazy_static::lazy_static;
+use parking_lot::RwLock;
 
 pub const TOPIC_MAX_LENGTH: usize = 127;
 
@@ -41,29 +41,37 @@ lazy_static! {
         }
         map
     };
-    static ref SYSTEM_TOPIC_SET: Mutex<HashSet<&'static str>> = {
-        let mut set = HashSet::new();
-        set.insert(TopicValidator::AUTO_CREATE_TOPIC_KEY_TOPIC);
-        set.insert(TopicValidator::RMQ_SYS_SCHEDULE_TOPIC);
-        set.insert(TopicValidator::RMQ_SYS_BENCHMARK_TOPIC);
-        set.insert(TopicValidator::RMQ_SYS_TRANS_HALF_TOPIC);
-        set.insert(TopicValidator::RMQ_SYS_TRACE_TOPIC);
-        set.insert(TopicValidator::RMQ_SYS_TRANS_OP_HALF_TOPIC);
-        set.insert(TopicValidator::RMQ_SYS_TRANS_CHECK_MAX_TIME_TOPIC);
-        set.insert(TopicValidator::RMQ_SYS_SELF_TEST_TOPIC);
-        set.insert(TopicValidator::RMQ_SYS_OFFSET_MOVED_EVENT);
-        set.insert(TopicValidator::RMQ_SYS_ROCKSDB_OFFSET_TOPIC);
-        Mutex::new(set)
+    static ref SYSTEM_TOPIC_SET: RwLock<HashSet<CheetahString>> = {
+        let set = [
+            TopicValidator::AUTO_CREATE_TOPIC_KEY_TOPIC,
+            TopicValidator::RMQ_SYS_SCHEDULE_TOPIC,
+            TopicValidator::RMQ_SYS_BENCHMARK_TOPIC,
+            TopicValidator::RMQ_SYS_TRANS_HALF_TOPIC,
+            TopicValidator::RMQ_SYS_TRACE_TOPIC,
+            TopicValidator::RMQ_SYS_TRANS_OP_HALF_TOPIC,
+            TopicValidator::RMQ_SYS_TRANS_CHECK_MAX_TIME_TOPIC,
+            TopicValidator::RMQ_SYS_SELF_TEST_TOPIC,
+            TopicValidator::RMQ_SYS_OFFSET_MOVED_EVENT,
+            TopicValidator::RMQ_SYS_ROCKSDB_OFFSET_TOPIC,
+        ]
+        .into_iter()
+        .map(CheetahString::from_static_str)
+        .collect();
+        RwLock::new(set)
     };
-    static ref NOT_ALLOWED_SEND_TOPIC_SET: Mutex<HashSet<&'static str>> = {
-        let mut set = HashSet::new();
-        set.insert(TopicValidator::RMQ_SYS_SCHEDULE_TOPIC);
-        set.insert(TopicValidator::RMQ_SYS_TRANS_HALF_TOPIC);
-        set.insert(TopicValidator::RMQ_SYS_TRANS_OP_HALF_TOPIC);
-        set.insert(TopicValidator::RMQ_SYS_TRANS_CHECK_MAX_TIME_TOPIC);
-        set.insert(TopicValidator::RMQ_SYS_SELF_TEST_TOPIC);
-        set.insert(TopicValidator::RMQ_SYS_OFFSET_MOVED_EVENT);
-        Mutex::new(set)
+    static ref NOT_ALLOWED_SEND_TOPIC_SET: RwLock<HashSet<CheetahString>> = {
+        let set = [
+            TopicValidator::RMQ_SYS_SCHEDULE_TOPIC,
+            TopicValidator::RMQ_SYS_TRANS_HALF_TOPIC,
+            TopicValidator::RMQ_SYS_TRANS_OP_HALF_TOPIC,
+            TopicValidator::RMQ_SYS_TRANS_CHECK_MAX_TIME_TOPIC,
+            TopicValidator::RMQ_SYS_SELF_TEST_TOPIC,
+            TopicValidator::RMQ_SYS_OFFSET_MOVED_EVENT,
+        ]
+        .into_iter()
+        .map(CheetahString::from_static_str)
+        .collect();
+        RwLock::new(set)
     };
 }
 
@@ -124,42 +132,91 @@ impl TopicValidator {
             };
         }
 
-        ValidateTopicResult {
-            valid: true,
-            remark: CheetahString::empty(),
-        }
+        ValidateTopicResult::ok()
     }
 
     pub fn is_system_topic(topic: &str) -> bool {
-        let system_topics = SYSTEM_TOPIC_SET.lock().unwrap();
-        system_topics.contains(topic) || topic.starts_with(TopicValidator::SYSTEM_TOPIC_PREFIX)
+        topic.starts_with(TopicValidator::SYSTEM_TOPIC_PREFIX)
+            || SYSTEM_TOPIC_SET.read().contains(topic)
+    }
+
+    /// Same as [`TopicValidator::is_system_topic`], but reports a conflict as an invalid
+    /// [`ValidateTopicResult`] carrying the remark sent back to the caller.
+    pub fn check_system_topic(topic: &str) -> ValidateTopicResult {
+        if Self::is_system_topic(topic) {
+            return ValidateTopicResult {
+                valid: false,
+                remark: CheetahString::from(format!(
+                    "The topic[{}] is conflict with system topic.",
+                    topic
+                )),
+            };
+        }
+        ValidateTopicResult::ok()
+    }
+
+    /// Refuses to delete a system topic; admin tools hitting one get the remark back.
+    pub fn check_system_topic_deletion(topic: &str) -> ValidateTopicResult {
+        if Self::is_system_topic(topic) {
+            return ValidateTopicResult {
+                valid: false,
+                remark: CheetahString::from(format!(
+                    "The topic[{}] is a system topic and can not be deleted.",
+                    topic
+                )),
+            };
+        }
+        ValidateTopicResult::ok()
     }
 
     pub fn is_not_allowed_send_topic(topic: &str) -> bool {
-        let not_allowed_topics = NOT_ALLOWED_SEND_TOPIC_SET.lock().unwrap();
-        not_allowed_topics.contains(topic)
+        NOT_ALLOWED_SEND_TOPIC_SET.read().contains(topic)
+    }
+
+    /// Same as [`TopicValidator::is_not_allowed_send_topic`], but reports a forbidden topic as an
+    /// invalid [`ValidateTopicResult`] carrying the remark sent back to the caller.
+    pub fn check_not_allowed_send_topic(topic: &str) -> ValidateTopicResult {
+        if Self::is_not_allowed_send_topic(topic) {
+            return ValidateTopicResult {
+                valid: false,
+                remark: CheetahString::from(format!(
+                    "Sending message to topic[{}] is forbidden.",
+                    topic
+                )),
+            };
+        }
+        ValidateTopicResult::ok()
     }
 
-    pub fn add_system_topic(system_topic: &'static str) {
-        let mut system_topics = SYSTEM_TOPIC_SET.lock().unwrap();
-        system_topics.insert(system_topic);
+    /// Registers an additional system topic at runtime, e.g. the broker cluster name.
+    pub fn add_system_topic(system_topic: impl Into<CheetahString>) {
+        SYSTEM_TOPIC_SET.write().insert(system_topic.into());
     }
 
-    pub fn get_system_topic_set() -> HashSet<&'static str> {
-        SYSTEM_TOPIC_SET.lock().unwrap().clone()
+    pub fn get_system_topic_set() -> HashSet<CheetahString> {
+        SYSTEM_TOPIC_SET.read().clone()
     }
 
-    pub fn get_not_allowed_send_topic_set() -> HashSet<&'static str> {
-        NOT_ALLOWED_SEND_TOPIC_SET.lock().unwrap().clone()
+    pub fn get_not_allowed_send_topic_set() -> HashSet<CheetahString> {
+        NOT_ALLOWED_SEND_TOPIC_SET.read().clone()
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct ValidateTopicResult {
     valid: bool,
     remark: CheetahString,
 }
 
 impl ValidateTopicResult {
+    #[inline]
+    fn ok() -> Self {
+        ValidateTopicResult {
+            valid: true,
+            remark: CheetahString::empty(),
+        }
+    }
+
     pub fn valid(&self) -> bool {
         self.valid
     }
@@ -228,6 +285,21 @@ mod tests {
         assert!(!TopicValidator::is_system_topic("non_system_topic"));
     }
 
+    #[test]
+    fn check_system_topic_deletion_refuses_system_topics() {
+        let result = TopicValidator::check_system_topic_deletion("rmq_sys_REVIVE_LOG_Cluster");
+        assert!(!result.valid());
+        assert_eq!(
+            result.remark(),
+            "The topic[rmq_sys_REVIVE_LOG_Cluster] is a system topic and can not be deleted."
+        );
+        assert!(
+            !TopicValidator::check_system_topic_deletion(TopicValidator::RMQ_SYS_TRACE_TOPIC)
+                .valid()
+        );
+        assert!(TopicValidator::check_system_topic_deletion("user_topic").valid());
+    }
+
     #[test]
     fn is_not_allowed_send_topic_with_not_allowed_topic() {
         assert!(TopicValidator::is_not_allowed_send_topic(
@@ -257,4 +329,84 @@ mod tests {
         let not_allowed_topics = TopicValidator::get_not_allowed_send_topic_set();
         assert!(not_allowed_topics.contains(TopicValidator::RMQ_SYS_SCHEDULE_TOPIC));
     }
+
+    #[test]
+    fn validate_topic_not_pass() {
+        let result = TopicValidator::validate_topic("");
+        assert!(!result.valid());
+        assert!(result.remark().contains("The specified topic is blank"));
+
+        let result = TopicValidator::validate_topic("../TopicTest");
+        assert!(!result.valid());
+        assert!(result
+            .remark()
+            .contains("The specified topic contains illegal characters"));
+
+        let result = TopicValidator::validate_topic(&"a".repeat(128));
+        assert!(!result.valid());
+        assert!(result
+            .remark()
+            .contains("The specified topic is longer than topic max length"));
+    }
+
+    #[test]
+    fn validate_topic_pass() {
+        for topic in [
+            "TestTopic",
+            "%RETRY%group",
+            "test-topic_1",
+            "a|b",
+            &"a".repeat(127),
+        ] {
+            let result = TopicValidator::validate_topic(topic);
+            assert!(result.valid(), "{} should be valid", topic);
+            assert!(result.remark().is_empty());
+        }
+        for topic in ["test topic", "topic.1", "topic/1", "topic\u{4e2d}", " "] {
+            assert!(!TopicValidator::validate_topic(topic).valid(), "{}", topic);
+        }
+    }
+
+    #[test]
+    fn is_system_topic_for_all_registered_topics() {
+        for topic in TopicValidator::get_system_topic_set() {
+            assert!(TopicValidator::is_system_topic(topic.as_str()));
+        }
+        assert!(TopicValidator::is_system_topic(
+            format!("{}_test", TopicValidator::SYSTEM_TOPIC_PREFIX).as_str()
+        ));
+        assert!(!TopicValidator::is_system_topic("test_not_system_topic"));
+    }
+
+    #[test]
+    fn check_system_topic_reports_conflict() {
+        let result = TopicValidator::check_system_topic(TopicValidator::RMQ_SYS_TRACE_TOPIC);
+        assert!(!result.valid());
+        assert_eq!(
+            result.remark(),
+            "The topic[RMQ_SYS_TRACE_TOPIC] is conflict with system topic."
+        );
+        assert!(TopicValidator::check_system_topic("test_not_system_topic").valid());
+    }
+
+    #[test]
+    fn check_not_allowed_send_topic_reports_forbidden() {
+        for topic in TopicValidator::get_not_allowed_send_topic_set() {
+            let result = TopicValidator::check_not_allowed_send_topic(topic.as_str());
+            assert!(!result.valid());
+            assert_eq!(
+                result.remark().as_str(),
+                format!("Sending message to topic[{}] is forbidden.", topic)
+            );
+        }
+        assert!(TopicValidator::check_not_allowed_send_topic("test_allowed_topic").valid());
+    }
+
+    #[test]
+    fn add_system_topic_accepts_owned_names() {
+        let topic = String::from("DefaultCluster_SYSTEM_TOPIC_TEST");
+        TopicValidator::add_system_topic(topic.clone());
+        assert!(TopicValidator::get_system_topic_set().contains(topic.as_str()));
+        assert!(!TopicValidator::is_not_allowed_send_topic(topic.as_str()));
+    }
 }
@@ -0,0 +1,215 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use thiserror::Error;
+
+use crate::common::topic::TopicValidator;
+
+/// Reason a client configuration is refused before the client is built or started.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ValidationError {
+    #[error("the specified group is blank")]
+    BlankGroup,
+
+    #[error("the specified group is longer than group max length {max}.")]
+    GroupTooLong { max: usize },
+
+    #[error(
+        "the specified group[{0}] contains illegal characters, allowing only ^[%|a-zA-Z0-9_-]+$"
+    )]
+    IllegalGroup(CheetahString),
+
+    #[error("the specified group[{0}] is reserved, please specify another one.")]
+    ReservedGroup(CheetahString),
+
+    #[error(
+        "the name server address is not specified, set namesrvAddr or configure \
+         rocketmq.namesrv.domain to look it up"
+    )]
+    MissingNameServerAddr,
+
+    #[error("{name} Out of range [{min}, {max}]")]
+    OutOfRange {
+        name: &'static str,
+        min: i64,
+        max: i64,
+    },
+
+    #[error("consumeThreadMin ({min}) is larger than consumeThreadMax ({max})")]
+    ConsumeThreadMinOverMax { min: u32, max: u32 },
+
+    #[error("orderly consumption is not supported in BROADCASTING message model")]
+    OrderlyBroadcasting,
+}
+
+/// Client configuration checks, shared by the client builders, which fail fast, and the
+/// `start()` of producers and consumers.
+pub struct Validators;
+
+impl Validators {
+    pub const CHARACTER_MAX_LENGTH: usize = 255;
+
+    pub fn check_group(group: &str) -> Result<(), ValidationError> {
+        if group.trim().is_empty() {
+            return Err(ValidationError::BlankGroup);
+        }
+        if group.len() > Self::CHARACTER_MAX_LENGTH {
+            return Err(ValidationError::GroupTooLong {
+                max: Self::CHARACTER_MAX_LENGTH,
+            });
+        }
+        if TopicValidator::is_topic_or_group_illegal(group) {
+            return Err(ValidationError::IllegalGroup(CheetahString::from_slice(
+                group,
+            )));
+        }
+        Ok(())
+    }
+
+    /// Like [`Validators::check_group`], also refusing the `reserved` default group of the
+    /// client kind.
+    pub fn check_client_group(group: &str, reserved: &str) -> Result<(), ValidationError> {
+        Self::check_group(group)?;
+        if group == reserved {
+            return Err(ValidationError::ReservedGroup(CheetahString::from_slice(
+                group,
+            )));
+        }
+        Ok(())
+    }
+
+    /// `namesrv_addr` is the `;` separated name server list. It may only be left empty when
+    /// the addresses are looked up from the address server.
+    pub fn check_name_server_addr(
+        namesrv_addr: Option<&str>,
+        lookup_enabled: bool,
+    ) -> Result<(), ValidationError> {
+        let specified = namesrv_addr.is_some_and(|namesrv_addr| {
+            namesrv_addr.split(';').any(|addr| !addr.trim().is_empty())
+        });
+        if !specified && !lookup_enabled {
+            return Err(ValidationError::MissingNameServerAddr);
+        }
+        Ok(())
+    }
+
+    pub fn check_range(
+        name: &'static str,
+        value: i64,
+        min: i64,
+        max: i64,
+    ) -> Result<(), ValidationError> {
+        if value < min || value > max {
+            return Err(ValidationError::OutOfRange { name, min, max });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn group_rules() {
+        assert_eq!(Validators::check_group("group_A-1%|"), Ok(()));
+        assert_eq!(
+            Validators::check_group(&"g".repeat(Validators::CHARACTER_MAX_LENGTH)),
+            Ok(())
+        );
+
+        let err = Validators::check_group(" ").unwrap_err();
+        assert_eq!(err, ValidationError::BlankGroup);
+        assert_eq!(err.to_string(), "the specified group is blank");
+
+        let err = Validators::check_group(&"g".repeat(256)).unwrap_err();
+        assert_eq!(err, ValidationError::GroupTooLong { max: 255 });
+        assert_eq!(
+            err.to_string(),
+            "the specified group is longer than group max length 255."
+        );
+
+        let err = Validators::check_group("bad@group").unwrap_err();
+        assert_eq!(err, ValidationError::IllegalGroup("bad@group".into()));
+        assert_eq!(
+            err.to_string(),
+            "the specified group[bad@group] contains illegal characters, allowing only \
+             ^[%|a-zA-Z0-9_-]+$"
+        );
+    }
+
+    #[test]
+    fn client_group_refuses_reserved_group() {
+        assert_eq!(
+            Validators::check_client_group("group_a", "DEFAULT_PRODUCER"),
+            Ok(())
+        );
+        let err =
+            Validators::check_client_group("DEFAULT_PRODUCER", "DEFAULT_PRODUCER").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the specified group[DEFAULT_PRODUCER] is reserved, please specify another one."
+        );
+        assert_eq!(
+            Validators::check_client_group("", "DEFAULT_PRODUCER"),
+            Err(ValidationError::BlankGroup)
+        );
+    }
+
+    #[test]
+    fn name_server_addr_rules() {
+        assert_eq!(
+            Validators::check_name_server_addr(Some("127.0.0.1:9876;127.0.0.2:9876"), false),
+            Ok(())
+        );
+        assert_eq!(Validators::check_name_server_addr(None, true), Ok(()));
+        for namesrv_addr in [None, Some(""), Some(" ; ")] {
+            let err = Validators::check_name_server_addr(namesrv_addr, false).unwrap_err();
+            assert_eq!(err, ValidationError::MissingNameServerAddr);
+            assert_eq!(
+                err.to_string(),
+                "the name server address is not specified, set namesrvAddr or configure \
+                 rocketmq.namesrv.domain to look it up"
+            );
+        }
+    }
+
+    #[test]
+    fn range_rules() {
+        assert_eq!(Validators::check_range("pullBatchSize", 1, 1, 1024), Ok(()));
+        assert_eq!(
+            Validators::check_range("pullBatchSize", 1024, 1, 1024),
+            Ok(())
+        );
+        for value in [0, 1025] {
+            let err = Validators::check_range("pullBatchSize", value, 1, 1024).unwrap_err();
+            assert_eq!(err.to_string(), "pullBatchSize Out of range [1, 1024]");
+        }
+    }
+
+    #[test]
+    fn consumer_conflict_messages() {
+        assert_eq!(
+            ValidationError::ConsumeThreadMinOverMax { min: 30, max: 20 }.to_string(),
+            "consumeThreadMin (30) is larger than consumeThreadMax (20)"
+        );
+        assert_eq!(
+            ValidationError::OrderlyBroadcasting.to_string(),
+            "orderly consumption is not supported in BROADCASTING message model"
+        );
+    }
+}
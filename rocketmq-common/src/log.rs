@@ -14,9 +14,80 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+pub mod rolling_file_appender;
+
+use std::path::PathBuf;
 use std::str::FromStr;
 
 use tracing::level_filters::LevelFilter;
+use tracing::Metadata;
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+use tracing_subscriber::Registry;
+
+use crate::log::rolling_file_appender::RollingFileAppender;
+
+/// Target used for broker watermark logs, e.g. `info!(target: WATER_MARK_LOGGER_NAME, ...)`.
+pub const WATER_MARK_LOGGER_NAME: &str = "RocketmqWaterMark";
+/// Target used for periodic statistics logs.
+pub const STATS_LOGGER_NAME: &str = "RocketmqStats";
+/// Target used for requests a broker took too long to process.
+pub const SLOW_REQUEST_LOGGER_NAME: &str = "RocketmqSlowRequest";
+/// When set to `true`, log events are also written to stdout.
+pub const LOG_CONSOLE_ENABLE_ENV: &str = "ROCKETMQ_LOG_CONSOLE";
+
+pub const BROKER_LOG_FILE: &str = "broker.log";
+pub const STORE_LOG_FILE: &str = "store.log";
+pub const REMOTING_LOG_FILE: &str = "remoting.log";
+pub const NAMESRV_LOG_FILE: &str = "namesrv.log";
+pub const WATER_MARK_LOG_FILE: &str = "watermark.log";
+pub const STATS_LOG_FILE: &str = "stats.log";
+pub const SLOW_REQUEST_LOG_FILE: &str = "slow_request.log";
+
+/// Log file name and the targets (module path prefixes or logger names) routed to it.
+const LOG_FILE_TARGETS: &[(&str, &[&str])] = &[
+    (BROKER_LOG_FILE, &["rocketmq_broker"]),
+    (STORE_LOG_FILE, &["rocketmq_store"]),
+    (REMOTING_LOG_FILE, &["rocketmq_remoting"]),
+    (NAMESRV_LOG_FILE, &["rocketmq_namesrv"]),
+    (WATER_MARK_LOG_FILE, &[WATER_MARK_LOGGER_NAME]),
+    (STATS_LOG_FILE, &[STATS_LOGGER_NAME]),
+    (SLOW_REQUEST_LOG_FILE, &[SLOW_REQUEST_LOGGER_NAME]),
+];
+
+/// File logging configuration used by [`init_logger_with_config`].
+#[derive(Debug, Clone)]
+pub struct LoggingConfig {
+    /// Directory holding all log files, usually `$ROCKETMQ_HOME/logs`.
+    pub log_dir: PathBuf,
+    /// Log file receiving events whose target is not routed to any other file.
+    pub default_log_file: String,
+    /// Max level, e.g. `INFO`.
+    pub level: String,
+    /// Size in bytes after which the active log file is rolled.
+    pub max_file_size: u64,
+    /// Number of rolled files kept per log file.
+    pub max_history: usize,
+    /// Whether to also log to stdout.
+    pub console_enabled: bool,
+}
+
+impl LoggingConfig {
+    pub fn new(log_dir: impl Into<PathBuf>, default_log_file: impl Into<String>) -> Self {
+        Self {
+            log_dir: log_dir.into(),
+            default_log_file: default_log_file.into(),
+            level: std::env::var("RUST_LOG").unwrap_or(String::from("INFO")),
+            max_file_size: 128 * 1024 * 1024,
+            max_history: 10,
+            console_enabled: std::env::var(LOG_CONSOLE_ENABLE_ENV)
+                .map(|value| value.eq_ignore_ascii_case("true"))
+                .unwrap_or(false),
+        }
+    }
+}
 
 /// Initializes the logger with the specified configuration.
 ///
@@ -34,3 +105,111 @@ pub fn init_logger() {
         .with_max_level(LevelFilter::from_str(info_level.as_str()).unwrap())
         .init();
 }
+
+/// Initializes file based logging laid out like the Java logback configuration.
+///
+/// Events are routed by target to `broker.log`, `store.log`, `remoting.log`, `namesrv.log`,
+/// `watermark.log`, `stats.log` and `slow_request.log` under `config.log_dir`; everything else goes
+/// to `config.default_log_file`. Each file rolls daily and on `config.max_file_size`.
+pub fn init_logger_with_config(config: LoggingConfig) -> std::io::Result<()> {
+    init_logger_with_layers(config, Vec::new())
+}
+
+/// Same as [`init_logger_with_config`], with `extra_layers`, e.g. a span exporter, installed
+/// next to the file layers.
+pub fn init_logger_with_layers(
+    config: LoggingConfig,
+    extra_layers: Vec<Box<dyn Layer<Registry> + Send + Sync>>,
+) -> std::io::Result<()> {
+    let level = LevelFilter::from_str(config.level.as_str()).unwrap_or(LevelFilter::INFO);
+    let mut layers = extra_layers;
+    let mut file_names: Vec<&str> = LOG_FILE_TARGETS.iter().map(|(name, _)| *name).collect();
+    if !file_names.contains(&config.default_log_file.as_str()) {
+        file_names.push(config.default_log_file.as_str());
+    }
+    for file_name in file_names {
+        let is_default = file_name == config.default_log_file;
+        let targets = LOG_FILE_TARGETS
+            .iter()
+            .find(|(name, _)| *name == file_name)
+            .map(|(_, targets)| *targets)
+            .unwrap_or_default();
+        let appender = RollingFileAppender::new(
+            &config.log_dir,
+            file_name,
+            config.max_file_size,
+            config.max_history,
+        )?;
+        let filter = filter_fn(move |metadata: &Metadata<'_>| {
+            if *metadata.level() > level {
+                return false;
+            }
+            let target = metadata.target();
+            targets.iter().any(|prefix| target_matches(target, prefix))
+                || (is_default && route_of(target).is_none())
+        });
+        layers.push(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_thread_names(true)
+                .with_line_number(true)
+                .with_writer(appender)
+                .with_filter(filter)
+                .boxed(),
+        );
+    }
+    if config.console_enabled {
+        layers.push(
+            tracing_subscriber::fmt::layer()
+                .with_thread_names(true)
+                .with_line_number(true)
+                .with_thread_ids(true)
+                .with_filter(level)
+                .boxed(),
+        );
+    }
+    tracing_subscriber::registry()
+        .with(layers)
+        .try_init()
+        .map_err(std::io::Error::other)
+}
+
+fn target_matches(target: &str, prefix: &str) -> bool {
+    target
+        .strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+/// Returns the log file a target is routed to, if any.
+fn route_of(target: &str) -> Option<&'static str> {
+    LOG_FILE_TARGETS
+        .iter()
+        .find(|(_, targets)| targets.iter().any(|prefix| target_matches(target, prefix)))
+        .map(|(name, _)| *name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_targets_by_module_path() {
+        assert_eq!(
+            route_of("rocketmq_broker::processor::send_message_processor"),
+            Some(BROKER_LOG_FILE)
+        );
+        assert_eq!(route_of("rocketmq_store"), Some(STORE_LOG_FILE));
+        assert_eq!(
+            route_of("rocketmq_remoting::clients"),
+            Some(REMOTING_LOG_FILE)
+        );
+        assert_eq!(route_of(WATER_MARK_LOGGER_NAME), Some(WATER_MARK_LOG_FILE));
+        assert_eq!(route_of(STATS_LOGGER_NAME), Some(STATS_LOG_FILE));
+        assert_eq!(
+            route_of(SLOW_REQUEST_LOGGER_NAME),
+            Some(SLOW_REQUEST_LOG_FILE)
+        );
+        assert_eq!(route_of("rocketmq_broker_ext"), None);
+        assert_eq!(route_of("rocketmq_common::utils"), None);
+    }
+}
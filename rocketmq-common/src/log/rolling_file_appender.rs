@@ -0,0 +1,229 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use chrono::Local;
+use chrono::NaiveDate;
+use parking_lot::Mutex;
+use tracing_subscriber::fmt::MakeWriter;
+
+const ARCHIVE_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// A file appender that rolls the active log file daily and whenever it grows beyond
+/// `max_file_size` bytes, mirroring logback's `SizeAndTimeBasedRollingPolicy`.
+///
+/// The active file is always `<dir>/<file_name>` and is only created on the first write. Rolled
+/// files are renamed to `<file_name>.<yyyy-MM-dd>.<index>` and at most `max_history` of them
+/// are kept.
+pub struct RollingFileAppender {
+    dir: PathBuf,
+    file_name: String,
+    max_file_size: u64,
+    max_history: usize,
+    state: Mutex<AppenderState>,
+}
+
+struct AppenderState {
+    file: Option<File>,
+    size: u64,
+    date: NaiveDate,
+}
+
+impl RollingFileAppender {
+    pub fn new(
+        dir: impl AsRef<Path>,
+        file_name: impl Into<String>,
+        max_file_size: u64,
+        max_history: usize,
+    ) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let file_name = file_name.into();
+        fs::create_dir_all(&dir)?;
+        let size = fs::metadata(dir.join(&file_name))
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        Ok(Self {
+            dir,
+            file_name,
+            max_file_size,
+            max_history,
+            state: Mutex::new(AppenderState {
+                file: None,
+                size,
+                date: Local::now().date_naive(),
+            }),
+        })
+    }
+
+    #[inline]
+    pub fn active_file(&self) -> PathBuf {
+        self.dir.join(&self.file_name)
+    }
+
+    fn write_buf(&self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.state.lock();
+        let today = Local::now().date_naive();
+        if state.size > 0
+            && (today != state.date || state.size + buf.len() as u64 > self.max_file_size)
+        {
+            self.roll(&mut state)?;
+        }
+        state.date = today;
+        if state.file.is_none() {
+            state.file = Some(open_append(&self.active_file())?);
+        }
+        let file = state.file.as_mut().expect("active log file opened above");
+        let written = file.write(buf)?;
+        state.size += written as u64;
+        Ok(written)
+    }
+
+    fn roll(&self, state: &mut AppenderState) -> io::Result<()> {
+        if let Some(mut file) = state.file.take() {
+            file.flush()?;
+        }
+        let archive_date = state.date.format(ARCHIVE_DATE_FORMAT).to_string();
+        let next_index = self
+            .archived_files()?
+            .iter()
+            .filter(|(date, _, _)| *date == archive_date)
+            .map(|(_, index, _)| *index + 1)
+            .max()
+            .unwrap_or(0);
+        let active = self.active_file();
+        let archived = self.dir.join(format!(
+            "{}.{}.{}",
+            self.file_name, archive_date, next_index
+        ));
+        fs::rename(&active, archived)?;
+        state.size = 0;
+        self.clean_history()
+    }
+
+    fn clean_history(&self) -> io::Result<()> {
+        let mut archived = self.archived_files()?;
+        if archived.len() <= self.max_history {
+            return Ok(());
+        }
+        archived.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+        let remove = archived.len() - self.max_history;
+        for (_, _, path) in archived.into_iter().take(remove) {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Lists rolled files as `(date, index, path)` tuples.
+    fn archived_files(&self) -> io::Result<Vec<(String, u32, PathBuf)>> {
+        let prefix = format!("{}.", self.file_name);
+        let mut result = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let Some(suffix) = name.to_str().and_then(|name| name.strip_prefix(&prefix)) else {
+                continue;
+            };
+            let Some((date, index)) = suffix.rsplit_once('.') else {
+                continue;
+            };
+            if NaiveDate::parse_from_str(date, ARCHIVE_DATE_FORMAT).is_err() {
+                continue;
+            }
+            if let Ok(index) = index.parse::<u32>() {
+                result.push((date.to_string(), index, entry.path()));
+            }
+        }
+        Ok(result)
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+impl Write for &RollingFileAppender {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_buf(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.state.lock().file.as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for RollingFileAppender {
+    type Writer = &'a RollingFileAppender;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolls_when_size_limit_exceeded() {
+        let dir = tempfile::tempdir().unwrap();
+        let appender = RollingFileAppender::new(dir.path(), "broker.log", 64, 10).unwrap();
+        let line = [b'a'; 40];
+        for _ in 0..5 {
+            (&appender).write_all(&line).unwrap();
+        }
+        let archived = appender.archived_files().unwrap();
+        assert_eq!(archived.len(), 4);
+        assert_eq!(fs::metadata(appender.active_file()).unwrap().len(), 40);
+        let mut indexes: Vec<u32> = archived.iter().map(|(_, index, _)| *index).collect();
+        indexes.sort();
+        assert_eq!(indexes, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn keeps_at_most_max_history_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let appender = RollingFileAppender::new(dir.path(), "store.log", 16, 2).unwrap();
+        for i in 0..6u8 {
+            (&appender).write_all(&[b'0' + i; 16]).unwrap();
+        }
+        let archived = appender.archived_files().unwrap();
+        assert_eq!(archived.len(), 2);
+        let mut indexes: Vec<u32> = archived.iter().map(|(_, index, _)| *index).collect();
+        indexes.sort();
+        assert_eq!(indexes, vec![3, 4]);
+        assert_eq!(fs::read(appender.active_file()).unwrap(), vec![b'5'; 16]);
+    }
+
+    #[test]
+    fn ignores_unrelated_files_in_log_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("broker.log.bak"), b"x").unwrap();
+        fs::write(dir.path().join("remoting.log.2024-01-01.0"), b"x").unwrap();
+        let appender = RollingFileAppender::new(dir.path(), "broker.log", 1024, 1).unwrap();
+        assert!(appender.archived_files().unwrap().is_empty());
+        assert!(!appender.active_file().exists());
+    }
+}
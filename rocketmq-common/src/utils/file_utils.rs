@@ -50,18 +50,25 @@ pub fn file_to_string_impl(file: &File) -> Result<String, io::Error> {
     }
 }
 
+/// Replaces the content of `file_name` with `str_content`.
+///
+/// The content is written to `<file_name>.tmp` and synced first, the previous content is copied
+/// to `<file_name>.bak`, then the temp file is renamed over `file_name`. A crash at any point
+/// leaves either the previous or the new content in `file_name`, and at worst a stale temp file.
 pub fn string_to_file(str_content: &str, file_name: &str) -> io::Result<()> {
     let lock = LOCK.lock();
 
-    let bak_file = format!("{}.bak", file_name);
+    let tmp_file = format!("{}.tmp", file_name);
+    string_to_file_not_safe(str_content, &tmp_file)?;
 
-    // Read previous content and create a backup
-    if let Ok(prev_content) = file_to_string(file_name) {
-        string_to_file_not_safe(&prev_content, &bak_file)?;
+    if Path::new(file_name).exists() {
+        let bak_file = format!("{}.bak", file_name);
+        std::fs::copy(file_name, &bak_file)?;
+        File::open(&bak_file)?.sync_all()?;
     }
 
-    // Write new content to the file
-    string_to_file_not_safe(str_content, file_name)?;
+    std::fs::rename(&tmp_file, file_name)?;
+    sync_parent_dir(file_name)?;
     drop(lock);
     Ok(())
 }
@@ -73,14 +80,28 @@ fn string_to_file_not_safe(str_content: &str, file_name: &str) -> io::Result<()>
     }
     let file = File::create(file_name)?;
 
-    write_string_to_file(&file, str_content, "UTF-8")
+    write_string_to_file(&file, str_content, "UTF-8")?;
+    file.sync_all()
 }
 
 fn write_string_to_file(file: &File, data: &str, _encoding: &str) -> io::Result<()> {
     let mut os = io::BufWriter::new(file);
 
     os.write_all(data.as_bytes())?;
+    os.flush()
+}
+
+/// Makes a rename in the directory of `file_name` durable.
+#[cfg(unix)]
+fn sync_parent_dir(file_name: &str) -> io::Result<()> {
+    match Path::new(file_name).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => File::open(parent)?.sync_all(),
+        _ => Ok(()),
+    }
+}
 
+#[cfg(not(unix))]
+fn sync_parent_dir(_file_name: &str) -> io::Result<()> {
     Ok(())
 }
 
@@ -120,4 +141,22 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(std::fs::read_to_string(file_path).unwrap(), content);
     }
+
+    #[test]
+    fn string_to_file_keeps_the_previous_content_as_bak() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("config.json");
+        let file_path = file_path.to_str().unwrap();
+
+        string_to_file("first", file_path).unwrap();
+        assert!(!Path::new(&format!("{}.bak", file_path)).exists());
+        string_to_file("second", file_path).unwrap();
+
+        assert_eq!(std::fs::read_to_string(file_path).unwrap(), "second");
+        assert_eq!(
+            std::fs::read_to_string(format!("{}.bak", file_path)).unwrap(),
+            "first"
+        );
+        assert!(!Path::new(&format!("{}.tmp", file_path)).exists());
+    }
 }
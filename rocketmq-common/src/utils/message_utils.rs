@@ -18,15 +18,21 @@ use std::collections::hash_map::DefaultHasher;
 use std::collections::HashSet;
 use std::hash::Hash;
 use std::hash::Hasher;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
 use std::net::SocketAddr;
 
+use bytes::Buf;
 use bytes::BufMut;
+use bytes::Bytes;
 
 use crate::common::message::message_ext::MessageExt;
 use crate::common::message::MessageConst;
 use crate::MessageDecoder::NAME_VALUE_SEPARATOR;
 use crate::MessageDecoder::PROPERTY_SEPARATOR;
 use crate::UtilAll::bytes_to_string;
+use crate::UtilAll::string_to_bytes;
 
 pub fn get_sharding_key_index(sharding_key: &str, index_size: usize) -> usize {
     let mut hasher = DefaultHasher::new();
@@ -168,8 +174,26 @@ pub fn build_batch_message_id(
     message_id
 }
 
-pub fn parse_message_id(_msg_id: impl Into<String>) -> (SocketAddr, i64) {
-    unimplemented!()
+/// Splits an offset message id into the store host and the commit log offset. IPv4 store hosts
+/// give a 32 character id, IPv6 ones a 56 character id; anything else is rejected.
+pub fn parse_message_id(msg_id: impl Into<String>) -> Option<(SocketAddr, i64)> {
+    let msg_id = msg_id.into();
+    if (msg_id.len() != 32 && msg_id.len() != 56) || !msg_id.bytes().all(|b| b.is_ascii_hexdigit())
+    {
+        return None;
+    }
+    let mut buffer = Bytes::from(string_to_bytes(msg_id.as_str())?);
+    let ip = if msg_id.len() == 32 {
+        let mut ip = [0u8; 4];
+        buffer.copy_to_slice(&mut ip);
+        IpAddr::V4(Ipv4Addr::from(ip))
+    } else {
+        let mut ip = [0u8; 16];
+        buffer.copy_to_slice(&mut ip);
+        IpAddr::V6(Ipv6Addr::from(ip))
+    };
+    let port = buffer.get_i32();
+    Some((SocketAddr::new(ip, port as u16), buffer.get_i64()))
 }
 
 #[cfg(test)]
@@ -182,6 +206,16 @@ mod tests {
     use super::*;
     use crate::common::message::message_ext::MessageExt;
 
+    #[test]
+    fn parse_message_id_handles_both_lengths() {
+        let v4: SocketAddr = "127.0.0.1:10911".parse().unwrap();
+        let v6: SocketAddr = "[::1]:10911".parse().unwrap();
+        assert_eq!(parse_message_id(build_message_id(v4, 100)), Some((v4, 100)));
+        assert_eq!(parse_message_id(build_message_id(v6, 200)), Some((v6, 200)));
+        assert_eq!(parse_message_id("7F000001"), None);
+        assert_eq!(parse_message_id("Z".repeat(32)), None);
+    }
+
     #[test]
     fn test_get_sharding_key_index() {
         let sharding_key = "example_key";
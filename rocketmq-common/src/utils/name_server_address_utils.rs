@@ -16,6 +16,7 @@
  */
 use std::env;
 
+use cheetah_string::CheetahString;
 use lazy_static::lazy_static;
 use regex::Regex;
 
@@ -39,6 +40,34 @@ impl NameServerAddressUtils {
             .ok()
     }
 
+    /// Splits a `;` separated name server address list, dropping blank entries.
+    pub fn split_addresses(addrs: &str) -> Vec<CheetahString> {
+        addrs
+            .split(';')
+            .map(str::trim)
+            .filter(|addr| !addr.is_empty())
+            .map(CheetahString::from_slice)
+            .collect()
+    }
+
+    /// Addresses of `new` missing from `old`, followed by those of `old` missing from `new`.
+    pub fn diff_addresses(
+        old: &[CheetahString],
+        new: &[CheetahString],
+    ) -> (Vec<CheetahString>, Vec<CheetahString>) {
+        let added = new
+            .iter()
+            .filter(|addr| !old.contains(addr))
+            .cloned()
+            .collect();
+        let removed = old
+            .iter()
+            .filter(|addr| !new.contains(addr))
+            .cloned()
+            .collect();
+        (added, removed)
+    }
+
     pub fn validate_instance_endpoint(endpoint: &str) -> bool {
         INST_ENDPOINT_PATTERN.is_match(endpoint)
     }
@@ -62,3 +91,32 @@ impl NameServerAddressUtils {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_addresses_skips_blank_entries() {
+        assert_eq!(
+            NameServerAddressUtils::split_addresses(" 10.0.0.1:9876;;10.0.0.2:9876; "),
+            vec![
+                CheetahString::from_static_str("10.0.0.1:9876"),
+                CheetahString::from_static_str("10.0.0.2:9876")
+            ]
+        );
+        assert!(NameServerAddressUtils::split_addresses("").is_empty());
+    }
+
+    #[test]
+    fn diff_addresses_reports_added_and_removed() {
+        let old = NameServerAddressUtils::split_addresses("a:9876;b:9876");
+        let new = NameServerAddressUtils::split_addresses("b:9876;c:9876");
+        let (added, removed) = NameServerAddressUtils::diff_addresses(&old, &new);
+        assert_eq!(added, vec![CheetahString::from_static_str("c:9876")]);
+        assert_eq!(removed, vec![CheetahString::from_static_str("a:9876")]);
+
+        let (added, removed) = NameServerAddressUtils::diff_addresses(&new, &new);
+        assert!(added.is_empty() && removed.is_empty());
+    }
+}
@@ -14,24 +14,132 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::env;
 use std::net::IpAddr;
+use std::net::SocketAddr;
+
+/// Environment variable forcing the local address, for hosts whose preferred interface is not
+/// the one the other nodes can reach.
+pub const ROCKETMQ_LOCAL_IP_ENV: &str = "ROCKETMQ_LOCAL_IP";
 
 pub struct NetworkUtil;
 
 impl NetworkUtil {
     pub fn get_local_address() -> Option<String> {
-        match local_ip_address::local_ip() {
-            Ok(value) => match value {
-                IpAddr::V4(ip) => Some(ip.to_string()),
-                IpAddr::V6(ip) => Some(ip.to_string()),
-            },
-            Err(_) => match local_ip_address::local_ipv6() {
-                Ok(value) => match value {
-                    IpAddr::V4(ip) => Some(ip.to_string()),
-                    IpAddr::V6(ip) => Some(ip.to_string()),
-                },
-                Err(_) => None,
-            },
+        if let Some(address) = Self::local_address_override() {
+            return Some(address);
+        }
+        Self::get_local_ip().map(|ip| ip.to_string())
+    }
+
+    /// The address of this host: a non-loopback IPv4 address if there is one, else a
+    /// non-loopback IPv6 address, else a loopback address.
+    pub fn get_local_ip() -> Option<IpAddr> {
+        if let Some(ip) = Self::local_address_override().and_then(|address| address.parse().ok()) {
+            return Some(ip);
         }
+        let candidates = local_ip_address::list_afinet_netifas()
+            .map(|interfaces| interfaces.into_iter().map(|(_, ip)| ip).collect::<Vec<_>>())
+            .unwrap_or_default();
+        Self::preferred_address(candidates)
+            .or_else(|| local_ip_address::local_ip().ok())
+            .or_else(|| local_ip_address::local_ipv6().ok())
+    }
+
+    /// Builds a socket address from an IP literal and a port. IPv6 literals are accepted with or
+    /// without the surrounding brackets, so `::1` and `[::1]` both resolve to `[::1]:port`.
+    pub fn string_to_socket_address(ip: &str, port: u16) -> Option<SocketAddr> {
+        let ip = ip.trim();
+        let ip = ip
+            .strip_prefix('[')
+            .and_then(|ip| ip.strip_suffix(']'))
+            .unwrap_or(ip);
+        ip.parse::<IpAddr>()
+            .ok()
+            .map(|ip| SocketAddr::new(ip, port))
+    }
+
+    fn local_address_override() -> Option<String> {
+        env::var(ROCKETMQ_LOCAL_IP_ENV)
+            .ok()
+            .map(|address| address.trim().to_string())
+            .filter(|address| !address.is_empty())
+    }
+
+    fn preferred_address(candidates: impl IntoIterator<Item = IpAddr>) -> Option<IpAddr> {
+        candidates
+            .into_iter()
+            .filter(|ip| !ip.is_unspecified() && !ip.is_multicast())
+            .min_by_key(|ip| match ip {
+                IpAddr::V4(ip) if ip.is_loopback() => 3,
+                IpAddr::V4(ip) if ip.is_link_local() => 1,
+                IpAddr::V4(_) => 0,
+                IpAddr::V6(ip) if ip.is_loopback() => 4,
+                IpAddr::V6(_) => 2,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+    use std::net::Ipv6Addr;
+
+    use super::*;
+
+    #[test]
+    fn preferred_address_picks_non_loopback_ipv4_first() {
+        let lan = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10));
+        let v6: IpAddr = "2001:db8::1".parse().unwrap();
+        let candidates = vec![
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+            v6,
+            IpAddr::V4(Ipv4Addr::new(169, 254, 0, 1)),
+            lan,
+        ];
+        assert_eq!(NetworkUtil::preferred_address(candidates), Some(lan));
+    }
+
+    #[test]
+    fn preferred_address_falls_back_to_ipv6_then_loopback() {
+        let v6: IpAddr = "2001:db8::1".parse().unwrap();
+        assert_eq!(
+            NetworkUtil::preferred_address(vec![IpAddr::V4(Ipv4Addr::LOCALHOST), v6]),
+            Some(v6)
+        );
+        assert_eq!(
+            NetworkUtil::preferred_address(vec![
+                IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                IpAddr::V4(Ipv4Addr::LOCALHOST)
+            ]),
+            Some(IpAddr::V4(Ipv4Addr::LOCALHOST))
+        );
+        assert_eq!(NetworkUtil::preferred_address(vec![]), None);
+    }
+
+    #[test]
+    fn string_to_socket_address_accepts_both_families() {
+        assert_eq!(
+            NetworkUtil::string_to_socket_address("10.0.0.1", 10911),
+            Some("10.0.0.1:10911".parse().unwrap())
+        );
+        assert_eq!(
+            NetworkUtil::string_to_socket_address("2001:db8::1", 10911),
+            Some("[2001:db8::1]:10911".parse().unwrap())
+        );
+        assert_eq!(
+            NetworkUtil::string_to_socket_address("[::1]", 10911),
+            Some("[::1]:10911".parse().unwrap())
+        );
+        assert_eq!(
+            NetworkUtil::string_to_socket_address("broker-a", 10911),
+            None
+        );
+    }
+
+    #[test]
+    fn local_ip_is_detected() {
+        assert!(NetworkUtil::get_local_ip().is_some());
     }
 }
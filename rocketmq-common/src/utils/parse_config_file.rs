@@ -16,10 +16,14 @@
  */
 
 use std::fmt::Debug;
+use std::path::Path;
 use std::path::PathBuf;
 
 use config::Config;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
 
 pub fn parse_config_file<'de, C>(config_file: PathBuf) -> anyhow::Result<C, anyhow::Error>
 where
@@ -36,3 +40,64 @@ where
     //info!("parse config: {:?}", config_file);
     Ok(config_file)
 }
+
+/// Reads the TOML `config_file` over the default value of `C`, so the file only needs the
+/// items that differ from the defaults. Unlike [`parse_config_file`], a missing or malformed
+/// file is an error.
+pub fn parse_config_file_over_default<C>(config_file: &Path) -> anyhow::Result<C>
+where
+    C: Default + Serialize + DeserializeOwned,
+{
+    let content = std::fs::read_to_string(config_file)?;
+    let mut config = serde_json::to_value(C::default())?;
+    merge(&mut config, toml::from_str::<Value>(&content)?);
+    Ok(serde_json::from_value(config)?)
+}
+
+/// Overwrites the items of `target` set in `source`, tables are merged item by item.
+fn merge(target: &mut Value, source: Value) {
+    match (target, source) {
+        (Value::Object(target), Value::Object(source)) => {
+            for (key, value) in source {
+                match target.get_mut(&key) {
+                    Some(item) => merge(item, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, source) => *target = source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::broker::broker_config::BrokerConfig;
+
+    #[test]
+    fn file_items_override_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broker-a.toml");
+        std::fs::write(
+            &path,
+            "listenPort = 30911\n\n[brokerIdentity]\nbrokerName = \"broker-a\"\nbrokerId = 1\n",
+        )
+        .unwrap();
+        let config = parse_config_file_over_default::<BrokerConfig>(&path).unwrap();
+        let default = BrokerConfig::default();
+        assert_eq!(config.listen_port, 30911);
+        assert_eq!(config.broker_identity.broker_name.as_str(), "broker-a");
+        assert_eq!(config.broker_identity.broker_id, 1);
+        assert_eq!(
+            config.broker_identity.broker_cluster_name,
+            default.broker_identity.broker_cluster_name
+        );
+        assert_eq!(config.store_path_root_dir, default.store_path_root_dir);
+
+        assert!(
+            parse_config_file_over_default::<BrokerConfig>(&dir.path().join("none.toml")).is_err()
+        );
+    }
+}
@@ -15,20 +15,16 @@
  * limitations under the License.
  */
 
+use crate::common::system_clock::since_epoch;
+
 #[inline]
 pub fn get_current_millis() -> u64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as u64
+    since_epoch().as_millis() as u64
 }
 
 #[inline]
 pub fn get_current_nano() -> u64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_nanos() as u64
+    since_epoch().as_nanos() as u64
 }
 
 #[cfg(test)]
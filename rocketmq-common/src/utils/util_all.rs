@@ -42,11 +42,12 @@ use tracing::info;
 
 use crate::common::mix_all::MULTI_PATH_SPLITTER;
 use crate::error::Error::RuntimeException;
+use crate::utils::network_util::NetworkUtil;
 use crate::Result;
 
-pub const YYYY_MM_DD_HH_MM_SS: &str = "%Y-%m-%d %H:%M:%S%";
+pub const YYYY_MM_DD_HH_MM_SS: &str = "%Y-%m-%d %H:%M:%S";
 pub const YYYY_MM_DD_HH_MM_SS_SSS: &str = "%Y-%m-%d %H:%M:%S%.f";
-pub const YYYYMMDDHHMMSS: &str = "%Y%m%d%H%M%S%";
+pub const YYYYMMDDHHMMSS: &str = "%Y%m%d%H%M%S";
 
 const HEX_ARRAY: [char; 16] = [
     '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'A', 'B', 'C', 'D', 'E', 'F',
@@ -57,18 +58,17 @@ pub fn compute_elapsed_time_milliseconds(begin_time: Instant) -> u64 {
     elapsed.as_millis() as u64
 }
 
+/// Whether the current local hour is one of the `;` separated hours in `when`, e.g. `"04;16"`.
 pub fn is_it_time_to_do(when: &str) -> bool {
-    let hours: Vec<&str> = when.split(";").collect();
-    if !hours.is_empty() {
-        let now = Local::now();
-        for hour in hours {
-            let now_hour: i32 = hour.parse().unwrap_or(0);
-            if now_hour == now.hour() as i32 {
-                return true;
-            }
-        }
-    }
-    false
+    is_it_time_to_do_at(when, Local::now().hour())
+}
+
+/// Whether `hour` is one of the `;` separated hours in `when`, entries that are not a number
+/// never match.
+pub fn is_it_time_to_do_at(when: &str, hour: u32) -> bool {
+    when.split(';')
+        .filter_map(|entry| entry.trim().parse::<u32>().ok())
+        .any(|entry| entry == hour)
 }
 
 pub fn time_millis_to_human_string2(t: i64) -> String {
@@ -107,6 +107,8 @@ pub fn is_path_exists(path: &str) -> bool {
     Path::new(path).exists()
 }
 
+/// Returns the used ratio (`0.0..=1.0`) of the disk partition holding `path`, or `-1.0` if it
+/// cannot be measured.
 pub fn get_disk_partition_space_used_percent(path: &str) -> f64 {
     if path.is_empty() {
         error!(
@@ -116,53 +118,147 @@ pub fn get_disk_partition_space_used_percent(path: &str) -> f64 {
         return -1.0;
     }
 
-    let path = Path::new(path);
-    if !path.exists() {
+    let path = match fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(e) => {
+            error!(
+                "Error when measuring disk space usage, file doesn't exist on this path: {}, {:?}",
+                path, e
+            );
+            return -1.0;
+        }
+    };
+
+    let Some(space) = disk_space(&path) else {
         error!(
-            "Error when measuring disk space usage, file doesn't exist on this path: {}",
-            path.to_string_lossy()
+            "Error when measuring disk space usage, got exception: {}",
+            io::Error::last_os_error()
         );
         return -1.0;
-    }
+    };
+    space.used_ratio()
+}
 
-    match fs::metadata(path) {
-        Ok(metadata) => {
-            let total_space = metadata.len();
-            if total_space > 0 {
-                match (fs::metadata(path), fs::metadata(path)) {
-                    (Ok(metadata1), Ok(metadata2)) => {
-                        let free_space = metadata1.len();
-                        let usable_space = metadata2.len();
-                        let used_space = total_space.saturating_sub(free_space);
-                        let entire_space = used_space + usable_space;
-                        let round_num = if used_space * 100 % entire_space != 0 {
-                            1
-                        } else {
-                            0
-                        };
-                        let result = used_space * 100 / entire_space + round_num;
-                        return result as f64 / 100.0;
-                    }
-                    (Err(e), _) | (_, Err(e)) => {
-                        error!(
-                            "Error when measuring disk space usage, got exception: {:?}",
-                            e
-                        );
-                        return -1.0;
-                    }
-                }
-            }
-        }
-        Err(e) => {
-            error!(
-                "Error when measuring disk space usage, got exception: {:?}",
-                e
-            );
+/// Space of a disk partition in bytes, as reported by the operating system.
+struct DiskSpace {
+    total: u64,
+    free: u64,
+    /// Free space available to unprivileged users, smaller than `free` by the reserved blocks.
+    usable: u64,
+}
+
+impl DiskSpace {
+    /// The used ratio rounded up to the next percent, the reserved blocks not counting as free.
+    fn used_ratio(&self) -> f64 {
+        let used = self.total.saturating_sub(self.free);
+        let entire = used + self.usable;
+        if entire == 0 {
             return -1.0;
         }
+        let round_num = if used * 100 % entire != 0 { 1 } else { 0 };
+        (used * 100 / entire + round_num) as f64 / 100.0
     }
+}
+
+#[cfg(unix)]
+fn disk_space(path: &Path) -> Option<DiskSpace> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is a valid NUL terminated string and `stat` is a properly sized buffer
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let block_size = stat.f_frsize as u64;
+    Some(DiskSpace {
+        total: stat.f_blocks as u64 * block_size,
+        free: stat.f_bfree as u64 * block_size,
+        usable: stat.f_bavail as u64 * block_size,
+    })
+}
+
+#[cfg(windows)]
+fn disk_space(path: &Path) -> Option<DiskSpace> {
+    use std::os::windows::ffi::OsStrExt;
+
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let path: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let (mut usable, mut total, mut free) = (0u64, 0u64, 0u64);
+    // SAFETY: `path` is NUL terminated and the out pointers are valid for writes
+    if unsafe { GetDiskFreeSpaceExW(path.as_ptr(), &mut usable, &mut total, &mut free) } == 0 {
+        return None;
+    }
+    Some(DiskSpace {
+        total,
+        free,
+        usable,
+    })
+}
 
-    -1.0
+#[cfg(not(any(unix, windows)))]
+fn disk_space(_path: &Path) -> Option<DiskSpace> {
+    None
+}
+
+/// Tries to take an exclusive advisory lock on `file` without blocking. `Ok(false)` when another
+/// open file, in this or another process, holds the lock. The lock is released when the file is
+/// closed.
+#[cfg(unix)]
+pub fn try_lock_file(file: &fs::File) -> io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: the descriptor stays open for the duration of the call
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+    let err = io::Error::last_os_error();
+    if err.kind() == io::ErrorKind::WouldBlock {
+        Ok(false)
+    } else {
+        Err(err)
+    }
+}
+
+#[cfg(windows)]
+pub fn try_lock_file(file: &fs::File) -> io::Result<bool> {
+    use std::os::windows::io::AsRawHandle;
+
+    use windows_sys::Win32::Foundation::ERROR_LOCK_VIOLATION;
+    use windows_sys::Win32::Storage::FileSystem::LockFileEx;
+    use windows_sys::Win32::Storage::FileSystem::LOCKFILE_EXCLUSIVE_LOCK;
+    use windows_sys::Win32::Storage::FileSystem::LOCKFILE_FAIL_IMMEDIATELY;
+    use windows_sys::Win32::System::IO::OVERLAPPED;
+
+    // SAFETY: an all zero OVERLAPPED locks from offset 0
+    let mut overlapped: OVERLAPPED = unsafe { std::mem::zeroed() };
+    // SAFETY: the handle stays open for the duration of the call and `overlapped` is valid
+    let locked = unsafe {
+        LockFileEx(
+            file.as_raw_handle() as _,
+            LOCKFILE_EXCLUSIVE_LOCK | LOCKFILE_FAIL_IMMEDIATELY,
+            0,
+            u32::MAX,
+            u32::MAX,
+            &mut overlapped,
+        )
+    };
+    if locked != 0 {
+        return Ok(true);
+    }
+    let err = io::Error::last_os_error();
+    if err.raw_os_error() == Some(ERROR_LOCK_VIOLATION as i32) {
+        Ok(false)
+    } else {
+        Err(err)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn try_lock_file(_file: &fs::File) -> io::Result<bool> {
+    Ok(true)
 }
 
 pub fn bytes_to_string(src: &[u8]) -> String {
@@ -221,6 +317,16 @@ pub fn offset_to_file_name(offset: u64) -> String {
     format!("{:020}", offset)
 }
 
+/// The start offset encoded in a file name made by [`offset_to_file_name`], the name may be
+/// given with its directory.
+pub fn file_name_to_offset(file_name: &str) -> Option<u64> {
+    let name = Path::new(file_name).file_name()?.to_str()?;
+    if name.len() != 20 || !name.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    name.parse().ok()
+}
+
 pub fn ensure_dir_ok(dir_name: &str) {
     if !dir_name.is_empty() {
         let multi_path_splitter = MULTI_PATH_SPLITTER.as_str();
@@ -284,18 +390,10 @@ pub fn delete_empty_directory<P: AsRef<Path>>(path: P) {
 }
 
 pub fn get_ip() -> Result<Vec<u8>> {
-    match local_ip_address::local_ip() {
-        Ok(value) => match value {
-            IpAddr::V4(ip) => Ok(ip.octets().to_vec()),
-            IpAddr::V6(ip) => Ok(ip.octets().to_vec()),
-        },
-        Err(_) => match local_ip_address::local_ipv6() {
-            Ok(value) => match value {
-                IpAddr::V4(ip) => Ok(ip.octets().to_vec()),
-                IpAddr::V6(ip) => Ok(ip.octets().to_vec()),
-            },
-            Err(value) => Err(RuntimeException(value.to_string())),
-        },
+    match NetworkUtil::get_local_ip() {
+        Some(IpAddr::V4(ip)) => Ok(ip.octets().to_vec()),
+        Some(IpAddr::V6(ip)) => Ok(ip.octets().to_vec()),
+        None => Err(RuntimeException("no local ip address found".to_string())),
     }
 }
 
@@ -332,6 +430,16 @@ mod tests {
         assert_eq!(is_it_time_to_do(&current_hour.to_string()), false);
     }
 
+    #[test]
+    fn is_it_time_to_do_at_matches_listed_hours_only() {
+        assert!(is_it_time_to_do_at("04", 4));
+        assert!(is_it_time_to_do_at("04;16", 16));
+        assert!(is_it_time_to_do_at(" 4 ; 16 ", 4));
+        assert!(!is_it_time_to_do_at("04;16", 5));
+        assert!(!is_it_time_to_do_at("", 0));
+        assert!(!is_it_time_to_do_at("abc;", 0));
+    }
+
     #[test]
     fn time_millis_to_human_string_formats_correctly() {
         let timestamp = 1625140800000; // 2021-07-01T12:00:00Z
@@ -359,6 +467,52 @@ mod tests {
         assert_eq!(offset_to_file_name(123), "00000000000000000123");
     }
 
+    #[test]
+    fn file_name_to_offset_reverses_offset_to_file_name() {
+        for offset in [0, 1024, 1 << 30, u64::MAX] {
+            assert_eq!(
+                file_name_to_offset(&offset_to_file_name(offset)),
+                Some(offset)
+            );
+        }
+        assert_eq!(
+            file_name_to_offset("/store/commitlog/00000000001073741824"),
+            Some(1 << 30)
+        );
+        assert_eq!(file_name_to_offset("123"), None);
+        assert_eq!(file_name_to_offset("0000000000000000012x"), None);
+        assert_eq!(file_name_to_offset("99999999999999999999"), None);
+    }
+
+    #[test]
+    fn disk_used_ratio_rounds_up_and_ignores_reserved_blocks() {
+        let space = DiskSpace {
+            total: 1000,
+            free: 500,
+            usable: 500,
+        };
+        assert_eq!(space.used_ratio(), 0.5);
+        // 100 reserved blocks are neither used nor usable
+        let space = DiskSpace {
+            total: 1000,
+            free: 600,
+            usable: 500,
+        };
+        assert_eq!(space.used_ratio(), 0.45);
+        let space = DiskSpace {
+            total: 1000,
+            free: 499,
+            usable: 499,
+        };
+        assert_eq!(space.used_ratio(), 0.51);
+        let space = DiskSpace {
+            total: 0,
+            free: 0,
+            usable: 0,
+        };
+        assert_eq!(space.used_ratio(), -1.0);
+    }
+
     #[test]
     fn ensure_dir_ok_creates_directory_if_not_exists() {
         let dir_name = "./test_dir";
@@ -379,6 +533,39 @@ mod tests {
         assert_eq!(next_minute % (60 * 1000), 0);
     }
 
+    #[test]
+    fn get_disk_partition_space_used_percent_measures_existing_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let ratio = get_disk_partition_space_used_percent(dir.path().to_str().unwrap());
+        assert!((0.0..=1.0).contains(&ratio), "ratio {ratio}");
+
+        assert_eq!(get_disk_partition_space_used_percent(""), -1.0);
+        let missing = dir.path().join("missing");
+        assert_eq!(
+            get_disk_partition_space_used_percent(missing.to_str().unwrap()),
+            -1.0
+        );
+    }
+
+    #[test]
+    fn try_lock_file_is_exclusive_until_the_holder_is_closed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lock");
+        let open = || {
+            fs::OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(&path)
+                .unwrap()
+        };
+        let holder = open();
+        assert!(try_lock_file(&holder).unwrap());
+        assert!(!try_lock_file(&open()).unwrap());
+        drop(holder);
+        assert!(try_lock_file(&open()).unwrap());
+    }
+
     /*    #[test]
     fn compute_next_morning_time_millis_returns_correct_time() {
         let now = Local::now();
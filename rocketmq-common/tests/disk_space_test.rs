@@ -0,0 +1,50 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Measures real disks, so it only runs where the platform call is implemented.
+
+#![cfg(any(unix, windows))]
+
+use std::fs;
+
+use rocketmq_common::UtilAll::get_disk_partition_space_used_percent;
+
+#[test]
+fn disk_space_used_ratio_of_existing_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    let ratio = get_disk_partition_space_used_percent(dir.path().to_str().unwrap());
+    assert!((0.0..=1.0).contains(&ratio), "ratio {ratio}");
+
+    // a file and its directory live on the same partition
+    let file = dir.path().join("data");
+    fs::write(&file, b"data").unwrap();
+    let file_ratio = get_disk_partition_space_used_percent(file.to_str().unwrap());
+    assert!(
+        (file_ratio - ratio).abs() <= 0.01,
+        "{file_ratio} vs {ratio}"
+    );
+}
+
+#[test]
+fn disk_space_of_missing_path_is_unknown() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("missing");
+    assert_eq!(
+        get_disk_partition_space_used_percent(missing.to_str().unwrap()),
+        -1.0
+    );
+}
@@ -0,0 +1,159 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Load generators for measuring the broker and client send/consume paths, mirroring the
+//! benchmark tools shipped with the Java distribution.
+
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use tokio::task::JoinHandle;
+
+pub mod consumer;
+pub mod producer;
+
+/// Request counters shared by the benchmark tasks, `rt` being the latency in milliseconds of
+/// one successful request.
+#[derive(Default)]
+pub struct StatsBenchmark {
+    success_count: AtomicU64,
+    failed_count: AtomicU64,
+    rt_total: AtomicU64,
+    rt_max: AtomicU64,
+}
+
+/// A point-in-time copy of [`StatsBenchmark`].
+#[derive(Debug, Clone, Copy)]
+pub struct Snapshot {
+    pub timestamp: Instant,
+    pub success_count: u64,
+    pub failed_count: u64,
+    pub rt_total: u64,
+    pub rt_max: u64,
+}
+
+impl StatsBenchmark {
+    pub fn record_success(&self, rt: u64) {
+        self.success_count.fetch_add(1, Ordering::Relaxed);
+        self.rt_total.fetch_add(rt, Ordering::Relaxed);
+        self.rt_max.fetch_max(rt, Ordering::Relaxed);
+    }
+
+    pub fn record_failure(&self) {
+        self.failed_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            timestamp: Instant::now(),
+            success_count: self.success_count.load(Ordering::Relaxed),
+            failed_count: self.failed_count.load(Ordering::Relaxed),
+            rt_total: self.rt_total.load(Ordering::Relaxed),
+            rt_max: self.rt_max.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Prints the rates between consecutive snapshots once a second with `report`, which is
+    /// handed the tps, the average rt and the snapshot closing the interval.
+    pub fn spawn_reporter(
+        self: &Arc<Self>,
+        report: fn(u64, f64, &Snapshot, &Snapshot),
+    ) -> JoinHandle<()> {
+        let stats = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(1));
+            interval.tick().await;
+            let mut begin = stats.snapshot();
+            loop {
+                interval.tick().await;
+                let end = stats.snapshot();
+                let success = end.success_count - begin.success_count;
+                let elapsed_millis = (end.timestamp - begin.timestamp).as_millis().max(1) as u64;
+                let tps = success * 1000 / elapsed_millis;
+                let average_rt = if success == 0 {
+                    0.0
+                } else {
+                    (end.rt_total - begin.rt_total) as f64 / success as f64
+                };
+                report(tps, average_rt, &begin, &end);
+                begin = end;
+            }
+        })
+    }
+
+    pub fn summary(&self, role: &'static str, elapsed: Duration) -> BenchmarkSummary {
+        let snapshot = self.snapshot();
+        BenchmarkSummary {
+            role,
+            elapsed,
+            success_count: snapshot.success_count,
+            failed_count: snapshot.failed_count,
+            average_rt: if snapshot.success_count == 0 {
+                0.0
+            } else {
+                snapshot.rt_total as f64 / snapshot.success_count as f64
+            },
+            max_rt: snapshot.rt_max,
+        }
+    }
+}
+
+/// Totals of a whole benchmark run, printed as CSV at exit.
+#[derive(Debug, Clone)]
+pub struct BenchmarkSummary {
+    pub role: &'static str,
+    pub elapsed: Duration,
+    pub success_count: u64,
+    pub failed_count: u64,
+    pub average_rt: f64,
+    pub max_rt: u64,
+}
+
+impl BenchmarkSummary {
+    pub const CSV_HEADER: &'static str =
+        "role,duration_ms,success_count,failed_count,tps,average_rt_ms,max_rt_ms";
+
+    pub fn tps(&self) -> f64 {
+        self.success_count as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{}\n{},{},{},{},{:.1},{:.3},{}",
+            Self::CSV_HEADER,
+            self.role,
+            self.elapsed.as_millis(),
+            self.success_count,
+            self.failed_count,
+            self.tps(),
+            self.average_rt,
+            self.max_rt
+        )
+    }
+}
+
+/// Runs for `duration` seconds, or until interrupted when no duration is given.
+pub(crate) async fn wait_until_done(duration: Option<u64>) {
+    match duration {
+        Some(duration) => tokio::time::sleep(Duration::from_secs(duration)).await,
+        None => {
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
@@ -0,0 +1,136 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use cheetah_string::CheetahString;
+use clap::Parser;
+use rocketmq_client_rust::base::client_config::ClientConfig;
+use rocketmq_client_rust::consumer::default_mq_push_consumer::DefaultMQPushConsumer;
+use rocketmq_client_rust::consumer::listener::consume_concurrently_context::ConsumeConcurrentlyContext;
+use rocketmq_client_rust::consumer::listener::consume_concurrently_status::ConsumeConcurrentlyStatus;
+use rocketmq_client_rust::consumer::listener::message_listener_concurrently::MessageListenerConcurrently;
+use rocketmq_client_rust::consumer::mq_push_consumer::MQPushConsumer;
+use rocketmq_common::common::consumer::consume_from_where::ConsumeFromWhere;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::TimeUtils::get_current_millis;
+
+use crate::benchmark::wait_until_done;
+use crate::benchmark::BenchmarkSummary;
+use crate::benchmark::Snapshot;
+use crate::benchmark::StatsBenchmark;
+
+#[derive(Parser, Debug, Clone)]
+#[command(
+    name = "benchmark_consumer",
+    about = "Consume messages and measure their delay"
+)]
+pub struct ConsumerArgs {
+    #[arg(
+        short = 'n',
+        long,
+        default_value = "127.0.0.1:9876",
+        help = "name server address list"
+    )]
+    pub namesrv_addr: String,
+
+    #[arg(
+        short = 't',
+        long,
+        default_value = "BenchmarkTest",
+        help = "topic name"
+    )]
+    pub topic: String,
+
+    #[arg(
+        short = 'g',
+        long,
+        default_value = "benchmark_consumer",
+        help = "consumer group"
+    )]
+    pub group: String,
+
+    #[arg(
+        short = 'e',
+        long,
+        default_value = "*",
+        help = "tag expression to subscribe with"
+    )]
+    pub expression: String,
+
+    #[arg(long, help = "seconds to run for, until interrupted if not set")]
+    pub duration: Option<u64>,
+}
+
+/// Consumes until the run is over, reporting the consume rate and the delay from the born
+/// timestamp of every message once a second.
+pub async fn run(args: ConsumerArgs) -> rocketmq_client_rust::Result<BenchmarkSummary> {
+    let client_config = ClientConfig {
+        namesrv_addr: Some(CheetahString::from_string(args.namesrv_addr.clone())),
+        instance_name: CheetahString::from_string(format!(
+            "benchmark_consumer_{}",
+            std::process::id()
+        )),
+        ..Default::default()
+    };
+    let stats = Arc::new(StatsBenchmark::default());
+    let mut consumer = DefaultMQPushConsumer::builder()
+        .client_config(client_config)
+        .consumer_group(args.group.as_str())
+        .consume_from_where(ConsumeFromWhere::ConsumeFromFirstOffset)
+        .message_listener_concurrently(DelayListener {
+            stats: stats.clone(),
+        })
+        .build()?;
+    consumer.subscribe(args.topic.as_str(), args.expression.as_str())?;
+    consumer.start().await?;
+
+    let reporter = stats.spawn_reporter(report);
+    let start = Instant::now();
+    wait_until_done(args.duration).await;
+    reporter.abort();
+    let summary = stats.summary("consumer", start.elapsed());
+    consumer.shutdown().await;
+    Ok(summary)
+}
+
+struct DelayListener {
+    stats: Arc<StatsBenchmark>,
+}
+
+impl MessageListenerConcurrently for DelayListener {
+    fn consume_message(
+        &self,
+        msgs: &[&MessageExt],
+        _context: &mut ConsumeConcurrentlyContext,
+    ) -> rocketmq_client_rust::Result<ConsumeConcurrentlyStatus> {
+        let now = get_current_millis() as i64;
+        for msg in msgs {
+            self.stats
+                .record_success((now - msg.born_timestamp).max(0) as u64);
+        }
+        Ok(ConsumeConcurrentlyStatus::ConsumeSuccess)
+    }
+}
+
+fn report(tps: u64, average_rt: f64, _begin: &Snapshot, end: &Snapshot) {
+    println!(
+        "Consume TPS: {} Average(B2C) RT(ms): {:7.3} MAX(B2C) RT(ms): {}",
+        tps, average_rt, end.rt_max
+    );
+}
@@ -0,0 +1,195 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Instant;
+
+use cheetah_string::CheetahString;
+use clap::Parser;
+use clap::ValueEnum;
+use rocketmq_client_rust::base::client_config::ClientConfig;
+use rocketmq_client_rust::producer::default_mq_producer::DefaultMQProducer;
+use rocketmq_client_rust::producer::mq_producer::MQProducer;
+use rocketmq_common::common::message::message_single::Message;
+use tokio::sync::Semaphore;
+
+use crate::benchmark::wait_until_done;
+use crate::benchmark::BenchmarkSummary;
+use crate::benchmark::Snapshot;
+use crate::benchmark::StatsBenchmark;
+
+/// Upper bound of the asynchronous sends awaiting their callback at once.
+const MAX_IN_FLIGHT_ASYNC_SENDS: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SendMode {
+    Sync,
+    Async,
+    Oneway,
+}
+
+#[derive(Parser, Debug, Clone)]
+#[command(
+    name = "benchmark_producer",
+    about = "Send messages as fast as possible"
+)]
+pub struct ProducerArgs {
+    #[arg(
+        short = 'n',
+        long,
+        default_value = "127.0.0.1:9876",
+        help = "name server address list"
+    )]
+    pub namesrv_addr: String,
+
+    #[arg(
+        short = 't',
+        long,
+        default_value = "BenchmarkTest",
+        help = "topic name"
+    )]
+    pub topic: String,
+
+    #[arg(
+        short = 'g',
+        long,
+        default_value = "benchmark_producer",
+        help = "producer group"
+    )]
+    pub group: String,
+
+    #[arg(
+        short = 'w',
+        long,
+        default_value_t = 64,
+        help = "number of sending tasks"
+    )]
+    pub threads: usize,
+
+    #[arg(
+        short = 's',
+        long,
+        default_value_t = 128,
+        help = "message body size in bytes"
+    )]
+    pub message_size: usize,
+
+    #[arg(short = 'k', long, help = "set a distinct key on every message")]
+    pub keys: bool,
+
+    #[arg(long, value_enum, default_value_t = SendMode::Sync, help = "send mode")]
+    pub mode: SendMode,
+
+    #[arg(long, help = "seconds to run for, until interrupted if not set")]
+    pub duration: Option<u64>,
+}
+
+/// Sends from `args.threads` tasks until the run is over, reporting the send rate every
+/// second.
+pub async fn run(args: ProducerArgs) -> rocketmq_client_rust::Result<BenchmarkSummary> {
+    let client_config = ClientConfig {
+        namesrv_addr: Some(CheetahString::from_string(args.namesrv_addr.clone())),
+        instance_name: CheetahString::from_string(format!(
+            "benchmark_producer_{}",
+            std::process::id()
+        )),
+        ..Default::default()
+    };
+    let mut producer = DefaultMQProducer::builder()
+        .client_config(client_config)
+        .producer_group(args.group.as_str())
+        .build()?;
+    producer.start().await?;
+
+    let stats = Arc::new(StatsBenchmark::default());
+    let reporter = stats.spawn_reporter(report);
+    let running = Arc::new(AtomicBool::new(true));
+    let next_key = Arc::new(AtomicU64::new(0));
+    let in_flight = Arc::new(Semaphore::new(MAX_IN_FLIGHT_ASYNC_SENDS));
+    let body = vec![b'a'; args.message_size];
+    let start = Instant::now();
+    let mut tasks = Vec::with_capacity(args.threads);
+    for _ in 0..args.threads {
+        let mut producer = producer.clone();
+        let stats = stats.clone();
+        let running = running.clone();
+        let next_key = next_key.clone();
+        let in_flight = in_flight.clone();
+        let args = args.clone();
+        let body = body.clone();
+        tasks.push(tokio::spawn(async move {
+            while running.load(Ordering::Relaxed) {
+                let mut message = Message::new(args.topic.as_str(), &body);
+                if args.keys {
+                    let key = next_key.fetch_add(1, Ordering::Relaxed);
+                    message.set_keys(CheetahString::from_string(key.to_string()));
+                }
+                let begin = Instant::now();
+                match args.mode {
+                    SendMode::Sync => match producer.send(message).await {
+                        Ok(_) => stats.record_success(begin.elapsed().as_millis() as u64),
+                        Err(_) => stats.record_failure(),
+                    },
+                    SendMode::Oneway => match producer.send_oneway(message).await {
+                        Ok(_) => stats.record_success(begin.elapsed().as_millis() as u64),
+                        Err(_) => stats.record_failure(),
+                    },
+                    SendMode::Async => {
+                        in_flight.acquire().await.unwrap().forget();
+                        let callback_stats = stats.clone();
+                        let callback_in_flight = in_flight.clone();
+                        let result = producer
+                            .send_with_callback(message, move |send_result, _error| {
+                                if send_result.is_some() {
+                                    callback_stats
+                                        .record_success(begin.elapsed().as_millis() as u64);
+                                } else {
+                                    callback_stats.record_failure();
+                                }
+                                callback_in_flight.add_permits(1);
+                            })
+                            .await;
+                        if result.is_err() {
+                            stats.record_failure();
+                            in_flight.add_permits(1);
+                        }
+                    }
+                }
+            }
+        }));
+    }
+
+    wait_until_done(args.duration).await;
+    running.store(false, Ordering::Relaxed);
+    for task in tasks {
+        let _ = task.await;
+    }
+    reporter.abort();
+    let summary = stats.summary("producer", start.elapsed());
+    producer.shutdown().await;
+    Ok(summary)
+}
+
+fn report(tps: u64, average_rt: f64, _begin: &Snapshot, end: &Snapshot) {
+    println!(
+        "Send TPS: {} Max RT(ms): {} Average RT(ms): {:7.3} Send Failed: {}",
+        tps, end.rt_max, average_rt, end.failed_count
+    );
+}
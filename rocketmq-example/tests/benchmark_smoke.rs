@@ -0,0 +1,270 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::BytesMut;
+use cheetah_string::CheetahString;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::common::mix_all;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_example::benchmark::consumer;
+use rocketmq_example::benchmark::consumer::ConsumerArgs;
+use rocketmq_example::benchmark::producer;
+use rocketmq_example::benchmark::producer::ProducerArgs;
+use rocketmq_example::benchmark::producer::SendMode;
+use rocketmq_example::benchmark::BenchmarkSummary;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::body::get_consumer_listby_group_response_body::GetConsumerListByGroupResponseBody;
+use rocketmq_remoting::protocol::header::get_max_offset_request_header::GetMaxOffsetRequestHeader;
+use rocketmq_remoting::protocol::header::get_max_offset_response_header::GetMaxOffsetResponseHeader;
+use rocketmq_remoting::protocol::header::message_operation_header::send_message_request_header::parse_request_header;
+use rocketmq_remoting::protocol::header::message_operation_header::send_message_response_header::SendMessageResponseHeader;
+use rocketmq_remoting::protocol::header::pull_message_request_header::PullMessageRequestHeader;
+use rocketmq_remoting::protocol::header::pull_message_response_header::PullMessageResponseHeader;
+use rocketmq_remoting::protocol::heartbeat::heartbeat_data::HeartbeatData;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::RemotingDeserializable;
+use rocketmq_remoting::protocol::RemotingSerializable;
+use rocketmq_remoting::test_util::FakeBroker;
+
+const QUEUE_NUMS: usize = 4;
+
+/// A fake broker keeping every topic's messages in memory.
+#[derive(Clone)]
+struct InProcessBroker {
+    addr: CheetahString,
+    queues: Arc<parking_lot::Mutex<Vec<Vec<MessageExt>>>>,
+    client_ids: Arc<parking_lot::Mutex<HashSet<CheetahString>>>,
+}
+
+impl InProcessBroker {
+    async fn start() -> Self {
+        let fake_broker = FakeBroker::bind().await.queue_nums(QUEUE_NUMS as u32);
+        let broker = InProcessBroker {
+            addr: fake_broker.addr(),
+            queues: Arc::new(parking_lot::Mutex::new(vec![Vec::new(); QUEUE_NUMS])),
+            client_ids: Default::default(),
+        };
+        let (send, send_v2, pull, heartbeat, consumer_list, max_offset) = (
+            broker.clone(),
+            broker.clone(),
+            broker.clone(),
+            broker.clone(),
+            broker.clone(),
+            broker.clone(),
+        );
+        fake_broker
+            .on(RequestCode::SendMessage, move |_, request| {
+                send.send(request)
+            })
+            .on(RequestCode::SendMessageV2, move |_, request| {
+                send_v2.send(request)
+            })
+            .on_async(RequestCode::PullMessage, move |_, request| {
+                let pull = pull.clone();
+                async move { pull.pull(&request).await }
+            })
+            .on(RequestCode::HeartBeat, move |_, request| {
+                heartbeat.heartbeat(request)
+            })
+            .on(RequestCode::GetConsumerListByGroup, move |_, _| {
+                consumer_list.consumer_list()
+            })
+            .on(RequestCode::GetMaxOffset, move |_, request| {
+                max_offset.max_offset(request)
+            })
+            .on(RequestCode::QueryConsumerOffset, |_, _| {
+                RemotingCommand::create_response_command_with_code(ResponseCode::QueryNotFound)
+            })
+            .spawn();
+        broker
+    }
+
+    fn store(&self, topic: &str, queue_id: usize, body: bytes::Bytes, born_timestamp: i64) -> i64 {
+        let mut queues = self.queues.lock();
+        let commit_log_offset = queues.iter().map(Vec::len).sum::<usize>() as i64;
+        let queue = &mut queues[queue_id];
+        let mut message_ext = MessageExt::default();
+        message_ext.set_topic(topic.into());
+        message_ext.set_body(body);
+        message_ext.queue_id = queue_id as i32;
+        message_ext.queue_offset = queue.len() as i64;
+        message_ext.commit_log_offset = commit_log_offset;
+        message_ext.born_timestamp = born_timestamp;
+        message_ext.store_timestamp = get_current_millis() as i64;
+        message_ext.store_host = self.addr.parse().unwrap();
+        queue.push(message_ext);
+        queue.len() as i64 - 1
+    }
+
+    fn stored(&self) -> usize {
+        self.queues.lock().iter().map(Vec::len).sum()
+    }
+
+    fn send(&self, request: &RemotingCommand) -> RemotingCommand {
+        let request_header =
+            parse_request_header(request, RequestCode::from(request.code())).unwrap();
+        let queue_id = request_header.queue_id.unwrap_or_default() as usize % QUEUE_NUMS;
+        let queue_offset = self.store(
+            request_header.topic.as_str(),
+            queue_id,
+            request.body().clone().unwrap_or_default(),
+            request_header.born_timestamp,
+        );
+        let mut response_header = SendMessageResponseHeader::default();
+        response_header.set_msg_id(format!("{queue_id}-{queue_offset}"));
+        response_header.set_queue_id(queue_id as i32);
+        response_header.set_queue_offset(queue_offset);
+        RemotingCommand::create_response_command().set_command_custom_header(response_header)
+    }
+
+    async fn pull(&self, request: &RemotingCommand) -> RemotingCommand {
+        let request_header = request
+            .decode_command_custom_header::<PullMessageRequestHeader>()
+            .unwrap();
+        let queue_id = request_header.queue_id.unwrap_or_default() as usize;
+        let mut body = BytesMut::new();
+        let (next_begin_offset, max_offset) = {
+            let queues = self.queues.lock();
+            let queue = queues.get(queue_id).map(Vec::as_slice).unwrap_or_default();
+            let begin = (request_header.queue_offset.max(0) as usize).min(queue.len());
+            let end = queue
+                .len()
+                .min(begin + request_header.max_msg_nums as usize);
+            for message_ext in queue[begin..end]
+                .iter()
+                .filter(|message_ext| message_ext.get_topic() == &request_header.topic)
+            {
+                body.extend_from_slice(&message_decoder::encode(message_ext, false).unwrap());
+            }
+            (end as i64, queue.len() as i64)
+        };
+        let response_header = PullMessageResponseHeader {
+            suggest_which_broker_id: Some(mix_all::MASTER_ID),
+            next_begin_offset: Some(next_begin_offset),
+            min_offset: Some(0),
+            max_offset: Some(max_offset),
+            ..Default::default()
+        };
+        if body.is_empty() {
+            // stands in for the broker holding the long polling request
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            return RemotingCommand::create_response_command_with_code(ResponseCode::PullNotFound)
+                .set_command_custom_header(response_header);
+        }
+        RemotingCommand::create_response_command()
+            .set_command_custom_header(response_header)
+            .set_body(body.freeze())
+    }
+
+    fn heartbeat(&self, request: &RemotingCommand) -> RemotingCommand {
+        let heartbeat_data = HeartbeatData::decode(request.body().as_ref().unwrap()).unwrap();
+        self.client_ids.lock().insert(heartbeat_data.client_id);
+        RemotingCommand::create_response_command()
+    }
+
+    fn consumer_list(&self) -> RemotingCommand {
+        let body = GetConsumerListByGroupResponseBody {
+            consumer_id_list: self.client_ids.lock().iter().cloned().collect(),
+        };
+        RemotingCommand::create_response_command().set_body(body.encode())
+    }
+
+    fn max_offset(&self, request: &RemotingCommand) -> RemotingCommand {
+        let request_header = request
+            .decode_command_custom_header::<GetMaxOffsetRequestHeader>()
+            .unwrap();
+        let offset = self
+            .queues
+            .lock()
+            .get(request_header.queue_id as usize)
+            .map_or(0, |queue| queue.len() as i64);
+        RemotingCommand::create_response_command_with_header(GetMaxOffsetResponseHeader { offset })
+    }
+}
+
+fn assert_csv_summary(summary: &BenchmarkSummary, role: &str) {
+    let csv = summary.to_csv();
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0], BenchmarkSummary::CSV_HEADER);
+    let columns = lines[1].split(',').collect::<Vec<_>>();
+    assert_eq!(columns.len(), lines[0].split(',').count());
+    assert_eq!(columns[0], role);
+    assert_eq!(columns[2], summary.success_count.to_string());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn benchmark_producer_sends_for_the_given_duration() {
+    let broker = InProcessBroker::start().await;
+
+    let summary = producer::run(ProducerArgs {
+        namesrv_addr: broker.addr.to_string(),
+        topic: "BenchmarkProducerTest".to_string(),
+        group: format!("benchmark_producer_{}", get_current_millis()),
+        threads: 4,
+        message_size: 64,
+        keys: true,
+        mode: SendMode::Sync,
+        duration: Some(1),
+    })
+    .await
+    .unwrap();
+
+    assert!(summary.success_count > 0);
+    assert_eq!(summary.failed_count, 0);
+    assert!(summary.elapsed >= Duration::from_secs(1));
+    assert_eq!(broker.stored() as u64, summary.success_count);
+    assert_csv_summary(&summary, "producer");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn benchmark_consumer_consumes_for_the_given_duration() {
+    let broker = InProcessBroker::start().await;
+    let topic = "BenchmarkConsumerTest";
+    let born_timestamp = get_current_millis() as i64;
+    for queue_id in 0..QUEUE_NUMS {
+        for index in 0..8 {
+            broker.store(
+                topic,
+                queue_id,
+                bytes::Bytes::from(format!("message-{index}")),
+                born_timestamp,
+            );
+        }
+    }
+
+    let summary = consumer::run(ConsumerArgs {
+        namesrv_addr: broker.addr.to_string(),
+        topic: topic.to_string(),
+        group: format!("benchmark_consumer_{}", get_current_millis()),
+        expression: "*".to_string(),
+        duration: Some(1),
+    })
+    .await
+    .unwrap();
+
+    assert!(summary.success_count > 0);
+    assert!(summary.success_count <= broker.stored() as u64);
+    assert_csv_summary(&summary, "consumer");
+}
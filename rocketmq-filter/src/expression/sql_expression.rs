@@ -0,0 +1,235 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::any::Any;
+use std::cmp::Ordering;
+use std::error::Error;
+
+use cheetah_string::CheetahString;
+
+use crate::expression::evaluation_context::EvaluationContext;
+use crate::expression::Expression;
+
+/// Value produced while evaluating a SQL92 selector.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Long(i64),
+    Double(f64),
+    String(String),
+}
+
+impl Value {
+    /// Properties are carried as strings, so a string is read as a boolean when it spells one.
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(value) => Some(*value),
+            Value::String(value) if value.eq_ignore_ascii_case("true") => Some(true),
+            Value::String(value) if value.eq_ignore_ascii_case("false") => Some(false),
+            _ => None,
+        }
+    }
+
+    fn as_number(&self) -> Option<Value> {
+        match self {
+            Value::Long(_) | Value::Double(_) => Some(self.clone()),
+            Value::String(value) => {
+                let value = value.trim();
+                value
+                    .parse::<i64>()
+                    .map(Value::Long)
+                    .or_else(|_| value.parse::<f64>().map(Value::Double))
+                    .ok()
+            }
+            _ => None,
+        }
+    }
+
+    fn into_any(self) -> Box<dyn Any> {
+        match self {
+            Value::Null => Box::new(()),
+            Value::Bool(value) => Box::new(value),
+            Value::Long(value) => Box::new(value),
+            Value::Double(value) => Box::new(value),
+            Value::String(value) => Box::new(value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    GreaterThan,
+    GreaterThanOrEqual,
+    LessThan,
+    LessThanOrEqual,
+}
+
+impl CompareOp {
+    fn matches(self, ordering: Ordering) -> bool {
+        match self {
+            CompareOp::GreaterThan => ordering == Ordering::Greater,
+            CompareOp::GreaterThanOrEqual => ordering != Ordering::Less,
+            CompareOp::LessThan => ordering == Ordering::Less,
+            CompareOp::LessThanOrEqual => ordering != Ordering::Greater,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringOp {
+    Contains,
+    StartsWith,
+    EndsWith,
+}
+
+impl StringOp {
+    fn matches(self, value: &str, pattern: &str) -> bool {
+        match self {
+            StringOp::Contains => value.contains(pattern),
+            StringOp::StartsWith => value.starts_with(pattern),
+            StringOp::EndsWith => value.ends_with(pattern),
+        }
+    }
+}
+
+/// Compiled SQL92 selector, evaluated against message properties.
+///
+/// Boolean selectors evaluate to a `bool`; an unknown result, e.g. comparing a property the
+/// message does not carry, evaluates to `()` and never matches.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlExpression {
+    Constant(Value),
+    Property(String),
+    And(Box<SqlExpression>, Box<SqlExpression>),
+    Or(Box<SqlExpression>, Box<SqlExpression>),
+    Not(Box<SqlExpression>),
+    Negate(Box<SqlExpression>),
+    Equal(Box<SqlExpression>, Box<SqlExpression>),
+    Compare(Box<SqlExpression>, CompareOp, Box<SqlExpression>),
+    IsNull(Box<SqlExpression>, bool),
+    In(Box<SqlExpression>, Vec<String>, bool),
+    StringMatch(Box<SqlExpression>, StringOp, String, bool),
+}
+
+impl SqlExpression {
+    /// Whether this expression yields a boolean, a property is assumed to hold one.
+    pub fn is_boolean(&self) -> bool {
+        match self {
+            SqlExpression::Constant(value) => matches!(value, Value::Bool(_)),
+            SqlExpression::Negate(_) => false,
+            _ => true,
+        }
+    }
+
+    pub fn evaluate_value(&self, context: &dyn EvaluationContext) -> Value {
+        match self {
+            SqlExpression::Constant(value) => value.clone(),
+            SqlExpression::Property(name) => property_value(context, name),
+            SqlExpression::And(left, right) => Value::Bool(
+                left.evaluate_value(context).as_bool() == Some(true)
+                    && right.evaluate_value(context).as_bool() == Some(true),
+            ),
+            SqlExpression::Or(left, right) => Value::Bool(
+                left.evaluate_value(context).as_bool() == Some(true)
+                    || right.evaluate_value(context).as_bool() == Some(true),
+            ),
+            SqlExpression::Not(expr) => expr
+                .evaluate_value(context)
+                .as_bool()
+                .map_or(Value::Null, |value| Value::Bool(!value)),
+            SqlExpression::Negate(expr) => match expr.evaluate_value(context).as_number() {
+                Some(Value::Long(value)) => value.checked_neg().map_or(Value::Null, Value::Long),
+                Some(Value::Double(value)) => Value::Double(-value),
+                _ => Value::Null,
+            },
+            SqlExpression::Equal(left, right) => equal(
+                &left.evaluate_value(context),
+                &right.evaluate_value(context),
+            )
+            .map_or(Value::Null, Value::Bool),
+            SqlExpression::Compare(left, op, right) => compare(
+                &left.evaluate_value(context),
+                &right.evaluate_value(context),
+            )
+            .map_or(Value::Null, |ordering| Value::Bool(op.matches(ordering))),
+            SqlExpression::IsNull(expr, not) => {
+                Value::Bool((expr.evaluate_value(context) == Value::Null) != *not)
+            }
+            SqlExpression::In(expr, list, not) => match expr.evaluate_value(context) {
+                Value::String(value) => Value::Bool(list.contains(&value) != *not),
+                _ => Value::Null,
+            },
+            SqlExpression::StringMatch(expr, op, pattern, not) => {
+                match expr.evaluate_value(context) {
+                    Value::String(value) => Value::Bool(op.matches(&value, pattern) != *not),
+                    _ => Value::Null,
+                }
+            }
+        }
+    }
+}
+
+impl Expression for SqlExpression {
+    fn evaluate(&self, context: &dyn EvaluationContext) -> Result<Box<dyn Any>, Box<dyn Error>> {
+        let value = self.evaluate_value(context);
+        if let SqlExpression::Property(_) = self {
+            // a selector made of a single property matches on its boolean value
+            return Ok(value.as_bool().map_or(Value::Null, Value::Bool).into_any());
+        }
+        Ok(value.into_any())
+    }
+}
+
+fn property_value(context: &dyn EvaluationContext, name: &str) -> Value {
+    let Some(value) = context.get(name) else {
+        return Value::Null;
+    };
+    if let Some(value) = value.downcast_ref::<CheetahString>() {
+        Value::String(value.to_string())
+    } else if let Some(value) = value.downcast_ref::<String>() {
+        Value::String(value.clone())
+    } else if let Some(value) = value.downcast_ref::<&str>() {
+        Value::String(value.to_string())
+    } else {
+        Value::Null
+    }
+}
+
+/// Equality across types converts the string side, so `a = 10` matches the property `"10"`.
+fn equal(left: &Value, right: &Value) -> Option<bool> {
+    match (left, right) {
+        (Value::Null, _) | (_, Value::Null) => None,
+        (Value::String(left), Value::String(right)) => Some(left == right),
+        (Value::Bool(_), _) | (_, Value::Bool(_)) => Some(left.as_bool()? == right.as_bool()?),
+        _ => compare(left, right).map(|ordering| ordering == Ordering::Equal),
+    }
+}
+
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left.as_number()?, right.as_number()?) {
+        (Value::Long(left), Value::Long(right)) => Some(left.cmp(&right)),
+        (left, right) => to_f64(&left)?.partial_cmp(&to_f64(&right)?),
+    }
+}
+
+fn to_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Long(value) => Some(*value as f64),
+        Value::Double(value) => Some(*value),
+        _ => None,
+    }
+}
@@ -0,0 +1,45 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::FilterError;
+use crate::expression::Expression;
+use crate::filter_spi::sql_filter::SqlFilter;
+use crate::filter_spi::FilterSpi;
+
+static SQL_FILTER: SqlFilter = SqlFilter;
+
+/// Looks up the compiler of a filter type, only SQL92 is built in.
+pub struct FilterFactory;
+
+impl FilterFactory {
+    pub fn get(type_: &str) -> Option<&'static dyn FilterSpi> {
+        if type_ == SQL_FILTER.of_type() {
+            Some(&SQL_FILTER)
+        } else {
+            None
+        }
+    }
+
+    pub fn compile(
+        type_: &str,
+        expression: &str,
+    ) -> Result<Box<dyn Expression + Send + Sync>, FilterError> {
+        match Self::get(type_) {
+            Some(filter) => filter.compile(expression),
+            None => Err(FilterError::UnsupportedType(type_.to_string())),
+        }
+    }
+}
@@ -0,0 +1,35 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use rocketmq_common::common::filter::expression_type::ExpressionType;
+
+use crate::error::FilterError;
+use crate::expression::Expression;
+use crate::filter_spi::FilterSpi;
+use crate::parser::selector_parser::SelectorParser;
+
+#[derive(Default)]
+pub struct SqlFilter;
+
+impl FilterSpi for SqlFilter {
+    fn compile(&self, expr: &str) -> Result<Box<dyn Expression + Send + Sync>, FilterError> {
+        Ok(Box::new(SelectorParser::parse(expr)?))
+    }
+
+    fn of_type(&self) -> &'static str {
+        ExpressionType::SQL92
+    }
+}
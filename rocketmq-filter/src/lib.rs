@@ -15,5 +15,9 @@
  * limitations under the License.
  */
 
+pub mod error;
 pub mod expression;
+pub mod filter_factory;
+pub mod filter_spi;
+pub mod parser;
 pub mod utils;
@@ -0,0 +1,578 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::FilterError;
+use crate::expression::sql_expression::CompareOp;
+use crate::expression::sql_expression::SqlExpression;
+use crate::expression::sql_expression::StringOp;
+use crate::expression::sql_expression::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Identifier(String),
+    Keyword(Keyword),
+    String(String),
+    Long(i64),
+    Double(f64),
+    LeftParen,
+    RightParen,
+    Comma,
+    Equal,
+    NotEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+    LessThan,
+    LessThanOrEqual,
+    Plus,
+    Minus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Keyword {
+    And,
+    Or,
+    Not,
+    Between,
+    In,
+    Is,
+    Null,
+    True,
+    False,
+    Contains,
+    StartsWith,
+    EndsWith,
+}
+
+impl Keyword {
+    fn parse(word: &str) -> Option<Keyword> {
+        let keyword = match word.to_ascii_uppercase().as_str() {
+            "AND" => Keyword::And,
+            "OR" => Keyword::Or,
+            "NOT" => Keyword::Not,
+            "BETWEEN" => Keyword::Between,
+            "IN" => Keyword::In,
+            "IS" => Keyword::Is,
+            "NULL" => Keyword::Null,
+            "TRUE" => Keyword::True,
+            "FALSE" => Keyword::False,
+            "CONTAINS" => Keyword::Contains,
+            "STARTSWITH" => Keyword::StartsWith,
+            "ENDSWITH" => Keyword::EndsWith,
+            _ => return None,
+        };
+        Some(keyword)
+    }
+}
+
+/// Parses SQL92 message selectors into a [`SqlExpression`].
+///
+/// The grammar is the one of RocketMQ's selector parser: comparisons, `BETWEEN`, `IN`,
+/// `IS [NOT] NULL`, `[NOT] CONTAINS | STARTSWITH | ENDSWITH`, combined with `AND`, `OR` and `NOT`
+/// over properties and string, numeric, boolean and `NULL` literals.
+pub struct SelectorParser<'a> {
+    expression: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl<'a> SelectorParser<'a> {
+    pub fn parse(expression: &'a str) -> Result<SqlExpression, FilterError> {
+        let mut parser = SelectorParser {
+            expression,
+            tokens: tokenize(expression).map_err(|reason| invalid(expression, reason))?,
+            pos: 0,
+        };
+        if parser.tokens.is_empty() {
+            return Err(parser.error("empty expression"));
+        }
+        let parsed = parser.or_expression()?;
+        if let Some(token) = parser.peek() {
+            return Err(parser.error(format!("unexpected token {:?}", token)));
+        }
+        parser.boolean(parsed)
+    }
+
+    fn or_expression(&mut self) -> Result<SqlExpression, FilterError> {
+        let mut left = self.and_expression()?;
+        while self.eat_keyword(Keyword::Or) {
+            let right = self.and_expression()?;
+            left = SqlExpression::Or(
+                Box::new(self.boolean(left)?),
+                Box::new(self.boolean(right)?),
+            );
+        }
+        Ok(left)
+    }
+
+    fn and_expression(&mut self) -> Result<SqlExpression, FilterError> {
+        let mut left = self.equality_expression()?;
+        while self.eat_keyword(Keyword::And) {
+            let right = self.equality_expression()?;
+            left = SqlExpression::And(
+                Box::new(self.boolean(left)?),
+                Box::new(self.boolean(right)?),
+            );
+        }
+        Ok(left)
+    }
+
+    fn equality_expression(&mut self) -> Result<SqlExpression, FilterError> {
+        let mut left = self.comparison_expression()?;
+        loop {
+            if self.eat(&Token::Equal) {
+                let right = self.comparison_expression()?;
+                left = self.equal(left, right)?;
+            } else if self.eat(&Token::NotEqual) {
+                let right = self.comparison_expression()?;
+                left = SqlExpression::Not(Box::new(self.equal(left, right)?));
+            } else if self.eat_keyword(Keyword::Is) {
+                let not = self.eat_keyword(Keyword::Not);
+                self.expect_keyword(Keyword::Null)?;
+                left = SqlExpression::IsNull(Box::new(left), not);
+            } else {
+                return Ok(left);
+            }
+        }
+    }
+
+    fn comparison_expression(&mut self) -> Result<SqlExpression, FilterError> {
+        let mut left = self.unary_expression()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::GreaterThan) => Some(CompareOp::GreaterThan),
+                Some(Token::GreaterThanOrEqual) => Some(CompareOp::GreaterThanOrEqual),
+                Some(Token::LessThan) => Some(CompareOp::LessThan),
+                Some(Token::LessThanOrEqual) => Some(CompareOp::LessThanOrEqual),
+                _ => None,
+            };
+            if let Some(op) = op {
+                self.pos += 1;
+                let right = self.unary_expression()?;
+                left = self.compare(left, op, right)?;
+                continue;
+            }
+            let not = matches!(self.peek(), Some(Token::Keyword(Keyword::Not)))
+                && matches!(
+                    self.tokens.get(self.pos + 1),
+                    Some(Token::Keyword(
+                        Keyword::Between
+                            | Keyword::In
+                            | Keyword::Contains
+                            | Keyword::StartsWith
+                            | Keyword::EndsWith
+                    ))
+                );
+            if not {
+                self.pos += 1;
+            }
+            let keyword = match self.peek() {
+                Some(Token::Keyword(keyword)) => *keyword,
+                _ => return Ok(left),
+            };
+            match keyword {
+                Keyword::Between => {
+                    self.pos += 1;
+                    let low = self.unary_expression()?;
+                    self.expect_keyword(Keyword::And)?;
+                    let high = self.unary_expression()?;
+                    left = if not {
+                        SqlExpression::Or(
+                            Box::new(self.compare(left.clone(), CompareOp::LessThan, low)?),
+                            Box::new(self.compare(left, CompareOp::GreaterThan, high)?),
+                        )
+                    } else {
+                        SqlExpression::And(
+                            Box::new(self.compare(
+                                left.clone(),
+                                CompareOp::GreaterThanOrEqual,
+                                low,
+                            )?),
+                            Box::new(self.compare(left, CompareOp::LessThanOrEqual, high)?),
+                        )
+                    };
+                }
+                Keyword::In => {
+                    self.pos += 1;
+                    self.expect(&Token::LeftParen)?;
+                    let mut list = vec![self.string_literal()?];
+                    while self.eat(&Token::Comma) {
+                        list.push(self.string_literal()?);
+                    }
+                    self.expect(&Token::RightParen)?;
+                    left = SqlExpression::In(Box::new(left), list, not);
+                }
+                Keyword::Contains | Keyword::StartsWith | Keyword::EndsWith => {
+                    self.pos += 1;
+                    let op = match keyword {
+                        Keyword::Contains => StringOp::Contains,
+                        Keyword::StartsWith => StringOp::StartsWith,
+                        _ => StringOp::EndsWith,
+                    };
+                    let pattern = self.string_literal()?;
+                    left = SqlExpression::StringMatch(Box::new(left), op, pattern, not);
+                }
+                _ => return Ok(left),
+            }
+        }
+    }
+
+    fn unary_expression(&mut self) -> Result<SqlExpression, FilterError> {
+        if self.eat(&Token::Plus) {
+            return self.unary_expression();
+        }
+        if self.eat(&Token::Minus) {
+            return match self.unary_expression()? {
+                SqlExpression::Constant(Value::Long(value)) => {
+                    Ok(SqlExpression::Constant(Value::Long(-value)))
+                }
+                SqlExpression::Constant(Value::Double(value)) => {
+                    Ok(SqlExpression::Constant(Value::Double(-value)))
+                }
+                SqlExpression::Constant(value) => {
+                    Err(self.error(format!("{:?} cannot be negated", value)))
+                }
+                expr => Ok(SqlExpression::Negate(Box::new(expr))),
+            };
+        }
+        if self.eat_keyword(Keyword::Not) {
+            let expr = self.unary_expression()?;
+            return Ok(SqlExpression::Not(Box::new(self.boolean(expr)?)));
+        }
+        self.primary_expression()
+    }
+
+    fn primary_expression(&mut self) -> Result<SqlExpression, FilterError> {
+        let token = self
+            .next()
+            .ok_or_else(|| self.error("unexpected end of expression"))?;
+        let expr = match token {
+            Token::Identifier(name) => SqlExpression::Property(name),
+            Token::String(value) => SqlExpression::Constant(Value::String(value)),
+            Token::Long(value) => SqlExpression::Constant(Value::Long(value)),
+            Token::Double(value) => SqlExpression::Constant(Value::Double(value)),
+            Token::Keyword(Keyword::True) => SqlExpression::Constant(Value::Bool(true)),
+            Token::Keyword(Keyword::False) => SqlExpression::Constant(Value::Bool(false)),
+            Token::Keyword(Keyword::Null) => SqlExpression::Constant(Value::Null),
+            Token::LeftParen => {
+                let expr = self.or_expression()?;
+                self.expect(&Token::RightParen)?;
+                expr
+            }
+            token => return Err(self.error(format!("unexpected token {:?}", token))),
+        };
+        Ok(expr)
+    }
+
+    fn equal(
+        &self,
+        left: SqlExpression,
+        right: SqlExpression,
+    ) -> Result<SqlExpression, FilterError> {
+        for operand in [&left, &right] {
+            if *operand == SqlExpression::Constant(Value::Null) {
+                return Err(self.error("'null' cannot be compared"));
+            }
+        }
+        Ok(SqlExpression::Equal(Box::new(left), Box::new(right)))
+    }
+
+    fn compare(
+        &self,
+        left: SqlExpression,
+        op: CompareOp,
+        right: SqlExpression,
+    ) -> Result<SqlExpression, FilterError> {
+        for operand in [&left, &right] {
+            if let SqlExpression::Constant(value) = operand {
+                if !matches!(value, Value::Long(_) | Value::Double(_)) {
+                    return Err(self.error(format!("value {:?} cannot be compared", value)));
+                }
+            }
+        }
+        Ok(SqlExpression::Compare(Box::new(left), op, Box::new(right)))
+    }
+
+    fn boolean(&self, expr: SqlExpression) -> Result<SqlExpression, FilterError> {
+        if expr.is_boolean() {
+            Ok(expr)
+        } else {
+            Err(self.error(format!("{:?} will not result in a boolean value", expr)))
+        }
+    }
+
+    fn string_literal(&mut self) -> Result<String, FilterError> {
+        match self.next() {
+            Some(Token::String(value)) => Ok(value),
+            token => Err(self.error(format!("expected a string literal, found {:?}", token))),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        if token.is_some() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: Keyword) -> bool {
+        self.eat(&Token::Keyword(keyword))
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), FilterError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected {:?}, found {:?}", token, self.peek())))
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: Keyword) -> Result<(), FilterError> {
+        self.expect(&Token::Keyword(keyword))
+    }
+
+    fn error(&self, reason: impl Into<String>) -> FilterError {
+        invalid(self.expression, reason.into())
+    }
+}
+
+fn invalid(expression: &str, reason: String) -> FilterError {
+    FilterError::InvalidExpression {
+        expression: expression.to_string(),
+        reason,
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let chars = expression.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let (token, len) = match c {
+            '(' => (Token::LeftParen, 1),
+            ')' => (Token::RightParen, 1),
+            ',' => (Token::Comma, 1),
+            '=' => (Token::Equal, 1),
+            '+' => (Token::Plus, 1),
+            '-' => (Token::Minus, 1),
+            '<' => match chars.get(i + 1) {
+                Some('>') => (Token::NotEqual, 2),
+                Some('=') => (Token::LessThanOrEqual, 2),
+                _ => (Token::LessThan, 1),
+            },
+            '>' => match chars.get(i + 1) {
+                Some('=') => (Token::GreaterThanOrEqual, 2),
+                _ => (Token::GreaterThan, 1),
+            },
+            '\'' => {
+                let mut value = String::new();
+                let mut end = i + 1;
+                loop {
+                    match chars.get(end) {
+                        None => return Err("unterminated string literal".to_string()),
+                        Some('\'') if chars.get(end + 1) == Some(&'\'') => {
+                            value.push('\'');
+                            end += 2;
+                        }
+                        Some('\'') => break,
+                        Some(c) => {
+                            value.push(*c);
+                            end += 1;
+                        }
+                    }
+                }
+                (Token::String(value), end + 1 - i)
+            }
+            c if c.is_ascii_digit()
+                || (c == '.' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit())) =>
+            {
+                number(&chars, i)?
+            }
+            c if c.is_ascii_alphabetic() || c == '_' || c == '$' => {
+                let mut end = i + 1;
+                while end < chars.len()
+                    && (chars[end].is_ascii_alphanumeric() || matches!(chars[end], '_' | '$' | '.'))
+                {
+                    end += 1;
+                }
+                let word = chars[i..end].iter().collect::<String>();
+                let token = match Keyword::parse(&word) {
+                    Some(keyword) => Token::Keyword(keyword),
+                    None => Token::Identifier(word),
+                };
+                (token, end - i)
+            }
+            c => return Err(format!("unexpected character '{}' at {}", c, i)),
+        };
+        tokens.push(token);
+        i += len;
+    }
+    Ok(tokens)
+}
+
+fn number(chars: &[char], start: usize) -> Result<(Token, usize), String> {
+    let digits = |mut i: usize| {
+        while i < chars.len() && chars[i].is_ascii_digit() {
+            i += 1;
+        }
+        i
+    };
+    let mut end = digits(start);
+    let mut floating = false;
+    if chars.get(end) == Some(&'.') {
+        floating = true;
+        end = digits(end + 1);
+    }
+    if matches!(chars.get(end), Some('e' | 'E')) {
+        let mut exponent = end + 1;
+        if matches!(chars.get(exponent), Some('+' | '-')) {
+            exponent += 1;
+        }
+        if chars.get(exponent).is_some_and(|c| c.is_ascii_digit()) {
+            floating = true;
+            end = digits(exponent);
+        }
+    }
+    let text = chars[start..end].iter().collect::<String>();
+    if floating {
+        let value = text
+            .parse::<f64>()
+            .map_err(|_| format!("invalid number {}", text))?;
+        return Ok((Token::Double(value), end - start));
+    }
+    let value = text
+        .parse::<i64>()
+        .map_err(|_| format!("invalid number {}", text))?;
+    // the Java grammar accepts a long suffix on decimal literals
+    if matches!(chars.get(end), Some('l' | 'L')) {
+        end += 1;
+    }
+    Ok((Token::Long(value), end - start))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::expression::evaluation_context::EvaluationContext;
+    use crate::expression::Expression;
+
+    struct Properties(HashMap<String, String>);
+
+    impl EvaluationContext for Properties {
+        fn get(&self, name: &str) -> Option<&dyn Any> {
+            self.0.get(name).map(|value| value as &dyn Any)
+        }
+
+        fn key_values(&self) -> HashMap<String, Box<dyn Any>> {
+            HashMap::new()
+        }
+    }
+
+    fn matches(expression: &str, properties: &[(&str, &str)]) -> bool {
+        let context = Properties(
+            properties
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        );
+        let result = SelectorParser::parse(expression)
+            .unwrap()
+            .evaluate(&context)
+            .unwrap();
+        result.downcast_ref::<bool>() == Some(&true)
+    }
+
+    #[test]
+    fn numeric_properties_compare_as_numbers() {
+        let props = [("a", "10"), ("b", "2.5")];
+        assert!(matches("a > 9", &props));
+        assert!(matches("a = 10 AND b < 3", &props));
+        assert!(matches("a between 10 and 12", &props));
+        assert!(!matches("a NOT BETWEEN 10 AND 12", &props));
+        assert!(matches("b >= -1.5e0", &props));
+        assert!(matches("a <> 11", &props));
+        assert!(!matches("b > 2.5", &props));
+    }
+
+    #[test]
+    fn string_predicates_and_null_handling() {
+        let props = [("tag", "TagA"), ("region", "it's")];
+        assert!(matches("tag IN ('TagA', 'TagB')", &props));
+        assert!(matches("tag NOT IN ('TagC')", &props));
+        assert!(matches("region = 'it''s'", &props));
+        assert!(matches(
+            "tag STARTSWITH 'Tag' and tag not endswith 'B'",
+            &props
+        ));
+        assert!(matches("missing IS NULL AND tag IS NOT NULL", &props));
+        // comparing a missing property is unknown, neither it nor its negation matches
+        assert!(!matches("missing > 1", &props));
+        assert!(!matches("NOT (missing > 1)", &props));
+        assert!(matches("NOT (missing > 1) OR tag = 'TagA'", &props));
+        assert!(!matches("tag > 1", &props));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert!(matches("a = 1 OR a = 2 AND b = 3", &[("a", "1")]));
+        assert!(!matches("(a = 1 OR a = 2) AND b = 3", &[("a", "1")]));
+        assert!(matches("TRUE", &[]));
+        assert!(matches("flag", &[("flag", "true")]));
+    }
+
+    #[test]
+    fn malformed_selectors_are_rejected() {
+        for expression in [
+            "",
+            "a >",
+            "a = NULL",
+            "a > 'abc'",
+            "a IN (1, 2)",
+            "'abc'",
+            "a = 1 AND 5",
+            "(a = 1",
+            "a = 'open",
+            "a # 1",
+        ] {
+            assert!(
+                SelectorParser::parse(expression).is_err(),
+                "{} should not compile",
+                expression
+            );
+        }
+    }
+}
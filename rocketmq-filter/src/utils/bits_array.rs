@@ -0,0 +1,102 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/// Fixed length bit set backed by bytes, bit `i` lives in byte `i / 8` at `1 << (i % 8)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BitsArray {
+    bytes: Vec<u8>,
+    bit_length: usize,
+}
+
+impl BitsArray {
+    pub fn create(bit_length: usize) -> Self {
+        BitsArray {
+            bytes: vec![0; bit_length.div_ceil(8)],
+            bit_length,
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        BitsArray {
+            bytes: bytes.to_vec(),
+            bit_length: bytes.len() * 8,
+        }
+    }
+
+    pub fn bit_length(&self) -> usize {
+        self.bit_length
+    }
+
+    pub fn byte_length(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn set_bit(&mut self, bit_pos: usize, set: bool) {
+        assert!(
+            bit_pos < self.bit_length,
+            "bit position {} out of range {}",
+            bit_pos,
+            self.bit_length
+        );
+        let mask = 1u8 << (bit_pos % 8);
+        if set {
+            self.bytes[bit_pos / 8] |= mask;
+        } else {
+            self.bytes[bit_pos / 8] &= !mask;
+        }
+    }
+
+    pub fn get_bit(&self, bit_pos: usize) -> bool {
+        assert!(
+            bit_pos < self.bit_length,
+            "bit position {} out of range {}",
+            bit_pos,
+            self.bit_length
+        );
+        self.bytes[bit_pos / 8] & (1u8 << (bit_pos % 8)) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bits_round_trip_through_bytes() {
+        let mut bits = BitsArray::create(20);
+        assert_eq!(bits.byte_length(), 3);
+        bits.set_bit(0, true);
+        bits.set_bit(9, true);
+        bits.set_bit(19, true);
+        bits.set_bit(9, false);
+        bits.set_bit(10, true);
+        assert_eq!(bits.bytes(), &[0b0000_0001, 0b0000_0100, 0b0000_1000]);
+
+        let copy = BitsArray::from_bytes(bits.bytes());
+        assert_eq!(copy.bit_length(), 24);
+        assert!(copy.get_bit(0) && copy.get_bit(10) && copy.get_bit(19));
+        assert!(!copy.get_bit(9) && !copy.get_bit(23));
+    }
+}
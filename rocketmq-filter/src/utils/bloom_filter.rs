@@ -14,6 +14,7 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use crate::utils::bits_array::BitsArray;
 use crate::utils::bloom_filter_data::BloomFilterData;
 
 #[derive(Clone, Copy)]
@@ -48,8 +49,8 @@ impl BloomFilter {
         }
 
         let error_rate = f as f64 / 100.0;
-        let k =
-            (0.5f64.log2() * (n as f64 * error_rate.ln()).abs() / error_rate.ln()).ceil() as i32;
+        // k = log0.5(f), the hash function count minimizing the error rate
+        let k = (error_rate.ln() / 0.5f64.ln()).ceil() as i32;
 
         if k < 1 {
             return Err(
@@ -83,9 +84,161 @@ impl BloomFilter {
     pub fn is_valid(&self, filter_data: Option<&BloomFilterData>) -> bool {
         match filter_data {
             Some(data) => {
-                data.bit_num() == self.m as u32 && data.bit_pos().len() == self.k as usize
+                data.bit_num() == self.m as u32
+                    && data.bit_pos().len() == self.k as usize
+                    && data.bit_pos().iter().all(|pos| (0..self.m).contains(pos))
             }
             None => false,
         }
     }
+
+    /// Calculates the `k` bit positions of `str` by double hashing one 64 bit murmur3 hash,
+    /// the same positions the Java broker assigns.
+    pub fn calc_bit_positions(&self, str: &str) -> Vec<i32> {
+        let hash64 = murmur3_x64_128(str.as_bytes());
+        let hash1 = hash64 as i32;
+        let hash2 = (hash64 >> 32) as i32;
+        (1..=self.k)
+            .map(|i| {
+                let mut combined_hash = hash1.wrapping_add(i.wrapping_mul(hash2));
+                if combined_hash < 0 {
+                    combined_hash = !combined_hash;
+                }
+                combined_hash % self.m
+            })
+            .collect()
+    }
+
+    pub fn generate(&self, str: &str) -> BloomFilterData {
+        BloomFilterData::new(self.calc_bit_positions(str), self.m as u32)
+    }
+
+    /// Sets the bits of `filter_data` in `bits`.
+    pub fn hash_to(
+        &self,
+        filter_data: &BloomFilterData,
+        bits: &mut BitsArray,
+    ) -> Result<(), &'static str> {
+        self.check(filter_data, bits)?;
+        for pos in filter_data.bit_pos() {
+            bits.set_bit(*pos as usize, true);
+        }
+        Ok(())
+    }
+
+    /// Whether every bit of `filter_data` is set in `bits`.
+    pub fn is_hit(
+        &self,
+        filter_data: &BloomFilterData,
+        bits: &BitsArray,
+    ) -> Result<bool, &'static str> {
+        self.check(filter_data, bits)?;
+        Ok(filter_data
+            .bit_pos()
+            .iter()
+            .all(|pos| bits.get_bit(*pos as usize)))
+    }
+
+    fn check(&self, filter_data: &BloomFilterData, bits: &BitsArray) -> Result<(), &'static str> {
+        if !self.is_valid(Some(filter_data)) {
+            return Err("Bloom filter data may not belong to this filter!");
+        }
+        if bits.bit_length() != self.m as usize {
+            return Err("Length of bits is not equal to the bit count of this filter!");
+        }
+        Ok(())
+    }
+}
+
+/// First 64 bits of the x64 128 bit murmur3 hash with seed 0.
+fn murmur3_x64_128(data: &[u8]) -> u64 {
+    const C1: u64 = 0x87c3_7b91_1142_53d5;
+    const C2: u64 = 0x4cf5_ad43_2745_937f;
+
+    fn mix_k1(k1: u64) -> u64 {
+        k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2)
+    }
+
+    fn mix_k2(k2: u64) -> u64 {
+        k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1)
+    }
+
+    fn fmix64(mut k: u64) -> u64 {
+        k ^= k >> 33;
+        k = k.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        k ^= k >> 33;
+        k = k.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        k ^ (k >> 33)
+    }
+
+    fn read_le(bytes: &[u8]) -> u64 {
+        bytes
+            .iter()
+            .rev()
+            .fold(0u64, |acc, byte| (acc << 8) | *byte as u64)
+    }
+
+    let (mut h1, mut h2) = (0u64, 0u64);
+    let mut blocks = data.chunks_exact(16);
+    for block in blocks.by_ref() {
+        h1 ^= mix_k1(read_le(&block[..8]));
+        h1 = h1
+            .rotate_left(27)
+            .wrapping_add(h2)
+            .wrapping_mul(5)
+            .wrapping_add(0x52dc_e729);
+        h2 ^= mix_k2(read_le(&block[8..]));
+        h2 = h2
+            .rotate_left(31)
+            .wrapping_add(h1)
+            .wrapping_mul(5)
+            .wrapping_add(0x3849_5ab5);
+    }
+    let tail = blocks.remainder();
+    if tail.len() > 8 {
+        h2 ^= mix_k2(read_le(&tail[8..]));
+    }
+    if !tail.is_empty() {
+        h1 ^= mix_k1(read_le(&tail[..tail.len().min(8)]));
+    }
+
+    h1 ^= data.len() as u64;
+    h2 ^= data.len() as u64;
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    h1 = fmix64(h1);
+    h2 = fmix64(h2);
+    h1.wrapping_add(h2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn murmur3_matches_the_reference_hash() {
+        assert_eq!(murmur3_x64_128(b""), 0);
+        assert_eq!(murmur3_x64_128(b"hello"), 0xcbd8_a7b3_41bd_9b02);
+    }
+
+    #[test]
+    fn generated_positions_are_hit_once_hashed() {
+        let bloom_filter = BloomFilter::new(20, 32).unwrap();
+        assert_eq!((bloom_filter.k(), bloom_filter.m()), (3, 112));
+
+        let data = bloom_filter.generate("group#topic");
+        assert!(bloom_filter.is_valid(Some(&data)));
+        assert_eq!(data, bloom_filter.generate("group#topic"));
+
+        let mut bits = BitsArray::create(bloom_filter.m() as usize);
+        assert!(!bloom_filter.is_hit(&data, &bits).unwrap());
+        bloom_filter.hash_to(&data, &mut bits).unwrap();
+        assert!(bloom_filter.is_hit(&data, &bits).unwrap());
+
+        assert!(bloom_filter
+            .is_hit(&data, &BitsArray::create(bloom_filter.m() as usize + 8))
+            .is_err());
+        let foreign = BloomFilterData::new(vec![0, 1], bloom_filter.m() as u32);
+        assert!(bloom_filter.hash_to(&foreign, &mut bits).is_err());
+    }
 }
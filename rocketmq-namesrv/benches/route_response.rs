@@ -0,0 +1,130 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::Criterion;
+use rocketmq_common::common::config::TopicConfig;
+use rocketmq_common::common::namesrv::namesrv_config::NamesrvConfig;
+use rocketmq_namesrv::RouteInfoManager;
+use rocketmq_remoting::clients::rocketmq_default_impl::RocketmqDefaultClient;
+use rocketmq_remoting::protocol::body::topic_info_wrapper::topic_config_wrapper::TopicConfigAndMappingSerializeWrapper;
+use rocketmq_remoting::request_processor::default_request_processor::DefaultRemotingRequestProcessor;
+use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
+use rocketmq_rust::ArcMut;
+
+/// Counts the allocations made, to report how many a route request costs.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// A name server with `TopicTest` served by eight broker groups of a master and a slave.
+fn route_info_manager(enable_route_response_cache: bool) -> RouteInfoManager {
+    let manager = RouteInfoManager::new(
+        ArcMut::new(NamesrvConfig {
+            enable_route_response_cache,
+            ..NamesrvConfig::default()
+        }),
+        ArcMut::new(RocketmqDefaultClient::new(
+            Arc::new(TokioClientConfig::default()),
+            DefaultRemotingRequestProcessor,
+        )),
+    );
+    for group in 0..8 {
+        for broker_id in 0..2 {
+            let broker_addr = format!("10.0.{group}.{broker_id}:10911");
+            let broker_name = format!("broker-{group}");
+            let mut topic_config_wrapper = TopicConfigAndMappingSerializeWrapper::default();
+            for topic in ["TopicTest", broker_name.as_str()] {
+                topic_config_wrapper
+                    .topic_config_serialize_wrapper
+                    .topic_config_table
+                    .insert(topic.into(), TopicConfig::with_queues(topic, 8, 8));
+            }
+            manager
+                .register_broker(
+                    "DefaultCluster".into(),
+                    broker_addr.clone().into(),
+                    broker_name.into(),
+                    broker_id,
+                    broker_addr.clone().into(),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    topic_config_wrapper,
+                    vec![],
+                    broker_addr.parse::<SocketAddr>().unwrap(),
+                )
+                .unwrap();
+        }
+    }
+    manager
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let topic = CheetahString::from_static_str("TopicTest");
+    for enable_route_response_cache in [false, true] {
+        let manager = route_info_manager(enable_route_response_cache);
+        let name = if enable_route_response_cache {
+            "route_response_cached"
+        } else {
+            "route_response_uncached"
+        };
+
+        let requests = 1000;
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        for _ in 0..requests {
+            manager.pickup_topic_route_body(&topic).unwrap();
+        }
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+        println!(
+            "{name}: {:.1} allocations per request",
+            allocations as f64 / requests as f64
+        );
+
+        c.bench_function(name, |b| {
+            b.iter(|| manager.pickup_topic_route_body(&topic).unwrap())
+        });
+    }
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
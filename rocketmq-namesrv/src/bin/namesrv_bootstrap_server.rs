@@ -16,38 +16,84 @@
  */
 
 use std::path::PathBuf;
+use std::time::Duration;
 
 use clap::Parser;
+use rocketmq_common::common::mq_version::RocketMqVersion;
 use rocketmq_common::common::namesrv::namesrv_config::NamesrvConfig;
 use rocketmq_common::common::server::config::ServerConfig;
+use rocketmq_common::log::init_logger_with_layers;
+use rocketmq_common::log::LoggingConfig;
+use rocketmq_common::log::NAMESRV_LOG_FILE;
 use rocketmq_common::EnvUtils::EnvUtils;
 use rocketmq_common::ParseConfigFile;
 use rocketmq_namesrv::bootstrap::Builder;
+use rocketmq_remoting::protocol::body::route_snapshot::RouteSnapshot;
+use rocketmq_remoting::protocol::RemotingDeserializable;
+use rocketmq_remoting::request_tracing;
 use rocketmq_rust::rocketmq;
 use tracing::info;
+use tracing_subscriber::Layer;
 
 #[rocketmq::main]
 async fn main() -> anyhow::Result<()> {
-    rocketmq_common::log::init_logger();
     let args = Args::parse();
     let home = EnvUtils::get_rocketmq_home();
+    let config_file = PathBuf::from(home.as_str())
+        .join("conf")
+        .join("namesrv.toml");
+    let namesrv_config = ParseConfigFile::parse_config_file::<NamesrvConfig>(config_file.clone())?;
+    let mut layers = Vec::new();
+    if !namesrv_config.trace_otlp_exporter_endpoint.is_empty() {
+        layers.push(
+            request_tracing::otlp_layer(
+                "rocketmq-namesrv",
+                namesrv_config.trace_otlp_exporter_endpoint.as_str(),
+                Duration::from_millis(namesrv_config.trace_otlp_exporter_time_out_in_mills),
+            )?
+            .boxed(),
+        );
+    }
+    init_logger_with_layers(
+        LoggingConfig::new(PathBuf::from(home.as_str()).join("logs"), NAMESRV_LOG_FILE),
+        layers,
+    )?;
 
     info!("Rocketmq(Rust) home: {}", home);
+    info!(
+        "Rocketmq name remoting_server(Rust) version: {}, commit: {}",
+        RocketMqVersion::CURRENT_VERSION.name(),
+        option_env!("ROCKETMQ_GIT_COMMIT").unwrap_or("unknown")
+    );
     info!(
         "Rocketmq name remoting_server(Rust) running on: {}:{}",
         args.ip, args.port
     );
-    let config_file = PathBuf::from(home).join("conf").join("namesrv.toml");
-    let namesrv_config = ParseConfigFile::parse_config_file::<NamesrvConfig>(config_file.clone())?;
-    Builder::new()
+    info!(
+        "Namesrv config: {}, orderMessageEnable: {}, supportActingMaster: {}, \
+         scanNotActiveBrokerInterval: {}ms",
+        config_file.display(),
+        namesrv_config.order_message_enable,
+        namesrv_config.support_acting_master,
+        namesrv_config.scan_not_active_broker_interval
+    );
+    let mut builder = Builder::new()
         .set_name_server_config(namesrv_config)
         .set_server_config(ServerConfig {
             listen_port: args.port,
             bind_address: args.ip,
-        })
-        .build()
-        .boot()
-        .await;
+        });
+    if let Some(import_route) = args.import_route {
+        let route_snapshot = RouteSnapshot::decode(&std::fs::read(&import_route)?)?;
+        info!(
+            "Import route snapshot from {}, topics: {}, brokers: {}",
+            import_route.display(),
+            route_snapshot.topic_queue_table.len(),
+            route_snapshot.broker_addr_table.len()
+        );
+        builder = builder.set_route_snapshot(route_snapshot);
+    }
+    builder.build().boot().await;
 
     Ok(())
 }
@@ -82,4 +128,7 @@ struct Args {
     /// rocketmq name remoting_server config file
     #[arg(short, long, value_name = "FILE", default_missing_value = "None")]
     config: Option<PathBuf>,
+    /// route snapshot to pre-warm the route tables with, as dumped by a running name server
+    #[arg(long, value_name = "FILE")]
+    import_route: Option<PathBuf>,
 }
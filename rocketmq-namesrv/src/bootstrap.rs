@@ -24,6 +24,7 @@ use rocketmq_common::common::server::config::ServerConfig;
 use rocketmq_common::utils::network_util::NetworkUtil;
 use rocketmq_remoting::clients::rocketmq_default_impl::RocketmqDefaultClient;
 use rocketmq_remoting::clients::RemotingClient;
+use rocketmq_remoting::protocol::body::route_snapshot::RouteSnapshot;
 use rocketmq_remoting::remoting::RemotingService;
 use rocketmq_remoting::remoting_server::server::RocketMQServer;
 use rocketmq_remoting::request_processor::default_request_processor::DefaultRemotingRequestProcessor;
@@ -46,6 +47,7 @@ pub struct NameServerBootstrap {
 pub struct Builder {
     name_server_config: Option<NamesrvConfig>,
     server_config: Option<ServerConfig>,
+    route_snapshot: Option<RouteSnapshot>,
 }
 
 struct NameServerRuntime {
@@ -67,6 +69,13 @@ impl NameServerBootstrap {
         }*/
         tokio::join!(self.name_server_runtime.start(), wait_for_signal());
     }
+
+    /// Starts serving without waiting for a shutdown signal, for a name server embedded in
+    /// another process. It stops when the bootstrap and the runtime it was started on are
+    /// dropped.
+    pub async fn start_embedded(&mut self) {
+        self.name_server_runtime.start().await;
+    }
 }
 
 impl NameServerRuntime {
@@ -119,6 +128,26 @@ impl NameServerRuntime {
                 Some(Duration::from_secs(5)),
                 Duration::from_secs(5),
             );
+        let route_info_manager = self.route_info_manager.clone();
+        self.name_server_runtime
+            .as_ref()
+            .unwrap()
+            .schedule_at_fixed_rate(
+                move || {
+                    if route_info_manager
+                        .namesrv_config
+                        .enable_route_response_cache
+                    {
+                        let stats = route_info_manager.route_response_cache_stats();
+                        info!(
+                            "route response cache hits: {}, misses: {}, entries: {}",
+                            stats.hits, stats.misses, stats.entries
+                        );
+                    }
+                },
+                Some(Duration::from_secs(60)),
+                Duration::from_secs(60),
+            );
         NameServerRequestProcessor {
             client_request_processor: ArcMut::new(client_request_processor),
             default_request_processor: ArcMut::new(default_request_processor),
@@ -145,6 +174,7 @@ impl Builder {
         Builder {
             name_server_config: None,
             server_config: None,
+            route_snapshot: None,
         }
     }
 
@@ -158,6 +188,13 @@ impl Builder {
         self
     }
 
+    /// Pre-warms the route tables with `route_snapshot`, so routes are served before brokers
+    /// register again.
+    pub fn set_route_snapshot(mut self, route_snapshot: RouteSnapshot) -> Self {
+        self.route_snapshot = Some(route_snapshot);
+        self
+    }
+
     pub fn build(self) -> NameServerBootstrap {
         let name_server_config = ArcMut::new(self.name_server_config.unwrap_or_default());
         let runtime = RocketMQRuntime::new_multi(10, "namesrv-thread");
@@ -167,15 +204,18 @@ impl Builder {
             DefaultRemotingRequestProcessor,
         ));
 
+        let route_info_manager =
+            RouteInfoManager::new(name_server_config.clone(), remoting_client.clone());
+        if let Some(route_snapshot) = self.route_snapshot {
+            route_info_manager.import(route_snapshot, false);
+        }
+
         NameServerBootstrap {
             name_server_runtime: NameServerRuntime {
                 name_server_config: name_server_config.clone(),
                 tokio_client_config,
                 server_config: Arc::new(self.server_config.unwrap()),
-                route_info_manager: RouteInfoManager::new(
-                    name_server_config.clone(),
-                    remoting_client.clone(),
-                ),
+                route_info_manager,
                 kvconfig_manager: KVConfigManager::new(name_server_config),
                 name_server_runtime: Some(runtime),
                 remoting_client,
@@ -0,0 +1,180 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::DataVersion;
+use thiserror::Error;
+
+/// Errors a name server request is answered with. The variant picks the response code, the
+/// message is sent as the remark.
+#[derive(Debug, Error)]
+pub enum NamesrvError {
+    #[error("{0}")]
+    TopicNotExist(String),
+
+    #[error("{0}")]
+    NoPermission(String),
+
+    #[error("{0}")]
+    QueryNotFound(String),
+
+    #[error("{0}")]
+    SystemError(String),
+}
+
+impl NamesrvError {
+    pub fn response_code(&self) -> ResponseCode {
+        match self {
+            NamesrvError::TopicNotExist(_) => ResponseCode::TopicNotExist,
+            NamesrvError::NoPermission(_) => ResponseCode::NoPermission,
+            NamesrvError::QueryNotFound(_) => ResponseCode::QueryNotFound,
+            NamesrvError::SystemError(_) => ResponseCode::SystemError,
+        }
+    }
+}
+
+impl From<NamesrvError> for RemotingCommand {
+    fn from(error: NamesrvError) -> Self {
+        RemotingCommand::create_response_command_with_code_remark(
+            error.response_code(),
+            error.to_string(),
+        )
+    }
+}
+
+/// Why the name server refused a broker registration. The message is sent back as the remark of
+/// the REGISTER_BROKER response, so the broker log tells what to fix.
+#[derive(Debug, Error)]
+pub enum RegistrationError {
+    #[error(
+        "broker {broker_addr} is registered with data version {old} by {old_broker_addr}, the \
+         registration with data version {new} is stale"
+    )]
+    StaleDataVersion {
+        broker_addr: CheetahString,
+        old_broker_addr: CheetahString,
+        old: Box<DataVersion>,
+        new: Box<DataVersion>,
+    },
+
+    #[error(
+        "broker {broker_addr} registers with epoch {epoch}, but epoch {recorded} is already \
+         recorded for broker id {broker_id}"
+    )]
+    StaleEpoch {
+        broker_addr: CheetahString,
+        broker_id: u64,
+        epoch: i32,
+        recorded: i32,
+    },
+
+    #[error(
+        "broker {broker_addr} registers a single topic as broker id {broker_id}, which has not \
+         registered yet"
+    )]
+    InconsistentTopicRegistration {
+        broker_addr: CheetahString,
+        broker_id: u64,
+    },
+
+    #[error("the route table was not writable within {timeout_millis}ms")]
+    Timeout { timeout_millis: u64 },
+
+    #[error("broker registers with an invalid address {broker_addr}: {reason}")]
+    InvalidBrokerAddr {
+        broker_addr: CheetahString,
+        reason: String,
+    },
+}
+
+impl RegistrationError {
+    /// A busy route table is worth retrying, anything else fails again until the broker changes.
+    pub fn response_code(&self) -> ResponseCode {
+        match self {
+            RegistrationError::Timeout { .. } => ResponseCode::SystemBusy,
+            _ => ResponseCode::SystemError,
+        }
+    }
+}
+
+impl From<RegistrationError> for RemotingCommand {
+    fn from(error: RegistrationError) -> Self {
+        RemotingCommand::create_response_command_with_code_remark(
+            error.response_code(),
+            error.to_string(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_become_responses_with_their_code_and_remark() {
+        let cases = [
+            (
+                NamesrvError::TopicNotExist("No topic route info".to_string()),
+                ResponseCode::TopicNotExist,
+            ),
+            (
+                NamesrvError::NoPermission("Cannot update config in blacklist.".to_string()),
+                ResponseCode::NoPermission,
+            ),
+            (
+                NamesrvError::QueryNotFound("No config item, Namespace: ns".to_string()),
+                ResponseCode::QueryNotFound,
+            ),
+            (
+                NamesrvError::SystemError("disable".to_string()),
+                ResponseCode::SystemError,
+            ),
+        ];
+        for (error, code) in cases {
+            let remark = error.to_string();
+            let response = RemotingCommand::from(error);
+            assert_eq!(response.code(), code as i32);
+            assert_eq!(response.remark().unwrap().as_str(), remark);
+        }
+    }
+    #[test]
+    fn registration_errors_tell_the_broker_why() {
+        let error = RegistrationError::StaleEpoch {
+            broker_addr: "10.0.0.2:10911".into(),
+            broker_id: 0,
+            epoch: 1,
+            recorded: 2,
+        };
+        let response = RemotingCommand::from(error);
+        assert_eq!(response.code(), ResponseCode::SystemError as i32);
+        assert_eq!(
+            response.remark().unwrap().as_str(),
+            "broker 10.0.0.2:10911 registers with epoch 1, but epoch 2 is already recorded for \
+             broker id 0"
+        );
+
+        let response = RemotingCommand::from(RegistrationError::Timeout {
+            timeout_millis: 3000,
+        });
+        assert_eq!(response.code(), ResponseCode::SystemBusy as i32);
+        assert_eq!(
+            response.remark().unwrap().as_str(),
+            "the route table was not writable within 3000ms"
+        );
+    }
+}
@@ -20,8 +20,10 @@
 pub use self::kvconfig::kvconfig_mananger::KVConfigManager;
 pub use self::namesrv_config_parse::parse_command_and_config_file;
 pub use self::route::route_info_manager::RouteInfoManager;
+pub use self::route::route_response_cache::RouteResponseCacheStats;
 
 pub mod bootstrap;
+pub mod error;
 mod kvconfig;
 mod namesrv_config_parse;
 pub mod processor;
@@ -19,13 +19,13 @@ use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::time::Duration;
 
+use bytes::Bytes;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::namesrv::namesrv_config::NamesrvConfig;
 use rocketmq_common::common::FAQUrl;
 use rocketmq_common::TimeUtils;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::RemotingSysResponseCode;
-use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::header::client_request_header::GetRouteInfoRequestHeader;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
@@ -34,6 +34,7 @@ use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerCon
 use rocketmq_rust::ArcMut;
 use tracing::warn;
 
+use crate::error::NamesrvError;
 use crate::kvconfig::kvconfig_mananger::KVConfigManager;
 use crate::processor::NAMESPACE_ORDER_TOPIC_CONFIG;
 use crate::route::route_info_manager::RouteInfoManager;
@@ -74,44 +75,35 @@ impl ClientRequestProcessor {
                 "name remoting_server not ready. request code {} ",
                 request.code()
             );
-            return RemotingCommand::create_response_command_with_code(
-                RemotingSysResponseCode::SystemError,
-            )
-            .set_remark("name remoting_server not ready");
+            return NamesrvError::SystemError("name remoting_server not ready".to_string()).into();
         }
-        match self
-            .route_info_manager
-            .pickup_topic_route_data(request_header.topic.as_ref())
-        {
-            None => RemotingCommand::create_response_command_with_code(ResponseCode::TopicNotExist)
-                .set_remark(format!(
-                    "No topic route info in name remoting_server for the topic:{}{}",
-                    request_header.topic,
-                    FAQUrl::suggest_todo(FAQUrl::APPLY_TOPIC_URL)
-                )),
-            Some(mut topic_route_data) => {
+        // the order topic config comes from the kv config, so only routes without it are cached
+        let content = if self.namesrv_config.order_message_enable {
+            self.route_info_manager
+                .pickup_topic_route_data(&request_header.topic)
+                .map(|mut topic_route_data| {
+                    topic_route_data.order_topic_conf = self.kvconfig_manager.get_kvconfig(
+                        &CheetahString::from_static_str(NAMESPACE_ORDER_TOPIC_CONFIG),
+                        &request_header.topic,
+                    );
+                    Bytes::from(topic_route_data.encode())
+                })
+        } else {
+            self.route_info_manager
+                .pickup_topic_route_body(&request_header.topic)
+        };
+        match content {
+            None => NamesrvError::TopicNotExist(format!(
+                "No topic route info in name remoting_server for the topic:{}{}",
+                request_header.topic,
+                FAQUrl::suggest_todo(FAQUrl::APPLY_TOPIC_URL)
+            ))
+            .into(),
+            Some(content) => {
                 if self.need_check_namesrv_ready.load(Ordering::Acquire) {
                     self.need_check_namesrv_ready
                         .store(false, Ordering::Release);
                 }
-                if self.namesrv_config.order_message_enable {
-                    //get kv config
-                    let order_topic_config = self.kvconfig_manager.get_kvconfig(
-                        &CheetahString::from_static_str(NAMESPACE_ORDER_TOPIC_CONFIG),
-                        &request_header.topic,
-                    );
-                    topic_route_data.order_topic_conf = order_topic_config;
-                };
-                /*let standard_json_only = request_header.accept_standard_json_only.unwrap_or(false);
-                let content = if request.version() >= RocketMqVersion::into(RocketMqVersion::V494)
-                    || standard_json_only
-                {
-                    //topic_route_data.encode()
-                    topic_route_data.encode()
-                } else {
-                    topic_route_data.encode()
-                };*/
-                let content = topic_route_data.encode();
                 RemotingCommand::create_response_command_with_code(RemotingSysResponseCode::Success)
                     .set_body(content)
             }
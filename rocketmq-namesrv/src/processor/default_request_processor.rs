@@ -23,14 +23,15 @@ use cheetah_string::CheetahString;
 use rocketmq_common::common::mix_all;
 use rocketmq_common::common::mix_all::string_to_properties;
 use rocketmq_common::common::mq_version::RocketMqVersion;
+use rocketmq_common::common::topic::TopicValidator;
 use rocketmq_common::utils::serde_json_utils::SerdeJsonUtils;
 use rocketmq_common::CRC32Utils;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::RemotingSysResponseCode;
-use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::body::broker_body::broker_member_group::GetBrokerMemberGroupResponseBody;
 use rocketmq_remoting::protocol::body::broker_body::register_broker_body::RegisterBrokerBody;
+use rocketmq_remoting::protocol::body::topic::topic_list::TopicListStream;
 use rocketmq_remoting::protocol::body::topic_info_wrapper::topic_config_wrapper::TopicConfigAndMappingSerializeWrapper;
 use rocketmq_remoting::protocol::header::namesrv::broker_request::BrokerHeartbeatRequestHeader;
 use rocketmq_remoting::protocol::header::namesrv::broker_request::GetBrokerMemberGroupRequestHeader;
@@ -49,6 +50,7 @@ use rocketmq_remoting::protocol::header::namesrv::query_data_version_header::Que
 use rocketmq_remoting::protocol::header::namesrv::register_broker_header::RegisterBrokerRequestHeader;
 use rocketmq_remoting::protocol::header::namesrv::register_broker_header::RegisterBrokerResponseHeader;
 use rocketmq_remoting::protocol::header::namesrv::topic_operation_header::DeleteTopicFromNamesrvRequestHeader;
+use rocketmq_remoting::protocol::header::namesrv::topic_operation_header::GetAllTopicListRequestHeader;
 use rocketmq_remoting::protocol::header::namesrv::topic_operation_header::GetTopicsByClusterRequestHeader;
 use rocketmq_remoting::protocol::header::namesrv::topic_operation_header::RegisterTopicRequestHeader;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
@@ -59,6 +61,7 @@ use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use tracing::warn;
 
+use crate::error::NamesrvError;
 use crate::processor::NAMESPACE_ORDER_TOPIC_CONFIG;
 use crate::route::route_info_manager::RouteInfoManager;
 use crate::KVConfigManager;
@@ -106,6 +109,7 @@ impl DefaultRequestProcessor {
                 self.get_has_unit_sub_un_unit_topic_list(request)
             }
             RequestCode::UpdateNamesrvConfig => self.update_config(request),
+            RequestCode::GetRouteSnapshotFromNamesrv => self.get_route_snapshot(request),
             _ => RemotingCommand::create_response_command_with_code(
                 RemotingSysResponseCode::SystemError,
             ),
@@ -149,11 +153,11 @@ impl DefaultRequestProcessor {
             return RemotingCommand::create_response_command()
                 .set_command_custom_header(GetKVConfigResponseHeader::new(value));
         }
-        RemotingCommand::create_response_command_with_code(RemotingSysResponseCode::SystemError)
-            .set_remark(format!(
-                "No config item, Namespace: {} Key: {}",
-                request_header.namespace, request_header.key
-            ))
+        NamesrvError::QueryNotFound(format!(
+            "No config item, Namespace: {} Key: {}",
+            request_header.namespace, request_header.key
+        ))
+        .into()
     }
 
     fn delete_kv_config(&mut self, request: RemotingCommand) -> RemotingCommand {
@@ -244,15 +248,16 @@ impl DefaultRequestProcessor {
                 .and_then(|map| map.get(mix_all::ZONE_NAME).cloned()),
             request_header.heartbeat_timeout_millis,
             request_header.enable_acting_master,
+            request_header.epoch,
+            request_header.max_phy_offset,
             topic_config_wrapper,
             filter_server_list,
             remote_addr,
         );
-        if result.is_none() {
-            return response_command
-                .set_code(RemotingSysResponseCode::SystemError)
-                .set_remark(CheetahString::from_static_str("register broker failed"));
-        }
+        let register_broker_result = match result {
+            Ok(register_broker_result) => register_broker_result,
+            Err(error) => return error.into(),
+        };
         if self
             .kvconfig_manager
             .namesrv_config
@@ -267,7 +272,6 @@ impl DefaultRequestProcessor {
                 response_command = response_command.set_body(value);
             }
         }
-        let register_broker_result = result.unwrap();
         response_command
             .set_code(RemotingSysResponseCode::Success)
             .set_command_custom_header(RegisterBrokerResponseHeader::new(
@@ -340,10 +344,18 @@ impl DefaultRequestProcessor {
             .set_command_custom_header(AddWritePermOfBrokerResponseHeader::new(add_topic_cnt))
     }
 
-    fn get_all_topic_list_from_nameserver(&self, _request: RemotingCommand) -> RemotingCommand {
+    fn get_all_topic_list_from_nameserver(&self, request: RemotingCommand) -> RemotingCommand {
         if self.route_info_manager.namesrv_config.enable_all_topic_list {
-            let topics = self.route_info_manager.get_all_topic_list();
-            return RemotingCommand::create_response_command().set_body(topics.encode());
+            let topic_prefix = request
+                .decode_command_custom_header::<GetAllTopicListRequestHeader>()
+                .and_then(|request_header| request_header.topic_prefix);
+            let topics = self
+                .route_info_manager
+                .get_all_topic_list(topic_prefix.as_ref().map(|prefix| prefix.as_str()))
+                .collect();
+            // streamed, the list of a large cluster runs into tens of megabytes
+            return RemotingCommand::create_response_command()
+                .set_body_stream(TopicListStream::new(topics));
         }
         RemotingCommand::create_response_command_with_code(RemotingSysResponseCode::SystemError)
             .set_remark(CheetahString::from_static_str("disable"))
@@ -353,6 +365,13 @@ impl DefaultRequestProcessor {
         let request_header = request
             .decode_command_custom_header::<DeleteTopicFromNamesrvRequestHeader>()
             .expect("decode DeleteTopicFromNamesrvRequestHeader failed");
+        let result = TopicValidator::check_system_topic_deletion(request_header.topic.as_str());
+        if !result.valid() {
+            return RemotingCommand::create_response_command_with_code_remark(
+                RemotingSysResponseCode::SystemError,
+                result.remark().clone(),
+            );
+        }
         self.route_info_manager
             .delete_topic(request_header.topic, request_header.cluster_name);
         RemotingCommand::create_response_command()
@@ -362,6 +381,13 @@ impl DefaultRequestProcessor {
         let request_header = request
             .decode_command_custom_header::<RegisterTopicRequestHeader>()
             .expect("decode RegisterTopicRequestHeader failed");
+        let result = TopicValidator::validate_topic(request_header.topic.as_str());
+        if !result.valid() {
+            return RemotingCommand::create_response_command_with_code_remark(
+                RemotingSysResponseCode::SystemError,
+                result.remark().clone(),
+            );
+        }
         if let Some(ref body) = request.body() {
             let topic_route_data = TopicRouteData::decode(body).unwrap_or_default();
             if !topic_route_data.queue_datas.is_empty() {
@@ -382,20 +408,16 @@ impl DefaultRequestProcessor {
         if let Some(value) = value {
             return RemotingCommand::create_response_command().set_body(value);
         }
-        RemotingCommand::create_response_command_with_code(ResponseCode::QueryNotFound).set_remark(
-            format!(
-                "No config item, Namespace: {}",
-                request_header.namespace.as_str()
-            ),
-        )
+        NamesrvError::QueryNotFound(format!(
+            "No config item, Namespace: {}",
+            request_header.namespace.as_str()
+        ))
+        .into()
     }
 
     fn get_topics_by_cluster(&self, request: RemotingCommand) -> RemotingCommand {
         if !self.route_info_manager.namesrv_config.enable_topic_list {
-            return RemotingCommand::create_response_command_with_code(
-                RemotingSysResponseCode::SystemError,
-            )
-            .set_remark(CheetahString::from_static_str("disable"));
+            return NamesrvError::SystemError("disable".to_string()).into();
         }
 
         let request_header = request
@@ -412,13 +434,17 @@ impl DefaultRequestProcessor {
         RemotingCommand::create_response_command().set_body(topic_list.encode())
     }
 
+    fn get_route_snapshot(&self, _request: RemotingCommand) -> RemotingCommand {
+        let snapshot = self.route_info_manager.dump();
+        RemotingCommand::create_response_command().set_body(snapshot.encode())
+    }
+
     fn get_unit_topic_list(&self, _request: RemotingCommand) -> RemotingCommand {
         if self.route_info_manager.namesrv_config.enable_topic_list {
             let topic_list = self.route_info_manager.get_unit_topics();
             return RemotingCommand::create_response_command().set_body(topic_list.encode());
         }
-        RemotingCommand::create_response_command_with_code(RemotingSysResponseCode::SystemError)
-            .set_remark("disable")
+        NamesrvError::SystemError("disable".to_string()).into()
     }
 
     fn get_has_unit_sub_topic_list(&self, _request: RemotingCommand) -> RemotingCommand {
@@ -426,8 +452,7 @@ impl DefaultRequestProcessor {
             let topic_list = self.route_info_manager.get_has_unit_sub_topic_list();
             return RemotingCommand::create_response_command().set_body(topic_list.encode());
         }
-        RemotingCommand::create_response_command_with_code(RemotingSysResponseCode::SystemError)
-            .set_remark("disable")
+        NamesrvError::SystemError("disable".to_string()).into()
     }
 
     fn get_has_unit_sub_un_unit_topic_list(&self, _request: RemotingCommand) -> RemotingCommand {
@@ -437,8 +462,7 @@ impl DefaultRequestProcessor {
                 .get_has_unit_sub_un_unit_topic_list();
             return RemotingCommand::create_response_command().set_body(topic_list.encode());
         }
-        RemotingCommand::create_response_command_with_code(RemotingSysResponseCode::SystemError)
-            .set_remark("disable")
+        NamesrvError::SystemError("disable".to_string()).into()
     }
 
     fn update_config(&mut self, request: RemotingCommand) -> RemotingCommand {
@@ -469,10 +493,10 @@ impl DefaultRequestProcessor {
                     .get_namesrv_config()
                     .get_config_blacklist(),
             ) {
-                return RemotingCommand::create_response_command_with_code(
-                    RemotingSysResponseCode::NoPermission,
+                return NamesrvError::NoPermission(
+                    "Cannot update config in blacklist.".to_string(),
                 )
-                .set_remark("Cannot update config in blacklist.".to_string());
+                .into();
             }
 
             let result = self.kvconfig_manager.update_namesrv_config(properties);
@@ -482,6 +506,7 @@ impl DefaultRequestProcessor {
                 )
                 .set_remark(format!("Update error {:?}", e));
             }
+            self.route_info_manager.invalidate_route_response_cache();
         }
 
         RemotingCommand::create_response_command_with_code(RemotingSysResponseCode::Success)
@@ -552,7 +577,15 @@ fn validate_blacklist_config_exist(
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use rocketmq_common::common::namesrv::namesrv_config::NamesrvConfig;
+    use rocketmq_remoting::clients::rocketmq_default_impl::RocketmqDefaultClient;
+    use rocketmq_remoting::code::response_code::ResponseCode;
     use rocketmq_remoting::protocol::header::namesrv::register_broker_header::RegisterBrokerRequestHeader;
+    use rocketmq_remoting::request_processor::default_request_processor::DefaultRemotingRequestProcessor;
+    use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
+    use rocketmq_rust::ArcMut;
 
     use super::*;
 
@@ -616,4 +649,40 @@ mod tests {
         let result = check_sum_crc32(&request, &request_header);
         assert!(result);
     }
+
+    #[test]
+    fn delete_topic_in_name_srv_refuses_system_topics() {
+        let namesrv_config = ArcMut::new(NamesrvConfig::default());
+        let mut processor = DefaultRequestProcessor::new(
+            RouteInfoManager::new(
+                namesrv_config.clone(),
+                ArcMut::new(RocketmqDefaultClient::new(
+                    Arc::new(TokioClientConfig::default()),
+                    DefaultRemotingRequestProcessor,
+                )),
+            ),
+            KVConfigManager::new(namesrv_config),
+        );
+        let delete_topic = |topic: &str| {
+            let mut request = RemotingCommand::create_request_command(
+                RequestCode::DeleteTopicInNamesrv,
+                DeleteTopicFromNamesrvRequestHeader {
+                    topic: topic.into(),
+                    cluster_name: None,
+                },
+            );
+            request.make_custom_header_to_net();
+            request
+        };
+
+        let response = processor
+            .delete_topic_in_name_srv(delete_topic(TopicValidator::RMQ_SYS_TRANS_HALF_TOPIC));
+        assert_eq!(response.code(), ResponseCode::SystemError as i32);
+        assert_eq!(
+            response.remark().map(CheetahString::as_str),
+            Some("The topic[RMQ_SYS_TRANS_HALF_TOPIC] is a system topic and can not be deleted.")
+        );
+        let response = processor.delete_topic_in_name_srv(delete_topic("TopicA"));
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+    }
 }
@@ -16,3 +16,4 @@
  */
 
 pub mod route_info_manager;
+pub mod route_response_cache;
@@ -19,21 +19,24 @@ use std::collections::HashMap;
 use std::collections::HashSet;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
+use bytes::Bytes;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::config::TopicConfig;
 use rocketmq_common::common::constant::PermName;
 use rocketmq_common::common::mix_all;
 use rocketmq_common::common::namesrv::namesrv_config::NamesrvConfig;
+use rocketmq_common::common::system_clock::Clock;
+use rocketmq_common::common::system_clock::SystemClock;
 use rocketmq_common::common::topic::TopicValidator;
 use rocketmq_common::common::TopicSysFlag;
-use rocketmq_common::TimeUtils;
-use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::clients::rocketmq_default_impl::RocketmqDefaultClient;
 use rocketmq_remoting::clients::RemotingClient;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::protocol::body::broker_body::broker_member_group::BrokerMemberGroup;
 use rocketmq_remoting::protocol::body::broker_body::cluster_info::ClusterInfo;
+use rocketmq_remoting::protocol::body::route_snapshot::RouteSnapshot;
 use rocketmq_remoting::protocol::body::topic::topic_list::TopicList;
 use rocketmq_remoting::protocol::body::topic_info_wrapper::topic_config_wrapper::TopicConfigAndMappingSerializeWrapper;
 use rocketmq_remoting::protocol::header::namesrv::broker_request::UnRegisterBrokerRequestHeader;
@@ -45,17 +48,30 @@ use rocketmq_remoting::protocol::route::route_data_view::QueueData;
 use rocketmq_remoting::protocol::route::topic_route_data::TopicRouteData;
 use rocketmq_remoting::protocol::static_topic::topic_queue_info::TopicQueueMappingInfo;
 use rocketmq_remoting::protocol::DataVersion;
+use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_rust::ArcMut;
 use tokio::sync::broadcast;
 use tracing::debug;
 use tracing::info;
 use tracing::warn;
 
+use crate::error::RegistrationError;
+use crate::route::route_response_cache::RouteResponseCache;
+use crate::route::route_response_cache::RouteResponseCacheStats;
+use crate::route_info::broker_addr_info::canonical_broker_addr;
+use crate::route_info::broker_addr_info::canonical_socket_addr;
 use crate::route_info::broker_addr_info::BrokerAddrInfo;
 use crate::route_info::broker_addr_info::BrokerLiveInfo;
 use crate::route_info::broker_addr_info::BrokerStatusChangeInfo;
 
 const DEFAULT_BROKER_CHANNEL_EXPIRED_TIME: i64 = 1000 * 60 * 2;
+/// Brokers register themselves every 30 seconds by default.
+const BROKER_HEARTBEAT_INTERVAL: i64 = 1000 * 30;
+/// How long every address of a broker group has to be silent before the group is dropped from
+/// topic routes, a single missed heartbeat must not make the group flap in and out.
+const UNAVAILABLE_BROKER_GRACE_TIME: i64 = BROKER_HEARTBEAT_INTERVAL * 2;
+/// How long a registration waits for the route table before the broker is told to retry.
+const REGISTER_BROKER_LOCK_TIMEOUT: Duration = Duration::from_secs(3);
 
 type TopicQueueTable = ArcMut<
     HashMap<CheetahString /* topic */, HashMap<CheetahString /* broker name */, QueueData>>,
@@ -83,7 +99,19 @@ pub struct RouteInfoManager {
     pub(crate) topic_queue_mapping_info_table: TopicQueueMappingInfoTable,
     pub(crate) namesrv_config: ArcMut<NamesrvConfig>,
     pub(crate) remoting_client: ArcMut<RocketmqDefaultClient>,
+    route_response_cache: Arc<RouteResponseCache>,
     lock: Arc<parking_lot::RwLock<()>>,
+    clock: Arc<dyn Clock>,
+    register_lock_timeout: Duration,
+}
+
+/// What the routes of the topics a broker group serves take from one of its brokers, besides
+/// the queues.
+#[derive(PartialEq)]
+struct BrokerRouteState {
+    broker_data: Option<BrokerData>,
+    filter_servers: Option<Vec<String>>,
+    available: bool,
 }
 
 #[allow(private_interfaces)]
@@ -101,9 +129,24 @@ impl RouteInfoManager {
             topic_queue_mapping_info_table: ArcMut::new(HashMap::new()),
             namesrv_config,
             remoting_client,
+            route_response_cache: Arc::new(Default::default()),
             lock: Arc::new(Default::default()),
+            clock: Arc::new(SystemClock),
+            register_lock_timeout: REGISTER_BROKER_LOCK_TIMEOUT,
         }
     }
+
+    /// Uses `clock` to stamp heartbeats and expire brokers.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Bounds how long a broker registration waits for the route table lock.
+    pub fn with_register_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.register_lock_timeout = timeout;
+        self
+    }
 }
 
 //impl register broker
@@ -118,12 +161,38 @@ impl RouteInfoManager {
         zone_name: Option<CheetahString>,
         _timeout_millis: Option<i64>,
         enable_acting_master: Option<bool>,
+        epoch: Option<i32>,
+        max_phy_offset: Option<i64>,
         topic_config_serialize_wrapper: TopicConfigAndMappingSerializeWrapper,
         filter_server_list: Vec<String>,
         remote_addr: SocketAddr,
-    ) -> Option<RegisterBrokerResult> {
+    ) -> Result<RegisterBrokerResult, RegistrationError> {
         let mut result = RegisterBrokerResult::default();
-        let _write = self.lock.write();
+        let broker_addr = match canonical_broker_addr(broker_addr.as_str()) {
+            Ok(canonical_addr) => canonical_addr,
+            Err(err) => {
+                warn!(
+                    "Reject broker registration, cluster:{}, brokerName:{}, brokerAddr:{}, {}",
+                    cluster_name, broker_name, broker_addr, err
+                );
+                return Err(RegistrationError::InvalidBrokerAddr {
+                    broker_addr,
+                    reason: err.to_string(),
+                });
+            }
+        };
+        let Some(_write) = self.lock.try_write_for(self.register_lock_timeout) else {
+            warn!(
+                "Reject broker registration, route table busy for {:?}, cluster:{}, \
+                 brokerName:{}, brokerAddr:{}",
+                self.register_lock_timeout, cluster_name, broker_name, broker_addr
+            );
+            return Err(RegistrationError::Timeout {
+                timeout_millis: self.register_lock_timeout.as_millis() as u64,
+            });
+        };
+        let route_broker_addr_info = BrokerAddrInfo::new(cluster_name.clone(), broker_addr.clone());
+        let route_state = self.broker_route_state(&broker_name, &route_broker_addr_info);
         //init or update cluster information
         self.cluster_addr_table
             .mut_from_ref()
@@ -165,6 +234,35 @@ impl RouteInfoManager {
             is_min_broker_id_changed = true;
         }
 
+        // A registration carrying an older epoch comes from a replica that lost the master role
+        if let Some(epoch) = epoch {
+            let recorded_epoch = broker_data
+                .broker_addrs()
+                .get(&broker_id)
+                .and_then(|addr| {
+                    self.broker_live_table
+                        .get(&BrokerAddrInfo::new(cluster_name.clone(), addr.clone()))
+                })
+                .map(|live_info| live_info.epoch());
+            if let Some(recorded_epoch) = recorded_epoch.filter(|recorded| epoch < *recorded) {
+                warn!(
+                    "Reject broker registration with stale epoch, cluster:{}, brokerName:{},                      brokerId:{}, brokerAddr:{}, epoch:{}, recorded epoch:{}",
+                    cluster_name, broker_name, broker_id, broker_addr, epoch, recorded_epoch
+                );
+                self.invalidate_routes_if_broker_changed(
+                    &broker_name,
+                    &route_broker_addr_info,
+                    route_state,
+                );
+                return Err(RegistrationError::StaleEpoch {
+                    broker_addr,
+                    broker_id,
+                    epoch,
+                    recorded: recorded_epoch,
+                });
+            }
+        }
+
         //Switch slave to master: first remove <1, IP:PORT> in rocketmq-namesrv, then add <0,
         // IP:PORT> The same IP:PORT must only have one record in brokerAddrTable
         broker_data.remove_broker_by_addr(broker_id, &broker_addr);
@@ -175,12 +273,12 @@ impl RouteInfoManager {
                 let addr_info_old =
                     BrokerAddrInfo::new(cluster_name.clone(), old_broker_addr.to_string());
                 if let Some(val) = self.broker_live_table.get(&addr_info_old) {
-                    let old_state_version = val.data_version().state_version();
-                    let new_state_version = topic_config_serialize_wrapper
+                    let old_data_version = val.data_version();
+                    let new_data_version = topic_config_serialize_wrapper
                         .topic_config_serialize_wrapper
-                        .data_version()
-                        .state_version();
-                    if old_state_version > new_state_version {
+                        .data_version();
+                    // An equal data version from another address is a replayed registration
+                    if old_data_version >= new_data_version {
                         warn!(
                             "Registered Broker conflicts with the existed one, just ignore.:  \
                              Cluster:{}, BrokerName:{}, BrokerId:{},Old BrokerAddr:{}, Old \
@@ -189,14 +287,24 @@ impl RouteInfoManager {
                             &broker_name,
                             broker_id,
                             old_broker_addr,
-                            old_state_version,
+                            old_data_version,
                             &broker_addr,
-                            new_state_version
+                            new_data_version
                         );
                         self.broker_live_table.mut_from_ref().remove(
                             BrokerAddrInfo::new(cluster_name.clone(), broker_addr.clone()).as_ref(),
                         );
-                        return Some(result);
+                        self.invalidate_routes_if_broker_changed(
+                            &broker_name,
+                            &route_broker_addr_info,
+                            route_state,
+                        );
+                        return Err(RegistrationError::StaleDataVersion {
+                            broker_addr,
+                            old_broker_addr: old_broker_addr.clone(),
+                            old: Box::new(old_data_version.clone()),
+                            new: Box::new(new_data_version.clone()),
+                        });
                     }
                 }
             }
@@ -214,7 +322,15 @@ impl RouteInfoManager {
                 broker_id,
                 broker_addr
             );
-            return None;
+            self.invalidate_routes_if_broker_changed(
+                &broker_name,
+                &route_broker_addr_info,
+                route_state,
+            );
+            return Err(RegistrationError::InconsistentTopicRegistration {
+                broker_addr,
+                broker_id,
+            });
         }
 
         let old_addr = broker_data
@@ -268,6 +384,7 @@ impl RouteInfoManager {
                                 .mut_from_ref()
                                 .remove(to_delete_topic.as_str());
                         }
+                        self.route_response_cache.invalidate(&to_delete_topic);
                     }
                 }
             }
@@ -305,6 +422,7 @@ impl RouteInfoManager {
                         .get_mut(topic)
                         .unwrap()
                         .insert(vtq_info.bname.as_ref().unwrap().clone(), vtq_info.clone());
+                    self.route_response_cache.invalidate(topic);
                 }
             }
         }
@@ -314,7 +432,7 @@ impl RouteInfoManager {
         self.broker_live_table.mut_from_ref().insert(
             broker_addr_info.clone(),
             BrokerLiveInfo::new(
-                get_current_millis() as i64,
+                self.clock.now_millis() as i64,
                 DEFAULT_BROKER_CHANNEL_EXPIRED_TIME,
                 topic_config_serialize_wrapper
                     .topic_config_serialize_wrapper
@@ -322,6 +440,10 @@ impl RouteInfoManager {
                     .clone(),
                 ha_server_addr.clone(),
                 remote_addr,
+            )
+            .with_epoch(
+                epoch.unwrap_or_default(),
+                max_phy_offset.unwrap_or_default(),
             ),
         );
         if filter_server_list.is_empty() {
@@ -359,8 +481,13 @@ impl RouteInfoManager {
                 ),
             )
         }
+        self.invalidate_routes_if_broker_changed(
+            &broker_name,
+            &route_broker_addr_info,
+            route_state,
+        );
         drop(_write);
-        Some(result)
+        Ok(result)
     }
 }
 
@@ -372,6 +499,95 @@ impl RouteInfoManager {
         )
     }
 
+    /// Snapshots the route tables. The broker live table is left out, so routes imported from
+    /// the snapshot still wait for real heartbeats before brokers count as alive.
+    pub fn dump(&self) -> RouteSnapshot {
+        let lock = self.lock.read();
+        let snapshot = RouteSnapshot {
+            topic_queue_table: self.topic_queue_table.as_ref().clone(),
+            broker_addr_table: self.broker_addr_table.as_ref().clone(),
+            cluster_addr_table: self.cluster_addr_table.as_ref().clone(),
+            topic_queue_mapping_info_table: self.topic_queue_mapping_info_table.as_ref().clone(),
+        };
+        drop(lock);
+        snapshot
+    }
+
+    /// Loads the route tables of `snapshot`.
+    ///
+    /// Without `merge` the tables are replaced. With `merge` only what the tables lack is
+    /// added, so data registered by live brokers wins over the snapshot where they overlap.
+    pub fn import(&self, snapshot: RouteSnapshot, merge: bool) {
+        let lock = self.lock.write();
+        if !merge {
+            *self.topic_queue_table.mut_from_ref() = snapshot.topic_queue_table;
+            *self.broker_addr_table.mut_from_ref() = snapshot.broker_addr_table;
+            *self.cluster_addr_table.mut_from_ref() = snapshot.cluster_addr_table;
+            *self.topic_queue_mapping_info_table.mut_from_ref() =
+                snapshot.topic_queue_mapping_info_table;
+        } else {
+            for (topic, queue_data_map) in snapshot.topic_queue_table {
+                let existing = self
+                    .topic_queue_table
+                    .mut_from_ref()
+                    .entry(topic)
+                    .or_default();
+                for (broker_name, queue_data) in queue_data_map {
+                    existing.entry(broker_name).or_insert(queue_data);
+                }
+            }
+            for (broker_name, broker_data) in snapshot.broker_addr_table {
+                match self.broker_addr_table.mut_from_ref().get_mut(&broker_name) {
+                    Some(existing) => {
+                        for (broker_id, broker_addr) in broker_data.broker_addrs() {
+                            // an address moved to another id by a live registration stays there
+                            if existing
+                                .broker_addrs()
+                                .values()
+                                .all(|addr| addr != broker_addr)
+                            {
+                                existing
+                                    .broker_addrs_mut()
+                                    .entry(*broker_id)
+                                    .or_insert_with(|| broker_addr.clone());
+                            }
+                        }
+                    }
+                    None => {
+                        self.broker_addr_table
+                            .mut_from_ref()
+                            .insert(broker_name, broker_data);
+                    }
+                }
+            }
+            for (cluster_name, broker_names) in snapshot.cluster_addr_table {
+                self.cluster_addr_table
+                    .mut_from_ref()
+                    .entry(cluster_name)
+                    .or_default()
+                    .extend(broker_names);
+            }
+            for (topic, mapping_info_map) in snapshot.topic_queue_mapping_info_table {
+                let existing = self
+                    .topic_queue_mapping_info_table
+                    .mut_from_ref()
+                    .entry(topic)
+                    .or_default();
+                for (broker_name, mapping_info) in mapping_info_map {
+                    existing.entry(broker_name).or_insert(mapping_info);
+                }
+            }
+        }
+        self.route_response_cache.invalidate_all();
+        drop(lock);
+        info!(
+            "import route snapshot, merge: {}, topics: {}, brokers: {}",
+            merge,
+            self.topic_queue_table.len(),
+            self.broker_addr_table.len()
+        );
+    }
+
     pub(crate) fn pickup_topic_route_data(&self, topic: &CheetahString) -> Option<TopicRouteData> {
         let mut topic_route_data = TopicRouteData {
             order_topic_conf: None,
@@ -417,6 +633,9 @@ impl RouteInfoManager {
                 }
             }
         }
+        if self.namesrv_config.filter_unavailable_broker_in_route {
+            self.filter_unavailable_broker(&mut topic_route_data);
+        }
         drop(lock);
         debug!("pickup_topic_route_data {:?} {:?}", topic, topic_route_data);
 
@@ -483,9 +702,152 @@ impl RouteInfoManager {
 
         None
     }
+
+    /// The encoded route of `topic`, as [`Self::pickup_topic_route_data`] gathers it. With
+    /// `enable_route_response_cache` the body is served from the cache until a change to the
+    /// route tables invalidates it.
+    pub fn pickup_topic_route_body(&self, topic: &CheetahString) -> Option<Bytes> {
+        if !self.namesrv_config.enable_route_response_cache {
+            return self
+                .pickup_topic_route_data(topic)
+                .map(|topic_route_data| Bytes::from(topic_route_data.encode()));
+        }
+        let now = self.clock.now_millis() as i64;
+        if let Some(body) = self.route_response_cache.get(topic, now) {
+            return Some(body);
+        }
+        let generation = self.route_response_cache.generation();
+        let topic_route_data = self.pickup_topic_route_data(topic)?;
+        let body = Bytes::from(topic_route_data.encode());
+        let expire_at = self.route_expire_at(&topic_route_data);
+        if now < expire_at {
+            self.route_response_cache
+                .insert(topic.clone(), body.clone(), expire_at, generation);
+        }
+        Some(body)
+    }
+
+    pub fn route_response_cache_stats(&self) -> RouteResponseCacheStats {
+        self.route_response_cache.stats()
+    }
+
+    /// Drops every cached route, for changes outside the route tables such as the name server
+    /// config.
+    pub(crate) fn invalidate_route_response_cache(&self) {
+        self.route_response_cache.invalidate_all();
+    }
 }
 
 impl RouteInfoManager {
+    /// Removes the broker groups, and their queues, whose every address is gone from the live
+    /// table or has not sent a heartbeat within [`UNAVAILABLE_BROKER_GRACE_TIME`]. The route is
+    /// left untouched when no group would remain: the name server missing heartbeats is more
+    /// likely than the whole cluster being down.
+    fn filter_unavailable_broker(&self, topic_route_data: &mut TopicRouteData) {
+        let now = self.clock.now_millis() as i64;
+        let is_available = |broker_data: &BrokerData| {
+            broker_data.broker_addrs().values().any(|broker_addr| {
+                self.broker_live_table
+                    .get(&BrokerAddrInfo::new(
+                        broker_data.cluster(),
+                        broker_addr.clone(),
+                    ))
+                    .is_some_and(|live_info| {
+                        now - live_info.last_update_timestamp <= UNAVAILABLE_BROKER_GRACE_TIME
+                    })
+            })
+        };
+        let unavailable: HashSet<CheetahString> = topic_route_data
+            .broker_datas
+            .iter()
+            .filter(|broker_data| !is_available(broker_data))
+            .map(|broker_data| broker_data.broker_name().clone())
+            .collect();
+        if unavailable.is_empty() || unavailable.len() == topic_route_data.broker_datas.len() {
+            return;
+        }
+        warn!(
+            "filter unavailable brokers {:?} out of the topic route",
+            unavailable
+        );
+        topic_route_data
+            .broker_datas
+            .retain(|broker_data| !unavailable.contains(broker_data.broker_name()));
+        topic_route_data
+            .queue_datas
+            .retain(|queue_data| !unavailable.contains(queue_data.broker_name()));
+        topic_route_data
+            .filter_server_table
+            .retain(|broker_addr, _| {
+                topic_route_data.broker_datas.iter().any(|broker_data| {
+                    broker_data
+                        .broker_addrs()
+                        .values()
+                        .any(|addr| addr == broker_addr)
+                })
+            });
+    }
+
+    /// When the broker groups of `topic_route_data` could stop counting as available, if
+    /// unavailable groups are filtered from routes.
+    fn route_expire_at(&self, topic_route_data: &TopicRouteData) -> i64 {
+        if !self.namesrv_config.filter_unavailable_broker_in_route {
+            return i64::MAX;
+        }
+        topic_route_data
+            .broker_datas
+            .iter()
+            .map(|broker_data| {
+                broker_data
+                    .broker_addrs()
+                    .values()
+                    .filter_map(|broker_addr| {
+                        self.broker_live_table.get(&BrokerAddrInfo::new(
+                            broker_data.cluster(),
+                            broker_addr.clone(),
+                        ))
+                    })
+                    .map(|live_info| {
+                        live_info.last_update_timestamp + UNAVAILABLE_BROKER_GRACE_TIME
+                    })
+                    .max()
+                    .unwrap_or(i64::MIN)
+            })
+            .min()
+            .unwrap_or(i64::MAX)
+    }
+
+    fn broker_route_state(
+        &self,
+        broker_name: &CheetahString,
+        broker_addr_info: &BrokerAddrInfo,
+    ) -> BrokerRouteState {
+        let now = self.clock.now_millis() as i64;
+        BrokerRouteState {
+            broker_data: self.broker_addr_table.get(broker_name).cloned(),
+            filter_servers: self.filter_server_table.get(broker_addr_info).cloned(),
+            available: self
+                .broker_live_table
+                .get(broker_addr_info)
+                .is_some_and(|live_info| {
+                    now - live_info.last_update_timestamp <= UNAVAILABLE_BROKER_GRACE_TIME
+                }),
+        }
+    }
+
+    /// Drops the cached routes if registering the broker behind `broker_addr_info` changed
+    /// what they take from it. Queue changes invalidate their topic where they are made.
+    fn invalidate_routes_if_broker_changed(
+        &self,
+        broker_name: &CheetahString,
+        broker_addr_info: &BrokerAddrInfo,
+        before: BrokerRouteState,
+    ) {
+        if self.broker_route_state(broker_name, broker_addr_info) != before {
+            self.route_response_cache.invalidate_all();
+        }
+    }
+
     fn topic_set_of_broker_name(&self, broker_name: &str) -> HashSet<String> {
         let mut topic_of_broker = HashSet::new();
         for (key, value) in self.topic_queue_table.iter() {
@@ -564,6 +926,8 @@ impl RouteInfoManager {
             let existed_qd = queue_data_map_inner.get(broker_name);
             if existed_qd.is_none() {
                 queue_data_map_inner.insert(broker_name.clone(), queue_data);
+                self.route_response_cache
+                    .invalidate(topic_config.topic_name.as_ref().unwrap());
             } else {
                 let unwrap = existed_qd.unwrap();
                 if unwrap != &queue_data {
@@ -574,6 +938,8 @@ impl RouteInfoManager {
                         queue_data
                     );
                     queue_data_map_inner.insert(broker_name.clone(), queue_data);
+                    self.route_response_cache
+                        .invalidate(topic_config.topic_name.as_ref().unwrap());
                 }
             }
         } else {
@@ -588,6 +954,8 @@ impl RouteInfoManager {
                 topic_config.topic_name.as_ref().unwrap().clone(),
                 queue_data_map_inner,
             );
+            self.route_response_cache
+                .invalidate(topic_config.topic_name.as_ref().unwrap());
         }
     }
 
@@ -657,7 +1025,13 @@ impl RouteInfoManager {
     ) {
         let broker_addr_info = BrokerAddrInfo::new(cluster_name, broker_addr);
         if let Some(value) = self.broker_live_table.get_mut(broker_addr_info.as_ref()) {
-            value.last_update_timestamp = TimeUtils::get_current_millis() as i64;
+            let now = self.clock.now_millis() as i64;
+            let was_available = now - value.last_update_timestamp <= UNAVAILABLE_BROKER_GRACE_TIME;
+            value.last_update_timestamp = now;
+            // the broker group may be back in the routes it was filtered out of
+            if !was_available {
+                self.route_response_cache.invalidate_all();
+            }
         }
     }
 
@@ -670,6 +1044,18 @@ impl RouteInfoManager {
         let lock_ = self.lock.read();
         if let Some(broker_data) = self.broker_addr_table.get(broker_name) {
             group_member.broker_addrs = broker_data.broker_addrs().clone();
+            group_member.broker_epochs = broker_data
+                .broker_addrs()
+                .iter()
+                .filter_map(|(broker_id, broker_addr)| {
+                    self.broker_live_table
+                        .get(&BrokerAddrInfo::new(
+                            cluster_name.clone(),
+                            broker_addr.clone(),
+                        ))
+                        .map(|live_info| (*broker_id, live_info.epoch()))
+                })
+                .collect();
         }
         drop(lock_);
         Some(group_member)
@@ -698,7 +1084,7 @@ impl RouteInfoManager {
         request_code: RequestCode,
     ) -> i32 {
         let mut topic_cnt = 0;
-        for (_topic, qd_map) in self.topic_queue_table.mut_from_ref().iter_mut() {
+        for (topic, qd_map) in self.topic_queue_table.mut_from_ref().iter_mut() {
             let qd = qd_map.get_mut(broker_name);
             if qd.is_none() {
                 continue;
@@ -715,23 +1101,27 @@ impl RouteInfoManager {
                 _ => {}
             }
             qd.perm = perm;
+            self.route_response_cache.invalidate(topic);
             topic_cnt += 1;
         }
         topic_cnt
     }
 
-    pub(crate) fn get_all_topic_list(&self) -> TopicList {
+    /// Names of all topics, only those starting with `topic_prefix` if given. They are taken
+    /// under the lock, which only bumps the reference count of each name.
+    pub(crate) fn get_all_topic_list(
+        &self,
+        topic_prefix: Option<&str>,
+    ) -> impl Iterator<Item = CheetahString> {
         let lock = self.lock.read();
         let topics = self
             .topic_queue_table
             .keys()
+            .filter(|topic| topic_prefix.map_or(true, |prefix| topic.starts_with(prefix)))
             .cloned()
             .collect::<Vec<CheetahString>>();
         drop(lock);
-        TopicList {
-            topic_list: topics,
-            broker_addr: None,
-        }
+        topics.into_iter()
     }
 
     pub(crate) fn delete_topic(
@@ -759,6 +1149,7 @@ impl RouteInfoManager {
         } else {
             self.topic_queue_table.mut_from_ref().remove(&topic);
         }
+        self.route_response_cache.invalidate(&topic);
         drop(lock)
     }
 
@@ -767,6 +1158,7 @@ impl RouteInfoManager {
             return;
         }
         let lock = self.lock.write();
+        self.route_response_cache.invalidate(&topic);
         if !self.topic_queue_table.contains_key(&topic) {
             self.topic_queue_table
                 .mut_from_ref()
@@ -828,6 +1220,14 @@ impl RouteInfoManager {
                 topic_list.push(broker_name.clone());
             });
         }
+        for (topic, queue_data_map) in self.topic_queue_table.iter() {
+            if queue_data_map.values().any(|queue_data| {
+                TopicSysFlag::has_retry_flag(queue_data.topic_sys_flag())
+                    || TopicSysFlag::has_dlq_flag(queue_data.topic_sys_flag())
+            }) {
+                topic_list.push(topic.clone());
+            }
+        }
         if !self.broker_addr_table.is_empty() {
             for broker_addr in self.broker_addr_table.values() {
                 let broker_addrs = broker_addr.broker_addrs();
@@ -890,10 +1290,9 @@ impl RouteInfoManager {
     }
 
     pub fn scan_not_active_broker(&mut self) {
+        let now = self.clock.now_millis() as i64;
         for (broker_addr_info, broker_live_info) in self.broker_live_table.clone().iter() {
-            if broker_live_info.heartbeat_timeout_millis + broker_live_info.last_update_timestamp
-                < TimeUtils::get_current_millis() as i64
-            {
+            if broker_live_info.is_expired(now) {
                 self.on_connection_disconnected(broker_addr_info);
             }
         }
@@ -951,7 +1350,8 @@ impl RouteInfoManager {
         for un_register_request in un_register_requests {
             let broker_name = &un_register_request.broker_name;
             let cluster_name = &un_register_request.cluster_name;
-            let broker_addr = &un_register_request.broker_addr;
+            let broker_addr = &canonical_broker_addr(un_register_request.broker_addr.as_str())
+                .unwrap_or_else(|_| un_register_request.broker_addr.clone());
 
             let broker_addr_info = BrokerAddrInfo::new(cluster_name.clone(), broker_addr.clone());
             let pre = self.broker_live_table.remove(&broker_addr_info);
@@ -985,14 +1385,15 @@ impl RouteInfoManager {
                         remove_broker_id_set.insert(*broker_id);
                     }
                 }
-                let _removed = if remove_broker_id_set.is_empty() {
-                    for broker_id in remove_broker_id_set {
-                        broker_data.broker_addrs_mut().remove(&broker_id);
-                    }
-                    true
-                } else {
-                    false
-                };
+                let removed = !remove_broker_id_set.is_empty();
+                for broker_id in remove_broker_id_set {
+                    broker_data.broker_addrs_mut().remove(&broker_id);
+                }
+                info!(
+                    "unregisterBroker, remove addr from brokerAddrTable {}, {}",
+                    if removed { "OK" } else { "Fail" },
+                    &broker_addr_info
+                );
 
                 if broker_data.broker_addrs_mut().is_empty() {
                     self.broker_addr_table.remove(broker_name.as_str());
@@ -1023,6 +1424,7 @@ impl RouteInfoManager {
             }
         }
         self.clean_topic_by_un_register_requests(remove_broker, reduced_broker);
+        self.route_response_cache.invalidate_all();
         if !need_notify_broker_map.is_empty() && self.namesrv_config.notify_min_broker_id_changed {
             for (broker_name, broker_status_change_info) in need_notify_broker_map {
                 let broker_data = self.broker_addr_table.get(&broker_name);
@@ -1128,6 +1530,7 @@ impl RouteInfoManager {
     }
 
     pub fn connection_disconnected(&mut self, socket_addr: SocketAddr) {
+        let socket_addr = canonical_socket_addr(socket_addr);
         let mut broker_addr_info = None;
         for (bai, bli) in self.broker_live_table.as_ref() {
             if bli.remote_addr == socket_addr {
@@ -1157,3 +1560,1026 @@ impl RouteInfoManager {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rocketmq_common::common::system_clock::MockClock;
+    use rocketmq_remoting::code::response_code::ResponseCode;
+    use rocketmq_remoting::protocol::RemotingDeserializable;
+    use rocketmq_remoting::protocol::RemotingSerializable;
+    use rocketmq_remoting::request_processor::default_request_processor::DefaultRemotingRequestProcessor;
+    use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
+
+    use super::*;
+
+    fn route_info_manager() -> RouteInfoManager {
+        RouteInfoManager::new(
+            ArcMut::new(NamesrvConfig::default()),
+            ArcMut::new(RocketmqDefaultClient::new(
+                Arc::new(TokioClientConfig::default()),
+                DefaultRemotingRequestProcessor,
+            )),
+        )
+    }
+
+    fn register(manager: &RouteInfoManager, broker_addr: &str, remote_addr: SocketAddr) -> bool {
+        register_with_epoch(manager, broker_addr, remote_addr, None)
+    }
+
+    fn register_with_epoch(
+        manager: &RouteInfoManager,
+        broker_addr: &str,
+        remote_addr: SocketAddr,
+        epoch: Option<i32>,
+    ) -> bool {
+        try_register(
+            manager,
+            broker_addr,
+            remote_addr,
+            epoch,
+            TopicConfigAndMappingSerializeWrapper::default(),
+        )
+        .is_ok()
+    }
+
+    fn try_register(
+        manager: &RouteInfoManager,
+        broker_addr: &str,
+        remote_addr: SocketAddr,
+        epoch: Option<i32>,
+        topic_config_wrapper: TopicConfigAndMappingSerializeWrapper,
+    ) -> Result<RegisterBrokerResult, RegistrationError> {
+        manager.register_broker(
+            CheetahString::from_static_str("DefaultCluster"),
+            CheetahString::from_slice(broker_addr),
+            CheetahString::from_static_str("broker-a"),
+            mix_all::MASTER_ID,
+            CheetahString::from_slice(broker_addr),
+            None,
+            None,
+            None,
+            epoch,
+            epoch.map(|epoch| epoch as i64 * 1024),
+            topic_config_wrapper,
+            vec![],
+            remote_addr,
+        )
+    }
+
+    #[test]
+    fn register_broker_ipv6_forms_map_to_single_entry() {
+        let manager = route_info_manager();
+        let remote_addr: SocketAddr = "[::1]:50000".parse().unwrap();
+        assert!(register(&manager, "[::1]:10911", remote_addr));
+        assert!(register(&manager, "0:0:0:0:0:0:0:1:10911", remote_addr));
+        assert!(register(&manager, "[0:0:0:0:0:0:0:1]:10911", remote_addr));
+        assert_eq!(manager.broker_live_table.len(), 1);
+        let broker_data = manager.broker_addr_table.get("broker-a").unwrap();
+        assert_eq!(broker_data.broker_addrs().len(), 1);
+        assert_eq!(
+            broker_data.broker_addrs().get(&mix_all::MASTER_ID).unwrap(),
+            "[::1]:10911"
+        );
+    }
+
+    #[test]
+    fn register_broker_hostname_is_case_insensitive() {
+        let manager = route_info_manager();
+        let remote_addr: SocketAddr = "10.0.0.1:50000".parse().unwrap();
+        assert!(register(&manager, "Broker-A.local:10911", remote_addr));
+        assert!(register(&manager, "broker-a.LOCAL:10911", remote_addr));
+        assert_eq!(manager.broker_live_table.len(), 1);
+        assert!(manager.broker_live_table.contains_key(&BrokerAddrInfo::new(
+            "DefaultCluster",
+            "broker-a.local:10911"
+        )));
+    }
+
+    #[test]
+    fn register_broker_ipv4_and_rejects_missing_port() {
+        let manager = route_info_manager();
+        let remote_addr: SocketAddr = "10.0.0.1:50000".parse().unwrap();
+        assert!(!register(&manager, "10.0.0.1", remote_addr));
+        assert!(manager.broker_live_table.is_empty());
+        assert!(register(&manager, "10.0.0.1:10911", remote_addr));
+        assert!(register(&manager, " 10.0.0.1:10911", remote_addr));
+        assert_eq!(manager.broker_live_table.len(), 1);
+    }
+
+    #[test]
+    fn connection_disconnected_matches_ipv6_peer() {
+        let mut manager = route_info_manager();
+        assert!(register(
+            &manager,
+            "[::1]:10911",
+            "[::1]:50000".parse().unwrap()
+        ));
+        manager.connection_disconnected("[0:0:0:0:0:0:0:1]:50000".parse().unwrap());
+        assert!(manager.broker_live_table.is_empty());
+        assert!(!manager.broker_addr_table.contains_key("broker-a"));
+    }
+
+    #[test]
+    fn connection_disconnected_matches_ipv4_mapped_peer() {
+        let mut manager = route_info_manager();
+        assert!(register(
+            &manager,
+            "10.0.0.1:10911",
+            "10.0.0.1:50000".parse().unwrap()
+        ));
+        manager.connection_disconnected("[::ffff:10.0.0.1]:50000".parse().unwrap());
+        assert!(manager.broker_live_table.is_empty());
+    }
+
+    #[test]
+    fn register_broker_rejects_stale_epoch() {
+        let mut manager = route_info_manager();
+        let remote_addr: SocketAddr = "10.0.0.1:50000".parse().unwrap();
+        assert!(register_with_epoch(
+            &manager,
+            "10.0.0.1:10911",
+            remote_addr,
+            Some(2)
+        ));
+        let error = try_register(
+            &manager,
+            "10.0.0.2:10911",
+            remote_addr,
+            Some(1),
+            TopicConfigAndMappingSerializeWrapper::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            RegistrationError::StaleEpoch {
+                epoch: 1,
+                recorded: 2,
+                ..
+            }
+        ));
+        let broker_data = manager.broker_addr_table.get("broker-a").unwrap();
+        assert_eq!(
+            broker_data.broker_addrs().get(&mix_all::MASTER_ID).unwrap(),
+            "10.0.0.1:10911"
+        );
+
+        let group = manager
+            .get_broker_member_group(
+                &CheetahString::from_static_str("DefaultCluster"),
+                &CheetahString::from_static_str("broker-a"),
+            )
+            .unwrap();
+        assert_eq!(group.broker_epochs.get(&mix_all::MASTER_ID), Some(&2));
+    }
+
+    #[test]
+    fn register_broker_rejects_stale_data_version_from_another_address() {
+        let manager = route_info_manager();
+        let mut topic_config_wrapper = TopicConfigAndMappingSerializeWrapper::default();
+        topic_config_wrapper
+            .topic_config_serialize_wrapper
+            .data_version
+            .next_version_with(1);
+        assert!(try_register(
+            &manager,
+            "10.0.0.1:10911",
+            "10.0.0.1:50000".parse().unwrap(),
+            None,
+            topic_config_wrapper,
+        )
+        .is_ok());
+
+        let error = try_register(
+            &manager,
+            "10.0.0.2:10911",
+            "10.0.0.2:50000".parse().unwrap(),
+            None,
+            TopicConfigAndMappingSerializeWrapper::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            &error,
+            RegistrationError::StaleDataVersion { old_broker_addr, old, new, .. }
+                if old_broker_addr == "10.0.0.1:10911" && old > new
+        ));
+        assert_eq!(error.response_code(), ResponseCode::SystemError);
+        let broker_data = manager.broker_addr_table.get("broker-a").unwrap();
+        assert_eq!(
+            broker_data.broker_addrs().get(&mix_all::MASTER_ID).unwrap(),
+            "10.0.0.1:10911"
+        );
+    }
+
+    #[test]
+    fn register_broker_rejects_single_topic_from_unregistered_broker() {
+        let manager = route_info_manager();
+        let mut topic_config_wrapper = TopicConfigAndMappingSerializeWrapper::default();
+        topic_config_wrapper
+            .topic_config_serialize_wrapper
+            .topic_config_table
+            .insert("TopicA".into(), TopicConfig::with_queues("TopicA", 4, 4));
+        let error = try_register(
+            &manager,
+            "10.0.0.1:10911",
+            "10.0.0.1:50000".parse().unwrap(),
+            None,
+            topic_config_wrapper,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            RegistrationError::InconsistentTopicRegistration {
+                broker_id: mix_all::MASTER_ID,
+                ..
+            }
+        ));
+        assert!(manager.broker_live_table.is_empty());
+    }
+
+    #[test]
+    fn register_broker_rejects_invalid_address() {
+        let manager = route_info_manager();
+        let error = try_register(
+            &manager,
+            "10.0.0.1",
+            "10.0.0.1:50000".parse().unwrap(),
+            None,
+            TopicConfigAndMappingSerializeWrapper::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            RegistrationError::InvalidBrokerAddr { ref broker_addr, .. } if broker_addr == "10.0.0.1"
+        ));
+        assert!(error
+            .to_string()
+            .starts_with("broker registers with an invalid address 10.0.0.1: "));
+    }
+
+    #[test]
+    fn register_broker_times_out_on_busy_route_table() {
+        let manager = route_info_manager().with_register_lock_timeout(Duration::from_millis(10));
+        let read = manager.lock.read();
+        let error = try_register(
+            &manager,
+            "10.0.0.1:10911",
+            "10.0.0.1:50000".parse().unwrap(),
+            None,
+            TopicConfigAndMappingSerializeWrapper::default(),
+        )
+        .unwrap_err();
+        drop(read);
+        assert!(matches!(
+            error,
+            RegistrationError::Timeout { timeout_millis: 10 }
+        ));
+        assert_eq!(error.response_code(), ResponseCode::SystemBusy);
+        assert!(register(
+            &manager,
+            "10.0.0.1:10911",
+            "10.0.0.1:50000".parse().unwrap()
+        ));
+    }
+
+    #[test]
+    fn register_broker_bumps_epoch_on_re_registration() {
+        let manager = route_info_manager();
+        let remote_addr: SocketAddr = "10.0.0.1:50000".parse().unwrap();
+        let live_info = |manager: &RouteInfoManager| {
+            manager
+                .broker_live_table
+                .get(&BrokerAddrInfo::new("DefaultCluster", "10.0.0.1:10911"))
+                .map(|live_info| (live_info.epoch(), live_info.max_phy_offset()))
+        };
+        assert!(register_with_epoch(
+            &manager,
+            "10.0.0.1:10911",
+            remote_addr,
+            Some(1)
+        ));
+        assert_eq!(live_info(&manager), Some((1, 1024)));
+        assert!(register_with_epoch(
+            &manager,
+            "10.0.0.1:10911",
+            remote_addr,
+            Some(1)
+        ));
+        assert!(register_with_epoch(
+            &manager,
+            "10.0.0.1:10911",
+            remote_addr,
+            Some(3)
+        ));
+        assert_eq!(live_info(&manager), Some((3, 3 * 1024)));
+        // brokers outside controller mode do not report an epoch
+        assert!(register(&manager, "10.0.0.1:10911", remote_addr));
+    }
+
+    #[test]
+    fn scan_not_active_broker_expires_silent_brokers() {
+        let clock = Arc::new(MockClock::new(1_000_000));
+        let mut manager = route_info_manager().with_clock(clock.clone());
+        let remote_addr: SocketAddr = "10.0.0.1:50000".parse().unwrap();
+        assert!(register(&manager, "10.0.0.1:10911", remote_addr));
+
+        clock.advance(Duration::from_millis(
+            DEFAULT_BROKER_CHANNEL_EXPIRED_TIME as u64,
+        ));
+        manager.scan_not_active_broker();
+        assert_eq!(manager.broker_live_table.len(), 1);
+
+        clock.advance(Duration::from_millis(1));
+        manager.scan_not_active_broker();
+        assert!(manager.broker_live_table.is_empty());
+        assert!(!manager.broker_addr_table.contains_key("broker-a"));
+    }
+
+    #[test]
+    fn system_topic_list_contains_cluster_and_broker_names() {
+        let manager = route_info_manager();
+        let remote_addr: SocketAddr = "10.0.0.1:50000".parse().unwrap();
+        assert!(register(&manager, "10.0.0.1:10911", remote_addr));
+
+        let topic_list = manager.get_system_topic_list();
+        assert!(topic_list.topic_list.contains(&"DefaultCluster".into()));
+        assert!(topic_list.topic_list.contains(&"broker-a".into()));
+        assert_eq!(topic_list.broker_addr.as_deref(), Some("10.0.0.1:10911"));
+    }
+
+    #[test]
+    fn system_topic_list_contains_retry_and_dlq_topics() {
+        let manager = route_info_manager();
+        let mut topic_config_wrapper = TopicConfigAndMappingSerializeWrapper::default();
+        for (topic, topic_sys_flag) in [
+            ("%RETRY%GroupA", TopicSysFlag::set_retry_flag(0)),
+            ("%DLQ%GroupA", TopicSysFlag::set_dlq_flag(0)),
+            ("TopicA", 0),
+        ] {
+            let mut topic_config = TopicConfig::with_queues(topic, 1, 1);
+            topic_config.topic_sys_flag = topic_sys_flag;
+            topic_config_wrapper
+                .topic_config_serialize_wrapper
+                .topic_config_table
+                .insert(topic.into(), topic_config);
+        }
+        assert!(manager
+            .register_broker(
+                CheetahString::from_static_str("DefaultCluster"),
+                CheetahString::from_static_str("10.0.0.1:10911"),
+                CheetahString::from_static_str("broker-a"),
+                mix_all::MASTER_ID,
+                CheetahString::from_static_str("10.0.0.1:10911"),
+                None,
+                None,
+                None,
+                None,
+                None,
+                topic_config_wrapper,
+                vec![],
+                "10.0.0.1:50000".parse().unwrap(),
+            )
+            .is_ok());
+
+        let topic_list = manager.get_system_topic_list();
+        assert!(topic_list.topic_list.contains(&"%RETRY%GroupA".into()));
+        assert!(topic_list.topic_list.contains(&"%DLQ%GroupA".into()));
+        assert!(!topic_list.topic_list.contains(&"TopicA".into()));
+    }
+
+    fn register_topic(
+        manager: &RouteInfoManager,
+        broker_name: &str,
+        broker_id: u64,
+        broker_addr: &str,
+        topic: &str,
+    ) {
+        register_topic_config(
+            manager,
+            broker_name,
+            broker_id,
+            broker_addr,
+            TopicConfig::with_queues(topic, 4, 4),
+            vec![],
+        );
+    }
+
+    fn register_topic_config(
+        manager: &RouteInfoManager,
+        broker_name: &str,
+        broker_id: u64,
+        broker_addr: &str,
+        topic_config: TopicConfig,
+        filter_server_list: Vec<String>,
+    ) {
+        let mut topic_config_wrapper = TopicConfigAndMappingSerializeWrapper::default();
+        topic_config_wrapper
+            .topic_config_serialize_wrapper
+            .topic_config_table
+            .insert(topic_config.topic_name.clone().unwrap(), topic_config);
+        // like a real broker, also register the topic named after the broker
+        topic_config_wrapper
+            .topic_config_serialize_wrapper
+            .topic_config_table
+            .insert(
+                broker_name.into(),
+                TopicConfig::with_queues(broker_name, 1, 1),
+            );
+        let remote_addr: SocketAddr = broker_addr.parse().unwrap();
+        assert!(manager
+            .register_broker(
+                CheetahString::from_static_str("DefaultCluster"),
+                CheetahString::from_slice(broker_addr),
+                CheetahString::from_slice(broker_name),
+                broker_id,
+                CheetahString::from_slice(broker_addr),
+                None,
+                None,
+                None,
+                None,
+                None,
+                topic_config_wrapper,
+                filter_server_list,
+                remote_addr,
+            )
+            .is_ok());
+    }
+
+    fn route_broker_names(manager: &RouteInfoManager, topic: &str) -> (Vec<String>, Vec<String>) {
+        let route = manager.pickup_topic_route_data(&topic.into()).unwrap();
+        let mut broker_names: Vec<String> = route
+            .broker_datas
+            .iter()
+            .map(|broker_data| broker_data.broker_name().to_string())
+            .collect();
+        let mut queue_broker_names: Vec<String> = route
+            .queue_datas
+            .iter()
+            .map(|queue_data| queue_data.broker_name().to_string())
+            .collect();
+        broker_names.sort();
+        queue_broker_names.sort();
+        (broker_names, queue_broker_names)
+    }
+
+    #[test]
+    fn unavailable_broker_group_is_filtered_from_route() {
+        let clock = Arc::new(MockClock::new(1_000_000));
+        let mut manager = RouteInfoManager::new(
+            ArcMut::new(NamesrvConfig {
+                filter_unavailable_broker_in_route: true,
+                ..NamesrvConfig::default()
+            }),
+            ArcMut::new(RocketmqDefaultClient::new(
+                Arc::new(TokioClientConfig::default()),
+                DefaultRemotingRequestProcessor,
+            )),
+        )
+        .with_clock(clock.clone());
+        register_topic(
+            &manager,
+            "broker-a",
+            mix_all::MASTER_ID,
+            "10.0.0.1:10911",
+            "TopicTest",
+        );
+        register_topic(&manager, "broker-a", 1, "10.0.0.2:10911", "TopicTest");
+        register_topic(
+            &manager,
+            "broker-b",
+            mix_all::MASTER_ID,
+            "10.0.0.3:10911",
+            "TopicTest",
+        );
+        let both = vec!["broker-a".to_string(), "broker-b".to_string()];
+        let only_b = vec!["broker-b".to_string()];
+        assert_eq!(
+            route_broker_names(&manager, "TopicTest"),
+            (both.clone(), both.clone())
+        );
+
+        // broker-a's master and slave both stop sending heartbeats
+        let heartbeat_b = |manager: &mut RouteInfoManager| {
+            manager.update_broker_info_update_timestamp(
+                CheetahString::from_static_str("DefaultCluster"),
+                CheetahString::from_static_str("10.0.0.3:10911"),
+            )
+        };
+        clock.advance(Duration::from_millis(BROKER_HEARTBEAT_INTERVAL as u64));
+        heartbeat_b(&mut manager);
+        assert_eq!(
+            route_broker_names(&manager, "TopicTest"),
+            (both.clone(), both.clone())
+        );
+
+        clock.advance(Duration::from_millis(BROKER_HEARTBEAT_INTERVAL as u64 + 1));
+        heartbeat_b(&mut manager);
+        assert_eq!(
+            route_broker_names(&manager, "TopicTest"),
+            (only_b.clone(), only_b.clone())
+        );
+
+        // one live address is enough to keep the group
+        manager.update_broker_info_update_timestamp(
+            CheetahString::from_static_str("DefaultCluster"),
+            CheetahString::from_static_str("10.0.0.2:10911"),
+        );
+        assert_eq!(
+            route_broker_names(&manager, "TopicTest"),
+            (both.clone(), both.clone())
+        );
+
+        // addresses gone from the live table count as unavailable
+        manager
+            .broker_live_table
+            .retain(|broker_addr_info, _| broker_addr_info.broker_addr != "10.0.0.2:10911");
+        assert_eq!(
+            route_broker_names(&manager, "TopicTest"),
+            (only_b.clone(), only_b)
+        );
+
+        // the route is kept as is when no group would remain
+        clock.advance(Duration::from_millis(
+            UNAVAILABLE_BROKER_GRACE_TIME as u64 + 1,
+        ));
+        assert_eq!(
+            route_broker_names(&manager, "TopicTest"),
+            (both.clone(), both.clone())
+        );
+
+        manager.namesrv_config.filter_unavailable_broker_in_route = false;
+        heartbeat_b(&mut manager);
+        assert_eq!(
+            route_broker_names(&manager, "TopicTest"),
+            (both.clone(), both)
+        );
+    }
+
+    #[test]
+    fn dump_and_import_round_trip() {
+        let manager = route_info_manager();
+        register_topic(
+            &manager,
+            "broker-a",
+            mix_all::MASTER_ID,
+            "10.0.0.1:10911",
+            "TopicTest",
+        );
+        register_topic(&manager, "broker-a", 1, "10.0.0.2:10911", "TopicTest");
+        let snapshot = manager.dump();
+        assert!(snapshot.topic_queue_table.contains_key("TopicTest"));
+        assert_eq!(
+            snapshot.broker_addr_table["broker-a"].broker_addrs().len(),
+            2
+        );
+
+        let decoded = RouteSnapshot::decode(&snapshot.encode()).unwrap();
+        assert_eq!(decoded, snapshot);
+
+        let imported = route_info_manager();
+        imported.import(decoded, false);
+        assert_eq!(imported.dump(), snapshot);
+        assert!(imported.broker_live_table.is_empty());
+        assert_eq!(
+            route_broker_names(&imported, "TopicTest"),
+            route_broker_names(&manager, "TopicTest")
+        );
+    }
+
+    #[test]
+    fn import_merge_keeps_live_data() {
+        let stale = route_info_manager();
+        register_topic(
+            &stale,
+            "broker-a",
+            mix_all::MASTER_ID,
+            "10.0.0.1:10911",
+            "TopicTest",
+        );
+        register_topic(
+            &stale,
+            "broker-b",
+            mix_all::MASTER_ID,
+            "10.0.0.3:10911",
+            "TopicOld",
+        );
+        let mut snapshot = stale.dump();
+        snapshot
+            .topic_queue_table
+            .get_mut("TopicTest")
+            .unwrap()
+            .get_mut("broker-a")
+            .unwrap()
+            .write_queue_nums = 1;
+
+        // broker-a came back with a new slave before the snapshot was imported
+        let manager = route_info_manager();
+        register_topic(
+            &manager,
+            "broker-a",
+            mix_all::MASTER_ID,
+            "10.0.0.1:10911",
+            "TopicTest",
+        );
+        register_topic(&manager, "broker-a", 1, "10.0.0.2:10911", "TopicTest");
+        manager.import(snapshot, true);
+
+        let topic_queue_table = manager.topic_queue_table.as_ref();
+        assert_eq!(
+            topic_queue_table["TopicTest"]["broker-a"].write_queue_nums,
+            4
+        );
+        assert!(topic_queue_table["TopicOld"].contains_key("broker-b"));
+        assert_eq!(
+            manager.broker_addr_table["broker-a"].broker_addrs().len(),
+            2
+        );
+        assert!(manager.broker_addr_table.contains_key("broker-b"));
+        assert_eq!(
+            manager.cluster_addr_table["DefaultCluster"],
+            HashSet::from(["broker-a".into(), "broker-b".into()])
+        );
+        // only brokers that registered are alive
+        assert_eq!(manager.broker_live_table.len(), 2);
+
+        // replacing drops what the snapshot does not have
+        manager.import(stale.dump(), false);
+        assert_eq!(
+            manager.broker_addr_table["broker-a"].broker_addrs().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn heartbeat_keeps_broker_alive() {
+        let clock = Arc::new(MockClock::new(1_000_000));
+        let mut manager = route_info_manager().with_clock(clock.clone());
+        let remote_addr: SocketAddr = "10.0.0.1:50000".parse().unwrap();
+        assert!(register(&manager, "10.0.0.1:10911", remote_addr));
+
+        for _ in 0..3 {
+            clock.advance(Duration::from_secs(100));
+            manager.update_broker_info_update_timestamp(
+                CheetahString::from_static_str("DefaultCluster"),
+                CheetahString::from_static_str("10.0.0.1:10911"),
+            );
+            manager.scan_not_active_broker();
+            assert_eq!(manager.broker_live_table.len(), 1);
+        }
+    }
+
+    #[test]
+    fn all_topic_list_can_be_narrowed_to_a_prefix() {
+        let mut manager = route_info_manager();
+        for topic in ["order-created", "order-paid", "payment", "%RETRY%order"] {
+            manager
+                .topic_queue_table
+                .insert(CheetahString::from_static_str(topic), HashMap::new());
+        }
+        let sorted = |topics: Vec<CheetahString>| {
+            let mut topics = topics;
+            topics.sort();
+            topics
+        };
+
+        assert_eq!(manager.get_all_topic_list(None).count(), 4);
+        assert_eq!(
+            sorted(manager.get_all_topic_list(Some("order-")).collect()),
+            vec!["order-created", "order-paid"]
+        );
+        assert_eq!(manager.get_all_topic_list(Some("missing")).count(), 0);
+    }
+
+    fn caching_route_info_manager(filter_unavailable_broker_in_route: bool) -> RouteInfoManager {
+        RouteInfoManager::new(
+            ArcMut::new(NamesrvConfig {
+                enable_route_response_cache: true,
+                filter_unavailable_broker_in_route,
+                ..NamesrvConfig::default()
+            }),
+            ArcMut::new(RocketmqDefaultClient::new(
+                Arc::new(TokioClientConfig::default()),
+                DefaultRemotingRequestProcessor,
+            )),
+        )
+    }
+
+    /// The route of `topic` as served twice in a row, the second time from the cache.
+    fn cached_route(manager: &RouteInfoManager, topic: &str) -> Option<TopicRouteData> {
+        let body = manager.pickup_topic_route_body(&topic.into())?;
+        let hits = manager.route_response_cache_stats().hits;
+        assert_eq!(
+            manager.pickup_topic_route_body(&topic.into()),
+            Some(body.clone())
+        );
+        assert_eq!(manager.route_response_cache_stats().hits, hits + 1);
+        Some(TopicRouteData::decode(&body).unwrap())
+    }
+
+    fn queue_data<'a>(route: &'a TopicRouteData, broker_name: &str) -> &'a QueueData {
+        route
+            .queue_datas
+            .iter()
+            .find(|queue_data| queue_data.broker_name() == broker_name)
+            .unwrap()
+    }
+
+    #[test]
+    fn route_response_is_cached_only_when_enabled() {
+        let manager = route_info_manager();
+        register_topic(
+            &manager,
+            "broker-a",
+            mix_all::MASTER_ID,
+            "10.0.0.1:10911",
+            "TopicTest",
+        );
+        assert!(manager
+            .pickup_topic_route_body(&"TopicTest".into())
+            .is_some());
+        assert_eq!(
+            manager.route_response_cache_stats(),
+            RouteResponseCacheStats::default()
+        );
+
+        let manager = caching_route_info_manager(false);
+        register_topic(
+            &manager,
+            "broker-a",
+            mix_all::MASTER_ID,
+            "10.0.0.1:10911",
+            "TopicTest",
+        );
+        let route = cached_route(&manager, "TopicTest").unwrap();
+        assert_eq!(
+            route,
+            manager
+                .pickup_topic_route_data(&"TopicTest".into())
+                .unwrap()
+        );
+        assert!(cached_route(&manager, "NoSuchTopic").is_none());
+        assert_eq!(
+            manager.route_response_cache_stats(),
+            RouteResponseCacheStats {
+                hits: 1,
+                misses: 2,
+                entries: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn registration_changing_queue_perm_is_in_the_next_route_response() {
+        let manager = caching_route_info_manager(false);
+        let mut topic_config = TopicConfig::with_queues("TopicTest", 4, 4);
+        register_topic_config(
+            &manager,
+            "broker-a",
+            mix_all::MASTER_ID,
+            "10.0.0.1:10911",
+            topic_config.clone(),
+            vec![],
+        );
+        let route = cached_route(&manager, "TopicTest").unwrap();
+        assert!(PermName::is_writeable(
+            queue_data(&route, "broker-a").perm()
+        ));
+
+        topic_config.perm = PermName::PERM_READ;
+        register_topic_config(
+            &manager,
+            "broker-a",
+            mix_all::MASTER_ID,
+            "10.0.0.1:10911",
+            topic_config.clone(),
+            vec![],
+        );
+        let route = cached_route(&manager, "TopicTest").unwrap();
+        assert_eq!(queue_data(&route, "broker-a").perm(), PermName::PERM_READ);
+
+        topic_config.read_queue_nums = 8;
+        topic_config.write_queue_nums = 8;
+        register_topic_config(
+            &manager,
+            "broker-a",
+            mix_all::MASTER_ID,
+            "10.0.0.1:10911",
+            topic_config,
+            vec![],
+        );
+        let route = cached_route(&manager, "TopicTest").unwrap();
+        assert_eq!(queue_data(&route, "broker-a").write_queue_nums(), 8);
+    }
+
+    #[test]
+    fn broker_changes_invalidate_cached_routes() {
+        let mut manager = caching_route_info_manager(false);
+        register_topic(
+            &manager,
+            "broker-a",
+            mix_all::MASTER_ID,
+            "10.0.0.1:10911",
+            "TopicTest",
+        );
+        assert_eq!(
+            cached_route(&manager, "TopicTest")
+                .unwrap()
+                .broker_datas
+                .len(),
+            1
+        );
+
+        // a new broker group serving the topic
+        register_topic(
+            &manager,
+            "broker-b",
+            mix_all::MASTER_ID,
+            "10.0.0.2:10911",
+            "TopicTest",
+        );
+        assert_eq!(
+            cached_route(&manager, "TopicTest")
+                .unwrap()
+                .broker_datas
+                .len(),
+            2
+        );
+
+        // a slave joining a group
+        register_topic(&manager, "broker-a", 1, "10.0.0.3:10911", "TopicTest");
+        let route = cached_route(&manager, "TopicTest").unwrap();
+        let broker_a = route
+            .broker_datas
+            .iter()
+            .find(|broker_data| broker_data.broker_name() == "broker-a")
+            .unwrap();
+        assert_eq!(broker_a.broker_addrs().len(), 2);
+
+        // filter servers coming and going
+        register_topic_config(
+            &manager,
+            "broker-a",
+            mix_all::MASTER_ID,
+            "10.0.0.1:10911",
+            TopicConfig::with_queues("TopicTest", 4, 4),
+            vec!["10.0.0.9:9999".to_string()],
+        );
+        let route = cached_route(&manager, "TopicTest").unwrap();
+        assert_eq!(
+            route.filter_server_table.get("10.0.0.1:10911"),
+            Some(&vec![CheetahString::from("10.0.0.9:9999")])
+        );
+        register_topic(
+            &manager,
+            "broker-a",
+            mix_all::MASTER_ID,
+            "10.0.0.1:10911",
+            "TopicTest",
+        );
+        assert!(cached_route(&manager, "TopicTest")
+            .unwrap()
+            .filter_server_table
+            .is_empty());
+
+        // a re-registration changing nothing keeps the cached route
+        let entries = manager.route_response_cache_stats().entries;
+        register_topic(
+            &manager,
+            "broker-b",
+            mix_all::MASTER_ID,
+            "10.0.0.2:10911",
+            "TopicTest",
+        );
+        assert_eq!(manager.route_response_cache_stats().entries, entries);
+
+        manager.un_register_broker(vec![UnRegisterBrokerRequestHeader {
+            broker_name: "broker-b".into(),
+            broker_addr: "10.0.0.2:10911".into(),
+            cluster_name: "DefaultCluster".into(),
+            broker_id: mix_all::MASTER_ID,
+        }]);
+        let route = cached_route(&manager, "TopicTest").unwrap();
+        assert_eq!(route.broker_datas.len(), 1);
+        assert_eq!(route.queue_datas.len(), 1);
+    }
+
+    #[test]
+    fn admin_requests_invalidate_cached_routes() {
+        let mut manager = caching_route_info_manager(false);
+        register_topic(
+            &manager,
+            "broker-a",
+            mix_all::MASTER_ID,
+            "10.0.0.1:10911",
+            "TopicTest",
+        );
+        register_topic(
+            &manager,
+            "broker-b",
+            mix_all::MASTER_ID,
+            "10.0.0.2:10911",
+            "TopicTest",
+        );
+        assert!(cached_route(&manager, "TopicTest").is_some());
+
+        assert_eq!(
+            manager.wipe_write_perm_of_broker_by_lock(&"broker-a".into()),
+            2
+        );
+        let route = cached_route(&manager, "TopicTest").unwrap();
+        assert!(!PermName::is_writeable(
+            queue_data(&route, "broker-a").perm()
+        ));
+        assert_eq!(
+            manager.add_write_perm_of_broker_by_lock(&"broker-a".into()),
+            2
+        );
+        let route = cached_route(&manager, "TopicTest").unwrap();
+        assert!(PermName::is_writeable(
+            queue_data(&route, "broker-a").perm()
+        ));
+
+        manager.register_topic(
+            "TopicTest".into(),
+            vec![QueueData::new(
+                "broker-b".into(),
+                16,
+                16,
+                PermName::PERM_READ | PermName::PERM_WRITE,
+                0,
+            )],
+        );
+        let route = cached_route(&manager, "TopicTest").unwrap();
+        assert_eq!(queue_data(&route, "broker-b").write_queue_nums(), 16);
+
+        let snapshot = manager.dump();
+        manager.delete_topic("TopicTest".into(), None);
+        assert!(cached_route(&manager, "TopicTest").is_none());
+
+        manager.import(snapshot, false);
+        assert_eq!(
+            cached_route(&manager, "TopicTest")
+                .unwrap()
+                .queue_datas
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn cached_route_follows_broker_availability() {
+        let clock = Arc::new(MockClock::new(1_000_000));
+        let mut manager = caching_route_info_manager(true).with_clock(clock.clone());
+        register_topic(
+            &manager,
+            "broker-a",
+            mix_all::MASTER_ID,
+            "10.0.0.1:10911",
+            "TopicTest",
+        );
+        register_topic(
+            &manager,
+            "broker-b",
+            mix_all::MASTER_ID,
+            "10.0.0.2:10911",
+            "TopicTest",
+        );
+        assert_eq!(
+            cached_route(&manager, "TopicTest")
+                .unwrap()
+                .broker_datas
+                .len(),
+            2
+        );
+
+        // broker-a stops sending heartbeats, nothing changes in the tables
+        clock.advance(Duration::from_millis(BROKER_HEARTBEAT_INTERVAL as u64));
+        manager
+            .update_broker_info_update_timestamp("DefaultCluster".into(), "10.0.0.2:10911".into());
+        assert_eq!(
+            cached_route(&manager, "TopicTest")
+                .unwrap()
+                .broker_datas
+                .len(),
+            2
+        );
+        clock.advance(Duration::from_millis(BROKER_HEARTBEAT_INTERVAL as u64 + 1));
+        manager
+            .update_broker_info_update_timestamp("DefaultCluster".into(), "10.0.0.2:10911".into());
+        let route = cached_route(&manager, "TopicTest").unwrap();
+        assert_eq!(route.broker_datas.len(), 1);
+        assert_eq!(route.broker_datas[0].broker_name(), "broker-b");
+
+        // and comes back
+        manager
+            .update_broker_info_update_timestamp("DefaultCluster".into(), "10.0.0.1:10911".into());
+        assert_eq!(
+            cached_route(&manager, "TopicTest")
+                .unwrap()
+                .broker_datas
+                .len(),
+            2
+        );
+    }
+}
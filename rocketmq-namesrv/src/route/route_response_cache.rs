@@ -0,0 +1,109 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use bytes::Bytes;
+use cheetah_string::CheetahString;
+
+/// Hits and misses of the route response cache since the name server started, and the number
+/// of routes it holds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RouteResponseCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+struct CachedRoute {
+    body: Bytes,
+    /// When the route may go stale without any table changing, because a broker group in it
+    /// stops counting as available.
+    expire_at: i64,
+}
+
+/// Encoded topic route bodies, so a route is only gathered and serialized again after one of
+/// the tables it was built from changed.
+///
+/// Every change to a route table invalidates the topics it touches, or all of them when it
+/// touches a broker. Routes gathered while an invalidation happened are not cached: each
+/// invalidation bumps the generation, and a route is only stored if the generation is still the
+/// one seen before the tables were read.
+#[derive(Default)]
+pub(crate) struct RouteResponseCache {
+    entries: parking_lot::Mutex<HashMap<CheetahString /* topic */, CachedRoute>>,
+    generation: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl RouteResponseCache {
+    pub(crate) fn get(&self, topic: &CheetahString, now: i64) -> Option<Bytes> {
+        let body = self
+            .entries
+            .lock()
+            .get(topic)
+            .filter(|cached| now < cached.expire_at)
+            .map(|cached| cached.body.clone());
+        let counter = if body.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        body
+    }
+
+    /// The generation to pass to [`Self::insert`], read before the route tables are.
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn insert(
+        &self,
+        topic: CheetahString,
+        body: Bytes,
+        expire_at: i64,
+        generation: u64,
+    ) {
+        let mut entries = self.entries.lock();
+        if self.generation.load(Ordering::Acquire) == generation {
+            entries.insert(topic, CachedRoute { body, expire_at });
+        }
+    }
+
+    pub(crate) fn invalidate(&self, topic: &str) {
+        let mut entries = self.entries.lock();
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        entries.remove(topic);
+    }
+
+    pub(crate) fn invalidate_all(&self) {
+        let mut entries = self.entries.lock();
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        entries.clear();
+    }
+
+    pub(crate) fn stats(&self) -> RouteResponseCacheStats {
+        RouteResponseCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().len(),
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::net::IpAddr;
 use std::net::SocketAddr;
 
 use cheetah_string::CheetahString;
@@ -19,17 +20,100 @@ pub(crate) struct BrokerAddrInfo {
 }
 
 impl BrokerAddrInfo {
+    /// Creates the key with the broker address in canonical form, see [`canonical_broker_addr`].
+    /// Addresses that cannot be parsed are kept as given.
     pub fn new(
         cluster_name: impl Into<CheetahString>,
         broker_addr: impl Into<CheetahString>,
     ) -> Self {
+        let broker_addr = broker_addr.into();
         Self {
             cluster_name: cluster_name.into(),
-            broker_addr: broker_addr.into(),
+            broker_addr: canonical_broker_addr(broker_addr.as_str()).unwrap_or(broker_addr),
         }
     }
 }
 
+/// Host part of a broker address.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum BrokerHost {
+    Ip(IpAddr),
+    Hostname(CheetahString),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum BrokerAddrParseError {
+    MissingPort,
+    InvalidPort,
+    InvalidHost,
+}
+
+impl Display for BrokerAddrParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BrokerAddrParseError::MissingPort => write!(f, "broker address has no port"),
+            BrokerAddrParseError::InvalidPort => write!(f, "broker address has an invalid port"),
+            BrokerAddrParseError::InvalidHost => write!(f, "broker address has an invalid host"),
+        }
+    }
+}
+
+/// Parses `host:port`, `ip:port`, `[ipv6]:port` or the unbracketed `ipv6:port` form used by Java
+/// brokers. No name resolution is done, hostnames are only lowercased.
+pub(crate) fn parse_broker_addr(addr: &str) -> Result<(BrokerHost, u16), BrokerAddrParseError> {
+    let addr = addr.trim();
+    let (host, port) = if let Some(rest) = addr.strip_prefix('[') {
+        let (host, rest) = rest
+            .split_once(']')
+            .ok_or(BrokerAddrParseError::InvalidHost)?;
+        let port = rest
+            .strip_prefix(':')
+            .ok_or(BrokerAddrParseError::MissingPort)?;
+        (host, port)
+    } else {
+        addr.rsplit_once(':')
+            .ok_or(BrokerAddrParseError::MissingPort)?
+    };
+    if port.is_empty() {
+        return Err(BrokerAddrParseError::MissingPort);
+    }
+    let port = port
+        .parse::<u16>()
+        .map_err(|_| BrokerAddrParseError::InvalidPort)?;
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok((BrokerHost::Ip(ip.to_canonical()), port));
+    }
+    let valid_hostname = !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == '_');
+    if !valid_hostname {
+        return Err(BrokerAddrParseError::InvalidHost);
+    }
+    Ok((
+        BrokerHost::Hostname(CheetahString::from_string(host.to_ascii_lowercase())),
+        port,
+    ))
+}
+
+/// Returns the canonical textual form of a broker address: `a.b.c.d:port`, `[ipv6]:port` with
+/// the compressed IPv6 notation (IPv4-mapped addresses become IPv4), or `hostname:port` in
+/// lowercase.
+pub(crate) fn canonical_broker_addr(addr: &str) -> Result<CheetahString, BrokerAddrParseError> {
+    let canonical = match parse_broker_addr(addr)? {
+        (BrokerHost::Ip(ip), port) => SocketAddr::new(ip, port).to_string(),
+        (BrokerHost::Hostname(host), port) => format!("{}:{}", host, port),
+    };
+    Ok(CheetahString::from_string(canonical))
+}
+
+/// Normalizes a peer address so an IPv4 peer seen through a dual-stack socket matches the same
+/// peer seen through an IPv4 socket.
+#[inline]
+pub(crate) fn canonical_socket_addr(addr: SocketAddr) -> SocketAddr {
+    SocketAddr::new(addr.ip().to_canonical(), addr.port())
+}
+
 impl AsRef<Self> for BrokerAddrInfo {
     fn as_ref(&self) -> &Self {
         self
@@ -108,6 +192,8 @@ pub(crate) struct BrokerLiveInfo {
     pub data_version: DataVersion,
     pub ha_server_addr: CheetahString,
     pub remote_addr: SocketAddr,
+    pub epoch: i32,
+    pub max_phy_offset: i64,
 }
 
 impl BrokerLiveInfo {
@@ -123,10 +209,18 @@ impl BrokerLiveInfo {
             heartbeat_timeout_millis,
             data_version,
             ha_server_addr,
-            remote_addr,
+            remote_addr: canonical_socket_addr(remote_addr),
+            epoch: 0,
+            max_phy_offset: 0,
         }
     }
 
+    pub fn with_epoch(mut self, epoch: i32, max_phy_offset: i64) -> Self {
+        self.epoch = epoch;
+        self.max_phy_offset = max_phy_offset;
+        self
+    }
+
     pub fn data_version(&self) -> &DataVersion {
         &self.data_version
     }
@@ -142,6 +236,21 @@ impl BrokerLiveInfo {
     pub fn ha_server_addr(&self) -> &CheetahString {
         &self.ha_server_addr
     }
+
+    pub fn epoch(&self) -> i32 {
+        self.epoch
+    }
+
+    pub fn max_phy_offset(&self) -> i64 {
+        self.max_phy_offset
+    }
+
+    /// Whether the broker missed heartbeats for longer than its timeout at `now_millis`.
+    pub fn is_expired(&self, now_millis: i64) -> bool {
+        self.last_update_timestamp
+            .saturating_add(self.heartbeat_timeout_millis)
+            < now_millis
+    }
 }
 
 #[cfg(test)]
@@ -155,10 +264,88 @@ mod tests {
 
     #[test]
     fn broker_addr_info_display_format() {
-        let broker_info = BrokerAddrInfo::new("TestCluster", "192.168.1.1");
+        let broker_info = BrokerAddrInfo::new("TestCluster", "192.168.1.1:10911");
         assert_eq!(
             format!("{}", broker_info),
-            "Cluster Name: TestCluster, Broker Address: 192.168.1.1"
+            "Cluster Name: TestCluster, Broker Address: 192.168.1.1:10911"
+        );
+    }
+
+    #[test]
+    fn parse_broker_addr_ipv4() {
+        assert_eq!(
+            parse_broker_addr("192.168.1.1:10911"),
+            Ok((BrokerHost::Ip("192.168.1.1".parse().unwrap()), 10911))
+        );
+        assert_eq!(
+            parse_broker_addr("192.168.1.1"),
+            Err(BrokerAddrParseError::MissingPort)
+        );
+        assert_eq!(
+            parse_broker_addr("192.168.1.1:"),
+            Err(BrokerAddrParseError::MissingPort)
+        );
+        assert_eq!(
+            parse_broker_addr("192.168.1.1:70000"),
+            Err(BrokerAddrParseError::InvalidPort)
+        );
+    }
+
+    #[test]
+    fn parse_broker_addr_ipv6() {
+        let expected = Ok((BrokerHost::Ip("::1".parse().unwrap()), 10911));
+        assert_eq!(parse_broker_addr("[::1]:10911"), expected);
+        assert_eq!(parse_broker_addr("[0:0:0:0:0:0:0:1]:10911"), expected);
+        assert_eq!(parse_broker_addr("0:0:0:0:0:0:0:1:10911"), expected);
+        assert_eq!(
+            parse_broker_addr("[::ffff:10.0.0.1]:10911"),
+            Ok((BrokerHost::Ip("10.0.0.1".parse().unwrap()), 10911))
+        );
+        assert_eq!(
+            parse_broker_addr("[::1]"),
+            Err(BrokerAddrParseError::MissingPort)
+        );
+        assert_eq!(
+            parse_broker_addr("[::1:10911"),
+            Err(BrokerAddrParseError::InvalidHost)
+        );
+    }
+
+    #[test]
+    fn parse_broker_addr_hostname() {
+        assert_eq!(
+            parse_broker_addr("Broker-A.Example.com:10911"),
+            Ok((BrokerHost::Hostname("broker-a.example.com".into()), 10911))
+        );
+        assert_eq!(
+            parse_broker_addr("broker a:10911"),
+            Err(BrokerAddrParseError::InvalidHost)
+        );
+        assert_eq!(
+            parse_broker_addr(":10911"),
+            Err(BrokerAddrParseError::InvalidHost)
+        );
+    }
+
+    #[test]
+    fn broker_addr_info_uses_canonical_addr() {
+        let ipv6 = [
+            "[::1]:10911",
+            "[0:0:0:0:0:0:0:1]:10911",
+            "0:0:0:0:0:0:0:1:10911",
+        ]
+        .map(|addr| BrokerAddrInfo::new("DefaultCluster", addr));
+        assert!(ipv6.iter().all(|info| info == &ipv6[0]));
+        assert_eq!(ipv6[0].broker_addr, "[::1]:10911");
+
+        let hostname = BrokerAddrInfo::new("DefaultCluster", "Broker-A:10911");
+        assert_eq!(
+            hostname,
+            BrokerAddrInfo::new("DefaultCluster", "broker-a:10911")
+        );
+        assert_eq!(
+            BrokerAddrInfo::new("DefaultCluster", " 10.0.0.1:10911 "),
+            BrokerAddrInfo::new("DefaultCluster", "[::ffff:10.0.0.1]:10911")
         );
     }
 
@@ -189,5 +376,31 @@ mod tests {
         assert_eq!(broker_live_info.heartbeat_timeout_millis(), 2000);
         assert_eq!(broker_live_info.data_version(), &data_version);
         assert_eq!(broker_live_info.ha_server_addr(), "192.168.1.4");
+        assert_eq!(broker_live_info.epoch(), 0);
+        let broker_live_info = broker_live_info.with_epoch(3, 1024);
+        assert_eq!(broker_live_info.epoch(), 3);
+        assert_eq!(broker_live_info.max_phy_offset(), 1024);
+    }
+
+    #[test]
+    fn broker_live_info_expiry_does_not_overflow() {
+        let remote_addr: SocketAddr = "10.0.0.1:50000".parse().unwrap();
+        let live_info = BrokerLiveInfo::new(
+            1000,
+            i64::MAX,
+            DataVersion::default(),
+            CheetahString::empty(),
+            remote_addr,
+        );
+        assert!(!live_info.is_expired(i64::MAX));
+        let live_info = BrokerLiveInfo::new(
+            1000,
+            500,
+            DataVersion::default(),
+            CheetahString::empty(),
+            remote_addr,
+        );
+        assert!(!live_info.is_expired(1500));
+        assert!(live_info.is_expired(1501));
     }
 }
@@ -27,6 +27,7 @@ use crate::base::connection_net_event::ConnectionNetEvent;
 use crate::base::response_future::ResponseFuture;
 use crate::code::response_code::ResponseCode;
 use crate::connection::Connection;
+use crate::error::Error::ChannelSendRequestFailed;
 use crate::error::Error::ConnectionInvalid;
 use crate::error::Error::Io;
 use crate::error::Error::RemoteException;
@@ -77,6 +78,7 @@ async fn run_recv<PR: RequestProcessor>(mut client: ArcMut<ClientInner>, mut pro
                 // handle request
                 RemotingCommandType::REQUEST => {
                     let opaque = msg.opaque();
+                    let oneway_rpc = msg.is_oneway_rpc();
                     let process_result = processor
                         .process_request(
                             client.channel.clone(),
@@ -86,7 +88,7 @@ async fn run_recv<PR: RequestProcessor>(mut client: ArcMut<ClientInner>, mut pro
                         .await;
                     match process_result {
                         Ok(response) => {
-                            if let Some(response) = response {
+                            if let Some(response) = response.filter(|_| !oneway_rpc) {
                                 let _ = client
                                     .tx
                                     .send((response.set_opaque(opaque), None, None))
@@ -95,11 +97,14 @@ async fn run_recv<PR: RequestProcessor>(mut client: ArcMut<ClientInner>, mut pro
                         }
                         Err(err) => {
                             error!("process request error: {:?}", err);
+                            if oneway_rpc {
+                                continue;
+                            }
                             let command = RemotingCommand::create_response_command()
                                 .set_opaque(opaque)
                                 .set_code(ResponseCode::SystemBusy)
                                 .set_remark_option(Some("System busy".to_string()));
-                            client.tx.send((command, None, None)).await.unwrap();
+                            let _ = client.tx.send((command, None, None)).await;
                         }
                     }
                 }
@@ -263,19 +268,30 @@ impl Client {
         let response = self.read().await?;
         Ok(response)*/
 
-        let (tx, rx) = tokio::sync::oneshot::channel::<Result<RemotingCommand>>();
+        let rx = self.send_request(request, timeout_millis).await?;
+        match rx.await {
+            Ok(value) => value,
+            Err(error) => Err(RemoteException(error.to_string())),
+        }
+    }
 
+    /// Hands `request` over to the connection for writing and returns the receiver its response
+    /// will be delivered to. Fails with `ChannelSendRequestFailed` when the request could not be
+    /// handed over, in which case it never reached the wire.
+    pub async fn send_request(
+        &mut self,
+        request: RemotingCommand,
+        timeout_millis: u64,
+    ) -> Result<tokio::sync::oneshot::Receiver<Result<RemotingCommand>>> {
+        let (tx, rx) = tokio::sync::oneshot::channel::<Result<RemotingCommand>>();
         if let Err(err) = self
             .tx
             .send((request, Some(tx), Some(timeout_millis)))
             .await
         {
-            return Err(RemoteException(err.to_string()));
-        }
-        match rx.await {
-            Ok(value) => value,
-            Err(error) => Err(RemoteException(error.to_string())),
+            return Err(ChannelSendRequestFailed(err.to_string()));
         }
+        Ok(rx)
     }
 
     /// Invokes a remote operation with the given `RemotingCommand` and provides a callback function
@@ -19,9 +19,13 @@ use std::collections::HashSet;
 use std::sync::atomic::AtomicI32;
 use std::sync::Arc;
 use std::time::Duration;
+use std::time::Instant;
 
 use cheetah_string::CheetahString;
+use parking_lot::Mutex as SyncMutex;
+use rand::seq::SliceRandom;
 use rand::Rng;
+use rocketmq_common::utils::name_server_address_utils::NameServerAddressUtils;
 use rocketmq_runtime::RocketMQRuntime;
 use rocketmq_rust::ArcMut;
 use rocketmq_rust::WeakArcMut;
@@ -31,6 +35,7 @@ use tracing::debug;
 use tracing::error;
 use tracing::info;
 use tracing::warn;
+use tracing::Instrument;
 
 use crate::base::connection_net_event::ConnectionNetEvent;
 use crate::clients::Client;
@@ -39,6 +44,7 @@ use crate::error::Error;
 use crate::protocol::remoting_command::RemotingCommand;
 use crate::remoting::RemotingService;
 use crate::request_processor::default_request_processor::DefaultRemotingRequestProcessor;
+use crate::request_tracing;
 use crate::runtime::config::client_config::TokioClientConfig;
 use crate::runtime::processor::RequestProcessor;
 use crate::runtime::RPCHook;
@@ -48,6 +54,14 @@ const LOCK_TIMEOUT_MILLIS: u64 = 3000;
 
 pub type ArcSyncClient = Arc<Mutex<Client>>;
 
+/// Connect and request failures of a name server.
+#[derive(Default)]
+struct NamesrvFailures {
+    count: u64,
+    /// The name server is skipped until then, unless every name server is.
+    unavailable_until: Option<Instant>,
+}
+
 pub struct RocketmqDefaultClient<PR = DefaultRemotingRequestProcessor> {
     tokio_client_config: Arc<TokioClientConfig>,
     //cache connection
@@ -56,6 +70,7 @@ pub struct RocketmqDefaultClient<PR = DefaultRemotingRequestProcessor> {
     namesrv_addr_choosed: ArcMut<Option<CheetahString>>,
     available_namesrv_addr_set: ArcMut<HashSet<CheetahString>>,
     namesrv_index: Arc<AtomicI32>,
+    namesrv_failures: Arc<SyncMutex<HashMap<CheetahString, NamesrvFailures>>>,
     client_runtime: Arc<RocketMQRuntime>,
     processor: PR,
     tx: Option<tokio::sync::broadcast::Sender<ConnectionNetEvent>>,
@@ -77,6 +92,7 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RocketmqDefaultClient<PR> {
             namesrv_addr_choosed: ArcMut::new(Default::default()),
             available_namesrv_addr_set: ArcMut::new(Default::default()),
             namesrv_index: Arc::new(AtomicI32::new(init_value_index())),
+            namesrv_failures: Arc::new(SyncMutex::new(HashMap::new())),
             client_runtime: Arc::new(RocketMQRuntime::new_multi(10, "client-thread")),
             processor,
             tx,
@@ -85,76 +101,133 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RocketmqDefaultClient<PR> {
 }
 
 impl<PR: RequestProcessor + Sync + Clone + 'static> RocketmqDefaultClient<PR> {
-    async fn get_and_create_nameserver_client(&self) -> Option<Client> {
-        let mut addr = self.namesrv_addr_choosed.as_ref().clone();
-        if let Some(ref addr) = addr {
-            let guard = self.connection_tables.lock().await;
-            let ct = guard.get(addr);
-            if let Some(ct) = ct {
-                let conn_status = ct.connection().ok;
-                //let conn_status = ct.lock().await.connection().ok;
-                if conn_status {
-                    return Some(ct.clone());
-                }
-            }
+    /// The name server currently used for calls without an address.
+    pub fn namesrv_addr_choosed(&self) -> Option<CheetahString> {
+        self.namesrv_addr_choosed.as_ref().clone()
+    }
+
+    /// Connect and request failures per name server since it was added.
+    pub fn namesrv_failure_counts(&self) -> HashMap<CheetahString, u64> {
+        self.namesrv_failures
+            .lock()
+            .iter()
+            .map(|(addr, failures)| (addr.clone(), failures.count))
+            .collect()
+    }
+
+    fn is_namesrv_backing_off(&self, addr: &CheetahString) -> bool {
+        self.namesrv_failures
+            .lock()
+            .get(addr)
+            .and_then(|failures| failures.unavailable_until)
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    /// Skips `addr` for the backoff window and moves calls without an address on to the next
+    /// name server.
+    fn mark_namesrv_failed(&self, addr: &CheetahString) {
+        let backoff =
+            Duration::from_millis(self.tokio_client_config.namesrv_unavailable_backoff_millis);
+        let mut namesrv_failures = self.namesrv_failures.lock();
+        let failures = namesrv_failures.entry(addr.clone()).or_default();
+        failures.count += 1;
+        failures.unavailable_until = Some(Instant::now() + backoff);
+        warn!(
+            "name server {} failed {} times, skip it for {:?}",
+            addr, failures.count, backoff
+        );
+        drop(namesrv_failures);
+        if self.namesrv_addr_choosed.as_ref().as_ref() == Some(addr) {
+            self.namesrv_addr_choosed.mut_from_ref().take();
         }
-        let connection_tables = self.connection_tables.lock().await;
-
-        addr.clone_from(self.namesrv_addr_choosed.as_ref());
-        if let Some(addr) = addr.as_ref() {
-            let ct = connection_tables.get(addr);
-            if let Some(ct) = ct {
-                let conn_status = ct.connection().ok;
-                //let conn_status = ct.lock().await.connection().ok;
-                if conn_status {
-                    return Some(ct.clone());
-                }
+    }
+
+    /// The last name server that worked while it keeps working, otherwise the next one in turn
+    /// that is not backing off from a failure. Name servers that all back off are tried anyway.
+    async fn get_and_create_nameserver_client(&self) -> Option<(CheetahString, Client)> {
+        if let Some(addr) = self.namesrv_addr_choosed.as_ref().clone() {
+            let client = self.connection_tables.lock().await.get(&addr).cloned();
+            if let Some(client) = client.filter(|client| client.connection().ok) {
+                return Some((addr, client));
             }
         }
-        let addr_list = self.namesrv_addr_list.as_ref();
-        if !addr_list.is_empty() {
+        let addr_list = self.namesrv_addr_list.as_ref().clone();
+        let mut backing_off = Vec::new();
+        for _ in 0..addr_list.len() {
             let index = self
                 .namesrv_index
-                .fetch_and(1, std::sync::atomic::Ordering::Release)
-                .abs();
-            let index = index as usize % addr_list.len();
-            let new_addr = &addr_list[index];
-            info!(
-                "new name remoting_server is chosen. OLD: {} , NEW: {}. namesrvIndex = {}",
-                new_addr, new_addr, index
-            );
-            self.namesrv_addr_choosed
-                .mut_from_ref()
-                .replace(new_addr.clone());
-            drop(connection_tables);
-            return self
-                .create_client(
-                    new_addr,
-                    //&mut connection_tables,
-                    Duration::from_millis(self.tokio_client_config.connect_timeout_millis as u64),
-                )
-                .await;
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+                .unsigned_abs() as usize
+                % addr_list.len();
+            let addr = &addr_list[index];
+            if self.is_namesrv_backing_off(addr) {
+                backing_off.push(addr);
+                continue;
+            }
+            if let Some(client) = self.choose_namesrv(addr).await {
+                return Some((addr.clone(), client));
+            }
+        }
+        for addr in backing_off {
+            if let Some(client) = self.choose_namesrv(addr).await {
+                return Some((addr.clone(), client));
+            }
         }
         None
     }
 
-    async fn get_and_create_client(&self, addr: Option<&CheetahString>) -> Option<Client> {
-        match addr {
-            None => self.get_and_create_nameserver_client().await,
-            Some(addr) => {
-                if addr.is_empty() {
-                    return self.get_and_create_nameserver_client().await;
+    async fn choose_namesrv(&self, addr: &CheetahString) -> Option<Client> {
+        info!(
+            "new name remoting_server is chosen. OLD: {:?} , NEW: {}",
+            self.namesrv_addr_choosed.as_ref(),
+            addr
+        );
+        let client = self
+            .create_client(
+                addr,
+                Duration::from_millis(self.tokio_client_config.connect_timeout_millis as u64),
+            )
+            .await;
+        match client {
+            Some(client) => {
+                if let Some(failures) = self.namesrv_failures.lock().get_mut(addr) {
+                    failures.unavailable_until = None;
                 }
+                self.namesrv_addr_choosed
+                    .mut_from_ref()
+                    .replace(addr.clone());
+                Some(client)
+            }
+            None => {
+                self.mark_namesrv_failed(addr);
+                None
+            }
+        }
+    }
+
+    /// The client for `addr`, or for the chosen name server along with its address when `addr`
+    /// is missing or empty.
+    async fn get_and_create_client(
+        &self,
+        addr: Option<&CheetahString>,
+    ) -> Option<(Option<CheetahString>, Client)> {
+        match addr.filter(|addr| !addr.is_empty()) {
+            None => self
+                .get_and_create_nameserver_client()
+                .await
+                .map(|(namesrv_addr, client)| (Some(namesrv_addr), client)),
+            Some(addr) => {
                 let client = self.connection_tables.lock().await.get(addr).cloned();
                 // if client.is_some() && client.as_ref()?.lock().await.connection().ok {
                 if client.is_some() && client.as_ref()?.connection().ok {
-                    return client;
+                    return client.map(|client| (None, client));
                 }
                 self.create_client(
                     addr,
                     Duration::from_millis(self.tokio_client_config.connect_timeout_millis as u64),
                 )
                 .await
+                .map(|client| (None, client))
             }
         }
     }
@@ -205,20 +278,23 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RocketmqDefaultClient<PR> {
         }
     }
 
-    async fn scan_available_name_srv(&self) {
+    /// Connects to every name server and keeps the reachable ones as available.
+    pub async fn scan_available_name_srv(&self) {
         if self.namesrv_addr_list.as_ref().is_empty() {
             debug!("scanAvailableNameSrv addresses of name remoting_server is null!");
             return;
         }
-        for address in self.available_namesrv_addr_set.as_ref().iter() {
-            if !self.namesrv_addr_list.as_ref().contains(address) {
-                warn!("scanAvailableNameSrv remove invalid address {}", address);
-                self.available_namesrv_addr_set
-                    .mut_from_ref()
-                    .remove(address);
-            }
-        }
-        for namesrv_addr in self.namesrv_addr_list.as_ref().iter() {
+        let namesrv_addr_list = self.namesrv_addr_list.as_ref().clone();
+        self.available_namesrv_addr_set
+            .mut_from_ref()
+            .retain(|address| {
+                let valid = namesrv_addr_list.contains(address);
+                if !valid {
+                    warn!("scanAvailableNameSrv remove invalid address {}", address);
+                }
+                valid
+            });
+        for namesrv_addr in namesrv_addr_list.iter() {
             let client = self.get_and_create_client(Some(namesrv_addr)).await;
             match client {
                 None => {
@@ -265,51 +341,34 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RemotingService for Rocketmq
 
 #[allow(unused_variables)]
 impl<PR: RequestProcessor + Sync + Clone + 'static> RemotingClient for RocketmqDefaultClient<PR> {
-    async fn update_name_server_address_list(&self, addrs: Vec<CheetahString>) {
-        let old = self.namesrv_addr_list.mut_from_ref();
-        let mut update = false;
-
-        if !addrs.is_empty() {
-            if old.is_empty() || addrs.len() != old.len() {
-                update = true;
-            } else {
-                for addr in &addrs {
-                    if !old.contains(addr) {
-                        update = true;
-                        break;
-                    }
-                }
-            }
-
-            if update {
-                // Shuffle the addresses
-                // Shuffle logic is not implemented here as it is not available in standard library
-                // You can implement it using various algorithms like Fisher-Yates shuffle
+    async fn update_name_server_address_list(&self, mut addrs: Vec<CheetahString>) {
+        if addrs.is_empty() {
+            return;
+        }
+        let (added, removed) =
+            NameServerAddressUtils::diff_addresses(self.namesrv_addr_list.as_ref(), &addrs);
+        if added.is_empty() && removed.is_empty() {
+            return;
+        }
+        info!(
+            "name remoting_server address updated. NEW : {:?} , OLD: {:?}, added: {:?}, removed: \
+             {:?}",
+            addrs,
+            self.namesrv_addr_list.as_ref(),
+            added,
+            removed
+        );
+        addrs.shuffle(&mut rand::thread_rng());
+        *self.namesrv_addr_list.mut_from_ref() = addrs;
 
-                info!(
-                    "name remoting_server address updated. NEW : {:?} , OLD: {:?}",
-                    addrs, old
-                );
-                /* let mut rng = thread_rng();
-                addrs.shuffle(&mut rng);*/
-                self.namesrv_addr_list.mut_from_ref().extend(addrs.clone());
-
-                // should close the channel if choosed addr is not exist.
-                if let Some(namesrv_addr) = self.namesrv_addr_choosed.as_ref() {
-                    if !addrs.contains(namesrv_addr) {
-                        let mut remove_vec = Vec::new();
-                        let mut result = self.connection_tables.lock().await;
-                        for (addr, client) in result.iter() {
-                            if addr == namesrv_addr {
-                                remove_vec.push(addr.clone());
-                            }
-                        }
-                        for addr in &remove_vec {
-                            result.remove(addr);
-                        }
-                    }
-                }
-            }
+        // should close the channel if choosed addr is not exist.
+        self.namesrv_failures
+            .lock()
+            .retain(|addr, _| !removed.contains(addr));
+        let choosed = self.namesrv_addr_choosed.as_ref().clone();
+        if let Some(namesrv_addr) = choosed.filter(|addr| removed.contains(addr)) {
+            self.namesrv_addr_choosed.mut_from_ref().take();
+            self.connection_tables.lock().await.remove(&namesrv_addr);
         }
     }
 
@@ -328,49 +387,82 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RemotingClient for RocketmqD
     async fn invoke_async(
         &self,
         addr: Option<&CheetahString>,
-        request: RemotingCommand,
+        mut request: RemotingCommand,
         timeout_millis: u64,
     ) -> Result<RemotingCommand> {
+        let span = request_tracing::client_span(&request, addr.map_or("", |addr| addr.as_str()));
+        request_tracing::inject_context(&span, &mut request);
         let client = self.get_and_create_client(addr).await;
-        match client {
-            None => Err(Error::RemoteException("get client failed".to_string())),
-            Some(mut client) => {
+        let mut namesrv_addr = None;
+        let result = match client {
+            None => Err(Error::ConnectionInvalid(format!(
+                "connect to {} failed",
+                addr.map_or("", |addr| addr.as_str())
+            ))),
+            Some((addr, mut client)) => {
+                namesrv_addr = addr;
+                let deadline = time::Instant::now() + Duration::from_millis(timeout_millis);
                 match self
                     .client_runtime
                     .get_handle()
                     .spawn(async move {
-                        time::timeout(Duration::from_millis(timeout_millis), async move {
-                            client.send_read(request, timeout_millis).await
-                        })
+                        // a request still waiting to be handed to the connection at the deadline
+                        // was never written, so the caller may safely send it elsewhere
+                        let response = match time::timeout_at(
+                            deadline,
+                            client.send_request(request, timeout_millis),
+                        )
                         .await
+                        {
+                            Ok(response) => response?,
+                            Err(_) => {
+                                return Err(Error::ChannelSendRequestFailed(format!(
+                                    "timed out after {}ms before the request was written",
+                                    timeout_millis
+                                )))
+                            }
+                        };
+                        match time::timeout_at(deadline, response).await {
+                            Ok(Ok(response)) => response,
+                            Ok(Err(err)) => Err(Error::RemoteException(err.to_string())),
+                            Err(err) => Err(Error::RemoteException(err.to_string())),
+                        }
                     })
+                    .instrument(span.clone())
                     .await
                 {
-                    Ok(result) => match result {
-                        Ok(response) => match response {
-                            Ok(value) => Ok(value),
-                            Err(e) => Err(Error::RemoteException(e.to_string())),
-                        },
-                        Err(err) => Err(Error::RemoteException(err.to_string())),
-                    },
+                    Ok(Err(err @ Error::ChannelSendRequestFailed(_))) => Err(err),
+                    Ok(result) => result.map_err(|err| Error::RemoteException(err.to_string())),
                     Err(err) => Err(Error::RemoteException(err.to_string())),
                 }
             }
+        };
+        match &result {
+            Ok(response) => request_tracing::record_response(&span, response),
+            // the next name server call goes elsewhere instead of waiting on this one again
+            Err(_) => {
+                if let Some(namesrv_addr) = namesrv_addr {
+                    self.mark_namesrv_failed(&namesrv_addr);
+                }
+            }
         }
+        result
     }
 
     async fn invoke_oneway(
         &self,
         addr: &CheetahString,
-        request: RemotingCommand,
+        mut request: RemotingCommand,
         timeout_millis: u64,
     ) {
+        let span = request_tracing::client_span(&request, addr.as_str());
+        request_tracing::inject_context(&span, &mut request);
         let client = self.get_and_create_client(Some(addr)).await;
         match client {
             None => {
                 error!("get client failed");
             }
-            Some(mut client) => {
+            Some((_, mut client)) => {
                 self.client_runtime.get_handle().spawn(async move {
                     match time::timeout(Duration::from_millis(timeout_millis), async move {
                         let mut request = request;
@@ -404,3 +496,252 @@ fn init_value_index() -> i32 {
     let mut rng = rand::thread_rng();
     rng.gen_range(0..999)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::net::channel::Channel;
+    use crate::runtime::connection_handler_context::ConnectionHandlerContext;
+
+    fn addresses(addrs: &str) -> Vec<CheetahString> {
+        NameServerAddressUtils::split_addresses(addrs)
+    }
+
+    fn sorted(addrs: &[CheetahString]) -> Vec<CheetahString> {
+        let mut addrs = addrs.to_vec();
+        addrs.sort();
+        addrs
+    }
+
+    #[test]
+    fn update_name_server_address_list_replaces_old_addresses() {
+        let client = RocketmqDefaultClient::new(
+            Arc::new(TokioClientConfig::default()),
+            DefaultRemotingRequestProcessor,
+        );
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            client
+                .update_name_server_address_list(addresses("a:9876;b:9876"))
+                .await;
+            assert_eq!(
+                sorted(client.get_name_server_address_list()),
+                addresses("a:9876;b:9876")
+            );
+
+            client
+                .namesrv_addr_choosed
+                .mut_from_ref()
+                .replace("a:9876".into());
+            client
+                .update_name_server_address_list(addresses("b:9876;c:9876"))
+                .await;
+            assert_eq!(
+                sorted(client.get_name_server_address_list()),
+                addresses("b:9876;c:9876")
+            );
+            assert!(client.namesrv_addr_choosed.as_ref().is_none());
+
+            client.update_name_server_address_list(Vec::new()).await;
+            assert_eq!(client.get_name_server_address_list().len(), 2);
+        });
+    }
+
+    #[derive(Clone)]
+    struct RespondingProcessor;
+
+    impl RequestProcessor for RespondingProcessor {
+        async fn process_request(
+            &mut self,
+            _channel: Channel,
+            _ctx: ConnectionHandlerContext,
+            _request: RemotingCommand,
+        ) -> Result<Option<RemotingCommand>> {
+            Ok(Some(RemotingCommand::create_response_command()))
+        }
+    }
+
+    #[test]
+    fn name_server_calls_skip_an_unreachable_address() {
+        let client = RocketmqDefaultClient::new(
+            Arc::new(TokioClientConfig::default()),
+            DefaultRemotingRequestProcessor,
+        );
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let dead = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let dead_addr = CheetahString::from(dead.local_addr().unwrap().to_string());
+            drop(dead);
+            let live = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let live_addr = CheetahString::from(live.local_addr().unwrap().to_string());
+            tokio::spawn(crate::remoting_server::server::run(
+                live,
+                std::future::pending::<()>(),
+                RespondingProcessor,
+                None,
+                vec![],
+            ));
+            *client.namesrv_addr_list.mut_from_ref() = vec![dead_addr, live_addr.clone()];
+            client
+                .namesrv_index
+                .store(0, std::sync::atomic::Ordering::Relaxed);
+
+            for _ in 0..3 {
+                client
+                    .invoke_async(None, RemotingCommand::create_remoting_command(1), 3000)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    client.namesrv_addr_choosed.as_ref(),
+                    &Some(live_addr.clone())
+                );
+            }
+        });
+    }
+
+    #[derive(Clone)]
+    struct CountingProcessor(Arc<AtomicUsize>);
+
+    impl RequestProcessor for CountingProcessor {
+        async fn process_request(
+            &mut self,
+            _channel: Channel,
+            _ctx: ConnectionHandlerContext,
+            _request: RemotingCommand,
+        ) -> Result<Option<RemotingCommand>> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(Some(RemotingCommand::create_response_command()))
+        }
+    }
+
+    /// Starts a name server on `listener` counting the requests it serves.
+    fn serve_counting(listener: TcpListener) -> Arc<AtomicUsize> {
+        let served = Arc::new(AtomicUsize::new(0));
+        tokio::spawn(crate::remoting_server::server::run(
+            listener,
+            std::future::pending::<()>(),
+            CountingProcessor(served.clone()),
+            None,
+            vec![],
+        ));
+        served
+    }
+
+    fn backing_off_client(backoff_millis: u64) -> RocketmqDefaultClient {
+        RocketmqDefaultClient::new(
+            Arc::new(TokioClientConfig {
+                namesrv_unavailable_backoff_millis: backoff_millis,
+                ..TokioClientConfig::default()
+            }),
+            DefaultRemotingRequestProcessor,
+        )
+    }
+
+    #[test]
+    fn name_server_calls_avoid_a_dead_address_until_it_recovers() {
+        let client = backing_off_client(300);
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let dead = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let dead_addr = CheetahString::from(dead.local_addr().unwrap().to_string());
+            drop(dead);
+            let live = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let live_addr = CheetahString::from(live.local_addr().unwrap().to_string());
+            let live_served = serve_counting(live);
+            *client.namesrv_addr_list.mut_from_ref() = vec![dead_addr.clone(), live_addr.clone()];
+
+            for _ in 0..10 {
+                client.namesrv_index.store(0, Ordering::Relaxed);
+                client
+                    .invoke_async(None, RemotingCommand::create_remoting_command(1), 3000)
+                    .await
+                    .unwrap();
+            }
+            assert_eq!(live_served.load(Ordering::Relaxed), 10);
+            assert_eq!(client.namesrv_addr_choosed(), Some(live_addr.clone()));
+            assert_eq!(
+                client.namesrv_failure_counts(),
+                HashMap::from([(dead_addr.clone(), 1)])
+            );
+
+            // back once its backoff is over, and used when the other one fails
+            let recovered_served =
+                serve_counting(TcpListener::bind(dead_addr.as_str()).await.unwrap());
+            time::sleep(Duration::from_millis(400)).await;
+            client.mark_namesrv_failed(&live_addr);
+            client.namesrv_index.store(1, Ordering::Relaxed);
+            for _ in 0..3 {
+                client
+                    .invoke_async(None, RemotingCommand::create_remoting_command(1), 3000)
+                    .await
+                    .unwrap();
+            }
+            assert_eq!(recovered_served.load(Ordering::Relaxed), 3);
+            assert_eq!(live_served.load(Ordering::Relaxed), 10);
+            assert_eq!(client.namesrv_addr_choosed(), Some(dead_addr.clone()));
+            assert_eq!(
+                client.namesrv_failure_counts(),
+                HashMap::from([(dead_addr, 1), (live_addr, 1)])
+            );
+        });
+    }
+
+    #[test]
+    fn name_server_that_times_out_is_skipped_by_the_next_calls() {
+        let client = backing_off_client(30_000);
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            // accepts connections but never answers
+            let silent = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let silent_addr = CheetahString::from(silent.local_addr().unwrap().to_string());
+            tokio::spawn(async move {
+                let mut connections = Vec::new();
+                while let Ok((stream, _)) = silent.accept().await {
+                    connections.push(stream);
+                }
+            });
+            let live = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let live_addr = CheetahString::from(live.local_addr().unwrap().to_string());
+            let live_served = serve_counting(live);
+            *client.namesrv_addr_list.mut_from_ref() = vec![silent_addr.clone(), live_addr.clone()];
+            client.namesrv_index.store(0, Ordering::Relaxed);
+
+            assert!(client
+                .invoke_async(None, RemotingCommand::create_remoting_command(1), 200)
+                .await
+                .is_err());
+            assert_eq!(client.namesrv_addr_choosed(), None);
+
+            for _ in 0..5 {
+                client.namesrv_index.store(0, Ordering::Relaxed);
+                let started = Instant::now();
+                client
+                    .invoke_async(None, RemotingCommand::create_remoting_command(1), 200)
+                    .await
+                    .unwrap();
+                assert!(started.elapsed() < Duration::from_millis(200));
+            }
+            assert_eq!(live_served.load(Ordering::Relaxed), 5);
+            assert_eq!(
+                client.namesrv_failure_counts(),
+                HashMap::from([(silent_addr, 1)])
+            );
+        });
+    }
+}
@@ -143,6 +143,9 @@ pub enum RequestCode {
     ResetMasterFlushOffset = 908,
     GetAllProducerInfo = 328,
     DeleteExpiredCommitlog = 329,
+    GetRouteSnapshotFromNamesrv = 330,
+    QueryDelayProgress = 331,
+    NotifyTopicRouteChanged = 332,
 
     UpdateColdDataFlowCtrConfig = 2001,
     RemoveColdDataFlowCtrConfig = 2002,
@@ -286,6 +289,9 @@ impl From<i32> for RequestCode {
             908 => RequestCode::ResetMasterFlushOffset,
             328 => RequestCode::GetAllProducerInfo,
             329 => RequestCode::DeleteExpiredCommitlog,
+            330 => RequestCode::GetRouteSnapshotFromNamesrv,
+            331 => RequestCode::QueryDelayProgress,
+            332 => RequestCode::NotifyTopicRouteChanged,
             2001 => RequestCode::UpdateColdDataFlowCtrConfig,
             2002 => RequestCode::RemoveColdDataFlowCtrConfig,
             2003 => RequestCode::GetColdDataFlowCtrInfo,
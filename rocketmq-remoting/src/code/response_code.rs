@@ -95,6 +95,7 @@ pub enum ResponseCode {
     RpcSendToChannelFailed = -1004,
     RpcTimeOut = -1006,
     GoAway = 1500,
+    ExtFieldsTooLarge = 1501,
     ControllerFencedMasterEpoch = 2000,
     ControllerFencedSyncStateSetEpoch = 2001,
     ControllerInvalidMaster = 2002,
@@ -167,6 +168,7 @@ impl From<i32> for ResponseCode {
             -1004 => ResponseCode::RpcSendToChannelFailed,
             -1006 => ResponseCode::RpcTimeOut,
             1500 => ResponseCode::GoAway,
+            1501 => ResponseCode::ExtFieldsTooLarge,
             2000 => ResponseCode::ControllerFencedMasterEpoch,
             2001 => ResponseCode::ControllerFencedSyncStateSetEpoch,
             2002 => ResponseCode::ControllerInvalidMaster,
@@ -291,6 +293,7 @@ mod tests {
         );
         assert_eq!(ResponseCode::from(-1006), ResponseCode::RpcTimeOut);
         assert_eq!(ResponseCode::from(1500), ResponseCode::GoAway);
+        assert_eq!(ResponseCode::from(1501), ResponseCode::ExtFieldsTooLarge);
         assert_eq!(
             ResponseCode::from(2000),
             ResponseCode::ControllerFencedMasterEpoch
@@ -15,13 +15,47 @@
  * limitations under the License.
  */
 
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
 use bytes::BufMut;
 use bytes::BytesMut;
+use lazy_static::lazy_static;
 use tokio_util::codec::Decoder;
 use tokio_util::codec::Encoder;
+use tracing::warn;
 
 use crate::error::Error;
 use crate::protocol::remoting_command::RemotingCommand;
+use crate::runtime::config::net_system_config::NetSystemConfig;
+
+lazy_static! {
+    static ref NET_SYSTEM_CONFIG: NetSystemConfig = NetSystemConfig::new();
+}
+
+static OVERSIZED_EXT_FIELDS_REJECTED: AtomicU64 = AtomicU64::new(0);
+
+/// Number of decoded commands whose `extFields` exceeded the size cap.
+pub fn oversized_ext_fields_rejected() -> u64 {
+    OVERSIZED_EXT_FIELDS_REJECTED.load(Ordering::Relaxed)
+}
+
+/// Drops the `extFields` of a command above `max_bytes`, the server then rejects it with
+/// [`ResponseCode::ExtFieldsTooLarge`](crate::code::response_code::ResponseCode::ExtFieldsTooLarge).
+pub(crate) fn check_ext_fields_size(cmd: &mut RemotingCommand, max_bytes: usize) {
+    let bytes = cmd.ext_fields_bytes();
+    if bytes > max_bytes {
+        warn!(
+            "extFields of {} bytes exceed the limit of {} bytes, code={}, opaque={}",
+            bytes,
+            max_bytes,
+            cmd.code(),
+            cmd.opaque()
+        );
+        cmd.drop_oversized_ext_fields();
+        OVERSIZED_EXT_FIELDS_REJECTED.fetch_add(1, Ordering::Relaxed);
+    }
+}
 
 /// Encodes a `RemotingCommand` into a `BytesMut` buffer.
 ///
@@ -89,7 +123,11 @@ impl Decoder for RemotingCommandCodec {
     ///
     /// This function will return an error if the decoding process fails.
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        RemotingCommand::decode(src)
+        let mut cmd = RemotingCommand::decode(src)?;
+        if let Some(cmd) = cmd.as_mut() {
+            check_ext_fields_size(cmd, NET_SYSTEM_CONFIG.ext_fields_max_bytes);
+        }
+        Ok(cmd)
         /* let read_to = src.len();
         if read_to < 4 {
             // Wait for more data when there are less than 4 bytes.
@@ -165,6 +203,16 @@ impl Encoder<RemotingCommand> for RemotingCommandCodec {
         if let Some(body_inner) = item.get_body() {
             dst.put(body_inner.as_ref());
         }
+        if let Some(body_parts) = item.body_parts() {
+            for part in body_parts.parts() {
+                dst.put(part);
+            }
+        }
+        if let Some(body_stream) = item.body_stream() {
+            for chunk in body_stream.chunks() {
+                dst.put(chunk);
+            }
+        }
         Ok(())
     }
 }
@@ -219,4 +267,39 @@ mod tests {
             .set_remark_option(Some("remark".to_string()));
         assert!(encoder.encode(command, &mut dst).is_ok());
     }
+
+    struct StaticParts(Vec<&'static [u8]>);
+
+    impl crate::protocol::remoting_command::BodyParts for StaticParts {
+        fn parts(&self) -> Vec<&[u8]> {
+            self.0.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn encode_body_parts_like_contiguous_body() {
+        let mut encoder = RemotingCommandCodec::new();
+        let command = RemotingCommand::create_response_command()
+            .set_opaque(7)
+            .set_remark_option(Some("remark".to_string()));
+
+        let mut expected = BytesMut::new();
+        encoder
+            .encode(
+                command.clone().set_body(Bytes::from("hello world")),
+                &mut expected,
+            )
+            .unwrap();
+        let mut actual = BytesMut::new();
+        encoder
+            .encode(
+                command.set_body_parts(StaticParts(vec![b"hello", b" ", b"world"])),
+                &mut actual,
+            )
+            .unwrap();
+        assert_eq!(expected, actual);
+
+        let decoded = encoder.decode(&mut actual).unwrap().unwrap();
+        assert_eq!(decoded.get_body().unwrap().as_ref(), b"hello world");
+    }
 }
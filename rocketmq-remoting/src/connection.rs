@@ -16,12 +16,16 @@
  */
 use std::hash::Hash;
 use std::hash::Hasher;
+use std::io::IoSlice;
 
-use futures_util::stream::SplitSink;
-use futures_util::stream::SplitStream;
-use futures_util::StreamExt;
+use bytes::BytesMut;
+use futures_util::SinkExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::net::tcp::OwnedWriteHalf;
 use tokio::net::TcpStream;
-use tokio_util::codec::Framed;
+use tokio_util::codec::FramedRead;
+use tokio_util::codec::FramedWrite;
 
 use crate::codec::remoting_command_codec::RemotingCommandCodec;
 use crate::protocol::remoting_command::RemotingCommand;
@@ -39,11 +43,11 @@ use crate::protocol::remoting_command::RemotingCommand;
 /// When sending frames, the frame is first encoded into the write buffer.
 /// The contents of the write buffer are then written to the socket.
 pub struct Connection {
-    /// The `Framed` instance used for reading from and writing to the TCP stream.
-    /// It leverages the `RemotingCommandCodec` for encoding and decoding frames.
+    /// The framed halves used for writing to and reading from the TCP stream.
+    /// They leverage the `RemotingCommandCodec` for encoding and decoding frames.
     //pub(crate) framed: Framed<TcpStream, RemotingCommandCodec>,
-    pub(crate) writer: SplitSink<Framed<TcpStream, RemotingCommandCodec>, RemotingCommand>,
-    pub(crate) reader: SplitStream<Framed<TcpStream, RemotingCommandCodec>>,
+    pub(crate) writer: FramedWrite<OwnedWriteHalf, RemotingCommandCodec>,
+    pub(crate) reader: FramedRead<OwnedReadHalf, RemotingCommandCodec>,
 
     /// A boolean flag indicating the current state of the connection.
     /// `true` means the connection is in a good state, while `false` indicates
@@ -58,13 +62,10 @@ impl Hash for Connection {
 
         // Use the addr: *const _ess of writer and reader to hash them (they serve as a unique
         // identifier for these components)
-        let writer_addr: *const SplitSink<
-            Framed<TcpStream, RemotingCommandCodec>,
-            RemotingCommand,
-        > = &self.writer
-            as *const SplitSink<Framed<TcpStream, RemotingCommandCodec>, RemotingCommand>;
-        let reader_addr: *const SplitStream<Framed<TcpStream, RemotingCommandCodec>> =
-            &self.reader as *const SplitStream<Framed<TcpStream, RemotingCommandCodec>>;
+        let writer_addr: *const FramedWrite<OwnedWriteHalf, RemotingCommandCodec> =
+            &self.writer as *const FramedWrite<OwnedWriteHalf, RemotingCommandCodec>;
+        let reader_addr: *const FramedRead<OwnedReadHalf, RemotingCommandCodec> =
+            &self.reader as *const FramedRead<OwnedReadHalf, RemotingCommandCodec>;
 
         writer_addr.hash(state);
         reader_addr.hash(state);
@@ -95,8 +96,9 @@ impl Connection {
     ///
     /// A new `Connection` instance.
     pub fn new(tcp_stream: TcpStream) -> Connection {
-        let framed = Framed::with_capacity(tcp_stream, RemotingCommandCodec::new(), 1024 * 4);
-        let (writer, reader) = framed.split();
+        let (read_half, write_half) = tcp_stream.into_split();
+        let reader = FramedRead::with_capacity(read_half, RemotingCommandCodec::new(), 1024 * 4);
+        let writer = FramedWrite::new(write_half, RemotingCommandCodec::new());
         Self {
             writer,
             reader,
@@ -109,11 +111,188 @@ impl Connection {
     /*pub fn framed(&self) -> &Framed<TcpStream, RemotingCommandCodec> {
         &self.framed
     }*/
-    pub fn reader(&self) -> &SplitStream<Framed<TcpStream, RemotingCommandCodec>> {
+    pub fn reader(&self) -> &FramedRead<OwnedReadHalf, RemotingCommandCodec> {
         &self.reader
     }
 
-    pub fn writer(&self) -> &SplitSink<Framed<TcpStream, RemotingCommandCodec>, RemotingCommand> {
+    pub fn writer(&self) -> &FramedWrite<OwnedWriteHalf, RemotingCommandCodec> {
         &self.writer
     }
+
+    /// Sends `command`, writing its [`BodyParts`](crate::protocol::remoting_command::BodyParts)
+    /// straight to the socket with vectored writes instead of copying them into the frame
+    /// buffer, then its [`BodyStream`](crate::protocol::remoting_command::BodyStream) one chunk
+    /// at a time.
+    pub async fn send_with_body_parts(
+        &mut self,
+        mut command: RemotingCommand,
+    ) -> crate::Result<()> {
+        let body_parts = command.body_parts().cloned();
+        let body_stream = command.body_stream().cloned();
+        if body_parts.is_none() && body_stream.is_none() {
+            return self.writer.send(command).await;
+        }
+        // frames queued before this one have to hit the socket first
+        self.writer.flush().await?;
+
+        let mut header = BytesMut::new();
+        command.fast_header_encode(&mut header);
+        if let Some(body) = command.get_body() {
+            header.extend_from_slice(body);
+        }
+        let parts = body_parts
+            .as_ref()
+            .map(|body_parts| body_parts.parts())
+            .unwrap_or_default();
+        let buffers: Vec<&[u8]> = std::iter::once(header.as_ref())
+            .chain(parts.into_iter().filter(|part| !part.is_empty()))
+            .collect();
+
+        let stream = self.writer.get_mut();
+        write_all_vectored(stream, &buffers).await?;
+        if let Some(body_stream) = body_stream {
+            for chunk in body_stream.chunks() {
+                stream.write_all(&chunk).await?;
+            }
+        }
+        stream.flush().await?;
+        Ok(())
+    }
+}
+
+async fn write_all_vectored(stream: &mut OwnedWriteHalf, buffers: &[&[u8]]) -> crate::Result<()> {
+    // (index of the first unwritten buffer, bytes of it already written)
+    let (mut index, mut offset) = (0, 0);
+    while index < buffers.len() {
+        let slices: Vec<IoSlice<'_>> = std::iter::once(&buffers[index][offset..])
+            .chain(buffers[index + 1..].iter().copied())
+            .map(IoSlice::new)
+            .collect();
+        let mut written = stream.write_vectored(&slices).await?;
+        if written == 0 {
+            return Err(std::io::Error::from(std::io::ErrorKind::WriteZero).into());
+        }
+        while index < buffers.len() && written >= buffers[index].len() - offset {
+            written -= buffers[index].len() - offset;
+            index += 1;
+            offset = 0;
+        }
+        offset += written;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use futures_util::StreamExt;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::protocol::remoting_command::BodyParts;
+    use crate::protocol::remoting_command::BodyStream;
+
+    struct StaticParts(Vec<&'static [u8]>);
+
+    impl BodyParts for StaticParts {
+        fn parts(&self) -> Vec<&[u8]> {
+            self.0.clone()
+        }
+    }
+
+    /// `count` chunks of `chunk_len` bytes, the i-th one filled with `b'a' + i % 26`.
+    struct Letters {
+        count: usize,
+        chunk_len: usize,
+    }
+
+    impl BodyStream for Letters {
+        fn length(&self) -> usize {
+            self.count * self.chunk_len
+        }
+
+        fn chunks(&self) -> Box<dyn Iterator<Item = Bytes> + Send + '_> {
+            Box::new(
+                (0..self.count).map(|i| Bytes::from(vec![b'a' + (i % 26) as u8; self.chunk_len])),
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn send_with_body_parts_writes_a_single_frame() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = tokio::spawn(async move { TcpStream::connect(addr).await.unwrap() });
+        let (server_stream, _) = listener.accept().await.unwrap();
+        let mut server = Connection::new(server_stream);
+        let mut client = Connection::new(client.await.unwrap());
+
+        server
+            .send_with_body_parts(RemotingCommand::create_response_command().set_opaque(1))
+            .await
+            .unwrap();
+        server
+            .send_with_body_parts(
+                RemotingCommand::create_response_command()
+                    .set_opaque(2)
+                    .set_body(Bytes::from_static(b"head-"))
+                    .set_body_parts(StaticParts(vec![b"mapped", b"", b"-tail"])),
+            )
+            .await
+            .unwrap();
+
+        let first = client.reader.next().await.unwrap().unwrap();
+        assert_eq!(first.opaque(), 1);
+        assert!(first.get_body().is_none());
+        let second = client.reader.next().await.unwrap().unwrap();
+        assert_eq!(second.opaque(), 2);
+        assert_eq!(second.get_body().unwrap().as_ref(), b"head-mapped-tail");
+    }
+
+    #[tokio::test]
+    async fn streamed_body_is_followed_by_the_next_frame() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = tokio::spawn(async move { TcpStream::connect(addr).await.unwrap() });
+        let (server_stream, _) = listener.accept().await.unwrap();
+        let mut server = Connection::new(server_stream);
+        let mut client = Connection::new(client.await.unwrap());
+
+        let letters = Letters {
+            count: 100,
+            chunk_len: 4096,
+        };
+        let expected: Vec<u8> = letters.chunks().flat_map(|chunk| chunk.to_vec()).collect();
+        let reader = tokio::spawn(async move {
+            let first = client.reader.next().await.unwrap().unwrap();
+            let second = client.reader.next().await.unwrap().unwrap();
+            (first, second)
+        });
+        server
+            .send_with_body_parts(
+                RemotingCommand::create_response_command()
+                    .set_opaque(1)
+                    .set_body(Bytes::from_static(b"head-"))
+                    .set_body_parts(StaticParts(vec![b"parts-"]))
+                    .set_body_stream(letters),
+            )
+            .await
+            .unwrap();
+        server
+            .send_with_body_parts(
+                RemotingCommand::create_response_command()
+                    .set_opaque(2)
+                    .set_body(Bytes::from_static(b"next")),
+            )
+            .await
+            .unwrap();
+
+        let (first, second) = reader.await.unwrap();
+        assert_eq!(first.opaque(), 1);
+        let body = first.get_body().unwrap();
+        assert_eq!(&body[..11], b"head-parts-");
+        assert_eq!(&body[11..], expected.as_slice());
+        assert_eq!(second.opaque(), 2);
+        assert_eq!(second.get_body().unwrap().as_ref(), b"next");
+    }
 }
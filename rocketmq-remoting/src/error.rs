@@ -64,6 +64,9 @@ pub enum Error {
 
     #[error("Channel recv Request failed: {0}")]
     ChannelRecvRequestFailed(String),
+
+    #[error("Channel to {0} closed")]
+    ChannelClosed(String),
 }
 
 #[cfg(test)]
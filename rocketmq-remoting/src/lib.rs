@@ -34,7 +34,10 @@ pub mod base;
 pub mod remoting;
 pub mod remoting_server;
 pub mod request_processor;
+pub mod request_tracing;
 pub mod rpc;
 pub mod runtime;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
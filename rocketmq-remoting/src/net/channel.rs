@@ -23,11 +23,15 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 
-use futures_util::SinkExt;
+use lazy_static::lazy_static;
 use rocketmq_rust::ArcMut;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc::error::SendTimeoutError;
 use tokio::sync::mpsc::Receiver;
 use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
 use tracing::error;
+use tracing::warn;
 use uuid::Uuid;
 
 use crate::base::response_future::ResponseFuture;
@@ -36,8 +40,16 @@ use crate::error::Error;
 use crate::error::Error::ChannelSendRequestFailed;
 use crate::error::Error::Io;
 use crate::protocol::remoting_command::RemotingCommand;
+use crate::runtime::config::net_system_config::NetSystemConfig;
 use crate::Result;
 
+lazy_static! {
+    static ref NET_SYSTEM_CONFIG: NetSystemConfig = NetSystemConfig::new();
+}
+
+/// Capacity of the per-channel outbound queue.
+const OUTBOUND_QUEUE_CAPACITY: usize = 1024;
+
 #[derive(Clone)]
 pub struct Channel {
     local_address: SocketAddr,
@@ -46,6 +58,10 @@ pub struct Channel {
     tx: tokio::sync::mpsc::Sender<ChannelMessage>,
     pub(crate) connection: ArcMut<Connection>,
     pub(crate) response_table: ArcMut<HashMap<i32, ResponseFuture>>,
+    /// How long a single frame may take to be queued or written before the peer is
+    /// considered stalled and the channel is closed.
+    write_timeout: Duration,
+    closed: CancellationToken,
 }
 
 type ChannelMessage = (
@@ -54,34 +70,55 @@ type ChannelMessage = (
     Option<u64>,
 );
 
+/// The single writer of a connection: requests, responses and server pushes are all
+/// funnelled through here so frames never interleave on the socket.
 pub(crate) async fn run_send(
     mut connection: ArcMut<Connection>,
     mut rx: Receiver<ChannelMessage>,
     mut response_table: ArcMut<HashMap<i32, ResponseFuture>>,
+    write_timeout: Duration,
+    closed: CancellationToken,
 ) {
-    while let Some((request, tx, timeout_millis)) = rx.recv().await {
-        let opaque = request.opaque();
+    loop {
+        let (command, tx, timeout_millis) = tokio::select! {
+            message = rx.recv() => match message {
+                Some(message) => message,
+                None => break,
+            },
+            _ = closed.cancelled() => break,
+        };
+        let opaque = command.opaque();
         if let Some(tx) = tx {
             response_table.insert(
                 opaque,
                 ResponseFuture::new(opaque, timeout_millis.unwrap_or(0), true, tx),
             );
         }
-        match connection.writer.send(request).await {
-            Ok(_) => {}
-            Err(error) => match error {
-                Io(error) => {
-                    error!("send request failed: {}", error);
-                    response_table.remove(&opaque);
-                    connection.ok = false;
-                    return;
-                }
-                _ => {
-                    response_table.remove(&opaque);
-                }
-            },
-        };
+        match timeout(write_timeout, connection.send_with_body_parts(command)).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(Io(error))) => {
+                error!("send command failed: {}", error);
+                response_table.remove(&opaque);
+                break;
+            }
+            Ok(Err(error)) => {
+                error!("send command failed: {}", error);
+                response_table.remove(&opaque);
+            }
+            Err(_) => {
+                // the frame may be half written, nothing else can follow it on this socket
+                warn!(
+                    "write of command[opaque={}] did not complete within {:?}, closing channel",
+                    opaque, write_timeout
+                );
+                response_table.remove(&opaque);
+                break;
+            }
+        }
     }
+    connection.ok = false;
+    closed.cancel();
+    let _ = connection.writer.get_mut().shutdown().await;
 }
 
 impl PartialEq for Channel {
@@ -135,12 +172,38 @@ impl Channel {
         remote_address: SocketAddr,
         connection: Connection,
         response_table: ArcMut<HashMap<i32, ResponseFuture>>,
+    ) -> Self {
+        let write_timeout =
+            Duration::from_secs(NET_SYSTEM_CONFIG.client_channel_max_idle_seconds.max(1) as u64);
+        Self::with_write_timeout(
+            local_address,
+            remote_address,
+            connection,
+            response_table,
+            write_timeout,
+        )
+    }
+
+    /// Like [`Channel::new`], but closes the channel once a frame cannot be queued or
+    /// written within `write_timeout` instead of the default `client_channel_max_idle`.
+    pub fn with_write_timeout(
+        local_address: SocketAddr,
+        remote_address: SocketAddr,
+        connection: Connection,
+        response_table: ArcMut<HashMap<i32, ResponseFuture>>,
+        write_timeout: Duration,
     ) -> Self {
         let channel_id = Uuid::new_v4().to_string();
-        let (tx, rx) = tokio::sync::mpsc::channel(1024);
-        //let response_table = ArcMut::new(HashMap::with_capacity(32));
+        let (tx, rx) = tokio::sync::mpsc::channel(OUTBOUND_QUEUE_CAPACITY);
         let connection = ArcMut::new(connection);
-        tokio::spawn(run_send(connection.clone(), rx, response_table.clone()));
+        let closed = CancellationToken::new();
+        tokio::spawn(run_send(
+            connection.clone(),
+            rx,
+            response_table.clone(),
+            write_timeout,
+            closed.clone(),
+        ));
         Self {
             local_address,
             remote_address,
@@ -148,6 +211,8 @@ impl Channel {
             tx,
             connection,
             response_table,
+            write_timeout,
+            closed,
         }
     }
 }
@@ -173,6 +238,25 @@ impl Channel {
         self.channel_id.as_str()
     }
 
+    pub fn write_timeout(&self) -> Duration {
+        self.write_timeout
+    }
+
+    /// Stops the writer and shuts down the write half of the connection.
+    pub fn close(&self) {
+        self.closed.cancel();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.is_cancelled()
+    }
+
+    /// Completes once the channel has been closed, either explicitly or because a write
+    /// failed or timed out.
+    pub async fn closed(&self) {
+        self.closed.cancelled().await
+    }
+
     pub fn connection(&self) -> ArcMut<Connection> {
         self.connection.clone()
     }
@@ -188,11 +272,16 @@ impl Channel {
         self.connection.mut_from_ref()
     }
 
+    /// Sends `request` and waits for the peer to answer it. Fails with
+    /// [`Error::ChannelClosed`] when the channel is closed before the answer arrives.
     pub async fn send_wait_response(
         &mut self,
         request: RemotingCommand,
         timeout_millis: u64,
     ) -> Result<RemotingCommand> {
+        if self.is_closed() {
+            return Err(Error::ChannelClosed(self.remote_address.to_string()));
+        }
         let (tx, rx) = tokio::sync::oneshot::channel::<Result<RemotingCommand>>();
         let opaque = request.opaque();
         if let Err(err) = self
@@ -203,7 +292,15 @@ impl Channel {
             return Err(ChannelSendRequestFailed(err.to_string()));
         }
 
-        match timeout(Duration::from_millis(timeout_millis), rx).await {
+        let response = tokio::select! {
+            biased;
+            response = timeout(Duration::from_millis(timeout_millis), rx) => response,
+            _ = self.closed.cancelled() => {
+                self.response_table.remove(&opaque);
+                return Err(Error::ChannelClosed(self.remote_address.to_string()));
+            }
+        };
+        match response {
             Ok(result) => match result {
                 Ok(response) => response,
                 Err(e) => {
@@ -218,6 +315,44 @@ impl Channel {
         }
     }
 
+    /// Queues `response` behind any frame already pending on this channel.
+    ///
+    /// If the outbound queue stays full for longer than the write timeout the peer has
+    /// stopped reading: the response is dropped and the channel is closed.
+    pub async fn send_response(&self, response: RemotingCommand) -> Result<()> {
+        if self.is_closed() {
+            return Err(ChannelSendRequestFailed(format!(
+                "channel to {} is closed",
+                self.remote_address
+            )));
+        }
+        match self
+            .tx
+            .send_timeout((response, None, None), self.write_timeout)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(SendTimeoutError::Timeout((response, _, _))) => {
+                warn!(
+                    "outbound queue of channel to {} stayed full for {:?}, dropping \
+                     response[opaque={}] and closing channel",
+                    self.remote_address,
+                    self.write_timeout,
+                    response.opaque()
+                );
+                self.close();
+                Err(ChannelSendRequestFailed(format!(
+                    "channel to {} is not writable",
+                    self.remote_address
+                )))
+            }
+            Err(SendTimeoutError::Closed(_)) => Err(ChannelSendRequestFailed(format!(
+                "channel to {} is closed",
+                self.remote_address
+            ))),
+        }
+    }
+
     pub async fn send_one_way(
         &mut self,
         request: RemotingCommand,
@@ -238,7 +373,113 @@ mod tests {
     use std::net::Ipv4Addr;
     use std::net::SocketAddr;
 
+    use futures_util::StreamExt;
+    use tokio::net::TcpListener;
+    use tokio::net::TcpStream;
+    use tokio_util::codec::FramedRead;
+
     use super::*;
+    use crate::code::request_code::RequestCode;
+    use crate::codec::remoting_command_codec::RemotingCommandCodec;
+    use crate::protocol::RemotingCommandType;
+
+    async fn accept_channel(write_timeout: Duration) -> (Channel, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = tokio::spawn(async move { TcpStream::connect(addr).await.unwrap() });
+        let (stream, remote_address) = listener.accept().await.unwrap();
+        let channel = Channel::with_write_timeout(
+            stream.local_addr().unwrap(),
+            remote_address,
+            Connection::new(stream),
+            ArcMut::new(HashMap::new()),
+            write_timeout,
+        );
+        (channel, client.await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn stalled_reader_closes_channel_after_write_timeout() {
+        let (channel, _stalled_client) = accept_channel(Duration::from_millis(200)).await;
+
+        // far more than the socket buffers can absorb while nobody reads
+        let response =
+            RemotingCommand::create_response_command().set_body(vec![0u8; 32 * 1024 * 1024]);
+        channel.send_response(response).await.unwrap();
+
+        timeout(Duration::from_secs(10), channel.closed())
+            .await
+            .expect("channel to a stalled reader was not closed");
+        assert!(!channel.connection_ref().ok);
+        assert!(channel
+            .send_response(RemotingCommand::create_response_command())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn pushes_and_responses_share_the_connection() {
+        let (mut channel, client) = accept_channel(Duration::from_secs(5)).await;
+        let mut reader = FramedRead::new(client, RemotingCommandCodec::new());
+
+        channel
+            .send_response(
+                RemotingCommand::create_response_command()
+                    .set_opaque(7)
+                    .set_body(vec![1u8; 256 * 1024]),
+            )
+            .await
+            .unwrap();
+        channel
+            .send_one_way(
+                RemotingCommand::create_remoting_command(RequestCode::NotifyConsumerIdsChanged),
+                100,
+            )
+            .await
+            .unwrap();
+        channel
+            .send_response(RemotingCommand::create_response_command().set_opaque(8))
+            .await
+            .unwrap();
+
+        let first = reader.next().await.unwrap().unwrap();
+        assert_eq!(first.get_type(), RemotingCommandType::RESPONSE);
+        assert_eq!(first.opaque(), 7);
+        assert_eq!(first.get_body().unwrap().len(), 256 * 1024);
+        let push = reader.next().await.unwrap().unwrap();
+        assert_eq!(push.get_type(), RemotingCommandType::REQUEST);
+        assert!(push.is_oneway_rpc());
+        assert_eq!(push.code(), RequestCode::NotifyConsumerIdsChanged as i32);
+        let second = reader.next().await.unwrap().unwrap();
+        assert_eq!(second.opaque(), 8);
+        assert!(!channel.is_closed());
+    }
+
+    #[tokio::test]
+    async fn pending_call_fails_once_the_channel_closes() {
+        let (mut channel, _silent_client) = accept_channel(Duration::from_secs(5)).await;
+        let closer = channel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            closer.close();
+        });
+
+        let request = RemotingCommand::create_remoting_command(RequestCode::GetConsumerRunningInfo);
+        let result = timeout(
+            Duration::from_secs(5),
+            channel.send_wait_response(request, 60_000),
+        )
+        .await
+        .expect("pending call outlived the channel");
+        assert!(matches!(result, Err(Error::ChannelClosed(_))));
+        assert!(channel.response_table.is_empty());
+
+        let request = RemotingCommand::create_remoting_command(RequestCode::GetConsumerRunningInfo);
+        assert!(matches!(
+            channel.send_wait_response(request, 60_000).await,
+            Err(Error::ChannelClosed(_))
+        ));
+    }
 
     #[test]
     fn channel_creation_with_new() {
@@ -21,7 +21,6 @@ use std::fmt::Formatter;
 use std::sync::atomic::AtomicI64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::time::SystemTime;
 
 use cheetah_string::CheetahString;
 use rocketmq_common::error::Error;
@@ -221,15 +220,16 @@ pub struct DataVersion {
     counter: Arc<AtomicI64>,
 }
 
+/// Serializes like Java's fastjson output, i.e. `{"counter":n,"stateVersion":n,"timestamp":n}`.
 impl Serialize for DataVersion {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
         let mut state = serializer.serialize_struct("DataVersion", 3)?;
+        state.serialize_field("counter", &self.counter.load(Ordering::SeqCst))?;
         state.serialize_field("stateVersion", &self.state_version)?;
         state.serialize_field("timestamp", &self.timestamp)?;
-        state.serialize_field("counter", &self.counter.load(Ordering::SeqCst))?;
         state.end()
     }
 }
@@ -239,29 +239,43 @@ impl<'de> Deserialize<'de> for DataVersion {
     where
         D: serde::Deserializer<'de>,
     {
+        /// The counter is a plain number in current payloads, while legacy payloads carry the
+        /// serialized `AtomicLong` wrapper, e.g. `{"value":n}`.
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum CounterHelper {
+            Plain(i64),
+            Wrapped { value: i64 },
+        }
+
         #[derive(Deserialize)]
         #[serde(rename_all = "camelCase")]
         struct DataVersionHelper {
+            #[serde(default)]
             state_version: i64,
             timestamp: i64,
-            counter: i64,
+            counter: CounterHelper,
         }
 
         let helper = DataVersionHelper::deserialize(deserializer)?;
+        let counter = match helper.counter {
+            CounterHelper::Plain(value) | CounterHelper::Wrapped { value } => value,
+        };
         Ok(DataVersion {
             state_version: helper.state_version,
             timestamp: helper.timestamp,
-            counter: Arc::new(AtomicI64::new(helper.counter)),
+            counter: Arc::new(AtomicI64::new(counter)),
         })
     }
 }
 
+/// Clones take a snapshot of the counter, so bumping the original does not change the copy.
 impl Clone for DataVersion {
     fn clone(&self) -> Self {
         DataVersion {
             state_version: self.state_version,
             timestamp: self.timestamp,
-            counter: self.counter.clone(),
+            counter: Arc::new(AtomicI64::new(self.counter.load(Ordering::SeqCst))),
         }
     }
 }
@@ -274,6 +288,28 @@ impl PartialEq for DataVersion {
     }
 }
 
+impl Eq for DataVersion {}
+
+impl PartialOrd for DataVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Orders by `(state_version, timestamp, counter)`.
+impl Ord for DataVersion {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.state_version
+            .cmp(&other.state_version)
+            .then_with(|| self.timestamp.cmp(&other.timestamp))
+            .then_with(|| {
+                self.counter
+                    .load(Ordering::Relaxed)
+                    .cmp(&other.counter.load(Ordering::Relaxed))
+            })
+    }
+}
+
 impl Default for DataVersion {
     fn default() -> Self {
         DataVersion::new()
@@ -282,10 +318,7 @@ impl Default for DataVersion {
 
 impl DataVersion {
     pub fn new() -> Self {
-        let timestamp = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_millis() as i64;
+        let timestamp = time_utils::get_current_millis() as i64;
 
         DataVersion {
             state_version: 0,
@@ -332,8 +365,9 @@ impl DataVersion {
         self.counter.load(Ordering::Relaxed)
     }
 
+    /// Bumps the counter and refreshes the timestamp, keeping the current state version.
     pub fn next_version(&mut self) {
-        self.next_version_with(0)
+        self.next_version_with(self.state_version)
     }
 
     pub fn next_version_with(&mut self, state_version: i64) {
@@ -586,6 +620,76 @@ mod tests {
             );
         }
 
+        #[test]
+        fn data_version_next_version_keeps_state_version() {
+            let mut data_version = DataVersion::new();
+            data_version.set_state_version(7);
+            data_version.next_version();
+            assert_eq!(7, data_version.state_version());
+            assert_eq!(1, data_version.counter());
+        }
+
+        #[test]
+        fn data_version_assign_new_one_copies_all_fields() {
+            let mut source = DataVersion::new();
+            source.next_version_with(3);
+            let mut target = DataVersion::new();
+            target.assign_new_one(&source);
+            assert_eq!(source, target);
+            source.next_version();
+            assert_eq!(1, target.counter());
+            assert_ne!(source, target);
+        }
+
+        #[test]
+        fn data_version_clone_does_not_share_counter() {
+            let data_version = DataVersion::new();
+            let snapshot = data_version.clone();
+            data_version.increment_counter();
+            assert_eq!(0, snapshot.counter());
+            assert!(snapshot < data_version);
+        }
+
+        #[test]
+        fn data_version_ordering() {
+            let version = |state_version, timestamp, counter| -> DataVersion {
+                serde_json::from_value(serde_json::json!({
+                    "stateVersion": state_version,
+                    "timestamp": timestamp,
+                    "counter": counter
+                }))
+                .unwrap()
+            };
+            assert!(version(1, 0, 0) > version(0, 100, 100));
+            assert!(version(1, 101, 0) > version(1, 100, 100));
+            assert!(version(1, 100, 101) > version(1, 100, 100));
+            assert_eq!(
+                version(1, 100, 100).cmp(&version(1, 100, 100)),
+                std::cmp::Ordering::Equal
+            );
+        }
+
+        #[test]
+        fn data_version_json_matches_java_shape() {
+            let data_version: DataVersion =
+                serde_json::from_str(r#"{"counter":5,"stateVersion":2,"timestamp":1700000000000}"#)
+                    .unwrap();
+            assert_eq!(
+                serde_json::to_string(&data_version).unwrap(),
+                r#"{"counter":5,"stateVersion":2,"timestamp":1700000000000}"#
+            );
+        }
+
+        #[test]
+        fn data_version_deserialize_legacy_atomic_counter() {
+            let data_version: DataVersion =
+                serde_json::from_str(r#"{"counter":{"value":9},"timestamp":1700000000000}"#)
+                    .unwrap();
+            assert_eq!(9, data_version.counter());
+            assert_eq!(0, data_version.state_version());
+            assert_eq!(1700000000000, data_version.timestamp());
+        }
+
         #[test]
         fn data_version_next_version_with_state() {
             let mut data_version = DataVersion::new();
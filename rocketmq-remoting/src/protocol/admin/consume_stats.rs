@@ -57,6 +57,10 @@ impl ConsumeStats {
         self.offset_table.clone()
     }
 
+    pub fn get_offset_table_mut(&mut self) -> &mut HashMap<MessageQueue, OffsetWrapper> {
+        &mut self.offset_table
+    }
+
     pub fn set_offset_table(&mut self, offset_table: HashMap<MessageQueue, OffsetWrapper>) {
         self.offset_table = offset_table;
     }
@@ -46,3 +46,46 @@ impl TopicStatsTable {
         self.offset_table = offset_table;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::RemotingDeserializable;
+    use crate::protocol::RemotingSerializable;
+
+    #[test]
+    fn offset_table_serializes_message_queue_as_object_key() {
+        let mut topic_offset = TopicOffset::new();
+        topic_offset.set_min_offset(1);
+        topic_offset.set_max_offset(10);
+        topic_offset.set_last_update_timestamp(1_700_000_000_000);
+        let mut offset_table = HashMap::new();
+        offset_table.insert(
+            MessageQueue::from_parts("TopicTest", "broker-a", 0),
+            topic_offset,
+        );
+        let mut table = TopicStatsTable::new();
+        table.set_offset_table(offset_table);
+
+        let json: serde_json::Value = serde_json::from_slice(&table.encode()).unwrap();
+        let entries = json["offsetTable"].as_object().unwrap();
+        assert_eq!(entries.len(), 1);
+        let (key, value) = entries.iter().next().unwrap();
+        let key: serde_json::Value = serde_json::from_str(key).unwrap();
+        assert_eq!(key["topic"], "TopicTest");
+        assert_eq!(key["brokerName"], "broker-a");
+        assert_eq!(key["queueId"], 0);
+        assert_eq!(value["minOffset"], 1);
+        assert_eq!(value["maxOffset"], 10);
+        assert_eq!(value["lastUpdateTimestamp"], 1_700_000_000_000i64);
+
+        let decoded = TopicStatsTable::decode(&table.encode()).unwrap();
+        let offset = decoded
+            .get_offset_table()
+            .get(&MessageQueue::from_parts("TopicTest", "broker-a", 0))
+            .cloned()
+            .unwrap();
+        assert_eq!(offset.get_max_offset(), 10);
+        assert_eq!(offset.get_last_update_timestamp(), 1_700_000_000_000);
+    }
+}
@@ -26,14 +26,21 @@ pub mod check_client_request_body;
 pub mod cm_result;
 pub mod connection;
 pub mod consume_message_directly_result;
+pub mod consume_queue_data;
+pub mod consume_stats_list;
+pub mod delay_progress;
 pub mod group_list;
 pub mod kv_table;
 pub mod pop_process_queue_info;
 pub mod process_queue_info;
 pub mod query_assignment_request_body;
 pub mod query_assignment_response_body;
+pub mod query_consume_queue_response_body;
+pub mod query_correction_offset_body;
 pub mod request;
+pub mod reset_offset_body;
 pub mod response;
+pub mod route_snapshot;
 pub mod set_message_request_mode_request_body;
 pub mod topic;
 pub mod topic_info_wrapper;
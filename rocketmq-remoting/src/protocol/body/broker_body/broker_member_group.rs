@@ -26,6 +26,9 @@ pub struct BrokerMemberGroup {
     pub cluster: CheetahString,
     pub broker_name: CheetahString,
     pub broker_addrs: HashMap<u64 /* brokerId */, CheetahString /* broker address */>,
+    /// Epoch each member registered with, lets peers pick the freshest replica.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub broker_epochs: HashMap<u64 /* brokerId */, i32 /* epoch */>,
 }
 
 impl BrokerMemberGroup {
@@ -34,6 +37,7 @@ impl BrokerMemberGroup {
             cluster,
             broker_name,
             broker_addrs: HashMap::new(),
+            broker_epochs: HashMap::new(),
         }
     }
 }
@@ -73,6 +77,7 @@ mod tests {
             cluster: cluster.clone(),
             broker_name: broker_name.clone(),
             broker_addrs: broker_addrs.clone(),
+            broker_epochs: HashMap::new(),
         };
 
         let serialized = serde_json::to_string(&group).unwrap();
@@ -126,4 +131,18 @@ mod tests {
         assert_eq!(group.broker_name, CheetahString::from("test_broker"));
         assert!(group.broker_addrs.is_empty());
     }
+
+    #[test]
+    fn broker_member_group_round_trips_epochs() {
+        let mut group = BrokerMemberGroup::new("test_cluster".into(), "test_broker".into());
+        group
+            .broker_addrs
+            .insert(0, CheetahString::from("127.0.0.1:10911"));
+        group.broker_epochs.insert(0, 3);
+
+        let serialized = serde_json::to_string(&group).unwrap();
+        assert!(serialized.contains(r#""brokerEpochs":{"0":3}"#));
+        let group: BrokerMemberGroup = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(group.broker_epochs.get(&0), Some(&3));
+    }
 }
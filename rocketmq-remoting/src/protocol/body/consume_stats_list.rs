@@ -0,0 +1,38 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+
+use cheetah_string::CheetahString;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::protocol::admin::consume_stats::ConsumeStats;
+
+/// Consume progress of every subscription group of a broker, answered to
+/// `GetBrokerConsumeStats`.
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsumeStatsList {
+    /// One single-entry map per group, from the group to the stats of each topic it consumes.
+    pub consume_stats_list: Vec<HashMap<CheetahString, Vec<ConsumeStats>>>,
+    pub broker_addr: CheetahString,
+    pub total_diff: i64,
+    pub total_inflight_diff: i64,
+    pub total_consume_tps: f64,
+    /// Set when the timeout ran out before every group was reported.
+    pub truncated: bool,
+}
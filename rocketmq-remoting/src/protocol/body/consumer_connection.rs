@@ -58,11 +58,11 @@ impl Serialize for ConsumerConnection {
         S: Serializer,
     {
         let mut s = serializer.serialize_struct("ConsumerConnection", 5)?;
-        s.serialize_field("connection_set", &self.connection_set)?;
-        s.serialize_field("subscription_table", &*self.subscription_table.read())?;
-        s.serialize_field("consume_type", &*self.consume_type.read())?;
-        s.serialize_field("message_model", &*self.message_model.read())?;
-        s.serialize_field("consume_from_where", &*self.consume_from_where.read())?;
+        s.serialize_field("connectionSet", &self.connection_set)?;
+        s.serialize_field("subscriptionTable", &*self.subscription_table.read())?;
+        s.serialize_field("consumeType", &*self.consume_type.read())?;
+        s.serialize_field("messageModel", &*self.message_model.read())?;
+        s.serialize_field("consumeFromWhere", &*self.consume_from_where.read())?;
         s.end()
     }
 }
@@ -0,0 +1,34 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::protocol::body::consume_queue_data::ConsumeQueueData;
+use crate::protocol::heartbeat::subscription_data::SubscriptionData;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryConsumeQueueResponseBody {
+    pub subscription_data: Option<SubscriptionData>,
+    pub filter_data: Option<String>,
+    pub queue_data: Vec<ConsumeQueueData>,
+    pub max_queue_index: i64,
+    pub min_queue_index: i64,
+    /// Messages from the queried index to the end of the queue the consumer group's filter is
+    /// estimated to accept, absent when no group was given.
+    pub estimated_message_count: Option<i64>,
+}
@@ -0,0 +1,42 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use cheetah_string::CheetahString;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::protocol::route::route_data_view::BrokerData;
+use crate::protocol::route::route_data_view::QueueData;
+use crate::protocol::static_topic::topic_queue_info::TopicQueueMappingInfo;
+
+/// The route tables of a name server, dumped for disaster recovery and imported to pre-warm a
+/// replacement. Broker liveness is deliberately not part of it.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteSnapshot {
+    pub topic_queue_table:
+        HashMap<CheetahString /* topic */, HashMap<CheetahString /* brokerName */, QueueData>>,
+    pub broker_addr_table: HashMap<CheetahString /* brokerName */, BrokerData>,
+    pub cluster_addr_table:
+        HashMap<CheetahString /* clusterName */, HashSet<CheetahString /* brokerName */>>,
+    pub topic_queue_mapping_info_table: HashMap<
+        CheetahString, /* topic */
+        HashMap<CheetahString /* brokerName */, TopicQueueMappingInfo>,
+    >,
+}
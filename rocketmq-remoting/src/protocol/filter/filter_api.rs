@@ -33,25 +33,29 @@ impl FilterAPI {
             ..Default::default()
         };
 
-        if sub_string.is_empty() || sub_string == SubscriptionData::SUB_ALL {
+        let trimmed = sub_string.trim();
+        if trimmed.is_empty() || trimmed == SubscriptionData::SUB_ALL {
             subscription_data.sub_string =
                 CheetahString::from_static_str(SubscriptionData::SUB_ALL);
             return Ok(subscription_data);
         }
 
-        let tags: Vec<&str> = sub_string.split("||").collect();
-        if tags.is_empty() {
-            return Err("subString split error".to_string());
-        }
-
-        for tag in tags {
-            let trimmed_tag = tag.trim();
-            if !trimmed_tag.is_empty() {
-                subscription_data.tags_set.insert(trimmed_tag.into());
-                subscription_data
-                    .code_set
-                    .insert(JavaStringHasher::new().hash_str(tag));
+        for tag in trimmed.split("||") {
+            let tag = tag.trim();
+            if tag.is_empty() {
+                return Err(format!("subString has an empty tag: {}", sub_string));
+            }
+            if tag == SubscriptionData::SUB_ALL {
+                return Err(format!(
+                    "subString can't mix {} with other tags: {}",
+                    SubscriptionData::SUB_ALL,
+                    sub_string
+                ));
             }
+            subscription_data.tags_set.insert(tag.into());
+            subscription_data
+                .code_set
+                .insert(JavaStringHasher::new().hash_str(tag));
         }
 
         Ok(subscription_data)
@@ -122,6 +126,48 @@ mod tests {
         assert_eq!(subscription_data.sub_string, SubscriptionData::SUB_ALL);
     }
 
+    #[test]
+    fn build_subscription_data_trims_tags_and_hashes_the_trimmed_tag() {
+        let topic = "test_topic".into();
+        let sub_string = " tagA ||tagB ".into();
+        let subscription_data = FilterAPI::build_subscription_data(&topic, &sub_string).unwrap();
+
+        assert_eq!(subscription_data.tags_set.len(), 2);
+        assert!(subscription_data.tags_set.contains("tagA"));
+        assert!(subscription_data.tags_set.contains("tagB"));
+        assert!(subscription_data
+            .code_set
+            .contains(&JavaStringHasher::new().hash_str("tagA")));
+        assert!(subscription_data
+            .code_set
+            .contains(&JavaStringHasher::new().hash_str("tagB")));
+    }
+
+    #[test]
+    fn build_subscription_data_treats_a_padded_star_as_sub_all() {
+        let subscription_data =
+            FilterAPI::build_subscription_data(&"test_topic".into(), &" * ".into()).unwrap();
+
+        assert_eq!(subscription_data.sub_string, SubscriptionData::SUB_ALL);
+        assert!(subscription_data.tags_set.is_empty());
+    }
+
+    #[test]
+    fn build_subscription_data_rejects_an_empty_tag() {
+        let topic = "test_topic".into();
+        for sub_string in ["tagA|| ||tagB", "tagA||||tagB", "||tagA", "tagA||"] {
+            let result = FilterAPI::build_subscription_data(&topic, &sub_string.into());
+            assert!(result.is_err(), "{sub_string} should not parse");
+        }
+    }
+
+    #[test]
+    fn build_subscription_data_rejects_star_mixed_with_tags() {
+        let result = FilterAPI::build_subscription_data(&"test_topic".into(), &"tagA||*".into());
+
+        assert!(result.is_err());
+    }
+
     #[test]
     fn build_subscription_data_with_expression_type_sets_expression_type() {
         let topic = "test_topic".into();
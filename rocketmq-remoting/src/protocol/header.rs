@@ -17,15 +17,22 @@
 pub mod broker;
 pub mod check_transaction_state_request_header;
 pub mod client_request_header;
+pub mod clone_group_offset_request_header;
 pub mod consumer_send_msg_back_request_header;
+pub mod create_access_config_request_header;
 pub mod create_topic_request_header;
+pub mod delete_access_config_request_header;
+pub mod delete_subscription_group_request_header;
 pub mod delete_topic_request_header;
 pub mod end_transaction_request_header;
 pub mod get_all_topic_config_response_header;
+pub mod get_broker_acl_config_response_header;
+pub mod get_consume_stats_in_broker_header;
 pub mod get_consume_stats_request_header;
 pub mod get_consumer_connection_list_request_header;
 pub mod get_consumer_listby_group_request_header;
 pub mod get_consumer_listby_group_response_header;
+pub mod get_earliest_msg_storetime_request_header;
 pub mod get_earliest_msg_storetime_response_header;
 pub mod get_max_offset_request_header;
 pub mod get_max_offset_response_header;
@@ -38,18 +45,24 @@ pub mod lock_batch_mq_request_header;
 pub mod message_operation_header;
 pub mod namesrv;
 pub mod notify_consumer_ids_changed_request_header;
+pub mod notify_topic_route_changed_request_header;
 pub mod pull_message_request_header;
 pub mod pull_message_response_header;
+pub mod query_consume_queue_request_header;
 pub mod query_consumer_offset_request_header;
 pub mod query_consumer_offset_response_header;
+pub mod query_correction_offset_header;
 pub mod query_message_request_header;
 pub mod query_message_response_header;
 pub mod query_topic_consume_by_who_request_header;
 pub mod query_topics_by_consumer_request_header;
 pub mod reply_message_request_header;
+pub mod reset_offset_request_header;
+pub mod search_offset_request_header;
 pub mod search_offset_response_header;
 pub mod unlock_batch_mq_request_header;
 pub mod unregister_client_request_header;
 pub mod update_consumer_offset_header;
+pub mod update_global_white_addrs_config_request_header;
 pub mod view_message_request_header;
 pub mod view_message_response_header;
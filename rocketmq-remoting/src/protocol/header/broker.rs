@@ -1 +1,2 @@
 pub mod broker_heartbeat_request_header;
+pub mod remove_broker_request_header;
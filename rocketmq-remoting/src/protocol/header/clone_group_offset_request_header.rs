@@ -0,0 +1,32 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use rocketmq_macros::RequestHeaderCodec;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Copies the committed offsets of `src_group` to `dest_group`, on `topic` or on every topic
+/// `src_group` has offsets of.
+#[derive(Debug, Clone, Serialize, Deserialize, RequestHeaderCodec, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CloneGroupOffsetRequestHeader {
+    pub src_group: CheetahString,
+    pub dest_group: CheetahString,
+    pub topic: Option<CheetahString>,
+    /// `src_group` is offline, clone its offsets even on topics it no longer subscribes to.
+    pub offline: bool,
+}
@@ -0,0 +1,35 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use rocketmq_macros::RequestHeaderCodec;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Creates or updates the plain ACL account `access_key`. The permission lists are comma
+/// separated `resource=PERM` entries.
+#[derive(Debug, Clone, Serialize, Deserialize, RequestHeaderCodec, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAccessConfigRequestHeader {
+    pub access_key: CheetahString,
+    pub secret_key: Option<CheetahString>,
+    pub white_remote_address: Option<CheetahString>,
+    pub admin: bool,
+    pub default_topic_perm: Option<CheetahString>,
+    pub default_group_perm: Option<CheetahString>,
+    pub topic_perms: Option<CheetahString>,
+    pub group_perms: Option<CheetahString>,
+}
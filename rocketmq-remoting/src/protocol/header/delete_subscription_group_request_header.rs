@@ -0,0 +1,114 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+
+use cheetah_string::CheetahString;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::protocol::command_custom_header::CommandCustomHeader;
+use crate::protocol::command_custom_header::FromMap;
+use crate::rpc::rpc_request_header::RpcRequestHeader;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteSubscriptionGroupRequestHeader {
+    pub group_name: CheetahString,
+    pub clean_offset: bool,
+    #[serde(flatten)]
+    pub rpc: Option<RpcRequestHeader>,
+}
+
+impl DeleteSubscriptionGroupRequestHeader {
+    pub const GROUP_NAME: &'static str = "groupName";
+    pub const CLEAN_OFFSET: &'static str = "cleanOffset";
+}
+
+impl CommandCustomHeader for DeleteSubscriptionGroupRequestHeader {
+    fn to_map(&self) -> Option<HashMap<CheetahString, CheetahString>> {
+        let mut map = HashMap::new();
+        map.insert(
+            CheetahString::from_static_str(Self::GROUP_NAME),
+            self.group_name.clone(),
+        );
+        map.insert(
+            CheetahString::from_static_str(Self::CLEAN_OFFSET),
+            CheetahString::from_string(self.clean_offset.to_string()),
+        );
+        if let Some(ref rpc) = self.rpc {
+            if let Some(rpc_map) = rpc.to_map() {
+                map.extend(rpc_map);
+            }
+        }
+        Some(map)
+    }
+}
+
+impl FromMap for DeleteSubscriptionGroupRequestHeader {
+    type Target = Self;
+
+    fn from(map: &HashMap<CheetahString, CheetahString>) -> Option<Self::Target> {
+        Some(DeleteSubscriptionGroupRequestHeader {
+            group_name: map
+                .get(&CheetahString::from_static_str(Self::GROUP_NAME))
+                .cloned()
+                .unwrap_or_default(),
+            clean_offset: map
+                .get(&CheetahString::from_static_str(Self::CLEAN_OFFSET))
+                .and_then(|value| value.parse::<bool>().ok())
+                .unwrap_or_default(),
+            rpc: <RpcRequestHeader as FromMap>::from(map),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn delete_subscription_group_request_header_round_trip() {
+        let header = DeleteSubscriptionGroupRequestHeader {
+            group_name: "test_group".into(),
+            clean_offset: true,
+            rpc: None,
+        };
+
+        let map = header.to_map().unwrap();
+        assert_eq!(
+            map.get(DeleteSubscriptionGroupRequestHeader::CLEAN_OFFSET),
+            Some(&"true".into())
+        );
+        let decoded = <DeleteSubscriptionGroupRequestHeader as FromMap>::from(&map).unwrap();
+        assert_eq!(decoded.group_name, "test_group");
+        assert!(decoded.clean_offset);
+    }
+
+    #[test]
+    fn delete_subscription_group_request_header_defaults_clean_offset_to_false() {
+        let mut map = HashMap::new();
+        map.insert(
+            DeleteSubscriptionGroupRequestHeader::GROUP_NAME.into(),
+            "test_group".into(),
+        );
+
+        let header = <DeleteSubscriptionGroupRequestHeader as FromMap>::from(&map).unwrap();
+        assert!(!header.clean_offset);
+    }
+}
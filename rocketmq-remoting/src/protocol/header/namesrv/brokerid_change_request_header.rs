@@ -1,6 +1,5 @@
 use std::collections::HashMap;
 
-use anyhow::Error;
 use cheetah_string::CheetahString;
 use serde::Deserialize;
 use serde::Serialize;
@@ -85,10 +84,6 @@ impl FromMap for NotifyMinBrokerIdChangeRequestHeader {
 }
 
 impl CommandCustomHeader for NotifyMinBrokerIdChangeRequestHeader {
-    fn check_fields(&self) -> anyhow::Result<(), Error> {
-        todo!()
-    }
-
     fn to_map(&self) -> Option<HashMap<CheetahString, CheetahString>> {
         let mut map = HashMap::<CheetahString, CheetahString>::new();
         if let Some(min_broker_id) = self.min_broker_id {
@@ -112,15 +107,6 @@ impl CommandCustomHeader for NotifyMinBrokerIdChangeRequestHeader {
             );
         }
 
-        if let Some(ref ha_broker_addr) = self.ha_broker_addr {
-            map.insert(
-                CheetahString::from_static_str(
-                    NotifyMinBrokerIdChangeRequestHeader::HA_BROKER_ADDR,
-                ),
-                ha_broker_addr.clone(),
-            );
-        }
-
         if let Some(ref offline_broker_addr) = self.offline_broker_addr {
             map.insert(
                 CheetahString::from_static_str(
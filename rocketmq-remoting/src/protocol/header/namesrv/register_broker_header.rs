@@ -59,6 +59,13 @@ pub struct RegisterBrokerRequestHeader {
     /// Indicates whether the data is compressed.
     pub compressed: bool,
 
+    /// The optional epoch of the broker, bumped every time it becomes master in controller mode.
+    pub epoch: Option<i32>,
+
+    /// The optional max physical offset of the broker's commit log.
+    #[serde(rename = "maxPhyOffset")]
+    pub max_phy_offset: Option<i64>,
+
     /// The CRC32 checksum for the message body.
     #[serde(rename = "bodyCrc32")]
     pub body_crc32: u32,
@@ -72,8 +79,10 @@ impl RegisterBrokerRequestHeader {
     const CLUSTER_NAME: &'static str = "clusterName";
     const COMPRESSED: &'static str = "compressed";
     const ENABLE_ACTING_MASTER: &'static str = "enableActingMaster";
+    const EPOCH: &'static str = "epoch";
     const HA_SERVER_ADDR: &'static str = "haServerAddr";
     const HEARTBEAT_TIMEOUT_MILLIS: &'static str = "heartbeatTimeoutMillis";
+    const MAX_PHY_OFFSET: &'static str = "maxPhyOffset";
 
     /// Creates a new instance of `RegisterBrokerRequestHeader`.
     ///
@@ -113,6 +122,8 @@ impl RegisterBrokerRequestHeader {
             heartbeat_timeout_millis,
             enable_acting_master,
             compressed,
+            epoch: None,
+            max_phy_offset: None,
             body_crc32,
         }
     }
@@ -169,6 +180,16 @@ impl FromMap for RegisterBrokerRequestHeader {
                 ))
                 .and_then(|s| s.parse::<bool>().ok())
                 .unwrap_or(false),
+            epoch: map
+                .get(&CheetahString::from_static_str(
+                    RegisterBrokerRequestHeader::EPOCH,
+                ))
+                .and_then(|s| s.parse::<i32>().ok()),
+            max_phy_offset: map
+                .get(&CheetahString::from_static_str(
+                    RegisterBrokerRequestHeader::MAX_PHY_OFFSET,
+                ))
+                .and_then(|s| s.parse::<i64>().ok()),
             body_crc32: map
                 .get(&CheetahString::from_static_str(
                     RegisterBrokerRequestHeader::BODY_CRC32,
@@ -228,6 +249,20 @@ impl CommandCustomHeader for RegisterBrokerRequestHeader {
             CheetahString::from_static_str(RegisterBrokerRequestHeader::COMPRESSED),
             CheetahString::from_string(self.compressed.to_string()),
         );
+
+        if let Some(epoch) = self.epoch {
+            map.insert(
+                CheetahString::from_static_str(RegisterBrokerRequestHeader::EPOCH),
+                CheetahString::from_string(epoch.to_string()),
+            );
+        }
+
+        if let Some(max_phy_offset) = self.max_phy_offset {
+            map.insert(
+                CheetahString::from_static_str(RegisterBrokerRequestHeader::MAX_PHY_OFFSET),
+                CheetahString::from_string(max_phy_offset.to_string()),
+            );
+        }
         map.insert(
             CheetahString::from_static_str(RegisterBrokerRequestHeader::BODY_CRC32),
             CheetahString::from_string(self.body_crc32.to_string()),
@@ -164,6 +164,42 @@ impl FromMap for GetTopicsByClusterRequestHeader {
     }
 }
 
+/// Asks for the topics of the name server, only those starting with `topic_prefix` if given.
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GetAllTopicListRequestHeader {
+    pub topic_prefix: Option<CheetahString>,
+}
+
+impl GetAllTopicListRequestHeader {
+    const TOPIC_PREFIX: &'static str = "topicPrefix";
+}
+
+impl CommandCustomHeader for GetAllTopicListRequestHeader {
+    fn to_map(&self) -> Option<HashMap<CheetahString, CheetahString>> {
+        let mut map = HashMap::new();
+        if let Some(ref topic_prefix) = self.topic_prefix {
+            map.insert(
+                CheetahString::from_static_str(Self::TOPIC_PREFIX),
+                topic_prefix.clone(),
+            );
+        }
+        Some(map)
+    }
+}
+
+impl FromMap for GetAllTopicListRequestHeader {
+    type Target = Self;
+
+    fn from(map: &HashMap<CheetahString, CheetahString>) -> Option<Self::Target> {
+        Some(GetAllTopicListRequestHeader {
+            topic_prefix: map
+                .get(&CheetahString::from_static_str(Self::TOPIC_PREFIX))
+                .cloned(),
+        })
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct TopicRequestHeader {
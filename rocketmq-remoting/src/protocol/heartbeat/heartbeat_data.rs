@@ -26,7 +26,7 @@ use crate::protocol::heartbeat::producer_data::ProducerData;
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct HeartbeatData {
-    #[serde(rename = "clientID")]
+    #[serde(rename = "clientID", default)]
     pub client_id: CheetahString,
     #[serde(default)]
     pub producer_data_set: HashSet<ProducerData>,
@@ -22,6 +22,10 @@ use serde::Serialize;
 #[serde(rename_all = "camelCase")]
 pub struct ProducerData {
     pub group_name: CheetahString,
+    /// Topics the producer published to, so the broker knows whom to tell when their route
+    /// changes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub topics: Vec<CheetahString>,
 }
 
 #[cfg(test)]
@@ -40,10 +44,12 @@ mod tests {
     fn producer_data_equality() {
         let producer_data1 = ProducerData {
             group_name: CheetahString::from("group1"),
+            ..Default::default()
         };
 
         let producer_data2 = ProducerData {
             group_name: CheetahString::from("group1"),
+            ..Default::default()
         };
 
         assert_eq!(producer_data1, producer_data2);
@@ -53,10 +59,12 @@ mod tests {
     fn producer_data_inequality() {
         let producer_data1 = ProducerData {
             group_name: CheetahString::from("group1"),
+            ..Default::default()
         };
 
         let producer_data2 = ProducerData {
             group_name: CheetahString::from("group2"),
+            ..Default::default()
         };
 
         assert_ne!(producer_data1, producer_data2);
@@ -66,6 +74,7 @@ mod tests {
     fn serialize_producer_data() {
         let producer_data = ProducerData {
             group_name: CheetahString::from("group1"),
+            ..Default::default()
         };
         let serialized = serde_json::to_string(&producer_data).unwrap();
         assert_eq!(serialized, r#"{"groupName":"group1"}"#);
@@ -76,6 +85,19 @@ mod tests {
         let json = r#"{"groupName":"group1"}"#;
         let deserialized: ProducerData = serde_json::from_str(json).unwrap();
         assert_eq!(deserialized.group_name, CheetahString::from("group1"));
+        assert!(deserialized.topics.is_empty());
+    }
+
+    #[test]
+    fn producer_data_topics_round_trip() {
+        let producer_data = ProducerData {
+            group_name: CheetahString::from("group1"),
+            topics: vec![CheetahString::from("TopicA")],
+        };
+        let serialized = serde_json::to_string(&producer_data).unwrap();
+        assert_eq!(serialized, r#"{"groupName":"group1","topics":["TopicA"]}"#);
+        let deserialized: ProducerData = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, producer_data);
     }
 
     #[test]
@@ -86,6 +108,7 @@ mod tests {
 
         let producer_data = ProducerData {
             group_name: CheetahString::from("group1"),
+            ..Default::default()
         };
 
         let mut hasher = DefaultHasher::new();
@@ -50,6 +50,20 @@ pub const SERIALIZE_TYPE_PROPERTY: &str = "rocketmq.serialize.type";
 pub const SERIALIZE_TYPE_ENV: &str = "ROCKETMQ_SERIALIZE_TYPE";
 pub const REMOTING_VERSION_KEY: &str = "rocketmq.remoting.version";
 
+/// `extFields` with this prefix are copied from a request to its response, e.g. a tenant or
+/// request id a gateway attaches.
+pub const PASSTHROUGH_EXT_FIELD_PREFIX: &str = "ext.";
+
+/// `key` with [`PASSTHROUGH_EXT_FIELD_PREFIX`] prepended, unless it already starts with it.
+pub fn passthrough_ext_field_key(key: impl Into<CheetahString>) -> CheetahString {
+    let key = key.into();
+    if key.starts_with(PASSTHROUGH_EXT_FIELD_PREFIX) {
+        key
+    } else {
+        CheetahString::from_string(format!("{}{}", PASSTHROUGH_EXT_FIELD_PREFIX, key))
+    }
+}
+
 lazy_static! {
     static ref requestId: Arc<AtomicI32> = Arc::new(AtomicI32::new(0));
     static ref CONFIG_VERSION: RwLock<i32> = RwLock::new(-1);
@@ -89,6 +103,27 @@ fn set_cmd_version(cmd: &mut RemotingCommand) {
     }
 }
 
+/// A body kept in several buffers, e.g. messages still living in the store's mapped files.
+///
+/// The parts are written to the wire one after another instead of being merged into a single
+/// buffer first.
+pub trait BodyParts: Send + Sync {
+    /// The buffers making up the body, in wire order.
+    fn parts(&self) -> Vec<&[u8]>;
+}
+
+/// A body produced chunk by chunk while it is written, for bodies too large to be built in a
+/// single buffer.
+///
+/// The frame header carries the body length, so it has to be known before the first chunk.
+pub trait BodyStream: Send + Sync {
+    /// Total length of the chunks.
+    fn length(&self) -> usize;
+
+    /// The chunks making up the body, in wire order. Each call starts over from the first one.
+    fn chunks(&self) -> Box<dyn Iterator<Item = Bytes> + Send + '_>;
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct RemotingCommand {
     code: i32,
@@ -109,8 +144,17 @@ pub struct RemotingCommand {
 
     #[serde(skip)]
     body: Option<Bytes>,
+    /// Written after `body`, see [`BodyParts`].
+    #[serde(skip)]
+    body_parts: Option<Arc<dyn BodyParts>>,
+    /// Written after `body_parts`, see [`BodyStream`].
+    #[serde(skip)]
+    body_stream: Option<Arc<dyn BodyStream>>,
     #[serde(skip)]
     suspended: bool,
+    /// Set at decode when the `extFields` exceeded the size cap and were dropped.
+    #[serde(skip)]
+    ext_fields_oversized: bool,
     #[serde(skip)]
     command_custom_header: Option<ArcMut<Box<dyn CommandCustomHeader + Send + Sync + 'static>>>,
     #[serde(rename = "serializeTypeCurrentRPC")]
@@ -128,7 +172,10 @@ impl Clone for RemotingCommand {
             remark: self.remark.clone(),
             ext_fields: self.ext_fields.clone(),
             body: self.body.clone(),
+            body_parts: self.body_parts.clone(),
+            body_stream: self.body_stream.clone(),
             suspended: self.suspended,
+            ext_fields_oversized: self.ext_fields_oversized,
             command_custom_header: self.command_custom_header.clone(),
             serialize_type: self.serialize_type,
         }
@@ -165,7 +212,10 @@ impl Default for RemotingCommand {
             remark: None,
             ext_fields: None,
             body: None,
+            body_parts: None,
+            body_stream: None,
             suspended: false,
+            ext_fields_oversized: false,
             command_custom_header: None,
             serialize_type: *SERIALIZE_TYPE_CONFIG_IN_THIS_SERVER,
         }
@@ -336,6 +386,20 @@ impl RemotingCommand {
         self.body = Some(body.into());
     }
 
+    pub fn set_body_parts(mut self, body_parts: impl BodyParts + 'static) -> Self {
+        self.body_parts = Some(Arc::new(body_parts));
+        self
+    }
+
+    pub fn set_body_parts_mut_ref(&mut self, body_parts: impl BodyParts + 'static) {
+        self.body_parts = Some(Arc::new(body_parts));
+    }
+
+    pub fn set_body_stream(mut self, body_stream: impl BodyStream + 'static) -> Self {
+        self.body_stream = Some(Arc::new(body_stream));
+        self
+    }
+
     pub fn set_suspended(mut self, suspended: bool) -> Self {
         self.suspended = suspended;
         self
@@ -415,7 +479,7 @@ impl RemotingCommand {
                     }
                 };
                 let header_length = header.as_ref().map_or(0, |h| h.len()) as i32;
-                let body_length = self.body.as_ref().map_or(0, |b| b.len()) as i32;
+                let body_length = self.body_length() as i32;
                 let total_length = 4 + header_length + body_length;
 
                 dst.reserve((total_length + 4) as usize);
@@ -437,7 +501,7 @@ impl RemotingCommand {
                     }
                 }
                 let header_size = RocketMQSerializable::rocketmq_protocol_encode(self, dst);
-                let body_length = self.body.as_ref().map_or(0, |b| b.len()) as i32;
+                let body_length = self.body_length() as i32;
                 let serialize_type = RemotingCommand::mark_serialize_type(
                     header_size as i32,
                     SerializeType::ROCKETMQ,
@@ -527,6 +591,27 @@ impl RemotingCommand {
         self.body.as_mut()
     }
 
+    pub fn body_parts(&self) -> Option<&Arc<dyn BodyParts>> {
+        self.body_parts.as_ref()
+    }
+
+    pub fn body_stream(&self) -> Option<&Arc<dyn BodyStream>> {
+        self.body_stream.as_ref()
+    }
+
+    /// Length of the body on the wire, `body` plus all [`BodyParts`] and the [`BodyStream`].
+    pub fn body_length(&self) -> usize {
+        let body_length = self.body.as_ref().map_or(0, |body| body.len());
+        let parts_length = self.body_parts.as_ref().map_or(0, |body_parts| {
+            body_parts.parts().iter().map(|part| part.len()).sum()
+        });
+        let stream_length = self
+            .body_stream
+            .as_ref()
+            .map_or(0, |body_stream| body_stream.length());
+        body_length + parts_length + stream_length
+    }
+
     pub fn mark_serialize_type(header_length: i32, protocol_type: SerializeType) -> i32 {
         (protocol_type.get_code() as i32) << 24 | (header_length & 0x00FFFFFF)
     }
@@ -638,9 +723,9 @@ impl RemotingCommand {
         key: impl Into<CheetahString>,
         value: impl Into<CheetahString>,
     ) -> &mut Self {
-        if let Some(ref mut ext) = self.ext_fields {
-            ext.insert(key.into(), value.into());
-        }
+        self.ext_fields
+            .get_or_insert_with(HashMap::new)
+            .insert(key.into(), value.into());
         self
     }
 
@@ -658,6 +743,49 @@ impl RemotingCommand {
         self.ext_fields.as_ref()
     }
 
+    /// The `extFields` starting with [`PASSTHROUGH_EXT_FIELD_PREFIX`], which the server echoes
+    /// back in the response.
+    pub fn passthrough_ext_fields(&self) -> HashMap<CheetahString, CheetahString> {
+        self.ext_fields
+            .iter()
+            .flatten()
+            .filter(|(key, _)| key.starts_with(PASSTHROUGH_EXT_FIELD_PREFIX))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    /// Adds the passthrough fields of a request, keeping the ones already set.
+    pub fn add_passthrough_ext_fields(&mut self, fields: &HashMap<CheetahString, CheetahString>) {
+        if fields.is_empty() {
+            return;
+        }
+        let ext_fields = self.ext_fields.get_or_insert_with(HashMap::new);
+        for (key, value) in fields {
+            ext_fields
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+
+    /// Total bytes of the `extFields` keys and values.
+    pub fn ext_fields_bytes(&self) -> usize {
+        self.ext_fields
+            .iter()
+            .flatten()
+            .map(|(key, value)| key.len() + value.len())
+            .sum()
+    }
+
+    /// Drops the `extFields` of a command exceeding the size cap, the server rejects it.
+    pub(crate) fn drop_oversized_ext_fields(&mut self) {
+        self.ext_fields = None;
+        self.ext_fields_oversized = true;
+    }
+
+    pub fn is_ext_fields_oversized(&self) -> bool {
+        self.ext_fields_oversized
+    }
+
     pub fn read_custom_header_ref<T>(&self) -> Option<&T>
     where
         T: CommandCustomHeader + Sync + Send + 'static,
@@ -785,4 +913,28 @@ mod tests {
         println!("i={}", RemotingCommand::default().opaque);
         println!("i={}", RemotingCommand::default().opaque);
     }
+
+    #[test]
+    fn passthrough_ext_fields_keep_the_fields_a_response_already_has() {
+        assert_eq!(passthrough_ext_field_key("tenant").as_str(), "ext.tenant");
+        assert_eq!(
+            passthrough_ext_field_key("ext.tenant").as_str(),
+            "ext.tenant"
+        );
+
+        let mut request = RemotingCommand::create_remoting_command(1);
+        request.add_ext_field("ext.tenant", "t1");
+        request.add_ext_field("ext.requestId", "r1");
+        request.add_ext_field("topic", "TopicA");
+        let passthrough = request.passthrough_ext_fields();
+        assert_eq!(passthrough.len(), 2);
+
+        let mut response = RemotingCommand::create_response_command();
+        response.add_ext_field("ext.requestId", "r2");
+        response.add_passthrough_ext_fields(&passthrough);
+        let ext_fields = response.get_ext_fields().unwrap();
+        assert_eq!(ext_fields.get("ext.tenant").unwrap().as_str(), "t1");
+        assert_eq!(ext_fields.get("ext.requestId").unwrap().as_str(), "r2");
+        assert!(!ext_fields.contains_key("topic"));
+    }
 }
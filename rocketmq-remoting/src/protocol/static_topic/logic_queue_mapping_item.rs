@@ -38,7 +38,7 @@ impl Default for LogicQueueMappingItem {
 
 impl LogicQueueMappingItem {
     pub fn compute_static_queue_offset_strictly(&self, physical_queue_offset: i64) -> i64 {
-        if physical_queue_offset > self.start_offset {
+        if physical_queue_offset < self.start_offset {
             return self.logic_offset;
         }
         self.logic_offset + (physical_queue_offset - self.start_offset)
@@ -66,6 +66,10 @@ impl LogicQueueMappingItem {
         self.logic_offset - self.start_offset
     }
 
+    pub fn check_if_logic_offset_decided(&self) -> bool {
+        self.logic_offset >= 0
+    }
+
     pub fn check_if_end_offset_decided(&self) -> bool {
         self.end_offset > self.start_offset
     }
@@ -16,7 +16,9 @@
  */
 use rocketmq_common::common::mix_all;
 
+use crate::error::Error;
 use crate::protocol::static_topic::logic_queue_mapping_item::LogicQueueMappingItem;
+use crate::Result;
 
 pub struct TopicQueueMappingUtils;
 
@@ -80,6 +82,43 @@ impl TopicQueueMappingUtils {
         None
     }
 
+    /// Checks that the items of a logic queue are ordered by gen and logic offset, the last item
+    /// being the leader.
+    pub fn check_logic_queue_mapping_item_offset(items: &[LogicQueueMappingItem]) -> Result<()> {
+        let mut last_gen = -1;
+        // logic offset of the next item, unknown while the leader is not yet decided
+        let mut last_offset = None;
+        for (i, item) in items.iter().enumerate().rev() {
+            if item.start_offset < 0 || item.gen < 0 || item.queue_id < 0 {
+                return Err(illegal("The field is illegal, should not be negative"));
+            }
+            if i + 1 < items.len() && item.logic_offset < 0 {
+                return Err(illegal("The non-latest item has negative logic offset"));
+            }
+            if last_gen != -1 && item.gen >= last_gen {
+                return Err(illegal("The gen does not increase monotonically"));
+            }
+            if item.end_offset != -1 && item.end_offset < item.start_offset {
+                return Err(illegal("The endOffset is smaller than the start offset"));
+            }
+            if let Some(last_offset) = last_offset.filter(|_| item.logic_offset >= 0) {
+                if item.logic_offset >= last_offset {
+                    return Err(illegal(
+                        "The base logic offset does not increase monotonically",
+                    ));
+                }
+                if item.compute_max_static_queue_offset() >= last_offset {
+                    return Err(illegal(
+                        "The max logic offset does not increase monotonically",
+                    ));
+                }
+            }
+            last_gen = item.gen;
+            last_offset = (item.logic_offset >= 0).then_some(item.logic_offset);
+        }
+        Ok(())
+    }
+
     pub fn get_mock_broker_name(scope: &str) -> String {
         assert!(!scope.is_empty(), "Scope cannot be null");
 
@@ -94,3 +133,55 @@ impl TopicQueueMappingUtils {
         }
     }
 }
+
+fn illegal(reason: &str) -> Error {
+    Error::RemotingCommandException(reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use cheetah_string::CheetahString;
+
+    use super::*;
+
+    fn item(gen: i32, bname: &str, logic_offset: i64, end_offset: i64) -> LogicQueueMappingItem {
+        LogicQueueMappingItem {
+            gen,
+            bname: Some(CheetahString::from_slice(bname)),
+            logic_offset,
+            end_offset,
+            ..LogicQueueMappingItem::default()
+        }
+    }
+
+    #[test]
+    fn check_accepts_items_ordered_by_gen_and_offset() {
+        let items = [item(0, "broker-a", 0, 100), item(1, "broker-b", 101, -1)];
+        assert!(TopicQueueMappingUtils::check_logic_queue_mapping_item_offset(&items).is_ok());
+        // the newly mapped leader may not have a logic offset yet
+        let items = [item(0, "broker-a", 0, 100), item(1, "broker-b", -1, -1)];
+        assert!(TopicQueueMappingUtils::check_logic_queue_mapping_item_offset(&items).is_ok());
+    }
+
+    #[test]
+    fn check_rejects_unordered_items() {
+        let out_of_gen = [item(1, "broker-a", 0, 100), item(1, "broker-b", 101, -1)];
+        let overlapping = [item(0, "broker-a", 0, 100), item(1, "broker-b", 100, -1)];
+        let negative = [item(0, "broker-a", -1, 100), item(1, "broker-b", 101, -1)];
+        for items in [out_of_gen, overlapping, negative] {
+            assert!(TopicQueueMappingUtils::check_logic_queue_mapping_item_offset(&items).is_err());
+        }
+    }
+
+    #[test]
+    fn static_and_physical_offsets_round_trip() {
+        let leader = LogicQueueMappingItem {
+            start_offset: 5,
+            ..item(1, "broker-b", 100, -1)
+        };
+        assert_eq!(leader.compute_physical_queue_offset(130), 35);
+        assert_eq!(leader.compute_static_queue_offset_strictly(35), 130);
+        assert_eq!(leader.compute_static_queue_offset_strictly(0), 100);
+        assert_eq!(leader.compute_static_queue_offset_loosely(35), 130);
+    }
+}
@@ -26,7 +26,7 @@ use crate::protocol::subscription::group_retry_policy::GroupRetryPolicy;
 use crate::protocol::subscription::simple_subscription_data::SimpleSubscriptionData;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct SubscriptionGroupConfig {
     group_name: CheetahString,
 
@@ -207,6 +207,7 @@ impl SubscriptionGroupConfig {
 #[cfg(test)]
 mod subscription_group_config_tests {
     use super::*;
+    use crate::protocol::subscription::group_retry_policy_type::GroupRetryPolicyType;
     //use crate::protocol::subscription::group_retry_policy::RetryPolicy;
 
     #[test]
@@ -270,4 +271,50 @@ mod subscription_group_config_tests {
             &HashMap::from([("key".into(), "value".into())])
         );
     }
+
+    #[test]
+    fn json_round_trip_keeps_exponential_retry_policy() {
+        let json = r#"{
+            "groupName": "test_group",
+            "consumeEnable": false,
+            "consumeBroadcastEnable": false,
+            "retryQueueNums": 2,
+            "retryMaxTimes": 5,
+            "brokerId": 0,
+            "whichBrokerWhenConsumeSlowly": 1,
+            "groupRetryPolicy": {
+                "type": "EXPONENTIAL",
+                "exponentialRetryPolicy": {"initial": 1000, "max": 60000, "multiplier": 3}
+            }
+        }"#;
+        let config: SubscriptionGroupConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.group_name(), "test_group");
+        assert!(!config.consume_enable());
+        assert!(!config.consume_broadcast_enable());
+        assert_eq!(config.retry_queue_nums(), 2);
+        assert_eq!(config.retry_max_times(), 5);
+        assert_eq!(config.consume_timeout_minute(), 15);
+        assert_eq!(
+            config.group_retry_policy().type_(),
+            GroupRetryPolicyType::Exponential
+        );
+
+        let decoded: SubscriptionGroupConfig =
+            serde_json::from_str(&serde_json::to_string(&config).unwrap()).unwrap();
+        let policy = decoded
+            .group_retry_policy()
+            .exponential_retry_policy()
+            .as_ref()
+            .unwrap();
+        assert_eq!(policy.initial(), 1000);
+        assert_eq!(policy.max(), 60000);
+        assert_eq!(policy.multiplier(), 3);
+        assert_eq!(
+            decoded
+                .group_retry_policy()
+                .get_retry_policy()
+                .next_delay_duration(1),
+            3000
+        );
+    }
 }
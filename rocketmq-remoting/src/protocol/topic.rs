@@ -19,6 +19,7 @@ use serde::Deserialize;
 use serde::Serialize;
 
 #[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct OffsetMovedEvent {
     pub consumer_group: String,
     pub message_queue: MessageQueue,
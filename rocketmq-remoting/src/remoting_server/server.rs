@@ -20,7 +20,7 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 
-use futures::SinkExt;
+use cheetah_string::CheetahString;
 use rocketmq_common::common::server::config::ServerConfig;
 use rocketmq_rust::ArcMut;
 use tokio::net::TcpListener;
@@ -33,6 +33,7 @@ use tokio_stream::StreamExt;
 use tracing::error;
 use tracing::info;
 use tracing::warn;
+use tracing::Instrument;
 
 use crate::base::response_future::ResponseFuture;
 use crate::code::response_code::ResponseCode;
@@ -41,6 +42,7 @@ use crate::error::Error;
 use crate::net::channel::Channel;
 use crate::protocol::remoting_command::RemotingCommand;
 use crate::protocol::RemotingCommandType;
+use crate::request_tracing;
 use crate::runtime::connection_handler_context::ConnectionHandlerContextWrapper;
 use crate::runtime::processor::RequestProcessor;
 use crate::runtime::RPCHook;
@@ -68,6 +70,8 @@ pub struct ConnectionHandler<RP> {
 
 impl<RP> Drop for ConnectionHandler<RP> {
     fn drop(&mut self) {
+        // nobody reads the connection anymore, calls waiting for the peer fail right away
+        self.channel.close();
         if let Some(ref sender) = self.conn_disconnect_notify {
             let socket_addr = self.channel.remote_address();
             warn!(
@@ -117,6 +121,10 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
                     //If a shutdown signal is received, return from `handle`.
                     return Ok(());
                 }
+                _ = self.channel.closed() => {
+                    //The writer gave up on a stalled or broken peer.
+                    return Ok(());
+                }
             };
 
             let mut cmd = match frame {
@@ -126,7 +134,7 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
                     return Ok(());
                 }
             };
-            //handle response
+            //handle response, requests pushed to the peer share this connection
             if cmd.get_type() == RemotingCommandType::RESPONSE {
                 let future_response = self.response_table.remove(&cmd.opaque());
                 if let Some(future_response) = future_response {
@@ -142,13 +150,32 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
             }
             let opaque = cmd.opaque();
             let oneway_rpc = cmd.is_oneway_rpc();
+            let span = request_tracing::server_span(&cmd, self.channel.remote_address());
+            if cmd.is_ext_fields_oversized() {
+                let exception = Error::AbortProcessException(
+                    ResponseCode::ExtFieldsTooLarge.into(),
+                    "extFields exceed the size limit".to_string(),
+                );
+                match self
+                    .handle_error(oneway_rpc, opaque, Some(exception), &HashMap::new())
+                    .await
+                {
+                    HandleErrorResult::ReturnMethod => return Ok(()),
+                    HandleErrorResult::Continue | HandleErrorResult::GoHead => continue,
+                }
+            }
+            //echoed in the response, whatever the processor or the hooks do to the request
+            let passthrough_ext_fields = cmd.passthrough_ext_fields();
             //before handle request hooks
             let exception = match self.do_before_rpc_hooks(&self.channel, Some(&mut cmd)) {
                 Ok(_) => None,
                 Err(error) => Some(error),
             };
             //handle error if return have
-            match self.handle_error(oneway_rpc, opaque, exception).await {
+            match self
+                .handle_error(oneway_rpc, opaque, exception, &passthrough_ext_fields)
+                .await
+            {
                 HandleErrorResult::Continue => continue,
                 HandleErrorResult::ReturnMethod => return Ok(()),
                 HandleErrorResult::GoHead => {}
@@ -158,7 +185,7 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
                 let channel = self.channel.clone();
                 let ctx = ArcMut::downgrade(&self.connection_handler_context);
                 tokio::select! {
-                    result = self.request_processor.process_request(channel,ctx,cmd) =>  match result{
+                    result = self.request_processor.process_request(channel,ctx,cmd).instrument(span.clone()) =>  match result{
                         Ok(value) => value,
                         Err(_err) => Some(RemotingCommand::create_response_command_with_code(
                                         ResponseCode::SystemError,
@@ -166,13 +193,18 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
                     },
                 }
             };
+            if let Some(response) = response.as_mut() {
+                response.add_passthrough_ext_fields(&passthrough_ext_fields);
+            }
 
-            let exception = match self.do_before_rpc_hooks(&self.channel, response.as_mut()) {
-                Ok(_) => None,
-                Err(error) => Some(error),
-            };
+            let exception = self
+                .do_after_rpc_hooks(&self.channel, response.as_mut())
+                .err();
 
-            match self.handle_error(oneway_rpc, opaque, exception).await {
+            match self
+                .handle_error(oneway_rpc, opaque, exception, &passthrough_ext_fields)
+                .await
+            {
                 HandleErrorResult::Continue => continue,
                 HandleErrorResult::ReturnMethod => return Ok(()),
                 HandleErrorResult::GoHead => {}
@@ -180,20 +212,11 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
             if response.is_none() || oneway_rpc {
                 continue;
             }
-            let response = response.unwrap();
-            tokio::select! {
-                result =self.connection_handler_context.channel.connection.writer.send(response.set_opaque(opaque)) => match result{
-                    Ok(_) =>{},
-                    Err(err) => {
-                        match err {
-                            Error::Io(io_error) => {
-                                error!("connection disconnect: {}", io_error);
-                                return Ok(())
-                            }
-                            _ => { error!("send response failed: {}", err);}
-                        }
-                    },
-                },
+            let response = response.unwrap().set_opaque(opaque);
+            request_tracing::record_response(&span, &response);
+            if let Err(err) = self.channel.send_response(response).instrument(span).await {
+                error!("send response failed: {}", err);
+                return Ok(());
             }
         }
         Ok(())
@@ -204,52 +227,30 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
         oneway_rpc: bool,
         opaque: i32,
         exception: Option<Error>,
+        passthrough_ext_fields: &HashMap<CheetahString, CheetahString>,
     ) -> HandleErrorResult {
         if let Some(exception_inner) = exception {
-            match exception_inner {
+            // a oneway request never gets an answer, not even an error
+            if oneway_rpc {
+                return HandleErrorResult::Continue;
+            }
+            let mut response = match exception_inner {
                 Error::AbortProcessException(code, message) => {
-                    if oneway_rpc {
-                        return HandleErrorResult::Continue;
-                    }
-                    let response =
-                        RemotingCommand::create_response_command_with_code_remark(code, message);
-                    tokio::select! {
-                        result =self.connection_handler_context.channel.connection.writer.send(response.set_opaque(opaque)) => match result{
-                            Ok(_) =>{},
-                            Err(err) => {
-                                match err {
-                                    Error::Io(io_error) => {
-                                        error!("send response failed: {}", io_error);
-                                        return HandleErrorResult::ReturnMethod;
-                                    }
-                                    _ => { error!("send response failed: {}", err);}
-                                }
-                            },
-                        },
-                    }
-                }
-                _ => {
-                    if !oneway_rpc {
-                        let response = RemotingCommand::create_response_command_with_code_remark(
-                            ResponseCode::SystemError,
-                            exception_inner.to_string(),
-                        );
-                        tokio::select! {
-                            result =self.connection_handler_context.channel.connection.writer.send(response.set_opaque(opaque)) => match result{
-                                Ok(_) =>{},
-                                Err(err) => {
-                                    match err {
-                                        Error::Io(io_error) => {
-                                            error!("send response failed: {}", io_error);
-                                            return HandleErrorResult::ReturnMethod;
-                                        }
-                                        _ => { error!("send response failed: {}", err);}
-                                    }
-                                },
-                            },
-                        }
-                    }
+                    RemotingCommand::create_response_command_with_code_remark(code, message)
                 }
+                _ => RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::SystemError,
+                    exception_inner.to_string(),
+                ),
+            };
+            response.add_passthrough_ext_fields(passthrough_ext_fields);
+            if let Err(err) = self
+                .channel
+                .send_response(response.set_opaque(opaque))
+                .await
+            {
+                error!("send response failed: {}", err);
+                return HandleErrorResult::ReturnMethod;
             }
             HandleErrorResult::Continue
         } else {
@@ -377,6 +378,7 @@ impl<RP: RequestProcessor + Sync + 'static + Clone> ConnectionListener<RP> {
 
 pub struct RocketMQServer<RP> {
     config: Arc<ServerConfig>,
+    conn_disconnect_notify: Option<broadcast::Sender<SocketAddr>>,
     _phantom_data: std::marker::PhantomData<RP>,
 }
 
@@ -384,30 +386,56 @@ impl<RP> RocketMQServer<RP> {
     pub fn new(config: Arc<ServerConfig>) -> Self {
         Self {
             config,
+            conn_disconnect_notify: None,
             _phantom_data: std::marker::PhantomData,
         }
     }
+
+    /// Sends the remote address of every connection that closes to `conn_disconnect_notify`.
+    pub fn with_conn_disconnect_notify(
+        mut self,
+        conn_disconnect_notify: broadcast::Sender<SocketAddr>,
+    ) -> Self {
+        self.conn_disconnect_notify = Some(conn_disconnect_notify);
+        self
+    }
 }
 
 impl<RP: RequestProcessor + Sync + 'static + Clone> RocketMQServer<RP> {
     pub async fn run(&self, request_processor: RP) {
-        let listener = TcpListener::bind(&format!(
-            "{}:{}",
-            self.config.bind_address, self.config.listen_port
+        self.run_with_rpc_hooks(request_processor, vec![]).await;
+    }
+
+    /// Like [`run`](Self::run), `rpc_hooks` see every request before it reaches
+    /// `request_processor` and every response before it is written.
+    pub async fn run_with_rpc_hooks(
+        &self,
+        request_processor: RP,
+        rpc_hooks: Vec<Box<dyn RPCHook>>,
+    ) {
+        let listener = TcpListener::bind((
+            self.config.bind_address.as_str(),
+            self.config.listen_port as u16,
         ))
         .await
         .unwrap();
         info!(
             "Bind local address: {}",
-            format!("{}:{}", self.config.bind_address, self.config.listen_port)
+            listener
+                .local_addr()
+                .map(|addr| addr.to_string())
+                .unwrap_or_default()
         );
-        let (notify_conn_disconnect, _) = broadcast::channel::<SocketAddr>(100);
+        let notify_conn_disconnect = self
+            .conn_disconnect_notify
+            .clone()
+            .unwrap_or_else(|| broadcast::channel::<SocketAddr>(100).0);
         run(
             listener,
             tokio::signal::ctrl_c(),
             request_processor,
             Some(notify_conn_disconnect),
-            vec![],
+            rpc_hooks,
         )
         .await;
     }
@@ -499,3 +527,290 @@ impl Shutdown {
         self.is_shutdown = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::SinkExt;
+    use tokio_util::codec::Framed;
+
+    use super::*;
+    use crate::codec::remoting_command_codec::RemotingCommandCodec;
+    use crate::runtime::connection_handler_context::ConnectionHandlerContext;
+
+    #[derive(Clone)]
+    struct FailingProcessor;
+
+    impl RequestProcessor for FailingProcessor {
+        async fn process_request(
+            &mut self,
+            _channel: Channel,
+            _ctx: ConnectionHandlerContext,
+            request: RemotingCommand,
+        ) -> Result<Option<RemotingCommand>> {
+            if request.code() == 1 {
+                return Err(Error::RemoteException("processor failed".to_string()));
+            }
+            Ok(Some(RemotingCommand::create_response_command()))
+        }
+    }
+
+    /// Hands the server side of every connection to the test.
+    #[derive(Clone)]
+    struct ChannelCapturingProcessor(mpsc::UnboundedSender<Channel>);
+
+    impl RequestProcessor for ChannelCapturingProcessor {
+        async fn process_request(
+            &mut self,
+            channel: Channel,
+            _ctx: ConnectionHandlerContext,
+            _request: RemotingCommand,
+        ) -> Result<Option<RemotingCommand>> {
+            let _ = self.0.send(channel);
+            Ok(Some(RemotingCommand::create_response_command()))
+        }
+    }
+
+    async fn connect_captured() -> (Framed<TcpStream, RemotingCommandCodec>, Channel) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        tokio::spawn(run(
+            listener,
+            std::future::pending::<()>(),
+            ChannelCapturingProcessor(tx),
+            None,
+            vec![],
+        ));
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut client = Framed::new(stream, RemotingCommandCodec::new());
+        client
+            .send(RemotingCommand::create_remoting_command(1).set_opaque(1))
+            .await
+            .unwrap();
+        client.next().await.unwrap().unwrap();
+        (client, rx.recv().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn server_calls_the_client_over_its_connection() {
+        let (mut client, mut channel) = connect_captured().await;
+        tokio::spawn(async move {
+            let request = client.next().await.unwrap().unwrap();
+            assert_eq!(request.code(), 2);
+            client
+                .send(
+                    RemotingCommand::create_response_command()
+                        .set_opaque(request.opaque())
+                        .set_remark("canned"),
+                )
+                .await
+                .unwrap();
+            // keep the connection open until the server is done
+            client.next().await;
+        });
+
+        let response = channel
+            .send_wait_response(RemotingCommand::create_remoting_command(2), 5_000)
+            .await
+            .unwrap();
+        assert_eq!(
+            response.remark().map(|remark| remark.as_str()),
+            Some("canned")
+        );
+    }
+
+    #[tokio::test]
+    async fn call_fails_when_the_client_disconnects() {
+        let (mut client, mut channel) = connect_captured().await;
+        tokio::spawn(async move {
+            client.next().await.unwrap().unwrap();
+            drop(client);
+        });
+
+        let result = time::timeout(
+            Duration::from_secs(5),
+            channel.send_wait_response(RemotingCommand::create_remoting_command(2), 60_000),
+        )
+        .await
+        .expect("call outlived the connection");
+        assert!(matches!(result, Err(Error::ChannelClosed(_))));
+    }
+
+    #[tokio::test]
+    async fn oneway_request_never_gets_a_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(run(
+            listener,
+            std::future::pending::<()>(),
+            FailingProcessor,
+            None,
+            vec![],
+        ));
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut framed = Framed::new(stream, RemotingCommandCodec::new());
+
+        framed
+            .send(
+                RemotingCommand::create_remoting_command(1)
+                    .set_opaque(1)
+                    .mark_oneway_rpc(),
+            )
+            .await
+            .unwrap();
+        framed
+            .send(
+                RemotingCommand::create_remoting_command(2)
+                    .set_opaque(2)
+                    .mark_oneway_rpc(),
+            )
+            .await
+            .unwrap();
+        framed
+            .send(RemotingCommand::create_remoting_command(1).set_opaque(3))
+            .await
+            .unwrap();
+
+        let response = time::timeout(Duration::from_secs(5), framed.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.opaque(), 3);
+        assert_eq!(response.code(), ResponseCode::SystemError as i32);
+    }
+
+    /// Records which hook saw the code of which command.
+    struct RecordingHook(Arc<std::sync::Mutex<Vec<(&'static str, i32)>>>);
+
+    impl RPCHook for RecordingHook {
+        fn do_before_request(
+            &self,
+            _remote_addr: SocketAddr,
+            request: &mut RemotingCommand,
+        ) -> Result<()> {
+            self.0.lock().unwrap().push(("request", request.code()));
+            Ok(())
+        }
+
+        fn do_after_response(
+            &self,
+            _remote_addr: SocketAddr,
+            response: &mut RemotingCommand,
+        ) -> Result<()> {
+            self.0.lock().unwrap().push(("response", response.code()));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn responses_only_reach_the_after_response_hooks() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        tokio::spawn(run(
+            listener,
+            std::future::pending::<()>(),
+            FailingProcessor,
+            None,
+            vec![Box::new(RecordingHook(calls.clone()))],
+        ));
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut framed = Framed::new(stream, RemotingCommandCodec::new());
+
+        framed
+            .send(RemotingCommand::create_remoting_command(2).set_opaque(1))
+            .await
+            .unwrap();
+        let response = time::timeout(Duration::from_secs(5), framed.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.opaque(), 1);
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![("request", 2), ("response", ResponseCode::Success as i32)]
+        );
+    }
+
+    /// Records the tenant the response of every request carries.
+    struct TenantRecordingHook(Arc<std::sync::Mutex<Vec<String>>>);
+
+    impl RPCHook for TenantRecordingHook {
+        fn do_before_request(
+            &self,
+            _remote_addr: SocketAddr,
+            _request: &mut RemotingCommand,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        fn do_after_response(
+            &self,
+            _remote_addr: SocketAddr,
+            response: &mut RemotingCommand,
+        ) -> Result<()> {
+            let tenant = response
+                .get_ext_fields()
+                .and_then(|fields| fields.get("ext.tenant"))
+                .map(|tenant| tenant.to_string())
+                .unwrap_or_default();
+            self.0.lock().unwrap().push(tenant);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn passthrough_ext_fields_are_echoed_and_oversized_ones_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let tenants = Arc::new(std::sync::Mutex::new(Vec::new()));
+        tokio::spawn(run(
+            listener,
+            std::future::pending::<()>(),
+            FailingProcessor,
+            None,
+            vec![Box::new(TenantRecordingHook(tenants.clone()))],
+        ));
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut framed = Framed::new(stream, RemotingCommandCodec::new());
+
+        let mut request = RemotingCommand::create_remoting_command(2).set_opaque(1);
+        request.add_ext_field("ext.tenant", "t1");
+        request.add_ext_field("topic", "TopicA");
+        framed.send(request).await.unwrap();
+        let response = framed.next().await.unwrap().unwrap();
+        let ext_fields = response.get_ext_fields().unwrap();
+        assert_eq!(ext_fields.get("ext.tenant").unwrap().as_str(), "t1");
+        assert!(!ext_fields.contains_key("topic"));
+        assert_eq!(*tenants.lock().unwrap(), vec!["t1".to_string()]);
+
+        // a failing processor still answers with the fields
+        let mut request = RemotingCommand::create_remoting_command(1).set_opaque(2);
+        request.add_ext_field("ext.tenant", "t2");
+        framed.send(request).await.unwrap();
+        let response = framed.next().await.unwrap().unwrap();
+        assert_eq!(response.code(), ResponseCode::SystemError as i32);
+        assert_eq!(
+            response
+                .get_ext_fields()
+                .unwrap()
+                .get("ext.tenant")
+                .unwrap()
+                .as_str(),
+            "t2"
+        );
+
+        let mut request = RemotingCommand::create_remoting_command(2).set_opaque(3);
+        request.add_ext_field("ext.tenant", "x".repeat(256 * 1024));
+        framed.send(request).await.unwrap();
+        let response = framed.next().await.unwrap().unwrap();
+        assert_eq!(response.opaque(), 3);
+        assert_eq!(response.code(), ResponseCode::ExtFieldsTooLarge as i32);
+        assert!(response.get_ext_fields().is_none());
+        assert!(crate::codec::remoting_command_codec::oversized_ext_fields_rejected() >= 1);
+        // the rejected request never reached the processor or the hooks
+        assert_eq!(tenants.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,204 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Spans following a request from the client invoking it to the server handling it.
+//!
+//! The spans are created at `TRACE` level so that they cost next to nothing unless a layer
+//! exporting them, see [`otlp_layer`], is installed. The client side passes its span context on
+//! in the W3C `traceparent` ext field, which the server picks up as the parent of its span.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use bytes::BytesMut;
+use cheetah_string::CheetahString;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::propagation::Injector;
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::trace::TraceError;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::Tracer;
+use opentelemetry_sdk::Resource;
+use tracing::field::Empty;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::protocol::remoting_command::RemotingCommand;
+
+/// Ext field carrying the span context of the invoking client.
+pub const TRACE_PARENT: &str = "traceparent";
+
+/// Opens the span covering the handling of `request` received from `peer`, up to the write of
+/// its response. Processors add the `topic`, `group` and `queue_id` they work on, see
+/// [`record_request_fields`].
+pub fn server_span(request: &RemotingCommand, peer: SocketAddr) -> Span {
+    let span = tracing::trace_span!(
+        "remoting.request",
+        otel.kind = "server",
+        request_code = request.code(),
+        opaque = request.opaque(),
+        peer = %peer,
+        topic = Empty,
+        group = Empty,
+        queue_id = Empty,
+        response_code = Empty,
+        response_size = Empty,
+    );
+    if span.is_disabled() {
+        return span;
+    }
+    if let Some(ext_fields) = request
+        .ext_fields()
+        .filter(|ext_fields| ext_fields.contains_key(TRACE_PARENT))
+    {
+        span.set_parent(TraceContextPropagator::new().extract(&ExtFields(ext_fields)));
+    }
+    span
+}
+
+/// Opens the span covering an invocation of `request` on the server at `addr`.
+pub fn client_span(request: &RemotingCommand, addr: &str) -> Span {
+    tracing::trace_span!(
+        "remoting.invoke",
+        otel.kind = "client",
+        request_code = request.code(),
+        peer = addr,
+        response_code = Empty,
+    )
+}
+
+/// Passes the context of `span` on to the server in the ext fields of `request`.
+pub fn inject_context(span: &Span, request: &mut RemotingCommand) {
+    if span.is_disabled() {
+        return;
+    }
+    let context = span.context();
+    if !context.span().span_context().is_valid() {
+        return;
+    }
+    TraceContextPropagator::new().inject_context(&context, &mut RequestInjector(request));
+}
+
+/// Records what the request handled by the current span works on.
+pub fn record_request_fields(topic: Option<&str>, group: Option<&str>, queue_id: Option<i32>) {
+    let span = Span::current();
+    if span.is_disabled() {
+        return;
+    }
+    if let Some(topic) = topic {
+        span.record("topic", topic);
+    }
+    if let Some(group) = group {
+        span.record("group", group);
+    }
+    if let Some(queue_id) = queue_id {
+        span.record("queue_id", queue_id);
+    }
+}
+
+/// Records the response closing `span`, with its serialized size on a server span.
+pub fn record_response(span: &Span, response: &RemotingCommand) {
+    if span.is_disabled() {
+        return;
+    }
+    span.record("response_code", response.code());
+    if span.has_field("response_size") {
+        let mut response = response.clone();
+        let mut header = BytesMut::new();
+        response.fast_header_encode(&mut header);
+        span.record(
+            "response_size",
+            (header.len() + response.body_length()) as i64,
+        );
+    }
+}
+
+/// Builds a layer exporting the spans over OTLP/gRPC to `endpoint`, as `service_name`.
+pub fn otlp_layer<S>(
+    service_name: &str,
+    endpoint: &str,
+    timeout: Duration,
+) -> Result<OpenTelemetryLayer<S, Tracer>, TraceError>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint)
+                .with_timeout(timeout),
+        )
+        .with_trace_config(
+            opentelemetry_sdk::trace::config().with_resource(Resource::new([KeyValue::new(
+                "service.name",
+                service_name.to_string(),
+            )])),
+        )
+        .install_batch(opentelemetry_sdk::runtime::Tokio)?;
+    Ok(tracing_opentelemetry::layer().with_tracer(tracer))
+}
+
+struct ExtFields<'a>(&'a HashMap<CheetahString, CheetahString>);
+
+impl Extractor for ExtFields<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(CheetahString::as_str)
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(CheetahString::as_str).collect()
+    }
+}
+
+struct RequestInjector<'a>(&'a mut RemotingCommand);
+
+impl Injector for RequestInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        self.0.add_ext_field(
+            CheetahString::from_slice(key),
+            CheetahString::from_string(value),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nothing_is_traced_without_a_subscriber() {
+        let mut request = RemotingCommand::create_remoting_command(10);
+        let span = client_span(&request, "127.0.0.1:10911");
+        assert!(span.is_disabled());
+        inject_context(&span, &mut request);
+        assert!(request.ext_fields().is_none());
+
+        request.add_ext_field(
+            TRACE_PARENT,
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+        );
+        assert!(server_span(&request, "127.0.0.1:50000".parse().unwrap()).is_disabled());
+    }
+}
@@ -16,5 +16,5 @@
  */
 
 pub mod client_config;
-mod net_system_config;
+pub(crate) mod net_system_config;
 mod server_config;
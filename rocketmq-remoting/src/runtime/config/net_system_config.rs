@@ -36,6 +36,7 @@ pub const WRITE_BUFFER_HIGH_WATER_MARK_VALUE: &str =
     "com.rocketmq.rocketmq-remoting.write.buffer.high.water.mark";
 pub const WRITE_BUFFER_LOW_WATER_MARK: &str =
     "com.rocketmq.rocketmq-remoting.write.buffer.low.water.mark";
+pub const EXT_FIELDS_MAX_BYTES: &str = "com.rocketmq.rocketmq-remoting.extFields.maxBytes";
 
 pub(crate) struct NetSystemConfig {
     pub(crate) pooled_byte_buf_allocator_enable: bool,
@@ -50,6 +51,7 @@ pub(crate) struct NetSystemConfig {
     pub(crate) client_close_socket_if_timeout: bool,
     pub(crate) write_buffer_high_water_mark_value: i32,
     pub(crate) write_buffer_low_water_mark: i32,
+    pub(crate) ext_fields_max_bytes: usize,
 }
 
 impl NetSystemConfig {
@@ -103,6 +105,10 @@ impl NetSystemConfig {
                 .unwrap_or_else(|_| "0".to_string())
                 .parse()
                 .unwrap_or(0),
+            ext_fields_max_bytes: env::var(EXT_FIELDS_MAX_BYTES)
+                .unwrap_or_else(|_| "131072".to_string())
+                .parse()
+                .unwrap_or(131072),
         }
     }
 }
@@ -14,7 +14,6 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-use futures_util::SinkExt;
 use rocketmq_rust::WeakArcMut;
 use tracing::error;
 
@@ -43,12 +42,11 @@ impl ConnectionHandlerContextWrapper {
         self.channel.connection_ref()
     }
 
+    /// Queues `cmd` on the channel, so it is written by the same task as every other frame of
+    /// the connection.
     pub async fn write(&mut self, cmd: RemotingCommand) {
-        match self.channel.connection_mut().writer.send(cmd).await {
-            Ok(_) => {}
-            Err(error) => {
-                error!("send response failed: {}", error);
-            }
+        if let Err(error) = self.channel.send_response(cmd).await {
+            error!("send response failed: {}", error);
         }
     }
 
@@ -0,0 +1,169 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! A fake broker shared by the tests of this crate and, through the `test-util` feature, by
+//! the crates talking to brokers.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::constant::PermName;
+use rocketmq_common::common::mix_all;
+use tokio::net::TcpListener;
+
+use crate::code::request_code::RequestCode;
+use crate::net::channel::Channel;
+use crate::protocol::remoting_command::RemotingCommand;
+use crate::protocol::route::route_data_view::BrokerData;
+use crate::protocol::route::route_data_view::QueueData;
+use crate::protocol::route::topic_route_data::TopicRouteData;
+use crate::protocol::RemotingSerializable;
+use crate::remoting_server::server;
+use crate::runtime::connection_handler_context::ConnectionHandlerContext;
+use crate::runtime::processor::RequestProcessor;
+
+/// The name of the only broker of the route served by a [`FakeBroker`].
+pub const FAKE_BROKER_NAME: &str = "broker-a";
+
+type Handler = Arc<
+    dyn Fn(Channel, RemotingCommand) -> Pin<Box<dyn Future<Output = RemotingCommand> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// Stands in for both the name server and the master broker [`FAKE_BROKER_NAME`] on a
+/// loopback port. Every topic is routed to that broker with `queue_nums` queues, request codes
+/// given a handler are answered by it and any other request succeeds with an empty response.
+pub struct FakeBroker {
+    listener: TcpListener,
+    addr: CheetahString,
+    queue_nums: u32,
+    handlers: HashMap<i32, Handler>,
+}
+
+impl FakeBroker {
+    /// Binds the port of the broker, which serves nothing until [`spawn`](Self::spawn)ed.
+    pub async fn bind() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = CheetahString::from_string(listener.local_addr().unwrap().to_string());
+        FakeBroker {
+            listener,
+            addr,
+            queue_nums: 1,
+            handlers: HashMap::new(),
+        }
+    }
+
+    /// Address of the broker, which is also the name server address of its clients.
+    pub fn addr(&self) -> CheetahString {
+        self.addr.clone()
+    }
+
+    /// Routes every topic to `queue_nums` read and write queues instead of one.
+    pub fn queue_nums(mut self, queue_nums: u32) -> Self {
+        self.queue_nums = queue_nums;
+        self
+    }
+
+    /// Answers the requests of `code` with `handler`.
+    pub fn on<F>(self, code: RequestCode, handler: F) -> Self
+    where
+        F: Fn(Channel, &RemotingCommand) -> RemotingCommand + Send + Sync + 'static,
+    {
+        self.on_async(code, move |channel, request| {
+            std::future::ready(handler(channel, &request))
+        })
+    }
+
+    /// Answers the requests of `code` with the response `handler` resolves to.
+    pub fn on_async<F, Fut>(mut self, code: RequestCode, handler: F) -> Self
+    where
+        F: Fn(Channel, RemotingCommand) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = RemotingCommand> + Send + 'static,
+    {
+        self.handlers.insert(
+            code.into(),
+            Arc::new(move |channel, request| Box::pin(handler(channel, request))),
+        );
+        self
+    }
+
+    /// Serves requests in the background until the runtime shuts down, returning the address.
+    pub fn spawn(self) -> CheetahString {
+        let processor = FakeBrokerProcessor {
+            addr: self.addr.clone(),
+            queue_nums: self.queue_nums,
+            handlers: Arc::new(self.handlers),
+        };
+        tokio::spawn(server::run(
+            self.listener,
+            std::future::pending::<()>(),
+            processor,
+            None,
+            vec![],
+        ));
+        self.addr
+    }
+}
+
+/// A route of [`FAKE_BROKER_NAME`] at `addr` with `queue_nums` read and write queues.
+pub fn topic_route(addr: &CheetahString, queue_nums: u32) -> RemotingCommand {
+    let topic_route_data = TopicRouteData {
+        queue_datas: vec![QueueData::new(
+            FAKE_BROKER_NAME.into(),
+            queue_nums,
+            queue_nums,
+            PermName::PERM_READ | PermName::PERM_WRITE,
+            0,
+        )],
+        broker_datas: vec![BrokerData::new(
+            "DefaultCluster".into(),
+            FAKE_BROKER_NAME.into(),
+            HashMap::from([(mix_all::MASTER_ID, addr.clone())]),
+            None,
+        )],
+        ..Default::default()
+    };
+    RemotingCommand::create_response_command().set_body(topic_route_data.encode())
+}
+
+#[derive(Clone)]
+struct FakeBrokerProcessor {
+    addr: CheetahString,
+    queue_nums: u32,
+    handlers: Arc<HashMap<i32, Handler>>,
+}
+
+impl RequestProcessor for FakeBrokerProcessor {
+    async fn process_request(
+        &mut self,
+        channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        request: RemotingCommand,
+    ) -> crate::Result<Option<RemotingCommand>> {
+        let response = match self.handlers.get(&request.code()) {
+            Some(handler) => handler(channel, request).await,
+            None if request.code() == i32::from(RequestCode::GetRouteinfoByTopic) => {
+                topic_route(&self.addr, self.queue_nums)
+            }
+            None => RemotingCommand::create_response_command(),
+        };
+        Ok(Some(response))
+    }
+}
@@ -0,0 +1,103 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Peak heap use of answering GET_ALL_TOPIC_LIST for a large cluster, with the topic list
+//! encoded into one body and streamed.
+
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::cell::Cell;
+
+use cheetah_string::CheetahString;
+use rocketmq_remoting::protocol::body::topic::topic_list::TopicList;
+use rocketmq_remoting::protocol::body::topic::topic_list::TopicListStream;
+use rocketmq_remoting::protocol::remoting_command::BodyStream;
+use rocketmq_remoting::protocol::RemotingSerializable;
+
+/// Keeps count of the bytes allocated by each thread so tests running in parallel do not
+/// disturb each other's figures.
+struct ThreadCountingAllocator;
+
+thread_local! {
+    static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+    static PEAK: Cell<usize> = const { Cell::new(0) };
+}
+
+fn record(grow: usize, shrink: usize) {
+    let _ = ALLOCATED.try_with(|allocated| {
+        let now = (allocated.get() + grow).saturating_sub(shrink);
+        allocated.set(now);
+        let _ = PEAK.try_with(|peak| peak.set(peak.get().max(now)));
+    });
+}
+
+unsafe impl GlobalAlloc for ThreadCountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record(layout.size(), 0);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        record(0, layout.size());
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record(new_size, layout.size());
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: ThreadCountingAllocator = ThreadCountingAllocator;
+
+/// How far above its starting point the heap of this thread grew while running `f`.
+fn peak_growth<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let start = ALLOCATED.with(Cell::get);
+    PEAK.with(|peak| peak.set(start));
+    let result = f();
+    (result, PEAK.with(Cell::get) - start)
+}
+
+#[test]
+fn streaming_half_a_million_topics_keeps_the_peak_flat() {
+    let topics: Vec<CheetahString> = (0..500_000)
+        .map(|i| CheetahString::from_string(format!("benchmark-topic-{i:08}")))
+        .collect();
+
+    // both bodies are built from the same snapshot of the names
+    let topic_list = TopicList {
+        topic_list: topics.clone(),
+        broker_addr: None,
+    };
+    let (encoded_len, encoded_peak) = peak_growth(|| topic_list.encode().len());
+    let stream = TopicListStream::new(topics);
+    let ((streamed_len, largest_chunk), streamed_peak) = peak_growth(|| {
+        stream.chunks().fold((0, 0), |(total, largest), chunk| {
+            (total + chunk.len(), usize::max(largest, chunk.len()))
+        })
+    });
+
+    assert_eq!(streamed_len, encoded_len);
+    assert_eq!(stream.length(), encoded_len);
+    assert!(encoded_peak > encoded_len, "{encoded_peak} {encoded_len}");
+    assert!(
+        streamed_peak < 4 * largest_chunk && streamed_peak * 100 < encoded_peak,
+        "streamed peak {streamed_peak} bytes, encoded peak {encoded_peak} bytes"
+    );
+}
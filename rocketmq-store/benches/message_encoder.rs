@@ -0,0 +1,49 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::Arc;
+
+use bytes::Bytes;
+use cheetah_string::CheetahString;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::Criterion;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
+use rocketmq_store::message_encoder::message_ext_encoder::MessageExtEncoder;
+
+fn message(body_size: usize) -> MessageExtBrokerInner {
+    let mut msg = MessageExtBrokerInner::default();
+    msg.message_ext_inner.message.topic = CheetahString::from_static_str("TopicTest");
+    msg.message_ext_inner.message.body = Some(Bytes::from(vec![7; body_size]));
+    msg
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let mut encoder = MessageExtEncoder::new(Arc::new(MessageStoreConfig::default()));
+    for body_size in [128, 4 * 1024] {
+        let msg = message(body_size);
+        c.bench_function(&format!("encode_{}", body_size), |b| {
+            b.iter(|| {
+                encoder.encode(&msg);
+                encoder.byte_buf()
+            })
+        });
+    }
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
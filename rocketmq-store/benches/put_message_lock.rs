@@ -0,0 +1,92 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::cell::UnsafeCell;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::Criterion;
+use rocketmq_store::base::put_message_lock::PutMessageLock;
+
+const PUTTERS: usize = 16;
+
+/// Stands in for the mapped file puts append to while holding the lock.
+struct CommitLogBuffer(UnsafeCell<Vec<u8>>);
+
+// SAFETY: only written by the holder of the put message lock
+unsafe impl Sync for CommitLogBuffer {}
+
+/// Runs `iters` rounds of `PUTTERS` concurrent puts of `body`, each copying the body under
+/// `lock`.
+fn put_concurrently(
+    runtime: &tokio::runtime::Runtime,
+    lock: &Arc<PutMessageLock>,
+    buffer: &Arc<CommitLogBuffer>,
+    body: &Arc<Vec<u8>>,
+    iters: u64,
+) -> Duration {
+    runtime.block_on(async {
+        let start = Instant::now();
+        let putters = (0..PUTTERS)
+            .map(|_| {
+                let lock = lock.clone();
+                let buffer = buffer.clone();
+                let body = body.clone();
+                tokio::spawn(async move {
+                    for _ in 0..iters {
+                        let _guard = lock.lock().await;
+                        // SAFETY: the guard makes this the only writer
+                        let buffer = unsafe { &mut *buffer.0.get() };
+                        buffer[..body.len()].copy_from_slice(&body);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for putter in putters {
+            putter.await.unwrap();
+        }
+        start.elapsed()
+    })
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(8)
+        .build()
+        .unwrap();
+    for body_size in [100, 64 * 1024] {
+        let body = Arc::new(vec![7u8; body_size]);
+        let buffer = Arc::new(CommitLogBuffer(UnsafeCell::new(vec![0; body_size])));
+        for use_reentrant_lock in [false, true] {
+            let lock = Arc::new(PutMessageLock::new(use_reentrant_lock));
+            let name = format!(
+                "put_{}x{}B_{}",
+                PUTTERS,
+                body_size,
+                if use_reentrant_lock { "mutex" } else { "spin" }
+            );
+            c.bench_function(&name, |b| {
+                b.iter_custom(|iters| put_concurrently(&runtime, &lock, &buffer, &body, iters))
+            });
+        }
+    }
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
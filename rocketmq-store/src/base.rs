@@ -21,13 +21,15 @@ pub mod allocate_mapped_file_service;
 pub mod append_message_callback;
 pub mod commit_log_dispatcher;
 pub mod compaction_append_msg_callback;
-pub(crate) mod dispatch_request;
+pub mod dispatch_request;
+pub mod epoch_file_checkpoint;
 pub mod flush_manager;
 pub mod get_message_result;
 pub mod message_arriving_listener;
 pub mod message_result;
 pub mod message_status_enum;
 pub mod put_message_context;
+pub mod put_message_lock;
 pub mod query_message_result;
 pub mod select_result;
 pub mod store_checkpoint;
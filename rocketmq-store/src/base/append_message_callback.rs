@@ -18,55 +18,57 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Instant;
 
-use bytes::BufMut;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::config::TopicConfig;
 use rocketmq_common::common::message::message_batch::MessageExtBatch;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
 use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
 use rocketmq_common::utils::message_utils;
+use rocketmq_common::CRC32Utils::crc32;
+use rocketmq_common::MessageDecoder;
 use rocketmq_common::MessageUtils::build_batch_message_id;
-use rocketmq_rust::SyncUnsafeCellWrapper;
 
 use crate::base::message_result::AppendMessageResult;
 use crate::base::message_status_enum::AppendMessageStatus;
 use crate::base::put_message_context::PutMessageContext;
 use crate::config::message_store_config::MessageStoreConfig;
 use crate::log_file::commit_log::get_message_num;
-use crate::log_file::commit_log::CommitLog;
 use crate::log_file::commit_log::BLANK_MAGIC_CODE;
 use crate::log_file::commit_log::CRC32_RESERVED_LEN;
-use crate::log_file::mapped_file::MappedFile;
 
 /// Write messages callback interface
+///
+/// The mapped file hands the callback the unwritten rest of the file as a byte slot, so the
+/// callback only deals with bytes and can be reused by every store that appends encoded
+/// messages.
 pub trait AppendMessageCallback {
-    /// After message serialization, write MappedByteBuffer
+    /// After message serialization, write it into the slot of the mapped file
     ///
     /// # Arguments
     ///
     /// * `file_from_offset` - The offset of the file
-    /// * `byte_buffer` - The buffer to write
-    /// * `max_blank` - The maximum blank space
+    /// * `wrote_position` - The position of the slot in the file
+    /// * `slot` - The unwritten rest of the file, its length is the maximum blank space
     /// * `msg` - The message to write
     /// * `put_message_context` - The context of putting message
     ///
     /// # Returns
     ///
     /// The number of bytes written
-    fn do_append<MF: MappedFile>(
+    fn do_append(
         &self,
         file_from_offset: i64,
-        mapped_file: &MF,
-        max_blank: i32,
+        wrote_position: i32,
+        slot: &mut [u8],
         msg: &mut MessageExtBrokerInner,
         put_message_context: &PutMessageContext,
     ) -> AppendMessageResult;
 
-    fn do_append_batch<MF: MappedFile>(
+    fn do_append_batch(
         &self,
         file_from_offset: i64,
-        mapped_file: &MF,
-        max_blank: i32,
+        wrote_position: i32,
+        slot: &mut [u8],
         msg: &mut MessageExtBatch,
         put_message_context: &mut PutMessageContext,
         enabled_append_prop_crc: bool,
@@ -77,7 +79,6 @@ pub trait AppendMessageCallback {
 const END_FILE_MIN_BLANK_LENGTH: i32 = 4 + 4;
 
 pub(crate) struct DefaultAppendMessageCallback {
-    msg_store_item_memory: SyncUnsafeCellWrapper<bytes::BytesMut>,
     crc32_reserved_length: i32,
     message_store_config: Arc<MessageStoreConfig>,
     topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
@@ -89,42 +90,46 @@ impl DefaultAppendMessageCallback {
         topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
     ) -> Self {
         Self {
-            msg_store_item_memory: SyncUnsafeCellWrapper::new(bytes::BytesMut::with_capacity(
-                END_FILE_MIN_BLANK_LENGTH as usize,
-            )),
             crc32_reserved_length: CRC32_RESERVED_LEN,
             message_store_config,
             topic_config_table,
         }
     }
+
+    /// Marks the rest of the file as blank so the reader skips to the next file.
+    fn write_end_of_file(slot: &mut [u8]) {
+        let max_blank = slot.len() as i32;
+        slot[..4].copy_from_slice(&max_blank.to_be_bytes());
+        slot[4..8].copy_from_slice(&BLANK_MAGIC_CODE.to_be_bytes());
+    }
+
+    /// Fills the CRC32 property reserved at the end of `message` with the checksum of the bytes
+    /// before it.
+    fn fill_crc32(&self, message: &mut [u8]) {
+        let check_size = message.len() - self.crc32_reserved_length as usize;
+        let crc = crc32(&message[..check_size]);
+        MessageDecoder::create_crc32(&mut message[check_size..], crc);
+    }
 }
 
 impl AppendMessageCallback for DefaultAppendMessageCallback {
-    fn do_append<MF: MappedFile>(
+    fn do_append(
         &self,
         file_from_offset: i64,
-        mapped_file: &MF,
-        max_blank: i32,
+        wrote_position: i32,
+        slot: &mut [u8],
         msg_inner: &mut MessageExtBrokerInner,
         put_message_context: &PutMessageContext,
     ) -> AppendMessageResult {
         let mut pre_encode_buffer = msg_inner.encoded_buff.take().unwrap(); // Assuming get_encoded_buff returns Option<ByteBuffer>
-        let is_multi_dispatch_msg = self.message_store_config.enable_multi_dispatch
-            && CommitLog::is_multi_dispatch_msg(msg_inner);
-        if is_multi_dispatch_msg {
-            unimplemented!("Multi dispatch message is not supported yet");
-        }
-
         let msg_len = i32::from_be_bytes(pre_encode_buffer[0..4].try_into().unwrap());
+        let max_blank = slot.len() as i32;
         //physic offset
-        let wrote_offset = file_from_offset + mapped_file.get_wrote_position() as i64;
+        let wrote_offset = file_from_offset + wrote_position as i64;
         let addr = msg_inner.message_ext_inner.store_host;
         let msg_id_supplier =
             move || -> String { message_utils::build_message_id(addr, wrote_offset) };
 
-        /*let msg_id =
-        message_utils::build_message_id(msg_inner.message_ext_inner.store_host, wrote_offset);*/
-
         let mut queue_offset = msg_inner.queue_offset();
         //let message_num = CommitLog::get_message_num(msg_inner);
         let message_num = get_message_num(&self.topic_config_table, msg_inner);
@@ -135,12 +140,10 @@ impl AppendMessageCallback for DefaultAppendMessageCallback {
         }
 
         if (msg_len + END_FILE_MIN_BLANK_LENGTH) > max_blank {
-            let bytes = self.msg_store_item_memory.mut_from_ref();
-            bytes.clear();
-            bytes.put_i32(max_blank);
-            bytes.put_i32(BLANK_MAGIC_CODE);
             let instant = Instant::now();
-            mapped_file.write_bytes_segment(bytes.as_ref(), wrote_offset as usize, 0, bytes.len());
+            Self::write_end_of_file(slot);
+            // The message is appended again at the start of the next file
+            msg_inner.encoded_buff = Some(pre_encode_buffer);
             return AppendMessageResult {
                 status: AppendMessageStatus::EndOfFile,
                 wrote_offset,
@@ -166,11 +169,12 @@ impl AppendMessageCallback for DefaultAppendMessageCallback {
         pos += 8 + 4 + 8 + ip_len;
         pre_encode_buffer[pos..(pos + 8)]
             .copy_from_slice(&msg_inner.store_timestamp().to_be_bytes());
+        if self.message_store_config.enabled_append_prop_crc {
+            self.fill_crc32(&mut pre_encode_buffer[..msg_len as usize]);
+        }
 
-        // msg_inner.encoded_buff = pre_encode_buffer;
-        let bytes = pre_encode_buffer.freeze();
         let instant = Instant::now();
-        mapped_file.append_message_bytes_no_position_update(&bytes);
+        slot[..msg_len as usize].copy_from_slice(&pre_encode_buffer[..msg_len as usize]);
         AppendMessageResult {
             status: AppendMessageStatus::PutOk,
             wrote_offset,
@@ -184,21 +188,22 @@ impl AppendMessageCallback for DefaultAppendMessageCallback {
         }
     }
 
-    fn do_append_batch<MF: MappedFile>(
+    fn do_append_batch(
         &self,
         file_from_offset: i64,
-        mapped_file: &MF,
-        max_blank: i32,
+        wrote_position: i32,
+        slot: &mut [u8],
         msg_batch: &mut MessageExtBatch,
         put_message_context: &mut PutMessageContext,
         enabled_append_prop_crc: bool,
     ) -> AppendMessageResult {
+        let max_blank = slot.len() as i32;
         //physic offset--The starting point for writing this message file.If, while writing a
         // message, it is found that the length is insufficient,the remaining length of the file
         // and the end-of-file marker should be rewritten at this point.
-        let wrote_offset = file_from_offset + mapped_file.get_wrote_position() as i64;
+        let wrote_offset = file_from_offset + wrote_position as i64;
         // Record ConsumeQueue information
-        let queue_offset = msg_batch.message_ext_broker_inner.queue_offset();
+        let mut queue_offset = msg_batch.message_ext_broker_inner.queue_offset();
         let begin_queue_offset = queue_offset;
 
         let begin_time_mills = Instant::now();
@@ -220,9 +225,11 @@ impl AppendMessageCallback for DefaultAppendMessageCallback {
         };
         let addr = msg_batch.message_ext_broker_inner.store_host();
         let batch_size = put_message_context.get_batch_size();
-        let phy_ops = put_message_context.get_phy_pos().to_vec();
-        let msg_id_supplier = move || -> String {
-            build_batch_message_id(addr, store_host_length, batch_size as usize, &phy_ops)
+        let msg_id_supplier = |put_message_context: &PutMessageContext| {
+            let phy_ops = put_message_context.get_phy_pos().to_vec();
+            move || -> String {
+                build_batch_message_id(addr, store_host_length, batch_size as usize, &phy_ops)
+            }
         };
         let mut total_msg_len = 0;
         let mut msg_num = 0;
@@ -236,21 +243,14 @@ impl AppendMessageCallback for DefaultAppendMessageCallback {
             );
             total_msg_len += msg_len;
             if total_msg_len + END_FILE_MIN_BLANK_LENGTH > max_blank {
-                let bytes = self.msg_store_item_memory.mut_from_ref();
-                bytes.clear();
-                bytes.put_i32(max_blank);
-                bytes.put_i32(BLANK_MAGIC_CODE);
-                mapped_file.write_bytes_segment(
-                    bytes.as_ref(),
-                    wrote_offset as usize,
-                    0,
-                    bytes.len(),
-                );
+                Self::write_end_of_file(slot);
+                // The batch is appended again at the start of the next file
+                msg_batch.encoded_buff = Some(messages_byte_buffer);
                 return AppendMessageResult {
                     status: AppendMessageStatus::EndOfFile,
                     wrote_offset,
                     wrote_bytes: max_blank,
-                    msg_id_supplier: Some(Arc::new(Box::new(msg_id_supplier))),
+                    msg_id_supplier: Some(Arc::new(Box::new(msg_id_supplier(put_message_context)))),
                     store_timestamp: msg_batch.message_ext_broker_inner.store_timestamp(),
                     logics_offset: begin_queue_offset,
                     page_cache_rt: begin_time_mills.elapsed().as_millis() as i64,
@@ -270,21 +270,22 @@ impl AppendMessageCallback for DefaultAppendMessageCallback {
                     .to_be_bytes(),
             );
             if enabled_append_prop_crc {
-                let _check_size = msg_len - self.crc32_reserved_length;
+                self.fill_crc32(&mut messages_byte_buffer[msg_pos..msg_pos + msg_len as usize]);
             }
             put_message_context.get_phy_pos_mut()[index] = phy_pos;
+            queue_offset += 1;
             msg_num += 1;
             msg_pos += msg_len as usize;
             index += 1;
         }
 
-        let bytes = messages_byte_buffer.freeze();
-        mapped_file.append_message_bytes_no_position_update(&bytes);
+        slot[..total_msg_len as usize]
+            .copy_from_slice(&messages_byte_buffer[..total_msg_len as usize]);
         AppendMessageResult {
             status: AppendMessageStatus::PutOk,
             wrote_offset,
             wrote_bytes: total_msg_len,
-            msg_id_supplier: Some(Arc::new(Box::new(msg_id_supplier))),
+            msg_id_supplier: Some(Arc::new(Box::new(msg_id_supplier(put_message_context)))),
             store_timestamp: msg_batch.message_ext_broker_inner.store_timestamp(),
             logics_offset: begin_queue_offset,
             page_cache_rt: begin_time_mills.elapsed().as_millis() as i64,
@@ -293,3 +294,115 @@ impl AppendMessageCallback for DefaultAppendMessageCallback {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use rocketmq_common::common::message::MessageConst;
+
+    use super::*;
+    use crate::message_encoder::message_ext_encoder::MessageExtEncoder;
+
+    fn callback(config: MessageStoreConfig) -> DefaultAppendMessageCallback {
+        DefaultAppendMessageCallback::new(Arc::new(config), Arc::default())
+    }
+
+    fn encoded_message(config: &MessageStoreConfig) -> MessageExtBrokerInner {
+        let mut msg = MessageExtBrokerInner::default();
+        msg.message_ext_inner.message.topic = CheetahString::from_static_str("TopicTest");
+        msg.message_ext_inner.message.body = Some(Bytes::from_static(b"hello"));
+        msg.message_ext_inner.body_crc = crc32(b"hello");
+        msg.message_ext_inner.queue_offset = 7;
+        msg.message_ext_inner.store_timestamp = 2_000;
+        let mut encoder = MessageExtEncoder::new(Arc::new(config.clone()));
+        assert!(encoder.encode(&msg).is_none());
+        msg.encoded_buff = Some(encoder.byte_buf());
+        msg
+    }
+
+    #[test]
+    fn append_writes_the_offsets_into_the_slot() {
+        let config = MessageStoreConfig::default();
+        let mut msg = encoded_message(&config);
+        let mut slot = vec![0; 1024];
+
+        let result = callback(config).do_append(
+            1_000,
+            100,
+            &mut slot,
+            &mut msg,
+            &PutMessageContext::new("TopicTest-0".to_string()),
+        );
+
+        assert_eq!(result.status, AppendMessageStatus::PutOk);
+        assert_eq!(result.wrote_offset, 1_100);
+        let mut written = Bytes::copy_from_slice(&slot[..result.wrote_bytes as usize]);
+        let decoded =
+            MessageDecoder::decode(&mut written, true, false, false, false, true).unwrap();
+        assert_eq!(decoded.queue_offset, 7);
+        assert_eq!(decoded.commit_log_offset, 1_100);
+        assert_eq!(decoded.store_timestamp, 2_000);
+        assert!(slot[result.wrote_bytes as usize..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn end_of_file_keeps_the_message_for_the_next_file() {
+        let config = MessageStoreConfig::default();
+        let mut msg = encoded_message(&config);
+        let mut slot = vec![0; 32];
+
+        let result = callback(config).do_append(
+            1_000,
+            100,
+            &mut slot,
+            &mut msg,
+            &PutMessageContext::new("TopicTest-0".to_string()),
+        );
+
+        assert_eq!(result.status, AppendMessageStatus::EndOfFile);
+        assert_eq!(result.wrote_bytes, 32);
+        assert_eq!(i32::from_be_bytes(slot[..4].try_into().unwrap()), 32);
+        assert_eq!(
+            i32::from_be_bytes(slot[4..8].try_into().unwrap()),
+            BLANK_MAGIC_CODE
+        );
+        assert!(msg.encoded_buff.is_some());
+    }
+
+    #[test]
+    fn crc32_property_covers_the_written_message() {
+        let config = MessageStoreConfig {
+            enabled_append_prop_crc: true,
+            ..MessageStoreConfig::default()
+        };
+        let mut msg = encoded_message(&config);
+        let mut slot = vec![0; 1024];
+
+        let result = callback(config).do_append(
+            0,
+            0,
+            &mut slot,
+            &mut msg,
+            &PutMessageContext::new("TopicTest-0".to_string()),
+        );
+
+        let written = &slot[..result.wrote_bytes as usize];
+        let check_size = written.len() - CRC32_RESERVED_LEN as usize;
+        let mut expected = [0; CRC32_RESERVED_LEN as usize];
+        MessageDecoder::create_crc32(&mut expected, crc32(&written[..check_size]));
+        assert_eq!(&written[check_size..], &expected[..]);
+        let decoded = MessageDecoder::decode(
+            &mut Bytes::copy_from_slice(written),
+            true,
+            false,
+            false,
+            false,
+            true,
+        )
+        .unwrap();
+        assert!(decoded
+            .message
+            .properties
+            .contains_key(MessageConst::PROPERTY_CRC32));
+    }
+}
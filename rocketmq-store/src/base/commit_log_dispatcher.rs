@@ -18,5 +18,7 @@
 use crate::base::dispatch_request::DispatchRequest;
 
 pub trait CommitLogDispatcher: Send + Sync + 'static {
-    fn dispatch(&self, dispatch_request: &DispatchRequest);
+    /// Dispatchers run in order and may enrich the request for the ones after them, e.g. with
+    /// the filter bit map the consume queue dispatcher stores in the queue extension.
+    fn dispatch(&self, dispatch_request: &mut DispatchRequest);
 }
@@ -0,0 +1,264 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+
+use parking_lot::RwLock;
+use rocketmq_common::utils::crc32_utils::crc32;
+use rocketmq_common::utils::file_utils;
+use tracing::info;
+use tracing::warn;
+
+/// The first commit log offset written in an epoch, i.e. while one broker was the master.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EpochEntry {
+    pub epoch: i32,
+    pub start_offset: i64,
+}
+
+/// The epochs this broker took part in, in the format of the `epochFileCheckpoint` file: the
+/// number of entries, the crc32 of the entry lines and one `epoch-startOffset` line per entry.
+///
+/// Comparing the entries of two replicas gives the offset their commit logs diverge at, which is
+/// where a demoted master truncates to before it replays from the new master.
+pub struct EpochFileCheckpoint {
+    file_path: String,
+    entries: RwLock<Vec<EpochEntry>>,
+}
+
+impl EpochFileCheckpoint {
+    /// Loads the entries from `file_path`, or from its backup if the file is corrupt. A missing
+    /// file is an empty checkpoint, it is created on the first change.
+    pub fn new(file_path: impl Into<String>) -> io::Result<Self> {
+        let file_path = file_path.into();
+        let entries = match Self::read(&file_path) {
+            Ok(entries) => entries,
+            Err(err) => {
+                warn!(
+                    "epoch file {} is corrupt, load its backup instead: {}",
+                    file_path, err
+                );
+                Self::read(&format!("{}.bak", file_path))?
+            }
+        };
+        info!("load {} epoch entries from {}", entries.len(), file_path);
+        Ok(Self {
+            file_path,
+            entries: RwLock::new(entries),
+        })
+    }
+
+    fn read(file_path: &str) -> io::Result<Vec<EpochEntry>> {
+        let content = file_utils::file_to_string(file_path)?;
+        if content.is_empty() {
+            return Ok(Vec::new());
+        }
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let mut lines = content.lines();
+        let size = lines
+            .next()
+            .and_then(|line| line.trim().parse::<usize>().ok())
+            .ok_or_else(|| invalid("missing entry count"))?;
+        let checksum = lines
+            .next()
+            .and_then(|line| line.trim().parse::<i64>().ok())
+            .ok_or_else(|| invalid("missing checksum"))?;
+        let mut entry_lines = String::new();
+        let mut entries = Vec::with_capacity(size);
+        for line in lines.filter(|line| !line.is_empty()) {
+            let (epoch, start_offset) = line
+                .split_once('-')
+                .and_then(|(epoch, start_offset)| {
+                    Some((epoch.parse().ok()?, start_offset.parse().ok()?))
+                })
+                .ok_or_else(|| invalid("malformed epoch entry"))?;
+            entries.push(EpochEntry {
+                epoch,
+                start_offset,
+            });
+            entry_lines.push_str(line);
+            entry_lines.push('\n');
+        }
+        if entries.len() != size {
+            return Err(invalid("entry count mismatch"));
+        }
+        if crc32(entry_lines.as_bytes()) as i64 != checksum {
+            return Err(invalid("checksum mismatch"));
+        }
+        Ok(entries)
+    }
+
+    fn persist(&self, entries: &[EpochEntry]) -> io::Result<()> {
+        let entry_lines = entries
+            .iter()
+            .map(|entry| format!("{}-{}\n", entry.epoch, entry.start_offset))
+            .collect::<String>();
+        let content = format!(
+            "{}\n{}\n{}",
+            entries.len(),
+            crc32(entry_lines.as_bytes()),
+            entry_lines
+        );
+        file_utils::string_to_file(&content, &self.file_path)
+    }
+
+    /// Starts `epoch` at `start_offset`. Epochs only move forward and never start before the
+    /// previous one, anything else is rejected and `false` returned.
+    pub fn set_last_epoch_entry(&self, epoch: i32, start_offset: i64) -> io::Result<bool> {
+        let mut entries = self.entries.write();
+        if let Some(last) = entries.last() {
+            if epoch <= last.epoch || start_offset < last.start_offset {
+                return Ok(false);
+            }
+        }
+        entries.push(EpochEntry {
+            epoch,
+            start_offset,
+        });
+        self.persist(&entries)?;
+        Ok(true)
+    }
+
+    pub fn last_entry(&self) -> Option<EpochEntry> {
+        self.entries.read().last().copied()
+    }
+
+    pub fn entries(&self) -> Vec<EpochEntry> {
+        self.entries.read().clone()
+    }
+
+    /// The entry of the epoch `offset` was written in.
+    pub fn find_epoch_entry_by_offset(&self, offset: i64) -> Option<EpochEntry> {
+        self.entries
+            .read()
+            .iter()
+            .rev()
+            .find(|entry| entry.start_offset <= offset)
+            .copied()
+    }
+
+    /// Drops the epochs starting at or past `offset`, as the commit log is truncated there.
+    pub fn truncate_suffix_by_offset(&self, offset: i64) -> io::Result<()> {
+        let mut entries = self.entries.write();
+        let len = entries.len();
+        entries.retain(|entry| entry.start_offset < offset);
+        if entries.len() != len {
+            info!(
+                "truncate {} epoch entries at or past offset {}",
+                len - entries.len(),
+                offset
+            );
+            self.persist(&entries)?;
+        }
+        Ok(())
+    }
+
+    /// The offset up to which this replica, holding `max_offset` bytes, agrees with one whose
+    /// entries are `other` and which holds `other_max_offset` bytes: the end of the newest epoch
+    /// both started at the same offset. `None` when they share no epoch.
+    pub fn find_last_consistent_point(
+        &self,
+        max_offset: i64,
+        other: &[EpochEntry],
+        other_max_offset: i64,
+    ) -> Option<i64> {
+        let entries = self.entries.read();
+        let end_offset = |entries: &[EpochEntry], index: usize, max_offset: i64| {
+            entries
+                .get(index + 1)
+                .map_or(max_offset, |next| next.start_offset)
+        };
+        entries.iter().enumerate().rev().find_map(|(index, entry)| {
+            let other_index = other.iter().position(|other_entry| other_entry == entry)?;
+            Some(end_offset(&entries, index, max_offset).min(end_offset(
+                other,
+                other_index,
+                other_max_offset,
+            )))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_survive_a_reload_and_corrupt_files_fall_back_to_the_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("epochFileCheckpoint");
+        let path = path.to_str().unwrap();
+        let checkpoint = EpochFileCheckpoint::new(path).unwrap();
+        assert!(checkpoint.set_last_epoch_entry(1, 0).unwrap());
+        assert!(checkpoint.set_last_epoch_entry(3, 1000).unwrap());
+        assert!(!checkpoint.set_last_epoch_entry(2, 2000).unwrap());
+        assert!(!checkpoint.set_last_epoch_entry(4, 500).unwrap());
+
+        let reloaded = EpochFileCheckpoint::new(path).unwrap();
+        assert_eq!(reloaded.entries(), checkpoint.entries());
+        assert_eq!(
+            reloaded.find_epoch_entry_by_offset(999),
+            Some(EpochEntry {
+                epoch: 1,
+                start_offset: 0
+            })
+        );
+
+        std::fs::write(path, "2\n0\n1-0\n3-1000\n").unwrap();
+        let from_backup = EpochFileCheckpoint::new(path).unwrap();
+        assert_eq!(
+            from_backup.entries(),
+            vec![EpochEntry {
+                epoch: 1,
+                start_offset: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn consistent_point_is_the_end_of_the_newest_shared_epoch() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoint =
+            EpochFileCheckpoint::new(dir.path().join("epoch").to_str().unwrap()).unwrap();
+        checkpoint.set_last_epoch_entry(1, 0).unwrap();
+        checkpoint.set_last_epoch_entry(2, 1000).unwrap();
+        checkpoint.set_last_epoch_entry(3, 1800).unwrap();
+
+        let new_master = [
+            EpochEntry {
+                epoch: 1,
+                start_offset: 0,
+            },
+            EpochEntry {
+                epoch: 2,
+                start_offset: 1000,
+            },
+            EpochEntry {
+                epoch: 4,
+                start_offset: 1500,
+            },
+        ];
+        assert_eq!(
+            checkpoint.find_last_consistent_point(2500, &new_master, 3000),
+            Some(1500)
+        );
+        assert_eq!(checkpoint.find_last_consistent_point(2500, &[], 0), None);
+
+        checkpoint.truncate_suffix_by_offset(1500).unwrap();
+        assert_eq!(checkpoint.last_entry().map(|entry| entry.epoch), Some(2));
+    }
+}
@@ -166,8 +166,6 @@ impl GetMessageResult {
         queue_offset: u64,
         batch_num: i32,
     ) {
-        let slice = maped_buffer.get_buffer();
-        // self.message_buffer_list.push(Bytes::copy_from_slice(slice));
         self.buffer_total_size += maped_buffer.size;
         self.message_count += batch_num;
         self.message_queue_offset.push(queue_offset);
@@ -177,14 +175,78 @@ impl GetMessageResult {
     pub fn message_mapped_list(&self) -> &[SelectMappedBufferResult] {
         self.message_mapped_list.as_slice()
     }
+
+    /// Releases the mapped files referenced by the selected messages.
+    pub fn release(&mut self) {
+        for mapped_buffer in self.message_mapped_list.iter_mut() {
+            mapped_buffer.release();
+        }
+        self.message_mapped_list.clear();
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use bytes::Bytes;
+    use cheetah_string::CheetahString;
 
     use super::*;
     use crate::base::message_status_enum::GetMessageStatus;
+    use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
+    use crate::log_file::mapped_file::MappedFile;
+
+    #[test]
+    fn mapped_file_outlives_destroy_while_referenced() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("00000000000000000000");
+        let mapped_file = Arc::new(DefaultMappedFile::new(
+            CheetahString::from_string(file_name.to_string_lossy().to_string()),
+            1024,
+        ));
+        assert!(mapped_file.append_message_bytes(&Bytes::from_static(b"hello rocketmq")));
+
+        let mut result = GetMessageResult::new();
+        let buffer = mapped_file.clone().select_mapped_buffer_size(0, 5).unwrap();
+        result.add_message(buffer, 0, 1);
+        assert_eq!(mapped_file.get_ref_count(), 2);
+
+        // the file is scheduled for deletion but a pull response still references it
+        assert!(!mapped_file.destroy(0));
+        assert!(!mapped_file.is_available());
+        assert!(!mapped_file.is_cleanup_over());
+        assert!(file_name.exists());
+        assert_eq!(
+            &*result.message_mapped_list()[0].get_buffer().unwrap(),
+            b"hello"
+        );
+        assert!(mapped_file.clone().select_mapped_buffer(0).is_none());
+
+        result.release();
+        assert_eq!(mapped_file.get_ref_count(), 0);
+        assert!(mapped_file.is_cleanup_over());
+        assert!(mapped_file.destroy(0));
+        assert!(!file_name.exists());
+    }
+
+    #[test]
+    fn dropping_mapped_buffer_releases_reference() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("00000000000000000000");
+        let mapped_file = Arc::new(DefaultMappedFile::new(
+            CheetahString::from_string(file_name.to_string_lossy().to_string()),
+            1024,
+        ));
+        assert!(mapped_file.append_message_bytes(&Bytes::from_static(b"hello")));
+        {
+            let mut result = GetMessageResult::new();
+            result.add_message(mapped_file.clone().select_mapped_buffer(0).unwrap(), 0, 1);
+            assert_eq!(mapped_file.get_ref_count(), 2);
+        }
+        assert_eq!(mapped_file.get_ref_count(), 1);
+        assert!(mapped_file.destroy(0));
+    }
     #[test]
     fn get_message_result_new() {
         let result = GetMessageResult::new();
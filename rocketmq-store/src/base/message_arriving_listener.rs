@@ -28,7 +28,7 @@ pub trait MessageArrivingListener {
     /// * `logic_offset` - An i64 that represents the logical offset of the message in the queue.
     /// * `tags_code` - An i64 that represents the tags associated with the message.
     /// * `msg_store_time` - An i64 that represents the time when the message was stored.
-    /// * `filter_bit_map` - A Vec<u8> that represents the filter bit map for the message.
+    /// * `filter_bit_map` - The filter bit map for the message, borrowed from the dispatch request.
     /// * `properties` - An Option containing a reference to a HashMap<String, String> that holds
     ///   the properties of the message.
     fn arriving(
@@ -38,7 +38,7 @@ pub trait MessageArrivingListener {
         logic_offset: i64,
         tags_code: Option<i64>,
         msg_store_time: i64,
-        filter_bit_map: Option<Vec<u8>>,
+        filter_bit_map: Option<&[u8]>,
         properties: Option<&HashMap<CheetahString, CheetahString>>,
     );
 }
@@ -0,0 +1,171 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::hint;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use opentelemetry::metrics::Histogram;
+use tokio::sync::MutexGuard;
+
+/// Failed attempts a spinning put makes before yielding to the runtime, so a holder scheduled
+/// on the same worker thread gets to release the lock.
+const SPINS_BEFORE_YIELD: u32 = 64;
+
+enum LockKind {
+    Spin(AtomicBool),
+    Mutex(tokio::sync::Mutex<()>),
+}
+
+/// The lock every put holds while appending to the commit log.
+///
+/// The spin lock suits small messages, whose appends are shorter than parking and waking a
+/// task. The mutex, selected by `use_reentrant_lock_when_put_message`, suits large messages and
+/// many concurrent putters, which would otherwise burn CPU spinning.
+pub struct PutMessageLock {
+    kind: LockKind,
+    wait_histogram: OnceLock<Histogram<f64>>,
+}
+
+/// Releases the [`PutMessageLock`] it was returned by when dropped.
+pub struct PutMessageLockGuard<'a> {
+    _inner: GuardInner<'a>,
+}
+
+enum GuardInner<'a> {
+    Spin(SpinGuard<'a>),
+    Mutex(MutexGuard<'a, ()>),
+}
+
+struct SpinGuard<'a>(&'a AtomicBool);
+
+impl Drop for SpinGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+impl PutMessageLock {
+    pub fn new(use_reentrant_lock: bool) -> Self {
+        let kind = if use_reentrant_lock {
+            LockKind::Mutex(tokio::sync::Mutex::new(()))
+        } else {
+            LockKind::Spin(AtomicBool::new(false))
+        };
+        Self {
+            kind,
+            wait_histogram: OnceLock::new(),
+        }
+    }
+
+    pub fn is_spin(&self) -> bool {
+        matches!(self.kind, LockKind::Spin(_))
+    }
+
+    pub async fn lock(&self) -> PutMessageLockGuard<'_> {
+        let wait_start = self.wait_histogram.get().map(|_| Instant::now());
+        let inner = match &self.kind {
+            LockKind::Spin(locked) => {
+                let mut spins = 0;
+                while locked
+                    .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+                    .is_err()
+                {
+                    spins += 1;
+                    if spins % SPINS_BEFORE_YIELD == 0 {
+                        tokio::task::yield_now().await;
+                    } else {
+                        hint::spin_loop();
+                    }
+                }
+                GuardInner::Spin(SpinGuard(locked))
+            }
+            LockKind::Mutex(mutex) => GuardInner::Mutex(mutex.lock().await),
+        };
+        if let (Some(histogram), Some(wait_start)) = (self.wait_histogram.get(), wait_start) {
+            histogram.record(wait_start.elapsed().as_secs_f64() * 1000.0, &[]);
+        }
+        PutMessageLockGuard { _inner: inner }
+    }
+
+    /// Records how long each acquisition waited, in ms, into `histogram` from now on.
+    pub(crate) fn set_wait_histogram(&self, histogram: Histogram<f64>) {
+        let _ = self.wait_histogram.set(histogram);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    use super::*;
+
+    async fn run_exclusively(lock: PutMessageLock) {
+        let lock = Arc::new(lock);
+        let holders = Arc::new(AtomicUsize::new(0));
+        let mut tasks = Vec::new();
+        for _ in 0..16 {
+            let lock = lock.clone();
+            let holders = holders.clone();
+            tasks.push(tokio::spawn(async move {
+                for _ in 0..200 {
+                    let _guard = lock.lock().await;
+                    assert_eq!(holders.fetch_add(1, Ordering::AcqRel), 0);
+                    hint::spin_loop();
+                    holders.fetch_sub(1, Ordering::AcqRel);
+                }
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn spin_lock_is_held_by_one_put_at_a_time() {
+        let lock = PutMessageLock::new(false);
+        assert!(lock.is_spin());
+        run_exclusively(lock).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn mutex_is_held_by_one_put_at_a_time() {
+        let lock = PutMessageLock::new(true);
+        assert!(!lock.is_spin());
+        run_exclusively(lock).await;
+    }
+
+    #[tokio::test]
+    async fn spinning_put_yields_to_a_holder_on_the_same_thread() {
+        let lock = Arc::new(PutMessageLock::new(false));
+        let guard = lock.lock().await;
+        let waiter = tokio::spawn({
+            let lock = lock.clone();
+            async move {
+                let _guard = lock.lock().await;
+            }
+        });
+        tokio::task::yield_now().await;
+        drop(guard);
+        tokio::time::timeout(std::time::Duration::from_secs(5), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
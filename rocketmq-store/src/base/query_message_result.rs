@@ -35,9 +35,9 @@ impl QueryMessageResult {
 
         let mut bytes_mut = BytesMut::with_capacity(self.buffer_total_size as usize);
         for msg in self.message_maped_list.iter() {
-            let data = &msg.mapped_file.as_ref().unwrap().get_mapped_file()
-                [msg.start_offset as usize..(msg.start_offset + msg.size as u64) as usize];
-            bytes_mut.extend_from_slice(data);
+            if let Ok(data) = msg.get_buffer() {
+                bytes_mut.extend_from_slice(&data);
+            }
         }
         Some(bytes_mut.freeze())
     }
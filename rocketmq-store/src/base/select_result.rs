@@ -21,9 +21,15 @@ use bytes::Bytes;
 use bytes::BytesMut;
 
 use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
+use crate::log_file::mapped_file::default_mapped_file_impl::MappedBuffer;
+use crate::log_file::mapped_file::default_mapped_file_impl::MappedFileUnavailable;
 use crate::log_file::mapped_file::MappedFile;
 
 /// Represents the result of selecting a mapped buffer.
+///
+/// The result holds a reference on its mapped file, so the file can't be cleaned up while the
+/// buffer is still in use. The reference is given back by [`release`](Self::release) or when the
+/// result is dropped.
 pub struct SelectMappedBufferResult {
     /// The start offset.
     pub start_offset: u64,
@@ -36,24 +42,36 @@ pub struct SelectMappedBufferResult {
 }
 
 impl SelectMappedBufferResult {
-    /// Returns the buffer.
-    pub fn get_buffer(&self) -> &[u8] {
-        self.mapped_file.as_ref().unwrap().get_mapped_file()
-            [self.start_offset as usize..(self.start_offset + self.size as u64) as usize]
-            .as_ref()
+    /// Returns the buffer, an error once the result has been released or its mapped file has
+    /// been forcibly unmapped.
+    pub fn get_buffer(&self) -> Result<MappedBuffer, MappedFileUnavailable> {
+        let mapped_file = self.mapped_file.as_ref().ok_or(MappedFileUnavailable)?;
+        let pos = self.relative_pos();
+        mapped_file.mapped_buffer(pos..pos + self.size as usize)
     }
 
-    pub fn get_buffer_slice_mut(&self) -> &mut [u8] {
-        self.mapped_file.as_ref().unwrap().get_mapped_file_mut()
-            [self.start_offset as usize..(self.start_offset + self.size as u64) as usize]
-            .as_mut()
+    /// Position of this buffer inside its mapped file, `start_offset` is a global offset.
+    #[inline]
+    pub fn relative_pos(&self) -> usize {
+        match self.mapped_file.as_ref() {
+            None => self.start_offset as usize,
+            Some(inner) => (self.start_offset - inner.get_file_from_offset()) as usize,
+        }
     }
 
     pub fn get_bytes(&self) -> Option<Bytes> {
-        if self.size <= 0 || self.mapped_file.is_none() {
+        if self.size <= 0 {
             return None;
         }
-        Some(BytesMut::from(self.get_buffer()).freeze())
+        let buffer = self.get_buffer().ok()?;
+        Some(BytesMut::from(&*buffer).freeze())
+    }
+
+    /// Gives back the reference held on the mapped file, the buffer is empty afterwards.
+    pub fn release(&mut self) {
+        if let Some(mapped_file) = self.mapped_file.take() {
+            mapped_file.release();
+        }
     }
 
     pub fn is_in_mem(&self) -> bool {
@@ -66,3 +84,9 @@ impl SelectMappedBufferResult {
         }
     }
 }
+
+impl Drop for SelectMappedBufferResult {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
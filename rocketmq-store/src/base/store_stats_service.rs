@@ -71,6 +71,7 @@ pub struct StoreStatsService {
     get_message_times_total_found: AtomicUsize,
     get_message_transferred_msg_count: AtomicUsize,
     get_message_times_total_miss: AtomicUsize,
+    get_message_crc_failed_count: AtomicUsize,
     put_times_list: Mutex<LinkedList<CallSnapshot>>,
     get_times_found_list: Mutex<LinkedList<CallSnapshot>>,
     get_times_miss_list: Mutex<LinkedList<CallSnapshot>>,
@@ -97,6 +98,7 @@ impl StoreStatsService {
             get_message_times_total_found: AtomicUsize::new(0),
             get_message_transferred_msg_count: AtomicUsize::new(0),
             get_message_times_total_miss: AtomicUsize::new(0),
+            get_message_crc_failed_count: AtomicUsize::new(0),
             put_times_list: Mutex::new(LinkedList::new()),
             get_times_found_list: Mutex::new(LinkedList::new()),
             get_times_miss_list: Mutex::new(LinkedList::new()),
@@ -129,6 +131,11 @@ impl StoreStatsService {
         &self.get_message_transferred_msg_count
     }
 
+    /// Messages skipped by gets because their body no longer matches its CRC.
+    pub fn get_message_crc_failed_count(&self) -> &AtomicUsize {
+        &self.get_message_crc_failed_count
+    }
+
     pub fn get_put_message_failed_times(&self) -> &AtomicUsize {
         &self.put_message_failed_times
     }
@@ -217,6 +224,12 @@ impl StoreStatsService {
             "getTransferredTps".to_string(),
             self.get_get_transferred_tps(),
         );
+        result.insert(
+            "getMessageCrcFailedCount".to_string(),
+            self.get_message_crc_failed_count
+                .load(Ordering::Relaxed)
+                .to_string(),
+        );
         result.insert(
             "putLatency99".to_string(),
             format!("{:.2}", self.find_put_message_entire_time_px(0.99)),
@@ -18,6 +18,8 @@ use std::fmt;
 
 use serde::Deserialize;
 use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
 
 #[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
 pub enum BrokerRole {
@@ -37,6 +39,15 @@ impl BrokerRole {
     }
 }
 
+impl Serialize for BrokerRole {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.get_broker_role())
+    }
+}
+
 impl<'de> Deserialize<'de> for BrokerRole {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
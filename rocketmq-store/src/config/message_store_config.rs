@@ -20,18 +20,21 @@ use std::path::PathBuf;
 
 use cheetah_string::CheetahString;
 use lazy_static::lazy_static;
+use rocketmq_common::common::broker::broker_config::BrokerIdentity;
 use serde::Deserialize;
+use serde::Serialize;
 
 use crate::base::store_enum::StoreType;
 use crate::config::broker_role::BrokerRole;
 use crate::config::flush_disk_type::FlushDiskType;
 use crate::queue::single_consume_queue::CQ_STORE_UNIT_SIZE;
+use crate::store_path_config_helper;
 
 lazy_static! {
     static ref USER_HOME: PathBuf = dirs::home_dir().unwrap();
 }
 
-#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct MessageStoreConfig {
     pub store_path_root_dir: CheetahString,
@@ -92,6 +95,8 @@ pub struct MessageStoreConfig {
     pub delete_file_batch_max: usize,
     pub put_msg_index_hight_water: usize,
     pub max_message_size: i32,
+    /// Verifies body CRCs on recovery, which stops at the first mismatch, and on gets, which
+    /// skip corrupt messages.
     pub check_crc_on_recover: bool,
     pub flush_commit_log_least_pages: i32,
     pub commit_commit_log_least_pages: i32,
@@ -123,6 +128,9 @@ pub struct MessageStoreConfig {
     pub slave_timeout: usize,
     pub message_delay_level: String,
     pub flush_delay_offset_interval: usize,
+    /// Pending messages of a single delay level above which the schedule service logs a
+    /// warning.
+    pub schedule_backlog_warn_threshold: i64,
     pub clean_file_forcibly_enable: bool,
     pub warm_mapped_file_enable: bool,
     pub offset_check_in_slave: bool,
@@ -182,6 +190,7 @@ pub struct MessageStoreConfig {
     pub ha_flow_control_enable: bool,
     pub max_ha_transfer_byte_in_second: usize,
     pub ha_max_time_slave_not_catchup: usize,
+    pub ha_slave_fallbehind_max: i64,
     pub sync_master_flush_offset_when_startup: bool,
     pub max_checksum_range: usize,
     pub replicas_per_disk_partition: usize,
@@ -206,6 +215,18 @@ pub struct MessageStoreConfig {
     pub enable_rocksdb_log: bool,
     pub topic_queue_lock_num: usize,
     pub max_filter_message_size: i32,
+    /// Nest the store root under `{clusterName}_{brokerName}_{brokerId}` so that several
+    /// brokers can share one configured root without clobbering each other.
+    pub isolate_store_path_by_identity: bool,
+    /// Reject new puts while the consume queues fall behind the commit log by more than
+    /// `dispatch_behind_protect_bytes`.
+    pub dispatch_behind_protect_enable: bool,
+    pub dispatch_behind_protect_bytes: i64,
+    /// Verify the consume queues against the commit log once recovery is done, see
+    /// `DefaultMessageStore::check_consume_queues`.
+    pub check_consume_queue_on_startup: bool,
+    /// Rebuild the consume queues the startup check finds damaged from the commit log.
+    pub repair_consume_queue_on_startup: bool,
 }
 
 impl Default for MessageStoreConfig {
@@ -242,7 +263,7 @@ impl Default for MessageStoreConfig {
             timer_enable_disruptor: false,
             timer_enable_check_metrics: false,
             timer_intercept_delay_level: false,
-            timer_max_delay_sec: 0,
+            timer_max_delay_sec: 3600 * 24 * 3,
             timer_wheel_enable: false,
             disappear_time_after_start: -1,
             timer_stop_enqueue: false,
@@ -282,10 +303,10 @@ impl Default for MessageStoreConfig {
             flush_commit_log_least_pages: 0,
             commit_commit_log_least_pages: 4,
             flush_least_pages_when_warm_mapped_file: 0,
-            flush_consume_queue_least_pages: 0,
+            flush_consume_queue_least_pages: 2,
             flush_commit_log_thorough_interval: 1000 * 10,
             commit_commit_log_thorough_interval: 200,
-            flush_consume_queue_thorough_interval: 0,
+            flush_consume_queue_thorough_interval: 1000 * 60,
             max_transfer_bytes_on_message_in_memory: 1024 * 256,
             max_transfer_count_on_message_in_memory: 32,
             max_transfer_bytes_on_message_in_disk: 1024 * 64,
@@ -307,8 +328,10 @@ impl Default for MessageStoreConfig {
             sync_flush_timeout: 1000 * 5,
             put_message_timeout: 0,
             slave_timeout: 0,
-            message_delay_level: "".to_string(),
-            flush_delay_offset_interval: 0,
+            message_delay_level: "1s 5s 10s 30s 1m 2m 3m 4m 5m 6m 7m 8m 9m 10m 20m 30m 1h 2h"
+                .to_string(),
+            flush_delay_offset_interval: 10_000,
+            schedule_backlog_warn_threshold: 100_000,
             clean_file_forcibly_enable: false,
             warm_mapped_file_enable: false,
             offset_check_in_slave: false,
@@ -329,7 +352,7 @@ impl Default for MessageStoreConfig {
             enable_schedule_message_stats: false,
             enable_lmq: false,
             enable_multi_dispatch: false,
-            max_lmq_consume_queue_num: 0,
+            max_lmq_consume_queue_num: 20000,
             enable_schedule_async_deliver: false,
             schedule_async_deliver_max_pending_limit: 0,
             schedule_async_deliver_max_resend_num2_blocked: 0,
@@ -368,6 +391,7 @@ impl Default for MessageStoreConfig {
             ha_flow_control_enable: false,
             max_ha_transfer_byte_in_second: 0,
             ha_max_time_slave_not_catchup: 0,
+            ha_slave_fallbehind_max: 1024 * 1024 * 256,
             sync_master_flush_offset_when_startup: false,
             max_checksum_range: 0,
             replicas_per_disk_partition: 0,
@@ -375,8 +399,8 @@ impl Default for MessageStoreConfig {
             max_slave_resend_length: 0,
             sync_from_last_file: false,
             async_learner: false,
-            max_consume_queue_scan: 0,
-            sample_count_threshold: 0,
+            max_consume_queue_scan: 20_000,
+            sample_count_threshold: 5000,
             cold_data_flow_control_enable: false,
             cold_data_scan_enable: false,
             data_read_ahead_enable: false,
@@ -392,6 +416,11 @@ impl Default for MessageStoreConfig {
             enable_rocksdb_log: false,
             topic_queue_lock_num: 32,
             max_filter_message_size: 16000,
+            isolate_store_path_by_identity: false,
+            dispatch_behind_protect_enable: false,
+            dispatch_behind_protect_bytes: 1024 * 1024 * 1024,
+            check_consume_queue_on_startup: false,
+            repair_consume_queue_on_startup: false,
         }
     }
 }
@@ -407,6 +436,88 @@ impl MessageStoreConfig {
         self.store_path_commit_log.clone().unwrap().to_string()
     }
 
+    pub fn get_store_path_consume_queue(&self) -> String {
+        store_path_config_helper::get_store_path_consume_queue(self.store_path_root_dir.as_str())
+    }
+
+    pub fn get_store_path_consume_queue_ext(&self) -> String {
+        store_path_config_helper::get_store_path_consume_queue_ext(
+            self.store_path_root_dir.as_str(),
+        )
+    }
+
+    pub fn get_store_path_batch_consume_queue(&self) -> String {
+        store_path_config_helper::get_store_path_batch_consume_queue(
+            self.store_path_root_dir.as_str(),
+        )
+    }
+
+    pub fn get_store_path_index(&self) -> String {
+        store_path_config_helper::get_store_path_index(self.store_path_root_dir.as_str())
+    }
+
+    pub fn get_store_path_config(&self) -> String {
+        PathBuf::from(self.store_path_root_dir.as_str())
+            .join("config")
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    pub fn get_store_checkpoint(&self) -> String {
+        store_path_config_helper::get_store_checkpoint(self.store_path_root_dir.as_str())
+    }
+
+    /// The epoch entry file, `storePathEpochFile` if set.
+    pub fn get_store_path_epoch_file(&self) -> String {
+        match self.store_path_epoch_file {
+            Some(ref path) if !path.is_empty() => path.to_string(),
+            _ => store_path_config_helper::get_epoch_file(self.store_path_root_dir.as_str()),
+        }
+    }
+
+    pub fn get_abort_file(&self) -> String {
+        store_path_config_helper::get_abort_file(self.store_path_root_dir.as_str())
+    }
+
+    pub fn get_lock_file(&self) -> String {
+        store_path_config_helper::get_lock_file(self.store_path_root_dir.as_str())
+    }
+
+    pub fn get_delay_offset_store_path(&self) -> String {
+        store_path_config_helper::get_delay_offset_store_path(self.store_path_root_dir.as_str())
+    }
+
+    pub fn get_timer_wheel_path(&self) -> String {
+        store_path_config_helper::get_timer_wheel_path(self.store_path_root_dir.as_str())
+    }
+
+    pub fn get_timer_log_path(&self) -> String {
+        store_path_config_helper::get_timer_log_path(self.store_path_root_dir.as_str())
+    }
+
+    pub fn get_timer_check_path(&self) -> String {
+        store_path_config_helper::get_timer_check_path(self.store_path_root_dir.as_str())
+    }
+
+    /// Resolves the store root for `identity` when `isolate_store_path_by_identity` is enabled,
+    /// returning whether the root changed. Calling it again with the same identity is a no-op.
+    pub fn isolate_store_path(&mut self, identity: &BrokerIdentity) -> bool {
+        if !self.isolate_store_path_by_identity {
+            return false;
+        }
+        let canonical_name = identity.get_canonical_name();
+        let root = PathBuf::from(self.store_path_root_dir.as_str());
+        if root.ends_with(canonical_name.as_str()) {
+            return false;
+        }
+        self.store_path_root_dir = root
+            .join(canonical_name)
+            .to_string_lossy()
+            .into_owned()
+            .into();
+        true
+    }
+
     pub fn is_enable_rocksdb_store(&self) -> bool {
         self.store_type == StoreType::RocksDB
     }
@@ -781,6 +892,10 @@ impl MessageStoreConfig {
             "flushDelayOffsetInterval".to_string(),
             self.flush_delay_offset_interval.to_string(),
         );
+        properties.insert(
+            "scheduleBacklogWarnThreshold".to_string(),
+            self.schedule_backlog_warn_threshold.to_string(),
+        );
         properties.insert(
             "cleanFileForciblyEnable".to_string(),
             self.clean_file_forcibly_enable.to_string(),
@@ -1012,6 +1127,10 @@ impl MessageStoreConfig {
             "haMaxTimeSlaveNotCatchup".to_string(),
             self.ha_max_time_slave_not_catchup.to_string(),
         );
+        properties.insert(
+            "haSlaveFallbehindMax".to_string(),
+            self.ha_slave_fallbehind_max.to_string(),
+        );
         properties.insert(
             "syncMasterFlushOffsetWhenStartup".to_string(),
             self.sync_master_flush_offset_when_startup.to_string(),
@@ -1102,9 +1221,63 @@ impl MessageStoreConfig {
             "maxFilterMessageSize".into(),
             self.max_filter_message_size.to_string(),
         );
+        properties.insert(
+            "isolateStorePathByIdentity".into(),
+            self.isolate_store_path_by_identity.to_string(),
+        );
+        properties.insert(
+            "dispatchBehindProtectEnable".into(),
+            self.dispatch_behind_protect_enable.to_string(),
+        );
+        properties.insert(
+            "dispatchBehindProtectBytes".into(),
+            self.dispatch_behind_protect_bytes.to_string(),
+        );
+        properties.insert(
+            "checkConsumeQueueOnStartup".into(),
+            self.check_consume_queue_on_startup.to_string(),
+        );
+        properties.insert(
+            "repairConsumeQueueOnStartup".into(),
+            self.repair_consume_queue_on_startup.to_string(),
+        );
         properties
             .into_iter()
             .map(|(k, v)| (k.into(), v.into()))
             .collect::<HashMap<CheetahString, CheetahString>>()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn isolate_store_path_nests_root_under_identity_once() {
+        let mut identity = BrokerIdentity::new();
+        identity.broker_cluster_name = "DefaultCluster".into();
+        identity.broker_name = "broker-a".into();
+        identity.broker_id = 1;
+
+        let mut config = MessageStoreConfig {
+            store_path_root_dir: "/tmp/store".into(),
+            ..MessageStoreConfig::default()
+        };
+        assert!(!config.isolate_store_path(&identity));
+        assert_eq!(config.store_path_root_dir.as_str(), "/tmp/store");
+
+        config.isolate_store_path_by_identity = true;
+        assert!(config.isolate_store_path(&identity));
+        assert!(!config.isolate_store_path(&identity));
+        let root = PathBuf::from("/tmp/store").join("DefaultCluster_broker-a_1");
+        assert_eq!(config.store_path_root_dir.as_str(), root.to_string_lossy());
+        assert_eq!(
+            config.get_store_path_consume_queue(),
+            root.join("consumequeue").to_string_lossy()
+        );
+        assert_eq!(
+            config.get_abort_file(),
+            root.join("abort").to_string_lossy()
+        );
+    }
+}
@@ -15,10 +15,15 @@
  * limitations under the License.
  */
 
-const MIN_EXT_UNIT_SIZE: i16 = 2  // size, 32k max
+use bytes::Buf;
+use bytes::BufMut;
+use bytes::Bytes;
+use bytes::BytesMut;
+
+pub(crate) const MIN_EXT_UNIT_SIZE: i16 = 2  // size, 32k max
  + 8 * 2 // msg time + tagCode
   + 2; // bitMapSize
-const MAX_EXT_UNIT_SIZE: i16 = i16::MAX;
+pub(crate) const MAX_EXT_UNIT_SIZE: i16 = i16::MAX;
 
 #[derive(Clone, Default)]
 pub struct CqExtUnit {
@@ -31,12 +36,10 @@ pub struct CqExtUnit {
 
 impl CqExtUnit {
     pub fn new(tags_code: i64, msg_store_time: i64, filter_bit_map: Option<Vec<u8>>) -> Self {
-        let bit_map_size = if let Some(val) = filter_bit_map.as_ref() {
-            val.len() as i16
-        } else {
-            0
-        };
-        let size = MIN_EXT_UNIT_SIZE + bit_map_size;
+        let bit_map_size = filter_bit_map
+            .as_ref()
+            .map_or(0, |bit_map| bit_map.len().min(i16::MAX as usize) as i16);
+        let size = MIN_EXT_UNIT_SIZE.saturating_add(bit_map_size);
         Self {
             size,
             tags_code,
@@ -62,3 +65,57 @@ impl CqExtUnit {
         &self.filter_bit_map
     }
 }
+
+impl CqExtUnit {
+    /// Full size of the unit once written, `None` when it does not fit the `i16` size field.
+    pub fn calc_unit_size(&self) -> Option<i16> {
+        let bit_map_len = self.filter_bit_map.as_ref().map_or(0, Vec::len);
+        i16::try_from(MIN_EXT_UNIT_SIZE as usize + bit_map_len).ok()
+    }
+
+    /// size(2) | tagsCode(8) | msgStoreTime(8) | bitMapSize(2) | bitMap
+    pub fn encode(&self) -> Bytes {
+        let mut buffer = BytesMut::with_capacity(self.size.max(MIN_EXT_UNIT_SIZE) as usize);
+        buffer.put_i16(self.size);
+        buffer.put_i64(self.tags_code);
+        buffer.put_i64(self.msg_store_time);
+        buffer.put_i16(self.bit_map_size);
+        if let Some(bit_map) = self.filter_bit_map.as_ref() {
+            buffer.put_slice(&bit_map[..self.bit_map_size as usize]);
+        }
+        buffer.freeze()
+    }
+
+    /// Reads a unit from the start of `buffer`, `None` on the end-of-file marker or a unit that
+    /// was never written.
+    pub fn decode(buffer: &mut Bytes) -> Option<Self> {
+        if buffer.remaining() < MIN_EXT_UNIT_SIZE as usize {
+            return None;
+        }
+        let size = buffer.get_i16();
+        if size < MIN_EXT_UNIT_SIZE {
+            return None;
+        }
+        let tags_code = buffer.get_i64();
+        let msg_store_time = buffer.get_i64();
+        let bit_map_size = buffer.get_i16();
+        if bit_map_size < 0 || MIN_EXT_UNIT_SIZE as i32 + bit_map_size as i32 != size as i32 {
+            return None;
+        }
+        let filter_bit_map = if bit_map_size > 0 {
+            if buffer.remaining() < bit_map_size as usize {
+                return None;
+            }
+            Some(buffer.split_to(bit_map_size as usize).to_vec())
+        } else {
+            None
+        };
+        Some(Self {
+            size,
+            tags_code,
+            msg_store_time,
+            bit_map_size,
+            filter_bit_map,
+        })
+    }
+}
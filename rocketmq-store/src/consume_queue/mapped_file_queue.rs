@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 
+use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
@@ -22,10 +23,13 @@ use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
+use bytes::Buf;
 use cheetah_string::CheetahString;
 use log::warn;
 use parking_lot::RwLock;
+use rocketmq_common::common::mix_all::MULTI_PATH_SPLITTER;
 use rocketmq_common::UtilAll::offset_to_file_name;
+use tracing::error;
 use tracing::info;
 
 use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
@@ -34,6 +38,8 @@ use crate::services::allocate_mapped_file_service::AllocateMappedFileService;
 
 #[derive(Default, Clone)]
 pub struct MappedFileQueue {
+    /// One directory, or several separated by [`MULTI_PATH_SPLITTER`] to spread the files over
+    /// multiple disks.
     pub(crate) store_path: String,
 
     pub(crate) mapped_file_size: u64,
@@ -50,6 +56,9 @@ pub struct MappedFileQueue {
     pub(crate) committed_where: Arc<AtomicU64>,
 
     pub(crate) store_timestamp: Arc<AtomicU64>,
+
+    /// Store paths whose disk is over the usage limit, new files are not placed there.
+    pub(crate) full_store_paths: Arc<RwLock<HashSet<String>>>,
 }
 
 impl MappedFileQueue {
@@ -66,22 +75,33 @@ impl MappedFileQueue {
             flushed_where: Arc::new(AtomicU64::new(0)),
             committed_where: Arc::new(AtomicU64::new(0)),
             store_timestamp: Arc::new(AtomicU64::new(0)),
+            full_store_paths: Arc::new(RwLock::new(HashSet::new())),
         }
     }
 }
 
 impl MappedFileQueue {
+    /// The directories the files are stored in.
+    pub fn store_paths(&self) -> Vec<&str> {
+        split_store_path(&self.store_path)
+    }
+
+    pub fn set_full_store_paths(&self, full_store_paths: HashSet<String>) {
+        *self.full_store_paths.write() = full_store_paths;
+    }
+
     pub fn load(&mut self) -> bool {
-        //list dir files
-        let dir = Path::new(&self.store_path);
-        if let Ok(ls) = fs::read_dir(dir) {
-            let files: Vec<_> = ls
-                .filter_map(Result::ok)
-                .map(|entry| entry.path())
-                .collect();
-            return self.do_load(files);
+        //list dir files, files are ordered by offset whichever path holds them
+        let mut files = Vec::new();
+        for store_path in self.store_paths() {
+            if let Ok(ls) = fs::read_dir(Path::new(store_path)) {
+                files.extend(ls.filter_map(Result::ok).map(|entry| entry.path()));
+            }
         }
-        true
+        if files.is_empty() {
+            return true;
+        }
+        self.do_load(files)
     }
 
     pub fn commit(&self, commit_least_pages: i32) -> bool {
@@ -102,8 +122,19 @@ impl MappedFileQueue {
         self.committed_where.load(Ordering::Acquire) as i64
     }
 
+    /// Logs adjacent files whose start offsets are not one file size apart.
     pub fn check_self(&self) {
-        println!("mapped_file_queue check self unimplemented")
+        for pair in self.mapped_files.read().windows(2) {
+            let (pre, cur) = (&pair[0], &pair[1]);
+            if pre.get_file_from_offset() + self.mapped_file_size != cur.get_file_from_offset() {
+                error!(
+                    "[BUG]The mappedFile queue's data is damaged, the adjacent mappedFile's \
+                     offset don't match. pre file {}, cur file {}",
+                    pre.get_file_name(),
+                    cur.get_file_name()
+                );
+            }
+        }
     }
 
     pub fn do_load(&mut self, files: Vec<std::path::PathBuf>) -> bool {
@@ -199,13 +230,36 @@ impl MappedFileQueue {
     }
 
     pub fn try_create_mapped_file(&mut self, create_offset: u64) -> Option<Arc<DefaultMappedFile>> {
-        let next_file_path =
-            PathBuf::from(self.store_path.clone()).join(offset_to_file_name(create_offset));
-        let next_next_file_path = PathBuf::from(self.store_path.clone())
-            .join(offset_to_file_name(create_offset + self.mapped_file_size));
+        let next_file_path = self
+            .choose_store_path(create_offset)
+            .join(offset_to_file_name(create_offset));
+        let next_next_offset = create_offset + self.mapped_file_size;
+        let next_next_file_path = self
+            .choose_store_path(next_next_offset)
+            .join(offset_to_file_name(next_next_offset));
         self.do_create_mapped_file(next_file_path, next_next_file_path)
     }
 
+    /// Spreads the files round-robin over the store paths that are not full, or over all of them
+    /// when every disk is full.
+    fn choose_store_path(&self, create_offset: u64) -> PathBuf {
+        let store_paths = self.store_paths();
+        if store_paths.len() <= 1 {
+            return PathBuf::from(&self.store_path);
+        }
+        let full_store_paths = self.full_store_paths.read();
+        let mut candidates: Vec<&str> = store_paths
+            .iter()
+            .copied()
+            .filter(|path| !full_store_paths.contains(*path))
+            .collect();
+        if candidates.is_empty() {
+            candidates = store_paths;
+        }
+        let index = (create_offset / self.mapped_file_size.max(1)) as usize % candidates.len();
+        PathBuf::from(candidates[index])
+    }
+
     fn do_create_mapped_file(
         &mut self,
         next_file_path: PathBuf,
@@ -264,6 +318,11 @@ impl MappedFileQueue {
                 }
             }
         }
+        if !will_remove_files.is_empty() {
+            self.mapped_files
+                .write()
+                .retain(|mf| !will_remove_files.contains(mf));
+        }
     }
 
     pub fn get_max_offset(&self) -> i64 {
@@ -286,24 +345,57 @@ impl MappedFileQueue {
         }
     }
 
-    pub(crate) fn delete_expired_file(&mut self, files: Vec<Arc<DefaultMappedFile>>) {
-        let mut files = files;
-        let read_guard = self.mapped_files.read();
+    /// Removes `files` from the queue, the caller destroys them.
+    pub(crate) fn delete_expired_file(&self, files: Vec<Arc<DefaultMappedFile>>) {
         if !files.is_empty() {
-            files.retain(|mf| read_guard.contains(mf));
             self.mapped_files.write().retain(|mf| !files.contains(mf));
         }
     }
 
+    /// Destroys the files, all but the last one, whose last unit points below `offset` in the
+    /// commit log, returning how many were deleted.
+    pub fn delete_expired_file_by_offset(&self, offset: i64, unit_size: i32) -> i32 {
+        let mapped_files = self.mapped_files.read().clone();
+        let last_unit_pos = self.mapped_file_size as usize - unit_size as usize;
+        let mut will_remove_files = Vec::new();
+        for mapped_file in mapped_files
+            .iter()
+            .take(mapped_files.len().saturating_sub(1))
+        {
+            let Some(mut bytes) = mapped_file.get_bytes(last_unit_pos, 8) else {
+                warn!(
+                    "this being not executed forever. {}",
+                    mapped_file.get_file_name()
+                );
+                break;
+            };
+            let max_offset_in_logic_queue = bytes.get_i64();
+            if max_offset_in_logic_queue >= offset || !mapped_file.destroy(1000 * 60) {
+                break;
+            }
+            info!(
+                "physic min offset {}, logics in current mapped file max offset {}, delete it",
+                offset, max_offset_in_logic_queue
+            );
+            will_remove_files.push(mapped_file.clone());
+        }
+        let deleted = will_remove_files.len() as i32;
+        self.delete_expired_file(will_remove_files);
+        deleted
+    }
+
     pub fn destroy(&mut self) {
         for mapped_file in self.mapped_files.read().iter() {
             mapped_file.destroy(1000 * 3);
         }
         self.mapped_files.write().clear();
         self.set_flushed_where(0);
-        let path = PathBuf::from(&self.store_path);
-        if path.is_dir() {
-            let _ = fs::remove_dir_all(path);
+        for store_path in self.store_paths() {
+            let path = PathBuf::from(store_path);
+            // files still held are deleted once released, the directory only goes when empty
+            if path.is_dir() {
+                let _ = fs::remove_dir(path);
+            }
         }
     }
 
@@ -403,6 +495,15 @@ impl MappedFileQueue {
     }
 }
 
+/// Splits a store path made of several directories separated by [`MULTI_PATH_SPLITTER`].
+pub fn split_store_path(store_path: &str) -> Vec<&str> {
+    store_path
+        .split(MULTI_PATH_SPLITTER.as_str())
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -475,4 +576,105 @@ mod tests {
         assert!(queue.load());
         assert_eq!(queue.mapped_files.read().len(), 1);
     }
+
+    fn rollover(queue: &mut MappedFileQueue, files: usize) {
+        for _ in 0..files {
+            let mapped_file = queue
+                .get_last_mapped_file_mut_start_offset(0, true)
+                .unwrap();
+            mapped_file.set_wrote_position(queue.mapped_file_size as i32);
+            mapped_file.set_flushed_position(queue.mapped_file_size as i32);
+            mapped_file.set_committed_position(queue.mapped_file_size as i32);
+        }
+    }
+
+    fn parent_of(mapped_file: &DefaultMappedFile) -> PathBuf {
+        PathBuf::from(mapped_file.get_file_name().as_str())
+            .parent()
+            .unwrap()
+            .to_path_buf()
+    }
+
+    #[test]
+    fn files_are_spread_over_multiple_store_paths_and_recovered_in_offset_order() {
+        let disk_a = tempfile::tempdir().unwrap();
+        let disk_b = tempfile::tempdir().unwrap();
+        let store_path = format!("{},{}", disk_a.path().display(), disk_b.path().display());
+        let mut queue = MappedFileQueue::new(store_path.clone(), 1024, None);
+        rollover(&mut queue, 4);
+
+        let files = queue.get_mapped_files();
+        let parents: Vec<PathBuf> = files.read().iter().map(|f| parent_of(f)).collect();
+        assert_eq!(
+            parents,
+            vec![
+                disk_a.path().to_path_buf(),
+                disk_b.path().to_path_buf(),
+                disk_a.path().to_path_buf(),
+                disk_b.path().to_path_buf(),
+            ]
+        );
+        drop(files);
+        drop(queue);
+
+        let mut recovered = MappedFileQueue::new(store_path, 1024, None);
+        assert!(recovered.load());
+        let offsets: Vec<u64> = recovered
+            .get_mapped_files()
+            .read()
+            .iter()
+            .map(|f| f.get_file_from_offset())
+            .collect();
+        assert_eq!(offsets, vec![0, 1024, 2048, 3072]);
+        assert_eq!(recovered.get_max_offset(), 4096);
+    }
+
+    #[test]
+    fn full_store_paths_are_skipped_for_new_files() {
+        let disk_a = tempfile::tempdir().unwrap();
+        let disk_b = tempfile::tempdir().unwrap();
+        let store_path = format!("{},{}", disk_a.path().display(), disk_b.path().display());
+        let mut queue = MappedFileQueue::new(store_path, 1024, None);
+        queue.set_full_store_paths(HashSet::from([disk_a.path().to_string_lossy().to_string()]));
+        rollover(&mut queue, 3);
+        assert!(queue
+            .get_mapped_files()
+            .read()
+            .iter()
+            .all(|f| parent_of(f) == disk_b.path()));
+
+        // with every disk full the files are spread over all of them again
+        queue.set_full_store_paths(
+            queue
+                .store_paths()
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+        );
+        rollover(&mut queue, 2);
+        let last = queue.get_last_mapped_file().unwrap();
+        assert_eq!(last.get_file_from_offset(), 4096);
+        assert_eq!(parent_of(&last), disk_a.path());
+    }
+
+    #[test]
+    fn truncated_file_is_deleted_once_its_reader_releases_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut queue = MappedFileQueue::new(dir.path().to_string_lossy().to_string(), 1024, None);
+        rollover(&mut queue, 2);
+        let last = queue.get_last_mapped_file().unwrap();
+        let file_name = PathBuf::from(last.get_file_name().as_str());
+        let reader = last.clone().select_mapped_buffer_size(0, 8).unwrap();
+
+        queue.truncate_dirty_files(1000);
+        assert_eq!(queue.get_mapped_files().read().len(), 1);
+        assert!(!last.is_available());
+        // still mapped for the reader
+        assert!(file_name.exists());
+        assert_eq!(reader.get_buffer().unwrap().len(), 8);
+
+        drop(reader);
+        assert!(last.is_cleanup_over());
+        assert!(!file_name.exists());
+    }
 }
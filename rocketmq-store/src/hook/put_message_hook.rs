@@ -14,7 +14,7 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
 
 use crate::base::message_result::PutMessageResult;
 
@@ -28,12 +28,15 @@ pub trait PutMessageHook {
     ///
     /// # Arguments
     ///
-    /// * `msg` - The message to be put
+    /// * `msg` - The message to be put, which the hook may rewrite
     ///
     /// # Returns
     ///
-    /// The result of putting the message
-    fn execute_before_put_message(&self, msg: &MessageExt) -> Option<PutMessageResult>;
+    /// `Some` to reject the message with the given result, `None` to let it through
+    fn execute_before_put_message(
+        &self,
+        msg: &mut MessageExtBrokerInner,
+    ) -> Option<PutMessageResult>;
 }
 
 /// Alias for `Arc<dyn PutMessageHook>`.
@@ -18,7 +18,6 @@
 use std::sync::Arc;
 
 use bytes::Buf;
-use bytes::Bytes;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::hasher::string_hasher::JavaStringHasher;
 
@@ -138,11 +137,7 @@ impl IndexFile {
             let slot_pos = key_hash as usize % self.hash_slot_num;
             let abs_slot_pos = INDEX_HEADER_SIZE + slot_pos * HASH_SLOT_SIZE;
 
-            let mut buffer = self
-                .mapped_file
-                .get_data(abs_slot_pos, abs_slot_pos + 4)
-                .unwrap();
-            let mut slot_value = buffer.get_i32();
+            let mut slot_value = self.read_i32(abs_slot_pos);
             if slot_value <= INVALID_INDEX || slot_value > self.index_header.get_index_count() {
                 slot_value = INVALID_INDEX;
             }
@@ -157,37 +152,21 @@ impl IndexFile {
                 time_diff = 0;
             }
 
+            let index_count = self.index_header.get_index_count();
             let abs_index_pos = INDEX_HEADER_SIZE
                 + self.hash_slot_num * HASH_SLOT_SIZE
-                + self.index_header.get_index_count() as usize * INDEX_SIZE;
-
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&key_hash.to_be_bytes()),
-                abs_index_pos,
-                4,
-            );
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&phy_offset.to_be_bytes()),
-                abs_index_pos,
-                8,
-            );
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&(time_diff as i32).to_be_bytes()),
-                abs_index_pos,
-                4,
-            );
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&slot_value.to_be_bytes()),
-                abs_index_pos,
-                4,
-            );
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&self.index_header.get_index_count().to_be_bytes()),
-                abs_slot_pos,
-                4,
-            );
-
-            if self.index_header.get_index_count() <= 1 {
+                + index_count as usize * INDEX_SIZE;
+
+            let mut index = [0u8; INDEX_SIZE];
+            index[0..4].copy_from_slice(&key_hash.to_be_bytes());
+            index[4..12].copy_from_slice(&phy_offset.to_be_bytes());
+            index[12..16].copy_from_slice(&(time_diff as i32).to_be_bytes());
+            index[16..20].copy_from_slice(&slot_value.to_be_bytes());
+            self.mapped_file.put_slice(&index, abs_index_pos);
+            self.mapped_file
+                .put_slice(&index_count.to_be_bytes(), abs_slot_pos);
+
+            if index_count <= 1 {
                 self.index_header.set_begin_phy_offset(phy_offset);
                 self.index_header.set_begin_timestamp(store_timestamp);
             }
@@ -210,9 +189,15 @@ impl IndexFile {
         }
     }
 
+    fn read_i32(&self, pos: usize) -> i32 {
+        self.mapped_file
+            .get_bytes(pos, 4)
+            .map_or(INVALID_INDEX, |mut bytes| bytes.get_i32())
+    }
+
     pub fn index_key_hash_method(&self, key: &str) -> i32 {
         let key_hash = JavaStringHasher::new().hash_str(key);
-        let key_hash_positive = key_hash.abs();
+        let key_hash_positive = key_hash.wrapping_abs();
         if key_hash_positive < 0 {
             0
         } else {
@@ -256,53 +241,98 @@ impl IndexFile {
         let slot_pos = key_hash as usize % self.hash_slot_num;
         let abs_slot_pos = INDEX_HEADER_SIZE + slot_pos * HASH_SLOT_SIZE;
 
-        let mut buffer = self
-            .mapped_file
-            .get_data(abs_slot_pos, abs_slot_pos + 4)
-            .unwrap();
-        let slot_value = buffer.get_i32();
-        if slot_value <= INVALID_INDEX
-            || slot_value > self.index_header.get_index_count()
-            || self.index_header.get_index_count() <= 1
-        {
-            return;
+        let slot_value = self.read_i32(abs_slot_pos);
+        let index_count = self.index_header.get_index_count();
+        if slot_value > INVALID_INDEX && slot_value <= index_count && index_count > 1 {
+            let mut next_index_to_read = slot_value;
+            while phy_offsets.len() < max_num {
+                let abs_index_pos = INDEX_HEADER_SIZE
+                    + self.hash_slot_num * HASH_SLOT_SIZE
+                    + next_index_to_read as usize * INDEX_SIZE;
+                let Some(mut index) = self.mapped_file.get_bytes(abs_index_pos, INDEX_SIZE) else {
+                    break;
+                };
+                let key_hash_read = index.get_i32();
+                let phy_offset_read = index.get_i64();
+                let time_diff = index.get_i32();
+                let prev_index_read = index.get_i32();
+
+                if time_diff < 0 {
+                    break;
+                }
+
+                let time_read = self.index_header.get_begin_timestamp() + time_diff as i64 * 1000;
+                if key_hash == key_hash_read && (time_read >= begin && time_read <= end) {
+                    phy_offsets.push(phy_offset_read);
+                }
+
+                if prev_index_read <= INVALID_INDEX
+                    || prev_index_read > index_count
+                    || prev_index_read == next_index_to_read
+                    || time_read < begin
+                {
+                    break;
+                }
+
+                next_index_to_read = prev_index_read;
+            }
         }
+        self.mapped_file.release();
+    }
 
-        let mut next_index_to_read = slot_value;
-        while phy_offsets.len() < max_num {
-            let abs_index_pos = INDEX_HEADER_SIZE
-                + self.hash_slot_num * HASH_SLOT_SIZE
-                + next_index_to_read as usize * INDEX_SIZE;
-
-            let key_hash_read = buffer.slice(abs_index_pos..abs_index_pos + 4).get_i32();
-            let phy_offset_read = buffer
-                .slice(abs_index_pos + 4..abs_index_pos + 12)
-                .get_i64();
-            let time_diff = buffer
-                .slice(abs_index_pos + 12..abs_index_pos + 16)
-                .get_i32();
-            let prev_index_read = buffer
-                .slice(abs_index_pos + 16..abs_index_pos + 20)
-                .get_i32();
-
-            if time_diff < 0 {
+    /// Drops the entries of messages at or past `phy_offset`, they are the newest ones as
+    /// entries are appended in commit log order. Every hash slot they head is pointed back at the
+    /// older entry it chained to. Returns the number of entries left.
+    pub fn truncate(&self, phy_offset: i64) -> i32 {
+        let index_count = self.index_header.get_index_count();
+        let abs_index_begin = INDEX_HEADER_SIZE + self.hash_slot_num * HASH_SLOT_SIZE;
+        let mut kept = index_count;
+        let mut hash_slot_count = self.index_header.get_hash_slot_count();
+        while kept > 1 {
+            let abs_index_pos = abs_index_begin + (kept - 1) as usize * INDEX_SIZE;
+            let Some(mut index) = self.mapped_file.get_bytes(abs_index_pos, INDEX_SIZE) else {
+                break;
+            };
+            let key_hash = index.get_i32();
+            if index.get_i64() < phy_offset {
                 break;
             }
-
-            let time_read = self.index_header.get_begin_timestamp() + time_diff as i64 * 1000;
-            if key_hash == key_hash_read && (time_read >= begin && time_read <= end) {
-                phy_offsets.push(phy_offset_read);
+            index.advance(4);
+            let mut prev_index = index.get_i32();
+            if prev_index <= INVALID_INDEX || prev_index >= kept {
+                prev_index = INVALID_INDEX;
+                hash_slot_count -= 1;
             }
+            let abs_slot_pos =
+                INDEX_HEADER_SIZE + key_hash as usize % self.hash_slot_num * HASH_SLOT_SIZE;
+            self.mapped_file
+                .put_slice(&prev_index.to_be_bytes(), abs_slot_pos);
+            kept -= 1;
+        }
+        if kept == index_count {
+            return kept - 1;
+        }
 
-            if prev_index_read <= INVALID_INDEX
-                || prev_index_read > self.index_header.get_index_count()
-                || prev_index_read == next_index_to_read
-                || time_read < begin
-            {
-                break;
+        self.index_header.set_index_count(kept);
+        self.index_header
+            .set_hash_slot_count(hash_slot_count.max(0));
+        if kept > 1 {
+            let abs_index_pos = abs_index_begin + (kept - 1) as usize * INDEX_SIZE;
+            if let Some(mut index) = self.mapped_file.get_bytes(abs_index_pos, INDEX_SIZE) {
+                index.advance(4);
+                let end_phy_offset = index.get_i64();
+                let time_diff = index.get_i32();
+                self.index_header.set_end_phy_offset(end_phy_offset);
+                self.index_header.set_end_timestamp(
+                    self.index_header.get_begin_timestamp() + time_diff as i64 * 1000,
+                );
             }
-
-            next_index_to_read = prev_index_read;
+        } else {
+            self.index_header
+                .set_end_phy_offset(self.index_header.get_begin_phy_offset().min(phy_offset));
+            self.index_header
+                .set_end_timestamp(self.index_header.get_begin_timestamp());
         }
+        kept - 1
     }
 }
@@ -35,7 +35,6 @@ use crate::base::store_checkpoint::StoreCheckpoint;
 use crate::config::message_store_config::MessageStoreConfig;
 use crate::index::index_file::IndexFile;
 use crate::index::query_offset_result::QueryOffsetResult;
-use crate::store_path_config_helper::get_store_path_index;
 
 const MAX_TRY_IDX_CREATE: i32 = 3;
 
@@ -57,7 +56,7 @@ impl IndexService {
         Self {
             hash_slot_num: message_store_config.max_hash_slot_num,
             index_num: message_store_config.max_index_num,
-            store_path: get_store_path_index(message_store_config.store_path_root_dir.as_str()),
+            store_path: message_store_config.get_store_path_index(),
             index_file_list: Arc::new(Default::default()),
             message_store_config,
             store_checkpoint,
@@ -118,8 +117,45 @@ impl IndexService {
                 break;
             }
         }
-        if !files.is_empty() {
-            index_file_list_lock.retain(|index_file| !files.contains(index_file));
+        let mut deleted = Vec::new();
+        for index_file in files {
+            if !index_file.destroy(3000) {
+                error!(
+                    "delete expired index file {} failed, it is still in use",
+                    index_file.get_file_name()
+                );
+                break;
+            }
+            deleted.push(index_file);
+        }
+        if !deleted.is_empty() {
+            index_file_list_lock.retain(|index_file| !deleted.contains(index_file));
+        }
+    }
+
+    /// Removes the index entries of messages at or past `phy_offset`, deleting the files left
+    /// empty, so that the truncated range is indexed again once it is dispatched.
+    pub fn truncate_dirty(&self, phy_offset: i64) {
+        let mut index_file_list_lock = self.index_file_list.write();
+        while let Some(index_file) = index_file_list_lock.last() {
+            if index_file.get_end_phy_offset() < phy_offset {
+                break;
+            }
+            if index_file.truncate(phy_offset) > 0 {
+                info!(
+                    "truncate index file {} to phy offset {}",
+                    index_file.get_file_name(),
+                    phy_offset
+                );
+                break;
+            }
+            info!(
+                "delete index file {}, all its entries are past phy offset {}",
+                index_file.get_file_name(),
+                phy_offset
+            );
+            index_file.destroy(0);
+            index_file_list_lock.pop();
         }
     }
 
@@ -145,36 +181,29 @@ impl IndexService {
         let max_num = max_num.min(self.message_store_config.max_msgs_num_batch as i32);
 
         let index_file_list = self.index_file_list.read();
-        let mut index = 0;
-        if !index_file_list.is_empty() {
-            for f in index_file_list.iter() {
-                let last_file = index == index_file_list.len() - 1;
-                if last_file {
-                    // Assuming IndexFile has methods to get end timestamp and phy offset
-                    index_last_update_timestamp = f.get_end_timestamp();
-                    index_last_update_phyoffset = f.get_end_phy_offset();
-                }
+        // newest file first, so the last update of the index is reported by the latest file
+        for (index, f) in index_file_list.iter().rev().enumerate() {
+            if index == 0 {
+                index_last_update_timestamp = f.get_end_timestamp();
+                index_last_update_phyoffset = f.get_end_phy_offset();
+            }
 
-                // Assuming IndexFile has a method to check if the file's timestamps match the query
-                if f.is_time_matched(begin, end) {
-                    // Assuming IndexFile has a method to select physical offsets based on the query
-                    f.select_phy_offset(
-                        &mut phy_offsets,
-                        build_key(topic, key).as_str(),
-                        max_num as usize,
-                        begin,
-                        end,
-                    );
-                }
+            if f.is_time_matched(begin, end) {
+                f.select_phy_offset(
+                    &mut phy_offsets,
+                    build_key(topic, key).as_str(),
+                    max_num as usize,
+                    begin,
+                    end,
+                );
+            }
 
-                if f.get_begin_timestamp() < begin {
-                    break;
-                }
+            if f.get_begin_timestamp() < begin {
+                break;
+            }
 
-                if phy_offsets.len() as i32 >= max_num {
-                    break;
-                }
-                index += 1;
+            if phy_offsets.len() as i32 >= max_num {
+                break;
             }
         }
         QueryOffsetResult::new(
@@ -204,39 +233,37 @@ impl IndexService {
                     _ => (),
                 }
 
-                let mut index_file_new = None;
+                let mut index_file = index_file_inner;
                 if let Some(ref uniq_key) = dispatch_request.uniq_key {
-                    index_file_new = self.put_key(
-                        index_file_inner,
+                    match self.put_key(
+                        index_file,
                         dispatch_request,
                         build_key(topic, uniq_key.as_str()).as_str(),
-                    );
-                    if index_file_new.is_none() {
-                        error!(
-                            "putKey error commitlog {} uniqkey {}",
-                            dispatch_request.commit_log_offset, uniq_key
-                        );
-                        return;
+                    ) {
+                        Some(file) => index_file = file,
+                        None => {
+                            error!(
+                                "putKey error commitlog {} uniqkey {}",
+                                dispatch_request.commit_log_offset, uniq_key
+                            );
+                            return;
+                        }
                     }
                 }
 
-                if !keys.is_empty() {
-                    let keyset = keys.split(MessageConst::KEY_SEPARATOR);
-                    for key in keyset {
-                        if !key.is_empty() {
-                            let index_file = self.put_key(
-                                index_file_new.take().unwrap(),
-                                dispatch_request,
-                                build_key(topic, key).as_str(),
+                for key in keys.split(MessageConst::KEY_SEPARATOR) {
+                    if key.is_empty() {
+                        continue;
+                    }
+                    match self.put_key(index_file, dispatch_request, build_key(topic, key).as_str())
+                    {
+                        Some(file) => index_file = file,
+                        None => {
+                            error!(
+                                "putKey error commitlog {} key {}",
+                                dispatch_request.commit_log_offset, key
                             );
-                            if index_file.is_none() {
-                                error!(
-                                    "putKey error commitlog {} uniqkey {}",
-                                    dispatch_request.commit_log_offset,
-                                    dispatch_request.uniq_key.as_ref().unwrap()
-                                );
-                                return;
-                            }
+                            return;
                         }
                     }
                 }
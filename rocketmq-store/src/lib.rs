@@ -26,12 +26,16 @@ pub mod hook;
 mod index;
 mod kv;
 pub mod log_file;
-pub(crate) mod message_encoder;
+pub mod message_encoder;
 pub mod message_store;
-mod queue;
+pub mod metrics;
+pub mod plugin;
+pub mod queue;
 pub(crate) mod services;
 pub mod stats;
 pub mod store;
 pub mod store_path_config_helper;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
 pub mod timer;
 pub mod utils;
@@ -30,6 +30,7 @@ use crate::base::get_message_result::GetMessageResult;
 use crate::base::message_result::PutMessageResult;
 use crate::base::query_message_result::QueryMessageResult;
 use crate::base::select_result::SelectMappedBufferResult;
+use crate::config::message_store_config::MessageStoreConfig;
 use crate::filter::MessageFilter;
 use crate::hook::put_message_hook::BoxedPutMessageHook;
 use crate::queue::ArcConsumeQueue;
@@ -77,6 +78,13 @@ pub trait RocketMQMessageStore: Sync + 'static {
     /// The maximum physical offset.
     fn get_max_phy_offset(&self) -> i64;
 
+    /// Get the minimum physical offset.
+    ///
+    /// # Returns
+    ///
+    /// The minimum physical offset.
+    fn get_min_phy_offset(&self) -> i64;
+
     /// Set the broker initial maximum offset.
     ///
     /// # Arguments
@@ -143,6 +151,13 @@ pub trait RocketMQMessageStore: Sync + 'static {
         false
     }
 
+    /// Get the configuration of the message store.
+    ///
+    /// # Returns
+    ///
+    /// A reference to the message store configuration.
+    fn get_message_store_config(&self) -> &MessageStoreConfig;
+
     /// Get the running flags of the message store.
     ///
     /// # Returns
@@ -205,6 +220,25 @@ pub trait RocketMQMessageStore: Sync + 'static {
     /// The maximum offset in the queue.
     fn get_max_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64;
 
+    /// Look up the consume queue offset of the first message stored at or after the given
+    /// timestamp.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic name.
+    /// * `queue_id` - The queue identifier.
+    /// * `timestamp` - The store timestamp in milliseconds.
+    ///
+    /// # Returns
+    ///
+    /// The matching offset, clamped to the queue's min/max offsets.
+    fn get_offset_in_queue_by_time(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        timestamp: i64,
+    ) -> i64;
+
     /// Get the maximum committed offset in the queue.
     ///
     /// # Arguments
@@ -269,6 +303,69 @@ pub trait RocketMQMessageStore: Sync + 'static {
         batch_size: i32,
     ) -> bool;
 
+    /// Check if the message at a consume offset lies in the cold area of the commit log, i.e.
+    /// further behind the max physical offset than the page cache is expected to hold
+    /// (`access_message_in_memory_max_ratio` of the physical memory).
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic name.
+    /// * `queue_id` - The queue identifier.
+    /// * `consume_offset` - The consume offset.
+    ///
+    /// # Returns
+    ///
+    /// `true` if reading the message likely hits the disk; `false` otherwise, including when
+    /// the offset has no message.
+    fn check_in_cold_area_by_consume_offset(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_offset: i64,
+    ) -> bool;
+
+    /// Get the commit log offset of the message at a consume offset.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic name.
+    /// * `queue_id` - The queue identifier.
+    /// * `consume_queue_offset` - The consume offset.
+    ///
+    /// # Returns
+    ///
+    /// The physical offset of the message, `0` if the offset has no message.
+    fn get_commit_log_offset_in_queue(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_queue_offset: i64,
+    ) -> i64;
+
+    /// Estimate how many messages of a consume queue between `from` (inclusive) and `to`
+    /// (exclusive) match `filter`, scanning at most `max_consume_queue_scan` entries and
+    /// extrapolating from them when the range is larger.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic name.
+    /// * `queue_id` - The queue identifier.
+    /// * `from` - The first queue offset of the range.
+    /// * `to` - The queue offset the range ends before.
+    /// * `filter` - The filter messages are matched with.
+    ///
+    /// # Returns
+    ///
+    /// The estimated number of matching messages, `0` if the queue does not exist.
+    fn estimate_message_count(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        from: i64,
+        to: i64,
+        filter: &dyn MessageFilter,
+    ) -> i64;
+
     /// Notify that a message has arrived if necessary.
     ///
     /// # Arguments
@@ -418,6 +515,18 @@ pub trait RocketMQMessageStore: Sync + 'static {
     /// * `i64` - Timestamp of the earliest message in this store.
     fn get_earliest_message_time(&self) -> i64;
 
+    /// Get the store time of the earliest message in the given queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic of the message.
+    /// * `queue_id` - The queue identifier.
+    ///
+    /// # Returns
+    ///
+    /// * `i64` - Timestamp of the earliest message in the queue, or -1 if there is none.
+    fn get_earliest_message_time_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64;
+
     /// Get the store time of the earliest message in this store.
     fn get_timer_message_store(&self) -> Arc<TimerMessageStore>;
 
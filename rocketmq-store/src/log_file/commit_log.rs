@@ -18,6 +18,7 @@
 
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::mem;
 use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
@@ -30,9 +31,11 @@ use rocketmq_common::common::attribute::cq_type::CQType;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
 use rocketmq_common::common::config::TopicConfig;
 use rocketmq_common::common::message::message_batch::MessageExtBatch;
+use rocketmq_common::common::message::message_decoder;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
 use rocketmq_common::common::message::message_single::tags_string2tags_code;
 use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::common::message::MessageVersion;
 use rocketmq_common::common::mix_all;
 use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
@@ -41,6 +44,7 @@ use rocketmq_common::utils::queue_type_utils::QueueTypeUtils;
 use rocketmq_common::utils::time_utils;
 use rocketmq_common::CRC32Utils::crc32;
 use rocketmq_common::MessageDecoder::string_to_message_properties;
+use rocketmq_common::MessageDecoder::BORN_TIMESTAMP_POSITION;
 use rocketmq_common::MessageDecoder::MESSAGE_MAGIC_CODE_POSITION;
 use rocketmq_common::MessageDecoder::MESSAGE_MAGIC_CODE_V2;
 use rocketmq_common::MessageDecoder::SYSFLAG_POSITION;
@@ -61,6 +65,7 @@ use crate::base::message_result::PutMessageResult;
 use crate::base::message_status_enum::AppendMessageStatus;
 use crate::base::message_status_enum::PutMessageStatus;
 use crate::base::put_message_context::PutMessageContext;
+use crate::base::put_message_lock::PutMessageLock;
 use crate::base::select_result::SelectMappedBufferResult;
 use crate::base::store_checkpoint::StoreCheckpoint;
 use crate::base::swappable::Swappable;
@@ -77,6 +82,7 @@ use crate::message_store::default_message_store::CommitLogDispatcherDefault;
 use crate::message_store::default_message_store::DefaultMessageStore;
 use crate::queue::local_file_consume_queue_store::ConsumeQueueStore;
 use crate::queue::ConsumeQueueStoreTrait;
+use crate::utils::multi_dispatch_utils;
 
 // Message's MAGIC CODE daa320a7
 pub const MESSAGE_MAGIC_CODE: i32 = -626843481;
@@ -192,7 +198,7 @@ pub struct CommitLog {
     confirm_offset: i64,
     store_checkpoint: Arc<StoreCheckpoint>,
     append_message_callback: Arc<DefaultAppendMessageCallback>,
-    put_message_lock: Arc<tokio::sync::Mutex<()>>,
+    put_message_lock: Arc<PutMessageLock>,
     topic_queue_lock: Arc<TopicQueueLock>,
     topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
     consume_queue_store: ConsumeQueueStore,
@@ -200,6 +206,7 @@ pub struct CommitLog {
     //flush_manager: Arc<parking_lot::Mutex<DefaultFlushManager>>,
     begin_time_in_lock: Arc<AtomicU64>,
     cold_data_check_service: Arc<ColdDataCheckService>,
+    recover_dispatch_count: Arc<AtomicU64>,
 }
 
 impl CommitLog {
@@ -228,7 +235,9 @@ impl CommitLog {
                 message_store_config.clone(),
                 topic_config_table.clone(),
             )),
-            put_message_lock: Arc::new(Default::default()),
+            put_message_lock: Arc::new(PutMessageLock::new(
+                message_store_config.use_reentrant_lock_when_put_message,
+            )),
             topic_queue_lock: Arc::new(TopicQueueLock::new(
                 message_store_config.topic_queue_lock_num,
             )),
@@ -241,6 +250,7 @@ impl CommitLog {
             ))),
             begin_time_in_lock: Arc::new(AtomicU64::new(0)),
             cold_data_check_service: Arc::new(Default::default()),
+            recover_dispatch_count: Arc::new(AtomicU64::new(0)),
         }
     }
 }
@@ -266,7 +276,12 @@ impl CommitLog {
         });
     }
 
-    pub fn shutdown(&mut self) {}
+    pub fn shutdown(&mut self) {
+        match self.flush_manager.try_lock() {
+            Ok(mut flush_manager) => flush_manager.shutdown(),
+            Err(_) => warn!("flush manager is busy, commit log flush services not shut down"),
+        }
+    }
 
     pub fn destroy(&mut self) {}
 
@@ -315,15 +330,21 @@ impl CommitLog {
             return PutMessageResult::new_default(PutMessageStatus::MessageIllegal);
         }
 
-        //setting ip type:IPV4 OR IPV6, default is ipv4
+        // The address flags follow the actual host families, whatever the producer claimed
         let born_host = msg_batch.message_ext_broker_inner.born_host();
         if born_host.is_ipv6() {
             msg_batch.message_ext_broker_inner.with_born_host_v6_flag();
+        } else {
+            msg_batch.message_ext_broker_inner.clear_born_host_v6_flag();
         }
 
         let store_host = msg_batch.message_ext_broker_inner.store_host();
         if store_host.is_ipv6() {
             msg_batch.message_ext_broker_inner.with_store_host_v6_flag();
+        } else {
+            msg_batch
+                .message_ext_broker_inner
+                .clear_store_host_v6_flag();
         }
 
         let mut _unlock_mapped_file = None;
@@ -511,15 +532,19 @@ impl CommitLog {
             msg.with_version(MessageVersion::V2);
         }
 
-        //setting ip type:IPV4 OR IPV6, default is ipv4
+        // The address flags follow the actual host families, whatever the producer claimed
         let born_host = msg.born_host();
         if born_host.is_ipv6() {
             msg.with_born_host_v6_flag();
+        } else {
+            msg.clear_born_host_v6_flag();
         }
 
         let store_host = msg.store_host();
         if store_host.is_ipv6() {
             msg.with_store_host_v6_flag();
+        } else {
+            msg.clear_store_host_v6_flag();
         }
 
         let topic_queue_key = generate_key(&msg);
@@ -552,14 +577,28 @@ impl CommitLog {
             self.assign_offset(&mut msg);
         }
 
-        let (put_message_result, encoded_buff) =
-            encode_message_ext(&msg, &self.message_store_config);
-        if let Some(result) = put_message_result {
-            return result;
+        // LMQ offsets are shared by every topic, so they are assigned under the put lock
+        let is_multi_dispatch_msg =
+            self.message_store_config.enable_multi_dispatch && Self::is_multi_dispatch_msg(&msg);
+        if !is_multi_dispatch_msg {
+            let (put_message_result, encoded_buff) =
+                encode_message_ext(&msg, &self.message_store_config);
+            if let Some(result) = put_message_result {
+                return result;
+            }
+            msg.encoded_buff = Some(encoded_buff);
         }
-        msg.encoded_buff = Some(encoded_buff);
         let put_message_context = PutMessageContext::new(topic_queue_key);
         let lock = self.put_message_lock.lock().await;
+        if is_multi_dispatch_msg {
+            self.assign_lmq_offset(&mut msg);
+            let (put_message_result, encoded_buff) =
+                encode_message_ext(&msg, &self.message_store_config);
+            if let Some(result) = put_message_result {
+                return result;
+            }
+            msg.encoded_buff = Some(encoded_buff);
+        }
         let begin_lock_timestamp = time_utils::get_current_millis();
         self.begin_time_in_lock
             .store(begin_lock_timestamp, std::sync::atomic::Ordering::Release);
@@ -643,6 +682,11 @@ impl CommitLog {
                 PutMessageResult::new_append_result(PutMessageStatus::UnknownError, Some(result))
             }
         };
+        if is_multi_dispatch_msg
+            && put_message_result.put_message_status() == PutMessageStatus::PutOk
+        {
+            self.increase_lmq_offset(&msg);
+        }
         let elapsed_time_in_lock = start_time.elapsed().as_millis() as u64;
         drop(lock);
         self.begin_time_in_lock
@@ -678,6 +722,44 @@ impl CommitLog {
         }
     }
 
+    fn assign_lmq_offset(&self, msg: &mut MessageExtBrokerInner) {
+        let Some(multi_dispatch_queue) = msg.property(MessageConst::PROPERTY_INNER_MULTI_DISPATCH)
+        else {
+            return;
+        };
+        let queue_offsets = multi_dispatch_queue
+            .split(mix_all::MULTI_DISPATCH_QUEUE_SPLITTER)
+            .map(|queue_name| {
+                if self.message_store_config.enable_lmq && mix_all::is_lmq(Some(queue_name)) {
+                    self.consume_queue_store
+                        .get_lmq_queue_offset(&multi_dispatch_utils::lmq_queue_key(queue_name))
+                } else {
+                    -1
+                }
+                .to_string()
+            })
+            .collect::<Vec<_>>()
+            .join(mix_all::MULTI_DISPATCH_QUEUE_SPLITTER);
+        msg.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_INNER_MULTI_QUEUE_OFFSET),
+            CheetahString::from_string(queue_offsets),
+        );
+        msg.properties_string = message_decoder::message_properties_to_string(msg.get_properties());
+    }
+
+    fn increase_lmq_offset(&self, msg: &MessageExtBrokerInner) {
+        let Some(multi_dispatch_queue) = msg.property(MessageConst::PROPERTY_INNER_MULTI_DISPATCH)
+        else {
+            return;
+        };
+        for queue_name in multi_dispatch_queue.split(mix_all::MULTI_DISPATCH_QUEUE_SPLITTER) {
+            if self.message_store_config.enable_lmq && mix_all::is_lmq(Some(queue_name)) {
+                self.consume_queue_store
+                    .increase_lmq_offset(&multi_dispatch_utils::lmq_queue_key(queue_name), 1);
+            }
+        }
+    }
+
     #[inline]
     fn assign_offset(&self, msg: &mut MessageExtBrokerInner) {
         let tran_type = MessageSysFlag::get_transaction_value(msg.sys_flag());
@@ -780,21 +862,31 @@ impl CommitLog {
 
     fn on_commit_log_dispatch(
         &mut self,
-        request: &DispatchRequest,
+        request: &mut DispatchRequest,
         do_dispatch: bool,
         is_recover: bool,
         is_file_end: bool,
     ) {
         if do_dispatch && !is_file_end {
             self.dispatcher.dispatch(request);
+            if is_recover {
+                self.recover_dispatch_count
+                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
         }
     }
 
+    /// Number of messages dispatched again while recovering from an abnormal shutdown.
+    pub fn recover_dispatch_count(&self) -> u64 {
+        self.recover_dispatch_count
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+
     pub fn is_multi_dispatch_msg(msg_inner: &MessageExtBrokerInner) -> bool {
         msg_inner
             .property(MessageConst::PROPERTY_INNER_MULTI_DISPATCH)
-            .is_some_and(|s| !s.is_empty())
-            && msg_inner
+            .is_some_and(|s| !s.trim().is_empty())
+            && !msg_inner
                 .topic()
                 .starts_with(mix_all::RETRY_GROUP_TOPIC_PREFIX)
     }
@@ -834,7 +926,7 @@ impl CommitLog {
                     break;
                 }
                 let mut msg_bytes = msg.unwrap();
-                let dispatch_request = check_message_and_return_size(
+                let mut dispatch_request = check_message_and_return_size(
                     &mut msg_bytes,
                     check_crc_on_recover,
                     check_dup_info,
@@ -845,12 +937,12 @@ impl CommitLog {
                 if dispatch_request.success && dispatch_request.msg_size > 0 {
                     last_valid_msg_phy_offset = process_offset + mapped_file_offset;
                     mapped_file_offset += dispatch_request.msg_size as u64;
-                    self.on_commit_log_dispatch(&dispatch_request, do_dispatch, true, false);
+                    self.on_commit_log_dispatch(&mut dispatch_request, do_dispatch, true, false);
                 } else if dispatch_request.success && dispatch_request.msg_size == 0 {
                     // Come the end of the file, switch to the next file Since the
                     // return 0 representatives met last hole,
                     // this can not be included in truncate offset
-                    self.on_commit_log_dispatch(&dispatch_request, do_dispatch, true, true);
+                    self.on_commit_log_dispatch(&mut dispatch_request, do_dispatch, true, true);
                     index += 1;
                     if index >= mapped_files_inner.len() {
                         info!(
@@ -988,7 +1080,7 @@ impl CommitLog {
                     break;
                 }
                 let mut msg_bytes = msg.unwrap();
-                let dispatch_request = check_message_and_return_size(
+                let mut dispatch_request = check_message_and_return_size(
                     &mut msg_bytes,
                     check_crc_on_recover,
                     check_dup_info,
@@ -1007,7 +1099,7 @@ impl CommitLog {
                             <= self.get_confirm_offset()
                         {
                             self.on_commit_log_dispatch(
-                                &dispatch_request,
+                                &mut dispatch_request,
                                 do_dispatch,
                                 true,
                                 false,
@@ -1016,13 +1108,18 @@ impl CommitLog {
                                 dispatch_request.commit_log_offset as u64 + size as u64;
                         }
                     } else {
-                        self.on_commit_log_dispatch(&dispatch_request, do_dispatch, true, false);
+                        self.on_commit_log_dispatch(
+                            &mut dispatch_request,
+                            do_dispatch,
+                            true,
+                            false,
+                        );
                     }
                 } else if dispatch_request.success && dispatch_request.msg_size == 0 {
                     // Come the end of the file, switch to the next file Since the
                     // return 0 representatives met last hole,
                     // this can not be included in truncate offset
-                    self.on_commit_log_dispatch(&dispatch_request, do_dispatch, true, true);
+                    self.on_commit_log_dispatch(&mut dispatch_request, do_dispatch, true, true);
                     index += 1;
                     if index >= mapped_files_inner.len() {
                         info!(
@@ -1094,6 +1191,31 @@ impl CommitLog {
         self.mapped_file_queue.get_max_offset()
     }
 
+    /// Cuts the files at `phy_offset`, deleting the ones past it.
+    pub fn truncate_dirty_files(&mut self, phy_offset: i64) {
+        if phy_offset <= self.mapped_file_queue.get_flushed_where() {
+            self.mapped_file_queue.set_flushed_where(phy_offset);
+        }
+        if phy_offset <= self.mapped_file_queue.get_committed_where() {
+            self.mapped_file_queue.set_committed_where(phy_offset);
+        }
+        self.mapped_file_queue.truncate_dirty_files(phy_offset);
+        if self.confirm_offset > phy_offset {
+            self.set_confirm_offset(phy_offset);
+        }
+    }
+
+    /// Held by every put, taking it keeps the commit log from growing.
+    pub(crate) fn put_message_lock(&self) -> &Arc<PutMessageLock> {
+        &self.put_message_lock
+    }
+
+    /// Store paths new commit log files must avoid because their disk is full.
+    pub fn set_full_store_paths(&self, full_store_paths: HashSet<String>) {
+        self.mapped_file_queue
+            .set_full_store_paths(full_store_paths);
+    }
+
     pub fn get_min_offset(&self) -> i64 {
         match self.mapped_file_queue.get_first_mapped_file() {
             None => -1,
@@ -1107,6 +1229,48 @@ impl CommitLog {
         }
     }
 
+    /// Appends already encoded messages at `start_offset`, as a slave does with data replicated
+    /// from its master.
+    pub async fn append_data(&mut self, start_offset: i64, data: &[u8]) -> bool {
+        let _lock = self.put_message_lock.lock().await;
+        let Some(mapped_file) = self
+            .mapped_file_queue
+            .get_last_mapped_file_mut_start_offset(start_offset as u64, true)
+        else {
+            error!("appendData getLastMappedFile error {}", start_offset);
+            return false;
+        };
+        mapped_file.append_message_bytes(&Bytes::copy_from_slice(data))
+    }
+
+    /// Reads the store timestamp of the message at `offset`, or `-1` if the message is no
+    /// longer (or not yet) available in the commit log.
+    pub fn pickup_store_timestamp(&self, offset: i64, size: i32) -> i64 {
+        if offset < self.get_min_offset() || offset + size as i64 > self.get_max_offset() {
+            return -1;
+        }
+        let Some(result) = self.get_message(offset, size) else {
+            return -1;
+        };
+        let Ok(buffer) = result.get_buffer() else {
+            return -1;
+        };
+        if buffer.len() < SYSFLAG_POSITION + 4 {
+            return -1;
+        }
+        let sys_flag = (&buffer[SYSFLAG_POSITION..]).get_i32();
+        let born_host_length = if sys_flag & MessageSysFlag::BORNHOST_V6_FLAG == 0 {
+            8
+        } else {
+            20
+        };
+        let store_timestamp_position = BORN_TIMESTAMP_POSITION + born_host_length;
+        if buffer.len() < store_timestamp_position + 8 {
+            return -1;
+        }
+        (&buffer[store_timestamp_position..]).get_i64()
+    }
+
     pub fn roll_next_file(&self, offset: i64) -> i64 {
         let mapped_file_size = self.message_store_config.mapped_file_size_commit_log as i64;
         offset + mapped_file_size - (offset % mapped_file_size)
@@ -1161,6 +1325,36 @@ impl CommitLog {
     }
 }
 
+/// Whether the body of the message at the start of `buffer` still matches the CRC stored with
+/// it. A buffer too short to hold the declared body does not match.
+pub fn body_crc_matches(buffer: &[u8]) -> bool {
+    let read_i32 = |pos: usize| {
+        buffer
+            .get(pos..pos + 4)
+            .map(|bytes| i32::from_be_bytes(bytes.try_into().unwrap()))
+    };
+    let (Some(body_crc), Some(sys_flag)) = (read_i32(8), read_i32(SYSFLAG_POSITION)) else {
+        return false;
+    };
+    let host_len = |v6_flag: i32| if sys_flag & v6_flag == 0 { 8 } else { 20 };
+    let body_len_pos = SYSFLAG_POSITION
+        + 4
+        + 8
+        + host_len(MessageSysFlag::BORNHOST_V6_FLAG)
+        + 8
+        + host_len(MessageSysFlag::STOREHOSTADDRESS_V6_FLAG)
+        + 4
+        + 8;
+    let Some(body_len) = read_i32(body_len_pos) else {
+        return false;
+    };
+    let body_pos = body_len_pos + 4;
+    match buffer.get(body_pos..body_pos + body_len.max(0) as usize) {
+        Some(body) => crc32(body) == body_crc as u32,
+        None => false,
+    }
+}
+
 pub fn check_message_and_return_size(
     bytes: &mut Bytes,
     check_crc: bool,
@@ -1238,7 +1432,7 @@ pub fn check_message_and_return_size(
     let properties_length = bytes.get_i16();
     let (tags_code, keys, uniq_key, properties_map) = if properties_length > 0 {
         let properties = bytes.copy_to_bytes(properties_length as usize);
-        let properties_content = String::from_utf8_lossy(topic_bytes.as_ref()).to_string();
+        let properties_content = String::from_utf8_lossy(properties.as_ref()).to_string();
         //need to optimize
         let properties_map =
             string_to_message_properties(Some(&CheetahString::from_string(properties_content)));
@@ -16,12 +16,15 @@
  */
 use std::sync::Arc;
 use std::sync::Weak;
+use std::time::Duration;
 
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
 use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_rust::ServiceTask;
+use rocketmq_rust::WaitNotifyObject;
 use tokio::sync::Mutex;
-use tokio::sync::Notify;
 use tokio::time;
+use tracing::info;
 
 use crate::base::flush_manager::FlushManager;
 use crate::base::message_result::AppendMessageResult;
@@ -49,30 +52,23 @@ impl DefaultFlushManager {
         let (group_commit_service, flush_real_time_service) =
             match message_store_config.flush_disk_type {
                 FlushDiskType::SyncFlush => (
-                    Some(GroupCommitService {
-                        store_checkpoint: store_checkpoint.clone(),
-                        rx_out: None,
-                        tx_in: None,
-                    }),
+                    Some(GroupCommitService::new(store_checkpoint.clone())),
                     None,
                 ),
                 FlushDiskType::AsyncFlush => (
                     None,
-                    Some(FlushRealTimeService {
-                        message_store_config: message_store_config.clone(),
-                        store_checkpoint: store_checkpoint.clone(),
-                        notified: Arc::new(Notify::new()),
-                    }),
+                    Some(FlushRealTimeService::new(
+                        message_store_config.clone(),
+                        store_checkpoint.clone(),
+                    )),
                 ),
             };
 
         let commit_real_time_service = if message_store_config.transient_store_pool_enable {
-            Some(CommitRealTimeService {
-                message_store_config: message_store_config.clone(),
+            Some(CommitRealTimeService::new(
+                message_store_config.clone(),
                 store_checkpoint,
-                notified: Arc::new(Default::default()),
-                flush_manager: None,
-            })
+            ))
         } else {
             None
         };
@@ -186,76 +182,110 @@ impl FlushManager for DefaultFlushManager {
 }
 
 struct GroupCommitService {
+    service: ServiceTask,
     store_checkpoint: Arc<StoreCheckpoint>,
-    rx_out: Option<tokio::sync::mpsc::Receiver<GroupCommitRequest>>,
-    tx_in: Option<tokio::sync::mpsc::Sender<GroupCommitRequest>>,
+    /// Woken up after every flush so that the waiting requests check their offsets.
+    flushed: Arc<WaitNotifyObject>,
+    mapped_file_queue: Option<MappedFileQueue>,
 }
 
 impl GroupCommitService {
-    pub async fn put_request(&mut self, request: GroupCommitRequest) -> Option<GroupCommitRequest> {
-        if let Some(ref tx_in) = self.tx_in {
-            let _ = tx_in.send(request).await;
+    fn new(store_checkpoint: Arc<StoreCheckpoint>) -> Self {
+        Self {
+            service: ServiceTask::new("GroupCommitService"),
+            store_checkpoint,
+            flushed: Arc::new(WaitNotifyObject::new()),
+            mapped_file_queue: None,
         }
-        match self.rx_out {
-            None => None,
-            Some(ref mut rx_out) => rx_out.recv().await,
+    }
+
+    pub async fn put_request(
+        &mut self,
+        mut request: GroupCommitRequest,
+    ) -> Option<GroupCommitRequest> {
+        let mapped_file_queue = self.mapped_file_queue.as_ref()?;
+        let mut observed = self.flushed.generation();
+        self.service.wakeup();
+        let mut flush_ok = mapped_file_queue.get_flushed_where() >= request.next_offset;
+        while !flush_ok && !self.service.is_stopped() {
+            self.flushed
+                .all_wait_for_running(&mut observed, Duration::from_millis(10))
+                .await;
+            flush_ok = mapped_file_queue.get_flushed_where() >= request.next_offset;
         }
+        request.flush_ok = Some(if flush_ok {
+            PutMessageStatus::PutOk
+        } else {
+            PutMessageStatus::FlushDiskTimeout
+        });
+        Some(request)
     }
 
     fn start(&mut self, mapped_file_queue: MappedFileQueue) {
-        let (tx_in, mut rx_in) = tokio::sync::mpsc::channel::<GroupCommitRequest>(1024);
-        self.tx_in = Some(tx_in);
-        let (tx_out, rx_out) = tokio::sync::mpsc::channel::<GroupCommitRequest>(1024);
-        self.rx_out = Some(rx_out);
-        tokio::spawn(async move {
-            loop {
-                match rx_in.recv().await {
-                    None => {}
-                    Some(mut request) => {
-                        let mut flush_ok =
-                            mapped_file_queue.get_flushed_where() >= request.next_offset;
-                        for i in 0..1000 {
-                            if flush_ok {
-                                break;
-                            }
-                            mapped_file_queue.flush(0);
-                            flush_ok = mapped_file_queue.get_flushed_where() >= request.next_offset;
-                            if flush_ok {
-                                break;
-                            }
-                            time::sleep(time::Duration::from_millis(1)).await;
-                        }
-                        request.flush_ok = Some(if flush_ok {
-                            PutMessageStatus::PutOk
-                        } else {
-                            PutMessageStatus::FlushDiskTimeout
-                        });
-                        let _ = tx_out.send(request).await;
-                    }
-                }
+        self.mapped_file_queue = Some(mapped_file_queue.clone());
+        let store_checkpoint = self.store_checkpoint.clone();
+        let flushed = self.flushed.clone();
+        self.service.start(move |context| async move {
+            info!("{} service started", context.service_name());
+            while !context.is_stopped() {
+                context.wait_for_running(Duration::from_millis(10)).await;
+                Self::do_commit(&mapped_file_queue, &store_checkpoint);
+                flushed.wakeup_all();
             }
+            info!("{} service end", context.service_name());
         });
     }
 
-    pub fn wakeup(&mut self) {}
+    fn do_commit(mapped_file_queue: &MappedFileQueue, store_checkpoint: &StoreCheckpoint) {
+        mapped_file_queue.flush(0);
+        let store_timestamp = mapped_file_queue.get_store_timestamp();
+        if store_timestamp > 0 {
+            store_checkpoint.set_physic_msg_timestamp(store_timestamp);
+        }
+    }
 
-    pub fn shutdown(&mut self) {}
+    pub fn wakeup(&mut self) {
+        self.service.wakeup();
+    }
+
+    pub fn shutdown(&mut self) {
+        self.service.shutdown(false);
+        // the requests still waiting are answered by the final flush
+        if let Some(mapped_file_queue) = self.mapped_file_queue.as_ref() {
+            Self::do_commit(mapped_file_queue, &self.store_checkpoint);
+            self.flushed.wakeup_all();
+        }
+    }
 }
 
 struct FlushRealTimeService {
+    service: ServiceTask,
     message_store_config: Arc<MessageStoreConfig>,
     store_checkpoint: Arc<StoreCheckpoint>,
-    notified: Arc<Notify>,
+    mapped_file_queue: Option<MappedFileQueue>,
 }
 
 impl FlushRealTimeService {
+    fn new(
+        message_store_config: Arc<MessageStoreConfig>,
+        store_checkpoint: Arc<StoreCheckpoint>,
+    ) -> Self {
+        Self {
+            service: ServiceTask::new("FlushRealTimeService"),
+            message_store_config,
+            store_checkpoint,
+            mapped_file_queue: None,
+        }
+    }
+
     fn start(&mut self, mapped_file_queue: MappedFileQueue) {
+        self.mapped_file_queue = Some(mapped_file_queue.clone());
         let message_store_config = self.message_store_config.clone();
         let store_checkpoint = self.store_checkpoint.clone();
-        let notified = self.notified.clone();
-        tokio::spawn(async move {
+        self.service.start(move |context| async move {
+            info!("{} service started", context.service_name());
             let mut last_flush_timestamp = 0;
-            loop {
+            while !context.is_stopped() {
                 let flush_commit_log_timed = message_store_config.flush_commit_log_timed;
                 let interval = message_store_config.flush_interval_commit_log;
                 let mut flush_physic_queue_least_pages =
@@ -274,10 +304,9 @@ impl FlushRealTimeService {
                 if flush_commit_log_timed {
                     time::sleep(time::Duration::from_millis(interval as u64)).await;
                 } else {
-                    tokio::select! {
-                        _ = notified.notified() => {}
-                        _ = tokio::time::sleep(std::time::Duration::from_millis(interval as u64)) => {}
-                    }
+                    context
+                        .wait_for_running(Duration::from_millis(interval as u64))
+                        .await;
                 }
 
                 mapped_file_queue.flush(flush_physic_queue_least_pages);
@@ -286,40 +315,60 @@ impl FlushRealTimeService {
                     store_checkpoint.set_physic_msg_timestamp(store_timestamp);
                 }
             }
+            info!("{} service end", context.service_name());
         });
     }
 
     pub fn wakeup(&mut self) {
         if !self.message_store_config.flush_commit_log_timed {
-            let notified = self.notified.clone();
-            tokio::spawn(async move { notified.notified().await });
+            self.service.wakeup();
         }
     }
 
-    pub fn shutdown(&mut self) {}
+    pub fn shutdown(&mut self) {
+        self.service.shutdown(false);
+        // normal shutdown, make sure every message is flushed
+        if let Some(mapped_file_queue) = self.mapped_file_queue.as_ref() {
+            mapped_file_queue.flush(0);
+        }
+    }
 }
 
 pub(crate) struct CommitRealTimeService {
+    service: ServiceTask,
     message_store_config: Arc<MessageStoreConfig>,
     store_checkpoint: Arc<StoreCheckpoint>,
-    notified: Arc<Notify>,
     flush_manager: Option<Weak<Mutex<DefaultFlushManager>>>,
+    mapped_file_queue: Option<MappedFileQueue>,
 }
 
 impl CommitRealTimeService {
+    fn new(
+        message_store_config: Arc<MessageStoreConfig>,
+        store_checkpoint: Arc<StoreCheckpoint>,
+    ) -> Self {
+        Self {
+            service: ServiceTask::new("CommitRealTimeService"),
+            message_store_config,
+            store_checkpoint,
+            flush_manager: None,
+            mapped_file_queue: None,
+        }
+    }
+
     pub fn wakeup(&mut self) {
-        let notified = self.notified.clone();
-        tokio::spawn(async move { notified.notified().await });
+        self.service.wakeup();
     }
 
     fn start(&mut self, mapped_file_queue: MappedFileQueue) {
+        self.mapped_file_queue = Some(mapped_file_queue.clone());
         let message_store_config = self.message_store_config.clone();
         let store_checkpoint = self.store_checkpoint.clone();
-        let notified = self.notified.clone();
         let flush_manager = self.flush_manager.clone();
-        tokio::spawn(async move {
+        self.service.start(move |context| async move {
+            info!("{} service started", context.service_name());
             let mut last_commit_timestamp = 0;
-            loop {
+            while !context.is_stopped() {
                 let interval = message_store_config.commit_interval_commit_log;
                 let mut commit_data_least_pages =
                     message_store_config.commit_commit_log_least_pages;
@@ -341,15 +390,20 @@ impl CommitRealTimeService {
                     }
                 }
 
-                tokio::select! {
-                    _ = notified.notified() => {}
-                    _ = tokio::time::sleep(std::time::Duration::from_millis(interval)) => {}
-                }
+                context
+                    .wait_for_running(Duration::from_millis(interval))
+                    .await;
             }
+            info!("{} service end", context.service_name());
         });
     }
 
-    pub fn shutdown(&mut self) {}
+    pub fn shutdown(&mut self) {
+        self.service.shutdown(false);
+        if let Some(mapped_file_queue) = self.mapped_file_queue.as_ref() {
+            mapped_file_queue.commit(0);
+        }
+    }
 
     pub fn set_flush_manager(&mut self, flush_manager: Option<Weak<Mutex<DefaultFlushManager>>>) {
         self.flush_manager = flush_manager;
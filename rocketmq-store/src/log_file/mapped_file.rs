@@ -392,14 +392,18 @@ pub trait MappedFile {
     /// requested slice goes beyond the store boundaries or the store is not available.
     fn get_data(&self, pos: usize, size: usize) -> Option<bytes::Bytes>;
 
-    /// Destroys the store after a specified interval.
+    /// Destroys the store: it becomes unavailable, is unmapped once every holder has released it
+    /// and its file is deleted then.
+    ///
+    /// Holders that have not released the store `interval_forcibly` milliseconds after the first
+    /// call are ignored by the calls after that, the store is unmapped anyway and their reads
+    /// fail from then on.
     ///
     /// # Arguments
-    /// * `interval_forcibly` - The time interval after which the store should be forcibly
-    ///   destroyed.
+    /// * `interval_forcibly` - The time in milliseconds after which holders are ignored.
     ///
     /// # Returns
-    /// `true` if the store was successfully destroyed, `false` otherwise.
+    /// `true` if the store was unmapped and its file deleted, `false` while it is still held.
     fn destroy(&self, interval_forcibly: i64) -> bool;
 
     /// Initiates a shutdown of the store after a specified interval.
@@ -18,6 +18,8 @@
 use std::fs::File;
 use std::fs::OpenOptions;
 use std::io::Write;
+use std::ops::Deref;
+use std::ops::Range;
 use std::path::PathBuf;
 use std::ptr;
 use std::sync::atomic::AtomicBool;
@@ -30,6 +32,7 @@ use bytes::Bytes;
 use bytes::BytesMut;
 use cheetah_string::CheetahString;
 use memmap2::MmapMut;
+use parking_lot::RwLock;
 use rocketmq_common::common::message::message_batch::MessageExtBatch;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
 use rocketmq_common::UtilAll::ensure_dir_ok;
@@ -54,10 +57,43 @@ pub const OS_PAGE_SIZE: u64 = 1024 * 4;
 static TOTAL_MAPPED_VIRTUAL_MEMORY: AtomicI64 = AtomicI64::new(0);
 static TOTAL_MAPPED_FILES: AtomicI32 = AtomicI32::new(0);
 
+type Mapping = Arc<SyncUnsafeCellWrapper<MmapMut>>;
+
+/// Returned by reads of a mapped file that has been unmapped, or of a buffer already released.
+#[derive(Debug, thiserror::Error)]
+#[error("mapped file is unavailable")]
+pub struct MappedFileUnavailable;
+
+/// Bytes read from a mapped file.
+///
+/// The buffer keeps the mapping it was read from alive, so a file unmapped while the buffer is
+/// in use is only munmapped once the buffer is dropped.
+pub struct MappedBuffer {
+    mapping: Mapping,
+    range: Range<usize>,
+}
+
+impl Deref for MappedBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.mapping[self.range.clone()]
+    }
+}
+
+impl AsRef<[u8]> for MappedBuffer {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
 pub struct DefaultMappedFile {
     reference_resource: ReferenceResource,
     file: File,
-    mmapped_file: SyncUnsafeCellWrapper<MmapMut>,
+    /// `None` once the file has been unmapped.
+    mmapped_file: RwLock<Option<Mapping>>,
+    /// Set by [`MappedFile::destroy`], the file is deleted as soon as it has been unmapped.
+    delete_on_cleanup: AtomicBool,
     transient_store_pool: Option<TransientStorePool>,
     file_name: CheetahString,
     file_from_offset: u64,
@@ -100,6 +136,27 @@ impl Default for DefaultMappedFile {
 }
 
 impl DefaultMappedFile {
+    /// Number of outstanding holders, including the file's own reference.
+    #[inline]
+    pub fn get_ref_count(&self) -> i64 {
+        self.reference_resource.get_ref_count()
+    }
+
+    #[inline]
+    pub fn is_cleanup_over(&self) -> bool {
+        self.reference_resource.is_cleanup_over()
+    }
+
+    /// Number of files currently mapped by the process.
+    pub fn total_mapped_files() -> i32 {
+        TOTAL_MAPPED_FILES.load(Ordering::Relaxed)
+    }
+
+    /// Bytes currently mapped by the process.
+    pub fn total_mapped_virtual_memory() -> i64 {
+        TOTAL_MAPPED_VIRTUAL_MEMORY.load(Ordering::Relaxed)
+    }
+
     pub fn new(file_name: CheetahString, file_size: u64) -> Self {
         let file_from_offset = Self::get_file_from_offset(&file_name);
         let path_buf = PathBuf::from(file_name.as_str());
@@ -114,6 +171,8 @@ impl DefaultMappedFile {
         file.set_len(file_size).unwrap();
 
         let mmap = unsafe { MmapMut::map_mut(&file).unwrap() };
+        TOTAL_MAPPED_VIRTUAL_MEMORY.fetch_add(file_size as i64, Ordering::Relaxed);
+        TOTAL_MAPPED_FILES.fetch_add(1, Ordering::Relaxed);
         Self {
             reference_resource: ReferenceResource {
                 ref_count: AtomicI64::new(1),
@@ -122,7 +181,8 @@ impl DefaultMappedFile {
                 first_shutdown_timestamp: AtomicI64::new(0),
             },
             file,
-            mmapped_file: SyncUnsafeCellWrapper::new(mmap),
+            mmapped_file: RwLock::new(Some(Arc::new(SyncUnsafeCellWrapper::new(mmap)))),
+            delete_on_cleanup: AtomicBool::new(false),
             file_name,
             file_from_offset,
             mapped_byte_buffer: None,
@@ -183,6 +243,8 @@ impl DefaultMappedFile {
         file.set_len(file_size).unwrap();
 
         let mmap = unsafe { MmapMut::map_mut(&file).unwrap() };
+        TOTAL_MAPPED_VIRTUAL_MEMORY.fetch_add(file_size as i64, Ordering::Relaxed);
+        TOTAL_MAPPED_FILES.fetch_add(1, Ordering::Relaxed);
         Self {
             reference_resource: ReferenceResource {
                 ref_count: AtomicI64::new(1),
@@ -206,7 +268,8 @@ impl DefaultMappedFile {
             start_timestamp: 0,
             transient_store_pool: Some(transient_store_pool),
             stop_timestamp: 0,
-            mmapped_file: SyncUnsafeCellWrapper::new(mmap),
+            mmapped_file: RwLock::new(Some(Arc::new(SyncUnsafeCellWrapper::new(mmap)))),
+            delete_on_cleanup: AtomicBool::new(false),
         }
     }
 }
@@ -250,10 +313,16 @@ impl MappedFile for DefaultMappedFile {
                 ..Default::default()
             };
         }
+        let Some(mapping) = self.mapping() else {
+            return AppendMessageResult {
+                status: AppendMessageStatus::UnknownError,
+                ..Default::default()
+            };
+        };
         let result = message_callback.do_append(
             self.file_from_offset as i64,
-            self,
-            (self.file_size - current_pos) as i32,
+            current_pos as i32,
+            &mut mapping.mut_from_ref()[current_pos as usize..self.file_size as usize],
             message,
             put_message_context,
         );
@@ -282,10 +351,16 @@ impl MappedFile for DefaultMappedFile {
                 ..Default::default()
             };
         }
+        let Some(mapping) = self.mapping() else {
+            return AppendMessageResult {
+                status: AppendMessageStatus::UnknownError,
+                ..Default::default()
+            };
+        };
         let result = message_callback.do_append_batch(
             self.file_from_offset as i64,
-            self,
-            (self.file_size - current_pos) as i32,
+            current_pos as i32,
+            &mut mapping.mut_from_ref()[current_pos as usize..self.file_size as usize],
             message,
             put_message_context,
             enabled_append_prop_crc,
@@ -308,20 +383,21 @@ impl MappedFile for DefaultMappedFile {
     }
 
     fn get_bytes(&self, pos: usize, size: usize) -> Option<bytes::Bytes> {
-        if pos + size > self.file_size as usize {
+        if pos + size > self.file_size as usize || !self.is_available() {
             return None;
         }
-        Some(Bytes::copy_from_slice(
-            &self.get_mapped_file()[pos..pos + size],
-        ))
+        let buffer = self.mapped_buffer(pos..pos + size).ok()?;
+        Some(Bytes::copy_from_slice(&buffer))
     }
 
     fn append_message_offset_length(&self, data: &Bytes, offset: usize, length: usize) -> bool {
         let current_pos = self.wrote_position.load(Ordering::Relaxed) as usize;
 
         if current_pos + length <= self.file_size as usize {
-            let mut mapped_file =
-                &mut self.get_mapped_file_mut()[current_pos..current_pos + length];
+            let Some(mapping) = self.mapping() else {
+                return false;
+            };
+            let mut mapped_file = &mut mapping.mut_from_ref()[current_pos..current_pos + length];
 
             if let Some(data_slice) = data.get(offset..offset + length) {
                 if mapped_file.write_all(data_slice).is_ok() {
@@ -347,8 +423,10 @@ impl MappedFile for DefaultMappedFile {
         let current_pos = self.wrote_position.load(Ordering::Relaxed) as usize;
 
         if current_pos + length <= self.file_size as usize {
-            let mut mapped_file =
-                &mut self.get_mapped_file_mut()[current_pos..current_pos + length];
+            let Some(mapping) = self.mapping() else {
+                return false;
+            };
+            let mut mapped_file = &mut mapping.mut_from_ref()[current_pos..current_pos + length];
 
             if let Some(data_slice) = data.get(offset..offset + length) {
                 if mapped_file.write_all(data_slice).is_ok() {
@@ -372,8 +450,10 @@ impl MappedFile for DefaultMappedFile {
         let current_pos = self.wrote_position.load(Ordering::Relaxed) as usize;
 
         if current_pos + length <= self.file_size as usize {
-            let mut mapped_file =
-                &mut self.get_mapped_file_mut()[current_pos..current_pos + length];
+            let Some(mapping) = self.mapping() else {
+                return false;
+            };
+            let mut mapped_file = &mut mapping.mut_from_ref()[current_pos..current_pos + length];
 
             if let Some(data_slice) = data.get(offset..offset + length) {
                 if mapped_file.write_all(data_slice).is_ok() {
@@ -390,7 +470,10 @@ impl MappedFile for DefaultMappedFile {
 
     fn write_bytes_segment(&self, data: &[u8], start: usize, offset: usize, length: usize) -> bool {
         if start + length <= self.file_size as usize {
-            let mut mapped_file = &mut self.get_mapped_file_mut()[start..start + length];
+            let Some(mapping) = self.mapping() else {
+                return false;
+            };
+            let mut mapped_file = &mut mapping.mut_from_ref()[start..start + length];
             if data.len() == length {
                 if mapped_file.write_all(data).is_ok() {
                     return true;
@@ -414,7 +497,10 @@ impl MappedFile for DefaultMappedFile {
         let length = data.len();
         let end_index = index + length;
         if length > 0 && end_index <= self.file_size as usize {
-            let mut mapped_file = &mut self.get_mapped_file_mut()[index..end_index];
+            let Some(mapping) = self.mapping() else {
+                return false;
+            };
+            let mut mapped_file = &mut mapping.mut_from_ref()[index..end_index];
             if mapped_file.write_all(data).is_ok() {
                 return true;
             } else {
@@ -433,13 +519,16 @@ impl MappedFile for DefaultMappedFile {
             if self.reference_resource.hold() {
                 let value = self.get_read_position();
                 if self.transient_store_pool.is_none() {
-                    self.get_mapped_file()
-                        .flush()
-                        .expect("Error occurred when force data to disk.");
+                    if let Some(mapping) = self.mapping() {
+                        mapping
+                            .flush()
+                            .expect("Error occurred when force data to disk.");
+                    }
                 } else {
                     unimplemented!()
                 }
                 self.flushed_position.store(value, Ordering::SeqCst);
+                self.release();
             } else {
                 warn!(
                     "in flush, hold failed, flush offset = {}",
@@ -520,8 +609,12 @@ impl MappedFile for DefaultMappedFile {
         let read_end_position = pos + size;
         if read_end_position <= read_position as usize {
             if self.hold() {
-                let buffer = BytesMut::from(&self.get_mapped_file()[pos..read_end_position]);
-                Some(buffer.freeze())
+                let buffer = self
+                    .mapped_buffer(pos..read_end_position)
+                    .ok()
+                    .map(|buffer| BytesMut::from(&*buffer).freeze());
+                self.release();
+                buffer
             } else {
                 debug!(
                     "matched, but hold failed, request pos: {}, fileFromOffset: {}",
@@ -540,7 +633,18 @@ impl MappedFile for DefaultMappedFile {
     }
 
     fn destroy(&self, interval_forcibly: i64) -> bool {
-        true
+        self.delete_on_cleanup.store(true, Ordering::Release);
+        self.shutdown(interval_forcibly);
+        if !self.reference_resource.is_cleanup_over() {
+            warn!(
+                "destroy mapped file[REF:{}] {} Failed. cleanupOver: {}",
+                self.reference_resource.get_ref_count(),
+                self.file_name,
+                self.reference_resource.cleanup_over.load(Ordering::Relaxed)
+            );
+            return false;
+        }
+        self.delete_file()
     }
 
     fn shutdown(&self, interval_forcibly: i64) {
@@ -567,6 +671,12 @@ impl MappedFile for DefaultMappedFile {
                     .load(Ordering::Relaxed))
                 >= interval_forcibly
         {
+            warn!(
+                "mapped file {} still has {} holders {}ms after shutdown, unmapping it anyway",
+                self.file_name,
+                self.reference_resource.get_ref_count(),
+                interval_forcibly
+            );
             self.reference_resource.ref_count.store(
                 -1000 - self.reference_resource.get_ref_count(),
                 Ordering::Relaxed,
@@ -584,9 +694,13 @@ impl MappedFile for DefaultMappedFile {
         if value > 0 {
             return;
         }
+        let cleanup_over = self.cleanup(value);
         self.reference_resource
             .cleanup_over
-            .store(self.cleanup(value), Ordering::SeqCst);
+            .store(cleanup_over, Ordering::SeqCst);
+        if cleanup_over && self.delete_on_cleanup.load(Ordering::Acquire) {
+            self.delete_file();
+        }
     }
 
     fn hold(&self) -> bool {
@@ -689,12 +803,18 @@ impl MappedFile for DefaultMappedFile {
 
 #[allow(unused_variables)]
 impl DefaultMappedFile {
-    pub fn get_mapped_file_mut(&self) -> &mut MmapMut {
-        self.mmapped_file.mut_from_ref()
+    /// The mapping of the file, `None` once it has been unmapped.
+    fn mapping(&self) -> Option<Mapping> {
+        self.mmapped_file.read().clone()
     }
 
-    pub fn get_mapped_file(&self) -> &MmapMut {
-        self.mmapped_file.as_ref()
+    /// Bytes `range` of the file, an error once the file has been unmapped.
+    pub fn mapped_buffer(
+        &self,
+        range: Range<usize>,
+    ) -> Result<MappedBuffer, MappedFileUnavailable> {
+        let mapping = self.mapping().ok_or(MappedFileUnavailable)?;
+        Ok(MappedBuffer { mapping, range })
     }
 
     fn is_able_to_flush(&self, flush_least_pages: i32) -> bool {
@@ -709,6 +829,24 @@ impl DefaultMappedFile {
         write > flush
     }
 
+    fn delete_file(&self) -> bool {
+        match std::fs::remove_file(self.file_name.as_str()) {
+            Ok(_) => {
+                info!(
+                    "delete file[REF:{}] {} OK",
+                    self.reference_resource.get_ref_count(),
+                    self.file_name
+                );
+                true
+            }
+            Err(ref error) if error.kind() == std::io::ErrorKind::NotFound => true,
+            Err(error) => {
+                warn!("close file channel {} Failed. {}", self.file_name, error);
+                false
+            }
+        }
+    }
+
     fn cleanup(&self, current_ref: i64) -> bool {
         if self.is_available() {
             error!(
@@ -725,6 +863,9 @@ impl DefaultMappedFile {
             );
             return true;
         }
+        // holders still reading keep their own reference on the mapping, it is munmapped
+        // once the last of them is dropped
+        self.mmapped_file.write().take();
         TOTAL_MAPPED_VIRTUAL_MEMORY.fetch_sub(self.file_size as i64, Ordering::Relaxed);
         TOTAL_MAPPED_FILES.fetch_sub(1, Ordering::Relaxed);
         info!("unmap file[REF:{}] {} OK", current_ref, self.file_name);
@@ -778,3 +919,75 @@ impl Default for ReferenceResource {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::time::Duration;
+
+    use super::*;
+
+    fn mapped_file(dir: &Path) -> Arc<DefaultMappedFile> {
+        let file_name = dir.join("00000000000000000000");
+        let mapped_file = Arc::new(DefaultMappedFile::new(
+            CheetahString::from_string(file_name.to_string_lossy().to_string()),
+            1024,
+        ));
+        assert!(mapped_file.append_message_bytes(&Bytes::from_static(b"hello rocketmq")));
+        mapped_file
+    }
+
+    #[test]
+    fn destroy_waits_for_holders_and_deletes_the_file_on_the_last_release() {
+        let dir = tempfile::tempdir().unwrap();
+        let mapped_file = mapped_file(dir.path());
+        let file_name = PathBuf::from(mapped_file.get_file_name().as_str());
+        assert!(mapped_file.hold());
+        let reader = mapped_file.clone().select_mapped_buffer_size(0, 5).unwrap();
+        assert_eq!(mapped_file.get_ref_count(), 3);
+
+        assert!(!mapped_file.destroy(60_000));
+        assert!(!mapped_file.destroy(60_000));
+        assert!(!mapped_file.is_available());
+        assert!(file_name.exists());
+        // no new holders once unavailable, the existing ones keep reading
+        assert!(!mapped_file.hold());
+        assert!(mapped_file.clone().select_mapped_buffer(0).is_none());
+        assert!(mapped_file.get_bytes(0, 5).is_none());
+        assert!(mapped_file.get_data(0, 5).is_none());
+        assert_eq!(&*reader.get_buffer().unwrap(), b"hello");
+
+        mapped_file.release();
+        assert!(file_name.exists());
+        drop(reader);
+        assert_eq!(mapped_file.get_ref_count(), 0);
+        assert!(mapped_file.is_cleanup_over());
+        assert!(!file_name.exists());
+        assert!(mapped_file.destroy(60_000));
+    }
+
+    #[test]
+    fn reader_that_never_releases_is_unmapped_after_the_interval() {
+        let dir = tempfile::tempdir().unwrap();
+        let mapped_file = mapped_file(dir.path());
+        let file_name = PathBuf::from(mapped_file.get_file_name().as_str());
+        let leaked = mapped_file.clone().select_mapped_buffer_size(0, 5).unwrap();
+        let in_flight = leaked.get_buffer().unwrap();
+
+        assert!(!mapped_file.destroy(100));
+        std::thread::sleep(Duration::from_millis(150));
+        assert!(mapped_file.destroy(100));
+        assert!(mapped_file.is_cleanup_over());
+        assert!(!file_name.exists());
+
+        // reads through the leaked holder fail instead of touching the unmapped file
+        assert!(leaked.get_buffer().is_err());
+        assert!(leaked.get_bytes().is_none());
+        assert!(!mapped_file.append_message_bytes(&Bytes::from_static(b"more")));
+        // a read in progress keeps its mapping until it is done
+        assert_eq!(&*in_flight, b"hello");
+        drop(in_flight);
+        drop(leaked);
+        assert!(mapped_file.is_cleanup_over());
+    }
+}
@@ -15,4 +15,4 @@
  * limitations under the License.
  */
 
-pub(crate) mod message_ext_encoder;
+pub mod message_ext_encoder;
@@ -32,7 +32,6 @@ use crate::base::message_result::PutMessageResult;
 use crate::base::message_status_enum::PutMessageStatus;
 use crate::base::put_message_context::PutMessageContext;
 use crate::config::message_store_config::MessageStoreConfig;
-use crate::log_file::commit_log::CommitLog;
 use crate::log_file::commit_log::CRC32_RESERVED_LEN;
 
 pub struct MessageExtEncoder {
@@ -228,12 +227,6 @@ impl MessageExtEncoder {
     pub fn encode(&mut self, msg_inner: &MessageExtBrokerInner) -> Option<PutMessageResult> {
         self.byte_buf.clear();
 
-        if self.message_store_config.enable_multi_dispatch
-            && CommitLog::is_multi_dispatch_msg(msg_inner)
-        {
-            return self.encode_without_properties(msg_inner);
-        }
-
         // Serialize message
         let properties_data = msg_inner.properties_string().as_bytes();
         let need_append_last_property_separator = self.crc32_reserved_length > 0
@@ -301,6 +294,9 @@ impl MessageExtEncoder {
             ));
         }
 
+        // Reclaims the space of the messages handed out before in one go
+        self.byte_buf.reserve(msg_len as usize);
+
         // 1 TOTALSIZE
         self.byte_buf.put_i32(msg_len);
 
@@ -369,8 +365,9 @@ impl MessageExtEncoder {
             self.byte_buf
                 .put_u8(MessageDecoder::PROPERTY_SEPARATOR as u8);
         }
-        // 18 CRC32
-        self.byte_buf.advance(self.crc32_reserved_length as usize);
+        // 18 CRC32, filled in by the append callback once the offsets are known
+        self.byte_buf
+            .put_bytes(0, self.crc32_reserved_length as usize);
         None
     }
 
@@ -517,7 +514,8 @@ impl MessageExtEncoder {
                 self.byte_buf.put(batch_prop_data);
             }
             // 18 CRC32
-            self.byte_buf.advance(self.crc32_reserved_length as usize);
+            self.byte_buf
+                .put_bytes(0, self.crc32_reserved_length as usize);
         }
         put_message_context.set_batch_size(batch_size);
         put_message_context.set_phy_pos(vec![0; batch_size as usize]);
@@ -540,9 +538,13 @@ impl MessageExtEncoder {
         } else {
             i32::MAX
         };
-        self.byte_buf.resize(self.max_message_size as usize, 0);
+        self.byte_buf.clear();
+        self.byte_buf.reserve(self.max_message_size as usize);
     }
 
+    /// Hands out the encoded message. The buffer keeps its allocation: the space comes back to
+    /// the encoder once the returned bytes are dropped, so steady-state encoding does not
+    /// allocate.
     pub fn byte_buf(&mut self) -> bytes::BytesMut {
         self.byte_buf.split()
     }
@@ -552,8 +554,30 @@ impl MessageExtEncoder {
 mod tests {
     use std::sync::Arc;
 
+    use bytes::Bytes;
+    use cheetah_string::CheetahString;
+    use rocketmq_common::common::message::MessageConst;
+
     use super::*;
 
+    fn message(body: &'static [u8]) -> MessageExtBrokerInner {
+        let mut msg = MessageExtBrokerInner::default();
+        msg.message_ext_inner.message.topic = CheetahString::from_static_str("TopicTest");
+        msg.message_ext_inner.message.body = Some(Bytes::from_static(body));
+        msg.message_ext_inner.body_crc = crc32(body);
+        msg.message_ext_inner.queue_id = 3;
+        msg.message_ext_inner.queue_offset = 42;
+        msg.message_ext_inner.born_timestamp = 1_000;
+        msg.message_ext_inner.store_timestamp = 2_000;
+        msg.properties_string = CheetahString::from_string(format!(
+            "{}{}TagA{}",
+            MessageConst::PROPERTY_TAGS,
+            MessageDecoder::NAME_VALUE_SEPARATOR,
+            PROPERTY_SEPARATOR
+        ));
+        msg
+    }
+
     #[test]
     fn message_ext_encoder_new_creates_encoder_with_correct_config() {
         let config = Arc::new(MessageStoreConfig::default());
@@ -616,6 +640,64 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[test]
+    fn encoded_message_decodes_back() {
+        let mut encoder = MessageExtEncoder::new(Arc::new(MessageStoreConfig::default()));
+        assert!(encoder.encode(&message(b"hello")).is_none());
+        let mut encoded = encoder.byte_buf().freeze();
+        let msg_len = encoded.len() as i32;
+
+        let decoded =
+            MessageDecoder::decode(&mut encoded, true, false, false, false, true).unwrap();
+        assert_eq!(decoded.store_size, msg_len);
+        assert_eq!(decoded.message.topic.as_str(), "TopicTest");
+        assert_eq!(decoded.message.body.as_deref(), Some(&b"hello"[..]));
+        assert_eq!(decoded.queue_id, 3);
+        assert_eq!(decoded.queue_offset, 42);
+        assert_eq!(decoded.born_timestamp, 1_000);
+        assert_eq!(decoded.store_timestamp, 2_000);
+        assert_eq!(
+            decoded.message.properties.get(MessageConst::PROPERTY_TAGS),
+            Some(&CheetahString::from_static_str("TagA"))
+        );
+    }
+
+    #[test]
+    fn crc32_slot_is_reserved_at_the_end() {
+        let config = MessageStoreConfig {
+            enabled_append_prop_crc: true,
+            ..MessageStoreConfig::default()
+        };
+        let mut plain = MessageExtEncoder::new(Arc::new(MessageStoreConfig::default()));
+        let mut encoder = MessageExtEncoder::new(Arc::new(config));
+        plain.encode(&message(b"hello"));
+        encoder.encode(&message(b"hello"));
+        let plain = plain.byte_buf();
+        let encoded = encoder.byte_buf();
+
+        assert_eq!(encoded.len(), plain.len() + CRC32_RESERVED_LEN as usize);
+        assert_eq!(
+            i32::from_be_bytes(encoded[..4].try_into().unwrap()),
+            encoded.len() as i32
+        );
+        assert!(encoded[plain.len()..].iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn encoding_reuses_the_buffer() {
+        let mut encoder = MessageExtEncoder::new(Arc::new(MessageStoreConfig::default()));
+        let start = encoder.byte_buf.as_ptr() as usize;
+        let end = start + encoder.byte_buf.capacity();
+        let mut encoded_total = 0;
+        while encoded_total <= 2 * (end - start) {
+            encoder.encode(&message(&[7; 64 * 1024]));
+            let encoded = encoder.byte_buf();
+            let at = encoded.as_ptr() as usize;
+            assert!(start <= at && at + encoded.len() <= end);
+            encoded_total += encoded.len();
+        }
+    }
+
     #[test]
     fn get_encoder_buffer_returns_correct_buffer() {
         let config = Arc::new(MessageStoreConfig::default());
@@ -17,20 +17,25 @@
 #![allow(unused_variables)]
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::error::Error;
 use std::fs;
+use std::panic;
+use std::panic::AssertUnwindSafe;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicI64;
+use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::thread;
 use std::time::Duration;
 use std::time::Instant;
 
 use bytes::Buf;
 use cheetah_string::CheetahString;
+use opentelemetry::metrics::MeterProvider;
 use rocketmq_common::common::attribute::cleanup_policy::CleanupPolicy;
+use rocketmq_common::common::attribute::cq_type::CQType;
 use rocketmq_common::common::message::message_batch::MessageExtBatch;
 use rocketmq_common::common::message::message_ext::MessageExt;
 use rocketmq_common::common::mix_all::is_lmq;
@@ -54,8 +59,7 @@ use rocketmq_common::{
     UtilAll::ensure_dir_ok,
 };
 use rocketmq_rust::ArcMut;
-use tokio::runtime::Handle;
-use tokio::sync::mpsc::Sender;
+use rocketmq_rust::ServiceTask;
 use tracing::error;
 use tracing::info;
 use tracing::warn;
@@ -63,6 +67,7 @@ use tracing::warn;
 use crate::base::allocate_mapped_file_service::AllocateMappedFileService;
 use crate::base::commit_log_dispatcher::CommitLogDispatcher;
 use crate::base::dispatch_request::DispatchRequest;
+use crate::base::epoch_file_checkpoint::EpochFileCheckpoint;
 use crate::base::get_message_result::GetMessageResult;
 use crate::base::message_arriving_listener::MessageArrivingListener;
 use crate::base::message_result::PutMessageResult;
@@ -75,8 +80,7 @@ use crate::base::store_stats_service::StoreStatsService;
 use crate::base::transient_store_pool::TransientStorePool;
 use crate::config::broker_role::BrokerRole;
 use crate::config::message_store_config::MessageStoreConfig;
-use crate::config::store_path_config_helper::get_store_path_batch_consume_queue;
-use crate::config::store_path_config_helper::get_store_path_consume_queue_ext;
+use crate::consume_queue::mapped_file_queue::split_store_path;
 use crate::filter::MessageFilter;
 use crate::hook::put_message_hook::BoxedPutMessageHook;
 use crate::index::index_dispatch::CommitLogDispatcherBuildIndex;
@@ -88,16 +92,19 @@ use crate::log_file::commit_log::CommitLog;
 use crate::log_file::mapped_file::MappedFile;
 use crate::log_file::MessageStore;
 use crate::log_file::MAX_PULL_MSG_SIZE;
+use crate::metrics::default_store_metrics_constant::METER_NAME;
+use crate::metrics::default_store_metrics_manager::DefaultStoreMetricsManager;
+use crate::metrics::default_store_metrics_manager::StoreMetricsSource;
 use crate::queue::build_consume_queue::CommitLogDispatcherBuildConsumeQueue;
+use crate::queue::consume_queue_check;
+use crate::queue::consume_queue_check::DamagedConsumeQueue;
 use crate::queue::local_file_consume_queue_store::ConsumeQueueStore;
 use crate::queue::ArcConsumeQueue;
 use crate::queue::ConsumeQueueStoreTrait;
 use crate::stats::broker_stats_manager::BrokerStatsManager;
 use crate::store::running_flags::RunningFlags;
-use crate::store_path_config_helper::get_abort_file;
-use crate::store_path_config_helper::get_store_checkpoint;
-use crate::store_path_config_helper::get_store_path_consume_queue;
 use crate::timer::timer_message_store::TimerMessageStore;
+use crate::utils::multi_dispatch_utils;
 use crate::utils::store_util::TOTAL_PHYSICAL_MEMORY_SIZE;
 
 ///Using local files to store message data, which is also the default method.
@@ -119,8 +126,11 @@ pub struct DefaultMessageStore {
     state_machine_version: Arc<AtomicI64>,
     shutdown: Arc<AtomicBool>,
     running_flags: Arc<RunningFlags>,
+    print_times: AtomicU64,
+    dispatch_fall_behind: AtomicBool,
     //reput_message_service: Arc<parking_lot::Mutex<ReputMessageService>>,
     reput_message_service: ReputMessageService,
+    flush_consume_queue_service: FlushConsumeQueueService,
     clean_commit_log_service: Arc<CleanCommitLogService>,
     correct_logic_offset_service: Arc<CorrectLogicOffsetService>,
     clean_consume_queue_service: Arc<CleanConsumeQueueService>,
@@ -133,6 +143,11 @@ pub struct DefaultMessageStore {
     timer_message_store: Arc<TimerMessageStore>,
     transient_store_pool: TransientStorePool,
     message_store_arc: Option<ArcMut<DefaultMessageStore>>,
+    store_metrics_manager: Option<Arc<DefaultStoreMetricsManager>>,
+    last_exit_ok: Arc<AtomicBool>,
+    /// Holds the exclusive lock on the store lock file from `start` until `shutdown`.
+    lock_file: Option<fs::File>,
+    epoch_file_checkpoint: Option<Arc<EpochFileCheckpoint>>,
 }
 
 impl DefaultMessageStore {
@@ -144,12 +159,8 @@ impl DefaultMessageStore {
         notify_message_arrive_in_batch: bool,
     ) -> Self {
         let running_flags = Arc::new(RunningFlags::new());
-        let store_checkpoint = Arc::new(
-            StoreCheckpoint::new(get_store_checkpoint(
-                message_store_config.store_path_root_dir.as_str(),
-            ))
-            .unwrap(),
-        );
+        let store_checkpoint =
+            Arc::new(StoreCheckpoint::new(message_store_config.get_store_checkpoint()).unwrap());
         let index_service =
             IndexService::new(message_store_config.clone(), store_checkpoint.clone());
         let build_index =
@@ -166,7 +177,10 @@ impl DefaultMessageStore {
             CommitLogDispatcherBuildConsumeQueue::new(consume_queue_store.clone());
 
         let dispatcher = CommitLogDispatcherDefault {
-            dispatcher_vec: Arc::new(vec![Box::new(build_consume_queue), Box::new(build_index)]),
+            dispatcher_vec: Arc::new(parking_lot::RwLock::new(vec![
+                Box::new(build_consume_queue),
+                Box::new(build_index),
+            ])),
         };
 
         let commit_log = CommitLog::new(
@@ -183,6 +197,22 @@ impl DefaultMessageStore {
         ensure_dir_ok(Self::get_store_path_logic(&message_store_config).as_str());
 
         let identity = broker_config.broker_identity.clone();
+        let flush_consume_queue_service = FlushConsumeQueueService::new(
+            message_store_config.clone(),
+            consume_queue_store.clone(),
+            store_checkpoint.clone(),
+        );
+        let clean_commit_log_service = Arc::new(CleanCommitLogService {
+            message_store_config: message_store_config.clone(),
+            commit_log: commit_log.clone(),
+            running_flags: running_flags.clone(),
+        });
+        let clean_consume_queue_service = Arc::new(CleanConsumeQueueService {
+            commit_log: commit_log.clone(),
+            consume_queue_store: consume_queue_store.clone(),
+            index_service: index_service.clone(),
+            last_physical_min_offset: AtomicI64::new(0),
+        });
         let transient_store_pool = TransientStorePool::new(
             message_store_config.transient_store_pool_size,
             message_store_config.mapped_file_size_commit_log,
@@ -205,15 +235,18 @@ impl DefaultMessageStore {
             state_machine_version: Arc::new(AtomicI64::new(0)),
             shutdown: Arc::new(AtomicBool::new(false)),
             running_flags,
+            print_times: AtomicU64::new(0),
+            dispatch_fall_behind: AtomicBool::new(false),
             reput_message_service: ReputMessageService {
-                tx: None,
-                reput_from_offset: None,
+                service: Arc::new(ServiceTask::new("ReputMessageService")),
+                reput_from_offset: Arc::new(AtomicI64::new(0)),
                 message_store_config,
                 inner: None,
             },
-            clean_commit_log_service: Arc::new(CleanCommitLogService {}),
+            flush_consume_queue_service,
+            clean_commit_log_service,
             correct_logic_offset_service: Arc::new(CorrectLogicOffsetService {}),
-            clean_consume_queue_service: Arc::new(CleanConsumeQueueService {}),
+            clean_consume_queue_service,
             broker_stats_manager,
             message_arriving_listener: None,
             notify_message_arrive_in_batch,
@@ -222,7 +255,158 @@ impl DefaultMessageStore {
             timer_message_store: Arc::new(TimerMessageStore::new_empty()),
             transient_store_pool,
             message_store_arc: None,
+            store_metrics_manager: None,
+            last_exit_ok: Arc::new(AtomicBool::new(true)),
+            lock_file: None,
+            epoch_file_checkpoint: None,
+        }
+    }
+
+    /// Creates the store instruments on `meter_provider`. Metrics stay disabled until this is
+    /// called; register [`DefaultStoreMetricsManager::get_metrics_view`] on the provider to get
+    /// the put latency buckets.
+    pub fn init_metrics(&mut self, meter_provider: &impl MeterProvider) {
+        let meter = meter_provider.meter(METER_NAME);
+        let source = StoreMetricsSource {
+            commit_log: self.commit_log.clone(),
+            message_store: ArcMut::downgrade(
+                self.message_store_arc
+                    .as_ref()
+                    .expect("message store arc is set before metrics are initialized"),
+            ),
+            consume_queue_store: self.consume_queue_store.clone(),
+            store_path_physic: Self::get_store_path_physic(&self.message_store_config),
+            last_exit_ok: self.last_exit_ok.clone(),
+        };
+        self.store_metrics_manager =
+            Some(Arc::new(DefaultStoreMetricsManager::new(&meter, source)));
+    }
+
+    pub fn store_metrics_manager(&self) -> Option<&Arc<DefaultStoreMetricsManager>> {
+        self.store_metrics_manager.as_ref()
+    }
+
+    fn record_put_metrics(&self, topic: &str, elapsed: Duration, result: &PutMessageResult) {
+        let Some(metrics) = self.store_metrics_manager.as_ref() else {
+            return;
+        };
+        metrics.record_put_latency(elapsed.as_secs_f64() * 1000.0);
+        if let Some(append_result) = result.append_message_result().filter(|_| result.is_ok()) {
+            metrics.inc_put_message(
+                topic,
+                append_result.msg_num as u64,
+                append_result.wrote_bytes as u64,
+            );
+        }
+    }
+
+    /// Rejects writes while the store is shut down, running as a slave, not writeable, falling
+    /// behind on dispatch or stalled on the commit log lock.
+    fn check_store_status(&self) -> PutMessageStatus {
+        if self.is_shutdown() {
+            warn!("message store has shutdown, so putMessage is forbidden");
+            return PutMessageStatus::ServiceNotAvailable;
+        }
+
+        if self.message_store_config.broker_role == BrokerRole::Slave {
+            let value = self.print_times.fetch_add(1, Ordering::Relaxed);
+            if value % 50000 == 0 {
+                warn!("broke role is slave, so putMessage is forbidden");
+            }
+            return PutMessageStatus::ServiceNotAvailable;
+        }
+
+        if !self.running_flags.is_writeable() {
+            let value = self.print_times.fetch_add(1, Ordering::Relaxed);
+            if value % 50000 == 0 {
+                warn!(
+                    "the message store is not writable. It may be caused by one of the following \
+                     reasons: the broker's disk is full, write to logic queue error, write to \
+                     index file error, etc"
+                );
+            }
+            return PutMessageStatus::ServiceNotAvailable;
+        } else {
+            self.print_times.store(0, Ordering::Relaxed);
         }
+
+        if self.is_dispatch_fall_behind() {
+            return PutMessageStatus::ServiceNotAvailable;
+        }
+
+        if self.is_os_page_cache_busy() {
+            return PutMessageStatus::OsPageCacheBusy;
+        }
+        PutMessageStatus::PutOk
+    }
+
+    /// Whether the consume queues fall so far behind the commit log that puts are held back,
+    /// logged when the protection kicks in and when it is lifted.
+    fn is_dispatch_fall_behind(&self) -> bool {
+        if !self.message_store_config.dispatch_behind_protect_enable {
+            return false;
+        }
+        let dispatch_behind_bytes = self.dispatch_behind_bytes();
+        let fall_behind =
+            dispatch_behind_bytes > self.message_store_config.dispatch_behind_protect_bytes;
+        if self
+            .dispatch_fall_behind
+            .swap(fall_behind, Ordering::AcqRel)
+            != fall_behind
+        {
+            if fall_behind {
+                warn!(
+                    "dispatch fall behind commit log {} bytes, exceeds {}, putMessage is forbidden",
+                    dispatch_behind_bytes, self.message_store_config.dispatch_behind_protect_bytes
+                );
+            } else {
+                info!(
+                    "dispatch caught up with commit log, {} bytes behind, putMessage is allowed \
+                     again",
+                    dispatch_behind_bytes
+                );
+            }
+        }
+        fall_behind
+    }
+
+    fn check_message(msg: &MessageExtBrokerInner) -> PutMessageStatus {
+        if msg.topic().len() > i8::MAX as usize {
+            warn!(
+                "putMessage message topic length too long {}",
+                msg.topic().len()
+            );
+            return PutMessageStatus::MessageIllegal;
+        }
+        if msg.properties_string.len() > i16::MAX as usize {
+            warn!(
+                "putMessage message properties length too long {}",
+                msg.properties_string.len()
+            );
+            return PutMessageStatus::MessageIllegal;
+        }
+        PutMessageStatus::PutOk
+    }
+
+    fn check_lmq_message(&self, msg: &MessageExtBrokerInner) -> PutMessageStatus {
+        if self.message_store_config.enable_lmq
+            && multi_dispatch_utils::is_need_handle_multi_dispatch(
+                &self.message_store_config,
+                msg.topic(),
+            )
+            && msg
+                .property(MessageConst::PROPERTY_INNER_MULTI_DISPATCH)
+                .is_some_and(|queues| !queues.trim().is_empty())
+            && self.consume_queue_store.get_lmq_num()
+                > self.message_store_config.max_lmq_consume_queue_num
+        {
+            warn!(
+                "putMessage the lmq consume queue num is exceeded, max {}",
+                self.message_store_config.max_lmq_consume_queue_num
+            );
+            return PutMessageStatus::LmqConsumeQueueNumExceeded;
+        }
+        PutMessageStatus::PutOk
     }
 
     pub fn get_store_path_physic(message_store_config: &Arc<MessageStoreConfig>) -> String {
@@ -235,7 +419,7 @@ impl DefaultMessageStore {
     }
 
     pub fn get_store_path_logic(message_store_config: &Arc<MessageStoreConfig>) -> String {
-        get_store_path_consume_queue(message_store_config.store_path_root_dir.as_str())
+        message_store_config.get_store_path_consume_queue()
     }
 
     pub fn message_store_config(&self) -> Arc<MessageStoreConfig> {
@@ -274,12 +458,38 @@ impl DefaultMessageStore {
     }
 
     fn is_temp_file_exist(&self) -> bool {
-        let file_name = get_abort_file(self.message_store_config.store_path_root_dir.as_str());
+        let file_name = self.message_store_config.get_abort_file();
         fs::metadata(file_name).is_ok()
     }
 
+    /// Takes the exclusive lock on the store lock file, so a second broker pointed at the same
+    /// store root refuses to start instead of writing into the files of the running one.
+    fn lock_store(&mut self) -> Result<(), Box<dyn Error>> {
+        let lock_file_name = self.message_store_config.get_lock_file();
+        let lock_file = fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .truncate(false)
+            .open(lock_file_name.as_str())?;
+        if !util_all::try_lock_file(&lock_file)? {
+            return Err(format!(
+                "Lock failed, MQ already started, lock file: {}",
+                lock_file_name
+            )
+            .into());
+        }
+        self.lock_file = Some(lock_file);
+        Ok(())
+    }
+
+    /// Whether the store was shut down normally before this boot, i.e. no abort file was left.
+    pub fn is_last_exit_ok(&self) -> bool {
+        self.last_exit_ok.load(Ordering::Relaxed)
+    }
+
     fn create_temp_file(&self) {
-        let file_name = get_abort_file(self.message_store_config.store_path_root_dir.as_str());
+        let file_name = self.message_store_config.get_abort_file();
         let pid = std::process::id();
         match fs::File::create(file_name.as_str()) {
             Ok(_) => {}
@@ -374,7 +584,7 @@ impl DefaultMessageStore {
 
     pub fn on_commit_log_dispatch(
         &mut self,
-        dispatch_request: &DispatchRequest,
+        dispatch_request: &mut DispatchRequest,
         do_dispatch: bool,
         is_recover: bool,
         _is_file_end: bool,
@@ -384,14 +594,62 @@ impl DefaultMessageStore {
         }
     }
 
-    pub fn do_dispatch(&mut self, dispatch_request: &DispatchRequest) {
+    pub fn do_dispatch(&mut self, dispatch_request: &mut DispatchRequest) {
         self.dispatcher.dispatch(dispatch_request)
     }
 
+    /// Registers a dispatcher that observes every dispatch request on the reput task, after the
+    /// built-in consume queue and index dispatchers and the ones registered before it.
+    pub fn register_dispatcher(&self, dispatcher: Box<dyn CommitLogDispatcher>) {
+        self.dispatcher.add_dispatcher(dispatcher);
+    }
+
+    /// Registers a dispatcher that runs ahead of the consume queue dispatcher, e.g. to
+    /// pre-calculate the consumer filter bitmap stored alongside each consume queue entry.
+    pub fn register_first_dispatcher(&self, dispatcher: Box<dyn CommitLogDispatcher>) {
+        self.dispatcher.add_first_dispatcher(dispatcher);
+    }
+
     pub fn truncate_dirty_logic_files(&mut self, phy_offset: i64) {
         self.consume_queue_store.truncate_dirty(phy_offset);
     }
 
+    fn start_reput_message_service(&mut self) {
+        self.reput_message_service.start(
+            Arc::new(self.commit_log.clone()),
+            self.message_store_config.clone(),
+            self.dispatcher.clone(),
+            self.notify_message_arrive_in_batch,
+            self.message_store_arc.clone().unwrap(),
+        );
+    }
+
+    /// Truncates the commit log to `phy_offset`, e.g. where a demoted master diverges from the
+    /// new one, along with the consume queue and index entries and the epochs past it. Puts wait
+    /// until it is done and dispatch resumes from `phy_offset` afterwards, so data replayed from
+    /// the new master is dispatched again. `false` if the commit log does not reach past it.
+    pub async fn truncate_files_after(&mut self, phy_offset: i64) -> bool {
+        let put_message_lock = self.commit_log.put_message_lock().clone();
+        let _lock = put_message_lock.lock().await;
+        self.truncate_files(phy_offset)
+    }
+
+    /// Up to `size` bytes of the commit log from `offset`, all within the file holding `offset`,
+    /// for a replica to append with [`CommitLog::append_data`]. `None` past the written data or
+    /// once the store is shut down.
+    pub fn get_commit_log_data(&self, offset: i64, size: i32) -> Option<SelectMappedBufferResult> {
+        if self.shutdown.load(Ordering::Acquire) || size <= 0 {
+            return None;
+        }
+        let mut result = self.commit_log.get_data_with_option(offset, false)?;
+        result.size = result.size.min(size);
+        Some(result)
+    }
+
+    pub fn epoch_file_checkpoint(&self) -> Option<&Arc<EpochFileCheckpoint>> {
+        self.epoch_file_checkpoint.as_ref()
+    }
+
     pub fn consume_queue_store_mut(&mut self) -> &mut ConsumeQueueStore {
         &mut self.consume_queue_store
     }
@@ -465,6 +723,123 @@ impl DefaultMessageStore {
         self.consume_queue_store.check_self();
     }
 
+    /// Verifies every simple consume queue against the commit log: its max physic offset has to
+    /// be within the commit log, a sample of its entries has to point at messages of the queue
+    /// and the queue offset table has to agree with its length. Each damaged queue is logged
+    /// and returned. Meant to run right after recovery, before dispatch starts.
+    pub fn check_consume_queues(&self) -> Vec<DamagedConsumeQueue> {
+        let consume_queues = self
+            .consume_queue_store
+            .get_consume_queue_table()
+            .lock()
+            .values()
+            .flat_map(|queues| queues.values().cloned())
+            .collect::<Vec<ArcConsumeQueue>>();
+        let mut damaged = Vec::new();
+        for consume_queue in consume_queues {
+            let topic = consume_queue.get_topic();
+            if consume_queue.get_cq_type() != CQType::SimpleCQ || is_lmq(Some(topic.as_str())) {
+                continue;
+            }
+            let queue_id = consume_queue.get_queue_id();
+            let table_offset = self
+                .consume_queue_store
+                .get_max_offset(topic, queue_id)
+                .unwrap_or(0);
+            if let Some(damage) = consume_queue_check::check_consume_queue(
+                &**consume_queue.as_ref(),
+                &self.commit_log,
+                table_offset,
+                &self.message_store_config,
+            ) {
+                let damaged_queue = DamagedConsumeQueue {
+                    topic: topic.clone(),
+                    queue_id,
+                    damage,
+                };
+                warn!("{}", damaged_queue);
+                damaged.push(damaged_queue);
+            }
+        }
+        damaged
+    }
+
+    /// Rebuilds the consume queue of `topic` and `queue_id` by dispatching the messages of the
+    /// queue in the commit log again, the other queues are left alone. Returns the number of
+    /// entries written. Not safe while messages are being dispatched.
+    pub fn rebuild_consume_queue(&mut self, topic: &CheetahString, queue_id: i32) -> usize {
+        let mut consume_queue = self
+            .consume_queue_store
+            .find_or_create_consume_queue(topic, queue_id);
+        consume_queue.destroy();
+
+        let mut rebuilt = 0;
+        let mut offset = self.commit_log.get_min_offset().max(0);
+        while let Some(result) = self.commit_log.get_data(offset) {
+            let mapped_file = result.mapped_file.as_ref().unwrap();
+            let start_pos = result.relative_pos();
+            let mut read_size = 0usize;
+            let mut file_end = false;
+            while read_size < result.size as usize {
+                let pos = start_pos + read_size;
+                let Some(mut bytes) = mapped_file
+                    .get_bytes(pos, 4)
+                    .and_then(|mut size| mapped_file.get_data(pos, size.get_i32() as usize))
+                else {
+                    break;
+                };
+                let dispatch_request = commit_log::check_message_and_return_size(
+                    &mut bytes,
+                    false,
+                    false,
+                    false,
+                    &self.message_store_config,
+                );
+                if !dispatch_request.success {
+                    error!(
+                        "rebuild consume queue {}-{} stopped at a broken message, offset={}",
+                        topic,
+                        queue_id,
+                        offset + read_size as i64
+                    );
+                    return rebuilt;
+                }
+                if dispatch_request.msg_size == 0 {
+                    file_end = true;
+                    break;
+                }
+                let tran_type = MessageSysFlag::get_transaction_value(dispatch_request.sys_flag);
+                if dispatch_request.topic == *topic
+                    && dispatch_request.queue_id == queue_id
+                    && (tran_type == MessageSysFlag::TRANSACTION_NOT_TYPE
+                        || tran_type == MessageSysFlag::TRANSACTION_COMMIT_TYPE)
+                {
+                    self.consume_queue_store
+                        .put_message_position_info_wrapper_with_cq(
+                            &mut **consume_queue.as_mut(),
+                            &dispatch_request,
+                        );
+                    rebuilt += 1;
+                }
+                read_size += dispatch_request.msg_size as usize;
+            }
+            if file_end {
+                offset = self.commit_log.roll_next_file(offset);
+            } else if read_size > 0 {
+                offset += read_size as i64;
+            } else {
+                break;
+            }
+        }
+        // the queue offset table and the min offset are derived from the queue lengths
+        self.recover_topic_queue_table();
+        info!(
+            "rebuilt consume queue {}-{} with {} entries",
+            topic, queue_id, rebuilt
+        );
+        rebuilt
+    }
+
     pub fn next_offset_correction(&self, old_offset: i64, new_offset: i64) -> i64 {
         let mut next_offset = old_offset;
         if self.message_store_config.broker_role != BrokerRole::Slave
@@ -549,6 +924,7 @@ fn is_the_batch_full(
 impl MessageStore for DefaultMessageStore {
     async fn load(&mut self) -> bool {
         let last_exit_ok = !self.is_temp_file_exist();
+        self.last_exit_ok.store(last_exit_ok, Ordering::Relaxed);
         info!(
             "last shutdown {}, store path root dir: {}",
             if last_exit_ok {
@@ -563,6 +939,15 @@ impl MessageStore for DefaultMessageStore {
         if !result {
             return result;
         }
+        match EpochFileCheckpoint::new(self.message_store_config.get_store_path_epoch_file()) {
+            Ok(epoch_file_checkpoint) => {
+                self.epoch_file_checkpoint = Some(Arc::new(epoch_file_checkpoint));
+            }
+            Err(err) => {
+                error!("load epoch file failed, {}", err);
+                return false;
+            }
+        }
         // load Consume Queue-- init Consume log mapped file queue
         result &= self.consume_queue_store.load();
 
@@ -585,7 +970,17 @@ impl MessageStore for DefaultMessageStore {
             info!(
                 "message store recover end, and the max phy offset = {}",
                 self.get_max_phy_offset()
-            )
+            );
+
+            if self.message_store_config.check_consume_queue_on_startup {
+                let damaged = self.check_consume_queues();
+                info!("consume queue check found {} damaged queues", damaged.len());
+                if self.message_store_config.repair_consume_queue_on_startup {
+                    for damaged_queue in damaged {
+                        self.rebuild_consume_queue(&damaged_queue.topic, damaged_queue.queue_id);
+                    }
+                }
+            }
         }
 
         let max_offset = self.get_max_phy_offset();
@@ -599,17 +994,13 @@ impl MessageStore for DefaultMessageStore {
     }
 
     fn start(&mut self) -> Result<(), Box<dyn Error>> {
+        self.lock_store()?;
         self.create_temp_file();
 
         self.reput_message_service
             .set_reput_from_offset(self.commit_log.get_confirm_offset());
-        self.reput_message_service.start(
-            Arc::new(self.commit_log.clone()),
-            self.message_store_config.clone(),
-            self.dispatcher.clone(),
-            self.notify_message_arrive_in_batch,
-            self.message_store_arc.clone().unwrap(),
-        );
+        self.start_reput_message_service();
+        self.flush_consume_queue_service.start();
 
         self.commit_log.start();
 
@@ -622,14 +1013,15 @@ impl MessageStore for DefaultMessageStore {
         if !self.shutdown.load(Ordering::Acquire) {
             self.shutdown.store(true, Ordering::SeqCst);
             self.reput_message_service.shutdown();
+            self.flush_consume_queue_service.shutdown();
             self.commit_log.shutdown();
 
             if self.running_flags.is_writeable() {
                 //delete abort file
-                self.delete_file(get_abort_file(
-                    self.message_store_config.store_path_root_dir.as_str(),
-                ))
+                self.delete_file(self.message_store_config.get_abort_file())
             }
+            // dropping the file releases the lock
+            self.lock_file = None;
         }
     }
 
@@ -641,6 +1033,10 @@ impl MessageStore for DefaultMessageStore {
         self.commit_log.get_max_offset()
     }
 
+    fn get_min_phy_offset(&self) -> i64 {
+        self.commit_log.get_min_offset()
+    }
+
     fn set_broker_init_max_offset(&mut self, broker_init_max_offset: i64) {
         self.broker_init_max_offset
             .store(broker_init_max_offset, Ordering::SeqCst);
@@ -650,9 +1046,22 @@ impl MessageStore for DefaultMessageStore {
         self.state_machine_version.load(Ordering::Relaxed)
     }
 
-    async fn put_message(&mut self, msg: MessageExtBrokerInner) -> PutMessageResult {
+    async fn put_message(&mut self, mut msg: MessageExtBrokerInner) -> PutMessageResult {
+        let status = self.check_store_status();
+        if status != PutMessageStatus::PutOk {
+            return PutMessageResult::new_default(status);
+        }
+        let status = Self::check_message(&msg);
+        if status != PutMessageStatus::PutOk {
+            return PutMessageResult::new_default(status);
+        }
+        let status = self.check_lmq_message(&msg);
+        if status != PutMessageStatus::PutOk {
+            return PutMessageResult::new_default(status);
+        }
+
         for hook in self.put_message_hook_list.read().iter() {
-            if let Some(result) = hook.execute_before_put_message(&msg.message_ext_inner) {
+            if let Some(result) = hook.execute_before_put_message(&mut msg) {
                 return result;
             }
         }
@@ -677,9 +1086,11 @@ impl MessageStore for DefaultMessageStore {
                 return PutMessageResult::new_default(PutMessageStatus::MessageIllegal);
             }
         }
+        let topic = msg.get_topic().clone();
         let begin_time = Instant::now();
         //put message to commit log
         let result = self.commit_log.put_message(msg).await;
+        self.record_put_metrics(&topic, begin_time.elapsed(), &result);
         let elapsed_time = begin_time.elapsed().as_millis();
         if elapsed_time > 500 {
             warn!(
@@ -697,18 +1108,33 @@ impl MessageStore for DefaultMessageStore {
         result
     }
 
-    async fn put_messages(&mut self, msg_batch: MessageExtBatch) -> PutMessageResult {
+    async fn put_messages(&mut self, mut msg_batch: MessageExtBatch) -> PutMessageResult {
+        let status = self.check_store_status();
+        if status != PutMessageStatus::PutOk {
+            return PutMessageResult::new_default(status);
+        }
+        let status = Self::check_message(&msg_batch.message_ext_broker_inner);
+        if status != PutMessageStatus::PutOk {
+            return PutMessageResult::new_default(status);
+        }
+        let status = self.check_lmq_message(&msg_batch.message_ext_broker_inner);
+        if status != PutMessageStatus::PutOk {
+            return PutMessageResult::new_default(status);
+        }
+
         for hook in self.put_message_hook_list.read().iter() {
-            if let Some(result) = hook
-                .execute_before_put_message(&msg_batch.message_ext_broker_inner.message_ext_inner)
+            if let Some(result) =
+                hook.execute_before_put_message(&mut msg_batch.message_ext_broker_inner)
             {
                 return result;
             }
         }
 
+        let topic = msg_batch.message_ext_broker_inner.get_topic().clone();
         let begin_time = Instant::now();
         //put message to commit log
         let result = self.commit_log.put_messages(msg_batch).await;
+        self.record_put_metrics(&topic, begin_time.elapsed(), &result);
         let elapsed_time = begin_time.elapsed().as_millis();
         if elapsed_time > 500 {
             warn!("not in lock eclipse time(ms) {}ms", elapsed_time,);
@@ -724,7 +1150,43 @@ impl MessageStore for DefaultMessageStore {
     }
 
     fn truncate_files(&mut self, offset_to_truncate: i64) -> bool {
-        unimplemented!()
+        let max_phy_offset = self.get_max_phy_offset();
+        if offset_to_truncate >= max_phy_offset {
+            info!(
+                "no need to truncate files, truncate offset {} >= max phy offset {}",
+                offset_to_truncate, max_phy_offset
+            );
+            return false;
+        }
+
+        // dispatching stops before the files it reads and writes are cut
+        let reput_running = self.reput_message_service.is_running();
+        self.reput_message_service.shutdown();
+        let old_reput_from_offset = self.reput_message_service.reput_from_offset();
+
+        self.truncate_dirty_logic_files(offset_to_truncate);
+        self.index_service.truncate_dirty(offset_to_truncate);
+        self.commit_log.truncate_dirty_files(offset_to_truncate);
+        self.recover_topic_queue_table();
+        if let Some(epoch_file_checkpoint) = self.epoch_file_checkpoint.as_ref() {
+            if let Err(err) = epoch_file_checkpoint.truncate_suffix_by_offset(offset_to_truncate) {
+                error!(
+                    "truncate epoch file to offset {} failed, {}",
+                    offset_to_truncate, err
+                );
+            }
+        }
+
+        self.reput_message_service
+            .set_reput_from_offset(old_reput_from_offset.min(offset_to_truncate));
+        if reput_running {
+            self.start_reput_message_service();
+        }
+        info!(
+            "truncate files to offset {}, max phy offset was {}",
+            offset_to_truncate, max_phy_offset
+        );
+        true
     }
 
     fn is_os_page_cache_busy(&self) -> bool {
@@ -733,13 +1195,16 @@ impl MessageStore for DefaultMessageStore {
         diff < 10000000 && diff > self.message_store_config.os_page_cache_busy_timeout_mills
     }
 
+    fn get_message_store_config(&self) -> &MessageStoreConfig {
+        self.message_store_config.as_ref()
+    }
+
     fn get_running_flags(&self) -> &RunningFlags {
         self.running_flags.as_ref()
     }
 
     fn is_shutdown(&self) -> bool {
-        // todo!()
-        false
+        self.shutdown.load(Ordering::Acquire)
     }
 
     fn get_put_message_hook_list(&self) -> Arc<parking_lot::RwLock<Vec<BoxedPutMessageHook>>> {
@@ -755,7 +1220,7 @@ impl MessageStore for DefaultMessageStore {
     }
 
     fn dispatch_behind_bytes(&self) -> i64 {
-        0
+        self.reput_message_service.behind(&self.commit_log)
     }
 
     fn get_min_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
@@ -767,6 +1232,20 @@ impl MessageStore for DefaultMessageStore {
         self.get_max_offset_in_queue_committed(topic, queue_id, true)
     }
 
+    fn get_offset_in_queue_by_time(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        timestamp: i64,
+    ) -> i64 {
+        match self.find_consume_queue(topic, queue_id) {
+            Some(consume_queue) => {
+                consume_queue.get_offset_in_queue_by_time(timestamp, &self.commit_log)
+            }
+            None => 0,
+        }
+    }
+
     fn get_max_offset_in_queue_committed(
         &self,
         topic: &CheetahString,
@@ -952,6 +1431,27 @@ impl MessageStore for DefaultMessageStore {
                                     self.commit_log.roll_next_file(offset_py);
                                 continue;
                             }
+                            let Ok(buffer) = select_result.as_ref().unwrap().get_buffer() else {
+                                if get_result_ref.buffer_total_size() == 0 {
+                                    status = GetMessageStatus::MessageWasRemoving;
+                                }
+                                next_phy_file_start_offset =
+                                    self.commit_log.roll_next_file(offset_py);
+                                continue;
+                            };
+                            if self.message_store_config.check_crc_on_recover
+                                && !commit_log::body_crc_matches(&buffer)
+                            {
+                                warn!(
+                                    "skip corrupt message, body CRC check failed. topic={}, \
+                                     queueId={}, queueOffset={}, phyOffset={}",
+                                    topic, queue_id, cq_unit.queue_offset, offset_py
+                                );
+                                self.store_stats_service
+                                    .get_message_crc_failed_count()
+                                    .fetch_add(1, Ordering::Relaxed);
+                                continue;
+                            }
                             if self.message_store_config.cold_data_flow_control_enable
                                 && !is_sys_consumer_group_for_no_cold_read_limit(group)
                                 && !select_result.as_ref().unwrap().is_in_cache
@@ -962,10 +1462,10 @@ impl MessageStore for DefaultMessageStore {
                             }
 
                             if message_filter.is_some()
-                                && !message_filter.as_ref().unwrap().is_matched_by_commit_log(
-                                    Some(select_result.as_ref().unwrap().get_buffer()),
-                                    None,
-                                )
+                                && !message_filter
+                                    .as_ref()
+                                    .unwrap()
+                                    .is_matched_by_commit_log(Some(&buffer), None)
                             {
                                 if get_result_ref.buffer_total_size() == 0 {
                                     status = GetMessageStatus::NoMatchedMessage;
@@ -1011,6 +1511,15 @@ impl MessageStore for DefaultMessageStore {
             self.store_stats_service
                 .get_message_times_total_found()
                 .fetch_add(1, Ordering::Relaxed);
+            if let (Some(metrics), Some(result)) =
+                (self.store_metrics_manager.as_ref(), get_result.as_ref())
+            {
+                metrics.inc_get_message(
+                    topic.as_str(),
+                    result.message_count() as u64,
+                    result.buffer_total_size() as u64,
+                );
+            }
         } else {
             self.store_stats_service
                 .get_message_times_total_miss()
@@ -1063,6 +1572,50 @@ impl MessageStore for DefaultMessageStore {
         self.check_in_mem_by_commit_offset(start_offset_py, size as i32)
     }
 
+    fn check_in_cold_area_by_consume_offset(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_offset: i64,
+    ) -> bool {
+        let consume_queue = self
+            .consume_queue_store
+            .find_or_create_consume_queue(topic, queue_id);
+        match consume_queue.get(consume_offset) {
+            None => false,
+            Some(cq_item) => !estimate_in_mem_by_commit_offset(
+                cq_item.pos,
+                self.commit_log.get_max_offset(),
+                &self.message_store_config,
+            ),
+        }
+    }
+
+    fn get_commit_log_offset_in_queue(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_queue_offset: i64,
+    ) -> i64 {
+        self.find_consume_queue(topic, queue_id)
+            .and_then(|consume_queue| consume_queue.get(consume_queue_offset))
+            .map_or(0, |cq_unit| cq_unit.pos)
+    }
+
+    fn estimate_message_count(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        from: i64,
+        to: i64,
+        filter: &dyn MessageFilter,
+    ) -> i64 {
+        self.find_consume_queue(topic, queue_id)
+            .map_or(0, |consume_queue| {
+                consume_queue.estimate_message_count(from, to, filter)
+            })
+    }
+
     fn notify_message_arrive_if_necessary(&self, dispatch_request: &mut DispatchRequest) {
         if self.broker_config.long_polling_enable && self.message_arriving_listener.is_some() {
             self.message_arriving_listener.as_ref().unwrap().arriving(
@@ -1071,7 +1624,7 @@ impl MessageStore for DefaultMessageStore {
                 dispatch_request.consume_queue_offset + 1,
                 Some(dispatch_request.tags_code),
                 dispatch_request.store_timestamp,
-                dispatch_request.bit_map.clone(),
+                dispatch_request.bit_map.as_deref(),
                 dispatch_request.properties_map.as_ref(),
             );
             self.reput_message_service
@@ -1114,13 +1667,17 @@ impl MessageStore for DefaultMessageStore {
                     .on_topic_deleted(topic);
             }
 
-            let root_dir = self.message_store_config.store_path_root_dir.as_str();
             let consume_queue_dir =
-                PathBuf::from(get_store_path_consume_queue(root_dir)).join(topic.as_str());
+                PathBuf::from(self.message_store_config.get_store_path_consume_queue())
+                    .join(topic.as_str());
             let consume_queue_ext_dir =
-                PathBuf::from(get_store_path_consume_queue_ext(root_dir)).join(topic.as_str());
-            let batch_consume_queue_dir =
-                PathBuf::from(get_store_path_batch_consume_queue(root_dir)).join(topic.as_str());
+                PathBuf::from(self.message_store_config.get_store_path_consume_queue_ext())
+                    .join(topic.as_str());
+            let batch_consume_queue_dir = PathBuf::from(
+                self.message_store_config
+                    .get_store_path_batch_consume_queue(),
+            )
+            .join(topic.as_str());
 
             util_all::delete_empty_directory(consume_queue_dir);
             util_all::delete_empty_directory(consume_queue_ext_dir);
@@ -1141,13 +1698,13 @@ impl MessageStore for DefaultMessageStore {
     ) -> Option<QueryMessageResult> {
         let mut query_message_result = QueryMessageResult::default();
         let mut last_query_msg_time = end_timestamp;
-        for i in 1..3 {
+        for _ in 1..3 {
             let mut query_offset_result = self.index_service.query_offset(
                 topic,
                 key,
                 max_num,
                 begin_timestamp,
-                end_timestamp,
+                last_query_msg_time,
             );
             if query_offset_result.get_phy_offsets().is_empty() {
                 break;
@@ -1160,14 +1717,13 @@ impl MessageStore for DefaultMessageStore {
             query_message_result.index_last_update_phyoffset =
                 query_offset_result.get_index_last_update_phyoffset();
             let phy_offsets = query_offset_result.get_phy_offsets();
-            for m in 0..phy_offsets.len() {
-                let offset = *phy_offsets.get(m).unwrap();
-                let msg = self.look_message_by_offset(offset);
+            for (m, &offset) in phy_offsets.iter().enumerate() {
                 if m == 0 {
-                    last_query_msg_time = msg.as_ref().unwrap().store_timestamp;
+                    if let Some(msg) = self.look_message_by_offset(offset) {
+                        last_query_msg_time = msg.store_timestamp;
+                    }
                 }
-                let result = self.commit_log.get_data_with_option(offset, false);
-                if let Some(sbr) = result {
+                if let Some(sbr) = self.select_one_message_by_offset(offset).await {
                     query_message_result.add_message(sbr);
                 }
             }
@@ -1188,7 +1744,7 @@ impl MessageStore for DefaultMessageStore {
     ) -> Option<SelectMappedBufferResult> {
         let sbr = self.commit_log.get_message(commit_log_offset, 4);
         if let Some(sbr) = sbr {
-            let size = sbr.get_buffer().get_i32();
+            let size = (&sbr.get_buffer().ok()?[..]).get_i32();
             self.commit_log.get_message(commit_log_offset, size)
         } else {
             None
@@ -1204,7 +1760,7 @@ impl MessageStore for DefaultMessageStore {
 
     fn look_message_by_offset(&self, commit_log_offset: i64) -> Option<MessageExt> {
         if let Some(sbr) = self.commit_log.get_message(commit_log_offset, 4) {
-            let size = sbr.get_buffer().get_i32();
+            let size = (&sbr.get_buffer().ok()?[..]).get_i32();
             self.look_message_by_offset_with_size(commit_log_offset, size)
         } else {
             None
@@ -1234,21 +1790,64 @@ impl MessageStore for DefaultMessageStore {
         queue_id: i32,
         consume_queue_offset: i64,
     ) -> i64 {
-        let consume_queue = self.find_consume_queue(topic, queue_id);
-        if let Some(consume_queue) = consume_queue {
-            if let Some((_, store_time)) =
-                consume_queue.get_cq_unit_and_store_time(consume_queue_offset)
-            {
-                store_time
-            } else {
-                -1
-            }
-        } else {
-            -1
-        }
+        let Some(consume_queue) = self.find_consume_queue(topic, queue_id) else {
+            return -1;
+        };
+        consume_queue
+            .get(consume_queue_offset)
+            .map_or(-1, |cq_unit| {
+                self.commit_log
+                    .pickup_store_timestamp(cq_unit.pos, cq_unit.size)
+            })
     }
     fn get_runtime_info(&self) -> HashMap<String, String> {
-        self.store_stats_service.get_runtime_info()
+        let mut runtime_info = self.store_stats_service.get_runtime_info();
+        let store_path_physic = Self::get_store_path_physic(&self.message_store_config);
+        let ratios = store_path_disk_ratios(&store_path_physic);
+        runtime_info.insert(
+            "commitLogDiskRatio".to_string(),
+            min_disk_ratio(&ratios).to_string(),
+        );
+        runtime_info.insert(
+            "dispatchBehindBytes".to_string(),
+            self.dispatch_behind_bytes().to_string(),
+        );
+        runtime_info.insert(
+            "flushBehindBytes".to_string(),
+            self.remain_how_many_data_to_flush().to_string(),
+        );
+        runtime_info.insert(
+            "lastBootAbnormal".to_string(),
+            (!self.is_last_exit_ok()).to_string(),
+        );
+        runtime_info.insert(
+            "recoverDispatchCount".to_string(),
+            self.commit_log.recover_dispatch_count().to_string(),
+        );
+        runtime_info.insert(
+            "putMessageLockTimeMills".to_string(),
+            self.commit_log.lock_time_mills().to_string(),
+        );
+        if let Some(checkpoint) = self.store_checkpoint.as_ref() {
+            runtime_info.insert(
+                "checkpointPhysicMsgTimestamp".to_string(),
+                checkpoint.physic_msg_timestamp().to_string(),
+            );
+            runtime_info.insert(
+                "checkpointLogicsMsgTimestamp".to_string(),
+                checkpoint.logics_msg_timestamp().to_string(),
+            );
+            runtime_info.insert(
+                "checkpointIndexMsgTimestamp".to_string(),
+                checkpoint.index_msg_timestamp().to_string(),
+            );
+        }
+        if ratios.len() > 1 {
+            for (path, ratio) in &ratios {
+                runtime_info.insert(format!("commitLogDiskRatio_{}", path), ratio.to_string());
+            }
+        }
+        runtime_info
     }
 
     fn lock_time_mills(&self) -> i64 {
@@ -1259,6 +1858,18 @@ impl MessageStore for DefaultMessageStore {
         -1
     }
 
+    fn get_earliest_message_time_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
+        let Some(consume_queue) = self.find_consume_queue(topic, queue_id) else {
+            return -1;
+        };
+        consume_queue
+            .get(consume_queue.get_min_offset_in_queue())
+            .map_or(-1, |cq_unit| {
+                self.commit_log
+                    .pickup_store_timestamp(cq_unit.pos, cq_unit.size)
+            })
+    }
+
     fn get_timer_message_store(&self) -> Arc<TimerMessageStore> {
         self.timer_message_store.clone()
     }
@@ -1287,22 +1898,42 @@ impl MessageStore for DefaultMessageStore {
 pub struct CommitLogDispatcherDefault {
     /*build_index: CommitLogDispatcherBuildIndex,
     build_consume_queue: CommitLogDispatcherBuildConsumeQueue,*/
-    dispatcher_vec: Arc<Vec<Box<dyn CommitLogDispatcher>>>,
+    dispatcher_vec: Arc<parking_lot::RwLock<Vec<Box<dyn CommitLogDispatcher>>>>,
+}
+
+impl CommitLogDispatcherDefault {
+    fn add_dispatcher(&self, dispatcher: Box<dyn CommitLogDispatcher>) {
+        self.dispatcher_vec.write().push(dispatcher);
+    }
+
+    fn add_first_dispatcher(&self, dispatcher: Box<dyn CommitLogDispatcher>) {
+        self.dispatcher_vec.write().insert(0, dispatcher);
+    }
 }
 
 impl CommitLogDispatcher for CommitLogDispatcherDefault {
-    fn dispatch(&self, dispatch_request: &DispatchRequest) {
+    fn dispatch(&self, dispatch_request: &mut DispatchRequest) {
         /*self.build_index.dispatch(dispatch_request);
         self.build_consume_queue.dispatch(dispatch_request);*/
-        for dispatcher in self.dispatcher_vec.iter() {
-            dispatcher.dispatch(dispatch_request);
+        for dispatcher in self.dispatcher_vec.read().iter() {
+            // a misbehaving dispatcher must not stop the reput task
+            if panic::catch_unwind(AssertUnwindSafe(|| dispatcher.dispatch(dispatch_request)))
+                .is_err()
+            {
+                error!(
+                    "dispatcher panicked, skip it. topic={}, queueId={}, commitLogOffset={}",
+                    dispatch_request.topic,
+                    dispatch_request.queue_id,
+                    dispatch_request.commit_log_offset
+                );
+            }
         }
     }
 }
 #[derive(Clone)]
 struct ReputMessageService {
-    tx: Option<Arc<Sender<()>>>,
-    reput_from_offset: Option<Arc<AtomicI64>>,
+    service: Arc<ServiceTask>,
+    reput_from_offset: Arc<AtomicI64>,
     message_store_config: Arc<MessageStoreConfig>,
     inner: Option<ReputMessageServiceInner>,
 }
@@ -1331,7 +1962,7 @@ impl ReputMessageService {
             .unwrap()
             .split(MULTI_DISPATCH_QUEUE_SPLITTER)
             .collect();
-        let queue_offsets: Vec<&str> = multi_dispatch_queue
+        let queue_offsets: Vec<&str> = multi_queue_offset
             .unwrap()
             .split(MULTI_DISPATCH_QUEUE_SPLITTER)
             .collect();
@@ -1341,7 +1972,9 @@ impl ReputMessageService {
         let reput_message_service_inner = self.inner.as_ref().unwrap();
         for i in 0..queues.len() {
             let queue_name = CheetahString::from_slice(queues[i]);
-            let queue_offset: i64 = queue_offsets[i].parse().unwrap();
+            let Ok(queue_offset) = queue_offsets[i].parse::<i64>() else {
+                continue;
+            };
             let mut queue_id = dispatch_request.queue_id;
             if self.message_store_config.enable_lmq && is_lmq(Some(queue_name.as_str())) {
                 queue_id = 0;
@@ -1357,14 +1990,28 @@ impl ReputMessageService {
                     queue_offset + 1,
                     Some(dispatch_request.tags_code),
                     dispatch_request.store_timestamp,
-                    dispatch_request.bit_map.clone(),
+                    dispatch_request.bit_map.as_deref(),
                     dispatch_request.properties_map.as_ref(),
                 );
         }
     }
 
     pub fn set_reput_from_offset(&mut self, reput_from_offset: i64) {
-        self.reput_from_offset = Some(Arc::new(AtomicI64::new(reput_from_offset)));
+        self.reput_from_offset
+            .store(reput_from_offset, Ordering::Release);
+    }
+
+    pub fn reput_from_offset(&self) -> i64 {
+        self.reput_from_offset.load(Ordering::Acquire)
+    }
+
+    fn is_running(&self) -> bool {
+        self.inner.is_some() && !self.service.is_stopped()
+    }
+
+    /// Bytes of the commit log not yet dispatched to the consume queues.
+    pub fn behind(&self, commit_log: &CommitLog) -> i64 {
+        commit_log.get_confirm_offset() - self.reput_from_offset.load(Ordering::Acquire)
     }
 
     pub fn start(
@@ -1376,7 +2023,7 @@ impl ReputMessageService {
         message_store: ArcMut<DefaultMessageStore>,
     ) {
         let mut inner = ReputMessageServiceInner {
-            reput_from_offset: self.reput_from_offset.clone().unwrap(),
+            reput_from_offset: self.reput_from_offset.clone(),
             commit_log,
             message_store_config,
             dispatcher,
@@ -1384,55 +2031,37 @@ impl ReputMessageService {
             message_store,
         };
         self.inner = Some(inner.clone());
-        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
-        self.tx = Some(Arc::new(tx));
-        let handle = tokio::spawn(async move {
-            let mut interval = tokio::time::interval(Duration::from_millis(1));
-            let mut break_flag = false;
-            loop {
-                tokio::select! {
-                    _ = inner.do_reput() => {}
-                    _ = rx.recv() => {
-                        let mut index = 0;
-                        while index < 50 && inner.is_commit_log_available() {
-                            tokio::time::sleep(Duration::from_millis(100)).await;
-                            if inner.is_commit_log_available() {
-                                warn!(
-                                    "shutdown ReputMessageService, but CommitLog have not finish to be \
-                                     dispatched, CommitLog max offset={}, reputFromOffset={}",
-                                    inner.commit_log.get_max_offset(),
-                                    inner.reput_from_offset.load(Ordering::Relaxed)
-                                );
-                            }
-                            index += 1;
-                        }
-                        info!("ReputMessageService shutdown now......");
-                        break_flag = true;
-                    }
-                }
-                if break_flag {
-                    break;
+        if self.service.is_stopped() {
+            // a stopped task does not run again, dispatching resumes on a new one
+            self.service = Arc::new(ServiceTask::new(self.service.service_name()));
+        }
+        self.service.start(move |context| async move {
+            info!("{} service started", context.service_name());
+            while !context.is_stopped() {
+                inner.do_reput().await;
+                context.wait_for_running(Duration::from_millis(1)).await;
+            }
+            // keep dispatching for a while, the consume queues should catch up with the commit log
+            let mut index = 0;
+            while index < 50 && inner.is_commit_log_available() {
+                inner.do_reput().await;
+                if inner.is_commit_log_available() {
+                    warn!(
+                        "shutdown ReputMessageService, but CommitLog have not finish to be \
+                         dispatched, CommitLog max offset={}, reputFromOffset={}",
+                        inner.commit_log.get_max_offset(),
+                        inner.reput_from_offset.load(Ordering::Relaxed)
+                    );
+                    tokio::time::sleep(Duration::from_millis(100)).await;
                 }
-                interval.tick().await;
+                index += 1;
             }
+            info!("ReputMessageService shutdown now......");
         });
     }
 
     pub fn shutdown(&mut self) {
-        let handle = Handle::current();
-        let tx_option = self.tx.take();
-        let _ = thread::spawn(move || {
-            handle.block_on(async move {
-                if let Some(tx) = tx_option {
-                    tokio::select! {
-                      _ =  tx.send(()) =>{
-
-                        }
-                    }
-                }
-            });
-        })
-        .join();
+        self.service.shutdown(false);
     }
 }
 
@@ -1476,7 +2105,9 @@ impl ReputMessageServiceInner {
         }
         for i in 0..queues.len() {
             let queue_name = CheetahString::from_slice(queues[i]);
-            let queue_offset: i64 = queue_offsets[i].parse().unwrap();
+            let Ok(queue_offset) = queue_offsets[i].parse::<i64>() else {
+                continue;
+            };
             let mut queue_id = dispatch_request.queue_id;
             if self.message_store_config.enable_lmq && is_lmq(Some(queue_name.as_str())) {
                 queue_id = 0;
@@ -1491,7 +2122,7 @@ impl ReputMessageServiceInner {
                     queue_offset + 1,
                     Some(dispatch_request.tags_code),
                     dispatch_request.store_timestamp,
-                    dispatch_request.bit_map.clone(),
+                    dispatch_request.bit_map.as_deref(),
                     dispatch_request.properties_map.as_ref(),
                 );
         }
@@ -1554,7 +2185,7 @@ impl ReputMessageServiceInner {
                 if dispatch_request.success {
                     match dispatch_request.msg_size.cmp(&0) {
                         std::cmp::Ordering::Greater => {
-                            self.dispatcher.dispatch(&dispatch_request);
+                            self.dispatcher.dispatch(&mut dispatch_request);
                             if !self.notify_message_arrive_in_batch {
                                 self.message_store
                                     .notify_message_arrive_if_necessary(&mut dispatch_request);
@@ -1617,19 +2248,228 @@ impl ReputMessageServiceInner {
     }
 }
 
-struct CleanCommitLogService {}
+const RETRY_TIMES_OVER: i32 = 3;
+
+/// Periodically flushes every consume queue and records the logics timestamp in the checkpoint.
+struct FlushConsumeQueueService {
+    service: ServiceTask,
+    inner: Arc<FlushConsumeQueueServiceInner>,
+}
+
+impl FlushConsumeQueueService {
+    fn new(
+        message_store_config: Arc<MessageStoreConfig>,
+        consume_queue_store: ConsumeQueueStore,
+        store_checkpoint: Arc<StoreCheckpoint>,
+    ) -> Self {
+        Self {
+            service: ServiceTask::new("FlushConsumeQueueService"),
+            inner: Arc::new(FlushConsumeQueueServiceInner {
+                message_store_config,
+                consume_queue_store,
+                store_checkpoint,
+                last_flush_timestamp: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    fn start(&mut self) {
+        let inner = self.inner.clone();
+        let interval = inner.message_store_config.flush_interval_consume_queue as u64;
+        self.service.start(move |context| async move {
+            info!("{} service started", context.service_name());
+            while !context.is_stopped() {
+                context
+                    .wait_for_running(Duration::from_millis(interval))
+                    .await;
+                if context.is_stopped() {
+                    break;
+                }
+                inner.do_flush(1, get_current_millis());
+            }
+            info!("{} service end", context.service_name());
+        });
+    }
+
+    fn shutdown(&mut self) {
+        // The final flush happens in place so that every consume queue is on disk before the
+        // store goes away, even when the run loop could not be joined.
+        self.service.shutdown(false);
+        if self.service.is_stopped() {
+            self.inner.do_flush(RETRY_TIMES_OVER, get_current_millis());
+        }
+    }
+}
+
+struct FlushConsumeQueueServiceInner {
+    message_store_config: Arc<MessageStoreConfig>,
+    consume_queue_store: ConsumeQueueStore,
+    store_checkpoint: Arc<StoreCheckpoint>,
+    last_flush_timestamp: AtomicU64,
+}
+
+impl FlushConsumeQueueServiceInner {
+    fn do_flush(&self, retry_times: i32, now: u64) {
+        let mut flush_consume_queue_least_pages =
+            self.message_store_config.flush_consume_queue_least_pages as i32;
+        if retry_times == RETRY_TIMES_OVER {
+            flush_consume_queue_least_pages = 0;
+        }
+
+        let mut logics_msg_timestamp = 0;
+        let flush_consume_queue_thorough_interval =
+            self.message_store_config
+                .flush_consume_queue_thorough_interval as u64;
+        if now
+            >= self.last_flush_timestamp.load(Ordering::Relaxed)
+                + flush_consume_queue_thorough_interval
+        {
+            self.last_flush_timestamp.store(now, Ordering::Relaxed);
+            flush_consume_queue_least_pages = 0;
+            logics_msg_timestamp = self.store_checkpoint.logics_msg_timestamp();
+        }
+
+        // take a snapshot so the table lock is not held while the files are forced to disk
+        let consume_queues: Vec<ArcConsumeQueue> = self
+            .consume_queue_store
+            .get_consume_queue_table()
+            .lock()
+            .values()
+            .flat_map(|queues| queues.values().cloned())
+            .collect();
+        for consume_queue in consume_queues {
+            let mut result = false;
+            let mut i = 0;
+            while i < retry_times && !result {
+                result = self
+                    .consume_queue_store
+                    .flush(&**consume_queue, flush_consume_queue_least_pages);
+                i += 1;
+            }
+        }
+
+        if flush_consume_queue_least_pages == 0 {
+            if logics_msg_timestamp > 0 {
+                self.store_checkpoint
+                    .set_logics_msg_timestamp(logics_msg_timestamp);
+            }
+            if let Err(err) = self.store_checkpoint.flush() {
+                error!("flush store checkpoint error: {:?}", err);
+            }
+        }
+    }
+}
+
+/// Measures the used ratio of the disk holding each path of `store_path`, which may name several
+/// directories separated by
+/// [`MULTI_PATH_SPLITTER`](rocketmq_common::common::mix_all::MULTI_PATH_SPLITTER).
+pub(crate) fn store_path_disk_ratios(store_path: &str) -> Vec<(&str, f64)> {
+    split_store_path(store_path)
+        .into_iter()
+        .map(|path| (path, util_all::get_disk_partition_space_used_percent(path)))
+        .collect()
+}
+
+/// The usage of a multi-disk store is the one of its emptiest disk, it is full only once every
+/// disk is. `-1.0` if no disk could be measured.
+pub(crate) fn min_disk_ratio(ratios: &[(&str, f64)]) -> f64 {
+    ratios
+        .iter()
+        .map(|(_, ratio)| *ratio)
+        .filter(|ratio| *ratio >= 0.0)
+        .reduce(f64::min)
+        .unwrap_or(-1.0)
+}
+
+struct CleanCommitLogService {
+    message_store_config: Arc<MessageStoreConfig>,
+    commit_log: CommitLog,
+    running_flags: Arc<RunningFlags>,
+}
 
 impl CleanCommitLogService {
     fn run(&self) {
-        info!("clean commit log service run unimplemented!")
+        let store_path_physic =
+            DefaultMessageStore::get_store_path_physic(&self.message_store_config);
+        let ratios = store_path_disk_ratios(&store_path_physic);
+        self.commit_log
+            .set_full_store_paths(self.full_store_paths(&ratios));
+        self.is_space_to_delete(min_disk_ratio(&ratios));
+    }
+
+    /// The store paths over `disk_max_used_space_ratio`, new commit log files go elsewhere.
+    fn full_store_paths(&self, ratios: &[(&str, f64)]) -> HashSet<String> {
+        let max_ratio = self.message_store_config.disk_max_used_space_ratio as f64 / 100.0;
+        ratios
+            .iter()
+            .filter(|(_, ratio)| *ratio > max_ratio)
+            .map(|(path, _)| path.to_string())
+            .collect()
+    }
+
+    /// Marks the disk full while `physic_ratio` is above `disk_max_used_space_ratio` and ok
+    /// again once enough space has been reclaimed, returning whether space should be reclaimed.
+    fn is_space_to_delete(&self, physic_ratio: f64) -> bool {
+        let ratio = self.message_store_config.disk_max_used_space_ratio as f64 / 100.0;
+        if physic_ratio > ratio {
+            if self.running_flags.get_and_make_disk_full() {
+                error!(
+                    "physic disk maybe full soon {}, so mark disk full, diskMaxUsedSpaceRatio {}",
+                    physic_ratio, ratio
+                );
+            }
+            return true;
+        }
+        if physic_ratio >= 0.0 && !self.running_flags.get_and_make_disk_ok() {
+            info!("physic disk space OK {}, so mark disk ok", physic_ratio);
+        }
+        physic_ratio < 0.0
     }
 }
 
-struct CleanConsumeQueueService {}
+struct CleanConsumeQueueService {
+    commit_log: CommitLog,
+    consume_queue_store: ConsumeQueueStore,
+    index_service: IndexService,
+    last_physical_min_offset: AtomicI64,
+}
 
 impl CleanConsumeQueueService {
     fn run(&self) {
-        println!("clean consume queue service run unimplemented!")
+        self.delete_expired_files();
+    }
+
+    /// Deletes the consume queue files, their ext files included, and the index files that only
+    /// point below the min offset of the commit log.
+    fn delete_expired_files(&self) {
+        let min_offset = self.commit_log.get_min_offset();
+        if min_offset <= self.last_physical_min_offset.load(Ordering::Acquire) {
+            return;
+        }
+        self.last_physical_min_offset
+            .store(min_offset, Ordering::Release);
+        let consume_queues: Vec<ArcConsumeQueue> = self
+            .consume_queue_store
+            .get_consume_queue_table()
+            .lock()
+            .values()
+            .flat_map(|queues| queues.values().cloned())
+            .collect();
+        for consume_queue in consume_queues {
+            let deleted = self
+                .consume_queue_store
+                .delete_expired_file(&**consume_queue, min_offset);
+            if deleted > 0 {
+                info!(
+                    "delete {} expired files of consume queue {}-{}, commit log min offset {}",
+                    deleted,
+                    consume_queue.get_topic(),
+                    consume_queue.get_queue_id(),
+                    min_offset
+                );
+            }
+        }
+        self.index_service.delete_expired_file(min_offset as u64);
     }
 }
 
@@ -1640,3 +2480,1468 @@ impl CorrectLogicOffsetService {
         println!("correct logic offset service run unimplemented!")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::sync::atomic::AtomicUsize;
+
+    use rocketmq_common::common::boundary_type::BoundaryType;
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::message::message_decoder;
+    use rocketmq_common::common::message::message_decoder::message_properties_to_string;
+    use rocketmq_common::common::message::message_single::tags_string2tags_code;
+    use rocketmq_common::common::message::MessageTrait;
+    use rocketmq_common::MessageAccessor::MessageAccessor;
+
+    use super::*;
+    use crate::config::flush_disk_type::FlushDiskType;
+    use crate::consume_queue::consume_queue_ext::CqExtUnit;
+    use crate::hook::put_message_hook::PutMessageHook;
+    use crate::metrics::default_store_metrics_constant::COUNTER_PUT_MESSAGES_TOTAL;
+    use crate::metrics::default_store_metrics_constant::GAUGE_DISPATCH_BEHIND_BYTES;
+    use crate::metrics::default_store_metrics_constant::GAUGE_LAST_BOOT_ABNORMAL;
+    use crate::metrics::default_store_metrics_constant::GAUGE_RECOVER_DISPATCH_MESSAGES;
+    use crate::metrics::default_store_metrics_constant::HISTOGRAM_PUT_LATENCY;
+    use crate::metrics::default_store_metrics_constant::HISTOGRAM_PUT_MESSAGE_LOCK_WAIT;
+    use crate::metrics::default_store_metrics_constant::PUT_LATENCY_BUCKETS;
+    use crate::metrics::default_store_metrics_constant::PUT_MESSAGE_LOCK_WAIT_BUCKETS;
+    use crate::queue::consume_queue_check::ConsumeQueueDamage;
+    use crate::queue::single_consume_queue::CQ_STORE_UNIT_SIZE;
+    use crate::test_util;
+    use crate::test_util::wait_dispatched;
+
+    fn dispatch_request(
+        topic: &str,
+        queue_id: i32,
+        consume_queue_offset: i64,
+        store_timestamp: i64,
+    ) -> DispatchRequest {
+        DispatchRequest {
+            topic: CheetahString::from_slice(topic),
+            queue_id,
+            commit_log_offset: consume_queue_offset * 100,
+            msg_size: 100,
+            store_timestamp,
+            consume_queue_offset,
+            ..DispatchRequest::default()
+        }
+    }
+
+    fn encoded_message(size: usize, store_timestamp: i64) -> Vec<u8> {
+        let mut data = vec![0u8; size];
+        data[..4].copy_from_slice(&(size as i32).to_be_bytes());
+        data[56..64].copy_from_slice(&store_timestamp.to_be_bytes());
+        data
+    }
+
+    #[tokio::test]
+    async fn get_offset_in_queue_by_time_searches_across_mapped_files() {
+        const MSG_SIZE: i32 = 100;
+        // the first commit log file holding queue offsets 0..10 has already been deleted
+        const FIRST_PHY_OFFSET: i64 = 1000;
+        const DELETED: i64 = 10;
+        const TOTAL: i64 = 40;
+
+        let dir = tempfile::tempdir().unwrap();
+        let message_store_config = Arc::new(MessageStoreConfig {
+            store_path_root_dir: CheetahString::from_string(
+                dir.path().to_string_lossy().to_string(),
+            ),
+            mapped_file_size_commit_log: 1000,
+            mapped_file_size_consume_queue: 200,
+            ..MessageStoreConfig::default()
+        });
+        let mut store = DefaultMessageStore::new(
+            message_store_config,
+            Arc::new(BrokerConfig::default()),
+            Arc::new(parking_lot::Mutex::new(HashMap::new())),
+            None,
+            false,
+        );
+        let topic = CheetahString::from_static_str("TimeTopic");
+        // two messages share every timestamp: 10_000, 10_000, 10_100, 10_100, ...
+        let store_timestamp = |queue_offset: i64| 10_000 + (queue_offset / 2) * 100;
+        for queue_offset in 0..TOTAL {
+            let phy_offset = queue_offset * MSG_SIZE as i64;
+            if queue_offset >= DELETED {
+                assert!(
+                    store
+                        .commit_log
+                        .append_data(
+                            phy_offset,
+                            &encoded_message(MSG_SIZE as usize, store_timestamp(queue_offset)),
+                        )
+                        .await
+                );
+            }
+            store
+                .consume_queue_store
+                .put_message_position_info_wrapper(&DispatchRequest {
+                    topic: topic.clone(),
+                    queue_id: 0,
+                    commit_log_offset: phy_offset,
+                    msg_size: MSG_SIZE,
+                    consume_queue_offset: queue_offset,
+                    ..DispatchRequest::default()
+                });
+        }
+        assert_eq!(store.commit_log.get_min_offset(), FIRST_PHY_OFFSET);
+        let consume_queue = store.find_consume_queue(&topic, 0).unwrap();
+        assert_eq!(consume_queue.get_min_offset_in_queue(), 0);
+        assert_eq!(consume_queue.get_max_offset_in_queue(), TOTAL);
+
+        let by_time = |timestamp: i64| store.get_offset_in_queue_by_time(&topic, 0, timestamp);
+        // before every readable message: the first entry still backed by the commit log
+        assert_eq!(by_time(0), DELETED);
+        assert_eq!(by_time(store_timestamp(DELETED)), DELETED);
+        // exact hits return the first message of the pair
+        assert_eq!(by_time(store_timestamp(24)), 24);
+        assert_eq!(by_time(store_timestamp(31)), 30);
+        // in between two timestamps: the next stored message
+        assert_eq!(by_time(store_timestamp(24) + 1), 26);
+        // after the last message: the max offset
+        assert_eq!(by_time(store_timestamp(TOTAL - 1)), TOTAL - 2);
+        assert_eq!(by_time(store_timestamp(TOTAL - 1) + 1), TOTAL);
+
+        let upper = |timestamp: i64| {
+            consume_queue.get_offset_in_queue_by_time_boundary(
+                timestamp,
+                BoundaryType::Upper,
+                &store.commit_log,
+            )
+        };
+        assert_eq!(upper(store_timestamp(24)), 25);
+        assert_eq!(upper(store_timestamp(24) + 1), 25);
+        assert_eq!(upper(i64::MAX), TOTAL - 1);
+        assert_eq!(upper(0), DELETED);
+    }
+
+    #[test]
+    fn flush_consume_queue_service_flushes_dirty_queues() {
+        let dir = tempfile::tempdir().unwrap();
+        let store_path_root_dir = dir.path().to_string_lossy().to_string();
+        let message_store_config = Arc::new(MessageStoreConfig {
+            store_path_root_dir: CheetahString::from_string(store_path_root_dir.clone()),
+            flush_consume_queue_least_pages: 2,
+            flush_consume_queue_thorough_interval: 60_000,
+            ..MessageStoreConfig::default()
+        });
+        let checkpoint_path = message_store_config.get_store_checkpoint();
+        let store_checkpoint = Arc::new(StoreCheckpoint::new(checkpoint_path.as_str()).unwrap());
+        let consume_queue_store = ConsumeQueueStore::new(
+            message_store_config.clone(),
+            Arc::new(BrokerConfig::default()),
+            Arc::new(parking_lot::Mutex::new(HashMap::new())),
+            Arc::new(RunningFlags::new()),
+            store_checkpoint.clone(),
+        );
+        let service = FlushConsumeQueueService::new(
+            message_store_config,
+            consume_queue_store.clone(),
+            store_checkpoint,
+        );
+        let persisted_logics_msg_timestamp = || {
+            StoreCheckpoint::new(checkpoint_path.as_str())
+                .unwrap()
+                .logics_msg_timestamp()
+        };
+        let flushed_where = |topic: &str, queue_id: i32| {
+            consume_queue_store
+                .find_or_create_consume_queue(&CheetahString::from_slice(topic), queue_id)
+                .get_flushed_where()
+        };
+
+        for queue_id in 0..2 {
+            consume_queue_store
+                .put_message_position_info_wrapper(&dispatch_request("TopicA", queue_id, 0, 1000));
+        }
+        consume_queue_store
+            .put_message_position_info_wrapper(&dispatch_request("TopicB", 0, 0, 2000));
+
+        // first round is always thorough
+        let mut now = 1_000_000;
+        service.inner.do_flush(1, now);
+        assert_eq!(flushed_where("TopicA", 0), 20);
+        assert_eq!(flushed_where("TopicA", 1), 20);
+        assert_eq!(flushed_where("TopicB", 0), 20);
+        assert_eq!(persisted_logics_msg_timestamp(), 2000);
+
+        consume_queue_store
+            .put_message_position_info_wrapper(&dispatch_request("TopicA", 0, 1, 3000));
+
+        // less than flush_consume_queue_least_pages dirty, nothing is forced yet
+        now += 1000;
+        service.inner.do_flush(1, now);
+        assert_eq!(flushed_where("TopicA", 0), 20);
+        assert_eq!(persisted_logics_msg_timestamp(), 2000);
+
+        // thorough interval elapsed
+        now += 60_000;
+        service.inner.do_flush(1, now);
+        assert_eq!(flushed_where("TopicA", 0), 40);
+        assert_eq!(persisted_logics_msg_timestamp(), 3000);
+    }
+
+    fn store_with_config(
+        dir: &tempfile::TempDir,
+        message_store_config: MessageStoreConfig,
+    ) -> DefaultMessageStore {
+        DefaultMessageStore::new(
+            Arc::new(MessageStoreConfig {
+                store_path_root_dir: CheetahString::from_string(
+                    dir.path().to_string_lossy().to_string(),
+                ),
+                ..message_store_config
+            }),
+            Arc::new(BrokerConfig::default()),
+            Arc::new(parking_lot::Mutex::new(HashMap::new())),
+            None,
+            false,
+        )
+    }
+
+    fn message(topic: &str) -> MessageExtBrokerInner {
+        let mut msg = MessageExtBrokerInner::default();
+        msg.message_ext_inner.message.topic = CheetahString::from_slice(topic);
+        msg
+    }
+
+    struct RejectHook;
+
+    impl PutMessageHook for RejectHook {
+        fn hook_name(&self) -> String {
+            "rejectHook".to_string()
+        }
+
+        fn execute_before_put_message(
+            &self,
+            msg: &mut MessageExtBrokerInner,
+        ) -> Option<PutMessageResult> {
+            (msg.topic() == "Rejected")
+                .then(|| PutMessageResult::new_default(PutMessageStatus::WheelTimerMsgIllegal))
+        }
+    }
+
+    #[tokio::test]
+    async fn put_message_rejected_after_shutdown() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = store_with_config(&dir, MessageStoreConfig::default());
+        store.shutdown.store(true, Ordering::Release);
+        let result = store.put_message(message("TopicTest")).await;
+        assert_eq!(
+            result.put_message_status(),
+            PutMessageStatus::ServiceNotAvailable
+        );
+    }
+
+    #[tokio::test]
+    async fn put_message_rejected_on_slave() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = store_with_config(
+            &dir,
+            MessageStoreConfig {
+                broker_role: BrokerRole::Slave,
+                ..MessageStoreConfig::default()
+            },
+        );
+        for _ in 0..2 {
+            let result = store.put_message(message("TopicTest")).await;
+            assert_eq!(
+                result.put_message_status(),
+                PutMessageStatus::ServiceNotAvailable
+            );
+        }
+        assert_eq!(store.print_times.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn put_message_rejected_when_not_writeable() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = store_with_config(&dir, MessageStoreConfig::default());
+        store.running_flags.get_and_make_not_writeable();
+        let result = store.put_message(message("TopicTest")).await;
+        assert_eq!(
+            result.put_message_status(),
+            PutMessageStatus::ServiceNotAvailable
+        );
+    }
+
+    #[tokio::test]
+    async fn put_message_rejects_illegal_messages() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = store_with_config(&dir, MessageStoreConfig::default());
+
+        let result = store.put_message(message(&"t".repeat(128))).await;
+        assert_eq!(
+            result.put_message_status(),
+            PutMessageStatus::MessageIllegal
+        );
+
+        let mut msg = message("TopicTest");
+        msg.properties_string = CheetahString::from_string("p".repeat(i16::MAX as usize + 1));
+        let result = store.put_message(msg).await;
+        assert_eq!(
+            result.put_message_status(),
+            PutMessageStatus::MessageIllegal
+        );
+    }
+
+    #[tokio::test]
+    async fn put_message_rejected_when_os_page_cache_busy() {
+        for use_reentrant_lock_when_put_message in [false, true] {
+            let dir = tempfile::tempdir().unwrap();
+            let mut store = store_with_config(
+                &dir,
+                MessageStoreConfig {
+                    os_page_cache_busy_timeout_mills: 1000,
+                    use_reentrant_lock_when_put_message,
+                    ..MessageStoreConfig::default()
+                },
+            );
+            assert_eq!(
+                store.commit_log.put_message_lock().is_spin(),
+                !use_reentrant_lock_when_put_message
+            );
+            assert_eq!(store.get_runtime_info()["putMessageLockTimeMills"], "0");
+            store
+                .commit_log
+                .begin_time_in_lock()
+                .store(get_current_millis() - 5000, Ordering::Relaxed);
+            let lock_time_mills: i64 = store.get_runtime_info()["putMessageLockTimeMills"]
+                .parse()
+                .unwrap();
+            assert!(lock_time_mills >= 5000, "{lock_time_mills}");
+            let result = store.put_message(message("TopicTest")).await;
+            assert_eq!(
+                result.put_message_status(),
+                PutMessageStatus::OsPageCacheBusy
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn put_message_hooks_run_after_store_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = store_with_config(&dir, MessageStoreConfig::default());
+        store.set_put_message_hook(Box::new(RejectHook));
+
+        let result = store.put_message(message("Rejected")).await;
+        assert_eq!(
+            result.put_message_status(),
+            PutMessageStatus::WheelTimerMsgIllegal
+        );
+
+        store.running_flags.get_and_make_not_writeable();
+        let result = store.put_message(message("Rejected")).await;
+        assert_eq!(
+            result.put_message_status(),
+            PutMessageStatus::ServiceNotAvailable
+        );
+    }
+
+    #[tokio::test]
+    async fn disk_full_rejects_writes_until_space_is_reclaimed() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = store_with_config(
+            &dir,
+            MessageStoreConfig {
+                disk_max_used_space_ratio: 75,
+                ..MessageStoreConfig::default()
+            },
+        );
+
+        assert!(store.clean_commit_log_service.is_space_to_delete(0.9));
+        assert!(store.running_flags.is_disk_full());
+        let result = store.put_message(message("TopicTest")).await;
+        assert_eq!(
+            result.put_message_status(),
+            PutMessageStatus::ServiceNotAvailable
+        );
+
+        assert!(!store.clean_commit_log_service.is_space_to_delete(0.5));
+        assert!(!store.running_flags.is_disk_full());
+        assert_eq!(store.check_store_status(), PutMessageStatus::PutOk);
+    }
+
+    #[test]
+    fn multi_path_commit_log_is_full_only_once_every_disk_is() {
+        let ratios = [("/disk_a", 0.9), ("/disk_b", 0.4), ("/missing", -1.0)];
+        assert_eq!(min_disk_ratio(&ratios), 0.4);
+        assert_eq!(min_disk_ratio(&[("/missing", -1.0)]), -1.0);
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = store_with_config(
+            &dir,
+            MessageStoreConfig {
+                disk_max_used_space_ratio: 75,
+                ..MessageStoreConfig::default()
+            },
+        );
+        assert_eq!(
+            store.clean_commit_log_service.full_store_paths(&ratios),
+            HashSet::from(["/disk_a".to_string()])
+        );
+    }
+
+    #[test]
+    fn runtime_info_reports_disk_ratio_of_every_commit_log_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let disk_a = dir.path().join("disk_a");
+        let disk_b = dir.path().join("disk_b");
+        let store = store_with_config(
+            &dir,
+            MessageStoreConfig {
+                store_path_commit_log: Some(CheetahString::from_string(format!(
+                    "{},{}",
+                    disk_a.display(),
+                    disk_b.display()
+                ))),
+                ..MessageStoreConfig::default()
+            },
+        );
+        assert!(disk_a.is_dir() && disk_b.is_dir());
+
+        let runtime_info = store.get_runtime_info();
+        let ratio: f64 = runtime_info["commitLogDiskRatio"].parse().unwrap();
+        for disk in [&disk_a, &disk_b] {
+            let key = format!("commitLogDiskRatio_{}", disk.display());
+            let disk_ratio: f64 = runtime_info[&key].parse().unwrap();
+            assert!(ratio <= disk_ratio);
+        }
+    }
+    #[tokio::test]
+    async fn put_latency_histogram_records_after_put() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = test_util::load_store(&dir, MessageStoreConfig::default()).await;
+
+        let registry = prometheus::Registry::new();
+        let exporter = opentelemetry_prometheus::exporter()
+            .with_registry(registry.clone())
+            .without_units()
+            .without_counter_suffixes()
+            .build()
+            .unwrap();
+        let mut builder =
+            opentelemetry_sdk::metrics::MeterProvider::builder().with_reader(exporter);
+        for view in DefaultStoreMetricsManager::get_metrics_view() {
+            builder = builder.with_view(view);
+        }
+        let meter_provider = builder.build();
+        store.init_metrics(&meter_provider);
+
+        let mut msg = message("TopicTest");
+        msg.message_ext_inner.message.body = Some(bytes::Bytes::from_static(b"hello"));
+        let result = store.put_message(msg).await;
+        assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+
+        let families = registry.gather();
+        let put_latency = families
+            .iter()
+            .find(|family| family.get_name() == HISTOGRAM_PUT_LATENCY)
+            .expect("put latency histogram exported");
+        let histogram = put_latency.get_metric()[0].get_histogram();
+        assert_eq!(histogram.get_sample_count(), 1);
+        assert_eq!(histogram.get_bucket().len(), PUT_LATENCY_BUCKETS.len());
+        let lock_wait = families
+            .iter()
+            .find(|family| family.get_name() == HISTOGRAM_PUT_MESSAGE_LOCK_WAIT)
+            .expect("put message lock wait histogram exported");
+        let histogram = lock_wait.get_metric()[0].get_histogram();
+        assert_eq!(histogram.get_sample_count(), 1);
+        assert_eq!(
+            histogram.get_bucket().len(),
+            PUT_MESSAGE_LOCK_WAIT_BUCKETS.len()
+        );
+        assert!(families
+            .iter()
+            .any(|family| family.get_name() == COUNTER_PUT_MESSAGES_TOTAL));
+        let last_boot_abnormal = families
+            .iter()
+            .find(|family| family.get_name() == GAUGE_LAST_BOOT_ABNORMAL)
+            .expect("last boot abnormal gauge exported");
+        assert_eq!(
+            last_boot_abnormal.get_metric()[0].get_gauge().get_value(),
+            0.0
+        );
+        assert!(families
+            .iter()
+            .any(|family| family.get_name() == GAUGE_RECOVER_DISPATCH_MESSAGES));
+        let dispatch_behind = families
+            .iter()
+            .find(|family| family.get_name() == GAUGE_DISPATCH_BEHIND_BYTES)
+            .expect("dispatch behind gauge exported");
+        assert_eq!(
+            dispatch_behind.get_metric()[0].get_gauge().get_value(),
+            store.dispatch_behind_bytes() as f64
+        );
+    }
+
+    async fn start_lmq_store(
+        dir: &tempfile::TempDir,
+        max_lmq_consume_queue_num: usize,
+    ) -> ArcMut<DefaultMessageStore> {
+        let store = test_util::start_store(
+            dir,
+            MessageStoreConfig {
+                enable_lmq: true,
+                enable_multi_dispatch: true,
+                max_lmq_consume_queue_num,
+                ..MessageStoreConfig::default()
+            },
+        )
+        .await;
+        store
+    }
+
+    fn lmq_message(topic: &str, multi_dispatch_queues: &str) -> MessageExtBrokerInner {
+        let mut msg = message(topic);
+        msg.message_ext_inner.message.body = Some(bytes::Bytes::from_static(b"lmq"));
+        MessageAccessor::put_property(
+            &mut msg,
+            CheetahString::from_static_str(MessageConst::PROPERTY_INNER_MULTI_DISPATCH),
+            CheetahString::from_slice(multi_dispatch_queues),
+        );
+        msg.properties_string = message_properties_to_string(msg.get_properties());
+        msg
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn sync_flush_put_waits_for_group_commit_and_shutdown_joins_services() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = ArcMut::new(store_with_config(
+            &dir,
+            MessageStoreConfig {
+                mapped_file_size_commit_log: 1024 * 1024,
+                flush_disk_type: FlushDiskType::SyncFlush,
+                ..MessageStoreConfig::default()
+            },
+        ));
+        let store_clone = store.clone();
+        store.set_message_store_arc(Some(store_clone));
+        assert!(store.load().await);
+        store.start().unwrap();
+        // the commit log starts its flush services from a spawned task
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        for _ in 0..3 {
+            let mut msg = message("SyncFlushTopic");
+            msg.message_ext_inner.message.body = Some(bytes::Bytes::from_static(b"sync"));
+            msg.set_wait_store_msg_ok(true);
+            let result = store.put_message(msg).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        }
+        wait_dispatched(&store).await;
+
+        let begin = Instant::now();
+        store.shutdown();
+        assert!(begin.elapsed() < Duration::from_secs(10));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn multi_dispatch_message_is_pulled_from_every_lmq() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = start_lmq_store(&dir, 20000).await;
+        for _ in 0..2 {
+            let result = store
+                .put_message(lmq_message("TopicA", "%LMQ%a,%LMQ%b"))
+                .await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        }
+        wait_dispatched(&store).await;
+
+        let group = CheetahString::from_static_str("GroupA");
+        for topic in ["TopicA", "%LMQ%a", "%LMQ%b"] {
+            let topic = CheetahString::from_static_str(topic);
+            assert_eq!(store.get_max_offset_in_queue(&topic, 0), 2, "{topic}");
+            let result = store
+                .get_message(&group, &topic, 0, 1, 32, 1024 * 1024, None)
+                .await
+                .unwrap();
+            assert_eq!(result.status(), Some(GetMessageStatus::Found));
+            assert_eq!(result.message_count(), 1);
+            assert_eq!(result.next_begin_offset(), 2);
+        }
+        assert_eq!(store.consume_queue_store.get_lmq_num(), 2);
+        assert_eq!(
+            store
+                .consume_queue_store
+                .get_lmq_queue_offset(&multi_dispatch_utils::lmq_queue_key("%LMQ%a")),
+            2
+        );
+        store.shutdown();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn put_message_rejected_when_lmq_num_exceeded() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = start_lmq_store(&dir, 1).await;
+        let result = store
+            .put_message(lmq_message("TopicA", "%LMQ%a,%LMQ%b"))
+            .await;
+        assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+
+        let result = store.put_message(lmq_message("TopicA", "%LMQ%c")).await;
+        assert_eq!(
+            result.put_message_status(),
+            PutMessageStatus::LmqConsumeQueueNumExceeded
+        );
+        // plain messages are not affected by the LMQ cap
+        let mut msg = message("TopicA");
+        msg.message_ext_inner.message.body = Some(bytes::Bytes::from_static(b"plain"));
+        let result = store.put_message(msg).await;
+        assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        store.shutdown();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn stored_message_is_found_by_unique_key_keys_and_offset_msg_id() {
+        const UNIQ_KEY: &str = "7F00000100002A9F0000000000000001";
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = start_lmq_store(&dir, 20000).await;
+        let topic = CheetahString::from_static_str("IndexTopic");
+        let mut wrote_offsets = Vec::new();
+        for (uniq_key, keys) in [(UNIQ_KEY, "order-1 order-2"), ("OTHER", "order-3")] {
+            let mut msg = message(&topic);
+            msg.message_ext_inner.message.body = Some(bytes::Bytes::from_static(b"indexed"));
+            MessageAccessor::put_property(
+                &mut msg,
+                CheetahString::from_static_str(
+                    MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX,
+                ),
+                CheetahString::from_slice(uniq_key),
+            );
+            MessageAccessor::put_property(
+                &mut msg,
+                CheetahString::from_static_str(MessageConst::PROPERTY_KEYS),
+                CheetahString::from_slice(keys),
+            );
+            msg.properties_string = message_properties_to_string(msg.get_properties());
+            let result = store.put_message(msg).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+            wrote_offsets.push(result.append_message_result().unwrap().clone());
+        }
+        wait_dispatched(&store).await;
+
+        let query = |key: &'static str| {
+            let store = store.clone();
+            let topic = topic.clone();
+            async move {
+                let result = store
+                    .query_message(
+                        &topic,
+                        &CheetahString::from_static_str(key),
+                        32,
+                        0,
+                        i64::MAX,
+                    )
+                    .await
+                    .unwrap();
+                match result.get_message_data() {
+                    Some(mut data) => message_decoder::decodes_batch(&mut data, true, false),
+                    None => Vec::new(),
+                }
+            }
+        };
+        let by_uniq_key = query(UNIQ_KEY).await;
+        assert_eq!(by_uniq_key.len(), 1);
+        assert_eq!(
+            by_uniq_key[0]
+                .get_property(&CheetahString::from_static_str(
+                    MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX
+                ))
+                .unwrap(),
+            UNIQ_KEY
+        );
+        // every key of a message is indexed, not only the first one
+        assert_eq!(query("order-2").await.len(), 1);
+        assert_eq!(query("order-3").await.len(), 1);
+        assert!(query("order-4").await.is_empty());
+
+        let msg_id = wrote_offsets[0].get_message_id().unwrap();
+        assert_eq!(msg_id.len(), 32);
+        let offset = message_decoder::decode_message_id(&msg_id).offset;
+        assert_eq!(offset, wrote_offsets[0].wrote_offset);
+        let mut data = store
+            .select_one_message_by_offset(offset)
+            .await
+            .unwrap()
+            .get_bytes()
+            .unwrap();
+        let by_offset = message_decoder::decodes_batch(&mut data, true, false);
+        assert_eq!(by_offset.len(), 1);
+        assert_eq!(by_offset[0].msg_id, by_uniq_key[0].msg_id);
+        assert_eq!(by_offset[0].get_body().unwrap().as_ref(), b"indexed");
+        store.shutdown();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn ipv4_and_ipv6_hosts_round_trip_through_the_commit_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = start_lmq_store(&dir, 20000).await;
+        let topic = CheetahString::from_static_str("MixedHostTopic");
+        let hosts: [(SocketAddr, SocketAddr); 4] = [
+            (
+                "10.0.0.1:5000".parse().unwrap(),
+                "10.0.0.2:10911".parse().unwrap(),
+            ),
+            (
+                "[2001:db8::1]:5000".parse().unwrap(),
+                "10.0.0.2:10911".parse().unwrap(),
+            ),
+            (
+                "10.0.0.1:5000".parse().unwrap(),
+                "[2001:db8::2]:10911".parse().unwrap(),
+            ),
+            (
+                "[2001:db8::1]:5000".parse().unwrap(),
+                "[2001:db8::2]:10911".parse().unwrap(),
+            ),
+        ];
+        let mut wrote = Vec::new();
+        for (born_host, store_host) in hosts {
+            let mut msg = message(&topic);
+            msg.message_ext_inner.message.body = Some(bytes::Bytes::from_static(b"mixed"));
+            msg.message_ext_inner.set_born_host(born_host);
+            msg.message_ext_inner.set_store_host(store_host);
+            let result = store.put_message(msg).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+            wrote.push(result.append_message_result().unwrap().clone());
+        }
+        wait_dispatched(&store).await;
+
+        let result = store
+            .get_message(
+                &CheetahString::from_static_str("GroupA"),
+                &topic,
+                0,
+                0,
+                32,
+                1024 * 1024,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.message_count(), 4);
+        for ((born_host, store_host), append_result) in hosts.into_iter().zip(&wrote) {
+            let msg_id = append_result.get_message_id().unwrap();
+            assert_eq!(msg_id.len(), if store_host.is_ipv6() { 56 } else { 32 });
+            let message_id = message_decoder::decode_message_id(&msg_id);
+            assert_eq!(message_id.address, store_host);
+            assert_eq!(message_id.offset, append_result.wrote_offset);
+
+            let mut data = store
+                .select_one_message_by_offset(message_id.offset)
+                .await
+                .unwrap()
+                .get_bytes()
+                .unwrap();
+            let decoded = message_decoder::decodes_batch(&mut data, true, false);
+            assert_eq!(decoded.len(), 1);
+            assert_eq!(decoded[0].born_host, born_host);
+            assert_eq!(decoded[0].store_host, store_host);
+            assert_eq!(decoded[0].msg_id, msg_id);
+            assert_eq!(decoded[0].get_body().unwrap().as_ref(), b"mixed");
+        }
+        store.shutdown();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cold_area_is_measured_from_the_commit_log_max_offset() {
+        let topic = CheetahString::from_static_str("ColdAreaTopic");
+        for (access_message_in_memory_max_ratio, cold) in [(40, false), (0, true)] {
+            let dir = tempfile::tempdir().unwrap();
+            let mut store = test_util::start_store(
+                &dir,
+                MessageStoreConfig {
+                    access_message_in_memory_max_ratio,
+                    ..MessageStoreConfig::default()
+                },
+            )
+            .await;
+            for _ in 0..2 {
+                let mut msg = message(&topic);
+                msg.message_ext_inner.message.body = Some(bytes::Bytes::from_static(b"cold"));
+                let result = store.put_message(msg).await;
+                assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+            }
+            wait_dispatched(&store).await;
+
+            for offset in 0..2 {
+                assert_eq!(
+                    store.check_in_cold_area_by_consume_offset(&topic, 0, offset),
+                    cold
+                );
+            }
+            // nothing to read past the end of the queue
+            assert!(!store.check_in_cold_area_by_consume_offset(&topic, 0, 2));
+            store.shutdown();
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn puts_are_rejected_while_dispatch_falls_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = test_util::start_store(
+            &dir,
+            MessageStoreConfig {
+                dispatch_behind_protect_enable: true,
+                dispatch_behind_protect_bytes: 1,
+                ..MessageStoreConfig::default()
+            },
+        )
+        .await;
+        let put = |mut store: ArcMut<DefaultMessageStore>| async move {
+            let mut msg = message("LagTopic");
+            msg.message_ext_inner.message.body = Some(bytes::Bytes::from_static(b"lag"));
+            store.put_message(msg).await.put_message_status()
+        };
+
+        // pause the reput task, nothing written from now on reaches the consume queues
+        store.reput_message_service.shutdown();
+        assert_eq!(put(store.clone()).await, PutMessageStatus::PutOk);
+        let dispatch_behind_bytes = store.dispatch_behind_bytes();
+        assert!(dispatch_behind_bytes > 1);
+        assert_eq!(
+            store.get_runtime_info()["dispatchBehindBytes"],
+            dispatch_behind_bytes.to_string()
+        );
+        assert_eq!(
+            put(store.clone()).await,
+            PutMessageStatus::ServiceNotAvailable
+        );
+
+        // once dispatch catches up puts are accepted again
+        let mut reput = store.reput_message_service.inner.clone().unwrap();
+        reput.do_reput().await;
+        assert_eq!(store.dispatch_behind_bytes(), 0);
+        assert_eq!(put(store.clone()).await, PutMessageStatus::PutOk);
+        store.shutdown();
+    }
+
+    struct TagsCodeFilter(i64);
+
+    impl MessageFilter for TagsCodeFilter {
+        fn is_matched_by_consume_queue(
+            &self,
+            tags_code: Option<i64>,
+            _cq_ext_unit: Option<&CqExtUnit>,
+        ) -> bool {
+            tags_code == Some(self.0)
+        }
+
+        fn is_matched_by_commit_log(
+            &self,
+            _msg_buffer: Option<&[u8]>,
+            _properties: Option<&HashMap<CheetahString, CheetahString>>,
+        ) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn estimate_message_count_samples_the_consume_queue() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = test_util::start_store(
+            &dir,
+            MessageStoreConfig {
+                // 16 entries per file, the estimate has to walk across files
+                mapped_file_size_consume_queue: 16 * CQ_STORE_UNIT_SIZE as usize,
+                max_consume_queue_scan: 40,
+                ..MessageStoreConfig::default()
+            },
+        )
+        .await;
+        let topic = CheetahString::from_static_str("EstimateTopic");
+        for i in 0..100 {
+            let mut msg = message(&topic);
+            msg.message_ext_inner.message.body = Some(bytes::Bytes::from_static(b"estimate"));
+            let tags = if i % 2 == 0 { "TagA" } else { "TagB" };
+            MessageAccessor::put_property(
+                &mut msg,
+                CheetahString::from_static_str(MessageConst::PROPERTY_TAGS),
+                CheetahString::from_static_str(tags),
+            );
+            msg.properties_string = message_properties_to_string(msg.get_properties());
+            let result = store.put_message(msg).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        }
+        wait_dispatched(&store).await;
+
+        let tag_a = TagsCodeFilter(tags_string2tags_code(Some(
+            &CheetahString::from_static_str("TagA"),
+        )));
+        // a range within the scan limit is counted exactly
+        assert_eq!(store.estimate_message_count(&topic, 0, 10, 40, &tag_a), 15);
+        // a larger range is extrapolated from the first 40 entries
+        let estimate = store.estimate_message_count(&topic, 0, 0, 100, &tag_a);
+        assert!((45..=55).contains(&estimate), "estimate {estimate}");
+        // the range is clamped to the queue
+        assert_eq!(
+            store.estimate_message_count(&topic, 0, 90, i64::MAX, &tag_a),
+            5
+        );
+        assert_eq!(store.estimate_message_count(&topic, 1, 0, 100, &tag_a), 0);
+        store.shutdown();
+    }
+
+    async fn load_store(dir: &tempfile::TempDir) -> ArcMut<DefaultMessageStore> {
+        let store = test_util::load_store(dir, MessageStoreConfig::default()).await;
+        store
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn second_store_on_the_same_root_refuses_to_start() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut running = load_store(&dir).await;
+        running.start().unwrap();
+
+        let mut second = load_store(&dir).await;
+        let err = second.start().unwrap_err();
+        assert!(err.to_string().contains("Lock failed"), "{err}");
+        // the running store still owns its abort file
+        assert!(fs::metadata(running.message_store_config.get_abort_file()).is_ok());
+
+        running.shutdown();
+        second.start().unwrap();
+        second.shutdown();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn abnormal_boot_is_detected_and_recovery_dispatches_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let topic = CheetahString::from_static_str("RecoverTopic");
+        let mut store = load_store(&dir).await;
+        assert!(store.is_last_exit_ok());
+        store.start().unwrap();
+        for _ in 0..3 {
+            let mut msg = message(&topic);
+            msg.message_ext_inner.message.body = Some(bytes::Bytes::from_static(b"recover"));
+            let result = store.put_message(msg).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        }
+        wait_dispatched(&store).await;
+        let runtime_info = store.get_runtime_info();
+        assert_eq!(runtime_info["lastBootAbnormal"], "false");
+        assert_eq!(runtime_info["recoverDispatchCount"], "0");
+        store.shutdown();
+        let abort_file = store.message_store_config.get_abort_file();
+        assert!(fs::metadata(&abort_file).is_err());
+
+        // a crash leaves the abort file behind
+        fs::write(&abort_file, std::process::id().to_string()).unwrap();
+        let mut store = load_store(&dir).await;
+        assert!(!store.is_last_exit_ok());
+        let runtime_info = store.get_runtime_info();
+        assert_eq!(runtime_info["lastBootAbnormal"], "true");
+        assert_eq!(runtime_info["recoverDispatchCount"], "3");
+        assert_eq!(store.get_max_offset_in_queue(&topic, 0), 3);
+        store.start().unwrap();
+        store.shutdown();
+
+        let store = load_store(&dir).await;
+        assert!(store.is_last_exit_ok());
+    }
+
+    async fn load_store_checking_crc(dir: &tempfile::TempDir) -> ArcMut<DefaultMessageStore> {
+        let store = test_util::load_store(
+            dir,
+            MessageStoreConfig {
+                check_crc_on_recover: true,
+                ..MessageStoreConfig::default()
+            },
+        )
+        .await;
+        store
+    }
+
+    /// Puts three messages and returns the commit log offsets they were written at.
+    async fn put_crc_messages(
+        store: &mut ArcMut<DefaultMessageStore>,
+        topic: &CheetahString,
+    ) -> Vec<i64> {
+        let mut offsets = Vec::new();
+        for _ in 0..3 {
+            let mut msg = message(topic);
+            msg.message_ext_inner.message.body = Some(bytes::Bytes::from_static(b"checksum"));
+            let result = store.put_message(msg).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+            offsets.push(result.append_message_result().unwrap().wrote_offset);
+        }
+        wait_dispatched(store).await;
+        offsets
+    }
+
+    /// Flips the first body byte of the IPv4 message written at `phy_offset`.
+    fn corrupt_body(dir: &tempfile::TempDir, phy_offset: i64) {
+        use std::io::Read;
+        use std::io::Seek;
+        use std::io::SeekFrom;
+        use std::io::Write;
+
+        const BODY_POSITION: u64 = 88;
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(dir.path().join("commitlog").join(format!("{:020}", 0)))
+            .unwrap();
+        let position = phy_offset as u64 + BODY_POSITION;
+        let mut byte = [0u8; 1];
+        file.seek(SeekFrom::Start(position)).unwrap();
+        file.read_exact(&mut byte).unwrap();
+        byte[0] ^= 0xFF;
+        file.seek(SeekFrom::Start(position)).unwrap();
+        file.write_all(&byte).unwrap();
+        file.sync_all().unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn recovery_truncates_before_a_crc_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let topic = CheetahString::from_static_str("CrcTopic");
+        let mut store = load_store_checking_crc(&dir).await;
+        store.start().unwrap();
+        let offsets = put_crc_messages(&mut store, &topic).await;
+        store.shutdown();
+
+        corrupt_body(&dir, offsets[1]);
+        let store = load_store_checking_crc(&dir).await;
+        assert_eq!(store.get_max_phy_offset(), offsets[1]);
+        assert_eq!(store.get_max_offset_in_queue(&topic, 0), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn get_skips_and_counts_corrupt_messages() {
+        let dir = tempfile::tempdir().unwrap();
+        let topic = CheetahString::from_static_str("CrcTopic");
+        let mut store = load_store_checking_crc(&dir).await;
+        store.start().unwrap();
+        let offsets = put_crc_messages(&mut store, &topic).await;
+
+        corrupt_body(&dir, offsets[1]);
+        let result = store
+            .get_message(
+                &CheetahString::from_static_str("CrcGroup"),
+                &topic,
+                0,
+                0,
+                32,
+                1024 * 1024,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.status(), Some(GetMessageStatus::Found));
+        assert_eq!(result.message_count(), 2);
+        assert_eq!(result.next_begin_offset(), 3);
+        assert_eq!(
+            store.get_runtime_info()["getMessageCrcFailedCount"],
+            "1".to_string()
+        );
+        store.shutdown();
+    }
+
+    async fn load_store_checking_consume_queues(
+        dir: &tempfile::TempDir,
+        repair: bool,
+    ) -> ArcMut<DefaultMessageStore> {
+        let store = test_util::load_store(
+            dir,
+            MessageStoreConfig {
+                check_consume_queue_on_startup: true,
+                repair_consume_queue_on_startup: repair,
+                ..MessageStoreConfig::default()
+            },
+        )
+        .await;
+        store
+    }
+
+    /// Rewrites every entry of a consume queue file with `rewrite(pos, size, tags_code)`.
+    fn rewrite_consume_queue(
+        dir: &tempfile::TempDir,
+        topic: &str,
+        queue_id: i32,
+        rewrite: impl Fn(&mut i64, &mut i32, &mut i64),
+    ) {
+        let path = dir
+            .path()
+            .join("consumequeue")
+            .join(topic)
+            .join(queue_id.to_string())
+            .join(format!("{:020}", 0));
+        let mut data = fs::read(&path).unwrap();
+        for entry in data.chunks_exact_mut(CQ_STORE_UNIT_SIZE as usize) {
+            let mut pos = i64::from_be_bytes(entry[0..8].try_into().unwrap());
+            let mut size = i32::from_be_bytes(entry[8..12].try_into().unwrap());
+            let mut tags_code = i64::from_be_bytes(entry[12..20].try_into().unwrap());
+            if size == 0 {
+                break;
+            }
+            rewrite(&mut pos, &mut size, &mut tags_code);
+            entry[0..8].copy_from_slice(&pos.to_be_bytes());
+            entry[8..12].copy_from_slice(&size.to_be_bytes());
+            entry[12..20].copy_from_slice(&tags_code.to_be_bytes());
+        }
+        fs::write(&path, data).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn startup_check_rebuilds_only_the_damaged_consume_queue() {
+        let dir = tempfile::tempdir().unwrap();
+        let topic = CheetahString::from_static_str("CheckTopic");
+        let mut store = load_store(&dir).await;
+        store.start().unwrap();
+        for i in 0..20 {
+            let mut msg = message(&topic);
+            msg.message_ext_inner.queue_id = i % 2;
+            msg.message_ext_inner.message.body =
+                Some(bytes::Bytes::from(format!("q{}-{}", i % 2, i / 2)));
+            let result = store.put_message(msg).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        }
+        wait_dispatched(&store).await;
+        store.shutdown();
+
+        // queue 0 points one byte past each message, queue 1 only gets tags codes the check
+        // does not look at, which a rebuild would restore
+        rewrite_consume_queue(&dir, &topic, 0, |pos, _, _| *pos += 1);
+        rewrite_consume_queue(&dir, &topic, 1, |_, _, tags_code| *tags_code = 42);
+
+        let mut store = load_store_checking_consume_queues(&dir, false).await;
+        assert_eq!(
+            store.check_consume_queues(),
+            vec![DamagedConsumeQueue {
+                topic: topic.clone(),
+                queue_id: 0,
+                damage: ConsumeQueueDamage::BrokenEntry { queue_offset: 0 },
+            }]
+        );
+        store.shutdown();
+
+        let mut store = load_store_checking_consume_queues(&dir, true).await;
+        assert!(store.check_consume_queues().is_empty());
+        assert_eq!(store.get_max_offset_in_queue(&topic, 0), 10);
+        for i in 0..10 {
+            let offset = store.get_commit_log_offset_in_queue(&topic, 0, i);
+            let msg = store.look_message_by_offset(offset).unwrap();
+            assert_eq!(msg.get_body().unwrap(), format!("q0-{i}").as_bytes());
+        }
+        let queue_1 = store.find_consume_queue(&topic, 1).unwrap();
+        assert_eq!(queue_1.get_max_offset_in_queue(), 10);
+        assert_eq!(queue_1.get(9).unwrap().tags_code, 42);
+        store.shutdown();
+    }
+
+    /// Example plugin: counts dispatched messages per topic.
+    #[derive(Default, Clone)]
+    struct TopicCountDispatcher {
+        counts: Arc<parking_lot::Mutex<HashMap<CheetahString, usize>>>,
+    }
+
+    impl CommitLogDispatcher for TopicCountDispatcher {
+        fn dispatch(&self, dispatch_request: &mut DispatchRequest) {
+            *self
+                .counts
+                .lock()
+                .entry(dispatch_request.topic.clone())
+                .or_default() += 1;
+        }
+    }
+
+    struct OrderDispatcher {
+        name: &'static str,
+        calls: Arc<parking_lot::Mutex<Vec<&'static str>>>,
+    }
+
+    impl CommitLogDispatcher for OrderDispatcher {
+        fn dispatch(&self, _dispatch_request: &mut DispatchRequest) {
+            self.calls.lock().push(self.name);
+        }
+    }
+
+    struct PanicDispatcher;
+
+    impl CommitLogDispatcher for PanicDispatcher {
+        fn dispatch(&self, _dispatch_request: &mut DispatchRequest) {
+            panic!("broken dispatcher");
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn registered_dispatchers_run_in_order_and_survive_panics() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = start_lmq_store(&dir, 20000).await;
+        let counter = TopicCountDispatcher::default();
+        let calls = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        store.register_dispatcher(Box::new(OrderDispatcher {
+            name: "a",
+            calls: calls.clone(),
+        }));
+        store.register_dispatcher(Box::new(PanicDispatcher));
+        store.register_dispatcher(Box::new(counter.clone()));
+        store.register_dispatcher(Box::new(OrderDispatcher {
+            name: "b",
+            calls: calls.clone(),
+        }));
+        store.register_first_dispatcher(Box::new(OrderDispatcher {
+            name: "first",
+            calls: calls.clone(),
+        }));
+
+        for topic in ["TopicA", "TopicA", "TopicB"] {
+            let mut msg = message(topic);
+            msg.message_ext_inner.message.body = Some(bytes::Bytes::from_static(b"dispatch"));
+            let result = store.put_message(msg).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        }
+        wait_dispatched(&store).await;
+
+        let counts = counter.counts.lock().clone();
+        assert_eq!(counts.get("TopicA"), Some(&2));
+        assert_eq!(counts.get("TopicB"), Some(&1));
+        assert_eq!(calls.lock().as_slice(), ["first", "a", "b"].repeat(3));
+        // the built-in consume queue dispatcher kept working despite the panicking plugin
+        assert_eq!(
+            store.get_max_offset_in_queue(&CheetahString::from_static_str("TopicA"), 0),
+            2
+        );
+        store.shutdown();
+    }
+
+    /// Records arrivals whose consume queue entry was not yet visible when notified.
+    struct VisibilityCheckListener {
+        store: ArcMut<DefaultMessageStore>,
+        arrivals: Arc<AtomicUsize>,
+        violations: Arc<parking_lot::Mutex<Vec<(CheetahString, i32, i64)>>>,
+    }
+
+    impl MessageArrivingListener for VisibilityCheckListener {
+        fn arriving(
+            &self,
+            topic: &CheetahString,
+            queue_id: i32,
+            logic_offset: i64,
+            _tags_code: Option<i64>,
+            _msg_store_time: i64,
+            _filter_bit_map: Option<&[u8]>,
+            _properties: Option<&HashMap<CheetahString, CheetahString>>,
+        ) {
+            self.arrivals.fetch_add(1, Ordering::SeqCst);
+            if self.store.get_max_offset_in_queue(topic, queue_id) < logic_offset {
+                self.violations
+                    .lock()
+                    .push((topic.clone(), queue_id, logic_offset));
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn arriving_listener_runs_after_consume_queue_entry_is_visible() {
+        const PRODUCERS: usize = 4;
+        const MESSAGES_PER_PRODUCER: usize = 200;
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = ArcMut::new(store_with_config(
+            &dir,
+            MessageStoreConfig {
+                mapped_file_size_commit_log: 1024 * 1024,
+                flush_disk_type: FlushDiskType::AsyncFlush,
+                enable_lmq: true,
+                enable_multi_dispatch: true,
+                ..MessageStoreConfig::default()
+            },
+        ));
+        let store_clone = store.clone();
+        store.set_message_store_arc(Some(store_clone));
+        let arrivals = Arc::new(AtomicUsize::new(0));
+        let violations = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let listener = VisibilityCheckListener {
+            store: store.clone(),
+            arrivals: arrivals.clone(),
+            violations: violations.clone(),
+        };
+        store.set_message_arriving_listener(Some(Arc::new(Box::new(listener))));
+        assert!(store.load().await);
+        store.start().unwrap();
+
+        let producers = (0..PRODUCERS)
+            .map(|producer| {
+                let mut store = store.clone();
+                tokio::spawn(async move {
+                    for _ in 0..MESSAGES_PER_PRODUCER {
+                        let mut msg = lmq_message("TopicA", "%LMQ%arrive");
+                        msg.message_ext_inner.queue_id = producer as i32;
+                        let result = store.put_message(msg).await;
+                        assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for producer in producers {
+            producer.await.unwrap();
+        }
+        wait_dispatched(&store).await;
+
+        // every message notifies its own queue and the LMQ it is multi-dispatched to
+        assert_eq!(
+            arrivals.load(Ordering::SeqCst),
+            2 * PRODUCERS * MESSAGES_PER_PRODUCER
+        );
+        assert!(violations.lock().is_empty(), "{:?}", violations.lock());
+        store.shutdown();
+    }
+
+    const TRUNCATE_FILE_SIZE: usize = 4096;
+
+    async fn start_truncate_store(dir: &tempfile::TempDir) -> ArcMut<DefaultMessageStore> {
+        let mut store = ArcMut::new(store_with_config(
+            dir,
+            MessageStoreConfig {
+                mapped_file_size_commit_log: TRUNCATE_FILE_SIZE,
+                flush_disk_type: FlushDiskType::AsyncFlush,
+                ..MessageStoreConfig::default()
+            },
+        ));
+        let store_clone = store.clone();
+        store.set_message_store_arc(Some(store_clone));
+        assert!(store.load().await);
+        store.start().unwrap();
+        store
+    }
+
+    /// Puts `count` messages keyed `key-<n>` and returns the offset each was written at.
+    async fn put_keyed_messages(
+        store: &mut ArcMut<DefaultMessageStore>,
+        first: usize,
+        count: usize,
+    ) -> Vec<i64> {
+        let mut offsets = Vec::with_capacity(count);
+        for n in first..first + count {
+            let mut msg = message("TruncateTopic");
+            msg.message_ext_inner.message.body = Some(bytes::Bytes::from(vec![b'x'; 200]));
+            MessageAccessor::put_property(
+                &mut msg,
+                CheetahString::from_static_str(MessageConst::PROPERTY_KEYS),
+                CheetahString::from_string(format!("key-{n}")),
+            );
+            msg.properties_string = message_properties_to_string(msg.get_properties());
+            let result = store.put_message(msg).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+            offsets.push(result.append_message_result().unwrap().wrote_offset);
+        }
+        wait_dispatched(store).await;
+        offsets
+    }
+
+    async fn keyed_messages(store: &ArcMut<DefaultMessageStore>, n: usize) -> usize {
+        let result = store
+            .query_message(
+                &CheetahString::from_static_str("TruncateTopic"),
+                &CheetahString::from_string(format!("key-{n}")),
+                32,
+                0,
+                i64::MAX,
+            )
+            .await
+            .unwrap();
+        result.get_message_data().map_or(0, |mut data| {
+            message_decoder::decodes_batch(&mut data, true, false).len()
+        })
+    }
+
+    async fn pulled_messages(store: &ArcMut<DefaultMessageStore>) -> i64 {
+        let group = CheetahString::from_static_str("TruncateGroup");
+        let topic = CheetahString::from_static_str("TruncateTopic");
+        let mut offset = 0;
+        loop {
+            let result = store
+                .get_message(&group, &topic, 0, offset, 32, 1024 * 1024, None)
+                .await
+                .unwrap();
+            if result.status() != Some(GetMessageStatus::Found) {
+                return offset;
+            }
+            offset = result.next_begin_offset();
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn truncate_mid_file_drops_the_tail_and_dispatches_the_replayed_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = start_truncate_store(&dir).await;
+        let offsets = put_keyed_messages(&mut store, 0, 10).await;
+        assert!(*offsets.last().unwrap() < TRUNCATE_FILE_SIZE as i64);
+        let epochs = store.epoch_file_checkpoint().unwrap().clone();
+        epochs.set_last_epoch_entry(1, 0).unwrap();
+        epochs.set_last_epoch_entry(2, offsets[8]).unwrap();
+
+        let truncate_offset = offsets[6];
+        let max_phy_offset = store.get_max_phy_offset();
+        assert!(!store.truncate_files_after(max_phy_offset).await);
+        assert!(store.truncate_files_after(truncate_offset).await);
+        assert_eq!(store.get_max_phy_offset(), truncate_offset);
+        let topic = CheetahString::from_static_str("TruncateTopic");
+        assert_eq!(store.get_max_offset_in_queue(&topic, 0), 6);
+        assert_eq!(pulled_messages(&store).await, 6);
+        assert_eq!(keyed_messages(&store, 5).await, 1);
+        assert_eq!(keyed_messages(&store, 6).await, 0);
+        assert_eq!(epochs.last_entry().map(|entry| entry.epoch), Some(1));
+
+        let data = store.get_commit_log_data(offsets[5], 1024 * 1024).unwrap();
+        assert_eq!(data.size as i64, truncate_offset - offsets[5]);
+        assert_eq!(store.get_commit_log_data(0, 16).unwrap().size, 16);
+        assert!(store.get_commit_log_data(truncate_offset, 16).is_none());
+
+        // the replayed data lands where the truncated data was and is dispatched again
+        let replayed = put_keyed_messages(&mut store, 20, 2).await;
+        assert_eq!(replayed[0], truncate_offset);
+        assert_eq!(store.get_max_offset_in_queue(&topic, 0), 8);
+        assert_eq!(pulled_messages(&store).await, 8);
+        assert_eq!(keyed_messages(&store, 21).await, 1);
+        store.shutdown();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn truncate_across_a_file_boundary_deletes_the_later_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = start_truncate_store(&dir).await;
+        let offsets = put_keyed_messages(&mut store, 0, 40).await;
+        let file_size = TRUNCATE_FILE_SIZE as i64;
+        assert!(*offsets.last().unwrap() >= 2 * file_size);
+        let mapped_files = || fs::read_dir(dir.path().join("commitlog")).unwrap().count();
+        let files_before = mapped_files();
+
+        // the last message of the first file, the rest of it is padding up to the boundary
+        let truncate_index = offsets
+            .iter()
+            .rposition(|&offset| offset < file_size)
+            .unwrap();
+        let truncate_offset = offsets[truncate_index];
+        assert!(store.truncate_files_after(truncate_offset).await);
+        assert_eq!(mapped_files(), 1);
+        assert!(files_before > mapped_files());
+        assert_eq!(store.get_max_phy_offset(), truncate_offset);
+        let topic = CheetahString::from_static_str("TruncateTopic");
+        assert_eq!(
+            store.get_max_offset_in_queue(&topic, 0),
+            truncate_index as i64
+        );
+        assert_eq!(pulled_messages(&store).await, truncate_index as i64);
+        assert_eq!(keyed_messages(&store, truncate_index - 1).await, 1);
+        assert_eq!(keyed_messages(&store, truncate_index + 1).await, 0);
+        assert!(store.get_commit_log_data(file_size, 16).is_none());
+
+        // replaying rolls over the boundary again
+        let replayed = put_keyed_messages(&mut store, 100, 10).await;
+        assert_eq!(replayed[0], truncate_offset);
+        assert!(*replayed.last().unwrap() >= file_size);
+        assert_eq!(
+            store.get_max_offset_in_queue(&topic, 0),
+            truncate_index as i64 + 10
+        );
+        assert_eq!(pulled_messages(&store).await, truncate_index as i64 + 10);
+        assert_eq!(keyed_messages(&store, 109).await, 1);
+        store.shutdown();
+    }
+}
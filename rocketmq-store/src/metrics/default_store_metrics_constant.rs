@@ -0,0 +1,46 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+pub const METER_NAME: &str = "rocketmq-message-store";
+
+pub const HISTOGRAM_PUT_LATENCY: &str = "rocketmq_message_store_put_latency";
+pub const HISTOGRAM_PUT_MESSAGE_LOCK_WAIT: &str = "rocketmq_message_store_put_message_lock_wait";
+
+pub const COUNTER_PUT_MESSAGES_TOTAL: &str = "rocketmq_message_store_put_messages_total";
+pub const COUNTER_PUT_BYTES_TOTAL: &str = "rocketmq_message_store_put_bytes_total";
+pub const COUNTER_GET_MESSAGES_TOTAL: &str = "rocketmq_message_store_get_messages_total";
+pub const COUNTER_GET_BYTES_TOTAL: &str = "rocketmq_message_store_get_bytes_total";
+
+pub const GAUGE_DISPATCH_BEHIND_BYTES: &str = "rocketmq_message_store_dispatch_behind_bytes";
+pub const GAUGE_FLUSH_BEHIND_BYTES: &str = "rocketmq_message_store_flush_behind_bytes";
+pub const GAUGE_CONSUME_QUEUE_COUNT: &str = "rocketmq_message_store_consume_queue_count";
+pub const GAUGE_DISK_USAGE_RATIO: &str = "rocketmq_message_store_disk_usage_ratio";
+pub const GAUGE_LAST_BOOT_ABNORMAL: &str = "rocketmq_message_store_last_boot_abnormal";
+pub const GAUGE_RECOVER_DISPATCH_MESSAGES: &str =
+    "rocketmq_message_store_recover_dispatch_messages";
+
+pub const LABEL_TOPIC: &str = "topic";
+
+/// Upper bounds (ms) of the put latency buckets, the same distribution `StoreStatsService`
+/// reports as `putMessageDistributeTime` in the Java broker.
+pub const PUT_LATENCY_BUCKETS: [f64; 12] = [
+    0.0, 10.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 3000.0, 4000.0, 5000.0, 10000.0,
+];
+
+/// Upper bounds (ms) of the buckets of the time puts wait for the commit log lock, which is
+/// normally held for microseconds.
+pub const PUT_MESSAGE_LOCK_WAIT_BUCKETS: [f64; 10] =
+    [0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 50.0, 100.0, 500.0];
@@ -0,0 +1,224 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use opentelemetry::metrics::Counter;
+use opentelemetry::metrics::Histogram;
+use opentelemetry::metrics::Meter;
+use opentelemetry::metrics::ObservableGauge;
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::metrics::new_view;
+use opentelemetry_sdk::metrics::Aggregation;
+use opentelemetry_sdk::metrics::Instrument;
+use opentelemetry_sdk::metrics::Stream;
+use opentelemetry_sdk::metrics::View;
+use rocketmq_rust::WeakArcMut;
+use tracing::warn;
+
+use crate::log_file::commit_log::CommitLog;
+use crate::log_file::MessageStore;
+use crate::message_store::default_message_store::min_disk_ratio;
+use crate::message_store::default_message_store::store_path_disk_ratios;
+use crate::message_store::default_message_store::DefaultMessageStore;
+use crate::metrics::default_store_metrics_constant::*;
+use crate::queue::local_file_consume_queue_store::ConsumeQueueStore;
+use crate::queue::ConsumeQueueStoreTrait;
+
+/// The store state sampled by the observable gauges.
+pub(crate) struct StoreMetricsSource {
+    pub(crate) commit_log: CommitLog,
+    /// Weak so the store does not keep itself alive through its own metrics.
+    pub(crate) message_store: WeakArcMut<DefaultMessageStore>,
+    pub(crate) consume_queue_store: ConsumeQueueStore,
+    pub(crate) store_path_physic: String,
+    pub(crate) last_exit_ok: Arc<AtomicBool>,
+}
+
+/// Instruments of the message store, created once a meter provider is hooked up through
+/// `DefaultMessageStore::init_metrics`.
+pub struct DefaultStoreMetricsManager {
+    put_latency: Histogram<f64>,
+    put_messages_total: Counter<u64>,
+    put_bytes_total: Counter<u64>,
+    get_messages_total: Counter<u64>,
+    get_bytes_total: Counter<u64>,
+    _dispatch_behind_bytes: ObservableGauge<i64>,
+    _flush_behind_bytes: ObservableGauge<i64>,
+    _consume_queue_count: ObservableGauge<u64>,
+    _disk_usage_ratio: ObservableGauge<f64>,
+    _last_boot_abnormal: ObservableGauge<u64>,
+    _recover_dispatch_messages: ObservableGauge<u64>,
+}
+
+impl DefaultStoreMetricsManager {
+    pub(crate) fn new(meter: &Meter, source: StoreMetricsSource) -> Self {
+        let source = Arc::new(source);
+
+        let put_latency = meter
+            .f64_histogram(HISTOGRAM_PUT_LATENCY)
+            .with_description("The latency of putting messages into the commit log in ms")
+            .init();
+        source.commit_log.put_message_lock().set_wait_histogram(
+            meter
+                .f64_histogram(HISTOGRAM_PUT_MESSAGE_LOCK_WAIT)
+                .with_description("The time puts waited for the commit log lock in ms")
+                .init(),
+        );
+        let put_messages_total = meter
+            .u64_counter(COUNTER_PUT_MESSAGES_TOTAL)
+            .with_description("The number of messages put into the store")
+            .init();
+        let put_bytes_total = meter
+            .u64_counter(COUNTER_PUT_BYTES_TOTAL)
+            .with_description("The size of messages put into the store in bytes")
+            .init();
+        let get_messages_total = meter
+            .u64_counter(COUNTER_GET_MESSAGES_TOTAL)
+            .with_description("The number of messages got from the store")
+            .init();
+        let get_bytes_total = meter
+            .u64_counter(COUNTER_GET_BYTES_TOTAL)
+            .with_description("The size of messages got from the store in bytes")
+            .init();
+
+        let dispatch_source = source.clone();
+        let dispatch_behind_bytes = meter
+            .i64_observable_gauge(GAUGE_DISPATCH_BEHIND_BYTES)
+            .with_description("The commit log bytes not yet dispatched to consume queues")
+            .with_callback(move |observer| {
+                if let Some(message_store) = dispatch_source.message_store.upgrade() {
+                    observer.observe(message_store.dispatch_behind_bytes(), &[]);
+                }
+            })
+            .init();
+        let flush_source = source.clone();
+        let flush_behind_bytes = meter
+            .i64_observable_gauge(GAUGE_FLUSH_BEHIND_BYTES)
+            .with_description("The commit log bytes not yet flushed to disk")
+            .with_callback(move |observer| {
+                if let Some(message_store) = flush_source.message_store.upgrade() {
+                    observer.observe(message_store.remain_how_many_data_to_flush(), &[]);
+                }
+            })
+            .init();
+        let consume_queue_source = source.clone();
+        let consume_queue_count = meter
+            .u64_observable_gauge(GAUGE_CONSUME_QUEUE_COUNT)
+            .with_description("The number of consume queues")
+            .with_callback(move |observer| {
+                let count = consume_queue_source
+                    .consume_queue_store
+                    .get_consume_queue_table()
+                    .lock()
+                    .values()
+                    .map(|queues| queues.len() as u64)
+                    .sum();
+                observer.observe(count, &[]);
+            })
+            .init();
+        let boot_source = source.clone();
+        let last_boot_abnormal = meter
+            .u64_observable_gauge(GAUGE_LAST_BOOT_ABNORMAL)
+            .with_description("1 when the store recovered from an abnormal shutdown at boot")
+            .with_callback(move |observer| {
+                let abnormal = !boot_source.last_exit_ok.load(Ordering::Relaxed);
+                observer.observe(abnormal as u64, &[]);
+            })
+            .init();
+        let recover_source = source.clone();
+        let recover_dispatch_messages = meter
+            .u64_observable_gauge(GAUGE_RECOVER_DISPATCH_MESSAGES)
+            .with_description("The number of messages dispatched again during recovery")
+            .with_callback(move |observer| {
+                observer.observe(recover_source.commit_log.recover_dispatch_count(), &[]);
+            })
+            .init();
+        let disk_usage_ratio = meter
+            .f64_observable_gauge(GAUGE_DISK_USAGE_RATIO)
+            .with_description("The used ratio of the disk holding the commit log")
+            .with_callback(move |observer| {
+                let ratio =
+                    min_disk_ratio(&store_path_disk_ratios(source.store_path_physic.as_str()));
+                if ratio >= 0.0 {
+                    observer.observe(ratio, &[]);
+                }
+            })
+            .init();
+
+        Self {
+            put_latency,
+            put_messages_total,
+            put_bytes_total,
+            get_messages_total,
+            get_bytes_total,
+            _dispatch_behind_bytes: dispatch_behind_bytes,
+            _flush_behind_bytes: flush_behind_bytes,
+            _consume_queue_count: consume_queue_count,
+            _disk_usage_ratio: disk_usage_ratio,
+            _last_boot_abnormal: last_boot_abnormal,
+            _recover_dispatch_messages: recover_dispatch_messages,
+        }
+    }
+
+    /// Views to register on the meter provider so the put latency and lock wait histograms use
+    /// the store's buckets.
+    pub fn get_metrics_view() -> Vec<Box<dyn View>> {
+        [
+            (HISTOGRAM_PUT_LATENCY, PUT_LATENCY_BUCKETS.to_vec()),
+            (
+                HISTOGRAM_PUT_MESSAGE_LOCK_WAIT,
+                PUT_MESSAGE_LOCK_WAIT_BUCKETS.to_vec(),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(name, boundaries)| {
+            let view = new_view(
+                Instrument::new().name(name),
+                Stream::new().aggregation(Aggregation::ExplicitBucketHistogram {
+                    boundaries,
+                    record_min_max: false,
+                }),
+            );
+            match view {
+                Ok(view) => Some(view),
+                Err(err) => {
+                    warn!("create {} view failed: {}", name, err);
+                    None
+                }
+            }
+        })
+        .collect()
+    }
+
+    pub fn record_put_latency(&self, latency_millis: f64) {
+        self.put_latency.record(latency_millis, &[]);
+    }
+
+    pub fn inc_put_message(&self, topic: &str, messages: u64, bytes: u64) {
+        let attributes = [KeyValue::new(LABEL_TOPIC, topic.to_string())];
+        self.put_messages_total.add(messages, &attributes);
+        self.put_bytes_total.add(bytes, &attributes);
+    }
+
+    pub fn inc_get_message(&self, topic: &str, messages: u64, bytes: u64) {
+        let attributes = [KeyValue::new(LABEL_TOPIC, topic.to_string())];
+        self.get_messages_total.add(messages, &attributes);
+        self.get_bytes_total.add(bytes, &attributes);
+    }
+}
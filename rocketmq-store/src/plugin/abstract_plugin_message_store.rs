@@ -0,0 +1,63 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::error::Error;
+use std::future::Future;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use parking_lot::RwLock;
+use rocketmq_common::common::message::message_batch::MessageExtBatch;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+
+use crate::base::dispatch_request::DispatchRequest;
+use crate::base::get_message_result::GetMessageResult;
+use crate::base::message_result::PutMessageResult;
+use crate::base::query_message_result::QueryMessageResult;
+use crate::base::select_result::SelectMappedBufferResult;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::filter::MessageFilter;
+use crate::hook::put_message_hook::BoxedPutMessageHook;
+use crate::log_file::MessageStore;
+use crate::plugin::dyn_message_store::delegate_message_store;
+use crate::plugin::dyn_message_store::BoxedMessageStore;
+use crate::queue::ArcConsumeQueue;
+use crate::stats::broker_stats_manager::BrokerStatsManager;
+use crate::store::running_flags::RunningFlags;
+use crate::timer::timer_message_store::TimerMessageStore;
+
+/// Base of a message store plugin, the counterpart of Java's `AbstractPluginMessageStore`.
+///
+/// A plugin owns the store it wraps and only has to hand it out through [`next`] and
+/// [`next_mut`]; every store operation delegates to it unless the plugin overrides that
+/// operation. Each plugin is a [`MessageStore`] itself, so it can be boxed and wrapped again.
+///
+/// [`next`]: AbstractPluginMessageStore::next
+/// [`next_mut`]: AbstractPluginMessageStore::next_mut
+pub trait AbstractPluginMessageStore: Send + Sync + 'static {
+    /// The wrapped store.
+    fn next(&self) -> &BoxedMessageStore;
+
+    fn next_mut(&mut self) -> &mut BoxedMessageStore;
+
+    delegate_message_store!([plugin_decl]);
+}
+
+impl<T: AbstractPluginMessageStore> MessageStore for T {
+    delegate_message_store!([store_impl AbstractPluginMessageStore]);
+}
@@ -0,0 +1,426 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::error::Error;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use parking_lot::RwLock;
+use rocketmq_common::common::message::message_batch::MessageExtBatch;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_rust::ArcMut;
+
+use crate::base::dispatch_request::DispatchRequest;
+use crate::base::get_message_result::GetMessageResult;
+use crate::base::message_result::PutMessageResult;
+use crate::base::query_message_result::QueryMessageResult;
+use crate::base::select_result::SelectMappedBufferResult;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::filter::MessageFilter;
+use crate::hook::put_message_hook::BoxedPutMessageHook;
+use crate::log_file::MessageStore;
+use crate::queue::ArcConsumeQueue;
+use crate::stats::broker_stats_manager::BrokerStatsManager;
+use crate::store::running_flags::RunningFlags;
+use crate::timer::timer_message_store::TimerMessageStore;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A [`MessageStore`] chained at runtime, e.g. a plugin wrapping the store it was given.
+pub type BoxedMessageStore = Box<dyn DynMessageStore>;
+
+/// Spells out every [`MessageStore`] operation in one of the shapes below, so the store traits
+/// and the delegations between them are generated from a single list:
+///
+/// - `[dyn_decl]` declares the operations of [`DynMessageStore`];
+/// - `[dyn_impl]` implements [`DynMessageStore`] by calling [`MessageStore`] on `self`;
+/// - `[store_impl Trait]` implements [`MessageStore`] by calling `Trait` on `self`, and
+///   `[store_impl Trait, **]` on `self` dereferenced twice;
+/// - `[store_from_dyn]` implements [`MessageStore`] by awaiting the boxed futures of the
+///   [`DynMessageStore`] behind `self`;
+/// - `[plugin_decl]` declares the operations of a plugin, defaulting to the wrapped store.
+///
+/// The futures of [`DynMessageStore`] borrow all their arguments for `'a`, the others elide it.
+macro_rules! delegate_message_store {
+    ([dyn_decl]) => {
+        delegate_message_store!(@each [dyn_decl] 'a, 'a);
+    };
+    ([dyn_impl]) => {
+        delegate_message_store!(@each [dyn_impl] 'a, 'a);
+    };
+    ([$($shape:tt)*]) => {
+        delegate_message_store!(@each [$($shape)*] '_);
+    };
+    (@each $shape:tt $lt:lifetime $(, $decl:lifetime)?) => {
+        delegate_message_store!(@method $shape async load<$($decl)?>(&$lt mut self) -> bool);
+        delegate_message_store!(@method $shape fn start(&mut self) -> Result<(), Box<dyn Error>>);
+        delegate_message_store!(@method $shape fn shutdown(&mut self));
+        delegate_message_store!(@method $shape fn set_confirm_offset(&mut self, phy_offset: i64));
+        delegate_message_store!(@method $shape fn get_max_phy_offset(&self) -> i64);
+        delegate_message_store!(@method $shape fn get_min_phy_offset(&self) -> i64);
+        delegate_message_store!(@method $shape fn set_broker_init_max_offset(
+            &mut self,
+            broker_init_max_offset: i64,
+        ));
+        delegate_message_store!(@method $shape fn now(&self) -> u64);
+        delegate_message_store!(@method $shape fn get_state_machine_version(&self) -> i64);
+        delegate_message_store!(@method $shape async put_message<$($decl)?>(
+            &$lt mut self,
+            msg: MessageExtBrokerInner,
+        ) -> PutMessageResult);
+        delegate_message_store!(@method $shape async put_messages<$($decl)?>(
+            &$lt mut self,
+            msg_batch: MessageExtBatch,
+        ) -> PutMessageResult);
+        delegate_message_store!(@method $shape fn truncate_files(
+            &mut self,
+            offset_to_truncate: i64,
+        ) -> bool);
+        delegate_message_store!(@method $shape fn is_os_page_cache_busy(&self) -> bool);
+        delegate_message_store!(@method $shape fn get_message_store_config(
+            &self,
+        ) -> &MessageStoreConfig);
+        delegate_message_store!(@method $shape fn get_running_flags(&self) -> &RunningFlags);
+        delegate_message_store!(@method $shape fn is_shutdown(&self) -> bool);
+        delegate_message_store!(@method $shape fn get_put_message_hook_list(
+            &self,
+        ) -> Arc<RwLock<Vec<BoxedPutMessageHook>>>);
+        delegate_message_store!(@method $shape fn set_put_message_hook(
+            &self,
+            put_message_hook: BoxedPutMessageHook,
+        ));
+        delegate_message_store!(@method $shape fn get_broker_stats_manager(
+            &self,
+        ) -> Option<Arc<BrokerStatsManager>>);
+        delegate_message_store!(@method $shape fn dispatch_behind_bytes(&self) -> i64);
+        delegate_message_store!(@method $shape fn get_min_offset_in_queue(
+            &self,
+            topic: &CheetahString,
+            queue_id: i32,
+        ) -> i64);
+        delegate_message_store!(@method $shape fn get_max_offset_in_queue(
+            &self,
+            topic: &CheetahString,
+            queue_id: i32,
+        ) -> i64);
+        delegate_message_store!(@method $shape fn get_offset_in_queue_by_time(
+            &self,
+            topic: &CheetahString,
+            queue_id: i32,
+            timestamp: i64,
+        ) -> i64);
+        delegate_message_store!(@method $shape fn get_max_offset_in_queue_committed(
+            &self,
+            topic: &CheetahString,
+            queue_id: i32,
+            committed: bool,
+        ) -> i64);
+        delegate_message_store!(@method $shape
+            #[allow(clippy::too_many_arguments)]
+            async get_message<$($decl)?>(
+                &$lt self,
+                group: &$lt CheetahString,
+                topic: &$lt CheetahString,
+                queue_id: i32,
+                offset: i64,
+                max_msg_nums: i32,
+                max_total_msg_size: i32,
+                message_filter: Option<&$lt dyn MessageFilter>,
+            ) -> Option<GetMessageResult>);
+        delegate_message_store!(@method $shape fn check_in_mem_by_consume_offset(
+            &self,
+            topic: &CheetahString,
+            queue_id: i32,
+            consume_offset: i64,
+            batch_size: i32,
+        ) -> bool);
+        delegate_message_store!(@method $shape fn check_in_cold_area_by_consume_offset(
+            &self,
+            topic: &CheetahString,
+            queue_id: i32,
+            consume_offset: i64,
+        ) -> bool);
+        delegate_message_store!(@method $shape fn get_commit_log_offset_in_queue(
+            &self,
+            topic: &CheetahString,
+            queue_id: i32,
+            consume_queue_offset: i64,
+        ) -> i64);
+        delegate_message_store!(@method $shape fn estimate_message_count(
+            &self,
+            topic: &CheetahString,
+            queue_id: i32,
+            from: i64,
+            to: i64,
+            filter: &dyn MessageFilter,
+        ) -> i64);
+        delegate_message_store!(@method $shape fn notify_message_arrive_if_necessary(
+            &self,
+            dispatch_request: &mut DispatchRequest,
+        ));
+        delegate_message_store!(@method $shape fn find_consume_queue(
+            &self,
+            topic: &CheetahString,
+            queue_id: i32,
+        ) -> Option<ArcConsumeQueue>);
+        delegate_message_store!(@method $shape fn delete_topics(
+            &mut self,
+            delete_topics: Vec<&CheetahString>,
+        ) -> i32);
+        delegate_message_store!(@method $shape async query_message<$($decl)?>(
+            &$lt self,
+            topic: &$lt CheetahString,
+            key: &$lt CheetahString,
+            max_num: i32,
+            begin_timestamp: i64,
+            end_timestamp: i64,
+        ) -> Option<QueryMessageResult>);
+        delegate_message_store!(@method $shape async select_one_message_by_offset<$($decl)?>(
+            &$lt self,
+            commit_log_offset: i64,
+        ) -> Option<SelectMappedBufferResult>);
+        delegate_message_store!(@method $shape
+            async select_one_message_by_offset_with_size<$($decl)?>(
+                &$lt self,
+                commit_log_offset: i64,
+                size: i32,
+            ) -> Option<SelectMappedBufferResult>);
+        delegate_message_store!(@method $shape fn look_message_by_offset(
+            &self,
+            commit_log_offset: i64,
+        ) -> Option<MessageExt>);
+        delegate_message_store!(@method $shape fn look_message_by_offset_with_size(
+            &self,
+            commit_log_offset: i64,
+            size: i32,
+        ) -> Option<MessageExt>);
+        delegate_message_store!(@method $shape fn get_message_store_timestamp(
+            &self,
+            topic: &CheetahString,
+            queue_id: i32,
+            consume_queue_offset: i64,
+        ) -> i64);
+        delegate_message_store!(@method $shape fn get_runtime_info(
+            &self,
+        ) -> HashMap<String, String>);
+        delegate_message_store!(@method $shape fn lock_time_mills(&self) -> i64);
+        delegate_message_store!(@method $shape fn get_earliest_message_time(&self) -> i64);
+        delegate_message_store!(@method $shape fn get_earliest_message_time_in_queue(
+            &self,
+            topic: &CheetahString,
+            queue_id: i32,
+        ) -> i64);
+        delegate_message_store!(@method $shape fn get_timer_message_store(
+            &self,
+        ) -> Arc<TimerMessageStore>);
+        delegate_message_store!(@method $shape fn set_timer_message_store(
+            &mut self,
+            timer_message_store: Arc<TimerMessageStore>,
+        ));
+        delegate_message_store!(@method $shape fn remain_transient_store_buffer_nums(&self) -> i32);
+        delegate_message_store!(@method $shape fn remain_how_many_data_to_commit(&self) -> i64);
+        delegate_message_store!(@method $shape fn remain_how_many_data_to_flush(&self) -> i64);
+    };
+
+    // [dyn_decl]
+    (@method [dyn_decl] $(#[$attr:meta])* fn $name:ident(
+        &mut $self:ident $(, $arg:ident: $ty:ty)* $(,)?
+    ) $(-> $ret:ty)?) => {
+        $(#[$attr])*
+        fn $name(&mut $self $(, $arg: $ty)*) $(-> $ret)?;
+    };
+    (@method [dyn_decl] $(#[$attr:meta])* fn $name:ident(
+        &$self:ident $(, $arg:ident: $ty:ty)* $(,)?
+    ) $(-> $ret:ty)?) => {
+        $(#[$attr])*
+        fn $name(&$self $(, $arg: $ty)*) $(-> $ret)?;
+    };
+    (@method [dyn_decl] $(#[$attr:meta])* async $name:ident<$($g:lifetime)?>(
+        &$l:lifetime mut $self:ident $(, $arg:ident: $ty:ty)* $(,)?
+    ) -> $ret:ty) => {
+        $(#[$attr])*
+        fn $name<$($g)?>(&$l mut $self $(, $arg: $ty)*) -> BoxFuture<$l, $ret>;
+    };
+    (@method [dyn_decl] $(#[$attr:meta])* async $name:ident<$($g:lifetime)?>(
+        &$l:lifetime $self:ident $(, $arg:ident: $ty:ty)* $(,)?
+    ) -> $ret:ty) => {
+        $(#[$attr])*
+        fn $name<$($g)?>(&$l $self $(, $arg: $ty)*) -> BoxFuture<$l, $ret>;
+    };
+
+    // [dyn_impl]
+    (@method [dyn_impl] $(#[$attr:meta])* fn $name:ident(
+        &mut $self:ident $(, $arg:ident: $ty:ty)* $(,)?
+    ) $(-> $ret:ty)?) => {
+        $(#[$attr])*
+        fn $name(&mut $self $(, $arg: $ty)*) $(-> $ret)? {
+            MessageStore::$name($self $(, $arg)*)
+        }
+    };
+    (@method [dyn_impl] $(#[$attr:meta])* fn $name:ident(
+        &$self:ident $(, $arg:ident: $ty:ty)* $(,)?
+    ) $(-> $ret:ty)?) => {
+        $(#[$attr])*
+        fn $name(&$self $(, $arg: $ty)*) $(-> $ret)? {
+            MessageStore::$name($self $(, $arg)*)
+        }
+    };
+    (@method [dyn_impl] $(#[$attr:meta])* async $name:ident<$($g:lifetime)?>(
+        &$l:lifetime mut $self:ident $(, $arg:ident: $ty:ty)* $(,)?
+    ) -> $ret:ty) => {
+        $(#[$attr])*
+        fn $name<$($g)?>(&$l mut $self $(, $arg: $ty)*) -> BoxFuture<$l, $ret> {
+            Box::pin(MessageStore::$name($self $(, $arg)*))
+        }
+    };
+    (@method [dyn_impl] $(#[$attr:meta])* async $name:ident<$($g:lifetime)?>(
+        &$l:lifetime $self:ident $(, $arg:ident: $ty:ty)* $(,)?
+    ) -> $ret:ty) => {
+        $(#[$attr])*
+        fn $name<$($g)?>(&$l $self $(, $arg: $ty)*) -> BoxFuture<$l, $ret> {
+            Box::pin(MessageStore::$name($self $(, $arg)*))
+        }
+    };
+
+    // [store_impl Trait] and [store_impl Trait, **]
+    (@method [store_impl $trait:ident $(, $($deref:tt)+)?] $(#[$attr:meta])* fn $name:ident(
+        &mut $self:ident $(, $arg:ident: $ty:ty)* $(,)?
+    ) $(-> $ret:ty)?) => {
+        $(#[$attr])*
+        fn $name(&mut $self $(, $arg: $ty)*) $(-> $ret)? {
+            $trait::$name(delegate_message_store!(@mut $self $($($deref)+)?) $(, $arg)*)
+        }
+    };
+    (@method [store_impl $trait:ident $(, $($deref:tt)+)?] $(#[$attr:meta])* fn $name:ident(
+        &$self:ident $(, $arg:ident: $ty:ty)* $(,)?
+    ) $(-> $ret:ty)?) => {
+        $(#[$attr])*
+        fn $name(&$self $(, $arg: $ty)*) $(-> $ret)? {
+            $trait::$name(delegate_message_store!(@ref $self $($($deref)+)?) $(, $arg)*)
+        }
+    };
+    (@method [store_impl $trait:ident $(, $($deref:tt)+)?] $(#[$attr:meta])*
+        async $name:ident<$($g:lifetime)?>(
+            &$l:lifetime mut $self:ident $(, $arg:ident: $ty:ty)* $(,)?
+        ) -> $ret:ty) => {
+        $(#[$attr])*
+        fn $name(&mut $self $(, $arg: $ty)*) -> impl Future<Output = $ret> + Send {
+            $trait::$name(delegate_message_store!(@mut $self $($($deref)+)?) $(, $arg)*)
+        }
+    };
+    (@method [store_impl $trait:ident $(, $($deref:tt)+)?] $(#[$attr:meta])*
+        async $name:ident<$($g:lifetime)?>(
+            &$l:lifetime $self:ident $(, $arg:ident: $ty:ty)* $(,)?
+        ) -> $ret:ty) => {
+        $(#[$attr])*
+        fn $name(&$self $(, $arg: $ty)*) -> impl Future<Output = $ret> + Send {
+            $trait::$name(delegate_message_store!(@ref $self $($($deref)+)?) $(, $arg)*)
+        }
+    };
+
+    // [store_from_dyn]
+    (@method [store_from_dyn] $(#[$attr:meta])* fn $($rest:tt)*) => {
+        delegate_message_store!(@method [store_impl DynMessageStore, **] $(#[$attr])* fn $($rest)*);
+    };
+    (@method [store_from_dyn] $(#[$attr:meta])* async $name:ident<$($g:lifetime)?>(
+        &$l:lifetime mut $self:ident $(, $arg:ident: $ty:ty)* $(,)?
+    ) -> $ret:ty) => {
+        $(#[$attr])*
+        async fn $name(&mut $self $(, $arg: $ty)*) -> $ret {
+            DynMessageStore::$name(&mut **$self $(, $arg)*).await
+        }
+    };
+    (@method [store_from_dyn] $(#[$attr:meta])* async $name:ident<$($g:lifetime)?>(
+        &$l:lifetime $self:ident $(, $arg:ident: $ty:ty)* $(,)?
+    ) -> $ret:ty) => {
+        $(#[$attr])*
+        async fn $name(&$self $(, $arg: $ty)*) -> $ret {
+            DynMessageStore::$name(&**$self $(, $arg)*).await
+        }
+    };
+
+    (@mut $self:ident) => {
+        $self
+    };
+    (@mut $self:ident $($deref:tt)+) => {
+        &mut $($deref)+ $self
+    };
+    (@ref $self:ident) => {
+        $self
+    };
+    (@ref $self:ident $($deref:tt)+) => {
+        &$($deref)+ $self
+    };
+
+    // [plugin_decl]
+    (@method [plugin_decl] $(#[$attr:meta])* fn $name:ident(
+        &mut $self:ident $(, $arg:ident: $ty:ty)* $(,)?
+    ) $(-> $ret:ty)?) => {
+        $(#[$attr])*
+        fn $name(&mut $self $(, $arg: $ty)*) $(-> $ret)? {
+            $self.next_mut().$name($($arg),*)
+        }
+    };
+    (@method [plugin_decl] $(#[$attr:meta])* fn $name:ident(
+        &$self:ident $(, $arg:ident: $ty:ty)* $(,)?
+    ) $(-> $ret:ty)?) => {
+        $(#[$attr])*
+        fn $name(&$self $(, $arg: $ty)*) $(-> $ret)? {
+            $self.next().$name($($arg),*)
+        }
+    };
+    (@method [plugin_decl] $(#[$attr:meta])* async $name:ident<$($g:lifetime)?>(
+        &$l:lifetime mut $self:ident $(, $arg:ident: $ty:ty)* $(,)?
+    ) -> $ret:ty) => {
+        $(#[$attr])*
+        fn $name(&mut $self $(, $arg: $ty)*) -> impl Future<Output = $ret> + Send {
+            $self.next_mut().$name($($arg),*)
+        }
+    };
+    (@method [plugin_decl] $(#[$attr:meta])* async $name:ident<$($g:lifetime)?>(
+        &$l:lifetime $self:ident $(, $arg:ident: $ty:ty)* $(,)?
+    ) -> $ret:ty) => {
+        $(#[$attr])*
+        fn $name(&$self $(, $arg: $ty)*) -> impl Future<Output = $ret> + Send {
+            $self.next().$name($($arg),*)
+        }
+    };
+}
+
+pub(crate) use delegate_message_store;
+
+/// The object safe form of [`MessageStore`]: the same operations, with the asynchronous ones
+/// returning boxed futures. Every [`MessageStore`] is one, and a [`BoxedMessageStore`] is a
+/// [`MessageStore`] again, so code generic over the store runs unchanged on a chain.
+pub trait DynMessageStore: Send + Sync + 'static {
+    delegate_message_store!([dyn_decl]);
+}
+
+impl<MS: MessageStore + Send> DynMessageStore for MS {
+    delegate_message_store!([dyn_impl]);
+}
+
+impl<MS: MessageStore> MessageStore for ArcMut<MS> {
+    delegate_message_store!([store_impl MessageStore, **]);
+}
+
+impl MessageStore for BoxedMessageStore {
+    delegate_message_store!([store_from_dyn]);
+}
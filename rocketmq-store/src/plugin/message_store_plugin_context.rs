@@ -0,0 +1,59 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::Arc;
+
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::stats::broker_stats_manager::BrokerStatsManager;
+
+/// What a message store plugin gets to see of the broker when it is created.
+#[derive(Clone)]
+pub struct MessageStorePluginContext {
+    message_store_config: Arc<MessageStoreConfig>,
+    broker_config: Arc<BrokerConfig>,
+    broker_stats_manager: Option<Arc<BrokerStatsManager>>,
+}
+
+impl MessageStorePluginContext {
+    pub fn new(
+        message_store_config: Arc<MessageStoreConfig>,
+        broker_config: Arc<BrokerConfig>,
+        broker_stats_manager: Option<Arc<BrokerStatsManager>>,
+    ) -> Self {
+        Self {
+            message_store_config,
+            broker_config,
+            broker_stats_manager,
+        }
+    }
+
+    #[inline]
+    pub fn message_store_config(&self) -> &Arc<MessageStoreConfig> {
+        &self.message_store_config
+    }
+
+    #[inline]
+    pub fn broker_config(&self) -> &Arc<BrokerConfig> {
+        &self.broker_config
+    }
+
+    #[inline]
+    pub fn broker_stats_manager(&self) -> Option<&Arc<BrokerStatsManager>> {
+        self.broker_stats_manager.as_ref()
+    }
+}
@@ -27,11 +27,13 @@ use crate::base::dispatch_request::DispatchRequest;
 use crate::base::swappable::Swappable;
 use crate::consume_queue::consume_queue_ext::CqExtUnit;
 use crate::filter::MessageFilter;
+use crate::log_file::commit_log::CommitLog;
 use crate::queue::consume_queue_ext::ConsumeQueueExt;
 use crate::queue::queue_offset_operator::QueueOffsetOperator;
 
 mod batch_consume_queue;
 pub mod build_consume_queue;
+pub mod consume_queue_check;
 mod consume_queue_ext;
 pub mod local_file_consume_queue_store;
 mod queue_offset_operator;
@@ -408,7 +410,7 @@ pub trait ConsumeQueueStoreTrait: Send + Sync {
     /// # Arguments
     /// * `queue_key` - The key identifying the logical message queue.
     /// * `message_num` - The number of messages added, used for batch operations.
-    fn increase_lmq_offset(&mut self, queue_key: &CheetahString, message_num: i16);
+    fn increase_lmq_offset(&self, queue_key: &CheetahString, message_num: i16);
 
     /// Retrieves the current offset for a given logical message queue (LMQ).
     ///
@@ -422,6 +424,9 @@ pub trait ConsumeQueueStoreTrait: Send + Sync {
     /// The current offset of the logical message queue as a 64-bit integer.
     fn get_lmq_queue_offset(&self, queue_key: &CheetahString) -> i64;
 
+    /// Returns how many logical message queues (LMQ) currently have an offset tracked.
+    fn get_lmq_num(&self) -> usize;
+
     /// Recovers the offset table based on the minimum physical offset.
     ///
     /// This method is used to recover or adjust the offset table for consume queues based on the
@@ -687,16 +692,20 @@ pub trait ConsumeQueueTrait: Send + Sync + FileQueueLifeCycle {
     ///
     /// # Arguments
     /// * `timestamp` - The timestamp to query by.
+    /// * `commit_log` - The commit log the queue entries point into, used to read store times.
     ///
     /// # Returns
-    /// The offset in the queue as a 64-bit integer.
-    fn get_offset_in_queue_by_time(&self, timestamp: i64) -> i64;
+    /// The first offset whose message was stored at or after `timestamp`, clamped to the
+    /// queue's min/max offsets.
+    fn get_offset_in_queue_by_time(&self, timestamp: i64, commit_log: &CommitLog) -> i64;
 
     /// Retrieves the offset in the queue by a specific timestamp, considering boundary conditions.
     ///
     /// # Arguments
     /// * `timestamp` - The timestamp to query by.
-    /// * `boundary_type` - The boundary condition to apply (e.g., exact match, closest match).
+    /// * `boundary_type` - `Lower` returns the first offset stored at or after `timestamp`, `Upper`
+    ///   the last offset stored at or before it.
+    /// * `commit_log` - The commit log the queue entries point into, used to read store times.
     ///
     /// # Returns
     /// The offset in the queue as a 64-bit integer.
@@ -704,6 +713,7 @@ pub trait ConsumeQueueTrait: Send + Sync + FileQueueLifeCycle {
         &self,
         timestamp: i64,
         boundary_type: BoundaryType,
+        commit_log: &CommitLog,
     ) -> i64;
 
     /// Returns the maximum physical offset in the consume queue.
@@ -715,6 +725,12 @@ pub trait ConsumeQueueTrait: Send + Sync + FileQueueLifeCycle {
     /// The maximum physical offset as a 64-bit integer.
     fn get_max_physic_offset(&self) -> i64;
 
+    /// Returns the logical offset up to which the consume queue has been flushed to disk.
+    ///
+    /// # Returns
+    /// The flushed position as a 64-bit integer.
+    fn get_flushed_where(&self) -> i64;
+
     /// Returns the minimum logical offset in the consume queue.
     ///
     /// This method retrieves the smallest logical offset in the consume queue, useful for
@@ -31,6 +31,7 @@ use crate::base::swappable::Swappable;
 use crate::config::message_store_config::MessageStoreConfig;
 use crate::consume_queue::mapped_file_queue::MappedFileQueue;
 use crate::filter::MessageFilter;
+use crate::log_file::commit_log::CommitLog;
 use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
 use crate::queue::queue_offset_operator::QueueOffsetOperator;
 use crate::queue::ConsumeQueueTrait;
@@ -149,11 +150,11 @@ impl FileQueueLifeCycle for BatchConsumeQueue {
     }
 
     fn check_self(&self) {
-        todo!()
+        self.mapped_file_queue.check_self();
     }
 
     fn flush(&self, flush_least_pages: i32) -> bool {
-        todo!()
+        self.mapped_file_queue.flush(flush_least_pages)
     }
 
     fn destroy(&mut self) {
@@ -241,7 +242,7 @@ impl ConsumeQueueTrait for BatchConsumeQueue {
         todo!()
     }
 
-    fn get_offset_in_queue_by_time(&self, timestamp: i64) -> i64 {
+    fn get_offset_in_queue_by_time(&self, timestamp: i64, commit_log: &CommitLog) -> i64 {
         todo!()
     }
 
@@ -249,6 +250,7 @@ impl ConsumeQueueTrait for BatchConsumeQueue {
         &self,
         timestamp: i64,
         boundary_type: BoundaryType,
+        commit_log: &CommitLog,
     ) -> i64 {
         todo!()
     }
@@ -257,6 +259,10 @@ impl ConsumeQueueTrait for BatchConsumeQueue {
         todo!()
     }
 
+    fn get_flushed_where(&self) -> i64 {
+        self.mapped_file_queue.get_flushed_where()
+    }
+
     fn get_min_logic_offset(&self) -> i64 {
         todo!()
     }
@@ -0,0 +1,168 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Startup sanity check of the consume queues against the commit log they index.
+
+use std::fmt;
+use std::fmt::Display;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::log_file::commit_log;
+use crate::log_file::commit_log::CommitLog;
+use crate::queue::ConsumeQueueTrait;
+
+/// Entries read back per queue on top of the last one, spread evenly over the queue.
+const SAMPLED_ENTRIES: i64 = 16;
+
+/// What the startup check found wrong with a consume queue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsumeQueueDamage {
+    /// The queue indexes data past the end of the commit log.
+    BeyondCommitLog {
+        max_physic_offset: i64,
+        commit_log_max_offset: i64,
+    },
+    /// The entry at `queue_offset` does not point at a message of this queue.
+    BrokenEntry { queue_offset: i64 },
+    /// The queue offset table, which assigns the next queue offset, disagrees with the number
+    /// of entries in the queue.
+    QueueOffsetMismatch {
+        table_offset: i64,
+        max_offset_in_queue: i64,
+    },
+}
+
+/// A consume queue reported by `DefaultMessageStore::check_consume_queues`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DamagedConsumeQueue {
+    pub topic: CheetahString,
+    pub queue_id: i32,
+    pub damage: ConsumeQueueDamage,
+}
+
+impl Display for DamagedConsumeQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "consume queue {}-{}: ", self.topic, self.queue_id)?;
+        match self.damage {
+            ConsumeQueueDamage::BeyondCommitLog {
+                max_physic_offset,
+                commit_log_max_offset,
+            } => write!(
+                f,
+                "max physic offset {max_physic_offset} is beyond the commit log max offset \
+                 {commit_log_max_offset}"
+            ),
+            ConsumeQueueDamage::BrokenEntry { queue_offset } => write!(
+                f,
+                "entry {queue_offset} does not point at a message of the queue"
+            ),
+            ConsumeQueueDamage::QueueOffsetMismatch {
+                table_offset,
+                max_offset_in_queue,
+            } => write!(
+                f,
+                "queue offset table holds {table_offset} but the queue has {max_offset_in_queue} \
+                 entries"
+            ),
+        }
+    }
+}
+
+/// Checks a simple consume queue against `commit_log`: its max physic offset, a sample of its
+/// entries, which have to point at the start of a message of this queue stored at the same
+/// queue offset, and `table_offset`, the next offset the queue offset table hands out.
+pub(crate) fn check_consume_queue(
+    consume_queue: &dyn ConsumeQueueTrait,
+    commit_log: &CommitLog,
+    table_offset: i64,
+    message_store_config: &Arc<MessageStoreConfig>,
+) -> Option<ConsumeQueueDamage> {
+    let max_physic_offset = consume_queue.get_max_physic_offset();
+    let commit_log_max_offset = commit_log.get_max_offset();
+    if max_physic_offset > commit_log_max_offset {
+        return Some(ConsumeQueueDamage::BeyondCommitLog {
+            max_physic_offset,
+            commit_log_max_offset,
+        });
+    }
+
+    let min_offset_in_queue = consume_queue.get_min_offset_in_queue();
+    let max_offset_in_queue = consume_queue.get_max_offset_in_queue();
+    if max_offset_in_queue > min_offset_in_queue {
+        let step = ((max_offset_in_queue - min_offset_in_queue) / SAMPLED_ENTRIES).max(1);
+        let sampled = (min_offset_in_queue..max_offset_in_queue)
+            .step_by(step as usize)
+            .chain(std::iter::once(max_offset_in_queue - 1));
+        for queue_offset in sampled {
+            if !entry_points_at_message(
+                consume_queue,
+                commit_log,
+                queue_offset,
+                message_store_config,
+            ) {
+                return Some(ConsumeQueueDamage::BrokenEntry { queue_offset });
+            }
+        }
+    }
+
+    if table_offset != max_offset_in_queue {
+        return Some(ConsumeQueueDamage::QueueOffsetMismatch {
+            table_offset,
+            max_offset_in_queue,
+        });
+    }
+    None
+}
+
+fn entry_points_at_message(
+    consume_queue: &dyn ConsumeQueueTrait,
+    commit_log: &CommitLog,
+    queue_offset: i64,
+    message_store_config: &Arc<MessageStoreConfig>,
+) -> bool {
+    let Some(cq_unit) = consume_queue.get(queue_offset) else {
+        return false;
+    };
+    if cq_unit.pos < commit_log.get_min_offset() {
+        // the commit log file has expired already, nothing left to compare with
+        return true;
+    }
+    let Some(mut bytes) = commit_log
+        .get_message(cq_unit.pos, cq_unit.size)
+        .and_then(|result| result.get_bytes())
+    else {
+        return false;
+    };
+    if bytes.len() != cq_unit.size as usize {
+        return false;
+    }
+    let request = commit_log::check_message_and_return_size(
+        &mut bytes,
+        false,
+        false,
+        false,
+        message_store_config,
+    );
+    request.success
+        && request.msg_size == cq_unit.size
+        && request.topic == *consume_queue.get_topic()
+        && request.queue_id == consume_queue.get_queue_id()
+        && request.consume_queue_offset == queue_offset
+}
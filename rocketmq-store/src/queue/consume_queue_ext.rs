@@ -15,12 +15,19 @@
  * limitations under the License.
  */
 use std::path::PathBuf;
+use std::sync::Arc;
 
+use bytes::Buf;
 use cheetah_string::CheetahString;
+use tracing::error;
 use tracing::info;
+use tracing::warn;
 
 use crate::consume_queue::consume_queue_ext::CqExtUnit;
+use crate::consume_queue::consume_queue_ext::MIN_EXT_UNIT_SIZE;
 use crate::consume_queue::mapped_file_queue::MappedFileQueue;
+use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
+use crate::log_file::mapped_file::MappedFile;
 
 const END_BLANK_DATA_LENGTH: usize = 4;
 
@@ -28,6 +35,12 @@ const END_BLANK_DATA_LENGTH: usize = 4;
 const MAX_ADDR: i64 = i32::MIN as i64 - 1;
 const MAX_REAL_OFFSET: i64 = MAX_ADDR - i64::MIN;
 
+/// Extend of the consume queue, holding what does not fit a 20 bytes consume queue unit: the
+/// store time and the filter bit map of each message.
+///
+/// The consume queue unit keeps the address of its [`CqExtUnit`] in the tags code field. Real
+/// offsets are shifted by `i64::MIN`, so addresses are always below `i32::MIN` and never collide
+/// with a plain tags code.
 #[derive(Clone)]
 pub struct ConsumeQueueExt {
     mapped_file_queue: MappedFileQueue,
@@ -43,7 +56,7 @@ impl ConsumeQueueExt {
         queue_id: i32,
         store_path: CheetahString,
         mapped_file_size: i32,
-        bit_map_length: i32,
+        _bit_map_length: i32,
     ) -> Self {
         let queue_dir = PathBuf::from(store_path.as_str())
             .join(topic.as_str())
@@ -65,12 +78,81 @@ impl ConsumeQueueExt {
     pub fn is_ext_addr(address: i64) -> bool {
         address <= MAX_ADDR
     }
+
+    /// Turns a real offset of the ext files into an address stored in the consume queue.
+    pub fn decorate(offset: i64) -> i64 {
+        if Self::is_ext_addr(offset) {
+            offset
+        } else {
+            offset.wrapping_add(i64::MIN)
+        }
+    }
+
+    /// Turns an address stored in the consume queue back into a real offset of the ext files.
+    pub fn un_decorate(address: i64) -> i64 {
+        if Self::is_ext_addr(address) {
+            address.wrapping_sub(i64::MIN)
+        } else {
+            address
+        }
+    }
 }
 
 impl ConsumeQueueExt {
-    pub fn truncate_by_max_address(&self, max_address: i64) {}
+    /// Drops everything written after the unit at `max_address`.
+    pub fn truncate_by_max_address(&mut self, max_address: i64) {
+        if !Self::is_ext_addr(max_address) {
+            return;
+        }
+        info!(
+            "Truncate consume queue ext by max address {}, {}-{}",
+            max_address, self.topic, self.queue_id
+        );
+        let Some(cq_ext_unit) = self.get(max_address) else {
+            error!(
+                "[BUG] address {} of consume queue ext {}-{} is not available",
+                max_address, self.topic, self.queue_id
+            );
+            return;
+        };
+        let real_offset = Self::un_decorate(max_address);
+        self.mapped_file_queue
+            .truncate_dirty_files(real_offset + cq_ext_unit.size() as i64);
+    }
 
-    pub fn truncate_by_min_address(&self, min_address: i64) {}
+    /// Deletes the files holding only units before `min_address`.
+    pub fn truncate_by_min_address(&self, min_address: i64) {
+        if !Self::is_ext_addr(min_address) {
+            return;
+        }
+        let real_offset = Self::un_decorate(min_address);
+        let will_remove_files: Vec<Arc<DefaultMappedFile>> = self
+            .mapped_file_queue
+            .get_mapped_files()
+            .read()
+            .iter()
+            .filter(|mapped_file| {
+                mapped_file.get_file_from_offset() as i64 + self.mapped_file_size as i64
+                    <= real_offset
+            })
+            .cloned()
+            .collect();
+        if will_remove_files.is_empty() {
+            return;
+        }
+        info!(
+            "Truncate consume queue ext {}-{} by min address {}, {} files",
+            self.topic,
+            self.queue_id,
+            min_address,
+            will_remove_files.len()
+        );
+        for mapped_file in &will_remove_files {
+            mapped_file.destroy(1000);
+        }
+        self.mapped_file_queue
+            .delete_expired_file(will_remove_files);
+    }
 
     pub fn load(&mut self) -> bool {
         let result = self.mapped_file_queue.load();
@@ -84,17 +166,235 @@ impl ConsumeQueueExt {
         result
     }
 
-    pub fn recover(&mut self) {}
+    /// Finds the end of the last written unit, the consume queue truncates the tail afterwards.
+    pub fn recover(&mut self) {
+        let Some(last_mapped_file) = self.mapped_file_queue.get_last_mapped_file() else {
+            return;
+        };
+        let mut mapped_file_offset = 0usize;
+        while let Some(unit) = Self::read_unit(&last_mapped_file, mapped_file_offset) {
+            mapped_file_offset += unit.size() as usize;
+        }
+        let process_offset =
+            last_mapped_file.get_file_from_offset() as i64 + mapped_file_offset as i64;
+        self.mapped_file_queue.set_flushed_where(process_offset);
+        self.mapped_file_queue.set_committed_where(process_offset);
+        self.mapped_file_queue.truncate_dirty_files(process_offset);
+    }
 
-    pub fn put(&self, cq_ext_unit: CqExtUnit) -> i64 {
-        unimplemented!()
+    /// Appends `cq_ext_unit`, returning its address or `1` when it could not be written.
+    pub fn put(&mut self, cq_ext_unit: CqExtUnit) -> i64 {
+        let Some(size) = cq_ext_unit.calc_unit_size() else {
+            error!(
+                "Size of consume queue ext unit is greater than {}",
+                i16::MAX
+            );
+            return 1;
+        };
+        let size = size as usize;
+        if size + END_BLANK_DATA_LENGTH > self.mapped_file_size as usize {
+            error!(
+                "Size of consume queue ext unit {} does not fit a file of {}",
+                size, self.mapped_file_size
+            );
+            return 1;
+        }
+        if self.mapped_file_queue.get_max_offset() + size as i64 > MAX_REAL_OFFSET {
+            warn!(
+                "Capacity of ext is maximum! {}",
+                self.mapped_file_queue.get_max_offset()
+            );
+            return 1;
+        }
+        loop {
+            let Some(mapped_file) = self
+                .mapped_file_queue
+                .get_last_mapped_file_mut_start_offset(0, true)
+            else {
+                error!(
+                    "Create mapped file when save consume queue extend, {}-{}",
+                    self.topic, self.queue_id
+                );
+                return 1;
+            };
+            let wrote_position = mapped_file.get_wrote_position() as usize;
+            let blank_size =
+                self.mapped_file_size as usize - wrote_position - END_BLANK_DATA_LENGTH;
+            if size > blank_size {
+                Self::full_fill_to_end(&mapped_file, wrote_position);
+                info!(
+                    "No enough space(need:{}, has:{}) of file {}, so fill to end",
+                    size,
+                    blank_size,
+                    mapped_file.get_file_name()
+                );
+                continue;
+            }
+            let data = cq_ext_unit.encode();
+            if mapped_file.append_message_offset_length(&data, 0, size) {
+                return Self::decorate(
+                    wrote_position as i64 + mapped_file.get_file_from_offset() as i64,
+                );
+            }
+            error!(
+                "Save consume queue extend error, {}-{}",
+                self.topic, self.queue_id
+            );
+            return 1;
+        }
+    }
+
+    pub fn flush(&self, flush_least_pages: i32) -> bool {
+        self.mapped_file_queue.flush(flush_least_pages)
     }
 
     pub fn destroy(&mut self) {
         self.mapped_file_queue.destroy();
     }
 
-    pub fn get(&self, address: i64, cq_ext_unit: &CqExtUnit) -> bool {
-        unimplemented!()
+    /// Reads the unit stored at `address`.
+    pub fn get(&self, address: i64) -> Option<CqExtUnit> {
+        if !Self::is_ext_addr(address) {
+            return None;
+        }
+        let real_offset = Self::un_decorate(address);
+        let mapped_file = self
+            .mapped_file_queue
+            .find_mapped_file_by_offset(real_offset, false)?;
+        let pos = (real_offset % self.mapped_file_size as i64) as usize;
+        Self::read_unit(&mapped_file, pos)
+    }
+
+    pub fn get_max_address(&self) -> i64 {
+        Self::decorate(self.mapped_file_queue.get_max_offset())
+    }
+
+    pub fn get_min_address(&self) -> i64 {
+        match self.mapped_file_queue.get_first_mapped_file() {
+            None => Self::decorate(0),
+            Some(mapped_file) => Self::decorate(mapped_file.get_file_from_offset() as i64),
+        }
+    }
+
+    fn read_unit(mapped_file: &DefaultMappedFile, pos: usize) -> Option<CqExtUnit> {
+        if pos + MIN_EXT_UNIT_SIZE as usize > mapped_file.get_read_position() as usize {
+            return None;
+        }
+        let size = mapped_file.get_bytes(pos, 2)?.get_i16();
+        if size < MIN_EXT_UNIT_SIZE {
+            return None;
+        }
+        CqExtUnit::decode(&mut mapped_file.get_bytes(pos, size as usize)?)
+    }
+
+    /// Marks the end of the data with a negative size so readers move to the next file.
+    fn full_fill_to_end(mapped_file: &DefaultMappedFile, wrote_position: usize) {
+        mapped_file.put_slice(&(-1i16).to_be_bytes(), wrote_position);
+        mapped_file.set_wrote_position(mapped_file.get_file_size() as i32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 3 units of 24 bytes fit a file, the 4th one goes to the next file
+    const FILE_SIZE: i32 = 90;
+
+    fn ext_queue(dir: &tempfile::TempDir) -> ConsumeQueueExt {
+        ConsumeQueueExt::new(
+            CheetahString::from_static_str("ext_topic"),
+            0,
+            CheetahString::from_string(dir.path().to_string_lossy().into_owned()),
+            FILE_SIZE,
+            64,
+        )
+    }
+
+    fn unit(n: i64) -> CqExtUnit {
+        CqExtUnit::new(n + i32::MAX as i64, 1_000 + n, Some(vec![n as u8; 4]))
+    }
+
+    #[test]
+    fn put_and_get_round_trip_across_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ext = ext_queue(&dir);
+        let addresses: Vec<i64> = (0..5).map(|n| ext.put(unit(n))).collect();
+        for (n, address) in addresses.iter().enumerate() {
+            assert!(ConsumeQueueExt::is_ext_addr(*address));
+            let read = ext.get(*address).unwrap();
+            assert_eq!(read.tags_code(), n as i64 + i32::MAX as i64);
+            assert_eq!(read.msg_store_time(), 1_000 + n as i64);
+            assert_eq!(read.filter_bit_map(), &Some(vec![n as u8; 4]));
+        }
+        assert_eq!(ConsumeQueueExt::un_decorate(addresses[3]), FILE_SIZE as i64);
+        assert!(ext.get(-5).is_none());
+
+        let no_bit_map = ext.put(CqExtUnit::new(7, 8, None));
+        let read = ext.get(no_bit_map).unwrap();
+        assert_eq!((read.tags_code(), read.msg_store_time()), (7, 8));
+        assert!(read.filter_bit_map().is_none());
+    }
+
+    #[test]
+    fn put_rejects_unit_larger_than_a_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ext = ext_queue(&dir);
+        assert_eq!(ext.put(CqExtUnit::new(1, 1, Some(vec![0; 200]))), 1);
+    }
+
+    #[test]
+    fn recover_finds_the_end_of_the_last_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let addresses: Vec<i64> = {
+            let mut ext = ext_queue(&dir);
+            let addresses = (0..4).map(|n| ext.put(unit(n))).collect();
+            ext.flush(0);
+            addresses
+        };
+
+        let mut ext = ext_queue(&dir);
+        assert!(ext.load());
+        ext.recover();
+        assert_eq!(
+            ConsumeQueueExt::un_decorate(ext.get_max_address()),
+            FILE_SIZE as i64 + 24
+        );
+        assert_eq!(ext.get(addresses[3]).unwrap().msg_store_time(), 1_003);
+        let next = ext.put(unit(9));
+        assert_eq!(ConsumeQueueExt::un_decorate(next), FILE_SIZE as i64 + 24);
+    }
+
+    #[test]
+    fn truncate_by_max_address_drops_later_units() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ext = ext_queue(&dir);
+        let addresses: Vec<i64> = (0..5).map(|n| ext.put(unit(n))).collect();
+
+        ext.truncate_by_max_address(addresses[1]);
+        assert!(ext.get(addresses[1]).is_some());
+        assert!(ext.get(addresses[2]).is_none());
+        assert!(ext.get(addresses[4]).is_none());
+        assert_eq!(ext.get_max_address(), addresses[2]);
+        // plain tags codes are not addresses
+        ext.truncate_by_max_address(1);
+        assert!(ext.get(addresses[1]).is_some());
+    }
+
+    #[test]
+    fn truncate_by_min_address_deletes_earlier_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ext = ext_queue(&dir);
+        let addresses: Vec<i64> = (0..7).map(|n| ext.put(unit(n))).collect();
+        assert_eq!(ext.mapped_file_queue.get_mapped_files_size(), 3);
+
+        ext.truncate_by_min_address(addresses[4]);
+        assert_eq!(ext.mapped_file_queue.get_mapped_files_size(), 2);
+        assert!(ext.get(addresses[0]).is_none());
+        assert_eq!(ext.get(addresses[4]).unwrap().msg_store_time(), 1_004);
+        assert_eq!(
+            ext.get_min_address(),
+            ConsumeQueueExt::decorate(FILE_SIZE as i64)
+        );
     }
 }
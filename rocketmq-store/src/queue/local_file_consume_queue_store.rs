@@ -27,9 +27,13 @@ use rocketmq_common::common::attribute::cq_type::CQType;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
 use rocketmq_common::common::config::TopicConfig;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::mix_all::is_lmq;
+use rocketmq_common::common::mix_all::LMQ_QUEUE_ID;
 use rocketmq_common::utils::queue_type_utils::QueueTypeUtils;
 use rocketmq_rust::ArcMut;
+use tracing::error;
 use tracing::info;
+use tracing::warn;
 
 use crate::base::dispatch_request::DispatchRequest;
 use crate::base::store_checkpoint::StoreCheckpoint;
@@ -43,8 +47,7 @@ use crate::queue::ConsumeQueueTable;
 use crate::queue::ConsumeQueueTrait;
 use crate::queue::CqUnit;
 use crate::store::running_flags::RunningFlags;
-use crate::store_path_config_helper::get_store_path_batch_consume_queue;
-use crate::store_path_config_helper::get_store_path_consume_queue;
+use crate::utils::multi_dispatch_utils;
 
 #[derive(Clone)]
 pub struct ConsumeQueueStore {
@@ -93,6 +96,37 @@ impl ConsumeQueueStore {
             topic_config_table,
         }
     }
+
+    fn multi_dispatch_lmq_queue(&self, request: &DispatchRequest) {
+        let Some(queue_offsets) = request
+            .properties_map
+            .as_ref()
+            .and_then(multi_dispatch_utils::multi_dispatch_queue_offsets)
+        else {
+            error!(
+                "[bug] queues and offsets of multi dispatch not matched, topic: {}, \
+                 commitLogOffset: {}",
+                request.topic, request.commit_log_offset
+            );
+            return;
+        };
+        for (queue_name, queue_offset) in queue_offsets {
+            if !self.inner.message_store_config.enable_lmq || !is_lmq(Some(queue_name.as_str())) {
+                warn!(
+                    "skip multi dispatch to {}, only light message queues are supported",
+                    queue_name
+                );
+                continue;
+            }
+            let mut lmq_request = request.clone();
+            lmq_request.topic = queue_name;
+            lmq_request.queue_id = LMQ_QUEUE_ID as i32;
+            lmq_request.consume_queue_offset = queue_offset;
+            let mut cq =
+                self.find_or_create_consume_queue(&lmq_request.topic, lmq_request.queue_id);
+            self.put_message_position_info_wrapper_with_cq(&mut **cq.as_mut(), &lmq_request);
+        }
+    }
 }
 
 #[allow(unused_variables)]
@@ -103,14 +137,18 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
 
     fn load(&mut self) -> bool {
         self.load_consume_queues(
-            CheetahString::from_string(get_store_path_consume_queue(
-                self.inner.message_store_config.store_path_root_dir.as_str(),
-            )),
+            CheetahString::from_string(
+                self.inner
+                    .message_store_config
+                    .get_store_path_consume_queue(),
+            ),
             CQType::SimpleCQ,
         ) & self.load_consume_queues(
-            CheetahString::from_string(get_store_path_batch_consume_queue(
-                self.inner.message_store_config.store_path_root_dir.as_str(),
-            )),
+            CheetahString::from_string(
+                self.inner
+                    .message_store_config
+                    .get_store_path_batch_consume_queue(),
+            ),
             CQType::BatchCQ,
         )
     }
@@ -158,7 +196,7 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
     }
 
     fn flush(&self, consume_queue: &dyn ConsumeQueueTrait, flush_least_pages: i32) -> bool {
-        todo!()
+        consume_queue.flush(flush_least_pages)
     }
 
     fn clean_expired(&self, min_phy_offset: i64) {
@@ -166,7 +204,11 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
     }
 
     fn check_self(&self) {
-        println!("ConsumeQueueStore::check_self unimplemented");
+        for consume_queue_table in self.inner.consume_queue_table.lock().values() {
+            for consume_queue in consume_queue_table.values() {
+                consume_queue.check_self();
+            }
+        }
     }
 
     fn delete_expired_file(
@@ -174,7 +216,7 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
         consume_queue: &dyn ConsumeQueueTrait,
         min_commit_log_pos: i64,
     ) -> i32 {
-        todo!()
+        consume_queue.delete_expired_file(min_commit_log_pos)
     }
 
     fn is_first_file_available(&self, consume_queue: &dyn ConsumeQueueTrait) -> bool {
@@ -203,7 +245,12 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
     fn put_message_position_info_wrapper(&self, request: &DispatchRequest) {
         let mut cq = self.find_or_create_consume_queue(request.topic.as_ref(), request.queue_id);
         self.put_message_position_info_wrapper_with_cq(&mut **cq.as_mut(), request);
-        // println!("put_message_position_info_wrapper-----{}", request.topic)
+        if multi_dispatch_utils::check_multi_dispatch_queue(
+            &self.inner.message_store_config,
+            request,
+        ) {
+            self.multi_dispatch_lmq_queue(request);
+        }
     }
 
     fn put_message_position_info_wrapper_with_cq(
@@ -239,12 +286,18 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
         consume_queue.assign_queue_offset(&self.inner.queue_offset_operator, msg);
     }
 
-    fn increase_lmq_offset(&mut self, queue_key: &CheetahString, message_num: i16) {
-        todo!()
+    fn increase_lmq_offset(&self, queue_key: &CheetahString, message_num: i16) {
+        self.inner
+            .queue_offset_operator
+            .increase_lmq_offset(queue_key, message_num);
     }
 
     fn get_lmq_queue_offset(&self, queue_key: &CheetahString) -> i64 {
-        todo!()
+        self.inner.queue_offset_operator.get_lmq_offset(queue_key)
+    }
+
+    fn get_lmq_num(&self) -> usize {
+        self.inner.queue_offset_operator.get_lmq_num()
     }
 
     fn recover_offset_table(&mut self, min_phy_offset: i64) {
@@ -336,9 +389,11 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
                 CQType::SimpleCQ => ArcMut::new(Box::new(ConsumeQueue::new(
                     topic.clone(),
                     queue_id,
-                    CheetahString::from_string(get_store_path_consume_queue(
-                        self.inner.message_store_config.store_path_root_dir.as_str(),
-                    )),
+                    CheetahString::from_string(
+                        self.inner
+                            .message_store_config
+                            .get_store_path_consume_queue(),
+                    ),
                     self.inner
                         .message_store_config
                         .get_mapped_file_size_consume_queue(),
@@ -349,9 +404,11 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
                 CQType::BatchCQ => ArcMut::new(Box::new(BatchConsumeQueue::new(
                     topic.clone(),
                     queue_id,
-                    CheetahString::from_string(get_store_path_batch_consume_queue(
-                        self.inner.message_store_config.store_path_root_dir.as_str(),
-                    )),
+                    CheetahString::from_string(
+                        self.inner
+                            .message_store_config
+                            .get_store_path_batch_consume_queue(),
+                    ),
                     self.inner
                         .message_store_config
                         .mapper_file_size_batch_consume_queue,
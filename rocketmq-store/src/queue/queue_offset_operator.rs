@@ -19,6 +19,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use cheetah_string::CheetahString;
+use rocketmq_common::common::mix_all::LMQ_PREFIX;
 use tracing::info;
 
 pub struct QueueOffsetOperator {
@@ -99,6 +100,10 @@ impl QueueOffsetOperator {
         *entry += message_num as i64;
     }
 
+    pub fn get_lmq_num(&self) -> usize {
+        self.lmq_topic_queue_table.lock().len()
+    }
+
     pub fn current_queue_offset(&self, topic_queue_key: &CheetahString) -> i64 {
         let topic_queue_table = self.topic_queue_table.lock();
         let table = topic_queue_table;
@@ -129,7 +134,7 @@ impl QueueOffsetOperator {
     pub fn set_lmq_topic_queue_table(&self, lmq_topic_queue_table: HashMap<CheetahString, i64>) {
         let mut table = HashMap::new();
         for (key, value) in lmq_topic_queue_table.iter() {
-            if key.contains("lmq") {
+            if key.contains(LMQ_PREFIX) {
                 table.insert(key.clone(), *value);
             }
         }
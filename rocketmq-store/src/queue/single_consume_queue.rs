@@ -41,6 +41,7 @@ use crate::config::message_store_config::MessageStoreConfig;
 use crate::consume_queue::consume_queue_ext::CqExtUnit;
 use crate::consume_queue::mapped_file_queue::MappedFileQueue;
 use crate::filter::MessageFilter;
+use crate::log_file::commit_log::CommitLog;
 use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
 use crate::log_file::mapped_file::MappedFile;
 use crate::queue::consume_queue_ext::ConsumeQueueExt;
@@ -49,7 +50,6 @@ use crate::queue::ConsumeQueueTrait;
 use crate::queue::CqUnit;
 use crate::queue::FileQueueLifeCycle;
 use crate::store::running_flags::RunningFlags;
-use crate::store_path_config_helper::get_store_path_consume_queue_ext;
 
 pub const CQ_STORE_UNIT_SIZE: i32 = 20;
 pub const MSG_TAG_OFFSET_INDEX: i32 = 12;
@@ -103,9 +103,7 @@ impl ConsumeQueue {
             Some(ConsumeQueueExt::new(
                 topic.clone(),
                 queue_id,
-                CheetahString::from_string(get_store_path_consume_queue_ext(
-                    message_store_config.store_path_root_dir.as_str(),
-                )),
+                CheetahString::from_string(message_store_config.get_store_path_consume_queue_ext()),
                 message_store_config.mapped_file_size_consume_queue_ext as i32,
                 message_store_config.bit_map_length_consume_queue_ext as i32,
             ))
@@ -207,7 +205,7 @@ impl ConsumeQueue {
         }
         if self.is_ext_read_enable() {
             self.consume_queue_ext
-                .as_ref()
+                .as_mut()
                 .unwrap()
                 .truncate_by_max_address(max_ext_addr);
         }
@@ -441,11 +439,15 @@ impl FileQueueLifeCycle for ConsumeQueue {
     }
 
     fn check_self(&self) {
-        todo!()
+        self.mapped_file_queue.check_self();
     }
 
     fn flush(&self, flush_least_pages: i32) -> bool {
-        todo!()
+        let mut result = self.mapped_file_queue.flush(flush_least_pages);
+        if let Some(consume_queue_ext) = self.consume_queue_ext.as_ref() {
+            result &= consume_queue_ext.flush(flush_least_pages);
+        }
+        result
     }
 
     fn destroy(&mut self) {
@@ -462,7 +464,12 @@ impl FileQueueLifeCycle for ConsumeQueue {
     }
 
     fn delete_expired_file(&self, min_commit_log_pos: i64) -> i32 {
-        todo!()
+        let count = self
+            .mapped_file_queue
+            .delete_expired_file_by_offset(min_commit_log_pos, CQ_STORE_UNIT_SIZE);
+        // also drops the ext files only referenced by the deleted units
+        self.correct_min_offset(min_commit_log_pos);
+        count
     }
 
     fn roll_next_file(&self, next_begin_offset: i64) -> i64 {
@@ -504,10 +511,7 @@ impl ConsumeQueueTrait for ConsumeQueue {
     }
 
     fn get(&self, index: i64) -> Option<CqUnit> {
-        match self.iterate_from(index) {
-            None => None,
-            Some(value) => None,
-        }
+        self.iterate_from(index)?.next()
     }
 
     fn get_cq_unit_and_store_time(&self, index: i64) -> Option<(CqUnit, i64)> {
@@ -542,22 +546,69 @@ impl ConsumeQueueTrait for ConsumeQueue {
         todo!()
     }
 
-    fn get_offset_in_queue_by_time(&self, timestamp: i64) -> i64 {
-        todo!()
+    fn get_offset_in_queue_by_time(&self, timestamp: i64, commit_log: &CommitLog) -> i64 {
+        self.get_offset_in_queue_by_time_boundary(timestamp, BoundaryType::Lower, commit_log)
     }
 
     fn get_offset_in_queue_by_time_boundary(
         &self,
         timestamp: i64,
         boundary_type: BoundaryType,
+        commit_log: &CommitLog,
     ) -> i64 {
-        todo!()
+        let min_offset = self.get_min_offset_in_queue();
+        let max_offset = self.get_max_offset_in_queue();
+        let min_phy_offset = commit_log.get_min_offset();
+        // Entries whose message has already been deleted from the commit log cannot be read, they
+        // are always older than the readable ones so the search just moves past them.
+        let store_time = |index: i64| -> Option<i64> {
+            let cq_unit = self.get(index)?;
+            if cq_unit.pos < min_phy_offset {
+                return None;
+            }
+            let store_time = commit_log.pickup_store_timestamp(cq_unit.pos, cq_unit.size);
+            (store_time >= 0).then_some(store_time)
+        };
+
+        // find the first offset stored after the boundary
+        let mut low = min_offset;
+        let mut high = max_offset;
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let after_boundary = match store_time(mid) {
+                None => false,
+                Some(store_time) => match boundary_type {
+                    BoundaryType::Lower => store_time >= timestamp,
+                    BoundaryType::Upper => store_time > timestamp,
+                },
+            };
+            if after_boundary {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        match boundary_type {
+            BoundaryType::Lower => low,
+            BoundaryType::Upper => {
+                if low > min_offset && store_time(low - 1).is_some() {
+                    low - 1
+                } else {
+                    // nothing readable is stored at or before the timestamp
+                    low.min((max_offset - 1).max(min_offset))
+                }
+            }
+        }
     }
 
     fn get_max_physic_offset(&self) -> i64 {
         self.max_physic_offset.load(Ordering::SeqCst)
     }
 
+    fn get_flushed_where(&self) -> i64 {
+        self.mapped_file_queue.get_flushed_where()
+    }
+
     fn get_min_logic_offset(&self) -> i64 {
         self.min_logic_offset.load(Ordering::Relaxed)
     }
@@ -575,7 +626,9 @@ impl ConsumeQueueTrait for ConsumeQueue {
     }
 
     fn correct_min_offset(&self, min_commit_log_offset: i64) {
-        if min_commit_log_offset >= self.mapped_file_queue.get_max_offset() {
+        // Check if the consume queue is the state of deprecation.
+        if self.min_logic_offset.load(Ordering::Acquire) >= self.mapped_file_queue.get_max_offset()
+        {
             info!(
                 "ConsumeQueue[Topic={}, queue-id={}] contains no valid entries",
                 self.topic, self.queue_id
@@ -601,7 +654,7 @@ impl ConsumeQueueTrait for ConsumeQueue {
                 .mapped_file
                 .as_ref()
                 .unwrap()
-                .get_bytes(last_record.start_offset as usize, last_record.size as usize)
+                .get_bytes(last_record.relative_pos(), last_record.size as usize)
                 .unwrap();
             let commit_log_offset = bytes.get_i64();
             if commit_log_offset < min_commit_log_offset {
@@ -659,7 +712,7 @@ impl ConsumeQueueTrait for ConsumeQueue {
             }
             let mapped = result.mapped_file.as_ref().unwrap();
             let commit_log_offset = mapped
-                .get_bytes(result.start_offset as usize, 8)
+                .get_bytes(result.relative_pos(), 8)
                 .unwrap()
                 .get_i64();
             if intact && commit_log_offset >= min_commit_log_offset {
@@ -722,7 +775,7 @@ impl ConsumeQueueTrait for ConsumeQueue {
         while i < max_retries && can_write {
             let mut tags_code = request.tags_code;
             if self.is_ext_write_enable() {
-                let ext_addr = self.consume_queue_ext.as_ref().unwrap().put(CqExtUnit::new(
+                let ext_addr = self.consume_queue_ext.as_mut().unwrap().put(CqExtUnit::new(
                     tags_code,
                     request.store_timestamp,
                     request.bit_map.clone(),
@@ -794,7 +847,49 @@ impl ConsumeQueueTrait for ConsumeQueue {
     }
 
     fn estimate_message_count(&self, from: i64, to: i64, filter: &dyn MessageFilter) -> i64 {
-        todo!()
+        let from = from.max(self.get_min_offset_in_queue());
+        let to = to.min(self.get_max_offset_in_queue());
+        if from >= to {
+            return 0;
+        }
+        let max_scan = self.message_store_config.max_consume_queue_scan as i64;
+        let sample_count_threshold = self.message_store_config.sample_count_threshold as i64;
+        let mut matched = 0;
+        let mut scanned = 0;
+        let mut sampled = false;
+        let mut index = from;
+        // each iteration walks what is left of the mapped file holding `index`
+        'scan: while index < to {
+            let Some(cq_units) = self.iterate_from(index) else {
+                break;
+            };
+            let scanned_before = scanned;
+            for cq_unit in cq_units {
+                if cq_unit.queue_offset >= to {
+                    break 'scan;
+                }
+                if filter.is_matched_by_consume_queue(
+                    Some(cq_unit.tags_code),
+                    cq_unit.cq_ext_unit.as_ref(),
+                ) {
+                    matched += 1;
+                }
+                scanned += 1;
+                index = cq_unit.queue_offset + 1;
+                if scanned >= max_scan || matched > sample_count_threshold {
+                    sampled = index < to;
+                    break 'scan;
+                }
+            }
+            if scanned == scanned_before {
+                break;
+            }
+        }
+        if !sampled {
+            return matched;
+        }
+        // extrapolate the match ratio of the scanned prefix to the whole range
+        matched * (to - from) / scanned
     }
 
     fn iterate_from(&self, start_index: i64) -> Option<Box<dyn Iterator<Item = CqUnit>>> {
@@ -826,11 +921,8 @@ struct ConsumeQueueIterator {
 }
 
 impl ConsumeQueueIterator {
-    fn get_ext(&self, offset: i64, cq_ext_unit: &CqExtUnit) -> bool {
-        match self.consume_queue_ext.as_ref() {
-            None => false,
-            Some(value) => value.get(offset, cq_ext_unit),
-        }
+    fn get_ext(&self, offset: i64) -> Option<CqExtUnit> {
+        self.consume_queue_ext.as_ref()?.get(offset)
     }
 }
 
@@ -844,17 +936,21 @@ impl Iterator for ConsumeQueueIterator {
                 if self.counter * CQ_STORE_UNIT_SIZE >= value.size {
                     return None;
                 }
-                let mmp = value.mapped_file.as_ref().unwrap().get_mapped_file();
-                let start =
-                    value.start_offset as usize + (self.counter * CQ_STORE_UNIT_SIZE) as usize;
+                let Ok(buffer) = value.get_buffer() else {
+                    return None;
+                };
+                let start = (self.counter * CQ_STORE_UNIT_SIZE) as usize;
+                let queue_offset = (value.start_offset as i64
+                    + (self.counter * CQ_STORE_UNIT_SIZE) as i64)
+                    / CQ_STORE_UNIT_SIZE as i64;
                 self.counter += 1;
                 let end = start + CQ_STORE_UNIT_SIZE as usize;
-                let mut bytes = Bytes::copy_from_slice(&mmp[start..end]);
+                let mut bytes = Bytes::copy_from_slice(&buffer[start..end]);
                 let pos = bytes.get_i64();
                 let size = bytes.get_i32();
                 let tags_code = bytes.get_i64();
                 let mut cq_unit = CqUnit {
-                    queue_offset: start as i64 / CQ_STORE_UNIT_SIZE as i64,
+                    queue_offset,
                     size,
                     pos,
                     tags_code,
@@ -862,16 +958,14 @@ impl Iterator for ConsumeQueueIterator {
                 };
 
                 if ConsumeQueueExt::is_ext_addr(cq_unit.tags_code) {
-                    let cq_ext_unit = CqExtUnit::default();
-                    let ext_ret = self.get_ext(cq_unit.tags_code, &cq_ext_unit);
-                    if ext_ret {
+                    if let Some(cq_ext_unit) = self.get_ext(cq_unit.tags_code) {
                         cq_unit.tags_code = cq_ext_unit.tags_code();
                         cq_unit.cq_ext_unit = Some(cq_ext_unit);
                     } else {
                         error!(
                             "[BUG] can't find consume queue extend file content! addr={}, \
                              offsetPy={}, sizePy={}",
-                            cq_unit.tags_code, cq_unit.pos, cq_unit.pos,
+                            cq_unit.tags_code, cq_unit.pos, cq_unit.size,
                         );
                     }
                 }
@@ -880,3 +974,62 @@ impl Iterator for ConsumeQueueIterator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ext_units_are_resolved_on_read_and_cleaned_with_the_queue() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().into_owned();
+        let message_store_config = Arc::new(MessageStoreConfig {
+            store_path_root_dir: CheetahString::from_string(root.clone()),
+            enable_consume_queue_ext: true,
+            // 3 ext units of 24 bytes per file
+            mapped_file_size_consume_queue_ext: 90,
+            ..MessageStoreConfig::default()
+        });
+        let mut consume_queue = ConsumeQueue::new(
+            CheetahString::from_static_str("ext_topic"),
+            0,
+            CheetahString::from_string(message_store_config.get_store_path_consume_queue()),
+            // 2 units per file
+            2 * CQ_STORE_UNIT_SIZE,
+            message_store_config.clone(),
+            Arc::new(RunningFlags::new()),
+            Arc::new(StoreCheckpoint::new(message_store_config.get_store_checkpoint()).unwrap()),
+        );
+        for n in 0..6i64 {
+            consume_queue.put_message_position_info_wrapper(&DispatchRequest {
+                topic: CheetahString::from_static_str("ext_topic"),
+                commit_log_offset: n * 100,
+                msg_size: 100,
+                tags_code: i32::MAX as i64 + n,
+                store_timestamp: 1_000 + n,
+                consume_queue_offset: n,
+                bit_map: Some(vec![n as u8; 4]),
+                ..DispatchRequest::default()
+            });
+        }
+
+        let units: Vec<CqUnit> = consume_queue.iterate_from(0).unwrap().collect();
+        assert_eq!(units.len(), 2);
+        let unit = consume_queue.get(3).unwrap();
+        assert_eq!(unit.tags_code, i32::MAX as i64 + 3);
+        let ext_unit = unit.cq_ext_unit.unwrap();
+        assert_eq!(ext_unit.msg_store_time(), 1_003);
+        assert_eq!(ext_unit.filter_bit_map(), &Some(vec![3u8; 4]));
+
+        let ext_dir = PathBuf::from(message_store_config.get_store_path_consume_queue_ext())
+            .join("ext_topic")
+            .join("0");
+        assert_eq!(std::fs::read_dir(&ext_dir).unwrap().count(), 2);
+        assert_eq!(consume_queue.delete_expired_file(450), 2);
+        assert_eq!(consume_queue.get_min_offset_in_queue(), 5);
+        assert_eq!(std::fs::read_dir(&ext_dir).unwrap().count(), 1);
+        let unit = consume_queue.get(5).unwrap();
+        assert_eq!(unit.tags_code, i32::MAX as i64 + 5);
+        assert_eq!(unit.cq_ext_unit.unwrap().msg_store_time(), 1_005);
+    }
+}
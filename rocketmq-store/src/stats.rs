@@ -17,4 +17,5 @@
 
 pub mod broker_stats;
 pub mod broker_stats_manager;
+pub mod put_message_distribution;
 pub mod stats_type;
@@ -30,6 +30,9 @@ use rocketmq_common::common::statistics::statistics_manager::StatisticsManager;
 use rocketmq_common::common::stats::moment_stats_item_set::MomentStatsItemSet;
 use rocketmq_common::common::stats::stats_item_set::StatsItemSet;
 use rocketmq_common::common::stats::Stats;
+use tracing::info;
+
+use crate::stats::put_message_distribution::PutMessageDistribution;
 
 pub struct BrokerStatsManager {
     stats_table: Arc<parking_lot::RwLock<HashMap<String, StatsItemSet>>>,
@@ -41,6 +44,7 @@ pub struct BrokerStatsManager {
     producer_state_getter: Option<Arc<dyn StateGetter>>,
     consumer_state_getter: Option<Arc<dyn StateGetter>>,
     broker_config: Option<Arc<BrokerConfig>>,
+    put_message_distribution: PutMessageDistribution,
 }
 
 impl BrokerStatsManager {
@@ -114,6 +118,7 @@ impl BrokerStatsManager {
             producer_state_getter: None,
             consumer_state_getter: None,
             broker_config: Some(broker_config),
+            put_message_distribution: PutMessageDistribution::new(),
         };
         broker_stats_manager.init();
         broker_stats_manager
@@ -139,6 +144,7 @@ impl BrokerStatsManager {
             producer_state_getter: None,
             consumer_state_getter: None,
             broker_config: Some(broker_config),
+            put_message_distribution: PutMessageDistribution::new(),
         };
         broker_stats_manager.init();
         broker_stats_manager
@@ -481,6 +487,8 @@ impl BrokerStatsManager {
 
     pub fn inc_topic_put_size(&self, topic: &str, size: i32) {}
 
+    pub fn inc_send_back_nums(&self, group: &str, topic: &str) {}
+
     pub fn inc_group_get_nums(&self, group: &str, topic: &str, inc_value: i32) {}
     pub fn inc_group_get_size(&self, group: &str, topic: &str, inc_value: i32) {}
 
@@ -496,6 +504,26 @@ impl BrokerStatsManager {
     pub fn inc_queue_put_size(&self, topic: &str, queue_id: i32, size: i32) {}
     pub fn inc_topic_put_latency(&self, topic: &str, queue_id: i32, inc_value: i32) {}
 
+    /// Records the born-to-store latency of `msg_num` messages put in one request.
+    pub fn record_put_message_latency(
+        &self,
+        born_timestamp: i64,
+        store_timestamp: i64,
+        msg_num: i32,
+    ) {
+        self.put_message_distribution
+            .record(born_timestamp, store_timestamp, msg_num);
+    }
+
+    /// Closes the current put latency interval and logs its distribution.
+    pub fn print_put_message_distribution(&self) {
+        info!("{}", self.put_message_distribution.roll().log_line());
+    }
+
+    pub fn put_message_distribution(&self) -> &PutMessageDistribution {
+        &self.put_message_distribution
+    }
+
     pub fn tps_group_get_nums(&self, group: &str, topic: &str) -> f64 {
         let stats_key = build_stats_key(Some(topic), Some(group));
         match self.stats_table.read().get(Stats::GROUP_GET_NUMS) {
@@ -0,0 +1,196 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+/// Inclusive upper bounds of the latency buckets in milliseconds, anything slower than the last
+/// bound lands in the final `[10s~]` bucket.
+const BUCKET_UPPER_BOUNDS_MILLIS: [i64; 12] =
+    [0, 1, 5, 10, 50, 100, 200, 500, 1000, 2000, 5000, 10000];
+
+pub const BUCKET_COUNT: usize = BUCKET_UPPER_BOUNDS_MILLIS.len() + 1;
+
+const BUCKET_DESC: [&str; BUCKET_COUNT] = [
+    "[<=0ms]",
+    "[0~1ms]",
+    "[1~5ms]",
+    "[5~10ms]",
+    "[10~50ms]",
+    "[50~100ms]",
+    "[100~200ms]",
+    "[200~500ms]",
+    "[500ms~1s]",
+    "[1~2s]",
+    "[2~5s]",
+    "[5~10s]",
+    "[10s~]",
+];
+
+/// Born-to-store latency of the messages put on this broker, counted per interval.
+///
+/// The latency is the store timestamp minus the born timestamp set by the producer. Producer and
+/// broker clocks are not synchronized, so a latency can come out negative: it is clamped into
+/// `[<=0ms]` and also counted as clock skew.
+#[derive(Default)]
+pub struct PutMessageDistribution {
+    buckets: [AtomicU64; BUCKET_COUNT],
+    max_latency_millis: AtomicI64,
+    clock_skew: AtomicU64,
+    last_interval: parking_lot::Mutex<PutMessageDistributionSnapshot>,
+}
+
+/// Counts of one closed interval of a [`PutMessageDistribution`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PutMessageDistributionSnapshot {
+    pub buckets: [u64; BUCKET_COUNT],
+    pub max_latency_millis: i64,
+    pub clock_skew: u64,
+}
+
+impl PutMessageDistribution {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records `msg_num` messages born at `born_timestamp` and stored at `store_timestamp`.
+    pub fn record(&self, born_timestamp: i64, store_timestamp: i64, msg_num: i32) {
+        if msg_num <= 0 {
+            return;
+        }
+        let msg_num = msg_num as u64;
+        let latency = store_timestamp - born_timestamp;
+        if latency < 0 {
+            self.clock_skew.fetch_add(msg_num, Ordering::Relaxed);
+        }
+        let latency = latency.max(0);
+        let index = BUCKET_UPPER_BOUNDS_MILLIS
+            .iter()
+            .position(|bound| latency <= *bound)
+            .unwrap_or(BUCKET_COUNT - 1);
+        self.buckets[index].fetch_add(msg_num, Ordering::Relaxed);
+        self.max_latency_millis
+            .fetch_max(latency, Ordering::Relaxed);
+    }
+
+    /// Closes the current interval and returns its counts. They stay available through
+    /// [`PutMessageDistribution::last_interval`] until the next roll.
+    pub fn roll(&self) -> PutMessageDistributionSnapshot {
+        let snapshot = PutMessageDistributionSnapshot {
+            buckets: std::array::from_fn(|i| self.buckets[i].swap(0, Ordering::Relaxed)),
+            max_latency_millis: self.max_latency_millis.swap(0, Ordering::Relaxed),
+            clock_skew: self.clock_skew.swap(0, Ordering::Relaxed),
+        };
+        *self.last_interval.lock() = snapshot.clone();
+        snapshot
+    }
+
+    pub fn last_interval(&self) -> PutMessageDistributionSnapshot {
+        self.last_interval.lock().clone()
+    }
+}
+
+impl PutMessageDistributionSnapshot {
+    pub fn total(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// Bucket counts in the `putMessageDistributeTime` runtime info format, e.g.
+    /// `[<=0ms]:0 [0~1ms]:12 [1~5ms]:3 ...`.
+    pub fn distribute_time_string(&self) -> String {
+        BUCKET_DESC
+            .iter()
+            .zip(self.buckets.iter())
+            .map(|(desc, count)| format!("{desc}:{count}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn log_line(&self) -> String {
+        format!(
+            "[PUTLATENCY] TotalPut {}, MaxLatency {}ms, ClockSkew {}, PutMessageDistributeTime {}",
+            self.total(),
+            self.max_latency_millis,
+            self.clock_skew,
+            self.distribute_time_string()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latencies_fall_into_their_buckets() {
+        let distribution = PutMessageDistribution::new();
+        let born = 1_000_000;
+        for (latency, msg_num) in [(0, 1), (1, 2), (3, 1), (10, 1), (700, 1), (10_001, 4)] {
+            distribution.record(born, born + latency, msg_num);
+        }
+
+        let snapshot = distribution.roll();
+        assert_eq!(snapshot.buckets, [1, 2, 1, 1, 0, 0, 0, 0, 1, 0, 0, 0, 4]);
+        assert_eq!(snapshot.total(), 10);
+        assert_eq!(snapshot.max_latency_millis, 10_001);
+        assert_eq!(snapshot.clock_skew, 0);
+    }
+
+    #[test]
+    fn negative_latency_is_clamped_and_counted_as_clock_skew() {
+        let distribution = PutMessageDistribution::new();
+        distribution.record(2_000, 1_500, 3);
+        distribution.record(2_000, 2_002, 1);
+
+        let snapshot = distribution.roll();
+        assert_eq!(snapshot.buckets[0], 3);
+        assert_eq!(snapshot.buckets[2], 1);
+        assert_eq!(snapshot.clock_skew, 3);
+        assert_eq!(snapshot.max_latency_millis, 2);
+    }
+
+    #[test]
+    fn roll_resets_the_interval_and_keeps_the_last_one() {
+        let distribution = PutMessageDistribution::new();
+        distribution.record(0, 80, 1);
+
+        let first = distribution.roll();
+        assert_eq!(distribution.last_interval(), first);
+        assert_eq!(first.max_latency_millis, 80);
+
+        distribution.record(0, 4, 1);
+        let second = distribution.roll();
+        assert_eq!(second.total(), 1);
+        assert_eq!(second.max_latency_millis, 4);
+        assert_eq!(distribution.last_interval(), second);
+    }
+
+    #[test]
+    fn log_line_lists_every_bucket() {
+        let distribution = PutMessageDistribution::new();
+        distribution.record(100, 99, 1);
+        distribution.record(100, 150, 2);
+
+        assert_eq!(
+            distribution.roll().log_line(),
+            "[PUTLATENCY] TotalPut 3, MaxLatency 50ms, ClockSkew 1, PutMessageDistributeTime \
+             [<=0ms]:1 [0~1ms]:0 [1~5ms]:0 [5~10ms]:0 [10~50ms]:2 [50~100ms]:0 [100~200ms]:0 \
+             [200~500ms]:0 [500ms~1s]:0 [1~2s]:0 [2~5s]:0 [5~10s]:0 [10s~]:0"
+        );
+    }
+}
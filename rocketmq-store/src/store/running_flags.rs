@@ -131,30 +131,32 @@ impl RunningFlags {
         flags & WRITE_INDEX_FILE_ERROR_BIT != 0
     }
 
+    /// Marks the disk full, returning whether it was ok before.
     pub fn get_and_make_disk_full(&self) -> bool {
-        let result = (self.flag_bits.fetch_and(!(DISK_FULL_BIT), Ordering::AcqRel)) == 0;
-        self.flag_bits.fetch_or(DISK_FULL_BIT, Ordering::AcqRel);
-        result
+        self.flag_bits.fetch_or(DISK_FULL_BIT, Ordering::AcqRel) & DISK_FULL_BIT == 0
+    }
+
+    pub fn is_disk_full(&self) -> bool {
+        let flags = self.flag_bits.load(Ordering::Acquire);
+        flags & DISK_FULL_BIT != 0
     }
 
+    /// Marks the disk ok, returning whether it was ok before.
     pub fn get_and_make_disk_ok(&self) -> bool {
-        (self.flag_bits.fetch_and(!(DISK_FULL_BIT), Ordering::AcqRel)) == 0
+        self.flag_bits.fetch_and(!DISK_FULL_BIT, Ordering::AcqRel) & DISK_FULL_BIT == 0
     }
 
     pub fn get_and_make_logic_disk_full(&self) -> bool {
-        let result = (self
-            .flag_bits
-            .fetch_and(!(LOGIC_DISK_FULL_BIT), Ordering::AcqRel))
-            == 0;
         self.flag_bits
-            .fetch_or(LOGIC_DISK_FULL_BIT, Ordering::AcqRel);
-        result
+            .fetch_or(LOGIC_DISK_FULL_BIT, Ordering::AcqRel)
+            & LOGIC_DISK_FULL_BIT
+            == 0
     }
 
     pub fn get_and_make_logic_disk_ok(&self) -> bool {
-        (self
-            .flag_bits
-            .fetch_and(!(LOGIC_DISK_FULL_BIT), Ordering::AcqRel))
+        self.flag_bits
+            .fetch_and(!LOGIC_DISK_FULL_BIT, Ordering::AcqRel)
+            & LOGIC_DISK_FULL_BIT
             == 0
     }
 }
@@ -308,6 +310,19 @@ mod tests {
         assert_eq!(running_flags.get_and_make_disk_ok(), true);
     }
 
+    #[test]
+    fn disk_full_blocks_writes_until_disk_ok() {
+        let running_flags = RunningFlags::new();
+        running_flags.make_index_file_error();
+        assert!(running_flags.get_and_make_disk_full());
+        assert!(!running_flags.get_and_make_disk_full());
+        assert!(running_flags.is_disk_full());
+        assert!(!running_flags.get_and_make_disk_ok());
+        assert!(!running_flags.is_disk_full());
+        assert!(running_flags.get_and_make_disk_ok());
+        assert!(running_flags.is_index_file_error());
+    }
+
     #[test]
     fn test_get_and_make_logic_disk_full() {
         let running_flags = RunningFlags::new();
@@ -74,6 +74,35 @@ pub fn get_delay_offset_store_path(root_dir: &str) -> String {
         .into_owned()
 }
 
+pub fn get_timer_wheel_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("timerwheel")
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn get_timer_log_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("timerlog")
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn get_timer_check_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("config")
+        .join("timercheck")
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn get_epoch_file(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("epochFileCheckpoint")
+        .to_string_lossy()
+        .into_owned()
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -140,5 +169,27 @@ mod tests {
                 .to_string_lossy()
                 .into_owned()
         );
+        assert_eq!(
+            get_timer_wheel_path(root_dir),
+            PathBuf::from(root_dir)
+                .join("timerwheel")
+                .to_string_lossy()
+                .into_owned()
+        );
+        assert_eq!(
+            get_timer_log_path(root_dir),
+            PathBuf::from(root_dir)
+                .join("timerlog")
+                .to_string_lossy()
+                .into_owned()
+        );
+        assert_eq!(
+            get_timer_check_path(root_dir),
+            PathBuf::from(root_dir)
+                .join("config")
+                .join("timercheck")
+                .to_string_lossy()
+                .into_owned()
+        );
     }
 }
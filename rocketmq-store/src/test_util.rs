@@ -0,0 +1,80 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Store fixtures shared by the tests of this crate and, through the `test-util` feature, by
+//! crates testing on top of the store.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_rust::ArcMut;
+
+use crate::config::flush_disk_type::FlushDiskType;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::log_file::MessageStore;
+use crate::message_store::default_message_store::DefaultMessageStore;
+
+/// Loads a store rooted at `dir` with a 1MB commit log flushed asynchronously, taking the rest
+/// of its config from `message_store_config`. The store is not started.
+pub async fn load_store(
+    dir: impl AsRef<Path>,
+    message_store_config: MessageStoreConfig,
+) -> ArcMut<DefaultMessageStore> {
+    let mut store = ArcMut::new(DefaultMessageStore::new(
+        Arc::new(MessageStoreConfig {
+            store_path_root_dir: CheetahString::from_string(
+                dir.as_ref().to_string_lossy().into_owned(),
+            ),
+            mapped_file_size_commit_log: 1024 * 1024,
+            flush_disk_type: FlushDiskType::AsyncFlush,
+            ..message_store_config
+        }),
+        Arc::new(BrokerConfig::default()),
+        Arc::new(parking_lot::Mutex::new(HashMap::new())),
+        None,
+        false,
+    ));
+    let store_clone = store.clone();
+    store.set_message_store_arc(Some(store_clone));
+    assert!(store.load().await);
+    store
+}
+
+/// Same as [`load_store`], then starts the store.
+pub async fn start_store(
+    dir: impl AsRef<Path>,
+    message_store_config: MessageStoreConfig,
+) -> ArcMut<DefaultMessageStore> {
+    let mut store = load_store(dir, message_store_config).await;
+    store.start().unwrap();
+    store
+}
+
+/// Waits for the commit log to be dispatched up to its tail, panicking after five seconds.
+pub async fn wait_dispatched(store: &DefaultMessageStore) {
+    for _ in 0..500 {
+        if store.dispatch_behind_bytes() == 0 {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("commit log not dispatched");
+}
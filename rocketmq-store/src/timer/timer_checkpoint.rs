@@ -0,0 +1,135 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+
+use memmap2::MmapMut;
+use rocketmq_common::UtilAll::ensure_dir_ok;
+use tracing::info;
+
+use crate::log_file::mapped_file::default_mapped_file_impl::OS_PAGE_SIZE;
+
+const CHECKPOINT_SIZE: usize = 32;
+
+/// Progress of the timer wheel persisted in `config/timercheck`.
+///
+/// The four values are always written together, so a restart resumes from a
+/// consistent view of the timer log, the wheel and the timer topic queue.
+pub struct TimerCheckpoint {
+    _file: File,
+    mmap: parking_lot::Mutex<MmapMut>,
+    last_read_time_ms: AtomicI64,
+    last_timer_log_flush_pos: AtomicI64,
+    last_timer_queue_offset: AtomicI64,
+    master_timer_queue_offset: AtomicI64,
+}
+
+impl TimerCheckpoint {
+    pub fn new<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        ensure_dir_ok(path.as_ref().parent().unwrap().to_str().unwrap());
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path.as_ref())?;
+        let exists = file.metadata()?.len() > 0;
+        file.set_len(OS_PAGE_SIZE)?;
+        let mmap = unsafe { MmapMut::map_mut(&file)? };
+        let read = |index: usize| i64::from_be_bytes(mmap[index..index + 8].try_into().unwrap());
+        let (last_read_time_ms, last_timer_log_flush_pos, last_timer_queue_offset, master_offset) =
+            if exists {
+                (read(0), read(8), read(16), read(24))
+            } else {
+                (0, 0, 0, 0)
+            };
+        if exists {
+            info!("timer checkpoint file exists, {}", path.as_ref().display());
+            info!("lastReadTimeMs: {}", last_read_time_ms);
+            info!("lastTimerLogFlushPos: {}", last_timer_log_flush_pos);
+            info!("lastTimerQueueOffset: {}", last_timer_queue_offset);
+            info!("masterTimerQueueOffset: {}", master_offset);
+        }
+        Ok(Self {
+            _file: file,
+            mmap: parking_lot::Mutex::new(mmap),
+            last_read_time_ms: AtomicI64::new(last_read_time_ms),
+            last_timer_log_flush_pos: AtomicI64::new(last_timer_log_flush_pos),
+            last_timer_queue_offset: AtomicI64::new(last_timer_queue_offset),
+            master_timer_queue_offset: AtomicI64::new(master_offset),
+        })
+    }
+
+    pub fn flush(&self) -> std::io::Result<()> {
+        let mut mmap = self.mmap.lock();
+        let mut buffer = &mut mmap[..CHECKPOINT_SIZE];
+        for value in [
+            &self.last_read_time_ms,
+            &self.last_timer_log_flush_pos,
+            &self.last_timer_queue_offset,
+            &self.master_timer_queue_offset,
+        ] {
+            buffer.write_all(&value.load(Ordering::Relaxed).to_be_bytes())?;
+        }
+        mmap.flush()
+    }
+
+    pub fn shutdown(&self) -> std::io::Result<()> {
+        self.flush()
+    }
+
+    pub fn set_last_read_time_ms(&self, last_read_time_ms: i64) {
+        self.last_read_time_ms
+            .store(last_read_time_ms, Ordering::Relaxed);
+    }
+
+    pub fn set_last_timer_log_flush_pos(&self, last_timer_log_flush_pos: i64) {
+        self.last_timer_log_flush_pos
+            .store(last_timer_log_flush_pos, Ordering::Relaxed);
+    }
+
+    pub fn set_last_timer_queue_offset(&self, last_timer_queue_offset: i64) {
+        self.last_timer_queue_offset
+            .store(last_timer_queue_offset, Ordering::Relaxed);
+    }
+
+    pub fn set_master_timer_queue_offset(&self, master_timer_queue_offset: i64) {
+        self.master_timer_queue_offset
+            .store(master_timer_queue_offset, Ordering::Relaxed);
+    }
+
+    pub fn last_read_time_ms(&self) -> i64 {
+        self.last_read_time_ms.load(Ordering::Relaxed)
+    }
+
+    pub fn last_timer_log_flush_pos(&self) -> i64 {
+        self.last_timer_log_flush_pos.load(Ordering::Relaxed)
+    }
+
+    pub fn last_timer_queue_offset(&self) -> i64 {
+        self.last_timer_queue_offset.load(Ordering::Relaxed)
+    }
+
+    pub fn master_timer_queue_offset(&self) -> i64 {
+        self.master_timer_queue_offset.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,157 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use bytes::Buf;
+use bytes::BufMut;
+use bytes::Bytes;
+use bytes::BytesMut;
+use tracing::warn;
+
+use crate::consume_queue::mapped_file_queue::MappedFileQueue;
+use crate::log_file::commit_log::BLANK_MAGIC_CODE;
+use crate::log_file::mapped_file::MappedFile;
+
+/// Size in bytes of one timer log record.
+pub const UNIT_SIZE: usize = 4 // size
+    + 8 // prev pos
+    + 4 // magic value
+    + 8 // curr write time, for trace
+    + 4 // delayed time, for check
+    + 8 // offset py
+    + 4 // size py
+    + 4 // hash code of real topic
+    + 8; // reserved value, just in case of
+
+/// Size of the header written at the end of a file that cannot hold a record.
+const MIN_BLANK_LEN: usize = 4 + 8 + 4;
+
+/// One record of the timer log, pointing at a message in the commit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerLogUnit {
+    pub prev_pos: i64,
+    pub magic: i32,
+    pub curr_write_time_ms: i64,
+    pub delayed_time: i32,
+    pub offset_py: i64,
+    pub size_py: i32,
+    pub topic_hash: i32,
+}
+
+impl TimerLogUnit {
+    pub fn encode(&self) -> Bytes {
+        let mut buffer = BytesMut::with_capacity(UNIT_SIZE);
+        buffer.put_i32(UNIT_SIZE as i32);
+        buffer.put_i64(self.prev_pos);
+        buffer.put_i32(self.magic);
+        buffer.put_i64(self.curr_write_time_ms);
+        buffer.put_i32(self.delayed_time);
+        buffer.put_i64(self.offset_py);
+        buffer.put_i32(self.size_py);
+        buffer.put_i32(self.topic_hash);
+        buffer.put_i64(0);
+        buffer.freeze()
+    }
+
+    pub fn decode(mut buffer: Bytes) -> Option<Self> {
+        if buffer.len() < UNIT_SIZE || buffer.get_i32() != UNIT_SIZE as i32 {
+            return None;
+        }
+        Some(Self {
+            prev_pos: buffer.get_i64(),
+            magic: buffer.get_i32(),
+            curr_write_time_ms: buffer.get_i64(),
+            delayed_time: buffer.get_i32(),
+            offset_py: buffer.get_i64(),
+            size_py: buffer.get_i32(),
+            topic_hash: buffer.get_i32(),
+        })
+    }
+}
+
+/// The append-only `timerlog`, a queue of mapped files holding [`TimerLogUnit`]s.
+pub struct TimerLog {
+    mapped_file_queue: parking_lot::Mutex<MappedFileQueue>,
+    file_size: u64,
+}
+
+impl TimerLog {
+    pub fn new(store_path: String, file_size: usize) -> Self {
+        Self {
+            mapped_file_queue: parking_lot::Mutex::new(MappedFileQueue::new(
+                store_path,
+                file_size as u64,
+                None,
+            )),
+            file_size: file_size as u64,
+        }
+    }
+
+    pub fn load(&self) -> bool {
+        self.mapped_file_queue.lock().load()
+    }
+
+    /// Appends `unit` and returns its position, or `None` when no file could
+    /// be created.
+    pub fn append(&self, unit: &TimerLogUnit) -> Option<i64> {
+        let mut mapped_file_queue = self.mapped_file_queue.lock();
+        let mut mapped_file = mapped_file_queue.get_last_mapped_file_mut_start_offset(0, true)?;
+        let remaining = self.file_size as usize - mapped_file.get_wrote_position() as usize;
+        if remaining < UNIT_SIZE + MIN_BLANK_LEN {
+            let mut blank = BytesMut::with_capacity(MIN_BLANK_LEN);
+            blank.put_i32(remaining as i32);
+            blank.put_i64(0);
+            blank.put_i32(BLANK_MAGIC_CODE);
+            if remaining >= MIN_BLANK_LEN {
+                mapped_file.append_message_bytes(&blank.freeze());
+            }
+            mapped_file.set_wrote_position(self.file_size as i32);
+            mapped_file = mapped_file_queue.get_last_mapped_file_mut_start_offset(0, true)?;
+        }
+        let pos =
+            mapped_file.get_file_from_offset() as i64 + mapped_file.get_wrote_position() as i64;
+        if !mapped_file.append_message_bytes(&unit.encode()) {
+            warn!("append timer log unit failed, pos: {}", pos);
+            return None;
+        }
+        Some(pos)
+    }
+
+    pub fn get_unit(&self, pos: i64) -> Option<TimerLogUnit> {
+        let mapped_file = self
+            .mapped_file_queue
+            .lock()
+            .find_mapped_file_by_offset(pos, false)?;
+        let relative_pos = (pos % self.file_size as i64) as usize;
+        TimerLogUnit::decode(mapped_file.get_bytes(relative_pos, UNIT_SIZE)?)
+    }
+
+    pub fn flush(&self) -> bool {
+        self.mapped_file_queue.lock().flush(0)
+    }
+
+    pub fn get_max_offset(&self) -> i64 {
+        self.mapped_file_queue.lock().get_max_offset()
+    }
+
+    /// Drops everything written at or beyond `offset`.
+    pub fn truncate(&self, offset: i64) {
+        let mut mapped_file_queue = self.mapped_file_queue.lock();
+        mapped_file_queue.truncate_dirty_files(offset);
+        mapped_file_queue.set_flushed_where(offset);
+        mapped_file_queue.set_committed_where(offset);
+    }
+}
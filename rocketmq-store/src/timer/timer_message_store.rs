@@ -14,15 +14,38 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::collections::HashSet;
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
 
 use cheetah_string::CheetahString;
+use rocketmq_common::common::hasher::string_hasher::JavaStringHasher;
+use rocketmq_common::common::message::message_client_id_setter::MessageClientIDSetter;
+use rocketmq_common::common::message::message_decoder::message_properties_to_string;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
 use rocketmq_common::common::message::MessageConst;
-use rocketmq_common::common::system_clock::SystemClock;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
+use rocketmq_common::common::TopicFilterType;
+use rocketmq_common::MessageAccessor::MessageAccessor;
+use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_rust::ArcMut;
+use tokio::sync::Notify;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
 
+use crate::base::message_status_enum::PutMessageStatus;
 use crate::log_file::MessageStore;
 use crate::message_store::default_message_store::DefaultMessageStore;
+use crate::timer::timer_checkpoint::TimerCheckpoint;
+use crate::timer::timer_log::TimerLog;
+use crate::timer::timer_log::TimerLogUnit;
+use crate::timer::timer_wheel::TimerWheel;
 
 pub const TIMER_TOPIC: &str = concat!("rmq_sys_", "wheel_timer");
 pub const TIMER_OUT_MS: &str = MessageConst::PROPERTY_TIMER_OUT_MS;
@@ -45,34 +68,494 @@ pub const MAGIC_DEFAULT: i32 = 1;
 pub const MAGIC_ROLL: i32 = 1 << 1;
 pub const MAGIC_DELETE: i32 = 1 << 2;
 
+/// Source of the current time for the timer wheel, replaceable in tests.
+pub trait TimerClock: Send + Sync {
+    fn now(&self) -> i64;
+}
+
+pub struct SystemTimerClock;
+
+impl TimerClock for SystemTimerClock {
+    fn now(&self) -> i64 {
+        get_current_millis() as i64
+    }
+}
+
+struct TimerFiles {
+    timer_wheel: TimerWheel,
+    timer_log: TimerLog,
+    timer_checkpoint: TimerCheckpoint,
+}
+
 pub struct TimerMessageStore {
     pub curr_read_time_ms: AtomicI64,
     pub curr_queue_offset: AtomicI64,
     pub default_message_store: Option<ArcMut<DefaultMessageStore>>,
+    files: Option<TimerFiles>,
+    clock: Arc<dyn TimerClock>,
+    precision_ms: i64,
+    timer_roll_window_slots: i64,
+    timer_flush_interval_ms: u64,
+    // held while the timer log, the wheel and the queue offset move together
+    enqueue_lock: parking_lot::Mutex<()>,
+    stopped: AtomicBool,
+    shutdown_notify: Notify,
 }
 
-impl Clone for TimerMessageStore {
-    fn clone(&self) -> Self {
-        Self {
-            curr_read_time_ms: AtomicI64::new(
-                self.curr_read_time_ms
-                    .load(std::sync::atomic::Ordering::Relaxed),
-            ),
-            curr_queue_offset: AtomicI64::new(
-                self.curr_queue_offset
-                    .load(std::sync::atomic::Ordering::Relaxed),
-            ),
-            default_message_store: self.default_message_store.clone(),
+impl TimerMessageStore {
+    pub fn load(&self) -> bool {
+        let Some(files) = self.files.as_ref() else {
+            return false;
+        };
+        let result = files.timer_log.load();
+        let flush_pos = files.timer_checkpoint.last_timer_log_flush_pos();
+        if files.timer_log.get_max_offset() > flush_pos {
+            let revised = files.timer_wheel.revise_slots(flush_pos, |pos| {
+                files.timer_log.get_unit(pos).map(|unit| unit.prev_pos)
+            });
+            if revised > 0 {
+                warn!(
+                    "timer wheel revised {} slots written after the checkpoint, flush pos {}",
+                    revised, flush_pos
+                );
+            }
+            files.timer_log.truncate(flush_pos);
         }
+        self.curr_queue_offset.store(
+            files.timer_checkpoint.last_timer_queue_offset(),
+            Ordering::Release,
+        );
+        let mut curr_read_time_ms = files.timer_checkpoint.last_read_time_ms();
+        if curr_read_time_ms <= 0 {
+            curr_read_time_ms = self.format_time_ms(self.clock.now());
+        }
+        self.curr_read_time_ms
+            .store(curr_read_time_ms, Ordering::Release);
+        info!(
+            "timer message store loaded, currReadTimeMs: {}, currQueueOffset: {}, \
+             timerLogMaxOffset: {}",
+            curr_read_time_ms,
+            self.curr_queue_offset.load(Ordering::Relaxed),
+            files.timer_log.get_max_offset()
+        );
+        result
     }
-}
 
-impl TimerMessageStore {
-    pub fn load(&mut self) -> bool {
-        true
+    pub fn start(self: &Arc<Self>) {
+        if self.files.is_none() || self.default_message_store.is_none() {
+            return;
+        }
+        let this = self.clone();
+        tokio::spawn(async move {
+            let flush_interval = Duration::from_millis(this.timer_flush_interval_ms);
+            let mut last_flush = tokio::time::Instant::now();
+            while !this.is_stopped() {
+                let enqueued = this.enqueue().await;
+                let mut dequeued = 0;
+                while dequeued < DEFAULT_CAPACITY && !this.is_stopped() {
+                    if this.dequeue().await < 0 {
+                        break;
+                    }
+                    dequeued += 1;
+                }
+                if last_flush.elapsed() >= flush_interval {
+                    this.flush();
+                    last_flush = tokio::time::Instant::now();
+                }
+                if enqueued == 0 && dequeued == 0 {
+                    tokio::select! {
+                        _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+                        _ = this.shutdown_notify.notified() => {}
+                    }
+                }
+            }
+            info!("timer message store service stopped");
+        });
+    }
+
+    pub fn shutdown(&self) {
+        if self.stopped.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        self.shutdown_notify.notify_waiters();
+        self.flush();
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Acquire)
+    }
+
+    /// Moves the messages written to the timer topic since the last call into
+    /// the timer log and the wheel, and returns how many were consumed.
+    pub async fn enqueue(&self) -> usize {
+        let (Some(store), Some(files)) = (self.default_message_store.as_ref(), self.files.as_ref())
+        else {
+            return 0;
+        };
+        if store.get_message_store_config().timer_stop_enqueue {
+            return 0;
+        }
+        let Some(consume_queue) =
+            store.find_consume_queue(&CheetahString::from_static_str(TIMER_TOPIC), 0)
+        else {
+            return 0;
+        };
+        let mut offset = self.curr_queue_offset.load(Ordering::Acquire);
+        let min_offset = consume_queue.get_min_offset_in_queue();
+        if offset < min_offset {
+            warn!(
+                "timer queue offset {} is behind the min offset {}, skip to it",
+                offset, min_offset
+            );
+            offset = min_offset;
+            self.curr_queue_offset.store(offset, Ordering::Release);
+        }
+        let units = match consume_queue.iterate_from(offset) {
+            Some(iter) => iter.collect::<Vec<_>>(),
+            None => return 0,
+        };
+        let mut count = 0;
+        for cq_unit in units {
+            let next_offset = cq_unit.queue_offset + 1;
+            match store.look_message_by_offset_with_size(cq_unit.pos, cq_unit.size) {
+                Some(msg) => {
+                    match msg
+                        .get_property(&CheetahString::from_static_str(TIMER_OUT_MS))
+                        .and_then(|value| value.parse::<i64>().ok())
+                    {
+                        Some(delayed_time)
+                            if delayed_time < self.curr_read_time_ms.load(Ordering::Acquire) =>
+                        {
+                            // the slot has already been passed, deliver right away
+                            if !self.put_back(&msg, false).await {
+                                break;
+                            }
+                        }
+                        Some(delayed_time) => {
+                            let _guard = self.enqueue_lock.lock();
+                            if !self.do_enqueue(
+                                files,
+                                cq_unit.pos,
+                                cq_unit.size,
+                                delayed_time,
+                                &msg,
+                            ) {
+                                break;
+                            }
+                            self.curr_queue_offset.store(next_offset, Ordering::Release);
+                            count += 1;
+                            continue;
+                        }
+                        None => warn!(
+                            "timer message without {} at offset {}, skip it",
+                            TIMER_OUT_MS, cq_unit.pos
+                        ),
+                    }
+                }
+                None => warn!(
+                    "timer message lost, queue offset {}, commit log offset {}",
+                    cq_unit.queue_offset, cq_unit.pos
+                ),
+            }
+            self.curr_queue_offset.store(next_offset, Ordering::Release);
+            count += 1;
+        }
+        count
+    }
+
+    fn do_enqueue(
+        &self,
+        files: &TimerFiles,
+        offset_py: i64,
+        size_py: i32,
+        mut delayed_time: i64,
+        msg: &MessageExt,
+    ) -> bool {
+        let curr_write_time_ms = self.format_time_ms(self.clock.now());
+        let roll_window_ms = self.timer_roll_window_slots * self.precision_ms;
+        let mut magic = MAGIC_DEFAULT;
+        if delayed_time - curr_write_time_ms >= roll_window_ms {
+            magic |= MAGIC_ROLL;
+            delayed_time = if delayed_time - curr_write_time_ms - roll_window_ms
+                < self.timer_roll_window_slots / 3 * self.precision_ms
+            {
+                // give enough time to the next roll
+                curr_write_time_ms + self.timer_roll_window_slots / 2 * self.precision_ms
+            } else {
+                curr_write_time_ms + roll_window_ms
+            };
+        }
+        let is_delete = msg
+            .get_property(&CheetahString::from_static_str(TIMER_DELETE_UNIQUE_KEY))
+            .is_some();
+        if is_delete {
+            magic |= MAGIC_DELETE;
+        }
+        let real_topic = msg
+            .get_property(&CheetahString::from_static_str(
+                MessageConst::PROPERTY_REAL_TOPIC,
+            ))
+            .unwrap_or_default();
+        let slot = files.timer_wheel.get_slot(delayed_time);
+        let unit = TimerLogUnit {
+            prev_pos: slot.last_pos,
+            magic,
+            curr_write_time_ms,
+            delayed_time: (delayed_time - curr_write_time_ms) as i32,
+            offset_py,
+            size_py,
+            topic_hash: JavaStringHasher::new().hash_str(real_topic.as_str()),
+        };
+        match files.timer_log.append(&unit) {
+            Some(pos) => {
+                files.timer_wheel.put_slot(
+                    delayed_time,
+                    if slot.first_pos == -1 {
+                        pos
+                    } else {
+                        slot.first_pos
+                    },
+                    pos,
+                    if is_delete {
+                        slot.num - 1
+                    } else {
+                        slot.num + 1
+                    },
+                    slot.magic,
+                );
+                true
+            }
+            None => {
+                error!(
+                    "append timer log failed, offsetPy: {}, delayedTime: {}",
+                    offset_py, delayed_time
+                );
+                false
+            }
+        }
+    }
+
+    /// Delivers the slot under the read pointer if it is due. Returns -1 when
+    /// the read pointer has caught up with the current time.
+    pub async fn dequeue(&self) -> i32 {
+        let (Some(store), Some(files)) = (self.default_message_store.as_ref(), self.files.as_ref())
+        else {
+            return -1;
+        };
+        if store.get_message_store_config().timer_stop_dequeue {
+            return -1;
+        }
+        let curr_read_time_ms = self.curr_read_time_ms.load(Ordering::Acquire);
+        if curr_read_time_ms >= self.format_time_ms(self.clock.now()) {
+            return -1;
+        }
+        let slot = files.timer_wheel.get_slot(curr_read_time_ms);
+        if slot.is_blank() {
+            self.move_read_time(curr_read_time_ms);
+            return 0;
+        }
+
+        let mut units = Vec::new();
+        let mut pos = slot.last_pos;
+        while pos >= 0 {
+            let Some(unit) = files.timer_log.get_unit(pos) else {
+                warn!("timer log unit missing at {}, slot {}", pos, slot.time_ms);
+                break;
+            };
+            units.push(unit);
+            if unit.prev_pos >= pos {
+                break;
+            }
+            pos = unit.prev_pos;
+        }
+        units.reverse();
+
+        let mut deleted_keys = HashSet::new();
+        let mut normal_msgs = Vec::with_capacity(units.len());
+        for unit in units {
+            let Some(msg) = store.look_message_by_offset_with_size(unit.offset_py, unit.size_py)
+            else {
+                warn!(
+                    "timer message lost, offsetPy: {}, sizePy: {}",
+                    unit.offset_py, unit.size_py
+                );
+                continue;
+            };
+            let need_roll = unit.magic & MAGIC_ROLL != 0;
+            if unit.magic & MAGIC_DELETE != 0 && !need_roll {
+                if let Some(key) =
+                    msg.get_property(&CheetahString::from_static_str(TIMER_DELETE_UNIQUE_KEY))
+                {
+                    deleted_keys.insert(key);
+                }
+            } else {
+                normal_msgs.push((msg, need_roll));
+            }
+        }
+
+        for (msg, need_roll) in normal_msgs {
+            if !need_roll && !deleted_keys.is_empty() {
+                let real_topic = msg
+                    .get_property(&CheetahString::from_static_str(
+                        MessageConst::PROPERTY_REAL_TOPIC,
+                    ))
+                    .unwrap_or_default();
+                let uniq_id = MessageClientIDSetter::get_uniq_id(&msg).unwrap_or_default();
+                if deleted_keys.contains(&Self::build_delete_key(&real_topic, &uniq_id)) {
+                    continue;
+                }
+            }
+            self.put_back(&msg, need_roll).await;
+        }
+        self.move_read_time(curr_read_time_ms);
+        1
+    }
+
+    pub fn flush(&self) {
+        let Some(files) = self.files.as_ref() else {
+            return;
+        };
+        let _guard = self.enqueue_lock.lock();
+        files.timer_log.flush();
+        if let Err(err) = files.timer_wheel.flush() {
+            error!("flush timer wheel failed: {}", err);
+            return;
+        }
+        let checkpoint = &files.timer_checkpoint;
+        checkpoint.set_last_read_time_ms(self.curr_read_time_ms.load(Ordering::Acquire));
+        checkpoint.set_last_timer_log_flush_pos(files.timer_log.get_max_offset());
+        let curr_queue_offset = self.curr_queue_offset.load(Ordering::Acquire);
+        checkpoint.set_last_timer_queue_offset(curr_queue_offset);
+        checkpoint.set_master_timer_queue_offset(curr_queue_offset);
+        if let Err(err) = checkpoint.flush() {
+            error!("flush timer checkpoint failed: {}", err);
+        }
+    }
+
+    pub fn build_delete_key(real_topic: &str, uniq_key: &str) -> CheetahString {
+        CheetahString::from_string(format!("{}+{}", real_topic, uniq_key))
+    }
+
+    fn convert_message(&self, msg_ext: &MessageExt, need_roll: bool) -> MessageExtBrokerInner {
+        let mut msg_ext = msg_ext.clone();
+        MessageAccessor::put_property(
+            &mut msg_ext,
+            CheetahString::from_static_str(TIMER_DEQUEUE_MS),
+            CheetahString::from_string(self.clock.now().to_string()),
+        );
+        if need_roll {
+            let roll_times = msg_ext
+                .get_property(&CheetahString::from_static_str(TIMER_ROLL_TIMES))
+                .and_then(|value| value.parse::<i32>().ok())
+                .unwrap_or_default();
+            MessageAccessor::put_property(
+                &mut msg_ext,
+                CheetahString::from_static_str(TIMER_ROLL_TIMES),
+                CheetahString::from_string((roll_times + 1).to_string()),
+            );
+        }
+
+        let mut msg_inner = MessageExtBrokerInner::default();
+        if let Some(body) = msg_ext.get_body() {
+            msg_inner.set_body(body.clone());
+        }
+        msg_inner.set_flag(msg_ext.get_flag());
+        MessageAccessor::set_properties(&mut msg_inner, msg_ext.get_properties().clone());
+        let topic_filter_type = if msg_ext.sys_flag & MessageSysFlag::MULTI_TAGS_FLAG
+            == MessageSysFlag::MULTI_TAGS_FLAG
+        {
+            TopicFilterType::MultiTag
+        } else {
+            TopicFilterType::SingleTag
+        };
+        msg_inner.tags_code = match msg_ext.get_tags() {
+            Some(tags) => {
+                MessageExtBrokerInner::tags_string2tags_code(&topic_filter_type, tags.as_str())
+            }
+            None => 0,
+        };
+        msg_inner.message_ext_inner.born_timestamp = msg_ext.born_timestamp;
+        msg_inner.message_ext_inner.born_host = msg_ext.born_host;
+        msg_inner.message_ext_inner.store_host = msg_ext.store_host;
+        msg_inner.message_ext_inner.reconsume_times = msg_ext.reconsume_times;
+        msg_inner.message_ext_inner.sys_flag = msg_ext.sys_flag;
+        msg_inner.set_wait_store_msg_ok(false);
+
+        if need_roll {
+            msg_inner.set_topic(msg_ext.get_topic().clone());
+            msg_inner.message_ext_inner.queue_id = msg_ext.queue_id;
+        } else {
+            msg_inner.set_topic(
+                msg_ext
+                    .get_property(&CheetahString::from_static_str(
+                        MessageConst::PROPERTY_REAL_TOPIC,
+                    ))
+                    .unwrap_or_default(),
+            );
+            msg_inner.message_ext_inner.queue_id = msg_ext
+                .get_property(&CheetahString::from_static_str(
+                    MessageConst::PROPERTY_REAL_QUEUE_ID,
+                ))
+                .and_then(|value| value.parse().ok())
+                .unwrap_or_default();
+            MessageAccessor::clear_property(&mut msg_inner, MessageConst::PROPERTY_REAL_TOPIC);
+            MessageAccessor::clear_property(&mut msg_inner, MessageConst::PROPERTY_REAL_QUEUE_ID);
+        }
+        msg_inner.properties_string =
+            message_properties_to_string(msg_inner.message_ext_inner.get_properties());
+        msg_inner
+    }
+
+    async fn put_back(&self, msg_ext: &MessageExt, need_roll: bool) -> bool {
+        let Some(mut store) = self.default_message_store.clone() else {
+            return false;
+        };
+        loop {
+            let result = store
+                .put_message(self.convert_message(msg_ext, need_roll))
+                .await;
+            match Self::put_result_process(result.put_message_status()) {
+                PUT_OK => return true,
+                PUT_NO_RETRY => {
+                    warn!(
+                        "skip timer message {:?}, put status: {:?}",
+                        MessageClientIDSetter::get_uniq_id(msg_ext),
+                        result.put_message_status()
+                    );
+                    return true;
+                }
+                _ => {
+                    if self.is_stopped() {
+                        return false;
+                    }
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+            }
+        }
+    }
+
+    fn put_result_process(status: PutMessageStatus) -> i32 {
+        match status {
+            PutMessageStatus::PutOk
+            | PutMessageStatus::FlushDiskTimeout
+            | PutMessageStatus::FlushSlaveTimeout
+            | PutMessageStatus::SlaveNotAvailable => PUT_OK,
+            PutMessageStatus::MessageIllegal
+            | PutMessageStatus::PropertiesSizeExceeded
+            | PutMessageStatus::WheelTimerNotEnable
+            | PutMessageStatus::WheelTimerMsgIllegal => PUT_NO_RETRY,
+            _ => PUT_NEED_RETRY,
+        }
     }
 
-    pub fn start(&mut self) {}
+    fn move_read_time(&self, curr_read_time_ms: i64) {
+        self.curr_read_time_ms
+            .store(curr_read_time_ms + self.precision_ms, Ordering::Release);
+    }
+
+    fn format_time_ms(&self, time_ms: i64) -> i64 {
+        time_ms / self.precision_ms * self.precision_ms
+    }
 
     pub fn is_reject(&self, _deliver_ms: u64) -> bool {
         false
@@ -83,16 +566,11 @@ impl TimerMessageStore {
     }
 
     pub fn get_dequeue_behind_millis(&self) -> i64 {
-        (SystemClock::now() as i64)
-            - self
-                .curr_read_time_ms
-                .load(std::sync::atomic::Ordering::Relaxed)
+        self.clock.now() - self.curr_read_time_ms.load(Ordering::Relaxed)
     }
 
     pub fn get_enqueue_behind_messages(&self) -> i64 {
-        let temp_queue_offset = self
-            .curr_queue_offset
-            .load(std::sync::atomic::Ordering::Relaxed);
+        let temp_queue_offset = self.curr_queue_offset.load(Ordering::Relaxed);
         let consume_queue = self
             .default_message_store
             .as_ref()
@@ -118,11 +596,35 @@ impl TimerMessageStore {
     }
 
     pub fn new(default_message_store: Option<ArcMut<DefaultMessageStore>>) -> Self {
-        Self {
-            curr_read_time_ms: AtomicI64::new(0),
-            curr_queue_offset: AtomicI64::new(0),
-            default_message_store,
+        let mut timer_message_store = Self::new_empty();
+        if let Some(message_store) = default_message_store.as_ref() {
+            let config = message_store.get_message_store_config();
+            timer_message_store.precision_ms = config.timer_precision_ms.max(1) as i64;
+            timer_message_store.timer_roll_window_slots = config.timer_roll_window_slot as i64;
+            timer_message_store.timer_flush_interval_ms = config.timer_flush_interval_ms as u64;
+            let slots_total = TIMER_WHEEL_TTL_DAY as i64 * DAY_SECS as i64;
+            let files = TimerWheel::new(
+                config.get_timer_wheel_path(),
+                slots_total,
+                timer_message_store.precision_ms,
+            )
+            .and_then(|timer_wheel| {
+                Ok(TimerFiles {
+                    timer_wheel,
+                    timer_log: TimerLog::new(
+                        config.get_timer_log_path(),
+                        config.mapped_file_size_timer_log,
+                    ),
+                    timer_checkpoint: TimerCheckpoint::new(config.get_timer_check_path())?,
+                })
+            });
+            match files {
+                Ok(files) => timer_message_store.files = Some(files),
+                Err(err) => error!("create timer message store files failed: {}", err),
+            }
         }
+        timer_message_store.default_message_store = default_message_store;
+        timer_message_store
     }
 
     pub fn new_empty() -> Self {
@@ -130,6 +632,14 @@ impl TimerMessageStore {
             curr_read_time_ms: AtomicI64::new(0),
             curr_queue_offset: AtomicI64::new(0),
             default_message_store: None,
+            files: None,
+            clock: Arc::new(SystemTimerClock),
+            precision_ms: 1000,
+            timer_roll_window_slots: 3600 * 24 * 2,
+            timer_flush_interval_ms: 1000,
+            enqueue_lock: parking_lot::Mutex::new(()),
+            stopped: AtomicBool::new(false),
+            shutdown_notify: Notify::new(),
         }
     }
 
@@ -139,4 +649,171 @@ impl TimerMessageStore {
     ) {
         self.default_message_store = default_message_store;
     }
+
+    pub fn set_clock(&mut self, clock: Arc<dyn TimerClock>) {
+        self.clock = clock;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::message_store_config::MessageStoreConfig;
+    use crate::test_util;
+    use crate::test_util::wait_dispatched;
+
+    const HOUR_MS: i64 = 3600 * 1000;
+    const START_MS: i64 = 1_700_000_000_000;
+
+    struct MockClock(AtomicI64);
+
+    impl TimerClock for MockClock {
+        fn now(&self) -> i64 {
+            self.0.load(Ordering::SeqCst)
+        }
+    }
+
+    async fn start_store(dir: &tempfile::TempDir) -> ArcMut<DefaultMessageStore> {
+        test_util::start_store(
+            dir,
+            MessageStoreConfig {
+                mapped_file_size_timer_log: 1024,
+                timer_wheel_enable: true,
+                timer_precision_ms: 1000,
+                // one hour window, so a delay of hours rolls several times
+                timer_roll_window_slot: 3600,
+                ..MessageStoreConfig::default()
+            },
+        )
+        .await
+    }
+
+    fn timer_store(
+        store: &ArcMut<DefaultMessageStore>,
+        clock: &Arc<MockClock>,
+    ) -> TimerMessageStore {
+        let mut timer_message_store = TimerMessageStore::new(Some(store.clone()));
+        timer_message_store.set_clock(clock.clone());
+        assert!(timer_message_store.load());
+        timer_message_store
+    }
+
+    async fn put_timer_message(
+        store: &mut ArcMut<DefaultMessageStore>,
+        real_topic: &str,
+        deliver_ms: i64,
+    ) {
+        let mut msg = MessageExtBrokerInner::default();
+        msg.set_topic(CheetahString::from_static_str(TIMER_TOPIC));
+        msg.set_body(bytes::Bytes::from_static(b"timer"));
+        for (key, value) in [
+            (TIMER_OUT_MS, deliver_ms.to_string()),
+            (MessageConst::PROPERTY_REAL_TOPIC, real_topic.to_string()),
+            (MessageConst::PROPERTY_REAL_QUEUE_ID, "0".to_string()),
+        ] {
+            MessageAccessor::put_property(
+                &mut msg,
+                CheetahString::from_static_str(key),
+                CheetahString::from_string(value),
+            );
+        }
+        msg.properties_string = message_properties_to_string(msg.get_properties());
+        let result = store.put_message(msg).await;
+        assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        wait_dispatched(store).await;
+    }
+
+    /// Moves the mocked time forward in ten minute steps, running the enqueue
+    /// and dequeue passes after each step.
+    async fn advance_to(
+        timer_message_store: &TimerMessageStore,
+        store: &ArcMut<DefaultMessageStore>,
+        clock: &MockClock,
+        target_ms: i64,
+    ) {
+        while clock.now() < target_ms {
+            clock
+                .0
+                .store((clock.now() + HOUR_MS / 6).min(target_ms), Ordering::SeqCst);
+            timer_message_store.enqueue().await;
+            while timer_message_store.dequeue().await >= 0 {}
+            wait_dispatched(store).await;
+        }
+        timer_message_store.enqueue().await;
+    }
+
+    fn delivered(store: &ArcMut<DefaultMessageStore>, topic: &str) -> Vec<MessageExt> {
+        let topic = CheetahString::from_slice(topic);
+        let max_offset = store.get_max_offset_in_queue(&topic, 0);
+        let Some(consume_queue) = store.find_consume_queue(&topic, 0) else {
+            return vec![];
+        };
+        (0..max_offset)
+            .filter_map(|offset| consume_queue.get(offset))
+            .filter_map(|cq_unit| store.look_message_by_offset_with_size(cq_unit.pos, cq_unit.size))
+            .collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn delivers_hours_ahead_across_wheel_rolls() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = start_store(&dir).await;
+        let clock = Arc::new(MockClock(AtomicI64::new(START_MS)));
+        let timer_message_store = timer_store(&store, &clock);
+
+        let deliver_ms = START_MS + 5 * HOUR_MS;
+        put_timer_message(&mut store, "TimerTopic", deliver_ms).await;
+
+        advance_to(&timer_message_store, &store, &clock, deliver_ms - 1000).await;
+        assert!(delivered(&store, "TimerTopic").is_empty());
+
+        advance_to(&timer_message_store, &store, &clock, deliver_ms + 1000).await;
+        let messages = delivered(&store, "TimerTopic");
+        assert_eq!(messages.len(), 1);
+        let roll_times = messages[0]
+            .get_property(&CheetahString::from_static_str(TIMER_ROLL_TIMES))
+            .unwrap()
+            .parse::<i32>()
+            .unwrap();
+        assert!(roll_times >= 2, "rolled {} times", roll_times);
+        assert_eq!(
+            messages[0].get_property(&CheetahString::from_static_str(
+                MessageConst::PROPERTY_REAL_TOPIC
+            )),
+            None
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn restart_recovers_from_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = start_store(&dir).await;
+        let clock = Arc::new(MockClock(AtomicI64::new(START_MS)));
+        let deliver_ms = START_MS + 2 * HOUR_MS;
+
+        let timer_message_store = timer_store(&store, &clock);
+        put_timer_message(&mut store, "TimerTopic", deliver_ms).await;
+        advance_to(&timer_message_store, &store, &clock, START_MS + HOUR_MS / 2).await;
+        timer_message_store.flush();
+        let checkpoint_read_time_ms = timer_message_store.curr_read_time_ms.load(Ordering::SeqCst);
+
+        // enqueued after the checkpoint, lost with the crash below
+        put_timer_message(&mut store, "TimerTopic", deliver_ms).await;
+        assert_eq!(timer_message_store.enqueue().await, 1);
+        drop(timer_message_store);
+
+        let timer_message_store = timer_store(&store, &clock);
+        assert_eq!(
+            timer_message_store.curr_read_time_ms.load(Ordering::SeqCst),
+            checkpoint_read_time_ms
+        );
+        assert_eq!(
+            timer_message_store.curr_queue_offset.load(Ordering::SeqCst),
+            1
+        );
+        assert_eq!(timer_message_store.get_enqueue_behind_messages(), 1);
+
+        advance_to(&timer_message_store, &store, &clock, deliver_ms + 1000).await;
+        assert_eq!(delivered(&store, "TimerTopic").len(), 2);
+    }
 }
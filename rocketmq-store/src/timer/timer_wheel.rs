@@ -0,0 +1,172 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::path::Path;
+
+use memmap2::MmapMut;
+use rocketmq_common::UtilAll::ensure_dir_ok;
+
+/// Size in bytes of one slot: time ms, first pos, last pos, num and magic.
+pub const SLOT_SIZE: usize = 8 + 8 + 8 + 4 + 4;
+
+/// One tick of the timer wheel.
+///
+/// `first_pos` and `last_pos` point into the timer log; the records of a slot
+/// are chained backwards from `last_pos` through their `prev_pos` field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slot {
+    pub time_ms: i64,
+    pub first_pos: i64,
+    pub last_pos: i64,
+    pub num: i32,
+    pub magic: i32,
+}
+
+impl Slot {
+    pub const BLANK: Slot = Slot {
+        time_ms: -1,
+        first_pos: -1,
+        last_pos: -1,
+        num: 0,
+        magic: 0,
+    };
+
+    pub fn is_blank(&self) -> bool {
+        self.time_ms == -1
+    }
+}
+
+/// The `timerwheel` file: `slots_total * 2` fixed-size slots indexed by
+/// `(time_ms / precision_ms) % (slots_total * 2)`.
+pub struct TimerWheel {
+    _file: File,
+    mmap: parking_lot::Mutex<MmapMut>,
+    slots_total: i64,
+    precision_ms: i64,
+}
+
+impl TimerWheel {
+    pub fn new<P: AsRef<Path>>(
+        path: P,
+        slots_total: i64,
+        precision_ms: i64,
+    ) -> std::io::Result<Self> {
+        ensure_dir_ok(path.as_ref().parent().unwrap().to_str().unwrap());
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path.as_ref())?;
+        file.set_len((slots_total * 2) as u64 * SLOT_SIZE as u64)?;
+        let mmap = unsafe { MmapMut::map_mut(&file)? };
+        Ok(Self {
+            _file: file,
+            mmap: parking_lot::Mutex::new(mmap),
+            slots_total,
+            precision_ms,
+        })
+    }
+
+    /// Returns the slot of `time_ms`, or [`Slot::BLANK`] when the stored slot
+    /// belongs to another round of the wheel.
+    pub fn get_slot(&self, time_ms: i64) -> Slot {
+        let slot = self.get_raw_slot(time_ms);
+        if slot.time_ms / self.precision_ms != time_ms / self.precision_ms {
+            return Slot::BLANK;
+        }
+        slot
+    }
+
+    pub fn put_slot(&self, time_ms: i64, first_pos: i64, last_pos: i64, num: i32, magic: i32) {
+        let start = self.get_slot_index(time_ms) * SLOT_SIZE;
+        let mut mmap = self.mmap.lock();
+        let buffer = &mut mmap[start..start + SLOT_SIZE];
+        buffer[0..8].copy_from_slice(&time_ms.to_be_bytes());
+        buffer[8..16].copy_from_slice(&first_pos.to_be_bytes());
+        buffer[16..24].copy_from_slice(&last_pos.to_be_bytes());
+        buffer[24..28].copy_from_slice(&num.to_be_bytes());
+        buffer[28..32].copy_from_slice(&magic.to_be_bytes());
+    }
+
+    pub fn get_slot_index(&self, time_ms: i64) -> usize {
+        ((time_ms / self.precision_ms) % (self.slots_total * 2)) as usize
+    }
+
+    /// Repairs every slot still pointing at timer log records at or beyond
+    /// `max_pos`, following the backward chain with `prev_pos_of` until a
+    /// surviving record is found. Slots without one are reset.
+    pub fn revise_slots(&self, max_pos: i64, prev_pos_of: impl Fn(i64) -> Option<i64>) -> usize {
+        let mut revised = 0;
+        for index in 0..(self.slots_total * 2) {
+            let time_ms = index * self.precision_ms;
+            let slot = self.get_raw_slot(time_ms);
+            if slot.last_pos < max_pos || slot.time_ms <= 0 {
+                continue;
+            }
+            let mut last_pos = slot.last_pos;
+            let mut num = slot.num;
+            while last_pos >= max_pos {
+                match prev_pos_of(last_pos) {
+                    Some(prev_pos) if prev_pos < last_pos => last_pos = prev_pos,
+                    _ => {
+                        last_pos = -1;
+                        break;
+                    }
+                }
+                num -= 1;
+            }
+            if last_pos < 0 || slot.first_pos >= max_pos {
+                self.reset_slot(slot.time_ms);
+            } else {
+                self.put_slot(
+                    slot.time_ms,
+                    slot.first_pos,
+                    last_pos,
+                    num.max(0),
+                    slot.magic,
+                );
+            }
+            revised += 1;
+        }
+        revised
+    }
+
+    pub fn flush(&self) -> std::io::Result<()> {
+        self.mmap.lock().flush()
+    }
+
+    fn get_raw_slot(&self, time_ms: i64) -> Slot {
+        let start = self.get_slot_index(time_ms) * SLOT_SIZE;
+        let mmap = self.mmap.lock();
+        let buffer = &mmap[start..start + SLOT_SIZE];
+        Slot {
+            time_ms: i64::from_be_bytes(buffer[0..8].try_into().unwrap()),
+            first_pos: i64::from_be_bytes(buffer[8..16].try_into().unwrap()),
+            last_pos: i64::from_be_bytes(buffer[16..24].try_into().unwrap()),
+            num: i32::from_be_bytes(buffer[24..28].try_into().unwrap()),
+            magic: i32::from_be_bytes(buffer[28..32].try_into().unwrap()),
+        }
+    }
+
+    fn reset_slot(&self, time_ms: i64) {
+        let start = self.get_slot_index(time_ms) * SLOT_SIZE;
+        self.mmap.lock()[start..start + SLOT_SIZE].fill(0);
+    }
+}
@@ -15,4 +15,5 @@
  * limitations under the License.
  */
 
+pub(crate) mod multi_dispatch_utils;
 pub(crate) mod store_util;
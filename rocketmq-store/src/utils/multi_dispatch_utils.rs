@@ -0,0 +1,135 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Helpers for dispatching one message into several light message queues (LMQ).
+
+use std::collections::HashMap;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::mix_all;
+use rocketmq_common::common::topic::TopicValidator;
+
+use crate::base::dispatch_request::DispatchRequest;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::timer::timer_message_store::TIMER_TOPIC;
+
+/// Key of an LMQ in the queue offset table, LMQs always live in queue 0.
+pub fn lmq_queue_key(queue_name: &str) -> CheetahString {
+    CheetahString::from_string(format!("{}-{}", queue_name, mix_all::LMQ_QUEUE_ID))
+}
+
+pub fn is_need_handle_multi_dispatch(
+    message_store_config: &MessageStoreConfig,
+    topic: &str,
+) -> bool {
+    message_store_config.enable_multi_dispatch
+        && !topic.starts_with(mix_all::RETRY_GROUP_TOPIC_PREFIX)
+        && !topic.starts_with(TIMER_TOPIC)
+        && topic != TopicValidator::RMQ_SYS_SCHEDULE_TOPIC
+}
+
+pub fn check_multi_dispatch_queue(
+    message_store_config: &MessageStoreConfig,
+    dispatch_request: &DispatchRequest,
+) -> bool {
+    if !is_need_handle_multi_dispatch(message_store_config, dispatch_request.topic.as_str()) {
+        return false;
+    }
+    let Some(properties) = dispatch_request.properties_map.as_ref() else {
+        return false;
+    };
+    !is_blank(properties, MessageConst::PROPERTY_INNER_MULTI_DISPATCH)
+        && !is_blank(properties, MessageConst::PROPERTY_INNER_MULTI_QUEUE_OFFSET)
+}
+
+/// Pairs every queue of `INNER_MULTI_DISPATCH` with its offset from
+/// `INNER_MULTI_QUEUE_OFFSET`, or `None` when the two lists do not line up.
+pub fn multi_dispatch_queue_offsets(
+    properties: &HashMap<CheetahString, CheetahString>,
+) -> Option<Vec<(CheetahString, i64)>> {
+    let queues = properties.get(MessageConst::PROPERTY_INNER_MULTI_DISPATCH)?;
+    let offsets = properties.get(MessageConst::PROPERTY_INNER_MULTI_QUEUE_OFFSET)?;
+    let queues = queues
+        .split(mix_all::MULTI_DISPATCH_QUEUE_SPLITTER)
+        .collect::<Vec<_>>();
+    let offsets = offsets
+        .split(mix_all::MULTI_DISPATCH_QUEUE_SPLITTER)
+        .collect::<Vec<_>>();
+    if queues.len() != offsets.len() {
+        return None;
+    }
+    queues
+        .into_iter()
+        .zip(offsets)
+        .map(|(queue, offset)| Some((CheetahString::from_slice(queue), offset.parse().ok()?)))
+        .collect()
+}
+
+fn is_blank(properties: &HashMap<CheetahString, CheetahString>, key: &str) -> bool {
+    properties
+        .get(key)
+        .map_or(true, |value| value.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(queues: &str, offsets: &str) -> HashMap<CheetahString, CheetahString> {
+        HashMap::from([
+            (
+                CheetahString::from_static_str(MessageConst::PROPERTY_INNER_MULTI_DISPATCH),
+                CheetahString::from_slice(queues),
+            ),
+            (
+                CheetahString::from_static_str(MessageConst::PROPERTY_INNER_MULTI_QUEUE_OFFSET),
+                CheetahString::from_slice(offsets),
+            ),
+        ])
+    }
+
+    #[test]
+    fn pairs_queues_with_offsets() {
+        let pairs = multi_dispatch_queue_offsets(&properties("%LMQ%a,%LMQ%b", "3,7")).unwrap();
+        assert_eq!(
+            pairs,
+            vec![
+                (CheetahString::from_static_str("%LMQ%a"), 3),
+                (CheetahString::from_static_str("%LMQ%b"), 7)
+            ]
+        );
+        assert!(multi_dispatch_queue_offsets(&properties("%LMQ%a,%LMQ%b", "3")).is_none());
+        assert_eq!(lmq_queue_key("%LMQ%a"), "%LMQ%a-0");
+    }
+
+    #[test]
+    fn retry_topics_are_not_multi_dispatched() {
+        let config = MessageStoreConfig {
+            enable_multi_dispatch: true,
+            ..MessageStoreConfig::default()
+        };
+        let mut request = DispatchRequest {
+            topic: CheetahString::from_static_str("TopicA"),
+            properties_map: Some(properties("%LMQ%a", "0")),
+            ..DispatchRequest::default()
+        };
+        assert!(check_multi_dispatch_queue(&config, &request));
+        request.topic = CheetahString::from_static_str("%RETRY%GroupA");
+        assert!(!check_multi_dispatch_queue(&config, &request));
+    }
+}
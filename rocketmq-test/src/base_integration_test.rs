@@ -0,0 +1,287 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+use cheetah_string::CheetahString;
+use rocketmq_client_rust::base::client_config::ClientConfig;
+use rocketmq_client_rust::consumer::default_mq_push_consumer::DefaultMQPushConsumer;
+use rocketmq_client_rust::consumer::default_mq_push_consumer_builder::DefaultMQPushConsumerBuilder;
+use rocketmq_client_rust::consumer::listener::message_listener_concurrently::MessageListenerConcurrently;
+use rocketmq_client_rust::consumer::mq_push_consumer::MQPushConsumer;
+use rocketmq_client_rust::producer::default_mq_producer::DefaultMQProducer;
+use rocketmq_client_rust::producer::mq_producer::MQProducer;
+use rocketmq_common::common::constant::PermName;
+use rocketmq_common::common::consumer::consume_from_where::ConsumeFromWhere;
+use rocketmq_common::common::topic::TopicValidator;
+use rocketmq_remoting::clients::Client;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::header::client_request_header::GetRouteInfoRequestHeader;
+use rocketmq_remoting::protocol::header::create_topic_request_header::CreateTopicRequestHeader;
+use rocketmq_remoting::protocol::header::get_max_offset_request_header::GetMaxOffsetRequestHeader;
+use rocketmq_remoting::protocol::header::get_max_offset_response_header::GetMaxOffsetResponseHeader;
+use rocketmq_remoting::protocol::header::query_consumer_offset_request_header::QueryConsumerOffsetRequestHeader;
+use rocketmq_remoting::protocol::header::query_consumer_offset_response_header::QueryConsumerOffsetResponseHeader;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::route::topic_route_data::TopicRouteData;
+use rocketmq_remoting::protocol::RemotingDeserializable;
+use rocketmq_remoting::request_processor::default_request_processor::DefaultRemotingRequestProcessor;
+use tempfile::TempDir;
+
+use crate::embedded_broker::EmbeddedBroker;
+use crate::embedded_name_server::EmbeddedNameServer;
+
+const ADMIN_TIMEOUT_MILLIS: u64 = 3000;
+
+/// Distinguishes the clients of every harness in the process, as clients sharing an instance
+/// name share their connections and route tables.
+static CLIENT_SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+
+/// A name server plus one broker registered to it, with helpers to create topics and clients.
+///
+/// Dropping the harness stops the broker, then the name server, then deletes the temporary
+/// directory holding their files. Running on drop means it happens on panics too.
+pub struct BaseIntegrationTest {
+    broker: EmbeddedBroker,
+    name_server: EmbeddedNameServer,
+    _home: TempDir,
+}
+
+impl BaseIntegrationTest {
+    pub async fn start() -> BaseIntegrationTest {
+        let home = tempfile::Builder::new()
+            .prefix("rocketmq-it-")
+            .tempdir()
+            .expect("failed to create test directory");
+        let name_server = EmbeddedNameServer::start(&home.path().join("namesrv")).await;
+        let broker = EmbeddedBroker::start(&home.path().join("store"), &name_server.addr()).await;
+        BaseIntegrationTest {
+            broker,
+            name_server,
+            _home: home,
+        }
+    }
+
+    pub fn namesrv_addr(&self) -> String {
+        self.name_server.addr()
+    }
+
+    pub fn broker(&self) -> &EmbeddedBroker {
+        &self.broker
+    }
+
+    /// Stops the broker and starts it again over the same store.
+    pub async fn restart_broker(&mut self) {
+        self.broker.restart().await;
+    }
+
+    /// Creates `topic` with `queue_nums` read and write queues on the broker and waits until
+    /// the name server routes it.
+    pub async fn create_topic(&self, topic: &str, queue_nums: u32) -> TopicRouteData {
+        let header = CreateTopicRequestHeader {
+            topic: topic.into(),
+            default_topic: TopicValidator::AUTO_CREATE_TOPIC_KEY_TOPIC.into(),
+            read_queue_nums: queue_nums as i32,
+            write_queue_nums: queue_nums as i32,
+            perm: (PermName::PERM_READ | PermName::PERM_WRITE) as i32,
+            topic_filter_type: "SINGLE_TAG".into(),
+            topic_sys_flag: None,
+            order: false,
+            attributes: None,
+            force: None,
+            topic_request_header: None,
+        };
+        let request =
+            RemotingCommand::create_request_command(RequestCode::UpdateAndCreateTopic, header);
+        let response = self.invoke(&self.broker.addr(), request).await;
+        assert_eq!(
+            response.code(),
+            ResponseCode::Success as i32,
+            "failed to create topic {topic}: {:?}",
+            response.remark()
+        );
+        self.wait_for_route(topic, Duration::from_secs(10), |route| {
+            route
+                .queue_datas
+                .iter()
+                .any(|queue_data| queue_data.write_queue_nums == queue_nums)
+        })
+        .await
+    }
+
+    /// Polls the name server until the route of `topic` satisfies `ready`, panicking after
+    /// `timeout`.
+    pub async fn wait_for_route(
+        &self,
+        topic: &str,
+        timeout: Duration,
+        ready: impl Fn(&TopicRouteData) -> bool,
+    ) -> TopicRouteData {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let request = RemotingCommand::create_request_command(
+                RequestCode::GetRouteinfoByTopic,
+                GetRouteInfoRequestHeader::new(topic, Some(true)),
+            );
+            let response = self.invoke(&self.namesrv_addr(), request).await;
+            if response.code() == ResponseCode::Success as i32 {
+                if let Some(body) = response.body() {
+                    let route = TopicRouteData::decode(body.as_ref())
+                        .expect("name server returned an undecodable route");
+                    if ready(&route) {
+                        return route;
+                    }
+                }
+            }
+            assert!(
+                Instant::now() < deadline,
+                "route of {topic} not ready after {timeout:?}"
+            );
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// The offset `group` committed on `queue_id` of `topic`, if any.
+    pub async fn consumer_offset(&self, group: &str, topic: &str, queue_id: i32) -> Option<i64> {
+        let header = QueryConsumerOffsetRequestHeader {
+            consumer_group: group.into(),
+            topic: topic.into(),
+            queue_id,
+            set_zero_if_not_found: Some(false),
+            topic_request_header: None,
+        };
+        let request =
+            RemotingCommand::create_request_command(RequestCode::QueryConsumerOffset, header);
+        let response = self.invoke(&self.broker.addr(), request).await;
+        if response.code() != ResponseCode::Success as i32 {
+            return None;
+        }
+        response
+            .decode_command_custom_header::<QueryConsumerOffsetResponseHeader>()
+            .and_then(|header| header.offset)
+    }
+
+    /// The offset the next message stored on `queue_id` of `topic` gets.
+    pub async fn max_offset(&self, topic: &str, queue_id: i32) -> i64 {
+        let header = GetMaxOffsetRequestHeader {
+            topic: topic.into(),
+            queue_id,
+            committed: true,
+            topic_request_header: None,
+        };
+        let request = RemotingCommand::create_request_command(RequestCode::GetMaxOffset, header);
+        let response = self.invoke(&self.broker.addr(), request).await;
+        response
+            .decode_command_custom_header::<GetMaxOffsetResponseHeader>()
+            .map(|header| header.offset)
+            .unwrap_or_else(|| panic!("no max offset for {topic}:{queue_id}"))
+    }
+
+    /// Waits until `group` committed every message stored on the first `queue_nums` queues of
+    /// `topic`, panicking after `timeout`.
+    pub async fn wait_for_consumer_offsets(
+        &self,
+        group: &str,
+        topic: &str,
+        queue_nums: u32,
+        timeout: Duration,
+    ) {
+        let deadline = Instant::now() + timeout;
+        for queue_id in 0..queue_nums as i32 {
+            let max_offset = self.max_offset(topic, queue_id).await;
+            loop {
+                let committed = self.consumer_offset(group, topic, queue_id).await;
+                if committed == Some(max_offset) {
+                    break;
+                }
+                assert!(
+                    Instant::now() < deadline,
+                    "{group} committed {committed:?} on {topic}:{queue_id}, expected {max_offset}"
+                );
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        }
+    }
+
+    /// A started producer of `group`.
+    pub async fn create_producer(&self, group: &str) -> DefaultMQProducer {
+        let mut producer = DefaultMQProducer::builder()
+            .client_config(self.client_config())
+            .producer_group(group)
+            .build()
+            .expect("failed to build producer");
+        producer.start().await.expect("failed to start producer");
+        producer
+    }
+
+    /// A builder for a push consumer of `group`, consuming from the first offset when the group
+    /// has none committed. Used to tune a consumer beyond what [`Self::create_push_consumer`]
+    /// sets.
+    pub fn push_consumer_builder(&self, group: &str) -> DefaultMQPushConsumerBuilder {
+        DefaultMQPushConsumer::builder()
+            .client_config(self.client_config())
+            .consumer_group(group)
+            .consume_from_where(ConsumeFromWhere::ConsumeFromFirstOffset)
+    }
+
+    /// A started push consumer of `group` subscribed to every message of `topic`, consuming
+    /// from the first offset when the group has none committed.
+    pub async fn create_push_consumer(
+        &self,
+        group: &str,
+        topic: &str,
+        listener: impl MessageListenerConcurrently + 'static,
+    ) -> DefaultMQPushConsumer {
+        let mut consumer = self
+            .push_consumer_builder(group)
+            .message_listener_concurrently(listener)
+            .build()
+            .expect("failed to build consumer");
+        consumer
+            .subscribe(topic, "*")
+            .expect("failed to subscribe consumer");
+        consumer.start().await.expect("failed to start consumer");
+        consumer
+    }
+
+    fn client_config(&self) -> ClientConfig {
+        let sequence = CLIENT_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+        ClientConfig {
+            namesrv_addr: Some(self.namesrv_addr().into()),
+            instance_name: CheetahString::from_string(format!(
+                "it-{}-{}",
+                std::process::id(),
+                sequence
+            )),
+            persist_consumer_offset_interval: 1000,
+            ..ClientConfig::default()
+        }
+    }
+
+    async fn invoke(&self, addr: &str, request: RemotingCommand) -> RemotingCommand {
+        let mut client = Client::connect(addr, DefaultRemotingRequestProcessor, None)
+            .await
+            .unwrap_or_else(|error| panic!("failed to connect to {addr}: {error}"));
+        client
+            .send_read(request, ADMIN_TIMEOUT_MILLIS)
+            .await
+            .unwrap_or_else(|error| panic!("request to {addr} failed: {error}"))
+    }
+}
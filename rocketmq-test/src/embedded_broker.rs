@@ -0,0 +1,166 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use cheetah_string::CheetahString;
+use rocketmq_broker::BrokerBootstrap;
+use rocketmq_broker::Builder;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::broker::broker_config::BrokerIdentity;
+use rocketmq_common::common::server::config::ServerConfig;
+use rocketmq_store::config::flush_disk_type::FlushDiskType;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
+use tokio::runtime::Runtime;
+use tracing::info;
+
+use crate::network;
+
+pub const DEFAULT_CLUSTER_NAME: &str = "IntegrationTestCluster";
+pub const DEFAULT_BROKER_NAME: &str = "integration-broker-a";
+
+/// A master broker on an ephemeral local port, registered to a name server.
+///
+/// The port and store root are kept across [`restart`](EmbeddedBroker::restart), so clients
+/// reconnect to the same address and the broker recovers what it stored before.
+pub struct EmbeddedBroker {
+    port: u16,
+    store_root: PathBuf,
+    namesrv_addr: String,
+    runtime: Option<Runtime>,
+    bootstrap: Option<BrokerBootstrap>,
+}
+
+impl EmbeddedBroker {
+    /// Starts a broker storing under `store_root` and registering to `namesrv_addr`.
+    pub async fn start(store_root: &Path, namesrv_addr: &str) -> EmbeddedBroker {
+        let mut broker = EmbeddedBroker {
+            port: network::pick_free_broker_port(),
+            store_root: store_root.to_path_buf(),
+            namesrv_addr: namesrv_addr.to_string(),
+            runtime: None,
+            bootstrap: None,
+        };
+        broker.boot().await;
+        broker
+    }
+
+    /// The `host:port` the broker serves clients on.
+    pub fn addr(&self) -> String {
+        format!("{}:{}", network::LOCALHOST, self.port)
+    }
+
+    pub fn broker_name(&self) -> &str {
+        DEFAULT_BROKER_NAME
+    }
+
+    pub fn store_root(&self) -> &Path {
+        &self.store_root
+    }
+
+    /// Stops the broker and starts it again on the same port and store root.
+    pub async fn restart(&mut self) {
+        self.shutdown();
+        self.boot().await;
+    }
+
+    /// Stops the broker. Calling it more than once has no effect.
+    pub fn shutdown(&mut self) {
+        if let (Some(bootstrap), Some(runtime)) = (self.bootstrap.take(), self.runtime.take()) {
+            network::drop_off_runtime(bootstrap, runtime);
+            network::wait_until_closed(self.port, Duration::from_secs(5));
+            info!("embedded broker on {} stopped", self.addr());
+        }
+    }
+
+    async fn boot(&mut self) {
+        let (broker_config, message_store_config, server_config) = self.configs();
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(4)
+            .thread_name("embedded-broker")
+            .enable_all()
+            .build()
+            .expect("failed to build broker runtime");
+        let bootstrap = runtime
+            .spawn(async move {
+                let mut bootstrap = Builder::new()
+                    .set_broker_config(broker_config)
+                    .set_message_store_config(message_store_config)
+                    .set_server_config(server_config)
+                    .build();
+                let started = bootstrap.start_embedded().await;
+                (bootstrap, started)
+            })
+            .await;
+        let (bootstrap, started) = match bootstrap {
+            Ok(bootstrap) => bootstrap,
+            Err(error) => {
+                network::drop_off_runtime((), runtime);
+                panic!("broker failed to start: {error}");
+            }
+        };
+        if !started {
+            network::drop_off_runtime(bootstrap, runtime);
+            panic!(
+                "broker failed to initialize under {}",
+                self.store_root.display()
+            );
+        }
+        self.runtime = Some(runtime);
+        self.bootstrap = Some(bootstrap);
+        network::wait_until_listening(self.port, Duration::from_secs(10)).await;
+        info!("embedded broker listening on {}", self.addr());
+    }
+
+    fn configs(&self) -> (BrokerConfig, MessageStoreConfig, ServerConfig) {
+        let store_root = CheetahString::from_string(self.store_root.to_string_lossy().into_owned());
+        let mut broker_config = BrokerConfig {
+            broker_ip1: network::LOCALHOST.into(),
+            listen_port: self.port as u32,
+            namesrv_addr: Some(self.namesrv_addr.clone().into()),
+            broker_name: DEFAULT_BROKER_NAME.into(),
+            store_path_root_dir: store_root.clone(),
+            flush_consumer_offset_interval: 1000,
+            ..BrokerConfig::default()
+        };
+        broker_config.broker_identity = BrokerIdentity {
+            broker_name: DEFAULT_BROKER_NAME.into(),
+            broker_cluster_name: DEFAULT_CLUSTER_NAME.into(),
+            broker_id: 0,
+            is_broker_container: false,
+            is_in_broker_container: false,
+        };
+        let message_store_config = MessageStoreConfig {
+            store_path_root_dir: store_root,
+            mapped_file_size_commit_log: 8 * 1024 * 1024,
+            flush_disk_type: FlushDiskType::AsyncFlush,
+            ..MessageStoreConfig::default()
+        };
+        let server_config = ServerConfig {
+            listen_port: self.port as u32,
+            bind_address: network::LOCALHOST.to_string(),
+        };
+        (broker_config, message_store_config, server_config)
+    }
+}
+
+impl Drop for EmbeddedBroker {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
@@ -0,0 +1,101 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::path::Path;
+use std::time::Duration;
+
+use rocketmq_common::common::namesrv::namesrv_config::NamesrvConfig;
+use rocketmq_common::common::server::config::ServerConfig;
+use rocketmq_namesrv::bootstrap::Builder;
+use rocketmq_namesrv::bootstrap::NameServerBootstrap;
+use tokio::runtime::Runtime;
+use tracing::info;
+
+use crate::network;
+
+/// A name server listening on an ephemeral local port.
+pub struct EmbeddedNameServer {
+    port: u16,
+    runtime: Option<Runtime>,
+    bootstrap: Option<NameServerBootstrap>,
+}
+
+impl EmbeddedNameServer {
+    /// Starts a name server keeping its kv config under `home`.
+    pub async fn start(home: &Path) -> EmbeddedNameServer {
+        let port = network::pick_free_port();
+        let name_server_config = NamesrvConfig {
+            rocketmq_home: home.to_string_lossy().into_owned(),
+            kv_config_path: home.join("kvConfig.json").to_string_lossy().into_owned(),
+            config_store_path: home
+                .join("namesrv.properties")
+                .to_string_lossy()
+                .into_owned(),
+            ..NamesrvConfig::default()
+        };
+        let server_config = ServerConfig {
+            listen_port: port as u32,
+            bind_address: network::LOCALHOST.to_string(),
+        };
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("embedded-namesrv")
+            .enable_all()
+            .build()
+            .expect("failed to build name server runtime");
+        let bootstrap = runtime
+            .spawn(async move {
+                let mut bootstrap = Builder::new()
+                    .set_name_server_config(name_server_config)
+                    .set_server_config(server_config)
+                    .build();
+                bootstrap.start_embedded().await;
+                bootstrap
+            })
+            .await
+            .expect("name server failed to start");
+        network::wait_until_listening(port, Duration::from_secs(10)).await;
+        info!(
+            "embedded name server listening on {}:{}",
+            network::LOCALHOST,
+            port
+        );
+        EmbeddedNameServer {
+            port,
+            runtime: Some(runtime),
+            bootstrap: Some(bootstrap),
+        }
+    }
+
+    /// The `host:port` clients and brokers should use.
+    pub fn addr(&self) -> String {
+        format!("{}:{}", network::LOCALHOST, self.port)
+    }
+
+    /// Stops the name server. Calling it more than once has no effect.
+    pub fn shutdown(&mut self) {
+        if let (Some(bootstrap), Some(runtime)) = (self.bootstrap.take(), self.runtime.take()) {
+            network::drop_off_runtime(bootstrap, runtime);
+            network::wait_until_closed(self.port, Duration::from_secs(5));
+        }
+    }
+}
+
+impl Drop for EmbeddedNameServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
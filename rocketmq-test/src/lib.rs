@@ -0,0 +1,33 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! In-process name server and broker for integration tests.
+//!
+//! [`BaseIntegrationTest`] starts a name server and a single master broker on ephemeral
+//! ports, each on a tokio runtime of its own, with the broker store in a temporary directory.
+//! Everything is torn down when the harness is dropped, including when the test panics.
+
+pub mod base_integration_test;
+pub mod embedded_broker;
+pub mod embedded_name_server;
+pub mod message_collector;
+mod network;
+
+pub use base_integration_test::BaseIntegrationTest;
+pub use embedded_broker::EmbeddedBroker;
+pub use embedded_name_server::EmbeddedNameServer;
+pub use message_collector::MessageCollector;
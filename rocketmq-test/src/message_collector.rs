@@ -0,0 +1,76 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use rocketmq_client_rust::consumer::listener::consume_concurrently_context::ConsumeConcurrentlyContext;
+use rocketmq_client_rust::consumer::listener::consume_concurrently_status::ConsumeConcurrentlyStatus;
+use rocketmq_client_rust::consumer::listener::message_listener_concurrently::MessageListenerConcurrently;
+use rocketmq_common::common::message::message_ext::MessageExt;
+
+/// A concurrent message listener that keeps every message it consumes.
+///
+/// Clones share what was collected, so one clone can be handed to a consumer while the test
+/// waits on another.
+#[derive(Clone, Default)]
+pub struct MessageCollector {
+    received: Arc<Mutex<Vec<MessageExt>>>,
+}
+
+impl MessageCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The messages consumed so far, in consumption order.
+    pub fn received(&self) -> Vec<MessageExt> {
+        self.received.lock().unwrap().clone()
+    }
+
+    /// Waits until at least `count` messages were consumed, panicking after `timeout`.
+    pub async fn wait_for(&self, count: usize, timeout: Duration) -> Vec<MessageExt> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let received = self.received();
+            if received.len() >= count {
+                return received;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "consumed {} of {count} messages after {timeout:?}",
+                received.len()
+            );
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+}
+
+impl MessageListenerConcurrently for MessageCollector {
+    fn consume_message(
+        &self,
+        msgs: &[&MessageExt],
+        _context: &mut ConsumeConcurrentlyContext,
+    ) -> rocketmq_client_rust::Result<ConsumeConcurrentlyStatus> {
+        self.received
+            .lock()
+            .unwrap()
+            .extend(msgs.iter().map(|msg| (*msg).clone()));
+        Ok(ConsumeConcurrentlyStatus::ConsumeSuccess)
+    }
+}
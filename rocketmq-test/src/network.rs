@@ -0,0 +1,81 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use tokio::runtime::Runtime;
+
+pub(crate) const LOCALHOST: &str = "127.0.0.1";
+
+/// Returns a port nothing is listening on right now.
+pub(crate) fn pick_free_port() -> u16 {
+    TcpListener::bind((LOCALHOST, 0))
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .expect("no free local port")
+}
+
+/// Returns a free port `p` such that `p - 2` is free as well, as a broker also serves its fast
+/// channel two ports below the main one.
+pub(crate) fn pick_free_broker_port() -> u16 {
+    loop {
+        let port = pick_free_port();
+        if port > 1026 && TcpListener::bind((LOCALHOST, port - 2)).is_ok() {
+            return port;
+        }
+    }
+}
+
+/// Blocks until something accepts connections on `port`.
+pub(crate) async fn wait_until_listening(port: u16, timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    while tokio::net::TcpStream::connect((LOCALHOST, port))
+        .await
+        .is_err()
+    {
+        assert!(
+            Instant::now() < deadline,
+            "nothing listening on {LOCALHOST}:{port} after {timeout:?}"
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+/// Polls until nothing accepts connections on `port` any more.
+pub(crate) fn wait_until_closed(port: u16, timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    while TcpStream::connect((LOCALHOST, port)).is_ok() && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// Drops `value` and shuts `runtime` down on a plain thread.
+///
+/// Servers own runtimes of their own, which must not be dropped from within an async context,
+/// and the harness is usually dropped at the end of a `#[tokio::test]`.
+pub(crate) fn drop_off_runtime<T: Send + 'static>(value: T, runtime: Runtime) {
+    let handle = thread::spawn(move || {
+        drop(value);
+        runtime.shutdown_timeout(Duration::from_secs(5));
+    });
+    if handle.join().is_err() && !thread::panicking() {
+        panic!("embedded server panicked on shutdown");
+    }
+}
@@ -0,0 +1,200 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::net::TcpListener;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use rocketmq_broker::BrokerContainer;
+use rocketmq_broker::BrokerContainerConfig;
+use rocketmq_common::common::constant::PermName;
+use rocketmq_common::common::topic::TopicValidator;
+use rocketmq_remoting::clients::Client;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::header::broker::remove_broker_request_header::RemoveBrokerRequestHeader;
+use rocketmq_remoting::protocol::header::create_topic_request_header::CreateTopicRequestHeader;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::request_processor::default_request_processor::DefaultRemotingRequestProcessor;
+use rocketmq_test::EmbeddedNameServer;
+
+const CLUSTER_NAME: &str = "ContainerCluster";
+
+/// Writes the config file of a broker hosted in the container, every broker stores under the
+/// same root and the container isolates them by identity.
+fn write_broker_config(
+    dir: &Path,
+    namesrv_addr: &str,
+    broker_name: &str,
+    broker_id: u64,
+    broker_role: &str,
+) -> PathBuf {
+    let store_root = dir.join("store").to_string_lossy().replace('\\', "/");
+    let config_path = dir.join(format!("{broker_name}.toml"));
+    std::fs::write(
+        &config_path,
+        format!(
+            "brokerIp1 = \"127.0.0.1\"\nnamesrvAddr = \"{namesrv_addr}\"\nstorePathRootDir = \
+             \"{store_root}\"\nmappedFileSizeCommitLog = 8388608\nbrokerRole = \
+             \"{broker_role}\"\n\n[brokerIdentity]\nbrokerName = \
+             \"{broker_name}\"\nbrokerClusterName = \"{CLUSTER_NAME}\"\nbrokerId = {broker_id}\n"
+        ),
+    )
+    .unwrap();
+    config_path
+}
+
+async fn invoke(addr: &str, request: RemotingCommand) -> RemotingCommand {
+    let mut client = Client::connect(addr, DefaultRemotingRequestProcessor, None)
+        .await
+        .unwrap_or_else(|error| panic!("failed to connect to {addr}: {error}"));
+    client
+        .send_read(request, 3000)
+        .await
+        .unwrap_or_else(|error| panic!("request to {addr} failed: {error}"))
+}
+
+async fn create_topic(addr: &str, broker_name: &str, topic: &str) -> RemotingCommand {
+    let header = CreateTopicRequestHeader {
+        topic: topic.into(),
+        default_topic: TopicValidator::AUTO_CREATE_TOPIC_KEY_TOPIC.into(),
+        read_queue_nums: 4,
+        write_queue_nums: 4,
+        perm: (PermName::PERM_READ | PermName::PERM_WRITE) as i32,
+        topic_filter_type: "SINGLE_TAG".into(),
+        topic_sys_flag: None,
+        order: false,
+        attributes: None,
+        force: None,
+        topic_request_header: None,
+    };
+    let mut request =
+        RemotingCommand::create_request_command(RequestCode::UpdateAndCreateTopic, header);
+    request.add_ext_field("brokerName", broker_name);
+    invoke(addr, request).await
+}
+
+/// The topic table of `broker_name` as JSON.
+async fn all_topic_config(addr: &str, broker_name: &str) -> RemotingCommand {
+    let mut request = RemotingCommand::create_remoting_command(RequestCode::GetAllTopicConfig);
+    request.add_ext_field("brokerName", broker_name);
+    invoke(addr, request).await
+}
+
+fn body(response: &RemotingCommand) -> String {
+    assert_eq!(
+        response.code(),
+        ResponseCode::Success as i32,
+        "{:?}",
+        response.remark()
+    );
+    String::from_utf8_lossy(response.body().as_deref().unwrap_or_default()).into_owned()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn container_hosts_isolated_brokers_added_and_removed_at_runtime() {
+    let home = tempfile::tempdir().unwrap();
+    let mut name_server = EmbeddedNameServer::start(home.path()).await;
+    let namesrv_addr = name_server.addr();
+    let master = write_broker_config(home.path(), &namesrv_addr, "broker-a", 0, "ASYNC_MASTER");
+    let slave = write_broker_config(home.path(), &namesrv_addr, "broker-b", 1, "SLAVE");
+
+    let port = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .unwrap()
+        .port();
+    let addr = format!("127.0.0.1:{port}");
+    let container = BrokerContainer::new(BrokerContainerConfig {
+        listen_port: port as u32,
+        bind_address: "127.0.0.1".to_string(),
+        broker_config_paths: vec![master],
+    });
+    assert!(container.start().await);
+    for _ in 0..100 {
+        if tokio::net::TcpStream::connect(&addr).await.is_ok() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    // The only broker also takes requests naming no broker.
+    assert!(body(
+        &invoke(
+            &addr,
+            RemotingCommand::create_remoting_command(RequestCode::GetAllTopicConfig)
+        )
+        .await
+    )
+    .contains(TopicValidator::RMQ_SYS_SELF_TEST_TOPIC));
+
+    let add_broker = RemotingCommand::create_remoting_command(RequestCode::AddBroker)
+        .set_body(slave.to_string_lossy().into_owned());
+    let response = invoke(&addr, add_broker.clone()).await;
+    assert_eq!(
+        response.code(),
+        ResponseCode::Success as i32,
+        "{:?}",
+        response.remark()
+    );
+    assert_eq!(container.broker_names(), vec!["broker-a", "broker-b"]);
+    let response = invoke(&addr, add_broker).await;
+    assert_eq!(response.code(), ResponseCode::SystemError as i32);
+
+    // Topics are created in the broker the request names only.
+    body(&create_topic(&addr, "broker-a", "ContainerTopicA").await);
+    body(&create_topic(&addr, "broker-b", "ContainerTopicB").await);
+    let topics_a = body(&all_topic_config(&addr, "broker-a").await);
+    let topics_b = body(&all_topic_config(&addr, "broker-b").await);
+    assert!(topics_a.contains("ContainerTopicA") && !topics_a.contains("ContainerTopicB"));
+    assert!(topics_b.contains("ContainerTopicB") && !topics_b.contains("ContainerTopicA"));
+    let store_root = home.path().join("store");
+    for (identity, topic) in [
+        ("ContainerCluster_broker-a_0", "ContainerTopicA"),
+        ("ContainerCluster_broker-b_1", "ContainerTopicB"),
+    ] {
+        let topics =
+            std::fs::read_to_string(store_root.join(identity).join("config").join("topics.json"))
+                .unwrap();
+        assert!(topics.contains(topic));
+    }
+
+    // Removing a broker leaves the other one serving.
+    let remove_broker = RemotingCommand::create_request_command(
+        RequestCode::RemoveBroker,
+        RemoveBrokerRequestHeader {
+            broker_name: "broker-a".into(),
+            broker_cluster_name: CLUSTER_NAME.into(),
+            broker_id: 0,
+        },
+    );
+    let response = invoke(&addr, remove_broker).await;
+    assert_eq!(
+        response.code(),
+        ResponseCode::Success as i32,
+        "{:?}",
+        response.remark()
+    );
+    assert_eq!(container.broker_names(), vec!["broker-b"]);
+    let response = all_topic_config(&addr, "broker-a").await;
+    assert_eq!(response.code(), ResponseCode::SystemError as i32);
+    assert!(body(&all_topic_config(&addr, "broker-b").await).contains("ContainerTopicB"));
+
+    tokio::task::spawn_blocking(move || container.shutdown())
+        .await
+        .unwrap();
+    name_server.shutdown();
+}
@@ -0,0 +1,103 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::time::Duration;
+
+use rocketmq_client_rust::consumer::mq_push_consumer::MQPushConsumer;
+use rocketmq_client_rust::producer::default_mq_producer::DefaultMQProducer;
+use rocketmq_client_rust::producer::mq_producer::MQProducer;
+use rocketmq_common::common::message::message_single::Message;
+use rocketmq_test::BaseIntegrationTest;
+use rocketmq_test::MessageCollector;
+
+const TOPIC: &str = "IntegrationRestartTopic";
+const PRODUCER_GROUP: &str = "integration_restart_producer";
+const CONSUMER_GROUP: &str = "integration_restart_consumer";
+const QUEUE_NUMS: u32 = 2;
+
+async fn send(producer: &mut DefaultMQProducer, prefix: &str, count: usize) {
+    for index in 0..count {
+        let message = Message::with_tags(TOPIC, "TagA", format!("{prefix}-{index}").as_bytes());
+        producer
+            .send_with_timeout(message, 3000)
+            .await
+            .expect("sync send failed");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn restarted_broker_recovers_messages_and_consumer_offsets() {
+    let mut harness = BaseIntegrationTest::start().await;
+    harness.create_topic(TOPIC, QUEUE_NUMS).await;
+
+    let mut producer = harness.create_producer(PRODUCER_GROUP).await;
+    send(&mut producer, "before-restart", 20).await;
+    producer.shutdown().await;
+
+    let collector = MessageCollector::new();
+    let mut consumer = harness
+        .create_push_consumer(CONSUMER_GROUP, TOPIC, collector.clone())
+        .await;
+    collector.wait_for(20, Duration::from_secs(60)).await;
+    harness
+        .wait_for_consumer_offsets(CONSUMER_GROUP, TOPIC, QUEUE_NUMS, Duration::from_secs(30))
+        .await;
+    consumer.shutdown().await;
+
+    let mut stored = Vec::new();
+    for queue_id in 0..QUEUE_NUMS as i32 {
+        stored.push(harness.max_offset(TOPIC, queue_id).await);
+    }
+    assert_eq!(stored.iter().sum::<i64>(), 20);
+
+    harness.restart_broker().await;
+    harness
+        .wait_for_route(TOPIC, Duration::from_secs(30), |route| {
+            !route.queue_datas.is_empty()
+        })
+        .await;
+
+    for (queue_id, max_offset) in stored.iter().enumerate() {
+        let queue_id = queue_id as i32;
+        assert_eq!(harness.max_offset(TOPIC, queue_id).await, *max_offset);
+        assert_eq!(
+            harness
+                .consumer_offset(CONSUMER_GROUP, TOPIC, queue_id)
+                .await,
+            Some(*max_offset)
+        );
+    }
+
+    let mut producer = harness.create_producer(PRODUCER_GROUP).await;
+    send(&mut producer, "after-restart", 10).await;
+    producer.shutdown().await;
+
+    let collector = MessageCollector::new();
+    let mut consumer = harness
+        .create_push_consumer(CONSUMER_GROUP, TOPIC, collector.clone())
+        .await;
+    collector.wait_for(10, Duration::from_secs(60)).await;
+    harness
+        .wait_for_consumer_offsets(CONSUMER_GROUP, TOPIC, QUEUE_NUMS, Duration::from_secs(30))
+        .await;
+    let received = collector.received();
+    assert_eq!(received.len(), 10);
+    for message in &received {
+        let body = String::from_utf8(message.body().unwrap().to_vec()).unwrap();
+        assert!(body.starts_with("after-restart-"), "redelivered {body}");
+    }
+    consumer.shutdown().await;
+}
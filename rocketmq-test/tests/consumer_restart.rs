@@ -0,0 +1,120 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashSet;
+use std::time::Duration;
+
+use rocketmq_client_rust::consumer::listener::consume_concurrently_context::ConsumeConcurrentlyContext;
+use rocketmq_client_rust::consumer::listener::consume_concurrently_status::ConsumeConcurrentlyStatus;
+use rocketmq_client_rust::consumer::listener::message_listener_concurrently::MessageListenerConcurrently;
+use rocketmq_client_rust::consumer::mq_push_consumer::MQPushConsumer;
+use rocketmq_client_rust::producer::mq_producer::MQProducer;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_single::Message;
+use rocketmq_test::BaseIntegrationTest;
+use rocketmq_test::MessageCollector;
+
+const TOPIC: &str = "IntegrationConsumerRestartTopic";
+const PRODUCER_GROUP: &str = "integration_consumer_restart_producer";
+const CONSUMER_GROUP: &str = "integration_consumer_restart_consumer";
+const QUEUE_NUMS: u32 = 2;
+const BACKLOG: usize = 200;
+
+/// Collects like [`MessageCollector`] but takes a while per message, so the consumer is shut
+/// down with consume requests still running.
+#[derive(Clone, Default)]
+struct SlowCollector {
+    collector: MessageCollector,
+}
+
+impl MessageListenerConcurrently for SlowCollector {
+    fn consume_message(
+        &self,
+        msgs: &[&MessageExt],
+        context: &mut ConsumeConcurrentlyContext,
+    ) -> rocketmq_client_rust::Result<ConsumeConcurrentlyStatus> {
+        std::thread::sleep(Duration::from_millis(20));
+        self.collector.consume_message(msgs, context)
+    }
+}
+
+async fn send(harness: &BaseIntegrationTest, prefix: &str, count: usize) {
+    let mut producer = harness.create_producer(PRODUCER_GROUP).await;
+    for index in 0..count {
+        let message = Message::with_tags(TOPIC, "TagA", format!("{prefix}-{index}").as_bytes());
+        producer
+            .send_with_timeout(message, 3000)
+            .await
+            .expect("sync send failed");
+    }
+    producer.shutdown().await;
+}
+
+fn bodies(messages: &[MessageExt]) -> Vec<String> {
+    messages
+        .iter()
+        .map(|message| String::from_utf8(message.body().unwrap().to_vec()).unwrap())
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn restarted_consumer_resumes_without_redelivery() {
+    let harness = BaseIntegrationTest::start().await;
+    harness.create_topic(TOPIC, QUEUE_NUMS).await;
+    send(&harness, "first-half", BACKLOG / 2).await;
+
+    // Shut down as soon as consumption starts: the requests already handed to the consume
+    // service have to finish and be committed by the shutdown itself.
+    let listener = SlowCollector::default();
+    let mut consumer = harness
+        .push_consumer_builder(CONSUMER_GROUP)
+        .await_termination_millis_when_shutdown(30_000)
+        .message_listener_concurrently(listener.clone())
+        .build()
+        .expect("failed to build consumer");
+    consumer
+        .subscribe(TOPIC, "*")
+        .expect("failed to subscribe consumer");
+    consumer.start().await.expect("failed to start consumer");
+    listener
+        .collector
+        .wait_for(1, Duration::from_secs(60))
+        .await;
+    consumer.shutdown().await;
+    let first_run = bodies(&listener.collector.received());
+
+    // Whatever the first consumer did not get to is consumed after the restart, together with
+    // what was sent meanwhile, and nothing the first consumer acknowledged comes back.
+    send(&harness, "second-half", BACKLOG / 2).await;
+    let collector = MessageCollector::new();
+    let mut consumer = harness
+        .create_push_consumer(CONSUMER_GROUP, TOPIC, collector.clone())
+        .await;
+    collector
+        .wait_for(BACKLOG - first_run.len(), Duration::from_secs(60))
+        .await;
+    harness
+        .wait_for_consumer_offsets(CONSUMER_GROUP, TOPIC, QUEUE_NUMS, Duration::from_secs(30))
+        .await;
+    consumer.shutdown().await;
+    let second_run = bodies(&collector.received());
+
+    let mut seen = HashSet::new();
+    for body in first_run.iter().chain(&second_run) {
+        assert!(seen.insert(body.as_str()), "redelivered {body}");
+    }
+    assert_eq!(seen.len(), BACKLOG);
+}
@@ -0,0 +1,99 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use rocketmq_client_rust::consumer::mq_push_consumer::MQPushConsumer;
+use rocketmq_client_rust::producer::mq_producer::MQProducer;
+use rocketmq_client_rust::producer::send_status::SendStatus;
+use rocketmq_common::common::message::message_single::Message;
+use rocketmq_test::BaseIntegrationTest;
+use rocketmq_test::MessageCollector;
+
+const TOPIC: &str = "IntegrationSendConsumeTopic";
+const PRODUCER_GROUP: &str = "integration_send_consume_producer";
+const CONSUMER_GROUP: &str = "integration_send_consume_consumer";
+const QUEUE_NUMS: u32 = 4;
+const MESSAGE_COUNT: usize = 100;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn sync_sent_messages_are_consumed_in_queue_order_and_committed() {
+    let harness = BaseIntegrationTest::start().await;
+    harness.create_topic(TOPIC, QUEUE_NUMS).await;
+
+    let mut producer = harness.create_producer(PRODUCER_GROUP).await;
+    // queue id -> bodies in the order they were sent to that queue
+    let mut sent: BTreeMap<i32, Vec<String>> = BTreeMap::new();
+    for index in 0..MESSAGE_COUNT {
+        let body = format!("message-{index}");
+        let message = Message::with_tags(TOPIC, "TagA", body.as_bytes());
+        let send_result = producer
+            .send_with_timeout(message, 3000)
+            .await
+            .expect("sync send failed");
+        assert_eq!(send_result.send_status, SendStatus::SendOk);
+        let queue_id = send_result
+            .message_queue
+            .as_ref()
+            .expect("send result without a queue")
+            .get_queue_id();
+        let sent_to_queue = sent.entry(queue_id).or_default();
+        assert_eq!(send_result.queue_offset, sent_to_queue.len() as u64);
+        sent_to_queue.push(body);
+    }
+    assert!(sent.len() > 1, "every message went to a single queue");
+
+    let collector = MessageCollector::new();
+    let mut consumer = harness
+        .create_push_consumer(CONSUMER_GROUP, TOPIC, collector.clone())
+        .await;
+    let received = collector
+        .wait_for(MESSAGE_COUNT, Duration::from_secs(60))
+        .await;
+
+    let mut consumed: BTreeMap<i32, BTreeMap<i64, String>> = BTreeMap::new();
+    for message in &received {
+        let body = String::from_utf8(message.body().unwrap().to_vec()).unwrap();
+        let duplicate = consumed
+            .entry(message.queue_id())
+            .or_default()
+            .insert(message.queue_offset(), body);
+        assert!(duplicate.is_none(), "consumed {message:?} twice");
+    }
+    for (queue_id, bodies) in &sent {
+        let consumed_from_queue = &consumed[queue_id];
+        let offsets: Vec<i64> = consumed_from_queue.keys().copied().collect();
+        assert_eq!(offsets, (0..bodies.len() as i64).collect::<Vec<_>>());
+        let consumed_bodies: Vec<&String> = consumed_from_queue.values().collect();
+        assert_eq!(consumed_bodies, bodies.iter().collect::<Vec<_>>());
+    }
+
+    harness
+        .wait_for_consumer_offsets(CONSUMER_GROUP, TOPIC, QUEUE_NUMS, Duration::from_secs(30))
+        .await;
+    for (queue_id, bodies) in &sent {
+        assert_eq!(
+            harness
+                .consumer_offset(CONSUMER_GROUP, TOPIC, *queue_id)
+                .await,
+            Some(bodies.len() as i64)
+        );
+    }
+
+    consumer.shutdown().await;
+    producer.shutdown().await;
+}
@@ -20,7 +20,9 @@ mod arc_mut;
 mod blocking_queue;
 pub mod count_down_latch;
 pub mod rocketmq_tokio_lock;
+pub mod service_task;
 mod shutdown;
+pub mod wait_notify_object;
 
 pub use arc_mut::ArcMut;
 pub use arc_mut::SyncUnsafeCellWrapper;
@@ -31,9 +33,12 @@ pub use count_down_latch::CountDownLatch;
 pub use rocketmq::main;
 pub use rocketmq_tokio_lock::RocketMQTokioMutex;
 pub use rocketmq_tokio_lock::RocketMQTokioRwLock;
+pub use service_task::ServiceContext;
+pub use service_task::ServiceTask;
 pub use shutdown::Shutdown;
 /// Re-export tokio module.
 pub use tokio as rocketmq;
+pub use wait_notify_object::WaitNotifyObject;
 
 /// On unix platforms we want to intercept SIGINT and SIGTERM
 /// This method returns if either are signalled
@@ -0,0 +1,312 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::future::Future;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use tokio::runtime::Handle;
+use tokio::runtime::RuntimeFlavor;
+use tokio::task::JoinHandle;
+use tracing::info;
+use tracing::warn;
+use tracing::Instrument;
+
+use crate::wait_notify_object::WaitNotifyObject;
+
+/// How long [`ServiceTask::shutdown`] waits for the run loop by default, as Java `ServiceThread`.
+pub const DEFAULT_JOIN_TIME: Duration = Duration::from_secs(90);
+
+/// State shared between a [`ServiceTask`] and its run loop.
+#[derive(Clone)]
+pub struct ServiceContext {
+    inner: Arc<ContextInner>,
+}
+
+struct ContextInner {
+    name: String,
+    stopped: AtomicBool,
+    wait_point: WaitNotifyObject,
+}
+
+impl ServiceContext {
+    pub fn service_name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// `true` once the service is shut down, the run loop should return.
+    pub fn is_stopped(&self) -> bool {
+        self.inner.stopped.load(Ordering::Acquire)
+    }
+
+    /// Ends the current or the next [`wait_for_running`](Self::wait_for_running).
+    pub fn wakeup(&self) {
+        self.inner.wait_point.wakeup();
+    }
+
+    /// Waits for a [`wakeup`](Self::wakeup), the shutdown of the service or `interval`. Returns
+    /// `true` when woken up.
+    pub async fn wait_for_running(&self, interval: Duration) -> bool {
+        self.inner.wait_point.wait_for_running(interval).await
+    }
+
+    fn make_stop(&self) {
+        self.inner.stopped.store(true, Ordering::Release);
+        self.wakeup();
+    }
+}
+
+/// A background service with a single async run loop. To replace Java `ServiceThread`.
+///
+/// The run loop is expected to check [`ServiceContext::is_stopped`] between rounds and to wait
+/// with [`ServiceContext::wait_for_running`], which returns early on [`wakeup`] and on
+/// [`shutdown`].
+///
+/// [`wakeup`]: ServiceTask::wakeup
+/// [`shutdown`]: ServiceTask::shutdown
+pub struct ServiceTask {
+    context: ServiceContext,
+    started: AtomicBool,
+    join_handle: Mutex<Option<JoinHandle<()>>>,
+    finished: Arc<(Mutex<bool>, Condvar)>,
+    join_time: Duration,
+}
+
+impl ServiceTask {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            context: ServiceContext {
+                inner: Arc::new(ContextInner {
+                    name: name.into(),
+                    stopped: AtomicBool::new(false),
+                    wait_point: WaitNotifyObject::new(),
+                }),
+            },
+            started: AtomicBool::new(false),
+            join_handle: Mutex::new(None),
+            finished: Arc::new((Mutex::new(false), Condvar::new())),
+            join_time: DEFAULT_JOIN_TIME,
+        }
+    }
+
+    /// Sets how long [`shutdown`](Self::shutdown) waits for the run loop to return.
+    pub fn with_join_time(mut self, join_time: Duration) -> Self {
+        self.join_time = join_time;
+        self
+    }
+
+    pub fn service_name(&self) -> &str {
+        self.context.service_name()
+    }
+
+    pub fn context(&self) -> &ServiceContext {
+        &self.context
+    }
+
+    /// Spawns the run loop on the current runtime, a started service is not started again.
+    pub fn start<F, Fut>(&self, run: F)
+    where
+        F: FnOnce(ServiceContext) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        if self
+            .started
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return;
+        }
+        info!("Try to start service {}", self.service_name());
+        *self.finished.0.lock().unwrap() = false;
+        let finished = FinishedGuard(self.finished.clone());
+        let run = run(self.context.clone());
+        // the loop keeps the caller's span so its records carry e.g. the broker identity
+        let handle = tokio::spawn(
+            async move {
+                // reports the end of the loop on return, panic and abort alike
+                let _finished = finished;
+                run.await;
+            }
+            .in_current_span(),
+        );
+        *self.join_handle.lock().unwrap() = Some(handle);
+    }
+
+    pub fn wakeup(&self) {
+        self.context.wakeup();
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.context.is_stopped()
+    }
+
+    /// Stops the service and waits up to the join time for the run loop to return, `interrupt`
+    /// aborts the loop at its next await point instead of letting it finish its round.
+    ///
+    /// Returns `false` when the loop is still running afterwards. A loop on a current thread
+    /// runtime cannot make progress while this call blocks its only thread, it is stopped without
+    /// being waited for.
+    pub fn shutdown(&self, interrupt: bool) -> bool {
+        if self
+            .started
+            .compare_exchange(true, false, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return true;
+        }
+        info!(
+            "Try to shutdown service {}, interrupt={}",
+            self.service_name(),
+            interrupt
+        );
+        self.context.make_stop();
+        if let Some(handle) = self.join_handle.lock().unwrap().take() {
+            if interrupt {
+                handle.abort();
+            }
+        }
+        let begin = Instant::now();
+        let joined = match Handle::try_current().map(|handle| handle.runtime_flavor()) {
+            Ok(RuntimeFlavor::CurrentThread) => *self.finished.0.lock().unwrap(),
+            Ok(_) => tokio::task::block_in_place(|| self.wait_finished()),
+            Err(_) => self.wait_finished(),
+        };
+        if joined {
+            info!(
+                "Join service {} elapsed time(ms) {}",
+                self.service_name(),
+                begin.elapsed().as_millis()
+            );
+        } else {
+            warn!(
+                "Service {} still running {:?} after shutdown",
+                self.service_name(),
+                self.join_time
+            );
+        }
+        joined
+    }
+
+    fn wait_finished(&self) -> bool {
+        let (finished, condvar) = &*self.finished;
+        let guard = finished.lock().unwrap();
+        let (guard, _) = condvar
+            .wait_timeout_while(guard, self.join_time, |finished| !*finished)
+            .unwrap();
+        *guard
+    }
+}
+
+struct FinishedGuard(Arc<(Mutex<bool>, Condvar)>);
+
+impl Drop for FinishedGuard {
+    fn drop(&mut self) {
+        let (finished, condvar) = &*self.0;
+        *finished.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        condvar.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn shutdown_during_wait_joins_the_loop() {
+        let service = ServiceTask::new("WaitingService");
+        let rounds = Arc::new(AtomicUsize::new(0));
+        let counter = rounds.clone();
+        service.start(move |context| async move {
+            while !context.is_stopped() {
+                context.wait_for_running(Duration::from_secs(60)).await;
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let begin = Instant::now();
+        assert!(service.shutdown(false));
+        assert!(begin.elapsed() < Duration::from_secs(10));
+        assert!(service.is_stopped());
+        assert_eq!(rounds.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn interrupt_aborts_a_busy_loop() {
+        let service = ServiceTask::new("BusyService");
+        service.start(|_| async {
+            tokio::time::sleep(Duration::from_secs(600)).await;
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(service.shutdown(true));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn shutdown_gives_up_after_join_time() {
+        let service = ServiceTask::new("StuckService").with_join_time(Duration::from_millis(50));
+        service.start(|_| async {
+            tokio::time::sleep(Duration::from_secs(600)).await;
+        });
+        assert!(!service.shutdown(false));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn spurious_wakeups_do_not_end_a_round_early() {
+        let service = ServiceTask::new("WakeupService");
+        let rounds = Arc::new(AtomicUsize::new(0));
+        let counter = rounds.clone();
+        // wakeups before the first wait are merged into a single one
+        service.wakeup();
+        service.wakeup();
+        service.start(move |context| async move {
+            while !context.is_stopped() {
+                if context.wait_for_running(Duration::from_secs(60)).await {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(rounds.load(Ordering::SeqCst), 1);
+
+        service.wakeup();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(rounds.load(Ordering::SeqCst), 2);
+        assert!(service.shutdown(false));
+    }
+
+    #[tokio::test]
+    async fn service_is_started_once() {
+        let service = ServiceTask::new("StartedOnce");
+        let starts = Arc::new(AtomicUsize::new(0));
+        for _ in 0..2 {
+            let counter = starts.clone();
+            service.start(move |_| async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(starts.load(Ordering::SeqCst), 1);
+        assert!(service.shutdown(false));
+    }
+}
@@ -0,0 +1,208 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::pin::Pin;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use tokio::sync::futures::Notified;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+/// Wait/notify primitive of the store services. To replace Java `WaitNotifyObject`.
+///
+/// [`wakeup`] wakes the single waiter of [`wait_for_running`]. A wakeup sent while nobody is
+/// waiting is remembered and consumed by the next wait, further wakeups until then are merged
+/// into it, so a waiter never returns twice for one wakeup.
+///
+/// [`wakeup_all`] wakes every waiter of [`all_wait_for_running`] exactly once, including the ones
+/// that are busy when it is called and only wait afterwards.
+///
+/// [`wakeup`]: WaitNotifyObject::wakeup
+/// [`wait_for_running`]: WaitNotifyObject::wait_for_running
+/// [`wakeup_all`]: WaitNotifyObject::wakeup_all
+/// [`all_wait_for_running`]: WaitNotifyObject::all_wait_for_running
+#[derive(Default)]
+pub struct WaitNotifyObject {
+    /// Set by `wakeup` until a waiter consumes it.
+    has_notified: AtomicBool,
+    /// Bumped by every `wakeup_all`.
+    generation: AtomicU64,
+    notify: Notify,
+}
+
+impl WaitNotifyObject {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wakes up the waiter of [`wait_for_running`](Self::wait_for_running).
+    pub fn wakeup(&self) {
+        if self
+            .has_notified
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+        {
+            self.notify.notify_waiters();
+        }
+    }
+
+    /// Waits until woken up or until `interval` elapses, returning `true` when woken up.
+    ///
+    /// Like the `CountDownLatch2` reset in Java, a wakeup racing with the timeout is discarded so
+    /// that it does not end the next wait early.
+    pub async fn wait_for_running(&self, interval: Duration) -> bool {
+        let deadline = Instant::now() + interval;
+        loop {
+            let mut notified = std::pin::pin!(self.notify.notified());
+            // register before checking the flag, a wakeup in between is not lost
+            notified.as_mut().enable();
+            if self
+                .has_notified
+                .compare_exchange(true, false, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                return true;
+            }
+            // a `wakeup_all` also ends the wait, check the flag again
+            if !Self::wait_until(notified, deadline).await {
+                self.has_notified.store(false, Ordering::Release);
+                return false;
+            }
+        }
+    }
+
+    /// Wakes up every waiter of [`all_wait_for_running`](Self::all_wait_for_running) once.
+    pub fn wakeup_all(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.notify.notify_waiters();
+    }
+
+    /// Current generation of [`wakeup_all`](Self::wakeup_all), the starting point of a waiter of
+    /// [`all_wait_for_running`](Self::all_wait_for_running).
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Waits until a `wakeup_all` newer than `observed` or until `interval` elapses, returning
+    /// `true` when woken up. `observed` is advanced to the generation that woke the waiter.
+    pub async fn all_wait_for_running(&self, observed: &mut u64, interval: Duration) -> bool {
+        let deadline = Instant::now() + interval;
+        loop {
+            let mut notified = std::pin::pin!(self.notify.notified());
+            notified.as_mut().enable();
+            let generation = self.generation();
+            if generation != *observed {
+                *observed = generation;
+                return true;
+            }
+            if !Self::wait_until(notified, deadline).await {
+                return false;
+            }
+        }
+    }
+
+    async fn wait_until(notified: Pin<&mut Notified<'_>>, deadline: Instant) -> bool {
+        tokio::time::timeout_at(deadline, notified).await.is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn wakeup_before_wait_is_consumed_once() {
+        let object = WaitNotifyObject::new();
+        object.wakeup();
+        object.wakeup();
+        assert!(object.wait_for_running(Duration::from_secs(5)).await);
+
+        let begin = Instant::now();
+        assert!(!object.wait_for_running(Duration::from_millis(50)).await);
+        assert!(begin.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn wakeup_all_does_not_end_single_wait() {
+        let object = Arc::new(WaitNotifyObject::new());
+        let waiter = {
+            let object = object.clone();
+            tokio::spawn(async move { object.wait_for_running(Duration::from_millis(200)).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        object.wakeup_all();
+        assert!(!waiter.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn wakeup_ends_pending_wait() {
+        let object = Arc::new(WaitNotifyObject::new());
+        let waiter = {
+            let object = object.clone();
+            tokio::spawn(async move { object.wait_for_running(Duration::from_secs(30)).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        object.wakeup();
+        assert!(waiter.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn wakeup_all_wakes_every_waiter_once() {
+        let object = Arc::new(WaitNotifyObject::new());
+        let mut waiters = Vec::new();
+        for _ in 0..3 {
+            let object = object.clone();
+            let mut observed = object.generation();
+            waiters.push(tokio::spawn(async move {
+                let first = object
+                    .all_wait_for_running(&mut observed, Duration::from_secs(30))
+                    .await;
+                let second = object
+                    .all_wait_for_running(&mut observed, Duration::from_millis(50))
+                    .await;
+                (first, second)
+            }));
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        object.wakeup_all();
+        for waiter in waiters {
+            assert_eq!(waiter.await.unwrap(), (true, false));
+        }
+    }
+
+    #[tokio::test]
+    async fn wakeup_all_is_seen_by_busy_waiter() {
+        let object = WaitNotifyObject::new();
+        let mut observed = object.generation();
+        object.wakeup_all();
+        assert!(
+            object
+                .all_wait_for_running(&mut observed, Duration::from_secs(5))
+                .await
+        );
+        assert!(
+            !object
+                .all_wait_for_running(&mut observed, Duration::from_millis(20))
+                .await
+        );
+    }
+}